@@ -0,0 +1,31 @@
+-- Workflow definitions and the resources moving through them
+
+-- Every version of each workflow definition; the current one is the last stored
+CREATE TABLE IF NOT EXISTS workflow_versions (
+    id TEXT NOT NULL,
+    version INTEGER NOT NULL,
+    definition JSONB NOT NULL,
+    stored_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (id, version)
+);
+
+CREATE TABLE IF NOT EXISTS workflows (
+    id TEXT PRIMARY KEY,
+    version INTEGER NOT NULL,
+    definition JSONB NOT NULL
+);
+
+-- Resources, with the columns queries filter on pulled out of the record
+CREATE TABLE IF NOT EXISTS resources (
+    id UUID PRIMARY KEY,
+    workflow_id TEXT NOT NULL,
+    state TEXT NOT NULL,
+    version BIGINT NOT NULL,
+    metadata JSONB NOT NULL,
+    record JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS resources_workflow_state ON resources (workflow_id, state);
+CREATE INDEX IF NOT EXISTS resources_metadata ON resources USING GIN (metadata);
@@ -0,0 +1,27 @@
+-- Agents, the states they run in and their executions
+
+CREATE TABLE IF NOT EXISTS agents (
+    id TEXT PRIMARY KEY,
+    record JSONB NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS state_agent_configs (
+    id UUID PRIMARY KEY,
+    state_id TEXT NOT NULL,
+    agent_id TEXT NOT NULL,
+    record JSONB NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS state_agent_configs_state ON state_agent_configs (state_id);
+
+CREATE TABLE IF NOT EXISTS agent_executions (
+    id UUID PRIMARY KEY,
+    agent_id TEXT NOT NULL,
+    resource_id UUID NOT NULL,
+    status TEXT NOT NULL,
+    record JSONB NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS agent_executions_agent ON agent_executions (agent_id);
+CREATE INDEX IF NOT EXISTS agent_executions_resource ON agent_executions (resource_id);
+CREATE INDEX IF NOT EXISTS agent_executions_status ON agent_executions (status);
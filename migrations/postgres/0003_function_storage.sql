@@ -0,0 +1,19 @@
+-- Functions, their executions and chains of them
+
+CREATE TABLE IF NOT EXISTS functions (
+    id TEXT PRIMARY KEY,
+    record JSONB NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS function_executions (
+    id UUID PRIMARY KEY,
+    function_id TEXT NOT NULL,
+    record JSONB NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS function_executions_function ON function_executions (function_id);
+
+CREATE TABLE IF NOT EXISTS function_chains (
+    id UUID PRIMARY KEY,
+    record JSONB NOT NULL
+);
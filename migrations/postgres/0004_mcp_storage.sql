@@ -0,0 +1,12 @@
+-- MCP server instances, apps, OAuth state and sessions, one table per kind of record
+-- would only repeat the same two columns
+
+CREATE TABLE IF NOT EXISTS mcp_records (
+    kind TEXT NOT NULL,
+    key TEXT NOT NULL,
+    record JSONB NOT NULL,
+    stored_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (kind, key)
+);
+
+CREATE INDEX IF NOT EXISTS mcp_records_stored_at ON mcp_records (kind, stored_at);
@@ -0,0 +1,8 @@
+-- Roles assigned to API keys, service accounts and MCP installations, by subject
+-- written <kind>:<id>
+
+CREATE TABLE IF NOT EXISTS role_assignments (
+    subject TEXT PRIMARY KEY,
+    role TEXT NOT NULL,
+    assigned_at TIMESTAMPTZ NOT NULL
+);
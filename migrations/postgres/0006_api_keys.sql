@@ -0,0 +1,11 @@
+-- Managed API keys, and the digests of their secrets they are looked up by
+
+CREATE TABLE IF NOT EXISTS api_keys (
+    id TEXT PRIMARY KEY,
+    record TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS api_key_secrets (
+    digest TEXT PRIMARY KEY,
+    key_id TEXT NOT NULL REFERENCES api_keys (id) ON DELETE CASCADE
+);
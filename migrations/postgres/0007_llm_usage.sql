@@ -0,0 +1,18 @@
+-- LLM usage, one row per request and user or project it is billed to
+
+CREATE TABLE IF NOT EXISTS llm_usage (
+    request_id UUID NOT NULL,
+    usage_key TEXT NOT NULL,
+    provider TEXT NOT NULL,
+    model TEXT NOT NULL,
+    input_tokens BIGINT NOT NULL,
+    output_tokens BIGINT NOT NULL,
+    cost_usd DOUBLE PRECISION NOT NULL,
+    recorded_at TIMESTAMPTZ NOT NULL,
+    user_id TEXT,
+    project_id TEXT
+);
+
+CREATE INDEX IF NOT EXISTS llm_usage_key_time ON llm_usage (usage_key, recorded_at);
+CREATE INDEX IF NOT EXISTS llm_usage_time ON llm_usage (recorded_at);
+CREATE INDEX IF NOT EXISTS llm_usage_request ON llm_usage (request_id);
@@ -0,0 +1,21 @@
+-- The audit trail; appending is the only change the table accepts
+
+CREATE TABLE IF NOT EXISTS audit_records (
+    seq BIGSERIAL PRIMARY KEY,
+    id UUID NOT NULL UNIQUE,
+    recorded_at TIMESTAMPTZ NOT NULL,
+    category TEXT NOT NULL,
+    action TEXT NOT NULL,
+    outcome TEXT NOT NULL,
+    actor TEXT,
+    tenant_id TEXT,
+    ip TEXT,
+    record TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS audit_records_time ON audit_records (recorded_at);
+CREATE INDEX IF NOT EXISTS audit_records_actor ON audit_records (actor, recorded_at);
+CREATE INDEX IF NOT EXISTS audit_records_tenant ON audit_records (tenant_id, recorded_at);
+
+CREATE OR REPLACE RULE audit_records_no_update AS ON UPDATE TO audit_records DO INSTEAD NOTHING;
+CREATE OR REPLACE RULE audit_records_no_delete AS ON DELETE TO audit_records DO INSTEAD NOTHING;
@@ -2,6 +2,7 @@
 //!
 //! This module provides client interfaces for creating and managing AI agents.
 
+use crate::llm::{HasUsageInfo, UsageInfo};
 use crate::{schema::QueryBuilder, Client, Result};
 use serde::{Deserialize, Serialize};
 
@@ -486,6 +487,14 @@ impl Agent {
 
     /// Send a message to the agent (for conversational agents)
     pub async fn send_message(&self, message: impl Into<String>) -> Result<String> {
+        Ok(self.send_message_with_usage(message).await?.content)
+    }
+
+    /// Send a message to the agent, keeping the routing, token usage and cost of the reply
+    pub async fn send_message_with_usage(
+        &self,
+        message: impl Into<String>,
+    ) -> Result<AgentResponse> {
         let message = message.into();
 
         // Build the messages array with system prompt and user message
@@ -517,6 +526,7 @@ impl Agent {
             user: None,
             functions: None,
             function_call: None,
+            response_format: None,
             circuit_breaker: None,
         };
 
@@ -524,7 +534,10 @@ impl Agent {
 
         // Extract the assistant's response
         if let Some(choice) = response.choices.first() {
-            Ok(choice.message.content.clone())
+            Ok(AgentResponse {
+                content: choice.message.content.clone(),
+                usage_info: response.usage_info(),
+            })
         } else {
             Err(crate::Error::Network {
                 message: "No response from agent".to_string(),
@@ -571,6 +584,19 @@ impl Agent {
     }
 }
 
+/// An agent's reply to a message
+#[derive(Debug, Clone)]
+pub struct AgentResponse {
+    pub content: String,
+    pub usage_info: UsageInfo,
+}
+
+impl HasUsageInfo for AgentResponse {
+    fn usage_info(&self) -> UsageInfo {
+        self.usage_info.clone()
+    }
+}
+
 // Internal data structures
 #[derive(Debug, Clone, Deserialize)]
 struct AgentData {
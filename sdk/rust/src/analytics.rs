@@ -79,6 +79,27 @@ impl AnalyticsClient {
         SetBudgetBuilder::new(self.client.clone())
     }
 
+    /// Fetch the cost the server recorded for a request
+    ///
+    /// `request_id` is the one reported by a response's `usage_info()`.
+    pub async fn report_for_request(&self, request_id: &str) -> Result<RequestCostReport> {
+        self.client.require_feature("request_cost")?;
+        self.client
+            .rest::<RequestCostReport, ()>(
+                reqwest::Method::GET,
+                &format!("/v1/requests/{}/cost", request_id),
+                None,
+            )
+            .await
+    }
+
+    /// Break recorded costs down by user, project, model, provider or day for chargeback
+    ///
+    /// Requires the client to be configured with the server's admin token as its API key.
+    pub fn chargeback(&self) -> ChargebackReportBuilder {
+        ChargebackReportBuilder::new(self.client.clone())
+    }
+
     /// Subscribe to real-time cost updates
     pub async fn subscribe_cost_updates(&self, user_id: Option<&str>) -> Result<CostUpdateStream> {
         let subscription_client = self.client.subscriptions();
@@ -142,6 +163,102 @@ pub struct CostAnalytics {
     pub period_end: String,
 }
 
+/// Cost the server recorded for a single request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestCostReport {
+    pub request_id: String,
+    /// Provider name such as `"OpenAI"`, or `{"Custom": name}` for custom providers
+    pub provider: serde_json::Value,
+    pub model: String,
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    /// Cost in USD
+    pub cost_usd: f64,
+    /// When the cost was recorded
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub user_id: Option<String>,
+    pub project_id: Option<String>,
+}
+
+impl RequestCostReport {
+    pub fn total_tokens(&self) -> u32 {
+        self.input_tokens + self.output_tokens
+    }
+}
+
+/// Column a chargeback report groups costs by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChargebackDimension {
+    User,
+    Project,
+    Model,
+    Provider,
+    Day,
+}
+
+impl ChargebackDimension {
+    /// Name of the dimension in the `group_by` query parameter
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Project => "project",
+            Self::Model => "model",
+            Self::Provider => "provider",
+            Self::Day => "day",
+        }
+    }
+}
+
+/// Costs of one group of a chargeback report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChargebackGroup {
+    /// The group's value of each grouping column (`user_id`, `project_id`, `model`,
+    /// `provider` or `day`); null for costs without a user or project
+    pub keys: HashMap<String, serde_json::Value>,
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub total_tokens: u64,
+    /// Cost in USD
+    pub cost_usd: f64,
+    /// Fraction of the report's total cost, between 0 and 1
+    pub share_of_cost: f64,
+}
+
+impl ChargebackGroup {
+    /// The group's value of a grouping column, if it has one
+    pub fn key(&self, column: &str) -> Option<&str> {
+        self.keys.get(column).and_then(|value| value.as_str())
+    }
+}
+
+/// Recorded costs grouped for chargeback
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChargebackReport {
+    pub from: chrono::DateTime<chrono::Utc>,
+    pub to: chrono::DateTime<chrono::Utc>,
+    /// Grouping columns, e.g. `["project_id", "model"]`
+    pub group_by: Vec<String>,
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub total_tokens: u64,
+    /// Cost in USD
+    pub cost_usd: f64,
+    /// Fraction of requests the totals were estimated from
+    #[serde(default = "full_sample")]
+    pub sample_rate: f64,
+    /// Groups left out for covering too few users; totals do not include them
+    #[serde(default)]
+    pub suppressed_groups: u64,
+    /// Most expensive first
+    pub groups: Vec<ChargebackGroup>,
+}
+
+fn full_sample() -> f64 {
+    1.0
+}
+
 /// Budget input for setting limits
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BudgetInput {
@@ -574,6 +691,118 @@ impl CostUpdateStream {
     }
 }
 
+/// Builder for chargeback reports
+pub struct ChargebackReportBuilder {
+    client: Client,
+    group_by: Vec<ChargebackDimension>,
+    user_id: Option<String>,
+    project_id: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    sample_rate: Option<f64>,
+    min_group_size: Option<usize>,
+}
+
+impl ChargebackReportBuilder {
+    fn new(client: Client) -> Self {
+        Self {
+            client,
+            group_by: Vec::new(),
+            user_id: None,
+            project_id: None,
+            from: None,
+            to: None,
+            sample_rate: None,
+            min_group_size: None,
+        }
+    }
+
+    /// Add a grouping column; the server groups by project when none is given
+    pub fn group_by(mut self, dimension: ChargebackDimension) -> Self {
+        if !self.group_by.contains(&dimension) {
+            self.group_by.push(dimension);
+        }
+        self
+    }
+
+    /// Only include costs of this user
+    pub fn user_id<S: Into<String>>(mut self, user_id: S) -> Self {
+        self.user_id = Some(user_id.into());
+        self
+    }
+
+    /// Only include costs of this project
+    pub fn project_id<S: Into<String>>(mut self, project_id: S) -> Self {
+        self.project_id = Some(project_id.into());
+        self
+    }
+
+    /// Start of the period, as an RFC 3339 timestamp or a date; defaults to 30 days before `to`
+    pub fn from<S: Into<String>>(mut self, from: S) -> Self {
+        self.from = Some(from.into());
+        self
+    }
+
+    /// End of the period; a date includes the whole day. Defaults to now.
+    pub fn to<S: Into<String>>(mut self, to: S) -> Self {
+        self.to = Some(to.into());
+        self
+    }
+
+    /// Estimate totals from this fraction of requests, in `(0, 1]`
+    pub fn sample_rate(mut self, sample_rate: f64) -> Self {
+        self.sample_rate = Some(sample_rate);
+        self
+    }
+
+    /// Leave out groups covering fewer than this many distinct users; the server may
+    /// enforce a larger minimum
+    pub fn min_group_size(mut self, min_group_size: usize) -> Self {
+        self.min_group_size = Some(min_group_size);
+        self
+    }
+
+    /// Query string of the report request
+    fn query(&self) -> String {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        if !self.group_by.is_empty() {
+            let group_by: Vec<&str> = self.group_by.iter().map(|d| d.as_str()).collect();
+            query.append_pair("group_by", &group_by.join(","));
+        }
+        for (name, value) in [
+            ("user_id", &self.user_id),
+            ("project_id", &self.project_id),
+            ("from", &self.from),
+            ("to", &self.to),
+        ] {
+            if let Some(value) = value {
+                query.append_pair(name, value);
+            }
+        }
+        if let Some(sample_rate) = self.sample_rate {
+            query.append_pair("sample_rate", &sample_rate.to_string());
+        }
+        if let Some(min_group_size) = self.min_group_size {
+            query.append_pair("min_group_size", &min_group_size.to_string());
+        }
+        query.finish()
+    }
+
+    /// Fetch the report
+    pub async fn get(self) -> Result<ChargebackReport> {
+        self.client.require_feature("chargeback_reports")?;
+        let query = self.query();
+        let path = if query.is_empty() {
+            "/v1/analytics/chargeback".to_string()
+        } else {
+            format!("/v1/analytics/chargeback?{}", query)
+        };
+        self.client
+            .rest::<ChargebackReport, ()>(reqwest::Method::GET, &path, None)
+            .await
+    }
+}
+
 /// Convenience function to create a cost analytics query
 pub fn cost_analytics(client: &Client, start_date: &str, end_date: &str) -> CostAnalyticsBuilder {
     client
@@ -617,6 +846,85 @@ mod tests {
         assert!(json.contains("monthly"));
     }
 
+    #[test]
+    fn test_request_cost_report_deserialization() {
+        let report: RequestCostReport = serde_json::from_value(serde_json::json!({
+            "request_id": "5f0c6f7e-4a1b-4d6a-9a53-0d3f3c0b2a11",
+            "provider": "OpenAI",
+            "model": "gpt-4",
+            "input_tokens": 12,
+            "output_tokens": 30,
+            "cost_usd": 0.0021,
+            "timestamp": "2026-10-15T12:00:00Z",
+            "user_id": "user123",
+            "project_id": null
+        }))
+        .unwrap();
+
+        assert_eq!(report.provider, "OpenAI");
+        assert_eq!(report.total_tokens(), 42);
+        assert_eq!(report.user_id.as_deref(), Some("user123"));
+    }
+
+    #[test]
+    fn test_chargeback_report_deserialization() {
+        let report: ChargebackReport = serde_json::from_value(serde_json::json!({
+            "from": "2026-09-15T00:00:00Z",
+            "to": "2026-10-15T00:00:00Z",
+            "group_by": ["project_id", "model"],
+            "requests": 3,
+            "input_tokens": 300,
+            "output_tokens": 150,
+            "total_tokens": 450,
+            "cost_usd": 2.0,
+            "groups": [
+                {
+                    "keys": {"project_id": "search", "model": "gpt-4"},
+                    "requests": 2,
+                    "input_tokens": 200,
+                    "output_tokens": 100,
+                    "total_tokens": 300,
+                    "cost_usd": 1.5,
+                    "share_of_cost": 0.75
+                },
+                {
+                    "keys": {"project_id": null, "model": "gpt-4"},
+                    "requests": 1,
+                    "input_tokens": 100,
+                    "output_tokens": 50,
+                    "total_tokens": 150,
+                    "cost_usd": 0.5,
+                    "share_of_cost": 0.25
+                }
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(report.groups[0].key("project_id"), Some("search"));
+        assert_eq!(report.groups[1].key("project_id"), None);
+        assert_eq!(report.groups[1].key("model"), Some("gpt-4"));
+    }
+
+    #[test]
+    fn test_chargeback_query() {
+        let client = Client::builder().build().unwrap();
+        let builder = client
+            .analytics()
+            .chargeback()
+            .group_by(ChargebackDimension::Project)
+            .group_by(ChargebackDimension::Model)
+            .group_by(ChargebackDimension::Project)
+            .user_id("a&b")
+            .from("2026-10-01")
+            .sample_rate(0.5)
+            .min_group_size(5);
+
+        assert_eq!(
+            builder.query(),
+            "group_by=project%2Cmodel&user_id=a%26b&from=2026-10-01&sample_rate=0.5&min_group_size=5"
+        );
+    }
+
     #[test]
     fn test_cost_analytics_input_validation() {
         let input = CostAnalyticsInput {
@@ -3,7 +3,7 @@
 //! This module provides the main client for communicating with the Circuit Breaker server.
 //! It handles HTTP requests, GraphQL queries, and authentication.
 
-use crate::{Error, Result};
+use crate::{Error, ErrorCode, Result};
 use reqwest::{header, Client as HttpClient, Method};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -19,6 +19,8 @@ pub struct ClientConfig {
     pub timeout_ms: u64,
     pub user_agent: String,
     pub headers: HashMap<String, String>,
+    /// Journal of REST requests and their responses, for resumable batch jobs
+    pub journal: Option<crate::journal::RequestJournal>,
 }
 
 impl Default for ClientConfig {
@@ -29,6 +31,7 @@ impl Default for ClientConfig {
             timeout_ms: 30000,
             user_agent: format!("circuit-breaker-sdk-rust/{}", crate::VERSION),
             headers: HashMap::new(),
+            journal: None,
         }
     }
 }
@@ -50,6 +53,9 @@ pub struct Client {
     graphql_endpoint: String,
     rest_endpoint: String,
     endpoint_health: Option<EndpointHealth>,
+    /// Server capabilities learned on connect; `None` until negotiated or when the
+    /// server predates `/v1/meta`
+    server_meta: Option<Arc<ServerMeta>>,
 }
 
 impl Client {
@@ -112,9 +118,34 @@ impl Client {
             graphql_endpoint,
             rest_endpoint,
             endpoint_health: None,
+            server_meta: None,
         })
     }
 
+    /// Create a client and negotiate compatibility with the server
+    ///
+    /// Fails only when the server speaks no REST API version this SDK understands.
+    /// Servers without `/v1/meta` are accepted with a warning, and every feature is
+    /// then assumed to be available.
+    pub async fn connect(config: ClientConfig) -> Result<Self> {
+        let mut client = Self::new(config)?;
+        match client.meta().await {
+            Ok(meta) => {
+                for warning in check_compatibility(&meta)? {
+                    tracing::warn!("{}", warning);
+                }
+                client.server_meta = Some(Arc::new(meta));
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Could not read server metadata, assuming all features are available: {}",
+                    e
+                );
+            }
+        }
+        Ok(client)
+    }
+
     /// Determine GraphQL and REST endpoints from base URL
     fn determine_endpoints(base_url: &Url) -> Result<(String, String)> {
         let port = base_url.port();
@@ -400,6 +431,50 @@ impl Client {
         Ok(info)
     }
 
+    /// Fetch the server version, supported API versions and enabled features
+    pub async fn meta(&self) -> Result<ServerMeta> {
+        let url = format!("{}/v1/meta", self.rest_endpoint.trim_end_matches('/'));
+        let response = self
+            .http_client
+            .get(&url)
+            .timeout(Duration::from_secs(5))
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            response.json().await.map_err(|e| Error::Parse {
+                message: format!("Failed to parse server metadata: {}", e),
+            })
+        } else {
+            let status = response.status().as_u16();
+            let error_text = response.text().await.unwrap_or_default();
+            Err(Error::from_response_body(status, &error_text))
+        }
+    }
+
+    /// Server metadata negotiated by [`Client::connect`]
+    pub fn server_meta(&self) -> Option<&ServerMeta> {
+        self.server_meta.as_deref()
+    }
+
+    /// Whether the server offers a feature; assumed true when nothing was negotiated
+    pub fn supports(&self, feature: &str) -> bool {
+        self.server_meta
+            .as_ref()
+            .map_or(true, |meta| meta.supports(feature))
+    }
+
+    /// Fail early with `Error::Unsupported` when the server lacks a feature
+    pub fn require_feature(&self, feature: &str) -> Result<()> {
+        match &self.server_meta {
+            Some(meta) if !meta.supports(feature) => Err(Error::Unsupported {
+                feature: feature.to_string(),
+                server_version: meta.version.clone(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
     /// Access workflows API
     pub fn workflows(&self) -> crate::workflows::WorkflowClient {
         crate::workflows::WorkflowClient::new(self.clone())
@@ -435,6 +510,11 @@ impl Client {
         crate::analytics::AnalyticsClient::new(self.clone())
     }
 
+    /// Access tenant administration API
+    pub fn tenants(&self) -> crate::tenants::TenantsClient {
+        crate::tenants::TenantsClient::new(self.clone())
+    }
+
     /// Access MCP (Model Context Protocol) API
     pub fn mcp(&self) -> crate::mcp::MCPClient {
         crate::mcp::MCPClient::new(self.clone())
@@ -503,6 +583,13 @@ impl Client {
         #[derive(Deserialize)]
         struct GraphQLError {
             message: String,
+            #[serde(default)]
+            extensions: Option<GraphQLErrorExtensions>,
+        }
+
+        #[derive(Deserialize)]
+        struct GraphQLErrorExtensions {
+            code: Option<ErrorCode>,
         }
 
         let request_body = GraphQLRequest {
@@ -533,6 +620,19 @@ impl Client {
             })?;
 
         if let Some(errors) = graphql_response.errors {
+            let coded = errors.iter().find_map(|e| {
+                e.extensions
+                    .as_ref()
+                    .and_then(|ext| ext.code)
+                    .map(|code| (code, e.message.clone()))
+            });
+            if let Some((code, message)) = coded {
+                return Err(Error::Api {
+                    code,
+                    status: 400,
+                    message,
+                });
+            }
             let error_messages: Vec<String> = errors.iter().map(|e| e.message.clone()).collect();
             return Err(Error::Server {
                 status: 400,
@@ -558,7 +658,53 @@ impl Client {
     }
 
     /// Make a REST request with endpoint validation
+    ///
+    /// With a journal configured, requests other than `GET` the journal holds a response
+    /// for are answered from it; the rest are recorded before they are sent.
     pub async fn rest<T, B>(&self, method: Method, path: &str, body: Option<B>) -> Result<T>
+    where
+        T: for<'de> Deserialize<'de>,
+        B: Serialize,
+    {
+        let journal = match &self.config.journal {
+            Some(journal) if method != Method::GET => journal,
+            _ => return self.send_rest(method, path, body).await,
+        };
+
+        let body = body
+            .map(serde_json::to_value)
+            .transpose()
+            .map_err(|e| Error::Parse {
+                message: format!("Failed to serialize REST request: {}", e),
+            })?;
+        let request = body.clone().unwrap_or(serde_json::Value::Null);
+        let key = crate::journal::RequestJournal::request_key(method.as_str(), path, &request);
+        let response = match journal.response(&key) {
+            Some(response) => response,
+            None => {
+                journal.record_started(&key, method.as_str(), path, &request)?;
+                match self
+                    .send_rest::<serde_json::Value, _>(method, path, body)
+                    .await
+                {
+                    Ok(response) => {
+                        journal.record_completed(&key, &response)?;
+                        response
+                    }
+                    Err(e) => {
+                        journal.record_failed(&key, &e.to_string())?;
+                        return Err(e);
+                    }
+                }
+            }
+        };
+        serde_json::from_value(response).map_err(|e| Error::Parse {
+            message: format!("Failed to parse REST response: {}", e),
+        })
+    }
+
+    /// Send a REST request
+    async fn send_rest<T, B>(&self, method: Method, path: &str, body: Option<B>) -> Result<T>
     where
         T: for<'de> Deserialize<'de>,
         B: Serialize,
@@ -587,17 +733,19 @@ impl Client {
             message: format!("REST request failed: {}", e),
         })?;
 
-        if response.status().is_success() {
+        if response.status() == reqwest::StatusCode::NO_CONTENT {
+            // Deletes answer without a body, which `()` reads as null
+            serde_json::from_value(serde_json::Value::Null).map_err(|e| Error::Parse {
+                message: format!("Expected a REST response body: {}", e),
+            })
+        } else if response.status().is_success() {
             response.json().await.map_err(|e| Error::Parse {
                 message: format!("Failed to parse REST response: {}", e),
             })
         } else {
             let status = response.status().as_u16();
             let error_text = response.text().await.unwrap_or_default();
-            Err(Error::Server {
-                status,
-                message: error_text,
-            })
+            Err(Error::from_response_body(status, &error_text))
         }
     }
 
@@ -701,10 +849,21 @@ impl ClientBuilder {
         self
     }
 
+    /// Journal REST requests so a rerun batch job skips the ones that already completed
+    pub fn journal(mut self, journal: crate::journal::RequestJournal) -> Self {
+        self.config.journal = Some(journal);
+        self
+    }
+
     /// Build the client
     pub fn build(self) -> Result<Client> {
         Client::new(self.config)
     }
+
+    /// Build the client and negotiate compatibility with the server
+    pub async fn connect(self) -> Result<Client> {
+        Client::connect(self.config).await
+    }
 }
 
 impl Default for ClientBuilder {
@@ -748,10 +907,111 @@ pub struct EndpointInfo {
     pub rest: bool,
 }
 
+/// Server version and capabilities from `GET /v1/meta`
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServerMeta {
+    pub service: String,
+    pub version: String,
+    pub api_versions: Vec<String>,
+    #[serde(default)]
+    pub mcp_protocol_versions: Vec<String>,
+    pub min_sdk_version: String,
+    pub features: Vec<String>,
+    #[serde(default)]
+    pub maintenance: bool,
+}
+
+impl ServerMeta {
+    pub fn supports(&self, feature: &str) -> bool {
+        self.features.iter().any(|f| f == feature)
+    }
+}
+
+/// REST API version this SDK speaks
+pub const API_VERSION: &str = "v1";
+
+/// Check the server can serve this SDK, returning warnings for soft mismatches
+pub fn check_compatibility(meta: &ServerMeta) -> Result<Vec<String>> {
+    if !meta.api_versions.iter().any(|v| v == API_VERSION) {
+        return Err(Error::Configuration {
+            message: format!(
+                "Server {} supports API versions [{}], but this SDK requires {}",
+                meta.version,
+                meta.api_versions.join(", "),
+                API_VERSION
+            ),
+        });
+    }
+
+    let mut warnings = Vec::new();
+    if parse_version(crate::VERSION) < parse_version(&meta.min_sdk_version) {
+        warnings.push(format!(
+            "Server {} expects SDK {} or newer, this is {}; some responses may not parse",
+            meta.version,
+            meta.min_sdk_version,
+            crate::VERSION
+        ));
+    }
+    if meta.maintenance {
+        warnings.push(format!(
+            "Server {} is in maintenance mode; writes will be rejected",
+            meta.version
+        ));
+    }
+    Ok(warnings)
+}
+
+/// Numeric `major.minor.patch` components, ignoring pre-release suffixes
+fn parse_version(version: &str) -> Vec<u64> {
+    version
+        .split(['.', '-', '+'])
+        .take(3)
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn meta(api_versions: &[&str], min_sdk_version: &str) -> ServerMeta {
+        ServerMeta {
+            service: "circuit-breaker".to_string(),
+            version: "0.2.0".to_string(),
+            api_versions: api_versions.iter().map(|v| v.to_string()).collect(),
+            mcp_protocol_versions: Vec::new(),
+            min_sdk_version: min_sdk_version.to_string(),
+            features: vec!["chat_completions".to_string()],
+            maintenance: false,
+        }
+    }
+
+    #[test]
+    fn test_compatibility_check() {
+        assert!(check_compatibility(&meta(&["v1"], "0.1.0"))
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            check_compatibility(&meta(&["v1"], "99.0.0")).unwrap().len(),
+            1
+        );
+        assert!(check_compatibility(&meta(&["v2"], "0.1.0")).is_err());
+    }
+
+    #[test]
+    fn test_feature_checks_degrade_without_meta() {
+        let mut client = Client::builder().build().unwrap();
+        assert!(client.supports("structured_output"));
+        assert!(client.require_feature("structured_output").is_ok());
+
+        client.server_meta = Some(Arc::new(meta(&["v1"], "0.1.0")));
+        assert!(client.supports("chat_completions"));
+        assert!(matches!(
+            client.require_feature("structured_output"),
+            Err(Error::Unsupported { .. })
+        ));
+    }
+
     #[test]
     fn test_client_builder() {
         let client = Client::builder()
@@ -767,4 +1027,29 @@ mod tests {
         assert_eq!(client.config.api_key, Some("test-key".to_string()));
         assert_eq!(client.config.timeout_ms, 60000);
     }
+
+    #[tokio::test]
+    async fn test_journaled_requests_are_not_resent() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal =
+            crate::journal::RequestJournal::open(dir.path().join("jobs.journal")).unwrap();
+        let body = serde_json::json!({"model": "gpt-4", "input": ["hello"]});
+        let key = crate::journal::RequestJournal::request_key("POST", "/v1/embeddings", &body);
+        journal
+            .record_completed(&key, &serde_json::json!({"object": "list"}))
+            .unwrap();
+
+        // Nothing listens on this port, so only the journal can answer
+        let client = Client::builder()
+            .base_url("http://127.0.0.1:9")
+            .unwrap()
+            .journal(journal)
+            .build()
+            .unwrap();
+        let response: serde_json::Value = client
+            .rest(Method::POST, "/v1/embeddings", Some(body))
+            .await
+            .unwrap();
+        assert_eq!(response["object"], "list");
+    }
 }
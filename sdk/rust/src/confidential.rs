@@ -0,0 +1,248 @@
+//! Confidential Metadata
+//!
+//! Workflows can mark resource metadata fields confidential. The server never sees
+//! those fields in the clear: the client encrypts them before creating or updating a
+//! resource, with a key only the client holds, and decrypts them after reading the
+//! resource back. Rules on the server only operate on non-encrypted fields, and the
+//! server refuses rules that read confidential ones.
+//!
+//! A [`MetadataCipher`] holds one AES-256-GCM key and its key id. Each value is
+//! serialized to JSON and sealed with a fresh random nonce and the field name as
+//! associated data, so an envelope copied to another field does not decrypt.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use circuit_breaker_sdk::{MetadataCipher, Result};
+//! use serde_json::json;
+//!
+//! fn main() -> Result<()> {
+//!     let cipher = MetadataCipher::generate("patients-2024")?;
+//!     let mut metadata = json!({"ssn": "123-45-6789", "tier": "gold"})
+//!         .as_object()
+//!         .cloned()
+//!         .unwrap();
+//!
+//!     // Send `metadata` when creating the resource; only `ssn` is encrypted
+//!     cipher.encrypt_fields(&mut metadata, &["ssn"])?;
+//!
+//!     // And decrypt what the server returns
+//!     cipher.decrypt_fields(&mut metadata, &["ssn"])?;
+//!     assert_eq!(metadata["ssn"], "123-45-6789");
+//!     Ok(())
+//! }
+//! ```
+
+use crate::{Error, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Key under which a metadata value holds its [`EncryptedValue`]
+pub const ENCRYPTED_KEY: &str = "$encrypted";
+
+/// Algorithm of the envelopes this SDK writes
+pub const ENCRYPTION_ALGORITHM: &str = "A256GCM";
+
+/// An encrypted metadata value as stored by the server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncryptedValue {
+    pub alg: String,
+    /// Id of the key that encrypted the value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    /// Base64 nonce
+    pub nonce: String,
+    /// Base64 ciphertext followed by the authentication tag
+    pub ciphertext: String,
+}
+
+impl EncryptedValue {
+    /// The envelope a metadata value holds, if it is one
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let object = value.as_object()?;
+        if object.len() != 1 {
+            return None;
+        }
+        serde_json::from_value(object.get(ENCRYPTED_KEY)?.clone()).ok()
+    }
+
+    /// The value to send as metadata
+    pub fn to_value(&self) -> Value {
+        serde_json::json!({ ENCRYPTED_KEY: self })
+    }
+}
+
+/// Encrypts and decrypts confidential metadata with a client-held key
+pub struct MetadataCipher {
+    kid: String,
+    key: LessSafeKey,
+    rng: SystemRandom,
+}
+
+impl std::fmt::Debug for MetadataCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MetadataCipher")
+            .field("kid", &self.kid)
+            .finish_non_exhaustive()
+    }
+}
+
+fn cipher_error(message: impl Into<String>) -> Error {
+    Error::Validation {
+        message: message.into(),
+    }
+}
+
+impl MetadataCipher {
+    /// Use a 32-byte AES-256 key identified by `kid`
+    pub fn new(kid: impl Into<String>, key: &[u8]) -> Result<Self> {
+        let key = UnboundKey::new(&AES_256_GCM, key)
+            .map_err(|_| cipher_error("Metadata encryption keys must be 32 bytes"))?;
+        Ok(Self {
+            kid: kid.into(),
+            key: LessSafeKey::new(key),
+            rng: SystemRandom::new(),
+        })
+    }
+
+    /// Use a random key that lives as long as the cipher; keys that must outlive the
+    /// process come from [`MetadataCipher::generate_key`] and the client's secret store
+    pub fn generate(kid: impl Into<String>) -> Result<Self> {
+        Self::new(kid, &Self::generate_key()?)
+    }
+
+    /// A random 32-byte key to store in the client's secret store
+    pub fn generate_key() -> Result<[u8; 32]> {
+        let mut key = [0u8; 32];
+        SystemRandom::new()
+            .fill(&mut key)
+            .map_err(|_| cipher_error("Failed to generate a metadata encryption key"))?;
+        Ok(key)
+    }
+
+    /// Id of the key, recorded in every envelope
+    pub fn kid(&self) -> &str {
+        &self.kid
+    }
+
+    /// Encrypt the value of a metadata field
+    pub fn encrypt(&self, field: &str, value: &Value) -> Result<EncryptedValue> {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| cipher_error("Failed to generate a nonce"))?;
+        let mut sealed = serde_json::to_vec(value)?;
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(field.as_bytes()),
+                &mut sealed,
+            )
+            .map_err(|_| cipher_error(format!("Failed to encrypt field '{}'", field)))?;
+
+        Ok(EncryptedValue {
+            alg: ENCRYPTION_ALGORITHM.to_string(),
+            kid: Some(self.kid.clone()),
+            nonce: STANDARD.encode(nonce),
+            ciphertext: STANDARD.encode(sealed),
+        })
+    }
+
+    /// Decrypt the envelope of a metadata field
+    pub fn decrypt(&self, field: &str, envelope: &EncryptedValue) -> Result<Value> {
+        if envelope.alg != ENCRYPTION_ALGORITHM {
+            return Err(cipher_error(format!(
+                "Field '{}' uses unsupported algorithm '{}'",
+                field, envelope.alg
+            )));
+        }
+        if let Some(kid) = envelope.kid.as_deref().filter(|kid| *kid != self.kid) {
+            return Err(cipher_error(format!(
+                "Field '{}' was encrypted with key '{}', not '{}'",
+                field, kid, self.kid
+            )));
+        }
+        let nonce: [u8; NONCE_LEN] = STANDARD
+            .decode(&envelope.nonce)
+            .ok()
+            .and_then(|nonce| nonce.try_into().ok())
+            .ok_or_else(|| cipher_error(format!("Field '{}' has an invalid nonce", field)))?;
+        let mut sealed = STANDARD
+            .decode(&envelope.ciphertext)
+            .map_err(|_| cipher_error(format!("Field '{}' has invalid ciphertext", field)))?;
+        let plaintext = self
+            .key
+            .open_in_place(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(field.as_bytes()),
+                &mut sealed,
+            )
+            .map_err(|_| cipher_error(format!("Failed to decrypt field '{}'", field)))?;
+
+        Ok(serde_json::from_slice(plaintext)?)
+    }
+
+    /// Encrypt the listed fields of a metadata object in place; missing fields and
+    /// fields already encrypted are left alone
+    pub fn encrypt_fields(&self, metadata: &mut Map<String, Value>, fields: &[&str]) -> Result<()> {
+        for field in fields {
+            if let Some(value) = metadata.get_mut(*field) {
+                if EncryptedValue::from_value(value).is_none() {
+                    *value = self.encrypt(field, value)?.to_value();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Decrypt the listed fields of a metadata object in place; fields that are not
+    /// encrypted are left alone
+    pub fn decrypt_fields(&self, metadata: &mut Map<String, Value>, fields: &[&str]) -> Result<()> {
+        for field in fields {
+            if let Some(value) = metadata.get_mut(*field) {
+                if let Some(envelope) = EncryptedValue::from_value(value) {
+                    *value = self.decrypt(field, &envelope)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_round_trip_binds_field_and_key() {
+        let cipher = MetadataCipher::new("k1", &[7u8; 32]).unwrap();
+        let mut metadata = json!({"ssn": "123-45-6789", "record": {"mrn": 42}, "tier": "gold"})
+            .as_object()
+            .cloned()
+            .unwrap();
+
+        cipher
+            .encrypt_fields(&mut metadata, &["ssn", "record", "missing"])
+            .unwrap();
+        assert_eq!(metadata["tier"], "gold");
+        let envelope = EncryptedValue::from_value(&metadata["ssn"]).unwrap();
+        assert_eq!(envelope.kid.as_deref(), Some("k1"));
+        assert!(!metadata["ssn"].to_string().contains("6789"));
+
+        // Envelopes only open under their own field name and key
+        assert!(cipher.decrypt("record", &envelope).is_err());
+        let other = MetadataCipher::new("k2", &[7u8; 32]).unwrap();
+        assert!(other.decrypt("ssn", &envelope).is_err());
+
+        cipher
+            .decrypt_fields(&mut metadata, &["ssn", "record", "tier"])
+            .unwrap();
+        assert_eq!(metadata["ssn"], "123-45-6789");
+        assert_eq!(metadata["record"], json!({"mrn": 42}));
+        assert!(MetadataCipher::new("short", &[0u8; 16]).is_err());
+    }
+}
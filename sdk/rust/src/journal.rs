@@ -0,0 +1,330 @@
+//! Request Journal
+//!
+//! Batch and ingestion jobs that die halfway should not pay twice for the items they
+//! already finished. A [`RequestJournal`] is an append-only JSON Lines file the client
+//! writes every outgoing REST request to before sending it, and its response to once
+//! it arrives. A client built with a journal answers a request the journal already holds
+//! a response for from the journal instead of sending it again, so rerunning a job after
+//! a crash resumes where it stopped.
+//!
+//! Requests are identified by a hash of their method, path and body: identical requests
+//! share one journal entry. Only requests that change or cost something are journaled;
+//! `GET` requests always reach the server so reads are never stale. Requests that failed,
+//! or were still in flight when the process stopped, are sent again on the next run.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use circuit_breaker_sdk::{create_chat, Client, RequestJournal, Result};
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<()> {
+//!     let client = Client::builder()
+//!         .journal(RequestJournal::open("ingest.journal")?)
+//!         .build()?;
+//!
+//!     for document in ["first", "second"] {
+//!         // Documents summarized before a crash are answered from the journal
+//!         let summary = create_chat("gpt-4o-mini")
+//!             .add_user_message(format!("Summarize: {}", document))
+//!             .execute(&client.llm())
+//!             .await?;
+//!         println!("{}", summary.choices[0].message.content);
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use crate::{Error, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// One line of a journal file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum JournalEntry {
+    /// The request is about to be sent
+    Started {
+        key: String,
+        method: String,
+        path: String,
+        request: Value,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    /// The server answered successfully
+    Completed {
+        key: String,
+        response: Value,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    /// The request failed and will be sent again on the next run
+    Failed {
+        key: String,
+        error: String,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+}
+
+/// Counts of the requests a journal holds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JournalStats {
+    /// Requests with a recorded response
+    pub completed: usize,
+    /// Requests whose last attempt failed
+    pub failed: usize,
+    /// Requests sent without a recorded outcome, e.g. because the process stopped
+    pub in_flight: usize,
+}
+
+#[derive(Debug)]
+struct JournalState {
+    file: std::fs::File,
+    completed: HashMap<String, Value>,
+    failed: HashSet<String>,
+    in_flight: HashSet<String>,
+}
+
+/// Append-only journal of REST requests and their responses
+///
+/// Clones write to the same file.
+#[derive(Debug, Clone)]
+pub struct RequestJournal {
+    path: PathBuf,
+    state: Arc<Mutex<JournalState>>,
+}
+
+impl RequestJournal {
+    /// Open the journal at `path`, creating it if needed and replaying what it holds
+    ///
+    /// Lines that cannot be parsed are skipped. A last line cut short by a crash is
+    /// truncated away, so the next entry starts a line of its own.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut completed = HashMap::new();
+        let mut failed = HashSet::new();
+        let mut in_flight = HashSet::new();
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| journal_error(&path, e))?;
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)
+            .map_err(|e| journal_error(&path, e))?;
+        let complete = contents
+            .iter()
+            .rposition(|byte| *byte == b'\n')
+            .map_or(0, |newline| newline + 1);
+        if complete < contents.len() {
+            file.set_len(complete as u64)
+                .map_err(|e| journal_error(&path, e))?;
+        }
+
+        for line in contents[..complete].split(|byte| *byte == b'\n') {
+            match serde_json::from_slice::<JournalEntry>(line) {
+                Ok(JournalEntry::Started { key, .. }) => {
+                    failed.remove(&key);
+                    in_flight.insert(key);
+                }
+                Ok(JournalEntry::Completed { key, response, .. }) => {
+                    in_flight.remove(&key);
+                    failed.remove(&key);
+                    completed.insert(key, response);
+                }
+                Ok(JournalEntry::Failed { key, .. }) => {
+                    in_flight.remove(&key);
+                    failed.insert(key);
+                }
+                Err(_) => {}
+            }
+        }
+
+        Ok(Self {
+            path,
+            state: Arc::new(Mutex::new(JournalState {
+                file,
+                completed,
+                failed,
+                in_flight,
+            })),
+        })
+    }
+
+    /// Path of the journal file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Key identifying a request by its method, path and body
+    pub fn request_key(method: &str, path: &str, body: &Value) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(method.as_bytes());
+        hasher.update([0]);
+        hasher.update(path.as_bytes());
+        hasher.update([0]);
+        hasher.update(body.to_string().as_bytes());
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// Recorded response of a completed request
+    pub fn response(&self, key: &str) -> Option<Value> {
+        self.state.lock().completed.get(key).cloned()
+    }
+
+    /// Whether the journal holds a response for the request
+    pub fn is_completed(&self, key: &str) -> bool {
+        self.state.lock().completed.contains_key(key)
+    }
+
+    /// Counts of completed, failed and in-flight requests
+    pub fn stats(&self) -> JournalStats {
+        let state = self.state.lock();
+        JournalStats {
+            completed: state.completed.len(),
+            failed: state.failed.len(),
+            in_flight: state.in_flight.len(),
+        }
+    }
+
+    /// Record that a request is about to be sent
+    pub fn record_started(
+        &self,
+        key: &str,
+        method: &str,
+        path: &str,
+        request: &Value,
+    ) -> Result<()> {
+        let mut state = self.state.lock();
+        self.append(
+            &mut state,
+            &JournalEntry::Started {
+                key: key.to_string(),
+                method: method.to_string(),
+                path: path.to_string(),
+                request: request.clone(),
+                timestamp: chrono::Utc::now(),
+            },
+        )?;
+        state.failed.remove(key);
+        state.in_flight.insert(key.to_string());
+        Ok(())
+    }
+
+    /// Record a request's response
+    pub fn record_completed(&self, key: &str, response: &Value) -> Result<()> {
+        let mut state = self.state.lock();
+        self.append(
+            &mut state,
+            &JournalEntry::Completed {
+                key: key.to_string(),
+                response: response.clone(),
+                timestamp: chrono::Utc::now(),
+            },
+        )?;
+        state.in_flight.remove(key);
+        state.completed.insert(key.to_string(), response.clone());
+        Ok(())
+    }
+
+    /// Record that a request failed, so the next run sends it again
+    pub fn record_failed(&self, key: &str, error: &str) -> Result<()> {
+        let mut state = self.state.lock();
+        self.append(
+            &mut state,
+            &JournalEntry::Failed {
+                key: key.to_string(),
+                error: error.to_string(),
+                timestamp: chrono::Utc::now(),
+            },
+        )?;
+        state.in_flight.remove(key);
+        state.failed.insert(key.to_string());
+        Ok(())
+    }
+
+    /// Append an entry and sync it to disk before the request moves on
+    fn append(&self, state: &mut JournalState, entry: &JournalEntry) -> Result<()> {
+        let line = serde_json::to_string(entry).map_err(|e| Error::Parse {
+            message: format!("Failed to serialize journal entry: {}", e),
+        })?;
+        writeln!(state.file, "{}", line)
+            .and_then(|_| state.file.sync_data())
+            .map_err(|e| journal_error(&self.path, e))
+    }
+}
+
+fn journal_error(path: &Path, e: std::io::Error) -> Error {
+    Error::Configuration {
+        message: format!("Request journal {}: {}", path.display(), e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal_replays_outcomes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("batch.journal");
+        let body = serde_json::json!({"model": "gpt-4", "input": ["a"]});
+        let done = RequestJournal::request_key("POST", "/v1/embeddings", &body);
+        let crashed = RequestJournal::request_key("POST", "/v1/chat/completions", &body);
+        let failed = RequestJournal::request_key("POST", "/v1/embeddings", &Value::Null);
+        assert_ne!(done, crashed);
+
+        let journal = RequestJournal::open(&path).unwrap();
+        journal
+            .record_started(&done, "POST", "/v1/embeddings", &body)
+            .unwrap();
+        journal
+            .record_completed(&done, &serde_json::json!({"ok": true}))
+            .unwrap();
+        journal
+            .record_started(&crashed, "POST", "/v1/chat/completions", &body)
+            .unwrap();
+        journal
+            .record_started(&failed, "POST", "/v1/embeddings", &Value::Null)
+            .unwrap();
+        journal.record_failed(&failed, "rate limited").unwrap();
+        drop(journal);
+
+        // A line torn by the crash is dropped, so the next entry is not appended to it
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        write!(file, "{{\"event\":\"completed\",\"key\":").unwrap();
+        RequestJournal::open(&path)
+            .unwrap()
+            .record_failed(&crashed, "timed out")
+            .unwrap();
+
+        let journal = RequestJournal::open(&path).unwrap();
+        assert_eq!(
+            journal.response(&done),
+            Some(serde_json::json!({"ok": true}))
+        );
+        assert!(!journal.is_completed(&crashed));
+        assert_eq!(
+            journal.stats(),
+            JournalStats {
+                completed: 1,
+                failed: 2,
+                in_flight: 0,
+            }
+        );
+    }
+}
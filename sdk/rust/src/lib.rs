@@ -39,7 +39,9 @@
 pub mod agents;
 pub mod analytics;
 pub mod client;
+pub mod confidential;
 pub mod functions;
+pub mod journal;
 pub mod llm;
 pub mod mcp;
 pub mod nats;
@@ -47,28 +49,39 @@ pub mod resources;
 pub mod rules;
 pub mod schema;
 pub mod subscriptions;
+pub mod tenants;
 pub mod types;
 pub mod workflows;
 
 // Re-export main client types
-pub use client::{Client, ClientBuilder, ClientConfig};
+pub use client::{Client, ClientBuilder, ClientConfig, ServerMeta};
 pub use types::*;
 
 // Re-export commonly used types from each module
-pub use agents::{Agent, AgentBuilder};
-pub use analytics::{AnalyticsClient, BudgetStatus, CostAnalytics};
+pub use agents::{Agent, AgentBuilder, AgentResponse};
+pub use analytics::{
+    AnalyticsClient, BudgetStatus, ChargebackDimension, ChargebackGroup, ChargebackReport,
+    CostAnalytics, RequestCostReport,
+};
+pub use confidential::{EncryptedValue, MetadataCipher};
 pub use functions::{Function, FunctionBuilder, FunctionExecution};
+pub use journal::{JournalStats, RequestJournal};
 pub use llm::{
     common_models, BudgetConstraint, ChatBuilder, ChatCompletionRequest, ChatCompletionResponse,
-    ChatMessage, ChatRole, CircuitBreakerOptions, LLMClient, RoutingStrategy,
-    SmartCompletionRequest, TaskType,
+    ChatMessage, ChatRole, CircuitBreakerOptions, EmbeddingCache, EmbeddingCacheStats,
+    EmbeddingsResponse, HasUsageInfo, JsonSchemaFormat, LLMClient, RequestPriority,
+    ResponseFormat, RoutingInfo, RoutingStrategy, SmartCompletionRequest, TaskType, UsageInfo,
 };
 pub use mcp::{MCPClient, MCPServer, MCPServerStatus, MCPServerType};
 pub use nats::{HistoryEvent, NATSClient, NATSResource};
 pub use resources::{Resource, ResourceBuilder};
 pub use rules::{Rule, RuleBuilder, RuleEvaluator};
 pub use subscriptions::{SubscriptionClient, SubscriptionId, SubscriptionManager};
-pub use workflows::{Workflow, WorkflowBuilder, WorkflowExecution};
+pub use tenants::{
+    IssuedApiKey, Tenant, TenantBudget, TenantDetails, TenantQuota, TenantRoutingPolicy,
+    TenantUsage, TenantsClient,
+};
+pub use workflows::{Workflow, WorkflowBuilder, WorkflowChangeset, WorkflowExecution};
 
 // Re-export convenience builders
 pub use agents::create_agent;
@@ -129,6 +142,98 @@ pub enum Error {
 
     #[error("Stream error: {message}")]
     Stream { message: String },
+
+    #[error("Server {server_version} does not support '{feature}'")]
+    Unsupported {
+        feature: String,
+        server_version: String,
+    },
+
+    #[error("API error {code:?} ({status}): {message}")]
+    Api {
+        code: ErrorCode,
+        status: u16,
+        message: String,
+    },
+}
+
+/// Stable, machine-readable error code returned by the server
+///
+/// Carried in `error.error_code` of REST error bodies, `extensions.code` of GraphQL
+/// errors and `error.data.error_code` of MCP errors. Codes added by newer servers
+/// deserialize as `Unknown`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    InvalidInput,
+    WorkflowNotFound,
+    ResourceNotFound,
+    AgentNotFound,
+    ExecutionNotFound,
+    RuleNotFound,
+    ModelNotFound,
+    NotFound,
+    InvalidStateTransition,
+    RuleValidationFailed,
+    ContextLengthExceeded,
+    ContentFlagged,
+    BudgetExceeded,
+    RateLimited,
+    ProviderUnavailable,
+    ProviderError,
+    AuthenticationFailed,
+    PermissionDenied,
+    Timeout,
+    StorageError,
+    MaintenanceMode,
+    Internal,
+    #[serde(other)]
+    Unknown,
+}
+
+impl Error {
+    /// Server error code, if the server returned one
+    pub fn code(&self) -> Option<ErrorCode> {
+        match self {
+            Error::Api { code, .. } => Some(*code),
+            _ => None,
+        }
+    }
+
+    /// Build an error from a non-success HTTP response body
+    ///
+    /// Bodies of the form `{"error": {"message": ..., "error_code": ...}}` become
+    /// `Error::Api`; anything else is reported as `Error::Server` with the raw body.
+    pub fn from_response_body(status: u16, body: &str) -> Self {
+        #[derive(serde::Deserialize)]
+        struct ErrorBody {
+            error: ErrorBodyDetail,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct ErrorBodyDetail {
+            message: String,
+            error_code: Option<ErrorCode>,
+        }
+
+        match serde_json::from_str::<ErrorBody>(body) {
+            Ok(ErrorBody {
+                error:
+                    ErrorBodyDetail {
+                        message,
+                        error_code: Some(code),
+                    },
+            }) => Error::Api {
+                code,
+                status,
+                message,
+            },
+            _ => Error::Server {
+                status,
+                message: body.to_string(),
+            },
+        }
+    }
 }
 
 impl From<reqwest::Error> for Error {
@@ -176,4 +281,22 @@ mod tests {
     fn test_default_base_url() {
         assert_eq!(DEFAULT_BASE_URL, "http://localhost:3000");
     }
+
+    #[test]
+    fn test_error_from_response_body() {
+        let error = Error::from_response_body(
+            404,
+            r#"{"error":{"message":"Model not found","type":"invalid_request_error","error_code":"MODEL_NOT_FOUND"}}"#,
+        );
+        assert_eq!(error.code(), Some(ErrorCode::ModelNotFound));
+
+        let error = Error::from_response_body(
+            400,
+            r#"{"error":{"message":"?","type":"x","error_code":"SOMETHING_NEW"}}"#,
+        );
+        assert_eq!(error.code(), Some(ErrorCode::Unknown));
+
+        let error = Error::from_response_body(500, "oops");
+        assert!(matches!(error, Error::Server { status: 500, .. }));
+    }
 }
@@ -4,7 +4,13 @@
 //! through the Circuit Breaker router using OpenAI-compatible API calls.
 
 use crate::{Client, Result};
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::Arc;
 
 /// Common LLM models used across providers
 pub mod common_models {
@@ -26,12 +32,22 @@ pub mod common_models {
 #[derive(Debug, Clone)]
 pub struct LLMClient {
     client: Client,
+    embedding_cache: Option<EmbeddingCache>,
 }
 
 impl LLMClient {
     /// Create a new LLM client
     pub(crate) fn new(client: Client) -> Self {
-        Self { client }
+        Self {
+            client,
+            embedding_cache: None,
+        }
+    }
+
+    /// Serve repeated embeddings of the same model and text from `cache`
+    pub fn with_embedding_cache(mut self, cache: EmbeddingCache) -> Self {
+        self.embedding_cache = Some(cache);
+        self
     }
 
     /// Make a smart completion request with Circuit Breaker routing
@@ -47,6 +63,9 @@ impl LLMClient {
         &self,
         request: ChatCompletionRequest,
     ) -> Result<ChatCompletionResponse> {
+        if request.response_format.is_some() {
+            self.client.require_feature("structured_output")?;
+        }
         self.client
             .rest(reqwest::Method::POST, "/v1/chat/completions", Some(request))
             .await
@@ -57,6 +76,7 @@ impl LLMClient {
         &self,
         request: ChatCompletionRequest,
     ) -> Result<impl futures::Stream<Item = Result<ChatCompletionChunk>>> {
+        self.client.require_feature("streaming")?;
         let mut streaming_request = request;
         streaming_request.stream = Some(true);
 
@@ -102,8 +122,11 @@ impl LLMClient {
         if !response.status().is_success() {
             let status = response.status();
             let error_text = response.text().await.unwrap_or_default();
-            return Err(crate::Error::Network {
-                message: format!("HTTP {} - {}", status, error_text),
+            return Err(match crate::Error::from_response_body(status.as_u16(), &error_text) {
+                crate::Error::Server { .. } => crate::Error::Network {
+                    message: format!("HTTP {} - {}", status, error_text),
+                },
+                error => error,
             });
         }
 
@@ -188,6 +211,106 @@ impl LLMClient {
         Ok(models_response.data)
     }
 
+    /// Embed texts through the Circuit Breaker router
+    ///
+    /// With an embedding cache attached, only texts the cache has not seen are sent to the
+    /// router; the response still holds one embedding per input, in input order, and its
+    /// usage counts only the tokens that were sent.
+    pub async fn embeddings<S: AsRef<str>>(
+        &self,
+        model: &str,
+        input: &[S],
+    ) -> Result<EmbeddingsResponse> {
+        self.client.require_feature("embeddings")?;
+
+        let mut embeddings: Vec<Option<Vec<f32>>> = input
+            .iter()
+            .map(|text| {
+                self.embedding_cache
+                    .as_ref()
+                    .and_then(|cache| cache.get(model, text.as_ref()))
+            })
+            .collect();
+        let missing: Vec<usize> = (0..input.len())
+            .filter(|&index| embeddings[index].is_none())
+            .collect();
+
+        let mut usage = EmbeddingsUsage::default();
+        let mut response_model = model.to_string();
+        if !missing.is_empty() {
+            let request = EmbeddingsRequest {
+                model: model.to_string(),
+                input: missing
+                    .iter()
+                    .map(|&index| input[index].as_ref().to_string())
+                    .collect(),
+            };
+            let response: EmbeddingsResponse = self
+                .client
+                .rest(reqwest::Method::POST, "/v1/embeddings", Some(request))
+                .await?;
+            if response.data.len() != missing.len() {
+                return Err(crate::Error::Parse {
+                    message: format!(
+                        "Expected {} embeddings, received {}",
+                        missing.len(),
+                        response.data.len()
+                    ),
+                });
+            }
+
+            for data in response.data {
+                let index = *missing.get(data.index as usize).ok_or_else(|| {
+                    crate::Error::Parse {
+                        message: format!("Unexpected embedding index {}", data.index),
+                    }
+                })?;
+                if let Some(cache) = &self.embedding_cache {
+                    cache.insert(model, input[index].as_ref(), data.embedding.clone());
+                }
+                embeddings[index] = Some(data.embedding);
+            }
+            usage = response.usage;
+            response_model = response.model;
+        }
+
+        let data = embeddings
+            .into_iter()
+            .enumerate()
+            .map(|(index, embedding)| {
+                embedding
+                    .map(|embedding| EmbeddingData {
+                        object: "embedding".to_string(),
+                        embedding,
+                        index: index as u32,
+                    })
+                    .ok_or_else(|| crate::Error::Parse {
+                        message: format!("Missing embedding for input {}", index),
+                    })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(EmbeddingsResponse {
+            object: "list".to_string(),
+            data,
+            model: response_model,
+            usage,
+        })
+    }
+
+    /// Embed a single text, returning its embedding vector
+    pub async fn embed(&self, model: &str, text: &str) -> Result<Vec<f32>> {
+        let response = self.embeddings(model, &[text]).await?;
+        response
+            .data
+            .into_iter()
+            .next()
+            .map(|data| data.embedding)
+            .ok_or_else(|| crate::Error::Parse {
+                message: "No embedding returned".to_string(),
+            })
+    }
+
     /// Simple chat method with just model and message
     pub async fn chat(&self, model: &str, message: &str) -> Result<String> {
         let request = ChatCompletionRequest {
@@ -279,9 +402,45 @@ pub struct ChatCompletionRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub function_call: Option<serde_json::Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub circuit_breaker: Option<CircuitBreakerOptions>,
 }
 
+/// Structured output format for a chat completion
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    Text,
+    JsonObject,
+    JsonSchema { json_schema: JsonSchemaFormat },
+}
+
+/// JSON schema the reply must conform to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonSchemaFormat {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub schema: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+impl ResponseFormat {
+    /// Request JSON conforming to `schema`
+    pub fn json_schema(name: impl Into<String>, schema: serde_json::Value) -> Self {
+        Self::JsonSchema {
+            json_schema: JsonSchemaFormat {
+                name: name.into(),
+                description: None,
+                schema,
+                strict: Some(true),
+            },
+        }
+    }
+}
+
 impl Default for ChatCompletionRequest {
     fn default() -> Self {
         Self {
@@ -297,6 +456,7 @@ impl Default for ChatCompletionRequest {
             user: None,
             functions: None,
             function_call: None,
+            response_format: None,
             circuit_breaker: None,
         }
     }
@@ -311,6 +471,23 @@ pub struct ChatCompletionResponse {
     pub model: String,
     pub choices: Vec<ChatChoice>,
     pub usage: Option<TokenUsage>,
+    /// ID the request's cost details are fetched by (Circuit Breaker extension)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// How the request was routed (Circuit Breaker extension)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routing_info: Option<RoutingInfo>,
+}
+
+impl HasUsageInfo for ChatCompletionResponse {
+    fn usage_info(&self) -> UsageInfo {
+        UsageInfo {
+            request_id: self.request_id.clone(),
+            routing_info: self.routing_info.clone(),
+            cost_usd: self.usage.as_ref().and_then(|usage| usage.estimated_cost),
+            usage: self.usage.clone(),
+        }
+    }
 }
 
 /// Chat completion chunk for streaming
@@ -333,7 +510,7 @@ pub struct ChatMessage {
 }
 
 /// Chat role
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ChatRole {
     System,
@@ -373,6 +550,45 @@ pub struct TokenUsage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
     pub total_tokens: u32,
+    /// Cost in USD computed by the server (Circuit Breaker extension)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimated_cost: Option<f64>,
+}
+
+/// Routing decision the server made for a request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutingInfo {
+    /// Provider name such as `"OpenAI"`, or `{"Custom": name}` for custom providers
+    pub selected_provider: serde_json::Value,
+    pub routing_strategy: serde_json::Value,
+    pub latency_ms: u64,
+    pub retry_count: u32,
+    pub fallback_used: bool,
+    pub provider_used: serde_json::Value,
+    pub total_latency_ms: u64,
+    pub provider_latency_ms: u64,
+}
+
+/// Routing, token usage and cost of a single request
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageInfo {
+    /// ID to pass to `AnalyticsClient::report_for_request`
+    pub request_id: Option<String>,
+    pub routing_info: Option<RoutingInfo>,
+    pub usage: Option<TokenUsage>,
+    /// Cost in USD computed by the server
+    pub cost_usd: Option<f64>,
+}
+
+impl UsageInfo {
+    pub fn total_tokens(&self) -> u32 {
+        self.usage.as_ref().map_or(0, |usage| usage.total_tokens)
+    }
+}
+
+/// Responses reporting the routing, token usage and cost of their request
+pub trait HasUsageInfo {
+    fn usage_info(&self) -> UsageInfo;
 }
 
 /// Function definition for function calling
@@ -399,6 +615,198 @@ pub struct ModelsResponse {
     pub data: Vec<ModelInfo>,
 }
 
+/// OpenAI-compatible embeddings request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingsRequest {
+    pub model: String,
+    pub input: Vec<String>,
+}
+
+/// Embeddings response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingsResponse {
+    pub object: String,
+    pub data: Vec<EmbeddingData>,
+    pub model: String,
+    pub usage: EmbeddingsUsage,
+}
+
+/// One input's embedding
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingData {
+    pub object: String,
+    pub embedding: Vec<f32>,
+    pub index: u32,
+}
+
+/// Tokens used by an embeddings request
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EmbeddingsUsage {
+    pub prompt_tokens: u32,
+    pub total_tokens: u32,
+}
+
+/// Client-side cache of embedding vectors keyed by a hash of the model and text
+///
+/// Clones share the same entries. A persistent cache appends every new embedding to a JSON
+/// Lines file and reloads it when reopened, so indexing jobs that re-embed the same
+/// documents across runs only pay for text they have not embedded before.
+#[derive(Debug, Clone, Default)]
+pub struct EmbeddingCache {
+    inner: Arc<Mutex<EmbeddingCacheInner>>,
+}
+
+#[derive(Debug, Default)]
+struct EmbeddingCacheInner {
+    entries: HashMap<String, Vec<f32>>,
+    /// Append handle of the persistence file
+    file: Option<std::fs::File>,
+    hits: u64,
+    misses: u64,
+}
+
+/// Line of a persistent embedding cache file
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedEmbedding {
+    key: String,
+    embedding: Vec<f32>,
+}
+
+/// Embedding cache counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EmbeddingCacheStats {
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl EmbeddingCache {
+    /// Create an in-memory cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open a cache persisted to `path`, loading the embeddings it already holds
+    ///
+    /// Lines that cannot be parsed are skipped. A last line cut short by an interrupted
+    /// write is truncated away, so the next embedding starts a line of its own.
+    pub fn persistent(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let io_error = |e: std::io::Error| crate::Error::Configuration {
+            message: format!("Embedding cache {}: {}", path.display(), e),
+        };
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)
+            .map_err(io_error)?;
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents).map_err(io_error)?;
+        let complete = contents
+            .iter()
+            .rposition(|byte| *byte == b'\n')
+            .map_or(0, |newline| newline + 1);
+        if complete < contents.len() {
+            file.set_len(complete as u64).map_err(io_error)?;
+        }
+
+        let mut entries = HashMap::new();
+        for line in contents[..complete].split(|byte| *byte == b'\n') {
+            if let Ok(cached) = serde_json::from_slice::<CachedEmbedding>(line) {
+                entries.insert(cached.key, cached.embedding);
+            }
+        }
+
+        Ok(Self {
+            inner: Arc::new(Mutex::new(EmbeddingCacheInner {
+                entries,
+                file: Some(file),
+                ..Default::default()
+            })),
+        })
+    }
+
+    /// Cache key of a model and text
+    pub fn key(model: &str, text: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(model.as_bytes());
+        hasher.update([0]);
+        hasher.update(text.as_bytes());
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// Cached embedding of `text` by `model`
+    pub fn get(&self, model: &str, text: &str) -> Option<Vec<f32>> {
+        let mut inner = self.inner.lock();
+        let embedding = inner.entries.get(&Self::key(model, text)).cloned();
+        if embedding.is_some() {
+            inner.hits += 1;
+        } else {
+            inner.misses += 1;
+        }
+        embedding
+    }
+
+    /// Cache the embedding of `text` by `model`
+    ///
+    /// A failed write to the persistence file is logged and the embedding kept in memory.
+    pub fn insert(&self, model: &str, text: &str, embedding: Vec<f32>) {
+        let key = Self::key(model, text);
+        let mut inner = self.inner.lock();
+        if let Some(file) = inner.file.as_mut() {
+            let line = CachedEmbedding {
+                key: key.clone(),
+                embedding: embedding.clone(),
+            };
+            let written = serde_json::to_string(&line)
+                .map_err(std::io::Error::from)
+                .and_then(|json| writeln!(file, "{}", json));
+            if let Err(e) = written {
+                tracing::warn!("Failed to persist cached embedding: {}", e);
+            }
+        }
+        inner.entries.insert(key, embedding);
+    }
+
+    /// Number of cached embeddings
+    pub fn len(&self) -> usize {
+        self.inner.lock().entries.len()
+    }
+
+    /// Whether the cache holds no embeddings
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Entry count and lookup hits and misses
+    pub fn stats(&self) -> EmbeddingCacheStats {
+        let inner = self.inner.lock();
+        EmbeddingCacheStats {
+            entries: inner.entries.len(),
+            hits: inner.hits,
+            misses: inner.misses,
+        }
+    }
+
+    /// Remove every cached embedding, truncating the persistence file
+    pub fn clear(&self) -> Result<()> {
+        let mut inner = self.inner.lock();
+        inner.entries.clear();
+        if let Some(file) = &inner.file {
+            file.set_len(0).map_err(|e| crate::Error::Configuration {
+                message: format!("Embedding cache: {}", e),
+            })?;
+        }
+        Ok(())
+    }
+}
+
 /// OpenAI error response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OpenAIErrorResponse {
@@ -447,6 +855,18 @@ pub struct CircuitBreakerOptions {
     pub require_streaming: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub budget_constraint: Option<BudgetConstraint>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<RequestPriority>,
+}
+
+/// Queue priority class for requests
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestPriority {
+    /// User-facing traffic, dispatched first when providers are saturated
+    Interactive,
+    /// Background traffic
+    Batch,
 }
 
 /// Routing strategies for smart model selection
@@ -510,6 +930,7 @@ pub struct ChatBuilder {
     stop: Option<Vec<String>>,
     user: Option<String>,
     functions: Option<Vec<ChatFunction>>,
+    response_format: Option<ResponseFormat>,
     circuit_breaker: Option<CircuitBreakerOptions>,
 }
 
@@ -528,6 +949,7 @@ impl ChatBuilder {
             stop: None,
             user: None,
             functions: None,
+            response_format: None,
             circuit_breaker: None,
         }
     }
@@ -619,6 +1041,17 @@ impl ChatBuilder {
         self
     }
 
+    /// Set the structured output format
+    pub fn set_response_format(mut self, format: ResponseFormat) -> Self {
+        self.response_format = Some(format);
+        self
+    }
+
+    /// Require a reply conforming to a JSON schema
+    pub fn set_json_schema(self, name: impl Into<String>, schema: serde_json::Value) -> Self {
+        self.set_response_format(ResponseFormat::json_schema(name, schema))
+    }
+
     /// Set Circuit Breaker routing options
     pub fn set_circuit_breaker(mut self, options: CircuitBreakerOptions) -> Self {
         self.circuit_breaker = Some(options);
@@ -636,6 +1069,7 @@ impl ChatBuilder {
                 task_type: None,
                 require_streaming: None,
                 budget_constraint: None,
+                priority: None,
             });
         }
         self.circuit_breaker.as_mut().unwrap().routing_strategy = Some(strategy);
@@ -653,6 +1087,7 @@ impl ChatBuilder {
                 task_type: None,
                 require_streaming: None,
                 budget_constraint: None,
+                priority: None,
             });
         }
         self.circuit_breaker
@@ -673,6 +1108,7 @@ impl ChatBuilder {
                 task_type: None,
                 require_streaming: None,
                 budget_constraint: None,
+                priority: None,
             });
         }
         self.circuit_breaker.as_mut().unwrap().task_type = Some(task_type);
@@ -690,12 +1126,31 @@ impl ChatBuilder {
                 task_type: None,
                 require_streaming: None,
                 budget_constraint: None,
+                priority: None,
             });
         }
         self.circuit_breaker.as_mut().unwrap().fallback_models = Some(models);
         self
     }
 
+    /// Set queue priority class
+    pub fn set_priority(mut self, priority: RequestPriority) -> Self {
+        if self.circuit_breaker.is_none() {
+            self.circuit_breaker = Some(CircuitBreakerOptions {
+                routing_strategy: None,
+                max_cost_per_1k_tokens: None,
+                max_latency_ms: None,
+                fallback_models: None,
+                task_type: None,
+                require_streaming: None,
+                budget_constraint: None,
+                priority: None,
+            });
+        }
+        self.circuit_breaker.as_mut().unwrap().priority = Some(priority);
+        self
+    }
+
     /// Build the chat request
     pub fn build(self) -> ChatCompletionRequest {
         ChatCompletionRequest {
@@ -711,6 +1166,7 @@ impl ChatBuilder {
             user: self.user,
             functions: self.functions,
             function_call: None,
+            response_format: self.response_format,
             circuit_breaker: self.circuit_breaker,
         }
     }
@@ -797,6 +1253,7 @@ pub fn smart_chat_request(
             task_type: None,
             require_streaming: None,
             budget_constraint: None,
+            priority: None,
         }),
         ..Default::default()
     }
@@ -806,6 +1263,55 @@ pub fn smart_chat_request(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_embedding_cache_keys_by_model_and_text() {
+        let cache = EmbeddingCache::new();
+        cache.insert("text-embedding-3-small", "hello", vec![0.1, 0.2]);
+
+        assert_eq!(
+            cache.get("text-embedding-3-small", "hello"),
+            Some(vec![0.1, 0.2])
+        );
+        assert_eq!(cache.get("text-embedding-3-large", "hello"), None);
+        assert_eq!(cache.get("text-embedding-3-small", "hello!"), None);
+        assert_eq!(
+            cache.stats(),
+            EmbeddingCacheStats {
+                entries: 1,
+                hits: 1,
+                misses: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_persistent_embedding_cache_reloads() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("embeddings.jsonl");
+
+        let cache = EmbeddingCache::persistent(&path).unwrap();
+        cache.insert("text-embedding-3-small", "hello", vec![0.5, -0.5]);
+        cache.insert("text-embedding-3-small", "world", vec![0.25]);
+        drop(cache);
+
+        // A torn trailing line is dropped, so the next embedding is not appended to it
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        write!(file, "{{\"key\":\"abc").unwrap();
+
+        let reopened = EmbeddingCache::persistent(&path).unwrap();
+        assert_eq!(reopened.len(), 2);
+        reopened.insert("text-embedding-3-small", "again", vec![1.0]);
+        assert_eq!(EmbeddingCache::persistent(&path).unwrap().len(), 3);
+        assert_eq!(
+            reopened.get("text-embedding-3-small", "hello"),
+            Some(vec![0.5, -0.5])
+        );
+
+        reopened.clear().unwrap();
+        assert!(reopened.is_empty());
+        assert!(EmbeddingCache::persistent(&path).unwrap().is_empty());
+    }
+
     #[test]
     fn test_chat_builder() {
         let request = create_chat("gpt-4")
@@ -975,12 +1481,65 @@ mod tests {
             prompt_tokens: 50,
             completion_tokens: 25,
             total_tokens: 75,
+            estimated_cost: None,
         };
 
         let json = serde_json::to_string(&usage).unwrap();
         assert!(json.contains("\"prompt_tokens\":50"));
         assert!(json.contains("\"completion_tokens\":25"));
         assert!(json.contains("\"total_tokens\":75"));
+        assert!(!json.contains("estimated_cost"));
+    }
+
+    #[test]
+    fn test_usage_info_from_response() {
+        let response: ChatCompletionResponse = serde_json::from_value(serde_json::json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1700000000,
+            "model": "cb:cost-optimal",
+            "choices": [],
+            "usage": {
+                "prompt_tokens": 12,
+                "completion_tokens": 30,
+                "total_tokens": 42,
+                "estimated_cost": 0.0021
+            },
+            "request_id": "5f0c6f7e-4a1b-4d6a-9a53-0d3f3c0b2a11",
+            "routing_info": {
+                "selected_provider": "Anthropic",
+                "routing_strategy": "CostOptimized",
+                "latency_ms": 120,
+                "retry_count": 0,
+                "fallback_used": false,
+                "provider_used": "Anthropic",
+                "total_latency_ms": 130,
+                "provider_latency_ms": 118
+            }
+        }))
+        .unwrap();
+
+        let info = response.usage_info();
+        assert_eq!(
+            info.request_id.as_deref(),
+            Some("5f0c6f7e-4a1b-4d6a-9a53-0d3f3c0b2a11")
+        );
+        assert_eq!(info.cost_usd, Some(0.0021));
+        assert_eq!(info.total_tokens(), 42);
+        assert_eq!(info.routing_info.unwrap().selected_provider, "Anthropic");
+
+        // Plain OpenAI responses carry none of the extensions
+        let response: ChatCompletionResponse = serde_json::from_value(serde_json::json!({
+            "id": "chatcmpl-2",
+            "object": "chat.completion",
+            "created": 1700000000,
+            "model": "gpt-4",
+            "choices": []
+        }))
+        .unwrap();
+        let info = response.usage_info();
+        assert!(info.request_id.is_none() && info.cost_usd.is_none());
+        assert_eq!(info.total_tokens(), 0);
     }
 
     #[test]
@@ -1012,9 +1571,11 @@ mod tests {
             max_latency_ms: None,
             require_streaming: None,
             budget_constraint: None,
+            priority: Some(RequestPriority::Batch),
         };
 
         let json = serde_json::to_string(&options).unwrap();
+        assert!(json.contains("\"priority\":\"batch\""));
         assert!(json.contains("\"routing_strategy\":\"cost_optimized\""));
         assert!(json.contains("\"max_cost_per_1k_tokens\":0.01"));
         assert!(json.contains("\"task_type\":\"coding\""));
@@ -1040,10 +1601,11 @@ mod tests {
                 max_latency_ms: None,
                 require_streaming: None,
                 budget_constraint: None,
+                priority: None,
             }),
         };
 
-        assert_eq!(request.model, "smart-cheap");
+        assert_eq!(request.model, common_models::SMART_CHEAP);
         assert!(request.circuit_breaker.is_some());
     }
 
@@ -1055,7 +1617,7 @@ mod tests {
             .set_max_cost_per_1k_tokens(0.02)
             .build();
 
-        assert_eq!(request.model, "smart-cheap");
+        assert_eq!(request.model, common_models::SMART_CHEAP);
         assert!(request.circuit_breaker.is_some());
         let cb = request.circuit_breaker.unwrap();
         assert!(matches!(
@@ -1073,11 +1635,11 @@ mod tests {
             "Hello",
             RoutingStrategy::CostOptimized,
         );
-        assert_eq!(cost_request.model, "smart-cheap");
+        assert_eq!(cost_request.model, common_models::SMART_CHEAP);
         assert!(cost_request.circuit_breaker.is_some());
 
         let fast_builder = create_fast_chat();
         let fast_request = fast_builder.add_user_message("Quick question").build();
-        assert_eq!(fast_request.model, "smart-fast");
+        assert_eq!(fast_request.model, common_models::SMART_FAST);
     }
 }
@@ -0,0 +1,444 @@
+//! Tenant Administration Client
+//!
+//! This module automates tenant onboarding against the server's tenant administration
+//! API: registering tenants, configuring their quotas and routing policies, issuing API
+//! keys, assigning budgets and retrieving usage. Every call requires the client to be
+//! configured with the server's admin token as its API key.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use circuit_breaker_sdk::{Client, Result, TenantQuota};
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<()> {
+//!     let client = Client::builder()
+//!         .base_url("http://localhost:3000")?
+//!         .api_key("admin-token".to_string())
+//!         .connect()
+//!         .await?;
+//!     let tenants = client.tenants();
+//!
+//!     // Register a tenant with a daily token quota
+//!     tenants
+//!         .create("acme")
+//!         .name("Acme Corp")
+//!         .quota(TenantQuota::default().daily_tokens(1_000_000))
+//!         .execute()
+//!         .await?;
+//!
+//!     // Give it a monthly budget and an API key
+//!     tenants.set_budget("acme", 250.0, "monthly", 0.8).await?;
+//!     let key = tenants.issue_api_key("acme").await?;
+//!     println!("Hand this key to Acme: {}", key.api_key);
+//!
+//!     // Check what it has spent this month
+//!     let usage = tenants.usage("acme").from("2026-10-01").get().await?;
+//!     println!("{} requests, ${:.2}", usage.requests, usage.cost_usd);
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::client::Client;
+use crate::Result;
+use serde::{Deserialize, Serialize};
+
+/// Feature the server reports when tenant administration is available
+const TENANT_ADMIN_FEATURE: &str = "tenant_admin";
+
+/// Tenant administration client
+pub struct TenantsClient {
+    client: Client,
+}
+
+impl TenantsClient {
+    /// Create a new tenants client
+    pub(crate) fn new(client: Client) -> Self {
+        Self { client }
+    }
+
+    /// Register a tenant
+    pub fn create<S: Into<String>>(&self, tenant_id: S) -> CreateTenantBuilder {
+        CreateTenantBuilder::new(self.client.clone(), tenant_id.into())
+    }
+
+    /// List registered tenants
+    pub async fn list(&self) -> Result<Vec<Tenant>> {
+        self.client.require_feature(TENANT_ADMIN_FEATURE)?;
+        self.client
+            .rest::<Vec<Tenant>, ()>(reqwest::Method::GET, "/v1/admin/tenants", None)
+            .await
+    }
+
+    /// Get a tenant with its routing policy, budget and API keys
+    pub async fn get(&self, tenant_id: &str) -> Result<TenantDetails> {
+        self.client.require_feature(TENANT_ADMIN_FEATURE)?;
+        self.client
+            .rest::<TenantDetails, ()>(reqwest::Method::GET, &tenant_path(tenant_id, ""), None)
+            .await
+    }
+
+    /// Remove a tenant along with its routing policy and budget, revoking its API keys
+    pub async fn delete(&self, tenant_id: &str) -> Result<()> {
+        self.client.require_feature(TENANT_ADMIN_FEATURE)?;
+        self.client
+            .rest::<(), ()>(reqwest::Method::DELETE, &tenant_path(tenant_id, ""), None)
+            .await
+    }
+
+    /// Set the usage limits applied to the tenant's API keys
+    pub async fn set_quota(&self, tenant_id: &str, quota: TenantQuota) -> Result<Tenant> {
+        self.client.require_feature(TENANT_ADMIN_FEATURE)?;
+        self.client
+            .rest(
+                reqwest::Method::PUT,
+                &tenant_path(tenant_id, "/quota"),
+                Some(quota),
+            )
+            .await
+    }
+
+    /// Issue an API key for the tenant
+    ///
+    /// The returned secret cannot be retrieved again.
+    pub async fn issue_api_key(&self, tenant_id: &str) -> Result<IssuedApiKey> {
+        self.client.require_feature(TENANT_ADMIN_FEATURE)?;
+        self.client
+            .rest::<IssuedApiKey, ()>(
+                reqwest::Method::POST,
+                &tenant_path(tenant_id, "/api-keys"),
+                None,
+            )
+            .await
+    }
+
+    /// Revoke one of the tenant's API keys
+    pub async fn revoke_api_key(&self, tenant_id: &str, key_id: &str) -> Result<()> {
+        self.client.require_feature(TENANT_ADMIN_FEATURE)?;
+        self.client
+            .rest::<(), ()>(
+                reqwest::Method::DELETE,
+                &tenant_path(tenant_id, &format!("/api-keys/{}", key_id)),
+                None,
+            )
+            .await
+    }
+
+    /// Assign the budget the tenant's requests are checked against
+    ///
+    /// `period` is `daily`, `weekly`, `monthly` or `yearly`; `warning_threshold` is the share of
+    /// `limit` (0.0 - 1.0) past which responses carry a budget warning.
+    pub async fn set_budget(
+        &self,
+        tenant_id: &str,
+        limit: f64,
+        period: &str,
+        warning_threshold: f64,
+    ) -> Result<TenantBudget> {
+        self.client.require_feature(TENANT_ADMIN_FEATURE)?;
+        self.client
+            .rest(
+                reqwest::Method::PUT,
+                &tenant_path(tenant_id, "/budget"),
+                Some(serde_json::json!({
+                    "limit": limit,
+                    "period": period,
+                    "warning_threshold": warning_threshold,
+                })),
+            )
+            .await
+    }
+
+    /// Retrieve the tenant's recorded usage
+    pub fn usage<S: Into<String>>(&self, tenant_id: S) -> TenantUsageBuilder {
+        TenantUsageBuilder::new(self.client.clone(), tenant_id.into())
+    }
+}
+
+/// Path of a tenant endpoint
+fn tenant_path(tenant_id: &str, suffix: &str) -> String {
+    format!("/v1/admin/tenants/{}{}", tenant_id, suffix)
+}
+
+/// A registered tenant
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tenant {
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Limits applied to the tenant's API keys
+    #[serde(default)]
+    pub quota: Option<TenantQuota>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Usage limits carried by a tenant's API keys
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TenantQuota {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily_tokens: Option<u64>,
+    /// Monthly spend in USD
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monthly_cost: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit_per_minute: Option<u32>,
+}
+
+impl TenantQuota {
+    pub fn daily_tokens(mut self, daily_tokens: u64) -> Self {
+        self.daily_tokens = Some(daily_tokens);
+        self
+    }
+
+    pub fn monthly_cost(mut self, monthly_cost: f64) -> Self {
+        self.monthly_cost = Some(monthly_cost);
+        self
+    }
+
+    pub fn rate_limit_per_minute(mut self, rate_limit_per_minute: u32) -> Self {
+        self.rate_limit_per_minute = Some(rate_limit_per_minute);
+        self
+    }
+}
+
+/// Routing overrides for a tenant; `None` fields keep the server's global behaviour
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TenantRoutingPolicy {
+    /// Strategy for virtual models, e.g. `cost_optimized` or `performance_first`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<String>,
+    /// Providers such as `"OpenAI"`, or `{"Custom": name}` for custom providers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_providers: Option<Vec<serde_json::Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_allowlist: Option<Vec<String>>,
+}
+
+/// An issued API key, without its secret
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiKeySummary {
+    pub key_id: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    #[serde(default)]
+    pub last_used: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// A newly issued API key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssuedApiKey {
+    pub key_id: String,
+    /// The bearer token to hand to the tenant
+    pub api_key: String,
+    pub tenant_id: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// A tenant's budget
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TenantBudget {
+    pub id: String,
+    #[serde(default)]
+    pub project_id: Option<String>,
+    /// Spending limit in USD per period
+    pub limit: f64,
+    pub period: String,
+    pub warning_threshold: f64,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// A tenant with everything configured for it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TenantDetails {
+    #[serde(flatten)]
+    pub tenant: Tenant,
+    #[serde(default)]
+    pub routing_policy: Option<TenantRoutingPolicy>,
+    #[serde(default)]
+    pub budget: Option<TenantBudget>,
+    #[serde(default)]
+    pub api_keys: Vec<ApiKeySummary>,
+}
+
+/// Usage of one model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelUsage {
+    pub provider: String,
+    pub model: String,
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+}
+
+/// Usage recorded for a tenant over a period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TenantUsage {
+    pub tenant_id: String,
+    pub from: chrono::DateTime<chrono::Utc>,
+    pub to: chrono::DateTime<chrono::Utc>,
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Cost in USD
+    pub cost_usd: f64,
+    /// Totals per provider and model, most expensive first
+    #[serde(default)]
+    pub by_model: Vec<ModelUsage>,
+}
+
+impl TenantUsage {
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
+}
+
+/// Builder for registering a tenant
+pub struct CreateTenantBuilder {
+    client: Client,
+    id: String,
+    name: Option<String>,
+    quota: Option<TenantQuota>,
+    routing_policy: Option<TenantRoutingPolicy>,
+}
+
+impl CreateTenantBuilder {
+    fn new(client: Client, id: String) -> Self {
+        Self {
+            client,
+            id,
+            name: None,
+            quota: None,
+            routing_policy: None,
+        }
+    }
+
+    /// Display name
+    pub fn name<S: Into<String>>(mut self, name: S) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Usage limits for the tenant's API keys
+    pub fn quota(mut self, quota: TenantQuota) -> Self {
+        self.quota = Some(quota);
+        self
+    }
+
+    /// Routing overrides for the tenant's requests
+    pub fn routing_policy(mut self, routing_policy: TenantRoutingPolicy) -> Self {
+        self.routing_policy = Some(routing_policy);
+        self
+    }
+
+    /// Register the tenant
+    pub async fn execute(self) -> Result<TenantDetails> {
+        self.client.require_feature(TENANT_ADMIN_FEATURE)?;
+        let body = serde_json::json!({
+            "id": self.id,
+            "name": self.name,
+            "quota": self.quota,
+            "routing_policy": self.routing_policy,
+        });
+        self.client
+            .rest(reqwest::Method::POST, "/v1/admin/tenants", Some(body))
+            .await
+    }
+}
+
+/// Builder for retrieving a tenant's usage
+pub struct TenantUsageBuilder {
+    client: Client,
+    tenant_id: String,
+    from: Option<String>,
+    to: Option<String>,
+}
+
+impl TenantUsageBuilder {
+    fn new(client: Client, tenant_id: String) -> Self {
+        Self {
+            client,
+            tenant_id,
+            from: None,
+            to: None,
+        }
+    }
+
+    /// Start of the period, as an RFC 3339 timestamp or a date; defaults to 30 days before `to`
+    pub fn from<S: Into<String>>(mut self, from: S) -> Self {
+        self.from = Some(from.into());
+        self
+    }
+
+    /// End of the period; a date includes the whole day. Defaults to now.
+    pub fn to<S: Into<String>>(mut self, to: S) -> Self {
+        self.to = Some(to.into());
+        self
+    }
+
+    /// Fetch the usage
+    pub async fn get(self) -> Result<TenantUsage> {
+        self.client.require_feature(TENANT_ADMIN_FEATURE)?;
+        let query: Vec<String> = [("from", &self.from), ("to", &self.to)]
+            .into_iter()
+            .filter_map(|(name, value)| value.as_ref().map(|value| format!("{}={}", name, value)))
+            .collect();
+        let mut path = tenant_path(&self.tenant_id, "/usage");
+        if !query.is_empty() {
+            path = format!("{}?{}", path, query.join("&"));
+        }
+        self.client
+            .rest::<TenantUsage, ()>(reqwest::Method::GET, &path, None)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tenant_details_deserialization() {
+        let details: TenantDetails = serde_json::from_value(serde_json::json!({
+            "id": "acme",
+            "name": "Acme Corp",
+            "quota": {"daily_tokens": 1000000},
+            "created_at": "2026-10-15T12:00:00Z",
+            "routing_policy": {"allowed_providers": ["OpenAI", {"Custom": "vllm"}]},
+            "budget": {
+                "id": "project:acme",
+                "user_id": null,
+                "project_id": "acme",
+                "limit": 250.0,
+                "period": "monthly",
+                "warning_threshold": 0.8,
+                "created_at": "2026-10-15T12:00:00Z",
+                "updated_at": "2026-10-15T12:00:00Z"
+            },
+            "api_keys": [{"key_id": "key_0123456789abcdef", "created_at": "2026-10-15T12:00:00Z"}]
+        }))
+        .unwrap();
+
+        assert_eq!(details.tenant.id, "acme");
+        assert_eq!(
+            details.tenant.quota,
+            Some(TenantQuota::default().daily_tokens(1_000_000))
+        );
+        assert_eq!(
+            details.routing_policy.unwrap().allowed_providers.unwrap()[1],
+            serde_json::json!({"Custom": "vllm"})
+        );
+        assert_eq!(details.budget.unwrap().period, "monthly");
+        assert_eq!(details.api_keys.len(), 1);
+    }
+
+    #[test]
+    fn test_quota_serialization_skips_unset_limits() {
+        let quota = TenantQuota::default().monthly_cost(50.0);
+        assert_eq!(
+            serde_json::to_value(&quota).unwrap(),
+            serde_json::json!({"monthly_cost": 50.0})
+        );
+    }
+}
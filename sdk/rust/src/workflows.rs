@@ -696,6 +696,24 @@ impl WorkflowBuilderStandalone {
             from: from.into(),
             to: to.into(),
             trigger: trigger.into(),
+            conditions: Vec::new(),
+        });
+        self
+    }
+
+    /// Add a transition that only fires when every condition holds
+    pub fn add_guarded_transition(
+        mut self,
+        from: impl Into<String>,
+        to: impl Into<String>,
+        trigger: impl Into<String>,
+        conditions: Vec<String>,
+    ) -> Self {
+        self.transitions.push(WorkflowTransition {
+            from: from.into(),
+            to: to.into(),
+            trigger: trigger.into(),
+            conditions,
         });
         self
     }
@@ -718,7 +736,7 @@ impl WorkflowBuilderStandalone {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct WorkflowDefinition {
     pub name: String,
     pub description: Option<String>,
@@ -727,15 +745,324 @@ pub struct WorkflowDefinition {
     pub initial_state: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl WorkflowDefinition {
+    /// Changes that turn this definition into `other`
+    ///
+    /// Transitions are matched by trigger and source state, so moving a transition to
+    /// another target or changing its conditions shows up as a change rather than a
+    /// removal and an addition.
+    pub fn diff(&self, other: &WorkflowDefinition) -> WorkflowChangeset {
+        let mut changes = WorkflowChangeset::default();
+
+        let states: HashMap<&str, &WorkflowState> =
+            self.states.iter().map(|s| (s.name.as_str(), s)).collect();
+        let other_states: HashMap<&str, &WorkflowState> =
+            other.states.iter().map(|s| (s.name.as_str(), s)).collect();
+        for state in &other.states {
+            match states.get(state.name.as_str()) {
+                None => changes.states_added.push(state.clone()),
+                Some(before) if before.state_type != state.state_type => {
+                    changes.states_changed.push(StateChange {
+                        name: state.name.clone(),
+                        state_type_before: before.state_type.clone(),
+                        state_type_after: state.state_type.clone(),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+        changes.states_removed = self
+            .states
+            .iter()
+            .filter(|s| !other_states.contains_key(s.name.as_str()))
+            .cloned()
+            .collect();
+
+        if self.initial_state != other.initial_state {
+            changes.initial_state_changed =
+                Some((self.initial_state.clone(), other.initial_state.clone()));
+        }
+
+        let transitions: HashMap<(&str, &str), &WorkflowTransition> =
+            self.transitions.iter().map(|t| (t.key(), t)).collect();
+        let other_transitions: HashMap<(&str, &str), &WorkflowTransition> =
+            other.transitions.iter().map(|t| (t.key(), t)).collect();
+        for transition in &other.transitions {
+            let Some(before) = transitions.get(&transition.key()) else {
+                changes.activities_added.push(transition.clone());
+                continue;
+            };
+            if before.to != transition.to {
+                changes.activities_changed.push(ActivityChange {
+                    trigger: transition.trigger.clone(),
+                    from: transition.from.clone(),
+                    to_before: before.to.clone(),
+                    to_after: transition.to.clone(),
+                });
+            }
+            let added: Vec<String> = transition
+                .conditions
+                .iter()
+                .filter(|c| !before.conditions.contains(c))
+                .cloned()
+                .collect();
+            let removed: Vec<String> = before
+                .conditions
+                .iter()
+                .filter(|c| !transition.conditions.contains(c))
+                .cloned()
+                .collect();
+            if !added.is_empty() || !removed.is_empty() {
+                changes.rules_changed.push(RuleChange {
+                    trigger: transition.trigger.clone(),
+                    from: transition.from.clone(),
+                    added,
+                    removed,
+                });
+            }
+        }
+        changes.activities_removed = self
+            .transitions
+            .iter()
+            .filter(|t| !other_transitions.contains_key(&t.key()))
+            .cloned()
+            .collect();
+
+        changes
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct WorkflowState {
     pub name: String,
     pub state_type: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct WorkflowTransition {
     pub from: String,
     pub to: String,
     pub trigger: String,
+    /// Conditions that must all hold for the transition to fire
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conditions: Vec<String>,
+}
+
+impl WorkflowTransition {
+    /// Identity of a transition across versions of a workflow
+    fn key(&self) -> (&str, &str) {
+        (self.trigger.as_str(), self.from.as_str())
+    }
+}
+
+/// Changes between two workflow definitions, see [`WorkflowDefinition::diff`]
+///
+/// `Display` renders the changeset as a plan, one `+` (added), `-` (removed) or `~`
+/// (changed) line per change.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WorkflowChangeset {
+    pub states_added: Vec<WorkflowState>,
+    pub states_removed: Vec<WorkflowState>,
+    pub states_changed: Vec<StateChange>,
+    /// Initial state before and after
+    pub initial_state_changed: Option<(Option<String>, Option<String>)>,
+    pub activities_added: Vec<WorkflowTransition>,
+    pub activities_removed: Vec<WorkflowTransition>,
+    pub activities_changed: Vec<ActivityChange>,
+    pub rules_changed: Vec<RuleChange>,
+}
+
+/// A state whose type changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateChange {
+    pub name: String,
+    pub state_type_before: String,
+    pub state_type_after: String,
+}
+
+/// A transition that now leads to another state
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActivityChange {
+    pub trigger: String,
+    pub from: String,
+    pub to_before: String,
+    pub to_after: String,
+}
+
+/// Conditions added to or removed from a transition
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleChange {
+    pub trigger: String,
+    pub from: String,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl WorkflowChangeset {
+    pub fn is_empty(&self) -> bool {
+        self.states_added.is_empty()
+            && self.states_removed.is_empty()
+            && self.states_changed.is_empty()
+            && self.initial_state_changed.is_none()
+            && self.activities_added.is_empty()
+            && self.activities_removed.is_empty()
+            && self.activities_changed.is_empty()
+            && self.rules_changed.is_empty()
+    }
+
+    /// Whether resources of the old definition may no longer fit the new one: states or
+    /// transitions were removed, or a transition leads elsewhere
+    pub fn is_breaking(&self) -> bool {
+        !self.states_removed.is_empty()
+            || !self.activities_removed.is_empty()
+            || !self.activities_changed.is_empty()
+    }
+
+    /// Number of individual changes
+    pub fn len(&self) -> usize {
+        self.states_added.len()
+            + self.states_removed.len()
+            + self.states_changed.len()
+            + usize::from(self.initial_state_changed.is_some())
+            + self.activities_added.len()
+            + self.activities_removed.len()
+            + self.activities_changed.len()
+            + self.rules_changed.len()
+    }
+}
+
+impl std::fmt::Display for WorkflowChangeset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No changes");
+        }
+        for state in &self.states_added {
+            writeln!(f, "+ state {} ({})", state.name, state.state_type)?;
+        }
+        for state in &self.states_removed {
+            writeln!(f, "- state {} ({})", state.name, state.state_type)?;
+        }
+        for change in &self.states_changed {
+            writeln!(
+                f,
+                "~ state {}: {} -> {}",
+                change.name, change.state_type_before, change.state_type_after
+            )?;
+        }
+        if let Some((before, after)) = &self.initial_state_changed {
+            writeln!(
+                f,
+                "~ initial state: {} -> {}",
+                before.as_deref().unwrap_or("(none)"),
+                after.as_deref().unwrap_or("(none)")
+            )?;
+        }
+        for transition in &self.activities_added {
+            writeln!(
+                f,
+                "+ activity {}: {} -> {}",
+                transition.trigger, transition.from, transition.to
+            )?;
+        }
+        for transition in &self.activities_removed {
+            writeln!(
+                f,
+                "- activity {}: {} -> {}",
+                transition.trigger, transition.from, transition.to
+            )?;
+        }
+        for change in &self.activities_changed {
+            writeln!(
+                f,
+                "~ activity {} from {}: -> {} becomes -> {}",
+                change.trigger, change.from, change.to_before, change.to_after
+            )?;
+        }
+        for change in &self.rules_changed {
+            let conditions: Vec<String> = change
+                .added
+                .iter()
+                .map(|c| format!("+{}", c))
+                .chain(change.removed.iter().map(|c| format!("-{}", c)))
+                .collect();
+            writeln!(
+                f,
+                "~ rules of {} from {}: {}",
+                change.trigger,
+                change.from,
+                conditions.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order_workflow() -> WorkflowDefinition {
+        create_workflow("orders")
+            .add_state("pending", "normal")
+            .add_state("processing", "normal")
+            .add_state("cancelled", "final")
+            .add_transition("pending", "processing", "approve")
+            .add_transition("pending", "cancelled", "cancel")
+            .set_initial_state("pending")
+            .build()
+    }
+
+    #[test]
+    fn test_workflow_diff() {
+        let current = order_workflow();
+        assert!(current.diff(&current).is_empty());
+
+        let mut desired = order_workflow();
+        desired.states.retain(|s| s.name != "cancelled");
+        desired.states.push(WorkflowState {
+            name: "shipped".to_string(),
+            state_type: "final".to_string(),
+        });
+        desired.transitions = vec![
+            WorkflowTransition {
+                from: "pending".to_string(),
+                to: "shipped".to_string(),
+                trigger: "approve".to_string(),
+                conditions: vec!["paid".to_string()],
+            },
+            WorkflowTransition {
+                from: "processing".to_string(),
+                to: "shipped".to_string(),
+                trigger: "ship".to_string(),
+                conditions: Vec::new(),
+            },
+        ];
+
+        let changes = current.diff(&desired);
+        assert_eq!(changes.states_added[0].name, "shipped");
+        assert_eq!(changes.states_removed[0].name, "cancelled");
+        assert_eq!(changes.activities_added[0].trigger, "ship");
+        assert_eq!(changes.activities_removed[0].trigger, "cancel");
+        assert_eq!(changes.activities_changed[0].to_after, "shipped");
+        assert_eq!(changes.rules_changed[0].added, vec!["paid".to_string()]);
+        assert_eq!(changes.len(), 6);
+        assert!(changes.is_breaking());
+        assert_eq!(
+            changes.to_string(),
+            "+ state shipped (final)\n\
+             - state cancelled (final)\n\
+             + activity ship: processing -> shipped\n\
+             - activity cancel: pending -> cancelled\n\
+             ~ activity approve from pending: -> processing becomes -> shipped\n\
+             ~ rules of approve from pending: +paid\n"
+        );
+
+        // Only adding is not breaking
+        let additive = WorkflowChangeset {
+            states_added: changes.states_added,
+            activities_added: changes.activities_added,
+            ..Default::default()
+        };
+        assert!(!additive.is_breaking());
+    }
 }
@@ -9,11 +9,40 @@ import {
   AgentType,
   ChatMessage,
   PaginationOptions,
+  UsageInfo,
   // PaginatedResult,
 } from "./types";
 import { Client } from "./client";
 import { QueryBuilder } from "./schema";
 
+/**
+ * An agent's reply to a chat
+ */
+export interface AgentChatResponse {
+  id: string;
+  model: string;
+  choices: Array<{
+    index: number;
+    message: { role: string; content: string };
+    finishReason?: string;
+  }>;
+  usage: {
+    promptTokens: number;
+    completionTokens: number;
+    totalTokens: number;
+    estimatedCost: number;
+  };
+  provider: string;
+  routingInfo: {
+    selectedProvider: string;
+    routingStrategy: string;
+    latencyMs: number;
+    fallbackUsed: boolean;
+  };
+  /** Routing, token usage and cost, as reported by `usageInfo()` */
+  usageInfo: UsageInfo;
+}
+
 export class AgentClient {
   constructor(private client: Client) {}
 
@@ -200,7 +229,10 @@ export class AgentClient {
   /**
    * Chat with an agent
    */
-  async chat(id: string, messages: ChatMessage[]): Promise<any> {
+  async chat(
+    id: string,
+    messages: ChatMessage[],
+  ): Promise<AgentChatResponse> {
     // First get the agent to retrieve its configuration
     const agent = await this.get(id);
 
@@ -211,7 +243,9 @@ export class AgentClient {
         "id",
         "model",
         "choices { index message { role content } finishReason }",
-        "usage { promptTokens completionTokens totalTokens }",
+        "usage { promptTokens completionTokens totalTokens estimatedCost }",
+        "provider",
+        "routingInfo { selectedProvider routingStrategy latencyMs fallbackUsed }",
       ],
       [["input", "LlmchatCompletionInput!"]],
     );
@@ -237,12 +271,24 @@ export class AgentClient {
       },
     };
 
-    const result = await this.client.mutation<{ llmChatCompletion: any }>(
-      mutation,
-      variables,
-    );
-
-    return result.llmChatCompletion;
+    const result = await this.client.mutation<{
+      llmChatCompletion: Omit<AgentChatResponse, "usageInfo">;
+    }>(mutation, variables);
+
+    const response = result.llmChatCompletion;
+    return {
+      ...response,
+      usageInfo: {
+        provider: response.routingInfo.selectedProvider,
+        routingStrategy: response.routingInfo.routingStrategy,
+        fallbackUsed: response.routingInfo.fallbackUsed,
+        latencyMs: response.routingInfo.latencyMs,
+        promptTokens: response.usage.promptTokens,
+        completionTokens: response.usage.completionTokens,
+        totalTokens: response.usage.totalTokens,
+        costUsd: response.usage.estimatedCost,
+      },
+    };
   }
 
   /**
@@ -87,6 +87,92 @@ export interface CostAnalyticsInput {
   endDate: string;
 }
 
+/**
+ * Cost the server recorded for a single request
+ */
+export interface RequestCostReport {
+  request_id: string;
+  /** Provider name such as "OpenAI", or `{ Custom: name }` for custom providers */
+  provider: string | Record<string, string>;
+  model: string;
+  input_tokens: number;
+  output_tokens: number;
+  /** Cost in USD */
+  cost_usd: number;
+  /** When the cost was recorded (ISO 8601) */
+  timestamp: string;
+  user_id?: string;
+  project_id?: string;
+}
+
+/**
+ * Column a chargeback report groups costs by
+ */
+export type ChargebackDimension =
+  | "user"
+  | "project"
+  | "model"
+  | "provider"
+  | "day";
+
+/**
+ * Options of a chargeback report
+ */
+export interface ChargebackOptions {
+  /** Grouping columns; the server groups by project when none are given */
+  groupBy?: ChargebackDimension[];
+  /** Only include costs of this user */
+  userId?: string;
+  /** Only include costs of this project */
+  projectId?: string;
+  /** Start of the period as an ISO 8601 timestamp or date; defaults to 30 days before `to` */
+  from?: string;
+  /** End of the period; a date includes the whole day. Defaults to now. */
+  to?: string;
+  /** Estimate totals from this fraction of requests, in (0, 1] */
+  sampleRate?: number;
+  /** Leave out groups covering fewer distinct users; the server may enforce a larger minimum */
+  minGroupSize?: number;
+}
+
+/**
+ * Costs of one group of a chargeback report
+ */
+export interface ChargebackGroup {
+  /** The group's value of each grouping column; null for costs without a user or project */
+  keys: Record<string, string | null>;
+  requests: number;
+  input_tokens: number;
+  output_tokens: number;
+  total_tokens: number;
+  /** Cost in USD */
+  cost_usd: number;
+  /** Fraction of the report's total cost, between 0 and 1 */
+  share_of_cost: number;
+}
+
+/**
+ * Recorded costs grouped for chargeback
+ */
+export interface ChargebackReport {
+  from: string;
+  to: string;
+  /** Grouping columns, e.g. ["project_id", "model"] */
+  group_by: string[];
+  requests: number;
+  input_tokens: number;
+  output_tokens: number;
+  total_tokens: number;
+  /** Cost in USD */
+  cost_usd: number;
+  /** Fraction of requests the totals were estimated from */
+  sample_rate: number;
+  /** Groups left out for covering too few users; totals do not include them */
+  suppressed_groups: number;
+  /** Most expensive first */
+  groups: ChargebackGroup[];
+}
+
 /**
  * Cost update event (for future subscription implementation)
  */
@@ -129,6 +215,46 @@ export class AnalyticsClient {
     return new SetBudgetBuilder(this.client);
   }
 
+  /**
+   * Fetch the cost the server recorded for a request
+   * @param requestId Request ID reported by `usageInfo()` of a response
+   */
+  async reportForRequest(requestId: string): Promise<RequestCostReport> {
+    this.client.requireFeature("request_cost");
+    return this.client.restRequest<RequestCostReport>(
+      "GET",
+      `/v1/requests/${encodeURIComponent(requestId)}/cost`,
+    );
+  }
+
+  /**
+   * Break recorded costs down by user, project, model, provider or day for chargeback
+   *
+   * Requires the client to be configured with the server's admin token as its API key.
+   */
+  async chargeback(options: ChargebackOptions = {}): Promise<ChargebackReport> {
+    this.client.requireFeature("chargeback_reports");
+    const params = new URLSearchParams();
+    if (options.groupBy?.length) {
+      params.set("group_by", [...new Set(options.groupBy)].join(","));
+    }
+    if (options.userId) params.set("user_id", options.userId);
+    if (options.projectId) params.set("project_id", options.projectId);
+    if (options.from) params.set("from", options.from);
+    if (options.to) params.set("to", options.to);
+    if (options.sampleRate !== undefined) {
+      params.set("sample_rate", String(options.sampleRate));
+    }
+    if (options.minGroupSize !== undefined) {
+      params.set("min_group_size", String(options.minGroupSize));
+    }
+    const query = params.toString();
+    return this.client.restRequest<ChargebackReport>(
+      "GET",
+      "/v1/analytics/chargeback" + (query ? `?${query}` : ""),
+    );
+  }
+
   /**
    * Subscribe to real-time cost updates
    * @param userId Optional user ID to filter updates
@@ -9,10 +9,14 @@ import {
   ClientConfig,
   PingResponse,
   ServerInfo,
+  ServerMeta,
   CircuitBreakerError,
   NetworkError,
   ValidationError,
   NotFoundError,
+  ApiError,
+  ErrorCode,
+  UnsupportedFeatureError,
 } from "./types.js";
 import { QueryBuilder } from "./schema";
 
@@ -25,9 +29,11 @@ import { ResourceClient } from "./resources.js";
 import { RuleClient } from "./rules.js";
 import { LLMClient } from "./llm.js";
 import { AnalyticsClient } from "./analytics.js";
+import { TenantsClient } from "./tenants.js";
 import { MCPClient } from "./mcp.js";
 import { SubscriptionClient } from "./subscriptions.js";
 import { NATSClient } from "./nats.js";
+import { RequestJournal } from "./journal.js";
 
 // ============================================================================
 // GraphQL Types
@@ -39,6 +45,7 @@ interface GraphQLResponse<T> {
     message: string;
     locations?: Array<{ line: number; column: number }>;
     path?: Array<string | number>;
+    extensions?: { code?: ErrorCode };
   }>;
 }
 
@@ -70,6 +77,11 @@ export interface ClientBuilderConfig extends Partial<ClientConfig> {
   baseUrl?: string;
 }
 
+/** REST API version this SDK speaks */
+export const API_VERSION = "v1";
+
+const SDK_VERSION = "0.1.0";
+
 const DEFAULT_CONFIG: Required<ClientConfig> = {
   baseUrl: "http://localhost:4000",
   apiKey: "",
@@ -87,8 +99,15 @@ export class Client {
   private readonly graphqlEndpoint: string;
   private readonly restEndpoint: string;
   private endpointHealth: EndpointHealth | null = null;
+  private serverMeta: ServerMeta | null = null;
 
-  constructor(config: Required<ClientConfig>) {
+  /**
+   * @param journal Journal of REST requests and their responses, for resumable batch jobs
+   */
+  constructor(
+    config: Required<ClientConfig>,
+    private readonly journal?: RequestJournal,
+  ) {
     this.config = config;
 
     // Smart endpoint detection - check if base URL includes port
@@ -116,7 +135,7 @@ export class Client {
     // Prepare base headers
     this.baseHeaders = {
       "Content-Type": "application/json",
-      "User-Agent": `circuit-breaker-sdk-typescript/0.1.0`,
+      "User-Agent": `circuit-breaker-sdk-typescript/${SDK_VERSION}`,
       ...config.headers,
     };
 
@@ -260,6 +279,70 @@ export class Client {
     return health;
   }
 
+  /**
+   * Fetch the server version, supported API versions and enabled features
+   */
+  async meta(): Promise<ServerMeta> {
+    const response = await fetch(`${this.restEndpoint}/v1/meta`, {
+      method: "GET",
+      headers: this.baseHeaders,
+      signal: AbortSignal.timeout(5000),
+    });
+    if (!response.ok) {
+      throw await this.handleHttpError(response);
+    }
+    return (await response.json()) as ServerMeta;
+  }
+
+  /**
+   * Negotiate compatibility with the server.
+   *
+   * Throws only when the server speaks no REST API version this SDK
+   * understands. Servers without `/v1/meta` are accepted with a warning, and
+   * every feature is then assumed to be available.
+   */
+  async connect(): Promise<ServerMeta | null> {
+    let meta: ServerMeta;
+    try {
+      meta = await this.meta();
+    } catch (error) {
+      console.warn(
+        "Could not read server metadata, assuming all features are available:",
+        error,
+      );
+      return null;
+    }
+
+    for (const warning of checkCompatibility(meta)) {
+      console.warn(warning);
+    }
+    this.serverMeta = meta;
+    return meta;
+  }
+
+  /**
+   * Server metadata negotiated by `connect()`
+   */
+  getServerMeta(): ServerMeta | null {
+    return this.serverMeta;
+  }
+
+  /**
+   * Whether the server offers a feature; assumed true when nothing was negotiated
+   */
+  supports(feature: string): boolean {
+    return this.serverMeta?.features.includes(feature) ?? true;
+  }
+
+  /**
+   * Fail early with `UnsupportedFeatureError` when the server lacks a feature
+   */
+  requireFeature(feature: string): void {
+    if (this.serverMeta && !this.supports(feature)) {
+      throw new UnsupportedFeatureError(feature, this.serverMeta.version);
+    }
+  }
+
   /**
    * Get server information from both endpoints
    */
@@ -408,6 +491,13 @@ export class Client {
     return new AnalyticsClient(this);
   }
 
+  /**
+   * Access tenant administration API
+   */
+  tenants(): TenantsClient {
+    return new TenantsClient(this);
+  }
+
   /**
    * Access MCP (Model Context Protocol) API
    */
@@ -514,12 +604,48 @@ export class Client {
 
   /**
    * Make a REST API request with automatic endpoint validation
+   *
+   * With a journal configured, requests other than GET the journal holds a response for
+   * are answered from it; the rest are recorded before they are sent.
    */
   async restRequest<T>(
     method: string,
     path: string,
     body?: any,
     headers?: Record<string, string>,
+  ): Promise<T> {
+    const journal = this.journal;
+    if (!journal || method === "GET") {
+      return this.sendRestRequest<T>(method, path, body, headers);
+    }
+
+    const key = RequestJournal.requestKey(method, path, body);
+    if (journal.isCompleted(key)) {
+      return journal.response<T>(key) as T;
+    }
+    journal.recordStarted(key, method, path, body);
+    let response: T;
+    try {
+      response = await this.sendRestRequest<T>(method, path, body, headers);
+    } catch (error) {
+      journal.recordFailed(key, String(error));
+      throw error;
+    }
+    // Streamed responses cannot be replayed
+    if (!(response instanceof Response)) {
+      journal.recordCompleted(key, response);
+    }
+    return response;
+  }
+
+  /**
+   * Send a REST API request
+   */
+  private async sendRestRequest<T>(
+    method: string,
+    path: string,
+    body?: any,
+    headers?: Record<string, string>,
   ): Promise<T> {
     // Ensure REST endpoint is available
     await this.checkEndpointHealth();
@@ -560,6 +686,11 @@ export class Client {
         throw await this.handleHttpError(response);
       }
 
+      // Deletes answer without a body
+      if (response.status === 204) {
+        return undefined as T;
+      }
+
       // Handle streaming responses
       if (
         response.headers.get("content-type")?.includes("text/plain") ||
@@ -681,7 +812,8 @@ export class Client {
     try {
       if (contentType?.includes("application/json")) {
         errorBody = await response.json();
-        errorMessage = errorBody.message || errorMessage;
+        errorMessage =
+          errorBody.error?.message || errorBody.message || errorMessage;
       } else {
         const text = await response.text();
         if (text) {
@@ -692,6 +824,11 @@ export class Client {
       // Ignore JSON parsing errors, use default message
     }
 
+    const errorCode: ErrorCode | undefined = errorBody?.error?.error_code;
+    if (errorCode) {
+      return new ApiError(errorMessage, errorCode, response.status, errorBody);
+    }
+
     switch (response.status) {
       case 400:
         return new ValidationError(errorMessage, errorBody);
@@ -716,10 +853,21 @@ export class Client {
   }
 
   private handleGraphQLErrors(
-    errors: Array<{ message: string; path?: Array<string | number> }>,
+    errors: Array<{
+      message: string;
+      path?: Array<string | number>;
+      extensions?: { code?: ErrorCode };
+    }>,
   ): CircuitBreakerError {
     const message = errors.map((e) => e.message).join(", ");
 
+    const coded = errors.find((e) => e.extensions?.code);
+    if (coded?.extensions?.code) {
+      return new ApiError(coded.message, coded.extensions.code, undefined, {
+        errors,
+      });
+    }
+
     // Determine error type based on message content
     if (
       message.includes("Unknown field") ||
@@ -744,6 +892,7 @@ export class Client {
 
 export class ClientBuilder {
   private config: Partial<ClientConfig> = {};
+  private requestJournal?: RequestJournal;
 
   /**
    * Set the base URL for the server
@@ -777,6 +926,14 @@ export class ClientBuilder {
     return this;
   }
 
+  /**
+   * Journal REST requests so a rerun batch job skips the ones that already completed
+   */
+  journal(journal: RequestJournal): ClientBuilder {
+    this.requestJournal = journal;
+    return this;
+  }
+
   /**
    * Build the client
    */
@@ -786,6 +943,59 @@ export class ClientBuilder {
       ...this.config,
     };
 
-    return new Client(finalConfig);
+    return new Client(finalConfig, this.requestJournal);
+  }
+
+  /**
+   * Build the client and negotiate compatibility with the server
+   */
+  async connect(): Promise<Client> {
+    const client = this.build();
+    await client.connect();
+    return client;
+  }
+}
+
+// ============================================================================
+// Compatibility
+// ============================================================================
+
+/**
+ * Check the server can serve this SDK, returning warnings for soft mismatches
+ */
+export function checkCompatibility(meta: ServerMeta): string[] {
+  if (!meta.api_versions.includes(API_VERSION)) {
+    throw new ValidationError(
+      `Server ${meta.version} supports API versions [${meta.api_versions.join(", ")}], but this SDK requires ${API_VERSION}`,
+    );
+  }
+
+  const warnings: string[] = [];
+  if (compareVersions(SDK_VERSION, meta.min_sdk_version) < 0) {
+    warnings.push(
+      `Server ${meta.version} expects SDK ${meta.min_sdk_version} or newer, this is ${SDK_VERSION}; some responses may not parse`,
+    );
+  }
+  if (meta.maintenance) {
+    warnings.push(
+      `Server ${meta.version} is in maintenance mode; writes will be rejected`,
+    );
+  }
+  return warnings;
+}
+
+function compareVersions(a: string, b: string): number {
+  const parse = (version: string) =>
+    version
+      .split(/[.+-]/)
+      .slice(0, 3)
+      .map((part) => parseInt(part, 10) || 0);
+  const [left, right] = [parse(a), parse(b)];
+  for (let i = 0; i < 3; i++) {
+    const diff = (left[i] ?? 0) - (right[i] ?? 0);
+    if (diff !== 0) {
+      return diff;
+    }
   }
+  return 0;
 }
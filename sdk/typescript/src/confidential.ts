@@ -0,0 +1,181 @@
+/**
+ * Confidential Metadata
+ *
+ * Workflows can mark resource metadata fields confidential. The server never sees those
+ * fields in the clear: the client encrypts them before creating or updating a resource,
+ * with a key only the client holds, and decrypts them after reading the resource back.
+ * Rules on the server only operate on non-encrypted fields, and the server refuses rules
+ * that read confidential ones.
+ *
+ * A `MetadataCipher` holds one AES-256-GCM key and its key id. Each value is serialized
+ * to JSON and sealed with a fresh random nonce and the field name as associated data,
+ * so an envelope copied to another field does not decrypt. Envelopes are compatible
+ * with the Rust SDK.
+ */
+
+import { createCipheriv, createDecipheriv, randomBytes } from "crypto";
+import { ValidationError } from "./types.js";
+
+/** Key under which a metadata value holds its `EncryptedValue` */
+export const ENCRYPTED_KEY = "$encrypted";
+
+/** Algorithm of the envelopes this SDK writes */
+export const ENCRYPTION_ALGORITHM = "A256GCM";
+
+const NONCE_LENGTH = 12;
+const TAG_LENGTH = 16;
+
+/**
+ * An encrypted metadata value as stored by the server
+ */
+export interface EncryptedValue {
+  alg: string;
+  /** Id of the key that encrypted the value */
+  kid?: string;
+  /** Base64 nonce */
+  nonce: string;
+  /** Base64 ciphertext followed by the authentication tag */
+  ciphertext: string;
+}
+
+/**
+ * The envelope a metadata value holds, if it is one
+ */
+export function encryptedValue(value: unknown): EncryptedValue | undefined {
+  if (!value || typeof value !== "object" || Array.isArray(value)) {
+    return undefined;
+  }
+  const keys = Object.keys(value);
+  const envelope = (value as Record<string, any>)[ENCRYPTED_KEY];
+  if (
+    keys.length !== 1 ||
+    !envelope ||
+    typeof envelope.alg !== "string" ||
+    typeof envelope.nonce !== "string" ||
+    typeof envelope.ciphertext !== "string"
+  ) {
+    return undefined;
+  }
+  return envelope as EncryptedValue;
+}
+
+/**
+ * Encrypts and decrypts confidential metadata with a client-held key
+ */
+export class MetadataCipher {
+  private readonly key: Buffer;
+
+  /**
+   * Use a 32-byte AES-256 key identified by `kid`
+   */
+  constructor(
+    readonly kid: string,
+    key: Uint8Array,
+  ) {
+    if (key.length !== 32) {
+      throw new ValidationError("Metadata encryption keys must be 32 bytes");
+    }
+    this.key = Buffer.from(key);
+  }
+
+  /**
+   * A random 32-byte key to store in the client's secret store
+   */
+  static generateKey(): Buffer {
+    return randomBytes(32);
+  }
+
+  /**
+   * Encrypt the value of a metadata field
+   */
+  encrypt(field: string, value: unknown): EncryptedValue {
+    const nonce = randomBytes(NONCE_LENGTH);
+    const cipher = createCipheriv("aes-256-gcm", this.key, nonce, {
+      authTagLength: TAG_LENGTH,
+    });
+    cipher.setAAD(Buffer.from(field, "utf8"));
+    const sealed = Buffer.concat([
+      cipher.update(JSON.stringify(value), "utf8"),
+      cipher.final(),
+      cipher.getAuthTag(),
+    ]);
+
+    return {
+      alg: ENCRYPTION_ALGORITHM,
+      kid: this.kid,
+      nonce: nonce.toString("base64"),
+      ciphertext: sealed.toString("base64"),
+    };
+  }
+
+  /**
+   * Decrypt the envelope of a metadata field
+   */
+  decrypt(field: string, envelope: EncryptedValue): unknown {
+    if (envelope.alg !== ENCRYPTION_ALGORITHM) {
+      throw new ValidationError(
+        `Field '${field}' uses unsupported algorithm '${envelope.alg}'`,
+      );
+    }
+    if (envelope.kid !== undefined && envelope.kid !== this.kid) {
+      throw new ValidationError(
+        `Field '${field}' was encrypted with key '${envelope.kid}', not '${this.kid}'`,
+      );
+    }
+    const nonce = Buffer.from(envelope.nonce, "base64");
+    const sealed = Buffer.from(envelope.ciphertext, "base64");
+    if (nonce.length !== NONCE_LENGTH || sealed.length < TAG_LENGTH) {
+      throw new ValidationError(`Field '${field}' has an invalid envelope`);
+    }
+
+    try {
+      const decipher = createDecipheriv("aes-256-gcm", this.key, nonce, {
+        authTagLength: TAG_LENGTH,
+      });
+      decipher.setAAD(Buffer.from(field, "utf8"));
+      decipher.setAuthTag(sealed.subarray(sealed.length - TAG_LENGTH));
+      const plaintext = Buffer.concat([
+        decipher.update(sealed.subarray(0, sealed.length - TAG_LENGTH)),
+        decipher.final(),
+      ]);
+      return JSON.parse(plaintext.toString("utf8"));
+    } catch {
+      throw new ValidationError(`Failed to decrypt field '${field}'`);
+    }
+  }
+
+  /**
+   * Copy of a metadata object with the listed fields encrypted; missing fields and
+   * fields already encrypted are left alone
+   */
+  encryptFields(
+    metadata: Record<string, unknown>,
+    fields: string[],
+  ): Record<string, unknown> {
+    const result = { ...metadata };
+    for (const field of fields) {
+      if (field in result && !encryptedValue(result[field])) {
+        result[field] = { [ENCRYPTED_KEY]: this.encrypt(field, result[field]) };
+      }
+    }
+    return result;
+  }
+
+  /**
+   * Copy of a metadata object with the listed fields decrypted; fields that are not
+   * encrypted are left alone
+   */
+  decryptFields(
+    metadata: Record<string, unknown>,
+    fields: string[],
+  ): Record<string, unknown> {
+    const result = { ...metadata };
+    for (const field of fields) {
+      const envelope = encryptedValue(result[field]);
+      if (envelope) {
+        result[field] = this.decrypt(field, envelope);
+      }
+    }
+    return result;
+  }
+}
@@ -9,7 +9,12 @@
 // Core Client
 // ============================================================================
 
-export { Client, ClientBuilder } from "./client.js";
+export {
+  Client,
+  ClientBuilder,
+  checkCompatibility,
+  API_VERSION,
+} from "./client.js";
 export type { ClientBuilderConfig } from "./client.js";
 export type { ClientConfig } from "./types.js";
 
@@ -22,7 +27,12 @@ export {
   WorkflowBuilder,
   createWorkflow,
 } from "./workflows.js";
-export { AgentClient, AgentBuilder, createAgent } from "./agents.js";
+export {
+  AgentClient,
+  AgentBuilder,
+  createAgent,
+  AgentChatResponse,
+} from "./agents.js";
 export {
   FunctionClient,
   FunctionBuilder,
@@ -46,6 +56,7 @@ export {
 } from "./rules.js";
 export {
   LLMClient,
+  EmbeddingCache,
   ChatBuilder,
   Conversation,
   createChat,
@@ -55,6 +66,7 @@ export {
   createFastChat,
   createBalancedChat,
   quickChat,
+  usageInfo,
   COMMON_MODELS,
 } from "./llm.js";
 export {
@@ -69,7 +81,31 @@ export {
   getProjectBudgetStatus,
   getUserMonthlyCostAnalytics,
   setUserMonthlyBudget,
+  RequestCostReport,
+  ChargebackDimension,
+  ChargebackOptions,
+  ChargebackGroup,
+  ChargebackReport,
 } from "./analytics.js";
+export { RequestJournal, JournalStats } from "./journal.js";
+export {
+  MetadataCipher,
+  encryptedValue,
+  ENCRYPTED_KEY,
+  ENCRYPTION_ALGORITHM,
+} from "./confidential.js";
+export type { EncryptedValue } from "./confidential.js";
+export {
+  TenantsClient,
+  Tenant,
+  TenantDetails,
+  TenantQuota,
+  TenantRoutingPolicy,
+  TenantBudget,
+  TenantUsage,
+  IssuedApiKey,
+  CreateTenantInput,
+} from "./tenants.js";
 export {
   MCPClient,
   MCPServersBuilder,
@@ -134,6 +170,7 @@ export type {
   // Common types
   PingResponse,
   ServerInfo,
+  ServerMeta,
   PaginationOptions,
   PaginatedResult,
   Result,
@@ -175,16 +212,22 @@ export type {
   ChatMessage,
   ChatCompletionRequest,
   ChatCompletionResponse,
+  ResponseFormat,
+  JsonSchemaFormat,
   SmartCompletionRequest,
   CircuitBreakerOptions,
   RoutingStrategy,
+  RequestPriority,
   TaskType,
+  ErrorCode,
   BudgetConstraint,
   ChatFunction,
   ModelInfo,
   ModelsResponse,
   Choice,
   Usage,
+  RoutingInfo,
+  UsageInfo,
 } from "./types.js";
 
 // ============================================================================
@@ -258,6 +301,8 @@ export {
   NetworkError,
   ValidationError,
   NotFoundError,
+  ApiError,
+  UnsupportedFeatureError,
 } from "./types.js";
 
 // ============================================================================
@@ -277,6 +322,7 @@ export type {
   MessageDelta,
   TokenCount,
   ProviderHealth,
+  EmbeddingCacheStats,
 } from "./llm.js";
 
 // ============================================================================
@@ -296,7 +342,12 @@ export const DEFAULT_TIMEOUT = 30000;
 // ============================================================================
 
 import { Client } from "./client.js";
-import type { ClientConfig, PingResponse, ServerInfo } from "./types.js";
+import type {
+  ClientConfig,
+  PingResponse,
+  ServerInfo,
+  ServerMeta,
+} from "./types.js";
 import type { WorkflowClient } from "./workflows.js";
 import type { AgentClient } from "./agents.js";
 import type { FunctionClient } from "./functions.js";
@@ -304,6 +355,7 @@ import type { ResourceClient } from "./resources.js";
 import type { RuleClient } from "./rules.js";
 import type { LLMClient } from "./llm.js";
 import type { AnalyticsClient } from "./analytics.js";
+import type { TenantsClient } from "./tenants.js";
 import type { MCPClient } from "./mcp.js";
 import type { SubscriptionClient } from "./subscriptions.js";
 import type { NATSClient } from "./nats.js";
@@ -362,6 +414,13 @@ export class CircuitBreakerSDK {
     return this.client.info();
   }
 
+  /**
+   * Negotiate compatibility with the server
+   */
+  async connect(): Promise<ServerMeta | null> {
+    return this.client.connect();
+  }
+
   /**
    * Access workflows API
    */
@@ -411,6 +470,13 @@ export class CircuitBreakerSDK {
     return this.client.analytics();
   }
 
+  /**
+   * Access tenant administration API
+   */
+  tenants(): TenantsClient {
+    return this.client.tenants();
+  }
+
   /**
    * Access MCP (Model Context Protocol) API
    */
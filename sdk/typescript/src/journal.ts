@@ -0,0 +1,174 @@
+/**
+ * Request Journal
+ *
+ * Batch and ingestion jobs that die halfway should not pay twice for the items they
+ * already finished. A `RequestJournal` is an append-only JSON Lines file the client
+ * writes every outgoing REST request to before sending it, and its response to once it
+ * arrives. A client built with a journal answers a request the journal already holds a
+ * response for from the journal instead of sending it again, so rerunning a job after a
+ * crash resumes where it stopped.
+ *
+ * Requests are identified by a hash of their method, path and body: identical requests
+ * share one journal entry. `GET` requests always reach the server so reads are never
+ * stale. Requests that failed, or were still in flight when the process stopped, are
+ * sent again on the next run.
+ */
+
+import { createHash } from "crypto";
+import { appendFileSync, existsSync, readFileSync } from "fs";
+
+/**
+ * One line of a journal file
+ */
+type JournalEntry =
+  | {
+      event: "started";
+      key: string;
+      method: string;
+      path: string;
+      request: unknown;
+      timestamp: string;
+    }
+  | { event: "completed"; key: string; response: unknown; timestamp: string }
+  | { event: "failed"; key: string; error: string; timestamp: string };
+
+/**
+ * Counts of the requests a journal holds
+ */
+export interface JournalStats {
+  /** Requests with a recorded response */
+  completed: number;
+  /** Requests whose last attempt failed */
+  failed: number;
+  /** Requests sent without a recorded outcome, e.g. because the process stopped */
+  in_flight: number;
+}
+
+/**
+ * Append-only journal of REST requests and their responses
+ */
+export class RequestJournal {
+  private completed = new Map<string, unknown>();
+  private failed = new Set<string>();
+  private inFlight = new Set<string>();
+
+  /**
+   * Open the journal at `path`, creating it on first write and replaying what it holds
+   *
+   * Lines that cannot be parsed, such as one cut short by a crash, are skipped.
+   */
+  constructor(readonly path: string) {
+    if (!existsSync(path)) {
+      return;
+    }
+    for (const line of readFileSync(path, "utf8").split("\n")) {
+      let entry: JournalEntry;
+      try {
+        entry = JSON.parse(line) as JournalEntry;
+      } catch {
+        continue;
+      }
+      this.apply(entry);
+    }
+  }
+
+  /**
+   * Key identifying a request by its method, path and body
+   */
+  static requestKey(method: string, path: string, body: unknown): string {
+    return createHash("sha256")
+      .update(`${method}\0${path}\0${JSON.stringify(body ?? null)}`)
+      .digest("hex");
+  }
+
+  /**
+   * Whether the journal holds a response for the request
+   */
+  isCompleted(key: string): boolean {
+    return this.completed.has(key);
+  }
+
+  /**
+   * Recorded response of a completed request
+   */
+  response<T>(key: string): T | undefined {
+    return this.completed.get(key) as T | undefined;
+  }
+
+  /**
+   * Counts of completed, failed and in-flight requests
+   */
+  stats(): JournalStats {
+    return {
+      completed: this.completed.size,
+      failed: this.failed.size,
+      in_flight: this.inFlight.size,
+    };
+  }
+
+  /**
+   * Record that a request is about to be sent
+   */
+  recordStarted(
+    key: string,
+    method: string,
+    path: string,
+    request: unknown,
+  ): void {
+    this.append({
+      event: "started",
+      key,
+      method,
+      path,
+      request: request ?? null,
+      timestamp: new Date().toISOString(),
+    });
+  }
+
+  /**
+   * Record a request's response
+   */
+  recordCompleted(key: string, response: unknown): void {
+    this.append({
+      event: "completed",
+      key,
+      response: response ?? null,
+      timestamp: new Date().toISOString(),
+    });
+  }
+
+  /**
+   * Record that a request failed, so the next run sends it again
+   */
+  recordFailed(key: string, error: string): void {
+    this.append({
+      event: "failed",
+      key,
+      error,
+      timestamp: new Date().toISOString(),
+    });
+  }
+
+  private append(entry: JournalEntry): void {
+    appendFileSync(this.path, JSON.stringify(entry) + "\n");
+    this.apply(entry);
+  }
+
+  private apply(entry: JournalEntry): void {
+    switch (entry.event) {
+      case "started":
+        this.failed.delete(entry.key);
+        this.inFlight.add(entry.key);
+        break;
+      case "completed":
+        this.inFlight.delete(entry.key);
+        this.failed.delete(entry.key);
+        this.completed.set(entry.key, entry.response);
+        break;
+      case "failed":
+        this.inFlight.delete(entry.key);
+        this.failed.add(entry.key);
+        break;
+    }
+  }
+}
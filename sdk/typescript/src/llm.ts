@@ -12,18 +12,32 @@ import {
   SmartCompletionRequest,
   CircuitBreakerOptions,
   RoutingStrategy,
+  RequestPriority,
   TaskType,
   BudgetConstraint,
   ModelInfo,
   ModelsResponse,
   EmbeddingResponse,
+  UsageInfo,
 } from "./types.js";
 import type { Client } from "./client.js";
 import { streamChatCompletionFromRouter } from "./sse";
+import { createHash } from "crypto";
+import { appendFileSync, existsSync, readFileSync, truncateSync } from "fs";
 
 export class LLMClient {
+  private embeddingCache?: EmbeddingCache;
+
   constructor(private client: Client) {}
 
+  /**
+   * Serve repeated embeddings of the same model and text from `cache`
+   */
+  withEmbeddingCache(cache: EmbeddingCache): this {
+    this.embeddingCache = cache;
+    return this;
+  }
+
   /**
    * Send a smart completion request with Circuit Breaker routing
    */
@@ -53,6 +67,9 @@ export class LLMClient {
   async chatCompletion(
     request: ChatCompletionRequest,
   ): Promise<ChatCompletionResponse> {
+    if (request.response_format) {
+      this.client.requireFeature("structured_output");
+    }
     return this.client.restRequest<ChatCompletionResponse>(
       "POST",
       "/v1/chat/completions",
@@ -225,19 +242,71 @@ export class LLMClient {
 
   /**
    * Get embeddings from the Circuit Breaker router
+   *
+   * With an embedding cache attached, only texts the cache has not seen are sent to the
+   * router; the response still holds one embedding per input, in input order, and its
+   * usage counts only the tokens that were sent.
    */
   async embeddings(
     model: string,
     input: string | string[],
   ): Promise<EmbeddingResponse> {
-    return this.client.restRequest<EmbeddingResponse>(
-      "POST",
-      "/v1/embeddings",
-      {
-        model,
-        input,
-      },
-    );
+    const cache = this.embeddingCache;
+    if (!cache) {
+      return this.client.restRequest<EmbeddingResponse>(
+        "POST",
+        "/v1/embeddings",
+        {
+          model,
+          input,
+        },
+      );
+    }
+
+    const texts = Array.isArray(input) ? input : [input];
+    const embeddings = texts.map((text) => cache.get(model, text));
+    const missing = texts
+      .map((_, index) => index)
+      .filter((index) => embeddings[index] === undefined);
+
+    let usage = { prompt_tokens: 0, total_tokens: 0 };
+    let responseModel = model;
+    if (missing.length > 0) {
+      const response = await this.client.restRequest<EmbeddingResponse>(
+        "POST",
+        "/v1/embeddings",
+        {
+          model,
+          input: missing.map((index) => texts[index]),
+        },
+      );
+      if (response.data.length !== missing.length) {
+        throw new Error(
+          `Expected ${missing.length} embeddings, received ${response.data.length}`,
+        );
+      }
+      for (const data of response.data) {
+        const index = missing[data.index];
+        if (index === undefined) {
+          throw new Error(`Unexpected embedding index ${data.index}`);
+        }
+        cache.set(model, texts[index], data.embedding);
+        embeddings[index] = data.embedding;
+      }
+      usage = response.usage;
+      responseModel = response.model;
+    }
+
+    return {
+      object: "list",
+      data: embeddings.map((embedding, index) => ({
+        object: "embedding",
+        index,
+        embedding: embedding!,
+      })),
+      model: responseModel,
+      usage,
+    };
   }
 
   /**
@@ -351,6 +420,104 @@ export interface EmbeddingData {
   embedding: number[];
 }
 
+/**
+ * Embedding cache counters
+ */
+export interface EmbeddingCacheStats {
+  entries: number;
+  hits: number;
+  misses: number;
+}
+
+/**
+ * Client-side cache of embedding vectors keyed by a hash of the model and text
+ *
+ * Given a `path`, every new embedding is appended to a JSON Lines file that is reloaded
+ * when the cache is created again, so indexing jobs that re-embed the same documents
+ * across runs only pay for text they have not embedded before. Lines that cannot be
+ * parsed, such as one cut short by an interrupted write, are skipped.
+ */
+export class EmbeddingCache {
+  private entries = new Map<string, number[]>();
+  private hits = 0;
+  private misses = 0;
+
+  constructor(private options: { path?: string } = {}) {
+    const path = options.path;
+    if (path && existsSync(path)) {
+      for (const line of readFileSync(path, "utf8").split("\n")) {
+        try {
+          const cached = JSON.parse(line) as {
+            key: string;
+            embedding: number[];
+          };
+          this.entries.set(cached.key, cached.embedding);
+        } catch {
+          // Skip blank and torn lines
+        }
+      }
+    }
+  }
+
+  /**
+   * Cache key of a model and text
+   */
+  static key(model: string, text: string): string {
+    return createHash("sha256").update(`${model}\0${text}`).digest("hex");
+  }
+
+  /**
+   * Cached embedding of `text` by `model`
+   */
+  get(model: string, text: string): number[] | undefined {
+    const embedding = this.entries.get(EmbeddingCache.key(model, text));
+    if (embedding) {
+      this.hits++;
+    } else {
+      this.misses++;
+    }
+    return embedding;
+  }
+
+  /**
+   * Cache the embedding of `text` by `model`
+   */
+  set(model: string, text: string, embedding: number[]): void {
+    const key = EmbeddingCache.key(model, text);
+    if (this.options.path) {
+      appendFileSync(
+        this.options.path,
+        JSON.stringify({ key, embedding }) + "\n",
+      );
+    }
+    this.entries.set(key, embedding);
+  }
+
+  /**
+   * Number of cached embeddings
+   */
+  get size(): number {
+    return this.entries.size;
+  }
+
+  /**
+   * Entry count and lookup hits and misses
+   */
+  stats(): EmbeddingCacheStats {
+    return { entries: this.entries.size, hits: this.hits, misses: this.misses };
+  }
+
+  /**
+   * Remove every cached embedding, truncating the persistence file
+   */
+  clear(): void {
+    this.entries.clear();
+    if (this.options.path && existsSync(this.options.path)) {
+      truncateSync(this.options.path);
+    }
+  }
+}
+
 /**
  * Token count response
  */
@@ -460,6 +627,17 @@ export class ChatBuilder {
     return this;
   }
 
+  /**
+   * Set queue priority class
+   */
+  setPriority(priority: RequestPriority): ChatBuilder {
+    if (!this.circuitBreakerOptions) {
+      this.circuitBreakerOptions = {};
+    }
+    this.circuitBreakerOptions.priority = priority;
+    return this;
+  }
+
   /**
    * Set maximum cost per 1k tokens
    */
@@ -637,6 +815,52 @@ export async function quickChat(
   return client.chat(model, prompt, options);
 }
 
+/**
+ * Name of a provider as reported by the server, which wraps custom providers
+ * as `{ Custom: name }`
+ */
+function providerName(
+  provider: string | Record<string, string> | undefined,
+): string | undefined {
+  if (provider === undefined || typeof provider === "string") {
+    return provider;
+  }
+  return Object.values(provider)[0];
+}
+
+/**
+ * Routing, token usage and cost of the request behind a chat, smart
+ * completion or agent response
+ */
+export function usageInfo(
+  response: ChatCompletionResponse | { usageInfo: UsageInfo },
+): UsageInfo {
+  if ("usageInfo" in response) {
+    return response.usageInfo;
+  }
+  const routing = response.routing_info;
+  return {
+    ...(response.request_id !== undefined && {
+      requestId: response.request_id,
+    }),
+    ...(routing && {
+      provider: providerName(routing.selected_provider),
+      routingStrategy:
+        typeof routing.routing_strategy === "string"
+          ? routing.routing_strategy
+          : Object.keys(routing.routing_strategy)[0],
+      fallbackUsed: routing.fallback_used,
+      latencyMs: routing.total_latency_ms,
+    }),
+    promptTokens: response.usage?.prompt_tokens ?? 0,
+    completionTokens: response.usage?.completion_tokens ?? 0,
+    totalTokens: response.usage?.total_tokens ?? 0,
+    ...(response.usage?.estimated_cost !== undefined && {
+      costUsd: response.usage.estimated_cost,
+    }),
+  };
+}
+
 /**
  * Common model constants for Circuit Breaker router
  * These are examples - actual available models depend on router configuration
@@ -0,0 +1,258 @@
+/**
+ * Tenant Administration Client
+ *
+ * This module automates tenant onboarding against the server's tenant administration
+ * API: registering tenants, configuring their quotas and routing policies, issuing API
+ * keys, assigning budgets and retrieving usage. Every call requires the client to be
+ * configured with the server's admin token as its API key.
+ */
+
+import { Client } from "./client.js";
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/** Feature the server reports when tenant administration is available */
+const TENANT_ADMIN_FEATURE = "tenant_admin";
+
+/**
+ * Usage limits carried by a tenant's API keys
+ */
+export interface TenantQuota {
+  daily_tokens?: number;
+  /** Monthly spend in USD */
+  monthly_cost?: number;
+  rate_limit_per_minute?: number;
+}
+
+/**
+ * Routing overrides for a tenant; unset fields keep the server's global behaviour
+ */
+export interface TenantRoutingPolicy {
+  /** Strategy for virtual models, e.g. "cost_optimized" or "performance_first" */
+  strategy?: string;
+  /** Providers such as "OpenAI", or `{ Custom: name }` for custom providers */
+  allowed_providers?: Array<string | Record<string, string>>;
+  model_allowlist?: string[];
+}
+
+/**
+ * A registered tenant
+ */
+export interface Tenant {
+  id: string;
+  name?: string;
+  /** Limits applied to the tenant's API keys */
+  quota?: TenantQuota;
+  /** When the tenant was registered (ISO 8601) */
+  created_at: string;
+}
+
+/**
+ * An issued API key, without its secret
+ */
+export interface ApiKeySummary {
+  key_id: string;
+  created_at: string;
+  last_used?: string;
+}
+
+/**
+ * A newly issued API key
+ */
+export interface IssuedApiKey {
+  key_id: string;
+  /** The bearer token to hand to the tenant; it cannot be retrieved again */
+  api_key: string;
+  tenant_id: string;
+  created_at: string;
+}
+
+/**
+ * A tenant's budget
+ */
+export interface TenantBudget {
+  id: string;
+  project_id?: string;
+  /** Spending limit in USD per period */
+  limit: number;
+  period: "daily" | "weekly" | "monthly" | "yearly";
+  warning_threshold: number;
+  created_at: string;
+  updated_at: string;
+}
+
+/**
+ * A tenant with everything configured for it
+ */
+export interface TenantDetails extends Tenant {
+  routing_policy?: TenantRoutingPolicy;
+  budget?: TenantBudget;
+  api_keys: ApiKeySummary[];
+}
+
+/**
+ * Input for registering a tenant
+ */
+export interface CreateTenantInput {
+  id: string;
+  name?: string;
+  quota?: TenantQuota;
+  routing_policy?: TenantRoutingPolicy;
+}
+
+/**
+ * Usage of one model
+ */
+export interface ModelUsage {
+  provider: string;
+  model: string;
+  requests: number;
+  input_tokens: number;
+  output_tokens: number;
+  cost_usd: number;
+}
+
+/**
+ * Usage recorded for a tenant over a period
+ */
+export interface TenantUsage {
+  tenant_id: string;
+  from: string;
+  to: string;
+  requests: number;
+  input_tokens: number;
+  output_tokens: number;
+  /** Cost in USD */
+  cost_usd: number;
+  /** Totals per provider and model, most expensive first */
+  by_model: ModelUsage[];
+}
+
+// ============================================================================
+// Tenants Client
+// ============================================================================
+
+/**
+ * Tenant administration client
+ */
+export class TenantsClient {
+  constructor(private client: Client) {}
+
+  /**
+   * Register a tenant
+   */
+  async create(input: CreateTenantInput): Promise<TenantDetails> {
+    this.client.requireFeature(TENANT_ADMIN_FEATURE);
+    return this.client.restRequest<TenantDetails>(
+      "POST",
+      "/v1/admin/tenants",
+      input,
+    );
+  }
+
+  /**
+   * List registered tenants
+   */
+  async list(): Promise<Tenant[]> {
+    this.client.requireFeature(TENANT_ADMIN_FEATURE);
+    return this.client.restRequest<Tenant[]>("GET", "/v1/admin/tenants");
+  }
+
+  /**
+   * Get a tenant with its routing policy, budget and API keys
+   */
+  async get(tenantId: string): Promise<TenantDetails> {
+    this.client.requireFeature(TENANT_ADMIN_FEATURE);
+    return this.client.restRequest<TenantDetails>("GET", tenantPath(tenantId));
+  }
+
+  /**
+   * Remove a tenant along with its routing policy and budget, revoking its API keys
+   */
+  async delete(tenantId: string): Promise<void> {
+    this.client.requireFeature(TENANT_ADMIN_FEATURE);
+    await this.client.restRequest<void>("DELETE", tenantPath(tenantId));
+  }
+
+  /**
+   * Set the usage limits applied to the tenant's API keys
+   */
+  async setQuota(tenantId: string, quota: TenantQuota): Promise<Tenant> {
+    this.client.requireFeature(TENANT_ADMIN_FEATURE);
+    return this.client.restRequest<Tenant>(
+      "PUT",
+      tenantPath(tenantId, "/quota"),
+      quota,
+    );
+  }
+
+  /**
+   * Issue an API key for the tenant; the returned secret cannot be retrieved again
+   */
+  async issueApiKey(tenantId: string): Promise<IssuedApiKey> {
+    this.client.requireFeature(TENANT_ADMIN_FEATURE);
+    return this.client.restRequest<IssuedApiKey>(
+      "POST",
+      tenantPath(tenantId, "/api-keys"),
+    );
+  }
+
+  /**
+   * Revoke one of the tenant's API keys
+   */
+  async revokeApiKey(tenantId: string, keyId: string): Promise<void> {
+    this.client.requireFeature(TENANT_ADMIN_FEATURE);
+    await this.client.restRequest<void>(
+      "DELETE",
+      tenantPath(tenantId, `/api-keys/${encodeURIComponent(keyId)}`),
+    );
+  }
+
+  /**
+   * Assign the budget the tenant's requests are checked against
+   * @param limit Spending limit in USD per period
+   * @param warningThreshold Share of the limit (0.0 - 1.0) past which responses carry a budget warning
+   */
+  async setBudget(
+    tenantId: string,
+    limit: number,
+    period: TenantBudget["period"],
+    warningThreshold = 0.8,
+  ): Promise<TenantBudget> {
+    this.client.requireFeature(TENANT_ADMIN_FEATURE);
+    return this.client.restRequest<TenantBudget>(
+      "PUT",
+      tenantPath(tenantId, "/budget"),
+      { limit, period, warning_threshold: warningThreshold },
+    );
+  }
+
+  /**
+   * Retrieve the tenant's recorded usage
+   * @param options.from Start of the period as an ISO 8601 timestamp or date; defaults to 30 days before `to`
+   * @param options.to End of the period; a date includes the whole day. Defaults to now.
+   */
+  async usage(
+    tenantId: string,
+    options: { from?: string; to?: string } = {},
+  ): Promise<TenantUsage> {
+    this.client.requireFeature(TENANT_ADMIN_FEATURE);
+    const params = new URLSearchParams();
+    if (options.from) params.set("from", options.from);
+    if (options.to) params.set("to", options.to);
+    const query = params.toString();
+    return this.client.restRequest<TenantUsage>(
+      "GET",
+      tenantPath(tenantId, "/usage") + (query ? `?${query}` : ""),
+    );
+  }
+}
+
+/**
+ * Path of a tenant endpoint
+ */
+function tenantPath(tenantId: string, suffix = ""): string {
+  return `/v1/admin/tenants/${encodeURIComponent(tenantId)}${suffix}`;
+}
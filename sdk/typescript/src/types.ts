@@ -43,6 +43,20 @@ export interface ServerInfo {
   };
 }
 
+/**
+ * Server version and capabilities from `GET /v1/meta`
+ */
+export interface ServerMeta {
+  service: string;
+  version: string;
+  api_versions: string[];
+  mcp_protocol_versions: string[];
+  min_sdk_version: string;
+  /** Enabled feature names, e.g. `streaming` or `structured_output` */
+  features: string[];
+  maintenance: boolean;
+}
+
 // ============================================================================
 // Workflow Types
 // ============================================================================
@@ -322,15 +336,32 @@ export interface ChatCompletionRequest {
   user?: string;
   functions?: ChatFunction[];
   function_call?: any;
+  response_format?: ResponseFormat;
   circuit_breaker?: CircuitBreakerOptions;
 }
 
+export type ResponseFormat =
+  | { type: "text" }
+  | { type: "json_object" }
+  | { type: "json_schema"; json_schema: JsonSchemaFormat };
+
+export interface JsonSchemaFormat {
+  name: string;
+  description?: string;
+  schema: Record<string, any>;
+  strict?: boolean;
+}
+
 export interface ChatCompletionResponse {
   id: string;
   choices: Choice[];
   usage: Usage;
   model: string;
   created: number;
+  /** ID the request's cost details are fetched by (Circuit Breaker extension) */
+  request_id?: string;
+  /** How the request was routed (Circuit Breaker extension) */
+  routing_info?: RoutingInfo;
 }
 
 export interface Choice {
@@ -343,6 +374,40 @@ export interface Usage {
   prompt_tokens: number;
   completion_tokens: number;
   total_tokens: number;
+  /** Cost in USD computed by the server (Circuit Breaker extension) */
+  estimated_cost?: number;
+}
+
+/**
+ * Routing decision the server made for a request
+ */
+export interface RoutingInfo {
+  /** Provider name such as "OpenAI", or `{ Custom: name }` for custom providers */
+  selected_provider: string | Record<string, string>;
+  routing_strategy: string | Record<string, string>;
+  latency_ms: number;
+  retry_count: number;
+  fallback_used: boolean;
+  provider_used: string | Record<string, string>;
+  total_latency_ms: number;
+  provider_latency_ms: number;
+}
+
+/**
+ * Routing, token usage and cost of a single request, the same for every response type
+ */
+export interface UsageInfo {
+  /** ID to pass to `AnalyticsClient.reportForRequest` */
+  requestId?: string;
+  provider?: string;
+  routingStrategy?: string;
+  fallbackUsed?: boolean;
+  latencyMs?: number;
+  promptTokens: number;
+  completionTokens: number;
+  totalTokens: number;
+  /** Cost in USD computed by the server */
+  costUsd?: number;
 }
 
 export interface SmartCompletionRequest {
@@ -362,8 +427,11 @@ export interface CircuitBreakerOptions {
   task_type?: TaskType;
   require_streaming?: boolean;
   budget_constraint?: BudgetConstraint;
+  priority?: RequestPriority;
 }
 
+export type RequestPriority = "interactive" | "batch";
+
 export type RoutingStrategy =
   | "cost_optimized"
   | "performance_first"
@@ -461,6 +529,61 @@ export class NotFoundError extends CircuitBreakerError {
   }
 }
 
+/**
+ * Stable, machine-readable error code returned by the server in
+ * `error.error_code` (REST), `extensions.code` (GraphQL) and
+ * `error.data.error_code` (MCP).
+ */
+export type ErrorCode =
+  | "INVALID_INPUT"
+  | "WORKFLOW_NOT_FOUND"
+  | "RESOURCE_NOT_FOUND"
+  | "AGENT_NOT_FOUND"
+  | "EXECUTION_NOT_FOUND"
+  | "RULE_NOT_FOUND"
+  | "MODEL_NOT_FOUND"
+  | "NOT_FOUND"
+  | "INVALID_STATE_TRANSITION"
+  | "RULE_VALIDATION_FAILED"
+  | "CONTEXT_LENGTH_EXCEEDED"
+  | "CONTENT_FLAGGED"
+  | "BUDGET_EXCEEDED"
+  | "RATE_LIMITED"
+  | "PROVIDER_UNAVAILABLE"
+  | "PROVIDER_ERROR"
+  | "AUTHENTICATION_FAILED"
+  | "PERMISSION_DENIED"
+  | "TIMEOUT"
+  | "STORAGE_ERROR"
+  | "MAINTENANCE_MODE"
+  | "INTERNAL";
+
+export class ApiError extends CircuitBreakerError {
+  constructor(
+    message: string,
+    public errorCode: ErrorCode,
+    public status?: number,
+    details?: Record<string, any>,
+  ) {
+    super(message, errorCode, details);
+    this.name = "ApiError";
+  }
+}
+
+export class UnsupportedFeatureError extends CircuitBreakerError {
+  constructor(
+    public feature: string,
+    public serverVersion: string,
+  ) {
+    super(
+      `Server ${serverVersion} does not support '${feature}'`,
+      "UNSUPPORTED_FEATURE",
+      { feature, serverVersion },
+    );
+    this.name = "UnsupportedFeatureError";
+  }
+}
+
 // ============================================================================
 // Utility Types
 // ============================================================================
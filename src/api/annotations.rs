@@ -0,0 +1,157 @@
+// Response annotations for routing transparency
+// Opt-in summary of how the router served a chat completion, returned as a header
+
+//! # Response Annotations
+//!
+//! Teams debugging the quality of a completion often need to know what happened to it
+//! without access to the server logs. A `POST /v1/chat/completions` request sending
+//! `X-Routing-Annotations: true` gets a JSON summary back in the response's
+//! `X-Routing-Annotations` header, for streamed and regular completions alike:
+//!
+//! - the provider and model that served the request
+//! - how many provider calls failed first, and whether another provider or model took
+//!   over
+//! - the providers and models passed over, and why
+//! - guardrail actions, e.g. moderation or shortening the prompt to fit the context
+//!   window
+//!
+//! The summary is drawn from the request's routing trace, so it is only returned while
+//! the router records traces; `GET /v1/requests/{id}/routing` has the full trace. For a
+//! streamed completion it reflects the state when the stream started.
+
+use axum::http::{HeaderMap, HeaderValue};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::llm::{LLMProviderType, RoutingExclusion, RoutingTrace};
+
+/// Header opting a request into annotations, and carrying them on the response
+pub const ANNOTATIONS_HEADER: &str = "x-routing-annotations";
+
+/// How the router served a request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponseAnnotations {
+    pub request_id: Uuid,
+    /// Model named by the caller, possibly a virtual model
+    pub requested_model: String,
+    pub provider: Option<LLMProviderType>,
+    pub model: Option<String>,
+    /// Provider calls that failed before the request was served
+    pub retries: usize,
+    /// Whether a provider or model other than the one that served the request was tried
+    pub fallback: bool,
+    /// Whether the response came from a cache; the router does not cache completions
+    pub cache_hit: bool,
+    /// Providers and models passed over, and why
+    pub skipped: Vec<RoutingExclusion>,
+    pub guardrails: Vec<String>,
+}
+
+impl From<&RoutingTrace> for ResponseAnnotations {
+    fn from(trace: &RoutingTrace) -> Self {
+        let served_by = |provider: &LLMProviderType, model: &str| {
+            trace.selected_provider.as_ref() == Some(provider)
+                && trace.resolved_model.as_deref() == Some(model)
+        };
+        Self {
+            request_id: trace.request_id,
+            requested_model: trace.requested_model.clone(),
+            provider: trace.selected_provider.clone(),
+            model: trace.resolved_model.clone(),
+            retries: trace
+                .attempts
+                .iter()
+                .filter(|attempt| !attempt.success)
+                .count(),
+            fallback: trace
+                .attempts
+                .iter()
+                .any(|attempt| !served_by(&attempt.provider, &attempt.model)),
+            cache_hit: false,
+            skipped: trace.exclusions.clone(),
+            guardrails: trace.guardrails.clone(),
+        }
+    }
+}
+
+/// Whether the request opted into annotations
+pub fn requested(headers: &HeaderMap) -> bool {
+    headers
+        .get(ANNOTATIONS_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| matches!(value.trim(), "1" | "true"))
+}
+
+/// Add `annotations` to the response headers
+pub fn insert(headers: &mut HeaderMap, annotations: &ResponseAnnotations) {
+    let Ok(json) = serde_json::to_string(annotations) else {
+        return;
+    };
+    if let Ok(value) = HeaderValue::from_str(&ascii_json(&json)) {
+        headers.insert(ANNOTATIONS_HEADER, value);
+    }
+}
+
+/// Escape non-ASCII characters in `json`, which header values cannot carry
+fn ascii_json(json: &str) -> String {
+    let mut escaped = String::with_capacity(json.len());
+    for c in json.chars() {
+        if c.is_ascii() {
+            escaped.push(c);
+        } else {
+            let mut units = [0; 2];
+            for unit in c.encode_utf16(&mut units) {
+                escaped.push_str(&format!("\\u{:04x}", unit));
+            }
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::RoutingAttempt;
+
+    #[test]
+    fn test_annotations_from_trace() {
+        let mut trace = RoutingTrace::new(Uuid::new_v4(), "smart-cheap");
+        trace.attempts.push(RoutingAttempt {
+            provider: LLMProviderType::OpenAI,
+            model: "gpt-4o-mini".to_string(),
+            success: false,
+            error: Some("Rate limit exceeded".to_string()),
+        });
+        trace.select(&LLMProviderType::Anthropic, "claude-3-haiku");
+        trace.attempts.push(RoutingAttempt {
+            provider: LLMProviderType::Anthropic,
+            model: "claude-3-haiku".to_string(),
+            success: true,
+            error: None,
+        });
+        trace.guardrail("Input passed moderation — no flags");
+
+        let annotations = ResponseAnnotations::from(&trace);
+        assert_eq!(annotations.provider, Some(LLMProviderType::Anthropic));
+        assert_eq!(annotations.model.as_deref(), Some("claude-3-haiku"));
+        assert_eq!(annotations.retries, 1);
+        assert!(annotations.fallback);
+        assert!(!annotations.cache_hit);
+
+        let mut headers = HeaderMap::new();
+        insert(&mut headers, &annotations);
+        let value = headers[ANNOTATIONS_HEADER].to_str().unwrap();
+        let parsed: ResponseAnnotations = serde_json::from_str(value).unwrap();
+        assert_eq!(parsed, annotations);
+    }
+
+    #[test]
+    fn test_annotations_requested() {
+        let mut headers = HeaderMap::new();
+        assert!(!requested(&headers));
+        headers.insert(ANNOTATIONS_HEADER, HeaderValue::from_static("true"));
+        assert!(requested(&headers));
+        headers.insert(ANNOTATIONS_HEADER, HeaderValue::from_static("false"));
+        assert!(!requested(&headers));
+    }
+}
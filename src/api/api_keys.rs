@@ -0,0 +1,365 @@
+// API key administration and enforcement
+// `/v1/admin/api-keys` manages scoped keys; middleware requires them when configured
+
+//! # API Keys
+//!
+//! Operators manage [API keys](crate::api_keys) through these endpoints:
+//!
+//! - `POST /v1/admin/api-keys` creates a key; the secret is only returned once
+//! - `GET /v1/admin/api-keys` and `GET /v1/admin/api-keys/{id}`
+//! - `POST /v1/admin/api-keys/{id}/rotate` issues a new secret, optionally keeping the
+//!   old one valid for `grace_period_secs`
+//! - `DELETE /v1/admin/api-keys/{id}` revokes a key
+//!
+//! Like tenant administration, every endpoint requires the admin token. With
+//! `api_key_required` on, [`require_api_key`] guards the OpenAI-compatible routes and
+//! [`require_mcp_api_key`] the MCP routes. Tenant API keys keep working on the
+//! OpenAI-compatible routes alongside managed keys. Both middlewares also hold managed
+//! keys to the [roles](crate::rbac) assigned to them.
+
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header, HeaderMap, HeaderValue, Method, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use tracing::info;
+
+use super::handlers::{authorize_admin, OpenAIApiState};
+use super::roles::role_denied;
+use super::types::{create_error_response, ErrorResponse};
+use super::usage_export::invalid_param;
+use super::validation::{read_body, DEFAULT_MAX_BODY_BYTES};
+use crate::api_keys::{
+    ApiKey, ApiKeyError, ApiKeyRejection, ApiKeyScope, ApiKeys, MintedApiKey, NewApiKey,
+};
+use crate::rbac::{
+    mcp_method_permission, mcp_permission, rest_permission, Domain, Permission, RoleAssignments,
+    Subject,
+};
+use crate::ErrorCode;
+
+/// Longest grace period a rotated secret may keep working
+pub const MAX_ROTATION_GRACE_SECS: u64 = 7 * 24 * 3600;
+
+/// Body of `POST /v1/admin/api-keys`
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    /// What the key may be used for
+    pub scopes: Vec<ApiKeyScope>,
+    /// Requests the key may make per minute; unlimited when unset
+    #[serde(default)]
+    pub rate_limit_per_minute: Option<u32>,
+    /// When the key stops working; never when unset
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Body of `POST /v1/admin/api-keys/{id}/rotate`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RotateApiKeyRequest {
+    /// How long the replaced secret keeps working; it stops at once when unset
+    #[serde(default)]
+    pub grace_period_secs: Option<u64>,
+}
+
+pub(crate) fn api_key_error(error: ApiKeyError) -> ErrorResponse {
+    match error {
+        ApiKeyError::NotFound(key_id) => create_error_response(
+            format!("API key '{}' not found", key_id),
+            "not_found_error".to_string(),
+            Some("key_id".to_string()),
+            None,
+        )
+        .with_error_code(ErrorCode::NotFound),
+        ApiKeyError::Store(message) => {
+            create_error_response(message, "internal_error".to_string(), None, None)
+                .with_error_code(ErrorCode::StorageError)
+        }
+    }
+}
+
+/// Error response refusing a request's API key
+pub(crate) fn rejected(rejection: ApiKeyRejection) -> Response {
+    let error_type = match rejection.code() {
+        ErrorCode::AuthenticationFailed => "authentication_error",
+        ErrorCode::PermissionDenied => "permission_error",
+        ErrorCode::RateLimited => "rate_limit_error",
+        _ => "internal_error",
+    };
+    let retry_after = match &rejection {
+        ApiKeyRejection::RateLimited(secs) => Some(*secs),
+        ApiKeyRejection::LockedOut(lockout) => Some(lockout.retry_after_secs),
+        _ => None,
+    };
+    let mut response = create_error_response(
+        rejection.message(),
+        error_type.to_string(),
+        None,
+        Some("invalid_api_key".to_string()).filter(|_| rejection == ApiKeyRejection::Invalid),
+    )
+    .with_error_code(rejection.code())
+    .into_response();
+    if let Some(secs) = retry_after {
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+    }
+    response
+}
+
+/// Create an API key - POST /v1/admin/api-keys
+pub async fn create_api_key(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Json(request): Json<CreateApiKeyRequest>,
+) -> Result<(StatusCode, Json<MintedApiKey>), ErrorResponse> {
+    authorize_admin(&state, &headers, "API key administration")?;
+
+    if request.name.trim().is_empty() {
+        return Err(
+            invalid_param("API key 'name' must not be empty".to_string(), "name")
+                .with_error_code(ErrorCode::InvalidInput),
+        );
+    }
+    if request.scopes.is_empty() {
+        return Err(invalid_param(
+            "An API key must have at least one scope".to_string(),
+            "scopes",
+        )
+        .with_error_code(ErrorCode::InvalidInput));
+    }
+    if request.rate_limit_per_minute == Some(0) {
+        return Err(invalid_param(
+            "Rate limit must be at least 1 request per minute".to_string(),
+            "rate_limit_per_minute",
+        )
+        .with_error_code(ErrorCode::InvalidInput));
+    }
+    if request
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= Utc::now())
+    {
+        return Err(
+            invalid_param("Expiry must be in the future".to_string(), "expires_at")
+                .with_error_code(ErrorCode::InvalidInput),
+        );
+    }
+
+    let minted = state
+        .managed_keys
+        .create(NewApiKey {
+            name: request.name.trim().to_string(),
+            scopes: request.scopes,
+            rate_limit_per_minute: request.rate_limit_per_minute,
+            expires_at: request.expires_at,
+        })
+        .await
+        .map_err(api_key_error)?;
+    info!(
+        "Created API key {} with scopes {:?}",
+        minted.key.id, minted.key.scopes
+    );
+
+    Ok((StatusCode::CREATED, Json(minted)))
+}
+
+/// List API keys, revoked ones included - GET /v1/admin/api-keys
+pub async fn list_api_keys(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<ApiKey>>, ErrorResponse> {
+    authorize_admin(&state, &headers, "API key administration")?;
+    Ok(Json(
+        state.managed_keys.list().await.map_err(api_key_error)?,
+    ))
+}
+
+/// Get an API key - GET /v1/admin/api-keys/{id}
+pub async fn get_api_key(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Path(key_id): Path<String>,
+) -> Result<Json<ApiKey>, ErrorResponse> {
+    authorize_admin(&state, &headers, "API key administration")?;
+    let key = state
+        .managed_keys
+        .get(&key_id)
+        .await
+        .map_err(api_key_error)?
+        .ok_or_else(|| api_key_error(ApiKeyError::NotFound(key_id)))?;
+    Ok(Json(key))
+}
+
+/// Issue a new secret for an API key - POST /v1/admin/api-keys/{id}/rotate
+pub async fn rotate_api_key(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Path(key_id): Path<String>,
+    request: Option<Json<RotateApiKeyRequest>>,
+) -> Result<Json<MintedApiKey>, ErrorResponse> {
+    authorize_admin(&state, &headers, "API key administration")?;
+
+    let grace_period_secs = request
+        .and_then(|Json(request)| request.grace_period_secs)
+        .unwrap_or(0);
+    if grace_period_secs > MAX_ROTATION_GRACE_SECS {
+        return Err(invalid_param(
+            format!(
+                "Grace period must be at most {} seconds",
+                MAX_ROTATION_GRACE_SECS
+            ),
+            "grace_period_secs",
+        )
+        .with_error_code(ErrorCode::InvalidInput));
+    }
+
+    let minted = state
+        .managed_keys
+        .rotate(&key_id, Duration::seconds(grace_period_secs as i64))
+        .await
+        .map_err(api_key_error)?;
+    info!(
+        "Rotated API key {}, previous secret valid for {} more seconds",
+        key_id, grace_period_secs
+    );
+
+    Ok(Json(minted))
+}
+
+/// Revoke an API key - DELETE /v1/admin/api-keys/{id}
+pub async fn revoke_api_key(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Path(key_id): Path<String>,
+) -> Result<StatusCode, ErrorResponse> {
+    authorize_admin(&state, &headers, "API key administration")?;
+    state
+        .managed_keys
+        .revoke(&key_id)
+        .await
+        .map_err(api_key_error)?;
+    info!("Revoked API key: {}", key_id);
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Middleware requiring an API key with the `llm` scope on the OpenAI-compatible routes
+///
+/// Tenant API keys are accepted too. Health checks and admin endpoints, which require
+/// the admin token instead, pass through.
+pub async fn require_api_key(
+    State(state): State<OpenAIApiState>,
+    mut request: Request<Body>,
+    next: Next<Body>,
+) -> Response {
+    let path = request.uri().path();
+    let exempt = path == "/health"
+        || path == "/v1/health"
+        || path.starts_with("/admin/")
+        || path.starts_with("/v1/admin/");
+    if exempt {
+        return next.run(request).await;
+    }
+
+    let headers = request.headers();
+    match state
+        .managed_keys
+        .authorize(headers, ApiKeyScope::Llm)
+        .await
+    {
+        Ok(Some(key)) => {
+            if let Some(permission) = rest_permission(request.method(), path) {
+                let subject = Subject::ApiKey(key.id.clone());
+                if let Err(denied) = state.roles.authorize(&subject, Some(permission)).await {
+                    return role_denied(denied);
+                }
+            }
+            request.extensions_mut().insert(key);
+            next.run(request).await
+        }
+        Ok(None) => match state.extract_api_key(headers).await {
+            Ok(Some(_)) => next.run(request).await,
+            Ok(None) => rejected(ApiKeyRejection::Missing),
+            Err(error) => error.into_response(),
+        },
+        Err(rejection) => rejected(rejection),
+    }
+}
+
+/// Middleware requiring an API key with the `mcp` scope on the MCP routes
+///
+/// MCP clients send their JWTs as bearer tokens, so the key goes in `X-API-Key`.
+/// CORS preflights and the OAuth discovery, registration and redirect endpoints pass
+/// through: browsers cannot add headers to them. Keys with a role that does not write MCP
+/// instances may only call the JSON-RPC methods their role allows.
+pub async fn require_mcp_api_key(
+    State(keys): State<ApiKeys>,
+    mut request: Request<Body>,
+    next: Next<Body>,
+) -> Response {
+    let path = request.uri().path();
+    let exempt = request.method() == Method::OPTIONS
+        || path.starts_with("/.well-known/")
+        || path.starts_with("/oauth/")
+        || path == "/register"
+        || path.ends_with("/oauth/init")
+        || path.contains("/oauth/callback");
+    if exempt {
+        return next.run(request).await;
+    }
+
+    let key = match keys.authorize(request.headers(), ApiKeyScope::Mcp).await {
+        Ok(Some(key)) => key,
+        Ok(None) => return rejected(ApiKeyRejection::Missing),
+        Err(rejection) => return rejected(rejection),
+    };
+
+    let roles = RoleAssignments::global();
+    let subject = Subject::ApiKey(key.id.clone());
+    request.extensions_mut().insert(key);
+    let role = match roles.role_of(&subject).await {
+        Ok(Some(role)) => role,
+        Ok(None) => return next.run(request).await,
+        Err(denied) => return role_denied(denied),
+    };
+    if let Some(permission) = mcp_permission(request.method(), request.uri().path()) {
+        return match roles.authorize(&subject, Some(permission)).await {
+            Ok(()) => next.run(request).await,
+            Err(denied) => role_denied(denied),
+        };
+    }
+    if role.allows(Permission::write(Domain::McpInstances)) {
+        return next.run(request).await;
+    }
+
+    // JSON-RPC posts need what the methods they call need, single or batched
+    let (parts, body) = request.into_parts();
+    let bytes = match read_body(body, DEFAULT_MAX_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(response) => return response,
+    };
+    let calls = match serde_json::from_slice::<serde_json::Value>(&bytes) {
+        Ok(serde_json::Value::Array(calls)) => calls,
+        Ok(call) => vec![call],
+        Err(_) => Vec::new(),
+    };
+    for call in &calls {
+        let Some(method) = call.get("method").and_then(|method| method.as_str()) else {
+            continue;
+        };
+        if let Err(denied) = roles
+            .authorize(&subject, Some(mcp_method_permission(method)))
+            .await
+        {
+            return role_denied(denied);
+        }
+    }
+    next.run(Request::from_parts(parts, Body::from(bytes)))
+        .await
+}
@@ -0,0 +1,303 @@
+// Audio endpoints
+// `POST /v1/audio/transcriptions` and `POST /v1/audio/speech` in OpenAI's format
+
+//! # Audio
+//!
+//! `POST /v1/audio/transcriptions` takes OpenAI's multipart upload (`file`, `model` and
+//! optionally `language`, `prompt`, `response_format`, `temperature` and `user`) and
+//! returns the transcription in the requested format. `POST /v1/audio/speech` takes
+//! OpenAI's JSON body and returns the generated audio. Both route by model through the
+//! [`AudioProviders`](crate::llm::AudioProviders) configured by the environment.
+//!
+//! Each request's cost is recorded with the cost optimizer under the request's user and
+//! tenant, like chat completions: transcriptions by the minute of audio, speech by the
+//! character of input.
+
+use axum::{
+    extract::{Multipart, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+    Json,
+};
+use tracing::{debug, warn};
+
+use super::handlers::{llm_error_response, OpenAIApiState};
+use super::types::ErrorResponse;
+use super::usage_export::invalid_param;
+use crate::llm::audio::{
+    self, SpeechRequest, TranscriptionFormat, TranscriptionRequest, MAX_AUDIO_FILE_BYTES,
+    MAX_SPEECH_INPUT_CHARS,
+};
+use crate::llm::LLMError;
+use crate::ErrorCode;
+
+/// Body limit of transcription uploads: the largest file plus room for the other fields
+pub const MAX_TRANSCRIPTION_UPLOAD_BYTES: usize = MAX_AUDIO_FILE_BYTES + 64 * 1024;
+
+/// Transcribe audio - POST /v1/audio/transcriptions
+pub async fn create_transcription(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    multipart: Multipart,
+) -> Result<Response, ErrorResponse> {
+    let (request, user) = read_transcription_request(multipart).await?;
+    debug!(
+        "Transcribing {} bytes of audio with {}",
+        request.file.len(),
+        request.model
+    );
+    let tenant_id = state.request_tenant(&headers).await?;
+
+    let provider = state
+        .audio
+        .transcription_provider(&request.model)
+        .map_err(unsupported_model)?;
+    let transcription = provider
+        .transcribe(&request)
+        .await
+        .map_err(|e| llm_error_response(&e, e.to_string()))?;
+
+    let provider_type = provider.provider_type();
+    if transcription.duration_seconds.is_none() {
+        warn!(
+            "{} did not report the duration of a transcription; recording no cost",
+            provider_type
+        );
+    }
+    let cost_usd = audio::transcription_cost(
+        &provider_type,
+        &request.model,
+        transcription.billed_minutes(),
+    );
+    state
+        .record_flat_cost(provider_type, &request.model, cost_usd, user, tenant_id)
+        .await;
+
+    Ok((
+        [(header::CONTENT_TYPE, transcription.format.content_type())],
+        transcription.body,
+    )
+        .into_response())
+}
+
+/// Generate speech - POST /v1/audio/speech
+pub async fn create_speech(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Json(request): Json<SpeechRequestBody>,
+) -> Result<Response, ErrorResponse> {
+    let SpeechRequestBody { speech, user } = request;
+    if speech.input.is_empty() || speech.input.chars().count() > MAX_SPEECH_INPUT_CHARS {
+        return Err(invalid_param(
+            format!(
+                "Input must be between 1 and {} characters",
+                MAX_SPEECH_INPUT_CHARS
+            ),
+            "input",
+        )
+        .with_error_code(ErrorCode::InvalidInput));
+    }
+    let tenant_id = state.request_tenant(&headers).await?;
+
+    let provider = state
+        .audio
+        .speech_provider(&speech.model)
+        .map_err(unsupported_model)?;
+    let audio = provider
+        .speak(&speech)
+        .await
+        .map_err(|e| llm_error_response(&e, e.to_string()))?;
+
+    let provider_type = provider.provider_type();
+    let cost_usd = audio::speech_cost(&provider_type, &speech.model, &speech.input);
+    state
+        .record_flat_cost(provider_type, &speech.model, cost_usd, user, tenant_id)
+        .await;
+
+    Ok(([(header::CONTENT_TYPE, audio.content_type)], audio.audio).into_response())
+}
+
+/// Body of `POST /v1/audio/speech`
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct SpeechRequestBody {
+    #[serde(flatten)]
+    pub speech: SpeechRequest,
+    /// End user the request is made for, to attribute its cost
+    #[serde(default)]
+    pub user: Option<String>,
+}
+
+/// Read the fields of a transcription upload, and the user it is made for
+async fn read_transcription_request(
+    mut multipart: Multipart,
+) -> Result<(TranscriptionRequest, Option<String>), ErrorResponse> {
+    let invalid = |message: String, param: &str| {
+        invalid_param(message, param).with_error_code(ErrorCode::InvalidInput)
+    };
+
+    let mut file = None;
+    let mut model = None;
+    let mut language = None;
+    let mut prompt = None;
+    let mut response_format = TranscriptionFormat::default();
+    let mut temperature = None;
+    let mut user = None;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| invalid(format!("Invalid multipart body: {}", e), "file"))?
+    {
+        let name = field.name().unwrap_or_default().to_string();
+        if name == "file" {
+            let filename = field.file_name().unwrap_or("audio").to_string();
+            let content_type = field.content_type().map(str::to_string);
+            let bytes = field
+                .bytes()
+                .await
+                .map_err(|e| invalid(format!("Failed to read file: {}", e), "file"))?;
+            file = Some((bytes, filename, content_type));
+            continue;
+        }
+
+        let value = field
+            .text()
+            .await
+            .map_err(|e| invalid(format!("Invalid field '{}': {}", name, e), &name))?;
+        match name.as_str() {
+            "model" => model = Some(value),
+            "language" => language = Some(value),
+            "prompt" => prompt = Some(value),
+            "user" => user = Some(value),
+            "response_format" => {
+                response_format = TranscriptionFormat::parse(&value).ok_or_else(|| {
+                    invalid(
+                        format!(
+                            "Unknown response format '{}', expected json, text, srt, verbose_json or vtt",
+                            value
+                        ),
+                        "response_format",
+                    )
+                })?
+            }
+            "temperature" => {
+                temperature = Some(value.parse::<f32>().map_err(|_| {
+                    invalid(
+                        format!("Temperature '{}' is not a number", value),
+                        "temperature",
+                    )
+                })?)
+            }
+            _ => {}
+        }
+    }
+
+    let (file, filename, content_type) =
+        file.ok_or_else(|| invalid("An audio file is required".to_string(), "file"))?;
+    if file.is_empty() || file.len() > MAX_AUDIO_FILE_BYTES {
+        return Err(invalid(
+            format!(
+                "Audio file must be between 1 byte and {} MB",
+                MAX_AUDIO_FILE_BYTES / (1024 * 1024)
+            ),
+            "file",
+        ));
+    }
+    let model = model.ok_or_else(|| invalid("A model is required".to_string(), "model"))?;
+
+    Ok((
+        TranscriptionRequest {
+            file,
+            filename,
+            content_type,
+            model,
+            language,
+            prompt,
+            response_format,
+            temperature,
+        },
+        user,
+    ))
+}
+
+/// Error for a model no audio provider serves
+fn unsupported_model(error: LLMError) -> ErrorResponse {
+    invalid_param(error.to_string(), "model").with_error_code(error.code())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::FromRequest;
+    use axum::http::Request;
+
+    async fn multipart(fields: &[(&str, Option<&str>, &str)]) -> Multipart {
+        let mut body = String::new();
+        for (name, filename, value) in fields {
+            body.push_str("--boundary\r\n");
+            match filename {
+                Some(filename) => body.push_str(&format!(
+                    "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: audio/mpeg\r\n\r\n",
+                    name, filename
+                )),
+                None => body.push_str(&format!(
+                    "Content-Disposition: form-data; name=\"{}\"\r\n\r\n",
+                    name
+                )),
+            }
+            body.push_str(value);
+            body.push_str("\r\n");
+        }
+        body.push_str("--boundary--\r\n");
+
+        let request = Request::builder()
+            .method("POST")
+            .header(
+                header::CONTENT_TYPE,
+                "multipart/form-data; boundary=boundary",
+            )
+            .body(axum::body::Body::from(body))
+            .unwrap();
+        Multipart::from_request(request, &()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_read_transcription_request() {
+        let (request, user) = read_transcription_request(
+            multipart(&[
+                ("file", Some("call.mp3"), "ID3-audio"),
+                ("model", None, "whisper-1"),
+                ("response_format", None, "srt"),
+                ("temperature", None, "0.2"),
+                ("user", None, "alice"),
+            ])
+            .await,
+        )
+        .await
+        .unwrap();
+        assert_eq!(request.file.as_ref(), b"ID3-audio");
+        assert_eq!(request.filename, "call.mp3");
+        assert_eq!(request.content_type.as_deref(), Some("audio/mpeg"));
+        assert_eq!(request.model, "whisper-1");
+        assert_eq!(request.response_format, TranscriptionFormat::Srt);
+        assert_eq!(request.temperature, Some(0.2));
+        assert_eq!(user.as_deref(), Some("alice"));
+
+        let missing_file =
+            read_transcription_request(multipart(&[("model", None, "whisper-1")]).await)
+                .await
+                .unwrap_err();
+        assert_eq!(missing_file.error.param.as_deref(), Some("file"));
+
+        let bad_format = read_transcription_request(
+            multipart(&[
+                ("file", Some("call.mp3"), "ID3-audio"),
+                ("model", None, "whisper-1"),
+                ("response_format", None, "docx"),
+            ])
+            .await,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(bad_format.error.param.as_deref(), Some("response_format"));
+    }
+}
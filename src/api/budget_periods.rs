@@ -0,0 +1,49 @@
+// Budget period history
+// `GET /v1/analytics/budgets/{budget_id}/periods` reports a budget's open period and the periods it closed
+
+//! # Budget Periods
+//!
+//! Budgets reset every day, week, month or year at midnight in their timezone, optionally
+//! carrying what was left of, or overspent in, one period into the next. When a period
+//! closes, the [`BudgetManager`](crate::llm::BudgetManager) records its spend;
+//! `GET /v1/analytics/budgets/{budget_id}/periods` returns the budget, its open period so
+//! far and the closed periods, most recent first.
+//!
+//! Budget IDs are the keys budgets are tracked under, such as `project:acme` or
+//! `user:alice`. Like the other analytics endpoints it requires the admin token.
+
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+
+use super::handlers::{authorize_admin, cost_error_response, OpenAIApiState};
+use super::types::{create_error_response, ErrorResponse};
+use crate::llm::cost::BudgetPeriods;
+use crate::ErrorCode;
+
+/// Report a budget's periods - GET /v1/analytics/budgets/{budget_id}/periods
+pub async fn get_budget_periods(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Path(budget_id): Path<String>,
+) -> Result<Json<BudgetPeriods>, ErrorResponse> {
+    authorize_admin(&state, &headers, "Budget period history")?;
+
+    let budget_manager = state.cost_optimizer.read().await.budget_manager();
+    budget_manager
+        .budget_periods(&budget_id)
+        .await
+        .map_err(cost_error_response)?
+        .map(Json)
+        .ok_or_else(|| {
+            create_error_response(
+                format!("Budget '{}' not found", budget_id),
+                "not_found_error".to_string(),
+                Some("budget_id".to_string()),
+                None,
+            )
+            .with_error_code(ErrorCode::NotFound)
+        })
+}
@@ -0,0 +1,295 @@
+// Chargeback reports
+// `GET /v1/analytics/chargeback` totals recorded costs per user, project, model, provider or day
+
+//! # Chargeback Reports
+//!
+//! Cost analytics only report aggregate spend. Finance teams charging LLM usage back to
+//! the teams that caused it need spend split by who and what: `GET /v1/analytics/chargeback`
+//! totals the costs the usage tracker recorded between `from` and `to` into one group per
+//! distinct combination of the `group_by` columns (a comma-separated list of `user`,
+//! `project`, `model`, `provider` and `day`; `project` by default).
+//!
+//! Each group carries its request count, token counts, cost and share of the report's
+//! total cost, most expensive first. `user_id` and `project_id` narrow the report to one
+//! user or project. The window and its defaults are those of the usage export, and like
+//! the usage export the endpoint requires the admin token.
+//!
+//! The usage export's `sample_rate` and `min_group_size` apply too. Totals and shares
+//! then cover the published groups only, so suppressed groups cannot be recovered by
+//! subtracting the others from the total.
+
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashSet};
+
+use super::handlers::{authorize_admin, cost_error_response, OpenAIApiState};
+use super::types::ErrorResponse;
+use super::usage_export::{invalid_param, parse_window, ExportPrivacy, GroupBy};
+use crate::llm::CostInfo;
+
+/// Query parameters of `GET /v1/analytics/chargeback`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ChargebackQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    /// Comma-separated grouping columns; `project` when unset
+    pub group_by: Option<String>,
+    pub user_id: Option<String>,
+    pub project_id: Option<String>,
+    /// Fraction of requests reported, in `(0, 1]`
+    pub sample_rate: Option<f64>,
+    /// Fewest distinct users a published group may cover
+    pub min_group_size: Option<usize>,
+}
+
+/// Totals of one group of a chargeback report
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChargebackGroup {
+    /// The group's value of each `group_by` column; `null` for costs without a user or project
+    pub keys: Map<String, Value>,
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub total_tokens: u64,
+    pub cost_usd: f64,
+    /// Fraction of the report's total cost, between 0 and 1
+    pub share_of_cost: f64,
+}
+
+/// Body of `GET /v1/analytics/chargeback`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChargebackReport {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub group_by: Vec<String>,
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub total_tokens: u64,
+    pub cost_usd: f64,
+    /// Fraction of requests the totals were estimated from
+    pub sample_rate: f64,
+    /// Groups left out for covering too few users
+    pub suppressed_groups: u64,
+    /// Most expensive first
+    pub groups: Vec<ChargebackGroup>,
+}
+
+impl ChargebackReport {
+    /// Total the sampled `costs` into one group per distinct combination of the
+    /// `group_by` columns, leaving out groups `privacy` does not allow publishing
+    pub fn build(
+        costs: &[CostInfo],
+        group_by: &[GroupBy],
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        privacy: &ExportPrivacy,
+    ) -> Self {
+        let mut report = Self {
+            from,
+            to,
+            group_by: group_by
+                .iter()
+                .map(|group| group.column().to_string())
+                .collect(),
+            requests: 0,
+            input_tokens: 0,
+            output_tokens: 0,
+            total_tokens: 0,
+            cost_usd: 0.0,
+            sample_rate: privacy.sample_rate,
+            suppressed_groups: 0,
+            groups: Vec::new(),
+        };
+
+        // Keyed by the group's values rendered as JSON so ties keep a stable order
+        let mut groups: BTreeMap<String, (ChargebackGroup, HashSet<Option<String>>)> =
+            BTreeMap::new();
+        for cost in costs.iter().filter(|cost| privacy.sampled(cost)) {
+            let keys: Map<String, Value> = group_by
+                .iter()
+                .map(|group| (group.column().to_string(), group.value(cost)))
+                .collect();
+            let (group, users) = groups
+                .entry(Value::Object(keys.clone()).to_string())
+                .or_insert_with(|| {
+                    let group = ChargebackGroup {
+                        keys,
+                        requests: 0,
+                        input_tokens: 0,
+                        output_tokens: 0,
+                        total_tokens: 0,
+                        cost_usd: 0.0,
+                        share_of_cost: 0.0,
+                    };
+                    (group, HashSet::new())
+                });
+            group.requests += 1;
+            group.input_tokens += cost.input_tokens as u64;
+            group.output_tokens += cost.output_tokens as u64;
+            group.cost_usd += cost.cost_usd;
+            users.insert(cost.user_id.clone());
+        }
+
+        for (mut group, users) in groups.into_values() {
+            if !privacy.publishable(users.len()) {
+                report.suppressed_groups += 1;
+                continue;
+            }
+            group.requests = privacy.scale_count(group.requests);
+            group.input_tokens = privacy.scale_count(group.input_tokens);
+            group.output_tokens = privacy.scale_count(group.output_tokens);
+            group.total_tokens = group.input_tokens + group.output_tokens;
+            group.cost_usd *= privacy.scale();
+
+            report.requests += group.requests;
+            report.input_tokens += group.input_tokens;
+            report.output_tokens += group.output_tokens;
+            report.cost_usd += group.cost_usd;
+            report.groups.push(group);
+        }
+        report.total_tokens = report.input_tokens + report.output_tokens;
+
+        for group in &mut report.groups {
+            if report.cost_usd > 0.0 {
+                group.share_of_cost = group.cost_usd / report.cost_usd;
+            }
+        }
+        report
+            .groups
+            .sort_by(|a, b| b.cost_usd.total_cmp(&a.cost_usd));
+        report
+    }
+}
+
+/// Report recorded costs per group - GET /v1/analytics/chargeback
+pub async fn chargeback_report(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Query(query): Query<ChargebackQuery>,
+) -> Result<Json<ChargebackReport>, ErrorResponse> {
+    authorize_admin(&state, &headers, "Chargeback reports")?;
+
+    let group_by = GroupBy::parse_list(query.group_by.as_deref().unwrap_or("project"))
+        .map_err(|message| invalid_param(message, "group_by"))?;
+    if group_by.is_empty() {
+        return Err(invalid_param(
+            "'group_by' must name at least one column".to_string(),
+            "group_by",
+        ));
+    }
+    let (from, to) = parse_window(query.from.as_deref(), query.to.as_deref())?;
+    let privacy = ExportPrivacy::from_query(query.sample_rate, query.min_group_size)?;
+
+    let usage_tracker = state.cost_optimizer.read().await.usage_tracker();
+    let costs: Vec<CostInfo> = usage_tracker
+        .list_usage(from, to)
+        .await
+        .map_err(cost_error_response)?
+        .into_iter()
+        .filter(|cost| {
+            query
+                .user_id
+                .as_ref()
+                .is_none_or(|user_id| cost.user_id.as_ref() == Some(user_id))
+                && query
+                    .project_id
+                    .as_ref()
+                    .is_none_or(|project_id| cost.project_id.as_ref() == Some(project_id))
+        })
+        .collect();
+
+    Ok(Json(ChargebackReport::build(
+        &costs, &group_by, from, to, &privacy,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::LLMProviderType;
+    use uuid::Uuid;
+
+    fn cost(
+        user_id: Option<&str>,
+        project_id: Option<&str>,
+        model: &str,
+        cost_usd: f64,
+    ) -> CostInfo {
+        CostInfo {
+            request_id: Uuid::new_v4(),
+            provider: LLMProviderType::OpenAI,
+            model: model.to_string(),
+            input_tokens: 100,
+            output_tokens: 50,
+            cost_usd,
+            timestamp: Utc::now(),
+            user_id: user_id.map(str::to_string),
+            project_id: project_id.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_chargeback_groups_by_project_and_model() {
+        let costs = vec![
+            cost(Some("alice"), Some("search"), "gpt-4", 0.5),
+            cost(Some("bob"), Some("search"), "gpt-4", 0.25),
+            cost(Some("alice"), Some("support"), "gpt-4o-mini", 1.0),
+            cost(Some("carol"), None, "gpt-4", 0.25),
+        ];
+        let now = Utc::now();
+        let group_by = GroupBy::parse_list("project,model").unwrap();
+        let report =
+            ChargebackReport::build(&costs, &group_by, now, now, &ExportPrivacy::default());
+
+        assert_eq!(report.group_by, vec!["project_id", "model"]);
+        assert_eq!(report.requests, 4);
+        assert_eq!(report.total_tokens, 600);
+        assert_eq!(report.cost_usd, 2.0);
+
+        let groups: Vec<(Value, f64, u64)> = report
+            .groups
+            .iter()
+            .map(|group| {
+                (
+                    group.keys["project_id"].clone(),
+                    group.cost_usd,
+                    group.requests,
+                )
+            })
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                (Value::from("support"), 1.0, 1),
+                (Value::from("search"), 0.75, 2),
+                (Value::Null, 0.25, 1),
+            ]
+        );
+        assert_eq!(report.groups[1].share_of_cost, 0.375);
+        assert_eq!(report.groups[1].keys["model"], "gpt-4");
+
+        // Only `search` covers two users; the total leaves the others out
+        let privacy = ExportPrivacy::from_query(None, Some(2)).unwrap();
+        let report = ChargebackReport::build(&costs, &group_by, now, now, &privacy);
+        assert_eq!(report.suppressed_groups, 2);
+        assert_eq!(report.groups.len(), 1);
+        assert_eq!(report.cost_usd, 0.75);
+        assert_eq!(report.groups[0].share_of_cost, 1.0);
+    }
+
+    #[test]
+    fn test_chargeback_of_no_costs() {
+        let now = Utc::now();
+        let report =
+            ChargebackReport::build(&[], &[GroupBy::User], now, now, &ExportPrivacy::default());
+        assert!(report.groups.is_empty());
+        assert_eq!(report.cost_usd, 0.0);
+    }
+}
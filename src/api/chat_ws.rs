@@ -0,0 +1,409 @@
+// WebSocket transport for chat completion streaming
+// `/v1/chat/ws` carries the same completions as the SSE endpoint, as JSON frames
+
+//! # Chat Completions over WebSocket
+//!
+//! For clients that cannot rely on server-sent events (browsers behind proxies that
+//! buffer responses, mobile networks), `GET /v1/chat/ws` upgrades to a WebSocket on
+//! which up to [`MAX_COMPLETIONS_PER_SOCKET`] completions can run side by side. Every frame is a JSON text
+//! message tagged by `type`; the client picks an `id` per completion and every frame
+//! about that completion carries it.
+//!
+//! Client to server:
+//! - `{"type": "completion", "id": "c1", "request": {...}}` starts a completion; the
+//!   request is the body `POST /v1/chat/completions` takes and is always streamed
+//! - `{"type": "cancel", "id": "c1"}` stops a running completion
+//!
+//! Server to client:
+//! - `{"type": "chunk", "id": "c1", "chunk": {...}}` carries a `chat.completion.chunk`
+//! - `{"type": "done", "id": "c1"}` ends a completion
+//! - `{"type": "cancelled", "id": "c1"}` confirms a cancellation
+//! - `{"type": "error", "id": "c1", "error": {...}}` reports a failed completion, or a
+//!   malformed frame when there is no `id`. A completion started while the socket is
+//!   at its limit fails right away with a `rate_limit_error`
+//!
+//! Authentication, tenant and priority headers are taken from the upgrade request and
+//! apply to every completion on the socket. Closing the socket cancels whatever is
+//! still running.
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    http::HeaderMap,
+    response::Response,
+};
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{debug, info};
+
+use super::handlers::{
+    open_completion_stream, prepare_chat_completion, record_stream_usage, to_stream_response,
+    OpenAIApiState, StreamUsage,
+};
+use super::types::{
+    create_error_response, ChatCompletionRequest, ChatCompletionStreamResponse, ErrorDetail,
+    ErrorResponse,
+};
+use crate::ErrorCode;
+
+/// Frames buffered for a slow client before completions wait for it
+const FRAME_BUFFER: usize = 64;
+
+/// Completions one socket may have running at once
+pub const MAX_COMPLETIONS_PER_SOCKET: usize = 8;
+
+/// A frame sent by the client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientFrame {
+    /// Start streaming a completion
+    Completion {
+        id: String,
+        request: Box<ChatCompletionRequest>,
+    },
+    /// Stop a running completion
+    Cancel { id: String },
+}
+
+/// A frame sent by the server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerFrame {
+    Chunk {
+        id: String,
+        chunk: ChatCompletionStreamResponse,
+    },
+    Done {
+        id: String,
+    },
+    Cancelled {
+        id: String,
+    },
+    Error {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        error: ErrorDetail,
+    },
+}
+
+impl ServerFrame {
+    fn error(id: Option<String>, error: ErrorResponse) -> Self {
+        let error_code = error.error_code();
+        let mut error = error.error;
+        error.error_code = Some(error_code);
+        ServerFrame::Error { id, error }
+    }
+}
+
+/// Chat completions over WebSocket - GET /v1/chat/ws
+pub async fn chat_websocket(
+    ws: WebSocketUpgrade,
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+) -> Result<Response, ErrorResponse> {
+    // Extract API key (optional for some deployments)
+    let _api_key_info = state.extract_api_key(&headers).await?;
+
+    Ok(ws.on_upgrade(move |socket| serve_socket(socket, state, headers)))
+}
+
+async fn serve_socket(socket: WebSocket, state: OpenAIApiState, headers: HeaderMap) {
+    info!("Chat completion WebSocket connected");
+
+    let (mut sink, mut incoming) = socket.split();
+    let (frames, mut outgoing) = mpsc::channel::<ServerFrame>(FRAME_BUFFER);
+
+    let writer = tokio::spawn(async move {
+        while let Some(frame) = outgoing.recv().await {
+            let Ok(text) = serde_json::to_string(&frame) else {
+                continue;
+            };
+            if sink.send(Message::Text(text)).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut session = Session::new(state, headers, frames);
+    while let Some(message) = incoming.next().await {
+        match message {
+            Ok(Message::Text(text)) => session.handle(&text).await,
+            Ok(Message::Binary(_)) => {
+                session
+                    .send(ServerFrame::error(
+                        None,
+                        invalid_frame("Frames must be JSON text messages".to_string()),
+                    ))
+                    .await
+            }
+            Ok(Message::Close(_)) | Err(_) => break,
+            // Pings are answered by the WebSocket layer
+            Ok(_) => {}
+        }
+    }
+
+    session.cancel_all();
+    writer.abort();
+    info!("Chat completion WebSocket disconnected");
+}
+
+/// Completions running on one socket
+struct Session {
+    state: OpenAIApiState,
+    headers: HeaderMap,
+    frames: mpsc::Sender<ServerFrame>,
+    running: HashMap<String, JoinHandle<()>>,
+}
+
+impl Session {
+    fn new(state: OpenAIApiState, headers: HeaderMap, frames: mpsc::Sender<ServerFrame>) -> Self {
+        Self {
+            state,
+            headers,
+            frames,
+            running: HashMap::new(),
+        }
+    }
+
+    async fn send(&self, frame: ServerFrame) {
+        let _ = self.frames.send(frame).await;
+    }
+
+    async fn handle(&mut self, text: &str) {
+        self.running.retain(|_, task| !task.is_finished());
+
+        match serde_json::from_str::<ClientFrame>(text) {
+            Ok(ClientFrame::Completion { id, request }) => self.start(id, *request).await,
+            Ok(ClientFrame::Cancel { id }) => self.cancel(id).await,
+            Err(e) => {
+                self.send(ServerFrame::error(
+                    None,
+                    invalid_frame(format!("Invalid frame: {}", e)),
+                ))
+                .await
+            }
+        }
+    }
+
+    async fn start(&mut self, id: String, request: ChatCompletionRequest) {
+        if self.running.contains_key(&id) {
+            let error = invalid_frame(format!("Completion '{}' is already running", id));
+            return self.send(ServerFrame::error(Some(id), error)).await;
+        }
+        if self.running.len() >= MAX_COMPLETIONS_PER_SOCKET {
+            let error = create_error_response(
+                format!(
+                    "At most {} completions may run at once on a socket",
+                    MAX_COMPLETIONS_PER_SOCKET
+                ),
+                "rate_limit_error".to_string(),
+                None,
+                Some("too_many_completions".to_string()),
+            )
+            .with_error_code(ErrorCode::RateLimited);
+            return self.send(ServerFrame::error(Some(id), error)).await;
+        }
+        // Completions count as writes, like POST /v1/chat/completions
+        if self.state.maintenance.is_enabled() {
+            let error = create_error_response(
+                self.state.maintenance.status().message(),
+                "service_unavailable_error".to_string(),
+                None,
+                Some("maintenance_mode".to_string()),
+            )
+            .with_error_code(ErrorCode::MaintenanceMode);
+            return self.send(ServerFrame::error(Some(id), error)).await;
+        }
+
+        debug!(
+            "Starting WebSocket completion {} for model: {}",
+            id, request.model
+        );
+        let state = self.state.clone();
+        let headers = self.headers.clone();
+        let frames = self.frames.clone();
+        let task_id = id.clone();
+        let task = tokio::spawn(async move {
+            let frame = match run_completion(&state, &headers, &request, &task_id, &frames).await {
+                Ok(()) => ServerFrame::Done { id: task_id },
+                Err(error) => ServerFrame::error(Some(task_id), error),
+            };
+            let _ = frames.send(frame).await;
+        });
+        self.running.insert(id, task);
+    }
+
+    async fn cancel(&mut self, id: String) {
+        match self.running.remove(&id) {
+            Some(task) if !task.is_finished() => {
+                // Dropping the provider stream stops generation
+                task.abort();
+                debug!("Cancelled WebSocket completion {}", id);
+                self.send(ServerFrame::Cancelled { id }).await
+            }
+            _ => {
+                let error = invalid_frame(format!("No running completion '{}'", id));
+                self.send(ServerFrame::error(Some(id), error)).await
+            }
+        }
+    }
+
+    fn cancel_all(&mut self) {
+        for (_, task) in self.running.drain() {
+            task.abort();
+        }
+    }
+}
+
+/// Stream one completion as chunk frames
+async fn run_completion(
+    state: &OpenAIApiState,
+    headers: &HeaderMap,
+    request: &ChatCompletionRequest,
+    id: &str,
+    frames: &mpsc::Sender<ServerFrame>,
+) -> Result<(), ErrorResponse> {
+    let prepared = prepare_chat_completion(state, headers, request).await?;
+    let mut usage =
+        StreamUsage::new(&prepared.llm_request).with_project(prepared.project_id.clone());
+    let mut filter = state.stream_filters.stage();
+    let mut stream = open_completion_stream(state, prepared).await?;
+
+    while let Some(chunk_result) = stream.next().await {
+        let chunk = chunk_result.map_err(|e| {
+            create_error_response(e.to_string(), "stream_error".to_string(), None, None)
+                .with_error_code(ErrorCode::ProviderError)
+        })?;
+        usage.observe(&chunk);
+        if chunk.choices.is_empty() {
+            continue;
+        }
+        let Some(chunk) = filter.process(chunk) else {
+            continue;
+        };
+        let frame = ServerFrame::Chunk {
+            id: id.to_string(),
+            chunk: to_stream_response(chunk),
+        };
+        if frames.send(frame).await.is_err() {
+            // The socket is gone
+            return Ok(());
+        }
+        if let Some(termination) = filter.termination() {
+            debug!(
+                "WebSocket completion {} terminated by content filter '{}': {}",
+                id, termination.filter, termination.reason
+            );
+            break;
+        }
+    }
+    if let Some(held_back) = filter.flush() {
+        let frame = ServerFrame::Chunk {
+            id: id.to_string(),
+            chunk: to_stream_response(held_back),
+        };
+        let _ = frames.send(frame).await;
+    }
+    record_stream_usage(state, &usage).await;
+
+    if request.include_usage() {
+        let frame = ServerFrame::Chunk {
+            id: id.to_string(),
+            chunk: usage.usage_chunk(state).await,
+        };
+        let _ = frames.send(frame).await;
+    }
+    Ok(())
+}
+
+fn invalid_frame(message: String) -> ErrorResponse {
+    create_error_response(message, "invalid_request_error".to_string(), None, None)
+        .with_error_code(ErrorCode::InvalidInput)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> (Session, mpsc::Receiver<ServerFrame>) {
+        let (frames, received) = mpsc::channel(FRAME_BUFFER);
+        let session = Session::new(OpenAIApiState::new(), HeaderMap::new(), frames);
+        (session, received)
+    }
+
+    #[test]
+    fn test_frame_format() {
+        let frame: ClientFrame = serde_json::from_str(
+            r#"{"type": "completion", "id": "c1", "request": {"model": "gpt-4", "messages": [{"role": "user", "content": "Hi"}]}}"#,
+        )
+        .unwrap();
+        assert!(matches!(frame, ClientFrame::Completion { ref id, .. } if id == "c1"));
+
+        let frame: ClientFrame = serde_json::from_str(r#"{"type": "cancel", "id": "c1"}"#).unwrap();
+        assert!(matches!(frame, ClientFrame::Cancel { ref id } if id == "c1"));
+
+        let done = serde_json::to_value(ServerFrame::Done {
+            id: "c1".to_string(),
+        })
+        .unwrap();
+        assert_eq!(done, serde_json::json!({"type": "done", "id": "c1"}));
+    }
+
+    #[tokio::test]
+    async fn test_errors_are_reported_per_completion() {
+        let (mut session, mut received) = session();
+
+        session.handle("not json").await;
+        match received.recv().await.unwrap() {
+            ServerFrame::Error { id, error } => {
+                assert_eq!(id, None);
+                assert_eq!(error.error_code, Some(ErrorCode::InvalidInput));
+            }
+            other => panic!("unexpected frame: {:?}", other),
+        }
+
+        session
+            .handle(r#"{"type": "completion", "id": "c1", "request": {"model": "no-such-model", "messages": [{"role": "user", "content": "Hi"}]}}"#)
+            .await;
+        match received.recv().await.unwrap() {
+            ServerFrame::Error { id, error } => {
+                assert_eq!(id.as_deref(), Some("c1"));
+                assert_eq!(error.error_code, Some(ErrorCode::ModelNotFound));
+            }
+            other => panic!("unexpected frame: {:?}", other),
+        }
+
+        session.handle(r#"{"type": "cancel", "id": "c2"}"#).await;
+        match received.recv().await.unwrap() {
+            ServerFrame::Error { id, .. } => assert_eq!(id.as_deref(), Some("c2")),
+            other => panic!("unexpected frame: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_completions_per_socket_are_bounded() {
+        let (mut session, mut received) = session();
+        for n in 0..MAX_COMPLETIONS_PER_SOCKET {
+            let task = tokio::spawn(futures::future::pending::<()>());
+            session.running.insert(format!("c{}", n), task);
+        }
+
+        session
+            .handle(r#"{"type": "completion", "id": "extra", "request": {"model": "gpt-4", "messages": [{"role": "user", "content": "Hi"}]}}"#)
+            .await;
+        match received.recv().await.unwrap() {
+            ServerFrame::Error { id, error } => {
+                assert_eq!(id.as_deref(), Some("extra"));
+                assert_eq!(error.error_code, Some(ErrorCode::RateLimited));
+            }
+            other => panic!("unexpected frame: {:?}", other),
+        }
+        assert!(!session.running.contains_key("extra"));
+        session.cancel_all();
+    }
+}
@@ -3,7 +3,8 @@
 
 use axum::{
     extract::State,
-    http::{header, HeaderMap, StatusCode},
+    http::{header, HeaderMap, HeaderValue, Method, Request, StatusCode},
+    middleware::Next,
     response::{IntoResponse, Response},
     Json,
 };
@@ -13,18 +14,58 @@ use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, sync::Arc};
 use tokio::sync::RwLock;
 use tracing::{debug, error, info};
+use uuid::Uuid;
 
+use super::annotations::{self, ResponseAnnotations};
+use super::log_stream::{self, LogFilter, LogStreamQuery};
+use super::tenants::{TenantDirectory, API_KEY_PREFIX};
 use super::types::{
     create_error_response, current_timestamp, generate_completion_id, get_virtual_models,
     is_virtual_model, ChatCompletionChoice, ChatCompletionRequest, ChatCompletionResponse,
     ChatCompletionStreamChoice, ChatCompletionStreamResponse, ChatMessage, ChatMessageDelta,
-    ChatRole, CircuitBreakerConfig, EmbeddingObject, EmbeddingsInput, EmbeddingsRequest,
-    EmbeddingsResponse, EmbeddingsUsage, ErrorResponse, Model, ModelsResponse, Usage,
+    ChatRole, CircuitBreakerConfig, EmbeddingObject, EmbeddingVector, EmbeddingsInput,
+    EmbeddingsRequest, EmbeddingsResponse, EmbeddingsUsage, ErrorResponse, Model, ModelsResponse,
+    TokenizeRequest, TokenizeResponse, ToolCallDelta, Usage,
 };
+use super::usage_export::invalid_param;
+use crate::api_keys::ApiKeys;
+use crate::audit::{AuditLog, AuthSurface, SecurityEvent, SecurityEventKind};
+use crate::audit_trail::{AuditCategory, AuditOutcome, AuditQuery, AuditRecord};
+use crate::auth_throttle::{client_ip, AuthThrottle, Lockout, ADMIN_TOKEN_PRINCIPAL};
+use crate::engine::service_accounts::ServiceAccounts;
+use crate::llm::audio::AudioProviders;
+use crate::llm::images::ImageProviders;
+use crate::llm::moderation::ModerationProviders;
+use crate::llm::sse::{EventId, ResumableStream, StreamRegistry, ABANDONED_STREAM_GRACE};
+use crate::llm::stream_filter::StreamFilters;
+use crate::llm::tokenizer::{self, Tokenizer};
 use crate::llm::{
-    cost::CostOptimizer, EmbeddingsInput as LLMEmbeddingsInput,
-    EmbeddingsRequest as LLMEmbeddingsRequest, LLMProviderType, LLMRequest, LLMRouter, MessageRole,
+    cost::{BudgetPreflight, CostContext, CostError, CostOptimizer},
+    sampling, CostInfo, EmbeddingsInput as LLMEmbeddingsInput,
+    EmbeddingsRequest as LLMEmbeddingsRequest, KeyValidation, LLMError, LLMProviderType,
+    LLMRequest, LLMResponse, LLMRouter, MessageRole, ModelCapability, RequestPriority,
+    RoutingTrace, StreamingChunk, TenantId, TenantRoutingPolicy,
 };
+use crate::rbac::RoleAssignments;
+use crate::{ErrorCode, MaintenanceMode, MaintenanceStatus};
+
+/// Header carrying the tenant a request belongs to
+pub const TENANT_ID_HEADER: &str = "x-tenant-id";
+
+/// Header selecting the queue priority class of a request
+pub const REQUEST_PRIORITY_HEADER: &str = "x-request-priority";
+
+/// Header carrying the request ID used to look up its routing trace
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Header a reconnecting client sends with the ID of the last stream event it received
+pub const LAST_EVENT_ID_HEADER: &str = "last-event-id";
+
+/// Header warning that a request took its budget past the soft limit
+pub const BUDGET_WARNING_HEADER: &str = "x-budget-warning";
+
+/// Header carrying the budget left, in USD, once a request's estimated cost is spent
+pub const BUDGET_REMAINING_HEADER: &str = "x-budget-remaining";
 
 /// Shared application state for the OpenAI API
 #[derive(Clone)]
@@ -33,6 +74,32 @@ pub struct OpenAIApiState {
     pub cost_optimizer: Arc<RwLock<CostOptimizer>>,
     pub api_keys: Arc<RwLock<HashMap<String, ApiKeyInfo>>>,
     pub models: Arc<RwLock<Vec<ModelConfig>>>,
+    /// Bearer token required by admin endpoints such as the live log stream
+    pub admin_token: Option<String>,
+    /// Read-only switch checked before every write
+    pub maintenance: MaintenanceMode,
+    /// Content filters applied to streamed completions before chunks reach the client
+    pub stream_filters: StreamFilters,
+    /// Registered tenants
+    pub tenants: TenantDirectory,
+    /// Scoped machine identities and their tokens
+    pub service_accounts: ServiceAccounts,
+    /// Managed API keys required when `api_key_required` is on
+    pub managed_keys: ApiKeys,
+    /// Roles of API keys, service accounts and MCP installations
+    pub roles: RoleAssignments,
+    /// Lockouts after repeated failed API key and admin token attempts
+    pub auth_throttle: AuthThrottle,
+    /// Security events, served to operators
+    pub audit_log: AuditLog,
+    /// Providers answering `/v1/moderations`
+    pub moderation: ModerationProviders,
+    /// Providers answering `/v1/audio/transcriptions` and `/v1/audio/speech`
+    pub audio: AudioProviders,
+    /// Providers answering `/v1/images/generations`
+    pub images: ImageProviders,
+    /// Whether chat completions are moderated before they are routed
+    pub moderate_chat_completions: bool,
 }
 
 /// API key information
@@ -43,13 +110,18 @@ pub struct ApiKeyInfo {
     pub usage_limits: Option<UsageLimits>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub last_used: Option<chrono::DateTime<chrono::Utc>>,
+    /// Tenant the key was issued to; requests made with it belong to that tenant
+    pub tenant_id: Option<TenantId>,
 }
 
 /// Usage limits for API keys
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct UsageLimits {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub daily_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub monthly_cost: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub rate_limit_per_minute: Option<u32>,
 }
 
@@ -62,6 +134,9 @@ pub struct ModelConfig {
     pub context_window: u32,
     pub max_output_tokens: u32,
     pub supports_streaming: bool,
+    /// Whether the model accepts image input
+    #[serde(default)]
+    pub supports_vision: bool,
     pub cost_per_input_token: f64,
     pub cost_per_output_token: f64,
 }
@@ -87,11 +162,28 @@ impl OpenAIApiState {
         // Initialize with empty models - will be populated by refresh_models()
         let models = Arc::new(RwLock::new(Vec::new()));
 
+        let admin_token = std::env::var(super::log_stream::ADMIN_TOKEN_ENV)
+            .ok()
+            .filter(|token| !token.is_empty());
+
         Self {
             llm_router,
             cost_optimizer,
             api_keys,
             models,
+            admin_token,
+            maintenance: MaintenanceMode::global(),
+            stream_filters: StreamFilters::from_env(),
+            tenants: TenantDirectory::default(),
+            service_accounts: ServiceAccounts::global(),
+            managed_keys: ApiKeys::global(),
+            roles: RoleAssignments::global(),
+            auth_throttle: AuthThrottle::global(),
+            audit_log: AuditLog::global(),
+            moderation: ModerationProviders::from_env(),
+            audio: AudioProviders::from_env(),
+            images: ImageProviders::from_env(),
+            moderate_chat_completions: false,
         }
     }
 
@@ -106,6 +198,7 @@ impl OpenAIApiState {
                 context_window: 200000, // Max context for virtual models
                 max_output_tokens: 4096,
                 supports_streaming: true,
+                supports_vision: false,
                 cost_per_input_token: virtual_model.max_cost.unwrap_or(0.000001),
                 cost_per_output_token: virtual_model.max_cost.unwrap_or(0.000002),
             });
@@ -130,6 +223,7 @@ impl OpenAIApiState {
                     context_window: model.context_window,
                     max_output_tokens: model.max_tokens,
                     supports_streaming: model.supports_streaming,
+                    supports_vision: model.capabilities.contains(&ModelCapability::Vision),
                     cost_per_input_token: model.cost_per_input_token,
                     cost_per_output_token: model.cost_per_output_token,
                 });
@@ -151,7 +245,7 @@ impl OpenAIApiState {
     }
 
     /// Extract API key from headers
-    async fn extract_api_key(
+    pub(crate) async fn extract_api_key(
         &self,
         headers: &HeaderMap,
     ) -> Result<Option<ApiKeyInfo>, ErrorResponse> {
@@ -160,14 +254,119 @@ impl OpenAIApiState {
             .and_then(|h| h.to_str().ok())
             .and_then(|s| s.strip_prefix("Bearer "));
 
-        if let Some(token) = auth_header {
-            let api_keys = self.api_keys.read().await;
-            Ok(api_keys.get(token).cloned())
-        } else {
-            Ok(None)
+        let Some(token) = auth_header else {
+            return Ok(None);
+        };
+        // Other bearer tokens are provider keys, not guesses at ours
+        if !token.starts_with(API_KEY_PREFIX) {
+            return Ok(self.api_keys.read().await.get(token).cloned());
+        }
+
+        let ip = client_ip(headers);
+        self.auth_throttle
+            .check(ip.as_deref(), None)
+            .map_err(locked_out)?;
+        if let Some(key) = self.api_keys.read().await.get(token) {
+            return Ok(Some(key.clone()));
+        }
+        let lockout = self.auth_throttle.record_failure(
+            AuthSurface::ApiKey,
+            ip.as_deref(),
+            None,
+            "Unknown or revoked API key",
+        );
+        Err(lockout.map(locked_out).unwrap_or_else(|| {
+            create_error_response(
+                "Invalid API key".to_string(),
+                "authentication_error".to_string(),
+                None,
+                Some("invalid_api_key".to_string()),
+            )
+            .with_error_code(ErrorCode::AuthenticationFailed)
+        }))
+    }
+
+    /// Tenant a request belongs to: the tenant its API key was issued to; the header is
+    /// only trusted for keys not scoped to a tenant, and a key scoped to one tenant may
+    /// not name another
+    pub(crate) async fn request_tenant(
+        &self,
+        headers: &HeaderMap,
+    ) -> Result<Option<TenantId>, ErrorResponse> {
+        let header_tenant = Self::extract_tenant_id(headers);
+        let key_tenant = self
+            .extract_api_key(headers)
+            .await?
+            .and_then(|key| key.tenant_id);
+        match (key_tenant, header_tenant) {
+            (Some(key_tenant), Some(header_tenant)) if key_tenant != header_tenant => {
+                Err(create_error_response(
+                    format!(
+                        "API key is scoped to tenant '{}' and cannot act for tenant '{}'",
+                        key_tenant, header_tenant
+                    ),
+                    "permission_error".to_string(),
+                    Some(TENANT_ID_HEADER.to_string()),
+                    Some("tenant_mismatch".to_string()),
+                ))
+            }
+            (Some(key_tenant), _) => Ok(Some(key_tenant)),
+            (None, header_tenant) => Ok(header_tenant),
         }
     }
 
+    /// Record the cost of a request priced by something other than tokens, such as
+    /// minutes of audio or images, under its user and tenant
+    pub(crate) async fn record_flat_cost(
+        &self,
+        provider: LLMProviderType,
+        model: &str,
+        cost_usd: f64,
+        user_id: Option<String>,
+        tenant_id: Option<TenantId>,
+    ) {
+        debug!("Request with {} cost ${:.4}", model, cost_usd);
+        self.cost_optimizer
+            .read()
+            .await
+            .record_actual_cost(CostInfo {
+                request_id: Uuid::new_v4(),
+                provider,
+                model: model.to_string(),
+                input_tokens: 0,
+                output_tokens: 0,
+                cost_usd,
+                timestamp: chrono::Utc::now(),
+                user_id,
+                project_id: tenant_id.map(|tenant_id| tenant_id.to_string()),
+            })
+            .await;
+    }
+
+    /// Extract the tenant ID from headers
+    fn extract_tenant_id(headers: &HeaderMap) -> Option<TenantId> {
+        headers
+            .get(TENANT_ID_HEADER)
+            .and_then(|h| h.to_str().ok())
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(TenantId::new)
+    }
+
+    /// Resolve the queue priority of a chat request.
+    /// The header wins over the request body; chat traffic defaults to interactive.
+    fn request_priority(
+        headers: &HeaderMap,
+        cb_config: Option<&CircuitBreakerConfig>,
+    ) -> RequestPriority {
+        headers
+            .get(REQUEST_PRIORITY_HEADER)
+            .and_then(|h| h.to_str().ok())
+            .and_then(RequestPriority::parse)
+            .or_else(|| cb_config.and_then(|config| config.priority))
+            .unwrap_or(RequestPriority::Interactive)
+    }
+
     /// Get model configuration by ID
     async fn get_model(&self, model_id: &str) -> Option<ModelConfig> {
         let models = self.models.read().await;
@@ -216,6 +415,10 @@ pub async fn list_models(
                     "supports_streaming".to_string(),
                     serde_json::Value::Bool(config.supports_streaming),
                 ),
+                (
+                    "supports_vision".to_string(),
+                    serde_json::Value::Bool(config.supports_vision),
+                ),
             ]),
         })
         .collect();
@@ -226,6 +429,31 @@ pub async fn list_models(
     }))
 }
 
+/// Reject images that cannot be sent, or that are sent to a model without vision
+fn validate_image_input(
+    request: &LLMRequest,
+    model_config: Option<&ModelConfig>,
+) -> Result<(), ErrorResponse> {
+    let invalid = |message: String| {
+        create_error_response(
+            message,
+            "invalid_request_error".to_string(),
+            Some("messages".to_string()),
+            None,
+        )
+        .with_error_code(ErrorCode::InvalidInput)
+    };
+
+    request.validate_images().map_err(invalid)?;
+    match model_config {
+        Some(config) if !config.supports_vision => Err(invalid(format!(
+            "Model '{}' does not accept image input",
+            config.id
+        ))),
+        _ => Ok(()),
+    }
+}
+
 /// Chat completions endpoint - POST /v1/chat/completions
 pub async fn chat_completions(
     State(state): State<OpenAIApiState>,
@@ -240,6 +468,94 @@ pub async fn chat_completions(
     // Extract API key (optional for some deployments)
     let _api_key_info = state.extract_api_key(&headers).await?;
 
+    // A client that lost its connection picks the stream back up instead of
+    // generating the completion again
+    if request.stream {
+        if let Some(last_event_id) = headers
+            .get(LAST_EVENT_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+        {
+            return resume_stream(last_event_id);
+        }
+    }
+
+    let prepared = prepare_chat_completion(&state, &headers, &request).await?;
+    let request_id = prepared.llm_request.id;
+    let budget = prepared.budget.clone();
+    let router = state.llm_router.clone();
+
+    // Check if streaming is requested
+    let mut response = if request.stream {
+        handle_streaming_completion(state, request, prepared).await
+    } else {
+        let PreparedCompletion {
+            llm_request,
+            cb_config,
+            use_smart_routing,
+            project_id,
+            ..
+        } = prepared;
+        if use_smart_routing {
+            handle_smart_regular_completion(state, request, cb_config, llm_request, project_id)
+                .await
+        } else {
+            let model_config = state.get_model(&request.model).await.unwrap();
+            handle_regular_completion(state, request, model_config, llm_request, project_id).await
+        }
+    }?;
+
+    // Let the caller look up how this request was routed
+    if let Ok(value) = header::HeaderValue::from_str(&request_id.to_string()) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    if let Some(budget) = budget.filter(|budget| budget.is_warning) {
+        insert_budget_warning(response.headers_mut(), &budget);
+    }
+    if annotations::requested(&headers) {
+        if let Some(trace) = router.routing_trace(&request_id).await {
+            annotations::insert(response.headers_mut(), &ResponseAnnotations::from(&trace));
+        }
+    }
+    Ok(response)
+}
+
+/// Warn that a request took its budget past the soft limit
+fn insert_budget_warning(headers: &mut HeaderMap, budget: &BudgetPreflight) {
+    let warning = format!(
+        "{:.1}% of budget {} used",
+        budget.projected_percentage * 100.0,
+        budget.status.budget_id
+    );
+    if let Ok(value) = HeaderValue::from_str(&warning) {
+        headers.insert(BUDGET_WARNING_HEADER, value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&format!("{:.4}", budget.projected_remaining())) {
+        headers.insert(BUDGET_REMAINING_HEADER, value);
+    }
+}
+
+/// A chat completion request validated and converted for the router
+pub(crate) struct PreparedCompletion {
+    pub llm_request: LLMRequest,
+    pub cb_config: Option<CircuitBreakerConfig>,
+    /// Virtual models and requests carrying Circuit Breaker options use smart routing
+    pub use_smart_routing: bool,
+    /// Project the request is billed to: its tenant
+    pub project_id: Option<String>,
+    /// The request's budget, when one applies and has room for it
+    pub budget: Option<BudgetPreflight>,
+}
+
+/// Validate a chat completion request and convert it for the router: the model must
+/// exist and accept any images sent, the header-supplied priority, request ID and
+/// the tenant's routing policy are applied, and the request must fit its budget
+pub(crate) async fn prepare_chat_completion(
+    state: &OpenAIApiState,
+    headers: &HeaderMap,
+    request: &ChatCompletionRequest,
+) -> Result<PreparedCompletion, ErrorResponse> {
+    let tenant_id = state.request_tenant(headers).await?;
+
     // Extract Circuit Breaker config from request
     let cb_config = request.circuit_breaker.clone();
 
@@ -247,62 +563,267 @@ pub async fn chat_completions(
     let use_smart_routing = cb_config.is_some() || is_virtual_model(&request.model);
 
     // For non-virtual models, validate they exist
-    if !is_virtual_model(&request.model) {
-        let _model_config = state.get_model(&request.model).await.ok_or_else(|| {
+    let model_config = if is_virtual_model(&request.model) {
+        None
+    } else {
+        Some(state.get_model(&request.model).await.ok_or_else(|| {
             create_error_response(
                 format!("Model '{}' not found", request.model),
                 "invalid_request_error".to_string(),
                 Some("model".to_string()),
                 None,
             )
-        })?;
+            .with_error_code(crate::ErrorCode::ModelNotFound)
+        })?)
+    };
+
+    if state.moderate_chat_completions {
+        super::moderations::moderate_chat_messages(state, &request.messages).await?;
+    }
+
+    if request
+        .n
+        .is_some_and(|n| n == 0 || n > sampling::MAX_CHOICES)
+    {
+        return Err(create_error_response(
+            format!("'n' must be between 1 and {}", sampling::MAX_CHOICES),
+            "invalid_request_error".to_string(),
+            Some("n".to_string()),
+            None,
+        )
+        .with_error_code(ErrorCode::InvalidInput));
     }
 
     // Convert to internal request format
-    let llm_request: LLMRequest = request.clone().into();
+    let mut llm_request: LLMRequest = request.clone().into();
+    if llm_request.has_images() {
+        validate_image_input(&llm_request, model_config.as_ref())?;
+    }
+    OpenAIApiState::request_priority(headers, cb_config.as_ref()).apply_to(&mut llm_request);
+    if state.moderate_chat_completions {
+        state
+            .llm_router
+            .record_guardrail(&llm_request, "Input passed moderation")
+            .await;
+    }
 
-    // Check if streaming is requested
-    if request.stream {
-        if use_smart_routing {
-            handle_smart_streaming_completion(state, request, cb_config, llm_request).await
-        } else {
-            let model_config = state.get_model(&request.model).await.unwrap();
-            handle_streaming_completion(state, request, model_config, llm_request).await
+    // Apply the tenant's routing overrides (strategy, provider and model allowlists)
+    let llm_request = match &tenant_id {
+        Some(tenant_id) => state
+            .llm_router
+            .apply_tenant_policy(llm_request, tenant_id)
+            .await
+            .map_err(|e| llm_error_response(&e, e.to_string()))?,
+        None => llm_request,
+    };
+
+    let project_id = tenant_id.map(|tenant_id| tenant_id.to_string());
+    let budget = preflight_budget(state, &llm_request, project_id.as_deref()).await?;
+
+    Ok(PreparedCompletion {
+        llm_request,
+        cb_config,
+        use_smart_routing,
+        project_id,
+        budget,
+    })
+}
+
+/// Estimate the most a request can cost - its prompt plus `max_tokens` of output,
+/// defaulting to the model's output limit - and refuse it when that would exceed the
+/// budget of its user or project
+async fn preflight_budget(
+    state: &OpenAIApiState,
+    llm_request: &LLMRequest,
+    project_id: Option<&str>,
+) -> Result<Option<BudgetPreflight>, ErrorResponse> {
+    if llm_request.user.is_none() && project_id.is_none() {
+        return Ok(None);
+    }
+
+    let model_config = state.get_model(&llm_request.model).await;
+    let prompt_tokens = estimate_prompt_tokens(llm_request);
+    // Every sampled choice produces its own output
+    let max_output_tokens = llm_request.max_tokens.unwrap_or_else(|| {
+        model_config
+            .as_ref()
+            .map_or(DEFAULT_EXPECTED_OUTPUT_TOKENS, |config| {
+                config.max_output_tokens
+            })
+    }) * sampling::choices(llm_request);
+    let estimated_cost = estimate_cost(
+        state,
+        model_config.as_ref().map(|config| &config.provider),
+        &llm_request.model,
+        prompt_tokens,
+        max_output_tokens,
+    )
+    .await;
+
+    let context = CostContext {
+        user_id: llm_request.user.clone().unwrap_or_default(),
+        project_id: project_id.map(str::to_string),
+        request_size: prompt_tokens,
+        expected_output_tokens: max_output_tokens,
+        current_time: chrono::Utc::now(),
+    };
+    let budget_manager = state.cost_optimizer.read().await.budget_manager();
+    budget_manager
+        .preflight(&context, estimated_cost)
+        .await
+        .map_err(|e| {
+            info!("Refused request {}: {}", llm_request.id, e);
+            cost_error_response(e)
+        })
+}
+
+/// Output tokens assumed for requests to models without a known output limit
+const DEFAULT_EXPECTED_OUTPUT_TOKENS: u32 = 1000;
+
+/// Prompt tokens of a request, counted with its model's tokenizer
+fn estimate_prompt_tokens(request: &LLMRequest) -> u32 {
+    tokenizer::count_prompt_tokens(&request.model, &request.messages)
+}
+
+/// Estimated cost of a number of tokens, priced by the cost module and falling back to
+/// the model's configured prices
+async fn estimate_cost(
+    state: &OpenAIApiState,
+    provider: Option<&LLMProviderType>,
+    model: &str,
+    prompt_tokens: u32,
+    completion_tokens: u32,
+) -> f64 {
+    if let Some(provider) = provider {
+        let estimate = state
+            .cost_optimizer
+            .read()
+            .await
+            .estimate_cost(provider, model, prompt_tokens, completion_tokens)
+            .await;
+        if let Ok(estimate) = estimate {
+            return estimate.total_cost;
         }
-    } else {
-        if use_smart_routing {
-            handle_smart_regular_completion(state, request, cb_config, llm_request).await
-        } else {
-            let model_config = state.get_model(&request.model).await.unwrap();
-            handle_regular_completion(state, request, model_config, llm_request).await
+    }
+    state.get_model(model).await.map_or(0.0, |config| {
+        prompt_tokens as f64 * config.cost_per_input_token
+            + completion_tokens as f64 * config.cost_per_output_token
+    })
+}
+
+/// Record what a completed request cost with the cost optimizer, returning the cost
+async fn record_completion_cost(
+    state: &OpenAIApiState,
+    llm_request: &LLMRequest,
+    project_id: Option<String>,
+    provider: LLMProviderType,
+    model: &str,
+    prompt_tokens: u32,
+    completion_tokens: u32,
+) -> f64 {
+    let cost_usd = estimate_cost(
+        state,
+        Some(&provider),
+        model,
+        prompt_tokens,
+        completion_tokens,
+    )
+    .await;
+    debug!("Estimated cost: ${:.4}", cost_usd);
+    state
+        .cost_optimizer
+        .read()
+        .await
+        .record_actual_cost(CostInfo {
+            request_id: llm_request.id,
+            provider,
+            model: model.to_string(),
+            input_tokens: prompt_tokens,
+            output_tokens: completion_tokens,
+            cost_usd,
+            timestamp: chrono::Utc::now(),
+            user_id: llm_request.user.clone(),
+            project_id,
+        })
+        .await;
+    cost_usd
+}
+
+/// Convert a cost optimization error into an OpenAI-style error response
+pub(crate) fn cost_error_response(error: CostError) -> ErrorResponse {
+    let error_code = error.code();
+    match error {
+        CostError::QuotaExceeded(quota) => {
+            let mut response = create_error_response(
+                format!("Quota exceeded: {}", quota),
+                "insufficient_quota".to_string(),
+                None,
+                Some("quota_exceeded".to_string()),
+            );
+            response.error.quota = Some(quota);
+            response.with_error_code(error_code)
         }
+        error => create_error_response(error.to_string(), "internal_error".to_string(), None, None)
+            .with_error_code(error_code),
     }
 }
 
+/// Convert the router's choices to OpenAI format, one per sampled choice
+fn completion_choices(response: &LLMResponse) -> Vec<ChatCompletionChoice> {
+    response
+        .choices
+        .iter()
+        .map(|choice| ChatCompletionChoice {
+            index: choice.index,
+            message: ChatMessage {
+                role: ChatRole::Assistant,
+                content: choice.message.content.clone().into(),
+                name: None,
+                tool_calls: choice
+                    .message
+                    .tool_calls
+                    .clone()
+                    .map(|calls| calls.into_iter().map(Into::into).collect()),
+                tool_call_id: None,
+            },
+            finish_reason: choice.finish_reason.clone(),
+            logprobs: None,
+        })
+        .collect()
+}
+
 /// Handle regular (non-streaming) chat completion
 async fn handle_regular_completion(
     state: OpenAIApiState,
     request: ChatCompletionRequest,
     model_config: ModelConfig,
     llm_request: LLMRequest,
+    project_id: Option<String>,
 ) -> Result<Response, ErrorResponse> {
     info!("Processing regular completion for model: {}", request.model);
 
     // Route the request through the LLM router
     let response = state
         .llm_router
-        .chat_completion(llm_request)
+        .chat_completion(llm_request.clone())
         .await
         .map_err(|e| {
             error!("LLM routing failed: {}", e);
-            create_error_response(
-                format!("Failed to process request: {}", e),
-                "internal_error".to_string(),
-                None,
-                None,
-            )
+            llm_error_response(&e, format!("Failed to process request: {}", e))
         })?;
 
+    // Track costs
+    let cost_usd = record_completion_cost(
+        &state,
+        &llm_request,
+        project_id,
+        model_config.provider,
+        &model_config.id,
+        response.usage.prompt_tokens,
+        response.usage.completion_tokens,
+    )
+    .await;
+
     // Convert to OpenAI format
     let completion_id = generate_completion_id();
     let created = current_timestamp();
@@ -312,131 +833,395 @@ async fn handle_regular_completion(
         object: "chat.completion".to_string(),
         created,
         model: request.model.clone(),
-        choices: vec![ChatCompletionChoice {
-            index: 0,
-            message: ChatMessage {
-                role: ChatRole::Assistant,
-                content: response
-                    .choices
-                    .first()
-                    .map(|c| c.message.content.clone())
-                    .unwrap_or_default(),
-                name: None,
-                tool_calls: None,
-                tool_call_id: None,
-            },
-            finish_reason: response
-                .choices
-                .first()
-                .and_then(|c| c.finish_reason.clone()),
-            logprobs: None,
-        }],
+        choices: completion_choices(&response),
         usage: Usage {
             prompt_tokens: response.usage.prompt_tokens,
             completion_tokens: response.usage.completion_tokens,
             total_tokens: response.usage.total_tokens,
+            estimated_cost: Some(cost_usd),
         },
         system_fingerprint: Some("circuit-breaker-v1".to_string()),
+        request_id: Some(llm_request.id),
+        routing_info: Some(response.routing_info.clone()),
     };
 
-    // Track costs
-    {
-        let _cost_optimizer = state.cost_optimizer.write().await;
-        let estimated_cost = (response.usage.prompt_tokens as f64
-            * model_config.cost_per_input_token)
-            + (response.usage.completion_tokens as f64 * model_config.cost_per_output_token);
+    Ok(Json(openai_response).into_response())
+}
 
-        debug!("Estimated cost: ${:.4}", estimated_cost);
-    }
+/// Provider stream of completion chunks
+pub(crate) type CompletionStream =
+    Box<dyn futures::Stream<Item = Result<StreamingChunk, LLMError>> + Send + Unpin>;
 
-    Ok(Json(openai_response).into_response())
+/// Start streaming a prepared completion from the router
+pub(crate) async fn open_completion_stream(
+    state: &OpenAIApiState,
+    prepared: PreparedCompletion,
+) -> Result<CompletionStream, ErrorResponse> {
+    let router = &state.llm_router;
+    if prepared.use_smart_routing {
+        // Get the LLM router stream with smart routing
+        router
+            .smart_chat_completion_stream(prepared.llm_request, prepared.cb_config)
+            .await
+            .map_err(|e| llm_error_response(&e, format!("Failed to start smart stream: {}", e)))
+    } else {
+        router
+            .stream_chat_completion(prepared.llm_request)
+            .await
+            .map_err(|e| llm_error_response(&e, format!("Failed to start stream: {}", e)))
+    }
 }
 
 /// Handle streaming chat completion
 async fn handle_streaming_completion(
     state: OpenAIApiState,
     request: ChatCompletionRequest,
-    _model_config: ModelConfig,
-    llm_request: LLMRequest,
+    prepared: PreparedCompletion,
 ) -> Result<Response, ErrorResponse> {
-    use futures::StreamExt;
-
+    let stream_id = prepared.llm_request.id.to_string();
     debug!("Starting streaming completion for model: {}", request.model);
 
-    // Get the LLM router stream
-    let router = &state.llm_router;
-    let stream_result = router.stream_chat_completion(llm_request).await;
+    let usage = StreamUsage::new(&prepared.llm_request).with_project(prepared.project_id.clone());
+    let include_usage = request.include_usage();
+    let stream = open_completion_stream(&state, prepared).await?;
 
-    let mut stream = match stream_result {
-        Ok(stream) => stream,
-        Err(e) => {
-            return Err(create_error_response(
-                format!("Failed to start stream: {}", e),
-                "internal_error".to_string(),
-                Some("stream".to_string()),
-                None,
-            ));
+    let resumable = StreamRegistry::global().create(stream_id);
+    spawn_stream_producer(state, resumable.clone(), stream, usage, include_usage);
+    resumable_sse_response(resumable, 0)
+}
+
+/// Tokens a streamed completion has used so far. Counts the provider reported win;
+/// until it does (and for providers that never do) they are estimated at ~4
+/// characters per token.
+pub(crate) struct StreamUsage {
+    request_id: Uuid,
+    model: String,
+    user_id: Option<String>,
+    project_id: Option<String>,
+    provider: Option<LLMProviderType>,
+    completion_id: Option<String>,
+    estimated_prompt_tokens: u32,
+    completion_chars: usize,
+    /// Largest counts the provider reported so far
+    reported_prompt_tokens: u32,
+    reported_completion_tokens: u32,
+}
+
+impl StreamUsage {
+    pub(crate) fn new(request: &LLMRequest) -> Self {
+        Self {
+            request_id: request.id,
+            model: request.model.clone(),
+            user_id: request.user.clone(),
+            project_id: None,
+            provider: None,
+            completion_id: None,
+            estimated_prompt_tokens: estimate_prompt_tokens(request),
+            completion_chars: 0,
+            reported_prompt_tokens: 0,
+            reported_completion_tokens: 0,
+        }
+    }
+
+    /// Bill the stream to a project
+    pub(crate) fn with_project(mut self, project_id: Option<String>) -> Self {
+        self.project_id = project_id;
+        self
+    }
+
+    pub(crate) fn observe(&mut self, chunk: &StreamingChunk) {
+        // The chunk names the model that actually served a virtual model
+        self.model = chunk.model.clone();
+        self.provider = Some(chunk.provider.clone());
+        self.completion_id = Some(chunk.id.clone());
+        self.completion_chars += chunk
+            .choices
+            .iter()
+            .map(|choice| choice.delta.content.len())
+            .sum::<usize>();
+        // Providers report running totals, some split across events
+        if let Some(reported) = &chunk.usage {
+            self.reported_prompt_tokens = self.reported_prompt_tokens.max(reported.prompt_tokens);
+            self.reported_completion_tokens = self
+                .reported_completion_tokens
+                .max(reported.completion_tokens);
+        }
+    }
+
+    fn prompt_tokens(&self) -> u32 {
+        match self.reported_prompt_tokens {
+            0 => self.estimated_prompt_tokens,
+            reported => reported,
+        }
+    }
+
+    fn completion_tokens(&self) -> u32 {
+        match self.reported_completion_tokens {
+            0 => self.completion_chars.div_ceil(4) as u32,
+            reported => reported,
+        }
+    }
+
+    /// Estimated cost of the tokens used so far, priced by the cost module and falling
+    /// back to the model's configured prices
+    async fn estimated_cost(&self, state: &OpenAIApiState) -> f64 {
+        estimate_cost(
+            state,
+            self.provider.as_ref(),
+            &self.model,
+            self.prompt_tokens(),
+            self.completion_tokens(),
+        )
+        .await
+    }
+
+    fn chunk(&self, choices: Vec<ChatCompletionStreamChoice>) -> ChatCompletionStreamResponse {
+        ChatCompletionStreamResponse {
+            id: self
+                .completion_id
+                .clone()
+                .unwrap_or_else(generate_completion_id),
+            object: "chat.completion.chunk".to_string(),
+            created: current_timestamp(),
+            model: self.model.clone(),
+            system_fingerprint: None,
+            choices,
+            usage: None,
         }
+    }
+
+    /// Final chunk of a cancelled stream
+    fn cancelled_chunk(&self) -> ChatCompletionStreamResponse {
+        self.chunk(vec![ChatCompletionStreamChoice {
+            index: 0,
+            delta: ChatMessageDelta {
+                role: Some(ChatRole::Assistant),
+                content: None,
+                tool_calls: None,
+            },
+            logprobs: None,
+            finish_reason: Some("cancelled".to_string()),
+        }])
+    }
+
+    /// Chunk sent last for `stream_options.include_usage`, carrying usage and no choices
+    pub(crate) async fn usage_chunk(&self, state: &OpenAIApiState) -> ChatCompletionStreamResponse {
+        let (prompt_tokens, completion_tokens) = (self.prompt_tokens(), self.completion_tokens());
+        let mut chunk = self.chunk(Vec::new());
+        chunk.usage = Some(Usage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+            estimated_cost: Some(self.estimated_cost(state).await),
+        });
+        chunk
+    }
+}
+
+/// Record what a stream consumed with the cost optimizer, including streams stopped early
+pub(crate) async fn record_stream_usage(state: &OpenAIApiState, usage: &StreamUsage) {
+    let provider = match &usage.provider {
+        Some(provider) => provider.clone(),
+        None => match state.get_model(&usage.model).await {
+            Some(config) => config.provider,
+            None => return,
+        },
     };
+    let (prompt_tokens, completion_tokens) = (usage.prompt_tokens(), usage.completion_tokens());
+    let cost_usd = usage.estimated_cost(state).await;
 
-    // Create manual SSE response with proper headers
-    let (mut sender, body) = Body::channel();
+    debug!(
+        "Recording usage of stream {}: {} prompt + {} completion tokens",
+        usage.request_id, prompt_tokens, completion_tokens
+    );
+    state
+        .cost_optimizer
+        .read()
+        .await
+        .record_actual_cost(CostInfo {
+            request_id: usage.request_id,
+            provider,
+            model: usage.model.clone(),
+            input_tokens: prompt_tokens,
+            output_tokens: completion_tokens,
+            cost_usd,
+            timestamp: chrono::Utc::now(),
+            user_id: usage.user_id.clone(),
+            project_id: usage.project_id.clone(),
+        })
+        .await;
+}
+
+/// Convert an internal streaming chunk into an OpenAI `chat.completion.chunk`
+pub(crate) fn to_stream_response(streaming_chunk: StreamingChunk) -> ChatCompletionStreamResponse {
+    ChatCompletionStreamResponse {
+        id: streaming_chunk.id.clone(),
+        object: "chat.completion.chunk".to_string(),
+        created: streaming_chunk.created,
+        model: streaming_chunk.model.clone(),
+        system_fingerprint: None,
+        choices: streaming_chunk
+            .choices
+            .into_iter()
+            .map(|choice| ChatCompletionStreamChoice {
+                index: choice.index,
+                delta: ChatMessageDelta {
+                    role: Some(match choice.delta.role {
+                        MessageRole::User => ChatRole::User,
+                        MessageRole::Assistant => ChatRole::Assistant,
+                        MessageRole::System => ChatRole::System,
+                        MessageRole::Function => ChatRole::Assistant,
+                        MessageRole::Tool => ChatRole::Tool,
+                    }),
+                    content: if choice.delta.content.is_empty() {
+                        None
+                    } else {
+                        Some(choice.delta.content)
+                    },
+                    tool_calls: choice.delta.tool_calls.map(ToolCallDelta::from_tool_calls),
+                },
+                logprobs: None,
+                finish_reason: choice.finish_reason,
+            })
+            .collect(),
+        usage: None,
+    }
+}
+
+/// Drain the provider stream into a resumable stream. Generation carries on for a
+/// while when the client disconnects so that it can resume with `Last-Event-ID`
+/// without paying for the tokens again; cancelling the stream aborts the provider
+/// request and records the usage so far. With `include_usage` the stream ends with a
+/// chunk carrying the token usage and its estimated cost.
+fn spawn_stream_producer(
+    state: OpenAIApiState,
+    resumable: Arc<ResumableStream>,
+    mut stream: CompletionStream,
+    mut usage: StreamUsage,
+    include_usage: bool,
+) {
+    use futures::StreamExt;
 
     tokio::spawn(async move {
-        while let Some(chunk_result) = stream.next().await {
+        let mut filter = state.stream_filters.stage();
+        let mut cancelled = false;
+        let mut failed = false;
+        let mut blocked = false;
+        loop {
+            let chunk_result = tokio::select! {
+                _ = resumable.cancelled() => {
+                    cancelled = true;
+                    break;
+                }
+                next = stream.next() => match next {
+                    Some(chunk_result) => chunk_result,
+                    None => break,
+                },
+            };
             match chunk_result {
                 Ok(streaming_chunk) => {
-                    let sse_data = ChatCompletionStreamResponse {
-                        id: streaming_chunk.id.clone(),
-                        object: "chat.completion.chunk".to_string(),
-                        created: streaming_chunk.created,
-                        model: streaming_chunk.model.clone(),
-                        system_fingerprint: None,
-                        choices: streaming_chunk
-                            .choices
-                            .into_iter()
-                            .map(|choice| ChatCompletionStreamChoice {
-                                index: choice.index,
-                                delta: ChatMessageDelta {
-                                    role: Some(match choice.delta.role {
-                                        MessageRole::User => ChatRole::User,
-                                        MessageRole::Assistant => ChatRole::Assistant,
-                                        MessageRole::System => ChatRole::System,
-                                        MessageRole::Function => ChatRole::Assistant,
-                                    }),
-                                    content: if choice.delta.content.is_empty() {
-                                        None
-                                    } else {
-                                        Some(choice.delta.content)
-                                    },
-                                    tool_calls: None,
-                                },
-                                logprobs: None,
-                                finish_reason: choice.finish_reason,
-                            })
-                            .collect(),
+                    usage.observe(&streaming_chunk);
+                    // Usage-only chunks are folded into the final usage chunk
+                    if streaming_chunk.choices.is_empty() {
+                        continue;
+                    }
+                    let Some(streaming_chunk) = filter.process(streaming_chunk) else {
+                        continue;
                     };
-
-                    if let Ok(json_str) = serde_json::to_string(&sse_data) {
-                        let sse_line = format!("data: {}\n\n", json_str);
-                        if sender.send_data(sse_line.into()).await.is_err() {
-                            break;
-                        }
+                    if let Ok(json_str) =
+                        serde_json::to_string(&to_stream_response(streaming_chunk))
+                    {
+                        resumable.push(json_str);
+                    }
+                    if let Some(termination) = filter.termination() {
+                        info!(
+                            "Stream {} terminated by content filter '{}': {}",
+                            resumable.id(),
+                            termination.filter,
+                            termination.reason
+                        );
+                        blocked = true;
+                        break;
                     }
                 }
                 Err(e) => {
-                    let error_data = format!(
-                        "data: {{\"error\": \"{}\", \"type\": \"stream_error\"}}\n\n",
+                    resumable.push(format!(
+                        "{{\"error\": \"{}\", \"type\": \"stream_error\"}}",
                         e
-                    );
-                    let _ = sender.send_data(error_data.into()).await;
+                    ));
+                    failed = true;
                     break;
                 }
             }
         }
 
+        if !cancelled && !failed && !blocked {
+            if let Some(held_back) = filter.flush() {
+                if let Ok(json_str) = serde_json::to_string(&to_stream_response(held_back)) {
+                    resumable.push(json_str);
+                }
+            }
+        }
+        if blocked {
+            // Dropping the provider stream closes the upstream connection
+            drop(stream);
+        } else if cancelled {
+            // Dropping the provider stream closes the upstream connection
+            drop(stream);
+            debug!("Cancelled stream {}", resumable.id());
+            if let Ok(json_str) = serde_json::to_string(&usage.cancelled_chunk()) {
+                resumable.push(json_str);
+            }
+        }
+        if !failed {
+            record_stream_usage(&state, &usage).await;
+        }
+        if include_usage && !failed {
+            if let Ok(json_str) = serde_json::to_string(&usage.usage_chunk(&state).await) {
+                resumable.push(json_str);
+            }
+        }
+
         // Send final done message
-        let _ = sender.send_data("data: [DONE]\n\n".into()).await;
+        resumable.push("[DONE]");
+        resumable.finish();
+    });
+}
+
+/// Cancel a stream whose clients all disconnected and did not resume in time
+async fn cancel_if_abandoned(resumable: &ResumableStream) {
+    tokio::select! {
+        _ = tokio::time::sleep(ABANDONED_STREAM_GRACE) => {}
+        _ = resumable.finished() => return,
+    }
+    if resumable.subscriber_count() == 0 && !resumable.is_finished() {
+        debug!("No client resumed stream {}, cancelling it", resumable.id());
+        resumable.cancel();
+    }
+}
+
+/// Serve a resumable stream as SSE, starting after the event with the given sequence
+fn resumable_sse_response(
+    resumable: Arc<ResumableStream>,
+    after: u64,
+) -> Result<Response, ErrorResponse> {
+    use futures::StreamExt;
+
+    let mut events = resumable.subscribe(after);
+    let (mut sender, body) = Body::channel();
+
+    tokio::spawn(async move {
+        let mut disconnected = false;
+        while let Some(event) = events.next().await {
+            if sender.send_data(event.to_wire().into()).await.is_err() {
+                disconnected = true;
+                break;
+            }
+        }
+        drop(events);
+        if disconnected {
+            cancel_if_abandoned(&resumable).await;
+        }
     });
 
     let response = Response::builder()
@@ -445,7 +1230,10 @@ async fn handle_streaming_completion(
         .header("Cache-Control", "no-cache")
         .header("Connection", "keep-alive")
         .header("Access-Control-Allow-Origin", "*")
-        .header("Access-Control-Allow-Headers", "Content-Type")
+        .header(
+            "Access-Control-Allow-Headers",
+            "Content-Type, Last-Event-ID",
+        )
         .body(body)
         .map_err(|e| {
             error!("Failed to build SSE response: {}", e);
@@ -460,106 +1248,88 @@ async fn handle_streaming_completion(
     Ok(response.into_response())
 }
 
-/// Handle smart streaming completion
-async fn handle_smart_streaming_completion(
-    state: OpenAIApiState,
-    request: ChatCompletionRequest,
-    cb_config: Option<CircuitBreakerConfig>,
-    llm_request: LLMRequest,
-) -> Result<Response, ErrorResponse> {
-    use futures::StreamExt;
+/// How long a cancel request waits for the stream to wind down
+const CANCEL_WAIT: std::time::Duration = std::time::Duration::from_secs(5);
 
-    debug!(
-        "Starting smart streaming completion for model: {}",
-        request.model
-    );
+/// Body of `POST /v1/chat/completions/{request_id}/cancel`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CancelCompletionResponse {
+    /// Request ID of the completion, as returned in the `x-request-id` header
+    pub id: String,
+    /// Always "chat.completion.cancellation"
+    pub object: String,
+    /// False when the completion had already finished
+    pub cancelled: bool,
+}
 
-    // Get the LLM router stream with smart routing
-    let router = &state.llm_router;
-    let stream_result = router
-        .smart_chat_completion_stream(llm_request, cb_config)
-        .await;
-
-    let mut stream = match stream_result {
-        Ok(stream) => stream,
-        Err(e) => {
-            return Err(create_error_response(
-                format!("Failed to start smart stream: {}", e),
-                "internal_error".to_string(),
-                Some("stream".to_string()),
-                None,
-            ));
-        }
-    };
-
-    // Create manual SSE response
-    let (mut sender, body) = Body::channel();
+/// Cancel a streaming completion - POST /v1/chat/completions/{request_id}/cancel
+pub async fn cancel_chat_completion(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    axum::extract::Path(request_id): axum::extract::Path<String>,
+) -> Result<Json<CancelCompletionResponse>, ErrorResponse> {
+    let _api_key_info = state.extract_api_key(&headers).await?;
 
-    tokio::spawn(async move {
-        while let Some(chunk_result) = stream.next().await {
-            match chunk_result {
-                Ok(streaming_chunk) => {
-                    let sse_data = ChatCompletionStreamResponse {
-                        id: streaming_chunk.id.clone(),
-                        object: "chat.completion.chunk".to_string(),
-                        created: streaming_chunk.created,
-                        model: streaming_chunk.model.clone(),
-                        system_fingerprint: None,
-                        choices: streaming_chunk
-                            .choices
-                            .into_iter()
-                            .map(|choice| ChatCompletionStreamChoice {
-                                index: choice.index,
-                                delta: ChatMessageDelta {
-                                    role: Some(match choice.delta.role {
-                                        MessageRole::User => ChatRole::User,
-                                        MessageRole::Assistant => ChatRole::Assistant,
-                                        MessageRole::System => ChatRole::System,
-                                        MessageRole::Function => ChatRole::Assistant,
-                                    }),
-                                    content: if choice.delta.content.is_empty() {
-                                        None
-                                    } else {
-                                        Some(choice.delta.content)
-                                    },
-                                    tool_calls: None,
-                                },
-                                logprobs: None,
-                                finish_reason: choice.finish_reason,
-                            })
-                            .collect(),
-                    };
+    let resumable = StreamRegistry::global().get(&request_id).ok_or_else(|| {
+        create_error_response(
+            format!("No streaming completion '{}'", request_id),
+            "not_found_error".to_string(),
+            Some("request_id".to_string()),
+            None,
+        )
+        .with_error_code(ErrorCode::NotFound)
+    })?;
 
-                    if let Ok(json_str) = serde_json::to_string(&sse_data) {
-                        let sse_line = format!("data: {}\n\n", json_str);
-                        if sender.send_data(sse_line.into()).await.is_err() {
-                            break;
-                        }
-                    }
-                }
-                Err(e) => {
-                    let error_data = format!(
-                        "data: {{\"error\": \"{}\", \"type\": \"stream_error\"}}\n\n",
-                        e
-                    );
-                    let _ = sender.send_data(error_data.into()).await;
-                    break;
-                }
-            }
-        }
+    let cancelled = !resumable.is_finished();
+    if cancelled {
+        info!("Cancelling streaming completion {}", request_id);
+        resumable.cancel();
+        // Answer once the partial usage is recorded, unless the provider is slow to let go
+        let _ = tokio::time::timeout(CANCEL_WAIT, resumable.finished()).await;
+    }
 
-        // Send final done message
-        let _ = sender.send_data("data: [DONE]\n\n".into()).await;
-    });
+    Ok(Json(CancelCompletionResponse {
+        id: request_id,
+        object: "chat.completion.cancellation".to_string(),
+        cancelled,
+    }))
+}
 
-    let response = Response::builder()
-        .header("Content-Type", "text/event-stream")
-        .header("Cache-Control", "no-cache")
-        .header("Connection", "keep-alive")
-        .body(body)
-        .unwrap();
+/// Continue a stream the client lost its connection to, from the `Last-Event-ID` it
+/// last received
+fn resume_stream(last_event_id: &str) -> Result<Response, ErrorResponse> {
+    let event_id = EventId::parse(last_event_id).ok_or_else(|| {
+        create_error_response(
+            format!(
+                "Invalid {} header '{}'",
+                LAST_EVENT_ID_HEADER, last_event_id
+            ),
+            "invalid_request_error".to_string(),
+            None,
+            None,
+        )
+        .with_error_code(ErrorCode::InvalidInput)
+    })?;
+    let resumable = StreamRegistry::global()
+        .get(&event_id.stream_id)
+        .ok_or_else(|| {
+            create_error_response(
+                format!(
+                    "Stream '{}' does not exist or can no longer be resumed",
+                    event_id.stream_id
+                ),
+                "not_found_error".to_string(),
+                None,
+                None,
+            )
+            .with_error_code(ErrorCode::NotFound)
+        })?;
 
-    Ok(response.into_response())
+    debug!(
+        "Resuming stream {} after event {}",
+        event_id.stream_id, event_id.sequence
+    );
+    resumable_sse_response(resumable, event_id.sequence)
 }
 
 /// Get model information endpoint - GET /v1/models/{model_id}
@@ -576,6 +1346,7 @@ pub async fn get_model(
             Some("model".to_string()),
             None,
         )
+        .with_error_code(crate::ErrorCode::ModelNotFound)
     })?;
 
     let model = Model {
@@ -620,12 +1391,499 @@ pub async fn get_model(
     Ok(Json(model))
 }
 
+/// Body of a provider key validation request
+#[derive(Debug, Clone, Deserialize)]
+pub struct ValidateProviderKeyRequest {
+    pub api_key: String,
+    /// Override the provider's base URL, e.g. for a proxy or self-hosted endpoint; must
+    /// be the provider's own endpoint or listed in `CIRCUIT_BREAKER_PROVIDER_URL_ALLOWLIST`
+    #[serde(default)]
+    pub base_url: Option<String>,
+}
+
+/// Check a provider API key before storing it - POST /v1/admin/providers/{type}/validate
+///
+/// A rejected key is reported as `valid: false` with a 200 status so setup UIs can show
+/// the provider's message; only failures to reach the provider are errors.
+pub async fn validate_provider_key(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    axum::extract::Path(provider_type): axum::extract::Path<String>,
+    Json(request): Json<ValidateProviderKeyRequest>,
+) -> Result<Json<KeyValidation>, ErrorResponse> {
+    authorize_admin(&state, &headers, "Provider key validation")?;
+
+    let provider = provider_type.parse::<LLMProviderType>().map_err(|_| {
+        create_error_response(
+            format!("Unknown provider type '{}'", provider_type),
+            "invalid_request_error".to_string(),
+            Some("provider_type".to_string()),
+            None,
+        )
+    })?;
+
+    if request.api_key.trim().is_empty() {
+        return Err(create_error_response(
+            "api_key must not be empty".to_string(),
+            "invalid_request_error".to_string(),
+            Some("api_key".to_string()),
+            None,
+        ));
+    }
+
+    info!("Validating API key for provider: {}", provider);
+    let validation = state
+        .llm_router
+        .validate_provider_key(&provider, request.api_key.trim(), request.base_url)
+        .await
+        .map_err(|e| llm_error_response(&e, format!("Key validation failed: {}", e)))?;
+
+    Ok(Json(validation))
+}
+
+/// Check the admin bearer token, rejecting the request when `feature` is disabled
+/// because no admin token is configured
+pub(crate) fn authorize_admin(
+    state: &OpenAIApiState,
+    headers: &HeaderMap,
+    feature: &str,
+) -> Result<(), ErrorResponse> {
+    let Some(admin_token) = state.admin_token.as_deref() else {
+        return Err(create_error_response(
+            format!(
+                "{} is disabled; set {} to enable it",
+                feature,
+                log_stream::ADMIN_TOKEN_ENV
+            ),
+            "permission_error".to_string(),
+            None,
+            None,
+        ));
+    };
+    let ip = client_ip(headers);
+    state
+        .auth_throttle
+        .check(ip.as_deref(), Some(ADMIN_TOKEN_PRINCIPAL))
+        .map_err(locked_out)?;
+    let authorization = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+    if !log_stream::is_authorized(authorization, admin_token) {
+        let lockout = authorization.and_then(|_| {
+            state.auth_throttle.record_failure(
+                AuthSurface::AdminToken,
+                ip.as_deref(),
+                Some(ADMIN_TOKEN_PRINCIPAL),
+                &format!("Invalid admin token for {}", feature),
+            )
+        });
+        return Err(lockout.map(locked_out).unwrap_or_else(|| {
+            create_error_response(
+                "Invalid or missing admin token".to_string(),
+                "authentication_error".to_string(),
+                None,
+                Some("invalid_admin_token".to_string()),
+            )
+        }));
+    }
+    Ok(())
+}
+
+/// Error for a client locked out after too many failed authentications
+pub(crate) fn locked_out(lockout: Lockout) -> ErrorResponse {
+    create_error_response(
+        lockout.message(),
+        "rate_limit_error".to_string(),
+        None,
+        Some("too_many_failed_authentications".to_string()),
+    )
+    .with_error_code(ErrorCode::RateLimited)
+}
+
+/// Query parameters of `GET /v1/admin/audit/events`
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuditEventsQuery {
+    /// Only events of this kind
+    #[serde(default)]
+    pub kind: Option<SecurityEventKind>,
+    /// Most events returned, 100 by default
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// Recent security events, newest first - GET /v1/admin/audit/events
+pub async fn list_audit_events(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<AuditEventsQuery>,
+) -> Result<Json<Vec<SecurityEvent>>, ErrorResponse> {
+    authorize_admin(&state, &headers, "Audit log")?;
+    Ok(Json(
+        state
+            .audit_log
+            .recent(query.kind, query.limit.unwrap_or(100)),
+    ))
+}
+
+/// Search the audit trail, newest first - GET /v1/admin/audit/records
+///
+/// `category`, `action`, `outcome`, `actor`, `tenant_id` and `ip` query parameters must
+/// match exactly; `since` and `until` take RFC 3339 timestamps.
+pub async fn list_audit_records(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<AuditQuery>,
+) -> Result<Json<Vec<AuditRecord>>, ErrorResponse> {
+    authorize_admin(&state, &headers, "Audit trail")?;
+    let records = state.audit_log.trail().query(&query).await.map_err(|e| {
+        create_error_response(e.to_string(), "internal_error".to_string(), None, None)
+            .with_error_code(ErrorCode::StorageError)
+    })?;
+    Ok(Json(records))
+}
+
+/// Tail the server's log output as server-sent events - GET /admin/logs/stream
+///
+/// Requires `Authorization: Bearer <token>` matching `CIRCUIT_BREAKER_ADMIN_TOKEN`; the
+/// endpoint is disabled when no admin token is configured. `level` and `target` query
+/// parameters narrow the stream.
+pub async fn stream_logs(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<LogStreamQuery>,
+) -> Result<Response, ErrorResponse> {
+    authorize_admin(&state, &headers, "Log streaming")?;
+
+    let filter = LogFilter::from_query(&query).map_err(|message| {
+        create_error_response(
+            message,
+            "invalid_request_error".to_string(),
+            Some("level".to_string()),
+            None,
+        )
+    })?;
+
+    info!(
+        "Operator attached to log stream (level: {:?}, targets: {:?})",
+        filter.level, filter.targets
+    );
+    let mut receiver = log_stream::subscribe();
+    let (mut sender, body) = Body::channel();
+
+    tokio::spawn(async move {
+        let mut keep_alive = tokio::time::interval(std::time::Duration::from_secs(15));
+        loop {
+            let frame = tokio::select! {
+                received = receiver.recv() => match received {
+                    Ok(record) if filter.matches(&record) => match serde_json::to_string(&record) {
+                        Ok(json) => format!("event: log\ndata: {}\n\n", json),
+                        Err(_) => continue,
+                    },
+                    Ok(_) => continue,
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                        format!("event: lagged\ndata: {{\"skipped\": {}}}\n\n", skipped)
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                },
+                _ = keep_alive.tick() => ": keep-alive\n\n".to_string(),
+            };
+            // Stop once the operator disconnects
+            if sender.send_data(frame.into()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let response = Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "text/event-stream")
+        .header("Cache-Control", "no-cache")
+        .header("Connection", "keep-alive")
+        .body(body)
+        .map_err(|e| {
+            error!("Failed to build log stream response: {}", e);
+            create_error_response(
+                "Failed to create streaming response".to_string(),
+                "internal_error".to_string(),
+                None,
+                None,
+            )
+        })?;
+
+    Ok(response.into_response())
+}
+
+/// Maintenance mode update - PUT /v1/admin/maintenance
+#[derive(Debug, Clone, Deserialize)]
+pub struct MaintenanceUpdate {
+    pub enabled: bool,
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// Seconds clients are told to wait before retrying
+    #[serde(default)]
+    pub retry_after_secs: Option<u64>,
+}
+
+/// Get the maintenance mode status - GET /v1/admin/maintenance
+pub async fn get_maintenance(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+) -> Result<Json<MaintenanceStatus>, ErrorResponse> {
+    authorize_admin(&state, &headers, "Maintenance control")?;
+    Ok(Json(state.maintenance.status()))
+}
+
+/// Switch maintenance mode on or off - PUT /v1/admin/maintenance
+pub async fn set_maintenance(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Json(update): Json<MaintenanceUpdate>,
+) -> Result<Json<MaintenanceStatus>, ErrorResponse> {
+    authorize_admin(&state, &headers, "Maintenance control")?;
+
+    let status = if update.enabled {
+        let status = state
+            .maintenance
+            .enable(update.reason, update.retry_after_secs);
+        info!(
+            "Maintenance mode enabled (reason: {:?}, retry after {}s)",
+            status.reason, status.retry_after_secs
+        );
+        status
+    } else {
+        info!("Maintenance mode disabled");
+        state.maintenance.disable()
+    };
+    Ok(Json(status))
+}
+
+/// Middleware rejecting writes with 503 and `Retry-After` while maintenance mode is on
+///
+/// Safe methods pass through, as do token counts and admin endpoints so operators can
+/// keep working and switch maintenance mode off again. Streams that are already open are unaffected and
+/// can still be cancelled.
+pub async fn reject_writes_during_maintenance(
+    State(state): State<OpenAIApiState>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Response {
+    let read_only = matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    );
+    let path = request.uri().path();
+    let admin = path.starts_with("/admin/") || path.starts_with("/v1/admin/");
+    let cancel = path.starts_with("/v1/chat/completions/") && path.ends_with("/cancel");
+    let tokenize = path == "/v1/tokenize";
+    if read_only || admin || cancel || tokenize || !state.maintenance.is_enabled() {
+        return next.run(request).await;
+    }
+
+    let status = state.maintenance.status();
+    debug!(
+        "Rejected {} {} during maintenance",
+        request.method(),
+        request.uri().path()
+    );
+    let mut response = create_error_response(
+        status.message(),
+        "service_unavailable_error".to_string(),
+        None,
+        Some("maintenance_mode".to_string()),
+    )
+    .with_error_code(ErrorCode::MaintenanceMode)
+    .into_response();
+    response.headers_mut().insert(
+        header::RETRY_AFTER,
+        HeaderValue::from(status.retry_after_secs),
+    );
+    response
+}
+
+/// Middleware recording every write to the admin API on the audit trail
+///
+/// The action is the method and route. Its outcome follows the response status, so
+/// requests refused for a missing or wrong admin token are recorded as denied.
+pub async fn record_admin_mutations(
+    State(state): State<OpenAIApiState>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Response {
+    let read_only = matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    );
+    let path = request.uri().path().to_string();
+    if read_only || !(path.starts_with("/admin/") || path.starts_with("/v1/admin/")) {
+        return next.run(request).await;
+    }
+
+    let route = request
+        .extensions()
+        .get::<axum::extract::MatchedPath>()
+        .map_or(path.as_str(), |route| route.as_str());
+    let action = format!("{} {}", request.method(), route);
+    let ip = client_ip(request.headers());
+    let tenant_id = path
+        .strip_prefix("/v1/admin/tenants/")
+        .and_then(|rest| rest.split('/').next())
+        .map(str::to_string);
+
+    let response = next.run(request).await;
+    let status = response.status();
+    let outcome = if status.is_success() {
+        AuditOutcome::Success
+    } else if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+        AuditOutcome::Denied
+    } else {
+        AuditOutcome::Failure
+    };
+    let mut record = AuditRecord::new(AuditCategory::AdminMutation, action, outcome)
+        .with_tenant(tenant_id.as_deref())
+        .with_ip(ip.as_deref())
+        .with_target(path)
+        .with_detail(status.to_string());
+    // Only holders of the admin token get past its check
+    if outcome != AuditOutcome::Denied {
+        record = record.with_actor("admin");
+    }
+    state.audit_log.trail().record(record).await;
+    response
+}
+
+/// Get the routing trace of a request - GET /v1/requests/{request_id}/routing
+pub async fn get_request_routing(
+    State(state): State<OpenAIApiState>,
+    axum::extract::Path(request_id): axum::extract::Path<String>,
+) -> Result<Json<RoutingTrace>, ErrorResponse> {
+    let id = Uuid::parse_str(&request_id).map_err(|_| {
+        create_error_response(
+            format!("Invalid request ID '{}'", request_id),
+            "invalid_request_error".to_string(),
+            Some("request_id".to_string()),
+            None,
+        )
+    })?;
+
+    let trace = state.llm_router.routing_trace(&id).await.ok_or_else(|| {
+        create_error_response(
+            format!("No routing trace recorded for request '{}'", request_id),
+            "not_found_error".to_string(),
+            Some("request_id".to_string()),
+            None,
+        )
+    })?;
+
+    Ok(Json(trace))
+}
+
+/// Get the recorded cost of a request - GET /v1/requests/{request_id}/cost
+pub async fn get_request_cost(
+    State(state): State<OpenAIApiState>,
+    axum::extract::Path(request_id): axum::extract::Path<String>,
+) -> Result<Json<CostInfo>, ErrorResponse> {
+    let id = Uuid::parse_str(&request_id).map_err(|_| {
+        create_error_response(
+            format!("Invalid request ID '{}'", request_id),
+            "invalid_request_error".to_string(),
+            Some("request_id".to_string()),
+            None,
+        )
+    })?;
+
+    let cost = state
+        .cost_optimizer
+        .read()
+        .await
+        .get_request_cost(&id)
+        .await
+        .map_err(cost_error_response)?
+        .ok_or_else(|| {
+            create_error_response(
+                format!("No cost recorded for request '{}'", request_id),
+                "not_found_error".to_string(),
+                Some("request_id".to_string()),
+                None,
+            )
+        })?;
+
+    Ok(Json(cost))
+}
+
+/// Get a tenant's routing policy - GET /v1/tenants/{tenant_id}/routing-policy
+pub async fn get_tenant_routing_policy(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    axum::extract::Path(tenant_id): axum::extract::Path<String>,
+) -> Result<Json<TenantRoutingPolicy>, ErrorResponse> {
+    authorize_admin(&state, &headers, "Tenant administration")?;
+    let tenant_id = TenantId::new(tenant_id);
+    let policy = state
+        .llm_router
+        .tenant_policies()
+        .get(&tenant_id)
+        .await
+        .ok_or_else(|| {
+            create_error_response(
+                format!("No routing policy set for tenant '{}'", tenant_id),
+                "not_found_error".to_string(),
+                Some("tenant_id".to_string()),
+                None,
+            )
+        })?;
+
+    Ok(Json(policy))
+}
+
+/// Set a tenant's routing policy - PUT /v1/tenants/{tenant_id}/routing-policy
+pub async fn set_tenant_routing_policy(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    axum::extract::Path(tenant_id): axum::extract::Path<String>,
+    Json(policy): Json<TenantRoutingPolicy>,
+) -> Result<Json<TenantRoutingPolicy>, ErrorResponse> {
+    authorize_admin(&state, &headers, "Tenant administration")?;
+    let tenant_id = TenantId::new(tenant_id);
+    info!("Setting routing policy for tenant: {}", tenant_id);
+
+    state
+        .llm_router
+        .tenant_policies()
+        .set(tenant_id, policy.clone())
+        .await;
+
+    Ok(Json(policy))
+}
+
+/// Remove a tenant's routing policy - DELETE /v1/tenants/{tenant_id}/routing-policy
+pub async fn delete_tenant_routing_policy(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    axum::extract::Path(tenant_id): axum::extract::Path<String>,
+) -> Result<StatusCode, ErrorResponse> {
+    authorize_admin(&state, &headers, "Tenant administration")?;
+    let tenant_id = TenantId::new(tenant_id);
+    match state.llm_router.tenant_policies().remove(&tenant_id).await {
+        Some(_) => {
+            info!("Removed routing policy for tenant: {}", tenant_id);
+            Ok(StatusCode::NO_CONTENT)
+        }
+        None => Err(create_error_response(
+            format!("No routing policy set for tenant '{}'", tenant_id),
+            "not_found_error".to_string(),
+            Some("tenant_id".to_string()),
+            None,
+        )),
+    }
+}
+
 /// Handle smart routing for regular (non-streaming) completion
 async fn handle_smart_regular_completion(
     state: OpenAIApiState,
     request: ChatCompletionRequest,
     cb_config: Option<CircuitBreakerConfig>,
     llm_request: LLMRequest,
+    project_id: Option<String>,
 ) -> Result<Response, ErrorResponse> {
     info!(
         "Processing smart regular completion for model: {}",
@@ -635,18 +1893,24 @@ async fn handle_smart_regular_completion(
     // Use smart routing
     let response = state
         .llm_router
-        .smart_chat_completion(llm_request, cb_config.clone())
+        .smart_chat_completion(llm_request.clone(), cb_config.clone())
         .await
         .map_err(|e| {
             error!("Smart LLM routing failed: {}", e);
-            create_error_response(
-                format!("Failed to process smart request: {}", e),
-                "internal_error".to_string(),
-                None,
-                None,
-            )
+            llm_error_response(&e, format!("Failed to process smart request: {}", e))
         })?;
 
+    let cost_usd = record_completion_cost(
+        &state,
+        &llm_request,
+        project_id,
+        response.provider.clone(),
+        &response.model,
+        response.usage.prompt_tokens,
+        response.usage.completion_tokens,
+    )
+    .await;
+
     // Convert to OpenAI format
     let completion_id = generate_completion_id();
     let created = current_timestamp();
@@ -656,31 +1920,16 @@ async fn handle_smart_regular_completion(
         object: "chat.completion".to_string(),
         created,
         model: response.routing_info.selected_provider.to_string(),
-        choices: vec![ChatCompletionChoice {
-            index: 0,
-            message: ChatMessage {
-                role: ChatRole::Assistant,
-                content: response
-                    .choices
-                    .first()
-                    .map(|c| c.message.content.clone())
-                    .unwrap_or_default(),
-                name: None,
-                tool_calls: None,
-                tool_call_id: None,
-            },
-            finish_reason: response
-                .choices
-                .first()
-                .and_then(|c| c.finish_reason.clone()),
-            logprobs: None,
-        }],
+        choices: completion_choices(&response),
         usage: Usage {
             prompt_tokens: response.usage.prompt_tokens,
             completion_tokens: response.usage.completion_tokens,
             total_tokens: response.usage.total_tokens,
+            estimated_cost: Some(cost_usd),
         },
         system_fingerprint: Some("circuit-breaker-smart-v1".to_string()),
+        request_id: Some(llm_request.id),
+        routing_info: Some(response.routing_info.clone()),
     };
 
     // Add routing information to response metadata
@@ -700,15 +1949,45 @@ async fn handle_smart_regular_completion(
 
 /// Error handler for invalid routes
 /// Handle embeddings requests
+///
+/// Large input arrays are batched across provider calls by the router, which also
+/// applies `dimensions` for backends that cannot shorten embeddings themselves.
 pub async fn embeddings(
     State(state): State<OpenAIApiState>,
     Json(request): Json<EmbeddingsRequest>,
 ) -> Result<Json<EmbeddingsResponse>, ErrorResponse> {
     debug!("Processing embeddings request for model: {}", request.model);
 
+    let base64 = match request.encoding_format.as_deref() {
+        None | Some("float") => false,
+        Some("base64") => true,
+        Some(other) => {
+            return Err(invalid_param(
+                format!(
+                    "Unknown encoding format '{}', expected float or base64",
+                    other
+                ),
+                "encoding_format",
+            )
+            .with_error_code(ErrorCode::InvalidInput))
+        }
+    };
+    if request.dimensions == Some(0) {
+        return Err(
+            invalid_param("dimensions must be at least 1".to_string(), "dimensions")
+                .with_error_code(ErrorCode::InvalidInput),
+        );
+    }
+
     // Convert input to LLM format
     let llm_input = match request.input {
         EmbeddingsInput::Single(text) => LLMEmbeddingsInput::Text(text),
+        EmbeddingsInput::Multiple(texts) if texts.is_empty() => {
+            return Err(
+                invalid_param("input must not be empty".to_string(), "input")
+                    .with_error_code(ErrorCode::InvalidInput),
+            )
+        }
         EmbeddingsInput::Multiple(texts) => LLMEmbeddingsInput::TextArray(texts),
     };
 
@@ -718,6 +1997,7 @@ pub async fn embeddings(
         model: request.model.clone(),
         user: request.user,
         metadata: HashMap::new(),
+        dimensions: request.dimensions,
     };
 
     // Route to appropriate provider
@@ -728,10 +2008,18 @@ pub async fn embeddings(
                 .data
                 .into_iter()
                 .enumerate()
-                .map(|(index, embedding)| EmbeddingObject {
-                    object: "embedding".to_string(),
-                    embedding: embedding.embedding.into_iter().map(|x| x as f32).collect(),
-                    index: index as u32,
+                .map(|(index, embedding)| {
+                    let vector: Vec<f32> =
+                        embedding.embedding.into_iter().map(|x| x as f32).collect();
+                    EmbeddingObject {
+                        object: "embedding".to_string(),
+                        embedding: if base64 {
+                            EmbeddingVector::Base64(crate::llm::embeddings::encode_base64(&vector))
+                        } else {
+                            EmbeddingVector::Float(vector)
+                        },
+                        index: index as u32,
+                    }
                 })
                 .collect();
 
@@ -764,6 +2052,8 @@ pub async fn embeddings(
                         error_type: "feature_disabled".to_string(),
                         param: Some("model".to_string()),
                         code: Some("embeddings_disabled".to_string()),
+                        error_code: Some(crate::ErrorCode::ModelNotFound),
+                        quota: None,
                     },
                 })
             } else {
@@ -775,6 +2065,8 @@ pub async fn embeddings(
                         error_type: "internal_error".to_string(),
                         param: Some("model".to_string()),
                         code: None,
+                        error_code: Some(e.code()),
+                        quota: None,
                     },
                 })
             }
@@ -782,28 +2074,120 @@ pub async fn embeddings(
     }
 }
 
+/// Count tokens without running a completion - POST /v1/tokenize
+///
+/// Uses the same estimator as budget checks and context-window routing, so clients
+/// can see what those checks will see. The model does not have to be configured here.
+pub async fn tokenize(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Json(request): Json<TokenizeRequest>,
+) -> Result<Json<TokenizeResponse>, ErrorResponse> {
+    let _api_key_info = state.extract_api_key(&headers).await?;
+
+    if request.messages.is_empty() && request.input.is_none() {
+        return Err(create_error_response(
+            "Either 'messages' or 'input' is required".to_string(),
+            "invalid_request_error".to_string(),
+            Some("messages".to_string()),
+            None,
+        )
+        .with_error_code(ErrorCode::InvalidInput));
+    }
+
+    let tokenizer = Tokenizer::for_model(&request.model);
+    let messages: Vec<crate::llm::ChatMessage> =
+        request.messages.into_iter().map(Into::into).collect();
+    let prompt_tokens = if messages.is_empty() {
+        0
+    } else {
+        tokenizer.count_prompt(&messages)
+    };
+    let input_tokens: Vec<u32> = match &request.input {
+        Some(EmbeddingsInput::Single(text)) => vec![tokenizer.count(text)],
+        Some(EmbeddingsInput::Multiple(texts)) => {
+            texts.iter().map(|text| tokenizer.count(text)).collect()
+        }
+        None => Vec::new(),
+    };
+    let total_tokens = prompt_tokens + input_tokens.iter().sum::<u32>();
+    let context_window = state
+        .get_model(&request.model)
+        .await
+        .map(|config| config.context_window);
+
+    Ok(Json(TokenizeResponse {
+        model: request.model,
+        tokenizer,
+        exact: tokenizer.is_exact(),
+        prompt_tokens,
+        input_tokens,
+        total_tokens,
+        context_window,
+    }))
+}
+
+/// Convert a router error into an OpenAI-style error response
+pub(crate) fn llm_error_response(error: &LLMError, message: String) -> ErrorResponse {
+    let response = match error {
+        LLMError::ContextLengthExceeded(_) => create_error_response(
+            message,
+            "invalid_request_error".to_string(),
+            Some("messages".to_string()),
+            Some("context_length_exceeded".to_string()),
+        ),
+        LLMError::RateLimitExceeded(_) => create_error_response(
+            message,
+            "rate_limit_error".to_string(),
+            None,
+            Some("rate_limit_exceeded".to_string()),
+        ),
+        LLMError::NotPermitted(_) => create_error_response(
+            message,
+            "permission_error".to_string(),
+            Some("model".to_string()),
+            Some("model_not_permitted".to_string()),
+        ),
+        LLMError::StructuredOutput(_) => create_error_response(
+            message,
+            "invalid_response_error".to_string(),
+            Some("response_format".to_string()),
+            Some("response_format_violation".to_string()),
+        ),
+        _ => create_error_response(message, "internal_error".to_string(), None, None),
+    };
+    response.with_error_code(error.code())
+}
+
 pub async fn not_found() -> impl IntoResponse {
     let error = create_error_response(
         "Not found".to_string(),
         "invalid_request_error".to_string(),
         None,
         None,
-    );
+    )
+    .with_error_code(crate::ErrorCode::NotFound);
     (StatusCode::NOT_FOUND, Json(error))
 }
 
 /// Error response implementation
 impl IntoResponse for ErrorResponse {
-    fn into_response(self) -> Response {
-        let status = match self.error.error_type.as_str() {
-            "invalid_request_error" => StatusCode::BAD_REQUEST,
-            "authentication_error" => StatusCode::UNAUTHORIZED,
-            "permission_error" => StatusCode::FORBIDDEN,
-            "not_found_error" => StatusCode::NOT_FOUND,
-            "rate_limit_error" => StatusCode::TOO_MANY_REQUESTS,
-            "internal_error" => StatusCode::INTERNAL_SERVER_ERROR,
-            _ => StatusCode::BAD_REQUEST,
+    fn into_response(mut self) -> Response {
+        // An explicit error code decides the status; otherwise the OpenAI error type does
+        let status = match self.error.error_code {
+            Some(error_code) => StatusCode::from_u16(error_code.http_status())
+                .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            None => match self.error.error_type.as_str() {
+                "invalid_request_error" => StatusCode::BAD_REQUEST,
+                "authentication_error" => StatusCode::UNAUTHORIZED,
+                "permission_error" => StatusCode::FORBIDDEN,
+                "not_found_error" => StatusCode::NOT_FOUND,
+                "rate_limit_error" => StatusCode::TOO_MANY_REQUESTS,
+                "internal_error" => StatusCode::INTERNAL_SERVER_ERROR,
+                _ => StatusCode::BAD_REQUEST,
+            },
         };
+        self.error.error_code = Some(self.error_code());
 
         (status, Json(self)).into_response()
     }
@@ -831,6 +2215,501 @@ mod tests {
         assert!(models.iter().any(|m| m.id.starts_with("cb:")));
     }
 
+    #[test]
+    fn test_extract_tenant_id() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(OpenAIApiState::extract_tenant_id(&headers), None);
+
+        headers.insert(TENANT_ID_HEADER, " acme ".parse().unwrap());
+        assert_eq!(
+            OpenAIApiState::extract_tenant_id(&headers),
+            Some(TenantId::new("acme"))
+        );
+    }
+
+    #[test]
+    fn test_request_priority_resolution() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(
+            OpenAIApiState::request_priority(&headers, None),
+            RequestPriority::Interactive
+        );
+
+        headers.insert(REQUEST_PRIORITY_HEADER, "batch".parse().unwrap());
+        assert_eq!(
+            OpenAIApiState::request_priority(&headers, None),
+            RequestPriority::Batch
+        );
+    }
+
+    #[test]
+    fn test_error_response_carries_error_code() {
+        let response = llm_error_response(
+            &LLMError::RateLimitExceeded("queue full".to_string()),
+            "queue full".to_string(),
+        );
+        assert_eq!(response.error_code(), crate::ErrorCode::RateLimited);
+        assert_eq!(
+            response.into_response().status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+
+        let response = create_error_response(
+            "Unknown endpoint".to_string(),
+            "not_found_error".to_string(),
+            None,
+            None,
+        );
+        assert_eq!(response.error_code(), crate::ErrorCode::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_log_stream_requires_admin_token() {
+        let mut state = OpenAIApiState::new();
+        state.admin_token = None;
+        let stream = |state: OpenAIApiState, headers: HeaderMap| async move {
+            match stream_logs(
+                State(state),
+                headers,
+                axum::extract::Query(LogStreamQuery::default()),
+            )
+            .await
+            {
+                Ok(response) => response.status(),
+                Err(error) => error.into_response().status(),
+            }
+        };
+
+        // Disabled until a token is configured
+        assert_eq!(
+            stream(state.clone(), HeaderMap::new()).await,
+            StatusCode::FORBIDDEN
+        );
+
+        state.admin_token = Some("s3cret".to_string());
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, "Bearer wrong".parse().unwrap());
+        assert_eq!(
+            stream(state.clone(), headers).await,
+            StatusCode::UNAUTHORIZED
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, "Bearer s3cret".parse().unwrap());
+        assert_eq!(stream(state, headers).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_tokenize() {
+        let request: TokenizeRequest = serde_json::from_value(serde_json::json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "hello world"}],
+            "input": ["hello world", "abc"]
+        }))
+        .unwrap();
+        let Json(response) = tokenize(
+            State(OpenAIApiState::new()),
+            HeaderMap::new(),
+            Json(request),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.tokenizer, Tokenizer::O200kBase);
+        assert!(response.exact);
+        assert_eq!(response.prompt_tokens, 2 + 4 + 3);
+        assert_eq!(response.input_tokens, vec![2, 1]);
+        assert_eq!(response.total_tokens, 12);
+
+        let request: TokenizeRequest = serde_json::from_value(serde_json::json!({
+            "model": "claude-3-haiku",
+            "input": "abcdefgh"
+        }))
+        .unwrap();
+        let Json(response) = tokenize(
+            State(OpenAIApiState::new()),
+            HeaderMap::new(),
+            Json(request),
+        )
+        .await
+        .unwrap();
+        assert!(!response.exact);
+        assert_eq!(response.prompt_tokens, 0);
+        assert_eq!(response.total_tokens, 2);
+
+        let request: TokenizeRequest =
+            serde_json::from_value(serde_json::json!({"model": "gpt-4o"})).unwrap();
+        let error = tokenize(
+            State(OpenAIApiState::new()),
+            HeaderMap::new(),
+            Json(request),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.error.error_code, Some(ErrorCode::InvalidInput));
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_mode_rejects_writes() {
+        use axum::body::HttpBody;
+        use tower::ServiceExt;
+
+        let mut state = OpenAIApiState::new();
+        state.maintenance = MaintenanceMode::new();
+        let app = axum::Router::new()
+            .route(
+                "/v1/chat/completions",
+                axum::routing::get(|| async { "read" }).post(|| async { "write" }),
+            )
+            .route(
+                "/v1/admin/maintenance",
+                axum::routing::put(|| async { "toggled" }),
+            )
+            .layer(axum::middleware::from_fn_with_state(
+                state.clone(),
+                reject_writes_during_maintenance,
+            ));
+        let send = |method: Method, uri: &'static str| {
+            app.clone().oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        let response = send(Method::POST, "/v1/chat/completions").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        state
+            .maintenance
+            .enable(Some("storage migration".to_string()), Some(90));
+        let response = send(Method::POST, "/v1/chat/completions").await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "90");
+        let body = response.into_body().data().await.unwrap().unwrap();
+        let error: ErrorResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error.error.error_code, Some(ErrorCode::MaintenanceMode));
+
+        // Reads and admin endpoints keep working
+        let response = send(Method::GET, "/v1/chat/completions").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = send(Method::PUT, "/v1/admin/maintenance").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        state.maintenance.disable();
+        let response = send(Method::POST, "/v1/chat/completions").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_resume_stream_from_last_event_id() {
+        use axum::body::HttpBody;
+
+        let status = |result: Result<Response, ErrorResponse>| match result {
+            Ok(response) => response.status(),
+            Err(error) => error.into_response().status(),
+        };
+        assert_eq!(
+            status(resume_stream("no-sequence")),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            status(resume_stream("unknown-stream:3")),
+            StatusCode::NOT_FOUND
+        );
+
+        let resumable = StreamRegistry::global().create("resume-test");
+        for data in ["first", "second", "[DONE]"] {
+            resumable.push(data);
+        }
+        resumable.finish();
+
+        let mut body = resume_stream("resume-test:1").unwrap().into_body();
+        let mut text = String::new();
+        while let Some(chunk) = body.data().await {
+            text.push_str(std::str::from_utf8(&chunk.unwrap()).unwrap());
+        }
+        assert_eq!(
+            text,
+            "id: resume-test:2\ndata: second\n\nid: resume-test:3\ndata: [DONE]\n\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cancel_streaming_completion() {
+        use axum::body::HttpBody;
+        use axum::extract::Path;
+
+        let state = OpenAIApiState::new();
+        let request: ChatCompletionRequest = serde_json::from_value(serde_json::json!({
+            "model": "gpt-4",
+            "messages": [{"role": "user", "content": "Write a very long story"}],
+            "stream": true
+        }))
+        .unwrap();
+        let llm_request: LLMRequest = request.into();
+        let request_id = llm_request.id.to_string();
+
+        // A provider that never finishes on its own
+        let resumable = StreamRegistry::global().create(request_id.clone());
+        spawn_stream_producer(
+            state.clone(),
+            resumable.clone(),
+            Box::new(futures::stream::pending()),
+            StreamUsage::new(&llm_request),
+            false,
+        );
+        let mut body = resumable_sse_response(resumable.clone(), 0)
+            .unwrap()
+            .into_body();
+
+        let cancel =
+            |id: String| cancel_chat_completion(State(state.clone()), HeaderMap::new(), Path(id));
+        let Json(response) = cancel(request_id.clone()).await.unwrap();
+        assert!(response.cancelled);
+        assert!(resumable.is_finished());
+
+        let mut text = String::new();
+        while let Some(chunk) = body.data().await {
+            text.push_str(std::str::from_utf8(&chunk.unwrap()).unwrap());
+        }
+        assert!(text.contains(r#""finish_reason":"cancelled""#));
+        assert!(text.ends_with("data: [DONE]\n\n"));
+
+        let Json(response) = cancel(request_id).await.unwrap();
+        assert!(!response.cancelled);
+        let error = cancel(Uuid::new_v4().to_string()).await.unwrap_err();
+        assert_eq!(error.into_response().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_stream_ends_with_usage_chunk() {
+        use crate::llm::streaming::create_streaming_chunk;
+        use axum::body::HttpBody;
+
+        let request: ChatCompletionRequest = serde_json::from_value(serde_json::json!({
+            "model": "gpt-4",
+            "messages": [{"role": "user", "content": "Hi"}],
+            "stream": true,
+            "stream_options": {"include_usage": true}
+        }))
+        .unwrap();
+        assert!(request.include_usage());
+        let llm_request: LLMRequest = request.into();
+
+        let chunk = |content: &str| {
+            create_streaming_chunk(
+                "chatcmpl-usage".to_string(),
+                content.to_string(),
+                "gpt-4".to_string(),
+                LLMProviderType::OpenAI,
+                None,
+            )
+        };
+        let mut usage_only = chunk("");
+        usage_only.choices.clear();
+        usage_only.usage = Some(crate::llm::TokenUsage {
+            prompt_tokens: 9,
+            completion_tokens: 2,
+            total_tokens: 11,
+            estimated_cost: 0.0,
+        });
+        let chunks = vec![Ok(chunk("Hello")), Ok(chunk(" there")), Ok(usage_only)];
+
+        let resumable = StreamRegistry::new().create(llm_request.id.to_string());
+        spawn_stream_producer(
+            OpenAIApiState::new(),
+            resumable.clone(),
+            Box::new(futures::stream::iter(chunks)),
+            StreamUsage::new(&llm_request),
+            true,
+        );
+        let mut body = resumable_sse_response(resumable, 0).unwrap().into_body();
+        let mut text = String::new();
+        while let Some(data) = body.data().await {
+            text.push_str(std::str::from_utf8(&data.unwrap()).unwrap());
+        }
+
+        let events: Vec<&str> = text
+            .split("\n\n")
+            .filter_map(|event| event.lines().find_map(|line| line.strip_prefix("data: ")))
+            .collect();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[3], "[DONE]");
+        let last: ChatCompletionStreamResponse = serde_json::from_str(events[2]).unwrap();
+        assert!(last.choices.is_empty());
+        let usage = last.usage.unwrap();
+        assert_eq!(
+            (
+                usage.prompt_tokens,
+                usage.completion_tokens,
+                usage.total_tokens
+            ),
+            (9, 2, 11)
+        );
+        // gpt-4 is priced by the cost analyzer
+        assert!(usage.estimated_cost.unwrap() > 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_content_filter_terminates_stream() {
+        use crate::llm::stream_filter::{PatternFilter, CONTENT_FILTER_FINISH_REASON};
+        use crate::llm::streaming::create_streaming_chunk;
+        use axum::body::HttpBody;
+
+        let request: ChatCompletionRequest = serde_json::from_value(serde_json::json!({
+            "model": "gpt-4",
+            "messages": [{"role": "user", "content": "Hi"}],
+            "stream": true
+        }))
+        .unwrap();
+        let llm_request: LLMRequest = request.into();
+
+        let chunk = |content: &str| {
+            Ok(create_streaming_chunk(
+                "chatcmpl-filter".to_string(),
+                content.to_string(),
+                "gpt-4".to_string(),
+                LLMProviderType::OpenAI,
+                None,
+            ))
+        };
+        let chunks = vec![
+            chunk("The launch code "),
+            chunk("is swordfish"),
+            chunk(" and more"),
+        ];
+
+        let mut state = OpenAIApiState::new();
+        state.stream_filters = StreamFilters::new()
+            .with_filter(Arc::new(PatternFilter::new().block_keywords(["swordfish"])));
+        let resumable = StreamRegistry::new().create(llm_request.id.to_string());
+        spawn_stream_producer(
+            state,
+            resumable.clone(),
+            Box::new(futures::stream::iter(chunks)),
+            StreamUsage::new(&llm_request),
+            false,
+        );
+        let mut body = resumable_sse_response(resumable, 0).unwrap().into_body();
+        let mut text = String::new();
+        while let Some(data) = body.data().await {
+            text.push_str(std::str::from_utf8(&data.unwrap()).unwrap());
+        }
+
+        assert!(!text.contains("swordfish"));
+        assert!(!text.contains("launch code"));
+        let events: Vec<&str> = text
+            .split("\n\n")
+            .filter_map(|event| event.lines().find_map(|line| line.strip_prefix("data: ")))
+            .collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1], "[DONE]");
+        let last: ChatCompletionStreamResponse = serde_json::from_str(events[0]).unwrap();
+        assert_eq!(
+            last.choices[0].finish_reason.as_deref(),
+            Some(CONTENT_FILTER_FINISH_REASON)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_budget_preflight() {
+        use crate::llm::cost::{Budget, BudgetPeriod};
+
+        let state = OpenAIApiState::new();
+        state.models.write().await.push(ModelConfig {
+            id: "metered".to_string(),
+            provider: LLMProviderType::Custom("metered".to_string()),
+            display_name: "Metered".to_string(),
+            context_window: 8192,
+            max_output_tokens: 1000,
+            supports_streaming: true,
+            supports_vision: false,
+            cost_per_input_token: 0.001,
+            cost_per_output_token: 0.004,
+        });
+        let now = chrono::Utc::now();
+        let budget_manager = state.cost_optimizer.read().await.budget_manager();
+        budget_manager
+            .set_budget(Budget {
+                id: "user:alice".to_string(),
+                user_id: Some("alice".to_string()),
+                project_id: None,
+                limit: 1.0,
+                period: BudgetPeriod::Daily,
+                warning_threshold: 0.5,
+                carry_over: Default::default(),
+                utc_offset_minutes: 0,
+                created_at: now,
+                updated_at: now,
+            })
+            .await;
+        let spend = |cost_usd: f64| {
+            let state = state.clone();
+            async move {
+                let cost_optimizer = state.cost_optimizer.read().await;
+                cost_optimizer
+                    .record_actual_cost(CostInfo {
+                        request_id: Uuid::new_v4(),
+                        provider: LLMProviderType::Custom("metered".to_string()),
+                        model: "metered".to_string(),
+                        input_tokens: 0,
+                        output_tokens: 0,
+                        cost_usd,
+                        timestamp: chrono::Utc::now(),
+                        user_id: Some("alice".to_string()),
+                        project_id: None,
+                    })
+                    .await
+            }
+        };
+
+        // 17 prompt tokens (10 of text plus message overhead) and 100 output tokens: $0.417
+        let request: ChatCompletionRequest = serde_json::from_value(serde_json::json!({
+            "model": "metered",
+            "messages": [{"role": "user", "content": "x".repeat(40)}],
+            "max_tokens": 100,
+            "user": "alice"
+        }))
+        .unwrap();
+        let prepared = prepare_chat_completion(&state, &HeaderMap::new(), &request)
+            .await
+            .unwrap();
+        let budget = prepared.budget.unwrap();
+        assert!((budget.estimated_cost - 0.417).abs() < 1e-9);
+        assert!(!budget.is_warning);
+
+        spend(0.2).await;
+        let prepared = prepare_chat_completion(&state, &HeaderMap::new(), &request)
+            .await
+            .unwrap();
+        let budget = prepared.budget.unwrap();
+        assert!(budget.is_warning);
+        let mut headers = HeaderMap::new();
+        insert_budget_warning(&mut headers, &budget);
+        assert_eq!(headers[BUDGET_REMAINING_HEADER], "0.3830");
+
+        spend(0.5).await;
+        let error = prepare_chat_completion(&state, &HeaderMap::new(), &request)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(error.error_code(), crate::ErrorCode::QuotaExceeded);
+        let quota = error.error.quota.clone().unwrap();
+        assert_eq!(quota.budget_id, "user:alice");
+        assert!((quota.used - 0.7).abs() < 1e-9);
+        assert_eq!(error.into_response().status(), StatusCode::PAYMENT_REQUIRED);
+
+        // Requests nobody is billed for have no budget
+        let mut anonymous = request.clone();
+        anonymous.user = None;
+        let prepared = prepare_chat_completion(&state, &HeaderMap::new(), &anonymous)
+            .await
+            .unwrap();
+        assert!(prepared.budget.is_none());
+    }
+
     #[test]
     fn test_completion_id_format() {
         let id = generate_completion_id();
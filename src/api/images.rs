@@ -0,0 +1,226 @@
+// Image generation endpoint
+// `POST /v1/images/generations` in OpenAI's format, routed to DALL·E, Imagen or Replicate
+
+//! # Images
+//!
+//! `POST /v1/images/generations` takes OpenAI's request (`prompt`, and optionally
+//! `model`, `n`, `size`, `quality`, `response_format`, `style` and `user`) and answers
+//! with OpenAI's response, each image as a URL or base64. The model picks the
+//! [`ImageProvider`](crate::llm::ImageProvider) among those configured by the
+//! environment; without one, `dall-e-2` is used as by OpenAI.
+//!
+//! Each request's cost is recorded with the cost optimizer under the request's user and
+//! tenant, like chat completions, priced per image by model, size and quality.
+
+use axum::{extract::State, http::HeaderMap, Json};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use super::handlers::{llm_error_response, OpenAIApiState};
+use super::types::ErrorResponse;
+use super::usage_export::invalid_param;
+use crate::llm::cost::image_cost;
+use crate::llm::images::{
+    GeneratedImage, ImageQuality, ImageRequest, ImageResponseFormat, ImageSize,
+};
+use crate::llm::LLMError;
+use crate::ErrorCode;
+
+/// Model used when a request names none, as for OpenAI
+pub const DEFAULT_IMAGE_MODEL: &str = "dall-e-2";
+
+/// Most images generated by one request, as for OpenAI
+pub const MAX_IMAGES_PER_REQUEST: u32 = 10;
+
+/// Longest prompt accepted, as for DALL·E 3
+pub const MAX_IMAGE_PROMPT_CHARS: usize = 4000;
+
+/// OpenAI Image Generation Request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageGenerationRequest {
+    pub prompt: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Number of images, 1 by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
+    /// `WIDTHxHEIGHT`, 1024x1024 by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<String>,
+    /// "standard" (default) or "hd"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quality: Option<String>,
+    /// "url" (default) or "b64_json"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<String>,
+    /// "vivid" or "natural"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style: Option<String>,
+    /// End user the request is made for, to attribute its cost
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+/// OpenAI Image Generation Response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImagesResponse {
+    pub created: i64,
+    pub data: Vec<GeneratedImage>,
+}
+
+/// Generate images - POST /v1/images/generations
+pub async fn create_image(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Json(body): Json<ImageGenerationRequest>,
+) -> Result<Json<ImagesResponse>, ErrorResponse> {
+    let user = body.user.clone();
+    let request = image_request(body).map_err(|(message, param)| {
+        invalid_param(message, param).with_error_code(ErrorCode::InvalidInput)
+    })?;
+    debug!("Generating {} image(s) with {}", request.n, request.model);
+    let tenant_id = state.request_tenant(&headers).await?;
+
+    let provider = state
+        .images
+        .provider(&request.model)
+        .map_err(unsupported_model)?;
+    let generation = provider
+        .generate(&request)
+        .await
+        .map_err(|e| llm_error_response(&e, e.to_string()))?;
+
+    let provider_type = provider.provider_type();
+    let cost_usd = image_cost(
+        &provider_type,
+        &request.model,
+        &generation.size.as_string(),
+        request.quality.as_str(),
+        generation.images.len(),
+    );
+    state
+        .record_flat_cost(provider_type, &request.model, cost_usd, user, tenant_id)
+        .await;
+
+    Ok(Json(ImagesResponse {
+        created: chrono::Utc::now().timestamp(),
+        data: generation.images,
+    }))
+}
+
+/// Validate a request and resolve its defaults; fails with the message and parameter
+/// of the first invalid one
+fn image_request(body: ImageGenerationRequest) -> Result<ImageRequest, (String, &'static str)> {
+    if body.prompt.trim().is_empty() || body.prompt.chars().count() > MAX_IMAGE_PROMPT_CHARS {
+        return Err((
+            format!(
+                "Prompt must be between 1 and {} characters",
+                MAX_IMAGE_PROMPT_CHARS
+            ),
+            "prompt",
+        ));
+    }
+    let n = body.n.unwrap_or(1);
+    if !(1..=MAX_IMAGES_PER_REQUEST).contains(&n) {
+        return Err((
+            format!("n must be between 1 and {}", MAX_IMAGES_PER_REQUEST),
+            "n",
+        ));
+    }
+    let size = match body.size.as_deref() {
+        Some(size) => ImageSize::parse(size).ok_or_else(|| {
+            (
+                format!("Size '{}' is not of the form WIDTHxHEIGHT", size),
+                "size",
+            )
+        })?,
+        None => ImageSize::default(),
+    };
+    let quality = match body.quality.as_deref() {
+        Some(quality) => ImageQuality::parse(quality).ok_or_else(|| {
+            (
+                format!("Unknown quality '{}', expected standard or hd", quality),
+                "quality",
+            )
+        })?,
+        None => ImageQuality::default(),
+    };
+    let response_format = match body.response_format.as_deref() {
+        Some(format) => ImageResponseFormat::parse(format).ok_or_else(|| {
+            (
+                format!(
+                    "Unknown response format '{}', expected url or b64_json",
+                    format
+                ),
+                "response_format",
+            )
+        })?,
+        None => ImageResponseFormat::default(),
+    };
+
+    Ok(ImageRequest {
+        model: body
+            .model
+            .unwrap_or_else(|| DEFAULT_IMAGE_MODEL.to_string()),
+        prompt: body.prompt,
+        n,
+        size,
+        quality,
+        response_format,
+        style: body.style,
+    })
+}
+
+/// Error for a model no image provider serves
+fn unsupported_model(error: LLMError) -> ErrorResponse {
+    invalid_param(error.to_string(), "model").with_error_code(error.code())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(json: serde_json::Value) -> ImageGenerationRequest {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_image_request_validation() {
+        let request = image_request(body(serde_json::json!({
+            "prompt": "a lighthouse at dusk",
+            "n": 2,
+            "size": "1792x1024",
+            "quality": "hd",
+            "response_format": "b64_json",
+        })))
+        .unwrap();
+        assert_eq!(request.model, DEFAULT_IMAGE_MODEL);
+        assert_eq!(request.n, 2);
+        assert_eq!(request.size.as_string(), "1792x1024");
+        assert_eq!(request.quality, ImageQuality::Hd);
+        assert_eq!(request.response_format, ImageResponseFormat::B64Json);
+
+        let defaults = image_request(body(serde_json::json!({"prompt": "a cat"}))).unwrap();
+        assert_eq!(defaults.size, ImageSize::square(1024));
+        assert_eq!(defaults.response_format, ImageResponseFormat::Url);
+
+        for (json, param) in [
+            (serde_json::json!({"prompt": " "}), "prompt"),
+            (serde_json::json!({"prompt": "a cat", "n": 11}), "n"),
+            (
+                serde_json::json!({"prompt": "a cat", "size": "large"}),
+                "size",
+            ),
+            (
+                serde_json::json!({"prompt": "a cat", "quality": "ultra"}),
+                "quality",
+            ),
+            (
+                serde_json::json!({"prompt": "a cat", "response_format": "png"}),
+                "response_format",
+            ),
+        ] {
+            assert_eq!(image_request(body(json)).unwrap_err().1, param);
+        }
+    }
+}
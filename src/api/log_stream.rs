@@ -0,0 +1,239 @@
+// Live log streaming for operators
+// A tracing layer that fans structured log events out to `/admin/logs/stream` subscribers
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use tokio::sync::broadcast;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+/// Environment variable holding the bearer token required by admin endpoints
+pub const ADMIN_TOKEN_ENV: &str = "CIRCUIT_BREAKER_ADMIN_TOKEN";
+
+/// Log records buffered per subscriber before the slowest ones start skipping
+const LOG_CHANNEL_CAPACITY: usize = 1024;
+
+lazy_static::lazy_static! {
+    static ref LOG_CHANNEL: broadcast::Sender<LogRecord> = broadcast::channel(LOG_CHANNEL_CAPACITY).0;
+}
+
+/// A single structured log event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogRecord {
+    pub timestamp: DateTime<Utc>,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub fields: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Tracing layer that publishes every event to live log subscribers
+///
+/// Events are only formatted while someone is subscribed, so the layer is cheap to
+/// leave installed.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LogStreamLayer;
+
+/// Layer to install next to the server's regular log output
+pub fn layer() -> LogStreamLayer {
+    LogStreamLayer
+}
+
+/// Subscribe to log events published from now on
+pub fn subscribe() -> broadcast::Receiver<LogRecord> {
+    LOG_CHANNEL.subscribe()
+}
+
+impl<S: Subscriber> Layer<S> for LogStreamLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if LOG_CHANNEL.receiver_count() == 0 {
+            return;
+        }
+
+        let metadata = event.metadata();
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+
+        let _ = LOG_CHANNEL.send(LogRecord {
+            timestamp: Utc::now(),
+            level: metadata.level().to_string(),
+            target: metadata.target().to_string(),
+            message: visitor.message,
+            fields: visitor.fields,
+        });
+    }
+}
+
+#[derive(Default)]
+struct FieldVisitor {
+    message: String,
+    fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl FieldVisitor {
+    fn insert(&mut self, field: &Field, value: serde_json::Value) {
+        if field.name() == "message" {
+            self.message = match value {
+                serde_json::Value::String(message) => message,
+                other => other.to_string(),
+            };
+        } else {
+            self.fields.insert(field.name().to_string(), value);
+        }
+    }
+}
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.insert(field, format!("{:?}", value).into());
+    }
+}
+
+/// Query parameters of `/admin/logs/stream`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LogStreamQuery {
+    /// Most verbose level to include, e.g. `info` includes `warn` and `error`
+    pub level: Option<String>,
+    /// Comma-separated target prefixes, e.g. `circuit_breaker::llm,tower_http`
+    pub target: Option<String>,
+}
+
+/// Level and target filter applied to a log stream
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    pub level: Option<Level>,
+    pub targets: Vec<String>,
+}
+
+impl LogFilter {
+    pub fn from_query(query: &LogStreamQuery) -> Result<Self, String> {
+        let level = query
+            .level
+            .as_deref()
+            .map(|level| {
+                Level::from_str(level).map_err(|_| format!("Unknown log level '{}'", level))
+            })
+            .transpose()?;
+        let targets = query
+            .target
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|target| !target.is_empty())
+            .map(str::to_string)
+            .collect();
+        Ok(Self { level, targets })
+    }
+
+    pub fn matches(&self, record: &LogRecord) -> bool {
+        let level_ok = self
+            .level
+            .is_none_or(|max| Level::from_str(&record.level).is_ok_and(|level| level <= max));
+        let target_ok = self.targets.is_empty()
+            || self
+                .targets
+                .iter()
+                .any(|target| record.target.starts_with(target.as_str()));
+        level_ok && target_ok
+    }
+}
+
+/// Check a request's `Authorization: Bearer` header against the admin token
+pub fn is_authorized(authorization: Option<&str>, admin_token: &str) -> bool {
+    let Some(token) = authorization.and_then(|value| value.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    // Compare in constant time so the token cannot be guessed byte by byte
+    token.len() == admin_token.len()
+        && token
+            .bytes()
+            .zip(admin_token.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    fn record(level: &str, target: &str) -> LogRecord {
+        LogRecord {
+            timestamp: Utc::now(),
+            level: level.to_string(),
+            target: target.to_string(),
+            message: "hello".to_string(),
+            fields: serde_json::Map::new(),
+        }
+    }
+
+    #[test]
+    fn test_filter_by_level_and_target() {
+        let filter = LogFilter::from_query(&LogStreamQuery {
+            level: Some("info".to_string()),
+            target: Some("circuit_breaker::llm, tower_http".to_string()),
+        })
+        .unwrap();
+
+        assert!(filter.matches(&record("WARN", "circuit_breaker::llm::router")));
+        assert!(filter.matches(&record("INFO", "tower_http::trace")));
+        assert!(!filter.matches(&record("DEBUG", "circuit_breaker::llm::router")));
+        assert!(!filter.matches(&record("ERROR", "circuit_breaker::engine")));
+
+        assert!(LogFilter::from_query(&LogStreamQuery {
+            level: Some("loud".to_string()),
+            target: None,
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_layer_publishes_structured_events() {
+        let mut receiver = subscribe();
+        let subscriber = tracing_subscriber::registry().with(layer());
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!(target: "log_stream_test", provider = "openai", retries = 2, "Provider slow");
+        });
+
+        let record = std::iter::from_fn(|| receiver.try_recv().ok())
+            .find(|record| record.target == "log_stream_test")
+            .unwrap();
+        assert_eq!(record.level, "WARN");
+        assert_eq!(record.message, "Provider slow");
+        assert_eq!(record.fields["provider"], "openai");
+        assert_eq!(record.fields["retries"], 2);
+    }
+
+    #[test]
+    fn test_admin_token_check() {
+        assert!(is_authorized(Some("Bearer s3cret"), "s3cret"));
+        assert!(!is_authorized(Some("Bearer s3cre"), "s3cret"));
+        assert!(!is_authorized(Some("s3cret"), "s3cret"));
+        assert!(!is_authorized(None, "s3cret"));
+    }
+}
@@ -1,5 +1,7 @@
 // MCP JWT Authentication Service
 // Implements GitHub Apps-style authentication for Circuit Breaker MCP servers
+// Apps sign their own JWTs; installation and session tokens are signed with the
+// server's rotating keys, published at /.well-known/jwks.json
 
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Duration, Utc};
@@ -9,8 +11,10 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
+use super::mcp_signing_keys::{SigningAlgorithm, SigningKeys};
+use super::mcp_storage::MCPStorage;
 use super::mcp_types::*;
 
 /// JWT Authentication Service for MCP
@@ -25,10 +29,23 @@ pub struct MCPJWTService {
     session_store: Arc<RwLock<HashMap<String, SessionTokenData>>>,
     /// Revoked tokens (token_id -> revoked_at)
     revoked_tokens: Arc<RwLock<HashMap<String, DateTime<Utc>>>>,
-    /// Algorithm used for JWT signing
+    /// Algorithm apps sign their JWTs with
     algorithm: Algorithm,
+    /// Keys installation and session tokens are signed with, published as a JWKS
+    signing_keys: Arc<SigningKeys>,
+    /// Storage sharing the signing keys with the other nodes
+    key_store: Option<Arc<dyn MCPStorage>>,
+    /// When the signing keys were last read back from `key_store`
+    keys_refreshed_at: Arc<RwLock<Option<std::time::Instant>>>,
 }
 
+/// How often the rotation task reads back keys other nodes may have rotated
+const SIGNING_KEY_SYNC_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How long a token naming an unknown key waits before its key is looked up in
+/// storage again, so unknown keys can't turn every request into a storage read
+const SIGNING_KEY_REFRESH_BACKOFF: std::time::Duration = std::time::Duration::from_secs(5);
+
 /// JWT Claims for MCP App authentication
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MCPAppClaims {
@@ -136,7 +153,8 @@ pub enum TokenType {
 }
 
 impl MCPJWTService {
-    /// Create a new JWT service
+    /// Create a new JWT service; the key file of [`SigningKeys::from_env`] is loaded by
+    /// [`MCPJWTService::load_signing_keys`]
     pub fn new() -> Self {
         Self {
             app_private_keys: Arc::new(RwLock::new(HashMap::new())),
@@ -145,7 +163,104 @@ impl MCPJWTService {
             session_store: Arc::new(RwLock::new(HashMap::new())),
             revoked_tokens: Arc::new(RwLock::new(HashMap::new())),
             algorithm: Algorithm::RS256,
+            signing_keys: Arc::new(SigningKeys::new(SigningAlgorithm::from_env())),
+            key_store: None,
+            keys_refreshed_at: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// Share the signing keys with the other nodes through `storage`
+    pub fn with_key_store(mut self, storage: Arc<dyn MCPStorage>) -> Self {
+        self.key_store = Some(storage);
+        self
+    }
+
+    /// Load the configured key file, failing when it can't be used, then take over the
+    /// keys other nodes already share, or share ours when there are none yet
+    pub async fn load_signing_keys(&self) -> Result<()> {
+        self.signing_keys.load_env_key()?;
+        let Some(store) = &self.key_store else {
+            return Ok(());
+        };
+        let stored = store.get_signing_keys().await?;
+        if stored.is_empty() {
+            store
+                .store_signing_keys(&self.signing_keys.export()?)
+                .await?;
+        } else {
+            self.signing_keys.import(&stored)?;
+            info!("Signing MCP tokens with the keys shared through MCP storage");
+        }
+        *self.keys_refreshed_at.write().await = Some(std::time::Instant::now());
+        Ok(())
+    }
+
+    /// Read back the keys shared through storage
+    async fn refresh_signing_keys(&self) -> Result<()> {
+        if let Some(store) = &self.key_store {
+            self.signing_keys.import(&store.get_signing_keys().await?)?;
+            *self.keys_refreshed_at.write().await = Some(std::time::Instant::now());
         }
+        Ok(())
+    }
+
+    /// Rotate the signing keys once the current one is `interval` old, sharing the new
+    /// one; a key another node rotated in the meantime is taken over instead
+    pub async fn rotate_signing_keys_when_due(&self, interval: std::time::Duration) -> Result<()> {
+        self.refresh_signing_keys().await?;
+        if !self.signing_keys.rotation_due(interval) {
+            return Ok(());
+        }
+        self.signing_keys.rotate()?;
+        if let Some(store) = &self.key_store {
+            store
+                .store_signing_keys(&self.signing_keys.export()?)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Verify a token signed by a server key, looking the key up in storage when it was
+    /// rotated by another node since the keys were last read
+    async fn verify_signed<T: serde::de::DeserializeOwned>(&self, token: &str) -> Result<T> {
+        let verified = self.signing_keys.verify(token);
+        if verified.is_err() && self.may_refresh_for(token).await {
+            if let Err(e) = self.refresh_signing_keys().await {
+                warn!("Failed to read MCP signing keys from storage: {}", e);
+            }
+            return self.signing_keys.verify(token);
+        }
+        verified
+    }
+
+    /// Whether `token` names a key not held yet and the keys weren't read back recently
+    async fn may_refresh_for(&self, token: &str) -> bool {
+        if self.key_store.is_none() {
+            return false;
+        }
+        let Some(kid) = jsonwebtoken::decode_header(token).ok().and_then(|h| h.kid) else {
+            return false;
+        };
+        if self.signing_keys.knows(&kid) {
+            return false;
+        }
+        let mut refreshed_at = self.keys_refreshed_at.write().await;
+        if refreshed_at.is_some_and(|at| at.elapsed() < SIGNING_KEY_REFRESH_BACKOFF) {
+            return false;
+        }
+        *refreshed_at = Some(std::time::Instant::now());
+        true
+    }
+
+    /// Sign installation and session tokens with `signing_keys`
+    pub fn with_signing_keys(mut self, signing_keys: SigningKeys) -> Self {
+        self.signing_keys = Arc::new(signing_keys);
+        self
+    }
+
+    /// Keys installation and session tokens are signed with
+    pub fn signing_keys(&self) -> &SigningKeys {
+        &self.signing_keys
     }
 
     /// Register an app with its RSA key pair
@@ -246,12 +361,9 @@ impl MCPJWTService {
             installation.permissions.clone()
         };
 
-        let private_key = {
-            let keys = self.app_private_keys.read().await;
-            keys.get(app_id)
-                .ok_or_else(|| anyhow!("App {} not found", app_id))?
-                .clone()
-        };
+        if !self.app_public_keys.read().await.contains_key(app_id) {
+            return Err(anyhow!("App {} not found", app_id));
+        }
 
         let now = Utc::now();
         let expires_at = now + Duration::hours(1); // Installation tokens last 1 hour
@@ -269,8 +381,8 @@ impl MCPJWTService {
             scopes: None, // TODO: Add scope support
         };
 
-        let header = Header::new(self.algorithm);
-        let token = encode(&header, &claims, &private_key)
+        let (header, signing_key) = self.signing_keys.signer()?;
+        let token = encode(&header, &claims, &signing_key)
             .map_err(|e| anyhow!("Failed to encode installation token: {}", e))?;
 
         info!(
@@ -306,12 +418,14 @@ impl MCPJWTService {
                 .clone()
         };
 
-        let private_key = {
-            let keys = self.app_private_keys.read().await;
-            keys.get(&installation.app_id)
-                .ok_or_else(|| anyhow!("App {} not found", installation.app_id))?
-                .clone()
-        };
+        if !self
+            .app_public_keys
+            .read()
+            .await
+            .contains_key(&installation.app_id)
+        {
+            return Err(anyhow!("App {} not found", installation.app_id));
+        }
 
         let now = Utc::now();
         let expires_at = now + Duration::hours(24); // Session tokens last 24 hours
@@ -329,8 +443,8 @@ impl MCPJWTService {
             jti: token_id.clone(),
         };
 
-        let header = Header::new(self.algorithm);
-        let token = encode(&header, &claims, &private_key)
+        let (header, signing_key) = self.signing_keys.signer()?;
+        let token = encode(&header, &claims, &signing_key)
             .map_err(|e| anyhow!("Failed to encode session token: {}", e))?;
 
         // Store session data
@@ -377,63 +491,14 @@ impl MCPJWTService {
         }
     }
 
-    /// Try to decode token as session token
+    /// Try to decode token as session token, signed by one of the server's keys
     async fn try_decode_as_session_token(&self, token: &str) -> Result<MCPSessionClaims> {
-        // Decode without verification first to get the app_id
-        let unverified: jsonwebtoken::TokenData<MCPSessionClaims> = decode(
-            token,
-            &DecodingKey::from_secret(&[]), // Dummy key for unverified decode
-            &Validation::new(self.algorithm),
-        )
-        .or_else(|_| {
-            // Try with no verification
-            let mut validation = Validation::new(self.algorithm);
-            validation.insecure_disable_signature_validation();
-            decode(token, &DecodingKey::from_secret(&[]), &validation)
-        })?;
-
-        let app_id = &unverified.claims.app_id;
-
-        // Get the proper public key
-        let public_key = {
-            let keys = self.app_public_keys.read().await;
-            keys.get(app_id)
-                .ok_or_else(|| anyhow!("App {} not found", app_id))?
-                .clone()
-        };
-
-        // Now decode with proper verification
-        let verified =
-            decode::<MCPSessionClaims>(token, &public_key, &Validation::new(self.algorithm))
-                .map_err(|e| anyhow!("Token verification failed: {}", e))?;
-
-        Ok(verified.claims)
+        self.verify_signed(token).await
     }
 
-    /// Try to decode token as installation token
+    /// Try to decode token as installation token, signed by one of the server's keys
     async fn try_decode_as_installation_token(&self, token: &str) -> Result<MCPInstallationClaims> {
-        // Similar process as session token
-        let unverified: jsonwebtoken::TokenData<MCPInstallationClaims> =
-            decode(token, &DecodingKey::from_secret(&[]), &{
-                let mut validation = Validation::new(self.algorithm);
-                validation.insecure_disable_signature_validation();
-                validation
-            })?;
-
-        let app_id = &unverified.claims.app_id;
-
-        let public_key = {
-            let keys = self.app_public_keys.read().await;
-            keys.get(app_id)
-                .ok_or_else(|| anyhow!("App {} not found", app_id))?
-                .clone()
-        };
-
-        let verified =
-            decode::<MCPInstallationClaims>(token, &public_key, &Validation::new(self.algorithm))
-                .map_err(|e| anyhow!("Token verification failed: {}", e))?;
-
-        Ok(verified.claims)
+        self.verify_signed(token).await
     }
 
     /// Try to decode token as app token
@@ -564,6 +629,25 @@ impl MCPJWTService {
         })
     }
 
+    /// Spawn the background task rotating the signing keys every `interval`, reading
+    /// back the keys shared by other nodes in between
+    pub fn spawn_signing_key_rotation(
+        self: Arc<Self>,
+        interval: std::time::Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval.min(SIGNING_KEY_SYNC_INTERVAL));
+            // The first tick completes at once; the current key is still fresh
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.rotate_signing_keys_when_due(interval).await {
+                    warn!("Failed to rotate MCP signing key: {}", e);
+                }
+            }
+        })
+    }
+
     /// Revoke a token by its JWT ID
     pub async fn revoke_token(&self, token_id: &str) -> Result<()> {
         let mut revoked = self.revoked_tokens.write().await;
@@ -738,9 +822,24 @@ impl Default for MCPJWTService {
     }
 }
 
+/// The app a token claims to come from, read without verifying it, so failed
+/// authentications can be attributed to the app being impersonated
+pub fn claimed_app_id(token: &str) -> Option<String> {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+
+    let payload = URL_SAFE_NO_PAD.decode(token.split('.').nth(1)?).ok()?;
+    let claims: serde_json::Value = serde_json::from_slice(&payload).ok()?;
+    claims
+        .get("app_id")
+        .or_else(|| claims.get("iss"))
+        .and_then(|app_id| app_id.as_str())
+        .map(str::to_string)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::api::mcp_signing_keys::SigningAlgorithm;
 
     #[tokio::test]
     async fn test_jwt_service_creation() {
@@ -840,10 +939,10 @@ mod tests {
 
     #[tokio::test]
     async fn test_jwt_authentication_flow() {
-        let service = MCPJWTService::new();
-
         // Load test keys
         let (private_key, public_key) = MCPJWTService::load_test_keys().unwrap();
+        let service =
+            MCPJWTService::new().with_signing_keys(SigningKeys::new(SigningAlgorithm::ES256));
 
         // Create and register test app
         let app = MCPJWTService::create_app_with_keys(
@@ -921,6 +1020,12 @@ mod tests {
         );
         assert_eq!(validated_claims.token_type, TokenType::Installation);
 
+        // Installation tokens are signed with a published server key, not the app's
+        let header = jsonwebtoken::decode_header(&installation_token.token).unwrap();
+        assert_eq!(header.alg, Algorithm::ES256);
+        let jwks = service.signing_keys().jwks().unwrap();
+        assert_eq!(jwks.keys[0].common.key_id, header.kid);
+
         // Test 4: Create session token
         let session_permissions = MCPSessionPermissions::default();
         let client_info = ClientInfo {
@@ -955,4 +1060,43 @@ mod tests {
         // Test 6: Token revocation (we would need the JWT ID for this)
         // This would be tested in a more complete integration test
     }
+
+    #[tokio::test]
+    async fn test_signing_keys_shared_between_nodes() {
+        let storage: Arc<dyn MCPStorage> =
+            Arc::new(crate::api::mcp_storage::InMemoryMCPStorage::default());
+        let node = |storage: &Arc<dyn MCPStorage>| {
+            MCPJWTService::new()
+                .with_signing_keys(SigningKeys::new(SigningAlgorithm::ES256))
+                .with_key_store(storage.clone())
+        };
+        let sign = |service: &MCPJWTService| {
+            let (header, key) = service.signing_keys().signer().unwrap();
+            let claims = serde_json::json!({ "exp": Utc::now().timestamp() + 60 });
+            encode(&header, &claims, &key).unwrap()
+        };
+        let first = node(&storage);
+        first.load_signing_keys().await.unwrap();
+        let second = node(&storage);
+        second.load_signing_keys().await.unwrap();
+        assert!(second
+            .verify_signed::<serde_json::Value>(&sign(&first))
+            .await
+            .is_ok());
+
+        // A key one node rotates is looked up by the other when a token names it
+        first
+            .rotate_signing_keys_when_due(std::time::Duration::ZERO)
+            .await
+            .unwrap();
+        *second.keys_refreshed_at.write().await = None;
+        assert!(second
+            .verify_signed::<serde_json::Value>(&sign(&first))
+            .await
+            .is_ok());
+        assert_eq!(
+            jsonwebtoken::decode_header(&sign(&second)).unwrap().kid,
+            jsonwebtoken::decode_header(&sign(&first)).unwrap().kid
+        );
+    }
 }
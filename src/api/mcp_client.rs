@@ -0,0 +1,697 @@
+// Outbound MCP client for the Circuit Breaker MCP server
+// Connects to upstream MCP servers and re-exposes their tools through an instance
+
+//! # MCP Client
+//!
+//! An instance can aggregate the tools of other MCP servers, its upstreams, behind its
+//! own authenticated endpoint. [`MCPClient`] speaks to an upstream over one of two
+//! transports:
+//!
+//! - `stdio`: the server is spawned as a child process and exchanges newline-delimited
+//!   JSON-RPC messages over its stdin and stdout
+//! - `http`: requests are POSTed to the server's endpoint, which answers with either a
+//!   JSON body or an SSE stream carrying the response; an `Mcp-Session-Id` returned by
+//!   `initialize` is sent with every later request
+//!
+//! On connect the client runs the `initialize` handshake and lists the upstream's
+//! tools. Each tool is registered on the instance as an [`MCPProxiedTool`] named
+//! `<prefix>_<tool>`, where the prefix defaults to the upstream's name, so tools of
+//! different upstreams cannot collide. Calls are forwarded with the upstream's own
+//! tool name and its result is passed through unchanged.
+//!
+//! Upstreams live as long as the server process; they are not persisted.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::{oneshot, Mutex, RwLock};
+use tracing::{debug, info, warn};
+
+use super::mcp_logging::MCPLogLevel;
+use super::mcp_tools::{MCPToolCallResult, MCPToolContext, MCPToolHandler};
+use super::mcp_types::{error_codes, MCPContent, MCPError, MCPTool, MCPToolResult};
+use crate::ErrorCode;
+
+/// Protocol version the client offers in `initialize`
+pub const CLIENT_PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// How long an upstream has to answer a request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How to reach an upstream MCP server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum MCPUpstreamTransport {
+    /// Spawn `command` and talk over its stdin and stdout
+    Stdio {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default)]
+        env: HashMap<String, String>,
+    },
+    /// POST JSON-RPC messages to `url`
+    Http {
+        url: String,
+        /// Sent with every request, e.g. `Authorization`
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+}
+
+/// An upstream MCP server whose tools an instance re-exposes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MCPUpstreamConfig {
+    /// Unique per instance
+    pub name: String,
+    pub transport: MCPUpstreamTransport,
+    /// Prefix of the proxied tool names; defaults to `name`
+    #[serde(default)]
+    pub tool_prefix: Option<String>,
+}
+
+impl MCPUpstreamConfig {
+    /// Prefix of the proxied tool names
+    pub fn tool_prefix(&self) -> &str {
+        self.tool_prefix.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// Why an upstream could not be reached or used
+#[derive(Debug, Clone, PartialEq)]
+pub enum MCPClientError {
+    /// The upstream configuration is unusable
+    InvalidConfig(String),
+    /// The upstream could not be started, reached or written to
+    Transport(String),
+    /// The upstream answered with something that is not valid MCP
+    Protocol(String),
+    /// The upstream answered with a JSON-RPC error
+    Remote { code: i32, message: String },
+    /// The upstream did not answer in time
+    Timeout(String),
+}
+
+impl fmt::Display for MCPClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidConfig(reason) => write!(f, "Invalid upstream: {}", reason),
+            Self::Transport(reason) => write!(f, "Upstream unreachable: {}", reason),
+            Self::Protocol(reason) => write!(f, "Upstream protocol error: {}", reason),
+            Self::Remote { code, message } => {
+                write!(f, "Upstream error {}: {}", code, message)
+            }
+            Self::Timeout(method) => write!(f, "Upstream did not answer {} in time", method),
+        }
+    }
+}
+
+impl std::error::Error for MCPClientError {}
+
+impl MCPClientError {
+    /// Stable error code for the error
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::InvalidConfig(_) => ErrorCode::InvalidInput,
+            Self::Transport(_) => ErrorCode::ProviderUnavailable,
+            Self::Protocol(_) | Self::Remote { .. } => ErrorCode::ProviderError,
+            Self::Timeout(_) => ErrorCode::Timeout,
+        }
+    }
+}
+
+impl From<MCPError> for MCPClientError {
+    fn from(error: MCPError) -> Self {
+        Self::Remote {
+            code: error.code,
+            message: error.message,
+        }
+    }
+}
+
+/// Replies awaited by request id
+type Pending = Arc<Mutex<HashMap<i64, oneshot::Sender<Value>>>>;
+
+enum Transport {
+    Stdio {
+        child: Mutex<Child>,
+        stdin: Arc<Mutex<ChildStdin>>,
+        pending: Pending,
+    },
+    Http {
+        client: reqwest::Client,
+        url: String,
+        headers: HashMap<String, String>,
+        session_id: RwLock<Option<String>>,
+    },
+}
+
+/// A connection to an upstream MCP server
+pub struct MCPClient {
+    config: MCPUpstreamConfig,
+    transport: Transport,
+    next_id: AtomicI64,
+    server_info: Value,
+}
+
+impl MCPClient {
+    /// Connect to an upstream and run the `initialize` handshake
+    pub async fn connect(config: MCPUpstreamConfig) -> Result<Self, MCPClientError> {
+        if config.name.is_empty() || !valid_tool_name(config.tool_prefix()) {
+            return Err(MCPClientError::InvalidConfig(format!(
+                "'{}' is not a valid tool prefix; use letters, digits, '_' and '-'",
+                config.tool_prefix()
+            )));
+        }
+        let transport = match &config.transport {
+            MCPUpstreamTransport::Stdio { command, args, env } => {
+                spawn_stdio(&config.name, command, args, env)?
+            }
+            MCPUpstreamTransport::Http { url, headers } => {
+                reqwest::Url::parse(url)
+                    .map_err(|e| MCPClientError::InvalidConfig(format!("{}: {}", url, e)))?;
+                Transport::Http {
+                    client: reqwest::Client::new(),
+                    url: url.clone(),
+                    headers: headers.clone(),
+                    session_id: RwLock::new(None),
+                }
+            }
+        };
+
+        let mut client = Self {
+            config,
+            transport,
+            next_id: AtomicI64::new(1),
+            server_info: Value::Null,
+        };
+        let initialized = client
+            .request(
+                "initialize",
+                serde_json::json!({
+                    "protocolVersion": CLIENT_PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": {
+                        "name": "circuit-breaker",
+                        "version": env!("CARGO_PKG_VERSION")
+                    }
+                }),
+            )
+            .await?;
+        client.server_info = initialized["serverInfo"].clone();
+        client.notify("notifications/initialized").await?;
+        info!(
+            "Connected to upstream MCP server {} ({})",
+            client.config.name, client.server_info
+        );
+        Ok(client)
+    }
+
+    /// Configuration the client was connected with
+    pub fn config(&self) -> &MCPUpstreamConfig {
+        &self.config
+    }
+
+    /// `serverInfo` the upstream reported in `initialize`
+    pub fn server_info(&self) -> &Value {
+        &self.server_info
+    }
+
+    /// All tools the upstream offers, following `nextCursor` pages
+    pub async fn list_tools(&self) -> Result<Vec<MCPTool>, MCPClientError> {
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = match &cursor {
+                Some(cursor) => serde_json::json!({ "cursor": cursor }),
+                None => serde_json::json!({}),
+            };
+            let page = self.request("tools/list", params).await?;
+            for tool in page["tools"].as_array().into_iter().flatten() {
+                tools.push(parse_tool(tool)?);
+            }
+            match page["nextCursor"].as_str() {
+                Some(next) if !next.is_empty() => cursor = Some(next.to_string()),
+                _ => return Ok(tools),
+            }
+        }
+    }
+
+    /// Call a tool of the upstream by its upstream name
+    pub async fn call_tool(
+        &self,
+        name: &str,
+        arguments: &HashMap<String, Value>,
+    ) -> Result<MCPToolResult, MCPClientError> {
+        let result = self
+            .request(
+                "tools/call",
+                serde_json::json!({ "name": name, "arguments": arguments }),
+            )
+            .await?;
+        Ok(parse_tool_result(&result))
+    }
+
+    /// Stop a stdio upstream's process
+    pub async fn close(&self) {
+        if let Transport::Stdio { child, .. } = &self.transport {
+            if let Err(e) = child.lock().await.kill().await {
+                warn!("Failed to stop upstream {}: {}", self.config.name, e);
+            }
+        }
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Result<Value, MCPClientError> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let message = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        });
+        debug!("→ upstream {}: {}", self.config.name, message);
+
+        let reply = match &self.transport {
+            Transport::Stdio { stdin, pending, .. } => {
+                let (sender, receiver) = oneshot::channel();
+                pending.lock().await.insert(id, sender);
+                if let Err(e) = write_line(stdin, &message).await {
+                    pending.lock().await.remove(&id);
+                    return Err(e);
+                }
+                match tokio::time::timeout(REQUEST_TIMEOUT, receiver).await {
+                    Ok(Ok(reply)) => reply,
+                    Ok(Err(_)) => {
+                        return Err(MCPClientError::Transport(
+                            "the upstream process exited".to_string(),
+                        ))
+                    }
+                    Err(_) => {
+                        pending.lock().await.remove(&id);
+                        return Err(MCPClientError::Timeout(method.to_string()));
+                    }
+                }
+            }
+            Transport::Http { .. } => {
+                tokio::time::timeout(REQUEST_TIMEOUT, self.post(&message, Some(id)))
+                    .await
+                    .map_err(|_| MCPClientError::Timeout(method.to_string()))??
+            }
+        };
+
+        if let Some(error) = reply.get("error").filter(|error| !error.is_null()) {
+            let error: MCPError = serde_json::from_value(error.clone()).map_err(|e| {
+                MCPClientError::Protocol(format!("malformed error {}: {}", error, e))
+            })?;
+            return Err(error.into());
+        }
+        Ok(reply.get("result").cloned().unwrap_or(Value::Null))
+    }
+
+    async fn notify(&self, method: &str) -> Result<(), MCPClientError> {
+        let message = serde_json::json!({ "jsonrpc": "2.0", "method": method });
+        match &self.transport {
+            Transport::Stdio { stdin, .. } => write_line(stdin, &message).await,
+            Transport::Http { .. } => self.post(&message, None).await.map(|_| ()),
+        }
+    }
+
+    /// POST a message, returning the reply to request `id` when one is expected
+    async fn post(&self, message: &Value, id: Option<i64>) -> Result<Value, MCPClientError> {
+        let Transport::Http {
+            client,
+            url,
+            headers,
+            session_id,
+        } = &self.transport
+        else {
+            unreachable!("post is only used by the HTTP transport");
+        };
+
+        let mut request = client
+            .post(url)
+            .header("Accept", "application/json, text/event-stream")
+            .json(message);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        if let Some(session_id) = session_id.read().await.as_ref() {
+            request = request.header("Mcp-Session-Id", session_id);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| MCPClientError::Transport(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(MCPClientError::Transport(format!(
+                "{} answered {}",
+                url,
+                response.status()
+            )));
+        }
+        if let Some(session) = response
+            .headers()
+            .get("mcp-session-id")
+            .and_then(|value| value.to_str().ok())
+        {
+            *session_id.write().await = Some(session.to_string());
+        }
+        let Some(id) = id else {
+            return Ok(Value::Null);
+        };
+
+        let is_stream = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|content_type| content_type.starts_with("text/event-stream"));
+        let body = response
+            .text()
+            .await
+            .map_err(|e| MCPClientError::Transport(e.to_string()))?;
+        if is_stream {
+            return sse_reply(&body, id).ok_or_else(|| {
+                MCPClientError::Protocol(format!("no reply to request {} in the stream", id))
+            });
+        }
+        serde_json::from_str(&body)
+            .map_err(|e| MCPClientError::Protocol(format!("invalid JSON reply: {}", e)))
+    }
+}
+
+fn spawn_stdio(
+    name: &str,
+    command: &str,
+    args: &[String],
+    env: &HashMap<String, String>,
+) -> Result<Transport, MCPClientError> {
+    let mut child = Command::new(command)
+        .args(args)
+        .envs(env)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| MCPClientError::Transport(format!("failed to start {}: {}", command, e)))?;
+    let stdin = Arc::new(Mutex::new(child.stdin.take().ok_or_else(|| {
+        MCPClientError::Transport("no stdin for the upstream process".to_string())
+    })?));
+    let stdout = child.stdout.take().ok_or_else(|| {
+        MCPClientError::Transport("no stdout for the upstream process".to_string())
+    })?;
+
+    let pending: Pending = Arc::default();
+    let (reader_pending, reader_stdin, upstream) =
+        (pending.clone(), stdin.clone(), name.to_string());
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(stdout).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let Ok(message) = serde_json::from_str::<Value>(&line) else {
+                debug!("upstream {} wrote a non-JSON line: {}", upstream, line);
+                continue;
+            };
+            let is_reply = message.get("result").is_some() || message.get("error").is_some();
+            match (message["id"].as_i64(), is_reply) {
+                (Some(id), true) => {
+                    if let Some(sender) = reader_pending.lock().await.remove(&id) {
+                        let _ = sender.send(message);
+                    }
+                }
+                // The upstream asks something of the client, which offers no capabilities
+                (Some(_), false) => {
+                    let reply = server_request_reply(&message);
+                    if let Err(e) = write_line(&reader_stdin, &reply).await {
+                        warn!("Failed to answer upstream {}: {}", upstream, e);
+                    }
+                }
+                (None, _) => debug!("upstream {} notification: {}", upstream, message),
+            }
+        }
+        info!("Upstream MCP server {} closed its output", upstream);
+        // Dropping the senders fails every request still waiting
+        reader_pending.lock().await.clear();
+    });
+
+    Ok(Transport::Stdio {
+        child: Mutex::new(child),
+        stdin,
+        pending,
+    })
+}
+
+async fn write_line(stdin: &Mutex<ChildStdin>, message: &Value) -> Result<(), MCPClientError> {
+    let mut line = message.to_string();
+    line.push('\n');
+    let mut stdin = stdin.lock().await;
+    stdin
+        .write_all(line.as_bytes())
+        .await
+        .map_err(|e| MCPClientError::Transport(e.to_string()))?;
+    stdin
+        .flush()
+        .await
+        .map_err(|e| MCPClientError::Transport(e.to_string()))
+}
+
+/// Reply to a request the upstream sent the client: `ping` succeeds, anything else is unknown
+fn server_request_reply(request: &Value) -> Value {
+    if request["method"] == "ping" {
+        serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": {} })
+    } else {
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "error": {
+                "code": error_codes::METHOD_NOT_FOUND,
+                "message": format!("Method not supported by client: {}", request["method"])
+            }
+        })
+    }
+}
+
+/// The JSON-RPC reply to request `id` among the `data:` events of an SSE body
+fn sse_reply(body: &str, id: i64) -> Option<Value> {
+    let mut events = Vec::new();
+    let mut data = String::new();
+    for line in body.lines() {
+        if let Some(value) = line.strip_prefix("data:") {
+            if !data.is_empty() {
+                data.push('\n');
+            }
+            data.push_str(value.strip_prefix(' ').unwrap_or(value));
+        } else if line.is_empty() && !data.is_empty() {
+            events.push(std::mem::take(&mut data));
+        }
+    }
+    if !data.is_empty() {
+        events.push(data);
+    }
+    events
+        .iter()
+        .filter_map(|event| serde_json::from_str::<Value>(event).ok())
+        .find(|message| message["id"].as_i64() == Some(id))
+}
+
+/// Tool names MCP clients accept
+fn valid_tool_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+fn parse_tool(tool: &Value) -> Result<MCPTool, MCPClientError> {
+    let name = tool["name"]
+        .as_str()
+        .ok_or_else(|| MCPClientError::Protocol(format!("tool without a name: {}", tool)))?;
+    Ok(MCPTool {
+        name: name.to_string(),
+        description: tool["description"].as_str().unwrap_or("").to_string(),
+        input_schema: tool
+            .get("inputSchema")
+            .cloned()
+            .unwrap_or_else(|| serde_json::json!({ "type": "object" })),
+    })
+}
+
+fn parse_tool_result(result: &Value) -> MCPToolResult {
+    let content = result["content"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|content| {
+            serde_json::from_value::<MCPContent>(content.clone())
+                .unwrap_or_else(|_| MCPContent::text(content.to_string()))
+        })
+        .collect();
+    MCPToolResult {
+        content,
+        is_error: result["isError"].as_bool().or(Some(false)),
+    }
+}
+
+/// An upstream connected to an instance and the names its tools are offered under
+#[derive(Clone)]
+pub struct MCPUpstream {
+    pub client: Arc<MCPClient>,
+    pub tools: Vec<String>,
+}
+
+/// A tool of an upstream MCP server, offered by an instance under a prefixed name
+pub struct MCPProxiedTool {
+    client: Arc<MCPClient>,
+    upstream_name: String,
+    definition: MCPTool,
+}
+
+impl MCPProxiedTool {
+    /// Proxy `tool` of the upstream behind `client`
+    pub fn new(client: Arc<MCPClient>, tool: MCPTool) -> Self {
+        let definition = MCPTool {
+            name: format!("{}_{}", client.config.tool_prefix(), tool.name),
+            description: format!("[{}] {}", client.config.name, tool.description),
+            input_schema: tool.input_schema,
+        };
+        Self {
+            client,
+            upstream_name: tool.name,
+            definition,
+        }
+    }
+}
+
+#[async_trait]
+impl MCPToolHandler for MCPProxiedTool {
+    fn definition(&self) -> MCPTool {
+        self.definition.clone()
+    }
+
+    async fn call(
+        &self,
+        context: &MCPToolContext<'_>,
+        arguments: &HashMap<String, Value>,
+    ) -> MCPToolCallResult {
+        context.log(
+            MCPLogLevel::Debug,
+            format!(
+                "Calling {} on upstream {}",
+                self.upstream_name, self.client.config.name
+            ),
+        );
+        match self.client.call_tool(&self.upstream_name, arguments).await {
+            Ok(result) => Ok(result),
+            Err(e) => {
+                warn!(
+                    "Proxied tool {} failed on upstream {}: {}",
+                    self.upstream_name, self.client.config.name, e
+                );
+                context.log(
+                    MCPLogLevel::Error,
+                    format!("Upstream {} failed: {}", self.client.config.name, e),
+                );
+                Ok(MCPToolResult {
+                    content: vec![MCPContent::text(e.to_string())],
+                    is_error: Some(true),
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_reply() {
+        let body = "event: message\ndata: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\"}\n\n\
+                    event: message\ndata: {\"jsonrpc\":\"2.0\",\"id\":7,\"result\":{\"tools\":[]}}\n\n";
+        let reply = sse_reply(body, 7).unwrap();
+        assert_eq!(reply["result"]["tools"], serde_json::json!([]));
+        assert!(sse_reply(body, 8).is_none());
+    }
+
+    #[test]
+    fn test_parse_tool_result() {
+        let result = parse_tool_result(&serde_json::json!({
+            "content": [{"type": "text", "text": "42"}, {"type": "audio", "data": "..."}],
+            "isError": true
+        }));
+        assert_eq!(result.is_error, Some(true));
+        assert_eq!(result.content.len(), 2);
+        assert!(matches!(&result.content[0], MCPContent::Text { text } if text == "42"));
+    }
+
+    #[test]
+    fn test_upstream_config() {
+        let config: MCPUpstreamConfig = serde_json::from_value(serde_json::json!({
+            "name": "files",
+            "transport": {"type": "stdio", "command": "mcp-files", "args": ["--root", "/srv"]}
+        }))
+        .unwrap();
+        assert_eq!(config.tool_prefix(), "files");
+        assert!(matches!(
+            config.transport,
+            MCPUpstreamTransport::Stdio { ref args, .. } if args.len() == 2
+        ));
+        assert!(!valid_tool_name("my files"));
+    }
+
+    #[tokio::test]
+    async fn test_stdio_upstream() {
+        // A shell stand-in for an MCP server answering initialize, tools/list and tools/call
+        let script = r#"
+            while IFS= read -r line; do
+              id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+              [ -z "$id" ] && continue
+              case "$line" in
+                *'"initialize"'*) printf '{"jsonrpc":"2.0","id":%s,"result":{"protocolVersion":"2024-11-05","capabilities":{"tools":{}},"serverInfo":{"name":"echo"}}}\n' "$id" ;;
+                *'"tools/list"'*) printf '{"jsonrpc":"2.0","id":%s,"result":{"tools":[{"name":"echo","description":"Echo","inputSchema":{"type":"object"}}]}}\n' "$id" ;;
+                *'"tools/call"'*) printf '{"jsonrpc":"2.0","id":%s,"result":{"content":[{"type":"text","text":"echoed"}]}}\n' "$id" ;;
+                *) printf '{"jsonrpc":"2.0","id":%s,"error":{"code":-32601,"message":"nope"}}\n' "$id" ;;
+              esac
+            done
+        "#;
+        let client = MCPClient::connect(MCPUpstreamConfig {
+            name: "echo".to_string(),
+            transport: MCPUpstreamTransport::Stdio {
+                command: "sh".to_string(),
+                args: vec!["-c".to_string(), script.to_string()],
+                env: HashMap::new(),
+            },
+            tool_prefix: Some("up".to_string()),
+        })
+        .await
+        .unwrap();
+        assert_eq!(client.server_info()["name"], "echo");
+
+        let tools = client.list_tools().await.unwrap();
+        assert_eq!(tools.len(), 1);
+        let proxied = MCPProxiedTool::new(Arc::new(client), tools[0].clone());
+        assert_eq!(proxied.definition().name, "up_echo");
+
+        let result = proxied
+            .client
+            .call_tool("echo", &HashMap::new())
+            .await
+            .unwrap();
+        assert!(matches!(&result.content[0], MCPContent::Text { text } if text == "echoed"));
+        assert_eq!(
+            proxied.client.request("resources/list", Value::Null).await,
+            Err(MCPClientError::Remote {
+                code: -32601,
+                message: "nope".to_string()
+            })
+        );
+        proxied.client.close().await;
+    }
+}
@@ -0,0 +1,290 @@
+// MCP argument completion for the Circuit Breaker MCP server
+// Answers `completion/complete` with suggestions for prompt arguments and resource URIs
+
+//! # MCP Completions
+//!
+//! `completion/complete` suggests values for one argument of a prompt or of a
+//! resource URI template, matching what the client typed so far:
+//!
+//! - prompt arguments suggest the `values` their template declares
+//! - project arguments (`project`, `project_id`, `project_path`) suggest the paths of
+//!   the projects the instance's OAuth user can reach on GitLab or GitHub
+//! - `context://` resource URIs suggest the instance's project contexts
+//!
+//! Provider project lists are fetched once per instance and kept in a
+//! [`CompletionCache`] for [`COMPLETION_CACHE_TTL`], so typing does not turn into a
+//! provider API call per keystroke.
+
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::{debug, warn};
+
+use super::mcp_server::MCPServerManager;
+use super::mcp_tools::{provider_headers, toolset};
+use super::mcp_types::MCPServerInstance;
+use super::oauth::OAuthProviderType;
+
+/// Most values one completion returns
+pub const MAX_COMPLETION_VALUES: usize = 100;
+
+/// How long provider lookups are reused
+pub const COMPLETION_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Argument names completed with provider project paths
+const PROJECT_ARGUMENTS: &[&str] = &["project", "project_id", "project_path"];
+
+/// What a completion is for
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type")]
+pub enum MCPCompletionRef {
+    #[serde(rename = "ref/prompt")]
+    Prompt { name: String },
+    #[serde(rename = "ref/resource")]
+    Resource { uri: String },
+}
+
+/// Argument being completed and what the client typed so far
+#[derive(Debug, Clone, Deserialize)]
+pub struct MCPCompletionArgument {
+    pub name: String,
+    #[serde(default)]
+    pub value: String,
+}
+
+/// MCP `completion/complete` request
+#[derive(Debug, Clone, Deserialize)]
+pub struct MCPCompleteRequest {
+    #[serde(rename = "ref")]
+    pub reference: MCPCompletionRef,
+    pub argument: MCPCompletionArgument,
+}
+
+/// Whether an argument is completed with provider project paths
+pub fn is_project_argument(name: &str) -> bool {
+    PROJECT_ARGUMENTS.contains(&name)
+}
+
+/// The `completion/complete` result for `candidates` matching `value`: case-insensitive
+/// substring matches, prefix matches first, at most [`MAX_COMPLETION_VALUES`] of them
+pub fn completion(candidates: &[String], value: &str) -> Value {
+    let value = value.to_lowercase();
+    let mut matches: Vec<&String> = candidates
+        .iter()
+        .filter(|candidate| candidate.to_lowercase().contains(&value))
+        .collect();
+    matches.sort_by_key(|candidate| !candidate.to_lowercase().starts_with(&value));
+
+    let total = matches.len();
+    matches.truncate(MAX_COMPLETION_VALUES);
+    serde_json::json!({
+        "completion": {
+            "values": matches,
+            "total": total,
+            "hasMore": total > MAX_COMPLETION_VALUES
+        }
+    })
+}
+
+// When values were fetched, and the values
+type CacheEntry = (Instant, Vec<String>);
+
+/// Provider lookups of completion values, by instance, reused for a TTL
+#[derive(Clone, Default)]
+pub struct CompletionCache {
+    entries: Arc<RwLock<HashMap<String, CacheEntry>>>,
+}
+
+impl CompletionCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Values cached for an instance less than `ttl` ago
+    pub async fn get(&self, instance_id: &str, ttl: Duration) -> Option<Vec<String>> {
+        let entries = self.entries.read().await;
+        entries
+            .get(instance_id)
+            .filter(|(fetched, _)| fetched.elapsed() < ttl)
+            .map(|(_, values)| values.clone())
+    }
+
+    pub async fn insert(&self, instance_id: &str, values: Vec<String>) {
+        self.entries
+            .write()
+            .await
+            .insert(instance_id.to_string(), (Instant::now(), values));
+    }
+
+    /// Forget an instance's values, e.g. after its OAuth user changed
+    pub async fn invalidate(&self, instance_id: &str) {
+        self.entries.write().await.remove(instance_id);
+    }
+}
+
+/// Paths of the projects the instance's OAuth user can reach, from the cache when fresh
+pub async fn provider_projects(
+    manager: &MCPServerManager,
+    instance: &MCPServerInstance,
+) -> Result<Vec<String>, String> {
+    if let Some(projects) = manager
+        .completion_cache
+        .get(&instance.instance_id, COMPLETION_CACHE_TTL)
+        .await
+    {
+        return Ok(projects);
+    }
+
+    let (provider, url, path_field) = match toolset(instance) {
+        "gitlab" => (
+            OAuthProviderType::GitLab,
+            "https://gitlab.com/api/v4/projects?membership=true&simple=true&per_page=100&order_by=last_activity_at",
+            "path_with_namespace",
+        ),
+        "github" => (
+            OAuthProviderType::GitHub,
+            "https://api.github.com/user/repos?per_page=100&sort=pushed",
+            "full_name",
+        ),
+        _ => return Ok(Vec::new()),
+    };
+
+    debug!(
+        "Fetching project paths for completions of instance {}",
+        instance.instance_id
+    );
+    let response = manager
+        .make_authenticated_api_request(
+            &provider,
+            &instance.installation_id,
+            None,
+            reqwest::Method::GET,
+            url,
+            None,
+            Some(provider_headers(&provider)),
+        )
+        .await?;
+    if !response.status().is_success() {
+        return Err(format!("API error: {}", response.status()));
+    }
+    let projects: Vec<Value> = response.json().await.map_err(|e| e.to_string())?;
+    let projects: Vec<String> = projects
+        .iter()
+        .filter_map(|project| project.get(path_field).and_then(Value::as_str))
+        .map(str::to_string)
+        .collect();
+
+    manager
+        .completion_cache
+        .insert(&instance.instance_id, projects.clone())
+        .await;
+    Ok(projects)
+}
+
+/// Candidate values of a completion request, before matching what was typed.
+/// Fails only when the request names a prompt the instance does not have.
+pub async fn candidates(
+    manager: &MCPServerManager,
+    instance: &MCPServerInstance,
+    request: &MCPCompleteRequest,
+) -> Result<Vec<String>, String> {
+    let argument = &request.argument.name;
+    match &request.reference {
+        MCPCompletionRef::Prompt { name } => {
+            let prompt = manager
+                .get_prompts(&instance.instance_id)
+                .await
+                .into_iter()
+                .find(|prompt| &prompt.name == name)
+                .ok_or_else(|| format!("Prompt '{}' not found", name))?;
+            let Some(declared) = prompt.arguments.iter().find(|a| &a.name == argument) else {
+                return Ok(Vec::new());
+            };
+            if !declared.values.is_empty() {
+                return Ok(declared.values.clone());
+            }
+        }
+        MCPCompletionRef::Resource { uri } if uri.starts_with("context://") => {
+            return Ok(instance.project_contexts.clone());
+        }
+        MCPCompletionRef::Resource { .. } => {}
+    }
+
+    if !is_project_argument(argument) {
+        return Ok(Vec::new());
+    }
+    // Completions are a convenience; a failed lookup offers nothing rather than failing
+    Ok(provider_projects(manager, instance)
+        .await
+        .unwrap_or_else(|e| {
+            warn!(
+                "Project completions unavailable for instance {}: {}",
+                instance.instance_id, e
+            );
+            Vec::new()
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn test_completion_matches_prefix_first() {
+        let candidates = strings(&["group/circuit-breaker", "circuit/tools", "other/repo"]);
+        let result = completion(&candidates, "CIRC");
+        assert_eq!(
+            result["completion"]["values"],
+            serde_json::json!(["circuit/tools", "group/circuit-breaker"])
+        );
+        assert_eq!(result["completion"]["total"], 2);
+        assert_eq!(result["completion"]["hasMore"], false);
+    }
+
+    #[test]
+    fn test_completion_caps_values() {
+        let candidates: Vec<String> = (0..150).map(|n| format!("project-{}", n)).collect();
+        let result = completion(&candidates, "");
+        assert_eq!(
+            result["completion"]["values"].as_array().unwrap().len(),
+            MAX_COMPLETION_VALUES
+        );
+        assert_eq!(result["completion"]["total"], 150);
+        assert_eq!(result["completion"]["hasMore"], true);
+    }
+
+    #[tokio::test]
+    async fn test_cache_expires() {
+        let cache = CompletionCache::new();
+        cache.insert("instance", strings(&["a/b"])).await;
+        assert_eq!(
+            cache.get("instance", COMPLETION_CACHE_TTL).await,
+            Some(strings(&["a/b"]))
+        );
+        assert!(cache.get("instance", Duration::ZERO).await.is_none());
+
+        cache.invalidate("instance").await;
+        assert!(cache.get("instance", COMPLETION_CACHE_TTL).await.is_none());
+    }
+
+    #[test]
+    fn test_parse_complete_request() {
+        let request: MCPCompleteRequest = serde_json::from_value(serde_json::json!({
+            "ref": {"type": "ref/resource", "uri": "context://{context_id}"},
+            "argument": {"name": "context_id", "value": "te"}
+        }))
+        .unwrap();
+        assert!(matches!(
+            request.reference,
+            MCPCompletionRef::Resource { .. }
+        ));
+        assert_eq!(request.argument.value, "te");
+    }
+}
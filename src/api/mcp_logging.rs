@@ -0,0 +1,101 @@
+// MCP logging capability for the Circuit Breaker MCP server
+// Sends `notifications/message` to clients at or above the level they chose
+// with `logging/setLevel`
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::{Arc, RwLock};
+use tokio::sync::mpsc;
+
+/// Syslog severities of MCP log messages, least severe first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MCPLogLevel {
+    Debug,
+    Info,
+    Notice,
+    Warning,
+    Error,
+    Critical,
+    Alert,
+    Emergency,
+}
+
+/// Level a session logs at until its client sets one
+pub const DEFAULT_LOG_LEVEL: MCPLogLevel = MCPLogLevel::Info;
+
+/// Logger of one client session; clones share the session's level and transport
+#[derive(Debug, Clone)]
+pub struct MCPLogger {
+    level: Arc<RwLock<MCPLogLevel>>,
+    notifications: mpsc::UnboundedSender<Value>,
+}
+
+impl MCPLogger {
+    /// A logger and the receiver its transport forwards notifications from
+    pub fn channel() -> (Self, mpsc::UnboundedReceiver<Value>) {
+        let (notifications, rx) = mpsc::unbounded_channel();
+        let logger = Self {
+            level: Arc::new(RwLock::new(DEFAULT_LOG_LEVEL)),
+            notifications,
+        };
+        (logger, rx)
+    }
+
+    /// Least severe level sent to the client
+    pub fn level(&self) -> MCPLogLevel {
+        *self.level.read().unwrap()
+    }
+
+    pub fn set_level(&self, level: MCPLogLevel) {
+        *self.level.write().unwrap() = level;
+    }
+
+    /// Send `data` to the client when `level` is at or above the session's level
+    pub fn log(&self, level: MCPLogLevel, logger: &str, data: impl Into<Value>) {
+        if level < self.level() {
+            return;
+        }
+        let notification = serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/message",
+            "params": {
+                "level": level,
+                "logger": logger,
+                "data": data.into()
+            }
+        });
+        // The transport is gone once the session ends; nothing is left to tell
+        let _ = self.notifications.send(notification);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_logger_filters_by_level() {
+        let (logger, mut rx) = MCPLogger::channel();
+        logger.log(MCPLogLevel::Debug, "tools", "hidden");
+        logger.log(MCPLogLevel::Error, "tools", "shown");
+
+        let notification = rx.try_recv().unwrap();
+        assert_eq!(notification["method"], "notifications/message");
+        assert_eq!(notification["params"]["level"], "error");
+        assert_eq!(notification["params"]["data"], "shown");
+        assert!(rx.try_recv().is_err());
+
+        logger.clone().set_level(MCPLogLevel::Debug);
+        logger.log(MCPLogLevel::Debug, "tools", "now shown");
+        assert_eq!(rx.try_recv().unwrap()["params"]["level"], "debug");
+    }
+
+    #[test]
+    fn test_log_level_order() {
+        let level: MCPLogLevel = serde_json::from_value("warning".into()).unwrap();
+        assert!(level > MCPLogLevel::Notice);
+        assert!(level < MCPLogLevel::Emergency);
+        assert!(serde_json::from_value::<MCPLogLevel>("verbose".into()).is_err());
+    }
+}
@@ -5,7 +5,7 @@
 //! registers providers when the server starts.
 
 use crate::api::mcp_server::MCPServerManager;
-use crate::api::oauth::{OAuthProvider, OAuthProviderType};
+use crate::api::oauth::{OAuthManager, OAuthProvider, OAuthProviderType};
 use anyhow::{anyhow, Result};
 use std::env;
 use tracing::{debug, error, info, warn};
@@ -27,6 +27,9 @@ pub struct OAuthProviderConfig {
     pub client_secret: String,
     pub scope: Vec<String>,
     pub enabled: bool,
+    /// Endpoints replacing the provider's defaults; Okta has none to replace
+    pub auth_url: Option<String>,
+    pub token_url: Option<String>,
 }
 
 impl OAuthConfig {
@@ -76,6 +79,8 @@ impl OAuthConfig {
                     client_secret,
                     scope,
                     enabled: true,
+                    auth_url: None,
+                    token_url: None,
                 });
             }
         }
@@ -100,6 +105,8 @@ impl OAuthConfig {
                     client_secret,
                     scope,
                     enabled: true,
+                    auth_url: None,
+                    token_url: None,
                 });
             }
         }
@@ -113,7 +120,7 @@ impl OAuthConfig {
                 && client_secret != "your_google_client_secret_here"
             {
                 let scope = env::var("GOOGLE_OAUTH_SCOPE")
-                    .unwrap_or_else(|_| "openid,profile,email".to_string())
+                    .unwrap_or_else(|_| "openid,profile,email,https://www.googleapis.com/auth/drive.readonly,https://www.googleapis.com/auth/documents.readonly".to_string())
                     .split(',')
                     .map(|s| s.trim().to_string())
                     .collect();
@@ -124,6 +131,155 @@ impl OAuthConfig {
                     client_secret,
                     scope,
                     enabled: true,
+                    auth_url: None,
+                    token_url: None,
+                });
+            }
+        }
+
+        // Atlassian (Jira Cloud) OAuth provider
+        if let (Ok(client_id), Ok(client_secret)) = (
+            env::var("ATLASSIAN_OAUTH_CLIENT_ID"),
+            env::var("ATLASSIAN_OAUTH_CLIENT_SECRET"),
+        ) {
+            if client_id != "your_atlassian_client_id_here"
+                && client_secret != "your_atlassian_client_secret_here"
+            {
+                let scope = env::var("ATLASSIAN_OAUTH_SCOPE")
+                    .unwrap_or_else(|_| "read:jira-work,write:jira-work,offline_access".to_string())
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .collect();
+
+                providers.push(OAuthProviderConfig {
+                    provider_type: OAuthProviderType::Atlassian,
+                    client_id,
+                    client_secret,
+                    scope,
+                    enabled: true,
+                    auth_url: None,
+                    token_url: None,
+                });
+            }
+        }
+
+        // Slack OAuth provider
+        if let (Ok(client_id), Ok(client_secret)) = (
+            env::var("SLACK_OAUTH_CLIENT_ID"),
+            env::var("SLACK_OAUTH_CLIENT_SECRET"),
+        ) {
+            if client_id != "your_slack_client_id_here"
+                && client_secret != "your_slack_client_secret_here"
+            {
+                let scope = env::var("SLACK_OAUTH_SCOPE")
+                    .unwrap_or_else(|_| {
+                        "channels:read,channels:history,groups:read,groups:history,chat:write"
+                            .to_string()
+                    })
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .collect();
+
+                providers.push(OAuthProviderConfig {
+                    provider_type: OAuthProviderType::Slack,
+                    client_id,
+                    client_secret,
+                    scope,
+                    enabled: true,
+                    auth_url: None,
+                    token_url: None,
+                });
+            }
+        }
+
+        // Bitbucket OAuth provider; scopes are those configured on the OAuth consumer
+        if let (Ok(client_id), Ok(client_secret)) = (
+            env::var("BITBUCKET_OAUTH_CLIENT_ID"),
+            env::var("BITBUCKET_OAUTH_CLIENT_SECRET"),
+        ) {
+            if client_id != "your_bitbucket_client_id_here"
+                && client_secret != "your_bitbucket_client_secret_here"
+            {
+                let scope = env::var("BITBUCKET_OAUTH_SCOPE")
+                    .unwrap_or_else(|_| "account,repository,pullrequest".to_string())
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .collect();
+
+                providers.push(OAuthProviderConfig {
+                    provider_type: OAuthProviderType::Bitbucket,
+                    client_id,
+                    client_secret,
+                    scope,
+                    enabled: true,
+                    auth_url: None,
+                    token_url: None,
+                });
+            }
+        }
+
+        // Azure AD OAuth provider, for one tenant or for any account with "common"
+        if let (Ok(client_id), Ok(client_secret)) = (
+            env::var("AZURE_AD_OAUTH_CLIENT_ID"),
+            env::var("AZURE_AD_OAUTH_CLIENT_SECRET"),
+        ) {
+            if client_id != "your_azure_ad_client_id_here"
+                && client_secret != "your_azure_ad_client_secret_here"
+            {
+                let scope = env::var("AZURE_AD_OAUTH_SCOPE")
+                    .unwrap_or_else(|_| "openid,profile,email,offline_access,User.Read".to_string())
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .collect();
+                let provider = OAuthManager::create_azure_ad_provider(
+                    client_id.clone(),
+                    client_secret.clone(),
+                    String::new(),
+                    env::var("AZURE_AD_TENANT_ID").ok(),
+                );
+
+                providers.push(OAuthProviderConfig {
+                    provider_type: OAuthProviderType::AzureAd,
+                    client_id,
+                    client_secret,
+                    scope,
+                    enabled: true,
+                    auth_url: Some(provider.auth_url),
+                    token_url: Some(provider.token_url),
+                });
+            }
+        }
+
+        // Okta OAuth provider, which also needs the Okta org domain
+        if let (Ok(client_id), Ok(client_secret), Ok(okta_domain)) = (
+            env::var("OKTA_OAUTH_CLIENT_ID"),
+            env::var("OKTA_OAUTH_CLIENT_SECRET"),
+            env::var("OKTA_DOMAIN"),
+        ) {
+            if client_id != "your_okta_client_id_here"
+                && client_secret != "your_okta_client_secret_here"
+            {
+                let scope = env::var("OKTA_OAUTH_SCOPE")
+                    .unwrap_or_else(|_| "openid,profile,email,offline_access".to_string())
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .collect();
+                let provider = OAuthManager::create_okta_provider(
+                    client_id.clone(),
+                    client_secret.clone(),
+                    String::new(),
+                    okta_domain,
+                    env::var("OKTA_AUTHORIZATION_SERVER").ok(),
+                );
+
+                providers.push(OAuthProviderConfig {
+                    provider_type: OAuthProviderType::Okta,
+                    client_id,
+                    client_secret,
+                    scope,
+                    enabled: true,
+                    auth_url: Some(provider.auth_url),
+                    token_url: Some(provider.token_url),
                 });
             }
         }
@@ -162,7 +318,9 @@ impl std::str::FromStr for OAuthProviderType {
             "github" => Ok(OAuthProviderType::GitHub),
             "gitlab" => Ok(OAuthProviderType::GitLab),
             "google" => Ok(OAuthProviderType::Google),
-            other => Ok(OAuthProviderType::Custom(other.to_string())),
+            "atlassian" | "jira" => Ok(OAuthProviderType::Atlassian),
+            "azure_ad" | "azuread" | "azure" | "microsoft" => Ok(OAuthProviderType::AzureAd),
+            other => Ok(OAuthProviderType::from_name(other)),
         }
     }
 }
@@ -244,7 +402,10 @@ fn create_oauth_provider(
     config: &OAuthProviderConfig,
     callback_url: &str,
 ) -> Result<OAuthProvider> {
-    let (auth_url, token_url) = get_provider_urls(&config.provider_type)?;
+    let (auth_url, token_url) = match (&config.auth_url, &config.token_url) {
+        (Some(auth_url), Some(token_url)) => (auth_url.clone(), token_url.clone()),
+        _ => get_provider_urls(&config.provider_type)?,
+    };
 
     Ok(OAuthProvider {
         provider_type: config.provider_type.clone(),
@@ -259,24 +420,15 @@ fn create_oauth_provider(
 
 /// Get OAuth URLs for different providers
 fn get_provider_urls(provider_type: &OAuthProviderType) -> Result<(String, String)> {
-    match provider_type {
-        OAuthProviderType::GitHub => Ok((
-            "https://github.com/login/oauth/authorize".to_string(),
-            "https://github.com/login/oauth/access_token".to_string(),
-        )),
-        OAuthProviderType::GitLab => Ok((
-            "https://gitlab.com/oauth/authorize".to_string(),
-            "https://gitlab.com/oauth/token".to_string(),
-        )),
-        OAuthProviderType::Google => Ok((
-            "https://accounts.google.com/o/oauth2/v2/auth".to_string(),
-            "https://oauth2.googleapis.com/token".to_string(),
-        )),
-        OAuthProviderType::Custom(name) => Err(anyhow!(
-            "Custom OAuth provider '{}' requires manual configuration",
-            name
-        )),
-    }
+    provider_type
+        .default_endpoints()
+        .map(|(auth_url, token_url)| (auth_url.to_string(), token_url.to_string()))
+        .ok_or_else(|| {
+            anyhow!(
+                "{} OAuth provider requires manual configuration",
+                format_provider_type(provider_type)
+            )
+        })
 }
 
 /// Format provider type for display
@@ -285,6 +437,11 @@ fn format_provider_type(provider_type: &OAuthProviderType) -> String {
         OAuthProviderType::GitHub => "GitHub".to_string(),
         OAuthProviderType::GitLab => "GitLab".to_string(),
         OAuthProviderType::Google => "Google".to_string(),
+        OAuthProviderType::Atlassian => "Atlassian".to_string(),
+        OAuthProviderType::Slack => "Slack".to_string(),
+        OAuthProviderType::Bitbucket => "Bitbucket".to_string(),
+        OAuthProviderType::AzureAd => "Azure AD".to_string(),
+        OAuthProviderType::Okta => "Okta".to_string(),
         OAuthProviderType::Custom(name) => name.clone(),
     }
 }
@@ -314,6 +471,33 @@ pub fn print_oauth_setup_instructions() {
     println!("   - Create OAuth 2.0 Client ID");
     println!("   - Authorized redirect URI: http://localhost:8080/mcp/remote/oauth/callback");
     println!();
+    println!("   🔷 Atlassian (Jira):");
+    println!("   - Go to: https://developer.atlassian.com/console/myapps/");
+    println!("   - Create an OAuth 2.0 integration with Jira API permissions");
+    println!("   - Callback URL: http://localhost:8080/mcp/remote/oauth/callback");
+    println!();
+    println!("   💬 Slack:");
+    println!("   - Go to: https://api.slack.com/apps");
+    println!("   - Create an app and add bot token scopes under 'OAuth & Permissions'");
+    println!("   - Redirect URL: http://localhost:8080/mcp/remote/oauth/callback");
+    println!();
+    println!("   🪣 Bitbucket:");
+    println!("   - Go to: Workspace settings > OAuth consumers > Add consumer");
+    println!("   - Grant the Account, Repositories and Pull requests permissions");
+    println!("   - Callback URL: http://localhost:8080/mcp/remote/oauth/callback");
+    println!();
+    println!("   🪟 Azure AD:");
+    println!("   - Go to: https://portal.azure.com > Microsoft Entra ID > App registrations");
+    println!("   - Register an app with a Web redirect URI and create a client secret");
+    println!("   - Redirect URI: http://localhost:8080/mcp/remote/oauth/callback");
+    println!("   - Set AZURE_AD_TENANT_ID to restrict sign-in to your tenant");
+    println!();
+    println!("   🔑 Okta:");
+    println!("   - Go to: your Okta admin console > Applications > Create App Integration");
+    println!("   - Choose OIDC and Web Application, with the Refresh Token grant");
+    println!("   - Sign-in redirect URI: http://localhost:8080/mcp/remote/oauth/callback");
+    println!("   - Set OKTA_DOMAIN to your org domain, e.g. dev-123456.okta.com");
+    println!();
     println!("2. Update your .env file:");
     println!();
     println!("   MCP_OAUTH_ENABLED=true");
@@ -424,6 +608,34 @@ mod tests {
         assert!(token_url.contains("github.com"));
     }
 
+    #[test]
+    fn test_additional_providers() {
+        assert_eq!(
+            "azure".parse::<OAuthProviderType>().unwrap(),
+            OAuthProviderType::AzureAd
+        );
+        assert_eq!(
+            "Bitbucket".parse::<OAuthProviderType>().unwrap(),
+            OAuthProviderType::Bitbucket
+        );
+        assert!(get_provider_urls(&OAuthProviderType::Okta).is_err());
+
+        let config = OAuthProviderConfig {
+            provider_type: OAuthProviderType::Okta,
+            client_id: "test_id".to_string(),
+            client_secret: "test_secret".to_string(),
+            scope: vec!["openid".to_string()],
+            enabled: true,
+            auth_url: Some("https://dev-1.okta.com/oauth2/default/v1/authorize".to_string()),
+            token_url: Some("https://dev-1.okta.com/oauth2/default/v1/token".to_string()),
+        };
+        let provider = create_oauth_provider(&config, "http://localhost/callback").unwrap();
+        assert_eq!(
+            provider.token_url,
+            "https://dev-1.okta.com/oauth2/default/v1/token"
+        );
+    }
+
     #[test]
     fn test_config_validation() {
         let config = OAuthConfig {
@@ -436,6 +648,8 @@ mod tests {
                 client_secret: "test_secret".to_string(),
                 scope: vec!["read:user".to_string()],
                 enabled: true,
+                auth_url: None,
+                token_url: None,
             }],
         };
 
@@ -0,0 +1,409 @@
+// MCP prompt templates for the Circuit Breaker MCP server
+// Validates templates and renders them into MCP message lists for `prompts/get`
+
+//! # MCP Prompt Templates
+//!
+//! Each instance serves the built-in prompts from [`default_templates`] plus the
+//! templates stored for it through the management API, a stored template replacing a
+//! built-in one of the same name:
+//!
+//! - `GET /mcp/instances/{id}/prompts` lists the instance's templates
+//! - `PUT /mcp/instances/{id}/prompts/{name}` creates or replaces a template
+//! - `DELETE /mcp/instances/{id}/prompts/{name}` removes a stored template
+//!
+//! Message text takes `{{argument}}` placeholders, and `{{#argument}}…{{/argument}}`
+//! sections that are only rendered when the argument is given. Templates are checked
+//! when they are stored, so every placeholder names a declared argument. `prompts/get`
+//! rejects arguments a template does not declare and requires the ones marked
+//! `required`; optional arguments left out render as empty text. An argument may list
+//! `values` that `completion/complete` suggests to clients.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use super::mcp_sampling::{SamplingContent, SamplingMessage, SamplingRole};
+use super::mcp_types::{
+    error_codes, MCPPrompt, MCPPromptArgument, MCPPromptTemplate, MCPPromptTemplateMessage,
+};
+use crate::ErrorCode;
+
+/// Longest prompt name accepted
+const MAX_PROMPT_NAME_LENGTH: usize = 128;
+
+/// Why a prompt template could not be stored or rendered
+#[derive(Debug, Clone, PartialEq)]
+pub enum PromptTemplateError {
+    /// The template is malformed
+    InvalidTemplate(String),
+    /// A required argument was not given
+    MissingArgument(String),
+    /// An argument the template does not declare was given
+    UnknownArgument(String),
+    /// The instance has no prompt of this name
+    NotFound(String),
+    /// Templates could not be read or written
+    Storage(String),
+}
+
+impl fmt::Display for PromptTemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidTemplate(reason) => write!(f, "Invalid prompt template: {}", reason),
+            Self::MissingArgument(name) => write!(f, "Missing required argument '{}'", name),
+            Self::UnknownArgument(name) => write!(f, "Unknown argument '{}'", name),
+            Self::NotFound(name) => write!(f, "Prompt '{}' not found", name),
+            Self::Storage(reason) => write!(f, "Prompt template storage failed: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for PromptTemplateError {}
+
+impl PromptTemplateError {
+    /// Stable error code for the error
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::InvalidTemplate(_) | Self::MissingArgument(_) | Self::UnknownArgument(_) => {
+                ErrorCode::InvalidInput
+            }
+            Self::NotFound(_) => ErrorCode::NotFound,
+            Self::Storage(_) => ErrorCode::StorageError,
+        }
+    }
+
+    /// JSON-RPC error code for the error in an MCP response
+    pub fn rpc_code(&self) -> i32 {
+        match self {
+            Self::Storage(_) => error_codes::INTERNAL_ERROR,
+            _ => error_codes::INVALID_PARAMS,
+        }
+    }
+}
+
+/// Part of a parsed message template
+#[derive(Debug, PartialEq)]
+enum Node<'a> {
+    Text(&'a str),
+    Placeholder(&'a str),
+    /// Nodes rendered only when the argument is given
+    Section(&'a str, Vec<Node<'a>>),
+}
+
+/// Parse message text into nodes
+fn parse(text: &str) -> Result<Vec<Node<'_>>, PromptTemplateError> {
+    let invalid = |reason: String| PromptTemplateError::InvalidTemplate(reason);
+    // Open sections, innermost last, under the top level
+    let mut open: Vec<(&str, Vec<Node<'_>>)> = vec![("", Vec::new())];
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        let nodes = &mut open.last_mut().expect("top level is never closed").1;
+        if start > 0 {
+            nodes.push(Node::Text(&rest[..start]));
+        }
+        let tag_start = &rest[start + 2..];
+        let end = tag_start
+            .find("}}")
+            .ok_or_else(|| invalid("unclosed '{{'".to_string()))?;
+        let tag = tag_start[..end].trim();
+        rest = &tag_start[end + 2..];
+
+        if let Some(name) = tag.strip_prefix('#') {
+            open.push((name.trim(), Vec::new()));
+        } else if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim();
+            if open.len() == 1 || open.last().map(|(open, _)| *open) != Some(name) {
+                return Err(invalid(format!("unexpected '{{{{/{}}}}}'", name)));
+            }
+            let (name, section) = open.pop().expect("checked above");
+            open.last_mut()
+                .expect("top level is never closed")
+                .1
+                .push(Node::Section(name, section));
+        } else {
+            nodes.push(Node::Placeholder(tag));
+        }
+    }
+
+    let (name, mut nodes) = open.pop().expect("top level is never closed");
+    if !open.is_empty() {
+        return Err(invalid(format!("section '{}' is never closed", name)));
+    }
+    if !rest.is_empty() {
+        nodes.push(Node::Text(rest));
+    }
+    Ok(nodes)
+}
+
+/// Arguments named by placeholders and sections in `nodes`
+fn referenced<'a>(nodes: &[Node<'a>], names: &mut Vec<&'a str>) {
+    for node in nodes {
+        match node {
+            Node::Text(_) => {}
+            Node::Placeholder(name) => names.push(name),
+            Node::Section(name, nodes) => {
+                names.push(name);
+                referenced(nodes, names);
+            }
+        }
+    }
+}
+
+fn render_nodes(nodes: &[Node<'_>], arguments: &HashMap<String, String>, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Placeholder(name) => {
+                if let Some(value) = arguments.get(*name) {
+                    out.push_str(value);
+                }
+            }
+            Node::Section(name, nodes) => {
+                if arguments.get(*name).is_some_and(|value| !value.is_empty()) {
+                    render_nodes(nodes, arguments, out);
+                }
+            }
+        }
+    }
+}
+
+/// Check that `template` can be stored
+pub fn validate_template(template: &MCPPromptTemplate) -> Result<(), PromptTemplateError> {
+    let invalid = |reason: String| Err(PromptTemplateError::InvalidTemplate(reason));
+
+    if template.name.is_empty() || template.name.len() > MAX_PROMPT_NAME_LENGTH {
+        return invalid(format!(
+            "name must be 1 to {} characters",
+            MAX_PROMPT_NAME_LENGTH
+        ));
+    }
+    if !template
+        .name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    {
+        return invalid(format!(
+            "name '{}' may only contain letters, digits, '_', '-' and '.'",
+            template.name
+        ));
+    }
+    if template.messages.is_empty() {
+        return invalid("a template needs at least one message".to_string());
+    }
+
+    let mut declared = HashSet::new();
+    for argument in &template.arguments {
+        if argument.name.is_empty() {
+            return invalid("argument names must not be empty".to_string());
+        }
+        if !declared.insert(argument.name.as_str()) {
+            return invalid(format!("argument '{}' is declared twice", argument.name));
+        }
+    }
+
+    for message in &template.messages {
+        let mut names = Vec::new();
+        referenced(&parse(&message.text)?, &mut names);
+        if let Some(name) = names.into_iter().find(|name| !declared.contains(name)) {
+            return invalid(format!("'{}' is not a declared argument", name));
+        }
+    }
+    Ok(())
+}
+
+/// Render `template` with `arguments` into MCP messages
+pub fn render(
+    template: &MCPPromptTemplate,
+    arguments: &HashMap<String, String>,
+) -> Result<Vec<SamplingMessage>, PromptTemplateError> {
+    if let Some(name) = arguments
+        .keys()
+        .find(|name| !template.arguments.iter().any(|arg| &arg.name == *name))
+    {
+        return Err(PromptTemplateError::UnknownArgument(name.clone()));
+    }
+    if let Some(argument) = template
+        .arguments
+        .iter()
+        .find(|arg| arg.required && !arguments.contains_key(&arg.name))
+    {
+        return Err(PromptTemplateError::MissingArgument(argument.name.clone()));
+    }
+
+    template
+        .messages
+        .iter()
+        .map(|message| {
+            let mut text = String::new();
+            render_nodes(&parse(&message.text)?, arguments, &mut text);
+            Ok(SamplingMessage {
+                role: message.role,
+                content: SamplingContent::Text { text },
+            })
+        })
+        .collect()
+}
+
+/// The prompt `prompts/list` advertises for `template`
+pub fn prompt(template: &MCPPromptTemplate) -> MCPPrompt {
+    MCPPrompt {
+        name: template.name.clone(),
+        description: template.description.clone(),
+        arguments: template.arguments.clone(),
+    }
+}
+
+/// Prompts every instance serves unless it stores a template of the same name
+pub fn default_templates() -> Vec<MCPPromptTemplate> {
+    let argument = |name: &str, description: &str, required, values: &[&str]| MCPPromptArgument {
+        name: name.to_string(),
+        description: description.to_string(),
+        required,
+        values: values.iter().map(|value| value.to_string()).collect(),
+    };
+    let user = |text: &str| MCPPromptTemplateMessage {
+        role: SamplingRole::User,
+        text: text.to_string(),
+    };
+
+    vec![
+        MCPPromptTemplate {
+            name: "workflow_template".to_string(),
+            description: "Generate a workflow template for a given task".to_string(),
+            arguments: vec![
+                argument(
+                    "task_description",
+                    "Description of the task to create a workflow for",
+                    true,
+                    &[],
+                ),
+                argument(
+                    "complexity_level",
+                    "Complexity level (simple, medium, complex)",
+                    false,
+                    &["simple", "medium", "complex"],
+                ),
+            ],
+            messages: vec![user(
+                "Design a Circuit Breaker workflow for this task:\n\n{{task_description}}\
+                 {{#complexity_level}}\n\nAim for a {{complexity_level}} workflow.{{/complexity_level}}\n\n\
+                 List the workflow's states, the transitions between them and the activity \
+                 that triggers each transition.",
+            )],
+        },
+        MCPPromptTemplate {
+            name: "agent_configuration".to_string(),
+            description: "Generate agent configuration for specific tasks".to_string(),
+            arguments: vec![
+                argument(
+                    "agent_type",
+                    "Type of agent (coding, analysis, creative, etc.)",
+                    true,
+                    &["coding", "analysis", "creative"],
+                ),
+                argument(
+                    "capabilities",
+                    "Required capabilities for the agent",
+                    false,
+                    &[],
+                ),
+            ],
+            messages: vec![user(
+                "Configure a {{agent_type}} agent for Circuit Breaker.\
+                 {{#capabilities}}\n\nThe agent needs these capabilities: {{capabilities}}{{/capabilities}}\n\n\
+                 Suggest the model, system prompt, temperature and tools the agent should use.",
+            )],
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arguments(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn text(messages: &[SamplingMessage]) -> &str {
+        match &messages[0].content {
+            SamplingContent::Text { text } => text,
+            SamplingContent::Image { .. } => panic!("expected text"),
+        }
+    }
+
+    #[test]
+    fn test_render_template() {
+        let template = &default_templates()[1];
+        assert!(validate_template(template).is_ok());
+
+        let messages = render(template, &arguments(&[("agent_type", "coding")])).unwrap();
+        assert_eq!(messages[0].role, SamplingRole::User);
+        assert!(text(&messages).starts_with("Configure a coding agent for Circuit Breaker.\n\n"));
+        assert!(!text(&messages).contains("capabilities"));
+
+        let messages = render(
+            template,
+            &arguments(&[("agent_type", "coding"), ("capabilities", "git, tests")]),
+        )
+        .unwrap();
+        assert!(text(&messages).contains("The agent needs these capabilities: git, tests\n\n"));
+
+        assert_eq!(
+            render(template, &arguments(&[("capabilities", "git")])),
+            Err(PromptTemplateError::MissingArgument(
+                "agent_type".to_string()
+            ))
+        );
+        assert_eq!(
+            render(
+                template,
+                &arguments(&[("agent_type", "coding"), ("tone", "dry")])
+            ),
+            Err(PromptTemplateError::UnknownArgument("tone".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_validate_template() {
+        let template = |text: &str| MCPPromptTemplate {
+            name: "review".to_string(),
+            description: "Review a change".to_string(),
+            arguments: vec![MCPPromptArgument {
+                name: "diff".to_string(),
+                description: "The change".to_string(),
+                required: true,
+                values: Vec::new(),
+            }],
+            messages: vec![MCPPromptTemplateMessage {
+                role: SamplingRole::User,
+                text: text.to_string(),
+            }],
+        };
+
+        assert!(validate_template(&template("Review {{ diff }}")).is_ok());
+        for text in [
+            "Review {{diff",
+            "Review {{#diff}}the change",
+            "Review {{/diff}}",
+            "Review {{#diff}}{{diff}}{{/other}}",
+            "Review {{patch}}",
+        ] {
+            assert!(
+                matches!(
+                    validate_template(&template(text)),
+                    Err(PromptTemplateError::InvalidTemplate(_))
+                ),
+                "{} should be rejected",
+                text
+            );
+        }
+
+        let mut unnamed = template("Review {{diff}}");
+        unnamed.name = "code review".to_string();
+        assert!(validate_template(&unnamed).is_err());
+    }
+}
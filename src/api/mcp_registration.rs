@@ -0,0 +1,407 @@
+// OAuth Dynamic Client Registration for the Circuit Breaker MCP server
+// Validates client metadata (RFC 7591) and manages registrations (RFC 7592)
+
+//! # Dynamic Client Registration
+//!
+//! MCP clients such as mcp-remote and Windsurf register themselves with
+//! `POST /register` and receive a `client_id`, a `client_secret` unless they are
+//! public clients (`token_endpoint_auth_method: "none"`), and a
+//! `registration_access_token`. The access token manages the registration at its
+//! `registration_client_uri`:
+//!
+//! - `GET /register/{client_id}` reads the registration
+//! - `PUT /register/{client_id}` replaces its metadata
+//! - `DELETE /register/{client_id}` removes it
+//!
+//! Secrets and access tokens are shown once and stored as SHA-256 digests.
+
+use chrono::Utc;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+/// Authentication methods a client may use at the token endpoint
+pub const TOKEN_ENDPOINT_AUTH_METHODS: &[&str] =
+    &["none", "client_secret_basic", "client_secret_post"];
+
+/// Grant types a client may register for
+pub const GRANT_TYPES: &[&str] = &["authorization_code", "refresh_token"];
+
+/// Response types a client may register for
+pub const RESPONSE_TYPES: &[&str] = &["code"];
+
+/// Client metadata of RFC 7591, section 2; unknown fields are ignored
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClientMetadata {
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+    #[serde(default = "default_token_endpoint_auth_method")]
+    pub token_endpoint_auth_method: String,
+    #[serde(default = "default_grant_types")]
+    pub grant_types: Vec<String>,
+    #[serde(default = "default_response_types")]
+    pub response_types: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_uri: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logo_uri: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contacts: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub software_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub software_version: Option<String>,
+}
+
+fn default_token_endpoint_auth_method() -> String {
+    "client_secret_basic".to_string()
+}
+
+fn default_grant_types() -> Vec<String> {
+    vec!["authorization_code".to_string()]
+}
+
+fn default_response_types() -> Vec<String> {
+    vec!["code".to_string()]
+}
+
+/// A registered client as stored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MCPClientRegistration {
+    pub client_id: String,
+    /// Digest of the client secret; public clients have none
+    pub client_secret_digest: Option<String>,
+    pub client_id_issued_at: i64,
+    /// Digest of the token managing the registration
+    pub registration_access_token_digest: String,
+    pub metadata: ClientMetadata,
+}
+
+/// A registration together with the secrets issued for it, which are not retrievable later
+#[derive(Debug, Clone)]
+pub struct IssuedClientRegistration {
+    pub registration: MCPClientRegistration,
+    pub client_secret: Option<String>,
+    pub registration_access_token: String,
+}
+
+/// Why client metadata was rejected, as the RFC 7591 error response
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegistrationError {
+    /// `invalid_redirect_uri` or `invalid_client_metadata`
+    pub error: &'static str,
+    pub description: String,
+}
+
+impl RegistrationError {
+    fn redirect_uri(description: impl Into<String>) -> Self {
+        Self {
+            error: "invalid_redirect_uri",
+            description: description.into(),
+        }
+    }
+
+    pub fn metadata(description: impl Into<String>) -> Self {
+        Self {
+            error: "invalid_client_metadata",
+            description: description.into(),
+        }
+    }
+
+    /// Body of the `400 Bad Request` answering the registration
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "error": self.error,
+            "error_description": self.description
+        })
+    }
+}
+
+/// Why a registration request failed
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientRegistrationFailure {
+    /// The metadata was rejected
+    Invalid(RegistrationError),
+    /// The registration access token is wrong, or the client is unknown
+    Unauthorized,
+    /// Registrations could not be read or written
+    Storage,
+}
+
+/// Check client metadata before it is registered
+pub fn validate_metadata(metadata: &ClientMetadata) -> Result<(), RegistrationError> {
+    if !TOKEN_ENDPOINT_AUTH_METHODS.contains(&metadata.token_endpoint_auth_method.as_str()) {
+        return Err(RegistrationError::metadata(format!(
+            "Unsupported token_endpoint_auth_method '{}'",
+            metadata.token_endpoint_auth_method
+        )));
+    }
+    if let Some(grant_type) = metadata
+        .grant_types
+        .iter()
+        .find(|grant_type| !GRANT_TYPES.contains(&grant_type.as_str()))
+    {
+        return Err(RegistrationError::metadata(format!(
+            "Unsupported grant type '{}'",
+            grant_type
+        )));
+    }
+    if let Some(response_type) = metadata
+        .response_types
+        .iter()
+        .find(|response_type| !RESPONSE_TYPES.contains(&response_type.as_str()))
+    {
+        return Err(RegistrationError::metadata(format!(
+            "Unsupported response type '{}'",
+            response_type
+        )));
+    }
+    let authorization_code = metadata
+        .grant_types
+        .iter()
+        .any(|grant_type| grant_type == "authorization_code");
+    if authorization_code != metadata.response_types.iter().any(|r| r == "code") {
+        return Err(RegistrationError::metadata(
+            "The authorization_code grant type and the code response type go together",
+        ));
+    }
+
+    if authorization_code && metadata.redirect_uris.is_empty() {
+        return Err(RegistrationError::redirect_uri(
+            "redirect_uris is required for the authorization_code grant type",
+        ));
+    }
+    for redirect_uri in &metadata.redirect_uris {
+        validate_redirect_uri(redirect_uri)?;
+    }
+    for uri in [&metadata.client_uri, &metadata.logo_uri]
+        .into_iter()
+        .flatten()
+    {
+        if !matches!(
+            Url::parse(uri)
+                .map(|url| url.scheme().to_string())
+                .as_deref(),
+            Ok("http" | "https")
+        ) {
+            return Err(RegistrationError::metadata(format!(
+                "'{}' is not an http(s) URL",
+                uri
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Redirect URIs are absolute without a fragment. Plain http is only accepted for
+/// loopback hosts, where native clients listen; private-use schemes such as
+/// `cursor://` are accepted too.
+fn validate_redirect_uri(redirect_uri: &str) -> Result<(), RegistrationError> {
+    let url = Url::parse(redirect_uri).map_err(|_| {
+        RegistrationError::redirect_uri(format!("'{}' is not an absolute URI", redirect_uri))
+    })?;
+    if url.fragment().is_some() {
+        return Err(RegistrationError::redirect_uri(format!(
+            "'{}' must not have a fragment",
+            redirect_uri
+        )));
+    }
+    let loopback = matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]"));
+    match url.scheme() {
+        "http" if !loopback => Err(RegistrationError::redirect_uri(format!(
+            "'{}' must use https unless it points to a loopback address",
+            redirect_uri
+        ))),
+        "javascript" | "data" | "file" => Err(RegistrationError::redirect_uri(format!(
+            "'{}' uses a scheme that cannot receive a redirect",
+            redirect_uri
+        ))),
+        _ => Ok(()),
+    }
+}
+
+// SHA-256 hex digest under which a secret is stored
+fn digest(secret: &str) -> String {
+    Sha256::digest(secret.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn generate_secret() -> String {
+    let bytes: [u8; 32] = rand::thread_rng().gen();
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Register a client with validated metadata, issuing its ID and secrets
+pub fn issue_registration(metadata: ClientMetadata) -> IssuedClientRegistration {
+    let client_secret = (metadata.token_endpoint_auth_method != "none").then(generate_secret);
+    let registration_access_token = generate_secret();
+    IssuedClientRegistration {
+        registration: MCPClientRegistration {
+            client_id: uuid::Uuid::new_v4().to_string(),
+            client_secret_digest: client_secret.as_deref().map(digest),
+            client_id_issued_at: Utc::now().timestamp(),
+            registration_access_token_digest: digest(&registration_access_token),
+            metadata,
+        },
+        client_secret,
+        registration_access_token,
+    }
+}
+
+impl MCPClientRegistration {
+    /// Whether `token` is the registration access token of this client
+    pub fn authorizes(&self, token: &str) -> bool {
+        digest(token) == self.registration_access_token_digest
+    }
+
+    /// Replace the client's metadata. Returns the new client secret when the client
+    /// stops being public; a client becoming public loses its secret.
+    pub fn replace_metadata(&mut self, metadata: ClientMetadata) -> Option<String> {
+        let mut client_secret = None;
+        if metadata.token_endpoint_auth_method == "none" {
+            self.client_secret_digest = None;
+        } else if self.client_secret_digest.is_none() {
+            let secret = generate_secret();
+            self.client_secret_digest = Some(digest(&secret));
+            client_secret = Some(secret);
+        }
+        self.metadata = metadata;
+        client_secret
+    }
+
+    /// Client information response of RFC 7591 and RFC 7592; secrets are only
+    /// included when they were just issued
+    pub fn to_response(
+        &self,
+        registration_client_uri: &str,
+        client_secret: Option<&str>,
+        registration_access_token: Option<&str>,
+    ) -> serde_json::Value {
+        let mut response = serde_json::to_value(&self.metadata).unwrap_or_default();
+        response["client_id"] = self.client_id.clone().into();
+        response["client_id_issued_at"] = self.client_id_issued_at.into();
+        response["registration_client_uri"] = registration_client_uri.into();
+        if self.client_secret_digest.is_some() {
+            // Secrets do not expire
+            response["client_secret_expires_at"] = 0.into();
+        }
+        if let Some(client_secret) = client_secret {
+            response["client_secret"] = client_secret.into();
+        }
+        if let Some(token) = registration_access_token {
+            response["registration_access_token"] = token.into();
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(value: serde_json::Value) -> ClientMetadata {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_metadata_defaults() {
+        let metadata = metadata(serde_json::json!({
+            "redirect_uris": ["http://localhost:6274/oauth/callback"],
+            "client_name": "mcp-remote",
+            "unknown_field": true
+        }));
+        assert_eq!(metadata.token_endpoint_auth_method, "client_secret_basic");
+        assert_eq!(metadata.grant_types, vec!["authorization_code"]);
+        assert_eq!(metadata.response_types, vec!["code"]);
+        assert!(validate_metadata(&metadata).is_ok());
+    }
+
+    #[test]
+    fn test_redirect_uri_validation() {
+        let with_redirect = |uri: &str| metadata(serde_json::json!({ "redirect_uris": [uri] }));
+        for uri in [
+            "https://app.example.com/callback",
+            "http://127.0.0.1:33418/callback",
+            "cursor://anysphere.cursor-retrieval/oauth/callback",
+        ] {
+            assert!(validate_metadata(&with_redirect(uri)).is_ok(), "{}", uri);
+        }
+        for uri in [
+            "http://app.example.com/callback",
+            "https://app.example.com/callback#fragment",
+            "/callback",
+            "javascript:alert(1)",
+        ] {
+            assert_eq!(
+                validate_metadata(&with_redirect(uri)).unwrap_err().error,
+                "invalid_redirect_uri",
+                "{}",
+                uri
+            );
+        }
+        assert_eq!(
+            validate_metadata(&metadata(serde_json::json!({})))
+                .unwrap_err()
+                .error,
+            "invalid_redirect_uri"
+        );
+    }
+
+    #[test]
+    fn test_metadata_validation() {
+        let invalid = [
+            serde_json::json!({"token_endpoint_auth_method": "private_key_jwt"}),
+            serde_json::json!({"grant_types": ["implicit"], "response_types": ["token"]}),
+            serde_json::json!({"grant_types": ["refresh_token"]}),
+            serde_json::json!({"client_uri": "ftp://example.com"}),
+        ];
+        for mut value in invalid {
+            value["redirect_uris"] = serde_json::json!(["https://example.com/callback"]);
+            assert_eq!(
+                validate_metadata(&metadata(value.clone()))
+                    .unwrap_err()
+                    .error,
+                "invalid_client_metadata",
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn test_issued_secrets_are_stored_as_digests() {
+        let issued = issue_registration(metadata(serde_json::json!({
+            "redirect_uris": ["http://localhost/callback"]
+        })));
+        let client_secret = issued.client_secret.as_deref().unwrap();
+        let registration = &issued.registration;
+        assert_eq!(
+            registration.client_secret_digest.as_deref(),
+            Some(digest(client_secret).as_str())
+        );
+        assert!(registration.authorizes(&issued.registration_access_token));
+        assert!(!registration.authorizes(client_secret));
+
+        let stored = serde_json::to_string(registration).unwrap();
+        assert!(!stored.contains(client_secret));
+        assert!(!stored.contains(&issued.registration_access_token));
+
+        let response = registration.to_response("https://mcp.example.com/register/x", None, None);
+        assert!(response.get("client_secret").is_none());
+        assert_eq!(response["client_secret_expires_at"], 0);
+
+        let public = issue_registration(metadata(serde_json::json!({
+            "redirect_uris": ["http://localhost/callback"],
+            "token_endpoint_auth_method": "none"
+        })));
+        assert!(public.client_secret.is_none());
+        assert!(public.registration.client_secret_digest.is_none());
+    }
+}
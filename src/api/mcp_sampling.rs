@@ -0,0 +1,530 @@
+// MCP sampling for the Circuit Breaker MCP server
+// Serves `sampling/createMessage` through the LLM router under the instance's tenant budget
+
+//! # MCP Sampling
+//!
+//! MCP clients can ask a server for a completion with `sampling/createMessage`. An
+//! instance answers those requests through the same [`LLMRouter`] as the
+//! OpenAI-compatible API, so sampling gets provider fallback, tenant routing policies
+//! and cost tracking for free:
+//!
+//! - `modelPreferences.hints` are matched in order against the ids of the models the
+//!   router offers; the first model containing a hint is used
+//! - without a matching hint the highest of `costPriority`, `speedPriority` and
+//!   `intelligencePriority` picks `cb:cost-optimal`, `cb:fastest` or `cb:smart-chat`
+//! - an instance created with a `tenant_id` is routed by that tenant's policy, and the
+//!   request must fit the tenant's budget, counting its prompt plus `maxTokens`
+//!
+//! `includeContext` is accepted but not acted on: an instance has no context from other
+//! servers to include.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use super::mcp_types::{error_codes, MCPRequest, MCPResponse, MCPServerInstance};
+use crate::llm::cost::{CostContext, CostOptimizer};
+use crate::llm::multimodal::{ContentPart, ImageUrl};
+use crate::llm::{
+    tokenizer, ChatMessage, CostInfo, LLMProviderType, LLMRequest, LLMResponse, LLMRouter,
+    MessageRole, TenantId,
+};
+use crate::ErrorCode;
+
+/// Model used when neither hints nor priorities pick one
+const DEFAULT_SAMPLING_MODEL: &str = "cb:smart-chat";
+
+/// Parameters of a `sampling/createMessage` request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMessageParams {
+    pub messages: Vec<SamplingMessage>,
+    #[serde(default)]
+    pub model_preferences: Option<ModelPreferences>,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    /// Context from other MCP servers to include; instances have none to offer
+    #[serde(default)]
+    pub include_context: Option<String>,
+    #[serde(default)]
+    pub temperature: Option<f64>,
+    pub max_tokens: u32,
+    #[serde(default)]
+    pub stop_sequences: Option<Vec<String>>,
+    #[serde(default)]
+    pub metadata: Option<serde_json::Value>,
+}
+
+/// A message of the conversation to sample from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SamplingMessage {
+    pub role: SamplingRole,
+    pub content: SamplingContent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SamplingRole {
+    User,
+    Assistant,
+}
+
+/// Content of a sampling message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SamplingContent {
+    Text {
+        text: String,
+    },
+    /// Base64-encoded image
+    Image {
+        data: String,
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+}
+
+/// The client's preferences for the model serving a request
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPreferences {
+    #[serde(default)]
+    pub hints: Vec<ModelHint>,
+    #[serde(default)]
+    pub cost_priority: Option<f64>,
+    #[serde(default)]
+    pub speed_priority: Option<f64>,
+    #[serde(default)]
+    pub intelligence_priority: Option<f64>,
+}
+
+/// Part of a model name the client would like to use
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelHint {
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// Result of a `sampling/createMessage` request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMessageResult {
+    pub role: SamplingRole,
+    pub content: SamplingContent,
+    /// Model that produced the message
+    pub model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<String>,
+}
+
+/// Sampling backend shared by the instances of an MCP server
+#[derive(Clone)]
+pub struct MCPSampling {
+    router: Arc<LLMRouter>,
+    cost_optimizer: Arc<RwLock<CostOptimizer>>,
+}
+
+impl MCPSampling {
+    pub fn new(router: Arc<LLMRouter>, cost_optimizer: Arc<RwLock<CostOptimizer>>) -> Self {
+        Self {
+            router,
+            cost_optimizer,
+        }
+    }
+
+    /// Handle a `sampling/createMessage` request for `instance`
+    pub async fn handle_create_message(
+        &self,
+        request: MCPRequest,
+        instance: &MCPServerInstance,
+    ) -> MCPResponse {
+        let params = match request
+            .params
+            .clone()
+            .map(serde_json::from_value::<CreateMessageParams>)
+        {
+            Some(Ok(params)) if params.messages.is_empty() => {
+                return invalid_params(&request, "'messages' must not be empty".to_string());
+            }
+            Some(Ok(params)) => params,
+            Some(Err(e)) => {
+                return invalid_params(&request, format!("Invalid sampling parameters: {}", e));
+            }
+            None => {
+                return invalid_params(&request, "Missing sampling parameters".to_string());
+            }
+        };
+
+        let tenant_id = instance.tenant_id.as_deref().map(TenantId::new);
+        let mut llm_request = self.llm_request(&params);
+        llm_request.metadata.insert(
+            "mcp_instance_id".to_string(),
+            serde_json::Value::String(instance.instance_id.clone()),
+        );
+        debug!(
+            "Sampling {} for MCP instance {}",
+            llm_request.model, instance.instance_id
+        );
+
+        // Apply the tenant's routing overrides (strategy, provider and model allowlists)
+        if let Some(tenant_id) = &tenant_id {
+            llm_request = match self
+                .router
+                .apply_tenant_policy(llm_request, tenant_id)
+                .await
+            {
+                Ok(llm_request) => llm_request,
+                Err(e) => return llm_error(&request, e),
+            };
+        }
+
+        let project_id = tenant_id.map(|tenant_id| tenant_id.to_string());
+        if let Some(project_id) = &project_id {
+            if let Err(response) = self
+                .preflight_budget(&request, &llm_request, project_id)
+                .await
+            {
+                return response;
+            }
+        }
+
+        let response = match self
+            .router
+            .smart_chat_completion(llm_request.clone(), None)
+            .await
+        {
+            Ok(response) => response,
+            Err(e) => return llm_error(&request, e),
+        };
+        self.record_cost(&llm_request, project_id, &response).await;
+
+        let Some(choice) = response.choices.into_iter().next() else {
+            return MCPResponse::error_from_request(
+                request.id,
+                error_codes::INTERNAL_ERROR,
+                "The model returned no message".to_string(),
+            )
+            .with_error_code(ErrorCode::ProviderError);
+        };
+        let result = CreateMessageResult {
+            role: SamplingRole::Assistant,
+            content: SamplingContent::Text {
+                text: choice.message.content,
+            },
+            model: response.model,
+            stop_reason: choice.finish_reason.as_deref().map(stop_reason),
+        };
+        info!(
+            "Sampled {} for MCP instance {}",
+            result.model, instance.instance_id
+        );
+        MCPResponse::success_from_request(
+            request.id,
+            serde_json::to_value(result).unwrap_or_default(),
+        )
+    }
+
+    /// The router request for a sampling request
+    fn llm_request(&self, params: &CreateMessageParams) -> LLMRequest {
+        let available_models: Vec<String> = self
+            .router
+            .get_available_providers()
+            .iter()
+            .filter_map(|provider| self.router.get_provider_client(provider))
+            .flat_map(|client| client.get_available_models())
+            .map(|model| model.id)
+            .collect();
+
+        let system = params.system_prompt.as_ref().map(|prompt| ChatMessage {
+            role: MessageRole::System,
+            content: prompt.clone(),
+            name: None,
+            function_call: None,
+            tool_calls: None,
+            tool_call_id: None,
+            content_parts: None,
+        });
+        let messages = system
+            .into_iter()
+            .chain(params.messages.iter().map(chat_message))
+            .collect();
+
+        LLMRequest {
+            id: Uuid::new_v4(),
+            model: select_model(params.model_preferences.as_ref(), &available_models),
+            messages,
+            temperature: params.temperature,
+            max_tokens: Some(params.max_tokens),
+            top_p: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            stop: params.stop_sequences.clone(),
+            stream: Some(false),
+            functions: None,
+            function_call: None,
+            user: None,
+            metadata: HashMap::new(),
+            response_format: None,
+            tool_choice: None,
+            n: None,
+        }
+    }
+
+    /// Refuse a request that could take the tenant past its budget
+    async fn preflight_budget(
+        &self,
+        request: &MCPRequest,
+        llm_request: &LLMRequest,
+        project_id: &str,
+    ) -> Result<(), MCPResponse> {
+        let prompt_tokens =
+            tokenizer::count_prompt_tokens(&llm_request.model, &llm_request.messages);
+        let max_output_tokens = llm_request.max_tokens.unwrap_or_default();
+        let provider = self.router.determine_provider_for_model(&llm_request.model);
+        let estimated_cost = self
+            .estimate_cost(
+                &provider,
+                &llm_request.model,
+                prompt_tokens,
+                max_output_tokens,
+            )
+            .await
+            .unwrap_or_default();
+
+        let context = CostContext {
+            user_id: String::new(),
+            project_id: Some(project_id.to_string()),
+            request_size: prompt_tokens,
+            expected_output_tokens: max_output_tokens,
+            current_time: chrono::Utc::now(),
+        };
+        let budget_manager = self.cost_optimizer.read().await.budget_manager();
+        match budget_manager.preflight(&context, estimated_cost).await {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                info!("Refused sampling request {}: {}", llm_request.id, e);
+                Err(MCPResponse::error_from_request(
+                    request.id.clone(),
+                    error_codes::INVALID_REQUEST,
+                    e.to_string(),
+                )
+                .with_error_code(e.code()))
+            }
+        }
+    }
+
+    /// Record what a sampling request cost with the cost optimizer
+    async fn record_cost(
+        &self,
+        llm_request: &LLMRequest,
+        project_id: Option<String>,
+        response: &LLMResponse,
+    ) {
+        let usage = &response.usage;
+        let cost_usd = self
+            .estimate_cost(
+                &response.provider,
+                &response.model,
+                usage.prompt_tokens,
+                usage.completion_tokens,
+            )
+            .await
+            .unwrap_or(usage.estimated_cost);
+        self.cost_optimizer
+            .read()
+            .await
+            .record_actual_cost(CostInfo {
+                request_id: llm_request.id,
+                provider: response.provider.clone(),
+                model: response.model.clone(),
+                input_tokens: usage.prompt_tokens,
+                output_tokens: usage.completion_tokens,
+                cost_usd,
+                timestamp: chrono::Utc::now(),
+                user_id: None,
+                project_id,
+            })
+            .await;
+    }
+
+    async fn estimate_cost(
+        &self,
+        provider: &LLMProviderType,
+        model: &str,
+        prompt_tokens: u32,
+        completion_tokens: u32,
+    ) -> Option<f64> {
+        self.cost_optimizer
+            .read()
+            .await
+            .estimate_cost(provider, model, prompt_tokens, completion_tokens)
+            .await
+            .ok()
+            .map(|estimate| estimate.total_cost)
+    }
+}
+
+/// Pick the model for a request from the client's preferences
+///
+/// Hints are tried in order and match any model whose id contains them.
+pub fn select_model(preferences: Option<&ModelPreferences>, available_models: &[String]) -> String {
+    let Some(preferences) = preferences else {
+        return DEFAULT_SAMPLING_MODEL.to_string();
+    };
+
+    let hinted = preferences
+        .hints
+        .iter()
+        .filter_map(|hint| hint.name.as_deref())
+        .filter(|name| !name.is_empty())
+        .find_map(|name| available_models.iter().find(|model| model.contains(name)));
+    if let Some(model) = hinted {
+        return model.clone();
+    }
+
+    let priorities = [
+        (preferences.intelligence_priority, DEFAULT_SAMPLING_MODEL),
+        (preferences.cost_priority, "cb:cost-optimal"),
+        (preferences.speed_priority, "cb:fastest"),
+    ];
+    priorities
+        .iter()
+        .filter_map(|(priority, model)| priority.map(|priority| (priority, *model)))
+        .filter(|(priority, _)| *priority > 0.0)
+        .fold(
+            None,
+            |best: Option<(f64, &str)>, (priority, model)| match best {
+                Some((best_priority, _)) if best_priority >= priority => best,
+                _ => Some((priority, model)),
+            },
+        )
+        .map_or(DEFAULT_SAMPLING_MODEL, |(_, model)| model)
+        .to_string()
+}
+
+/// The router message for a sampling message
+fn chat_message(message: &SamplingMessage) -> ChatMessage {
+    let role = match message.role {
+        SamplingRole::User => MessageRole::User,
+        SamplingRole::Assistant => MessageRole::Assistant,
+    };
+    let (content, content_parts) = match &message.content {
+        SamplingContent::Text { text } => (text.clone(), None),
+        SamplingContent::Image { data, mime_type } => (
+            String::new(),
+            Some(vec![ContentPart::ImageUrl {
+                image_url: ImageUrl {
+                    url: format!("data:{};base64,{}", mime_type, data),
+                    detail: None,
+                },
+            }]),
+        ),
+    };
+    ChatMessage {
+        role,
+        content,
+        name: None,
+        function_call: None,
+        tool_calls: None,
+        tool_call_id: None,
+        content_parts,
+    }
+}
+
+/// MCP stop reason for a provider finish reason
+fn stop_reason(finish_reason: &str) -> String {
+    match finish_reason {
+        "stop" | "end_turn" => "endTurn".to_string(),
+        "length" | "max_tokens" => "maxTokens".to_string(),
+        "stop_sequence" => "stopSequence".to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn invalid_params(request: &MCPRequest, message: String) -> MCPResponse {
+    MCPResponse::error_from_request(request.id.clone(), error_codes::INVALID_PARAMS, message)
+        .with_error_code(ErrorCode::InvalidInput)
+}
+
+fn llm_error(request: &MCPRequest, error: crate::llm::LLMError) -> MCPResponse {
+    MCPResponse::error_from_request(
+        request.id.clone(),
+        error_codes::INTERNAL_ERROR,
+        error.to_string(),
+    )
+    .with_error_code(error.code())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_create_message_params() {
+        let params: CreateMessageParams = serde_json::from_value(json!({
+            "messages": [
+                {"role": "user", "content": {"type": "text", "text": "What is in this image?"}},
+                {"role": "user", "content": {"type": "image", "data": "aGk=", "mimeType": "image/png"}}
+            ],
+            "modelPreferences": {"hints": [{"name": "claude-3"}], "speedPriority": 0.8},
+            "systemPrompt": "You are terse.",
+            "includeContext": "none",
+            "maxTokens": 100
+        }))
+        .unwrap();
+
+        assert_eq!(params.max_tokens, 100);
+        assert_eq!(params.system_prompt.as_deref(), Some("You are terse."));
+        let image = chat_message(&params.messages[1]);
+        assert!(matches!(image.role, MessageRole::User));
+        assert_eq!(
+            image.content_parts,
+            Some(vec![ContentPart::ImageUrl {
+                image_url: ImageUrl {
+                    url: "data:image/png;base64,aGk=".to_string(),
+                    detail: None,
+                },
+            }])
+        );
+
+        let missing_max_tokens = json!({"messages": []});
+        assert!(serde_json::from_value::<CreateMessageParams>(missing_max_tokens).is_err());
+    }
+
+    #[test]
+    fn test_select_model() {
+        let available = vec![
+            "gpt-4o-mini".to_string(),
+            "claude-3-5-sonnet-20241022".to_string(),
+            "claude-3-haiku-20240307".to_string(),
+        ];
+        let preferences = |hints: &[&str], cost, speed, intelligence| ModelPreferences {
+            hints: hints
+                .iter()
+                .map(|name| ModelHint {
+                    name: Some(name.to_string()),
+                })
+                .collect(),
+            cost_priority: cost,
+            speed_priority: speed,
+            intelligence_priority: intelligence,
+        };
+
+        assert_eq!(select_model(None, &available), "cb:smart-chat");
+        let hinted = preferences(&["gemini", "haiku", "gpt"], None, None, None);
+        assert_eq!(
+            select_model(Some(&hinted), &available),
+            "claude-3-haiku-20240307"
+        );
+        let cheap = preferences(&["gemini"], Some(0.9), Some(0.5), Some(0.1));
+        assert_eq!(select_model(Some(&cheap), &available), "cb:cost-optimal");
+        let fast = preferences(&[], Some(0.2), Some(0.7), None);
+        assert_eq!(select_model(Some(&fast), &available), "cb:fastest");
+        let undecided = preferences(&[], Some(0.5), Some(0.5), Some(0.5));
+        assert_eq!(select_model(Some(&undecided), &available), "cb:smart-chat");
+    }
+}
@@ -9,50 +9,187 @@ use axum::{
         sse::{Event, KeepAlive, Sse},
         IntoResponse, Response,
     },
-    routing::{get, post},
+    routing::{delete, get, post, put},
     Json, Router,
 };
 
+type SSESender = mpsc::UnboundedSender<Result<Event, Infallible>>;
+
+/// Default idle timeout after which an SSE channel with no MCP traffic is reaped
+const SSE_CHANNEL_IDLE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Least time between writes of a session's activity to storage
+const SESSION_ACTIVITY_PERSIST_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Interval between SSE channel reaper sweeps
+const SSE_REAPER_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// NATS subject prefix on which nodes hand MCP responses to the node holding the SSE channel
+const SSE_RELAY_SUBJECT_PREFIX: &str = "circuit_breaker.mcp.sse";
+
+/// How long a node waits for another node to deliver a relayed response
+const SSE_RELAY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+// A registered SSE channel and its liveness bookkeeping
+struct SSEChannel {
+    sender: SSESender,
+    registered_at: std::time::Instant,
+    last_activity: std::time::Instant,
+    // Delivers responses other nodes relay for this channel; stopped with the channel
+    relay: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl Drop for SSEChannel {
+    fn drop(&mut self) {
+        if let Some(relay) = &self.relay {
+            relay.abort();
+        }
+    }
+}
+
 // Global SSE Response Router for multi-tenant SSE communication
+#[derive(Clone)]
 pub struct SSEResponseRouter {
-    // Maps Bearer token -> SSE channel sender
-    channels: Arc<RwLock<HashMap<String, mpsc::UnboundedSender<Result<Event, Infallible>>>>>,
+    // Maps Bearer token -> SSE channel
+    channels: Arc<RwLock<HashMap<String, SSEChannel>>>,
+    // Channels removed by the reaper or after failed sends since startup
+    reaped_total: Arc<std::sync::atomic::AtomicU64>,
+    // NATS connection shared with the other nodes, when running as a cluster
+    cluster: Arc<RwLock<Option<async_nats::Client>>>,
 }
 
 impl SSEResponseRouter {
     pub fn new() -> Self {
         Self {
             channels: Arc::new(RwLock::new(HashMap::new())),
+            reaped_total: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            cluster: Arc::new(RwLock::new(None)),
         }
     }
 
-    pub async fn register_channel(
-        &self,
-        token: String,
-        sender: mpsc::UnboundedSender<Result<Event, Infallible>>,
-    ) {
+    /// Route responses across every node connected to `client`. Channels registered
+    /// from now on also take responses that other nodes received for them.
+    pub async fn enable_cluster(&self, client: async_nats::Client) {
+        *self.cluster.write().await = Some(client);
+        info!("SSE response routing spans the NATS cluster");
+    }
+
+    pub async fn register_channel(&self, token: String, sender: SSESender) {
+        let relay = match self.cluster.read().await.clone() {
+            Some(client) => self.spawn_relay(client, &token).await,
+            None => None,
+        };
         let mut channels = self.channels.write().await;
         info!("Registered SSE channel for token: {}...", &token[..8]);
-        channels.insert(token, sender);
+        let now = std::time::Instant::now();
+        channels.insert(
+            token,
+            SSEChannel {
+                sender,
+                registered_at: now,
+                last_activity: now,
+                relay,
+            },
+        );
+    }
+
+    // Subscribe to the relay subject of `token` and deliver what arrives on it locally
+    async fn spawn_relay(
+        &self,
+        client: async_nats::Client,
+        token: &str,
+    ) -> Option<tokio::task::JoinHandle<()>> {
+        let mut subscriber = match client.subscribe(sse_relay_subject(token)).await {
+            Ok(subscriber) => subscriber,
+            Err(e) => {
+                warn!("Failed to subscribe to SSE relay subject: {}", e);
+                return None;
+            }
+        };
+        let router = self.clone();
+        let token = token.to_string();
+        Some(tokio::spawn(async move {
+            use futures::StreamExt;
+            while let Some(message) = subscriber.next().await {
+                let delivered = match serde_json::from_slice::<MCPResponse>(&message.payload) {
+                    Ok(response) => router.send_local(&token, &response).await,
+                    Err(e) => {
+                        warn!("Dropping malformed relayed MCP response: {}", e);
+                        false
+                    }
+                };
+                if let Some(reply) = message.reply {
+                    let ack: &'static [u8] = if delivered { b"delivered" } else { b"gone" };
+                    if let Err(e) = client.publish(reply, ack.into()).await {
+                        warn!("Failed to acknowledge relayed MCP response: {}", e);
+                    }
+                }
+            }
+        }))
     }
 
-    pub async fn unregister_channel(&self, token: &str) {
+    /// Unregister the channel for a token, but only if it is still `sender`.
+    /// A reconnecting client may already have replaced it with a newer channel.
+    pub async fn unregister_channel(&self, token: &str, sender: &SSESender) {
         let mut channels = self.channels.write().await;
-        channels.remove(token);
-        info!("Unregistered SSE channel for token: {}...", &token[..8]);
+        if channels
+            .get(token)
+            .is_some_and(|channel| channel.sender.same_channel(sender))
+        {
+            channels.remove(token);
+            info!("Unregistered SSE channel for token: {}...", &token[..8]);
+        }
     }
 
+    /// Send a response down the SSE channel of `token`, on this node or, in a
+    /// cluster, on whichever node holds it
     pub async fn send_response(
         &self,
         token: &str,
         response: &super::mcp_types::MCPResponse,
     ) -> bool {
-        let channels = self.channels.read().await;
-        if let Some(sender) = channels.get(token) {
+        if self.send_local(token, response).await {
+            return true;
+        }
+        let Some(client) = self.cluster.read().await.clone() else {
+            return false;
+        };
+        let Ok(payload) = serde_json::to_vec(response) else {
+            return false;
+        };
+        match tokio::time::timeout(
+            SSE_RELAY_TIMEOUT,
+            client.request(sse_relay_subject(token), payload.into()),
+        )
+        .await
+        {
+            Ok(Ok(reply)) if reply.payload.as_ref() == b"delivered" => {
+                info!(
+                    "Relayed MCP response to the node holding the SSE channel for token: {}...",
+                    &token[..8]
+                );
+                true
+            }
+            Ok(Ok(_)) => false,
+            Ok(Err(e)) => {
+                debug!("No node took the relayed MCP response: {}", e);
+                false
+            }
+            Err(_) => {
+                warn!("Timed out relaying MCP response over NATS");
+                false
+            }
+        }
+    }
+
+    async fn send_local(&self, token: &str, response: &super::mcp_types::MCPResponse) -> bool {
+        let mut channels = self.channels.write().await;
+        if let Some(channel) = channels.get_mut(token) {
             if let Ok(response_json) = serde_json::to_string(response) {
                 let event = Event::default().event("mcp-response").data(response_json);
 
-                if sender.send(Ok(event)).is_ok() {
+                if channel.sender.send(Ok(event)).is_ok() {
+                    channel.last_activity = std::time::Instant::now();
                     info!("Sent MCP response via SSE for token: {}...", &token[..8]);
                     return true;
                 } else {
@@ -60,6 +197,9 @@ impl SSEResponseRouter {
                         "Failed to send MCP response - channel closed for token: {}...",
                         &token[..8]
                     );
+                    channels.remove(token);
+                    self.reaped_total
+                        .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
                 }
             }
         } else {
@@ -72,6 +212,71 @@ impl SSEResponseRouter {
         let channels = self.channels.read().await;
         channels.keys().cloned().collect()
     }
+
+    /// Remove channels whose client disconnected or that saw no traffic within `idle_timeout`
+    pub async fn reap_stale(&self, idle_timeout: std::time::Duration) -> usize {
+        let mut channels = self.channels.write().await;
+        let before = channels.len();
+        channels.retain(|token, channel| {
+            let alive =
+                !channel.sender.is_closed() && channel.last_activity.elapsed() <= idle_timeout;
+            if !alive {
+                debug!(
+                    "Reaping SSE channel for token: {}... (open for {:?})",
+                    &token[..8.min(token.len())],
+                    channel.registered_at.elapsed()
+                );
+            }
+            alive
+        });
+        let reaped = before - channels.len();
+        self.reaped_total
+            .fetch_add(reaped as u64, std::sync::atomic::Ordering::SeqCst);
+        reaped
+    }
+
+    /// Number of registered SSE channels
+    pub async fn channel_count(&self) -> usize {
+        self.channels.read().await.len()
+    }
+
+    /// Current SSE channel gauges
+    pub async fn gauges(&self) -> StreamGauges {
+        StreamGauges {
+            active_subscribers: self.channel_count().await,
+            reaped_total: self.reaped_total.load(std::sync::atomic::Ordering::SeqCst),
+            ..Default::default()
+        }
+    }
+}
+
+/// Spawn the background task that reaps stale SSE channels from the global router
+pub fn spawn_sse_reaper() -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(SSE_REAPER_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let reaped = SSE_ROUTER.reap_stale(SSE_CHANNEL_IDLE_TIMEOUT).await;
+            if reaped > 0 {
+                info!(
+                    "Reaped {} stale SSE channels ({} still active)",
+                    reaped,
+                    SSE_ROUTER.channel_count().await
+                );
+            }
+        }
+    })
+}
+
+/// NATS subject carrying responses for the SSE channel of `token`; tokens may hold
+/// characters subjects cannot, so the subject names their digest
+fn sse_relay_subject(token: &str) -> String {
+    use sha2::{Digest, Sha256};
+    let digest: String = Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("{}.{}", SSE_RELAY_SUBJECT_PREFIX, digest)
 }
 
 // Global SSE router instance
@@ -87,11 +292,36 @@ use tokio::sync::{mpsc, RwLock};
 use tracing::{debug, error, info, warn};
 use uuid;
 
-use super::mcp_auth::{ClientInfo, MCPJWTService, MCPTokenClaims};
-use super::mcp_storage::{InMemoryMCPStorage, MCPStorage, NATSMCPStorage};
+use super::mcp_auth::{claimed_app_id, ClientInfo, MCPJWTService, MCPTokenClaims};
+use super::mcp_client::{
+    MCPClient, MCPClientError, MCPProxiedTool, MCPUpstream, MCPUpstreamConfig, MCPUpstreamTransport,
+};
+use super::mcp_completion::{self, CompletionCache, MCPCompleteRequest};
+use super::mcp_logging::{MCPLogLevel, MCPLogger};
+use super::mcp_prompts::{self, PromptTemplateError};
+use super::mcp_registration::{
+    self, ClientMetadata, ClientRegistrationFailure, IssuedClientRegistration,
+    MCPClientRegistration, RegistrationError,
+};
+use super::mcp_sampling::{MCPSampling, SamplingMessage};
+use super::mcp_storage::{InMemoryMCPStorage, MCPStorage, NATSMCPStorage, PostgresMCPStorage};
+use super::mcp_streamable::{
+    negotiate_protocol_version, StreamableSessions, LAST_EVENT_ID_HEADER, SESSION_IDLE_TIMEOUT,
+    SESSION_ID_HEADER,
+};
+use super::mcp_tool_cache::ToolResultCache;
+use super::mcp_tools::{self, MCPToolContext, MCPToolHandler, MCPToolRegistry};
 use super::mcp_types::*;
 use super::oauth::{OAuthManager, OAuthProviderType};
+use super::types::{create_error_response, ErrorResponse};
 use crate::api::mcp_types::{MCPApplicationType, MCPId, RemoteOAuthConfig};
+use crate::api_keys::ApiKeys;
+use crate::audit::AuthSurface;
+use crate::audit_trail::{AuditCategory, AuditOutcome, AuditRecord, AuditTrail};
+use crate::auth_throttle::{client_ip, record_peer_addr, AuthThrottle};
+use crate::engine::StreamGauges;
+use crate::llm::{cost::CostOptimizer, LLMRouter};
+use crate::rbac::{mcp_method_permission, RoleAssignments, Subject};
 
 /// Circuit Breaker MCP Server Manager - manages multiple MCP server instances
 #[derive(Clone)]
@@ -101,6 +331,20 @@ pub struct MCPServerManager {
     pub jwt_service: Arc<MCPJWTService>,
     pub oauth_manager: Arc<OAuthManager>,
     pub storage: Arc<dyn MCPStorage>,
+    /// Backend for `sampling/createMessage`, when the server offers sampling
+    pub sampling: Option<MCPSampling>,
+    /// Tools offered by tool set and by instance
+    pub tools: Arc<RwLock<MCPToolRegistry>>,
+    /// Upstream MCP servers whose tools an instance re-exposes, by instance
+    pub upstreams: Arc<RwLock<HashMap<String, Vec<MCPUpstream>>>>,
+    /// Whether the management API may add upstreams that spawn local processes
+    pub allow_stdio_upstreams: bool,
+    /// Sessions of the streamable HTTP transport
+    pub streamable_sessions: StreamableSessions,
+    /// Provider lookups behind `completion/complete`
+    pub completion_cache: CompletionCache,
+    /// Results of read-only tool calls, by instance
+    pub tool_cache: ToolResultCache,
 }
 
 impl MCPServerManager {
@@ -114,9 +358,16 @@ impl MCPServerManager {
         Self {
             registry: Arc::new(RwLock::new(MCPServerRegistry::new())),
             sessions: Arc::new(RwLock::new(HashMap::new())),
-            jwt_service: Arc::new(MCPJWTService::new()),
+            jwt_service: Arc::new(MCPJWTService::new().with_key_store(storage.clone())),
             oauth_manager: Arc::new(OAuthManager::with_storage(storage.clone())),
             storage,
+            sampling: None,
+            tools: Arc::new(RwLock::new(MCPToolRegistry::with_builtin_tools())),
+            upstreams: Arc::new(RwLock::new(HashMap::new())),
+            allow_stdio_upstreams: false,
+            streamable_sessions: StreamableSessions::new(),
+            completion_cache: CompletionCache::new(),
+            tool_cache: ToolResultCache::default(),
         }
     }
 
@@ -126,6 +377,7 @@ impl MCPServerManager {
             .await
             .map_err(|e| format!("Failed to create NATS storage: {}", e))?;
 
+        SSE_ROUTER.enable_cluster(nats_storage.client()).await;
         let manager = Self::with_storage(Arc::new(nats_storage));
 
         // Load existing instances from storage
@@ -134,6 +386,19 @@ impl MCPServerManager {
         Ok(manager)
     }
 
+    /// Create a new MCP server manager with Postgres storage
+    pub async fn with_postgres_storage(database_url: &str) -> Result<Self, String> {
+        let postgres_storage = PostgresMCPStorage::connect(database_url)
+            .await
+            .map_err(|e| format!("Failed to create Postgres storage: {}", e))?;
+        let manager = Self::with_storage(Arc::new(postgres_storage));
+
+        // Load existing instances from storage
+        manager.load_instances_from_storage().await?;
+
+        Ok(manager)
+    }
+
     /// Load existing instances from persistent storage
     async fn load_instances_from_storage(&self) -> Result<(), String> {
         match self.storage.list_server_instances().await {
@@ -204,6 +469,7 @@ impl MCPServerManager {
         description: String,
         project_contexts: Vec<String>,
         app_type: MCPApplicationType,
+        tenant_id: Option<String>,
     ) -> Result<String, String> {
         let instance_id = uuid::Uuid::new_v4().to_string();
 
@@ -216,6 +482,8 @@ impl MCPServerManager {
             capabilities: MCPCapabilities::default(),
             project_contexts,
             app_type: app_type.clone(),
+            tenant_id,
+            tool_policy: MCPToolPolicy::default(),
             created_at: chrono::Utc::now(),
             last_activity: chrono::Utc::now(),
             status: MCPServerStatus::Active,
@@ -288,6 +556,88 @@ impl MCPServerManager {
         }
     }
 
+    /// Register an OAuth client from its metadata, issuing its ID and secrets
+    pub async fn register_client(
+        &self,
+        metadata: ClientMetadata,
+    ) -> Result<IssuedClientRegistration, ClientRegistrationFailure> {
+        mcp_registration::validate_metadata(&metadata)
+            .map_err(ClientRegistrationFailure::Invalid)?;
+        let issued = mcp_registration::issue_registration(metadata);
+        self.storage
+            .store_client_registration(&issued.registration)
+            .await
+            .map_err(|e| {
+                error!("Failed to store client registration: {}", e);
+                ClientRegistrationFailure::Storage
+            })?;
+        info!(
+            "Registered OAuth client {} ({})",
+            issued.registration.client_id,
+            issued
+                .registration
+                .metadata
+                .client_name
+                .as_deref()
+                .unwrap_or("unnamed")
+        );
+        Ok(issued)
+    }
+
+    /// A client registration, provided `token` is its registration access token
+    pub async fn authorized_client_registration(
+        &self,
+        client_id: &str,
+        token: &str,
+    ) -> Result<MCPClientRegistration, ClientRegistrationFailure> {
+        match self.storage.get_client_registration(client_id).await {
+            Ok(Some(registration)) if registration.authorizes(token) => Ok(registration),
+            // Unknown clients answer like a wrong token, so IDs cannot be probed
+            Ok(_) => Err(ClientRegistrationFailure::Unauthorized),
+            Err(e) => {
+                error!("Failed to get client registration from storage: {}", e);
+                Err(ClientRegistrationFailure::Storage)
+            }
+        }
+    }
+
+    /// Replace the metadata of a registered client. A secret is issued when the client
+    /// stops being public, and dropped when it becomes public.
+    pub async fn update_client_registration(
+        &self,
+        mut registration: MCPClientRegistration,
+        metadata: ClientMetadata,
+    ) -> Result<(MCPClientRegistration, Option<String>), ClientRegistrationFailure> {
+        mcp_registration::validate_metadata(&metadata)
+            .map_err(ClientRegistrationFailure::Invalid)?;
+        let client_secret = registration.replace_metadata(metadata);
+        self.storage
+            .store_client_registration(&registration)
+            .await
+            .map_err(|e| {
+                error!("Failed to store client registration: {}", e);
+                ClientRegistrationFailure::Storage
+            })?;
+        info!("Updated OAuth client {}", registration.client_id);
+        Ok((registration, client_secret))
+    }
+
+    /// Remove a registered client
+    pub async fn delete_client_registration(
+        &self,
+        client_id: &str,
+    ) -> Result<(), ClientRegistrationFailure> {
+        self.storage
+            .delete_client_registration(client_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to delete client registration: {}", e);
+                ClientRegistrationFailure::Storage
+            })?;
+        info!("Deleted OAuth client {}", client_id);
+        Ok(())
+    }
+
     /// List all server instances
     pub async fn list_server_instances(&self) -> Vec<MCPServerInstance> {
         match self.storage.list_server_instances().await {
@@ -340,13 +690,30 @@ impl MCPServerManager {
             .and_then(|h| h.to_str().ok())
             .and_then(|s| s.strip_prefix("Bearer "));
 
-        if let Some(token) = auth_header {
-            self.jwt_service
-                .validate_token(token)
-                .await
-                .map_err(|e| e.to_string())
-        } else {
-            Err("Missing authorization header".to_string())
+        let Some(token) = auth_header else {
+            return Err("Missing authorization header".to_string());
+        };
+
+        let ip = client_ip(headers);
+        let app_id = claimed_app_id(token);
+        let throttle = AuthThrottle::global();
+        throttle
+            .check(ip.as_deref(), app_id.as_deref())
+            .map_err(|lockout| lockout.message())?;
+        match self.jwt_service.validate_token(token).await {
+            Ok(claims) => {
+                throttle.record_success(&claims.app_id);
+                Ok(claims)
+            }
+            Err(e) => {
+                throttle.record_failure(
+                    AuthSurface::Jwt,
+                    ip.as_deref(),
+                    app_id.as_deref(),
+                    &format!("JWT validation failed: {}", e),
+                );
+                Err(e.to_string())
+            }
         }
     }
 
@@ -472,383 +839,286 @@ impl MCPServerManager {
             .map_err(|e| e.to_string())
     }
 
-    /// Get default tools for a server instance
-    pub async fn get_default_tools(&self, instance_id: &str) -> Vec<MCPTool> {
-        debug!("🔧 get_default_tools called for instance: {}", instance_id);
+    /// Replace the tool policy of a server instance
+    pub async fn set_tool_policy(
+        &self,
+        instance_id: &str,
+        tool_policy: MCPToolPolicy,
+    ) -> Result<(), String> {
+        let mut instance = self
+            .get_server_instance(instance_id)
+            .await
+            .ok_or_else(|| format!("Server instance '{}' not found", instance_id))?;
+        instance.tool_policy = tool_policy;
+        self.storage
+            .store_server_instance(&instance)
+            .await
+            .map_err(|e| format!("Failed to store MCP instance: {}", e))?;
+        info!("Updated tool policy of MCP instance: {}", instance_id);
+        self.registry.write().await.add_server_instance(instance);
+        Ok(())
+    }
 
-        // Get the instance to check its configuration
-        if let Some(instance) = self.get_server_instance(instance_id).await {
-            debug!(
-                "✅ Found instance: {} ({})",
-                instance.name, instance.instance_id
-            );
-            debug!("📋 Instance app_type: {:?}", instance.app_type);
+    /// Tools a server instance offers
+    pub async fn get_tools(&self, instance_id: &str) -> Vec<MCPTool> {
+        let Some(instance) = self.get_server_instance(instance_id).await else {
+            warn!("❌ Instance '{}' not found, offering no tools", instance_id);
+            return Vec::new();
+        };
+        let tools: Vec<MCPTool> = self
+            .tools
+            .read()
+            .await
+            .handlers(&instance)
+            .iter()
+            .filter(|handler| {
+                instance
+                    .tool_policy
+                    .permits(&handler.definition().name, handler.read_only())
+            })
+            .map(|handler| handler.definition())
+            .collect();
+        debug!(
+            "🛠️  Offering {} {} tools for instance {}",
+            tools.len(),
+            mcp_tools::toolset(&instance),
+            instance_id
+        );
+        tools
+    }
 
-            match &instance.app_type {
-                MCPApplicationType::Remote(oauth_config) => {
-                    debug!(
-                        "🌐 Remote instance with provider: {}",
-                        oauth_config.provider_type
-                    );
-                    // Return provider-specific tools based on the OAuth provider type
-                    let tools = match oauth_config.provider_type.as_str() {
-                        "gitlab" => {
-                            debug!("🦊 Returning GitLab tools");
-                            self.get_gitlab_tools()
-                        }
-                        "github" => {
-                            debug!("🐙 Returning GitHub tools");
-                            self.get_github_tools()
-                        }
-                        "google" => {
-                            debug!("🔍 Returning Google tools");
-                            self.get_google_tools()
-                        }
-                        provider => {
-                            debug!(
-                                "❓ Unknown provider '{}', returning generic remote tools",
-                                provider
-                            );
-                            self.get_generic_remote_tools()
-                        }
-                    };
-                    debug!(
-                        "🛠️  Returning {} tools for {} instance",
-                        tools.len(),
-                        oauth_config.provider_type
-                    );
-                    for tool in &tools {
-                        debug!("   • {}: {}", tool.name, tool.description);
-                    }
-                    tools
-                }
-                MCPApplicationType::Local => {
-                    debug!("🏠 Local instance, returning generic local tools");
-                    let tools = self.get_generic_local_tools();
-                    debug!("🛠️  Returning {} local tools", tools.len());
-                    for tool in &tools {
-                        debug!("   • {}: {}", tool.name, tool.description);
-                    }
-                    tools
-                }
+    /// Offer a custom tool to a single server instance
+    pub async fn register_tool(&self, instance_id: &str, handler: Arc<dyn MCPToolHandler>) {
+        info!(
+            "Registering tool {} for instance {}",
+            handler.definition().name,
+            instance_id
+        );
+        self.tools
+            .write()
+            .await
+            .register_instance_tool(instance_id, handler);
+    }
+
+    /// Connect an upstream MCP server and offer its tools on an instance, returning
+    /// the names they are offered under
+    pub async fn connect_upstream(
+        &self,
+        instance_id: &str,
+        config: MCPUpstreamConfig,
+    ) -> Result<Vec<String>, MCPClientError> {
+        let exists = self
+            .upstreams
+            .read()
+            .await
+            .get(instance_id)
+            .is_some_and(|upstreams| {
+                upstreams
+                    .iter()
+                    .any(|upstream| upstream.client.config().name == config.name)
+            });
+        if exists {
+            return Err(MCPClientError::InvalidConfig(format!(
+                "instance {} already has an upstream named '{}'",
+                instance_id, config.name
+            )));
+        }
+
+        let client = Arc::new(MCPClient::connect(config).await?);
+        let tools = match client.list_tools().await {
+            Ok(tools) => tools,
+            Err(e) => {
+                client.close().await;
+                return Err(e);
             }
-        } else {
-            warn!(
-                "❌ Instance '{}' not found, returning fallback tools",
-                instance_id
-            );
-            let tools = self.get_generic_local_tools();
-            debug!("🛠️  Returning {} fallback tools", tools.len());
-            tools
+        };
+        let mut names = Vec::new();
+        for tool in tools {
+            let handler = Arc::new(MCPProxiedTool::new(client.clone(), tool));
+            names.push(handler.definition().name);
+            self.register_tool(instance_id, handler).await;
         }
+        info!(
+            "Proxying {} tools of upstream {} on instance {}",
+            names.len(),
+            client.config().name,
+            instance_id
+        );
+        self.upstreams
+            .write()
+            .await
+            .entry(instance_id.to_string())
+            .or_default()
+            .push(MCPUpstream {
+                client,
+                tools: names.clone(),
+            });
+        Ok(names)
     }
 
-    /// Get GitLab-specific tools
-    fn get_gitlab_tools(&self) -> Vec<MCPTool> {
-        vec![
-            MCPTool {
-                name: "gitlab_search".to_string(),
-                description: "Search across GitLab projects and repositories".to_string(),
-                input_schema: serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "query": {"type": "string", "description": "Search query"},
-                        "scope": {"type": "string", "enum": ["projects", "issues", "merge_requests", "commits"], "default": "projects"},
-                        "project_id": {"type": "string", "description": "Specific project ID to search within (optional)"}
-                    },
-                    "required": ["query"]
-                }),
-            },
-            MCPTool {
-                name: "gitlab_list_projects".to_string(),
-                description: "List GitLab projects accessible to the authenticated user"
-                    .to_string(),
-                input_schema: serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "visibility": {"type": "string", "enum": ["private", "internal", "public"], "description": "Filter by visibility"},
-                        "owned": {"type": "boolean", "description": "Only show owned projects"},
-                        "starred": {"type": "boolean", "description": "Only show starred projects"},
-                        "per_page": {"type": "integer", "minimum": 1, "maximum": 100, "default": 20}
-                    }
-                }),
-            },
-            MCPTool {
-                name: "gitlab_get_project".to_string(),
-                description: "Get detailed information about a specific GitLab project".to_string(),
-                input_schema: serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "project_id": {"type": "string", "description": "GitLab project ID or path"}
-                    },
-                    "required": ["project_id"]
-                }),
-            },
-            MCPTool {
-                name: "gitlab_list_issues".to_string(),
-                description: "List issues in a GitLab project".to_string(),
-                input_schema: serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "project_id": {"type": "string", "description": "GitLab project ID"},
-                        "state": {"type": "string", "enum": ["opened", "closed", "all"], "default": "opened"},
-                        "labels": {"type": "string", "description": "Comma-separated list of labels"},
-                        "per_page": {"type": "integer", "minimum": 1, "maximum": 100, "default": 20}
-                    },
-                    "required": ["project_id"]
-                }),
-            },
-            MCPTool {
-                name: "gitlab_create_issue".to_string(),
-                description: "Create a new issue in a GitLab project".to_string(),
-                input_schema: serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "project_id": {"type": "string", "description": "GitLab project ID"},
-                        "title": {"type": "string", "description": "Issue title"},
-                        "description": {"type": "string", "description": "Issue description"},
-                        "labels": {"type": "array", "items": {"type": "string"}, "description": "Issue labels"},
-                        "assignee_ids": {"type": "array", "items": {"type": "integer"}, "description": "User IDs to assign"}
-                    },
-                    "required": ["project_id", "title"]
-                }),
-            },
-            MCPTool {
-                name: "gitlab_list_merge_requests".to_string(),
-                description: "List merge requests in a GitLab project".to_string(),
-                input_schema: serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "project_id": {"type": "string", "description": "GitLab project ID"},
-                        "state": {"type": "string", "enum": ["opened", "closed", "merged", "all"], "default": "opened"},
-                        "per_page": {"type": "integer", "minimum": 1, "maximum": 100, "default": 20}
-                    },
-                    "required": ["project_id"]
-                }),
-            },
-            MCPTool {
-                name: "gitlab_get_file".to_string(),
-                description: "Get file contents from a GitLab repository".to_string(),
-                input_schema: serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "project_id": {"type": "string", "description": "GitLab project ID"},
-                        "file_path": {"type": "string", "description": "Path to the file"},
-                        "ref": {"type": "string", "description": "Branch, tag, or commit SHA", "default": "main"}
-                    },
-                    "required": ["project_id", "file_path"]
-                }),
-            },
-            MCPTool {
-                name: "gitlab_list_pipelines".to_string(),
-                description: "List CI/CD pipelines for a GitLab project".to_string(),
-                input_schema: serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "project_id": {"type": "string", "description": "GitLab project ID"},
-                        "status": {"type": "string", "enum": ["running", "pending", "success", "failed", "canceled", "skipped"], "description": "Filter by status"},
-                        "per_page": {"type": "integer", "minimum": 1, "maximum": 100, "default": 20}
-                    },
-                    "required": ["project_id"]
-                }),
-            },
-            MCPTool {
-                name: "gitlab_get_user".to_string(),
-                description: "Get information about the authenticated GitLab user".to_string(),
-                input_schema: serde_json::json!({
-                    "type": "object",
-                    "properties": {}
-                }),
-            },
-        ]
+    /// Upstream MCP servers of an instance
+    pub async fn get_upstreams(&self, instance_id: &str) -> Vec<MCPUpstream> {
+        self.upstreams
+            .read()
+            .await
+            .get(instance_id)
+            .cloned()
+            .unwrap_or_default()
     }
 
-    /// Get GitHub-specific tools
-    fn get_github_tools(&self) -> Vec<MCPTool> {
-        vec![
-            MCPTool {
-                name: "github_list_repositories".to_string(),
-                description: "List GitHub repositories accessible to the authenticated user"
-                    .to_string(),
-                input_schema: serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "visibility": {"type": "string", "enum": ["all", "public", "private"], "default": "all"},
-                        "affiliation": {"type": "string", "enum": ["owner", "collaborator", "organization_member"], "default": "owner"},
-                        "per_page": {"type": "integer", "minimum": 1, "maximum": 100, "default": 30}
-                    }
-                }),
-            },
-            MCPTool {
-                name: "github_get_repository".to_string(),
-                description: "Get detailed information about a specific GitHub repository"
-                    .to_string(),
-                input_schema: serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "owner": {"type": "string", "description": "Repository owner"},
-                        "repo": {"type": "string", "description": "Repository name"}
-                    },
-                    "required": ["owner", "repo"]
-                }),
-            },
-            MCPTool {
-                name: "github_list_issues".to_string(),
-                description: "List issues in a GitHub repository".to_string(),
-                input_schema: serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "owner": {"type": "string", "description": "Repository owner"},
-                        "repo": {"type": "string", "description": "Repository name"},
-                        "state": {"type": "string", "enum": ["open", "closed", "all"], "default": "open"},
-                        "per_page": {"type": "integer", "minimum": 1, "maximum": 100, "default": 30}
-                    },
-                    "required": ["owner", "repo"]
-                }),
-            },
-        ]
-    }
-
-    /// Get Google-specific tools (placeholder)
-    fn get_google_tools(&self) -> Vec<MCPTool> {
-        vec![MCPTool {
-            name: "google_drive_list".to_string(),
-            description: "List files in Google Drive".to_string(),
-            input_schema: serde_json::json!({
-                "type": "object",
-                "properties": {
-                    "query": {"type": "string", "description": "Search query"},
-                    "max_results": {"type": "integer", "minimum": 1, "maximum": 100, "default": 10}
-                }
-            }),
-        }]
-    }
-
-    /// Get generic tools for remote instances with unknown providers
-    fn get_generic_remote_tools(&self) -> Vec<MCPTool> {
-        vec![MCPTool {
-            name: "api_request".to_string(),
-            description: "Make authenticated API requests to the configured provider".to_string(),
-            input_schema: serde_json::json!({
-                "type": "object",
-                "properties": {
-                    "method": {"type": "string", "enum": ["GET", "POST", "PUT", "PATCH", "DELETE"], "default": "GET"},
-                    "endpoint": {"type": "string", "description": "API endpoint path"},
-                    "body": {"type": "object", "description": "Request body for POST/PUT/PATCH"}
-                },
-                "required": ["endpoint"]
-            }),
-        }]
+    /// Disconnect an upstream and withdraw its tools, returning whether it existed
+    pub async fn disconnect_upstream(&self, instance_id: &str, name: &str) -> bool {
+        let upstream = {
+            let mut upstreams = self.upstreams.write().await;
+            let Some(connected) = upstreams.get_mut(instance_id) else {
+                return false;
+            };
+            let Some(index) = connected
+                .iter()
+                .position(|upstream| upstream.client.config().name == name)
+            else {
+                return false;
+            };
+            connected.remove(index)
+        };
+
+        let mut tools = self.tools.write().await;
+        for tool in &upstream.tools {
+            tools.unregister_instance_tool(instance_id, tool);
+        }
+        drop(tools);
+        upstream.client.close().await;
+        info!(
+            "Disconnected upstream {} from instance {}",
+            name, instance_id
+        );
+        true
     }
 
-    /// Get generic tools for local instances
-    fn get_generic_local_tools(&self) -> Vec<MCPTool> {
-        vec![
-            MCPTool {
-                name: "create_workflow".to_string(),
-                description: "Create a new workflow definition".to_string(),
-                input_schema: serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "name": {"type": "string"},
-                        "description": {"type": "string"},
-                        "steps": {"type": "array"}
-                    },
-                    "required": ["name", "steps"]
-                }),
-            },
-            MCPTool {
-                name: "execute_agent".to_string(),
-                description: "Execute an agent with given parameters".to_string(),
-                input_schema: serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "agent_id": {"type": "string"},
-                        "parameters": {"type": "object"}
-                    },
-                    "required": ["agent_id"]
-                }),
-            },
-            MCPTool {
-                name: "search_project_context".to_string(),
-                description: "Search within a project context".to_string(),
-                input_schema: serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "context_id": {"type": "string"},
-                        "query": {"type": "string"},
-                        "filters": {"type": "object"}
-                    },
-                    "required": ["context_id", "query"]
-                }),
-            },
-        ]
-    }
-
-    /// Get default prompts for a server instance
-    pub async fn get_default_prompts(&self, _instance_id: &str) -> Vec<MCPPrompt> {
-        vec![
-            MCPPrompt {
-                name: "workflow_template".to_string(),
-                description: "Generate a workflow template for a given task".to_string(),
-                arguments: vec![
-                    MCPPromptArgument {
-                        name: "task_description".to_string(),
-                        description: "Description of the task to create a workflow for".to_string(),
-                        required: true,
-                    },
-                    MCPPromptArgument {
-                        name: "complexity_level".to_string(),
-                        description: "Complexity level (simple, medium, complex)".to_string(),
-                        required: false,
-                    },
-                ],
-            },
-            MCPPrompt {
-                name: "agent_configuration".to_string(),
-                description: "Generate agent configuration for specific tasks".to_string(),
-                arguments: vec![
-                    MCPPromptArgument {
-                        name: "agent_type".to_string(),
-                        description: "Type of agent (coding, analysis, creative, etc.)".to_string(),
-                        required: true,
-                    },
-                    MCPPromptArgument {
-                        name: "capabilities".to_string(),
-                        description: "Required capabilities for the agent".to_string(),
-                        required: false,
-                    },
-                ],
-            },
-        ]
+    /// Prompt templates of a server instance: the built-in ones, replaced or extended
+    /// by those stored for the instance
+    pub async fn get_prompt_templates(
+        &self,
+        instance_id: &str,
+    ) -> Result<Vec<MCPPromptTemplate>, PromptTemplateError> {
+        let stored = self
+            .storage
+            .get_prompt_templates(instance_id)
+            .await
+            .map_err(|e| PromptTemplateError::Storage(e.to_string()))?;
+        let mut templates: Vec<MCPPromptTemplate> = mcp_prompts::default_templates()
+            .into_iter()
+            .filter(|template| !stored.iter().any(|stored| stored.name == template.name))
+            .collect();
+        templates.extend(stored);
+        Ok(templates)
+    }
+
+    /// Prompts a server instance advertises
+    pub async fn get_prompts(&self, instance_id: &str) -> Vec<MCPPrompt> {
+        let templates = self
+            .get_prompt_templates(instance_id)
+            .await
+            .unwrap_or_else(|e| {
+                warn!(
+                    "Serving built-in prompts for instance {}: {}",
+                    instance_id, e
+                );
+                mcp_prompts::default_templates()
+            });
+        templates.iter().map(mcp_prompts::prompt).collect()
     }
 
-    /// Create a new session for a specific server instance
-    pub async fn create_session(
+    /// Create or replace a prompt template of a server instance
+    pub async fn set_prompt_template(
         &self,
-        server_instance_id: String,
-        installation_id: String,
-        app_id: String,
-        client_info: MCPClientInfo,
-        user_id: Option<String>,
-        permissions: MCPSessionPermissions,
-        project_contexts: Vec<String>,
-    ) -> String {
-        let session_id = uuid::Uuid::new_v4().to_string();
+        instance_id: &str,
+        template: MCPPromptTemplate,
+    ) -> Result<(), PromptTemplateError> {
+        mcp_prompts::validate_template(&template)?;
+
+        let mut stored = self
+            .storage
+            .get_prompt_templates(instance_id)
+            .await
+            .map_err(|e| PromptTemplateError::Storage(e.to_string()))?;
+        stored.retain(|stored| stored.name != template.name);
+        info!(
+            "Storing prompt template {} for instance {}",
+            template.name, instance_id
+        );
+        stored.push(template);
+        self.storage
+            .store_prompt_templates(instance_id, &stored)
+            .await
+            .map_err(|e| PromptTemplateError::Storage(e.to_string()))
+    }
+
+    /// Remove a stored prompt template of a server instance, returning whether it existed
+    pub async fn delete_prompt_template(
+        &self,
+        instance_id: &str,
+        name: &str,
+    ) -> Result<bool, PromptTemplateError> {
+        let mut stored = self
+            .storage
+            .get_prompt_templates(instance_id)
+            .await
+            .map_err(|e| PromptTemplateError::Storage(e.to_string()))?;
+        let count = stored.len();
+        stored.retain(|stored| stored.name != name);
+        if stored.len() == count {
+            return Ok(false);
+        }
+        self.storage
+            .store_prompt_templates(instance_id, &stored)
+            .await
+            .map_err(|e| PromptTemplateError::Storage(e.to_string()))?;
+        Ok(true)
+    }
+
+    /// Render a prompt of a server instance with the given arguments
+    pub async fn render_prompt(
+        &self,
+        instance_id: &str,
+        name: &str,
+        arguments: &HashMap<String, String>,
+    ) -> Result<(MCPPromptTemplate, Vec<SamplingMessage>), PromptTemplateError> {
+        let template = self
+            .get_prompt_templates(instance_id)
+            .await?
+            .into_iter()
+            .find(|template| template.name == name)
+            .ok_or_else(|| PromptTemplateError::NotFound(name.to_string()))?;
+        let messages = mcp_prompts::render(&template, arguments)?;
+        Ok((template, messages))
+    }
+
+    /// Create a new session for a specific server instance, under the session ID
+    /// the client picked when there is one
+    pub async fn create_session(&self, params: MCPSessionParams) -> String {
+        let session_id = params
+            .session_id
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
         let session = MCPSession {
             session_id: session_id.clone(),
-            server_instance_id,
-            installation_id,
-            app_id,
-            user_id,
-            client_info,
-            permissions,
-            project_contexts,
+            server_instance_id: params.server_instance_id,
+            installation_id: params.installation_id,
+            app_id: params.app_id,
+            user_id: params.user_id,
+            client_info: params.client_info,
+            permissions: params.permissions,
+            project_contexts: params.project_contexts,
             created_at: chrono::Utc::now(),
             last_activity: chrono::Utc::now(),
             expires_at: Some(chrono::Utc::now() + chrono::Duration::hours(24)),
         };
 
+        // Store in persistent storage so every node can resume the session
+        if let Err(e) = self.storage.store_session(&session).await {
+            error!("Failed to store MCP session in persistent storage: {}", e);
+        }
+
         let server_instance_id = session.server_instance_id.clone();
         let mut sessions = self.sessions.write().await;
         sessions.insert(session_id.clone(), session);
@@ -860,18 +1130,63 @@ impl MCPServerManager {
         session_id
     }
 
-    /// Update session activity
+    /// Update session activity, writing it through to storage at most once per
+    /// [`SESSION_ACTIVITY_PERSIST_INTERVAL`]
     pub async fn update_session_activity(&self, session_id: &str) {
-        let mut sessions = self.sessions.write().await;
-        if let Some(session) = sessions.get_mut(session_id) {
-            session.last_activity = chrono::Utc::now();
+        let Some(mut session) = self.get_session(session_id).await else {
+            return;
+        };
+        let now = chrono::Utc::now();
+        let persist = (now - session.last_activity).to_std().unwrap_or_default()
+            >= SESSION_ACTIVITY_PERSIST_INTERVAL;
+        session.last_activity = now;
+        if persist {
+            if let Err(e) = self.storage.store_session(&session).await {
+                warn!(
+                    "Failed to store activity of MCP session {}: {}",
+                    session_id, e
+                );
+            }
         }
+        self.sessions
+            .write()
+            .await
+            .insert(session_id.to_string(), session);
     }
 
-    /// Get session
+    /// Get a session that has not expired, from this node or from storage
     pub async fn get_session(&self, session_id: &str) -> Option<MCPSession> {
-        let sessions = self.sessions.read().await;
-        sessions.get(session_id).cloned()
+        let cached = self.sessions.read().await.get(session_id).cloned();
+        let session = match cached {
+            Some(session) => session,
+            None => match self.storage.get_session(session_id).await {
+                Ok(Some(session)) => {
+                    debug!("Loaded MCP session {} from storage", session_id);
+                    self.sessions
+                        .write()
+                        .await
+                        .insert(session_id.to_string(), session.clone());
+                    session
+                }
+                Ok(None) => return None,
+                Err(e) => {
+                    error!("Failed to get session from storage: {}", e);
+                    return None;
+                }
+            },
+        };
+
+        if session
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= chrono::Utc::now())
+        {
+            self.sessions.write().await.remove(session_id);
+            if let Err(e) = self.storage.delete_session(session_id).await {
+                warn!("Failed to delete expired MCP session {}: {}", session_id, e);
+            }
+            return None;
+        }
+        Some(session)
     }
 
     /// Get the OAuth manager
@@ -880,41 +1195,165 @@ impl MCPServerManager {
     }
 }
 
+/// Audit record of a `tools/call` request on `instance`, naming the installation that
+/// called it when the request was authenticated
+fn tool_invocation_record(
+    request: &MCPRequest,
+    instance: &MCPServerInstance,
+    claims: Option<&MCPTokenClaims>,
+    outcome: AuditOutcome,
+) -> AuditRecord {
+    let tool = request
+        .params
+        .as_ref()
+        .and_then(|params| params.get("name"))
+        .and_then(|name| name.as_str())
+        .unwrap_or("-");
+    let record = AuditRecord::new(AuditCategory::ToolInvocation, "tools/call", outcome)
+        .with_tenant(instance.tenant_id.as_deref())
+        .with_target(format!("{}/{}", instance.instance_id, tool));
+    match claims {
+        Some(claims) => record.with_actor(claims.installation_id.clone()),
+        None => record,
+    }
+}
+
 /// Circuit Breaker MCP Server - handles multi-tenant MCP instances
 pub struct CircuitBreakerMCPServer {
     manager: MCPServerManager,
+    /// Whether requests need an API key with the `mcp` scope
+    api_key_required: bool,
+    /// Log notifications to the client session the requests come from
+    logger: Option<MCPLogger>,
 }
 
 impl CircuitBreakerMCPServer {
     /// Create a new MCP server
     pub fn new() -> Self {
-        Self {
-            manager: MCPServerManager::new(),
-        }
+        Self::with_manager(MCPServerManager::new())
     }
 
     /// Create a new MCP server with an existing manager
     pub fn with_manager(manager: MCPServerManager) -> Self {
-        Self { manager }
+        Self {
+            manager,
+            api_key_required: false,
+            logger: None,
+        }
+    }
+
+    /// Send `notifications/message` for the handled requests through `logger`
+    pub fn with_logger(mut self, logger: Option<MCPLogger>) -> Self {
+        self.logger = logger;
+        self
     }
 
     /// Create a new MCP server with NATS storage
     pub async fn with_nats_storage(nats_url: &str) -> Result<Self, String> {
         let manager = MCPServerManager::with_nats_storage(nats_url).await?;
-        Ok(Self { manager })
+        Ok(Self::with_manager(manager))
+    }
+
+    /// Create a new MCP server with Postgres storage
+    pub async fn with_postgres_storage(database_url: &str) -> Result<Self, String> {
+        let manager = MCPServerManager::with_postgres_storage(database_url).await?;
+        Ok(Self::with_manager(manager))
+    }
+
+    /// Serve `sampling/createMessage` through `router`, billing each instance's tenant
+    pub fn with_sampling(
+        mut self,
+        router: Arc<LLMRouter>,
+        cost_optimizer: Arc<RwLock<CostOptimizer>>,
+    ) -> Self {
+        self.manager.sampling = Some(MCPSampling::new(router, cost_optimizer));
+        self
+    }
+
+    /// Let the management API add upstream MCP servers that run as local processes
+    pub fn with_stdio_upstreams(mut self, allowed: bool) -> Self {
+        self.manager.allow_stdio_upstreams = allowed;
+        self
+    }
+
+    /// Reuse results of read-only tool calls for `ttl`; zero turns caching off
+    pub fn with_tool_cache_ttl(mut self, ttl: std::time::Duration) -> Self {
+        self.manager.tool_cache = ToolResultCache::new(ttl);
+        self
+    }
+
+    /// Spawn the background task refreshing the instances' OAuth tokens before they expire
+    pub fn spawn_token_refresher(&self) -> tokio::task::JoinHandle<()> {
+        self.manager.oauth_manager.clone().spawn_token_refresher()
+    }
+
+    /// Load the keys installation and session tokens are signed with: the configured key
+    /// file, or the keys shared through storage by other nodes
+    pub async fn load_signing_keys(&self) -> Result<(), String> {
+        self.manager
+            .jwt_service
+            .load_signing_keys()
+            .await
+            .map_err(|e| format!("Failed to load MCP signing keys: {}", e))
+    }
+
+    /// Spawn the background task rotating the keys installation and session tokens are
+    /// signed with every `interval`
+    pub fn spawn_signing_key_rotation(
+        &self,
+        interval: std::time::Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        self.manager
+            .jwt_service
+            .clone()
+            .spawn_signing_key_rotation(interval)
+    }
+
+    /// Require an API key with the `mcp` scope on MCP requests
+    pub fn with_api_key_required(mut self, required: bool) -> Self {
+        self.api_key_required = required;
+        self
     }
 
     /// Create the MCP router with multi-tenant support
     pub fn create_router(&self) -> Router {
-        Router::new()
+        let router = Router::new()
             // Multi-tenant MCP protocol endpoints
             .route("/mcp/:instance_id", post(handle_mcp_request))
             .route("/mcp/:instance_id", get(handle_mcp_get_request))
             .route("/mcp/:instance_id/ws", get(handle_mcp_websocket))
+            // Streamable HTTP transport endpoint
+            .route(
+                "/mcp/:instance_id/stream",
+                post(handle_streamable_post)
+                    .get(handle_streamable_get)
+                    .delete(handle_streamable_delete)
+                    .options(handle_streamable_options),
+            )
             // Server instance management
             .route("/mcp/instances", post(create_mcp_instance))
             .route("/mcp/instances/:instance_id", get(get_mcp_instance))
             .route("/mcp/instances/:instance_id/info", get(get_server_info))
+            .route(
+                "/mcp/instances/:instance_id/prompts",
+                get(list_prompt_templates),
+            )
+            .route(
+                "/mcp/instances/:instance_id/prompts/:name",
+                put(put_prompt_template).delete(delete_prompt_template),
+            )
+            .route(
+                "/mcp/instances/:instance_id/tool-policy",
+                get(get_tool_policy).put(put_tool_policy),
+            )
+            .route(
+                "/mcp/instances/:instance_id/upstreams",
+                get(list_upstreams).post(connect_upstream),
+            )
+            .route(
+                "/mcp/instances/:instance_id/upstreams/:name",
+                delete(disconnect_upstream),
+            )
             // Tool management endpoints (per instance)
             .route("/mcp/:instance_id/tools", get(list_tools))
             .route("/mcp/:instance_id/prompts", get(list_prompts))
@@ -954,6 +1393,11 @@ impl CircuitBreakerMCPServer {
                 "/.well-known/mcp",
                 get(handle_mcp_discovery).options(handle_options),
             )
+            // Public keys validating the installation and session tokens issued here
+            .route(
+                "/.well-known/jwks.json",
+                get(handle_jwks).options(handle_options),
+            )
             .route(
                 "/oauth/metadata",
                 get(handle_oauth_metadata).options(handle_options),
@@ -962,8 +1406,16 @@ impl CircuitBreakerMCPServer {
                 "/register",
                 post(handle_oauth_register).options(handle_options),
             )
+            .route(
+                "/register/:client_id",
+                get(handle_get_client_registration)
+                    .put(handle_update_client_registration)
+                    .delete(handle_delete_client_registration)
+                    .options(handle_client_registration_options),
+            )
             // Debug endpoint to list instances
             .route("/debug/instances", get(handle_debug_instances))
+            .route("/debug/streams", get(handle_debug_streams))
             // Authentication endpoints
             // OAuth endpoints
             .route("/mcp/auth/apps", post(register_app))
@@ -995,8 +1447,18 @@ impl CircuitBreakerMCPServer {
                 "/oauth/callback",
                 get(handle_mcp_client_oauth_callback).post(handle_mcp_client_oauth_callback),
             )
+            .layer(axum::middleware::from_fn(record_peer_addr))
             // Add state
-            .with_state(self.manager.clone())
+            .with_state(self.manager.clone());
+
+        if self.api_key_required {
+            router.layer(axum::middleware::from_fn_with_state(
+                ApiKeys::global(),
+                super::api_keys::require_mcp_api_key,
+            ))
+        } else {
+            router
+        }
     }
 
     /// Handle MCP request for a specific instance
@@ -1027,7 +1489,8 @@ impl CircuitBreakerMCPServer {
                     Some(get_request_id()),
                     error_codes::INVALID_REQUEST,
                     format!("Server instance '{}' not found", instance_id),
-                );
+                )
+                .with_error_code(crate::ErrorCode::NotFound);
             }
         };
 
@@ -1039,7 +1502,8 @@ impl CircuitBreakerMCPServer {
                     Some(get_request_id()),
                     error_codes::INVALID_REQUEST,
                     "Token not valid for this instance".to_string(),
-                );
+                )
+                .with_error_code(crate::ErrorCode::AuthenticationFailed);
             }
 
             // Check if the app matches
@@ -1048,7 +1512,36 @@ impl CircuitBreakerMCPServer {
                     Some(get_request_id()),
                     error_codes::INVALID_REQUEST,
                     "Token app_id does not match instance".to_string(),
-                );
+                )
+                .with_error_code(crate::ErrorCode::AuthenticationFailed);
+            }
+
+            // Installations with a role may only call the methods it allows
+            let subject = Subject::McpInstallation(claims.installation_id.clone());
+            let permission = mcp_method_permission(&request.method);
+            if let Err(denied) = RoleAssignments::global()
+                .authorize(&subject, Some(permission))
+                .await
+            {
+                if request.method == "tools/call" {
+                    AuditTrail::global()
+                        .record(
+                            tool_invocation_record(
+                                &request,
+                                &instance,
+                                Some(claims),
+                                AuditOutcome::Denied,
+                            )
+                            .with_detail(denied.message()),
+                        )
+                        .await;
+                }
+                return MCPResponse::error_from_request(
+                    Some(get_request_id()),
+                    error_codes::INVALID_REQUEST,
+                    denied.message(),
+                )
+                .with_error_code(crate::ErrorCode::PermissionDenied);
             }
         }
 
@@ -1070,9 +1563,43 @@ impl CircuitBreakerMCPServer {
                 info!("🛠️  Handling tools/list request");
                 self.handle_list_tools(request, &instance).await
             }
+            // Tool calls and sampling are new executions, which maintenance mode holds back
+            "tools/call" | "sampling/createMessage"
+                if crate::MaintenanceMode::global().is_enabled() =>
+            {
+                MCPResponse::error_from_request(
+                    Some(get_request_id()),
+                    error_codes::INVALID_REQUEST,
+                    crate::MaintenanceMode::global().status().message(),
+                )
+                .with_error_code(crate::ErrorCode::MaintenanceMode)
+            }
             "tools/call" => {
                 info!("⚡ Handling tools/call request");
-                self.handle_call_tool(request, &instance).await
+                let record = tool_invocation_record(
+                    &request,
+                    &instance,
+                    claims.as_ref(),
+                    AuditOutcome::Success,
+                );
+                let response = self.handle_call_tool(request, &instance).await;
+                let failed = response.error.is_some()
+                    || response
+                        .result
+                        .as_ref()
+                        .and_then(|result| result.get("isError"))
+                        .and_then(|is_error| is_error.as_bool())
+                        .unwrap_or(false);
+                let record = if failed {
+                    AuditRecord {
+                        outcome: AuditOutcome::Failure,
+                        ..record
+                    }
+                } else {
+                    record
+                };
+                AuditTrail::global().record(record).await;
+                response
             }
             "prompts/list" => {
                 info!("📝 Handling prompts/list request");
@@ -1090,6 +1617,25 @@ impl CircuitBreakerMCPServer {
                 info!("📖 Handling resources/read request");
                 self.handle_read_resource(request, &instance).await
             }
+            "completion/complete" => {
+                info!("🔤 Handling completion/complete request");
+                self.handle_complete(request, &instance).await
+            }
+            "logging/setLevel" => {
+                info!("📣 Handling logging/setLevel request");
+                self.handle_set_log_level(request)
+            }
+            "sampling/createMessage" => match &self.manager.sampling {
+                Some(sampling) => {
+                    info!("🧠 Handling sampling/createMessage request");
+                    sampling.handle_create_message(request, &instance).await
+                }
+                None => MCPResponse::error_from_request(
+                    Some(get_request_id()),
+                    error_codes::METHOD_NOT_FOUND,
+                    "Sampling is not enabled on this server".to_string(),
+                ),
+            },
             method => {
                 warn!("❌ Unknown method: {}", method);
                 MCPResponse::error_from_request(
@@ -1110,7 +1656,7 @@ impl CircuitBreakerMCPServer {
         debug!("Initializing MCP server instance: {}", instance.instance_id);
 
         // For gitlab-demo instance, provide GitLab-specific capabilities
-        let capabilities = if instance.instance_id == "gitlab-demo" {
+        let mut capabilities = if instance.instance_id == "gitlab-demo" {
             serde_json::json!({
                 "tools": {
                     "list_repositories": {
@@ -1206,6 +1752,10 @@ impl CircuitBreakerMCPServer {
         } else {
             serde_json::to_value(&instance.capabilities).unwrap_or_default()
         };
+        if self.manager.sampling.is_some() {
+            capabilities["sampling"] = serde_json::json!({});
+        }
+        capabilities["completions"] = serde_json::json!({});
 
         let result = serde_json::json!({
             "protocolVersion": "2024-11-05",
@@ -1234,7 +1784,7 @@ impl CircuitBreakerMCPServer {
         );
         debug!("📋 Instance details: app_type={:?}", instance.app_type);
 
-        let tools = self.manager.get_default_tools(&instance.instance_id).await;
+        let tools = self.manager.get_tools(&instance.instance_id).await;
 
         info!(
             "🛠️  Retrieved {} tools for instance {}",
@@ -1258,6 +1808,75 @@ impl CircuitBreakerMCPServer {
         MCPResponse::success_from_request(request.id, result)
     }
 
+    /// Handle logging/setLevel request
+    /// Handle completion request
+    async fn handle_complete(
+        &self,
+        request: MCPRequest,
+        instance: &MCPServerInstance,
+    ) -> MCPResponse {
+        let complete = request
+            .params
+            .clone()
+            .map(serde_json::from_value::<MCPCompleteRequest>);
+        let complete = match complete {
+            Some(Ok(complete)) => complete,
+            Some(Err(e)) => {
+                return MCPResponse::error_from_request(
+                    request.id,
+                    error_codes::INVALID_PARAMS,
+                    format!("Invalid completion parameters: {}", e),
+                )
+                .with_error_code(crate::ErrorCode::InvalidInput);
+            }
+            None => {
+                return MCPResponse::error_from_request(
+                    request.id,
+                    error_codes::INVALID_PARAMS,
+                    "Missing completion parameters".to_string(),
+                );
+            }
+        };
+
+        match mcp_completion::candidates(&self.manager, instance, &complete).await {
+            Ok(candidates) => MCPResponse::success_from_request(
+                request.id,
+                mcp_completion::completion(&candidates, &complete.argument.value),
+            ),
+            Err(e) => MCPResponse::error_from_request(request.id, error_codes::INVALID_PARAMS, e)
+                .with_error_code(crate::ErrorCode::NotFound),
+        }
+    }
+
+    fn handle_set_log_level(&self, request: MCPRequest) -> MCPResponse {
+        let Some(logger) = &self.logger else {
+            return MCPResponse::error_from_request(
+                request.id,
+                error_codes::INVALID_REQUEST,
+                "Logging needs a session transport such as streamable HTTP or WebSocket"
+                    .to_string(),
+            );
+        };
+        let level = request
+            .params
+            .as_ref()
+            .and_then(|params| params.get("level"))
+            .cloned()
+            .map(serde_json::from_value::<MCPLogLevel>);
+        match level {
+            Some(Ok(level)) => {
+                logger.set_level(level);
+                MCPResponse::success_from_request(request.id, serde_json::json!({}))
+            }
+            _ => MCPResponse::error_from_request(
+                request.id,
+                error_codes::INVALID_PARAMS,
+                "level must be one of debug, info, notice, warning, error, critical, alert or emergency".to_string(),
+            )
+            .with_error_code(crate::ErrorCode::InvalidInput),
+        }
+    }
+
     /// Handle call tool request
     async fn handle_call_tool(
         &self,
@@ -1288,19 +1907,128 @@ impl CircuitBreakerMCPServer {
             }
         };
 
+        let handler = self
+            .manager
+            .tools
+            .read()
+            .await
+            .handler(instance, &tool_call.name);
+        let Some(handler) = handler else {
+            return MCPResponse::error_from_request(
+                request.id,
+                error_codes::INVALID_PARAMS,
+                format!("Unknown tool: {}", tool_call.name),
+            )
+            .with_error_code(crate::ErrorCode::NotFound);
+        };
         // Execute the tool in the context of this instance
-        let result = self.execute_tool(tool_call, instance).await;
-
-        match result {
-            Ok(tool_result) => MCPResponse::success_from_request(
+        let context = MCPToolContext {
+            manager: &self.manager,
+            instance,
+            logger: self.logger.as_ref(),
+        };
+        if !instance
+            .tool_policy
+            .permits(&tool_call.name, handler.read_only())
+        {
+            warn!(
+                "Tool {} is blocked by the tool policy of instance {}",
+                tool_call.name, instance.instance_id
+            );
+            context.log(
+                MCPLogLevel::Warning,
+                format!(
+                    "{} is blocked by the instance's tool policy",
+                    tool_call.name
+                ),
+            );
+            return MCPResponse::error_from_request(
                 request.id,
-                serde_json::to_value(tool_result).unwrap(),
-            ),
-            Err(e) => MCPResponse::error_from_request(
+                error_codes::INVALID_REQUEST,
+                format!("Tool '{}' is not allowed on this instance", tool_call.name),
+            )
+            .with_error_code(crate::ErrorCode::PermissionDenied);
+        }
+        if let Err(message) =
+            mcp_tools::validate_arguments(&handler.definition(), &tool_call.arguments)
+        {
+            context.log(MCPLogLevel::Warning, message.clone());
+            return MCPResponse::error_from_request(
                 request.id,
-                error_codes::INTERNAL_ERROR,
-                format!("Tool execution failed: {}", e),
-            ),
+                error_codes::INVALID_PARAMS,
+                message,
+            )
+            .with_error_code(crate::ErrorCode::InvalidInput);
+        }
+
+        let read_only = handler.read_only();
+        let cache = &self.manager.tool_cache;
+        if read_only {
+            if let Some(tool_result) = cache
+                .get(&instance.instance_id, &tool_call.name, &tool_call.arguments)
+                .await
+            {
+                context.log(
+                    MCPLogLevel::Debug,
+                    format!("{} served from cache", tool_call.name),
+                );
+                return MCPResponse::success_from_request(
+                    request.id,
+                    serde_json::to_value(tool_result).unwrap(),
+                );
+            }
+        }
+
+        context.log(MCPLogLevel::Debug, format!("Running {}", tool_call.name));
+        let started = std::time::Instant::now();
+        let result = handler.call(&context, &tool_call.arguments).await;
+        if !read_only {
+            // A write may change anything cached for the instance
+            cache.invalidate(&instance.instance_id).await;
+        } else if let Ok(tool_result) = &result {
+            cache
+                .insert(
+                    &instance.instance_id,
+                    &tool_call.name,
+                    &tool_call.arguments,
+                    tool_result,
+                )
+                .await;
+        }
+
+        match result {
+            Ok(tool_result) => {
+                if tool_result.is_error == Some(true) {
+                    context.log(
+                        MCPLogLevel::Error,
+                        format!("{} returned an error result", tool_call.name),
+                    );
+                } else {
+                    context.log(
+                        MCPLogLevel::Info,
+                        format!(
+                            "{} finished in {} ms",
+                            tool_call.name,
+                            started.elapsed().as_millis()
+                        ),
+                    );
+                }
+                MCPResponse::success_from_request(
+                    request.id,
+                    serde_json::to_value(tool_result).unwrap(),
+                )
+            }
+            Err(e) => {
+                context.log(
+                    MCPLogLevel::Error,
+                    format!("{} failed: {}", tool_call.name, e),
+                );
+                MCPResponse::error_from_request(
+                    request.id,
+                    error_codes::INTERNAL_ERROR,
+                    format!("Tool execution failed: {}", e),
+                )
+            }
         }
     }
 
@@ -1315,10 +2043,7 @@ impl CircuitBreakerMCPServer {
             instance.instance_id
         );
 
-        let prompts = self
-            .manager
-            .get_default_prompts(&instance.instance_id)
-            .await;
+        let prompts = self.manager.get_prompts(&instance.instance_id).await;
         let result = serde_json::json!({
             "prompts": prompts
         });
@@ -1334,21 +2059,47 @@ impl CircuitBreakerMCPServer {
     ) -> MCPResponse {
         debug!("Getting prompt for instance: {}", instance.instance_id);
 
-        // For now, return a placeholder response
-        MCPResponse::success_from_request(
-            request.id,
-            serde_json::json!({
-                "messages": [
-                    {
-                        "role": "user",
-                        "content": {
-                            "type": "text",
-                            "text": format!("This is a placeholder prompt response for instance {}", instance.instance_id)
-                        }
-                    }
-                ]
-            }),
-        )
+        let params = match request.params {
+            Some(params) => params,
+            None => {
+                return MCPResponse::error_from_request(
+                    request.id,
+                    error_codes::INVALID_PARAMS,
+                    "Missing prompt parameters".to_string(),
+                );
+            }
+        };
+
+        let get_prompt: MCPGetPrompt = match serde_json::from_value(params) {
+            Ok(get_prompt) => get_prompt,
+            Err(e) => {
+                return MCPResponse::error_from_request(
+                    request.id,
+                    error_codes::INVALID_PARAMS,
+                    format!("Invalid prompt parameters: {}", e),
+                );
+            }
+        };
+
+        match self
+            .manager
+            .render_prompt(
+                &instance.instance_id,
+                &get_prompt.name,
+                &get_prompt.arguments,
+            )
+            .await
+        {
+            Ok((template, messages)) => MCPResponse::success_from_request(
+                request.id,
+                serde_json::json!({
+                    "description": template.description,
+                    "messages": messages
+                }),
+            ),
+            Err(e) => MCPResponse::error_from_request(request.id, e.rpc_code(), e.to_string())
+                .with_error_code(e.code()),
+        }
     }
 
     /// Handle list resources request
@@ -1403,161 +2154,9 @@ impl CircuitBreakerMCPServer {
             }),
         )
     }
+}
 
-    /// Execute a tool call in the context of a specific instance
-    async fn execute_tool(
-        &self,
-        tool_call: MCPToolCall,
-        instance: &MCPServerInstance,
-    ) -> Result<MCPToolResult, Box<dyn std::error::Error + Send + Sync>> {
-        match tool_call.name.as_str() {
-            "create_workflow" => {
-                info!(
-                    "Creating workflow with parameters: {:?} for instance: {}",
-                    tool_call.arguments, instance.instance_id
-                );
-                Ok(MCPToolResult {
-                    content: vec![MCPContent::text(format!(
-                        "Workflow created successfully for instance {} (placeholder)",
-                        instance.instance_id
-                    ))],
-                    is_error: Some(false),
-                })
-            }
-            "execute_agent" => {
-                info!(
-                    "Executing agent with parameters: {:?} for instance: {}",
-                    tool_call.arguments, instance.instance_id
-                );
-                Ok(MCPToolResult {
-                    content: vec![MCPContent::text(format!(
-                        "Agent executed successfully for instance {} (placeholder)",
-                        instance.instance_id
-                    ))],
-                    is_error: Some(false),
-                })
-            }
-            "search_project_context" => {
-                info!(
-                    "Searching project context with parameters: {:?} for instance: {}",
-                    tool_call.arguments, instance.instance_id
-                );
-
-                // Check if the requested context is available to this instance
-                let context_id = tool_call
-                    .arguments
-                    .get("context_id")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("");
-
-                if !instance.project_contexts.contains(&context_id.to_string()) {
-                    return Ok(MCPToolResult {
-                        content: vec![MCPContent::text(format!(
-                            "Access denied: Project context '{}' not available to this instance",
-                            context_id
-                        ))],
-                        is_error: Some(true),
-                    });
-                }
-
-                // Get search query
-                let query = tool_call
-                    .arguments
-                    .get("query")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("");
-
-                // Note: Authentication check would be performed at the request level
-                // For now, we'll log the context access attempt
-                info!(
-                    "Accessing project context '{}' for instance {}",
-                    context_id, instance.instance_id
-                );
-
-                // Attempt to perform real search using OAuth if available
-                let search_result = self
-                    .perform_context_search(instance, context_id, query)
-                    .await;
-
-                match search_result {
-                    Ok(results) => Ok(MCPToolResult {
-                        content: vec![MCPContent::text(format!(
-                            "Search results for '{}' in context {}: {}",
-                            query, context_id, results
-                        ))],
-                        is_error: Some(false),
-                    }),
-                    Err(e) => Ok(MCPToolResult {
-                        content: vec![MCPContent::text(format!(
-                            "Search failed for context {}: {}",
-                            context_id, e
-                        ))],
-                        is_error: Some(true),
-                    }),
-                }
-            }
-            // GitLab tools
-            "gitlab_get_user" => {
-                info!(
-                    "Getting GitLab user info for instance: {}",
-                    instance.instance_id
-                );
-                self.execute_gitlab_tool(&tool_call, instance).await
-            }
-            "gitlab_list_projects" => {
-                info!(
-                    "Listing GitLab projects for instance: {}",
-                    instance.instance_id
-                );
-                self.execute_gitlab_tool(&tool_call, instance).await
-            }
-            "gitlab_get_project" => {
-                info!(
-                    "Getting GitLab project for instance: {}",
-                    instance.instance_id
-                );
-                self.execute_gitlab_tool(&tool_call, instance).await
-            }
-            "gitlab_list_issues" => {
-                info!(
-                    "Listing GitLab issues for instance: {}",
-                    instance.instance_id
-                );
-                self.execute_gitlab_tool(&tool_call, instance).await
-            }
-            "gitlab_create_issue" => {
-                info!(
-                    "Creating GitLab issue for instance: {}",
-                    instance.instance_id
-                );
-                self.execute_gitlab_tool(&tool_call, instance).await
-            }
-            "gitlab_list_merge_requests" => {
-                info!(
-                    "Listing GitLab merge requests for instance: {}",
-                    instance.instance_id
-                );
-                self.execute_gitlab_tool(&tool_call, instance).await
-            }
-            "gitlab_get_file" => {
-                info!("Getting GitLab file for instance: {}", instance.instance_id);
-                self.execute_gitlab_tool(&tool_call, instance).await
-            }
-            "gitlab_list_pipelines" => {
-                info!(
-                    "Listing GitLab pipelines for instance: {}",
-                    instance.instance_id
-                );
-                self.execute_gitlab_tool(&tool_call, instance).await
-            }
-            "gitlab_search" => {
-                info!("Searching GitLab for instance: {}", instance.instance_id);
-                self.execute_gitlab_tool(&tool_call, instance).await
-            }
-            _ => Err(format!("Unknown tool: {}", tool_call.name).into()),
-        }
-    }
-
+impl MCPServerManager {
     /// Check if the authenticated user has permission to access a project context
     async fn check_project_context_permission(
         &self,
@@ -1644,7 +2243,7 @@ impl CircuitBreakerMCPServer {
     }
 
     /// Execute GitLab-specific tools using OAuth authentication
-    async fn execute_gitlab_tool(
+    pub(crate) async fn execute_gitlab_tool(
         &self,
         tool_call: &MCPToolCall,
         instance: &MCPServerInstance,
@@ -1665,7 +2264,6 @@ impl CircuitBreakerMCPServer {
             "gitlab_get_user" => {
                 let url = "https://gitlab.com/api/v4/user";
                 match self
-                    .manager
                     .make_authenticated_api_request(
                         &OAuthProviderType::GitLab,
                         &instance.installation_id,
@@ -1734,7 +2332,6 @@ impl CircuitBreakerMCPServer {
                 }
 
                 match self
-                    .manager
                     .make_authenticated_api_request(
                         &OAuthProviderType::GitLab,
                         &instance.installation_id,
@@ -1794,7 +2391,6 @@ impl CircuitBreakerMCPServer {
                 let url = format!("https://gitlab.com/api/v4/projects/{}", project_id);
 
                 match self
-                    .manager
                     .make_authenticated_api_request(
                         &OAuthProviderType::GitLab,
                         &instance.installation_id,
@@ -1858,7 +2454,6 @@ impl CircuitBreakerMCPServer {
                     project_id
                 );
                 match self
-                    .manager
                     .make_authenticated_api_request(
                         &OAuthProviderType::GitLab,
                         &instance.installation_id,
@@ -1933,7 +2528,6 @@ impl CircuitBreakerMCPServer {
                 .to_string();
 
                 match self
-                    .manager
                     .make_authenticated_api_request(
                         &OAuthProviderType::GitLab,
                         &instance.installation_id,
@@ -1995,7 +2589,6 @@ impl CircuitBreakerMCPServer {
                     project_id
                 );
                 match self
-                    .manager
                     .make_authenticated_api_request(
                         &OAuthProviderType::GitLab,
                         &instance.installation_id,
@@ -2069,7 +2662,6 @@ impl CircuitBreakerMCPServer {
                 );
 
                 match self
-                    .manager
                     .make_authenticated_api_request(
                         &OAuthProviderType::GitLab,
                         &instance.installation_id,
@@ -2131,7 +2723,6 @@ impl CircuitBreakerMCPServer {
                     project_id
                 );
                 match self
-                    .manager
                     .make_authenticated_api_request(
                         &OAuthProviderType::GitLab,
                         &instance.installation_id,
@@ -2191,7 +2782,6 @@ impl CircuitBreakerMCPServer {
                 );
 
                 match self
-                    .manager
                     .make_authenticated_api_request(
                         &OAuthProviderType::GitLab,
                         &instance.installation_id,
@@ -2246,7 +2836,7 @@ impl CircuitBreakerMCPServer {
     }
 
     /// Perform search in project context using OAuth-authenticated APIs
-    async fn perform_context_search(
+    pub(crate) async fn perform_context_search(
         &self,
         instance: &MCPServerInstance,
         context_id: &str,
@@ -2292,7 +2882,6 @@ impl CircuitBreakerMCPServer {
         );
 
         match self
-            .manager
             .make_authenticated_api_request(
                 &OAuthProviderType::GitLab,
                 installation_id,
@@ -2341,7 +2930,6 @@ impl CircuitBreakerMCPServer {
         );
 
         match self
-            .manager
             .make_authenticated_api_request(
                 &OAuthProviderType::GitHub,
                 installation_id,
@@ -2430,12 +3018,7 @@ async fn handle_mcp_get_request(
             if headers.get("authorization").is_none() {
                 if let Some(oauth_config) = manager.get_oauth_config(&instance_id).await {
                     // Convert provider type string to enum
-                    let provider_type = match oauth_config.provider_type.as_str() {
-                        "gitlab" => crate::api::oauth::OAuthProviderType::GitLab,
-                        "github" => crate::api::oauth::OAuthProviderType::GitHub,
-                        "google" => crate::api::oauth::OAuthProviderType::Google,
-                        custom => crate::api::oauth::OAuthProviderType::Custom(custom.to_string()),
-                    };
+                    let provider_type = OAuthProviderType::from_name(&oauth_config.provider_type);
 
                     // Register the OAuth provider if not already registered
                     let redirect_uri = format!(
@@ -2444,30 +3027,23 @@ async fn handle_mcp_get_request(
                         instance_id
                     );
 
+                    // Providers without fixed endpoints fall back to gitlab.com's
+                    let (default_auth_url, default_token_url) = provider_type
+                        .default_endpoints()
+                        .or_else(|| OAuthProviderType::GitLab.default_endpoints())
+                        .unwrap_or_default();
                     let oauth_provider = crate::api::oauth::OAuthProvider {
                         provider_type: provider_type.clone(),
                         client_id: oauth_config.client_id.clone(),
                         client_secret: oauth_config.client_secret.clone(),
-                        auth_url: oauth_config.auth_url.clone().unwrap_or_else(
-                            || match oauth_config.provider_type.as_str() {
-                                "gitlab" => "https://gitlab.com/oauth/authorize".to_string(),
-                                "github" => "https://github.com/login/oauth/authorize".to_string(),
-                                "google" => {
-                                    "https://accounts.google.com/o/oauth2/v2/auth".to_string()
-                                }
-                                _ => "https://gitlab.com/oauth/authorize".to_string(),
-                            },
-                        ),
-                        token_url: oauth_config.token_url.clone().unwrap_or_else(|| {
-                            match oauth_config.provider_type.as_str() {
-                                "gitlab" => "https://gitlab.com/oauth/token".to_string(),
-                                "github" => {
-                                    "https://github.com/login/oauth/access_token".to_string()
-                                }
-                                "google" => "https://oauth2.googleapis.com/token".to_string(),
-                                _ => "https://gitlab.com/oauth/token".to_string(),
-                            }
-                        }),
+                        auth_url: oauth_config
+                            .auth_url
+                            .clone()
+                            .unwrap_or_else(|| default_auth_url.to_string()),
+                        token_url: oauth_config
+                            .token_url
+                            .clone()
+                            .unwrap_or_else(|| default_token_url.to_string()),
                         scope: oauth_config.scope.clone(),
                         redirect_uri: redirect_uri.clone(),
                     };
@@ -2566,15 +3142,177 @@ async fn handle_mcp_request_internal(
     let instance = match manager.get_server_instance(&instance_id).await {
         Some(instance) => instance,
         None => {
-            return Ok(axum::Json(MCPResponse::error_from_request(
-                request.id,
-                error_codes::INVALID_REQUEST,
-                format!("Instance {} not found", instance_id),
-            )));
+            return Ok(axum::Json(
+                MCPResponse::error_from_request(
+                    request.id,
+                    error_codes::INVALID_REQUEST,
+                    format!("Instance {} not found", instance_id),
+                )
+                .with_error_code(crate::ErrorCode::NotFound),
+            ));
         }
     };
 
     // Attempt authentication (required for most operations except initialize)
+    let claims = match authenticate_mcp_request(&manager, &instance, &headers, &uri, &request).await
+    {
+        Ok(claims) => claims,
+        Err(response) => return Ok(axum::Json(response)),
+    };
+
+    // Extract Bearer token for SSE routing OR session ID
+    let auth_token = if let Some(auth_header) = headers.get("authorization") {
+        if let Ok(auth_str) = auth_header.to_str() {
+            if auth_str.starts_with("Bearer ") {
+                Some(auth_str[7..].to_string())
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    } else if let Some(session_header) = headers.get("x-mcp-session") {
+        // For session-based auth, check if session exists or create one
+        if let Ok(session_id) = session_header.to_str() {
+            info!("Using session-based authentication: {}", session_id);
+
+            // Check if session already exists
+            if manager.get_session(session_id).await.is_some() {
+                info!("Found existing session: {}", session_id);
+                manager.update_session_activity(session_id).await;
+                // Use the session's OAuth token if available
+                if let Some(instance) = manager.get_server_instance(&instance_id).await {
+                    if matches!(instance.app_type, MCPApplicationType::Remote(_)) {
+                        // For Remote OAuth instances, get a valid OAuth token for this session
+                        if let MCPApplicationType::Remote(ref oauth_config) = instance.app_type {
+                            let provider_type =
+                                OAuthProviderType::from_name(&oauth_config.provider_type);
+                            match manager
+                                .oauth_manager
+                                .get_token(&provider_type, &instance.installation_id, None)
+                                .await
+                            {
+                                Ok(token) => {
+                                    info!("Retrieved OAuth token for session: {}", session_id);
+                                    Some(token.access_token)
+                                }
+                                Err(e) => {
+                                    warn!(
+                                        "Failed to get OAuth token for session {}: {}",
+                                        session_id, e
+                                    );
+                                    None
+                                }
+                            }
+                        } else {
+                            // For non-Remote instances, use a placeholder token
+                            Some(format!("session-{}", session_id))
+                        }
+                    } else {
+                        // For non-Remote instances, use a placeholder token
+                        Some(format!("session-{}", session_id))
+                    }
+                } else {
+                    None
+                }
+            } else {
+                info!("Creating new session: {}", session_id);
+                // Create a new session for this session ID
+                if let Some(instance) = manager.get_server_instance(&instance_id).await {
+                    let session_id_created = manager
+                        .create_session(MCPSessionParams {
+                            session_id: Some(session_id.to_string()),
+                            server_instance_id: instance_id.clone(),
+                            installation_id: instance.installation_id.clone(),
+                            app_id: instance.app_id.clone(),
+                            user_id: None,
+                            client_info: MCPClientInfo {
+                                name: "windsurf".to_string(),
+                                version: "1.0.0".to_string(),
+                                user_agent: headers
+                                    .get("user-agent")
+                                    .and_then(|h| h.to_str().ok())
+                                    .map(|s| s.to_string()),
+                            },
+                            permissions: MCPSessionPermissions {
+                                tools: vec!["*".to_string()],
+                                prompts: vec!["*".to_string()],
+                                resources: vec!["*".to_string()],
+                                project_contexts: std::collections::HashMap::new(),
+                            },
+                            project_contexts: vec![],
+                        })
+                        .await;
+
+                    info!("Created session: {}", session_id_created);
+
+                    // For Remote OAuth instances, get a valid OAuth token
+                    if let MCPApplicationType::Remote(ref oauth_config) = instance.app_type {
+                        let provider_type =
+                            OAuthProviderType::from_name(&oauth_config.provider_type);
+                        match manager
+                            .oauth_manager
+                            .get_token(&provider_type, &instance.installation_id, None)
+                            .await
+                        {
+                            Ok(token) => {
+                                info!("Retrieved OAuth token for new session: {}", session_id);
+                                Some(token.access_token)
+                            }
+                            Err(e) => {
+                                warn!(
+                                    "Failed to get OAuth token for new session {}: {}",
+                                    session_id, e
+                                );
+                                None
+                            }
+                        }
+                    } else {
+                        // For non-Remote instances, use a placeholder token
+                        Some(format!("session-{}", session_id))
+                    }
+                } else {
+                    warn!("Instance {} not found for session creation", instance_id);
+                    None
+                }
+            }
+        } else {
+            None
+        }
+    } else {
+        None
+    };
+
+    let server = CircuitBreakerMCPServer::with_manager(manager.clone());
+    let response = server
+        .handle_request(&instance_id, request.clone(), claims)
+        .await;
+
+    // Try to route the response via SSE if there's an active SSE connection
+    if let Some(token) = auth_token {
+        if SSE_ROUTER.send_response(&token, &response).await {
+            info!("Routed MCP response via SSE for token: {}...", &token[..8]);
+            // Return a minimal acknowledgment response since the real response went via SSE
+            let ack_response = MCPResponse::success_from_request(
+                Some(response.id.clone()),
+                serde_json::json!({"status": "response_sent_via_sse"}),
+            );
+            return Ok(axum::Json(ack_response));
+        }
+    }
+
+    // If no SSE routing available, return the response via HTTP as usual
+    Ok(axum::Json(response))
+}
+
+/// Work out the token claims of an MCP request; initialize needs no authentication
+async fn authenticate_mcp_request(
+    manager: &MCPServerManager,
+    instance: &MCPServerInstance,
+    headers: &HeaderMap,
+    uri: &axum::http::Uri,
+    request: &MCPRequest,
+) -> Result<Option<MCPTokenClaims>, MCPResponse> {
     let claims = if request.method == "initialize" {
         // Initialize doesn't require authentication
         None
@@ -2594,11 +3332,12 @@ async fn handle_mcp_request_internal(
                 project_contexts: vec![],
             })
         } else {
-            return Ok(axum::Json(MCPResponse::error_from_request(
-                request.id,
+            return Err(MCPResponse::error_from_request(
+                request.id.clone(),
                 error_codes::INVALID_REQUEST,
                 "Invalid session header".to_string(),
-            )));
+            )
+            .with_error_code(crate::ErrorCode::AuthenticationFailed));
         }
     } else if matches!(instance.app_type, MCPApplicationType::Remote(_)) {
         // For Remote OAuth instances, check for OAuth Bearer token
@@ -2609,7 +3348,7 @@ async fn handle_mcp_request_internal(
                     // Create minimal claims for the OAuth user
                     info!(
                         "Accepting OAuth Bearer token for Remote instance: {}",
-                        instance_id
+                        instance.instance_id
                     );
                     Some(MCPTokenClaims {
                         installation_id: instance.installation_id.clone(),
@@ -2621,25 +3360,28 @@ async fn handle_mcp_request_internal(
                         project_contexts: vec![],
                     })
                 } else {
-                    return Ok(axum::Json(MCPResponse::error_from_request(
-                        request.id,
+                    return Err(MCPResponse::error_from_request(
+                        request.id.clone(),
                         error_codes::INVALID_REQUEST,
                         "OAuth Bearer token required for Remote instances".to_string(),
-                    )));
+                    )
+                    .with_error_code(crate::ErrorCode::AuthenticationFailed));
                 }
             } else {
-                return Ok(axum::Json(MCPResponse::error_from_request(
-                    request.id,
+                return Err(MCPResponse::error_from_request(
+                    request.id.clone(),
                     error_codes::INVALID_REQUEST,
                     "Invalid authorization header".to_string(),
-                )));
+                )
+                .with_error_code(crate::ErrorCode::AuthenticationFailed));
             }
         } else {
-            return Ok(axum::Json(MCPResponse::error_from_request(
-                request.id,
+            return Err(MCPResponse::error_from_request(
+                request.id.clone(),
                 error_codes::INVALID_REQUEST,
                 "Authorization required for Remote instances".to_string(),
-            )));
+            )
+            .with_error_code(crate::ErrorCode::AuthenticationFailed));
         }
     } else {
         // For non-Remote instances, use JWT authentication
@@ -2670,25 +3412,27 @@ async fn handle_mcp_request_internal(
                             "URL-based token validation failed for app {}: {}",
                             app_id, e
                         );
-                        return Ok(axum::Json(MCPResponse::error_from_request(
-                            request.id,
+                        return Err(MCPResponse::error_from_request(
+                            request.id.clone(),
                             error_codes::INVALID_REQUEST,
                             format!("Authentication failed: {}", e),
-                        )));
+                        )
+                        .with_error_code(crate::ErrorCode::AuthenticationFailed));
                     }
                 },
                 Err(e) => {
                     warn!("No token found for app {}: {}", app_id, e);
-                    return Ok(axum::Json(MCPResponse::error_from_request(
-                        request.id,
+                    return Err(MCPResponse::error_from_request(
+                        request.id.clone(),
                         error_codes::INVALID_REQUEST,
                         format!("Authentication failed: {}", e),
-                    )));
+                    )
+                    .with_error_code(crate::ErrorCode::AuthenticationFailed));
                 }
             }
         } else {
             // Try standard Bearer token authentication (for JWT tokens)
-            match manager.authenticate_request(&headers).await {
+            match manager.authenticate_request(headers).await {
                 Ok(claims) => Some(claims),
                 Err(e) => {
                     // If Bearer auth also fails, check URL authority as fallback
@@ -2703,195 +3447,257 @@ async fn handle_mcp_request_internal(
                                 {
                                     Ok(claims) => Some(claims),
                                     Err(token_err) => {
-                                        return Ok(axum::Json(MCPResponse::error_from_request(
-                                            request.id,
+                                        return Err(MCPResponse::error_from_request(
+                                            request.id.clone(),
                                             error_codes::INVALID_REQUEST,
                                             format!("Authentication failed: {}", token_err),
-                                        )));
+                                        )
+                                        .with_error_code(crate::ErrorCode::AuthenticationFailed));
                                     }
                                 },
                                 Err(_) => {
-                                    return Ok(axum::Json(MCPResponse::error_from_request(
-                                        request.id,
+                                    return Err(MCPResponse::error_from_request(
+                                        request.id.clone(),
                                         error_codes::INVALID_REQUEST,
                                         format!("Authentication failed: {}", e),
-                                    )));
+                                    )
+                                    .with_error_code(crate::ErrorCode::AuthenticationFailed));
                                 }
                             }
                         } else {
-                            return Ok(axum::Json(MCPResponse::error_from_request(
-                                request.id,
+                            return Err(MCPResponse::error_from_request(
+                                request.id.clone(),
                                 error_codes::INVALID_REQUEST,
                                 format!("Authentication failed: {}", e),
-                            )));
+                            )
+                            .with_error_code(crate::ErrorCode::AuthenticationFailed));
                         }
                     } else {
-                        return Ok(axum::Json(MCPResponse::error_from_request(
-                            request.id,
+                        return Err(MCPResponse::error_from_request(
+                            request.id.clone(),
                             error_codes::INVALID_REQUEST,
                             format!("Authentication failed: {}", e),
-                        )));
+                        )
+                        .with_error_code(crate::ErrorCode::AuthenticationFailed));
                     }
                 }
             }
         }
     };
+    Ok(claims)
+}
 
-    // Extract Bearer token for SSE routing OR session ID
-    let auth_token = if let Some(auth_header) = headers.get("authorization") {
-        if let Ok(auth_str) = auth_header.to_str() {
-            if auth_str.starts_with("Bearer ") {
-                Some(auth_str[7..].to_string())
-            } else {
-                None
-            }
-        } else {
-            None
-        }
-    } else if let Some(session_header) = headers.get("x-mcp-session") {
-        // For session-based auth, check if session exists or create one
-        if let Ok(session_id) = session_header.to_str() {
-            info!("Using session-based authentication: {}", session_id);
+fn streamable_session_id(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(SESSION_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+}
 
-            // Check if session already exists
-            if let Some(session) = manager.get_session(session_id).await {
-                info!("Found existing session: {}", session_id);
-                // Use the session's OAuth token if available
-                if let Some(instance) = manager.get_server_instance(&instance_id).await {
-                    if matches!(instance.app_type, MCPApplicationType::Remote(_)) {
-                        // For Remote OAuth instances, get a valid OAuth token for this session
-                        if let MCPApplicationType::Remote(ref oauth_config) = instance.app_type {
-                            let provider_type = match oauth_config.provider_type.as_str() {
-                                "gitlab" => crate::api::oauth::OAuthProviderType::GitLab,
-                                "github" => crate::api::oauth::OAuthProviderType::GitHub,
-                                "google" => crate::api::oauth::OAuthProviderType::Google,
-                                custom => {
-                                    crate::api::oauth::OAuthProviderType::Custom(custom.to_string())
-                                }
-                            };
-                            match manager
-                                .oauth_manager
-                                .get_token(&provider_type, &instance.installation_id, None)
-                                .await
-                            {
-                                Ok(token) => {
-                                    info!("Retrieved OAuth token for session: {}", session_id);
-                                    Some(token.access_token)
-                                }
-                                Err(e) => {
-                                    warn!(
-                                        "Failed to get OAuth token for session {}: {}",
-                                        session_id, e
-                                    );
-                                    None
-                                }
-                            }
-                        } else {
-                            // For non-Remote instances, use a placeholder token
-                            Some(format!("session-{}", session_id))
-                        }
-                    } else {
-                        // For non-Remote instances, use a placeholder token
-                        Some(format!("session-{}", session_id))
-                    }
-                } else {
-                    None
-                }
-            } else {
-                info!("Creating new session: {}", session_id);
-                // Create a new session for this session ID
-                if let Some(instance) = manager.get_server_instance(&instance_id).await {
-                    let session_id_created = manager
-                        .create_session(
-                            instance_id.clone(),
-                            instance.installation_id.clone(),
-                            instance.app_id.clone(),
-                            MCPClientInfo {
-                                name: "windsurf".to_string(),
-                                version: "1.0.0".to_string(),
-                                user_agent: headers
-                                    .get("user-agent")
-                                    .and_then(|h| h.to_str().ok())
-                                    .map(|s| s.to_string()),
-                            },
-                            None, // user_id
-                            MCPSessionPermissions {
-                                tools: vec!["*".to_string()],
-                                prompts: vec!["*".to_string()],
-                                resources: vec!["*".to_string()],
-                                project_contexts: std::collections::HashMap::new(),
-                            },
-                            vec![], // project_contexts
-                        )
-                        .await;
+fn streamable_status(status: StatusCode, message: &str) -> Response {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "text/plain")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(Body::from(message.to_string()))
+        .unwrap()
+        .into_response()
+}
 
-                    info!("Created session: {}", session_id_created);
+fn streamable_json(
+    status: StatusCode,
+    session_id: Option<&str>,
+    response: &MCPResponse,
+) -> Response {
+    let mut builder = Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .header("Access-Control-Expose-Headers", "Mcp-Session-Id");
+    if let Some(session_id) = session_id {
+        builder = builder.header("Mcp-Session-Id", session_id);
+    }
+    builder
+        .body(Body::from(serde_json::to_string(response).unwrap()))
+        .unwrap()
+        .into_response()
+}
 
-                    // For Remote OAuth instances, get a valid OAuth token
-                    if let MCPApplicationType::Remote(ref oauth_config) = instance.app_type {
-                        let provider_type = match oauth_config.provider_type.as_str() {
-                            "gitlab" => crate::api::oauth::OAuthProviderType::GitLab,
-                            "github" => crate::api::oauth::OAuthProviderType::GitHub,
-                            "google" => crate::api::oauth::OAuthProviderType::Google,
-                            custom => {
-                                crate::api::oauth::OAuthProviderType::Custom(custom.to_string())
-                            }
-                        };
-                        match manager
-                            .oauth_manager
-                            .get_token(&provider_type, &instance.installation_id, None)
-                            .await
-                        {
-                            Ok(token) => {
-                                info!("Retrieved OAuth token for new session: {}", session_id);
-                                Some(token.access_token)
-                            }
-                            Err(e) => {
-                                warn!(
-                                    "Failed to get OAuth token for new session {}: {}",
-                                    session_id, e
-                                );
-                                None
-                            }
-                        }
-                    } else {
-                        // For non-Remote instances, use a placeholder token
-                        Some(format!("session-{}", session_id))
-                    }
-                } else {
-                    warn!("Instance {} not found for session creation", instance_id);
-                    None
-                }
-            }
-        } else {
-            None
+/// Streamable HTTP transport: take one JSON-RPC message and answer requests as JSON.
+/// Initialize starts a session; every other message must carry its Mcp-Session-Id.
+async fn handle_streamable_post(
+    State(manager): State<MCPServerManager>,
+    Path(instance_id): Path<String>,
+    headers: HeaderMap,
+    uri: axum::http::Uri,
+    axum::Json(message): axum::Json<serde_json::Value>,
+) -> Response {
+    let Some(instance) = manager.get_server_instance(&instance_id).await else {
+        return streamable_status(StatusCode::NOT_FOUND, "Instance not found");
+    };
+
+    let is_initialize =
+        message.get("method").and_then(|method| method.as_str()) == Some("initialize");
+    let session_id = streamable_session_id(&headers);
+    if !is_initialize {
+        let Some(session_id) = &session_id else {
+            return streamable_status(StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id header");
+        };
+        if !manager
+            .streamable_sessions
+            .touch(session_id, &instance_id)
+            .await
+        {
+            return streamable_status(StatusCode::NOT_FOUND, "Unknown or terminated session");
+        }
+        if matches!(instance.app_type, MCPApplicationType::Remote(_))
+            && headers.get("authorization").is_none()
+        {
+            return Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .header("WWW-Authenticate", "Bearer realm=\"MCP OAuth\"")
+                .header("Access-Control-Allow-Origin", "*")
+                .body(Body::empty())
+                .unwrap()
+                .into_response();
+        }
+    }
+
+    // Responses and notifications from the client need no reply
+    if message.get("result").is_some() || message.get("error").is_some() {
+        return StatusCode::ACCEPTED.into_response();
+    }
+    let request: MCPRequest = match serde_json::from_value(message) {
+        Ok(request) => request,
+        Err(e) => {
+            let response = MCPResponse::error_from_request(
+                None,
+                error_codes::INVALID_REQUEST,
+                format!("Invalid JSON-RPC message: {}", e),
+            );
+            return streamable_json(StatusCode::BAD_REQUEST, session_id.as_deref(), &response);
+        }
+    };
+    if request.id.is_none() {
+        return StatusCode::ACCEPTED.into_response();
+    }
+
+    let claims = match authenticate_mcp_request(&manager, &instance, &headers, &uri, &request).await
+    {
+        Ok(claims) => claims,
+        Err(response) => {
+            return streamable_json(StatusCode::UNAUTHORIZED, session_id.as_deref(), &response)
+        }
+    };
+
+    let params = request.params.clone();
+    let logger = match &session_id {
+        Some(session_id) => manager.streamable_sessions.logger(session_id).await,
+        None => None,
+    };
+    let server = CircuitBreakerMCPServer::with_manager(manager.clone()).with_logger(logger);
+    let mut response = server.handle_request(&instance_id, request, claims).await;
+
+    let session_id = if is_initialize {
+        if response.error.is_some() {
+            return streamable_json(StatusCode::OK, None, &response);
         }
+        if let Some(result) = response.result.as_mut() {
+            result["protocolVersion"] = negotiate_protocol_version(params.as_ref()).into();
+        }
+        let sessions = &manager.streamable_sessions;
+        let reaped = sessions.reap_idle(SESSION_IDLE_TIMEOUT).await;
+        if reaped > 0 {
+            debug!("Reaped {} idle streamable HTTP sessions", reaped);
+        }
+        sessions.create(&instance_id).await
     } else {
-        None
+        session_id.unwrap_or_default()
     };
 
-    let server = CircuitBreakerMCPServer {
-        manager: manager.clone(),
+    streamable_json(StatusCode::OK, Some(&session_id), &response)
+}
+
+/// Streamable HTTP transport: open the server message stream of a session,
+/// replaying what followed Last-Event-ID when the client resumes
+async fn handle_streamable_get(
+    State(manager): State<MCPServerManager>,
+    Path(instance_id): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let accepts_sse = headers
+        .get("accept")
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/event-stream"));
+    if !accepts_sse {
+        return streamable_status(
+            StatusCode::NOT_ACCEPTABLE,
+            "Accept must include text/event-stream",
+        );
+    }
+    let Some(session_id) = streamable_session_id(&headers) else {
+        return streamable_status(StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id header");
     };
-    let response = server
-        .handle_request(&instance_id, request.clone(), claims)
-        .await;
+    let last_event_id = headers
+        .get(LAST_EVENT_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok());
+
+    let Some(rx) = manager
+        .streamable_sessions
+        .open_stream(&session_id, &instance_id, last_event_id)
+        .await
+    else {
+        return streamable_status(StatusCode::NOT_FOUND, "Unknown or terminated session");
+    };
+    info!(
+        "Opened streamable HTTP stream for session {} (resuming after {:?})",
+        session_id, last_event_id
+    );
 
-    // Try to route the response via SSE if there's an active SSE connection
-    if let Some(token) = auth_token {
-        if SSE_ROUTER.send_response(&token, &response).await {
-            info!("Routed MCP response via SSE for token: {}...", &token[..8]);
-            // Return a minimal acknowledgment response since the real response went via SSE
-            let ack_response = MCPResponse::success_from_request(
-                Some(response.id.clone()),
-                serde_json::json!({"status": "response_sent_via_sse"}),
-            );
-            return Ok(axum::Json(ack_response));
-        }
+    let stream = futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|item| (item, rx))
+    });
+    Sse::new(stream)
+        .keep_alive(KeepAlive::new().interval(tokio::time::Duration::from_secs(15)))
+        .into_response()
+}
+
+/// Streamable HTTP transport: terminate a session
+async fn handle_streamable_delete(
+    State(manager): State<MCPServerManager>,
+    Path(instance_id): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let Some(session_id) = streamable_session_id(&headers) else {
+        return streamable_status(StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id header");
+    };
+    if manager
+        .streamable_sessions
+        .remove(&session_id, &instance_id)
+        .await
+    {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        streamable_status(StatusCode::NOT_FOUND, "Unknown or terminated session")
     }
+}
 
-    // If no SSE routing available, return the response via HTTP as usual
-    Ok(axum::Json(response))
+/// CORS preflight for the streamable HTTP endpoint
+async fn handle_streamable_options() -> Response {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Access-Control-Allow-Origin", "*")
+        .header("Access-Control-Allow-Methods", "GET, POST, DELETE, OPTIONS")
+        .header(
+            "Access-Control-Allow-Headers",
+            "Content-Type, Authorization, Mcp-Session-Id, Last-Event-ID, MCP-Protocol-Version",
+        )
+        .header("Access-Control-Expose-Headers", "Mcp-Session-Id")
+        .header("Access-Control-Max-Age", "86400")
+        .body(Body::empty())
+        .unwrap()
+        .into_response()
 }
 
 /// Handle default MCP HTTP endpoint for mcp-remote compatibility
@@ -3035,19 +3841,12 @@ async fn handle_mcp_sse_internal(
     if let Some(token) = auth_token.clone() {
         SSE_ROUTER.register_channel(token.clone(), tx.clone()).await;
 
-        // Clean up on disconnect
-        let cleanup_token = token.clone();
+        // Clean up as soon as the client disconnects; the background reaper
+        // catches anything this misses
         let cleanup_tx = tx.clone();
         tokio::spawn(async move {
-            // Wait for the channel to be closed (client disconnected)
-            while cleanup_tx
-                .send(Ok(Event::default().event("ping").data("")))
-                .is_ok()
-            {
-                tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
-            }
-            // Clean up the channel from the router
-            SSE_ROUTER.unregister_channel(&cleanup_token).await;
+            cleanup_tx.closed().await;
+            SSE_ROUTER.unregister_channel(&token, &cleanup_tx).await;
         });
     }
 
@@ -3127,6 +3926,7 @@ async fn handle_oauth_authorization_server_metadata(
         "authorization_endpoint": "https://gitlab.com/oauth/authorize",
         "token_endpoint": "https://gitlab.com/oauth/token",
         "registration_endpoint": format!("{}/register", base_url),
+        "jwks_uri": format!("{}/.well-known/jwks.json", base_url),
         "response_types_supported": ["code"],
         "grant_types_supported": ["authorization_code"],
         "code_challenge_methods_supported": ["S256"],
@@ -3151,6 +3951,26 @@ async fn handle_oauth_authorization_server_metadata(
 }
 
 /// Handle MCP discovery endpoint (/.well-known/mcp) - optional endpoint
+/// JSON Web Key Set of the keys installation and session tokens are signed with
+async fn handle_jwks(State(manager): State<MCPServerManager>) -> Response {
+    match manager.jwt_service.signing_keys().jwks() {
+        Ok(jwks) => Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Type", "application/json")
+            // Short enough that validators pick up rotated keys well within their retention
+            .header("Cache-Control", "public, max-age=300")
+            .header("Access-Control-Allow-Origin", "*")
+            .header("Access-Control-Allow-Methods", "GET, OPTIONS")
+            .body(Body::from(serde_json::to_string(&jwks).unwrap()))
+            .unwrap()
+            .into_response(),
+        Err(e) => {
+            error!("Failed to load MCP signing keys: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
 async fn handle_mcp_discovery(
     State(_manager): State<MCPServerManager>,
     headers: HeaderMap,
@@ -3221,132 +4041,203 @@ async fn handle_oauth_metadata(State(_manager): State<MCPServerManager>) -> Resp
         .into_response()
 }
 
-/// Handle OAuth dynamic client registration
-async fn handle_oauth_register(
-    State(manager): State<MCPServerManager>,
-    headers: HeaderMap,
-    Json(request): Json<serde_json::Value>,
-) -> Result<Response, StatusCode> {
-    info!("=== OAuth Client Registration Called ===");
-    info!(
-        "Request body: {}",
-        serde_json::to_string_pretty(&request).unwrap_or_default()
-    );
-
-    // Check if gitlab-demo instance exists
-    let instance_exists = manager.get_server_instance("gitlab-demo").await.is_some();
-    info!("gitlab-demo instance exists: {}", instance_exists);
-
-    let base_url = get_base_url_from_headers(&headers);
+/// Response of a registration endpoint; it may carry secrets, so it is never cached
+fn client_registration_response(status: StatusCode, body: &serde_json::Value) -> Response {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .header("Cache-Control", "no-store")
+        .header("Pragma", "no-cache")
+        .header("Access-Control-Allow-Origin", "*")
+        .header(
+            "Access-Control-Allow-Methods",
+            "GET, POST, PUT, DELETE, OPTIONS",
+        )
+        .header(
+            "Access-Control-Allow-Headers",
+            "Content-Type, Authorization",
+        )
+        .body(Body::from(serde_json::to_string(body).unwrap()))
+        .unwrap()
+        .into_response()
+}
 
-    // Extract redirect_uris from the request
-    let redirect_uris = match request.get("redirect_uris") {
-        Some(uris) => {
-            if let Some(arr) = uris.as_array() {
-                let uris: Vec<String> = arr
-                    .iter()
-                    .filter_map(|v| v.as_str())
-                    .map(|s| s.to_string())
-                    .collect();
-                if uris.is_empty() {
-                    warn!("Empty redirect_uris array provided, using default");
-                    vec![format!("{}/oauth/callback", base_url)]
-                } else {
-                    uris
-                }
-            } else {
-                warn!("redirect_uris is not an array, using default");
-                vec![format!("{}/oauth/callback", base_url)]
-            }
-        }
-        None => {
-            info!("No redirect_uris provided, using default");
-            vec![format!("{}/oauth/callback", base_url)]
+fn client_registration_failure(failure: ClientRegistrationFailure) -> Response {
+    match failure {
+        ClientRegistrationFailure::Invalid(error) => {
+            warn!("Rejected client registration: {}", error.description);
+            client_registration_response(StatusCode::BAD_REQUEST, &error.to_json())
         }
-    };
-
-    info!("Client requested redirect URIs: {:?}", redirect_uris);
-
-    // Validate that redirect URIs are not empty
-    if redirect_uris.is_empty() {
-        error!("No valid redirect URIs provided");
-        return Err(StatusCode::BAD_REQUEST);
+        ClientRegistrationFailure::Unauthorized => Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .header("WWW-Authenticate", "Bearer error=\"invalid_token\"")
+            .header("Access-Control-Allow-Origin", "*")
+            .body(Body::empty())
+            .unwrap()
+            .into_response(),
+        ClientRegistrationFailure::Storage => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
     }
+}
 
-    // Try to get OAuth config
-    match manager.get_oauth_config("gitlab-demo").await {
-        Some(oauth_config) => {
-            info!("Found OAuth config for gitlab-demo");
-            info!("Client ID: {}", oauth_config.client_id);
-            info!("Provider type: {}", oauth_config.provider_type);
-            info!("Scopes: {:?}", oauth_config.scope);
-
-            let timestamp = chrono::Utc::now().timestamp();
-
-            // Return the OAuth app configuration for GitLab
-            let mut response = serde_json::json!({
-                "client_id": oauth_config.client_id,
-                "client_secret": oauth_config.client_secret,
-                "client_id_issued_at": timestamp,
-                "client_secret_expires_at": 0,
-                "application_type": "web",
-                "client_name": "mcp-remote",
-                "client_uri": request.get("client_uri").unwrap_or(&serde_json::Value::String("https://github.com/modelcontextprotocol/inspector".to_string())),
-                "subject_type": "public",
-                "token_endpoint_auth_method": "client_secret_basic",
-                "grant_types": ["authorization_code"],
-                "response_types": ["code"],
-                "scope": oauth_config.scope.join(" "),
-                "contacts": [],
-                // Use server-based redirect instead of client's dynamic port
-                "redirect_uris": [format!("{}/oauth/callback/debug", base_url)]
-            });
+/// Client metadata of a registration request, rejected like invalid metadata when malformed
+fn parse_client_metadata(
+    request: serde_json::Value,
+) -> Result<ClientMetadata, ClientRegistrationFailure> {
+    serde_json::from_value(request).map_err(|e| {
+        ClientRegistrationFailure::Invalid(RegistrationError::metadata(format!(
+            "Malformed client metadata: {}",
+            e
+        )))
+    })
+}
 
-            // Echo back any additional parameters from the client request
-            if let Some(obj) = request.as_object() {
-                for (key, value) in obj {
-                    if !response.as_object().unwrap().contains_key(key) {
-                        response[key] = value.clone();
-                        info!("Echoing back client parameter: {} = {:?}", key, value);
-                    }
-                }
-            }
+/// The registration access token a management request presents
+async fn authorize_client_registration(
+    manager: &MCPServerManager,
+    headers: &HeaderMap,
+    client_id: &str,
+) -> Result<MCPClientRegistration, ClientRegistrationFailure> {
+    let token = headers
+        .get("authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|s| s.strip_prefix("Bearer "))
+        .ok_or(ClientRegistrationFailure::Unauthorized)?;
+    manager
+        .authorized_client_registration(client_id, token)
+        .await
+}
 
-            info!("=== OAuth Registration Response ===");
-            info!(
-                "{}",
-                serde_json::to_string_pretty(&response).unwrap_or_default()
+/// Handle OAuth dynamic client registration (RFC 7591) - POST /register
+async fn handle_oauth_register(
+    State(manager): State<MCPServerManager>,
+    headers: HeaderMap,
+    Json(request): Json<serde_json::Value>,
+) -> Response {
+    let issued = match parse_client_metadata(request) {
+        Ok(metadata) => manager.register_client(metadata).await,
+        Err(failure) => Err(failure),
+    };
+    match issued {
+        Ok(issued) => {
+            let registration_client_uri = format!(
+                "{}/register/{}",
+                get_base_url_from_headers(&headers),
+                issued.registration.client_id
             );
-
-            Ok(Response::builder()
-                .status(StatusCode::CREATED)
-                .header("Content-Type", "application/json")
-                .header("Access-Control-Allow-Origin", "*")
-                .header("Access-Control-Allow-Methods", "GET, POST, OPTIONS")
-                .header(
-                    "Access-Control-Allow-Headers",
-                    "Content-Type, Authorization",
-                )
-                .body(String::from(serde_json::to_string(&response).unwrap()))
-                .unwrap()
-                .into_response())
+            client_registration_response(
+                StatusCode::CREATED,
+                &issued.registration.to_response(
+                    &registration_client_uri,
+                    issued.client_secret.as_deref(),
+                    Some(&issued.registration_access_token),
+                ),
+            )
         }
-        None => {
-            error!("=== OAuth Registration Failed ===");
-            error!("No OAuth config found for gitlab-demo instance");
+        Err(failure) => client_registration_failure(failure),
+    }
+}
 
-            // List all available instances for debugging
-            let instances = manager.list_server_instances().await;
-            info!(
-                "Available instances: {:?}",
-                instances.iter().map(|i| &i.instance_id).collect::<Vec<_>>()
-            );
+/// Read a client registration (RFC 7592) - GET /register/{client_id}
+async fn handle_get_client_registration(
+    State(manager): State<MCPServerManager>,
+    Path(client_id): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    match authorize_client_registration(&manager, &headers, &client_id).await {
+        Ok(registration) => client_registration_response(
+            StatusCode::OK,
+            &registration.to_response(
+                &format!(
+                    "{}/register/{}",
+                    get_base_url_from_headers(&headers),
+                    client_id
+                ),
+                None,
+                None,
+            ),
+        ),
+        Err(failure) => client_registration_failure(failure),
+    }
+}
 
-            Err(StatusCode::NOT_FOUND)
+/// Replace a client's metadata (RFC 7592) - PUT /register/{client_id}
+async fn handle_update_client_registration(
+    State(manager): State<MCPServerManager>,
+    Path(client_id): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<serde_json::Value>,
+) -> Response {
+    let registration = match authorize_client_registration(&manager, &headers, &client_id).await {
+        Ok(registration) => registration,
+        Err(failure) => return client_registration_failure(failure),
+    };
+    if request.get("client_id").and_then(|id| id.as_str()) != Some(client_id.as_str()) {
+        return client_registration_failure(ClientRegistrationFailure::Invalid(
+            RegistrationError::metadata("client_id must match the registration being updated"),
+        ));
+    }
+    let updated = match parse_client_metadata(request) {
+        Ok(metadata) => {
+            manager
+                .update_client_registration(registration, metadata)
+                .await
         }
+        Err(failure) => Err(failure),
+    };
+    match updated {
+        Ok((registration, client_secret)) => client_registration_response(
+            StatusCode::OK,
+            &registration.to_response(
+                &format!(
+                    "{}/register/{}",
+                    get_base_url_from_headers(&headers),
+                    client_id
+                ),
+                client_secret.as_deref(),
+                None,
+            ),
+        ),
+        Err(failure) => client_registration_failure(failure),
+    }
+}
+
+/// Remove a client registration (RFC 7592) - DELETE /register/{client_id}
+async fn handle_delete_client_registration(
+    State(manager): State<MCPServerManager>,
+    Path(client_id): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let deleted = match authorize_client_registration(&manager, &headers, &client_id).await {
+        Ok(_) => manager.delete_client_registration(&client_id).await,
+        Err(failure) => Err(failure),
+    };
+    match deleted {
+        Ok(()) => Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .header("Access-Control-Allow-Origin", "*")
+            .body(Body::empty())
+            .unwrap()
+            .into_response(),
+        Err(failure) => client_registration_failure(failure),
     }
 }
 
+/// Handle CORS preflight of the client registration management endpoint
+async fn handle_client_registration_options() -> Response {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Access-Control-Allow-Origin", "*")
+        .header("Access-Control-Allow-Methods", "GET, PUT, DELETE, OPTIONS")
+        .header(
+            "Access-Control-Allow-Headers",
+            "Content-Type, Authorization",
+        )
+        .header("Access-Control-Max-Age", "86400")
+        .body(Body::empty())
+        .unwrap()
+        .into_response()
+}
+
 /// Handle OPTIONS requests for CORS preflight
 async fn handle_options() -> Response {
     Response::builder()
@@ -3363,6 +4254,19 @@ async fn handle_options() -> Response {
         .into_response()
 }
 
+/// Handle debug endpoint reporting open SSE channels and streamable HTTP sessions
+async fn handle_debug_streams(State(manager): State<MCPServerManager>) -> Json<serde_json::Value> {
+    let streamable = &manager.streamable_sessions;
+    Json(serde_json::json!({
+        "sse_channels": SSE_ROUTER.gauges().await,
+        "streamable_sessions": {
+            "sessions": streamable.session_count().await,
+            "open_streams": streamable.open_stream_count().await
+        },
+        "timestamp": chrono::Utc::now().to_rfc3339()
+    }))
+}
+
 /// Handle debug endpoint to list all instances
 async fn handle_debug_instances(
     State(manager): State<MCPServerManager>,
@@ -3432,10 +4336,22 @@ async fn create_mcp_instance(
             request.description,
             request.project_contexts,
             request.app_type,
+            request.tenant_id,
         )
         .await
     {
-        Ok(instance_id) => Ok(axum::Json(CreateMCPInstanceResponse { instance_id })),
+        Ok(instance_id) => {
+            if request.tool_policy != MCPToolPolicy::default() {
+                manager
+                    .set_tool_policy(&instance_id, request.tool_policy)
+                    .await
+                    .map_err(|e| {
+                        error!("Failed to set tool policy of MCP instance: {}", e);
+                        StatusCode::INTERNAL_SERVER_ERROR
+                    })?;
+            }
+            Ok(axum::Json(CreateMCPInstanceResponse { instance_id }))
+        }
         Err(e) => {
             error!("Failed to create MCP instance: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
@@ -3459,22 +4375,48 @@ async fn validate_instance_oauth_token(
 async fn handle_instance_oauth_callback(
     State(manager): State<MCPServerManager>,
     Path(instance_id): Path<String>,
+    headers: HeaderMap,
     axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
 ) -> Result<Response, StatusCode> {
-    handle_instance_oauth_callback_internal(manager, instance_id, params).await
+    handle_instance_oauth_callback_internal(manager, instance_id, client_ip(&headers), params).await
+}
+
+/// Refuse an OAuth callback from a locked out IP or for a locked out instance
+fn check_oauth_callback_throttle(
+    ip: Option<&str>,
+    instance_id: Option<&str>,
+) -> Result<(), StatusCode> {
+    AuthThrottle::global()
+        .check(ip, instance_id)
+        .map_err(|lockout| {
+            warn!("Refused OAuth callback: {}", lockout.message());
+            StatusCode::TOO_MANY_REQUESTS
+        })
+}
+
+/// Count an OAuth callback that failed to authenticate
+fn record_oauth_callback_failure(ip: Option<&str>, instance_id: Option<&str>, detail: &str) {
+    AuthThrottle::global().record_failure(AuthSurface::OAuthCallback, ip, instance_id, detail);
 }
 
 /// Internal OAuth callback handler
 async fn handle_instance_oauth_callback_internal(
     manager: MCPServerManager,
     instance_id: String,
+    ip: Option<String>,
     params: std::collections::HashMap<String, String>,
 ) -> Result<Response, StatusCode> {
+    check_oauth_callback_throttle(ip.as_deref(), Some(&instance_id))?;
     let code = params.get("code").ok_or(StatusCode::BAD_REQUEST)?;
     let state = params.get("state").ok_or(StatusCode::BAD_REQUEST)?;
 
     // Verify state contains our instance_id
     if !state.starts_with(&format!("{}:", instance_id)) {
+        record_oauth_callback_failure(
+            ip.as_deref(),
+            Some(&instance_id),
+            "OAuth callback state does not match the instance",
+        );
         return Err(StatusCode::BAD_REQUEST);
     }
 
@@ -3579,6 +4521,198 @@ async fn get_mcp_instance(
     }
 }
 
+/// Authenticate a prompt template management request for an existing instance
+async fn authorize_instance_management(
+    manager: &MCPServerManager,
+    instance_id: &str,
+    headers: &HeaderMap,
+) -> Result<(), ErrorResponse> {
+    manager.authenticate_request(headers).await.map_err(|e| {
+        create_error_response(e, "authentication_error".to_string(), None, None)
+            .with_error_code(crate::ErrorCode::AuthenticationFailed)
+    })?;
+    match manager.get_server_instance(instance_id).await {
+        Some(_) => Ok(()),
+        None => Err(create_error_response(
+            format!("Server instance '{}' not found", instance_id),
+            "invalid_request_error".to_string(),
+            None,
+            None,
+        )
+        .with_error_code(crate::ErrorCode::NotFound)),
+    }
+}
+
+fn prompt_template_error_response(error: PromptTemplateError) -> ErrorResponse {
+    let error_type = match error {
+        PromptTemplateError::Storage(_) => "internal_error",
+        _ => "invalid_request_error",
+    };
+    create_error_response(error.to_string(), error_type.to_string(), None, None)
+        .with_error_code(error.code())
+}
+
+/// List the prompt templates of a specific instance
+async fn list_prompt_templates(
+    State(manager): State<MCPServerManager>,
+    Path(instance_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<axum::Json<Vec<MCPPromptTemplate>>, ErrorResponse> {
+    authorize_instance_management(&manager, &instance_id, &headers).await?;
+    manager
+        .get_prompt_templates(&instance_id)
+        .await
+        .map(axum::Json)
+        .map_err(prompt_template_error_response)
+}
+
+/// Create or replace a prompt template of a specific instance
+async fn put_prompt_template(
+    State(manager): State<MCPServerManager>,
+    Path((instance_id, name)): Path<(String, String)>,
+    headers: HeaderMap,
+    axum::Json(request): axum::Json<PromptTemplateRequest>,
+) -> Result<axum::Json<MCPPromptTemplate>, ErrorResponse> {
+    authorize_instance_management(&manager, &instance_id, &headers).await?;
+    let template = MCPPromptTemplate {
+        name,
+        description: request.description,
+        arguments: request.arguments,
+        messages: request.messages,
+    };
+    manager
+        .set_prompt_template(&instance_id, template.clone())
+        .await
+        .map_err(prompt_template_error_response)?;
+    Ok(axum::Json(template))
+}
+
+/// Remove a stored prompt template of a specific instance
+async fn delete_prompt_template(
+    State(manager): State<MCPServerManager>,
+    Path((instance_id, name)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<StatusCode, ErrorResponse> {
+    authorize_instance_management(&manager, &instance_id, &headers).await?;
+    match manager.delete_prompt_template(&instance_id, &name).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(prompt_template_error_response(
+            PromptTemplateError::NotFound(name),
+        )),
+        Err(e) => Err(prompt_template_error_response(e)),
+    }
+}
+
+/// Get the tool policy of a specific instance
+async fn get_tool_policy(
+    State(manager): State<MCPServerManager>,
+    Path(instance_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<axum::Json<MCPToolPolicy>, ErrorResponse> {
+    authorize_instance_management(&manager, &instance_id, &headers).await?;
+    let instance = manager.get_server_instance(&instance_id).await;
+    Ok(axum::Json(
+        instance.map(|i| i.tool_policy).unwrap_or_default(),
+    ))
+}
+
+/// Replace the tool policy of a specific instance
+async fn put_tool_policy(
+    State(manager): State<MCPServerManager>,
+    Path(instance_id): Path<String>,
+    headers: HeaderMap,
+    axum::Json(tool_policy): axum::Json<MCPToolPolicy>,
+) -> Result<axum::Json<MCPToolPolicy>, ErrorResponse> {
+    authorize_instance_management(&manager, &instance_id, &headers).await?;
+    manager
+        .set_tool_policy(&instance_id, tool_policy.clone())
+        .await
+        .map_err(|e| {
+            create_error_response(e, "internal_error".to_string(), None, None)
+                .with_error_code(crate::ErrorCode::StorageError)
+        })?;
+    Ok(axum::Json(tool_policy))
+}
+
+fn upstream_error_response(error: MCPClientError) -> ErrorResponse {
+    let error_type = match error {
+        MCPClientError::InvalidConfig(_) => "invalid_request_error",
+        _ => "upstream_error",
+    };
+    create_error_response(error.to_string(), error_type.to_string(), None, None)
+        .with_error_code(error.code())
+}
+
+/// List the upstream MCP servers of a specific instance
+async fn list_upstreams(
+    State(manager): State<MCPServerManager>,
+    Path(instance_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<axum::Json<Vec<UpstreamResponse>>, ErrorResponse> {
+    authorize_instance_management(&manager, &instance_id, &headers).await?;
+    let upstreams = manager
+        .get_upstreams(&instance_id)
+        .await
+        .into_iter()
+        .map(UpstreamResponse::from)
+        .collect();
+    Ok(axum::Json(upstreams))
+}
+
+/// Connect an upstream MCP server and proxy its tools through a specific instance
+async fn connect_upstream(
+    State(manager): State<MCPServerManager>,
+    Path(instance_id): Path<String>,
+    headers: HeaderMap,
+    axum::Json(config): axum::Json<MCPUpstreamConfig>,
+) -> Result<(StatusCode, axum::Json<UpstreamResponse>), ErrorResponse> {
+    authorize_instance_management(&manager, &instance_id, &headers).await?;
+    if matches!(config.transport, MCPUpstreamTransport::Stdio { .. })
+        && !manager.allow_stdio_upstreams
+    {
+        return Err(create_error_response(
+            "Upstreams that spawn local processes are disabled on this server".to_string(),
+            "permission_error".to_string(),
+            None,
+            None,
+        )
+        .with_error_code(crate::ErrorCode::PermissionDenied));
+    }
+    let name = config.name.clone();
+    manager
+        .connect_upstream(&instance_id, config)
+        .await
+        .map_err(upstream_error_response)?;
+    let upstream = manager
+        .get_upstreams(&instance_id)
+        .await
+        .into_iter()
+        .find(|upstream| upstream.client.config().name == name)
+        .map(UpstreamResponse::from)
+        .ok_or_else(|| upstream_error_response(MCPClientError::Transport(name)))?;
+    Ok((StatusCode::CREATED, axum::Json(upstream)))
+}
+
+/// Disconnect an upstream MCP server from a specific instance
+async fn disconnect_upstream(
+    State(manager): State<MCPServerManager>,
+    Path((instance_id, name)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<StatusCode, ErrorResponse> {
+    authorize_instance_management(&manager, &instance_id, &headers).await?;
+    if manager.disconnect_upstream(&instance_id, &name).await {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(create_error_response(
+            format!("Upstream '{}' not found", name),
+            "invalid_request_error".to_string(),
+            None,
+            None,
+        )
+        .with_error_code(crate::ErrorCode::NotFound))
+    }
+}
+
 /// Request/Response types for instance management
 #[derive(Debug, serde::Deserialize)]
 pub struct CreateMCPInstanceRequest {
@@ -3588,11 +4722,51 @@ pub struct CreateMCPInstanceRequest {
     pub description: String,
     pub project_contexts: Vec<String>,
     pub app_type: MCPApplicationType,
+    /// Tenant whose routing policy and budget apply to the instance's sampling requests
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+    /// Which tools the instance exposes; every tool when left out
+    #[serde(default)]
+    pub tool_policy: MCPToolPolicy,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct CreateMCPInstanceResponse {
+    pub instance_id: String,
+}
+
+/// An upstream MCP server of an instance; stdio environments and HTTP headers are
+/// left out as they may carry credentials
+#[derive(Debug, serde::Serialize)]
+pub struct UpstreamResponse {
+    pub name: String,
+    pub transport: &'static str,
+    pub server_info: serde_json::Value,
+    pub tools: Vec<String>,
+}
+
+impl From<MCPUpstream> for UpstreamResponse {
+    fn from(upstream: MCPUpstream) -> Self {
+        let config = upstream.client.config();
+        Self {
+            name: config.name.clone(),
+            transport: match config.transport {
+                MCPUpstreamTransport::Stdio { .. } => "stdio",
+                MCPUpstreamTransport::Http { .. } => "http",
+            },
+            server_info: upstream.client.server_info().clone(),
+            tools: upstream.tools,
+        }
+    }
 }
 
-#[derive(Debug, serde::Serialize)]
-pub struct CreateMCPInstanceResponse {
-    pub instance_id: String,
+/// Body of a prompt template create or replace request; the name comes from the path
+#[derive(Debug, serde::Deserialize)]
+pub struct PromptTemplateRequest {
+    pub description: String,
+    #[serde(default)]
+    pub arguments: Vec<MCPPromptArgument>,
+    pub messages: Vec<MCPPromptTemplateMessage>,
 }
 
 /// Handle MCP WebSocket connection for a specific instance
@@ -3641,7 +4815,8 @@ async fn handle_websocket_connection(
             Some(MCPId::String("init".to_string())),
             error_codes::INVALID_REQUEST,
             format!("Server instance '{}' not found", instance_id),
-        );
+        )
+        .with_error_code(crate::ErrorCode::NotFound);
         if let Ok(error_json) = serde_json::to_string(&error_response) {
             let _ = socket
                 .send(axum::extract::ws::Message::Text(error_json))
@@ -3650,7 +4825,25 @@ async fn handle_websocket_connection(
         return;
     }
 
-    while let Some(msg) = socket.recv().await {
+    // Log notifications of this connection's requests go out between responses
+    let (logger, mut notifications) = MCPLogger::channel();
+    loop {
+        let msg = tokio::select! {
+            Some(notification) = notifications.recv() => {
+                if socket
+                    .send(axum::extract::ws::Message::Text(notification.to_string()))
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+                continue;
+            }
+            msg = socket.recv() => match msg {
+                Some(msg) => msg,
+                None => break,
+            },
+        };
         match msg {
             Ok(axum::extract::ws::Message::Text(text)) => {
                 debug!(
@@ -3662,9 +4855,8 @@ async fn handle_websocket_connection(
                 match serde_json::from_str::<MCPRequest>(&text) {
                     Ok(request) => {
                         // Handle the request in the context of this instance
-                        let server = CircuitBreakerMCPServer {
-                            manager: manager.clone(),
-                        };
+                        let server = CircuitBreakerMCPServer::with_manager(manager.clone())
+                            .with_logger(Some(logger.clone()));
                         let response = server
                             .handle_request(&instance_id, request, claims.clone())
                             .await;
@@ -3748,7 +4940,7 @@ async fn list_tools(
 
     match manager.get_server_instance(&instance_id).await {
         Some(_instance) => {
-            let tools = manager.get_default_tools(&instance_id).await;
+            let tools = manager.get_tools(&instance_id).await;
             Ok(axum::Json(tools))
         }
         None => Err(StatusCode::NOT_FOUND),
@@ -3766,7 +4958,7 @@ async fn list_prompts(
 
     match manager.get_server_instance(&instance_id).await {
         Some(_instance) => {
-            let prompts = manager.get_default_prompts(&instance_id).await;
+            let prompts = manager.get_prompts(&instance_id).await;
             Ok(axum::Json(prompts))
         }
         None => Err(StatusCode::NOT_FOUND),
@@ -3998,8 +5190,11 @@ async fn get_oauth_authorization_url(
 /// Handle OAuth callback
 async fn handle_oauth_callback(
     State(manager): State<MCPServerManager>,
+    headers: HeaderMap,
     axum::Json(callback): axum::Json<super::oauth::OAuthCallback>,
 ) -> Result<axum::Json<serde_json::Value>, StatusCode> {
+    let ip = client_ip(&headers);
+    check_oauth_callback_throttle(ip.as_deref(), None)?;
     match manager.handle_oauth_callback(callback).await {
         Ok(token) => Ok(axum::Json(serde_json::json!({
             "success": true,
@@ -4009,6 +5204,11 @@ async fn handle_oauth_callback(
         }))),
         Err(e) => {
             error!("Failed to handle OAuth callback: {}", e);
+            record_oauth_callback_failure(
+                ip.as_deref(),
+                None,
+                &format!("OAuth callback failed: {}", e),
+            );
             Err(StatusCode::BAD_REQUEST)
         }
     }
@@ -4055,6 +5255,7 @@ fn get_base_url_from_headers(headers: &HeaderMap) -> String {
 /// Handle general OAuth callback - extract instance from state and redirect
 async fn handle_general_oauth_callback(
     State(manager): State<MCPServerManager>,
+    headers: HeaderMap,
     axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
 ) -> Result<Response, StatusCode> {
     let state = params.get("state").ok_or(StatusCode::BAD_REQUEST)?;
@@ -4063,7 +5264,13 @@ async fn handle_general_oauth_callback(
     let instance_id = state.split(':').next().ok_or(StatusCode::BAD_REQUEST)?;
 
     // Forward to instance-specific handler
-    handle_instance_oauth_callback_internal(manager, instance_id.to_string(), params).await
+    handle_instance_oauth_callback_internal(
+        manager,
+        instance_id.to_string(),
+        client_ip(&headers),
+        params,
+    )
+    .await
 }
 
 /// Handle OAuth callback for MCP clients (like mcp-remote) - extract instance from state and redirect
@@ -4138,12 +5345,7 @@ async fn handle_mcp_oauth_init(
 
     // Check if this is a Remote OAuth instance
     if let MCPApplicationType::Remote(ref oauth_config) = instance.app_type {
-        let provider_type = match oauth_config.provider_type.as_str() {
-            "gitlab" => crate::api::oauth::OAuthProviderType::GitLab,
-            "github" => crate::api::oauth::OAuthProviderType::GitHub,
-            "google" => crate::api::oauth::OAuthProviderType::Google,
-            custom => crate::api::oauth::OAuthProviderType::Custom(custom.to_string()),
-        };
+        let provider_type = OAuthProviderType::from_name(&oauth_config.provider_type);
 
         // Get session ID from query params (for MCP clients to track their session)
         let session_id = params.get("session_id").unwrap_or(&instance_id).clone();
@@ -4183,9 +5385,12 @@ async fn handle_mcp_oauth_init(
 async fn handle_mcp_oauth_callback(
     State(manager): State<MCPServerManager>,
     Path(instance_id): Path<String>,
+    headers: HeaderMap,
     Query(params): Query<std::collections::HashMap<String, String>>,
 ) -> Result<axum::response::Html<String>, StatusCode> {
     info!("OAuth callback received for instance: {}", instance_id);
+    let ip = client_ip(&headers);
+    check_oauth_callback_throttle(ip.as_deref(), Some(&instance_id))?;
 
     // Get the instance
     let instance = match manager.get_server_instance(&instance_id).await {
@@ -4198,12 +5403,7 @@ async fn handle_mcp_oauth_callback(
 
     // Check if this is a Remote OAuth instance
     if let MCPApplicationType::Remote(ref oauth_config) = instance.app_type {
-        let provider_type = match oauth_config.provider_type.as_str() {
-            "gitlab" => crate::api::oauth::OAuthProviderType::GitLab,
-            "github" => crate::api::oauth::OAuthProviderType::GitHub,
-            "google" => crate::api::oauth::OAuthProviderType::Google,
-            custom => crate::api::oauth::OAuthProviderType::Custom(custom.to_string()),
-        };
+        let provider_type = OAuthProviderType::from_name(&oauth_config.provider_type);
 
         // Get authorization code from callback
         if let Some(code) = params.get("code") {
@@ -4234,21 +5434,27 @@ async fn handle_mcp_oauth_callback(
                     };
 
                     let created_session_id = manager
-                        .create_session(
-                            instance_id.clone(),
-                            instance.installation_id.clone(),
-                            instance.app_id.clone(),
+                        .create_session(MCPSessionParams {
+                            session_id: None,
+                            server_instance_id: instance_id.clone(),
+                            installation_id: instance.installation_id.clone(),
+                            app_id: instance.app_id.clone(),
+                            user_id: Some(format!("oauth-user-{}", session_id)),
                             client_info,
-                            Some(format!("oauth-user-{}", session_id)),
-                            MCPSessionPermissions::default(),
-                            vec![],
-                        )
+                            permissions: MCPSessionPermissions::default(),
+                            project_contexts: vec![],
+                        })
                         .await;
 
                     info!("Created session: {} for OAuth user", created_session_id);
                 }
                 Err(e) => {
                     error!("Failed to handle OAuth callback: {}", e);
+                    record_oauth_callback_failure(
+                        ip.as_deref(),
+                        Some(&instance_id),
+                        &format!("OAuth callback failed: {}", e),
+                    );
                     return Err(StatusCode::INTERNAL_SERVER_ERROR);
                 }
             }
@@ -4380,6 +5586,7 @@ mod tests {
                 "Test server for unit tests".to_string(),
                 vec!["test-context".to_string()],
                 crate::api::mcp_types::MCPApplicationType::Local,
+                None,
             )
             .await
             .unwrap();
@@ -4424,6 +5631,532 @@ mod tests {
         assert!(response.error.is_none());
     }
 
+    struct EchoTool;
+
+    #[async_trait::async_trait]
+    impl MCPToolHandler for EchoTool {
+        fn definition(&self) -> MCPTool {
+            MCPTool {
+                name: "echo".to_string(),
+                description: "Echo a message".to_string(),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {"message": {"type": "string"}},
+                    "required": ["message"]
+                }),
+            }
+        }
+
+        async fn call(
+            &self,
+            context: &MCPToolContext<'_>,
+            arguments: &HashMap<String, serde_json::Value>,
+        ) -> mcp_tools::MCPToolCallResult {
+            Ok(MCPToolResult {
+                content: vec![MCPContent::text(format!(
+                    "{}: {}",
+                    context.instance.instance_id, arguments["message"]
+                ))],
+                is_error: Some(false),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_call_registered_tool() {
+        let (server, instance_id) = create_test_server_with_instance().await;
+        server
+            .manager
+            .register_tool(&instance_id, Arc::new(EchoTool))
+            .await;
+
+        let tools = server.manager.get_tools(&instance_id).await;
+        assert!(tools.iter().any(|tool| tool.name == "echo"));
+        assert!(tools.iter().any(|tool| tool.name == "create_workflow"));
+
+        let call = |arguments: serde_json::Value| MCPRequest {
+            id: Some(MCPId::String("test-tool".to_string())),
+            method: "tools/call".to_string(),
+            params: Some(serde_json::json!({"name": "echo", "arguments": arguments})),
+        };
+
+        let response = server
+            .handle_request(
+                &instance_id,
+                call(serde_json::json!({"message": "hi"})),
+                None,
+            )
+            .await;
+        let result = response.result.unwrap();
+        assert_eq!(
+            result["content"][0]["text"],
+            format!("{}: \"hi\"", instance_id)
+        );
+
+        let response = server
+            .handle_request(&instance_id, call(serde_json::json!({"message": 1})), None)
+            .await;
+        assert_eq!(response.error.unwrap().code, error_codes::INVALID_PARAMS);
+
+        let response = server
+            .handle_request(&instance_id, call(serde_json::json!({})), None)
+            .await;
+        assert_eq!(response.error.unwrap().code, error_codes::INVALID_PARAMS);
+    }
+
+    /// Counts its calls; writes unless `read_only`
+    struct CountingTool {
+        name: &'static str,
+        read_only: bool,
+        calls: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl MCPToolHandler for CountingTool {
+        fn definition(&self) -> MCPTool {
+            MCPTool {
+                name: self.name.to_string(),
+                description: "Count calls".to_string(),
+                input_schema: serde_json::json!({"type": "object"}),
+            }
+        }
+
+        fn read_only(&self) -> bool {
+            self.read_only
+        }
+
+        async fn call(
+            &self,
+            _context: &MCPToolContext<'_>,
+            _arguments: &HashMap<String, serde_json::Value>,
+        ) -> mcp_tools::MCPToolCallResult {
+            let calls = self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(MCPToolResult {
+                content: vec![MCPContent::text(calls.to_string())],
+                is_error: Some(false),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_only_tool_results_are_cached_until_a_write() {
+        let (server, instance_id) = create_test_server_with_instance().await;
+        let reads = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let writes = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        for (name, read_only, calls) in [("read", true, &reads), ("write", false, &writes)] {
+            server
+                .manager
+                .register_tool(
+                    &instance_id,
+                    Arc::new(CountingTool {
+                        name,
+                        read_only,
+                        calls: calls.clone(),
+                    }),
+                )
+                .await;
+        }
+        let call = |name: &str| MCPRequest {
+            id: Some(MCPId::Number(1)),
+            method: "tools/call".to_string(),
+            params: Some(serde_json::json!({"name": name, "arguments": {}})),
+        };
+
+        for _ in 0..2 {
+            server
+                .handle_request(&instance_id, call("read"), None)
+                .await;
+        }
+        assert_eq!(reads.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        // Writes are never cached and drop what the instance had cached
+        for _ in 0..2 {
+            server
+                .handle_request(&instance_id, call("write"), None)
+                .await;
+        }
+        assert_eq!(writes.load(std::sync::atomic::Ordering::SeqCst), 2);
+        server
+            .handle_request(&instance_id, call("read"), None)
+            .await;
+        assert_eq!(reads.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_tool_calls_log_to_session() {
+        let (server, instance_id) = create_test_server_with_instance().await;
+        server
+            .manager
+            .register_tool(&instance_id, Arc::new(EchoTool))
+            .await;
+        let set_level = MCPRequest {
+            id: Some(MCPId::Number(1)),
+            method: "logging/setLevel".to_string(),
+            params: Some(serde_json::json!({"level": "debug"})),
+        };
+
+        // Without a session transport there is nowhere to log to
+        let response = server
+            .handle_request(&instance_id, set_level.clone(), None)
+            .await;
+        assert!(response.error.is_some());
+
+        let (logger, mut notifications) = MCPLogger::channel();
+        let server = CircuitBreakerMCPServer::with_manager(server.manager.clone())
+            .with_logger(Some(logger.clone()));
+        let response = server.handle_request(&instance_id, set_level, None).await;
+        assert!(response.error.is_none());
+        assert_eq!(logger.level(), MCPLogLevel::Debug);
+
+        let call = MCPRequest {
+            id: Some(MCPId::Number(2)),
+            method: "tools/call".to_string(),
+            params: Some(serde_json::json!({"name": "echo", "arguments": {"message": "hi"}})),
+        };
+        server.handle_request(&instance_id, call, None).await;
+        let levels: Vec<serde_json::Value> = std::iter::from_fn(|| notifications.try_recv().ok())
+            .map(|notification| notification["params"]["level"].clone())
+            .collect();
+        assert_eq!(levels, vec!["debug", "info"]);
+    }
+
+    #[tokio::test]
+    async fn test_completion_suggests_argument_values() {
+        let (server, instance_id) = create_test_server_with_instance().await;
+        let complete = |reference: serde_json::Value, name: &str, value: &str| MCPRequest {
+            id: Some(MCPId::Number(1)),
+            method: "completion/complete".to_string(),
+            params: Some(serde_json::json!({
+                "ref": reference,
+                "argument": {"name": name, "value": value}
+            })),
+        };
+
+        let prompt = serde_json::json!({"type": "ref/prompt", "name": "workflow_template"});
+        let response = server
+            .handle_request(
+                &instance_id,
+                complete(prompt.clone(), "complexity_level", "m"),
+                None,
+            )
+            .await;
+        assert_eq!(
+            response.result.unwrap()["completion"]["values"],
+            serde_json::json!(["medium", "simple", "complex"])
+        );
+
+        let resource = serde_json::json!({"type": "ref/resource", "uri": "context://{context_id}"});
+        let response = server
+            .handle_request(&instance_id, complete(resource, "context_id", "test"), None)
+            .await;
+        assert_eq!(
+            response.result.unwrap()["completion"]["values"],
+            serde_json::json!(["test-context"])
+        );
+
+        // Project arguments are served from the instance's cached provider lookup
+        server
+            .manager
+            .completion_cache
+            .insert(&instance_id, vec!["group/circuit-breaker".to_string()])
+            .await;
+        let resource = serde_json::json!({"type": "ref/resource", "uri": "gitlab://{project}"});
+        let response = server
+            .handle_request(&instance_id, complete(resource, "project", "circuit"), None)
+            .await;
+        assert_eq!(response.result.unwrap()["completion"]["total"], 1);
+
+        let missing = serde_json::json!({"type": "ref/prompt", "name": "missing"});
+        let response = server
+            .handle_request(&instance_id, complete(missing, "x", ""), None)
+            .await;
+        assert_eq!(response.error.unwrap().code, error_codes::INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn test_tool_policy_hides_and_blocks_tools() {
+        let (server, instance_id) = create_test_server_with_instance().await;
+        server
+            .manager
+            .set_tool_policy(
+                &instance_id,
+                MCPToolPolicy {
+                    read_only: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        let names: Vec<String> = server
+            .manager
+            .get_tools(&instance_id)
+            .await
+            .into_iter()
+            .map(|tool| tool.name)
+            .collect();
+        assert_eq!(names, vec!["search_project_context".to_string()]);
+
+        let request = MCPRequest {
+            id: Some(MCPId::String("blocked".to_string())),
+            method: "tools/call".to_string(),
+            params: Some(serde_json::json!({
+                "name": "create_workflow",
+                "arguments": {"name": "w", "steps": []}
+            })),
+        };
+        let response = server.handle_request(&instance_id, request, None).await;
+        let data = response.error.unwrap().data.unwrap();
+        assert_eq!(data["error_code"], "PERMISSION_DENIED");
+    }
+
+    #[tokio::test]
+    async fn test_upstream_tools_follow_connection() {
+        let (server, instance_id) = create_test_server_with_instance().await;
+        // Answers initialize and tools/list with a single "search" tool
+        let script = r#"
+            while IFS= read -r line; do
+              id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+              [ -z "$id" ] && continue
+              case "$line" in
+                *'"initialize"'*) printf '{"jsonrpc":"2.0","id":%s,"result":{"protocolVersion":"2024-11-05","capabilities":{"tools":{}},"serverInfo":{"name":"docs"}}}\n' "$id" ;;
+                *) printf '{"jsonrpc":"2.0","id":%s,"result":{"tools":[{"name":"search","inputSchema":{"type":"object"}}]}}\n' "$id" ;;
+              esac
+            done
+        "#;
+        let config = MCPUpstreamConfig {
+            name: "docs".to_string(),
+            transport: MCPUpstreamTransport::Stdio {
+                command: "sh".to_string(),
+                args: vec!["-c".to_string(), script.to_string()],
+                env: HashMap::new(),
+            },
+            tool_prefix: None,
+        };
+
+        let tools = server
+            .manager
+            .connect_upstream(&instance_id, config.clone())
+            .await
+            .unwrap();
+        assert_eq!(tools, vec!["docs_search".to_string()]);
+        assert!(matches!(
+            server.manager.connect_upstream(&instance_id, config).await,
+            Err(MCPClientError::InvalidConfig(_))
+        ));
+        let names = |tools: Vec<crate::api::mcp_types::MCPTool>| {
+            tools.into_iter().map(|tool| tool.name).collect::<Vec<_>>()
+        };
+        assert!(names(server.manager.get_tools(&instance_id).await).contains(&tools[0]));
+
+        assert!(
+            server
+                .manager
+                .disconnect_upstream(&instance_id, "docs")
+                .await
+        );
+        assert!(!names(server.manager.get_tools(&instance_id).await).contains(&tools[0]));
+        assert!(
+            !server
+                .manager
+                .disconnect_upstream(&instance_id, "docs")
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_streamable_http_session_lifecycle() {
+        use axum::body::HttpBody;
+        use tower::ServiceExt;
+
+        let (server, instance_id) = create_test_server_with_instance().await;
+        let app = server.create_router();
+        let uri = format!("/mcp/{}/stream", instance_id);
+        let request = |method: &str, session: Option<&str>, body: serde_json::Value| {
+            let mut builder = axum::http::Request::builder()
+                .method(method)
+                .uri(&uri)
+                .header("content-type", "application/json")
+                .header("accept", "application/json, text/event-stream");
+            if let Some(session) = session {
+                builder = builder.header("mcp-session-id", session);
+            }
+            builder.body(Body::from(body.to_string())).unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(request(
+                "POST",
+                None,
+                serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "initialize",
+                    "params": {"protocolVersion": "2025-03-26"}
+                }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let session_id = response.headers()["mcp-session-id"]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = response.into_body().data().await.unwrap().unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["result"]["protocolVersion"], "2025-03-26");
+
+        let initialized = serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/initialized"
+        });
+        let response = app
+            .clone()
+            .oneshot(request("POST", None, initialized.clone()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = app
+            .clone()
+            .oneshot(request("POST", Some(&session_id), initialized.clone()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        let response = app
+            .clone()
+            .oneshot(request("DELETE", Some(&session_id), serde_json::json!({})))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let response = app
+            .oneshot(request("POST", Some(&session_id), initialized))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_dynamic_client_registration_lifecycle() {
+        use axum::body::HttpBody;
+        use tower::ServiceExt;
+
+        let (server, _) = create_test_server_with_instance().await;
+        let app = server.create_router();
+        let request =
+            |method: &str, uri: &str, token: Option<&str>, body: Option<serde_json::Value>| {
+                let mut builder = axum::http::Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .header("host", "mcp.example.com")
+                    .header("content-type", "application/json");
+                if let Some(token) = token {
+                    builder = builder.header("authorization", format!("Bearer {}", token));
+                }
+                let body = body.map_or_else(Body::empty, |body| Body::from(body.to_string()));
+                builder.body(body).unwrap()
+            };
+        async fn json(response: Response) -> serde_json::Value {
+            let body = response.into_body().data().await.unwrap().unwrap();
+            serde_json::from_slice(&body).unwrap()
+        }
+
+        let response = app
+            .clone()
+            .oneshot(request(
+                "POST",
+                "/register",
+                None,
+                Some(serde_json::json!({"redirect_uris": ["http://evil.example.com/cb"]})),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(json(response).await["error"], "invalid_redirect_uri");
+
+        let response = app
+            .clone()
+            .oneshot(request(
+                "POST",
+                "/register",
+                None,
+                Some(serde_json::json!({
+                    "client_name": "mcp-remote",
+                    "redirect_uris": ["http://localhost:3334/oauth/callback"],
+                    "token_endpoint_auth_method": "none",
+                    "grant_types": ["authorization_code", "refresh_token"]
+                })),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()["cache-control"], "no-store");
+        let registered = json(response).await;
+        assert!(registered.get("client_secret").is_none());
+        let client_id = registered["client_id"].as_str().unwrap().to_string();
+        let token = registered["registration_access_token"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let uri = format!("/register/{}", client_id);
+        assert_eq!(
+            registered["registration_client_uri"],
+            format!("https://mcp.example.com{}", uri)
+        );
+
+        for token in [None, Some("wrong")] {
+            let response = app
+                .clone()
+                .oneshot(request("GET", &uri, token, None))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+        let response = app
+            .clone()
+            .oneshot(request("GET", &uri, Some(&token), None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let read = json(response).await;
+        assert_eq!(read["client_name"], "mcp-remote");
+        assert!(read.get("registration_access_token").is_none());
+
+        // Becoming a confidential client issues a secret
+        let response = app
+            .clone()
+            .oneshot(request(
+                "PUT",
+                &uri,
+                Some(&token),
+                Some(serde_json::json!({
+                    "client_id": client_id,
+                    "client_name": "Windsurf",
+                    "redirect_uris": ["http://127.0.0.1:3334/oauth/callback"]
+                })),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let updated = json(response).await;
+        assert_eq!(updated["client_name"], "Windsurf");
+        assert_eq!(updated["token_endpoint_auth_method"], "client_secret_basic");
+        assert!(updated["client_secret"].is_string());
+
+        let response = app
+            .clone()
+            .oneshot(request("DELETE", &uri, Some(&token), None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let response = app
+            .oneshot(request("GET", &uri, Some(&token), None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
     #[tokio::test]
     async fn test_unknown_method() {
         let (server, instance_id) = create_test_server_with_instance().await;
@@ -4441,6 +6174,62 @@ mod tests {
         assert_eq!(error.code, error_codes::METHOD_NOT_FOUND);
     }
 
+    #[tokio::test]
+    async fn test_get_stored_prompt() {
+        let (server, instance_id) = create_test_server_with_instance().await;
+        server
+            .manager
+            .set_prompt_template(
+                &instance_id,
+                MCPPromptTemplate {
+                    name: "summarize".to_string(),
+                    description: "Summarize a document".to_string(),
+                    arguments: vec![MCPPromptArgument {
+                        name: "document".to_string(),
+                        description: "Text to summarize".to_string(),
+                        required: true,
+                        values: Vec::new(),
+                    }],
+                    messages: vec![MCPPromptTemplateMessage {
+                        role: crate::api::mcp_sampling::SamplingRole::User,
+                        text: "Summarize this:\n\n{{document}}".to_string(),
+                    }],
+                },
+            )
+            .await
+            .unwrap();
+
+        let prompts = server.manager.get_prompts(&instance_id).await;
+        assert!(prompts.iter().any(|prompt| prompt.name == "summarize"));
+        assert!(prompts
+            .iter()
+            .any(|prompt| prompt.name == "workflow_template"));
+
+        let get_prompt = |arguments: serde_json::Value| MCPRequest {
+            id: Some(MCPId::String("test-5".to_string())),
+            method: "prompts/get".to_string(),
+            params: Some(serde_json::json!({"name": "summarize", "arguments": arguments})),
+        };
+        let response = server
+            .handle_request(
+                &instance_id,
+                get_prompt(serde_json::json!({"document": "Hello"})),
+                None,
+            )
+            .await;
+        let result = response.result.unwrap();
+        assert_eq!(result["messages"][0]["role"], "user");
+        assert_eq!(
+            result["messages"][0]["content"]["text"],
+            "Summarize this:\n\nHello"
+        );
+
+        let response = server
+            .handle_request(&instance_id, get_prompt(serde_json::json!({})), None)
+            .await;
+        assert_eq!(response.error.unwrap().code, error_codes::INVALID_PARAMS);
+    }
+
     #[tokio::test]
     async fn test_nonexistent_instance() {
         let server = CircuitBreakerMCPServer::new();
@@ -4459,4 +6248,90 @@ mod tests {
         let error = response.error.unwrap();
         assert_eq!(error.code, error_codes::INVALID_REQUEST);
     }
+
+    #[tokio::test]
+    async fn test_sse_router_reaps_closed_channels() {
+        let router = SSEResponseRouter::new();
+        let (tx, rx) = mpsc::unbounded_channel();
+        router
+            .register_channel("closed-token-123".to_string(), tx)
+            .await;
+        let (live_tx, _live_rx) = mpsc::unbounded_channel();
+        router
+            .register_channel("live-token-456".to_string(), live_tx)
+            .await;
+
+        drop(rx);
+        let reaped = router
+            .reap_stale(std::time::Duration::from_secs(3600))
+            .await;
+
+        assert_eq!(reaped, 1);
+        let gauges = router.gauges().await;
+        assert_eq!(gauges.active_subscribers, 1);
+        assert_eq!(gauges.reaped_total, 1);
+    }
+
+    #[tokio::test]
+    async fn test_sse_router_unregister_keeps_newer_channel() {
+        let router = SSEResponseRouter::new();
+        let (old_tx, _old_rx) = mpsc::unbounded_channel();
+        let (new_tx, _new_rx) = mpsc::unbounded_channel();
+        router
+            .register_channel("shared-token-789".to_string(), old_tx.clone())
+            .await;
+        router
+            .register_channel("shared-token-789".to_string(), new_tx)
+            .await;
+
+        router.unregister_channel("shared-token-789", &old_tx).await;
+        assert_eq!(router.channel_count().await, 1);
+    }
+
+    #[test]
+    fn test_sse_relay_subject_hides_token() {
+        let subject = sse_relay_subject("eyJhbGciOi.payload.signature");
+        let suffix = subject
+            .strip_prefix(&format!("{}.", SSE_RELAY_SUBJECT_PREFIX))
+            .unwrap();
+        assert_eq!(suffix.len(), 64);
+        assert!(suffix.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(subject, sse_relay_subject("eyJhbGciOi.payload.signature"));
+    }
+
+    #[tokio::test]
+    async fn test_sessions_are_shared_through_storage() {
+        let storage: Arc<dyn MCPStorage> = Arc::new(InMemoryMCPStorage::default());
+        let node_a = MCPServerManager::with_storage(storage.clone());
+        let node_b = MCPServerManager::with_storage(storage.clone());
+
+        let session_id = node_a
+            .create_session(MCPSessionParams {
+                session_id: Some("client-chosen".to_string()),
+                server_instance_id: "instance".to_string(),
+                installation_id: "installation".to_string(),
+                app_id: "app".to_string(),
+                user_id: None,
+                client_info: MCPClientInfo {
+                    name: "test".to_string(),
+                    version: "1.0.0".to_string(),
+                    user_agent: None,
+                },
+                permissions: MCPSessionPermissions::default(),
+                project_contexts: vec![],
+            })
+            .await;
+        assert_eq!(session_id, "client-chosen");
+
+        let session = node_b.get_session(&session_id).await.unwrap();
+        assert_eq!(session.server_instance_id, "instance");
+
+        // Expired sessions are gone on every node
+        let mut expired = session;
+        expired.expires_at = Some(chrono::Utc::now() - chrono::Duration::minutes(1));
+        storage.store_session(&expired).await.unwrap();
+        let node_c = MCPServerManager::with_storage(storage.clone());
+        assert!(node_c.get_session(&session_id).await.is_none());
+        assert!(storage.get_session(&session_id).await.unwrap().is_none());
+    }
 }
@@ -0,0 +1,635 @@
+// Signing keys for MCP installation and session tokens
+// Asymmetric keys with rotation, published as a JSON Web Key Set
+
+//! # MCP Signing Keys
+//!
+//! Circuit Breaker signs the installation and session tokens it issues with its own
+//! RS256 or ES256 key, and publishes the public halves at `/.well-known/jwks.json` so
+//! other services can validate those tokens without sharing a secret. Every key is
+//! identified by its RFC 7638 thumbprint, which tokens carry as the `kid` of their
+//! header.
+//!
+//! Rotating replaces the key new tokens are signed with. Retired keys stay published
+//! and keep validating the tokens they signed for [`RETIRED_KEY_RETENTION_HOURS`], the
+//! lifetime of session tokens, then drop out of the set.
+//!
+//! [`MCP_JWT_ALGORITHM_ENV`] picks the algorithm, RS256 by default, and
+//! [`MCP_JWT_SIGNING_KEY_ENV`] a PKCS#8 PEM file holding the first key; a file that
+//! cannot be read or parsed is an error rather than a reason to sign with another key.
+//! Without one a key is generated when the first token is signed.
+//!
+//! Nodes sharing their MCP storage share their keys too: [`SigningKeys::export`] turns
+//! them into [`StoredSigningKey`] records and [`SigningKeys::import`] takes them back,
+//! so a token signed by one node validates on all of them and survives restarts.
+
+use anyhow::{anyhow, Result};
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine as _,
+};
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters,
+    EllipticCurveKeyType, Jwk, JwkSet, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{
+    decode, decode_header, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+use rsa::pkcs1::EncodeRsaPrivateKey;
+use rsa::pkcs8::{DecodePrivateKey, EncodePrivateKey};
+use rsa::traits::PublicKeyParts;
+use rsa::RsaPrivateKey;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::RwLock;
+use tracing::{info, warn};
+
+/// Signing algorithm: `RS256` (default) or `ES256`
+pub const MCP_JWT_ALGORITHM_ENV: &str = "MCP_JWT_ALGORITHM";
+
+/// PKCS#8 PEM file holding the first signing key; generated when unset
+pub const MCP_JWT_SIGNING_KEY_ENV: &str = "MCP_JWT_SIGNING_KEY_PATH";
+
+/// How long a retired key keeps validating the tokens it signed
+pub const RETIRED_KEY_RETENTION_HOURS: i64 = 24;
+
+/// Size of generated RSA keys
+const RSA_KEY_BITS: usize = 2048;
+
+/// Asymmetric algorithms tokens may be signed with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigningAlgorithm {
+    RS256,
+    ES256,
+}
+
+impl SigningAlgorithm {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_uppercase().as_str() {
+            "RS256" => Some(SigningAlgorithm::RS256),
+            "ES256" => Some(SigningAlgorithm::ES256),
+            _ => None,
+        }
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        match self {
+            SigningAlgorithm::RS256 => Algorithm::RS256,
+            SigningAlgorithm::ES256 => Algorithm::ES256,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SigningAlgorithm::RS256 => "RS256",
+            SigningAlgorithm::ES256 => "ES256",
+        }
+    }
+
+    /// Algorithm named by [`MCP_JWT_ALGORITHM_ENV`], RS256 when unset or unsupported
+    pub fn from_env() -> Self {
+        match std::env::var(MCP_JWT_ALGORITHM_ENV) {
+            Ok(name) => SigningAlgorithm::from_name(&name).unwrap_or_else(|| {
+                warn!(
+                    "Unsupported {} '{}', signing MCP tokens with RS256",
+                    MCP_JWT_ALGORITHM_ENV, name
+                );
+                SigningAlgorithm::RS256
+            }),
+            Err(_) => SigningAlgorithm::RS256,
+        }
+    }
+}
+
+/// A signing key as MCP storage keeps it, private half included
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StoredSigningKey {
+    pub kid: String,
+    pub algorithm: String,
+    /// Base64 PKCS#8 DER of the private key
+    pub private_key: String,
+    pub created_at: DateTime<Utc>,
+    pub retired_at: Option<DateTime<Utc>>,
+}
+
+impl std::fmt::Debug for StoredSigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StoredSigningKey")
+            .field("kid", &self.kid)
+            .field("algorithm", &self.algorithm)
+            .field("created_at", &self.created_at)
+            .field("retired_at", &self.retired_at)
+            .finish_non_exhaustive()
+    }
+}
+
+struct SigningKey {
+    kid: String,
+    algorithm: SigningAlgorithm,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    jwk: Jwk,
+    /// PKCS#8 DER of the private key
+    pkcs8: Vec<u8>,
+    created_at: DateTime<Utc>,
+    retired_at: Option<DateTime<Utc>>,
+}
+
+/// Unpadded base64url, as JWKs encode key material
+fn base64url(bytes: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// RFC 7638 thumbprint of a public key's required members, in lexicographic order
+fn thumbprint(members: &[(&str, &str)]) -> String {
+    let canonical = members
+        .iter()
+        .map(|(name, value)| format!("\"{}\":\"{}\"", name, value))
+        .collect::<Vec<_>>()
+        .join(",");
+    base64url(&Sha256::digest(format!("{{{}}}", canonical).as_bytes()))
+}
+
+fn jwk(kid: &str, algorithm: SigningAlgorithm, parameters: AlgorithmParameters) -> Jwk {
+    Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            algorithm: Some(algorithm.algorithm()),
+            key_id: Some(kid.to_string()),
+            ..Default::default()
+        },
+        algorithm: parameters,
+    }
+}
+
+/// DER contents of a PEM document
+fn pem_der(pem: &str) -> Result<Vec<u8>> {
+    let body: String = pem
+        .lines()
+        .filter(|line| !line.starts_with("-----"))
+        .map(str::trim)
+        .collect();
+    STANDARD
+        .decode(body)
+        .map_err(|e| anyhow!("Invalid PEM: {}", e))
+}
+
+impl SigningKey {
+    fn generate(algorithm: SigningAlgorithm) -> Result<Self> {
+        match algorithm {
+            SigningAlgorithm::RS256 => {
+                let key = RsaPrivateKey::new(&mut rand::thread_rng(), RSA_KEY_BITS)
+                    .map_err(|e| anyhow!("Failed to generate RSA key: {}", e))?;
+                Self::from_rsa(key)
+            }
+            SigningAlgorithm::ES256 => {
+                let pkcs8 = EcdsaKeyPair::generate_pkcs8(
+                    &ECDSA_P256_SHA256_FIXED_SIGNING,
+                    &SystemRandom::new(),
+                )
+                .map_err(|_| anyhow!("Failed to generate P-256 key"))?;
+                Self::from_ec_pkcs8(pkcs8.as_ref())
+            }
+        }
+    }
+
+    fn from_pkcs8_pem(algorithm: SigningAlgorithm, pem: &str) -> Result<Self> {
+        match algorithm {
+            SigningAlgorithm::RS256 => {
+                let key = RsaPrivateKey::from_pkcs8_pem(pem)
+                    .map_err(|e| anyhow!("Invalid RSA private key: {}", e))?;
+                Self::from_rsa(key)
+            }
+            SigningAlgorithm::ES256 => Self::from_ec_pkcs8(&pem_der(pem)?),
+        }
+    }
+
+    fn from_pkcs8_der(algorithm: SigningAlgorithm, der: &[u8]) -> Result<Self> {
+        match algorithm {
+            SigningAlgorithm::RS256 => {
+                let key = RsaPrivateKey::from_pkcs8_der(der)
+                    .map_err(|e| anyhow!("Invalid RSA private key: {}", e))?;
+                Self::from_rsa(key)
+            }
+            SigningAlgorithm::ES256 => Self::from_ec_pkcs8(der),
+        }
+    }
+
+    fn from_stored(stored: &StoredSigningKey) -> Result<Self> {
+        let algorithm = SigningAlgorithm::from_name(&stored.algorithm)
+            .ok_or_else(|| anyhow!("Unsupported signing algorithm {}", stored.algorithm))?;
+        let der = STANDARD
+            .decode(&stored.private_key)
+            .map_err(|e| anyhow!("Invalid stored signing key {}: {}", stored.kid, e))?;
+        let mut key = Self::from_pkcs8_der(algorithm, &der)?;
+        if key.kid != stored.kid {
+            return Err(anyhow!(
+                "Stored signing key {} holds key {}",
+                stored.kid,
+                key.kid
+            ));
+        }
+        key.created_at = stored.created_at;
+        key.retired_at = stored.retired_at;
+        Ok(key)
+    }
+
+    fn stored(&self) -> StoredSigningKey {
+        StoredSigningKey {
+            kid: self.kid.clone(),
+            algorithm: self.algorithm.name().to_string(),
+            private_key: STANDARD.encode(&self.pkcs8),
+            created_at: self.created_at,
+            retired_at: self.retired_at,
+        }
+    }
+
+    fn from_rsa(key: RsaPrivateKey) -> Result<Self> {
+        let der = key
+            .to_pkcs1_der()
+            .map_err(|e| anyhow!("Failed to encode RSA key: {}", e))?;
+        let pkcs8 = key
+            .to_pkcs8_der()
+            .map_err(|e| anyhow!("Failed to encode RSA key: {}", e))?;
+        let n = base64url(&key.n().to_bytes_be());
+        let e = base64url(&key.e().to_bytes_be());
+        let kid = thumbprint(&[("e", &e), ("kty", "RSA"), ("n", &n)]);
+        let decoding_key = DecodingKey::from_rsa_components(&n, &e)
+            .map_err(|e| anyhow!("Invalid RSA public key: {}", e))?;
+        let parameters = AlgorithmParameters::RSA(RSAKeyParameters {
+            key_type: RSAKeyType::RSA,
+            n,
+            e,
+        });
+        Ok(Self {
+            jwk: jwk(&kid, SigningAlgorithm::RS256, parameters),
+            kid,
+            algorithm: SigningAlgorithm::RS256,
+            encoding_key: EncodingKey::from_rsa_der(der.as_bytes()),
+            decoding_key,
+            pkcs8: pkcs8.as_bytes().to_vec(),
+            created_at: Utc::now(),
+            retired_at: None,
+        })
+    }
+
+    fn from_ec_pkcs8(pkcs8: &[u8]) -> Result<Self> {
+        let pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8)
+            .map_err(|e| anyhow!("Invalid P-256 private key: {}", e))?;
+        // Uncompressed point: 0x04 followed by the x and y coordinates
+        let point = pair.public_key().as_ref();
+        let x = base64url(&point[1..33]);
+        let y = base64url(&point[33..65]);
+        let kid = thumbprint(&[("crv", "P-256"), ("kty", "EC"), ("x", &x), ("y", &y)]);
+        let decoding_key = DecodingKey::from_ec_components(&x, &y)
+            .map_err(|e| anyhow!("Invalid P-256 public key: {}", e))?;
+        let parameters = AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+            key_type: EllipticCurveKeyType::EC,
+            curve: EllipticCurve::P256,
+            x,
+            y,
+        });
+        Ok(Self {
+            jwk: jwk(&kid, SigningAlgorithm::ES256, parameters),
+            kid,
+            algorithm: SigningAlgorithm::ES256,
+            encoding_key: EncodingKey::from_ec_der(pkcs8),
+            decoding_key,
+            pkcs8: pkcs8.to_vec(),
+            created_at: Utc::now(),
+            retired_at: None,
+        })
+    }
+}
+
+/// The server's signing keys: the one new tokens are signed with, and retired ones
+/// still validating older tokens
+pub struct SigningKeys {
+    algorithm: SigningAlgorithm,
+    /// Oldest first; the last one signs
+    keys: RwLock<Vec<SigningKey>>,
+}
+
+impl SigningKeys {
+    /// Keys for `algorithm`; the first one is generated when it is needed
+    pub fn new(algorithm: SigningAlgorithm) -> Self {
+        Self {
+            algorithm,
+            keys: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Keys starting from a PKCS#8 PEM private key
+    pub fn with_key_pem(algorithm: SigningAlgorithm, pem: &str) -> Result<Self> {
+        let key = SigningKey::from_pkcs8_pem(algorithm, pem)?;
+        Ok(Self {
+            algorithm,
+            keys: RwLock::new(vec![key]),
+        })
+    }
+
+    /// Keys configured by [`MCP_JWT_ALGORITHM_ENV`] and [`MCP_JWT_SIGNING_KEY_ENV`]; a
+    /// key file that cannot be read or parsed is an error
+    pub fn from_env() -> Result<Self> {
+        let keys = Self::new(SigningAlgorithm::from_env());
+        keys.load_env_key()?;
+        Ok(keys)
+    }
+
+    /// Sign with the key file named by [`MCP_JWT_SIGNING_KEY_ENV`], when set
+    pub fn load_env_key(&self) -> Result<()> {
+        match std::env::var(MCP_JWT_SIGNING_KEY_ENV) {
+            Ok(path) => self.load_key_file(&path),
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// Sign with the PKCS#8 PEM key in `path` from now on
+    pub fn load_key_file(&self, path: &str) -> Result<()> {
+        let pem = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read MCP signing key {}: {}", path, e))?;
+        let key = SigningKey::from_pkcs8_pem(self.algorithm, &pem)
+            .map_err(|e| anyhow!("Invalid MCP signing key {}: {}", path, e))?;
+        info!(
+            "Signing MCP tokens with the {:?} key in {}",
+            self.algorithm, path
+        );
+        *self.keys.write().unwrap() = vec![key];
+        Ok(())
+    }
+
+    pub fn algorithm(&self) -> SigningAlgorithm {
+        self.algorithm
+    }
+
+    fn ensure_key(&self) -> Result<()> {
+        if !self.keys.read().unwrap().is_empty() {
+            return Ok(());
+        }
+        let key = SigningKey::generate(self.algorithm)?;
+        let mut keys = self.keys.write().unwrap();
+        if keys.is_empty() {
+            info!("Generated {:?} MCP signing key {}", self.algorithm, key.kid);
+            keys.push(key);
+        }
+        Ok(())
+    }
+
+    /// Header and key to sign a new token with
+    pub fn signer(&self) -> Result<(Header, EncodingKey)> {
+        self.ensure_key()?;
+        let keys = self.keys.read().unwrap();
+        let key = keys.last().ok_or_else(|| anyhow!("No MCP signing key"))?;
+        let mut header = Header::new(key.algorithm.algorithm());
+        header.kid = Some(key.kid.clone());
+        Ok((header, key.encoding_key.clone()))
+    }
+
+    /// Verify a token signed by one of the keys, current or retired, and decode its claims
+    pub fn verify<T: DeserializeOwned>(&self, token: &str) -> Result<T> {
+        let header = decode_header(token).map_err(|e| anyhow!("Invalid token header: {}", e))?;
+        let kid = header
+            .kid
+            .ok_or_else(|| anyhow!("Token does not name its signing key"))?;
+        let (decoding_key, algorithm) = {
+            let now = Utc::now();
+            let keys = self.keys.read().unwrap();
+            let key = keys
+                .iter()
+                .filter(|key| Self::retained(key, now))
+                .find(|key| key.kid == kid)
+                .ok_or_else(|| anyhow!("Unknown signing key {}", kid))?;
+            (key.decoding_key.clone(), key.algorithm.algorithm())
+        };
+        decode::<T>(token, &decoding_key, &Validation::new(algorithm))
+            .map(|data| data.claims)
+            .map_err(|e| anyhow!("Token verification failed: {}", e))
+    }
+
+    /// Whether a current or retired key carries `kid`
+    pub fn knows(&self, kid: &str) -> bool {
+        self.keys.read().unwrap().iter().any(|key| key.kid == kid)
+    }
+
+    /// Whether the key new tokens are signed with is at least `max_age` old; a key not
+    /// generated yet is not due
+    pub fn rotation_due(&self, max_age: std::time::Duration) -> bool {
+        let max_age = Duration::from_std(max_age).unwrap_or(Duration::MAX);
+        self.keys
+            .read()
+            .unwrap()
+            .last()
+            .is_some_and(|key| Utc::now() - key.created_at >= max_age)
+    }
+
+    /// The retained keys, oldest first, for MCP storage to share
+    pub fn export(&self) -> Result<Vec<StoredSigningKey>> {
+        self.ensure_key()?;
+        let now = Utc::now();
+        let keys = self.keys.read().unwrap();
+        Ok(keys
+            .iter()
+            .filter(|key| Self::retained(key, now))
+            .map(SigningKey::stored)
+            .collect())
+    }
+
+    /// Replace the keys with those exported by another node; the newest one signs.
+    /// Keys already held are kept rather than parsed again.
+    pub fn import(&self, stored: &[StoredSigningKey]) -> Result<()> {
+        if stored.is_empty() {
+            return Ok(());
+        }
+        let mut parsed = HashMap::new();
+        for record in stored.iter().filter(|record| !self.knows(&record.kid)) {
+            parsed.insert(record.kid.clone(), SigningKey::from_stored(record)?);
+        }
+        let mut keys = self.keys.write().unwrap();
+        let mut held = std::mem::take(&mut *keys);
+        for record in stored {
+            let key = match held.iter().position(|key| key.kid == record.kid) {
+                Some(index) => Some(held.swap_remove(index)),
+                None => parsed.remove(&record.kid),
+            };
+            if let Some(mut key) = key {
+                key.created_at = record.created_at;
+                key.retired_at = record.retired_at;
+                keys.push(key);
+            }
+        }
+        keys.sort_by_key(|key| key.created_at);
+        Ok(())
+    }
+
+    fn retained(key: &SigningKey, now: DateTime<Utc>) -> bool {
+        key.retired_at.is_none_or(|retired_at| {
+            now - retired_at < Duration::hours(RETIRED_KEY_RETENTION_HOURS)
+        })
+    }
+
+    /// Sign new tokens with a fresh key, returning its `kid`; the previous key keeps
+    /// validating for [`RETIRED_KEY_RETENTION_HOURS`]
+    pub fn rotate(&self) -> Result<String> {
+        self.rotate_at(Utc::now())
+    }
+
+    fn rotate_at(&self, now: DateTime<Utc>) -> Result<String> {
+        let key = SigningKey::generate(self.algorithm)?;
+        let kid = key.kid.clone();
+        let mut keys = self.keys.write().unwrap();
+        keys.retain(|key| Self::retained(key, now));
+        if let Some(current) = keys.last_mut() {
+            current.retired_at = Some(now);
+        }
+        keys.push(key);
+        info!("Rotated MCP signing key, now signing with {}", kid);
+        Ok(kid)
+    }
+
+    /// Public keys validating tokens: the current one and those retired recently
+    pub fn jwks(&self) -> Result<JwkSet> {
+        self.ensure_key()?;
+        let now = Utc::now();
+        let keys = self.keys.read().unwrap();
+        Ok(JwkSet {
+            keys: keys
+                .iter()
+                .rev()
+                .filter(|key| Self::retained(key, now))
+                .map(|key| key.jwk.clone())
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::encode;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Claims {
+        sub: String,
+        exp: i64,
+    }
+
+    fn sign(keys: &SigningKeys, sub: &str) -> String {
+        let (header, key) = keys.signer().unwrap();
+        let claims = Claims {
+            sub: sub.to_string(),
+            exp: (Utc::now() + Duration::hours(1)).timestamp(),
+        };
+        encode(&header, &claims, &key).unwrap()
+    }
+
+    #[test]
+    fn test_es256_keys_rotate_and_publish() {
+        let keys = SigningKeys::new(SigningAlgorithm::ES256);
+        let first = sign(&keys, "installation-1");
+        let claims: Claims = keys.verify(&first).unwrap();
+        assert_eq!(claims.sub, "installation-1");
+
+        let jwks = keys.jwks().unwrap();
+        assert_eq!(jwks.keys.len(), 1);
+        let jwk = &jwks.keys[0];
+        assert_eq!(jwk.common.algorithm, Some(Algorithm::ES256));
+        // The published key validates tokens on its own
+        let decoding_key = DecodingKey::from_jwk(jwk).unwrap();
+        assert!(
+            decode::<Claims>(&first, &decoding_key, &Validation::new(Algorithm::ES256)).is_ok()
+        );
+
+        // Tokens signed before a rotation keep validating until the retired key is dropped
+        let kid = keys.rotate().unwrap();
+        let second = sign(&keys, "installation-2");
+        assert_eq!(decode_header(&second).unwrap().kid, Some(kid.clone()));
+        assert!(keys.verify::<Claims>(&first).is_ok());
+        assert_eq!(keys.jwks().unwrap().keys.len(), 2);
+
+        let later = Utc::now() + Duration::hours(RETIRED_KEY_RETENTION_HOURS + 1);
+        keys.rotate_at(later).unwrap();
+        assert!(keys.verify::<Claims>(&first).is_err());
+        assert!(keys.verify::<Claims>(&second).is_ok());
+        assert_eq!(keys.jwks().unwrap().keys.len(), 2);
+    }
+
+    #[test]
+    fn test_rs256_key_from_pem() {
+        let pem = std::fs::read_to_string("test_keys/test.pem").unwrap();
+        let keys = SigningKeys::with_key_pem(SigningAlgorithm::RS256, &pem).unwrap();
+        let token = sign(&keys, "session-1");
+        assert_eq!(
+            keys.verify::<Claims>(&token).unwrap().sub,
+            "session-1".to_string()
+        );
+
+        let jwks = keys.jwks().unwrap();
+        let jwk = &jwks.keys[0];
+        assert_eq!(jwk.common.key_id, decode_header(&token).unwrap().kid);
+        let decoding_key = DecodingKey::from_jwk(jwk).unwrap();
+        assert!(
+            decode::<Claims>(&token, &decoding_key, &Validation::new(Algorithm::RS256)).is_ok()
+        );
+
+        // A token naming no key, or a key this server never had, is refused
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some("unknown".to_string());
+        let (_, key) = keys.signer().unwrap();
+        let forged = encode(
+            &header,
+            &Claims {
+                sub: "x".to_string(),
+                exp: Utc::now().timestamp() + 60,
+            },
+            &key,
+        )
+        .unwrap();
+        assert!(keys.verify::<Claims>(&forged).is_err());
+        assert_eq!(
+            SigningAlgorithm::from_name("es256"),
+            Some(SigningAlgorithm::ES256)
+        );
+    }
+
+    #[test]
+    fn test_keys_shared_through_storage() {
+        let first = SigningKeys::new(SigningAlgorithm::ES256);
+        let before = sign(&first, "installation-1");
+        first.rotate().unwrap();
+        let after = sign(&first, "installation-2");
+
+        // Another node signs with the same key and validates what the first one signed
+        let second = SigningKeys::new(SigningAlgorithm::RS256);
+        second.import(&first.export().unwrap()).unwrap();
+        assert!(second.verify::<Claims>(&before).is_ok());
+        assert!(second.verify::<Claims>(&after).is_ok());
+        let token = sign(&second, "installation-3");
+        assert_eq!(
+            decode_header(&token).unwrap().kid,
+            decode_header(&after).unwrap().kid
+        );
+        assert!(first.verify::<Claims>(&token).is_ok());
+        assert!(!second.rotation_due(std::time::Duration::from_secs(3600)));
+        assert!(second.rotation_due(std::time::Duration::ZERO));
+
+        // A record whose key does not match its kid is refused
+        let mut forged = first.export().unwrap();
+        forged[0].kid = "unknown".to_string();
+        assert!(SigningKeys::new(SigningAlgorithm::ES256)
+            .import(&forged)
+            .is_err());
+    }
+
+    #[test]
+    fn test_unusable_key_file_is_an_error() {
+        let keys = SigningKeys::new(SigningAlgorithm::RS256);
+        assert!(keys.load_key_file("test_keys/missing.pem").is_err());
+        assert!(keys.load_key_file("Cargo.toml").is_err());
+        assert!(keys.load_key_file("test_keys/test.pem").is_ok());
+    }
+}
@@ -1,18 +1,24 @@
 //! MCP Instance Storage
 //!
-//! This module provides persistent storage for MCP server instances using NATS KV.
+//! This module provides persistent storage for MCP server instances using NATS KV or
+//! Postgres.
 
 use anyhow::{anyhow, Result};
 use async_nats::jetstream::kv::Store;
 use async_trait::async_trait;
 use futures::StreamExt;
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{debug, error, info, warn};
 
-use super::mcp_types::{MCPApp, MCPInstallation, MCPServerInstance, RemoteOAuthConfig};
-use super::oauth::StoredOAuthToken;
+use super::mcp_registration::MCPClientRegistration;
+use super::mcp_signing_keys::StoredSigningKey;
+use super::mcp_types::{
+    MCPApp, MCPInstallation, MCPPromptTemplate, MCPServerInstance, MCPSession, RemoteOAuthConfig,
+};
+use super::oauth::{OAuthAuthRequest, StoredOAuthToken, OAUTH_FLOW_MAX_AGE};
 
 /// Storage trait for MCP instances
 #[async_trait]
@@ -29,6 +35,14 @@ pub trait MCPStorage: Send + Sync {
     async fn get_oauth_config(&self, instance_id: &str) -> Result<Option<RemoteOAuthConfig>>;
     async fn delete_oauth_config(&self, instance_id: &str) -> Result<bool>;
 
+    // Prompt templates, stored together per instance
+    async fn store_prompt_templates(
+        &self,
+        instance_id: &str,
+        templates: &[MCPPromptTemplate],
+    ) -> Result<()>;
+    async fn get_prompt_templates(&self, instance_id: &str) -> Result<Vec<MCPPromptTemplate>>;
+
     // Apps
     async fn store_app(&self, app: &MCPApp) -> Result<()>;
     async fn get_app(&self, app_id: &str) -> Result<Option<MCPApp>>;
@@ -46,6 +60,58 @@ pub trait MCPStorage: Send + Sync {
     async fn get_oauth_token(&self, token_key: &str) -> Result<Option<StoredOAuthToken>>;
     async fn list_oauth_tokens(&self) -> Result<Vec<(String, StoredOAuthToken)>>;
     async fn delete_oauth_token(&self, token_key: &str) -> Result<bool>;
+
+    // Client sessions, shared by every node serving the instances
+    //
+    // Stores that don't share sessions keep the defaults, and each node only knows the
+    // sessions it created itself.
+    async fn store_session(&self, _session: &MCPSession) -> Result<()> {
+        Ok(())
+    }
+    async fn get_session(&self, _session_id: &str) -> Result<Option<MCPSession>> {
+        Ok(None)
+    }
+    async fn delete_session(&self, _session_id: &str) -> Result<bool> {
+        Ok(false)
+    }
+
+    // Keys installation and session tokens are signed with, stored together
+    //
+    // Stores that don't share them keep the defaults, and each node signs with keys of
+    // its own.
+    async fn store_signing_keys(&self, _keys: &[StoredSigningKey]) -> Result<()> {
+        Ok(())
+    }
+    async fn get_signing_keys(&self) -> Result<Vec<StoredSigningKey>> {
+        Ok(Vec::new())
+    }
+
+    // OAuth authorization flows awaiting their callback, by state
+    async fn store_oauth_flow(&self, state: &str, flow: &OAuthAuthRequest) -> Result<()>;
+    async fn get_oauth_flow(&self, state: &str) -> Result<Option<OAuthAuthRequest>>;
+    async fn delete_oauth_flow(&self, state: &str) -> Result<bool>;
+
+    // OAuth clients registered dynamically, by client ID
+    async fn store_client_registration(&self, registration: &MCPClientRegistration) -> Result<()>;
+    async fn get_client_registration(
+        &self,
+        client_id: &str,
+    ) -> Result<Option<MCPClientRegistration>>;
+    async fn delete_client_registration(&self, client_id: &str) -> Result<bool>;
+}
+
+/// Sessions live as long as their 24 hour expiry
+const SESSION_MAX_AGE: std::time::Duration = std::time::Duration::from_secs(24 * 60 * 60);
+
+/// The one key the signing keys are stored under, all of them together
+const SIGNING_KEYS_KEY: &str = "current";
+
+/// KV key of a session or OAuth state; both come from clients and may hold any character
+fn session_key(session_id: &str) -> String {
+    Sha256::digest(session_id.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
 }
 
 /// In-memory implementation of MCPStorage for development/testing
@@ -53,9 +119,14 @@ pub trait MCPStorage: Send + Sync {
 pub struct InMemoryMCPStorage {
     instances: RwLock<HashMap<String, MCPServerInstance>>,
     oauth_configs: RwLock<HashMap<String, RemoteOAuthConfig>>,
+    prompt_templates: RwLock<HashMap<String, Vec<MCPPromptTemplate>>>,
     apps: RwLock<HashMap<String, MCPApp>>,
     installations: RwLock<HashMap<String, MCPInstallation>>,
     oauth_tokens: RwLock<HashMap<String, StoredOAuthToken>>,
+    sessions: RwLock<HashMap<String, MCPSession>>,
+    oauth_flows: RwLock<HashMap<String, OAuthAuthRequest>>,
+    client_registrations: RwLock<HashMap<String, MCPClientRegistration>>,
+    signing_keys: RwLock<Vec<StoredSigningKey>>,
 }
 
 impl Default for InMemoryMCPStorage {
@@ -63,9 +134,14 @@ impl Default for InMemoryMCPStorage {
         Self {
             instances: RwLock::new(HashMap::new()),
             oauth_configs: RwLock::new(HashMap::new()),
+            prompt_templates: RwLock::new(HashMap::new()),
             apps: RwLock::new(HashMap::new()),
             installations: RwLock::new(HashMap::new()),
             oauth_tokens: RwLock::new(HashMap::new()),
+            sessions: RwLock::new(HashMap::new()),
+            oauth_flows: RwLock::new(HashMap::new()),
+            client_registrations: RwLock::new(HashMap::new()),
+            signing_keys: RwLock::new(Vec::new()),
         }
     }
 }
@@ -118,6 +194,29 @@ impl MCPStorage for InMemoryMCPStorage {
         Ok(configs.remove(instance_id).is_some())
     }
 
+    async fn store_prompt_templates(
+        &self,
+        instance_id: &str,
+        templates: &[MCPPromptTemplate],
+    ) -> Result<()> {
+        let mut prompt_templates = self.prompt_templates.write().await;
+        prompt_templates.insert(instance_id.to_string(), templates.to_vec());
+        debug!(
+            "Stored {} prompt templates in memory for instance: {}",
+            templates.len(),
+            instance_id
+        );
+        Ok(())
+    }
+
+    async fn get_prompt_templates(&self, instance_id: &str) -> Result<Vec<MCPPromptTemplate>> {
+        let prompt_templates = self.prompt_templates.read().await;
+        Ok(prompt_templates
+            .get(instance_id)
+            .cloned()
+            .unwrap_or_default())
+    }
+
     async fn store_app(&self, app: &MCPApp) -> Result<()> {
         let mut apps = self.apps.write().await;
         apps.insert(app.app_id.clone(), app.clone());
@@ -199,6 +298,67 @@ impl MCPStorage for InMemoryMCPStorage {
         );
         Ok(removed)
     }
+
+    async fn store_session(&self, session: &MCPSession) -> Result<()> {
+        let mut sessions = self.sessions.write().await;
+        sessions.insert(session.session_id.clone(), session.clone());
+        debug!("Stored MCP session in memory: {}", session.session_id);
+        Ok(())
+    }
+
+    async fn get_session(&self, session_id: &str) -> Result<Option<MCPSession>> {
+        let sessions = self.sessions.read().await;
+        Ok(sessions.get(session_id).cloned())
+    }
+
+    async fn delete_session(&self, session_id: &str) -> Result<bool> {
+        let mut sessions = self.sessions.write().await;
+        Ok(sessions.remove(session_id).is_some())
+    }
+
+    async fn store_signing_keys(&self, keys: &[StoredSigningKey]) -> Result<()> {
+        *self.signing_keys.write().await = keys.to_vec();
+        Ok(())
+    }
+
+    async fn get_signing_keys(&self) -> Result<Vec<StoredSigningKey>> {
+        Ok(self.signing_keys.read().await.clone())
+    }
+
+    async fn store_oauth_flow(&self, state: &str, flow: &OAuthAuthRequest) -> Result<()> {
+        let mut flows = self.oauth_flows.write().await;
+        flows.insert(state.to_string(), flow.clone());
+        Ok(())
+    }
+
+    async fn get_oauth_flow(&self, state: &str) -> Result<Option<OAuthAuthRequest>> {
+        let flows = self.oauth_flows.read().await;
+        Ok(flows.get(state).cloned())
+    }
+
+    async fn delete_oauth_flow(&self, state: &str) -> Result<bool> {
+        let mut flows = self.oauth_flows.write().await;
+        Ok(flows.remove(state).is_some())
+    }
+
+    async fn store_client_registration(&self, registration: &MCPClientRegistration) -> Result<()> {
+        let mut registrations = self.client_registrations.write().await;
+        registrations.insert(registration.client_id.clone(), registration.clone());
+        Ok(())
+    }
+
+    async fn get_client_registration(
+        &self,
+        client_id: &str,
+    ) -> Result<Option<MCPClientRegistration>> {
+        let registrations = self.client_registrations.read().await;
+        Ok(registrations.get(client_id).cloned())
+    }
+
+    async fn delete_client_registration(&self, client_id: &str) -> Result<bool> {
+        let mut registrations = self.client_registrations.write().await;
+        Ok(registrations.remove(client_id).is_some())
+    }
 }
 
 /// NATS KV-based implementation of MCPStorage
@@ -208,9 +368,14 @@ pub struct NATSMCPStorage {
     jetstream: async_nats::jetstream::Context,
     instances_store: Arc<RwLock<Option<Store>>>,
     oauth_configs_store: Arc<RwLock<Option<Store>>>,
+    prompt_templates_store: Arc<RwLock<Option<Store>>>,
     apps_store: Arc<RwLock<Option<Store>>>,
     installations_store: Arc<RwLock<Option<Store>>>,
     oauth_tokens_store: Arc<RwLock<Option<Store>>>,
+    sessions_store: Arc<RwLock<Option<Store>>>,
+    oauth_flows_store: Arc<RwLock<Option<Store>>>,
+    client_registrations_store: Arc<RwLock<Option<Store>>>,
+    signing_keys_store: Arc<RwLock<Option<Store>>>,
 }
 
 impl NATSMCPStorage {
@@ -227,9 +392,14 @@ impl NATSMCPStorage {
             jetstream,
             instances_store: Arc::new(RwLock::new(None)),
             oauth_configs_store: Arc::new(RwLock::new(None)),
+            prompt_templates_store: Arc::new(RwLock::new(None)),
             apps_store: Arc::new(RwLock::new(None)),
             installations_store: Arc::new(RwLock::new(None)),
             oauth_tokens_store: Arc::new(RwLock::new(None)),
+            sessions_store: Arc::new(RwLock::new(None)),
+            oauth_flows_store: Arc::new(RwLock::new(None)),
+            client_registrations_store: Arc::new(RwLock::new(None)),
+            signing_keys_store: Arc::new(RwLock::new(None)),
         };
 
         // Initialize KV stores
@@ -267,6 +437,19 @@ impl NATSMCPStorage {
 
         *self.oauth_configs_store.write().await = Some(oauth_configs_store);
 
+        // Prompt Templates
+        let prompt_templates_store = self
+            .jetstream
+            .create_key_value(async_nats::jetstream::kv::Config {
+                bucket: "mcp_prompt_templates".to_string(),
+                description: "MCP Prompt Templates".to_string(),
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| anyhow!("Failed to create mcp_prompt_templates KV store: {}", e))?;
+
+        *self.prompt_templates_store.write().await = Some(prompt_templates_store);
+
         // MCP Apps
         let apps_store = self
             .jetstream
@@ -306,6 +489,60 @@ impl NATSMCPStorage {
 
         *self.oauth_tokens_store.write().await = Some(oauth_tokens_store);
 
+        // Client sessions, expired by the bucket once they can no longer be used
+        let sessions_store = self
+            .jetstream
+            .create_key_value(async_nats::jetstream::kv::Config {
+                bucket: "mcp_sessions".to_string(),
+                description: "MCP Client Sessions".to_string(),
+                max_age: SESSION_MAX_AGE,
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| anyhow!("Failed to create mcp_sessions KV store: {}", e))?;
+
+        *self.sessions_store.write().await = Some(sessions_store);
+
+        // OAuth flows, expired by the bucket once too old to finish
+        let oauth_flows_store = self
+            .jetstream
+            .create_key_value(async_nats::jetstream::kv::Config {
+                bucket: "mcp_oauth_flows".to_string(),
+                description: "MCP OAuth Authorization Flows".to_string(),
+                max_age: OAUTH_FLOW_MAX_AGE,
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| anyhow!("Failed to create mcp_oauth_flows KV store: {}", e))?;
+
+        *self.oauth_flows_store.write().await = Some(oauth_flows_store);
+
+        // Dynamically registered OAuth clients
+        let client_registrations_store = self
+            .jetstream
+            .create_key_value(async_nats::jetstream::kv::Config {
+                bucket: "mcp_client_registrations".to_string(),
+                description: "MCP OAuth Client Registrations".to_string(),
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| anyhow!("Failed to create mcp_client_registrations KV store: {}", e))?;
+
+        *self.client_registrations_store.write().await = Some(client_registrations_store);
+
+        // Token signing keys, shared by every node
+        let signing_keys_store = self
+            .jetstream
+            .create_key_value(async_nats::jetstream::kv::Config {
+                bucket: "mcp_signing_keys".to_string(),
+                description: "MCP Token Signing Keys".to_string(),
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| anyhow!("Failed to create mcp_signing_keys KV store: {}", e))?;
+
+        *self.signing_keys_store.write().await = Some(signing_keys_store);
+
         info!("All NATS KV stores for MCP storage initialized");
         Ok(())
     }
@@ -328,6 +565,15 @@ impl NATSMCPStorage {
             .cloned()
     }
 
+    /// Get the prompt templates KV store
+    async fn get_prompt_templates_store(&self) -> Result<Store> {
+        let store_lock = self.prompt_templates_store.read().await;
+        store_lock
+            .as_ref()
+            .ok_or_else(|| anyhow!("MCP prompt templates KV store not initialized"))
+            .cloned()
+    }
+
     /// Get the apps KV store
     async fn get_apps_store(&self) -> Result<Store> {
         let store_lock = self.apps_store.read().await;
@@ -354,6 +600,47 @@ impl NATSMCPStorage {
             .ok_or_else(|| anyhow!("MCP OAuth tokens KV store not initialized"))
             .cloned()
     }
+
+    /// Get the sessions KV store
+    async fn get_sessions_store(&self) -> Result<Store> {
+        let store_lock = self.sessions_store.read().await;
+        store_lock
+            .as_ref()
+            .ok_or_else(|| anyhow!("MCP sessions KV store not initialized"))
+            .cloned()
+    }
+
+    /// Get the OAuth flows KV store
+    async fn get_oauth_flows_store(&self) -> Result<Store> {
+        let store_lock = self.oauth_flows_store.read().await;
+        store_lock
+            .as_ref()
+            .ok_or_else(|| anyhow!("MCP OAuth flows KV store not initialized"))
+            .cloned()
+    }
+
+    /// Get the client registrations KV store
+    async fn get_client_registrations_store(&self) -> Result<Store> {
+        let store_lock = self.client_registrations_store.read().await;
+        store_lock
+            .as_ref()
+            .ok_or_else(|| anyhow!("MCP client registrations KV store not initialized"))
+            .cloned()
+    }
+
+    /// Get the signing keys KV store
+    async fn get_signing_keys_store(&self) -> Result<Store> {
+        let store_lock = self.signing_keys_store.read().await;
+        store_lock
+            .as_ref()
+            .ok_or_else(|| anyhow!("MCP signing keys KV store not initialized"))
+            .cloned()
+    }
+
+    /// The NATS connection behind the storage
+    pub fn client(&self) -> async_nats::Client {
+        self.client.clone()
+    }
 }
 
 #[async_trait]
@@ -501,6 +788,45 @@ impl MCPStorage for NATSMCPStorage {
         }
     }
 
+    async fn store_prompt_templates(
+        &self,
+        instance_id: &str,
+        templates: &[MCPPromptTemplate],
+    ) -> Result<()> {
+        let store = self.get_prompt_templates_store().await?;
+        let data = serde_json::to_vec(templates)
+            .map_err(|e| anyhow!("Failed to serialize prompt templates: {}", e))?;
+
+        store
+            .put(instance_id, data.into())
+            .await
+            .map_err(|e| anyhow!("Failed to store prompt templates in NATS KV: {}", e))?;
+
+        info!(
+            "Stored {} prompt templates in NATS KV for instance: {}",
+            templates.len(),
+            instance_id
+        );
+        Ok(())
+    }
+
+    async fn get_prompt_templates(&self, instance_id: &str) -> Result<Vec<MCPPromptTemplate>> {
+        let store = self.get_prompt_templates_store().await?;
+
+        match store.get(instance_id).await {
+            Ok(Some(entry)) => serde_json::from_slice(entry.as_ref())
+                .map_err(|e| anyhow!("Failed to deserialize prompt templates: {}", e)),
+            Ok(None) => Ok(Vec::new()),
+            Err(e) => {
+                error!("Failed to get prompt templates from NATS KV: {}", e);
+                Err(anyhow!(
+                    "Failed to get prompt templates from NATS KV: {}",
+                    e
+                ))
+            }
+        }
+    }
+
     async fn store_app(&self, app: &MCPApp) -> Result<()> {
         let store = self.get_apps_store().await?;
         let data =
@@ -742,6 +1068,493 @@ impl MCPStorage for NATSMCPStorage {
             }
         }
     }
+
+    async fn store_session(&self, session: &MCPSession) -> Result<()> {
+        let store = self.get_sessions_store().await?;
+        let data = serde_json::to_vec(session)
+            .map_err(|e| anyhow!("Failed to serialize MCP session: {}", e))?;
+
+        store
+            .put(session_key(&session.session_id), data.into())
+            .await
+            .map_err(|e| anyhow!("Failed to store MCP session in NATS KV: {}", e))?;
+
+        debug!("Stored MCP session in NATS KV: {}", session.session_id);
+        Ok(())
+    }
+
+    async fn get_session(&self, session_id: &str) -> Result<Option<MCPSession>> {
+        let store = self.get_sessions_store().await?;
+
+        match store.get(session_key(session_id)).await {
+            Ok(Some(entry)) => serde_json::from_slice(entry.as_ref())
+                .map(Some)
+                .map_err(|e| anyhow!("Failed to deserialize MCP session: {}", e)),
+            Ok(None) => Ok(None),
+            Err(e) => {
+                error!("Failed to get MCP session from NATS KV: {}", e);
+                Err(anyhow!("Failed to get MCP session from NATS KV: {}", e))
+            }
+        }
+    }
+
+    async fn delete_session(&self, session_id: &str) -> Result<bool> {
+        let store = self.get_sessions_store().await?;
+
+        match store.delete(session_key(session_id)).await {
+            Ok(_) => {
+                debug!("Deleted MCP session from NATS KV: {}", session_id);
+                Ok(true)
+            }
+            Err(e) => {
+                error!("Failed to delete MCP session from NATS KV: {}", e);
+                Ok(false)
+            }
+        }
+    }
+
+    async fn store_signing_keys(&self, keys: &[StoredSigningKey]) -> Result<()> {
+        let store = self.get_signing_keys_store().await?;
+        let data = serde_json::to_vec(keys)
+            .map_err(|e| anyhow!("Failed to serialize MCP signing keys: {}", e))?;
+
+        store
+            .put(SIGNING_KEYS_KEY, data.into())
+            .await
+            .map_err(|e| anyhow!("Failed to store MCP signing keys in NATS KV: {}", e))?;
+        Ok(())
+    }
+
+    async fn get_signing_keys(&self) -> Result<Vec<StoredSigningKey>> {
+        let store = self.get_signing_keys_store().await?;
+
+        match store.get(SIGNING_KEYS_KEY).await {
+            Ok(Some(entry)) => serde_json::from_slice(entry.as_ref())
+                .map_err(|e| anyhow!("Failed to deserialize MCP signing keys: {}", e)),
+            Ok(None) => Ok(Vec::new()),
+            Err(e) => Err(anyhow!(
+                "Failed to get MCP signing keys from NATS KV: {}",
+                e
+            )),
+        }
+    }
+
+    async fn store_oauth_flow(&self, state: &str, flow: &OAuthAuthRequest) -> Result<()> {
+        let store = self.get_oauth_flows_store().await?;
+        let data = serde_json::to_vec(flow)
+            .map_err(|e| anyhow!("Failed to serialize OAuth flow: {}", e))?;
+
+        // States are UUIDs, but hashing keeps keys valid whatever a client sends back
+        store
+            .put(session_key(state), data.into())
+            .await
+            .map_err(|e| anyhow!("Failed to store OAuth flow in NATS KV: {}", e))?;
+
+        debug!("Stored OAuth flow in NATS KV");
+        Ok(())
+    }
+
+    async fn get_oauth_flow(&self, state: &str) -> Result<Option<OAuthAuthRequest>> {
+        let store = self.get_oauth_flows_store().await?;
+
+        match store.get(session_key(state)).await {
+            Ok(Some(entry)) => serde_json::from_slice(entry.as_ref())
+                .map(Some)
+                .map_err(|e| anyhow!("Failed to deserialize OAuth flow: {}", e)),
+            Ok(None) => Ok(None),
+            Err(e) => {
+                error!("Failed to get OAuth flow from NATS KV: {}", e);
+                Err(anyhow!("Failed to get OAuth flow from NATS KV: {}", e))
+            }
+        }
+    }
+
+    async fn delete_oauth_flow(&self, state: &str) -> Result<bool> {
+        let store = self.get_oauth_flows_store().await?;
+
+        match store.delete(session_key(state)).await {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                error!("Failed to delete OAuth flow from NATS KV: {}", e);
+                Ok(false)
+            }
+        }
+    }
+
+    async fn store_client_registration(&self, registration: &MCPClientRegistration) -> Result<()> {
+        let store = self.get_client_registrations_store().await?;
+        let data = serde_json::to_vec(registration)
+            .map_err(|e| anyhow!("Failed to serialize client registration: {}", e))?;
+
+        store
+            .put(&registration.client_id, data.into())
+            .await
+            .map_err(|e| anyhow!("Failed to store client registration in NATS KV: {}", e))?;
+
+        debug!(
+            "Stored client registration {} in NATS KV",
+            registration.client_id
+        );
+        Ok(())
+    }
+
+    async fn get_client_registration(
+        &self,
+        client_id: &str,
+    ) -> Result<Option<MCPClientRegistration>> {
+        let store = self.get_client_registrations_store().await?;
+
+        // Client IDs arrive in request paths; anything but an issued UUID is unknown
+        if uuid::Uuid::parse_str(client_id).is_err() {
+            return Ok(None);
+        }
+        match store.get(client_id).await {
+            Ok(Some(entry)) => serde_json::from_slice(entry.as_ref())
+                .map(Some)
+                .map_err(|e| anyhow!("Failed to deserialize client registration: {}", e)),
+            Ok(None) => Ok(None),
+            Err(e) => {
+                error!("Failed to get client registration from NATS KV: {}", e);
+                Err(anyhow!(
+                    "Failed to get client registration from NATS KV: {}",
+                    e
+                ))
+            }
+        }
+    }
+
+    async fn delete_client_registration(&self, client_id: &str) -> Result<bool> {
+        let store = self.get_client_registrations_store().await?;
+
+        if uuid::Uuid::parse_str(client_id).is_err() {
+            return Ok(false);
+        }
+        match store.delete(client_id).await {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                error!("Failed to delete client registration from NATS KV: {}", e);
+                Ok(false)
+            }
+        }
+    }
+}
+
+/// Postgres implementation of MCPStorage
+///
+/// Every kind of record shares the `mcp_records` table created by the Postgres
+/// migrations, keyed by kind and key. Sessions and OAuth flows expire after the same
+/// ages as their NATS buckets: older ones are no longer read and are pruned as new ones
+/// are stored.
+#[derive(Debug, Clone)]
+pub struct PostgresMCPStorage {
+    pool: sqlx::PgPool,
+}
+
+const INSTANCE_RECORD: &str = "instance";
+const OAUTH_CONFIG_RECORD: &str = "oauth_config";
+const PROMPT_TEMPLATES_RECORD: &str = "prompt_templates";
+const APP_RECORD: &str = "app";
+const INSTALLATION_RECORD: &str = "installation";
+const OAUTH_TOKEN_RECORD: &str = "oauth_token";
+const SESSION_RECORD: &str = "session";
+const OAUTH_FLOW_RECORD: &str = "oauth_flow";
+const CLIENT_REGISTRATION_RECORD: &str = "client_registration";
+const SIGNING_KEYS_RECORD: &str = "signing_keys";
+
+impl PostgresMCPStorage {
+    /// Connect to Postgres and create the MCP table if needed
+    pub async fn connect(database_url: &str) -> Result<Self> {
+        let pool = crate::engine::postgres_storage::connect(database_url).await?;
+        info!("Postgres MCP storage initialized successfully");
+        Ok(Self::new(pool))
+    }
+
+    /// Store MCP records through an existing, migrated connection pool
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self { pool }
+    }
+
+    async fn put<T: serde::Serialize + Sync>(
+        &self,
+        kind: &str,
+        key: &str,
+        record: &T,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO mcp_records (kind, key, record) VALUES ($1, $2, $3)
+             ON CONFLICT (kind, key) DO UPDATE SET record = EXCLUDED.record, stored_at = now()",
+        )
+        .bind(kind)
+        .bind(key)
+        .bind(sqlx::types::Json(record))
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to store MCP {} in Postgres: {}", kind, e))?;
+        Ok(())
+    }
+
+    /// Store a record that expires `max_age` after it was stored, pruning expired ones
+    async fn put_expiring<T: serde::Serialize + Sync>(
+        &self,
+        kind: &str,
+        key: &str,
+        record: &T,
+        max_age: std::time::Duration,
+    ) -> Result<()> {
+        sqlx::query(
+            "DELETE FROM mcp_records
+             WHERE kind = $1 AND stored_at <= now() - $2 * interval '1 second'",
+        )
+        .bind(kind)
+        .bind(max_age.as_secs_f64())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to prune expired MCP {} records: {}", kind, e))?;
+        self.put(kind, key, record).await
+    }
+
+    async fn get<T: serde::de::DeserializeOwned + Send + Unpin + 'static>(
+        &self,
+        kind: &str,
+        key: &str,
+        max_age: Option<std::time::Duration>,
+    ) -> Result<Option<T>> {
+        let row: Option<(sqlx::types::Json<T>,)> = sqlx::query_as(
+            "SELECT record FROM mcp_records
+             WHERE kind = $1 AND key = $2
+               AND ($3::float8 IS NULL OR stored_at > now() - $3 * interval '1 second')",
+        )
+        .bind(kind)
+        .bind(key)
+        .bind(max_age.map(|max_age| max_age.as_secs_f64()))
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to get MCP {} from Postgres: {}", kind, e))?;
+        Ok(row.map(|(sqlx::types::Json(record),)| record))
+    }
+
+    async fn list<T: serde::de::DeserializeOwned + Send + Unpin + 'static>(
+        &self,
+        kind: &str,
+    ) -> Result<Vec<(String, T)>> {
+        let rows: Vec<(String, sqlx::types::Json<T>)> =
+            sqlx::query_as("SELECT key, record FROM mcp_records WHERE kind = $1")
+                .bind(kind)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| anyhow!("Failed to list MCP {} records from Postgres: {}", kind, e))?;
+        Ok(rows
+            .into_iter()
+            .map(|(key, sqlx::types::Json(record))| (key, record))
+            .collect())
+    }
+
+    async fn delete(&self, kind: &str, key: &str) -> Result<bool> {
+        let deleted = sqlx::query("DELETE FROM mcp_records WHERE kind = $1 AND key = $2")
+            .bind(kind)
+            .bind(key)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to delete MCP {} from Postgres: {}", kind, e))?;
+        Ok(deleted.rows_affected() > 0)
+    }
+}
+
+#[async_trait]
+impl MCPStorage for PostgresMCPStorage {
+    async fn store_server_instance(&self, instance: &MCPServerInstance) -> Result<()> {
+        self.put(INSTANCE_RECORD, &instance.instance_id, instance)
+            .await?;
+        info!("Stored MCP instance in Postgres: {}", instance.instance_id);
+        Ok(())
+    }
+
+    async fn get_server_instance(&self, instance_id: &str) -> Result<Option<MCPServerInstance>> {
+        self.get(INSTANCE_RECORD, instance_id, None).await
+    }
+
+    async fn list_server_instances(&self) -> Result<Vec<MCPServerInstance>> {
+        let instances = self.list(INSTANCE_RECORD).await?;
+        Ok(instances
+            .into_iter()
+            .map(|(_, instance)| instance)
+            .collect())
+    }
+
+    async fn delete_server_instance(&self, instance_id: &str) -> Result<bool> {
+        self.delete(INSTANCE_RECORD, instance_id).await
+    }
+
+    async fn store_oauth_config(
+        &self,
+        instance_id: &str,
+        config: &RemoteOAuthConfig,
+    ) -> Result<()> {
+        self.put(OAUTH_CONFIG_RECORD, instance_id, config).await
+    }
+
+    async fn get_oauth_config(&self, instance_id: &str) -> Result<Option<RemoteOAuthConfig>> {
+        self.get(OAUTH_CONFIG_RECORD, instance_id, None).await
+    }
+
+    async fn delete_oauth_config(&self, instance_id: &str) -> Result<bool> {
+        self.delete(OAUTH_CONFIG_RECORD, instance_id).await
+    }
+
+    async fn store_prompt_templates(
+        &self,
+        instance_id: &str,
+        templates: &[MCPPromptTemplate],
+    ) -> Result<()> {
+        self.put(PROMPT_TEMPLATES_RECORD, instance_id, &templates)
+            .await
+    }
+
+    async fn get_prompt_templates(&self, instance_id: &str) -> Result<Vec<MCPPromptTemplate>> {
+        Ok(self
+            .get(PROMPT_TEMPLATES_RECORD, instance_id, None)
+            .await?
+            .unwrap_or_default())
+    }
+
+    async fn store_app(&self, app: &MCPApp) -> Result<()> {
+        self.put(APP_RECORD, &app.app_id, app).await
+    }
+
+    async fn get_app(&self, app_id: &str) -> Result<Option<MCPApp>> {
+        self.get(APP_RECORD, app_id, None).await
+    }
+
+    async fn list_apps(&self) -> Result<Vec<MCPApp>> {
+        let apps = self.list(APP_RECORD).await?;
+        Ok(apps.into_iter().map(|(_, app)| app).collect())
+    }
+
+    async fn delete_app(&self, app_id: &str) -> Result<bool> {
+        self.delete(APP_RECORD, app_id).await
+    }
+
+    async fn store_installation(&self, installation: &MCPInstallation) -> Result<()> {
+        self.put(
+            INSTALLATION_RECORD,
+            &installation.installation_id,
+            installation,
+        )
+        .await
+    }
+
+    async fn get_installation(&self, installation_id: &str) -> Result<Option<MCPInstallation>> {
+        self.get(INSTALLATION_RECORD, installation_id, None).await
+    }
+
+    async fn list_installations(&self) -> Result<Vec<MCPInstallation>> {
+        let installations = self.list(INSTALLATION_RECORD).await?;
+        Ok(installations
+            .into_iter()
+            .map(|(_, installation)| installation)
+            .collect())
+    }
+
+    async fn delete_installation(&self, installation_id: &str) -> Result<bool> {
+        self.delete(INSTALLATION_RECORD, installation_id).await
+    }
+
+    async fn store_oauth_token(&self, token_key: &str, token: &StoredOAuthToken) -> Result<()> {
+        self.put(OAUTH_TOKEN_RECORD, token_key, token).await?;
+        debug!("Stored OAuth token in Postgres for key: {}", token_key);
+        Ok(())
+    }
+
+    async fn get_oauth_token(&self, token_key: &str) -> Result<Option<StoredOAuthToken>> {
+        self.get(OAUTH_TOKEN_RECORD, token_key, None).await
+    }
+
+    async fn list_oauth_tokens(&self) -> Result<Vec<(String, StoredOAuthToken)>> {
+        self.list(OAUTH_TOKEN_RECORD).await
+    }
+
+    async fn delete_oauth_token(&self, token_key: &str) -> Result<bool> {
+        self.delete(OAUTH_TOKEN_RECORD, token_key).await
+    }
+
+    async fn store_session(&self, session: &MCPSession) -> Result<()> {
+        self.put_expiring(
+            SESSION_RECORD,
+            &session_key(&session.session_id),
+            session,
+            SESSION_MAX_AGE,
+        )
+        .await?;
+        debug!("Stored MCP session in Postgres: {}", session.session_id);
+        Ok(())
+    }
+
+    async fn get_session(&self, session_id: &str) -> Result<Option<MCPSession>> {
+        self.get(
+            SESSION_RECORD,
+            &session_key(session_id),
+            Some(SESSION_MAX_AGE),
+        )
+        .await
+    }
+
+    async fn delete_session(&self, session_id: &str) -> Result<bool> {
+        self.delete(SESSION_RECORD, &session_key(session_id)).await
+    }
+
+    async fn store_signing_keys(&self, keys: &[StoredSigningKey]) -> Result<()> {
+        self.put(SIGNING_KEYS_RECORD, SIGNING_KEYS_KEY, &keys).await
+    }
+
+    async fn get_signing_keys(&self) -> Result<Vec<StoredSigningKey>> {
+        Ok(self
+            .get(SIGNING_KEYS_RECORD, SIGNING_KEYS_KEY, None)
+            .await?
+            .unwrap_or_default())
+    }
+
+    async fn store_oauth_flow(&self, state: &str, flow: &OAuthAuthRequest) -> Result<()> {
+        self.put_expiring(
+            OAUTH_FLOW_RECORD,
+            &session_key(state),
+            flow,
+            OAUTH_FLOW_MAX_AGE,
+        )
+        .await
+    }
+
+    async fn get_oauth_flow(&self, state: &str) -> Result<Option<OAuthAuthRequest>> {
+        self.get(
+            OAUTH_FLOW_RECORD,
+            &session_key(state),
+            Some(OAUTH_FLOW_MAX_AGE),
+        )
+        .await
+    }
+
+    async fn delete_oauth_flow(&self, state: &str) -> Result<bool> {
+        self.delete(OAUTH_FLOW_RECORD, &session_key(state)).await
+    }
+
+    async fn store_client_registration(&self, registration: &MCPClientRegistration) -> Result<()> {
+        self.put(
+            CLIENT_REGISTRATION_RECORD,
+            &registration.client_id,
+            registration,
+        )
+        .await
+    }
+
+    async fn get_client_registration(
+        &self,
+        client_id: &str,
+    ) -> Result<Option<MCPClientRegistration>> {
+        self.get(CLIENT_REGISTRATION_RECORD, client_id, None).await
+    }
+
+    async fn delete_client_registration(&self, client_id: &str) -> Result<bool> {
+        self.delete(CLIENT_REGISTRATION_RECORD, client_id).await
+    }
 }
 
 #[cfg(test)]
@@ -764,6 +1577,8 @@ mod tests {
             },
             project_contexts: vec!["project1".to_string(), "project2".to_string()],
             app_type: MCPApplicationType::Local,
+            tenant_id: None,
+            tool_policy: Default::default(),
             created_at: chrono::Utc::now(),
             last_activity: chrono::Utc::now(),
             status: MCPServerStatus::Active,
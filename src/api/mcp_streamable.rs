@@ -0,0 +1,323 @@
+// Streamable HTTP transport sessions for the MCP server
+// One endpoint per instance takes JSON-RPC over POST, streams server messages
+// over GET and ends the session on DELETE. Server messages are kept per session
+// so a client can resume a dropped stream with Last-Event-ID.
+
+use axum::response::sse::Event;
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::Infallible,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::{mpsc, RwLock};
+use tracing::{debug, info};
+
+use super::mcp_logging::MCPLogger;
+
+/// Header carrying the session assigned on initialize
+pub const SESSION_ID_HEADER: &str = "mcp-session-id";
+
+/// Header a reconnecting client sends with the last event it received
+pub const LAST_EVENT_ID_HEADER: &str = "last-event-id";
+
+/// Protocol versions served over the streamable transport, newest first
+pub const STREAMABLE_PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26"];
+
+/// Idle time after which a session without an open stream is dropped
+pub const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// Server messages kept per session for replay on resume
+const REPLAY_BUFFER_SIZE: usize = 256;
+
+pub type StreamSender = mpsc::UnboundedSender<Result<Event, Infallible>>;
+pub type StreamReceiver = mpsc::UnboundedReceiver<Result<Event, Infallible>>;
+
+struct StreamableSession {
+    instance_id: String,
+    last_activity: Instant,
+    next_event_id: u64,
+    // (event id, JSON-RPC message) pairs, oldest first
+    replay: VecDeque<(u64, String)>,
+    stream: Option<StreamSender>,
+    // Log notifications end up on the stream like any other server message
+    logger: MCPLogger,
+}
+
+impl StreamableSession {
+    fn has_open_stream(&self) -> bool {
+        self.stream
+            .as_ref()
+            .is_some_and(|stream| !stream.is_closed())
+    }
+}
+
+/// Sessions of the streamable HTTP transport, keyed by session ID
+#[derive(Clone, Default)]
+pub struct StreamableSessions {
+    sessions: Arc<RwLock<HashMap<String, StreamableSession>>>,
+}
+
+impl StreamableSessions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a session for an instance and return its ID
+    pub async fn create(&self, instance_id: &str) -> String {
+        let session_id = uuid::Uuid::new_v4().to_string();
+        let (logger, mut notifications) = MCPLogger::channel();
+        self.sessions.write().await.insert(
+            session_id.clone(),
+            StreamableSession {
+                instance_id: instance_id.to_string(),
+                last_activity: Instant::now(),
+                next_event_id: 1,
+                replay: VecDeque::new(),
+                stream: None,
+                logger,
+            },
+        );
+
+        // Runs until the session ends and drops its logger
+        let sessions = self.clone();
+        let id = session_id.clone();
+        tokio::spawn(async move {
+            while let Some(notification) = notifications.recv().await {
+                if !sessions.send(&id, &notification).await {
+                    break;
+                }
+            }
+        });
+        info!(
+            "Created streamable HTTP session {} for instance: {}",
+            session_id, instance_id
+        );
+        session_id
+    }
+
+    /// Record activity on a session; false when the instance has no such session
+    pub async fn touch(&self, session_id: &str, instance_id: &str) -> bool {
+        match self.sessions.write().await.get_mut(session_id) {
+            Some(session) if session.instance_id == instance_id => {
+                session.last_activity = Instant::now();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Logger sending `notifications/message` down a session's stream
+    pub async fn logger(&self, session_id: &str) -> Option<MCPLogger> {
+        let sessions = self.sessions.read().await;
+        sessions
+            .get(session_id)
+            .map(|session| session.logger.clone())
+    }
+
+    /// End a session, closing its stream; false when the instance has no such session
+    pub async fn remove(&self, session_id: &str, instance_id: &str) -> bool {
+        let mut sessions = self.sessions.write().await;
+        if sessions
+            .get(session_id)
+            .is_some_and(|session| session.instance_id == instance_id)
+        {
+            sessions.remove(session_id);
+            info!("Terminated streamable HTTP session: {}", session_id);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Open the server message stream of a session, replacing any earlier one.
+    /// Messages after `last_event_id` are replayed first when resuming.
+    pub async fn open_stream(
+        &self,
+        session_id: &str,
+        instance_id: &str,
+        last_event_id: Option<u64>,
+    ) -> Option<StreamReceiver> {
+        let mut sessions = self.sessions.write().await;
+        let session = sessions
+            .get_mut(session_id)
+            .filter(|session| session.instance_id == instance_id)?;
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        if let Some(last_event_id) = last_event_id {
+            for (id, data) in session.replay.iter().filter(|(id, _)| *id > last_event_id) {
+                let _ = tx.send(Ok(Event::default().id(id.to_string()).data(data)));
+            }
+        }
+        session.stream = Some(tx);
+        session.last_activity = Instant::now();
+        Some(rx)
+    }
+
+    /// Queue a server message on a session and deliver it if a stream is open.
+    /// Returns false when the session does not exist.
+    pub async fn send(&self, session_id: &str, message: &serde_json::Value) -> bool {
+        let mut sessions = self.sessions.write().await;
+        let Some(session) = sessions.get_mut(session_id) else {
+            return false;
+        };
+
+        let id = session.next_event_id;
+        session.next_event_id += 1;
+        let data = message.to_string();
+        if let Some(stream) = &session.stream {
+            let event = Event::default().id(id.to_string()).data(&data);
+            if stream.send(Ok(event)).is_err() {
+                session.stream = None;
+            }
+        }
+        session.replay.push_back((id, data));
+        if session.replay.len() > REPLAY_BUFFER_SIZE {
+            session.replay.pop_front();
+        }
+        true
+    }
+
+    /// Drop sessions without an open stream that were idle for longer than `idle_timeout`
+    pub async fn reap_idle(&self, idle_timeout: Duration) -> usize {
+        let mut sessions = self.sessions.write().await;
+        let before = sessions.len();
+        sessions.retain(|session_id, session| {
+            let alive =
+                session.has_open_stream() || session.last_activity.elapsed() <= idle_timeout;
+            if !alive {
+                debug!("Reaping idle streamable HTTP session: {}", session_id);
+            }
+            alive
+        });
+        before - sessions.len()
+    }
+
+    /// Number of live sessions
+    pub async fn session_count(&self) -> usize {
+        self.sessions.read().await.len()
+    }
+
+    /// Number of sessions with an open server message stream
+    pub async fn open_stream_count(&self) -> usize {
+        self.sessions
+            .read()
+            .await
+            .values()
+            .filter(|session| session.has_open_stream())
+            .count()
+    }
+}
+
+/// The protocol version to answer an initialize with over the streamable transport:
+/// the client's when supported, the newest otherwise
+pub fn negotiate_protocol_version(params: Option<&serde_json::Value>) -> &'static str {
+    let requested = params
+        .and_then(|params| params.get("protocolVersion"))
+        .and_then(|version| version.as_str());
+    STREAMABLE_PROTOCOL_VERSIONS
+        .iter()
+        .find(|version| Some(**version) == requested)
+        .unwrap_or(&STREAMABLE_PROTOCOL_VERSIONS[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_sessions_are_scoped_to_instance() {
+        let sessions = StreamableSessions::new();
+        let session_id = sessions.create("instance-a").await;
+
+        assert!(sessions.touch(&session_id, "instance-a").await);
+        assert!(!sessions.touch(&session_id, "instance-b").await);
+        assert!(sessions
+            .open_stream(&session_id, "instance-b", None)
+            .await
+            .is_none());
+        assert!(!sessions.remove(&session_id, "instance-b").await);
+
+        assert!(sessions.remove(&session_id, "instance-a").await);
+        assert!(!sessions.touch(&session_id, "instance-a").await);
+        assert!(!sessions.send(&session_id, &json!({})).await);
+    }
+
+    #[tokio::test]
+    async fn test_resume_replays_missed_messages() {
+        let sessions = StreamableSessions::new();
+        let session_id = sessions.create("instance").await;
+
+        // Messages sent before any stream is open are kept for replay
+        for n in 1..=3 {
+            assert!(sessions.send(&session_id, &json!({ "n": n })).await);
+        }
+        let mut first = sessions
+            .open_stream(&session_id, "instance", None)
+            .await
+            .unwrap();
+        assert!(first.try_recv().is_err());
+
+        assert!(sessions.send(&session_id, &json!({ "n": 4 })).await);
+        assert!(first.try_recv().is_ok());
+
+        // Reconnecting replaces the stream and replays everything after event 2
+        let mut resumed = sessions
+            .open_stream(&session_id, "instance", Some(2))
+            .await
+            .unwrap();
+        let mut replayed = 0;
+        while resumed.try_recv().is_ok() {
+            replayed += 1;
+        }
+        assert_eq!(replayed, 2);
+        assert!(first.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reap_keeps_sessions_with_open_streams() {
+        let sessions = StreamableSessions::new();
+        let idle = sessions.create("instance").await;
+        let streaming = sessions.create("instance").await;
+        let _stream = sessions
+            .open_stream(&streaming, "instance", None)
+            .await
+            .unwrap();
+
+        assert_eq!(sessions.reap_idle(Duration::ZERO).await, 1);
+        assert!(!sessions.touch(&idle, "instance").await);
+        assert!(sessions.touch(&streaming, "instance").await);
+        assert_eq!(sessions.open_stream_count().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_logger_writes_to_session_stream() {
+        let sessions = StreamableSessions::new();
+        let session_id = sessions.create("instance").await;
+        let mut stream = sessions
+            .open_stream(&session_id, "instance", None)
+            .await
+            .unwrap();
+
+        let logger = sessions.logger(&session_id).await.unwrap();
+        logger.log(crate::api::mcp_logging::MCPLogLevel::Info, "tools", "hello");
+        assert!(tokio::time::timeout(Duration::from_secs(1), stream.recv())
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn test_negotiate_protocol_version() {
+        assert_eq!(
+            negotiate_protocol_version(Some(&json!({"protocolVersion": "2025-03-26"}))),
+            "2025-03-26"
+        );
+        assert_eq!(
+            negotiate_protocol_version(Some(&json!({"protocolVersion": "2024-11-05"}))),
+            "2025-06-18"
+        );
+        assert_eq!(negotiate_protocol_version(None), "2025-06-18");
+    }
+}
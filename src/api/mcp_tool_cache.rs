@@ -0,0 +1,200 @@
+// MCP tool result cache for the Circuit Breaker MCP server
+// Reuses results of read-only tool calls so repeated calls spare the provider APIs
+
+//! # MCP Tool Result Cache
+//!
+//! Results of read-only tools are cached per instance, keyed by tool name and
+//! arguments, for the cache's TTL. Error results are never cached. A call to any tool
+//! that may write drops every result cached for its instance, so a client reading
+//! after a write sees the change. A TTL of zero turns caching off.
+
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+use super::mcp_types::MCPToolResult;
+
+/// How long results are reused unless configured otherwise
+pub const DEFAULT_TOOL_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Results kept per instance; the oldest go first beyond this
+const MAX_ENTRIES_PER_INSTANCE: usize = 256;
+
+// When the result was stored, and the result
+type CacheEntry = (Instant, MCPToolResult);
+
+/// Cached tool results by instance, then by tool call
+#[derive(Clone)]
+pub struct ToolResultCache {
+    ttl: Duration,
+    entries: Arc<RwLock<HashMap<String, HashMap<String, CacheEntry>>>>,
+}
+
+impl Default for ToolResultCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_TOOL_CACHE_TTL)
+    }
+}
+
+impl ToolResultCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.ttl.is_zero()
+    }
+
+    /// A fresh result of the same call on the instance
+    pub async fn get(
+        &self,
+        instance_id: &str,
+        tool: &str,
+        arguments: &HashMap<String, Value>,
+    ) -> Option<MCPToolResult> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let entries = self.entries.read().await;
+        entries
+            .get(instance_id)?
+            .get(&cache_key(tool, arguments))
+            .filter(|(stored, _)| stored.elapsed() < self.ttl)
+            .map(|(_, result)| result.clone())
+    }
+
+    /// Keep a successful result of a read-only call
+    pub async fn insert(
+        &self,
+        instance_id: &str,
+        tool: &str,
+        arguments: &HashMap<String, Value>,
+        result: &MCPToolResult,
+    ) {
+        if !self.is_enabled() || result.is_error == Some(true) {
+            return;
+        }
+        let mut entries = self.entries.write().await;
+        let instance = entries.entry(instance_id.to_string()).or_default();
+        instance.retain(|_, (stored, _)| stored.elapsed() < self.ttl);
+        if instance.len() >= MAX_ENTRIES_PER_INSTANCE {
+            let oldest = instance
+                .iter()
+                .min_by_key(|(_, (stored, _))| *stored)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                instance.remove(&oldest);
+            }
+        }
+        instance.insert(cache_key(tool, arguments), (Instant::now(), result.clone()));
+    }
+
+    /// Drop every result cached for an instance, e.g. after a write tool ran
+    pub async fn invalidate(&self, instance_id: &str) {
+        self.entries.write().await.remove(instance_id);
+    }
+
+    /// Number of results cached for an instance, fresh or not
+    pub async fn len(&self, instance_id: &str) -> usize {
+        self.entries
+            .read()
+            .await
+            .get(instance_id)
+            .map_or(0, HashMap::len)
+    }
+}
+
+// Arguments are sorted so the same call always maps to the same key
+fn cache_key(tool: &str, arguments: &HashMap<String, Value>) -> String {
+    let arguments: BTreeMap<&String, &Value> = arguments.iter().collect();
+    format!(
+        "{}:{}",
+        tool,
+        serde_json::to_string(&arguments).unwrap_or_default()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::mcp_types::MCPContent;
+
+    fn result(text: &str, is_error: bool) -> MCPToolResult {
+        MCPToolResult {
+            content: vec![MCPContent::text(text.to_string())],
+            is_error: Some(is_error),
+        }
+    }
+
+    fn arguments(pairs: &[(&str, &str)]) -> HashMap<String, Value> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), Value::from(*value)))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_cache_is_keyed_by_tool_and_arguments() {
+        let cache = ToolResultCache::default();
+        let args = arguments(&[("project_id", "1"), ("state", "opened")]);
+        cache
+            .insert("instance", "list_issues", &args, &result("issues", false))
+            .await;
+
+        assert!(cache.get("instance", "list_issues", &args).await.is_some());
+        assert!(cache.get("other", "list_issues", &args).await.is_none());
+        assert!(cache.get("instance", "get_issue", &args).await.is_none());
+        assert!(cache
+            .get(
+                "instance",
+                "list_issues",
+                &arguments(&[("project_id", "2")])
+            )
+            .await
+            .is_none());
+
+        cache.invalidate("instance").await;
+        assert!(cache.get("instance", "list_issues", &args).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_errors_and_disabled_cache_store_nothing() {
+        let cache = ToolResultCache::default();
+        let args = arguments(&[]);
+        cache
+            .insert("instance", "get_user", &args, &result("boom", true))
+            .await;
+        assert_eq!(cache.len("instance").await, 0);
+
+        let disabled = ToolResultCache::new(Duration::ZERO);
+        disabled
+            .insert("instance", "get_user", &args, &result("user", false))
+            .await;
+        assert!(disabled.get("instance", "get_user", &args).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cache_evicts_oldest_beyond_limit() {
+        let cache = ToolResultCache::default();
+        for n in 0..=MAX_ENTRIES_PER_INSTANCE {
+            let args = arguments(&[("n", &n.to_string())]);
+            cache
+                .insert("instance", "get_file", &args, &result("file", false))
+                .await;
+        }
+        assert_eq!(cache.len("instance").await, MAX_ENTRIES_PER_INSTANCE);
+        assert!(cache
+            .get("instance", "get_file", &arguments(&[("n", "0")]))
+            .await
+            .is_none());
+    }
+}
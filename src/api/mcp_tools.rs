@@ -0,0 +1,1807 @@
+// MCP tool registry for the Circuit Breaker MCP server
+// Resolves tool calls to registered handlers and validates their arguments
+
+//! # MCP Tools
+//!
+//! Every tool an instance offers is an [`MCPToolHandler`] in the manager's
+//! [`MCPToolRegistry`]. Tools are registered either for a tool set, shared by every
+//! instance of one kind, or for a single instance:
+//!
+//! - [`LOCAL_TOOLSET`] for local instances
+//! - the OAuth provider of remote instances, e.g. `gitlab`, `github` or `atlassian`
+//! - [`REMOTE_TOOLSET`] for remote instances whose provider has no tool set
+//!
+//! An instance's own tools replace tool set tools of the same name. Arguments are
+//! validated against the tool's `input_schema` before its handler runs, so handlers
+//! can rely on required arguments being present and well-typed. The built-in tools
+//! register themselves through [`MCPToolRegistry::with_builtin_tools`]; custom tools
+//! are added with [`MCPServerManager::register_tool`].
+
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use super::mcp_logging::{MCPLogLevel, MCPLogger};
+use super::mcp_server::MCPServerManager;
+use super::mcp_types::{
+    MCPApplicationType, MCPContent, MCPServerInstance, MCPTool, MCPToolCall, MCPToolResult,
+};
+use super::oauth::OAuthProviderType;
+use crate::llm::structured;
+
+/// Tool set of local instances
+pub const LOCAL_TOOLSET: &str = "local";
+/// Tool set of remote instances whose provider has none of its own
+pub const REMOTE_TOOLSET: &str = "remote";
+
+/// Result of a tool call
+pub type MCPToolCallResult = Result<MCPToolResult, Box<dyn std::error::Error + Send + Sync>>;
+
+/// What a tool handler can reach while it runs
+pub struct MCPToolContext<'a> {
+    pub manager: &'a MCPServerManager,
+    pub instance: &'a MCPServerInstance,
+    /// Logger of the calling client's session, when its transport has one
+    pub logger: Option<&'a MCPLogger>,
+}
+
+impl MCPToolContext<'_> {
+    /// Log a tool execution step to the calling client
+    pub fn log(&self, level: MCPLogLevel, data: impl Into<Value>) {
+        if let Some(logger) = self.logger {
+            logger.log(level, "tools", data);
+        }
+    }
+}
+
+/// A tool MCP clients can call
+#[async_trait]
+pub trait MCPToolHandler: Send + Sync {
+    /// Name, description and argument schema of the tool
+    fn definition(&self) -> MCPTool;
+
+    /// Whether the tool only reads; read-only tool policies hide every other tool
+    fn read_only(&self) -> bool {
+        false
+    }
+
+    /// Run the tool with arguments that match its schema
+    async fn call(
+        &self,
+        context: &MCPToolContext<'_>,
+        arguments: &HashMap<String, Value>,
+    ) -> MCPToolCallResult;
+}
+
+/// Tool handlers by tool set and by instance
+#[derive(Clone, Default)]
+pub struct MCPToolRegistry {
+    toolsets: HashMap<String, Vec<Arc<dyn MCPToolHandler>>>,
+    instances: HashMap<String, Vec<Arc<dyn MCPToolHandler>>>,
+}
+
+impl MCPToolRegistry {
+    /// Registry with the built-in local, GitLab, GitHub, Google, Jira and Slack tools
+    pub fn with_builtin_tools() -> Self {
+        let mut registry = Self::default();
+        let builtin = [
+            (LOCAL_TOOLSET, local_tools(), Builtin::Local),
+            ("gitlab", gitlab_tools(), Builtin::GitLab),
+            ("github", github_tools(), Builtin::GitHub),
+            ("google", google_tools(), Builtin::Google),
+            ("atlassian", jira_tools(), Builtin::Jira),
+            ("slack", slack_tools(), Builtin::Slack),
+            (REMOTE_TOOLSET, generic_remote_tools(), Builtin::Unavailable),
+        ];
+        for (toolset, tools, kind) in builtin {
+            for definition in tools {
+                registry.register_toolset_tool(toolset, Arc::new(BuiltinTool { definition, kind }));
+            }
+        }
+        registry
+    }
+
+    /// Offer a tool to every instance of a tool set, replacing one of the same name
+    pub fn register_toolset_tool(&mut self, toolset: &str, handler: Arc<dyn MCPToolHandler>) {
+        register(
+            self.toolsets.entry(toolset.to_string()).or_default(),
+            handler,
+        );
+    }
+
+    /// Offer a tool to a single instance, replacing one of the same name
+    pub fn register_instance_tool(&mut self, instance_id: &str, handler: Arc<dyn MCPToolHandler>) {
+        register(
+            self.instances.entry(instance_id.to_string()).or_default(),
+            handler,
+        );
+    }
+
+    /// Withdraw a tool registered for a single instance, returning whether it existed
+    pub fn unregister_instance_tool(&mut self, instance_id: &str, name: &str) -> bool {
+        let Some(handlers) = self.instances.get_mut(instance_id) else {
+            return false;
+        };
+        let count = handlers.len();
+        handlers.retain(|handler| handler.definition().name != name);
+        handlers.len() != count
+    }
+
+    /// Handlers of the tools `instance` offers, its own tools last
+    pub fn handlers(&self, instance: &MCPServerInstance) -> Vec<Arc<dyn MCPToolHandler>> {
+        let own = self
+            .instances
+            .get(&instance.instance_id)
+            .cloned()
+            .unwrap_or_default();
+        let shared = self
+            .toolsets
+            .get(toolset(instance))
+            .or_else(|| match instance.app_type {
+                MCPApplicationType::Remote(_) => self.toolsets.get(REMOTE_TOOLSET),
+                MCPApplicationType::Local => None,
+            })
+            .cloned()
+            .unwrap_or_default();
+
+        let own_names: Vec<String> = own
+            .iter()
+            .map(|handler| handler.definition().name)
+            .collect();
+        shared
+            .into_iter()
+            .filter(|handler| !own_names.contains(&handler.definition().name))
+            .chain(own)
+            .collect()
+    }
+
+    /// Handler of the tool `name` offered by `instance`
+    pub fn handler(
+        &self,
+        instance: &MCPServerInstance,
+        name: &str,
+    ) -> Option<Arc<dyn MCPToolHandler>> {
+        self.handlers(instance)
+            .into_iter()
+            .rev()
+            .find(|handler| handler.definition().name == name)
+    }
+}
+
+fn register(handlers: &mut Vec<Arc<dyn MCPToolHandler>>, handler: Arc<dyn MCPToolHandler>) {
+    let name = handler.definition().name;
+    handlers.retain(|existing| existing.definition().name != name);
+    handlers.push(handler);
+}
+
+/// Tool set serving `instance`: [`LOCAL_TOOLSET`] or its OAuth provider
+pub fn toolset(instance: &MCPServerInstance) -> &str {
+    match &instance.app_type {
+        MCPApplicationType::Local => LOCAL_TOOLSET,
+        MCPApplicationType::Remote(oauth_config) => oauth_config.provider_type.as_str(),
+    }
+}
+
+/// Check tool call arguments against the tool's input schema
+pub fn validate_arguments(
+    tool: &MCPTool,
+    arguments: &HashMap<String, Value>,
+) -> Result<(), String> {
+    let arguments = Value::Object(arguments.clone().into_iter().collect());
+    let errors = structured::validate(&arguments, &tool.input_schema);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid arguments for tool '{}': {}",
+            tool.name,
+            errors.join("; ")
+        ))
+    }
+}
+
+/// Built-in tools that change nothing on the provider or in Circuit Breaker
+const READ_ONLY_BUILTIN_TOOLS: &[&str] = &[
+    "gitlab_search",
+    "gitlab_list_projects",
+    "gitlab_get_project",
+    "gitlab_list_issues",
+    "gitlab_list_merge_requests",
+    "gitlab_get_file",
+    "gitlab_list_pipelines",
+    "gitlab_get_user",
+    "github_list_repositories",
+    "github_get_repository",
+    "github_list_issues",
+    "github_list_pull_requests",
+    "github_get_file",
+    "github_search",
+    "github_list_workflow_runs",
+    "github_get_user",
+    "jira_search_issues",
+    "jira_get_issue",
+    "slack_list_channels",
+    "slack_search_messages",
+    "slack_get_thread",
+    "google_drive_list",
+    "google_drive_search",
+    "google_drive_get_file",
+    "google_docs_read",
+    "search_project_context",
+];
+
+/// How a built-in tool runs
+#[derive(Debug, Clone, Copy)]
+enum Builtin {
+    Local,
+    GitLab,
+    GitHub,
+    Google,
+    Jira,
+    Slack,
+    /// Listed, but the provider has no API the server knows how to call
+    Unavailable,
+}
+
+struct BuiltinTool {
+    definition: MCPTool,
+    kind: Builtin,
+}
+
+#[async_trait]
+impl MCPToolHandler for BuiltinTool {
+    fn definition(&self) -> MCPTool {
+        self.definition.clone()
+    }
+
+    fn read_only(&self) -> bool {
+        READ_ONLY_BUILTIN_TOOLS.contains(&self.definition.name.as_str())
+    }
+
+    async fn call(
+        &self,
+        context: &MCPToolContext<'_>,
+        arguments: &HashMap<String, Value>,
+    ) -> MCPToolCallResult {
+        let name = self.definition.name.as_str();
+        info!(
+            "Running {} for instance: {}",
+            name, context.instance.instance_id
+        );
+        match self.kind {
+            Builtin::Local => call_local_tool(context, name, arguments).await,
+            Builtin::GitLab => {
+                let tool_call = MCPToolCall {
+                    name: name.to_string(),
+                    arguments: arguments.clone(),
+                };
+                context
+                    .manager
+                    .execute_gitlab_tool(&tool_call, context.instance)
+                    .await
+            }
+            Builtin::GitHub => call_github_tool(context, name, arguments).await,
+            Builtin::Google => call_google_tool(context, name, arguments).await,
+            Builtin::Jira => call_jira_tool(context, name, arguments).await,
+            Builtin::Slack => call_slack_tool(context, name, arguments).await,
+            Builtin::Unavailable => Ok(MCPToolResult {
+                content: vec![MCPContent::text(format!(
+                    "Tool '{}' is not available: the provider's API is not known to the server",
+                    name
+                ))],
+                is_error: Some(true),
+            }),
+        }
+    }
+}
+
+async fn call_local_tool(
+    context: &MCPToolContext<'_>,
+    name: &str,
+    arguments: &HashMap<String, Value>,
+) -> MCPToolCallResult {
+    let instance = context.instance;
+    let text = |text: String, is_error| MCPToolResult {
+        content: vec![MCPContent::text(text)],
+        is_error: Some(is_error),
+    };
+    match name {
+        "create_workflow" => Ok(text(
+            format!(
+                "Workflow created successfully for instance {} (placeholder)",
+                instance.instance_id
+            ),
+            false,
+        )),
+        "execute_agent" => Ok(text(
+            format!(
+                "Agent executed successfully for instance {} (placeholder)",
+                instance.instance_id
+            ),
+            false,
+        )),
+        "search_project_context" => {
+            let argument = |key: &str| arguments.get(key).and_then(Value::as_str).unwrap_or("");
+            let (context_id, query) = (argument("context_id"), argument("query"));
+
+            // Check if the requested context is available to this instance
+            if !instance.project_contexts.iter().any(|id| id == context_id) {
+                return Ok(text(
+                    format!(
+                        "Access denied: Project context '{}' not available to this instance",
+                        context_id
+                    ),
+                    true,
+                ));
+            }
+
+            match context
+                .manager
+                .perform_context_search(instance, context_id, query)
+                .await
+            {
+                Ok(results) => Ok(text(
+                    format!(
+                        "Search results for '{}' in context {}: {}",
+                        query, context_id, results
+                    ),
+                    false,
+                )),
+                Err(e) => Ok(text(
+                    format!("Search failed for context {}: {}", context_id, e),
+                    true,
+                )),
+            }
+        }
+        _ => Err(format!("Unknown tool: {}", name).into()),
+    }
+}
+
+async fn call_github_tool(
+    context: &MCPToolContext<'_>,
+    name: &str,
+    arguments: &HashMap<String, Value>,
+) -> MCPToolCallResult {
+    let argument = |key: &str, default: &str| {
+        arguments
+            .get(key)
+            .and_then(Value::as_str)
+            .unwrap_or(default)
+            .to_string()
+    };
+    let per_page = arguments
+        .get("per_page")
+        .and_then(Value::as_u64)
+        .unwrap_or(30);
+    let repository = || {
+        format!(
+            "https://api.github.com/repos/{}/{}",
+            urlencoding::encode(&argument("owner", "")),
+            urlencoding::encode(&argument("repo", ""))
+        )
+    };
+
+    let (title, method, url, body) = match name {
+        "github_list_repositories" => (
+            "GitHub Repositories",
+            reqwest::Method::GET,
+            format!(
+                "https://api.github.com/user/repos?visibility={}&affiliation={}&per_page={}",
+                urlencoding::encode(&argument("visibility", "all")),
+                urlencoding::encode(&argument("affiliation", "owner")),
+                per_page
+            ),
+            None,
+        ),
+        "github_get_repository" => (
+            "GitHub Repository",
+            reqwest::Method::GET,
+            repository(),
+            None,
+        ),
+        "github_list_issues" => (
+            "GitHub Issues",
+            reqwest::Method::GET,
+            format!(
+                "{}/issues?state={}&per_page={}",
+                repository(),
+                urlencoding::encode(&argument("state", "open")),
+                per_page
+            ),
+            None,
+        ),
+        "github_create_issue" => {
+            let mut body = serde_json::json!({ "title": argument("title", "") });
+            for key in ["body", "labels", "assignees"] {
+                if let Some(value) = arguments.get(key) {
+                    body[key] = value.clone();
+                }
+            }
+            (
+                "Created GitHub Issue",
+                reqwest::Method::POST,
+                format!("{}/issues", repository()),
+                Some(body),
+            )
+        }
+        "github_list_pull_requests" => (
+            "GitHub Pull Requests",
+            reqwest::Method::GET,
+            format!(
+                "{}/pulls?state={}&per_page={}",
+                repository(),
+                urlencoding::encode(&argument("state", "open")),
+                per_page
+            ),
+            None,
+        ),
+        "github_get_file" => return github_get_file(context, arguments).await,
+        "github_search" => (
+            "GitHub Search Results",
+            reqwest::Method::GET,
+            format!(
+                "https://api.github.com/search/{}?q={}&per_page={}",
+                urlencoding::encode(&argument("scope", "repositories")),
+                urlencoding::encode(&argument("query", "")),
+                per_page
+            ),
+            None,
+        ),
+        "github_list_workflow_runs" => {
+            let mut url = format!("{}/actions/runs?per_page={}", repository(), per_page);
+            for key in ["status", "branch"] {
+                if let Some(value) = arguments.get(key).and_then(Value::as_str) {
+                    url.push_str(&format!("&{}={}", key, urlencoding::encode(value)));
+                }
+            }
+            ("GitHub Workflow Runs", reqwest::Method::GET, url, None)
+        }
+        "github_get_user" => (
+            "GitHub User",
+            reqwest::Method::GET,
+            "https://api.github.com/user".to_string(),
+            None,
+        ),
+        _ => return Err(format!("Unknown tool: {}", name).into()),
+    };
+    provider_request(
+        context,
+        OAuthProviderType::GitHub,
+        "GitHub",
+        title,
+        method,
+        &url,
+        body,
+    )
+    .await
+}
+
+/// Contents of a file in a GitHub repository, decoded from the contents API
+async fn github_get_file(
+    context: &MCPToolContext<'_>,
+    arguments: &HashMap<String, Value>,
+) -> MCPToolCallResult {
+    let argument = |key: &str| arguments.get(key).and_then(Value::as_str).unwrap_or("");
+    let path = argument("path");
+    let encoded_path: Vec<String> = path
+        .split('/')
+        .map(|segment| urlencoding::encode(segment).into_owned())
+        .collect();
+    let mut url = format!(
+        "https://api.github.com/repos/{}/{}/contents/{}",
+        urlencoding::encode(argument("owner")),
+        urlencoding::encode(argument("repo")),
+        encoded_path.join("/")
+    );
+    if let Some(git_ref) = arguments.get("ref").and_then(Value::as_str) {
+        url.push_str(&format!("?ref={}", urlencoding::encode(git_ref)));
+    }
+
+    let text = |text: String, is_error| MCPToolResult {
+        content: vec![MCPContent::text(text)],
+        is_error: Some(is_error),
+    };
+    let file = match provider_json(
+        context,
+        OAuthProviderType::GitHub,
+        reqwest::Method::GET,
+        &url,
+        None,
+    )
+    .await
+    {
+        Ok(file) => file,
+        Err(e) => {
+            return Ok(text(
+                format!("Failed to get GitHub file {}: {}", path, e),
+                true,
+            ))
+        }
+    };
+    if file.is_array() {
+        return Ok(text(format!("{} is a directory: {}", path, file), true));
+    }
+    let content = file["content"].as_str().unwrap_or("").replace('\n', "");
+    match general_purpose::STANDARD
+        .decode(content)
+        .map(String::from_utf8)
+    {
+        Ok(Ok(content)) => Ok(text(format!("GitHub File {}:\n{}", path, content), false)),
+        _ => Ok(text(
+            format!("GitHub File {} is binary ({} bytes)", path, file["size"]),
+            false,
+        )),
+    }
+}
+
+async fn call_google_tool(
+    context: &MCPToolContext<'_>,
+    name: &str,
+    arguments: &HashMap<String, Value>,
+) -> MCPToolCallResult {
+    let argument = |key: &str| arguments.get(key).and_then(Value::as_str).unwrap_or("");
+    let max_results = arguments
+        .get("max_results")
+        .and_then(Value::as_u64)
+        .unwrap_or(10);
+    let quoted = |value: &str| format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"));
+
+    let mut filters = vec!["trashed = false".to_string()];
+    match name {
+        "google_drive_list" => {
+            if let Some(folder_id) = arguments.get("folder_id").and_then(Value::as_str) {
+                filters.push(format!("{} in parents", quoted(folder_id)));
+            }
+        }
+        "google_drive_search" => {
+            filters.push(format!("fullText contains {}", quoted(argument("query"))));
+            if let Some(mime_type) = arguments.get("mime_type").and_then(Value::as_str) {
+                filters.push(format!("mimeType = {}", quoted(mime_type)));
+            }
+        }
+        "google_drive_get_file" => {
+            return google_drive_get_file(context, argument("file_id")).await
+        }
+        "google_docs_read" => return google_docs_read(context, argument("document_id")).await,
+        _ => return Err(format!("Unknown tool: {}", name).into()),
+    }
+
+    let url = format!(
+        "https://www.googleapis.com/drive/v3/files?pageSize={}&q={}&orderBy=modifiedTime%20desc&fields={}",
+        max_results,
+        urlencoding::encode(&filters.join(" and ")),
+        urlencoding::encode("files(id,name,mimeType,modifiedTime,size,webViewLink)")
+    );
+    provider_get(
+        context,
+        OAuthProviderType::Google,
+        "Google",
+        "Google Drive Files",
+        &url,
+    )
+    .await
+}
+
+/// Metadata and readable content of a Drive file
+async fn google_drive_get_file(context: &MCPToolContext<'_>, file_id: &str) -> MCPToolCallResult {
+    let text = |text: String, is_error| MCPToolResult {
+        content: vec![MCPContent::text(text)],
+        is_error: Some(is_error),
+    };
+    let file_url = format!(
+        "https://www.googleapis.com/drive/v3/files/{}",
+        urlencoding::encode(file_id)
+    );
+    let metadata = match provider_json(
+        context,
+        OAuthProviderType::Google,
+        reqwest::Method::GET,
+        &format!(
+            "{}?fields=id,name,mimeType,size,modifiedTime,webViewLink",
+            file_url
+        ),
+        None,
+    )
+    .await
+    {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            return Ok(text(
+                format!("Failed to get Google Drive file {}: {}", file_id, e),
+                true,
+            ))
+        }
+    };
+
+    let mime_type = metadata["mimeType"].as_str().unwrap_or("");
+    let content_url = match drive_content(mime_type) {
+        DriveContent::Export(export_type) => format!(
+            "{}/export?mimeType={}",
+            file_url,
+            urlencoding::encode(export_type)
+        ),
+        DriveContent::Download => format!("{}?alt=media", file_url),
+        DriveContent::Unreadable => {
+            return Ok(text(
+                format!(
+                    "Google Drive File {} has no text content: {}",
+                    file_id, metadata
+                ),
+                false,
+            ))
+        }
+    };
+
+    let response = context
+        .manager
+        .make_authenticated_api_request(
+            &OAuthProviderType::Google,
+            &context.instance.installation_id,
+            None,
+            reqwest::Method::GET,
+            &content_url,
+            None,
+            None,
+        )
+        .await;
+    match response {
+        Ok(response) if response.status().is_success() => {
+            let content = response.text().await?;
+            Ok(text(
+                format!("Google Drive File: {}\n\n{}", metadata, content),
+                false,
+            ))
+        }
+        Ok(response) => Ok(text(
+            format!("Google API error: {}", response.status()),
+            true,
+        )),
+        Err(e) => Ok(text(format!("Failed to call Google API: {}", e), true)),
+    }
+}
+
+/// How the content of a Drive file is read
+#[derive(Debug, PartialEq)]
+enum DriveContent {
+    /// Google Workspace file, exported to the given text format
+    Export(&'static str),
+    /// Stored file whose bytes are text
+    Download,
+    /// Binary file, or a Workspace type with no text export
+    Unreadable,
+}
+
+fn drive_content(mime_type: &str) -> DriveContent {
+    match mime_type {
+        "application/vnd.google-apps.document" => DriveContent::Export("text/plain"),
+        "application/vnd.google-apps.spreadsheet" => DriveContent::Export("text/csv"),
+        "application/vnd.google-apps.presentation" => DriveContent::Export("text/plain"),
+        "application/vnd.google-apps.script" => {
+            DriveContent::Export("application/vnd.google-apps.script+json")
+        }
+        mime_type if mime_type.starts_with("application/vnd.google-apps.") => {
+            DriveContent::Unreadable
+        }
+        mime_type
+            if mime_type.starts_with("text/")
+                || matches!(
+                    mime_type,
+                    "application/json"
+                        | "application/xml"
+                        | "application/javascript"
+                        | "application/x-yaml"
+                        | "application/yaml"
+                ) =>
+        {
+            DriveContent::Download
+        }
+        _ => DriveContent::Unreadable,
+    }
+}
+
+/// Title and text of a Google Doc, with its structure flattened to lines
+async fn google_docs_read(context: &MCPToolContext<'_>, document_id: &str) -> MCPToolCallResult {
+    let text = |text: String, is_error| MCPToolResult {
+        content: vec![MCPContent::text(text)],
+        is_error: Some(is_error),
+    };
+    let url = format!(
+        "https://docs.googleapis.com/v1/documents/{}",
+        urlencoding::encode(document_id)
+    );
+    match provider_json(
+        context,
+        OAuthProviderType::Google,
+        reqwest::Method::GET,
+        &url,
+        None,
+    )
+    .await
+    {
+        Ok(document) => {
+            let mut content = String::new();
+            document_text(&document["body"]["content"], &mut content);
+            Ok(text(
+                format!(
+                    "Google Doc {}:\n\n{}",
+                    document["title"].as_str().unwrap_or(document_id),
+                    content
+                ),
+                false,
+            ))
+        }
+        Err(e) => Ok(text(
+            format!("Failed to read Google Doc {}: {}", document_id, e),
+            true,
+        )),
+    }
+}
+
+/// Append the text of Docs structural elements, one tab-separated line per table row
+fn document_text(elements: &Value, out: &mut String) {
+    for element in elements.as_array().into_iter().flatten() {
+        if let Some(paragraph) = element.get("paragraph") {
+            for run in paragraph["elements"].as_array().into_iter().flatten() {
+                out.push_str(run["textRun"]["content"].as_str().unwrap_or(""));
+            }
+        } else if let Some(table) = element.get("table") {
+            for row in table["tableRows"].as_array().into_iter().flatten() {
+                let cells: Vec<String> = row["tableCells"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|cell| {
+                        let mut text = String::new();
+                        document_text(&cell["content"], &mut text);
+                        text.trim().replace('\n', " ")
+                    })
+                    .collect();
+                out.push_str(&cells.join("\t"));
+                out.push('\n');
+            }
+        } else if let Some(toc) = element.get("tableOfContents") {
+            document_text(&toc["content"], out);
+        }
+    }
+}
+
+/// Headers `provider`'s API expects on every request
+pub(crate) fn provider_headers(provider: &OAuthProviderType) -> HashMap<String, String> {
+    match provider {
+        // GitHub rejects requests without a User-Agent
+        OAuthProviderType::GitHub => HashMap::from([
+            (
+                "Accept".to_string(),
+                "application/vnd.github+json".to_string(),
+            ),
+            ("User-Agent".to_string(), "circuit-breaker".to_string()),
+            ("X-GitHub-Api-Version".to_string(), "2022-11-28".to_string()),
+        ]),
+        _ => HashMap::new(),
+    }
+}
+
+/// Call `url` with the instance's OAuth credentials for `provider`, sending `body` as JSON
+async fn provider_request(
+    context: &MCPToolContext<'_>,
+    provider: OAuthProviderType,
+    provider_name: &str,
+    title: &str,
+    method: reqwest::Method,
+    url: &str,
+    body: Option<Value>,
+) -> MCPToolCallResult {
+    let text = |text: String, is_error| MCPToolResult {
+        content: vec![MCPContent::text(text)],
+        is_error: Some(is_error),
+    };
+    let mut headers = provider_headers(&provider);
+    if body.is_some() {
+        headers.insert("Content-Type".to_string(), "application/json".to_string());
+        headers
+            .entry("Accept".to_string())
+            .or_insert_with(|| "application/json".to_string());
+    }
+    context.log(MCPLogLevel::Debug, format!("{} {}", method, url));
+    match context
+        .manager
+        .make_authenticated_api_request(
+            &provider,
+            &context.instance.installation_id,
+            None,
+            method,
+            url,
+            body.map(|body| body.to_string()),
+            Some(headers),
+        )
+        .await
+    {
+        Ok(response) if response.status().is_success() => {
+            let body = response.text().await?;
+            Ok(text(format!("{}: {}", title, body), false))
+        }
+        Ok(response) => {
+            context.log(
+                MCPLogLevel::Error,
+                format!(
+                    "{} answered {} with {}",
+                    provider_name,
+                    url,
+                    response.status()
+                ),
+            );
+            Ok(text(
+                format!("{} API error: {}", provider_name, response.status()),
+                true,
+            ))
+        }
+        Err(e) => {
+            warn!("{} API request failed: {}", provider_name, e);
+            context.log(
+                MCPLogLevel::Error,
+                format!("{} API request failed: {}", provider_name, e),
+            );
+            Ok(text(
+                format!("Failed to call {} API: {}", provider_name, e),
+                true,
+            ))
+        }
+    }
+}
+
+/// GET `url` with the instance's OAuth credentials for `provider`
+async fn provider_get(
+    context: &MCPToolContext<'_>,
+    provider: OAuthProviderType,
+    provider_name: &str,
+    title: &str,
+    url: &str,
+) -> MCPToolCallResult {
+    provider_request(
+        context,
+        provider,
+        provider_name,
+        title,
+        reqwest::Method::GET,
+        url,
+        None,
+    )
+    .await
+}
+
+/// Call `url` with the instance's OAuth credentials for `provider` and parse the JSON reply
+async fn provider_json(
+    context: &MCPToolContext<'_>,
+    provider: OAuthProviderType,
+    method: reqwest::Method,
+    url: &str,
+    body: Option<Value>,
+) -> Result<Value, String> {
+    let mut headers = provider_headers(&provider);
+    if body.is_some() {
+        headers.insert(
+            "Content-Type".to_string(),
+            "application/json; charset=utf-8".to_string(),
+        );
+    }
+    context.log(MCPLogLevel::Debug, format!("{} {}", method, url));
+    let response = context
+        .manager
+        .make_authenticated_api_request(
+            &provider,
+            &context.instance.installation_id,
+            None,
+            method,
+            url,
+            body.map(|body| body.to_string()),
+            Some(headers),
+        )
+        .await?;
+    if !response.status().is_success() {
+        context.log(
+            MCPLogLevel::Error,
+            format!("{} answered with {}", url, response.status()),
+        );
+        return Err(format!("API error: {}", response.status()));
+    }
+    response.json().await.map_err(|e| e.to_string())
+}
+
+async fn call_jira_tool(
+    context: &MCPToolContext<'_>,
+    name: &str,
+    arguments: &HashMap<String, Value>,
+) -> MCPToolCallResult {
+    let argument = |key: &str| arguments.get(key).and_then(Value::as_str).unwrap_or("");
+    let failed = |text: String| MCPToolResult {
+        content: vec![MCPContent::text(text)],
+        is_error: Some(true),
+    };
+    let api = match jira_api_root(context).await {
+        Ok(api) => api,
+        Err(e) => return Ok(failed(format!("Failed to find a Jira site: {}", e))),
+    };
+    let issue = || {
+        format!(
+            "{}/issue/{}",
+            api,
+            urlencoding::encode(argument("issue_key"))
+        )
+    };
+    let request = |title: &'static str, method, url: String, body| async move {
+        provider_request(
+            context,
+            OAuthProviderType::Atlassian,
+            "Jira",
+            title,
+            method,
+            &url,
+            body,
+        )
+        .await
+    };
+
+    match name {
+        "jira_search_issues" => {
+            let max_results = arguments
+                .get("max_results")
+                .and_then(Value::as_u64)
+                .unwrap_or(20);
+            let url = format!(
+                "{}/search/jql?jql={}&maxResults={}&fields=summary,status,assignee,priority,issuetype",
+                api,
+                urlencoding::encode(argument("jql")),
+                max_results
+            );
+            request("Jira Issues", reqwest::Method::GET, url, None).await
+        }
+        "jira_get_issue" => request("Jira Issue", reqwest::Method::GET, issue(), None).await,
+        "jira_create_issue" => {
+            let mut fields = serde_json::json!({
+                "project": {"key": argument("project_key")},
+                "summary": argument("summary"),
+                "issuetype": {"name": arguments
+                    .get("issue_type")
+                    .and_then(Value::as_str)
+                    .unwrap_or("Task")},
+            });
+            if let Some(description) = arguments.get("description").and_then(Value::as_str) {
+                fields["description"] = jira_document(description);
+            }
+            let body = serde_json::json!({ "fields": fields });
+            let url = format!("{}/issue", api);
+            request("Created Jira Issue", reqwest::Method::POST, url, Some(body)).await
+        }
+        "jira_transition_issue" => {
+            let transitions = match provider_json(
+                context,
+                OAuthProviderType::Atlassian,
+                reqwest::Method::GET,
+                &format!("{}/transitions", issue()),
+                None,
+            )
+            .await
+            {
+                Ok(transitions) => transitions,
+                Err(e) => {
+                    return Ok(failed(format!(
+                        "Failed to list transitions of {}: {}",
+                        argument("issue_key"),
+                        e
+                    )))
+                }
+            };
+            let wanted = argument("transition");
+            let transitions = transitions["transitions"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default();
+            let Some(transition) = find_transition(&transitions, wanted) else {
+                let available: Vec<&str> = transitions
+                    .iter()
+                    .filter_map(|transition| transition["name"].as_str())
+                    .collect();
+                return Ok(failed(format!(
+                    "Issue {} has no transition '{}'; available: {}",
+                    argument("issue_key"),
+                    wanted,
+                    available.join(", ")
+                )));
+            };
+            let body = serde_json::json!({"transition": {"id": transition["id"]}});
+            let url = format!("{}/transitions", issue());
+            let result = request(
+                "Transitioned Jira Issue",
+                reqwest::Method::POST,
+                url,
+                Some(body),
+            )
+            .await?;
+            if result.is_error == Some(true) {
+                return Ok(result);
+            }
+            Ok(MCPToolResult {
+                content: vec![MCPContent::text(format!(
+                    "Transitioned Jira Issue {} with '{}'",
+                    argument("issue_key"),
+                    transition["name"].as_str().unwrap_or(wanted)
+                ))],
+                is_error: Some(false),
+            })
+        }
+        "jira_add_comment" => {
+            let body = serde_json::json!({ "body": jira_document(argument("body")) });
+            let url = format!("{}/comment", issue());
+            request("Added Jira Comment", reqwest::Method::POST, url, Some(body)).await
+        }
+        _ => Err(format!("Unknown tool: {}", name).into()),
+    }
+}
+
+/// REST API root of the first Jira site the instance's Atlassian token can reach
+async fn jira_api_root(context: &MCPToolContext<'_>) -> Result<String, String> {
+    let resources = provider_json(
+        context,
+        OAuthProviderType::Atlassian,
+        reqwest::Method::GET,
+        "https://api.atlassian.com/oauth/token/accessible-resources",
+        None,
+    )
+    .await?;
+    resources
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|resource| {
+            resource["scopes"].as_array().is_some_and(|scopes| {
+                scopes
+                    .iter()
+                    .any(|scope| scope.as_str().is_some_and(|scope| scope.contains("jira")))
+            })
+        })
+        .and_then(|resource| resource["id"].as_str())
+        .map(|cloud_id| format!("https://api.atlassian.com/ex/jira/{}/rest/api/3", cloud_id))
+        .ok_or_else(|| "no Jira site is authorized for this installation".to_string())
+}
+
+/// Transition whose id, name or target status is `wanted`
+fn find_transition<'a>(transitions: &'a [Value], wanted: &str) -> Option<&'a Value> {
+    transitions.iter().find(|transition| {
+        transition["id"].as_str() == Some(wanted)
+            || [&transition["name"], &transition["to"]["name"]]
+                .iter()
+                .any(|name| {
+                    name.as_str()
+                        .is_some_and(|name| name.eq_ignore_ascii_case(wanted))
+                })
+    })
+}
+
+/// Plain text as an Atlassian document, one paragraph per non-empty line
+fn jira_document(text: &str) -> Value {
+    let paragraphs: Vec<Value> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::json!({
+                "type": "paragraph",
+                "content": [{"type": "text", "text": line}]
+            })
+        })
+        .collect();
+    serde_json::json!({"type": "doc", "version": 1, "content": paragraphs})
+}
+
+async fn call_slack_tool(
+    context: &MCPToolContext<'_>,
+    name: &str,
+    arguments: &HashMap<String, Value>,
+) -> MCPToolCallResult {
+    let argument = |key: &str| arguments.get(key).and_then(Value::as_str).unwrap_or("");
+    let limit = |default: u64| {
+        arguments
+            .get("limit")
+            .and_then(Value::as_u64)
+            .unwrap_or(default)
+    };
+
+    let (title, method, query, body) = match name {
+        "slack_list_channels" => (
+            "Slack Channels",
+            "conversations.list",
+            vec![
+                (
+                    "types",
+                    arguments
+                        .get("types")
+                        .and_then(Value::as_str)
+                        .unwrap_or("public_channel")
+                        .to_string(),
+                ),
+                ("exclude_archived", "true".to_string()),
+                ("limit", limit(100).to_string()),
+            ],
+            None,
+        ),
+        "slack_post_message" => {
+            let mut body = serde_json::json!({
+                "channel": argument("channel"),
+                "text": argument("text"),
+            });
+            if let Some(thread_ts) = arguments.get("thread_ts").and_then(Value::as_str) {
+                body["thread_ts"] = Value::from(thread_ts);
+            }
+            (
+                "Posted Slack Message",
+                "chat.postMessage",
+                Vec::new(),
+                Some(body),
+            )
+        }
+        "slack_search_messages" => (
+            "Slack Messages",
+            "search.messages",
+            vec![
+                ("query", argument("query").to_string()),
+                ("count", limit(20).to_string()),
+            ],
+            None,
+        ),
+        "slack_get_thread" => (
+            "Slack Thread",
+            "conversations.replies",
+            vec![
+                ("channel", argument("channel").to_string()),
+                ("ts", argument("thread_ts").to_string()),
+                ("limit", limit(100).to_string()),
+            ],
+            None,
+        ),
+        _ => return Err(format!("Unknown tool: {}", name).into()),
+    };
+
+    let query: Vec<String> = query
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, urlencoding::encode(value)))
+        .collect();
+    let url = format!("https://slack.com/api/{}?{}", method, query.join("&"));
+    let http_method = if body.is_some() {
+        reqwest::Method::POST
+    } else {
+        reqwest::Method::GET
+    };
+
+    let text = |text: String, is_error| MCPToolResult {
+        content: vec![MCPContent::text(text)],
+        is_error: Some(is_error),
+    };
+    match provider_json(context, OAuthProviderType::Slack, http_method, &url, body).await {
+        // Slack reports failures in the body of a 200 response
+        Ok(reply) if reply["ok"].as_bool() == Some(true) => {
+            Ok(text(format!("{}: {}", title, reply), false))
+        }
+        Ok(reply) => Ok(text(
+            format!(
+                "Slack API error: {}",
+                reply["error"].as_str().unwrap_or("unknown_error")
+            ),
+            true,
+        )),
+        Err(e) => {
+            warn!("Slack API request failed: {}", e);
+            Ok(text(format!("Failed to call Slack API: {}", e), true))
+        }
+    }
+}
+
+/// Built-in GitLab tools
+fn gitlab_tools() -> Vec<MCPTool> {
+    vec![
+        MCPTool {
+            name: "gitlab_search".to_string(),
+            description: "Search across GitLab projects and repositories".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "query": {"type": "string", "description": "Search query"},
+                    "scope": {"type": "string", "enum": ["projects", "issues", "merge_requests", "commits"], "default": "projects"},
+                    "project_id": {"type": "string", "description": "Specific project ID to search within (optional)"}
+                },
+                "required": ["query"]
+            }),
+        },
+        MCPTool {
+            name: "gitlab_list_projects".to_string(),
+            description: "List GitLab projects accessible to the authenticated user".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "visibility": {"type": "string", "enum": ["private", "internal", "public"], "description": "Filter by visibility"},
+                    "owned": {"type": "boolean", "description": "Only show owned projects"},
+                    "starred": {"type": "boolean", "description": "Only show starred projects"},
+                    "per_page": {"type": "integer", "minimum": 1, "maximum": 100, "default": 20}
+                }
+            }),
+        },
+        MCPTool {
+            name: "gitlab_get_project".to_string(),
+            description: "Get detailed information about a specific GitLab project".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "project_id": {"type": "string", "description": "GitLab project ID or path"}
+                },
+                "required": ["project_id"]
+            }),
+        },
+        MCPTool {
+            name: "gitlab_list_issues".to_string(),
+            description: "List issues in a GitLab project".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "project_id": {"type": "string", "description": "GitLab project ID"},
+                    "state": {"type": "string", "enum": ["opened", "closed", "all"], "default": "opened"},
+                    "labels": {"type": "string", "description": "Comma-separated list of labels"},
+                    "per_page": {"type": "integer", "minimum": 1, "maximum": 100, "default": 20}
+                },
+                "required": ["project_id"]
+            }),
+        },
+        MCPTool {
+            name: "gitlab_create_issue".to_string(),
+            description: "Create a new issue in a GitLab project".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "project_id": {"type": "string", "description": "GitLab project ID"},
+                    "title": {"type": "string", "description": "Issue title"},
+                    "description": {"type": "string", "description": "Issue description"},
+                    "labels": {"type": "array", "items": {"type": "string"}, "description": "Issue labels"},
+                    "assignee_ids": {"type": "array", "items": {"type": "integer"}, "description": "User IDs to assign"}
+                },
+                "required": ["project_id", "title"]
+            }),
+        },
+        MCPTool {
+            name: "gitlab_list_merge_requests".to_string(),
+            description: "List merge requests in a GitLab project".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "project_id": {"type": "string", "description": "GitLab project ID"},
+                    "state": {"type": "string", "enum": ["opened", "closed", "merged", "all"], "default": "opened"},
+                    "per_page": {"type": "integer", "minimum": 1, "maximum": 100, "default": 20}
+                },
+                "required": ["project_id"]
+            }),
+        },
+        MCPTool {
+            name: "gitlab_get_file".to_string(),
+            description: "Get file contents from a GitLab repository".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "project_id": {"type": "string", "description": "GitLab project ID"},
+                    "file_path": {"type": "string", "description": "Path to the file"},
+                    "ref": {"type": "string", "description": "Branch, tag, or commit SHA", "default": "main"}
+                },
+                "required": ["project_id", "file_path"]
+            }),
+        },
+        MCPTool {
+            name: "gitlab_list_pipelines".to_string(),
+            description: "List CI/CD pipelines for a GitLab project".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "project_id": {"type": "string", "description": "GitLab project ID"},
+                    "status": {"type": "string", "enum": ["running", "pending", "success", "failed", "canceled", "skipped"], "description": "Filter by status"},
+                    "per_page": {"type": "integer", "minimum": 1, "maximum": 100, "default": 20}
+                },
+                "required": ["project_id"]
+            }),
+        },
+        MCPTool {
+            name: "gitlab_get_user".to_string(),
+            description: "Get information about the authenticated GitLab user".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {}
+            }),
+        },
+    ]
+}
+
+/// Built-in GitHub tools
+fn github_tools() -> Vec<MCPTool> {
+    vec![
+        MCPTool {
+            name: "github_list_repositories".to_string(),
+            description: "List GitHub repositories accessible to the authenticated user"
+                .to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "visibility": {"type": "string", "enum": ["all", "public", "private"], "default": "all"},
+                    "affiliation": {"type": "string", "enum": ["owner", "collaborator", "organization_member"], "default": "owner"},
+                    "per_page": {"type": "integer", "minimum": 1, "maximum": 100, "default": 30}
+                }
+            }),
+        },
+        MCPTool {
+            name: "github_get_repository".to_string(),
+            description: "Get detailed information about a specific GitHub repository".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "owner": {"type": "string", "description": "Repository owner"},
+                    "repo": {"type": "string", "description": "Repository name"}
+                },
+                "required": ["owner", "repo"]
+            }),
+        },
+        MCPTool {
+            name: "github_list_issues".to_string(),
+            description: "List issues in a GitHub repository".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "owner": {"type": "string", "description": "Repository owner"},
+                    "repo": {"type": "string", "description": "Repository name"},
+                    "state": {"type": "string", "enum": ["open", "closed", "all"], "default": "open"},
+                    "per_page": {"type": "integer", "minimum": 1, "maximum": 100, "default": 30}
+                },
+                "required": ["owner", "repo"]
+            }),
+        },
+        MCPTool {
+            name: "github_create_issue".to_string(),
+            description: "Create a new issue in a GitHub repository".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "owner": {"type": "string", "description": "Repository owner"},
+                    "repo": {"type": "string", "description": "Repository name"},
+                    "title": {"type": "string", "description": "Issue title"},
+                    "body": {"type": "string", "description": "Issue body (Markdown)"},
+                    "labels": {"type": "array", "items": {"type": "string"}, "description": "Issue labels"},
+                    "assignees": {"type": "array", "items": {"type": "string"}, "description": "Usernames to assign"}
+                },
+                "required": ["owner", "repo", "title"]
+            }),
+        },
+        MCPTool {
+            name: "github_list_pull_requests".to_string(),
+            description: "List pull requests in a GitHub repository".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "owner": {"type": "string", "description": "Repository owner"},
+                    "repo": {"type": "string", "description": "Repository name"},
+                    "state": {"type": "string", "enum": ["open", "closed", "all"], "default": "open"},
+                    "per_page": {"type": "integer", "minimum": 1, "maximum": 100, "default": 30}
+                },
+                "required": ["owner", "repo"]
+            }),
+        },
+        MCPTool {
+            name: "github_get_file".to_string(),
+            description: "Get file contents from a GitHub repository".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "owner": {"type": "string", "description": "Repository owner"},
+                    "repo": {"type": "string", "description": "Repository name"},
+                    "path": {"type": "string", "description": "Path to the file"},
+                    "ref": {"type": "string", "description": "Branch, tag, or commit SHA (default branch if omitted)"}
+                },
+                "required": ["owner", "repo", "path"]
+            }),
+        },
+        MCPTool {
+            name: "github_search".to_string(),
+            description: "Search GitHub repositories, issues, code or commits".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "query": {"type": "string", "description": "GitHub search query, e.g. repo:owner/name is:open"},
+                    "scope": {"type": "string", "enum": ["repositories", "issues", "code", "commits"], "default": "repositories"},
+                    "per_page": {"type": "integer", "minimum": 1, "maximum": 100, "default": 30}
+                },
+                "required": ["query"]
+            }),
+        },
+        MCPTool {
+            name: "github_list_workflow_runs".to_string(),
+            description: "List GitHub Actions workflow runs for a repository".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "owner": {"type": "string", "description": "Repository owner"},
+                    "repo": {"type": "string", "description": "Repository name"},
+                    "status": {"type": "string", "enum": ["queued", "in_progress", "completed", "success", "failure", "cancelled", "skipped"], "description": "Filter by status"},
+                    "branch": {"type": "string", "description": "Filter by branch"},
+                    "per_page": {"type": "integer", "minimum": 1, "maximum": 100, "default": 30}
+                },
+                "required": ["owner", "repo"]
+            }),
+        },
+        MCPTool {
+            name: "github_get_user".to_string(),
+            description: "Get information about the authenticated GitHub user".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {}
+            }),
+        },
+    ]
+}
+
+/// Built-in Jira tools, served to instances of the Atlassian provider
+fn jira_tools() -> Vec<MCPTool> {
+    vec![
+        MCPTool {
+            name: "jira_search_issues".to_string(),
+            description: "Search Jira issues with JQL".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "jql": {"type": "string", "description": "JQL query, e.g. project = OPS AND status = \"In Progress\""},
+                    "max_results": {"type": "integer", "minimum": 1, "maximum": 100, "default": 20}
+                },
+                "required": ["jql"]
+            }),
+        },
+        MCPTool {
+            name: "jira_get_issue".to_string(),
+            description: "Get a Jira issue with its fields".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "issue_key": {"type": "string", "description": "Issue key, e.g. OPS-42"}
+                },
+                "required": ["issue_key"]
+            }),
+        },
+        MCPTool {
+            name: "jira_create_issue".to_string(),
+            description: "Create a new issue in a Jira project".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "project_key": {"type": "string", "description": "Project key, e.g. OPS"},
+                    "summary": {"type": "string", "description": "Issue summary"},
+                    "description": {"type": "string", "description": "Issue description"},
+                    "issue_type": {"type": "string", "description": "Issue type name", "default": "Task"}
+                },
+                "required": ["project_key", "summary"]
+            }),
+        },
+        MCPTool {
+            name: "jira_transition_issue".to_string(),
+            description: "Move a Jira issue through its workflow".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "issue_key": {"type": "string", "description": "Issue key, e.g. OPS-42"},
+                    "transition": {"type": "string", "description": "Transition ID or name, or the name of the target status"}
+                },
+                "required": ["issue_key", "transition"]
+            }),
+        },
+        MCPTool {
+            name: "jira_add_comment".to_string(),
+            description: "Add a comment to a Jira issue".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "issue_key": {"type": "string", "description": "Issue key, e.g. OPS-42"},
+                    "body": {"type": "string", "description": "Comment text"}
+                },
+                "required": ["issue_key", "body"]
+            }),
+        },
+    ]
+}
+
+/// Built-in Slack tools, served to instances of the Slack provider
+fn slack_tools() -> Vec<MCPTool> {
+    vec![
+        MCPTool {
+            name: "slack_list_channels".to_string(),
+            description: "List channels in the Slack workspace".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "types": {"type": "string", "description": "Comma-separated channel types: public_channel, private_channel", "default": "public_channel"},
+                    "limit": {"type": "integer", "minimum": 1, "maximum": 1000, "default": 100}
+                }
+            }),
+        },
+        MCPTool {
+            name: "slack_post_message".to_string(),
+            description: "Post a message to a Slack channel or thread".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "channel": {"type": "string", "description": "Channel ID"},
+                    "text": {"type": "string", "description": "Message text (Slack mrkdwn)"},
+                    "thread_ts": {"type": "string", "description": "Timestamp of the parent message to reply in its thread"}
+                },
+                "required": ["channel", "text"]
+            }),
+        },
+        MCPTool {
+            name: "slack_search_messages".to_string(),
+            description:
+                "Search messages in the Slack workspace (requires a user token with search:read)"
+                    .to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "query": {"type": "string", "description": "Slack search query, e.g. deploy in:#ops"},
+                    "limit": {"type": "integer", "minimum": 1, "maximum": 100, "default": 20}
+                },
+                "required": ["query"]
+            }),
+        },
+        MCPTool {
+            name: "slack_get_thread".to_string(),
+            description: "Fetch a Slack message and its thread replies".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "channel": {"type": "string", "description": "Channel ID"},
+                    "thread_ts": {"type": "string", "description": "Timestamp of the parent message"},
+                    "limit": {"type": "integer", "minimum": 1, "maximum": 1000, "default": 100}
+                },
+                "required": ["channel", "thread_ts"]
+            }),
+        },
+    ]
+}
+
+/// Built-in Google tools
+fn google_tools() -> Vec<MCPTool> {
+    vec![
+        MCPTool {
+            name: "google_drive_list".to_string(),
+            description: "List recently modified files in Google Drive".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "folder_id": {"type": "string", "description": "Only list files in this folder"},
+                    "max_results": {"type": "integer", "minimum": 1, "maximum": 100, "default": 10}
+                }
+            }),
+        },
+        MCPTool {
+            name: "google_drive_search".to_string(),
+            description: "Search the content and names of Google Drive files".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "query": {"type": "string", "description": "Text to search for"},
+                    "mime_type": {"type": "string", "description": "Only return files of this MIME type, e.g. application/vnd.google-apps.document"},
+                    "max_results": {"type": "integer", "minimum": 1, "maximum": 100, "default": 10}
+                },
+                "required": ["query"]
+            }),
+        },
+        MCPTool {
+            name: "google_drive_get_file".to_string(),
+            description: "Get a Google Drive file's metadata and text content; Docs and Slides are exported as text, Sheets as CSV".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "file_id": {"type": "string", "description": "Drive file ID"}
+                },
+                "required": ["file_id"]
+            }),
+        },
+        MCPTool {
+            name: "google_docs_read".to_string(),
+            description: "Read the text of a Google Doc, including its tables".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "document_id": {"type": "string", "description": "Document ID from the Doc's URL"}
+                },
+                "required": ["document_id"]
+            }),
+        },
+    ]
+}
+
+/// Built-in tools for remote instances with unknown providers
+fn generic_remote_tools() -> Vec<MCPTool> {
+    vec![MCPTool {
+        name: "api_request".to_string(),
+        description: "Make authenticated API requests to the configured provider".to_string(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "method": {"type": "string", "enum": ["GET", "POST", "PUT", "PATCH", "DELETE"], "default": "GET"},
+                "endpoint": {"type": "string", "description": "API endpoint path"},
+                "body": {"type": "object", "description": "Request body for POST/PUT/PATCH"}
+            },
+            "required": ["endpoint"]
+        }),
+    }]
+}
+
+/// Built-in tools for local instances
+fn local_tools() -> Vec<MCPTool> {
+    vec![
+        MCPTool {
+            name: "create_workflow".to_string(),
+            description: "Create a new workflow definition".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "name": {"type": "string"},
+                    "description": {"type": "string"},
+                    "steps": {"type": "array"}
+                },
+                "required": ["name", "steps"]
+            }),
+        },
+        MCPTool {
+            name: "execute_agent".to_string(),
+            description: "Execute an agent with given parameters".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "agent_id": {"type": "string"},
+                    "parameters": {"type": "object"}
+                },
+                "required": ["agent_id"]
+            }),
+        },
+        MCPTool {
+            name: "search_project_context".to_string(),
+            description: "Search within a project context".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "context_id": {"type": "string"},
+                    "query": {"type": "string"},
+                    "filters": {"type": "object"}
+                },
+                "required": ["context_id", "query"]
+            }),
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_transition() {
+        let transitions = vec![
+            serde_json::json!({"id": "11", "name": "Start Progress", "to": {"name": "In Progress"}}),
+            serde_json::json!({"id": "31", "name": "Resolve", "to": {"name": "Done"}}),
+        ];
+
+        let id = |wanted| find_transition(&transitions, wanted).map(|t| t["id"].clone());
+        assert_eq!(id("31"), Some(Value::from("31")));
+        assert_eq!(id("start progress"), Some(Value::from("11")));
+        assert_eq!(id("Done"), Some(Value::from("31")));
+        assert_eq!(id("Reopen"), None);
+    }
+
+    #[test]
+    fn test_jira_document() {
+        let document = jira_document("First line\n\nSecond line");
+        assert_eq!(document["type"], "doc");
+        let paragraphs = document["content"].as_array().unwrap();
+        assert_eq!(paragraphs.len(), 2);
+        assert_eq!(paragraphs[1]["content"][0]["text"], "Second line");
+    }
+
+    #[test]
+    fn test_github_tools_match_gitlab() {
+        let registry = MCPToolRegistry::with_builtin_tools();
+        let names = |toolset: &str| -> Vec<String> {
+            registry.toolsets[toolset]
+                .iter()
+                .map(|handler| handler.definition().name)
+                .collect()
+        };
+        let github = names("github");
+
+        for gitlab in names("gitlab") {
+            let counterpart = gitlab
+                .replace("gitlab_", "github_")
+                .replace("merge_requests", "pull_requests")
+                .replace("projects", "repositories")
+                .replace("project", "repository")
+                .replace("pipelines", "workflow_runs");
+            assert!(github.contains(&counterpart), "missing {}", counterpart);
+        }
+    }
+
+    #[test]
+    fn test_drive_content() {
+        assert_eq!(
+            drive_content("application/vnd.google-apps.spreadsheet"),
+            DriveContent::Export("text/csv")
+        );
+        assert_eq!(
+            drive_content("application/vnd.google-apps.document"),
+            DriveContent::Export("text/plain")
+        );
+        assert_eq!(
+            drive_content("application/vnd.google-apps.folder"),
+            DriveContent::Unreadable
+        );
+        assert_eq!(drive_content("text/markdown"), DriveContent::Download);
+        assert_eq!(drive_content("application/json"), DriveContent::Download);
+        assert_eq!(drive_content("image/png"), DriveContent::Unreadable);
+    }
+
+    #[test]
+    fn test_document_text() {
+        let content = serde_json::json!([
+            {"paragraph": {"elements": [
+                {"textRun": {"content": "Release "}},
+                {"textRun": {"content": "plan\n"}}
+            ]}},
+            {"table": {"tableRows": [
+                {"tableCells": [
+                    {"content": [{"paragraph": {"elements": [{"textRun": {"content": "Owner\n"}}]}}]},
+                    {"content": [{"paragraph": {"elements": [{"textRun": {"content": "Date\n"}}]}}]}
+                ]}
+            ]}}
+        ]);
+
+        let mut text = String::new();
+        document_text(&content, &mut text);
+        assert_eq!(text, "Release plan\nOwner\tDate\n");
+    }
+
+    #[test]
+    fn test_builtin_jira_tools_validate() {
+        let registry = MCPToolRegistry::with_builtin_tools();
+        let tools = &registry.toolsets["atlassian"];
+        let transition = tools
+            .iter()
+            .map(|handler| handler.definition())
+            .find(|tool| tool.name == "jira_transition_issue")
+            .unwrap();
+
+        let arguments = |value: Value| serde_json::from_value(value).unwrap();
+        assert!(validate_arguments(
+            &transition,
+            &arguments(serde_json::json!({"issue_key": "OPS-1", "transition": "Done"}))
+        )
+        .is_ok());
+        assert!(validate_arguments(
+            &transition,
+            &arguments(serde_json::json!({"issue_key": "OPS-1"}))
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_read_only_builtin_tools_exist() {
+        let registry = MCPToolRegistry::with_builtin_tools();
+        let read_only: Vec<String> = registry
+            .toolsets
+            .values()
+            .flatten()
+            .filter(|handler| handler.read_only())
+            .map(|handler| handler.definition().name)
+            .collect();
+        for name in READ_ONLY_BUILTIN_TOOLS {
+            assert!(read_only.iter().any(|tool| tool == name), "{}", name);
+        }
+        assert!(!read_only.contains(&"gitlab_create_issue".to_string()));
+    }
+}
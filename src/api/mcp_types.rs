@@ -5,6 +5,7 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::HashMap;
 
+use super::mcp_sampling::SamplingRole;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum MCPApplicationType {
@@ -14,7 +15,7 @@ pub enum MCPApplicationType {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RemoteOAuthConfig {
-    pub provider_type: String, // "github", "gitlab", "google", "custom"
+    pub provider_type: String, // "github", "gitlab", "google", "atlassian", "slack", "custom"
     pub client_id: String,
     pub client_secret: String,
     pub auth_url: Option<String>,  // For custom providers
@@ -33,11 +34,72 @@ pub struct MCPServerInstance {
     pub capabilities: MCPCapabilities,
     pub project_contexts: Vec<String>,
     pub app_type: MCPApplicationType,
+    /// Tenant whose routing policy and budget apply to sampling requests
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+    /// Which of its tools the instance exposes
+    #[serde(default)]
+    pub tool_policy: MCPToolPolicy,
     pub created_at: DateTime<Utc>,
     pub last_activity: DateTime<Utc>,
     pub status: MCPServerStatus,
 }
 
+/// Tool allow/deny policy of an instance. Patterns match tool names, with `*`
+/// standing for any run of characters, e.g. `gitlab_list_*`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MCPToolPolicy {
+    /// Patterns of the tools to expose; empty exposes every tool
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Patterns of tools never exposed, even when allowed
+    #[serde(default)]
+    pub deny: Vec<String>,
+    /// Expose only tools that do not change anything
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+impl MCPToolPolicy {
+    /// Whether the policy exposes the tool `name`
+    pub fn permits(&self, name: &str, read_only: bool) -> bool {
+        if self.read_only && !read_only {
+            return false;
+        }
+        if self
+            .deny
+            .iter()
+            .any(|pattern| matches_pattern(pattern, name))
+        {
+            return false;
+        }
+        self.allow.is_empty()
+            || self
+                .allow
+                .iter()
+                .any(|pattern| matches_pattern(pattern, name))
+    }
+}
+
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
 /// MCP Server Registry - manages multiple MCP server instances
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MCPServerRegistry {
@@ -230,6 +292,34 @@ pub struct MCPPromptArgument {
     pub name: String,
     pub description: String,
     pub required: bool,
+    /// Values `completion/complete` suggests for the argument
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<String>,
+}
+
+/// MCP `prompts/get` request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MCPGetPrompt {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: HashMap<String, String>,
+}
+
+/// Prompt an instance renders for `prompts/get`; see [`super::mcp_prompts`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MCPPromptTemplate {
+    pub name: String,
+    pub description: String,
+    #[serde(default)]
+    pub arguments: Vec<MCPPromptArgument>,
+    pub messages: Vec<MCPPromptTemplateMessage>,
+}
+
+/// Message of a prompt template, with `{{argument}}` placeholders in its text
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MCPPromptTemplateMessage {
+    pub role: SamplingRole,
+    pub text: String,
 }
 
 /// MCP Session information - tied to a specific server instance and user
@@ -248,6 +338,20 @@ pub struct MCPSession {
     pub expires_at: Option<DateTime<Utc>>,
 }
 
+/// Details of a session about to be created; timestamps and expiry are set on creation
+#[derive(Debug, Clone)]
+pub struct MCPSessionParams {
+    /// ID the client picked, or `None` to generate one
+    pub session_id: Option<String>,
+    pub server_instance_id: String,
+    pub installation_id: String,
+    pub app_id: String,
+    pub user_id: Option<String>,
+    pub client_info: MCPClientInfo,
+    pub permissions: MCPSessionPermissions,
+    pub project_contexts: Vec<String>,
+}
+
 /// MCP Session permissions
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MCPSessionPermissions {
@@ -307,23 +411,18 @@ impl MCPResponse {
             error: Some(MCPError {
                 code,
                 message,
-                data: None,
+                data: Some(error_code_data(code, None)),
             }),
         }
     }
 
     /// Create an error response from an optional ID (handles notifications)
     pub fn error_from_request(request_id: Option<MCPId>, code: i32, message: String) -> Self {
-        Self {
-            jsonrpc: Some("2.0".to_string()),
-            id: request_id.unwrap_or_else(|| MCPId::String("null".to_string())),
-            result: None,
-            error: Some(MCPError {
-                code,
-                message,
-                data: None,
-            }),
-        }
+        Self::error(
+            request_id.unwrap_or_else(|| MCPId::String("null".to_string())),
+            code,
+            message,
+        )
     }
 
     /// Create an error response with data
@@ -335,23 +434,59 @@ impl MCPResponse {
             error: Some(MCPError {
                 code,
                 message,
-                data: Some(data),
+                data: Some(error_code_data(code, Some(data))),
             }),
         }
     }
 
     /// Create an error response with data from an optional ID (handles notifications)
     pub fn error_with_data_from_request(request_id: Option<MCPId>, code: i32, message: String, data: serde_json::Value) -> Self {
-        Self {
-            jsonrpc: Some("2.0".to_string()),
-            id: request_id.unwrap_or_else(|| MCPId::String("null".to_string())),
-            result: None,
-            error: Some(MCPError {
-                code,
-                message,
-                data: Some(data),
-            }),
+        Self::error_with_data(
+            request_id.unwrap_or_else(|| MCPId::String("null".to_string())),
+            code,
+            message,
+            data,
+        )
+    }
+
+    /// Override the stable error code carried in `error.data.error_code`
+    pub fn with_error_code(mut self, error_code: crate::ErrorCode) -> Self {
+        if let Some(error) = self.error.as_mut() {
+            let mut data = match error.data.take() {
+                Some(serde_json::Value::Object(map)) => map,
+                _ => serde_json::Map::new(),
+            };
+            data.insert("error_code".to_string(), serde_json::json!(error_code));
+            error.data = Some(serde_json::Value::Object(data));
         }
+        self
+    }
+}
+
+/// Default stable error code for a JSON-RPC error code
+pub fn error_code_for_jsonrpc(code: i32) -> crate::ErrorCode {
+    use crate::ErrorCode;
+    match code {
+        error_codes::PARSE_ERROR | error_codes::INVALID_REQUEST | error_codes::INVALID_PARAMS => {
+            ErrorCode::InvalidInput
+        }
+        error_codes::METHOD_NOT_FOUND => ErrorCode::NotFound,
+        401 => ErrorCode::AuthenticationFailed,
+        403 => ErrorCode::PermissionDenied,
+        _ => ErrorCode::Internal,
+    }
+}
+
+/// Error data carrying the stable error code, merged into any caller-provided object
+fn error_code_data(code: i32, data: Option<serde_json::Value>) -> serde_json::Value {
+    let error_code = serde_json::json!(error_code_for_jsonrpc(code));
+    match data {
+        Some(serde_json::Value::Object(mut map)) => {
+            map.entry("error_code").or_insert(error_code);
+            serde_json::Value::Object(map)
+        }
+        Some(other) => serde_json::json!({ "error_code": error_code, "details": other }),
+        None => serde_json::json!({ "error_code": error_code }),
     }
 }
 
@@ -638,6 +773,21 @@ mod tests {
         assert!(error_response.error.is_some());
     }
 
+    #[test]
+    fn test_mcp_error_carries_error_code() {
+        let error_response = MCPResponse::error(
+            MCPId::String("test-id".to_string()),
+            error_codes::INVALID_PARAMS,
+            "Missing parameters".to_string(),
+        );
+        let data = error_response.error.as_ref().unwrap().data.clone().unwrap();
+        assert_eq!(data["error_code"], "INVALID_INPUT");
+
+        let error_response = error_response.with_error_code(crate::ErrorCode::WorkflowNotFound);
+        let data = error_response.error.unwrap().data.unwrap();
+        assert_eq!(data["error_code"], "WORKFLOW_NOT_FOUND");
+    }
+
     #[test]
     fn test_mcp_content_creation() {
         let text_content = MCPContent::text("Hello world".to_string());
@@ -647,6 +797,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_tool_policy() {
+        let policy = MCPToolPolicy::default();
+        assert!(policy.permits("gitlab_create_issue", false));
+
+        let policy = MCPToolPolicy {
+            allow: vec!["gitlab_*".to_string()],
+            deny: vec!["*_create_*".to_string()],
+            read_only: false,
+        };
+        assert!(policy.permits("gitlab_list_issues", true));
+        assert!(!policy.permits("gitlab_create_issue", false));
+        assert!(!policy.permits("github_list_issues", true));
+
+        let policy = MCPToolPolicy {
+            read_only: true,
+            ..Default::default()
+        };
+        assert!(policy.permits("gitlab_get_file", true));
+        assert!(!policy.permits("slack_post_message", false));
+
+        assert!(matches_pattern("*", ""));
+        assert!(matches_pattern("a*a", "aba"));
+        assert!(!matches_pattern("a*a", "a"));
+        assert!(matches_pattern("exact", "exact"));
+        assert!(!matches_pattern("exact", "exactly"));
+    }
+
     #[test]
     fn test_default_capabilities() {
         let caps = MCPCapabilities::default();
@@ -0,0 +1,160 @@
+// Server metadata for SDK compatibility negotiation
+// `/v1/meta` tells clients which server version they talk to, which API and protocol
+// versions it speaks and which optional features are switched on
+
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::ApiConfig;
+use crate::MaintenanceMode;
+
+/// REST API versions served, oldest first
+pub const API_VERSIONS: &[&str] = &["v1"];
+
+/// MCP protocol revisions the MCP server accepts
+pub const MCP_PROTOCOL_VERSIONS: &[&str] = &["2024-11-05"];
+
+/// Oldest SDK release that understands this server's responses
+pub const MIN_SDK_VERSION: &str = "0.1.0";
+
+/// Features served whenever the OpenAI-compatible API is on
+const OPENAI_API_FEATURES: &[&str] = &[
+    "chat_completions",
+    "embeddings",
+    "smart_routing",
+    "virtual_models",
+    "tool_calling",
+    "structured_output",
+    "vision",
+    "request_priority",
+    "routing_traces",
+    "tenant_routing_policies",
+    "provider_key_validation",
+    "maintenance_mode",
+    "log_stream",
+    "budget_enforcement",
+    "request_cost",
+    "usage_export",
+    "tokenize",
+    "tenant_admin",
+    "chargeback_reports",
+    "budget_periods",
+    "moderations",
+    "audio_transcriptions",
+    "audio_speech",
+    "image_generations",
+    "service_accounts",
+    "api_keys",
+    "invoice_reconciliation",
+    "parallel_sampling",
+    "response_annotations",
+    "request_validation",
+];
+
+/// What a server offers, as reported by `GET /v1/meta`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerMeta {
+    pub service: String,
+    pub version: String,
+    pub api_versions: Vec<String>,
+    pub mcp_protocol_versions: Vec<String>,
+    pub min_sdk_version: String,
+    /// Enabled feature names, e.g. `streaming` or `structured_output`
+    pub features: Vec<String>,
+}
+
+/// Body of `GET /v1/meta`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetaResponse {
+    #[serde(flatten)]
+    pub meta: ServerMeta,
+    /// Whether the server currently rejects writes
+    pub maintenance: bool,
+}
+
+impl ServerMeta {
+    /// Describe a server running with the given configuration
+    pub fn from_config(config: &ApiConfig) -> Self {
+        let mut features: Vec<String> = Vec::new();
+        if config.enable_openai_api {
+            features.extend(OPENAI_API_FEATURES.iter().map(|f| f.to_string()));
+            if config.enable_streaming {
+                features.push("streaming".to_string());
+                features.push("stream_resumption".to_string());
+                features.push("websocket_streaming".to_string());
+                features.push("stream_cancellation".to_string());
+                features.push("stream_usage".to_string());
+                features.push("stream_content_filter".to_string());
+            }
+            if config.moderate_chat_completions {
+                features.push("chat_moderation".to_string());
+            }
+        }
+        if config.enable_mcp_server {
+            features.push("mcp".to_string());
+            features.push("mcp_sampling".to_string());
+        }
+
+        Self {
+            service: "circuit-breaker".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            api_versions: API_VERSIONS.iter().map(|v| v.to_string()).collect(),
+            mcp_protocol_versions: if config.enable_mcp_server {
+                MCP_PROTOCOL_VERSIONS
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect()
+            } else {
+                Vec::new()
+            },
+            min_sdk_version: MIN_SDK_VERSION.to_string(),
+            features,
+        }
+    }
+
+    pub fn supports(&self, feature: &str) -> bool {
+        self.features.iter().any(|f| f == feature)
+    }
+}
+
+/// Server metadata endpoint - GET /v1/meta
+pub async fn get_meta(State(meta): State<Arc<ServerMeta>>) -> Json<MetaResponse> {
+    Json(MetaResponse {
+        meta: meta.as_ref().clone(),
+        maintenance: MaintenanceMode::global().is_enabled(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_features_follow_config() {
+        let meta = ServerMeta::from_config(&ApiConfig::default());
+        assert!(meta.supports("chat_completions"));
+        assert!(meta.supports("streaming"));
+        assert!(meta.supports("mcp"));
+        assert!(meta.supports("mcp_sampling"));
+        assert!(meta.supports("moderations"));
+        assert!(!meta.supports("chat_moderation"));
+        assert_eq!(meta.api_versions, vec!["v1"]);
+        assert_eq!(meta.mcp_protocol_versions, vec!["2024-11-05"]);
+
+        let meta = ServerMeta::from_config(&ApiConfig {
+            enable_streaming: false,
+            enable_mcp_server: false,
+            moderate_chat_completions: true,
+            ..ApiConfig::default()
+        });
+        assert!(meta.supports("structured_output"));
+        assert!(!meta.supports("streaming"));
+        assert!(!meta.supports("stream_resumption"));
+        assert!(!meta.supports("websocket_streaming"));
+        assert!(!meta.supports("mcp"));
+        assert!(!meta.supports("mcp_sampling"));
+        assert!(meta.supports("chat_moderation"));
+        assert!(meta.mcp_protocol_versions.is_empty());
+    }
+}
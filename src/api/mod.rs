@@ -3,24 +3,54 @@
 // - OpenAI-compatible REST API
 // - MCP (Model Context Protocol) server
 
+pub mod annotations;
+pub mod api_keys;
+pub mod audio;
+pub mod budget_periods;
+pub mod chargeback;
+pub mod chat_ws;
 pub mod handlers;
+pub mod images;
+pub mod log_stream;
 pub mod mcp_auth;
+pub mod mcp_client;
+pub mod mcp_completion;
+pub mod mcp_logging;
 pub mod mcp_oauth_setup;
+pub mod mcp_prompts;
+pub mod mcp_registration;
+pub mod mcp_sampling;
 pub mod mcp_server;
+pub mod mcp_signing_keys;
 pub mod mcp_storage;
+pub mod mcp_streamable;
+pub mod mcp_tool_cache;
+pub mod mcp_tools;
 pub mod mcp_types;
+pub mod meta;
+pub mod moderations;
 pub mod oauth;
+pub mod reconciliation;
+pub mod roles;
+pub mod service_accounts;
+pub mod tenants;
 pub mod types;
+pub mod usage_export;
+pub mod validation;
 
 use axum::{
-    routing::{get, post},
+    extract::DefaultBodyLimit,
+    routing::{delete, get, post, put},
     Router,
 };
+use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tower_http::cors::CorsLayer;
 use tracing::info;
 
+use crate::auth_throttle::record_peer_addr;
 use crate::llm::cost::CostOptimizer;
 use crate::llm::LLMRouter;
 use handlers::{chat_completions, get_model, health_check, list_models, not_found, OpenAIApiState};
@@ -36,10 +66,19 @@ pub struct ApiConfig {
     pub cors_enabled: bool,
     pub api_key_required: bool,
     pub enable_streaming: bool,
+    /// Highest `max_tokens` a request may ask for
     pub max_tokens_per_request: Option<u32>,
+    /// Largest JSON request body accepted, in bytes
+    pub max_body_bytes: usize,
+    /// Most messages a chat completion may carry
+    pub max_messages_per_request: Option<usize>,
+    /// Request parameters rejected per model; see [`validation`]
+    pub disallowed_params: HashMap<String, Vec<String>>,
     pub rate_limit_per_minute: Option<u32>,
     pub enable_openai_api: bool,
     pub enable_mcp_server: bool,
+    /// Moderate the user messages of chat completions and reject flagged requests
+    pub moderate_chat_completions: bool,
 }
 
 /// OpenAI API server configuration (for backward compatibility)
@@ -54,9 +93,13 @@ impl Default for ApiConfig {
             api_key_required: false,
             enable_streaming: true,
             max_tokens_per_request: Some(4096),
+            max_body_bytes: validation::DEFAULT_MAX_BODY_BYTES,
+            max_messages_per_request: None,
+            disallowed_params: HashMap::new(),
             rate_limit_per_minute: Some(60),
             enable_openai_api: true,
             enable_mcp_server: true,
+            moderate_chat_completions: false,
         }
     }
 }
@@ -74,7 +117,8 @@ pub type OpenAIApiServer = CircuitBreakerApiServer;
 impl CircuitBreakerApiServer {
     /// Create a new Circuit Breaker API server
     pub fn new(config: ApiConfig) -> Self {
-        let openai_state = OpenAIApiState::new();
+        let mut openai_state = OpenAIApiState::new();
+        openai_state.moderate_chat_completions = config.moderate_chat_completions;
         let mcp_manager = MCPServerManager::new();
 
         Self {
@@ -89,7 +133,8 @@ impl CircuitBreakerApiServer {
         config: ApiConfig,
         nats_url: &str,
     ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
-        let openai_state = OpenAIApiState::new();
+        let mut openai_state = OpenAIApiState::new();
+        openai_state.moderate_chat_completions = config.moderate_chat_completions;
         let mcp_manager = MCPServerManager::with_nats_storage(nats_url)
             .await
             .map_err(|e| {
@@ -132,39 +177,226 @@ impl CircuitBreakerApiServer {
         self
     }
 
+    /// Set the bearer token required by admin endpoints
+    pub fn with_admin_token(mut self, token: impl Into<String>) -> Self {
+        self.openai_state.admin_token = Some(token.into());
+        self
+    }
+
     /// Set custom cost optimizer
     pub fn with_cost_optimizer(mut self, optimizer: CostOptimizer) -> Self {
         self.openai_state.cost_optimizer = Arc::new(RwLock::new(optimizer));
         self
     }
 
+    /// The LLM router serving requests
+    pub fn llm_router(&self) -> Arc<LLMRouter> {
+        self.openai_state.llm_router.clone()
+    }
+
+    /// The cost optimizer tracking spend and budgets
+    pub fn cost_optimizer(&self) -> Arc<RwLock<CostOptimizer>> {
+        self.openai_state.cost_optimizer.clone()
+    }
+
     /// Create the Axum router with all API routes
     pub fn create_router(&self) -> Router {
         let mut app = Router::new();
 
         // Add OpenAI-compatible API routes if enabled
         if self.config.enable_openai_api {
-            let openai_router = Router::new()
+            let mut openai_router = Router::new()
                 // Models endpoints
                 .route("/v1/models", get(list_models))
                 .route("/v1/models/:model_id", get(get_model))
                 // Chat completions endpoint (both streaming and non-streaming)
                 .route("/v1/chat/completions", post(chat_completions))
+                // Abort a streaming completion and its provider request
+                .route(
+                    "/v1/chat/completions/:request_id/cancel",
+                    post(handlers::cancel_chat_completion),
+                )
+                // Chat completion streaming over WebSocket
+                .route("/v1/chat/ws", get(chat_ws::chat_websocket))
                 // Embeddings endpoint
                 .route("/v1/embeddings", post(handlers::embeddings))
+                // Content moderation in OpenAI's format
+                .route("/v1/moderations", post(moderations::create_moderation))
+                // Audio transcription and speech
+                .route(
+                    "/v1/audio/transcriptions",
+                    post(audio::create_transcription)
+                        .layer(DefaultBodyLimit::max(audio::MAX_TRANSCRIPTION_UPLOAD_BYTES)),
+                )
+                .route("/v1/audio/speech", post(audio::create_speech))
+                // Image generation
+                .route("/v1/images/generations", post(images::create_image))
+                // Prompt token counts, as budget and context-window checks estimate them
+                .route("/v1/tokenize", post(handlers::tokenize))
+                // Provider key validation for setup UIs
+                .route(
+                    "/v1/admin/providers/:provider_type/validate",
+                    post(handlers::validate_provider_key),
+                )
+                // Live log tail for operators
+                .route("/admin/logs/stream", get(handlers::stream_logs))
+                // Read-only switch for upgrades and storage migrations
+                .route(
+                    "/v1/admin/maintenance",
+                    get(handlers::get_maintenance).put(handlers::set_maintenance),
+                )
+                // Failed authentications, lockouts and anomalies
+                .route("/v1/admin/audit/events", get(handlers::list_audit_events))
+                // Append-only trail of auth, key usage, grants, tool calls and admin changes
+                .route("/v1/admin/audit/records", get(handlers::list_audit_records))
+                // Routing decision traces
+                .route(
+                    "/v1/requests/:request_id/routing",
+                    get(handlers::get_request_routing),
+                )
+                // Recorded cost of a request
+                .route(
+                    "/v1/requests/:request_id/cost",
+                    get(handlers::get_request_cost),
+                )
+                // Usage export for billing systems
+                .route("/v1/usage/export", get(usage_export::export_usage))
+                // Costs per user, project, model, provider or day for chargeback
+                .route(
+                    "/v1/analytics/chargeback",
+                    get(chargeback::chargeback_report),
+                )
+                // Provider billing exports checked against recorded costs
+                .route(
+                    "/v1/analytics/reconciliation",
+                    post(reconciliation::reconcile_invoice).layer(DefaultBodyLimit::max(
+                        reconciliation::MAX_BILLING_EXPORT_BYTES,
+                    )),
+                )
+                // A budget's open period and the periods it closed
+                .route(
+                    "/v1/analytics/budgets/:budget_id/periods",
+                    get(budget_periods::get_budget_periods),
+                )
+                // Tenant onboarding: registration, quotas, API keys, budgets and usage
+                .route(
+                    "/v1/admin/tenants",
+                    get(tenants::list_tenants).post(tenants::create_tenant),
+                )
+                .route(
+                    "/v1/admin/tenants/:tenant_id",
+                    get(tenants::get_tenant).delete(tenants::delete_tenant),
+                )
+                .route(
+                    "/v1/admin/tenants/:tenant_id/quota",
+                    put(tenants::set_tenant_quota),
+                )
+                .route(
+                    "/v1/admin/tenants/:tenant_id/api-keys",
+                    post(tenants::issue_tenant_api_key),
+                )
+                .route(
+                    "/v1/admin/tenants/:tenant_id/api-keys/:key_id",
+                    delete(tenants::revoke_tenant_api_key),
+                )
+                .route(
+                    "/v1/admin/tenants/:tenant_id/budget",
+                    put(tenants::set_tenant_budget),
+                )
+                .route(
+                    "/v1/admin/tenants/:tenant_id/usage",
+                    get(tenants::get_tenant_usage),
+                )
+                // Service accounts for CI systems and external workers
+                .route(
+                    "/v1/admin/service-accounts",
+                    get(service_accounts::list_service_accounts)
+                        .post(service_accounts::create_service_account),
+                )
+                .route(
+                    "/v1/admin/service-accounts/:account_id",
+                    get(service_accounts::get_service_account)
+                        .delete(service_accounts::delete_service_account),
+                )
+                .route(
+                    "/v1/admin/service-accounts/:account_id/tokens",
+                    post(service_accounts::mint_service_token),
+                )
+                .route(
+                    "/v1/admin/service-accounts/:account_id/tokens/:token_id",
+                    delete(service_accounts::revoke_service_token),
+                )
+                // Scoped, rate-limited API keys for every API surface
+                .route(
+                    "/v1/admin/api-keys",
+                    get(api_keys::list_api_keys).post(api_keys::create_api_key),
+                )
+                .route(
+                    "/v1/admin/api-keys/:key_id",
+                    get(api_keys::get_api_key).delete(api_keys::revoke_api_key),
+                )
+                .route(
+                    "/v1/admin/api-keys/:key_id/rotate",
+                    post(api_keys::rotate_api_key),
+                )
+                // Roles of API keys, service accounts and MCP installations
+                .route("/v1/admin/roles", get(roles::list_role_assignments))
+                .route(
+                    "/v1/admin/roles/:subject",
+                    get(roles::get_role_assignment)
+                        .put(roles::assign_role)
+                        .delete(roles::unassign_role),
+                )
+                // Per-tenant routing policies
+                .route(
+                    "/v1/tenants/:tenant_id/routing-policy",
+                    get(handlers::get_tenant_routing_policy)
+                        .put(handlers::set_tenant_routing_policy)
+                        .delete(handlers::delete_tenant_routing_policy),
+                )
                 // Health check
                 .route("/health", get(health_check))
                 .route("/v1/health", get(health_check))
-                // Add OpenAI state
-                .with_state(self.openai_state.clone());
+                // Reject writes while maintenance mode is on
+                .layer(axum::middleware::from_fn_with_state(
+                    self.openai_state.clone(),
+                    handlers::reject_writes_during_maintenance,
+                ))
+                // Record admin writes on the audit trail
+                .layer(axum::middleware::from_fn_with_state(
+                    self.openai_state.clone(),
+                    handlers::record_admin_mutations,
+                ))
+                // Reject oversized and malformed requests before they reach providers
+                .layer(axum::middleware::from_fn_with_state(
+                    Arc::new(validation::RequestLimits::from_config(&self.config)),
+                    validation::validate_request,
+                ));
+
+            // Require an API key before anything else runs
+            if self.config.api_key_required {
+                openai_router = openai_router.layer(axum::middleware::from_fn_with_state(
+                    self.openai_state.clone(),
+                    api_keys::require_api_key,
+                ));
+            }
 
-            app = app.merge(openai_router);
+            // Add OpenAI state
+            app = app.merge(openai_router.with_state(self.openai_state.clone()));
         }
 
+        // Version and feature discovery for SDKs, served whichever APIs are enabled
+        let meta_router = Router::new()
+            .route("/v1/meta", get(meta::get_meta))
+            .with_state(Arc::new(meta::ServerMeta::from_config(&self.config)));
+        app = app.merge(meta_router);
+
         // Add MCP server routes if enabled
         if self.config.enable_mcp_server {
             let mcp_server =
-                mcp_server::CircuitBreakerMCPServer::with_manager(self.mcp_manager.clone());
+                mcp_server::CircuitBreakerMCPServer::with_manager(self.mcp_manager.clone())
+                    .with_api_key_required(self.config.api_key_required)
+                    .with_sampling(self.llm_router(), self.cost_optimizer());
             let mcp_router = mcp_server.create_router();
             app = app.merge(mcp_router);
 
@@ -174,6 +406,9 @@ impl CircuitBreakerApiServer {
         // Add fallback for unknown routes
         app = app.fallback(not_found);
 
+        // Let authentication throttling see who is really connected
+        app = app.layer(axum::middleware::from_fn(record_peer_addr));
+
         // Add CORS if enabled
         if self.config.cors_enabled {
             app.layer(CorsLayer::permissive())
@@ -187,6 +422,23 @@ impl CircuitBreakerApiServer {
         // Setup OAuth providers before starting the server
         self.setup_oauth().await?;
 
+        // Reap SSE channels left behind by clients that disconnected
+        if self.config.enable_mcp_server {
+            mcp_server::spawn_sse_reaper();
+            // Renew OAuth tokens before tool calls run into expired ones
+            self.mcp_manager.oauth_manager.clone().spawn_token_refresher();
+        }
+
+        // Close budget periods as they end, carrying balances into the next ones
+        if self.config.enable_openai_api {
+            self.openai_state
+                .cost_optimizer
+                .read()
+                .await
+                .budget_manager()
+                .spawn_rollover();
+        }
+
         let app = self.create_router();
         let addr = format!("{}:{}", self.config.host, self.config.port);
 
@@ -197,6 +449,15 @@ impl CircuitBreakerApiServer {
         if self.config.enable_openai_api {
             info!("   OpenAI-compatible API:");
             info!("     POST http://{}/v1/chat/completions", addr);
+            info!(
+                "     POST http://{}/v1/chat/completions/{{request_id}}/cancel",
+                addr
+            );
+            info!("     WS   http://{}/v1/chat/ws", addr);
+            info!("     POST http://{}/v1/moderations", addr);
+            info!("     POST http://{}/v1/audio/transcriptions", addr);
+            info!("     POST http://{}/v1/audio/speech", addr);
+            info!("     POST http://{}/v1/images/generations", addr);
             info!("     GET  http://{}/v1/models", addr);
             info!("     GET  http://{}/health", addr);
         }
@@ -211,16 +472,23 @@ impl CircuitBreakerApiServer {
             info!("     POST http://{}/mcp/{{instance_id}}", addr);
         }
 
+        info!("   Server metadata:");
+        info!("     GET  http://{}/v1/meta", addr);
+
         info!("📋 Configuration:");
         info!("   CORS enabled: {}", self.config.cors_enabled);
         info!("   API key required: {}", self.config.api_key_required);
         info!("   Streaming enabled: {}", self.config.enable_streaming);
         info!("   OpenAI API enabled: {}", self.config.enable_openai_api);
         info!("   MCP server enabled: {}", self.config.enable_mcp_server);
+        info!(
+            "   Chat moderation enabled: {}",
+            self.config.moderate_chat_completions
+        );
 
         // Start the server
         axum::Server::bind(&addr.parse()?)
-            .serve(app.into_make_service())
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
             .await?;
 
         Ok(())
@@ -278,6 +546,24 @@ impl CircuitBreakerApiServerBuilder {
         self
     }
 
+    /// Reject JSON request bodies larger than `max_body_bytes`
+    pub fn with_max_body_bytes(mut self, max_body_bytes: usize) -> Self {
+        self.config.max_body_bytes = max_body_bytes;
+        self
+    }
+
+    /// Reject chat completions carrying more than `max_messages` messages
+    pub fn with_max_messages(mut self, max_messages: usize) -> Self {
+        self.config.max_messages_per_request = Some(max_messages);
+        self
+    }
+
+    /// Reject `params` in requests for `model`, or models it prefixes when it ends in `*`
+    pub fn with_disallowed_params(mut self, model: impl Into<String>, params: Vec<String>) -> Self {
+        self.config.disallowed_params.insert(model.into(), params);
+        self
+    }
+
     pub fn with_rate_limit(mut self, requests_per_minute: u32) -> Self {
         self.config.rate_limit_per_minute = Some(requests_per_minute);
         self
@@ -308,6 +594,12 @@ impl CircuitBreakerApiServerBuilder {
         self
     }
 
+    /// Moderate chat completion requests before routing them
+    pub fn with_chat_moderation(mut self, enabled: bool) -> Self {
+        self.config.moderate_chat_completions = enabled;
+        self
+    }
+
     pub async fn build_async(self) -> CircuitBreakerApiServer {
         let mut server = if let Some(nats_url) = self.nats_url {
             CircuitBreakerApiServer::with_nats_storage(self.config, &nats_url)
@@ -442,4 +734,263 @@ mod tests {
 
         assert_eq!(response.status(), StatusCode::OK);
     }
+
+    #[tokio::test]
+    async fn test_meta_endpoint_without_openai_api() {
+        use axum::body::HttpBody;
+
+        let app = create_mcp_only_server().create_router();
+
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .method(Method::GET)
+                    .uri("/v1/meta")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().data().await.unwrap().unwrap();
+        let meta: meta::MetaResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(meta.meta.version, env!("CARGO_PKG_VERSION"));
+        assert!(meta.meta.supports("mcp"));
+        assert!(!meta.meta.supports("chat_completions"));
+    }
+
+    #[tokio::test]
+    async fn test_api_key_required() {
+        use crate::api_keys::{ApiKeyScope, ApiKeys, NewApiKey};
+
+        let app = OpenAIApiServerBuilder::new()
+            .with_api_key_required(true)
+            .with_mcp_server(false)
+            .build()
+            .create_router();
+        let get = |uri: &str, key: Option<&str>| {
+            let mut request = axum::http::Request::builder().method(Method::GET).uri(uri);
+            if let Some(key) = key {
+                request = request.header("x-api-key", key);
+            }
+            request.body(axum::body::Body::empty()).unwrap()
+        };
+        let mint = |scopes: Vec<ApiKeyScope>| async move {
+            ApiKeys::global()
+                .create(NewApiKey {
+                    name: "router test".to_string(),
+                    scopes,
+                    ..Default::default()
+                })
+                .await
+                .unwrap()
+                .secret
+        };
+
+        let response = app.clone().oneshot(get("/health", None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.clone().oneshot(get("/v1/models", None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let mcp_only = mint(vec![ApiKeyScope::Mcp]).await;
+        let response = app
+            .clone()
+            .oneshot(get("/v1/models", Some(&mcp_only)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let llm = mint(vec![ApiKeyScope::Llm]).await;
+        let response = app.oneshot(get("/v1/models", Some(&llm))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_admin_mutations_are_audited() {
+        use axum::body::HttpBody;
+
+        let app = OpenAIApiServerBuilder::new()
+            .build()
+            .with_admin_token("audit-secret")
+            .create_router();
+        let request = |method: Method, uri: &str, token: &str| {
+            axum::http::Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("authorization", format!("Bearer {}", token))
+                .extension(axum::extract::ConnectInfo(SocketAddr::from((
+                    [198, 51, 100, 74],
+                    40000,
+                ))))
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(request(
+                Method::DELETE,
+                "/v1/admin/roles/api_key:missing",
+                "wrong",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = app
+            .clone()
+            .oneshot(request(
+                Method::DELETE,
+                "/v1/admin/tenants/audited",
+                "audit-secret",
+            ))
+            .await
+            .unwrap();
+        assert!(!response.status().is_success());
+
+        let response = app
+            .oneshot(request(
+                Method::GET,
+                "/v1/admin/audit/records?category=admin_mutation&ip=198.51.100.74",
+                "audit-secret",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().data().await.unwrap().unwrap();
+        let records: Vec<crate::audit_trail::AuditRecord> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].action, "DELETE /v1/admin/tenants/:tenant_id");
+        assert_eq!(records[0].tenant_id.as_deref(), Some("audited"));
+        assert_eq!(records[0].actor.as_deref(), Some("admin"));
+        assert_eq!(records[1].action, "DELETE /v1/admin/roles/:subject");
+        assert_eq!(records[1].outcome, crate::audit_trail::AuditOutcome::Denied);
+        assert_eq!(records[1].actor, None);
+    }
+
+    #[tokio::test]
+    async fn test_roles_limit_api_keys() {
+        use crate::api_keys::{ApiKeyScope, ApiKeys, NewApiKey};
+        use crate::rbac::{Role, RoleAssignments, Subject};
+
+        let app = OpenAIApiServerBuilder::new()
+            .with_api_key_required(true)
+            .build()
+            .create_router();
+        let minted = ApiKeys::global()
+            .create(NewApiKey {
+                name: "viewer".to_string(),
+                scopes: vec![ApiKeyScope::Llm, ApiKeyScope::Mcp],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        RoleAssignments::global()
+            .assign(Subject::ApiKey(minted.key.id.clone()), Role::Viewer)
+            .await
+            .unwrap();
+        let request = |method: Method, uri: &str, body: Option<serde_json::Value>| {
+            let builder = axum::http::Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("x-api-key", &minted.secret);
+            match body {
+                Some(body) => builder
+                    .header("content-type", "application/json")
+                    .body(axum::body::Body::from(body.to_string())),
+                None => builder.body(axum::body::Body::empty()),
+            }
+            .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(request(Method::GET, "/v1/models", None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let completion = serde_json::json!({
+            "model": "gpt-4",
+            "messages": [{"role": "user", "content": "Hello"}]
+        });
+        let response = app
+            .clone()
+            .oneshot(request(Method::POST, "/v1/chat/completions", Some(completion)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // Viewers may list an MCP instance's tools but not call them
+        let call = |method: &str| serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": {}});
+        let response = app
+            .clone()
+            .oneshot(request(Method::POST, "/mcp/missing", Some(call("tools/call"))))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = app
+            .clone()
+            .oneshot(request(Method::POST, "/mcp/missing", Some(call("tools/list"))))
+            .await
+            .unwrap();
+        assert_ne!(response.status(), StatusCode::FORBIDDEN);
+        let response = app
+            .oneshot(request(
+                Method::POST,
+                "/mcp/instances",
+                Some(serde_json::json!({})),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_request_validation() {
+        use axum::body::HttpBody;
+
+        let app = OpenAIApiServerBuilder::new()
+            .with_max_body_bytes(256)
+            .with_max_tokens(1024)
+            .with_mcp_server(false)
+            .build()
+            .create_router();
+        let post = |body: String| {
+            axum::http::Request::builder()
+                .method(Method::POST)
+                .uri("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(body))
+                .unwrap()
+        };
+        let error = |response: axum::response::Response| async move {
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            let body = response.into_body().data().await.unwrap().unwrap();
+            serde_json::from_slice::<types::ErrorResponse>(&body)
+                .unwrap()
+                .error
+        };
+
+        let truncated = post("{\"model\": ".to_string());
+        let response = app.clone().oneshot(truncated).await.unwrap();
+        assert_eq!(
+            error(response).await.error_code,
+            Some(crate::ErrorCode::InvalidInput)
+        );
+
+        let greedy = serde_json::json!({
+            "model": "gpt-4",
+            "messages": [{"role": "user", "content": "Hi"}],
+            "max_tokens": 100000
+        });
+        let response = app.clone().oneshot(post(greedy.to_string())).await.unwrap();
+        assert_eq!(error(response).await.param.as_deref(), Some("max_tokens"));
+
+        let oversized = serde_json::json!({
+            "model": "gpt-4",
+            "messages": [{"role": "user", "content": "x".repeat(512)}]
+        });
+        let response = app.oneshot(post(oversized.to_string())).await.unwrap();
+        assert_eq!(error(response).await.code.as_deref(), Some("request_too_large"));
+    }
 }
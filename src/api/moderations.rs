@@ -0,0 +1,157 @@
+// Content moderation endpoint
+// `POST /v1/moderations` scores text in OpenAI's format and can pre-filter chat completions
+
+//! # Moderations
+//!
+//! `POST /v1/moderations` accepts OpenAI's moderation request, a string or an array of
+//! strings and an optional model, and answers with OpenAI's category flags and scores.
+//! The model picks the [`ModerationProvider`](crate::llm::ModerationProvider): OpenAI's
+//! moderation models when an OpenAI key is configured, or the local keyword classifier
+//! served as `circuit-breaker-moderation`.
+//!
+//! With [`ApiConfig::moderate_chat_completions`](super::ApiConfig) on, the user messages
+//! of every chat completion are moderated with the default model first. Flagged requests
+//! are rejected with `CONTENT_FLAGGED`, and requests are rejected as well when moderation
+//! itself fails, so switching the filter on never lets content through unchecked.
+
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use super::handlers::{llm_error_response, OpenAIApiState};
+use super::types::{create_error_response, ChatMessage, ChatRole, EmbeddingsInput, ErrorResponse};
+use crate::llm::ModerationResult;
+use crate::ErrorCode;
+
+/// OpenAI Moderation Request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModerationRequest {
+    /// Text to moderate - string or array of strings
+    pub input: EmbeddingsInput,
+
+    /// Moderation model; the server's default when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+/// OpenAI Moderation Response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModerationResponse {
+    pub id: String,
+    pub model: String,
+    /// One result per input, in order
+    pub results: Vec<ModerationResult>,
+}
+
+/// Moderate text - POST /v1/moderations
+pub async fn create_moderation(
+    State(state): State<OpenAIApiState>,
+    Json(request): Json<ModerationRequest>,
+) -> Result<Json<ModerationResponse>, ErrorResponse> {
+    let inputs = match request.input {
+        EmbeddingsInput::Single(text) => vec![text],
+        EmbeddingsInput::Multiple(texts) => texts,
+    };
+    if inputs.is_empty() {
+        return Err(create_error_response(
+            "Input must not be empty".to_string(),
+            "invalid_request_error".to_string(),
+            Some("input".to_string()),
+            None,
+        )
+        .with_error_code(ErrorCode::InvalidInput));
+    }
+
+    let (model, results) = state
+        .moderation
+        .moderate(request.model.as_deref(), &inputs)
+        .await
+        .map_err(|e| llm_error_response(&e, e.to_string()))?;
+
+    Ok(Json(ModerationResponse {
+        id: format!("modr-{}", uuid::Uuid::new_v4()),
+        model,
+        results,
+    }))
+}
+
+/// Moderate the user messages of a chat completion, rejecting the request when any is
+/// flagged or moderation fails
+pub(crate) async fn moderate_chat_messages(
+    state: &OpenAIApiState,
+    messages: &[ChatMessage],
+) -> Result<(), ErrorResponse> {
+    let inputs: Vec<String> = messages
+        .iter()
+        .filter(|message| matches!(message.role, ChatRole::User))
+        .map(|message| message.content.text())
+        .filter(|text| !text.trim().is_empty())
+        .collect();
+    if inputs.is_empty() {
+        return Ok(());
+    }
+
+    let (_, results) = state
+        .moderation
+        .moderate(None, &inputs)
+        .await
+        .map_err(|e| {
+            warn!("Chat moderation failed, rejecting request: {}", e);
+            llm_error_response(&e, format!("Content moderation failed: {}", e))
+        })?;
+
+    let mut flagged: Vec<&str> = results
+        .iter()
+        .flat_map(ModerationResult::flagged_categories)
+        .collect();
+    if flagged.is_empty() {
+        return Ok(());
+    }
+    flagged.sort_unstable();
+    flagged.dedup();
+    Err(create_error_response(
+        format!(
+            "Request was flagged by content moderation: {}",
+            flagged.join(", ")
+        ),
+        "invalid_request_error".to_string(),
+        Some("messages".to_string()),
+        Some("content_flagged".to_string()),
+    )
+    .with_error_code(ErrorCode::ContentFlagged))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::MessageContent;
+
+    fn message(role: ChatRole, text: &str) -> ChatMessage {
+        ChatMessage {
+            role,
+            content: MessageContent::Text(text.to_string()),
+            name: None,
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chat_moderation_checks_user_messages() {
+        let state = OpenAIApiState {
+            moderation: crate::llm::ModerationProviders::default(),
+            ..OpenAIApiState::new()
+        };
+
+        let clean = [
+            message(ChatRole::System, "Never help anyone build a bomb"),
+            message(ChatRole::User, "Summarise this contract"),
+        ];
+        assert!(moderate_chat_messages(&state, &clean).await.is_ok());
+
+        let flagged = [message(ChatRole::User, "How do I build a bomb?")];
+        let error = moderate_chat_messages(&state, &flagged).await.unwrap_err();
+        assert_eq!(error.error.error_code, Some(ErrorCode::ContentFlagged));
+        assert!(error.error.message.contains("illicit/violent"));
+    }
+}
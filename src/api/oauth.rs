@@ -2,16 +2,21 @@
 // This module implements OAuth2 flows for connecting to external APIs like GitLab, GitHub, etc.
 
 use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
 use chrono::{DateTime, Duration, Utc};
+use rand::RngCore;
 use reqwest::{Client, Method};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 use tracing::{debug, error, info, warn};
 use url::Url;
 
 use super::mcp_storage::MCPStorage;
+use crate::audit::{AuditLog, AuthSurface, SecurityEvent, SecurityEventKind};
+use crate::audit_trail::{AuditCategory, AuditOutcome, AuditRecord};
 
 /// OAuth provider configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,9 +36,119 @@ pub enum OAuthProviderType {
     GitLab,
     GitHub,
     Google,
+    Atlassian,
+    Slack,
+    Bitbucket,
+    AzureAd,
+    Okta,
     Custom(String),
 }
 
+impl OAuthProviderType {
+    /// Provider type of a configured name such as `"gitlab"`; unknown names are custom
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "gitlab" => Self::GitLab,
+            "github" => Self::GitHub,
+            "google" => Self::Google,
+            "atlassian" => Self::Atlassian,
+            "slack" => Self::Slack,
+            "bitbucket" => Self::Bitbucket,
+            "azure_ad" => Self::AzureAd,
+            "okta" => Self::Okta,
+            custom => Self::Custom(custom.to_string()),
+        }
+    }
+
+    /// Name of the provider in configuration and token keys
+    pub fn name(&self) -> &str {
+        match self {
+            Self::GitLab => "gitlab",
+            Self::GitHub => "github",
+            Self::Google => "google",
+            Self::Atlassian => "atlassian",
+            Self::Slack => "slack",
+            Self::Bitbucket => "bitbucket",
+            Self::AzureAd => "azure_ad",
+            Self::Okta => "okta",
+            Self::Custom(name) => name,
+        }
+    }
+
+    /// Authorization and token endpoints of providers with fixed hosts. GitLab's are
+    /// those of gitlab.com and Azure AD's those of its multi-tenant `common` endpoint;
+    /// Okta and custom providers have none.
+    pub fn default_endpoints(&self) -> Option<(&'static str, &'static str)> {
+        match self {
+            Self::GitLab => Some((
+                "https://gitlab.com/oauth/authorize",
+                "https://gitlab.com/oauth/token",
+            )),
+            Self::GitHub => Some((
+                "https://github.com/login/oauth/authorize",
+                "https://github.com/login/oauth/access_token",
+            )),
+            Self::Google => Some((
+                "https://accounts.google.com/o/oauth2/v2/auth",
+                "https://oauth2.googleapis.com/token",
+            )),
+            Self::Atlassian => Some((
+                "https://auth.atlassian.com/authorize",
+                "https://auth.atlassian.com/oauth/token",
+            )),
+            Self::Slack => Some((
+                "https://slack.com/oauth/v2/authorize",
+                "https://slack.com/api/oauth.v2.access",
+            )),
+            Self::Bitbucket => Some((
+                "https://bitbucket.org/site/oauth2/authorize",
+                "https://bitbucket.org/site/oauth2/access_token",
+            )),
+            Self::AzureAd => Some((
+                "https://login.microsoftonline.com/common/oauth2/v2.0/authorize",
+                "https://login.microsoftonline.com/common/oauth2/v2.0/token",
+            )),
+            Self::Okta | Self::Custom(_) => None,
+        }
+    }
+
+    /// Whether authorization requests carry a PKCE challenge. GitLab and Google
+    /// require one from public clients; Atlassian, Slack and Bitbucket do not take one.
+    pub fn uses_pkce(&self) -> bool {
+        !matches!(self, Self::Atlassian | Self::Slack | Self::Bitbucket)
+    }
+
+    /// Separator of scopes in authorization requests and token responses
+    pub fn scope_separator(&self) -> &'static str {
+        match self {
+            Self::Slack => ",",
+            _ => " ",
+        }
+    }
+
+    /// Whether authorization requests name scopes. Bitbucket grants the scopes
+    /// configured on its OAuth consumer and ignores requested ones.
+    pub fn requests_scopes(&self) -> bool {
+        !matches!(self, Self::Bitbucket)
+    }
+
+    /// Whether the client authenticates to the token endpoint with HTTP Basic
+    /// rather than form parameters
+    pub fn uses_basic_client_auth(&self) -> bool {
+        matches!(self, Self::Bitbucket)
+    }
+}
+
+/// How often the background refresher looks for expiring tokens
+pub const TOKEN_REFRESH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How long before expiry the background refresher renews a token; ahead of the
+/// five minutes at which requests refresh tokens themselves
+const TOKEN_REFRESH_AHEAD_MINUTES: i64 = 10;
+
+/// How long a user has to finish an authorization flow
+pub const OAUTH_FLOW_MAX_AGE: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
 /// OAuth token response from provider
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OAuthTokenResponse {
@@ -41,6 +156,8 @@ pub struct OAuthTokenResponse {
     pub token_type: String,
     pub expires_in: Option<u64>,
     pub refresh_token: Option<String>,
+    // Bitbucket names the granted scopes `scopes`
+    #[serde(alias = "scopes")]
     pub scope: Option<String>,
 }
 
@@ -67,6 +184,140 @@ pub struct OAuthAuthRequest {
     pub state: String,
     pub redirect_uri: String,
     pub scope: Vec<String>,
+    /// PKCE verifier whose S256 challenge went out with the authorization request
+    #[serde(default)]
+    pub code_verifier: Option<String>,
+    #[serde(default = "Utc::now")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl OAuthAuthRequest {
+    fn is_expired(&self) -> bool {
+        Utc::now() - self.created_at
+            > Duration::from_std(OAUTH_FLOW_MAX_AGE).unwrap_or_else(|_| Duration::minutes(10))
+    }
+}
+
+/// Audit record of the grant a flow asked for; the user acts if known, else the installation
+fn grant_record(auth_request: &OAuthAuthRequest, outcome: AuditOutcome) -> AuditRecord {
+    AuditRecord::new(AuditCategory::OauthGrant, "oauth.grant", outcome)
+        .with_actor(
+            auth_request
+                .user_id
+                .clone()
+                .unwrap_or_else(|| auth_request.installation_id.clone()),
+        )
+        .with_target(format!(
+            "{}/{}",
+            auth_request.provider_type.name(),
+            auth_request.installation_id
+        ))
+}
+
+/// A random PKCE code verifier: 32 bytes, base64url encoded to 43 characters
+pub fn generate_code_verifier() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// The S256 PKCE challenge of `verifier`
+pub fn code_challenge(verifier: &str) -> String {
+    general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+/// A token endpoint response. GitHub and Slack answer errors with a success status,
+/// so a body without an access token is an error either way.
+fn parse_token_response(body: serde_json::Value) -> Result<OAuthTokenResponse> {
+    if body.get("access_token").is_none() {
+        let error = body
+            .get("error")
+            .and_then(|error| error.as_str())
+            .unwrap_or("no access token in response");
+        return match body.get("error_description").and_then(|d| d.as_str()) {
+            Some(description) => Err(anyhow!("{} - {}", error, description)),
+            None => Err(anyhow!("{}", error)),
+        };
+    }
+    Ok(serde_json::from_value(body)?)
+}
+
+/// The account behind an OAuth token, in the same shape for every provider
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OAuthUserInfo {
+    pub provider_type: OAuthProviderType,
+    /// Stable account ID at the provider
+    pub id: String,
+    pub username: Option<String>,
+    pub email: Option<String>,
+    pub name: Option<String>,
+}
+
+impl OAuthUserInfo {
+    /// Read the user info response of a provider
+    pub fn from_response(
+        provider_type: &OAuthProviderType,
+        body: &serde_json::Value,
+    ) -> Result<Self> {
+        // Slack reports failures in the body of a successful response
+        if body.get("ok").and_then(|ok| ok.as_bool()) == Some(false) {
+            return Err(anyhow!(
+                "User info request failed: {}",
+                body.get("error")
+                    .and_then(|e| e.as_str())
+                    .unwrap_or("unknown error")
+            ));
+        }
+
+        // ID, username, email and name fields, in order of preference
+        let (id, username, email, name): (&[&str], &[&str], &[&str], &[&str]) = match provider_type
+        {
+            OAuthProviderType::GitLab => (&["id"], &["username"], &["email"], &["name"]),
+            OAuthProviderType::GitHub => (&["id"], &["login"], &["email"], &["name"]),
+            OAuthProviderType::Atlassian => (&["account_id"], &["nickname"], &["email"], &["name"]),
+            OAuthProviderType::Slack => (&["user_id"], &["user"], &[], &[]),
+            OAuthProviderType::Bitbucket => (
+                &["uuid", "account_id"],
+                &["username", "nickname"],
+                &[],
+                &["display_name"],
+            ),
+            // OpenID Connect userinfo
+            _ => (&["sub"], &["preferred_username"], &["email"], &["name"]),
+        };
+        let field = |names: &[&str]| {
+            names.iter().find_map(|name| match body.get(*name)? {
+                serde_json::Value::String(value) => Some(value.clone()),
+                serde_json::Value::Number(value) => Some(value.to_string()),
+                _ => None,
+            })
+        };
+
+        Ok(Self {
+            provider_type: provider_type.clone(),
+            id: field(id).ok_or_else(|| anyhow!("User info response has no account ID"))?,
+            username: field(username),
+            email: field(email),
+            name: field(name),
+        })
+    }
+}
+
+/// User info endpoint of a provider, derived from its token endpoint where the
+/// provider is self-hosted or tenant-specific
+pub fn userinfo_url(provider: &OAuthProvider) -> Option<String> {
+    let url = match provider.provider_type {
+        OAuthProviderType::GitLab => provider.token_url.replace("/oauth/token", "/api/v4/user"),
+        OAuthProviderType::GitHub => "https://api.github.com/user".to_string(),
+        OAuthProviderType::Google => "https://openidconnect.googleapis.com/v1/userinfo".to_string(),
+        OAuthProviderType::Atlassian => "https://api.atlassian.com/me".to_string(),
+        OAuthProviderType::Slack => "https://slack.com/api/auth.test".to_string(),
+        OAuthProviderType::Bitbucket => "https://api.bitbucket.org/2.0/user".to_string(),
+        OAuthProviderType::AzureAd => "https://graph.microsoft.com/oidc/userinfo".to_string(),
+        OAuthProviderType::Okta => provider.token_url.replace("/v1/token", "/v1/userinfo"),
+        OAuthProviderType::Custom(_) => return None,
+    };
+    Some(url)
 }
 
 /// OAuth callback parameters
@@ -85,6 +336,11 @@ pub struct OAuthManager {
     pending_auths: Arc<RwLock<HashMap<String, OAuthAuthRequest>>>,
     http_client: Client,
     storage: Option<Arc<dyn MCPStorage>>,
+    // Background and on-demand refreshes must not both spend a rotating refresh token
+    refresh_lock: Arc<Mutex<()>>,
+    // Tokens whose failed refresh was already alerted, until one succeeds
+    refresh_failures: Arc<RwLock<HashSet<String>>>,
+    audit: AuditLog,
 }
 
 impl OAuthManager {
@@ -96,6 +352,9 @@ impl OAuthManager {
             pending_auths: Arc::new(RwLock::new(HashMap::new())),
             http_client: Client::new(),
             storage: None,
+            refresh_lock: Arc::new(Mutex::new(())),
+            refresh_failures: Arc::new(RwLock::new(HashSet::new())),
+            audit: AuditLog::global(),
         }
     }
 
@@ -107,6 +366,9 @@ impl OAuthManager {
             pending_auths: Arc::new(RwLock::new(HashMap::new())),
             http_client: Client::new(),
             storage: Some(storage),
+            refresh_lock: Arc::new(Mutex::new(())),
+            refresh_failures: Arc::new(RwLock::new(HashSet::new())),
+            audit: AuditLog::global(),
         }
     }
 
@@ -146,6 +408,8 @@ impl OAuthManager {
         // Use provided scope or default from provider
         let scope = scope.unwrap_or(provider.scope.clone());
 
+        let code_verifier = provider_type.uses_pkce().then(generate_code_verifier);
+
         // Store pending auth request
         let auth_request = OAuthAuthRequest {
             provider_type: provider_type.clone(),
@@ -154,10 +418,19 @@ impl OAuthManager {
             state: state.clone(),
             redirect_uri: redirect_uri.clone(),
             scope: scope.clone(),
+            code_verifier: code_verifier.clone(),
+            created_at: Utc::now(),
         };
 
+        // The callback may reach another node, or this one after a restart
+        if let Some(storage) = &self.storage {
+            if let Err(e) = storage.store_oauth_flow(&state, &auth_request).await {
+                warn!("Failed to store OAuth flow in persistent storage: {}", e);
+            }
+        }
         {
             let mut pending = self.pending_auths.write().await;
+            pending.retain(|_, pending| !pending.is_expired());
             pending.insert(state.clone(), auth_request);
         }
 
@@ -167,8 +440,24 @@ impl OAuthManager {
             .append_pair("client_id", &provider.client_id)
             .append_pair("redirect_uri", &redirect_uri)
             .append_pair("response_type", "code")
-            .append_pair("state", &state)
-            .append_pair("scope", &scope.join(" "));
+            .append_pair("state", &state);
+        if provider_type.requests_scopes() {
+            url.query_pairs_mut()
+                .append_pair("scope", &scope.join(provider_type.scope_separator()));
+        }
+
+        if let Some(code_verifier) = &code_verifier {
+            url.query_pairs_mut()
+                .append_pair("code_challenge", &code_challenge(code_verifier))
+                .append_pair("code_challenge_method", "S256");
+        }
+
+        // Atlassian issues tokens for its cloud API only when asked for that audience
+        if provider_type == OAuthProviderType::Atlassian {
+            url.query_pairs_mut()
+                .append_pair("audience", "api.atlassian.com")
+                .append_pair("prompt", "consent");
+        }
 
         info!(
             "Generated OAuth authorization URL for {:?}, installation: {}",
@@ -181,20 +470,24 @@ impl OAuthManager {
     /// Handle OAuth callback and exchange code for token
     pub async fn handle_callback(&self, callback: OAuthCallback) -> Result<StoredOAuthToken> {
         // Verify state parameter
-        let auth_request = {
-            let mut pending = self.pending_auths.write().await;
-            pending
-                .remove(&callback.state)
-                .ok_or_else(|| anyhow!("Invalid or expired state parameter"))?
-        };
+        let auth_request = self
+            .take_pending_auth(&callback.state)
+            .await
+            .filter(|auth_request| !auth_request.is_expired())
+            .ok_or_else(|| anyhow!("Invalid or expired state parameter"))?;
 
         // Check for OAuth errors
         if let Some(error) = callback.error {
-            return Err(anyhow!(
+            let message = format!(
                 "OAuth error: {} - {}",
                 error,
                 callback.error_description.unwrap_or_default()
-            ));
+            );
+            self.audit
+                .trail()
+                .record(grant_record(&auth_request, AuditOutcome::Denied).with_detail(&message))
+                .await;
+            return Err(anyhow!(message));
         }
 
         let provider = {
@@ -206,15 +499,35 @@ impl OAuthManager {
         };
 
         // Exchange code for token
-        let token_response = self
-            .exchange_code_for_token(&provider, &callback.code, &auth_request.redirect_uri)
-            .await?;
+        let token_response = match self
+            .exchange_code_for_token(
+                &provider,
+                &callback.code,
+                &auth_request.redirect_uri,
+                auth_request.code_verifier.as_deref(),
+            )
+            .await
+        {
+            Ok(token_response) => token_response,
+            Err(e) => {
+                self.audit
+                    .trail()
+                    .record(
+                        grant_record(&auth_request, AuditOutcome::Failure)
+                            .with_detail(e.to_string()),
+                    )
+                    .await;
+                return Err(e);
+            }
+        };
 
         // Calculate expiration
         let expires_at = token_response
             .expires_in
             .map(|expires_in| Utc::now() + Duration::seconds(expires_in as i64));
 
+        let granted = grant_record(&auth_request, AuditOutcome::Success);
+
         // Create stored token
         let stored_token = StoredOAuthToken {
             provider_type: auth_request.provider_type.clone(),
@@ -225,7 +538,13 @@ impl OAuthManager {
             expires_at,
             scope: token_response
                 .scope
-                .map(|s| s.split(' ').map(|s| s.to_string()).collect())
+                .map(|scope| {
+                    scope
+                        .split(auth_request.provider_type.scope_separator())
+                        .filter(|scope| !scope.is_empty())
+                        .map(|scope| scope.to_string())
+                        .collect()
+                })
                 .unwrap_or(auth_request.scope),
             created_at: Utc::now(),
             last_refreshed: None,
@@ -258,10 +577,38 @@ impl OAuthManager {
             "Successfully stored OAuth token for {:?}, installation: {}",
             auth_request.provider_type, auth_request.installation_id
         );
+        self.audit
+            .trail()
+            .record(
+                granted.with_detail(format!("Granted scopes: {}", stored_token.scope.join(" "))),
+            )
+            .await;
 
         Ok(stored_token)
     }
 
+    /// Remove the pending flow of `state`, wherever it was started
+    async fn take_pending_auth(&self, state: &str) -> Option<OAuthAuthRequest> {
+        let pending = self.pending_auths.write().await.remove(state);
+        let Some(storage) = &self.storage else {
+            return pending;
+        };
+        // A flow is used once, so it leaves storage whichever copy is used
+        let stored = match storage.get_oauth_flow(state).await {
+            Ok(stored) => stored,
+            Err(e) => {
+                warn!("Failed to read OAuth flow from persistent storage: {}", e);
+                None
+            }
+        };
+        if stored.is_some() {
+            if let Err(e) = storage.delete_oauth_flow(state).await {
+                warn!("Failed to delete OAuth flow from persistent storage: {}", e);
+            }
+        }
+        pending.or(stored)
+    }
+
     /// Get stored token for a provider and installation
     pub async fn get_token(
         &self,
@@ -269,17 +616,12 @@ impl OAuthManager {
         installation_id: &str,
         user_id: Option<&str>,
     ) -> Result<StoredOAuthToken> {
-        let provider_name = match provider_type {
-            OAuthProviderType::GitLab => "gitlab",
-            OAuthProviderType::GitHub => "github", 
-            OAuthProviderType::Google => "google",
-            OAuthProviderType::Custom(name) => name,
-        };
-        
+        let provider_name = provider_type.name();
+
         // Use same key format as generate_token_key
         let safe_installation_id = installation_id.replace(".", "_").replace(" ", "_");
         let safe_user_id = user_id.unwrap_or("system").replace(".", "_").replace(" ", "_");
-        
+
         let token_key = format!(
             "{}_{}_{}",
             provider_name,
@@ -333,7 +675,7 @@ impl OAuthManager {
 
         // Check if token needs refresh
         if self.token_needs_refresh(&token) {
-            token = self.refresh_token(token).await?;
+            token = self.refresh_or_alert(token).await?;
         }
 
         Ok(token)
@@ -341,6 +683,16 @@ impl OAuthManager {
 
     /// Refresh an OAuth token
     pub async fn refresh_token(&self, mut token: StoredOAuthToken) -> Result<StoredOAuthToken> {
+        let _refreshing = self.refresh_lock.lock().await;
+        let token_key = self.generate_token_key(&token);
+
+        // Another refresh may have replaced the token while this one waited
+        if let Some(current) = self.tokens.read().await.get(&token_key) {
+            if current.access_token != token.access_token && !self.token_needs_refresh(current) {
+                return Ok(current.clone());
+            }
+        }
+
         let refresh_token = token
             .refresh_token
             .as_ref()
@@ -356,26 +708,20 @@ impl OAuthManager {
 
         debug!("Refreshing OAuth token for {:?}", token.provider_type);
 
-        let params = [
+        let mut params = vec![
             ("grant_type", "refresh_token"),
-            ("refresh_token", refresh_token),
-            ("client_id", &provider.client_id),
-            ("client_secret", &provider.client_secret),
+            ("refresh_token", refresh_token.as_str()),
         ];
-
-        let response = self
-            .http_client
-            .post(&provider.token_url)
-            .form(&params)
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow!("Token refresh failed: {}", error_text));
+        // Azure AD issues a refreshed token for the scopes asked again
+        let scope = token.scope.join(" ");
+        if token.provider_type == OAuthProviderType::AzureAd {
+            params.push(("scope", &scope));
         }
 
-        let token_response: OAuthTokenResponse = response.json().await?;
+        let token_response = self
+            .request_token(&provider, params)
+            .await
+            .map_err(|e| anyhow!("Token refresh failed: {}", e))?;
 
         // Update token
         token.access_token = token_response.access_token;
@@ -387,9 +733,6 @@ impl OAuthManager {
             .map(|expires_in| Utc::now() + Duration::seconds(expires_in as i64));
         token.last_refreshed = Some(Utc::now());
 
-        // Store updated token
-        let token_key = self.generate_token_key(&token);
-        
         // Store in memory cache
         {
             let mut tokens = self.tokens.write().await;
@@ -414,6 +757,81 @@ impl OAuthManager {
         Ok(token)
     }
 
+    /// Refresh a token, alerting operators when the provider refuses
+    async fn refresh_or_alert(&self, token: StoredOAuthToken) -> Result<StoredOAuthToken> {
+        let token_key = self.generate_token_key(&token);
+        match self.refresh_token(token.clone()).await {
+            Ok(refreshed) => {
+                self.refresh_failures.write().await.remove(&token_key);
+                Ok(refreshed)
+            }
+            Err(e) => {
+                error!("Failed to refresh OAuth token {}: {}", token_key, e);
+                // One alert per token until a refresh succeeds again
+                if self.refresh_failures.write().await.insert(token_key) {
+                    let expiry = token
+                        .expires_at
+                        .map(|expires_at| format!("expires at {}", expires_at))
+                        .unwrap_or_else(|| "was rejected".to_string());
+                    self.audit.publish(SecurityEvent::new(
+                        SecurityEventKind::TokenRefreshFailure,
+                        AuthSurface::OAuthToken,
+                        None,
+                        Some(&token.installation_id),
+                        format!(
+                            "{:?} OAuth token could not be refreshed and {}: {}",
+                            token.provider_type, expiry, e
+                        ),
+                    ));
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Refresh every known token expiring soon that has a refresh token; returns how
+    /// many were refreshed
+    pub async fn refresh_expiring_tokens(&self) -> usize {
+        let stored = match &self.storage {
+            Some(storage) => storage.list_oauth_tokens().await.map_err(|e| {
+                warn!("Failed to list OAuth tokens for refresh: {}", e);
+            }),
+            None => Err(()),
+        };
+        let tokens: Vec<StoredOAuthToken> = match stored {
+            Ok(tokens) => tokens.into_iter().map(|(_, token)| token).collect(),
+            Err(()) => self.tokens.read().await.values().cloned().collect(),
+        };
+
+        let mut refreshed = 0;
+        for token in tokens {
+            let expiring = token.expires_at.is_some_and(|expires_at| {
+                expires_at <= Utc::now() + Duration::minutes(TOKEN_REFRESH_AHEAD_MINUTES)
+            });
+            if expiring
+                && token.refresh_token.is_some()
+                && self.refresh_or_alert(token).await.is_ok()
+            {
+                refreshed += 1;
+            }
+        }
+        refreshed
+    }
+
+    /// Spawn the background task that refreshes tokens before they expire
+    pub fn spawn_token_refresher(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(TOKEN_REFRESH_INTERVAL);
+            loop {
+                ticker.tick().await;
+                let refreshed = self.refresh_expiring_tokens().await;
+                if refreshed > 0 {
+                    info!("Refreshed {} expiring OAuth tokens", refreshed);
+                }
+            }
+        })
+    }
+
     /// Revoke an OAuth token
     pub async fn revoke_token(
         &self,
@@ -421,17 +839,12 @@ impl OAuthManager {
         installation_id: &str,
         user_id: Option<&str>,
     ) -> Result<()> {
-        let provider_name = match provider_type {
-            OAuthProviderType::GitLab => "gitlab",
-            OAuthProviderType::GitHub => "github", 
-            OAuthProviderType::Google => "google",
-            OAuthProviderType::Custom(name) => name,
-        };
-        
+        let provider_name = provider_type.name();
+
         // Use same key format as generate_token_key
         let safe_installation_id = installation_id.replace(".", "_").replace(" ", "_");
         let safe_user_id = user_id.unwrap_or("system").replace(".", "_").replace(" ", "_");
-        
+
         let token_key = format!(
             "{}_{}_{}",
             provider_name,
@@ -468,6 +881,43 @@ impl OAuthManager {
         Ok(())
     }
 
+    /// Fetch the account behind the stored token of an installation
+    pub async fn fetch_user_info(
+        &self,
+        provider_type: &OAuthProviderType,
+        installation_id: &str,
+        user_id: Option<&str>,
+    ) -> Result<OAuthUserInfo> {
+        let url = {
+            let providers = self.providers.read().await;
+            let provider = providers
+                .get(provider_type)
+                .ok_or_else(|| anyhow!("Provider {:?} not found", provider_type))?;
+            userinfo_url(provider)
+                .ok_or_else(|| anyhow!("Provider {:?} has no user info endpoint", provider_type))?
+        };
+
+        let mut headers = HashMap::new();
+        headers.insert("Accept".to_string(), "application/json".to_string());
+        // GitHub rejects requests without a user agent
+        headers.insert("User-Agent".to_string(), "circuit-breaker".to_string());
+        let response = self
+            .make_authenticated_request(
+                provider_type,
+                installation_id,
+                user_id,
+                Method::GET,
+                &url,
+                None,
+                Some(headers),
+            )
+            .await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("User info request failed: {}", response.status()));
+        }
+        OAuthUserInfo::from_response(provider_type, &response.json().await?)
+    }
+
     /// Make authenticated API request using stored OAuth token
     pub async fn make_authenticated_request(
         &self,
@@ -483,6 +933,35 @@ impl OAuthManager {
             .get_token(provider_type, installation_id, user_id)
             .await?;
 
+        let response = self
+            .send_with_token(&token, method.clone(), url, body.clone(), headers.clone())
+            .await?;
+        if response.status().as_u16() != 401 || token.refresh_token.is_none() {
+            return Ok(response);
+        }
+
+        // The provider may revoke a token before its expiry; retry once with a fresh one
+        warn!(
+            "Received 401, refreshing OAuth token for {:?}",
+            provider_type
+        );
+        match self.refresh_or_alert(token).await {
+            Ok(token) => {
+                self.send_with_token(&token, method, url, body, headers)
+                    .await
+            }
+            Err(_) => Ok(response),
+        }
+    }
+
+    async fn send_with_token(
+        &self,
+        token: &StoredOAuthToken,
+        method: Method,
+        url: &str,
+        body: Option<String>,
+        headers: Option<HashMap<String, String>>,
+    ) -> Result<reqwest::Response> {
         let mut request = self.http_client.request(method, url);
 
         // Add OAuth token to Authorization header
@@ -500,16 +979,7 @@ impl OAuthManager {
             request = request.body(body);
         }
 
-        let response = request.send().await?;
-
-        if response.status().as_u16() == 401 {
-            warn!(
-                "Received 401, token may have expired for {:?}",
-                provider_type
-            );
-        }
-
-        Ok(response)
+        Ok(request.send().await?)
     }
 
     /// Exchange authorization code for access token
@@ -518,34 +988,54 @@ impl OAuthManager {
         provider: &OAuthProvider,
         code: &str,
         redirect_uri: &str,
+        code_verifier: Option<&str>,
     ) -> Result<OAuthTokenResponse> {
-        let params = [
+        let mut params = vec![
             ("grant_type", "authorization_code"),
             ("code", code),
             ("redirect_uri", redirect_uri),
-            ("client_id", &provider.client_id),
-            ("client_secret", &provider.client_secret),
         ];
+        if let Some(code_verifier) = code_verifier {
+            params.push(("code_verifier", code_verifier));
+        }
 
         debug!(
             "Exchanging OAuth code for token with {:?}",
             provider.provider_type
         );
 
-        let response = self
+        self.request_token(provider, params)
+            .await
+            .map_err(|e| anyhow!("Token exchange failed: {}", e))
+    }
+
+    /// Post a grant to the provider's token endpoint, authenticating the client the
+    /// way the provider expects
+    async fn request_token(
+        &self,
+        provider: &OAuthProvider,
+        mut params: Vec<(&str, &str)>,
+    ) -> Result<OAuthTokenResponse> {
+        let mut request = self
             .http_client
             .post(&provider.token_url)
-            .form(&params)
-            .send()
-            .await?;
+            // GitHub answers form-encoded unless asked for JSON
+            .header("Accept", "application/json");
+        if provider.provider_type.uses_basic_client_auth() {
+            request = request.basic_auth(&provider.client_id, Some(&provider.client_secret));
+        } else {
+            params.push(("client_id", &provider.client_id));
+            // Public clients have no secret to send
+            if !provider.client_secret.is_empty() {
+                params.push(("client_secret", &provider.client_secret));
+            }
+        }
 
+        let response = request.form(&params).send().await?;
         if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow!("Token exchange failed: {}", error_text));
+            return Err(anyhow!("{}", response.text().await?));
         }
-
-        let token_response: OAuthTokenResponse = response.json().await?;
-        Ok(token_response)
+        parse_token_response(response.json().await?)
     }
 
     /// Check if token needs refresh
@@ -561,13 +1051,8 @@ impl OAuthManager {
 
     /// Generate a key for storing tokens
     fn generate_token_key(&self, token: &StoredOAuthToken) -> String {
-        let provider_name = match &token.provider_type {
-            OAuthProviderType::GitLab => "gitlab",
-            OAuthProviderType::GitHub => "github", 
-            OAuthProviderType::Google => "google",
-            OAuthProviderType::Custom(name) => name,
-        };
-        
+        let provider_name = token.provider_type.name();
+
         // NATS KV keys have restrictions: cannot be empty, start/end with '.', or contain certain chars
         // Replace any problematic characters with underscores
         let safe_installation_id = token.installation_id.replace(".", "_").replace(" ", "_");
@@ -642,6 +1127,42 @@ impl OAuthManager {
                     return Err(anyhow!("Token revocation failed: {}", error_text));
                 }
             }
+            OAuthProviderType::Slack => {
+                // Slack revokes the token it is called with
+                let response = self
+                    .http_client
+                    .post("https://slack.com/api/auth.revoke")
+                    .bearer_auth(&token.access_token)
+                    .send()
+                    .await?;
+
+                let body: serde_json::Value = response.json().await?;
+                if body.get("ok").and_then(|ok| ok.as_bool()) != Some(true) {
+                    return Err(anyhow!("Token revocation failed: {}", body));
+                }
+            }
+            OAuthProviderType::Okta => {
+                // Okta supports RFC 7009 revocation next to its token endpoint
+                let revoke_url = provider.token_url.replace("/v1/token", "/v1/revoke");
+                let params = [
+                    ("token", token.access_token.as_str()),
+                    ("token_type_hint", "access_token"),
+                    ("client_id", &provider.client_id),
+                    ("client_secret", &provider.client_secret),
+                ];
+
+                let response = self
+                    .http_client
+                    .post(&revoke_url)
+                    .form(&params)
+                    .send()
+                    .await?;
+
+                if !response.status().is_success() {
+                    let error_text = response.text().await?;
+                    return Err(anyhow!("Token revocation failed: {}", error_text));
+                }
+            }
             _ => {
                 // Provider doesn't support revocation or we don't know how
                 return Ok(());
@@ -707,6 +1228,140 @@ impl OAuthManager {
                 "openid".to_string(),
                 "email".to_string(),
                 "profile".to_string(),
+                "https://www.googleapis.com/auth/drive.readonly".to_string(),
+                "https://www.googleapis.com/auth/documents.readonly".to_string(),
+            ],
+            redirect_uri,
+        }
+    }
+
+    /// Create Atlassian (Jira Cloud) OAuth provider configuration
+    pub fn create_atlassian_provider(
+        client_id: String,
+        client_secret: String,
+        redirect_uri: String,
+    ) -> OAuthProvider {
+        OAuthProvider {
+            provider_type: OAuthProviderType::Atlassian,
+            client_id,
+            client_secret,
+            auth_url: "https://auth.atlassian.com/authorize".to_string(),
+            token_url: "https://auth.atlassian.com/oauth/token".to_string(),
+            scope: vec![
+                "read:jira-work".to_string(),
+                "write:jira-work".to_string(),
+                "offline_access".to_string(),
+            ],
+            redirect_uri,
+        }
+    }
+
+    /// Create Slack OAuth provider configuration for a bot installed in a workspace
+    pub fn create_slack_provider(
+        client_id: String,
+        client_secret: String,
+        redirect_uri: String,
+    ) -> OAuthProvider {
+        OAuthProvider {
+            provider_type: OAuthProviderType::Slack,
+            client_id,
+            client_secret,
+            auth_url: "https://slack.com/oauth/v2/authorize".to_string(),
+            token_url: "https://slack.com/api/oauth.v2.access".to_string(),
+            scope: vec![
+                "channels:read".to_string(),
+                "channels:history".to_string(),
+                "groups:read".to_string(),
+                "groups:history".to_string(),
+                "chat:write".to_string(),
+            ],
+            redirect_uri,
+        }
+    }
+
+    /// Create Bitbucket Cloud OAuth provider configuration. Bitbucket grants the
+    /// scopes configured on the OAuth consumer; these only describe them.
+    pub fn create_bitbucket_provider(
+        client_id: String,
+        client_secret: String,
+        redirect_uri: String,
+    ) -> OAuthProvider {
+        let (auth_url, token_url) = OAuthProviderType::Bitbucket.default_endpoints().unwrap();
+        OAuthProvider {
+            provider_type: OAuthProviderType::Bitbucket,
+            client_id,
+            client_secret,
+            auth_url: auth_url.to_string(),
+            token_url: token_url.to_string(),
+            scope: vec![
+                "account".to_string(),
+                "repository".to_string(),
+                "pullrequest".to_string(),
+            ],
+            redirect_uri,
+        }
+    }
+
+    /// Create Azure AD (Microsoft Entra ID) OAuth provider configuration for a tenant,
+    /// or for any work and personal account with the `common` tenant
+    pub fn create_azure_ad_provider(
+        client_id: String,
+        client_secret: String,
+        redirect_uri: String,
+        tenant: Option<String>,
+    ) -> OAuthProvider {
+        let tenant = tenant.unwrap_or_else(|| "common".to_string());
+        let base_url = format!("https://login.microsoftonline.com/{}/oauth2/v2.0", tenant);
+
+        OAuthProvider {
+            provider_type: OAuthProviderType::AzureAd,
+            client_id,
+            client_secret,
+            auth_url: format!("{}/authorize", base_url),
+            token_url: format!("{}/token", base_url),
+            scope: vec![
+                "openid".to_string(),
+                "profile".to_string(),
+                "email".to_string(),
+                "offline_access".to_string(),
+                "User.Read".to_string(),
+            ],
+            redirect_uri,
+        }
+    }
+
+    /// Create Okta OAuth provider configuration for an org domain such as
+    /// `dev-123456.okta.com`, on its `default` authorization server unless another is given
+    pub fn create_okta_provider(
+        client_id: String,
+        client_secret: String,
+        redirect_uri: String,
+        okta_domain: String,
+        authorization_server: Option<String>,
+    ) -> OAuthProvider {
+        let okta_domain = okta_domain.trim_end_matches('/');
+        let origin = if okta_domain.starts_with("http://") || okta_domain.starts_with("https://") {
+            okta_domain.to_string()
+        } else {
+            format!("https://{}", okta_domain)
+        };
+        let base_url = format!(
+            "{}/oauth2/{}/v1",
+            origin,
+            authorization_server.unwrap_or_else(|| "default".to_string())
+        );
+
+        OAuthProvider {
+            provider_type: OAuthProviderType::Okta,
+            client_id,
+            client_secret,
+            auth_url: format!("{}/authorize", base_url),
+            token_url: format!("{}/token", base_url),
+            scope: vec![
+                "openid".to_string(),
+                "profile".to_string(),
+                "email".to_string(),
+                "offline_access".to_string(),
             ],
             redirect_uri,
         }
@@ -805,6 +1460,251 @@ mod tests {
         assert!(auth_url.contains("client_id=test-id"));
         assert!(auth_url.contains("response_type=code"));
         assert!(auth_url.contains("state="));
+        assert!(auth_url.contains("code_challenge_method=S256"));
+    }
+
+    #[test]
+    fn test_code_challenge() {
+        // Example from RFC 7636, appendix B
+        assert_eq!(
+            code_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+        let verifier = generate_code_verifier();
+        assert_eq!(verifier.len(), 43);
+        assert_ne!(verifier, generate_code_verifier());
+    }
+
+    #[tokio::test]
+    async fn test_pending_flow_survives_restart() {
+        let storage: Arc<dyn MCPStorage> =
+            Arc::new(super::super::mcp_storage::InMemoryMCPStorage::default());
+        let provider = OAuthManager::create_gitlab_provider(
+            "test-id".to_string(),
+            "test-secret".to_string(),
+            "http://localhost/callback".to_string(),
+            None,
+        );
+        let manager = OAuthManager::with_storage(storage.clone());
+        manager.register_provider(provider).await.unwrap();
+        let auth_url = manager
+            .get_authorization_url(
+                OAuthProviderType::GitLab,
+                "test-installation".to_string(),
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let state = Url::parse(&auth_url)
+            .unwrap()
+            .query_pairs()
+            .find(|(name, _)| name == "state")
+            .map(|(_, state)| state.to_string())
+            .unwrap();
+
+        // A manager without the in-memory flow still finds it, and only once
+        let restarted = OAuthManager::with_storage(storage);
+        let flow = restarted.take_pending_auth(&state).await.unwrap();
+        assert_eq!(flow.installation_id, "test-installation");
+        assert!(auth_url.contains(&code_challenge(flow.code_verifier.as_deref().unwrap())));
+        assert!(restarted.take_pending_auth(&state).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_atlassian_authorization_url() {
+        let manager = OAuthManager::new();
+        let provider = OAuthManager::create_atlassian_provider(
+            "test-id".to_string(),
+            "test-secret".to_string(),
+            "http://localhost/callback".to_string(),
+        );
+
+        manager.register_provider(provider).await.unwrap();
+
+        let auth_url = manager
+            .get_authorization_url(
+                OAuthProviderType::Atlassian,
+                "test-installation".to_string(),
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert!(auth_url.starts_with("https://auth.atlassian.com/authorize"));
+        assert!(auth_url.contains("audience=api.atlassian.com"));
+        assert!(auth_url.contains("prompt=consent"));
+        assert!(!auth_url.contains("code_challenge"));
+    }
+
+    #[tokio::test]
+    async fn test_slack_authorization_url() {
+        let manager = OAuthManager::new();
+        let provider = OAuthManager::create_slack_provider(
+            "test-id".to_string(),
+            "test-secret".to_string(),
+            "http://localhost/callback".to_string(),
+        );
+
+        manager.register_provider(provider).await.unwrap();
+
+        let auth_url = manager
+            .get_authorization_url(
+                OAuthProviderType::Slack,
+                "test-installation".to_string(),
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert!(auth_url.starts_with("https://slack.com/oauth/v2/authorize"));
+        assert!(auth_url.contains("scope=channels%3Aread%2Cchannels%3Ahistory"));
+    }
+
+    #[test]
+    fn test_provider_names_round_trip() {
+        for provider_type in [
+            OAuthProviderType::GitLab,
+            OAuthProviderType::GitHub,
+            OAuthProviderType::Google,
+            OAuthProviderType::Atlassian,
+            OAuthProviderType::Slack,
+            OAuthProviderType::Bitbucket,
+            OAuthProviderType::AzureAd,
+            OAuthProviderType::Okta,
+            OAuthProviderType::Custom("internal".to_string()),
+        ] {
+            assert_eq!(
+                OAuthProviderType::from_name(provider_type.name()),
+                provider_type
+            );
+        }
+    }
+
+    #[test]
+    fn test_tenant_and_domain_endpoints() {
+        let azure = OAuthManager::create_azure_ad_provider(
+            "test-id".to_string(),
+            "test-secret".to_string(),
+            "http://localhost/callback".to_string(),
+            Some("contoso.onmicrosoft.com".to_string()),
+        );
+        assert_eq!(
+            azure.token_url,
+            "https://login.microsoftonline.com/contoso.onmicrosoft.com/oauth2/v2.0/token"
+        );
+
+        let okta = OAuthManager::create_okta_provider(
+            "test-id".to_string(),
+            "test-secret".to_string(),
+            "http://localhost/callback".to_string(),
+            "dev-123456.okta.com/".to_string(),
+            None,
+        );
+        assert_eq!(
+            okta.auth_url,
+            "https://dev-123456.okta.com/oauth2/default/v1/authorize"
+        );
+        assert_eq!(
+            userinfo_url(&okta).unwrap(),
+            "https://dev-123456.okta.com/oauth2/default/v1/userinfo"
+        );
+        assert!(OAuthProviderType::Okta.default_endpoints().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_bitbucket_authorization_url() {
+        let manager = OAuthManager::new();
+        let provider = OAuthManager::create_bitbucket_provider(
+            "test-id".to_string(),
+            "test-secret".to_string(),
+            "http://localhost/callback".to_string(),
+        );
+
+        manager.register_provider(provider).await.unwrap();
+
+        let auth_url = manager
+            .get_authorization_url(
+                OAuthProviderType::Bitbucket,
+                "test-installation".to_string(),
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert!(auth_url.starts_with("https://bitbucket.org/site/oauth2/authorize"));
+        assert!(!auth_url.contains("scope="));
+        assert!(!auth_url.contains("code_challenge"));
+    }
+
+    #[test]
+    fn test_token_errors_in_successful_responses() {
+        let error = parse_token_response(serde_json::json!({
+            "error": "bad_verification_code",
+            "error_description": "The code passed is incorrect or expired."
+        }))
+        .unwrap_err();
+        assert!(error.to_string().starts_with("bad_verification_code"));
+
+        let error = parse_token_response(serde_json::json!({
+            "ok": false,
+            "error": "invalid_code"
+        }))
+        .unwrap_err();
+        assert_eq!(error.to_string(), "invalid_code");
+
+        let token = parse_token_response(serde_json::json!({
+            "access_token": "token",
+            "token_type": "bearer",
+            "scopes": "account repository"
+        }))
+        .unwrap();
+        assert_eq!(token.scope.as_deref(), Some("account repository"));
+    }
+
+    #[test]
+    fn test_user_info_is_normalized() {
+        let github = OAuthUserInfo::from_response(
+            &OAuthProviderType::GitHub,
+            &serde_json::json!({"id": 42, "login": "octocat", "name": "The Octocat", "email": null}),
+        )
+        .unwrap();
+        assert_eq!(github.id, "42");
+        assert_eq!(github.username.as_deref(), Some("octocat"));
+        assert_eq!(github.email, None);
+
+        let okta = OAuthUserInfo::from_response(
+            &OAuthProviderType::Okta,
+            &serde_json::json!({
+                "sub": "00uid4BxXw6I6TV4m0g3",
+                "preferred_username": "jane@example.com",
+                "email": "jane@example.com",
+                "name": "Jane Doe"
+            }),
+        )
+        .unwrap();
+        assert_eq!(okta.id, "00uid4BxXw6I6TV4m0g3");
+        assert_eq!(okta.name.as_deref(), Some("Jane Doe"));
+
+        let bitbucket = OAuthUserInfo::from_response(
+            &OAuthProviderType::Bitbucket,
+            &serde_json::json!({"uuid": "{1234}", "nickname": "jdoe", "display_name": "J Doe"}),
+        )
+        .unwrap();
+        assert_eq!(bitbucket.username.as_deref(), Some("jdoe"));
+
+        assert!(OAuthUserInfo::from_response(
+            &OAuthProviderType::Slack,
+            &serde_json::json!({"ok": false, "error": "invalid_auth"}),
+        )
+        .is_err());
     }
 
     #[test]
@@ -826,6 +1726,160 @@ mod tests {
         assert_eq!(key, "gitlab_install-123_user-456");
     }
 
+    /// Serve a token endpoint that refreshes to `fresh` or refuses, and an API that
+    /// only takes `fresh`; returns the server's base URL
+    async fn mock_provider() -> String {
+        use axum::{http::HeaderMap, http::StatusCode, routing::get, routing::post, Router};
+        let app = Router::new()
+            .route(
+                "/token",
+                post(|| async {
+                    axum::Json(serde_json::json!({
+                        "access_token": "fresh",
+                        "token_type": "bearer",
+                        "expires_in": 3600,
+                        "refresh_token": "rotated"
+                    }))
+                }),
+            )
+            .route("/refuse", post(|| async { StatusCode::BAD_REQUEST }))
+            .route(
+                "/basic",
+                post(|headers: HeaderMap, form: String| async move {
+                    let basic = headers
+                        .get("authorization")
+                        .is_some_and(|value| value.as_bytes().starts_with(b"Basic "));
+                    if !basic || form.contains("client_id") {
+                        return Err(StatusCode::UNAUTHORIZED);
+                    }
+                    Ok(axum::Json(serde_json::json!({
+                        "access_token": "fresh",
+                        "token_type": "bearer",
+                        "scopes": "account repository"
+                    })))
+                }),
+            )
+            .route(
+                "/api",
+                get(|headers: HeaderMap| async move {
+                    match headers.get("authorization") {
+                        Some(value) if value == "Bearer fresh" => StatusCode::OK,
+                        _ => StatusCode::UNAUTHORIZED,
+                    }
+                }),
+            );
+        let server =
+            axum::Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(app.into_make_service());
+        let url = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+        url
+    }
+
+    async fn manager_with_token(token_url: String, expires_in: Duration) -> OAuthManager {
+        let mut manager = OAuthManager::new();
+        manager.audit = AuditLog::new();
+        manager
+            .register_provider(OAuthProvider {
+                provider_type: OAuthProviderType::Custom("mock".to_string()),
+                client_id: "client".to_string(),
+                client_secret: String::new(),
+                auth_url: "http://localhost/authorize".to_string(),
+                token_url,
+                scope: vec![],
+                redirect_uri: "http://localhost/callback".to_string(),
+            })
+            .await
+            .unwrap();
+        let token = StoredOAuthToken {
+            provider_type: OAuthProviderType::Custom("mock".to_string()),
+            installation_id: "installation".to_string(),
+            user_id: None,
+            access_token: "stale".to_string(),
+            refresh_token: Some("refresh".to_string()),
+            expires_at: Some(Utc::now() + expires_in),
+            scope: vec![],
+            created_at: Utc::now(),
+            last_refreshed: None,
+        };
+        let key = manager.generate_token_key(&token);
+        manager.tokens.write().await.insert(key, token);
+        manager
+    }
+
+    #[tokio::test]
+    async fn test_refresher_renews_expiring_tokens() {
+        let provider = mock_provider().await;
+        let manager = manager_with_token(format!("{}/token", provider), Duration::minutes(8)).await;
+
+        assert_eq!(manager.refresh_expiring_tokens().await, 1);
+        let token = manager
+            .get_token(
+                &OAuthProviderType::Custom("mock".to_string()),
+                "installation",
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(token.access_token, "fresh");
+        assert_eq!(token.refresh_token.as_deref(), Some("rotated"));
+
+        // Renewed tokens are left alone until they near expiry again
+        assert_eq!(manager.refresh_expiring_tokens().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_failed_refresh_alerts_once() {
+        let provider = mock_provider().await;
+        let manager =
+            manager_with_token(format!("{}/refuse", provider), Duration::minutes(8)).await;
+
+        assert_eq!(manager.refresh_expiring_tokens().await, 0);
+        assert_eq!(manager.refresh_expiring_tokens().await, 0);
+        let alerts = manager
+            .audit
+            .recent(Some(SecurityEventKind::TokenRefreshFailure), 10);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].principal.as_deref(), Some("installation"));
+    }
+
+    #[tokio::test]
+    async fn test_unauthorized_request_retries_with_refreshed_token() {
+        let provider = mock_provider().await;
+        let manager = manager_with_token(format!("{}/token", provider), Duration::hours(1)).await;
+
+        let response = manager
+            .make_authenticated_request(
+                &OAuthProviderType::Custom("mock".to_string()),
+                "installation",
+                None,
+                Method::GET,
+                &format!("{}/api", provider),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+    }
+
+    #[tokio::test]
+    async fn test_bitbucket_authenticates_with_basic_auth() {
+        let url = mock_provider().await;
+        let mut provider = OAuthManager::create_bitbucket_provider(
+            "client".to_string(),
+            "secret".to_string(),
+            "http://localhost/callback".to_string(),
+        );
+        provider.token_url = format!("{}/basic", url);
+
+        let token = OAuthManager::new()
+            .exchange_code_for_token(&provider, "code", "http://localhost/callback", None)
+            .await
+            .unwrap();
+        assert_eq!(token.access_token, "fresh");
+        assert_eq!(token.scope.as_deref(), Some("account repository"));
+    }
+
     #[test]
     fn test_token_needs_refresh() {
         let manager = OAuthManager::new();
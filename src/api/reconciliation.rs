@@ -0,0 +1,548 @@
+// Provider invoice reconciliation
+// `POST /v1/analytics/reconciliation` compares a provider's billing export with recorded costs
+
+//! # Invoice Reconciliation
+//!
+//! Finance teams pay what providers bill, not what Circuit Breaker recorded. To show the
+//! two agree, `POST /v1/analytics/reconciliation?provider=openai` takes a provider's
+//! billing export as a CSV body and compares it with the costs the usage tracker recorded
+//! for that provider over the days the export covers.
+//!
+//! Columns are found by their header, so OpenAI's usage and cost exports and Anthropic's
+//! cost reports are read alike:
+//!
+//! - the day: `date`, `usage_date_utc`, `start_time` (RFC 3339, a date or Unix seconds)
+//!   and similar
+//! - the model: `model`, `model_id` or OpenAI's `line_item` (`"gpt-4o-2024-08-06, input"`)
+//! - the cost in USD: `cost_usd`, `cost`, `amount_value` and similar; rows whose
+//!   `currency` is not USD are refused
+//! - token counts, when present: `input_tokens`, `n_context_tokens_total`,
+//!   `output_tokens`, `n_generated_tokens_total` and similar
+//!
+//! Snapshot dates are dropped from model names on both sides, since providers bill the
+//! snapshot an alias resolved to. The report holds one row per day and model, marking
+//! costs that differ by more than `tolerance` (a fraction of the larger cost, 1% by
+//! default, and at least [`ABSOLUTE_TOLERANCE_USD`]) and costs only one side has. Like
+//! the other analytics reports, the endpoint requires the admin token.
+
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    Json,
+};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tracing::info;
+
+use super::handlers::{authorize_admin, cost_error_response, OpenAIApiState};
+use super::types::ErrorResponse;
+use super::usage_export::invalid_param;
+use crate::llm::{CostInfo, LLMProviderType};
+use crate::ErrorCode;
+
+/// Body limit of billing exports
+pub const MAX_BILLING_EXPORT_BYTES: usize = 20 * 1024 * 1024;
+
+/// Relative difference tolerated when `tolerance` is not given
+pub const DEFAULT_TOLERANCE: f64 = 0.01;
+
+/// Difference always tolerated, covering rounding on invoices
+pub const ABSOLUTE_TOLERANCE_USD: f64 = 0.01;
+
+const DAY_COLUMNS: &[&str] = &[
+    "date",
+    "day",
+    "usage_date",
+    "usage_date_utc",
+    "start_time",
+    "bucket_start",
+    "timestamp",
+];
+const MODEL_COLUMNS: &[&str] = &[
+    "model",
+    "model_id",
+    "model_name",
+    "snapshot_id",
+    "line_item",
+];
+const COST_COLUMNS: &[&str] = &[
+    "cost_usd",
+    "cost",
+    "amount_usd",
+    "amount_value",
+    "amount",
+    "total_cost",
+];
+const CURRENCY_COLUMNS: &[&str] = &["currency", "amount_currency"];
+const INPUT_TOKEN_COLUMNS: &[&str] = &[
+    "input_tokens",
+    "n_context_tokens_total",
+    "prompt_tokens",
+    "context_tokens",
+];
+const OUTPUT_TOKEN_COLUMNS: &[&str] = &[
+    "output_tokens",
+    "n_generated_tokens_total",
+    "completion_tokens",
+    "generated_tokens",
+];
+
+/// Query parameters of `POST /v1/analytics/reconciliation`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ReconciliationQuery {
+    /// Provider that issued the export, e.g. `openai` or `anthropic`
+    pub provider: String,
+    /// Relative difference tolerated, e.g. `0.01` for 1%
+    pub tolerance: Option<f64>,
+}
+
+/// One row of a provider billing export
+#[derive(Debug, Clone, PartialEq)]
+pub struct BillingLine {
+    pub day: NaiveDate,
+    /// Model name without its snapshot date
+    pub model: String,
+    pub cost_usd: f64,
+    pub input_tokens: Option<u64>,
+    pub output_tokens: Option<u64>,
+}
+
+/// How the two sides of a reconciliation row compare
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReconciliationStatus {
+    /// Costs agree within the tolerance
+    Matched,
+    /// Both sides have costs that differ by more than the tolerance
+    Discrepancy,
+    /// The provider billed costs Circuit Breaker did not record
+    MissingInternal,
+    /// Circuit Breaker recorded costs the provider did not bill
+    MissingProvider,
+}
+
+/// Costs of one day and model on both sides
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReconciliationRow {
+    pub day: NaiveDate,
+    pub model: String,
+    pub status: ReconciliationStatus,
+    pub provider_cost_usd: f64,
+    pub internal_cost_usd: f64,
+    /// Provider cost minus internal cost
+    pub difference_usd: f64,
+    /// Token counts from the export, when it has them
+    pub provider_input_tokens: Option<u64>,
+    pub provider_output_tokens: Option<u64>,
+    pub internal_requests: u64,
+    pub internal_input_tokens: u64,
+    pub internal_output_tokens: u64,
+}
+
+/// Body of `POST /v1/analytics/reconciliation`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReconciliationReport {
+    pub provider: String,
+    /// First and last day of the export
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub tolerance: f64,
+    pub provider_cost_usd: f64,
+    pub internal_cost_usd: f64,
+    pub difference_usd: f64,
+    /// Rows not matched
+    pub discrepancies: u64,
+    /// By day, then model
+    pub rows: Vec<ReconciliationRow>,
+}
+
+/// Fields of each CSV record, honouring quotes around separators, quotes and line breaks
+fn csv_records(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', true) => quoted = false,
+            ('"', false) if field.is_empty() => quoted = true,
+            (',', false) => record.push(std::mem::take(&mut field)),
+            ('\r', false) => {}
+            ('\n', false) => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            (c, _) => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+        .into_iter()
+        .filter(|record| record.iter().any(|field| !field.trim().is_empty()))
+        .collect()
+}
+
+/// Model name without a trailing snapshot date such as `-2024-08-06` or `-20241022`
+pub fn normalize_model(model: &str) -> String {
+    let model = model.trim().to_ascii_lowercase();
+    let bytes = model.as_bytes();
+    let is_date = |suffix: &[u8], dashes: &[usize]| {
+        suffix.iter().enumerate().all(|(i, b)| {
+            if dashes.contains(&i) {
+                *b == b'-'
+            } else {
+                b.is_ascii_digit()
+            }
+        })
+    };
+    for (len, dashes) in [(11, &[0, 5, 8][..]), (9, &[0][..])] {
+        if bytes.len() > len && is_date(&bytes[bytes.len() - len..], dashes) {
+            return model[..model.len() - len].to_string();
+        }
+    }
+    model
+}
+
+fn parse_day(value: &str) -> Option<NaiveDate> {
+    let value = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Utc).date_naive());
+    }
+    if let Ok(seconds) = value.parse::<i64>() {
+        return DateTime::from_timestamp(seconds, 0).map(|time| time.date_naive());
+    }
+    NaiveDate::parse_from_str(value.get(..10)?, "%Y-%m-%d").ok()
+}
+
+fn parse_amount(value: &str) -> Option<f64> {
+    let value: String = value
+        .trim()
+        .chars()
+        .filter(|c| !matches!(c, '$' | ',' | ' '))
+        .collect();
+    value.parse().ok().filter(|amount: &f64| amount.is_finite())
+}
+
+/// Read a provider billing export
+///
+/// Errors name the offending line, counting the header as line 1.
+pub fn parse_billing_export(text: &str) -> Result<Vec<BillingLine>, String> {
+    let mut records = csv_records(text).into_iter();
+    let header: Vec<String> = records
+        .next()
+        .ok_or("The billing export is empty")?
+        .iter()
+        .map(|name| name.trim().to_ascii_lowercase())
+        .collect();
+    let column = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| header.iter().position(|column| column == name))
+    };
+    let required = |names: &[&str], what: &str| {
+        column(names).ok_or_else(|| {
+            format!(
+                "The billing export has no {} column; expected one of {}",
+                what,
+                names.join(", ")
+            )
+        })
+    };
+    let day_column = required(DAY_COLUMNS, "date")?;
+    let model_column = required(MODEL_COLUMNS, "model")?;
+    let cost_column = required(COST_COLUMNS, "cost")?;
+    let currency_column = column(CURRENCY_COLUMNS);
+    let input_column = column(INPUT_TOKEN_COLUMNS);
+    let output_column = column(OUTPUT_TOKEN_COLUMNS);
+
+    let mut lines = Vec::new();
+    for (index, record) in records.enumerate() {
+        let line = index + 2;
+        let field = |column: usize| record.get(column).map(|value| value.trim()).unwrap_or("");
+        let tokens = |column: Option<usize>| {
+            column
+                .map(field)
+                .filter(|value| !value.is_empty())
+                .and_then(parse_amount)
+                .map(|tokens| tokens as u64)
+        };
+
+        if let Some(currency) = currency_column.map(field).filter(|c| !c.is_empty()) {
+            if !currency.eq_ignore_ascii_case("usd") {
+                return Err(format!(
+                    "Line {} is billed in {}; only USD exports can be reconciled",
+                    line, currency
+                ));
+            }
+        }
+        let day = parse_day(field(day_column))
+            .ok_or_else(|| format!("Line {} has an invalid date '{}'", line, field(day_column)))?;
+        // OpenAI's line items name the model, then what was billed
+        let model = field(model_column).split(',').next().unwrap_or("");
+        if model.trim().is_empty() {
+            return Err(format!("Line {} has no model", line));
+        }
+        let cost_usd = parse_amount(field(cost_column))
+            .ok_or_else(|| format!("Line {} has an invalid cost '{}'", line, field(cost_column)))?;
+
+        lines.push(BillingLine {
+            day,
+            model: normalize_model(model),
+            cost_usd,
+            input_tokens: tokens(input_column),
+            output_tokens: tokens(output_column),
+        });
+    }
+    if lines.is_empty() {
+        return Err("The billing export has no rows".to_string());
+    }
+    Ok(lines)
+}
+
+/// The row of a day and model, added when missing
+fn row<'a>(
+    rows: &'a mut BTreeMap<(NaiveDate, String), ReconciliationRow>,
+    day: NaiveDate,
+    model: &str,
+) -> &'a mut ReconciliationRow {
+    rows.entry((day, model.to_string()))
+        .or_insert_with(|| ReconciliationRow {
+            day,
+            model: model.to_string(),
+            status: ReconciliationStatus::Matched,
+            provider_cost_usd: 0.0,
+            internal_cost_usd: 0.0,
+            difference_usd: 0.0,
+            provider_input_tokens: None,
+            provider_output_tokens: None,
+            internal_requests: 0,
+            internal_input_tokens: 0,
+            internal_output_tokens: 0,
+        })
+}
+
+impl ReconciliationReport {
+    /// Compare billed lines with the recorded `costs` of `provider`, per day and model
+    pub fn build(
+        provider: &LLMProviderType,
+        lines: &[BillingLine],
+        costs: &[CostInfo],
+        tolerance: f64,
+    ) -> Self {
+        let from = lines.iter().map(|line| line.day).min().unwrap_or_default();
+        let to = lines.iter().map(|line| line.day).max().unwrap_or_default();
+        let mut report = Self {
+            provider: provider.to_string(),
+            from,
+            to,
+            tolerance,
+            provider_cost_usd: 0.0,
+            internal_cost_usd: 0.0,
+            difference_usd: 0.0,
+            discrepancies: 0,
+            rows: Vec::new(),
+        };
+
+        let mut rows: BTreeMap<(NaiveDate, String), ReconciliationRow> = BTreeMap::new();
+        for line in lines {
+            let row = row(&mut rows, line.day, &line.model);
+            row.provider_cost_usd += line.cost_usd;
+            if let Some(tokens) = line.input_tokens {
+                *row.provider_input_tokens.get_or_insert(0) += tokens;
+            }
+            if let Some(tokens) = line.output_tokens {
+                *row.provider_output_tokens.get_or_insert(0) += tokens;
+            }
+        }
+        let recorded = costs.iter().filter(|cost| {
+            cost.provider == *provider && (from..=to).contains(&cost.timestamp.date_naive())
+        });
+        for cost in recorded {
+            let row = row(
+                &mut rows,
+                cost.timestamp.date_naive(),
+                &normalize_model(&cost.model),
+            );
+            row.internal_cost_usd += cost.cost_usd;
+            row.internal_requests += 1;
+            row.internal_input_tokens += cost.input_tokens as u64;
+            row.internal_output_tokens += cost.output_tokens as u64;
+        }
+
+        for mut row in rows.into_values() {
+            row.difference_usd = row.provider_cost_usd - row.internal_cost_usd;
+            let allowed = (tolerance * row.provider_cost_usd.max(row.internal_cost_usd))
+                .max(ABSOLUTE_TOLERANCE_USD);
+            row.status = if row.difference_usd.abs() <= allowed {
+                ReconciliationStatus::Matched
+            } else if row.internal_requests == 0 {
+                ReconciliationStatus::MissingInternal
+            } else if row.provider_cost_usd == 0.0 {
+                ReconciliationStatus::MissingProvider
+            } else {
+                ReconciliationStatus::Discrepancy
+            };
+
+            report.provider_cost_usd += row.provider_cost_usd;
+            report.internal_cost_usd += row.internal_cost_usd;
+            if row.status != ReconciliationStatus::Matched {
+                report.discrepancies += 1;
+            }
+            report.rows.push(row);
+        }
+        report.difference_usd = report.provider_cost_usd - report.internal_cost_usd;
+        report
+    }
+}
+
+/// Reconcile a provider billing export with recorded costs - POST /v1/analytics/reconciliation
+pub async fn reconcile_invoice(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Query(query): Query<ReconciliationQuery>,
+    body: String,
+) -> Result<Json<ReconciliationReport>, ErrorResponse> {
+    authorize_admin(&state, &headers, "Invoice reconciliation")?;
+
+    let provider: LLMProviderType = query.provider.parse().map_err(|_| {
+        invalid_param(format!("Unknown provider '{}'", query.provider), "provider")
+            .with_error_code(ErrorCode::InvalidInput)
+    })?;
+    let tolerance = query.tolerance.unwrap_or(DEFAULT_TOLERANCE);
+    if !(0.0..1.0).contains(&tolerance) {
+        return Err(invalid_param(
+            format!(
+                "Invalid tolerance {}; expected a fraction of at least 0 and below 1",
+                tolerance
+            ),
+            "tolerance",
+        )
+        .with_error_code(ErrorCode::InvalidInput));
+    }
+    let lines = parse_billing_export(&body).map_err(|message| {
+        invalid_param(message, "body").with_error_code(ErrorCode::InvalidInput)
+    })?;
+
+    let from = lines.iter().map(|line| line.day).min().unwrap_or_default();
+    let to = lines.iter().map(|line| line.day).max().unwrap_or_default();
+    let start = from.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    let end = to.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc() + Duration::days(1)
+        - Duration::nanoseconds(1);
+    let usage_tracker = state.cost_optimizer.read().await.usage_tracker();
+    let costs = usage_tracker
+        .list_usage(start, end)
+        .await
+        .map_err(cost_error_response)?;
+
+    let report = ReconciliationReport::build(&provider, &lines, &costs, tolerance);
+    info!(
+        "Reconciled {} {} billing lines from {} to {}: {} discrepancies, ${:.2} difference",
+        lines.len(),
+        report.provider,
+        from,
+        to,
+        report.discrepancies,
+        report.difference_usd
+    );
+    Ok(Json(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn cost(provider: LLMProviderType, model: &str, day: &str, cost_usd: f64) -> CostInfo {
+        CostInfo {
+            request_id: Uuid::new_v4(),
+            provider,
+            model: model.to_string(),
+            input_tokens: 1000,
+            output_tokens: 100,
+            cost_usd,
+            timestamp: DateTime::parse_from_rfc3339(&format!("{}T12:00:00Z", day))
+                .unwrap()
+                .with_timezone(&Utc),
+            user_id: None,
+            project_id: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_billing_exports() {
+        let openai = "start_time,end_time,line_item,amount_value,amount_currency\n\
+                      1791936000,1792022400,\"gpt-4o-2024-08-06, input\",1.25,usd\n\
+                      1791936000,1792022400,\"gpt-4o-2024-08-06, output\",\"1,000.50\",usd\n";
+        let lines = parse_billing_export(openai).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].model, "gpt-4o");
+        assert_eq!(lines[0].day, NaiveDate::from_ymd_opt(2026, 10, 14).unwrap());
+        assert_eq!(lines[1].cost_usd, 1000.5);
+
+        let anthropic = "\u{feff}usage_date_utc,model,input_tokens,output_tokens,cost_usd\r\n\
+                         2026-10-14,claude-3-5-sonnet-20241022,5000,700,$0.03\r\n";
+        let lines = parse_billing_export(anthropic).unwrap();
+        assert_eq!(lines[0].model, "claude-3-5-sonnet");
+        assert_eq!(lines[0].input_tokens, Some(5000));
+
+        assert!(parse_billing_export("date,model\n2026-10-14,gpt-4o\n")
+            .unwrap_err()
+            .contains("no cost column"));
+        assert!(
+            parse_billing_export("date,model,cost,currency\n2026-10-14,gpt-4o,1,eur\n")
+                .unwrap_err()
+                .contains("Line 2")
+        );
+    }
+
+    #[test]
+    fn test_reconciliation_report() {
+        let text = "date,model,cost\n\
+                    2026-10-14,gpt-4o-2024-08-06,1.00\n\
+                    2026-10-14,gpt-4o-mini,0.50\n\
+                    2026-10-15,gpt-4o,2.00\n";
+        let lines = parse_billing_export(text).unwrap();
+        let costs = vec![
+            cost(LLMProviderType::OpenAI, "gpt-4o", "2026-10-14", 0.995),
+            cost(LLMProviderType::OpenAI, "gpt-4o", "2026-10-15", 1.50),
+            cost(LLMProviderType::OpenAI, "gpt-4", "2026-10-15", 0.75),
+            // Other providers and days outside the export are left out
+            cost(LLMProviderType::Anthropic, "gpt-4o", "2026-10-14", 9.0),
+            cost(LLMProviderType::OpenAI, "gpt-4o", "2026-10-16", 9.0),
+        ];
+        let report = ReconciliationReport::build(
+            &LLMProviderType::OpenAI,
+            &lines,
+            &costs,
+            DEFAULT_TOLERANCE,
+        );
+
+        let statuses: Vec<(&str, ReconciliationStatus)> = report
+            .rows
+            .iter()
+            .map(|row| (row.model.as_str(), row.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("gpt-4o", ReconciliationStatus::Matched),
+                ("gpt-4o-mini", ReconciliationStatus::MissingInternal),
+                ("gpt-4", ReconciliationStatus::MissingProvider),
+                ("gpt-4o", ReconciliationStatus::Discrepancy),
+            ]
+        );
+        assert_eq!(report.discrepancies, 3);
+        assert_eq!(report.provider_cost_usd, 3.5);
+        assert!((report.difference_usd - 0.255).abs() < 1e-9);
+        assert_eq!(report.rows[3].internal_requests, 1);
+    }
+}
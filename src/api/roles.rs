@@ -0,0 +1,160 @@
+// Role assignment administration
+// `/v1/admin/roles` assigns API keys, service accounts and MCP installations their roles
+
+//! # Roles
+//!
+//! Operators limit principals to a [role](crate::rbac) through these endpoints:
+//!
+//! - `GET /v1/admin/roles` lists assignments
+//! - `GET /v1/admin/roles/{subject}`
+//! - `PUT /v1/admin/roles/{subject}` assigns a role, replacing the previous one
+//! - `DELETE /v1/admin/roles/{subject}` removes it, lifting the restriction
+//!
+//! Subjects are written `api_key:{id}`, `service_account:{id}` or
+//! `mcp_installation:{id}`; API keys and service accounts must exist. Like tenant
+//! administration, every endpoint requires the admin token.
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use tracing::info;
+
+use super::api_keys::api_key_error;
+use super::handlers::{authorize_admin, OpenAIApiState};
+use super::types::{create_error_response, ErrorResponse};
+use super::usage_export::invalid_param;
+use crate::rbac::{Role, RoleAssignment, RoleDenied, RoleStoreError, Subject};
+use crate::ErrorCode;
+
+/// Body of `PUT /v1/admin/roles/{subject}`
+#[derive(Debug, Clone, Deserialize)]
+pub struct AssignRoleRequest {
+    pub role: Role,
+}
+
+fn subject_not_found(subject: &Subject) -> ErrorResponse {
+    create_error_response(
+        format!("{} not found", subject),
+        "not_found_error".to_string(),
+        Some("subject".to_string()),
+        None,
+    )
+    .with_error_code(ErrorCode::NotFound)
+}
+
+fn invalid_subject(message: String) -> ErrorResponse {
+    invalid_param(message, "subject").with_error_code(ErrorCode::InvalidInput)
+}
+
+fn no_role(subject: &Subject) -> ErrorResponse {
+    create_error_response(
+        format!("No role assigned to {}", subject),
+        "not_found_error".to_string(),
+        Some("subject".to_string()),
+        None,
+    )
+    .with_error_code(ErrorCode::NotFound)
+}
+
+fn role_store_error(error: RoleStoreError) -> ErrorResponse {
+    create_error_response(error.to_string(), "internal_error".to_string(), None, None)
+        .with_error_code(ErrorCode::StorageError)
+}
+
+/// Error response refusing a request its principal's role does not allow
+pub(crate) fn role_denied(denied: RoleDenied) -> Response {
+    let error_type = match denied {
+        RoleDenied::Forbidden { .. } => "permission_error",
+        RoleDenied::Unavailable { .. } => "internal_error",
+    };
+    create_error_response(denied.message(), error_type.to_string(), None, None)
+        .with_error_code(denied.error_code())
+        .into_response()
+}
+
+/// List role assignments - GET /v1/admin/roles
+pub async fn list_role_assignments(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<RoleAssignment>>, ErrorResponse> {
+    authorize_admin(&state, &headers, "Role administration")?;
+    Ok(Json(state.roles.list().await.map_err(role_store_error)?))
+}
+
+/// Get a principal's role - GET /v1/admin/roles/{subject}
+pub async fn get_role_assignment(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Path(subject): Path<String>,
+) -> Result<Json<RoleAssignment>, ErrorResponse> {
+    authorize_admin(&state, &headers, "Role administration")?;
+    let subject: Subject = subject.parse().map_err(invalid_subject)?;
+    let assignment = state
+        .roles
+        .get(&subject)
+        .await
+        .map_err(role_store_error)?
+        .ok_or_else(|| no_role(&subject))?;
+    Ok(Json(assignment))
+}
+
+/// Assign a principal a role - PUT /v1/admin/roles/{subject}
+pub async fn assign_role(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Path(subject): Path<String>,
+    Json(request): Json<AssignRoleRequest>,
+) -> Result<Json<RoleAssignment>, ErrorResponse> {
+    authorize_admin(&state, &headers, "Role administration")?;
+    let subject: Subject = subject.parse().map_err(invalid_subject)?;
+    let exists = match &subject {
+        Subject::ApiKey(key_id) => state
+            .managed_keys
+            .get(key_id)
+            .await
+            .map_err(api_key_error)?
+            .is_some(),
+        Subject::ServiceAccount(account_id) => state.service_accounts.get(account_id).is_some(),
+        // Installations live in the MCP server's storage
+        Subject::McpInstallation(_) => true,
+    };
+    if !exists {
+        return Err(subject_not_found(&subject));
+    }
+
+    let assignment = state
+        .roles
+        .assign(subject, request.role)
+        .await
+        .map_err(role_store_error)?;
+    info!(
+        "Assigned role {} to {}",
+        assignment.role.name(),
+        assignment.subject
+    );
+
+    Ok(Json(assignment))
+}
+
+/// Remove a principal's role - DELETE /v1/admin/roles/{subject}
+pub async fn unassign_role(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Path(subject): Path<String>,
+) -> Result<StatusCode, ErrorResponse> {
+    authorize_admin(&state, &headers, "Role administration")?;
+    let subject: Subject = subject.parse().map_err(invalid_subject)?;
+    state
+        .roles
+        .unassign(&subject)
+        .await
+        .map_err(role_store_error)?
+        .ok_or_else(|| no_role(&subject))?;
+    info!("Removed role of {}", subject);
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,224 @@
+// Service account administration
+// `/v1/admin/service-accounts` creates scoped machine identities and mints their tokens
+
+//! # Service Accounts
+//!
+//! CI systems and external workers act on workflows through
+//! [service accounts](crate::engine::service_accounts), each limited to some workflows
+//! and operations. These endpoints manage them:
+//!
+//! - `POST /v1/admin/service-accounts` creates an account
+//! - `GET /v1/admin/service-accounts` and `GET|DELETE /v1/admin/service-accounts/{id}`;
+//!   deleting an account revokes its tokens
+//! - `POST /v1/admin/service-accounts/{id}/tokens` mints a token; the secret is only
+//!   returned once
+//! - `DELETE /v1/admin/service-accounts/{id}/tokens/{token_id}` revokes one
+//!
+//! Tokens are presented to the GraphQL server as `Authorization: Bearer cbsa-...`.
+//! Like tenant administration, every endpoint requires the admin token.
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use super::handlers::{authorize_admin, OpenAIApiState};
+use super::types::{create_error_response, ErrorResponse};
+use super::usage_export::invalid_param;
+use crate::engine::service_accounts::{
+    MintedToken, ServiceAccount, ServiceOperation, ServiceToken, DEFAULT_TOKEN_TTL_SECS,
+    MAX_TOKEN_TTL_SECS,
+};
+use crate::ErrorCode;
+
+/// Body of `POST /v1/admin/service-accounts`
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateServiceAccountRequest {
+    pub name: String,
+    /// Workflows the account may act on
+    pub workflow_ids: Vec<String>,
+    /// What the account may do on those workflows
+    pub operations: Vec<ServiceOperation>,
+    /// Lifetime of the account's tokens, an hour by default
+    #[serde(default)]
+    pub token_ttl_secs: Option<u64>,
+}
+
+/// A service account with its unexpired tokens
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceAccountDetails {
+    #[serde(flatten)]
+    pub account: ServiceAccount,
+    pub tokens: Vec<ServiceToken>,
+}
+
+fn account_not_found(account_id: &str) -> ErrorResponse {
+    create_error_response(
+        format!("Service account '{}' not found", account_id),
+        "not_found_error".to_string(),
+        Some("account_id".to_string()),
+        None,
+    )
+    .with_error_code(ErrorCode::NotFound)
+}
+
+fn account_details(state: &OpenAIApiState, account: ServiceAccount) -> ServiceAccountDetails {
+    let tokens = state.service_accounts.tokens(&account.id);
+    ServiceAccountDetails { account, tokens }
+}
+
+/// Create a service account - POST /v1/admin/service-accounts
+pub async fn create_service_account(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Json(request): Json<CreateServiceAccountRequest>,
+) -> Result<(StatusCode, Json<ServiceAccount>), ErrorResponse> {
+    authorize_admin(&state, &headers, "Service account administration")?;
+
+    if let Some((message, param)) = invalid_account_request(&request) {
+        return Err(invalid_param(message, param).with_error_code(ErrorCode::InvalidInput));
+    }
+
+    let mut account = ServiceAccount::new(
+        request.name.trim(),
+        request.workflow_ids,
+        request.operations,
+    );
+    if let Some(ttl) = request.token_ttl_secs {
+        account.token_ttl_secs = ttl;
+    }
+    state.service_accounts.create(account.clone());
+    info!(
+        "Created service account {} for workflows {:?}",
+        account.id, account.workflow_ids
+    );
+
+    Ok((StatusCode::CREATED, Json(account)))
+}
+
+/// The message and parameter of the first invalid field of a create request
+fn invalid_account_request(
+    request: &CreateServiceAccountRequest,
+) -> Option<(String, &'static str)> {
+    if request.name.trim().is_empty() {
+        return Some((
+            "Service account 'name' must not be empty".to_string(),
+            "name",
+        ));
+    }
+    if request.workflow_ids.is_empty() {
+        return Some((
+            "A service account must be scoped to at least one workflow".to_string(),
+            "workflow_ids",
+        ));
+    }
+    if request.operations.is_empty() {
+        return Some((
+            "A service account must be allowed at least one operation".to_string(),
+            "operations",
+        ));
+    }
+    let ttl = request.token_ttl_secs.unwrap_or(DEFAULT_TOKEN_TTL_SECS);
+    if !(1..=MAX_TOKEN_TTL_SECS).contains(&ttl) {
+        return Some((
+            format!(
+                "Token lifetime must be between 1 and {} seconds",
+                MAX_TOKEN_TTL_SECS
+            ),
+            "token_ttl_secs",
+        ));
+    }
+    None
+}
+
+/// List service accounts - GET /v1/admin/service-accounts
+pub async fn list_service_accounts(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<ServiceAccount>>, ErrorResponse> {
+    authorize_admin(&state, &headers, "Service account administration")?;
+    Ok(Json(state.service_accounts.list()))
+}
+
+/// Get a service account with its tokens - GET /v1/admin/service-accounts/{id}
+pub async fn get_service_account(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Path(account_id): Path<String>,
+) -> Result<Json<ServiceAccountDetails>, ErrorResponse> {
+    authorize_admin(&state, &headers, "Service account administration")?;
+    let account = state
+        .service_accounts
+        .get(&account_id)
+        .ok_or_else(|| account_not_found(&account_id))?;
+    Ok(Json(account_details(&state, account)))
+}
+
+/// Remove a service account and revoke its tokens - DELETE /v1/admin/service-accounts/{id}
+pub async fn delete_service_account(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Path(account_id): Path<String>,
+) -> Result<StatusCode, ErrorResponse> {
+    authorize_admin(&state, &headers, "Service account administration")?;
+    state
+        .service_accounts
+        .remove(&account_id)
+        .ok_or_else(|| account_not_found(&account_id))?;
+    info!("Removed service account: {}", account_id);
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Mint a token for a service account - POST /v1/admin/service-accounts/{id}/tokens
+pub async fn mint_service_token(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Path(account_id): Path<String>,
+) -> Result<(StatusCode, Json<MintedToken>), ErrorResponse> {
+    authorize_admin(&state, &headers, "Service account administration")?;
+    let minted = state
+        .service_accounts
+        .mint(&account_id)
+        .ok_or_else(|| account_not_found(&account_id))?;
+    info!(
+        "Minted token {} for service account {}, expiring at {}",
+        minted.token.token_id, account_id, minted.token.expires_at
+    );
+
+    Ok((StatusCode::CREATED, Json(minted)))
+}
+
+/// Revoke a service account token -
+/// DELETE /v1/admin/service-accounts/{id}/tokens/{token_id}
+pub async fn revoke_service_token(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Path((account_id, token_id)): Path<(String, String)>,
+) -> Result<StatusCode, ErrorResponse> {
+    authorize_admin(&state, &headers, "Service account administration")?;
+    if state.service_accounts.get(&account_id).is_none() {
+        return Err(account_not_found(&account_id));
+    }
+    if !state.service_accounts.revoke(&account_id, &token_id) {
+        return Err(create_error_response(
+            format!(
+                "Token '{}' not found for service account '{}'",
+                token_id, account_id
+            ),
+            "not_found_error".to_string(),
+            Some("token_id".to_string()),
+            None,
+        )
+        .with_error_code(ErrorCode::NotFound));
+    }
+    info!(
+        "Revoked token {} of service account: {}",
+        token_id, account_id
+    );
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,706 @@
+// Tenant administration
+// `/v1/admin/tenants` registers tenants and manages their quotas, API keys, budgets and usage
+
+//! # Tenants
+//!
+//! A request belongs to a tenant through the `x-tenant-id` header or through an API key
+//! issued to that tenant. Routing policies, budgets and usage were already tracked per
+//! tenant; these endpoints let platform teams onboard a tenant in one place:
+//!
+//! - `POST /v1/admin/tenants` registers a tenant, optionally with a quota and routing policy
+//! - `GET /v1/admin/tenants` and `GET|DELETE /v1/admin/tenants/{id}`
+//! - `PUT /v1/admin/tenants/{id}/quota` sets the usage limits carried by its API keys
+//! - `POST /v1/admin/tenants/{id}/api-keys` issues a key; the secret is only returned once
+//! - `DELETE /v1/admin/tenants/{id}/api-keys/{key_id}` revokes one
+//! - `PUT /v1/admin/tenants/{id}/budget` sets the budget requests are checked against
+//! - `GET /v1/admin/tenants/{id}/usage` totals recorded usage between `from` and `to`
+//!
+//! Like the log stream, every endpoint requires the admin token.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::info;
+use uuid::Uuid;
+
+use super::handlers::{
+    authorize_admin, cost_error_response, ApiKeyInfo, OpenAIApiState, UsageLimits,
+};
+use super::types::{create_error_response, ErrorResponse};
+use super::usage_export::{invalid_param, parse_window};
+use crate::llm::cost::{usage_key, Budget, BudgetPeriod, CarryOver, MAX_UTC_OFFSET_MINUTES};
+use crate::llm::{TenantId, TenantRoutingPolicy};
+use crate::ErrorCode;
+
+/// Prefix of issued API keys
+pub const API_KEY_PREFIX: &str = "cb-";
+
+/// A registered tenant
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tenant {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Limits applied to the tenant's API keys
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quota: Option<UsageLimits>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// In-memory registry of tenants
+#[derive(Debug, Clone, Default)]
+pub struct TenantDirectory {
+    tenants: Arc<RwLock<HashMap<TenantId, Tenant>>>,
+}
+
+impl TenantDirectory {
+    pub async fn get(&self, tenant: &TenantId) -> Option<Tenant> {
+        self.tenants.read().await.get(tenant).cloned()
+    }
+
+    /// Register a tenant, returning `false` when one with the same ID exists
+    pub async fn insert(&self, tenant: Tenant) -> bool {
+        let mut tenants = self.tenants.write().await;
+        let id = TenantId::new(tenant.id.clone());
+        if tenants.contains_key(&id) {
+            return false;
+        }
+        tenants.insert(id, tenant);
+        true
+    }
+
+    /// Replace a tenant's quota, returning the updated tenant
+    pub async fn set_quota(&self, tenant: &TenantId, quota: UsageLimits) -> Option<Tenant> {
+        let mut tenants = self.tenants.write().await;
+        let entry = tenants.get_mut(tenant)?;
+        entry.quota = Some(quota);
+        Some(entry.clone())
+    }
+
+    pub async fn remove(&self, tenant: &TenantId) -> Option<Tenant> {
+        self.tenants.write().await.remove(tenant)
+    }
+
+    /// All tenants, ordered by ID
+    pub async fn list(&self) -> Vec<Tenant> {
+        let mut tenants: Vec<Tenant> = self.tenants.read().await.values().cloned().collect();
+        tenants.sort_by(|a, b| a.id.cmp(&b.id));
+        tenants
+    }
+}
+
+/// Body of `POST /v1/admin/tenants`
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateTenantRequest {
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub quota: Option<UsageLimits>,
+    #[serde(default)]
+    pub routing_policy: Option<TenantRoutingPolicy>,
+}
+
+/// An issued API key, without its secret
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeySummary {
+    pub key_id: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+/// A tenant with everything configured for it
+#[derive(Debug, Clone, Serialize)]
+pub struct TenantDetails {
+    #[serde(flatten)]
+    pub tenant: Tenant,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub routing_policy: Option<TenantRoutingPolicy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub budget: Option<Budget>,
+    pub api_keys: Vec<ApiKeySummary>,
+}
+
+/// Response of `POST /v1/admin/tenants/{id}/api-keys`
+#[derive(Debug, Clone, Serialize)]
+pub struct IssuedApiKey {
+    pub key_id: String,
+    /// The bearer token; not retrievable later
+    pub api_key: String,
+    pub tenant_id: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Body of `PUT /v1/admin/tenants/{id}/budget`
+#[derive(Debug, Clone, Deserialize)]
+pub struct TenantBudgetRequest {
+    /// Spending limit in USD per period
+    pub limit: f64,
+    pub period: BudgetPeriod,
+    /// Share of the limit at which requests carry a budget warning
+    #[serde(default = "default_warning_threshold")]
+    pub warning_threshold: f64,
+    /// What a closed period passes on to the next one
+    #[serde(default)]
+    pub carry_over: CarryOver,
+    /// Offset from UTC, in minutes, of the timezone whose midnight starts each period
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+}
+
+fn default_warning_threshold() -> f64 {
+    0.8
+}
+
+/// Query parameters of `GET /v1/admin/tenants/{id}/usage`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TenantUsageQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+/// Usage of one model
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelUsage {
+    pub provider: String,
+    pub model: String,
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+}
+
+/// Usage recorded for a tenant between `from` and `to`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TenantUsage {
+    pub tenant_id: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+    /// Totals per provider and model, most expensive first
+    pub by_model: Vec<ModelUsage>,
+}
+
+/// Budget ID requests of a tenant are checked against
+fn budget_id(tenant: &TenantId) -> String {
+    usage_key("", Some(tenant.as_str()))
+}
+
+/// A new bearer token: the prefix followed by 48 hex characters
+fn generate_api_key() -> String {
+    let bytes: [u8; 24] = rand::thread_rng().gen();
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("{}{}", API_KEY_PREFIX, hex)
+}
+
+fn tenant_not_found(tenant: &TenantId) -> ErrorResponse {
+    create_error_response(
+        format!("Tenant '{}' not found", tenant),
+        "not_found_error".to_string(),
+        Some("tenant_id".to_string()),
+        None,
+    )
+    .with_error_code(ErrorCode::NotFound)
+}
+
+async fn require_tenant(
+    state: &OpenAIApiState,
+    tenant: &TenantId,
+) -> Result<Tenant, ErrorResponse> {
+    state
+        .tenants
+        .get(tenant)
+        .await
+        .ok_or_else(|| tenant_not_found(tenant))
+}
+
+async fn tenant_details(state: &OpenAIApiState, tenant: Tenant) -> TenantDetails {
+    let id = TenantId::new(tenant.id.clone());
+    let routing_policy = state.llm_router.tenant_policies().get(&id).await;
+    let budget_manager = state.cost_optimizer.read().await.budget_manager();
+    let budget = budget_manager.get_budget(&budget_id(&id)).await;
+    let mut api_keys: Vec<ApiKeySummary> = state
+        .api_keys
+        .read()
+        .await
+        .values()
+        .filter(|key| key.tenant_id.as_ref() == Some(&id))
+        .map(|key| ApiKeySummary {
+            key_id: key.key_id.clone(),
+            created_at: key.created_at,
+            last_used: key.last_used,
+        })
+        .collect();
+    api_keys.sort_by_key(|key| key.created_at);
+    TenantDetails {
+        tenant,
+        routing_policy,
+        budget,
+        api_keys,
+    }
+}
+
+/// Register a tenant - POST /v1/admin/tenants
+pub async fn create_tenant(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Json(request): Json<CreateTenantRequest>,
+) -> Result<(StatusCode, Json<TenantDetails>), ErrorResponse> {
+    authorize_admin(&state, &headers, "Tenant administration")?;
+
+    let id = request.id.trim();
+    if id.is_empty() {
+        return Err(
+            invalid_param("Tenant 'id' must not be empty".to_string(), "id")
+                .with_error_code(ErrorCode::InvalidInput),
+        );
+    }
+    let tenant_id = TenantId::new(id);
+    let tenant = Tenant {
+        id: id.to_string(),
+        name: request.name,
+        quota: request.quota,
+        created_at: Utc::now(),
+    };
+    if !state.tenants.insert(tenant.clone()).await {
+        return Err(create_error_response(
+            format!("Tenant '{}' already exists", tenant_id),
+            "invalid_request_error".to_string(),
+            Some("id".to_string()),
+            Some("tenant_exists".to_string()),
+        )
+        .with_error_code(ErrorCode::InvalidInput));
+    }
+    if let Some(policy) = request.routing_policy {
+        state
+            .llm_router
+            .tenant_policies()
+            .set(tenant_id.clone(), policy)
+            .await;
+    }
+    info!("Registered tenant: {}", tenant_id);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(tenant_details(&state, tenant).await),
+    ))
+}
+
+/// List tenants - GET /v1/admin/tenants
+pub async fn list_tenants(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<Tenant>>, ErrorResponse> {
+    authorize_admin(&state, &headers, "Tenant administration")?;
+    Ok(Json(state.tenants.list().await))
+}
+
+/// Get a tenant with its policy, budget and keys - GET /v1/admin/tenants/{id}
+pub async fn get_tenant(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Path(tenant_id): Path<String>,
+) -> Result<Json<TenantDetails>, ErrorResponse> {
+    authorize_admin(&state, &headers, "Tenant administration")?;
+    let tenant = require_tenant(&state, &TenantId::new(tenant_id)).await?;
+    Ok(Json(tenant_details(&state, tenant).await))
+}
+
+/// Remove a tenant, its routing policy and budget, and revoke its keys -
+/// DELETE /v1/admin/tenants/{id}
+pub async fn delete_tenant(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Path(tenant_id): Path<String>,
+) -> Result<StatusCode, ErrorResponse> {
+    authorize_admin(&state, &headers, "Tenant administration")?;
+    let tenant_id = TenantId::new(tenant_id);
+    state
+        .tenants
+        .remove(&tenant_id)
+        .await
+        .ok_or_else(|| tenant_not_found(&tenant_id))?;
+
+    state.llm_router.tenant_policies().remove(&tenant_id).await;
+    let budget_manager = state.cost_optimizer.read().await.budget_manager();
+    budget_manager.remove_budget(&budget_id(&tenant_id)).await;
+    state
+        .api_keys
+        .write()
+        .await
+        .retain(|_, key| key.tenant_id.as_ref() != Some(&tenant_id));
+    info!("Removed tenant: {}", tenant_id);
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Set a tenant's quota - PUT /v1/admin/tenants/{id}/quota
+///
+/// The quota is copied onto every key the tenant holds and every key issued later.
+pub async fn set_tenant_quota(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Path(tenant_id): Path<String>,
+    Json(quota): Json<UsageLimits>,
+) -> Result<Json<Tenant>, ErrorResponse> {
+    authorize_admin(&state, &headers, "Tenant administration")?;
+    let tenant_id = TenantId::new(tenant_id);
+    let tenant = state
+        .tenants
+        .set_quota(&tenant_id, quota.clone())
+        .await
+        .ok_or_else(|| tenant_not_found(&tenant_id))?;
+
+    for key in state.api_keys.write().await.values_mut() {
+        if key.tenant_id.as_ref() == Some(&tenant_id) {
+            key.usage_limits = Some(quota.clone());
+        }
+    }
+    info!("Set quota for tenant: {}", tenant_id);
+
+    Ok(Json(tenant))
+}
+
+/// Issue an API key for a tenant - POST /v1/admin/tenants/{id}/api-keys
+pub async fn issue_tenant_api_key(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Path(tenant_id): Path<String>,
+) -> Result<(StatusCode, Json<IssuedApiKey>), ErrorResponse> {
+    authorize_admin(&state, &headers, "Tenant administration")?;
+    let tenant_id = TenantId::new(tenant_id);
+    let tenant = require_tenant(&state, &tenant_id).await?;
+
+    let api_key = generate_api_key();
+    let key_id = format!("key_{}", &Uuid::new_v4().simple().to_string()[..16]);
+    let created_at = Utc::now();
+    state.api_keys.write().await.insert(
+        api_key.clone(),
+        ApiKeyInfo {
+            key_id: key_id.clone(),
+            provider_keys: HashMap::new(),
+            usage_limits: tenant.quota,
+            created_at,
+            last_used: None,
+            tenant_id: Some(tenant_id.clone()),
+        },
+    );
+    info!("Issued API key {} for tenant: {}", key_id, tenant_id);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(IssuedApiKey {
+            key_id,
+            api_key,
+            tenant_id: tenant_id.to_string(),
+            created_at,
+        }),
+    ))
+}
+
+/// Revoke one of a tenant's API keys - DELETE /v1/admin/tenants/{id}/api-keys/{key_id}
+pub async fn revoke_tenant_api_key(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Path((tenant_id, key_id)): Path<(String, String)>,
+) -> Result<StatusCode, ErrorResponse> {
+    authorize_admin(&state, &headers, "Tenant administration")?;
+    let tenant_id = TenantId::new(tenant_id);
+    require_tenant(&state, &tenant_id).await?;
+
+    let mut api_keys = state.api_keys.write().await;
+    let before = api_keys.len();
+    api_keys.retain(|_, key| !(key.key_id == key_id && key.tenant_id.as_ref() == Some(&tenant_id)));
+    if api_keys.len() == before {
+        return Err(create_error_response(
+            format!("API key '{}' not found for tenant '{}'", key_id, tenant_id),
+            "not_found_error".to_string(),
+            Some("key_id".to_string()),
+            None,
+        )
+        .with_error_code(ErrorCode::NotFound));
+    }
+    info!("Revoked API key {} of tenant: {}", key_id, tenant_id);
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Set a tenant's budget - PUT /v1/admin/tenants/{id}/budget
+pub async fn set_tenant_budget(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Path(tenant_id): Path<String>,
+    Json(request): Json<TenantBudgetRequest>,
+) -> Result<Json<Budget>, ErrorResponse> {
+    authorize_admin(&state, &headers, "Tenant administration")?;
+    let tenant_id = TenantId::new(tenant_id);
+    require_tenant(&state, &tenant_id).await?;
+    if request.limit < 0.0 {
+        return Err(
+            invalid_param("Budget 'limit' must not be negative".to_string(), "limit")
+                .with_error_code(ErrorCode::InvalidInput),
+        );
+    }
+    if request.utc_offset_minutes.abs() > MAX_UTC_OFFSET_MINUTES {
+        return Err(invalid_param(
+            format!(
+                "Budget 'utc_offset_minutes' must be between -{0} and {0}",
+                MAX_UTC_OFFSET_MINUTES
+            ),
+            "utc_offset_minutes",
+        )
+        .with_error_code(ErrorCode::InvalidInput));
+    }
+
+    let budget_manager = state.cost_optimizer.read().await.budget_manager();
+    let now = Utc::now();
+    let created_at = budget_manager
+        .get_budget(&budget_id(&tenant_id))
+        .await
+        .map_or(now, |budget| budget.created_at);
+    let budget = Budget {
+        id: budget_id(&tenant_id),
+        user_id: None,
+        project_id: Some(tenant_id.to_string()),
+        limit: request.limit,
+        period: request.period,
+        warning_threshold: request.warning_threshold.clamp(0.0, 1.0),
+        carry_over: request.carry_over,
+        utc_offset_minutes: request.utc_offset_minutes,
+        created_at,
+        updated_at: now,
+    };
+    budget_manager.set_budget(budget.clone()).await;
+    info!(
+        "Set {:?} budget of ${:.2} for tenant: {}",
+        budget.period, budget.limit, tenant_id
+    );
+
+    Ok(Json(budget))
+}
+
+/// Total a tenant's recorded usage - GET /v1/admin/tenants/{id}/usage
+///
+/// The window defaults to the last [`super::usage_export::DEFAULT_EXPORT_DAYS`]
+/// days, as for the usage export.
+pub async fn get_tenant_usage(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Path(tenant_id): Path<String>,
+    Query(query): Query<TenantUsageQuery>,
+) -> Result<Json<TenantUsage>, ErrorResponse> {
+    authorize_admin(&state, &headers, "Tenant administration")?;
+    let tenant_id = TenantId::new(tenant_id);
+    require_tenant(&state, &tenant_id).await?;
+
+    let (from, to) = parse_window(query.from.as_deref(), query.to.as_deref())?;
+
+    let usage_tracker = state.cost_optimizer.read().await.usage_tracker();
+    let costs = usage_tracker
+        .list_usage(from, to)
+        .await
+        .map_err(cost_error_response)?;
+
+    let mut usage = TenantUsage {
+        tenant_id: tenant_id.to_string(),
+        from,
+        to,
+        requests: 0,
+        input_tokens: 0,
+        output_tokens: 0,
+        cost_usd: 0.0,
+        by_model: Vec::new(),
+    };
+    let mut by_model: BTreeMap<(String, String), ModelUsage> = BTreeMap::new();
+    for cost in costs
+        .iter()
+        .filter(|cost| cost.project_id.as_deref() == Some(tenant_id.as_str()))
+    {
+        usage.requests += 1;
+        usage.input_tokens += cost.input_tokens as u64;
+        usage.output_tokens += cost.output_tokens as u64;
+        usage.cost_usd += cost.cost_usd;
+
+        let provider = cost.provider.to_string();
+        let model = by_model
+            .entry((provider.clone(), cost.model.clone()))
+            .or_insert_with(|| ModelUsage {
+                provider,
+                model: cost.model.clone(),
+                ..ModelUsage::default()
+            });
+        model.requests += 1;
+        model.input_tokens += cost.input_tokens as u64;
+        model.output_tokens += cost.output_tokens as u64;
+        model.cost_usd += cost.cost_usd;
+    }
+    usage.by_model = by_model.into_values().collect();
+    usage
+        .by_model
+        .sort_by(|a, b| b.cost_usd.total_cmp(&a.cost_usd));
+
+    Ok(Json(usage))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{CostInfo, LLMProviderType};
+    use axum::http::header;
+
+    fn admin_headers() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, "Bearer s3cret".parse().unwrap());
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_tenant_onboarding() {
+        let mut state = OpenAIApiState::new();
+        state.admin_token = Some("s3cret".to_string());
+
+        let request: CreateTenantRequest = serde_json::from_value(serde_json::json!({
+            "id": "acme",
+            "name": "Acme Corp",
+            "routing_policy": {"model_allowlist": ["gpt-4o-mini"]}
+        }))
+        .unwrap();
+        let (status, Json(created)) =
+            create_tenant(State(state.clone()), admin_headers(), Json(request.clone()))
+                .await
+                .unwrap();
+        assert_eq!(status, StatusCode::CREATED);
+        assert!(created.routing_policy.is_some());
+        let duplicate = create_tenant(State(state.clone()), admin_headers(), Json(request))
+            .await
+            .unwrap_err();
+        assert_eq!(duplicate.error.code.as_deref(), Some("tenant_exists"));
+
+        // Keys carry the tenant and pick up quota changes
+        let (_, Json(issued)) = issue_tenant_api_key(
+            State(state.clone()),
+            admin_headers(),
+            Path("acme".to_string()),
+        )
+        .await
+        .unwrap();
+        assert!(issued.api_key.starts_with(API_KEY_PREFIX));
+        let quota = UsageLimits {
+            daily_tokens: Some(100_000),
+            ..UsageLimits::default()
+        };
+        let Json(tenant) = set_tenant_quota(
+            State(state.clone()),
+            admin_headers(),
+            Path("acme".to_string()),
+            Json(quota.clone()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(tenant.quota.as_ref(), Some(&quota));
+        let key = state.api_keys.read().await[&issued.api_key].clone();
+        assert_eq!(key.tenant_id, Some(TenantId::new("acme")));
+        assert_eq!(key.usage_limits, Some(quota));
+
+        let request: TenantBudgetRequest =
+            serde_json::from_value(serde_json::json!({"limit": 50.0, "period": "monthly"}))
+                .unwrap();
+        let Json(budget) = set_tenant_budget(
+            State(state.clone()),
+            admin_headers(),
+            Path("acme".to_string()),
+            Json(request),
+        )
+        .await
+        .unwrap();
+        assert_eq!(budget.id, "project:acme");
+
+        let Json(details) = get_tenant(
+            State(state.clone()),
+            admin_headers(),
+            Path("acme".to_string()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(details.api_keys.len(), 1);
+        assert_eq!(details.budget.map(|budget| budget.limit), Some(50.0));
+
+        let status = delete_tenant(
+            State(state.clone()),
+            admin_headers(),
+            Path("acme".to_string()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert!(state.api_keys.read().await.is_empty());
+        let missing = get_tenant(State(state), admin_headers(), Path("acme".to_string()))
+            .await
+            .unwrap_err();
+        assert_eq!(missing.error.error_code, Some(ErrorCode::NotFound));
+    }
+
+    #[tokio::test]
+    async fn test_tenant_usage_totals() {
+        let mut state = OpenAIApiState::new();
+        state.admin_token = Some("s3cret".to_string());
+        let request: CreateTenantRequest =
+            serde_json::from_value(serde_json::json!({"id": "acme"})).unwrap();
+        let (status, _) = create_tenant(State(state.clone()), admin_headers(), Json(request))
+            .await
+            .unwrap();
+        assert_eq!(status, StatusCode::CREATED);
+
+        for (project_id, model, cost_usd) in [
+            (Some("acme"), "gpt-4o", 0.5),
+            (Some("acme"), "gpt-4o-mini", 0.1),
+            (Some("acme"), "gpt-4o", 0.25),
+            (Some("other"), "gpt-4o", 9.0),
+            (None, "gpt-4o", 9.0),
+        ] {
+            state
+                .cost_optimizer
+                .read()
+                .await
+                .record_actual_cost(CostInfo {
+                    request_id: Uuid::new_v4(),
+                    provider: LLMProviderType::OpenAI,
+                    model: model.to_string(),
+                    input_tokens: 100,
+                    output_tokens: 10,
+                    cost_usd,
+                    timestamp: Utc::now(),
+                    user_id: Some("alice".to_string()),
+                    project_id: project_id.map(str::to_string),
+                })
+                .await;
+        }
+
+        let Json(usage) = get_tenant_usage(
+            State(state),
+            admin_headers(),
+            Path("acme".to_string()),
+            Query(TenantUsageQuery::default()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(usage.requests, 3);
+        assert_eq!(usage.input_tokens, 300);
+        assert!((usage.cost_usd - 0.85).abs() < 1e-9);
+        assert_eq!(usage.by_model.len(), 2);
+        assert_eq!(usage.by_model[0].model, "gpt-4o");
+        assert_eq!(usage.by_model[0].requests, 2);
+    }
+}
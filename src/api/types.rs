@@ -25,7 +25,11 @@ pub struct ChatCompletionRequest {
     /// Whether to return a stream of partial results
     #[serde(default)]
     pub stream: bool,
-    
+
+    /// Options for streamed responses, e.g. `{"include_usage": true}`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<StreamOptions>,
+
     /// Number of chat completion choices to generate
     #[serde(skip_serializing_if = "Option::is_none")]
     pub n: Option<u32>,
@@ -50,6 +54,18 @@ pub struct ChatCompletionRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user: Option<String>,
     
+    /// Structured output format, e.g. `{"type": "json_schema", "json_schema": {...}}`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<crate::llm::ResponseFormat>,
+    
+    /// Tools the model may call, in OpenAI format regardless of the provider
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<crate::llm::ToolDefinition>>,
+    
+    /// Whether and which tool the model must call: "auto", "none", "required" or a specific function
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<crate::llm::ToolChoice>,
+    
     /// Circuit Breaker smart routing configuration (optional extension)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub circuit_breaker: Option<CircuitBreakerConfig>,
@@ -59,14 +75,33 @@ pub struct ChatCompletionRequest {
     pub extra: HashMap<String, serde_json::Value>,
 }
 
+impl ChatCompletionRequest {
+    /// Whether a streamed response should end with a usage chunk
+    pub fn include_usage(&self) -> bool {
+        self.stream_options
+            .as_ref()
+            .is_some_and(|options| options.include_usage)
+    }
+}
+
+/// Options for streamed chat completions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StreamOptions {
+    /// Send a final chunk, with no choices, carrying the usage of the whole request
+    #[serde(default)]
+    pub include_usage: bool,
+}
+
 /// OpenAI Chat Message
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatMessage {
     /// The role of the message author
     pub role: ChatRole,
     
-    /// The contents of the message
-    pub content: String,
+    /// The contents of the message: text, or text and image parts (null for messages
+    /// that only call tools)
+    #[serde(default, deserialize_with = "crate::llm::MessageContent::deserialize_nullable")]
+    pub content: crate::llm::MessageContent,
     
     /// The name of the author of this message (optional)
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -108,6 +143,33 @@ pub struct FunctionCall {
     pub arguments: String,
 }
 
+impl From<crate::llm::ToolCall> for ToolCall {
+    fn from(call: crate::llm::ToolCall) -> Self {
+        Self {
+            id: call.id,
+            call_type: call.call_type,
+            function: FunctionCall {
+                name: call.function.name,
+                arguments: call.function.arguments,
+            },
+        }
+    }
+}
+
+impl From<ToolCall> for crate::llm::ToolCall {
+    fn from(call: ToolCall) -> Self {
+        Self {
+            index: None,
+            id: call.id,
+            call_type: call.call_type,
+            function: crate::llm::FunctionCall {
+                name: call.function.name,
+                arguments: call.function.arguments,
+            },
+        }
+    }
+}
+
 /// OpenAI Chat Completion Response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatCompletionResponse {
@@ -119,7 +181,7 @@ pub struct ChatCompletionResponse {
     
     /// The Unix timestamp (in seconds) when the chat completion was created
     pub created: u64,
-    
+
     /// The model used for the chat completion
     pub model: String,
     
@@ -132,6 +194,14 @@ pub struct ChatCompletionResponse {
     /// The system fingerprint of the model used
     #[serde(skip_serializing_if = "Option::is_none")]
     pub system_fingerprint: Option<String>,
+
+    /// ID the request's routing trace and cost are looked up by (Circuit Breaker extension)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<uuid::Uuid>,
+
+    /// How the request was routed (Circuit Breaker extension)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routing_info: Option<crate::llm::RoutingInfo>,
 }
 
 /// Chat completion choice
@@ -145,7 +215,7 @@ pub struct ChatCompletionChoice {
     
     /// The reason the model stopped generating tokens
     pub finish_reason: Option<String>,
-    
+
     /// Log probability information for the choice tokens
     #[serde(skip_serializing_if = "Option::is_none")]
     pub logprobs: Option<serde_json::Value>,
@@ -162,6 +232,10 @@ pub struct Usage {
     
     /// Total number of tokens used in the request (prompt + completion)
     pub total_tokens: u32,
+
+    /// Estimated cost in USD (Circuit Breaker extension)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimated_cost: Option<f64>,
 }
 
 /// OpenAI Streaming Chat Completion Response
@@ -175,7 +249,7 @@ pub struct ChatCompletionStreamResponse {
     
     /// The Unix timestamp (in seconds) when the chat completion was created
     pub created: u64,
-    
+
     /// The model used for the chat completion
     pub model: String,
     
@@ -185,6 +259,11 @@ pub struct ChatCompletionStreamResponse {
     /// The system fingerprint of the model used
     #[serde(skip_serializing_if = "Option::is_none")]
     pub system_fingerprint: Option<String>,
+
+    /// Usage of the whole request, only on the final chunk of a stream requested with
+    /// `stream_options.include_usage`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
 }
 
 /// Streaming chat completion choice
@@ -198,7 +277,7 @@ pub struct ChatCompletionStreamChoice {
     
     /// The reason the model stopped generating tokens
     pub finish_reason: Option<String>,
-    
+
     /// Log probability information for the choice tokens
     #[serde(skip_serializing_if = "Option::is_none")]
     pub logprobs: Option<serde_json::Value>,
@@ -241,6 +320,26 @@ pub struct FunctionCallDelta {
     pub arguments: Option<String>,
 }
 
+impl ToolCallDelta {
+    /// Stream deltas for tool calls, leaving out fields a chunk did not carry
+    pub fn from_tool_calls(calls: Vec<crate::llm::ToolCall>) -> Vec<Self> {
+        let non_empty = |value: String| (!value.is_empty()).then_some(value);
+        calls
+            .into_iter()
+            .enumerate()
+            .map(|(position, call)| Self {
+                index: call.index.unwrap_or(position as u32),
+                id: non_empty(call.id),
+                call_type: Some(call.call_type),
+                function: Some(FunctionCallDelta {
+                    name: non_empty(call.function.name),
+                    arguments: non_empty(call.function.arguments),
+                }),
+            })
+            .collect()
+    }
+}
+
 /// OpenAI Models List Response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelsResponse {
@@ -294,6 +393,42 @@ pub struct ErrorDetail {
     /// Error code (if applicable)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub code: Option<String>,
+
+    /// Stable Circuit Breaker error code (filled from the error type when not set)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<crate::ErrorCode>,
+
+    /// Budget a request was refused by, for `QUOTA_EXCEEDED` errors
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quota: Option<crate::llm::cost::QuotaExceeded>,
+}
+
+impl ErrorResponse {
+    /// Attach a stable error code
+    pub fn with_error_code(mut self, error_code: crate::ErrorCode) -> Self {
+        self.error.error_code = Some(error_code);
+        self
+    }
+
+    /// The stable error code, falling back to one derived from the OpenAI error type
+    pub fn error_code(&self) -> crate::ErrorCode {
+        self.error
+            .error_code
+            .unwrap_or_else(|| error_code_for_type(&self.error.error_type))
+    }
+}
+
+/// Default stable error code for an OpenAI error type
+pub fn error_code_for_type(error_type: &str) -> crate::ErrorCode {
+    use crate::ErrorCode;
+    match error_type {
+        "authentication_error" => ErrorCode::AuthenticationFailed,
+        "permission_error" => ErrorCode::PermissionDenied,
+        "not_found_error" => ErrorCode::NotFound,
+        "rate_limit_error" => ErrorCode::RateLimited,
+        "internal_error" => ErrorCode::Internal,
+        _ => ErrorCode::InvalidInput,
+    }
 }
 
 // ============================================================================
@@ -328,6 +463,10 @@ pub struct CircuitBreakerConfig {
     /// Preferred providers (in priority order)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub preferred_providers: Option<Vec<String>>,
+
+    /// Queue priority class ("interactive" or "batch")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<crate::llm::RequestPriority>,
 }
 
 /// Smart routing strategies
@@ -484,6 +623,8 @@ pub fn create_error_response(message: String, error_type: String, param: Option<
             error_type,
             param,
             code,
+            error_code: None,
+            quota: None,
         },
     }
 }
@@ -550,14 +691,23 @@ pub struct EmbeddingsResponse {
 pub struct EmbeddingObject {
     /// The object type, which is always "embedding"
     pub object: String,
-    
-    /// The embedding vector
-    pub embedding: Vec<f32>,
-    
+
+    /// The embedding vector, as floats or base64 per the request's `encoding_format`
+    pub embedding: EmbeddingVector,
+
     /// The index of the embedding in the list of embeddings
     pub index: u32,
 }
 
+/// An embedding vector in the requested encoding
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EmbeddingVector {
+    Float(Vec<f32>),
+    /// Little-endian 32-bit floats, base64-encoded
+    Base64(String),
+}
+
 /// Usage statistics for embeddings request
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmbeddingsUsage {
@@ -568,6 +718,47 @@ pub struct EmbeddingsUsage {
     pub total_tokens: u32,
 }
 
+/// Body of `POST /v1/tokenize`: a conversation, raw text, or both
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenizeRequest {
+    /// Model whose tokenizer counts the tokens
+    pub model: String,
+
+    /// Conversation to count as a chat prompt, including per-message overhead
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub messages: Vec<ChatMessage>,
+
+    /// Text to count as-is, as a string or array of strings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<EmbeddingsInput>,
+}
+
+/// Body of a `POST /v1/tokenize` response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenizeResponse {
+    pub model: String,
+
+    /// Tokenizer used: `o200k_base`, `cl100k_base` or `heuristic`
+    pub tokenizer: crate::llm::Tokenizer,
+
+    /// Whether counts match the provider's tokenizer rather than being estimated
+    pub exact: bool,
+
+    /// Tokens of `messages` as a chat prompt; 0 when no messages were sent
+    pub prompt_tokens: u32,
+
+    /// Tokens of each `input` string, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub input_tokens: Vec<u32>,
+
+    /// Prompt and input tokens together
+    pub total_tokens: u32,
+
+    /// Context window of the model, when it is configured on this server
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_window: Option<u32>,
+}
+
 /// Convert internal ChatMessage to OpenAI format
 impl From<crate::llm::ChatMessage> for ChatMessage {
     fn from(msg: crate::llm::ChatMessage) -> Self {
@@ -577,11 +768,17 @@ impl From<crate::llm::ChatMessage> for ChatMessage {
                 crate::llm::MessageRole::User => ChatRole::User,
                 crate::llm::MessageRole::Assistant => ChatRole::Assistant,
                 crate::llm::MessageRole::Function => ChatRole::Function,
+                crate::llm::MessageRole::Tool => ChatRole::Tool,
+            },
+            content: match msg.content_parts {
+                Some(parts) => crate::llm::MessageContent::Parts(parts),
+                None => crate::llm::MessageContent::Text(msg.content),
             },
-            content: msg.content,
             name: msg.name,
-            tool_calls: None,
-            tool_call_id: None,
+            tool_calls: msg
+                .tool_calls
+                .map(|calls| calls.into_iter().map(ToolCall::from).collect()),
+            tool_call_id: msg.tool_call_id,
         }
     }
 }
@@ -589,16 +786,23 @@ impl From<crate::llm::ChatMessage> for ChatMessage {
 /// Convert OpenAI ChatMessage to internal format
 impl From<ChatMessage> for crate::llm::ChatMessage {
     fn from(msg: ChatMessage) -> Self {
+        let (content, content_parts) = msg.content.into_text_and_parts();
         Self {
             role: match msg.role {
                 ChatRole::System => crate::llm::MessageRole::System,
                 ChatRole::User => crate::llm::MessageRole::User,
                 ChatRole::Assistant => crate::llm::MessageRole::Assistant,
-                ChatRole::Tool | ChatRole::Function => crate::llm::MessageRole::Function,
+                ChatRole::Tool => crate::llm::MessageRole::Tool,
+                ChatRole::Function => crate::llm::MessageRole::Function,
             },
-            content: msg.content,
+            content,
             name: msg.name,
             function_call: None,
+            tool_calls: msg
+                .tool_calls
+                .map(|calls| calls.into_iter().map(Into::into).collect()),
+            tool_call_id: msg.tool_call_id,
+            content_parts,
         }
     }
 }
@@ -617,10 +821,15 @@ impl From<ChatCompletionRequest> for crate::llm::LLMRequest {
             presence_penalty: req.presence_penalty.map(|p| p as f64),
             stop: req.stop,
             stream: Some(req.stream),
-            functions: None,
+            functions: req
+                .tools
+                .map(|tools| tools.into_iter().map(|tool| tool.function).collect()),
             function_call: None,
             user: req.user,
             metadata: std::collections::HashMap::new(),
+            response_format: req.response_format,
+            tool_choice: req.tool_choice,
+            n: req.n,
         }
     }
 }
@@ -655,6 +864,9 @@ mod tests {
             content: "Hello".to_string(),
             name: None,
             function_call: None,
+            tool_calls: None,
+            tool_call_id: None,
+            content_parts: None,
         };
         
         let openai_msg: ChatMessage = internal_msg.into();
@@ -665,4 +877,4 @@ mod tests {
         assert_eq!(back_to_internal.content, "Hello");
         assert!(matches!(back_to_internal.role, crate::llm::MessageRole::User));
     }
-}
\ No newline at end of file
+}
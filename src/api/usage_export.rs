@@ -0,0 +1,624 @@
+// Usage export for external billing systems
+// `GET /v1/usage/export` streams the costs recorded by the usage tracker as CSV or JSONL
+
+//! # Usage Export
+//!
+//! Billing systems ingest usage as files, so `GET /v1/usage/export` writes the costs the
+//! usage tracker recorded between `from` and `to` as CSV (the default) or JSONL:
+//!
+//! - without `group_by`, one row per request: `request_id`, `timestamp`, `provider`,
+//!   `model`, `user_id`, `project_id`, token counts and `cost_usd`
+//! - with `group_by` (a comma-separated list of `day`, `provider`, `model`, `user` and
+//!   `project`), one row per group holding the group's columns, the number of
+//!   `requests`, token counts and `cost_usd`
+//!
+//! `from` and `to` take RFC 3339 timestamps or dates; a date as `to` includes the whole
+//! day. The window defaults to the last [`DEFAULT_EXPORT_DAYS`] days. Like the log
+//! stream, the endpoint requires the admin token.
+//!
+//! Exports headed to third parties can be made harder to trace back to individual users
+//! (see [`ExportPrivacy`]):
+//!
+//! - `sample_rate` keeps that fraction of requests; grouped totals are scaled up to
+//!   estimate the full totals
+//! - `min_group_size` suppresses groups covering fewer distinct users, and needs
+//!   `group_by`. [`MIN_GROUP_SIZE_ENV`] sets a floor callers cannot go below
+
+use axum::{
+    body::{Bytes, StreamBody},
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::convert::Infallible;
+use tracing::info;
+
+use super::handlers::{authorize_admin, cost_error_response, OpenAIApiState};
+use super::types::{create_error_response, ErrorResponse};
+use crate::llm::CostInfo;
+
+/// Days exported when `from` is not given
+pub const DEFAULT_EXPORT_DAYS: i64 = 30;
+
+/// Rows written per body chunk
+const ROWS_PER_CHUNK: usize = 500;
+
+/// Environment variable holding the smallest `min_group_size` an export may use
+pub const MIN_GROUP_SIZE_ENV: &str = "USAGE_EXPORT_MIN_GROUP_SIZE";
+
+/// Query parameters of `GET /v1/usage/export`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UsageExportQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    /// Comma-separated grouping columns; individual records when unset
+    pub group_by: Option<String>,
+    /// `csv` or `jsonl`
+    pub format: Option<String>,
+    /// Fraction of requests exported, in `(0, 1]`
+    pub sample_rate: Option<f64>,
+    /// Fewest distinct users a published group may cover
+    pub min_group_size: Option<usize>,
+}
+
+/// Sampling and aggregation thresholds applied before usage leaves the server
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExportPrivacy {
+    /// Fraction of requests kept
+    pub sample_rate: f64,
+    /// Groups covering fewer distinct users are suppressed; requests without a user
+    /// count as one user
+    pub min_group_size: usize,
+}
+
+impl Default for ExportPrivacy {
+    fn default() -> Self {
+        Self {
+            sample_rate: 1.0,
+            min_group_size: 1,
+        }
+    }
+}
+
+impl ExportPrivacy {
+    /// Controls from the `sample_rate` and `min_group_size` query parameters, raised to
+    /// the floor in [`MIN_GROUP_SIZE_ENV`]
+    pub fn from_query(
+        sample_rate: Option<f64>,
+        min_group_size: Option<usize>,
+    ) -> Result<Self, ErrorResponse> {
+        let sample_rate = sample_rate.unwrap_or(1.0);
+        if !(sample_rate > 0.0 && sample_rate <= 1.0) {
+            return Err(invalid_param(
+                format!(
+                    "Invalid sample_rate {}; expected a fraction above 0 and at most 1",
+                    sample_rate
+                ),
+                "sample_rate",
+            ));
+        }
+        let floor = std::env::var(MIN_GROUP_SIZE_ENV)
+            .ok()
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(1);
+        Ok(Self {
+            sample_rate,
+            min_group_size: min_group_size.unwrap_or(1).max(floor).max(1),
+        })
+    }
+
+    /// Whether a recorded cost is in the sample
+    ///
+    /// Decided by request id, so repeated exports sample the same requests and cannot
+    /// be combined to recover the ones left out.
+    pub fn sampled(&self, cost: &CostInfo) -> bool {
+        if self.sample_rate >= 1.0 {
+            return true;
+        }
+        let digest = Sha256::digest(cost.request_id.as_bytes());
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&digest[..8]);
+        (u64::from_be_bytes(bytes) as f64) < self.sample_rate * u64::MAX as f64
+    }
+
+    /// Factor turning totals of the sample into estimates of the full totals
+    pub fn scale(&self) -> f64 {
+        1.0 / self.sample_rate
+    }
+
+    /// Estimate of a full count from a count of the sample
+    pub fn scale_count(&self, count: u64) -> u64 {
+        (count as f64 * self.scale()).round() as u64
+    }
+
+    /// Whether a group covering `users` distinct users may be published
+    pub fn publishable(&self, users: usize) -> bool {
+        users >= self.min_group_size
+    }
+}
+
+/// File format of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Jsonl,
+}
+
+impl ExportFormat {
+    pub fn parse(format: &str) -> Option<Self> {
+        match format.trim().to_ascii_lowercase().as_str() {
+            "csv" => Some(Self::Csv),
+            "jsonl" | "ndjson" => Some(Self::Jsonl),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Jsonl => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Jsonl => "jsonl",
+        }
+    }
+}
+
+/// Column usage can be grouped by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    Day,
+    Provider,
+    Model,
+    User,
+    Project,
+}
+
+impl GroupBy {
+    pub fn parse(column: &str) -> Option<Self> {
+        match column.trim().to_ascii_lowercase().as_str() {
+            "day" | "date" => Some(Self::Day),
+            "provider" => Some(Self::Provider),
+            "model" => Some(Self::Model),
+            "user" | "user_id" => Some(Self::User),
+            "project" | "project_id" => Some(Self::Project),
+            _ => None,
+        }
+    }
+
+    /// Parse a comma-separated list, dropping repeated columns
+    pub fn parse_list(list: &str) -> Result<Vec<Self>, String> {
+        let mut columns = Vec::new();
+        for column in list.split(',').filter(|c| !c.trim().is_empty()) {
+            let group = Self::parse(column).ok_or_else(|| {
+                format!(
+                    "Unknown group_by column '{}'; expected day, provider, model, user or project",
+                    column.trim()
+                )
+            })?;
+            if !columns.contains(&group) {
+                columns.push(group);
+            }
+        }
+        Ok(columns)
+    }
+
+    pub fn column(&self) -> &'static str {
+        match self {
+            Self::Day => "day",
+            Self::Provider => "provider",
+            Self::Model => "model",
+            Self::User => "user_id",
+            Self::Project => "project_id",
+        }
+    }
+
+    /// The column's value for a recorded cost
+    pub fn value(&self, cost: &CostInfo) -> Value {
+        match self {
+            Self::Day => Value::String(cost.timestamp.format("%Y-%m-%d").to_string()),
+            Self::Provider => Value::String(cost.provider.to_string()),
+            Self::Model => Value::String(cost.model.clone()),
+            Self::User => cost.user_id.clone().map_or(Value::Null, Value::String),
+            Self::Project => cost.project_id.clone().map_or(Value::Null, Value::String),
+        }
+    }
+}
+
+/// Rows of an export under fixed columns
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageExport {
+    pub columns: Vec<&'static str>,
+    pub rows: Vec<Vec<Value>>,
+    /// Groups left out for covering too few users
+    pub suppressed_groups: usize,
+}
+
+impl UsageExport {
+    /// One row per sampled cost, or per publishable group when `group_by` is not empty
+    pub fn build(costs: &[CostInfo], group_by: &[GroupBy], privacy: &ExportPrivacy) -> Self {
+        let sampled: Vec<&CostInfo> = costs.iter().filter(|cost| privacy.sampled(cost)).collect();
+        if group_by.is_empty() {
+            return Self::records(&sampled);
+        }
+
+        #[derive(Default)]
+        struct Totals {
+            requests: u64,
+            input_tokens: u64,
+            output_tokens: u64,
+            cost_usd: f64,
+            users: HashSet<Option<String>>,
+        }
+
+        // Keyed by the group's values rendered as JSON so groups sort stably
+        let mut groups: BTreeMap<String, (Vec<Value>, Totals)> = BTreeMap::new();
+        for cost in sampled {
+            let values: Vec<Value> = group_by.iter().map(|group| group.value(cost)).collect();
+            let key = Value::Array(values.clone()).to_string();
+            let (_, totals) = groups
+                .entry(key)
+                .or_insert_with(|| (values, Totals::default()));
+            totals.requests += 1;
+            totals.input_tokens += cost.input_tokens as u64;
+            totals.output_tokens += cost.output_tokens as u64;
+            totals.cost_usd += cost.cost_usd;
+            totals.users.insert(cost.user_id.clone());
+        }
+        let group_count = groups.len();
+        groups.retain(|_, (_, totals)| privacy.publishable(totals.users.len()));
+
+        let mut columns: Vec<&'static str> = group_by.iter().map(GroupBy::column).collect();
+        columns.extend([
+            "requests",
+            "input_tokens",
+            "output_tokens",
+            "total_tokens",
+            "cost_usd",
+        ]);
+        let suppressed_groups = group_count - groups.len();
+        let rows = groups
+            .into_values()
+            .map(|(mut values, totals)| {
+                let input_tokens = privacy.scale_count(totals.input_tokens);
+                let output_tokens = privacy.scale_count(totals.output_tokens);
+                values.extend([
+                    Value::from(privacy.scale_count(totals.requests)),
+                    Value::from(input_tokens),
+                    Value::from(output_tokens),
+                    Value::from(input_tokens + output_tokens),
+                    Value::from(totals.cost_usd * privacy.scale()),
+                ]);
+                values
+            })
+            .collect();
+        Self {
+            columns,
+            rows,
+            suppressed_groups,
+        }
+    }
+
+    fn records(costs: &[&CostInfo]) -> Self {
+        let columns = vec![
+            "request_id",
+            "timestamp",
+            "provider",
+            "model",
+            "user_id",
+            "project_id",
+            "input_tokens",
+            "output_tokens",
+            "total_tokens",
+            "cost_usd",
+        ];
+        let rows = costs
+            .iter()
+            .map(|cost| {
+                vec![
+                    Value::String(cost.request_id.to_string()),
+                    Value::String(cost.timestamp.to_rfc3339()),
+                    Value::String(cost.provider.to_string()),
+                    Value::String(cost.model.clone()),
+                    cost.user_id.clone().map_or(Value::Null, Value::String),
+                    cost.project_id.clone().map_or(Value::Null, Value::String),
+                    Value::from(cost.input_tokens),
+                    Value::from(cost.output_tokens),
+                    Value::from(cost.input_tokens as u64 + cost.output_tokens as u64),
+                    Value::from(cost.cost_usd),
+                ]
+            })
+            .collect();
+        Self {
+            columns,
+            rows,
+            suppressed_groups: 0,
+        }
+    }
+
+    /// Header line, if the format has one
+    pub fn header(&self, format: ExportFormat) -> Option<String> {
+        match format {
+            ExportFormat::Csv => Some(format!("{}\n", self.columns.join(","))),
+            ExportFormat::Jsonl => None,
+        }
+    }
+
+    /// A row as one line of the format
+    pub fn render_row(&self, row: &[Value], format: ExportFormat) -> String {
+        match format {
+            ExportFormat::Csv => {
+                let fields: Vec<String> = row.iter().map(csv_field).collect();
+                format!("{}\n", fields.join(","))
+            }
+            ExportFormat::Jsonl => {
+                let object: serde_json::Map<String, Value> = self
+                    .columns
+                    .iter()
+                    .map(|column| column.to_string())
+                    .zip(row.iter().cloned())
+                    .collect();
+                format!("{}\n", Value::Object(object))
+            }
+        }
+    }
+}
+
+/// A value as a CSV field, quoted when it holds a separator, quote or line break
+fn csv_field(value: &Value) -> String {
+    let text = match value {
+        Value::Null => return String::new(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+/// Parse an RFC 3339 timestamp or a date; `end_of_day` picks the last instant of a date
+pub(crate) fn parse_time(value: &str, end_of_day: bool) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+    let start = date.and_hms_opt(0, 0, 0)?.and_utc();
+    Some(if end_of_day {
+        start + Duration::days(1) - Duration::nanoseconds(1)
+    } else {
+        start
+    })
+}
+
+/// Window between the `from` and `to` query parameters, defaulting to the last
+/// [`DEFAULT_EXPORT_DAYS`] days
+pub(crate) fn parse_window(
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<(DateTime<Utc>, DateTime<Utc>), ErrorResponse> {
+    let to = match to {
+        Some(to) => parse_time(to, true)
+            .ok_or_else(|| invalid_param(format!("Invalid 'to' time '{}'", to), "to"))?,
+        None => Utc::now(),
+    };
+    let from = match from {
+        Some(from) => parse_time(from, false)
+            .ok_or_else(|| invalid_param(format!("Invalid 'from' time '{}'", from), "from"))?,
+        None => to - Duration::days(DEFAULT_EXPORT_DAYS),
+    };
+    if from > to {
+        return Err(invalid_param(
+            "'from' must not be after 'to'".to_string(),
+            "from",
+        ));
+    }
+    Ok((from, to))
+}
+
+pub(crate) fn invalid_param(message: String, param: &str) -> ErrorResponse {
+    create_error_response(
+        message,
+        "invalid_request_error".to_string(),
+        Some(param.to_string()),
+        None,
+    )
+}
+
+/// Export recorded usage - GET /v1/usage/export
+pub async fn export_usage(
+    State(state): State<OpenAIApiState>,
+    headers: HeaderMap,
+    Query(query): Query<UsageExportQuery>,
+) -> Result<Response, ErrorResponse> {
+    authorize_admin(&state, &headers, "Usage export")?;
+
+    let format = match query.format.as_deref() {
+        Some(format) => ExportFormat::parse(format).ok_or_else(|| {
+            invalid_param(
+                format!("Unknown format '{}'; expected csv or jsonl", format),
+                "format",
+            )
+        })?,
+        None => ExportFormat::Csv,
+    };
+    let group_by = match query.group_by.as_deref() {
+        Some(list) => {
+            GroupBy::parse_list(list).map_err(|message| invalid_param(message, "group_by"))?
+        }
+        None => Vec::new(),
+    };
+    let (from, to) = parse_window(query.from.as_deref(), query.to.as_deref())?;
+    let privacy = ExportPrivacy::from_query(query.sample_rate, query.min_group_size)?;
+    if group_by.is_empty() && privacy.min_group_size > 1 {
+        return Err(invalid_param(
+            format!(
+                "Groups of at least {} users are required; set 'group_by' to export aggregates",
+                privacy.min_group_size
+            ),
+            "min_group_size",
+        ));
+    }
+
+    let usage_tracker = state.cost_optimizer.read().await.usage_tracker();
+    let costs = usage_tracker
+        .list_usage(from, to)
+        .await
+        .map_err(cost_error_response)?;
+
+    let export = UsageExport::build(&costs, &group_by, &privacy);
+    info!(
+        "Exporting {} usage rows from {} to {} (sample rate {}, {} groups suppressed)",
+        export.rows.len(),
+        from,
+        to,
+        privacy.sample_rate,
+        export.suppressed_groups
+    );
+    let mut chunks: Vec<String> = export.header(format).into_iter().collect();
+    for rows in export.rows.chunks(ROWS_PER_CHUNK) {
+        chunks.push(
+            rows.iter()
+                .map(|row| export.render_row(row, format))
+                .collect(),
+        );
+    }
+    let body = StreamBody::new(futures::stream::iter(
+        chunks
+            .into_iter()
+            .map(|chunk| Ok::<_, Infallible>(Bytes::from(chunk))),
+    ));
+
+    let filename = format!(
+        "usage-{}-{}.{}",
+        from.format("%Y%m%d"),
+        to.format("%Y%m%d"),
+        format.extension()
+    );
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::LLMProviderType;
+    use uuid::Uuid;
+
+    fn cost(provider: LLMProviderType, user_id: Option<&str>, cost_usd: f64, day: u32) -> CostInfo {
+        CostInfo {
+            request_id: Uuid::new_v4(),
+            provider,
+            model: "gpt-4".to_string(),
+            input_tokens: 100,
+            output_tokens: 50,
+            cost_usd,
+            timestamp: parse_time(&format!("2026-10-{:02}", day), false).unwrap(),
+            user_id: user_id.map(str::to_string),
+            project_id: None,
+        }
+    }
+
+    #[test]
+    fn test_grouped_export() {
+        let costs = vec![
+            cost(LLMProviderType::OpenAI, Some("alice"), 0.5, 1),
+            cost(LLMProviderType::OpenAI, Some("alice"), 0.25, 1),
+            cost(LLMProviderType::Anthropic, Some("bob, jr."), 1.0, 2),
+        ];
+        let group_by = GroupBy::parse_list("day,user,day").unwrap();
+        assert_eq!(group_by, vec![GroupBy::Day, GroupBy::User]);
+
+        let export = UsageExport::build(&costs, &group_by, &ExportPrivacy::default());
+        assert_eq!(
+            export.header(ExportFormat::Csv).unwrap(),
+            "day,user_id,requests,input_tokens,output_tokens,total_tokens,cost_usd\n"
+        );
+        assert_eq!(export.rows.len(), 2);
+        assert_eq!(
+            export.render_row(&export.rows[0], ExportFormat::Csv),
+            "2026-10-01,alice,2,200,100,300,0.75\n"
+        );
+        assert_eq!(
+            export.render_row(&export.rows[1], ExportFormat::Csv),
+            "2026-10-02,\"bob, jr.\",1,100,50,150,1.0\n"
+        );
+
+        let line: Value =
+            serde_json::from_str(&export.render_row(&export.rows[0], ExportFormat::Jsonl)).unwrap();
+        assert_eq!(line["user_id"], "alice");
+        assert_eq!(line["cost_usd"], 0.75);
+
+        assert!(GroupBy::parse_list("day,tenant").is_err());
+    }
+
+    #[test]
+    fn test_record_export_and_time_range() {
+        let costs = vec![cost(LLMProviderType::OpenAI, None, 0.5, 1)];
+        let export = UsageExport::build(&costs, &[], &ExportPrivacy::default());
+        assert_eq!(export.columns[0], "request_id");
+        let line = export.render_row(&export.rows[0], ExportFormat::Csv);
+        assert!(line.contains(",openai,gpt-4,,,100,50,150,0.5\n"));
+
+        let end = parse_time("2026-10-01", true).unwrap();
+        assert!(end > parse_time("2026-10-01T23:59:59Z", false).unwrap());
+        assert!(end < parse_time("2026-10-02", false).unwrap());
+        assert!(parse_time("yesterday", false).is_none());
+    }
+
+    #[test]
+    fn test_private_export() {
+        let mut costs = vec![
+            cost(LLMProviderType::OpenAI, Some("alice"), 0.5, 1),
+            cost(LLMProviderType::OpenAI, Some("bob"), 0.25, 1),
+            cost(LLMProviderType::Anthropic, Some("carol"), 1.0, 1),
+            cost(LLMProviderType::Anthropic, Some("carol"), 1.0, 2),
+        ];
+        let privacy = ExportPrivacy::from_query(None, Some(2)).unwrap();
+        let group_by = [GroupBy::Provider];
+        let export = UsageExport::build(&costs, &group_by, &privacy);
+        // Anthropic's costs all come from carol
+        assert_eq!(export.suppressed_groups, 1);
+        assert_eq!(export.rows.len(), 1);
+        assert_eq!(export.rows[0][0], "openai");
+
+        // Sampling keeps the same requests every time and scales totals up
+        for _ in 0..400 {
+            costs.push(cost(LLMProviderType::OpenAI, Some("dave"), 0.01, 3));
+        }
+        let privacy = ExportPrivacy::from_query(Some(0.5), None).unwrap();
+        let sampled = costs.iter().filter(|cost| privacy.sampled(cost)).count();
+        assert!(sampled > 100 && sampled < 300);
+        assert_eq!(
+            costs.iter().filter(|cost| privacy.sampled(cost)).count(),
+            sampled
+        );
+        let export = UsageExport::build(&costs, &group_by, &privacy);
+        let requests: u64 = export.rows.iter().map(|row| row[1].as_u64().unwrap()).sum();
+        assert_eq!(requests, 2 * sampled as u64);
+
+        assert!(ExportPrivacy::from_query(Some(0.0), None).is_err());
+        assert!(ExportPrivacy::from_query(Some(1.5), None).is_err());
+    }
+}
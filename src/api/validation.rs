@@ -0,0 +1,281 @@
+// Request validation for the OpenAI-compatible API
+// Rejects oversized and malformed JSON requests with structured errors before routing
+
+//! # Request Validation
+//!
+//! Every JSON request to the OpenAI-compatible routes passes [`validate_request`]
+//! first, so a malformed request gets an OpenAI-style `400` with error code
+//! `INVALID_INPUT` and the offending `param` instead of reaching a provider:
+//!
+//! - bodies larger than `max_body_bytes` ([`DEFAULT_MAX_BODY_BYTES`] unless configured)
+//! - bodies that are not valid JSON
+//! - chat completions without messages or with more than `max_messages_per_request`
+//! - `max_tokens` or `max_completion_tokens` above `max_tokens_per_request`
+//! - parameters listed in `disallowed_params` for the requested model
+//!
+//! `disallowed_params` maps models to parameter names. A model ending in `*` matches by
+//! prefix, e.g. `o1*` → `temperature` rejects `temperature` for every o1 model. Routes
+//! taking uploads in other formats, like audio transcription, keep their own limits.
+
+use axum::{
+    body::{Body, Bytes, HttpBody},
+    extract::State,
+    http::{header, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::types::{create_error_response, ErrorResponse};
+use super::ApiConfig;
+use crate::ErrorCode;
+
+/// Largest JSON request body accepted unless configured otherwise
+pub const DEFAULT_MAX_BODY_BYTES: usize = 10 * 1024 * 1024;
+
+/// Limits applied to JSON requests
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestLimits {
+    pub max_body_bytes: usize,
+    /// Most messages a chat completion may carry
+    pub max_messages: Option<usize>,
+    /// Highest `max_tokens` a request may ask for
+    pub max_tokens: Option<u32>,
+    /// Parameters rejected per model or model prefix ending in `*`
+    pub disallowed_params: HashMap<String, Vec<String>>,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            max_messages: None,
+            max_tokens: None,
+            disallowed_params: HashMap::new(),
+        }
+    }
+}
+
+impl RequestLimits {
+    pub fn from_config(config: &ApiConfig) -> Self {
+        Self {
+            max_body_bytes: config.max_body_bytes,
+            max_messages: config.max_messages_per_request,
+            max_tokens: config.max_tokens_per_request,
+            disallowed_params: config.disallowed_params.clone(),
+        }
+    }
+
+    /// Parameters requests for `model` must not carry
+    fn disallowed_for<'a>(&'a self, model: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.disallowed_params
+            .iter()
+            .filter(move |(pattern, _)| match pattern.strip_suffix('*') {
+                Some(prefix) => model.starts_with(prefix),
+                None => pattern.as_str() == model,
+            })
+            .flat_map(|(_, params)| params.iter().map(String::as_str))
+    }
+
+    /// Check a parsed JSON request body sent to `path`
+    pub fn validate(&self, path: &str, body: &Value) -> Result<(), ErrorResponse> {
+        let Some(fields) = body.as_object() else {
+            return Err(invalid(
+                "Request body must be a JSON object".to_string(),
+                None,
+            ));
+        };
+
+        if path == "/v1/chat/completions" {
+            match fields.get("messages").and_then(Value::as_array) {
+                Some(messages) if messages.is_empty() => {
+                    return Err(invalid(
+                        "'messages' must contain at least one message".to_string(),
+                        Some("messages"),
+                    ));
+                }
+                Some(messages) if self.max_messages.is_some_and(|max| messages.len() > max) => {
+                    return Err(invalid(
+                        format!(
+                            "'messages' has {} messages; at most {} are allowed",
+                            messages.len(),
+                            self.max_messages.unwrap_or_default()
+                        ),
+                        Some("messages"),
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(max_tokens) = self.max_tokens {
+            for param in ["max_tokens", "max_completion_tokens"] {
+                if let Some(requested) = fields.get(param).and_then(Value::as_u64) {
+                    if requested > max_tokens as u64 {
+                        return Err(invalid(
+                            format!(
+                                "'{}' is {}; at most {} tokens may be requested",
+                                param, requested, max_tokens
+                            ),
+                            Some(param),
+                        ));
+                    }
+                }
+            }
+        }
+
+        if let Some(model) = fields.get("model").and_then(Value::as_str) {
+            if let Some(param) = self
+                .disallowed_for(model)
+                .find(|param| fields.get(*param).is_some_and(|value| !value.is_null()))
+            {
+                return Err(invalid(
+                    format!("'{}' is not supported for model '{}'", param, model),
+                    Some(param),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse `disallowed_params` from `model=param,param;model=param`
+pub fn parse_disallowed_params(spec: &str) -> HashMap<String, Vec<String>> {
+    spec.split(';')
+        .filter_map(|entry| entry.split_once('='))
+        .map(|(model, params)| {
+            let params = params
+                .split(',')
+                .map(str::trim)
+                .filter(|param| !param.is_empty())
+                .map(str::to_string)
+                .collect();
+            (model.trim().to_string(), params)
+        })
+        .filter(|(model, _)| !model.is_empty())
+        .collect()
+}
+
+fn invalid(message: String, param: Option<&str>) -> ErrorResponse {
+    create_error_response(
+        message,
+        "invalid_request_error".to_string(),
+        param.map(str::to_string),
+        None,
+    )
+    .with_error_code(ErrorCode::InvalidInput)
+}
+
+fn too_large(limit: usize) -> Response {
+    create_error_response(
+        format!("Request body exceeds the {} byte limit", limit),
+        "invalid_request_error".to_string(),
+        None,
+        Some("request_too_large".to_string()),
+    )
+    .with_error_code(ErrorCode::InvalidInput)
+    .into_response()
+}
+
+/// Read `body`, giving up once it exceeds `limit` bytes
+pub(crate) async fn read_body(mut body: Body, limit: usize) -> Result<Bytes, Response> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|e| {
+            invalid(format!("Failed to read request body: {}", e), None).into_response()
+        })?;
+        if bytes.len() + chunk.len() > limit {
+            return Err(too_large(limit));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(Bytes::from(bytes))
+}
+
+/// Middleware validating JSON requests against the configured [`RequestLimits`]
+pub async fn validate_request(
+    State(limits): State<Arc<RequestLimits>>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Response {
+    let is_json = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("application/json"));
+    if !is_json {
+        return next.run(request).await;
+    }
+
+    let declared_length = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    if declared_length.is_some_and(|length| length > limits.max_body_bytes) {
+        return too_large(limits.max_body_bytes);
+    }
+
+    let (parts, body) = request.into_parts();
+    let bytes = match read_body(body, limits.max_body_bytes).await {
+        Ok(bytes) => bytes,
+        Err(response) => return response,
+    };
+    if !bytes.is_empty() {
+        let checked = serde_json::from_slice::<Value>(&bytes)
+            .map_err(|e| invalid(format!("Request body is not valid JSON: {}", e), None))
+            .and_then(|body| limits.validate(parts.uri.path(), &body));
+        if let Err(error) = checked {
+            return error.into_response();
+        }
+    }
+
+    next.run(Request::from_parts(parts, Body::from(bytes)))
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rejected_param(limits: &RequestLimits, body: Value) -> Option<String> {
+        limits
+            .validate("/v1/chat/completions", &body)
+            .err()
+            .map(|error| error.error.param.unwrap_or_default())
+    }
+
+    #[test]
+    fn test_validate_chat_completion() {
+        let limits = RequestLimits {
+            max_messages: Some(2),
+            max_tokens: Some(1024),
+            disallowed_params: parse_disallowed_params("o1*=temperature, top_p; gpt-4=logit_bias"),
+            ..RequestLimits::default()
+        };
+        let message = json!({"role": "user", "content": "Hi"});
+
+        let ok = json!({"model": "gpt-4", "messages": [message], "max_tokens": 1024});
+        assert_eq!(rejected_param(&limits, ok), None);
+        let empty = json!({"model": "gpt-4", "messages": []});
+        assert_eq!(rejected_param(&limits, empty).as_deref(), Some("messages"));
+        let long = json!({"model": "gpt-4", "messages": [message, message, message]});
+        assert_eq!(rejected_param(&limits, long).as_deref(), Some("messages"));
+        let greedy =
+            json!({"model": "gpt-4", "messages": [message], "max_completion_tokens": 4096});
+        assert_eq!(
+            rejected_param(&limits, greedy).as_deref(),
+            Some("max_completion_tokens")
+        );
+        let tuned = json!({"model": "o1-mini", "messages": [message], "top_p": 0.5});
+        assert_eq!(rejected_param(&limits, tuned).as_deref(), Some("top_p"));
+        let unset = json!({"model": "o1-mini", "messages": [message], "temperature": null});
+        assert_eq!(rejected_param(&limits, unset), None);
+        let other = json!({"model": "gpt-4o", "messages": [message], "logit_bias": {}});
+        assert_eq!(rejected_param(&limits, other), None);
+    }
+}
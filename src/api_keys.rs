@@ -0,0 +1,957 @@
+//! API Keys
+//!
+//! With `api_key_required` on, the OpenAI-compatible API, the GraphQL server and the MCP
+//! server refuse requests that do not present a valid API key. Operators manage keys
+//! through `/v1/admin/api-keys`; each [`ApiKey`] carries the [`ApiKeyScope`]s it may
+//! be used for and optionally its own requests-per-minute limit and expiry.
+//!
+//! Keys are presented in the `X-API-Key` header, or as `Authorization: Bearer cbk-...`
+//! where the bearer token is not taken by something else (MCP sends its JWTs there).
+//! Secrets are shown once, when a key is created or rotated; stores only keep their
+//! SHA-256 digests. Rotating a key issues a new secret under the same key ID and lets
+//! the old one keep working for a grace period, so clients switch over without a gap.
+//! Revoked keys stay listed but no longer authenticate.
+//!
+//! Keys live in an [`ApiKeyStore`]: in memory by default, or in NATS or Postgres so they
+//! survive restarts and are shared by every replica. The server picks one with
+//! [`API_KEY_STORE_ENV`]. Unknown keys count as failed authentications for the
+//! [authentication throttle](crate::auth_throttle). Every use of a known key, and every
+//! refusal of one, is recorded on the [audit trail](crate::audit_trail).
+
+use async_nats::jetstream::{self, kv, stream};
+use axum::http::{header, HeaderMap};
+use chrono::{DateTime, Duration, Utc};
+use futures::StreamExt;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, RwLock};
+use uuid::Uuid;
+
+use crate::audit::AuthSurface;
+use crate::audit_trail::{AuditCategory, AuditOutcome, AuditRecord, AuditTrail};
+use crate::auth_throttle::{client_ip, AuthThrottle, Lockout};
+use crate::ErrorCode;
+
+lazy_static::lazy_static! {
+    static ref GLOBAL: ApiKeys = ApiKeys::new(Arc::new(InMemoryApiKeyStore::default()));
+}
+
+/// API key store backend: `memory` (default), `nats` or `postgres`
+pub const API_KEY_STORE_ENV: &str = "CIRCUIT_BREAKER_API_KEY_STORE";
+
+/// Prefix of managed API key secrets
+pub const MANAGED_KEY_PREFIX: &str = "cbk-";
+
+/// Header API keys are presented in
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Key-value bucket holding API keys and the digests of their secrets
+pub const API_KEYS_BUCKET: &str = "CB_API_KEYS";
+
+/// Window per-key rate limits are counted over
+const RATE_LIMIT_WINDOW_SECS: i64 = 60;
+
+/// How stale `last_used_at` may get before an authentication writes it again
+const LAST_USED_RESOLUTION_SECS: i64 = 60;
+
+/// Attempts at recording a key's use when other writes to it keep getting in first
+const MAX_TOUCH_ATTEMPTS: usize = 3;
+
+/// What an API key may be used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyScope {
+    /// The OpenAI-compatible API: completions, embeddings, models and the like
+    Llm,
+    /// GraphQL queries and subscriptions
+    WorkflowsRead,
+    /// GraphQL mutations, and everything `workflows_read` allows
+    WorkflowsWrite,
+    /// The MCP server
+    Mcp,
+}
+
+/// An API key, without its secret
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: String,
+    pub name: String,
+    /// Start of the current secret, to tell keys apart
+    pub prefix: String,
+    pub scopes: Vec<ApiKeyScope>,
+    /// Requests the key may make per minute; unlimited when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit_per_minute: Option<u32>,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotated_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    /// Whether the key may be used for `scope`
+    pub fn allows(&self, scope: ApiKeyScope) -> bool {
+        self.scopes.contains(&scope)
+            || (scope == ApiKeyScope::WorkflowsRead
+                && self.scopes.contains(&ApiKeyScope::WorkflowsWrite))
+    }
+
+    /// Whether the key authenticates at `now`: neither revoked nor expired
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.revoked_at.is_none() && self.expires_at.is_none_or(|expires_at| expires_at > now)
+    }
+}
+
+/// Digest of a secret that authenticates a key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SecretDigest {
+    pub digest: String,
+    /// End of the grace period of a secret replaced by rotation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// What a store keeps of a key: the key and the digests of its secrets
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredApiKey {
+    #[serde(flatten)]
+    pub key: ApiKey,
+    pub secrets: Vec<SecretDigest>,
+}
+
+impl StoredApiKey {
+    /// Whether `digest` is one of the key's secrets and still valid at `now`
+    fn accepts(&self, digest: &str, now: DateTime<Utc>) -> bool {
+        self.secrets.iter().any(|secret| {
+            secret.digest == digest && secret.expires_at.is_none_or(|expires_at| expires_at > now)
+        })
+    }
+}
+
+/// A newly created or rotated key together with its secret, which is not retrievable
+/// later
+#[derive(Debug, Clone, Serialize)]
+pub struct MintedApiKey {
+    #[serde(flatten)]
+    pub key: ApiKey,
+    /// The API key to present
+    pub secret: String,
+}
+
+/// A key to create
+#[derive(Debug, Clone, Default)]
+pub struct NewApiKey {
+    pub name: String,
+    pub scopes: Vec<ApiKeyScope>,
+    pub rate_limit_per_minute: Option<u32>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// API key errors
+#[derive(Debug, thiserror::Error)]
+pub enum ApiKeyError {
+    #[error("API key '{0}' not found")]
+    NotFound(String),
+
+    #[error("API key store error: {0}")]
+    Store(String),
+}
+
+fn store_error(action: &str, error: impl std::fmt::Display) -> ApiKeyError {
+    ApiKeyError::Store(format!("Failed to {}: {}", action, error))
+}
+
+/// SHA-256 hex digest under which a secret is stored
+fn digest(secret: &str) -> String {
+    Sha256::digest(secret.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn generate_secret() -> String {
+    let bytes: [u8; 24] = rand::thread_rng().gen();
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("{}{}", MANAGED_KEY_PREFIX, hex)
+}
+
+/// Start of a secret shown in listings
+fn secret_prefix(secret: &str) -> String {
+    secret.chars().take(MANAGED_KEY_PREFIX.len() + 8).collect()
+}
+
+/// Persistence for API keys
+#[async_trait::async_trait]
+pub trait ApiKeyStore: Send + Sync {
+    /// Insert or replace a key
+    async fn put(&self, key: &StoredApiKey) -> Result<(), ApiKeyError>;
+
+    async fn get(&self, key_id: &str) -> Result<Option<StoredApiKey>, ApiKeyError>;
+
+    /// The key one of whose secrets has `digest`
+    async fn find(&self, digest: &str) -> Result<Option<StoredApiKey>, ApiKeyError>;
+
+    async fn list(&self) -> Result<Vec<StoredApiKey>, ApiKeyError>;
+
+    /// Set a key's `last_used_at`, leaving the rest of the stored key as it is now
+    ///
+    /// Authentication calls this concurrently with rotation and revocation, so it must
+    /// not write back a key read earlier.
+    async fn touch(&self, key_id: &str, used_at: DateTime<Utc>) -> Result<(), ApiKeyError>;
+}
+
+/// API key store that forgets every key on restart
+#[derive(Debug, Default)]
+pub struct InMemoryApiKeyStore {
+    keys: RwLock<HashMap<String, StoredApiKey>>,
+}
+
+#[async_trait::async_trait]
+impl ApiKeyStore for InMemoryApiKeyStore {
+    async fn put(&self, key: &StoredApiKey) -> Result<(), ApiKeyError> {
+        self.keys
+            .write()
+            .unwrap()
+            .insert(key.key.id.clone(), key.clone());
+        Ok(())
+    }
+
+    async fn get(&self, key_id: &str) -> Result<Option<StoredApiKey>, ApiKeyError> {
+        Ok(self.keys.read().unwrap().get(key_id).cloned())
+    }
+
+    async fn find(&self, digest: &str) -> Result<Option<StoredApiKey>, ApiKeyError> {
+        Ok(self
+            .keys
+            .read()
+            .unwrap()
+            .values()
+            .find(|key| key.secrets.iter().any(|secret| secret.digest == digest))
+            .cloned())
+    }
+
+    async fn list(&self) -> Result<Vec<StoredApiKey>, ApiKeyError> {
+        Ok(self.keys.read().unwrap().values().cloned().collect())
+    }
+
+    async fn touch(&self, key_id: &str, used_at: DateTime<Utc>) -> Result<(), ApiKeyError> {
+        if let Some(stored) = self.keys.write().unwrap().get_mut(key_id) {
+            stored.key.last_used_at = Some(used_at);
+        }
+        Ok(())
+    }
+}
+
+/// API key store backed by a NATS key-value bucket, shared by every replica
+///
+/// Keys are stored under `key.<id>`, and each secret digest under `digest.<digest>`
+/// pointing at its key.
+pub struct NATSApiKeyStore {
+    bucket: kv::Store,
+}
+
+impl NATSApiKeyStore {
+    /// Connect to NATS and create the API key bucket if needed
+    pub async fn connect(nats_url: &str) -> Result<Self, ApiKeyError> {
+        let client = async_nats::connect(nats_url)
+            .await
+            .map_err(|e| store_error("connect to NATS", e))?;
+        Self::new(jetstream::new(client)).await
+    }
+
+    /// Store keys through an existing JetStream context
+    pub async fn new(jetstream: jetstream::Context) -> Result<Self, ApiKeyError> {
+        let bucket = match jetstream.get_key_value(API_KEYS_BUCKET).await {
+            Ok(bucket) => bucket,
+            Err(_) => jetstream
+                .create_key_value(kv::Config {
+                    bucket: API_KEYS_BUCKET.to_string(),
+                    description: "API keys and the digests of their secrets".to_string(),
+                    history: 1,
+                    storage: stream::StorageType::File,
+                    ..Default::default()
+                })
+                .await
+                .map_err(|e| store_error("create API key bucket", e))?,
+        };
+        Ok(Self { bucket })
+    }
+
+    async fn read(&self, entry: &str) -> Result<Option<StoredApiKey>, ApiKeyError> {
+        match self
+            .bucket
+            .get(entry)
+            .await
+            .map_err(|e| store_error("read API key", e))?
+        {
+            Some(value) => serde_json::from_slice(&value)
+                .map(Some)
+                .map_err(|e| store_error("decode API key", e)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ApiKeyStore for NATSApiKeyStore {
+    async fn put(&self, key: &StoredApiKey) -> Result<(), ApiKeyError> {
+        if let Some(previous) = self.get(&key.key.id).await? {
+            for secret in previous.secrets {
+                if !key.secrets.iter().any(|s| s.digest == secret.digest) {
+                    self.bucket
+                        .delete(format!("digest.{}", secret.digest))
+                        .await
+                        .map_err(|e| store_error("remove API key secret", e))?;
+                }
+            }
+        }
+        for secret in &key.secrets {
+            self.bucket
+                .put(
+                    format!("digest.{}", secret.digest),
+                    key.key.id.clone().into_bytes().into(),
+                )
+                .await
+                .map_err(|e| store_error("store API key secret", e))?;
+        }
+        let value = serde_json::to_vec(key).map_err(|e| store_error("encode API key", e))?;
+        self.bucket
+            .put(format!("key.{}", key.key.id), value.into())
+            .await
+            .map_err(|e| store_error("store API key", e))?;
+        Ok(())
+    }
+
+    async fn get(&self, key_id: &str) -> Result<Option<StoredApiKey>, ApiKeyError> {
+        self.read(&format!("key.{}", key_id)).await
+    }
+
+    async fn find(&self, digest: &str) -> Result<Option<StoredApiKey>, ApiKeyError> {
+        let Some(key_id) = self
+            .bucket
+            .get(format!("digest.{}", digest))
+            .await
+            .map_err(|e| store_error("read API key secret", e))?
+        else {
+            return Ok(None);
+        };
+        let key = self.get(&String::from_utf8_lossy(&key_id)).await?;
+        // The digest entry may outlive a rotation that failed halfway
+        Ok(key.filter(|key| key.secrets.iter().any(|secret| secret.digest == digest)))
+    }
+
+    async fn list(&self) -> Result<Vec<StoredApiKey>, ApiKeyError> {
+        let mut entries = self
+            .bucket
+            .keys()
+            .await
+            .map_err(|e| store_error("list API keys", e))?;
+        let mut keys = Vec::new();
+        while let Some(entry) = entries.next().await {
+            let entry = entry.map_err(|e| store_error("list API keys", e))?;
+            if entry.starts_with("key.") {
+                if let Some(key) = self.read(&entry).await? {
+                    keys.push(key);
+                }
+            }
+        }
+        Ok(keys)
+    }
+
+    async fn touch(&self, key_id: &str, used_at: DateTime<Utc>) -> Result<(), ApiKeyError> {
+        let entry_key = format!("key.{}", key_id);
+        for _ in 0..MAX_TOUCH_ATTEMPTS {
+            let Some(entry) = self
+                .bucket
+                .entry(entry_key.as_str())
+                .await
+                .map_err(|e| store_error("read API key", e))?
+                .filter(|entry| entry.operation == kv::Operation::Put)
+            else {
+                return Ok(());
+            };
+            let mut stored: StoredApiKey = serde_json::from_slice(&entry.value)
+                .map_err(|e| store_error("decode API key", e))?;
+            stored.key.last_used_at = Some(used_at);
+            let value =
+                serde_json::to_vec(&stored).map_err(|e| store_error("encode API key", e))?;
+            // Only written over the revision just read, so a concurrent rotation or
+            // revocation is never undone
+            if self
+                .bucket
+                .update(entry_key.as_str(), value.into(), entry.revision)
+                .await
+                .is_ok()
+            {
+                return Ok(());
+            }
+        }
+        Err(store_error(
+            "record API key use",
+            format!("key '{}' is contended", key_id),
+        ))
+    }
+}
+
+/// API key store backed by Postgres tables
+pub struct PostgresApiKeyStore {
+    pool: sqlx::PgPool,
+}
+
+impl PostgresApiKeyStore {
+    /// Connect to Postgres and migrate its schema
+    pub async fn connect(database_url: &str) -> Result<Self, ApiKeyError> {
+        let pool = crate::engine::postgres_storage::connect(database_url)
+            .await
+            .map_err(|e| store_error("connect to Postgres", e))?;
+        Ok(Self::new(pool))
+    }
+
+    /// Store keys through an existing, migrated connection pool
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+fn key_from_record(record: String) -> Result<StoredApiKey, ApiKeyError> {
+    serde_json::from_str(&record).map_err(|e| store_error("decode API key", e))
+}
+
+#[async_trait::async_trait]
+impl ApiKeyStore for PostgresApiKeyStore {
+    async fn put(&self, key: &StoredApiKey) -> Result<(), ApiKeyError> {
+        let record = serde_json::to_string(key).map_err(|e| store_error("encode API key", e))?;
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| store_error("store API key", e))?;
+        sqlx::query(
+            "INSERT INTO api_keys (id, record) VALUES ($1, $2)
+             ON CONFLICT (id) DO UPDATE SET record = EXCLUDED.record",
+        )
+        .bind(&key.key.id)
+        .bind(record)
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| store_error("store API key", e))?;
+        sqlx::query("DELETE FROM api_key_secrets WHERE key_id = $1")
+            .bind(&key.key.id)
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| store_error("store API key secrets", e))?;
+        for secret in &key.secrets {
+            sqlx::query("INSERT INTO api_key_secrets (digest, key_id) VALUES ($1, $2)")
+                .bind(&secret.digest)
+                .bind(&key.key.id)
+                .execute(&mut *transaction)
+                .await
+                .map_err(|e| store_error("store API key secrets", e))?;
+        }
+        transaction
+            .commit()
+            .await
+            .map_err(|e| store_error("store API key", e))
+    }
+
+    async fn get(&self, key_id: &str) -> Result<Option<StoredApiKey>, ApiKeyError> {
+        let record: Option<(String,)> = sqlx::query_as("SELECT record FROM api_keys WHERE id = $1")
+            .bind(key_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| store_error("read API key", e))?;
+        record.map(|(record,)| key_from_record(record)).transpose()
+    }
+
+    async fn find(&self, digest: &str) -> Result<Option<StoredApiKey>, ApiKeyError> {
+        let record: Option<(String,)> = sqlx::query_as(
+            "SELECT k.record FROM api_key_secrets s JOIN api_keys k ON k.id = s.key_id
+             WHERE s.digest = $1",
+        )
+        .bind(digest)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| store_error("read API key", e))?;
+        record.map(|(record,)| key_from_record(record)).transpose()
+    }
+
+    async fn list(&self) -> Result<Vec<StoredApiKey>, ApiKeyError> {
+        let records: Vec<(String,)> = sqlx::query_as("SELECT record FROM api_keys")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| store_error("list API keys", e))?;
+        records
+            .into_iter()
+            .map(|(record,)| key_from_record(record))
+            .collect()
+    }
+
+    async fn touch(&self, key_id: &str, used_at: DateTime<Utc>) -> Result<(), ApiKeyError> {
+        let used_at =
+            serde_json::to_value(used_at).map_err(|e| store_error("encode API key", e))?;
+        sqlx::query(
+            "UPDATE api_keys
+             SET record = jsonb_set(record::jsonb, '{last_used_at}', $2)::text
+             WHERE id = $1",
+        )
+        .bind(key_id)
+        .bind(sqlx::types::Json(used_at))
+        .execute(&self.pool)
+        .await
+        .map_err(|e| store_error("record API key use", e))?;
+        Ok(())
+    }
+}
+
+/// Why a request's API key was refused
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiKeyRejection {
+    /// The surface requires a key and none was presented
+    Missing,
+    /// The key is unknown, revoked or expired
+    Invalid,
+    /// The key may not be used for this surface or operation
+    Forbidden(ApiKeyScope),
+    /// The key used up its rate limit; retry after this many seconds
+    RateLimited(u64),
+    /// The client IP is locked out after repeated failures
+    LockedOut(Lockout),
+    /// The key store could not be reached
+    Unavailable(String),
+}
+
+impl ApiKeyRejection {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ApiKeyRejection::Missing | ApiKeyRejection::Invalid => ErrorCode::AuthenticationFailed,
+            ApiKeyRejection::Forbidden(_) => ErrorCode::PermissionDenied,
+            ApiKeyRejection::RateLimited(_) | ApiKeyRejection::LockedOut(_) => {
+                ErrorCode::RateLimited
+            }
+            ApiKeyRejection::Unavailable(_) => ErrorCode::StorageError,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            ApiKeyRejection::Missing => format!(
+                "An API key is required; send it in the {} header",
+                API_KEY_HEADER
+            ),
+            ApiKeyRejection::Invalid => "Invalid, revoked or expired API key".to_string(),
+            ApiKeyRejection::Forbidden(scope) => format!(
+                "API key lacks the '{}' scope",
+                serde_json::to_value(scope)
+                    .ok()
+                    .and_then(|value| value.as_str().map(str::to_string))
+                    .unwrap_or_default()
+            ),
+            ApiKeyRejection::RateLimited(retry_after_secs) => format!(
+                "API key rate limit exceeded; retry in {} seconds",
+                retry_after_secs
+            ),
+            ApiKeyRejection::LockedOut(lockout) => lockout.message(),
+            ApiKeyRejection::Unavailable(message) => message.clone(),
+        }
+    }
+}
+
+/// The managed API key a request presents: `X-API-Key`, or a bearer token with the
+/// managed key prefix
+pub fn presented_key(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .or_else(|| {
+            headers
+                .get(header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .filter(|token| token.starts_with(MANAGED_KEY_PREFIX))
+        })
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+}
+
+/// Shared registry of API keys
+///
+/// Clones read and update the same store and rate limits. Servers use
+/// [`ApiKeys::global`], whose store [`ApiKeys::use_store`] replaces at startup.
+#[derive(Clone)]
+pub struct ApiKeys {
+    store: Arc<RwLock<Arc<dyn ApiKeyStore>>>,
+    /// Key ID -> its requests within the rate limit window
+    requests: Arc<Mutex<HashMap<String, VecDeque<DateTime<Utc>>>>>,
+    throttle: AuthThrottle,
+    trail: AuditTrail,
+}
+
+impl std::fmt::Debug for ApiKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiKeys").finish_non_exhaustive()
+    }
+}
+
+impl ApiKeys {
+    pub fn new(store: Arc<dyn ApiKeyStore>) -> Self {
+        Self {
+            store: Arc::new(RwLock::new(store)),
+            requests: Arc::new(Mutex::new(HashMap::new())),
+            throttle: AuthThrottle::global(),
+            trail: AuditTrail::global(),
+        }
+    }
+
+    /// The process-wide registry shared by every API surface
+    pub fn global() -> Self {
+        GLOBAL.clone()
+    }
+
+    /// Keep keys in `store` from now on, for this registry and its clones
+    pub fn use_store(&self, store: Arc<dyn ApiKeyStore>) {
+        *self.store.write().unwrap() = store;
+    }
+
+    fn store(&self) -> Arc<dyn ApiKeyStore> {
+        self.store.read().unwrap().clone()
+    }
+
+    /// Create a key and its first secret
+    pub async fn create(&self, request: NewApiKey) -> Result<MintedApiKey, ApiKeyError> {
+        let secret = generate_secret();
+        let key = ApiKey {
+            id: format!("key_{}", &Uuid::new_v4().simple().to_string()[..16]),
+            name: request.name,
+            prefix: secret_prefix(&secret),
+            scopes: request.scopes,
+            rate_limit_per_minute: request.rate_limit_per_minute,
+            created_at: Utc::now(),
+            expires_at: request.expires_at,
+            last_used_at: None,
+            rotated_at: None,
+            revoked_at: None,
+        };
+        self.store()
+            .put(&StoredApiKey {
+                key: key.clone(),
+                secrets: vec![SecretDigest {
+                    digest: digest(&secret),
+                    expires_at: None,
+                }],
+            })
+            .await?;
+        Ok(MintedApiKey { key, secret })
+    }
+
+    pub async fn get(&self, key_id: &str) -> Result<Option<ApiKey>, ApiKeyError> {
+        Ok(self.store().get(key_id).await?.map(|stored| stored.key))
+    }
+
+    /// All keys, revoked ones included, oldest first
+    pub async fn list(&self) -> Result<Vec<ApiKey>, ApiKeyError> {
+        let mut keys: Vec<ApiKey> = self
+            .store()
+            .list()
+            .await?
+            .into_iter()
+            .map(|stored| stored.key)
+            .collect();
+        keys.sort_by_key(|key| key.created_at);
+        Ok(keys)
+    }
+
+    /// Issue a new secret for a key; its current secrets keep working for `grace`
+    pub async fn rotate(&self, key_id: &str, grace: Duration) -> Result<MintedApiKey, ApiKeyError> {
+        let store = self.store();
+        let now = Utc::now();
+        let mut stored = store
+            .get(key_id)
+            .await?
+            .filter(|stored| stored.key.revoked_at.is_none())
+            .ok_or_else(|| ApiKeyError::NotFound(key_id.to_string()))?;
+
+        let grace_end = now + grace;
+        stored.secrets.retain(|secret| {
+            grace > Duration::zero() && secret.expires_at.is_none_or(|expires_at| expires_at > now)
+        });
+        for secret in &mut stored.secrets {
+            secret.expires_at = Some(
+                secret
+                    .expires_at
+                    .map_or(grace_end, |end| end.min(grace_end)),
+            );
+        }
+        let secret = generate_secret();
+        stored.secrets.push(SecretDigest {
+            digest: digest(&secret),
+            expires_at: None,
+        });
+        stored.key.prefix = secret_prefix(&secret);
+        stored.key.rotated_at = Some(now);
+        store.put(&stored).await?;
+
+        Ok(MintedApiKey {
+            key: stored.key,
+            secret,
+        })
+    }
+
+    /// Revoke a key; it stays listed but no longer authenticates
+    pub async fn revoke(&self, key_id: &str) -> Result<ApiKey, ApiKeyError> {
+        let store = self.store();
+        let mut stored = store
+            .get(key_id)
+            .await?
+            .ok_or_else(|| ApiKeyError::NotFound(key_id.to_string()))?;
+        stored.key.revoked_at.get_or_insert_with(Utc::now);
+        stored.secrets.clear();
+        store.put(&stored).await?;
+        self.requests.lock().unwrap().remove(key_id);
+        Ok(stored.key)
+    }
+
+    /// The active key a secret belongs to, recording that it was used
+    pub async fn authenticate(&self, secret: &str) -> Result<Option<ApiKey>, ApiKeyError> {
+        let store = self.store();
+        let now = Utc::now();
+        let digest = digest(secret);
+        let Some(mut stored) = store.find(&digest).await? else {
+            return Ok(None);
+        };
+        if !stored.accepts(&digest, now) || !stored.key.is_active(now) {
+            return Ok(None);
+        }
+
+        let stale = stored.key.last_used_at.is_none_or(|last_used_at| {
+            now - last_used_at >= Duration::seconds(LAST_USED_RESOLUTION_SECS)
+        });
+        if stale {
+            store.touch(&stored.key.id, now).await?;
+            stored.key.last_used_at = Some(now);
+        }
+        Ok(Some(stored.key))
+    }
+
+    /// Count a request against the key's rate limit, refusing it with the seconds until
+    /// it may retry once the limit is used up
+    pub fn check_rate_limit(&self, key: &ApiKey) -> Result<(), u64> {
+        self.check_rate_limit_at(key, Utc::now())
+    }
+
+    fn check_rate_limit_at(&self, key: &ApiKey, now: DateTime<Utc>) -> Result<(), u64> {
+        let Some(limit) = key.rate_limit_per_minute else {
+            return Ok(());
+        };
+        let window_start = now - Duration::seconds(RATE_LIMIT_WINDOW_SECS);
+        let mut requests = self.requests.lock().unwrap();
+        let times = requests.entry(key.id.clone()).or_default();
+        while times.front().is_some_and(|time| *time <= window_start) {
+            times.pop_front();
+        }
+        if times.len() >= limit as usize {
+            let oldest = times.front().copied().unwrap_or(now);
+            let retry_after = RATE_LIMIT_WINDOW_SECS - (now - oldest).num_seconds();
+            return Err(retry_after.max(1) as u64);
+        }
+        times.push_back(now);
+        Ok(())
+    }
+
+    /// Authenticate the key a request presents and check it may be used for `scope`
+    ///
+    /// Requests without a key yield `Ok(None)`; whether that is acceptable is up to the
+    /// surface. Unknown keys count against the client IP's failed authentications.
+    pub async fn authorize(
+        &self,
+        headers: &HeaderMap,
+        scope: ApiKeyScope,
+    ) -> Result<Option<ApiKey>, ApiKeyRejection> {
+        let Some(secret) = presented_key(headers) else {
+            return Ok(None);
+        };
+        let ip = client_ip(headers);
+        self.throttle
+            .check(ip.as_deref(), None)
+            .map_err(ApiKeyRejection::LockedOut)?;
+
+        let key = self
+            .authenticate(secret)
+            .await
+            .map_err(|e| ApiKeyRejection::Unavailable(e.to_string()))?;
+        let Some(key) = key else {
+            let lockout = self.throttle.record_failure(
+                AuthSurface::ApiKey,
+                ip.as_deref(),
+                None,
+                "Unknown, revoked or expired API key",
+            );
+            return Err(lockout
+                .map(ApiKeyRejection::LockedOut)
+                .unwrap_or(ApiKeyRejection::Invalid));
+        };
+        let usage = |outcome, detail: String| {
+            AuditRecord::new(AuditCategory::KeyUsage, "api_key.use", outcome)
+                .with_actor(key.id.clone())
+                .with_ip(ip.as_deref())
+                .with_detail(detail)
+        };
+        if !key.allows(scope) {
+            self.trail
+                .record(usage(
+                    AuditOutcome::Denied,
+                    format!("Key lacks the {:?} scope", scope),
+                ))
+                .await;
+            return Err(ApiKeyRejection::Forbidden(scope));
+        }
+        if let Err(retry_after) = self.check_rate_limit(&key) {
+            self.trail
+                .record(usage(
+                    AuditOutcome::Denied,
+                    format!("Rate limited for {} seconds", retry_after),
+                ))
+                .await;
+            return Err(ApiKeyRejection::RateLimited(retry_after));
+        }
+        self.trail
+            .record(usage(
+                AuditOutcome::Success,
+                format!("Used for {:?}", scope),
+            ))
+            .await;
+        Ok(Some(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(secret: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(API_KEY_HEADER, HeaderValue::from_str(secret).unwrap());
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_key_lifecycle() {
+        let keys = ApiKeys::new(Arc::new(InMemoryApiKeyStore::default()));
+        let minted = keys
+            .create(NewApiKey {
+                name: "ci".to_string(),
+                scopes: vec![ApiKeyScope::Llm, ApiKeyScope::WorkflowsWrite],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(minted.secret.starts_with(&minted.key.prefix));
+
+        // Only digests are stored
+        let stored = keys.store().get(&minted.key.id).await.unwrap().unwrap();
+        assert!(!serde_json::to_string(&stored)
+            .unwrap()
+            .contains(&minted.secret));
+
+        let key = keys
+            .authorize(&headers(&minted.secret), ApiKeyScope::WorkflowsRead)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(key.last_used_at.is_some());
+        assert_eq!(
+            keys.authorize(&headers(&minted.secret), ApiKeyScope::Mcp)
+                .await,
+            Err(ApiKeyRejection::Forbidden(ApiKeyScope::Mcp))
+        );
+        assert_eq!(
+            keys.authorize(&HeaderMap::new(), ApiKeyScope::Llm).await,
+            Ok(None)
+        );
+
+        // Rotated secrets keep working through their grace period
+        let rotated = keys
+            .rotate(&minted.key.id, Duration::seconds(60))
+            .await
+            .unwrap();
+        assert_eq!(rotated.key.id, minted.key.id);
+        assert!(keys.authenticate(&minted.secret).await.unwrap().is_some());
+        let rotated_again = keys.rotate(&minted.key.id, Duration::zero()).await.unwrap();
+        assert!(keys.authenticate(&minted.secret).await.unwrap().is_none());
+        assert!(keys.authenticate(&rotated.secret).await.unwrap().is_none());
+
+        keys.revoke(&minted.key.id).await.unwrap();
+        assert_eq!(
+            keys.authorize(&headers(&rotated_again.secret), ApiKeyScope::Llm)
+                .await,
+            Err(ApiKeyRejection::Invalid)
+        );
+        assert!(keys.list().await.unwrap()[0].revoked_at.is_some());
+        assert!(matches!(
+            keys.rotate(&minted.key.id, Duration::zero()).await,
+            Err(ApiKeyError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_recording_use_keeps_concurrent_revocation() {
+        let keys = ApiKeys::new(Arc::new(InMemoryApiKeyStore::default()));
+        let minted = keys
+            .create(NewApiKey {
+                name: "ci".to_string(),
+                scopes: vec![ApiKeyScope::Llm],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        // The key is revoked between authentication reading it and recording its use
+        keys.revoke(&minted.key.id).await.unwrap();
+        keys.store()
+            .touch(&minted.key.id, Utc::now())
+            .await
+            .unwrap();
+
+        let stored = keys.store().get(&minted.key.id).await.unwrap().unwrap();
+        assert!(stored.key.revoked_at.is_some());
+        assert!(stored.key.last_used_at.is_some());
+        assert!(stored.secrets.is_empty());
+        assert!(keys.authenticate(&minted.secret).await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let keys = ApiKeys::new(Arc::new(InMemoryApiKeyStore::default()));
+        let key = ApiKey {
+            id: "key_limited".to_string(),
+            name: "limited".to_string(),
+            prefix: "cbk-".to_string(),
+            scopes: vec![ApiKeyScope::Llm],
+            rate_limit_per_minute: Some(2),
+            created_at: Utc::now(),
+            expires_at: None,
+            last_used_at: None,
+            rotated_at: None,
+            revoked_at: None,
+        };
+        let start = Utc::now();
+        assert!(keys.check_rate_limit_at(&key, start).is_ok());
+        assert!(keys
+            .check_rate_limit_at(&key, start + Duration::seconds(20))
+            .is_ok());
+        assert_eq!(
+            keys.check_rate_limit_at(&key, start + Duration::seconds(30)),
+            Err(30)
+        );
+        assert!(keys
+            .check_rate_limit_at(&key, start + Duration::seconds(61))
+            .is_ok());
+    }
+}
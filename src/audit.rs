@@ -0,0 +1,223 @@
+//! Audit Log
+//!
+//! Security events - failed authentications, lockouts and anomalies such as credential
+//! stuffing - are published here by every surface that authenticates callers: the REST
+//! API, the GraphQL server and MCP. MCP also reports OAuth tokens its providers refuse
+//! to refresh. The log keeps the most recent events in memory for
+//! `GET /v1/admin/audit/events`, hands them to live subscribers, and writes each one to
+//! the `audit` tracing target so it also reaches the server's log output and
+//! `/admin/logs/stream`. Every event is also appended to the
+//! [audit trail](crate::audit_trail) as an authentication record.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::audit_trail::{AuditCategory, AuditOutcome, AuditRecord, AuditTrail};
+
+/// Events kept in memory; older ones are dropped
+pub const AUDIT_LOG_CAPACITY: usize = 10_000;
+
+/// Events buffered per live subscriber before the slowest ones start skipping
+const AUDIT_CHANNEL_CAPACITY: usize = 256;
+
+lazy_static::lazy_static! {
+    static ref GLOBAL: AuditLog = AuditLog::with_trail(AuditTrail::global());
+}
+
+/// How a caller tried to authenticate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthSurface {
+    /// A tenant API key on the OpenAI-compatible API
+    ApiKey,
+    /// The operator token guarding admin endpoints
+    AdminToken,
+    /// A service account token on the GraphQL server
+    ServiceToken,
+    /// An MCP app, installation or session JWT
+    Jwt,
+    /// An OAuth authorization callback
+    OAuthCallback,
+    /// A stored OAuth token the server renews with its provider
+    OAuthToken,
+}
+
+impl AuthSurface {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AuthSurface::ApiKey => "api_key",
+            AuthSurface::AdminToken => "admin_token",
+            AuthSurface::ServiceToken => "service_token",
+            AuthSurface::Jwt => "jwt",
+            AuthSurface::OAuthCallback => "oauth_callback",
+            AuthSurface::OAuthToken => "oauth_token",
+        }
+    }
+}
+
+/// What happened
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecurityEventKind {
+    /// Credentials were presented and rejected
+    AuthFailure,
+    /// Too many failures locked a client IP or principal out
+    Lockout,
+    /// A pattern of failures suggests an attack, such as one IP trying many principals
+    Anomaly,
+    /// A provider refused to refresh a stored OAuth token; its integration stops
+    /// working once the token expires
+    TokenRefreshFailure,
+}
+
+impl SecurityEventKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SecurityEventKind::AuthFailure => "auth_failure",
+            SecurityEventKind::Lockout => "lockout",
+            SecurityEventKind::Anomaly => "anomaly",
+            SecurityEventKind::TokenRefreshFailure => "token_refresh_failure",
+        }
+    }
+}
+
+/// One entry of the audit log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SecurityEvent {
+    pub id: Uuid,
+    pub timestamp: DateTime<Utc>,
+    pub kind: SecurityEventKind,
+    pub surface: AuthSurface,
+    /// Client IP the attempt came from, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+    /// Account, app or instance the caller tried to authenticate as, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub principal: Option<String>,
+    pub detail: String,
+}
+
+impl SecurityEvent {
+    pub fn new(
+        kind: SecurityEventKind,
+        surface: AuthSurface,
+        ip: Option<&str>,
+        principal: Option<&str>,
+        detail: impl Into<String>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            kind,
+            surface,
+            ip: ip.map(str::to_string),
+            principal: principal.map(str::to_string),
+            detail: detail.into(),
+        }
+    }
+
+    /// The audit trail's record of this event
+    pub fn to_record(&self) -> AuditRecord {
+        let outcome = match self.kind {
+            SecurityEventKind::Lockout => AuditOutcome::Denied,
+            _ => AuditOutcome::Failure,
+        };
+        let mut record = AuditRecord::new(
+            AuditCategory::Auth,
+            format!("{}.{}", self.surface.name(), self.kind.name()),
+            outcome,
+        )
+        .with_ip(self.ip.as_deref())
+        .with_detail(self.detail.clone());
+        record.id = self.id;
+        record.timestamp = self.timestamp;
+        record.actor = self.principal.clone();
+        record
+    }
+}
+
+/// Shared, bounded log of security events
+///
+/// Clones publish to and read from the same log. Servers use [`AuditLog::global`];
+/// separate instances, with a trail of their own, are mainly useful in tests.
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    events: Arc<Mutex<VecDeque<SecurityEvent>>>,
+    sender: broadcast::Sender<SecurityEvent>,
+    trail: AuditTrail,
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AuditLog {
+    pub fn new() -> Self {
+        Self::with_trail(AuditTrail::default())
+    }
+
+    /// A log that appends its events to `trail`
+    pub fn with_trail(trail: AuditTrail) -> Self {
+        Self {
+            events: Arc::new(Mutex::new(VecDeque::new())),
+            sender: broadcast::channel(AUDIT_CHANNEL_CAPACITY).0,
+            trail,
+        }
+    }
+
+    /// The process-wide audit log
+    pub fn global() -> Self {
+        GLOBAL.clone()
+    }
+
+    /// The trail this log appends its events to
+    pub fn trail(&self) -> &AuditTrail {
+        &self.trail
+    }
+
+    /// Record an event, log it, append it to the trail and send it to live subscribers
+    pub fn publish(&self, event: SecurityEvent) {
+        tracing::warn!(
+            target: "audit",
+            kind = ?event.kind,
+            surface = ?event.surface,
+            ip = event.ip.as_deref().unwrap_or("-"),
+            principal = event.principal.as_deref().unwrap_or("-"),
+            "{}",
+            event.detail
+        );
+        if let Ok(mut events) = self.events.lock() {
+            if events.len() == AUDIT_LOG_CAPACITY {
+                events.pop_front();
+            }
+            events.push_back(event.clone());
+        }
+        self.trail.record_detached(event.to_record());
+        let _ = self.sender.send(event);
+    }
+
+    /// Up to `limit` of the most recent events, newest first, optionally of one kind
+    pub fn recent(&self, kind: Option<SecurityEventKind>, limit: usize) -> Vec<SecurityEvent> {
+        let Ok(events) = self.events.lock() else {
+            return Vec::new();
+        };
+        events
+            .iter()
+            .rev()
+            .filter(|event| kind.is_none_or(|kind| event.kind == kind))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// Subscribe to events published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<SecurityEvent> {
+        self.sender.subscribe()
+    }
+}
@@ -0,0 +1,623 @@
+//! Audit Trail
+//!
+//! An append-only record of who did what, for compliance reviews: authentications and
+//! their failures, API key usage, OAuth grants, MCP tool invocations and mutations
+//! through the admin API. Each [`AuditRecord`] names its actor, tenant, client IP and
+//! outcome where they are known. Security events of the [audit log](crate::audit) are
+//! copied here as authentication records.
+//!
+//! Records live in an [`AuditStore`]: in memory by default, or in a NATS stream or a
+//! Postgres table that refuse deletes, so the trail survives restarts and is shared by
+//! every replica. The server picks one with [`AUDIT_STORE_ENV`]. Operators search it
+//! through `GET /v1/admin/audit/records`.
+
+use async_nats::jetstream::{self, consumer, stream};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, RwLock};
+use uuid::Uuid;
+
+lazy_static::lazy_static! {
+    static ref GLOBAL: AuditTrail = AuditTrail::new(Arc::new(InMemoryAuditStore::default()));
+}
+
+/// Audit store backend: `memory` (default), `nats` or `postgres`
+pub const AUDIT_STORE_ENV: &str = "CIRCUIT_BREAKER_AUDIT_STORE";
+
+/// Stream holding audit records
+pub const AUDIT_STREAM: &str = "CB_AUDIT";
+
+/// Subjects audit records are published on, followed by their category
+pub const AUDIT_SUBJECT_PREFIX: &str = "cb.audit";
+
+/// Records kept by the in-memory store; older ones are dropped
+pub const IN_MEMORY_AUDIT_CAPACITY: usize = 100_000;
+
+/// Records returned by a query that sets no limit
+pub const DEFAULT_QUERY_LIMIT: usize = 100;
+
+/// Most records a query returns, whatever limit it sets
+pub const MAX_QUERY_LIMIT: usize = 10_000;
+
+/// Messages fetched at a time while replaying the audit stream
+const REPLAY_BATCH_SIZE: usize = 1000;
+
+/// What kind of activity a record describes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditCategory {
+    /// A caller authenticated, or failed to
+    Auth,
+    /// A managed API key was presented
+    KeyUsage,
+    /// A user granted, or refused, an OAuth provider access for an installation
+    OauthGrant,
+    /// An MCP tool was called
+    ToolInvocation,
+    /// The admin API changed something
+    AdminMutation,
+}
+
+impl AuditCategory {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AuditCategory::Auth => "auth",
+            AuditCategory::KeyUsage => "key_usage",
+            AuditCategory::OauthGrant => "oauth_grant",
+            AuditCategory::ToolInvocation => "tool_invocation",
+            AuditCategory::AdminMutation => "admin_mutation",
+        }
+    }
+}
+
+/// How the recorded action ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Success,
+    /// The action was attempted and failed
+    Failure,
+    /// The actor was not allowed to attempt it
+    Denied,
+}
+
+impl AuditOutcome {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AuditOutcome::Success => "success",
+            AuditOutcome::Failure => "failure",
+            AuditOutcome::Denied => "denied",
+        }
+    }
+}
+
+/// One entry of the audit trail
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub id: Uuid,
+    pub timestamp: DateTime<Utc>,
+    pub category: AuditCategory,
+    /// What was done, such as `api_key.use`, `tools/call` or `DELETE /v1/admin/roles/:subject`
+    pub action: String,
+    pub outcome: AuditOutcome,
+    /// API key, service account, installation or user that acted, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    /// Client IP the action came from, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+    /// What the action was applied to, such as a tool name or request path
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl AuditRecord {
+    pub fn new(category: AuditCategory, action: impl Into<String>, outcome: AuditOutcome) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            category,
+            action: action.into(),
+            outcome,
+            actor: None,
+            tenant_id: None,
+            ip: None,
+            target: None,
+            detail: None,
+        }
+    }
+
+    pub fn with_actor(mut self, actor: impl Into<String>) -> Self {
+        self.actor = Some(actor.into());
+        self
+    }
+
+    pub fn with_tenant(mut self, tenant_id: Option<&str>) -> Self {
+        self.tenant_id = tenant_id.map(str::to_string);
+        self
+    }
+
+    pub fn with_ip(mut self, ip: Option<&str>) -> Self {
+        self.ip = ip.map(str::to_string);
+        self
+    }
+
+    pub fn with_target(mut self, target: impl Into<String>) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+/// Filter of an audit trail search; every field set must match
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct AuditQuery {
+    #[serde(default)]
+    pub category: Option<AuditCategory>,
+    #[serde(default)]
+    pub action: Option<String>,
+    #[serde(default)]
+    pub outcome: Option<AuditOutcome>,
+    #[serde(default)]
+    pub actor: Option<String>,
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+    #[serde(default)]
+    pub ip: Option<String>,
+    /// Only records at or after this time
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+    /// Only records before this time
+    #[serde(default)]
+    pub until: Option<DateTime<Utc>>,
+    /// Most records returned, [`DEFAULT_QUERY_LIMIT`] by default and at most
+    /// [`MAX_QUERY_LIMIT`]
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    pub fn matches(&self, record: &AuditRecord) -> bool {
+        fn field_matches(expected: &Option<String>, actual: &Option<String>) -> bool {
+            expected.is_none() || expected == actual
+        }
+
+        self.category
+            .is_none_or(|category| record.category == category)
+            && self.outcome.is_none_or(|outcome| record.outcome == outcome)
+            && self
+                .action
+                .as_ref()
+                .is_none_or(|action| &record.action == action)
+            && field_matches(&self.actor, &record.actor)
+            && field_matches(&self.tenant_id, &record.tenant_id)
+            && field_matches(&self.ip, &record.ip)
+            && self.since.is_none_or(|since| record.timestamp >= since)
+            && self.until.is_none_or(|until| record.timestamp < until)
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+            .unwrap_or(DEFAULT_QUERY_LIMIT)
+            .min(MAX_QUERY_LIMIT)
+    }
+}
+
+/// Audit trail errors
+#[derive(Debug, thiserror::Error)]
+pub enum AuditError {
+    #[error("Audit store error: {0}")]
+    Store(String),
+}
+
+fn store_error(action: &str, error: impl std::fmt::Display) -> AuditError {
+    AuditError::Store(format!("Failed to {}: {}", action, error))
+}
+
+/// Persistence for the audit trail; records are only ever appended
+#[async_trait::async_trait]
+pub trait AuditStore: Send + Sync {
+    async fn append(&self, record: &AuditRecord) -> Result<(), AuditError>;
+
+    /// Records matching `query`, newest first, at most `query.limit()` of them
+    async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>, AuditError>;
+}
+
+/// Audit store that keeps the most recent records until restart
+#[derive(Debug, Default)]
+pub struct InMemoryAuditStore {
+    records: Mutex<VecDeque<AuditRecord>>,
+}
+
+#[async_trait::async_trait]
+impl AuditStore for InMemoryAuditStore {
+    async fn append(&self, record: &AuditRecord) -> Result<(), AuditError> {
+        let mut records = self.records.lock().unwrap();
+        if records.len() == IN_MEMORY_AUDIT_CAPACITY {
+            records.pop_front();
+        }
+        records.push_back(record.clone());
+        Ok(())
+    }
+
+    async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>, AuditError> {
+        Ok(self
+            .records
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .filter(|record| query.matches(record))
+            .take(query.limit())
+            .cloned()
+            .collect())
+    }
+}
+
+/// Audit store backed by a JetStream stream that refuses deletes and purges
+///
+/// Records are published on `cb.audit.<category>`. Queries replay the stream from
+/// `since`, or from its start.
+pub struct NATSAuditStore {
+    jetstream: jetstream::Context,
+}
+
+impl NATSAuditStore {
+    /// Connect to NATS and create the audit stream if needed
+    pub async fn connect(nats_url: &str) -> Result<Self, AuditError> {
+        let client = async_nats::connect(nats_url)
+            .await
+            .map_err(|e| store_error("connect to NATS", e))?;
+        Self::new(jetstream::new(client)).await
+    }
+
+    /// Store records through an existing JetStream context
+    pub async fn new(jetstream: jetstream::Context) -> Result<Self, AuditError> {
+        jetstream
+            .get_or_create_stream(stream::Config {
+                name: AUDIT_STREAM.to_string(),
+                description: Some("Append-only audit trail".to_string()),
+                subjects: vec![format!("{}.>", AUDIT_SUBJECT_PREFIX)],
+                storage: stream::StorageType::File,
+                deny_delete: true,
+                deny_purge: true,
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| store_error("create audit stream", e))?;
+        Ok(Self { jetstream })
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditStore for NATSAuditStore {
+    async fn append(&self, record: &AuditRecord) -> Result<(), AuditError> {
+        let subject = format!("{}.{}", AUDIT_SUBJECT_PREFIX, record.category.name());
+        let payload =
+            serde_json::to_vec(record).map_err(|e| store_error("encode audit record", e))?;
+        self.jetstream
+            .publish(subject, payload.into())
+            .await
+            .map_err(|e| store_error("publish audit record", e))?
+            .await
+            .map_err(|e| store_error("store audit record", e))?;
+        Ok(())
+    }
+
+    async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>, AuditError> {
+        let deliver_policy = match query.since {
+            Some(since) => consumer::DeliverPolicy::ByStartTime {
+                start_time: time::OffsetDateTime::from_unix_timestamp(since.timestamp())
+                    .map_err(|e| store_error("convert start time", e))?,
+            },
+            None => consumer::DeliverPolicy::All,
+        };
+        let filter_subject = query
+            .category
+            .map(|category| format!("{}.{}", AUDIT_SUBJECT_PREFIX, category.name()))
+            .unwrap_or_default();
+        let stream = self
+            .jetstream
+            .get_stream(AUDIT_STREAM)
+            .await
+            .map_err(|e| store_error("open audit stream", e))?;
+        let consumer = stream
+            .create_consumer(consumer::pull::Config {
+                durable_name: None,
+                deliver_policy,
+                filter_subject,
+                ack_policy: consumer::AckPolicy::None,
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| store_error("create audit consumer", e))?;
+
+        // The stream is oldest first; keep the newest matches
+        let limit = query.limit();
+        let mut records = VecDeque::new();
+        loop {
+            let mut batch = consumer
+                .fetch()
+                .max_messages(REPLAY_BATCH_SIZE)
+                .messages()
+                .await
+                .map_err(|e| store_error("fetch audit records", e))?;
+
+            let mut received = 0;
+            while let Some(message) = batch.next().await {
+                let message = message.map_err(|e| store_error("receive audit record", e))?;
+                received += 1;
+                if let Ok(record) = serde_json::from_slice::<AuditRecord>(&message.payload) {
+                    if query.matches(&record) {
+                        if records.len() == limit {
+                            records.pop_front();
+                        }
+                        records.push_back(record);
+                    }
+                }
+            }
+            if received < REPLAY_BATCH_SIZE {
+                break;
+            }
+        }
+
+        Ok(records.into_iter().rev().collect())
+    }
+}
+
+/// Audit store backed by a Postgres table whose rows cannot be updated or deleted
+pub struct PostgresAuditStore {
+    pool: sqlx::PgPool,
+}
+
+impl PostgresAuditStore {
+    /// Connect to Postgres and migrate its schema
+    pub async fn connect(database_url: &str) -> Result<Self, AuditError> {
+        let pool = crate::engine::postgres_storage::connect(database_url)
+            .await
+            .map_err(|e| store_error("connect to Postgres", e))?;
+        Ok(Self::new(pool))
+    }
+
+    /// Store records through an existing, migrated connection pool
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditStore for PostgresAuditStore {
+    async fn append(&self, record: &AuditRecord) -> Result<(), AuditError> {
+        let encoded =
+            serde_json::to_string(record).map_err(|e| store_error("encode audit record", e))?;
+        sqlx::query(
+            "INSERT INTO audit_records
+                (id, recorded_at, category, action, outcome, actor, tenant_id, ip, record)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+        )
+        .bind(record.id)
+        .bind(record.timestamp)
+        .bind(record.category.name())
+        .bind(&record.action)
+        .bind(record.outcome.name())
+        .bind(&record.actor)
+        .bind(&record.tenant_id)
+        .bind(&record.ip)
+        .bind(encoded)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| store_error("store audit record", e))?;
+        Ok(())
+    }
+
+    async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>, AuditError> {
+        let mut builder = sqlx::QueryBuilder::<sqlx::Postgres>::new(
+            "SELECT record FROM audit_records WHERE TRUE",
+        );
+        if let Some(category) = query.category {
+            builder.push(" AND category = ").push_bind(category.name());
+        }
+        if let Some(outcome) = query.outcome {
+            builder.push(" AND outcome = ").push_bind(outcome.name());
+        }
+        for (column, value) in [
+            ("action", &query.action),
+            ("actor", &query.actor),
+            ("tenant_id", &query.tenant_id),
+            ("ip", &query.ip),
+        ] {
+            if let Some(value) = value {
+                builder
+                    .push(format!(" AND {} = ", column))
+                    .push_bind(value.clone());
+            }
+        }
+        if let Some(since) = query.since {
+            builder.push(" AND recorded_at >= ").push_bind(since);
+        }
+        if let Some(until) = query.until {
+            builder.push(" AND recorded_at < ").push_bind(until);
+        }
+        builder
+            .push(" ORDER BY seq DESC LIMIT ")
+            .push_bind(query.limit() as i64);
+
+        let rows: Vec<(String,)> = builder
+            .build_query_as()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| store_error("query audit records", e))?;
+        rows.into_iter()
+            .map(|(record,)| {
+                serde_json::from_str(&record).map_err(|e| store_error("decode audit record", e))
+            })
+            .collect()
+    }
+}
+
+/// Shared handle on the audit trail
+///
+/// Clones append to and search the same store. Servers use [`AuditTrail::global`],
+/// whose store [`AuditTrail::use_store`] replaces at startup.
+#[derive(Clone)]
+pub struct AuditTrail {
+    store: Arc<RwLock<Arc<dyn AuditStore>>>,
+}
+
+impl std::fmt::Debug for AuditTrail {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuditTrail").finish_non_exhaustive()
+    }
+}
+
+impl Default for AuditTrail {
+    fn default() -> Self {
+        Self::new(Arc::new(InMemoryAuditStore::default()))
+    }
+}
+
+impl AuditTrail {
+    pub fn new(store: Arc<dyn AuditStore>) -> Self {
+        Self {
+            store: Arc::new(RwLock::new(store)),
+        }
+    }
+
+    /// The process-wide audit trail
+    pub fn global() -> Self {
+        GLOBAL.clone()
+    }
+
+    /// Append records to `store` from now on, for this trail and its clones
+    pub fn use_store(&self, store: Arc<dyn AuditStore>) {
+        *self.store.write().unwrap() = store;
+    }
+
+    fn store(&self) -> Arc<dyn AuditStore> {
+        self.store.read().unwrap().clone()
+    }
+
+    /// Append a record
+    ///
+    /// The action being audited has already happened, so a store failure is logged
+    /// rather than returned.
+    pub async fn record(&self, record: AuditRecord) {
+        tracing::info!(
+            target: "audit",
+            category = record.category.name(),
+            outcome = record.outcome.name(),
+            actor = record.actor.as_deref().unwrap_or("-"),
+            tenant = record.tenant_id.as_deref().unwrap_or("-"),
+            ip = record.ip.as_deref().unwrap_or("-"),
+            "{}",
+            record.action
+        );
+        if let Err(e) = self.store().append(&record).await {
+            tracing::error!(target: "audit", "Failed to append audit record {}: {}", record.id, e);
+        }
+    }
+
+    /// Append a record from code that cannot wait for the store
+    pub fn record_detached(&self, record: AuditRecord) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            tracing::error!(target: "audit", "Dropped audit record {}: no runtime", record.id);
+            return;
+        };
+        let trail = self.clone();
+        runtime.spawn(async move { trail.record(record).await });
+    }
+
+    /// Records matching `query`, newest first
+    pub async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>, AuditError> {
+        self.store().query(query).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_query_filters_newest_first() {
+        let trail = AuditTrail::default();
+        trail
+            .record(
+                AuditRecord::new(
+                    AuditCategory::KeyUsage,
+                    "api_key.use",
+                    AuditOutcome::Success,
+                )
+                .with_actor("key_a")
+                .with_ip(Some("10.0.0.1")),
+            )
+            .await;
+        trail
+            .record(
+                AuditRecord::new(
+                    AuditCategory::ToolInvocation,
+                    "tools/call",
+                    AuditOutcome::Failure,
+                )
+                .with_actor("inst_1")
+                .with_tenant(Some("acme"))
+                .with_target("create_issue"),
+            )
+            .await;
+        trail
+            .record(
+                AuditRecord::new(AuditCategory::KeyUsage, "api_key.use", AuditOutcome::Denied)
+                    .with_actor("key_a"),
+            )
+            .await;
+
+        let key_a = trail
+            .query(&AuditQuery {
+                actor: Some("key_a".to_string()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(key_a.len(), 2);
+        assert_eq!(key_a[0].outcome, AuditOutcome::Denied);
+
+        let acme = trail
+            .query(&AuditQuery {
+                tenant_id: Some("acme".to_string()),
+                outcome: Some(AuditOutcome::Failure),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(acme.len(), 1);
+        assert_eq!(acme[0].target.as_deref(), Some("create_issue"));
+
+        let limited = trail
+            .query(&AuditQuery {
+                limit: Some(1),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(limited.len(), 1);
+        assert_eq!(limited[0].actor.as_deref(), Some("key_a"));
+
+        let future = trail
+            .query(&AuditQuery {
+                since: Some(Utc::now() + chrono::Duration::minutes(1)),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(future.is_empty());
+    }
+}
@@ -0,0 +1,492 @@
+//! Authentication Throttling
+//!
+//! Guards every way of authenticating - tenant API keys, the admin token, service
+//! account tokens, MCP JWTs and OAuth callbacks - against brute force. Failed attempts
+//! are counted per client IP and per principal (the account, app or instance a caller
+//! tries to authenticate as) over a sliding window. After a few free failures each
+//! further one locks the IP or principal out for twice as long as the last, up to a
+//! cap; while locked out, attempts are refused with `RATE_LIMITED` before their
+//! credentials are even checked. A successful authentication clears its principal's
+//! failures, but not its IP's.
+//!
+//! One IP failing against many different principals looks like credential stuffing
+//! rather than a forgotten password, so it is locked out for the longest period at once.
+//! The admin token has no principal of its own, so its failures are also counted under
+//! [`ADMIN_TOKEN_PRINCIPAL`] to stop guesses spread over many IPs.
+//!
+//! Client IPs come from the connected peer; `X-Forwarded-For` and `X-Real-IP` are only
+//! honoured from the proxies listed in `CIRCUIT_BREAKER_TRUSTED_PROXIES`.
+//! Failures, lockouts and such anomalies are published to the [audit log](crate::audit).
+
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{HeaderMap, HeaderValue, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, VecDeque};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+
+use crate::audit::{AuditLog, AuthSurface, SecurityEvent, SecurityEventKind};
+
+/// Comma-separated addresses of the proxies whose forwarded headers are trusted
+pub const TRUSTED_PROXIES_ENV: &str = "CIRCUIT_BREAKER_TRUSTED_PROXIES";
+
+/// Header [`record_peer_addr`] sets to the address of the connected peer
+pub const PEER_ADDR_HEADER: &str = "x-circuit-breaker-peer-addr";
+
+/// Principal admin token failures are counted under, whatever IP they come from
+pub const ADMIN_TOKEN_PRINCIPAL: &str = "admin-token";
+
+lazy_static::lazy_static! {
+    static ref GLOBAL: AuthThrottle = AuthThrottle::new(ThrottlePolicy::default(), AuditLog::global());
+    static ref TRUSTED_PROXIES: Vec<IpAddr> = trusted_proxies_from_env();
+}
+
+/// When failed authentications start locking callers out, and for how long
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThrottlePolicy {
+    /// Failures within the window that are let through before the first lockout
+    pub free_failures: usize,
+    /// How far back failures are counted
+    pub window_secs: u64,
+    /// Length of the first lockout, doubled by every further failure
+    pub base_lockout_secs: u64,
+    /// Longest lockout, also imposed on IPs that look like credential stuffing
+    pub max_lockout_secs: u64,
+    /// Distinct principals one IP may fail against within the window before it is
+    /// treated as credential stuffing
+    pub stuffing_principals: usize,
+}
+
+impl Default for ThrottlePolicy {
+    fn default() -> Self {
+        Self {
+            free_failures: 5,
+            window_secs: 900,
+            base_lockout_secs: 5,
+            max_lockout_secs: 900,
+            stuffing_principals: 10,
+        }
+    }
+}
+
+impl ThrottlePolicy {
+    /// Lockout after `failures` failures within the window, if any
+    fn lockout_secs(&self, failures: usize) -> Option<u64> {
+        let excess = failures.checked_sub(self.free_failures + 1)?;
+        let factor = 1u64.checked_shl(excess.min(63) as u32).unwrap_or(u64::MAX);
+        Some(
+            self.base_lockout_secs
+                .saturating_mul(factor)
+                .min(self.max_lockout_secs),
+        )
+    }
+}
+
+/// An IP or principal refused until its lockout ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lockout {
+    pub retry_after_secs: u64,
+}
+
+impl Lockout {
+    /// Explanation returned with refused attempts
+    pub fn message(&self) -> String {
+        format!(
+            "Too many failed authentication attempts; retry in {} seconds",
+            self.retry_after_secs
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ThrottleKey {
+    Ip(String),
+    Principal(String),
+}
+
+#[derive(Debug, Default)]
+struct Failures {
+    times: VecDeque<DateTime<Utc>>,
+    locked_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default)]
+struct ThrottleState {
+    failures: HashMap<ThrottleKey, Failures>,
+    /// IP -> principals it failed against, with the latest failure
+    principals_by_ip: HashMap<String, HashMap<String, DateTime<Utc>>>,
+}
+
+impl ThrottleState {
+    /// Forget failures outside the window and lockouts that have ended
+    fn prune(&mut self, window_start: DateTime<Utc>, now: DateTime<Utc>) {
+        self.failures.retain(|_, failures| {
+            while failures
+                .times
+                .front()
+                .is_some_and(|time| *time < window_start)
+            {
+                failures.times.pop_front();
+            }
+            !failures.times.is_empty() || failures.locked_until.is_some_and(|until| until > now)
+        });
+        self.principals_by_ip.retain(|_, principals| {
+            principals.retain(|_, time| *time >= window_start);
+            !principals.is_empty()
+        });
+    }
+}
+
+fn throttle_keys(ip: Option<&str>, principal: Option<&str>) -> Vec<ThrottleKey> {
+    ip.map(|ip| ThrottleKey::Ip(ip.to_string()))
+        .into_iter()
+        .chain(principal.map(|principal| ThrottleKey::Principal(principal.to_string())))
+        .collect()
+}
+
+/// Shared failed-authentication tracker
+///
+/// Clones track the same failures. Servers use [`AuthThrottle::global`]; separate
+/// instances are mainly useful in tests.
+#[derive(Debug, Clone)]
+pub struct AuthThrottle {
+    policy: ThrottlePolicy,
+    state: Arc<Mutex<ThrottleState>>,
+    audit: AuditLog,
+}
+
+impl AuthThrottle {
+    pub fn new(policy: ThrottlePolicy, audit: AuditLog) -> Self {
+        Self {
+            policy,
+            state: Arc::new(Mutex::new(ThrottleState::default())),
+            audit,
+        }
+    }
+
+    /// The process-wide tracker shared by every API surface
+    pub fn global() -> Self {
+        GLOBAL.clone()
+    }
+
+    /// Refuse an attempt from a locked out IP or for a locked out principal
+    pub fn check(&self, ip: Option<&str>, principal: Option<&str>) -> Result<(), Lockout> {
+        self.check_at(ip, principal, Utc::now())
+    }
+
+    fn check_at(
+        &self,
+        ip: Option<&str>,
+        principal: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Result<(), Lockout> {
+        let state = self.state.lock().unwrap();
+        let retry_after_secs = throttle_keys(ip, principal)
+            .iter()
+            .filter_map(|key| state.failures.get(key)?.locked_until)
+            .filter(|until| *until > now)
+            .map(|until| (until - now).num_seconds().max(1) as u64)
+            .max();
+        match retry_after_secs {
+            Some(retry_after_secs) => Err(Lockout { retry_after_secs }),
+            None => Ok(()),
+        }
+    }
+
+    /// Count a rejected attempt and publish it, returning the lockout it started, if any
+    pub fn record_failure(
+        &self,
+        surface: AuthSurface,
+        ip: Option<&str>,
+        principal: Option<&str>,
+        detail: &str,
+    ) -> Option<Lockout> {
+        self.record_failure_at(surface, ip, principal, detail, Utc::now())
+    }
+
+    fn record_failure_at(
+        &self,
+        surface: AuthSurface,
+        ip: Option<&str>,
+        principal: Option<&str>,
+        detail: &str,
+        now: DateTime<Utc>,
+    ) -> Option<Lockout> {
+        self.audit.publish(SecurityEvent::new(
+            SecurityEventKind::AuthFailure,
+            surface,
+            ip,
+            principal,
+            detail,
+        ));
+
+        let mut events = Vec::new();
+        let mut lockout: Option<u64> = None;
+        {
+            let mut state = self.state.lock().unwrap();
+            state.prune(now - Duration::seconds(self.policy.window_secs as i64), now);
+
+            for key in throttle_keys(ip, principal) {
+                let failures = state.failures.entry(key.clone()).or_default();
+                failures.times.push_back(now);
+                if let Some(secs) = self.policy.lockout_secs(failures.times.len()) {
+                    failures.locked_until = Some(now + Duration::seconds(secs as i64));
+                    lockout = lockout.max(Some(secs));
+                    let subject = match &key {
+                        ThrottleKey::Ip(ip) => format!("IP {}", ip),
+                        ThrottleKey::Principal(principal) => format!("Principal '{}'", principal),
+                    };
+                    events.push(SecurityEvent::new(
+                        SecurityEventKind::Lockout,
+                        surface,
+                        ip,
+                        principal,
+                        format!(
+                            "{} locked out for {} seconds after {} failed attempts",
+                            subject,
+                            secs,
+                            failures.times.len()
+                        ),
+                    ));
+                }
+            }
+
+            if let (Some(ip), Some(principal)) = (ip, principal) {
+                let principals = state.principals_by_ip.entry(ip.to_string()).or_default();
+                let new_principal = principals.insert(principal.to_string(), now).is_none();
+                let tried = principals.len();
+                if new_principal && tried == self.policy.stuffing_principals {
+                    let secs = self.policy.max_lockout_secs;
+                    state
+                        .failures
+                        .entry(ThrottleKey::Ip(ip.to_string()))
+                        .or_default()
+                        .locked_until = Some(now + Duration::seconds(secs as i64));
+                    lockout = Some(secs);
+                    events.push(SecurityEvent::new(
+                        SecurityEventKind::Anomaly,
+                        surface,
+                        Some(ip),
+                        None,
+                        format!(
+                            "IP {} failed against {} different principals; locked out for {} seconds",
+                            ip, tried, secs
+                        ),
+                    ));
+                }
+            }
+        }
+
+        for event in events {
+            self.audit.publish(event);
+        }
+        lockout.map(|retry_after_secs| Lockout { retry_after_secs })
+    }
+
+    /// Clear the failures of a principal that authenticated
+    pub fn record_success(&self, principal: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .failures
+            .remove(&ThrottleKey::Principal(principal.to_string()));
+    }
+}
+
+/// Middleware recording the address of the connected peer in [`PEER_ADDR_HEADER`]
+///
+/// Any value the client sent is discarded, so handlers can rely on the header. The
+/// server must be started with `into_make_service_with_connect_info::<SocketAddr>()`.
+pub async fn record_peer_addr(mut request: Request<Body>, next: Next<Body>) -> Response {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let headers = request.headers_mut();
+    headers.remove(PEER_ADDR_HEADER);
+    if let Some(value) = peer.and_then(|peer| HeaderValue::from_str(&peer.to_string()).ok()) {
+        headers.insert(PEER_ADDR_HEADER, value);
+    }
+    next.run(request).await
+}
+
+/// The client IP of a request
+///
+/// This is the connected peer, unless the peer is one of the proxies listed in
+/// `CIRCUIT_BREAKER_TRUSTED_PROXIES`; then it is the last address in `X-Forwarded-For`
+/// not added by a trusted proxy, or `X-Real-IP`. Forwarded headers from anyone else
+/// are ignored, since the client controls them.
+pub fn client_ip(headers: &HeaderMap) -> Option<String> {
+    client_ip_behind(headers, &TRUSTED_PROXIES)
+}
+
+fn client_ip_behind(headers: &HeaderMap, trusted_proxies: &[IpAddr]) -> Option<String> {
+    let peer = header_ip(headers.get(PEER_ADDR_HEADER))?;
+    if !trusted_proxies.contains(&peer) {
+        return Some(peer.to_string());
+    }
+
+    let forwarded: Vec<IpAddr> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|ip| ip.trim().parse().ok())
+        .collect();
+    let client = forwarded
+        .into_iter()
+        .rev()
+        .find(|ip| !trusted_proxies.contains(ip))
+        .or_else(|| header_ip(headers.get("x-real-ip")))
+        .unwrap_or(peer);
+    Some(client.to_string())
+}
+
+fn header_ip(value: Option<&HeaderValue>) -> Option<IpAddr> {
+    value?.to_str().ok()?.trim().parse().ok()
+}
+
+fn trusted_proxies_from_env() -> Vec<IpAddr> {
+    std::env::var(TRUSTED_PROXIES_ENV)
+        .map(|proxies| {
+            proxies
+                .split(',')
+                .filter_map(|ip| ip.trim().parse().ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progressive_lockout() {
+        let audit = AuditLog::new();
+        let throttle = AuthThrottle::new(ThrottlePolicy::default(), audit.clone());
+        let start = Utc::now();
+        let ip = Some("10.0.0.1");
+
+        for attempt in 0..5 {
+            let at = start + Duration::seconds(attempt);
+            assert!(throttle.check_at(ip, None, at).is_ok());
+            assert!(throttle
+                .record_failure_at(AuthSurface::ApiKey, ip, None, "Invalid API key", at)
+                .is_none());
+        }
+
+        // The sixth failure locks the IP out, the seventh for twice as long
+        let lockout = throttle
+            .record_failure_at(AuthSurface::ApiKey, ip, None, "Invalid API key", start)
+            .unwrap();
+        assert_eq!(lockout.retry_after_secs, 5);
+        assert!(throttle.check_at(ip, None, start).is_err());
+        assert!(throttle.check_at(Some("10.0.0.2"), None, start).is_ok());
+        let later = start + Duration::seconds(6);
+        assert!(throttle.check_at(ip, None, later).is_ok());
+        let lockout = throttle
+            .record_failure_at(AuthSurface::ApiKey, ip, None, "Invalid API key", later)
+            .unwrap();
+        assert_eq!(lockout.retry_after_secs, 10);
+
+        // Failures age out of the window
+        let much_later = later + Duration::seconds(901);
+        assert!(throttle.check_at(ip, None, much_later).is_ok());
+        assert!(throttle
+            .record_failure_at(AuthSurface::ApiKey, ip, None, "Invalid API key", much_later)
+            .is_none());
+
+        let lockouts = audit.recent(Some(SecurityEventKind::Lockout), 10);
+        assert_eq!(lockouts.len(), 2);
+        assert_eq!(lockouts[0].ip.as_deref(), ip);
+        assert_eq!(
+            audit
+                .recent(Some(SecurityEventKind::AuthFailure), 100)
+                .len(),
+            8
+        );
+    }
+
+    #[test]
+    fn test_principal_lockout_and_credential_stuffing() {
+        let audit = AuditLog::new();
+        let throttle = AuthThrottle::new(ThrottlePolicy::default(), audit.clone());
+        let now = Utc::now();
+
+        // A principal is locked out whichever IPs the failures come from, until it
+        // authenticates
+        for attempt in 0..6 {
+            let ip = format!("10.0.1.{}", attempt);
+            throttle.record_failure_at(AuthSurface::Jwt, Some(&ip), Some("app-1"), "bad", now);
+        }
+        assert!(throttle.check_at(None, Some("app-1"), now).is_err());
+        throttle.record_success("app-1");
+        assert!(throttle.check_at(None, Some("app-1"), now).is_ok());
+
+        // One IP trying many principals is locked out for the longest period at once
+        for n in 0..9 {
+            let principal = format!("instance-{}", n);
+            let at = now + Duration::seconds(n);
+            throttle.record_failure_at(
+                AuthSurface::OAuthCallback,
+                Some("10.0.2.1"),
+                Some(&principal),
+                "bad",
+                at,
+            );
+        }
+        assert!(throttle
+            .check_at(Some("10.0.2.1"), None, now + Duration::seconds(9))
+            .is_err());
+        assert!(audit
+            .recent(Some(SecurityEventKind::Anomaly), 10)
+            .is_empty());
+        let lockout = throttle
+            .record_failure_at(
+                AuthSurface::OAuthCallback,
+                Some("10.0.2.1"),
+                Some("instance-9"),
+                "bad",
+                now,
+            )
+            .unwrap();
+        assert_eq!(lockout.retry_after_secs, 900);
+        assert_eq!(audit.recent(Some(SecurityEventKind::Anomaly), 10).len(), 1);
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("x-real-ip", "192.0.2.7".parse().unwrap());
+        headers.insert(
+            "x-forwarded-for",
+            "198.51.100.9, 203.0.113.5, 10.0.0.1".parse().unwrap(),
+        );
+        assert_eq!(client_ip_behind(&headers, &[proxy]), None);
+
+        // Forwarded headers from an untrusted peer are ignored
+        headers.insert(PEER_ADDR_HEADER, "192.0.2.200".parse().unwrap());
+        assert_eq!(
+            client_ip_behind(&headers, &[proxy]).as_deref(),
+            Some("192.0.2.200")
+        );
+
+        // Behind a trusted proxy the last untrusted forwarded address wins, not the
+        // leftmost one the client may have made up
+        headers.insert(PEER_ADDR_HEADER, "10.0.0.1".parse().unwrap());
+        assert_eq!(
+            client_ip_behind(&headers, &[proxy]).as_deref(),
+            Some("203.0.113.5")
+        );
+        headers.remove("x-forwarded-for");
+        assert_eq!(
+            client_ip_behind(&headers, &[proxy]).as_deref(),
+            Some("192.0.2.7")
+        );
+    }
+}
@@ -71,6 +71,13 @@ enum Commands {
     /// List all rules
     ListRules,
 
+    /// Snapshot the latest resource states so reads skip replaying old history
+    Compact {
+        /// Workflow ID to compact (all workflows if omitted)
+        #[arg(long)]
+        workflow_id: Option<String>,
+    },
+
     /// Delete specific workflow
     DeleteWorkflow {
         /// Workflow ID to delete
@@ -189,6 +196,10 @@ async fn main() -> Result<()> {
             list_rules(&cli.nats_url).await?;
         }
 
+        Commands::Compact { workflow_id } => {
+            compact_snapshots(&storage, workflow_id).await?;
+        }
+
         Commands::DeleteWorkflow {
             workflow_id,
             confirm,
@@ -367,6 +378,18 @@ async fn list_resources(storage: &NATSStorage, workflow_id: Option<String>) -> R
     Ok(())
 }
 
+async fn compact_snapshots(storage: &NATSStorage, workflow_id: Option<String>) -> Result<()> {
+    info!("🗜️  Compacting resource snapshots...");
+
+    let written = match workflow_id {
+        Some(wf_id) => storage.compact_workflow(&wf_id).await?,
+        None => storage.compact_snapshots().await?,
+    };
+
+    info!("✅ Wrote {} resource snapshots", written);
+    Ok(())
+}
+
 async fn list_rules(nats_url: &str) -> Result<()> {
     info!("🔍 Connecting to NATS for rule listing...");
 
@@ -76,13 +76,23 @@ use async_nats;
 
 use circuit_breaker::{
     api::mcp_server::CircuitBreakerMCPServer,
-    llm::{cost::CostOptimizer, LLMRouter},
+    api_keys::{ApiKeyStore, ApiKeys, InMemoryApiKeyStore, NATSApiKeyStore, PostgresApiKeyStore},
+    audit_trail::{AuditStore, AuditTrail, InMemoryAuditStore, NATSAuditStore, PostgresAuditStore},
+    engine::RedisStorageConfig,
+    llm::{
+        cost::{CostOptimizer, InMemoryUsageTracker, UsageTracker},
+        LLMRouter, NATSUsageTracker, PostgresUsageTracker,
+    },
+    rbac::{InMemoryRoleStore, NATSRoleStore, PostgresRoleStore, RoleAssignments, RoleStore},
     GraphQLServerBuilder, OpenAIApiServerBuilder,
 };
 use dotenv::dotenv;
 use std::env;
+use std::net::SocketAddr;
 use tokio;
 use tracing::{error, info, warn};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::EnvFilter;
 
 /// Configuration from environment variables
@@ -95,18 +105,31 @@ struct ServerConfig {
     openai_port: u16,
     openai_host: String,
     openai_cors_enabled: bool,
-    openai_api_key_required: bool,
     openai_enable_streaming: bool,
+    openai_moderate_chat: bool,
+    openai_max_tokens: Option<u32>,
+    openai_max_body_bytes: Option<usize>,
+    openai_max_messages: Option<usize>,
+    openai_disallowed_params: Option<String>,
 
     // MCP Server
     mcp_port: u16,
     mcp_host: String,
+    mcp_stdio_upstreams: bool,
+    mcp_tool_cache_ttl_secs: u64,
+    mcp_jwt_key_rotation_hours: u64,
 
     // Shared
+    api_key_required: bool,
+    api_key_store: String,
+    audit_store: String,
     log_level: String,
     environment: String,
     storage_type: String,
     nats_url: String,
+    redis_url: String,
+    usage_tracker: String,
+    database_url: Option<String>,
 
     // API Keys (optional)
     openai_api_key: Option<String>,
@@ -135,25 +158,63 @@ impl ServerConfig {
                 .unwrap_or_else(|_| "true".to_string())
                 .parse()
                 .unwrap_or(true),
-            openai_api_key_required: env::var("OPENAI_API_KEY_REQUIRED")
-                .unwrap_or_else(|_| "false".to_string())
-                .parse()
-                .unwrap_or(false),
             openai_enable_streaming: env::var("OPENAI_ENABLE_STREAMING")
                 .unwrap_or_else(|_| "true".to_string())
                 .parse()
                 .unwrap_or(true),
+            openai_moderate_chat: env::var("OPENAI_MODERATE_CHAT")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            openai_max_tokens: env::var("OPENAI_MAX_TOKENS_PER_REQUEST")
+                .ok()
+                .and_then(|v| v.parse().ok()),
+            openai_max_body_bytes: env::var("OPENAI_MAX_BODY_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok()),
+            openai_max_messages: env::var("OPENAI_MAX_MESSAGES_PER_REQUEST")
+                .ok()
+                .and_then(|v| v.parse().ok()),
+            // e.g. "o1*=temperature,top_p;gpt-4=logit_bias"
+            openai_disallowed_params: env::var("OPENAI_DISALLOWED_PARAMS").ok(),
             mcp_port: env::var("MCP_PORT")
                 .unwrap_or_else(|_| "8080".to_string())
                 .parse()
                 .unwrap_or(8080),
             mcp_host: env::var("MCP_HOST").unwrap_or_else(|_| "0.0.0.0".to_string()),
+            // Upstreams over stdio run arbitrary commands on this host
+            mcp_stdio_upstreams: env::var("MCP_STDIO_UPSTREAMS")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            // Zero turns tool result caching off
+            mcp_tool_cache_ttl_secs: env::var("MCP_TOOL_CACHE_TTL_SECS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .unwrap_or(30),
+            // Zero keeps signing installation and session tokens with one key
+            mcp_jwt_key_rotation_hours: env::var("MCP_JWT_KEY_ROTATION_HOURS")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .unwrap_or(0),
 
             // Shared
+            // OPENAI_API_KEY_REQUIRED predates keys on the other surfaces
+            api_key_required: env::var("API_KEY_REQUIRED")
+                .or_else(|_| env::var("OPENAI_API_KEY_REQUIRED"))
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            api_key_store: store_backend(circuit_breaker::api_keys::API_KEY_STORE_ENV),
+            audit_store: store_backend(circuit_breaker::audit_trail::AUDIT_STORE_ENV),
             log_level: env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string()),
             environment: env::var("ENVIRONMENT").unwrap_or_else(|_| "development".to_string()),
-            storage_type: env::var("STORAGE_BACKEND").unwrap_or_else(|_| "memory".to_string()),
+            storage_type: storage_backend(),
             nats_url: env::var("NATS_URL").unwrap_or_else(|_| "nats://localhost:4222".to_string()),
+            redis_url: env::var("REDIS_URL")
+                .unwrap_or_else(|_| "redis://localhost:6379".to_string()),
+            usage_tracker: store_backend(circuit_breaker::llm::usage_tracking::USAGE_TRACKER_ENV),
+            database_url: env::var("DATABASE_URL").ok(),
 
             // LLM Provider Keys
             openai_api_key: env::var("OPENAI_API_KEY").ok(),
@@ -164,6 +225,32 @@ impl ServerConfig {
     }
 }
 
+/// The workflow storage backend `STORAGE_BACKEND` names, `memory` by default;
+/// `postgresql` is accepted for `postgres`
+fn storage_backend() -> String {
+    backend_name(&env::var("STORAGE_BACKEND").unwrap_or_else(|_| "memory".to_string()))
+}
+
+/// The backend `env_var` names for a store, following workflow storage onto NATS or
+/// Postgres unless configured otherwise
+fn store_backend(env_var: &str) -> String {
+    match env::var(env_var) {
+        Ok(backend) => backend_name(&backend),
+        Err(_) => match storage_backend().as_str() {
+            backend @ ("nats" | "postgres") => backend.to_string(),
+            _ => "memory".to_string(),
+        },
+    }
+}
+
+fn backend_name(backend: &str) -> String {
+    let backend = backend.trim().to_ascii_lowercase();
+    match backend.as_str() {
+        "postgresql" => "postgres".to_string(),
+        _ => backend,
+    }
+}
+
 /// Main entry point for the Circuit Breaker unified server
 ///
 /// ## Rust Learning Notes:
@@ -225,6 +312,8 @@ async fn main() -> Result<(), String> {
     info!("Storage: {}", config.storage_type);
     if config.storage_type == "nats" {
         info!("NATS URL: {}", config.nats_url);
+    } else if config.storage_type == "redis" {
+        info!("Redis URL: {}", config.redis_url);
     }
 
     // Log agent provider configuration (without exposing API keys)
@@ -266,13 +355,22 @@ async fn main() -> Result<(), String> {
     })?;
 
     // Create cost optimizer with dependencies
-    let usage_tracker =
-        std::sync::Arc::new(circuit_breaker::llm::cost::InMemoryUsageTracker::new());
-    let budget_manager = std::sync::Arc::new(circuit_breaker::llm::cost::BudgetManager::new(
-        usage_tracker,
-    ));
-    let cost_analyzer = std::sync::Arc::new(circuit_breaker::llm::cost::CostAnalyzer::new());
-    let cost_optimizer = CostOptimizer::new(budget_manager, cost_analyzer);
+    let usage_tracker = create_usage_tracker(&config).await?;
+    let cost_optimizer = CostOptimizer::with_usage_tracker(usage_tracker);
+
+    // Managed API keys are shared by all three servers
+    ApiKeys::global().use_store(create_api_key_store(&config).await?);
+    // Roles live with the keys they restrict, so a restart cannot drop them
+    RoleAssignments::global().use_store(create_role_store(&config).await?);
+    // So is the audit trail
+    AuditTrail::global().use_store(create_audit_store(&config).await?);
+    if config.api_key_required {
+        info!("🔑 API keys required on GraphQL, OpenAI-compatible and MCP requests");
+    }
+
+    // Seed the price catalog now so a bad catalog file is reported at startup
+    let model_prices = circuit_breaker::llm::PriceCatalog::global().list().len();
+    info!("💲 Price catalog: {} model price overrides", model_prices);
 
     info!("✅ Shared LLM infrastructure initialized");
 
@@ -290,6 +388,7 @@ async fn main() -> Result<(), String> {
 
     let mut graphql_builder = GraphQLServerBuilder::new()
         .with_port(config.graphql_port)
+        .with_api_key_required(config.api_key_required)
         .with_agents();
 
     // Configure storage backend based on environment variable
@@ -332,11 +431,43 @@ async fn main() -> Result<(), String> {
             info!("✅ NATS storage backend successfully configured");
             info!("🎯 Circuit Breaker will use NATS JetStream for persistent storage");
         }
-        "memory" | _ => {
+        "postgres" => {
+            let database_url = config
+                .database_url
+                .as_deref()
+                .ok_or("DATABASE_URL is required to store workflows in Postgres")?;
+            info!("🔧 Configuring Postgres storage backend...");
+            graphql_builder = graphql_builder
+                .with_postgres(database_url)
+                .await
+                .map_err(|e| {
+                    error!("❌ Failed to initialize Postgres storage: {}", e);
+                    format!("Failed to initialize Postgres storage: {}", e)
+                })?;
+            info!("✅ Postgres storage backend successfully configured");
+        }
+        "redis" => {
+            info!("🔧 Configuring Redis storage backend...");
+            graphql_builder = graphql_builder
+                .with_redis(RedisStorageConfig::from_env(&config.redis_url))
+                .await
+                .map_err(|e| {
+                    error!("❌ Failed to initialize Redis storage: {}", e);
+                    format!("Failed to initialize Redis storage: {}", e)
+                })?;
+            info!("✅ Redis storage backend successfully configured");
+        }
+        "memory" => {
             info!("🔧 Configuring in-memory storage backend");
             info!("⚠️  Note: Data will not persist between server restarts");
             info!("✅ In-memory storage backend configured");
         }
+        other => {
+            return Err(format!(
+                "Unknown STORAGE_BACKEND '{}': expected memory, nats, postgres or redis",
+                other
+            ));
+        }
     }
 
     // Build OpenAI API server with NATS storage if configured
@@ -344,10 +475,25 @@ async fn main() -> Result<(), String> {
         .with_port(config.openai_port)
         .with_host(config.openai_host.clone())
         .with_cors(config.openai_cors_enabled)
-        .with_api_key_required(config.openai_api_key_required)
+        .with_api_key_required(config.api_key_required)
         .with_streaming(config.openai_enable_streaming)
+        .with_chat_moderation(config.openai_moderate_chat)
         .with_llm_router(llm_router)
         .with_cost_optimizer(cost_optimizer);
+    if let Some(max_tokens) = config.openai_max_tokens {
+        openai_builder = openai_builder.with_max_tokens(max_tokens);
+    }
+    if let Some(max_body_bytes) = config.openai_max_body_bytes {
+        openai_builder = openai_builder.with_max_body_bytes(max_body_bytes);
+    }
+    if let Some(max_messages) = config.openai_max_messages {
+        openai_builder = openai_builder.with_max_messages(max_messages);
+    }
+    if let Some(spec) = &config.openai_disallowed_params {
+        for (model, params) in circuit_breaker::api::validation::parse_disallowed_params(spec) {
+            openai_builder = openai_builder.with_disallowed_params(model, params);
+        }
+    }
 
     // Add NATS storage if configured
     if config.storage_type == "nats" {
@@ -370,9 +516,35 @@ async fn main() -> Result<(), String> {
                 ));
             }
         }
+    } else if config.storage_type == "postgres" {
+        info!("🔧 Configuring MCP server with Postgres storage");
+        let database_url = config
+            .database_url
+            .as_deref()
+            .ok_or("DATABASE_URL is required to store MCP state in Postgres")?;
+        CircuitBreakerMCPServer::with_postgres_storage(database_url)
+            .await
+            .map_err(|e| {
+                error!(
+                    "❌ Failed to create MCP server with Postgres storage: {}",
+                    e
+                );
+                format!("Failed to create MCP server with Postgres storage: {}", e)
+            })?
     } else {
         CircuitBreakerMCPServer::new()
     };
+    let mcp_server = mcp_server
+        .with_api_key_required(config.api_key_required)
+        .with_stdio_upstreams(config.mcp_stdio_upstreams)
+        .with_tool_cache_ttl(std::time::Duration::from_secs(
+            config.mcp_tool_cache_ttl_secs,
+        ))
+        .with_sampling(openai_server.llm_router(), openai_server.cost_optimizer());
+    mcp_server.load_signing_keys().await.map_err(|e| {
+        error!("❌ {}", e);
+        e
+    })?;
 
     // Print server information
     info!("");
@@ -482,6 +654,14 @@ async fn main() -> Result<(), String> {
         }
     });
 
+    // Renew OAuth tokens before tool calls run into expired ones
+    mcp_server.spawn_token_refresher();
+    if config.mcp_jwt_key_rotation_hours > 0 {
+        mcp_server.spawn_signing_key_rotation(std::time::Duration::from_secs(
+            config.mcp_jwt_key_rotation_hours * 3600,
+        ));
+    }
+
     let mcp_handle = tokio::spawn(async move {
         let app = mcp_server.create_router();
         let addr = format!("{}:{}", config.mcp_host, config.mcp_port);
@@ -489,7 +669,7 @@ async fn main() -> Result<(), String> {
         info!("🔐 MCP server listening on {}", addr);
 
         if let Err(e) = axum::Server::bind(&addr.parse().unwrap())
-            .serve(app.into_make_service())
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
             .await
         {
             error!("❌ MCP server error: {}", e);
@@ -528,17 +708,154 @@ async fn main() -> Result<(), String> {
     Ok(())
 }
 
+/// Create the usage tracker budgets and cost analytics are kept in
+async fn create_usage_tracker(
+    config: &ServerConfig,
+) -> Result<std::sync::Arc<dyn UsageTracker>, String> {
+    match config.usage_tracker.as_str() {
+        "nats" => {
+            info!("📊 Tracking LLM usage in NATS at {}", config.nats_url);
+            let tracker = NATSUsageTracker::connect(&config.nats_url)
+                .await
+                .map_err(|e| format!("Failed to initialize NATS usage tracker: {}", e))?;
+            Ok(std::sync::Arc::new(tracker))
+        }
+        "postgres" => {
+            let database_url = config
+                .database_url
+                .as_deref()
+                .ok_or("DATABASE_URL is required to track usage in Postgres")?;
+            info!("📊 Tracking LLM usage in Postgres");
+            let tracker = PostgresUsageTracker::connect(database_url)
+                .await
+                .map_err(|e| format!("Failed to initialize Postgres usage tracker: {}", e))?;
+            Ok(std::sync::Arc::new(tracker))
+        }
+        other => {
+            if other != "memory" {
+                warn!(
+                    "Unknown usage tracker '{}', tracking usage in memory",
+                    other
+                );
+            }
+            Ok(std::sync::Arc::new(InMemoryUsageTracker::new()))
+        }
+    }
+}
+
+/// Create the store managed API keys are kept in
+async fn create_role_store(config: &ServerConfig) -> Result<std::sync::Arc<dyn RoleStore>, String> {
+    match config.api_key_store.as_str() {
+        "nats" => {
+            info!("🛂 Storing role assignments in NATS at {}", config.nats_url);
+            let store = NATSRoleStore::connect(&config.nats_url)
+                .await
+                .map_err(|e| format!("Failed to initialize NATS role store: {}", e))?;
+            Ok(std::sync::Arc::new(store))
+        }
+        "postgres" => {
+            let database_url = config
+                .database_url
+                .as_deref()
+                .ok_or("DATABASE_URL is required to store role assignments in Postgres")?;
+            info!("🛂 Storing role assignments in Postgres");
+            let store = PostgresRoleStore::connect(database_url)
+                .await
+                .map_err(|e| format!("Failed to initialize Postgres role store: {}", e))?;
+            Ok(std::sync::Arc::new(store))
+        }
+        _ => Ok(std::sync::Arc::new(InMemoryRoleStore::default())),
+    }
+}
+
+async fn create_api_key_store(
+    config: &ServerConfig,
+) -> Result<std::sync::Arc<dyn ApiKeyStore>, String> {
+    match config.api_key_store.as_str() {
+        "nats" => {
+            info!("🔑 Storing API keys in NATS at {}", config.nats_url);
+            let store = NATSApiKeyStore::connect(&config.nats_url)
+                .await
+                .map_err(|e| format!("Failed to initialize NATS API key store: {}", e))?;
+            Ok(std::sync::Arc::new(store))
+        }
+        "postgres" => {
+            let database_url = config
+                .database_url
+                .as_deref()
+                .ok_or("DATABASE_URL is required to store API keys in Postgres")?;
+            info!("🔑 Storing API keys in Postgres");
+            let store = PostgresApiKeyStore::connect(database_url)
+                .await
+                .map_err(|e| format!("Failed to initialize Postgres API key store: {}", e))?;
+            Ok(std::sync::Arc::new(store))
+        }
+        other => {
+            if other != "memory" {
+                warn!(
+                    "Unknown API key store '{}', storing API keys in memory",
+                    other
+                );
+            }
+            Ok(std::sync::Arc::new(InMemoryApiKeyStore::default()))
+        }
+    }
+}
+
+/// Create the store the audit trail is appended to
+async fn create_audit_store(
+    config: &ServerConfig,
+) -> Result<std::sync::Arc<dyn AuditStore>, String> {
+    match config.audit_store.as_str() {
+        "nats" => {
+            info!(
+                "📜 Appending the audit trail to NATS at {}",
+                config.nats_url
+            );
+            let store = NATSAuditStore::connect(&config.nats_url)
+                .await
+                .map_err(|e| format!("Failed to initialize NATS audit store: {}", e))?;
+            Ok(std::sync::Arc::new(store))
+        }
+        "postgres" => {
+            let database_url = config
+                .database_url
+                .as_deref()
+                .ok_or("DATABASE_URL is required to keep the audit trail in Postgres")?;
+            info!("📜 Appending the audit trail to Postgres");
+            let store = PostgresAuditStore::connect(database_url)
+                .await
+                .map_err(|e| format!("Failed to initialize Postgres audit store: {}", e))?;
+            Ok(std::sync::Arc::new(store))
+        }
+        other => {
+            if other != "memory" {
+                warn!(
+                    "Unknown audit store '{}', keeping the audit trail in memory",
+                    other
+                );
+            }
+            Ok(std::sync::Arc::new(InMemoryAuditStore::default()))
+        }
+    }
+}
+
 /// Initialize logging based on configuration
 fn init_logging(log_level: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(log_level));
 
-    tracing_subscriber::fmt()
-        .with_env_filter(filter)
-        .with_target(false)
-        .with_thread_ids(false)
-        .with_file(false)
-        .with_line_number(false)
-        .compact()
+    // Operators can tail the same events through /admin/logs/stream
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_target(false)
+                .with_thread_ids(false)
+                .with_file(false)
+                .with_line_number(false)
+                .compact(),
+        )
+        .with(circuit_breaker::api::log_stream::layer())
         .init();
 
     Ok(())
@@ -26,7 +26,10 @@ use tokio::time::sleep;
 use tracing::error;
 use uuid::Uuid;
 
+use crate::engine::feature_flags::FeatureFlags;
 use crate::engine::rules::RulesEngine;
+use crate::engine::state_counters::{status_key, StateCounters};
+use crate::engine::subscriptions::{StreamDelivery, StreamGauges, StreamHub, StreamSubscription};
 use crate::models::{
     AgentActivityConfig, AgentDefinition, AgentExecution, AgentExecutionStatus, AgentId,
     AgentStreamEvent, LLMConfig, LLMProvider, Resource, StateAgentConfig, StateId,
@@ -54,6 +57,45 @@ pub trait AgentStorage: Send + Sync {
     async fn list_executions_for_resource(&self, resource_id: &Uuid)
         -> Result<Vec<AgentExecution>>;
     async fn list_executions_for_agent(&self, agent_id: &AgentId) -> Result<Vec<AgentExecution>>;
+
+    /// Executions of several resources at once, keyed by resource ID
+    ///
+    /// Used by GraphQL dataloaders; the default implementation lists them resource by
+    /// resource.
+    async fn list_executions_for_resources(
+        &self,
+        resource_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Vec<AgentExecution>>> {
+        let mut executions = HashMap::new();
+        for resource_id in resource_ids {
+            executions.insert(
+                *resource_id,
+                self.list_executions_for_resource(resource_id).await?,
+            );
+        }
+        Ok(executions)
+    }
+
+    // Dashboard counters
+    /// Number of executions in each status, keyed by status name
+    ///
+    /// The default implementation counts the executions of every agent; stores that
+    /// keep materialized counters answer without listing them.
+    async fn count_executions_by_status(&self) -> Result<HashMap<String, u64>> {
+        let mut counts = HashMap::new();
+        for agent in self.list_agents().await? {
+            for execution in self.list_executions_for_agent(&agent.id).await? {
+                *counts
+                    .entry(status_key(&execution.status).to_string())
+                    .or_default() += 1;
+            }
+        }
+        Ok(counts)
+    }
+    /// Recompute materialized counters from the stored executions
+    async fn rebuild_execution_counters(&self) -> Result<()> {
+        Ok(())
+    }
 }
 
 /// In-memory implementation of AgentStorage for development/testing
@@ -62,6 +104,7 @@ pub struct InMemoryAgentStorage {
     agents: RwLock<HashMap<AgentId, AgentDefinition>>,
     state_configs: RwLock<HashMap<Uuid, StateAgentConfig>>,
     executions: RwLock<HashMap<Uuid, AgentExecution>>,
+    counters: StateCounters,
 }
 
 #[async_trait::async_trait]
@@ -115,7 +158,9 @@ impl AgentStorage for InMemoryAgentStorage {
 
     async fn store_execution(&self, execution: &AgentExecution) -> Result<()> {
         let mut executions = self.executions.write().await;
-        executions.insert(execution.id, execution.clone());
+        let previous = executions.insert(execution.id, execution.clone());
+        self.counters
+            .execution_stored(previous.as_ref().map(|e| &e.status), &execution.status);
         Ok(())
     }
 
@@ -144,6 +189,33 @@ impl AgentStorage for InMemoryAgentStorage {
             .cloned()
             .collect())
     }
+
+    async fn list_executions_for_resources(
+        &self,
+        resource_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Vec<AgentExecution>>> {
+        let executions = self.executions.read().await;
+        let mut by_resource: HashMap<Uuid, Vec<AgentExecution>> = resource_ids
+            .iter()
+            .map(|resource_id| (*resource_id, Vec::new()))
+            .collect();
+        for execution in executions.values() {
+            if let Some(list) = by_resource.get_mut(&execution.resource_id) {
+                list.push(execution.clone());
+            }
+        }
+        Ok(by_resource)
+    }
+
+    async fn count_executions_by_status(&self) -> Result<HashMap<String, u64>> {
+        Ok(self.counters.execution_counts())
+    }
+
+    async fn rebuild_execution_counters(&self) -> Result<()> {
+        let executions = self.executions.read().await;
+        self.counters.rebuild_executions(executions.values());
+        Ok(())
+    }
 }
 
 /// Configuration for the agent engine
@@ -154,6 +226,11 @@ pub struct AgentEngineConfig {
     pub connection_timeout: Duration,
     pub execution_timeout: Duration,
     pub cleanup_interval: Duration,
+    pub subscriber_idle_timeout: Duration,
+    /// Delivery mode for tracked stream subscriptions
+    pub stream_delivery: StreamDelivery,
+    /// Queue capacity per subscriber in guaranteed delivery mode
+    pub subscriber_buffer_size: usize,
 }
 
 impl Default for AgentEngineConfig {
@@ -164,6 +241,9 @@ impl Default for AgentEngineConfig {
             connection_timeout: Duration::from_secs(30),
             execution_timeout: Duration::from_secs(300),
             cleanup_interval: Duration::from_secs(60),
+            subscriber_idle_timeout: Duration::from_secs(300),
+            stream_delivery: StreamDelivery::Lossy,
+            subscriber_buffer_size: 256,
         }
     }
 }
@@ -173,7 +253,7 @@ pub struct AgentEngine {
     storage: Arc<dyn AgentStorage>,
     rules_engine: Arc<RulesEngine>,
     config: AgentEngineConfig,
-    stream_sender: broadcast::Sender<AgentStreamEvent>,
+    stream_hub: StreamHub<AgentStreamEvent>,
 }
 
 impl AgentEngine {
@@ -182,23 +262,73 @@ impl AgentEngine {
         rules_engine: Arc<RulesEngine>,
         config: AgentEngineConfig,
     ) -> Self {
-        let (stream_sender, _) = broadcast::channel(config.stream_buffer_size);
+        let stream_hub = StreamHub::new(config.stream_buffer_size, config.subscriber_buffer_size);
 
         Self {
             storage,
             rules_engine,
             config,
-            stream_sender,
+            stream_hub,
         }
     }
 
     /// Subscribe to agent execution stream events
     pub fn subscribe_to_stream(&self) -> broadcast::Receiver<AgentStreamEvent> {
-        self.stream_sender.subscribe()
+        self.stream_hub.subscribe_raw()
+    }
+
+    /// Subscribe to agent execution stream events with liveness tracking, using the
+    /// configured delivery mode. Tracked subscriptions are closed by the stream reaper
+    /// once they go idle.
+    pub fn subscribe_tracked(&self) -> StreamSubscription<AgentStreamEvent> {
+        self.subscribe_with_delivery(self.config.stream_delivery)
+    }
+
+    /// Subscribe to agent execution stream events with an explicit delivery mode
+    pub fn subscribe_with_delivery(
+        &self,
+        delivery: StreamDelivery,
+    ) -> StreamSubscription<AgentStreamEvent> {
+        self.stream_hub.subscribe(delivery)
+    }
+
+    /// Subscribe to the stream events of one execution. Guaranteed subscribers only
+    /// have that execution's events queued.
+    pub fn subscribe_to_execution(
+        &self,
+        execution_id: Uuid,
+        delivery: StreamDelivery,
+    ) -> StreamSubscription<AgentStreamEvent> {
+        self.stream_hub
+            .subscribe_filtered(delivery, move |event: &AgentStreamEvent| {
+                event.execution_id() == execution_id
+            })
+    }
+
+    /// Delivery mode used for tracked subscriptions
+    pub fn stream_delivery(&self) -> StreamDelivery {
+        self.config.stream_delivery
+    }
+
+    /// Start the background reaper for stale stream subscribers
+    pub fn spawn_stream_reaper(&self) -> tokio::task::JoinHandle<()> {
+        self.stream_hub.registry().spawn_reaper(
+            self.config.cleanup_interval,
+            self.config.subscriber_idle_timeout,
+        )
+    }
+
+    /// Current stream subscriber gauges
+    pub fn stream_gauges(&self) -> StreamGauges {
+        self.stream_hub.registry().gauges()
     }
 
     /// Execute agents for a resource that entered or exists in a state
     pub async fn execute_state_agents(&self, resource: &Resource) -> Result<Vec<AgentExecution>> {
+        // Paused and cancelled resources get no further work
+        if resource.is_halted() {
+            return Ok(Vec::new());
+        }
         let configs = self
             .storage
             .get_state_agent_configs(&StateId::from(resource.current_state()))
@@ -206,6 +336,10 @@ impl AgentEngine {
         let mut executions = Vec::new();
 
         for config in configs {
+            if !config.applies_to_workflow(&resource.workflow_id) {
+                continue;
+            }
+
             // Check trigger conditions
             if !self.should_trigger_agent(&config, resource).await? {
                 continue;
@@ -241,6 +375,7 @@ impl AgentEngine {
         config: &AgentActivityConfig,
         resource: &Resource,
     ) -> Result<AgentExecution> {
+        super::lifecycle::ensure_active(resource)?;
         let agent = self
             .storage
             .get_agent(&config.agent_id)
@@ -249,7 +384,7 @@ impl AgentEngine {
                 CircuitBreakerError::NotFound(format!("Agent {}", config.agent_id.as_str()))
             })?;
 
-        let input_data = self.map_input_data(&config.input_mapping, resource)?;
+        let input_data = self.prepare_input(&agent, &config.input_mapping, resource)?;
         let mut execution = AgentExecution::new(
             config.agent_id.clone(),
             resource.id,
@@ -303,7 +438,7 @@ impl AgentEngine {
                 CircuitBreakerError::NotFound(format!("Agent {}", config.agent_id.as_str()))
             })?;
 
-        let input_data = self.map_input_data(&config.input_mapping, resource)?;
+        let input_data = self.prepare_input(&agent, &config.input_mapping, resource)?;
         let mut execution = AgentExecution::new(
             config.agent_id.clone(),
             resource.id,
@@ -335,10 +470,12 @@ impl AgentEngine {
         self.storage.store_execution(execution).await?;
 
         // Emit starting event
-        let _ = self.stream_sender.send(AgentStreamEvent::ThinkingStatus {
-            execution_id: execution.id,
-            status: "Starting agent execution".to_string(),
-        });
+        self.stream_hub
+            .publish(AgentStreamEvent::ThinkingStatus {
+                execution_id: execution.id,
+                status: "Starting agent execution".to_string(),
+            })
+            .await;
 
         // Execute the LLM call (this would integrate with actual LLM providers)
         match self
@@ -353,20 +490,24 @@ impl AgentEngine {
                 execution.complete(response.clone());
 
                 // Emit completion event
-                let _ = self.stream_sender.send(AgentStreamEvent::Completed {
-                    execution_id: execution.id,
-                    final_response: response,
-                    usage: None,
-                });
+                self.stream_hub
+                    .publish(AgentStreamEvent::Completed {
+                        execution_id: execution.id,
+                        final_response: response,
+                        usage: None,
+                    })
+                    .await;
             }
             Err(e) => {
                 execution.fail(e.to_string());
 
                 // Emit failure event
-                let _ = self.stream_sender.send(AgentStreamEvent::Failed {
-                    execution_id: execution.id,
-                    error: e.to_string(),
-                });
+                self.stream_hub
+                    .publish(AgentStreamEvent::Failed {
+                        execution_id: execution.id,
+                        error: e.to_string(),
+                    })
+                    .await;
             }
         }
 
@@ -374,6 +515,24 @@ impl AgentEngine {
         Ok(())
     }
 
+    /// Map resource data to agent input and render the agent's user prompt into it
+    ///
+    /// The prompt template sees the mapped input and the feature flags in effect for the
+    /// resource. It becomes the input's `content` unless the mapping already sets one.
+    fn prepare_input(
+        &self,
+        agent: &AgentDefinition,
+        mapping: &HashMap<String, String>,
+        resource: &Resource,
+    ) -> Result<Value> {
+        let mut input = self.map_input_data(mapping, resource)?;
+        if !agent.prompts.user_template.is_empty() && input.get("content").is_none() {
+            let flags = FeatureFlags::global().evaluate_for(resource);
+            input["content"] = json!(agent.prompts.render_user_prompt(&input, &flags));
+        }
+        Ok(input)
+    }
+
     /// Map resource data to agent input using the provided mapping
     fn map_input_data(
         &self,
@@ -0,0 +1,246 @@
+// Bulk activity evaluation across many resources
+// Evaluates every activity of a workflow for all matching resources and aggregates the results
+
+//! # Bulk Evaluation
+//!
+//! Answers "which of our resources can move, and through which activities" in one call
+//! instead of one `availableActivities` query per resource. Resources are split into
+//! chunks that are evaluated on blocking threads, at most `max_concurrency` at a time,
+//! through a shared [`RulesEngine`] so compiled rules are reused across the whole run.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::Semaphore;
+use uuid::Uuid;
+
+use super::rules::RulesEngine;
+use crate::models::{Resource, WorkflowDefinition};
+
+/// Resources evaluated per blocking task
+pub const BULK_CHUNK_SIZE: usize = 256;
+
+/// Selects the resources a bulk evaluation covers; empty criteria match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResourceFilter {
+    /// Only resources currently in one of these states
+    #[serde(default)]
+    pub states: Vec<String>,
+    /// Only these resources
+    #[serde(default)]
+    pub resource_ids: Vec<Uuid>,
+    /// Only resources whose metadata has all of these values
+    #[serde(default)]
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+    /// Evaluate at most this many resources
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+impl ResourceFilter {
+    pub fn matches(&self, resource: &Resource) -> bool {
+        (self.states.is_empty() || self.states.iter().any(|s| s == resource.state.as_str()))
+            && (self.resource_ids.is_empty() || self.resource_ids.contains(&resource.id))
+            && self
+                .metadata
+                .iter()
+                .all(|(key, value)| resource.metadata.get(key) == Some(value))
+    }
+
+    /// Keep the resources that match, up to `limit`
+    pub fn apply(&self, resources: Vec<Resource>) -> Vec<Resource> {
+        resources
+            .into_iter()
+            .filter(|resource| self.matches(resource))
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+}
+
+/// How one activity fared across the evaluated resources
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ActivityBulkSummary {
+    pub activity_id: String,
+    /// Resources that can execute the activity now
+    pub available_count: usize,
+    /// Resources in a source state of the activity whose rules or conditions fail
+    pub blocked_count: usize,
+    /// Resources the activity does not start from
+    pub not_in_source_state_count: usize,
+}
+
+/// Aggregate result of a bulk evaluation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkEvaluationReport {
+    pub workflow_id: String,
+    pub resources_evaluated: usize,
+    /// Resources with at least one available activity
+    pub resources_with_available_activities: usize,
+    /// One entry per workflow activity, in workflow order
+    pub activities: Vec<ActivityBulkSummary>,
+    pub duration_ms: u64,
+}
+
+impl BulkEvaluationReport {
+    fn empty(workflow: &WorkflowDefinition) -> Self {
+        Self {
+            workflow_id: workflow.id.clone(),
+            resources_evaluated: 0,
+            resources_with_available_activities: 0,
+            activities: workflow
+                .activities
+                .iter()
+                .map(|activity| ActivityBulkSummary {
+                    activity_id: activity.id.as_str().to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+            duration_ms: 0,
+        }
+    }
+
+    fn merge(&mut self, other: BulkEvaluationReport) {
+        self.resources_evaluated += other.resources_evaluated;
+        self.resources_with_available_activities += other.resources_with_available_activities;
+        for (summary, partial) in self.activities.iter_mut().zip(other.activities) {
+            summary.available_count += partial.available_count;
+            summary.blocked_count += partial.blocked_count;
+            summary.not_in_source_state_count += partial.not_in_source_state_count;
+        }
+    }
+}
+
+/// Evaluate every activity of `workflow` for each resource, at most `max_concurrency`
+/// chunks at a time
+pub async fn evaluate_bulk(
+    engine: Arc<RulesEngine>,
+    workflow: Arc<WorkflowDefinition>,
+    resources: Vec<Resource>,
+    max_concurrency: usize,
+) -> BulkEvaluationReport {
+    let started = Instant::now();
+    let permits = Arc::new(Semaphore::new(max_concurrency.max(1)));
+
+    let mut chunks = Vec::new();
+    let mut resources = resources.into_iter().peekable();
+    while resources.peek().is_some() {
+        let chunk: Vec<Resource> = resources.by_ref().take(BULK_CHUNK_SIZE).collect();
+        let permits = permits.clone();
+        let engine = engine.clone();
+        let workflow = workflow.clone();
+        chunks.push(tokio::spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            tokio::task::spawn_blocking(move || evaluate_chunk(&engine, &workflow, &chunk)).await
+        }));
+    }
+
+    let mut report = BulkEvaluationReport::empty(&workflow);
+    for chunk in chunks {
+        if let Ok(Ok(partial)) = chunk.await {
+            report.merge(partial);
+        }
+    }
+    report.duration_ms = started.elapsed().as_millis() as u64;
+    report
+}
+
+fn evaluate_chunk(
+    engine: &RulesEngine,
+    workflow: &WorkflowDefinition,
+    resources: &[Resource],
+) -> BulkEvaluationReport {
+    let mut report = BulkEvaluationReport::empty(workflow);
+    for resource in resources {
+        let mut any_available = false;
+        for (summary, activity) in report.activities.iter_mut().zip(&workflow.activities) {
+            if !activity.can_execute_on(resource) {
+                summary.not_in_source_state_count += 1;
+            } else if engine.can_execute_activity(resource, activity) {
+                summary.available_count += 1;
+                any_available = true;
+            } else {
+                summary.blocked_count += 1;
+            }
+        }
+        report.resources_evaluated += 1;
+        if any_available {
+            report.resources_with_available_activities += 1;
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ActivityDefinition, Rule, StateId};
+
+    fn workflow() -> WorkflowDefinition {
+        WorkflowDefinition::new(
+            "review",
+            "Review",
+            vec![
+                StateId::from("draft"),
+                StateId::from("review"),
+                StateId::from("published"),
+            ],
+            vec![
+                ActivityDefinition::with_rules(
+                    "submit",
+                    vec!["draft"],
+                    "review",
+                    vec![Rule::field_exists("has_content", "content")],
+                ),
+                ActivityDefinition::new("publish", vec!["review"], "published"),
+            ],
+            "draft",
+        )
+    }
+
+    #[tokio::test]
+    async fn test_bulk_report_counts_each_activity() {
+        let mut resources = Vec::new();
+        for i in 0..600 {
+            let mut resource = Resource::new("review", StateId::from("draft"));
+            if i % 3 == 0 {
+                resource.data = serde_json::json!({"content": "ready"});
+            }
+            resource.set_metadata("team", serde_json::json!(if i < 300 { "a" } else { "b" }));
+            resources.push(resource);
+        }
+        resources.push(Resource::new("review", StateId::from("review")));
+
+        let report = evaluate_bulk(
+            Arc::new(RulesEngine::new()),
+            Arc::new(workflow()),
+            resources.clone(),
+            2,
+        )
+        .await;
+        assert_eq!(report.resources_evaluated, 601);
+        assert_eq!(report.resources_with_available_activities, 201);
+        assert_eq!(
+            report.activities[0],
+            ActivityBulkSummary {
+                activity_id: "submit".to_string(),
+                available_count: 200,
+                blocked_count: 400,
+                not_in_source_state_count: 1,
+            }
+        );
+        assert_eq!(report.activities[1].available_count, 1);
+        assert_eq!(report.activities[1].not_in_source_state_count, 600);
+
+        let mut filter = ResourceFilter {
+            states: vec!["draft".to_string()],
+            limit: Some(50),
+            ..Default::default()
+        };
+        filter
+            .metadata
+            .insert("team".to_string(), serde_json::json!("b"));
+        let matching = filter.apply(resources);
+        assert_eq!(matching.len(), 50);
+        assert!(matching.iter().all(|r| r.metadata["team"] == "b"));
+    }
+}
@@ -0,0 +1,256 @@
+// Saga-style compensation
+// Rolls a resource back by running the compensations of the activities it went through
+
+//! # Compensation
+//!
+//! An activity can declare a compensating activity that undoes it, e.g. `release_inventory`
+//! for `reserve_inventory`. [`compensate`] rolls a resource back by walking its history in
+//! reverse and executing the compensation of every activity that has one, so a multi-step
+//! operation spread over several systems can be undone step by step.
+//!
+//! Every history event a compensation records carries `{"compensates": <index>}` as its
+//! data, pointing at the event it undid. Compensated events are not undone twice, and
+//! compensations are never compensated themselves, so compensating a resource again only
+//! undoes what it went through since.
+//!
+//! The whole walk is checked before anything is stored: when a compensation cannot run
+//! from the state the resource would be in, nothing changes and an
+//! [`InvalidTransition`](crate::CircuitBreakerError::InvalidTransition) error names it.
+//! Compensations skip rules, conditions and state capacity - a rollback must not be
+//! blocked by the checks that guarded the way forward. Paused resources are not
+//! compensated until resumed; cancelled ones are, undoing what they went through.
+
+use serde::Serialize;
+use std::collections::HashSet;
+use tracing::info;
+use uuid::Uuid;
+
+use super::storage::WorkflowStorage;
+use crate::models::{ActivityId, HistoryEvent, LifecycleStatus, Resource, StateId};
+use crate::{CircuitBreakerError, Result};
+
+/// Key of the history event data linking a compensation to the event it undid
+pub const COMPENSATES_KEY: &str = "compensates";
+
+/// An activity undone by its compensation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CompensationStep {
+    /// Index of the compensated event in the resource's history
+    pub history_index: usize,
+    pub compensated: ActivityId,
+    pub compensation: ActivityId,
+    pub from_state: StateId,
+    pub to_state: StateId,
+}
+
+/// Outcome of compensating a resource
+#[derive(Debug, Clone)]
+pub struct Compensation {
+    /// The resource as stored after the compensations
+    pub resource: Resource,
+    /// Compensations executed, latest activity first
+    pub steps: Vec<CompensationStep>,
+}
+
+/// Index of the event a history event compensated, if it is a compensation
+fn compensated_index(event: &HistoryEvent) -> Option<usize> {
+    event
+        .data
+        .as_ref()?
+        .get(COMPENSATES_KEY)?
+        .as_u64()
+        .map(|index| index as usize)
+}
+
+/// Roll a resource back by executing, latest first, the compensations of the activities
+/// in its history that have not been compensated yet
+///
+/// Activities without a compensation are passed over. The resource is stored once, after
+/// every compensation has run.
+pub async fn compensate<S: WorkflowStorage + ?Sized>(
+    storage: &S,
+    resource_id: &Uuid,
+) -> Result<Compensation> {
+    let mut resource = storage
+        .get_resource(resource_id)
+        .await?
+        .ok_or_else(|| CircuitBreakerError::NotFound(format!("Resource {}", resource_id)))?;
+    if resource.lifecycle_status() == LifecycleStatus::Paused {
+        return Err(CircuitBreakerError::ResourceHalted {
+            id: resource_id.to_string(),
+            status: LifecycleStatus::Paused.as_str().to_string(),
+        });
+    }
+    let workflow = storage
+        .get_workflow(&resource.workflow_id)
+        .await?
+        .ok_or_else(|| CircuitBreakerError::WorkflowNotFound {
+            id: resource.workflow_id.clone(),
+        })?;
+
+    let compensated: HashSet<usize> = resource
+        .history
+        .iter()
+        .filter_map(compensated_index)
+        .collect();
+    let pending: Vec<(usize, ActivityId)> = resource
+        .history
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|(index, event)| compensated_index(event).is_none() && !compensated.contains(index))
+        .filter_map(|(index, event)| {
+            let compensation = workflow
+                .activities
+                .iter()
+                .find(|a| a.id == event.activity)?
+                .compensation
+                .clone()?;
+            Some((index, compensation))
+        })
+        .collect();
+
+    let mut steps = Vec::with_capacity(pending.len());
+    for (history_index, compensation_id) in pending {
+        let compensated = resource.history[history_index].activity.clone();
+        let compensation = workflow
+            .activities
+            .iter()
+            .find(|a| a.id == compensation_id)
+            .filter(|a| a.can_execute_from(&resource.state))
+            .ok_or_else(|| CircuitBreakerError::InvalidTransition {
+                from: resource.state.as_str().to_string(),
+                to: format!("compensation of '{}'", compensated.as_str()),
+                transition: compensation_id.as_str().to_string(),
+            })?;
+
+        let from_state = resource.state.clone();
+        resource.execute_activity(compensation.to_state.clone(), compensation.id.clone());
+        if let Some(event) = resource.history.last_mut() {
+            event.data = Some(serde_json::json!({ COMPENSATES_KEY: history_index }));
+        }
+        steps.push(CompensationStep {
+            history_index,
+            compensated,
+            compensation: compensation.id.clone(),
+            from_state,
+            to_state: compensation.to_state.clone(),
+        });
+    }
+
+    if steps.is_empty() {
+        return Ok(Compensation { resource, steps });
+    }
+
+    let resource = storage.update_resource(resource).await?;
+    info!(
+        "↩️ Compensated {} activities of resource {}, now in '{}'",
+        steps.len(),
+        resource.id,
+        resource.state.as_str()
+    );
+
+    Ok(Compensation { resource, steps })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::storage::InMemoryStorage;
+    use crate::models::{ActivityDefinition, WorkflowDefinition};
+
+    fn order_workflow() -> WorkflowDefinition {
+        WorkflowDefinition::new(
+            "order_saga",
+            "Order Saga",
+            vec![
+                StateId::from("placed"),
+                StateId::from("reserved"),
+                StateId::from("charged"),
+                StateId::from("notified"),
+                StateId::from("cancelled"),
+            ],
+            vec![
+                ActivityDefinition::new("reserve", vec!["placed"], "reserved")
+                    .with_compensation("release"),
+                ActivityDefinition::new("charge", vec!["reserved"], "charged")
+                    .with_compensation("refund"),
+                ActivityDefinition::new("notify", vec!["charged"], "notified"),
+                ActivityDefinition::new("refund", vec!["charged", "notified"], "reserved"),
+                ActivityDefinition::new("release", vec!["reserved"], "cancelled"),
+            ],
+            "placed",
+        )
+    }
+
+    #[tokio::test]
+    async fn test_compensate_walks_history_in_reverse() {
+        let storage = InMemoryStorage::default();
+        let workflow = storage.create_workflow(order_workflow()).await.unwrap();
+        let mut resource = Resource::new(&workflow.id, StateId::from("placed"));
+        for (activity, state) in [
+            ("reserve", "reserved"),
+            ("charge", "charged"),
+            ("notify", "notified"),
+        ] {
+            resource.execute_activity(StateId::from(state), ActivityId::from(activity));
+        }
+        let resource = storage.create_resource(resource).await.unwrap();
+
+        let compensation = compensate(&storage, &resource.id).await.unwrap();
+        let undone: Vec<_> = compensation
+            .steps
+            .iter()
+            .map(|step| (step.history_index, step.compensation.as_str()))
+            .collect();
+        assert_eq!(undone, vec![(1, "refund"), (0, "release")]);
+        assert_eq!(compensation.resource.state, StateId::from("cancelled"));
+        assert_eq!(
+            compensation.resource.history[3].data,
+            Some(serde_json::json!({ "compensates": 1 }))
+        );
+
+        // Nothing is left to undo
+        let again = compensate(&storage, &resource.id).await.unwrap();
+        assert!(again.steps.is_empty());
+        assert_eq!(again.resource.history.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_compensate_changes_nothing_when_a_step_cannot_run() {
+        let storage = InMemoryStorage::default();
+        let mut workflow = order_workflow();
+        workflow.activities.retain(|a| a.id.as_str() != "refund");
+        workflow.activities.push(ActivityDefinition::new(
+            "refund",
+            vec!["charged"],
+            "reserved",
+        ));
+        let workflow = storage.create_workflow(workflow).await.unwrap();
+        let mut resource = Resource::new(&workflow.id, StateId::from("placed"));
+        resource.execute_activity(StateId::from("reserved"), ActivityId::from("reserve"));
+        resource.execute_activity(StateId::from("charged"), ActivityId::from("charge"));
+        resource.execute_activity(StateId::from("notified"), ActivityId::from("notify"));
+        let resource = storage.create_resource(resource).await.unwrap();
+
+        let error = compensate(&storage, &resource.id).await.unwrap_err();
+        assert!(matches!(
+            error,
+            CircuitBreakerError::InvalidTransition { .. }
+        ));
+        let stored = storage.get_resource(&resource.id).await.unwrap().unwrap();
+        assert_eq!(stored.state, StateId::from("notified"));
+        assert_eq!(stored.history.len(), 3);
+    }
+
+    #[test]
+    fn test_compensation_must_be_a_workflow_activity() {
+        let mut workflow = order_workflow();
+        assert!(workflow.validate().is_ok());
+        workflow.activities.retain(|a| a.id.as_str() != "release");
+        assert!(workflow
+            .validate()
+            .unwrap_err()
+            .contains("unknown activity 'release'"));
+    }
+}
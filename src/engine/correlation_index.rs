@@ -0,0 +1,205 @@
+// Correlation key index for aggregate conditions
+// Resources grouped by the values in their metadata, kept up to date by storage
+
+//! # Correlation Index
+//!
+//! A `SiblingsInState` condition asks about every resource sharing a correlation key
+//! value with the one being evaluated. Scanning all resources for each evaluation does
+//! not scale, so storage backends keep a [`CorrelationIndex`] up to date on every write:
+//! each resource is filed under every string, number or boolean value in its metadata,
+//! together with its workflow and current state. Any metadata key can then serve as a
+//! correlation key, and a resource's siblings are a single lookup.
+//!
+//! Like the state counters the index is derived data; `rebuild` recomputes it from the
+//! stored resources. The rules engine reads the process-wide index by default, the same
+//! one in-memory storage maintains.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use uuid::Uuid;
+
+use crate::models::correlation::correlation_value;
+use crate::models::{Resource, Sibling, SiblingGroups};
+
+lazy_static::lazy_static! {
+    static ref GLOBAL: CorrelationIndex = CorrelationIndex::new();
+}
+
+/// Index entries keyed by correlation key and value
+type CorrelationKey = (String, String);
+
+#[derive(Debug, Default)]
+struct IndexState {
+    /// Correlation key and value -> resources filed under it
+    groups: HashMap<CorrelationKey, HashMap<Uuid, Sibling>>,
+    /// Resource -> correlation keys and values it is filed under
+    entries: HashMap<Uuid, Vec<CorrelationKey>>,
+}
+
+impl IndexState {
+    fn remove(&mut self, resource_id: &Uuid) {
+        for key in self.entries.remove(resource_id).unwrap_or_default() {
+            if let Some(group) = self.groups.get_mut(&key) {
+                group.remove(resource_id);
+                if group.is_empty() {
+                    self.groups.remove(&key);
+                }
+            }
+        }
+    }
+
+    fn insert(&mut self, resource: &Resource) {
+        let sibling = Sibling {
+            resource_id: resource.id,
+            workflow_id: resource.workflow_id.clone(),
+            state: resource.current_state().to_string(),
+        };
+        let keys: Vec<CorrelationKey> = resource
+            .metadata
+            .keys()
+            .filter_map(|key| {
+                correlation_value(&resource.metadata, key).map(|value| (key.clone(), value))
+            })
+            .collect();
+        for key in &keys {
+            self.groups
+                .entry(key.clone())
+                .or_default()
+                .insert(resource.id, sibling.clone());
+        }
+        if !keys.is_empty() {
+            self.entries.insert(resource.id, keys);
+        }
+    }
+}
+
+/// Shared index of resources by correlation key value
+///
+/// Clones read and update the same index.
+#[derive(Debug, Clone, Default)]
+pub struct CorrelationIndex {
+    state: Arc<RwLock<IndexState>>,
+}
+
+impl CorrelationIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The process-wide index consulted by the rules engine
+    pub fn global() -> Self {
+        GLOBAL.clone()
+    }
+
+    /// File a stored resource under its current metadata and state, replacing its
+    /// previous entries
+    pub fn resource_stored(&self, resource: &Resource) {
+        let mut state = self.state.write().unwrap();
+        state.remove(&resource.id);
+        state.insert(resource);
+    }
+
+    /// Forget a resource that is no longer stored
+    pub fn resource_removed(&self, resource_id: &Uuid) {
+        self.state.write().unwrap().remove(resource_id);
+    }
+
+    /// Recompute the index from scratch
+    pub fn rebuild<'a>(&self, resources: impl IntoIterator<Item = &'a Resource>) {
+        let mut state = IndexState::default();
+        for resource in resources {
+            state.insert(resource);
+        }
+        *self.state.write().unwrap() = state;
+    }
+
+    /// IDs of the resources with `value` under the metadata key `key`
+    pub fn resource_ids(&self, key: &str, value: &str) -> Vec<Uuid> {
+        self.state
+            .read()
+            .unwrap()
+            .groups
+            .get(&(key.to_string(), value.to_string()))
+            .map(|group| group.keys().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Siblings of a resource under each of `keys` it has a value for, itself excluded
+    pub fn siblings(&self, resource: &Resource, keys: &[String]) -> SiblingGroups {
+        let state = self.state.read().unwrap();
+        keys.iter()
+            .filter_map(|key| {
+                let value = correlation_value(&resource.metadata, key)?;
+                let siblings = state
+                    .groups
+                    .get(&(key.clone(), value))
+                    .into_iter()
+                    .flat_map(HashMap::values)
+                    .filter(|sibling| sibling.resource_id != resource.id)
+                    .cloned()
+                    .collect();
+                Some((key.clone(), siblings))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::StateId;
+
+    fn line_item(order_id: &str, state: &str) -> Resource {
+        let mut resource = Resource::new("line_item", StateId::from(state));
+        resource
+            .metadata
+            .insert("order_id".to_string(), serde_json::json!(order_id));
+        resource
+    }
+
+    #[test]
+    fn test_siblings_follow_writes() {
+        let index = CorrelationIndex::new();
+        let mut order = Resource::new("order", StateId::from("open"));
+        order
+            .metadata
+            .insert("order_id".to_string(), serde_json::json!("o-1"));
+        let mut first = line_item("o-1", "pending");
+        let second = line_item("o-1", "validated");
+        let other_order = line_item("o-2", "validated");
+        for resource in [&order, &first, &second, &other_order] {
+            index.resource_stored(resource);
+        }
+
+        let keys = vec!["order_id".to_string()];
+        let siblings = index.siblings(&order, &keys);
+        assert_eq!(siblings["order_id"].len(), 2);
+        assert_eq!(index.resource_ids("order_id", "o-1").len(), 3);
+
+        // Moving a resource updates its state in the index
+        first.state = StateId::from("validated");
+        index.resource_stored(&first);
+        let siblings = index.siblings(&order, &keys);
+        assert!(siblings["order_id"]
+            .iter()
+            .all(|sibling| sibling.state == "validated"));
+
+        // Changing the correlation value moves it to another group
+        first
+            .metadata
+            .insert("order_id".to_string(), serde_json::json!("o-2"));
+        index.resource_stored(&first);
+        assert_eq!(index.siblings(&order, &keys)["order_id"].len(), 1);
+        assert_eq!(index.resource_ids("order_id", "o-2").len(), 2);
+
+        index.resource_removed(&second.id);
+        assert!(index.siblings(&order, &keys)["order_id"].is_empty());
+
+        // Resources without the key have no group at all
+        let unkeyed = Resource::new("order", StateId::from("open"));
+        assert!(index.siblings(&unkeyed, &keys).is_empty());
+
+        index.rebuild([&order, &second]);
+        assert_eq!(index.siblings(&order, &keys)["order_id"].len(), 1);
+    }
+}
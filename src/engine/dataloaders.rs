@@ -0,0 +1,211 @@
+// GraphQL dataloaders
+// Batch the storage lookups GraphQL resolvers make for related entities
+
+//! # Dataloaders
+//!
+//! Nested GraphQL fields such as `resources { workflow { ... } executions { ... } }`
+//! would otherwise hit storage once per resource. The loaders here collect the keys
+//! requested while a query resolves and fetch them with one batched storage call
+//! ([`WorkflowStorage::get_workflows`], [`AgentStorage::list_executions_for_resources`]),
+//! so listing 500 resources with their workflows costs one lookup instead of 500.
+//!
+//! Loaders are registered as schema data by the `create_schema_*` builders. They do not
+//! cache between requests, so results are never stale.
+
+use async_graphql::dataloader::{DataLoader, Loader};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::engine::storage::WorkflowStorage;
+use crate::engine::AgentStorage;
+use crate::models::{AgentExecution, WorkflowDefinition};
+use crate::CircuitBreakerError;
+
+/// Loads workflow definitions by ID
+pub struct WorkflowLoader {
+    storage: Arc<dyn WorkflowStorage>,
+}
+
+impl WorkflowLoader {
+    pub fn new(storage: Arc<dyn WorkflowStorage>) -> Self {
+        Self { storage }
+    }
+
+    /// A dataloader batching workflow lookups on the Tokio runtime
+    pub fn dataloader(storage: Arc<dyn WorkflowStorage>) -> DataLoader<Self> {
+        DataLoader::new(Self::new(storage), tokio::spawn)
+    }
+}
+
+#[async_trait::async_trait]
+impl Loader<String> for WorkflowLoader {
+    type Value = WorkflowDefinition;
+    type Error = Arc<CircuitBreakerError>;
+
+    async fn load(
+        &self,
+        keys: &[String],
+    ) -> Result<HashMap<String, WorkflowDefinition>, Self::Error> {
+        self.storage.get_workflows(keys).await.map_err(Arc::new)
+    }
+}
+
+/// Loads the agent executions of resources by resource ID
+pub struct ResourceExecutionsLoader {
+    storage: Arc<dyn AgentStorage>,
+}
+
+impl ResourceExecutionsLoader {
+    pub fn new(storage: Arc<dyn AgentStorage>) -> Self {
+        Self { storage }
+    }
+
+    /// A dataloader batching execution lookups on the Tokio runtime
+    pub fn dataloader(storage: Arc<dyn AgentStorage>) -> DataLoader<Self> {
+        DataLoader::new(Self::new(storage), tokio::spawn)
+    }
+}
+
+#[async_trait::async_trait]
+impl Loader<Uuid> for ResourceExecutionsLoader {
+    type Value = Vec<AgentExecution>;
+    type Error = Arc<CircuitBreakerError>;
+
+    async fn load(&self, keys: &[Uuid]) -> Result<HashMap<Uuid, Vec<AgentExecution>>, Self::Error> {
+        let mut executions = self
+            .storage
+            .list_executions_for_resources(keys)
+            .await
+            .map_err(Arc::new)?;
+        for list in executions.values_mut() {
+            list.sort_by_key(|execution| execution.started_at);
+        }
+        Ok(executions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::graphql::create_schema_with_agents;
+    use crate::engine::storage::InMemoryStorage;
+    use crate::engine::{AgentEngine, AgentEngineConfig, InMemoryAgentStorage, RulesEngine};
+    use crate::models::{AgentId, Resource, StateId};
+    use crate::Result;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// In-memory storage counting workflow lookups
+    struct CountingStorage {
+        inner: InMemoryStorage,
+        single_lookups: AtomicUsize,
+        batch_lookups: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl WorkflowStorage for CountingStorage {
+        async fn create_workflow(
+            &self,
+            definition: WorkflowDefinition,
+        ) -> Result<WorkflowDefinition> {
+            self.inner.create_workflow(definition).await
+        }
+
+        async fn get_workflow(&self, id: &str) -> Result<Option<WorkflowDefinition>> {
+            self.single_lookups.fetch_add(1, Ordering::SeqCst);
+            self.inner.get_workflow(id).await
+        }
+
+        async fn list_workflows(&self) -> Result<Vec<WorkflowDefinition>> {
+            self.inner.list_workflows().await
+        }
+
+        async fn create_resource(&self, resource: Resource) -> Result<Resource> {
+            self.inner.create_resource(resource).await
+        }
+
+        async fn get_resource(&self, id: &Uuid) -> Result<Option<Resource>> {
+            self.inner.get_resource(id).await
+        }
+
+        async fn update_resource(&self, resource: Resource) -> Result<Resource> {
+            self.inner.update_resource(resource).await
+        }
+
+        async fn list_resources(&self, workflow_id: Option<&str>) -> Result<Vec<Resource>> {
+            self.inner.list_resources(workflow_id).await
+        }
+
+        async fn get_workflows(
+            &self,
+            ids: &[String],
+        ) -> Result<HashMap<String, WorkflowDefinition>> {
+            self.batch_lookups.fetch_add(1, Ordering::SeqCst);
+            self.inner.get_workflows(ids).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resource_relations_are_batched() {
+        let storage = Arc::new(CountingStorage {
+            inner: InMemoryStorage::default(),
+            single_lookups: AtomicUsize::new(0),
+            batch_lookups: AtomicUsize::new(0),
+        });
+        let agent_storage = Arc::new(InMemoryAgentStorage::default());
+        for workflow_id in ["review", "billing"] {
+            storage
+                .create_workflow(WorkflowDefinition::new(
+                    workflow_id,
+                    workflow_id,
+                    vec![StateId::from("draft")],
+                    vec![],
+                    "draft",
+                ))
+                .await
+                .unwrap();
+        }
+        for i in 0..50 {
+            let workflow_id = if i % 2 == 0 { "review" } else { "billing" };
+            let resource = storage
+                .create_resource(Resource::new(workflow_id, StateId::from("draft")))
+                .await
+                .unwrap();
+            if i < 3 {
+                let execution = AgentExecution::new(
+                    AgentId::from("summarizer"),
+                    resource.id,
+                    StateId::from("draft"),
+                    serde_json::json!({}),
+                );
+                agent_storage.store_execution(&execution).await.unwrap();
+            }
+        }
+
+        let engine = AgentEngine::new(
+            agent_storage.clone(),
+            Arc::new(RulesEngine::new()),
+            AgentEngineConfig::default(),
+        );
+        let schema = create_schema_with_agents(Box::new(storage.clone()), agent_storage, engine);
+        let response = schema
+            .execute("{ resources { id workflow { id } executions { id } } }")
+            .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+
+        let data = response.data.into_json().unwrap();
+        let resources = data["resources"].as_array().unwrap();
+        assert_eq!(resources.len(), 50);
+        assert!(resources
+            .iter()
+            .all(|r| r["workflow"]["id"] == "review" || r["workflow"]["id"] == "billing"));
+        let executions: usize = resources
+            .iter()
+            .map(|r| r["executions"].as_array().unwrap().len())
+            .sum();
+        assert_eq!(executions, 3);
+
+        assert_eq!(storage.single_lookups.load(Ordering::SeqCst), 0);
+        assert_eq!(storage.batch_lookups.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,382 @@
+// Workflow diagrams
+// Renders workflow definitions as Graphviz DOT and Mermaid state diagrams
+
+//! # Workflow Diagrams
+//!
+//! [`render_dot`] and [`render_mermaid`] turn a [`WorkflowDefinition`] into diagram
+//! source UIs and docs can display as is:
+//!
+//! - every state is a node; the initial state is entered from a start marker and
+//!   states without activities leaving them lead to an end marker
+//! - every activity is an edge from each of its source states, labelled with its ID;
+//!   joins are marked, splits lead on to their branch states, human tasks with a
+//!   `reject_to` state get a rejection edge and every route an edge labelled with
+//!   its expression
+//!
+//! Given a resource, the diagram also shows where it is: its current state - or the
+//! states of its branches while split - is highlighted, and so are the states and
+//! activities its history passed through. [`workflow_diagram`] loads both from storage,
+//! using the definition version the resource runs on.
+
+use serde::Serialize;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+use super::storage::WorkflowStorage;
+use crate::models::{ActivityId, Gateway, Resource, StateId, WorkflowDefinition};
+use crate::{CircuitBreakerError, Result};
+
+/// A workflow rendered in both diagram languages
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WorkflowDiagram {
+    /// Graphviz DOT source
+    pub dot: String,
+    /// Mermaid `stateDiagram-v2` source
+    pub mermaid: String,
+}
+
+/// One edge of a diagram
+struct Edge<'a> {
+    from: &'a StateId,
+    to: &'a StateId,
+    activity: &'a ActivityId,
+    label: String,
+    /// Split and rejection edges are drawn dashed
+    dashed: bool,
+}
+
+/// The states and activities a resource is at or passed through
+#[derive(Default)]
+struct Position {
+    current: HashSet<StateId>,
+    visited: HashSet<StateId>,
+    traversed: HashSet<(StateId, StateId, ActivityId)>,
+}
+
+impl Position {
+    fn of(resource: Option<&Resource>) -> Self {
+        let Some(resource) = resource else {
+            return Self::default();
+        };
+        let mut current: HashSet<StateId> = resource.branches().into_values().collect();
+        current.insert(resource.state.clone());
+        let mut position = Self {
+            visited: current.clone(),
+            current,
+            traversed: HashSet::new(),
+        };
+        for event in &resource.history {
+            position.visited.insert(event.from.clone());
+            position.visited.insert(event.to.clone());
+            position.traversed.insert((
+                event.from.clone(),
+                event.to.clone(),
+                event.activity.clone(),
+            ));
+        }
+        position
+    }
+
+    fn passed(&self, edge: &Edge) -> bool {
+        self.traversed
+            .contains(&(edge.from.clone(), edge.to.clone(), edge.activity.clone()))
+    }
+}
+
+fn edges(workflow: &WorkflowDefinition) -> Vec<Edge<'_>> {
+    let mut edges = Vec::new();
+    for activity in &workflow.activities {
+        let label = if activity.is_join() {
+            format!("{} (join)", activity.id.as_str())
+        } else {
+            activity.id.as_str().to_string()
+        };
+        for from in &activity.from_states {
+            edges.push(Edge {
+                from,
+                to: &activity.to_state,
+                activity: &activity.id,
+                label: label.clone(),
+                dashed: false,
+            });
+            for route in &activity.routes {
+                edges.push(Edge {
+                    from,
+                    to: &route.to_state,
+                    activity: &activity.id,
+                    label: format!("{} [{}]", activity.id.as_str(), route.when),
+                    dashed: true,
+                });
+            }
+            if let Some(reject_to) = activity.manual.as_ref().and_then(|m| m.reject_to.as_ref()) {
+                edges.push(Edge {
+                    from,
+                    to: reject_to,
+                    activity: &activity.id,
+                    label: format!("{} (reject)", activity.id.as_str()),
+                    dashed: true,
+                });
+            }
+        }
+        if let Some(Gateway::Split(branches)) = &activity.gateway {
+            for branch in branches {
+                edges.push(Edge {
+                    from: &activity.to_state,
+                    to: branch,
+                    activity: &activity.id,
+                    label: format!("{} (split)", activity.id.as_str()),
+                    dashed: true,
+                });
+            }
+        }
+    }
+    edges
+}
+
+/// States no activity leaves, split regions aside
+fn terminal_states(workflow: &WorkflowDefinition) -> Vec<&StateId> {
+    workflow
+        .states
+        .iter()
+        .filter(|state| {
+            !workflow.activities.iter().any(|a| {
+                a.can_execute_from(state)
+                    || matches!(&a.gateway, Some(Gateway::Split(_)) if &a.to_state == *state)
+            })
+        })
+        .collect()
+}
+
+fn dot_quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Render `workflow` as Graphviz DOT, highlighting where `resource` is and has been
+pub fn render_dot(workflow: &WorkflowDefinition, resource: Option<&Resource>) -> String {
+    let position = Position::of(resource);
+    let mut dot = format!("digraph {} {{\n", dot_quote(&workflow.id));
+    dot.push_str(&format!("  label={};\n", dot_quote(&workflow.name)));
+    dot.push_str("  rankdir=LR;\n");
+    dot.push_str("  node [shape=box, style=rounded];\n");
+    dot.push_str("  \"__start\" [shape=point, label=\"\"];\n");
+    dot.push_str("  \"__end\" [shape=doublecircle, label=\"\", width=0.2];\n");
+
+    for state in &workflow.states {
+        let style = if position.current.contains(state) {
+            ", style=\"rounded,filled,bold\", fillcolor=\"#f9a825\""
+        } else if position.visited.contains(state) {
+            ", style=\"rounded,filled\", fillcolor=\"#bbdefb\""
+        } else {
+            ""
+        };
+        dot.push_str(&format!(
+            "  {} [label={}{}];\n",
+            dot_quote(state.as_str()),
+            dot_quote(state.as_str()),
+            style
+        ));
+    }
+
+    dot.push_str(&format!(
+        "  \"__start\" -> {};\n",
+        dot_quote(workflow.initial_state.as_str())
+    ));
+    for edge in edges(workflow) {
+        let mut attributes = vec![format!("label={}", dot_quote(&edge.label))];
+        if edge.dashed {
+            attributes.push("style=dashed".to_string());
+        }
+        if position.passed(&edge) {
+            attributes.push("color=\"#1565c0\", penwidth=2".to_string());
+        }
+        dot.push_str(&format!(
+            "  {} -> {} [{}];\n",
+            dot_quote(edge.from.as_str()),
+            dot_quote(edge.to.as_str()),
+            attributes.join(", ")
+        ));
+    }
+    for state in terminal_states(workflow) {
+        dot.push_str(&format!("  {} -> \"__end\";\n", dot_quote(state.as_str())));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Mermaid identifier of a state; states with other characters get an alias
+fn mermaid_id(state: &StateId) -> String {
+    state
+        .as_str()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+fn mermaid_label(text: &str) -> String {
+    text.replace(['\n', ':', '"'], " ")
+}
+
+/// Render `workflow` as a Mermaid state diagram, highlighting where `resource` is and
+/// has been
+///
+/// Mermaid cannot style single transitions, so passed activities are marked with a
+/// check in their label instead.
+pub fn render_mermaid(workflow: &WorkflowDefinition, resource: Option<&Resource>) -> String {
+    let position = Position::of(resource);
+    let mut mermaid = String::from("stateDiagram-v2\n");
+    mermaid.push_str(&format!("  %% {}\n", mermaid_label(&workflow.name)));
+
+    for state in &workflow.states {
+        let id = mermaid_id(state);
+        if id != state.as_str() {
+            mermaid.push_str(&format!(
+                "  state \"{}\" as {}\n",
+                mermaid_label(state.as_str()),
+                id
+            ));
+        }
+    }
+
+    mermaid.push_str(&format!(
+        "  [*] --> {}\n",
+        mermaid_id(&workflow.initial_state)
+    ));
+    for edge in edges(workflow) {
+        let label = mermaid_label(&edge.label);
+        let label = if position.passed(&edge) {
+            format!("{} ✓", label)
+        } else {
+            label
+        };
+        mermaid.push_str(&format!(
+            "  {} --> {} : {}\n",
+            mermaid_id(edge.from),
+            mermaid_id(edge.to),
+            label
+        ));
+    }
+    for state in terminal_states(workflow) {
+        mermaid.push_str(&format!("  {} --> [*]\n", mermaid_id(state)));
+    }
+
+    if resource.is_some() {
+        mermaid.push_str("  classDef current fill:#f9a825,stroke:#e65100,font-weight:bold\n");
+        mermaid.push_str("  classDef visited fill:#bbdefb\n");
+        for state in &workflow.states {
+            let class = if position.current.contains(state) {
+                "current"
+            } else if position.visited.contains(state) {
+                "visited"
+            } else {
+                continue;
+            };
+            mermaid.push_str(&format!("  class {} {}\n", mermaid_id(state), class));
+        }
+    }
+    mermaid
+}
+
+/// Render a workflow, and optionally where one of its resources is, in both languages
+///
+/// With a resource, the definition version the resource runs on is rendered.
+pub async fn workflow_diagram<S: WorkflowStorage + ?Sized>(
+    storage: &S,
+    workflow_id: &str,
+    resource_id: Option<&Uuid>,
+) -> Result<WorkflowDiagram> {
+    let resource = match resource_id {
+        Some(id) => {
+            let resource = storage
+                .get_resource(id)
+                .await?
+                .ok_or_else(|| CircuitBreakerError::TokenNotFound { id: id.to_string() })?;
+            if resource.workflow_id != workflow_id {
+                return Err(CircuitBreakerError::InvalidInput(format!(
+                    "Resource {} belongs to workflow '{}', not '{}'",
+                    id, resource.workflow_id, workflow_id
+                )));
+            }
+            Some(resource)
+        }
+        None => None,
+    };
+    let workflow = match &resource {
+        Some(resource) => {
+            storage
+                .get_workflow_version(workflow_id, resource.workflow_version)
+                .await?
+        }
+        None => storage.get_workflow(workflow_id).await?,
+    }
+    .ok_or_else(|| CircuitBreakerError::WorkflowNotFound {
+        id: workflow_id.to_string(),
+    })?;
+
+    Ok(WorkflowDiagram {
+        dot: render_dot(&workflow, resource.as_ref()),
+        mermaid: render_mermaid(&workflow, resource.as_ref()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ActivityDefinition;
+
+    fn review_workflow() -> WorkflowDefinition {
+        WorkflowDefinition::new(
+            "review",
+            "Review",
+            vec![
+                StateId::from("draft"),
+                StateId::from("in-review"),
+                StateId::from("published"),
+            ],
+            vec![
+                ActivityDefinition::new("submit", vec!["draft"], "in-review"),
+                ActivityDefinition::new("revise", vec!["in-review"], "draft"),
+                ActivityDefinition::new("publish", vec!["in-review"], "published"),
+            ],
+            "draft",
+        )
+    }
+
+    #[test]
+    fn test_render_definition() {
+        let workflow = review_workflow();
+
+        let dot = render_dot(&workflow, None);
+        assert!(dot.starts_with("digraph \"review\" {"));
+        assert!(dot.contains("\"__start\" -> \"draft\";"));
+        assert!(dot.contains("\"draft\" -> \"in-review\" [label=\"submit\"];"));
+        assert!(dot.contains("\"published\" -> \"__end\";"));
+        assert!(!dot.contains("filled"));
+
+        let mermaid = render_mermaid(&workflow, None);
+        assert!(mermaid.starts_with("stateDiagram-v2\n"));
+        assert!(mermaid.contains("state \"in-review\" as in_review"));
+        assert!(mermaid.contains("[*] --> draft"));
+        assert!(mermaid.contains("in_review --> published : publish"));
+        assert!(mermaid.contains("published --> [*]"));
+        assert!(!mermaid.contains("classDef"));
+    }
+
+    #[test]
+    fn test_render_resource_position() {
+        let workflow = review_workflow();
+        let mut resource = Resource::new("review", StateId::from("draft"));
+        resource.execute_activity(StateId::from("in-review"), ActivityId::from("submit"));
+
+        let dot = render_dot(&workflow, Some(&resource));
+        assert!(dot.contains("\"in-review\" [label=\"in-review\", style=\"rounded,filled,bold\""));
+        assert!(dot.contains("\"draft\" [label=\"draft\", style=\"rounded,filled\""));
+        assert!(dot.contains("[label=\"submit\", color=\"#1565c0\", penwidth=2]"));
+        assert!(dot.contains("\"published\" [label=\"published\"];"));
+
+        let mermaid = render_mermaid(&workflow, Some(&resource));
+        assert!(mermaid.contains("draft --> in_review : submit ✓"));
+        assert!(mermaid.contains("in_review --> draft : revise\n"));
+        assert!(mermaid.contains("class in_review current"));
+        assert!(mermaid.contains("class draft visited"));
+    }
+}
@@ -0,0 +1,237 @@
+// Event replay for NATS storage
+// Rate-limited replays of stored events for rebuilding downstream projections
+
+//! # Event Replay
+//!
+//! Downstream consumers such as analytics databases build projections from the events
+//! NATS storage publishes. When a projection's schema changes it has to be rebuilt from
+//! history; instead of operators creating consumers on the stream by hand,
+//! [`NATSStorage::replay_events`](super::nats_storage::NATSStorage::replay_events) replays
+//! the messages on a subject filter published in a time range, at no more than the
+//! requested number of events per second so a rebuild does not starve live traffic. The
+//! GraphQL `eventReplay` subscription streams a replay to clients.
+//!
+//! A replay covers the messages stored when it starts and then ends; events published
+//! while it runs reach live subscribers as usual. At most [`MAX_CONCURRENT_REPLAYS`]
+//! replays run at once.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::{CircuitBreakerError, Result};
+
+/// Events replayed per second when a request does not say
+pub const DEFAULT_REPLAY_RATE: u32 = 1000;
+
+/// Most events per second a replay may request
+pub const MAX_REPLAY_RATE: u32 = 10_000;
+
+/// Replays that may run at once
+pub const MAX_CONCURRENT_REPLAYS: usize = 4;
+
+/// Subjects of the global stream; replays cannot reach outside it
+const REPLAYABLE_SUBJECT_PREFIX: &str = "cb.workflows.";
+
+lazy_static::lazy_static! {
+    static ref REPLAY_SLOTS: Arc<Semaphore> = Arc::new(Semaphore::new(MAX_CONCURRENT_REPLAYS));
+}
+
+/// Which stored events to replay, and how fast
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventReplayRequest {
+    /// Subject filter such as `cb.workflows.*.events.>`
+    pub subject: String,
+    pub from: DateTime<Utc>,
+    /// End of the range; when unset, the time the replay starts
+    pub to: Option<DateTime<Utc>>,
+    pub max_events_per_second: u32,
+}
+
+impl EventReplayRequest {
+    /// Replay the events on `subject` published since `from`, at the default rate
+    pub fn new(subject: impl Into<String>, from: DateTime<Utc>) -> Self {
+        Self {
+            subject: subject.into(),
+            from,
+            to: None,
+            max_events_per_second: DEFAULT_REPLAY_RATE,
+        }
+    }
+
+    /// Stop at events published after `to`
+    pub fn to(mut self, to: DateTime<Utc>) -> Self {
+        self.to = Some(to);
+        self
+    }
+
+    /// Replay at most `rate` events per second
+    pub fn max_events_per_second(mut self, rate: u32) -> Self {
+        self.max_events_per_second = rate;
+        self
+    }
+
+    /// Check the subject filter, time range and rate
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |message: String| Err(CircuitBreakerError::InvalidInput(message));
+
+        let tokens: Vec<&str> = self.subject.split('.').collect();
+        let malformed = tokens.iter().enumerate().any(|(index, token)| {
+            token.is_empty()
+                || token.chars().any(char::is_whitespace)
+                || (token.contains('>') && (*token != ">" || index + 1 != tokens.len()))
+                || (token.contains('*') && *token != "*")
+        });
+        if malformed || !self.subject.starts_with(REPLAYABLE_SUBJECT_PREFIX) {
+            return invalid(format!(
+                "Replay subject '{}' must be a subject filter under '{}>'",
+                self.subject, REPLAYABLE_SUBJECT_PREFIX
+            ));
+        }
+        if let Some(to) = self.to {
+            if to < self.from {
+                return invalid("Replay 'to' must not be before 'from'".to_string());
+            }
+        }
+        if self.max_events_per_second == 0 || self.max_events_per_second > MAX_REPLAY_RATE {
+            return invalid(format!(
+                "Replay rate must be between 1 and {} events per second",
+                MAX_REPLAY_RATE
+            ));
+        }
+        Ok(())
+    }
+
+    /// Time between two replayed events
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(1) / self.max_events_per_second.max(1)
+    }
+}
+
+/// A stored event as replayed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayedEvent {
+    /// Position in the stream, for resuming an interrupted rebuild
+    pub sequence: u64,
+    pub subject: String,
+    pub published_at: DateTime<Utc>,
+    /// The event's JSON payload; payloads that are not JSON are replayed as strings
+    pub payload: serde_json::Value,
+}
+
+impl ReplayedEvent {
+    pub fn new(
+        sequence: u64,
+        subject: impl Into<String>,
+        published_at: DateTime<Utc>,
+        payload: &[u8],
+    ) -> Self {
+        Self {
+            sequence,
+            subject: subject.into(),
+            published_at,
+            payload: serde_json::from_slice(payload).unwrap_or_else(|_| {
+                serde_json::Value::String(String::from_utf8_lossy(payload).into_owned())
+            }),
+        }
+    }
+}
+
+/// Events of a running replay
+pub type EventReplayStream = futures::stream::BoxStream<'static, Result<ReplayedEvent>>;
+
+/// One of the [`MAX_CONCURRENT_REPLAYS`] replay slots, released when dropped
+#[derive(Debug)]
+pub struct ReplaySlot {
+    _permit: OwnedSemaphorePermit,
+}
+
+impl ReplaySlot {
+    /// Take a slot, failing when every slot is held by a running replay
+    pub fn acquire() -> Result<Self> {
+        REPLAY_SLOTS
+            .clone()
+            .try_acquire_owned()
+            .map(|permit| Self { _permit: permit })
+            .map_err(|_| {
+                CircuitBreakerError::Storage(anyhow::anyhow!(
+                    "{} event replays are already running",
+                    MAX_CONCURRENT_REPLAYS
+                ))
+            })
+    }
+}
+
+/// Convert a JetStream timestamp
+pub(crate) fn published_at(published: time::OffsetDateTime) -> DateTime<Utc> {
+    DateTime::from_timestamp(published.unix_timestamp(), published.nanosecond()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_replay_request() {
+        let from = Utc::now() - chrono::Duration::days(1);
+        assert!(EventReplayRequest::new("cb.workflows.*.events.>", from)
+            .validate()
+            .is_ok());
+        assert!(
+            EventReplayRequest::new("cb.workflows.w1.events.lifecycle", from)
+                .to(Utc::now())
+                .max_events_per_second(MAX_REPLAY_RATE)
+                .validate()
+                .is_ok()
+        );
+
+        for subject in [
+            "cb.>",
+            "other.workflows.>",
+            "cb.workflows.>.events",
+            "cb.workflows..events",
+            "cb.workflows.w*.events",
+            "cb.workflows.a b",
+        ] {
+            assert!(
+                EventReplayRequest::new(subject, from).validate().is_err(),
+                "{}",
+                subject
+            );
+        }
+        assert!(EventReplayRequest::new("cb.workflows.>", from)
+            .to(from - chrono::Duration::seconds(1))
+            .validate()
+            .is_err());
+        assert!(EventReplayRequest::new("cb.workflows.>", from)
+            .max_events_per_second(0)
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_replayed_event_payloads() {
+        let now = published_at(time::OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap());
+        assert_eq!(now.timestamp(), 1_700_000_000);
+
+        let event = ReplayedEvent::new(7, "cb.workflows.w1.events.lifecycle", now, br#"{"a":1}"#);
+        assert_eq!(event.payload, serde_json::json!({"a": 1}));
+        let event = ReplayedEvent::new(8, "cb.workflows.w1.events.lifecycle", now, b"plain");
+        assert_eq!(event.payload, "plain");
+
+        let request = EventReplayRequest::new("cb.workflows.>", now).max_events_per_second(250);
+        assert_eq!(request.interval(), Duration::from_millis(4));
+    }
+
+    #[test]
+    fn test_replay_slots_are_bounded() {
+        let slots: Vec<ReplaySlot> = (0..MAX_CONCURRENT_REPLAYS)
+            .map(|_| ReplaySlot::acquire().unwrap())
+            .collect();
+        assert!(ReplaySlot::acquire().is_err());
+        drop(slots);
+        assert!(ReplaySlot::acquire().is_ok());
+    }
+}
@@ -12,9 +12,13 @@ use tokio::sync::broadcast;
 use tracing::debug;
 use uuid::Uuid;
 
-use crate::models::{ActivityId, EventType, Resource, StateId, TriggerEvent};
+use crate::models::{ActivityId, EventType, LifecycleStatus, Resource, StateId, TriggerEvent};
 use crate::Result;
 
+lazy_static::lazy_static! {
+    static ref GLOBAL: EventBus = EventBus::new();
+}
+
 /// Event bus for publishing and subscribing to workflow events
 pub struct EventBus {
     sender: broadcast::Sender<TriggerEvent>,
@@ -28,6 +32,11 @@ impl EventBus {
         Self { sender }
     }
 
+    /// The process-wide bus engine-driven events are published on
+    pub fn global() -> Self {
+        GLOBAL.clone()
+    }
+
     /// Publish an event to all subscribers
     pub async fn publish(&self, event: TriggerEvent) -> Result<()> {
         // Send to broadcast channel (for any future subscribers)
@@ -94,6 +103,75 @@ impl EventBus {
         self.publish(event).await
     }
 
+    /// Emit an SLA breached event for a resource overstaying its state
+    pub async fn emit_sla_breached(
+        &self,
+        resource: &Resource,
+        breach: serde_json::Value,
+    ) -> Result<()> {
+        let event = TriggerEvent {
+            id: Uuid::new_v4(),
+            event_type: EventType::SlaBreached {
+                place: Some(StateId::from(resource.current_state())),
+            },
+            workflow_id: resource.workflow_id.clone(),
+            token_id: Some(resource.id),
+            data: breach,
+            metadata: resource.metadata.clone(),
+            timestamp: chrono::Utc::now(),
+        };
+
+        self.publish(event).await
+    }
+
+    /// Emit a paused, resumed or cancelled event for a resource whose lifecycle changed
+    pub async fn emit_lifecycle_changed(
+        &self,
+        resource: &Resource,
+        status: LifecycleStatus,
+    ) -> Result<()> {
+        let place = Some(StateId::from(resource.current_state()));
+        let event_type = match status {
+            LifecycleStatus::Active => EventType::TokenResumed { place },
+            LifecycleStatus::Paused => EventType::TokenPaused { place },
+            LifecycleStatus::Cancelled => EventType::TokenCancelled { place },
+        };
+        let event = TriggerEvent {
+            id: Uuid::new_v4(),
+            event_type,
+            workflow_id: resource.workflow_id.clone(),
+            token_id: Some(resource.id),
+            data: serde_json::json!(resource.lifecycle()),
+            metadata: resource.metadata.clone(),
+            timestamp: chrono::Utc::now(),
+        };
+
+        self.publish(event).await
+    }
+
+    /// Emit a signal received event for a resource a signal was delivered to
+    pub async fn emit_signal_received(
+        &self,
+        resource: &Resource,
+        signal: &str,
+        payload: serde_json::Value,
+    ) -> Result<()> {
+        let event = TriggerEvent {
+            id: Uuid::new_v4(),
+            event_type: EventType::SignalReceived {
+                place: Some(StateId::from(resource.current_state())),
+                signal: Some(signal.to_string()),
+            },
+            workflow_id: resource.workflow_id.clone(),
+            token_id: Some(resource.id),
+            data: payload,
+            metadata: resource.metadata.clone(),
+            timestamp: chrono::Utc::now(),
+        };
+
+        self.publish(event).await
+    }
+
     /// Emit a workflow created event
     pub async fn emit_workflow_created(&self, workflow_id: &str) -> Result<()> {
         let event = TriggerEvent {
@@ -184,6 +262,7 @@ impl ResourceEvents for Resource {
         activity_id: ActivityId,
         event_bus: &EventBus,
     ) -> Result<()> {
+        super::lifecycle::ensure_active(self)?;
         let old_state = StateId::from(self.current_state());
         self.execute_activity(new_state, activity_id.clone());
         event_bus
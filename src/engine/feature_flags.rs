@@ -0,0 +1,177 @@
+// Feature flag store
+// Global and workflow-scoped flags toggled at runtime and evaluated per resource
+
+//! # Feature Flags
+//!
+//! Workflow definitions are deployed as a whole; switching a single path on or off
+//! should not take a redeploy. [`FeatureFlags`] holds the [`FeatureFlag`]s in effect:
+//! the process-wide [`FeatureFlags::global`] store is updated at runtime through the
+//! `setFeatureFlag` and `removeFeatureFlag` GraphQL mutations.
+//!
+//! A flag scoped to a workflow takes precedence over a global flag of the same name for
+//! that workflow's resources. [`FeatureFlags::evaluate_for`] resolves whether every flag
+//! is on for a resource; the [`RulesEngine`](super::RulesEngine) installs those values
+//! while evaluating rules that read flags, and the [`AgentEngine`](super::AgentEngine)
+//! passes them to agent prompt templates.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use crate::models::{FeatureFlag, FlagValues, Resource};
+use crate::{CircuitBreakerError, Result};
+
+lazy_static::lazy_static! {
+    static ref GLOBAL: FeatureFlags = FeatureFlags::new();
+}
+
+/// Flags keyed by workflow (`None` for global flags) and name
+type FlagKey = (Option<String>, String);
+
+/// Shared store of feature flags
+///
+/// Clones read and update the same store.
+#[derive(Debug, Clone, Default)]
+pub struct FeatureFlags {
+    flags: Arc<RwLock<HashMap<FlagKey, FeatureFlag>>>,
+}
+
+impl FeatureFlags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The process-wide store consulted when evaluating rules and prompts
+    pub fn global() -> Self {
+        GLOBAL.clone()
+    }
+
+    /// Add or replace a flag, returning the flag it replaces
+    pub fn set_flag(&self, flag: FeatureFlag) -> Result<Option<FeatureFlag>> {
+        flag.validate().map_err(CircuitBreakerError::InvalidInput)?;
+        Ok(self
+            .flags
+            .write()
+            .unwrap()
+            .insert((flag.workflow_id.clone(), flag.name.clone()), flag))
+    }
+
+    /// Remove the global flag, or the flag scoped to `workflow_id`
+    pub fn remove_flag(&self, name: &str, workflow_id: Option<&str>) -> Option<FeatureFlag> {
+        self.flags
+            .write()
+            .unwrap()
+            .remove(&(workflow_id.map(str::to_string), name.to_string()))
+    }
+
+    /// The global flag, or the flag scoped to `workflow_id`, without falling back
+    pub fn get(&self, name: &str, workflow_id: Option<&str>) -> Option<FeatureFlag> {
+        self.flags
+            .read()
+            .unwrap()
+            .get(&(workflow_id.map(str::to_string), name.to_string()))
+            .cloned()
+    }
+
+    /// The flag that applies to a workflow's resources: its own, else the global one
+    pub fn resolve(&self, name: &str, workflow_id: &str) -> Option<FeatureFlag> {
+        self.get(name, Some(workflow_id))
+            .or_else(|| self.get(name, None))
+    }
+
+    /// Flags ordered by name; with a workflow, the flags applying to its resources
+    pub fn list(&self, workflow_id: Option<&str>) -> Vec<FeatureFlag> {
+        let flags = self.flags.read().unwrap();
+        let mut listed: Vec<FeatureFlag> = match workflow_id {
+            None => flags.values().cloned().collect(),
+            Some(workflow_id) => {
+                let mut applying: HashMap<&str, &FeatureFlag> = HashMap::new();
+                for flag in flags.values() {
+                    match flag.workflow_id.as_deref() {
+                        None => {
+                            applying.entry(&flag.name).or_insert(flag);
+                        }
+                        Some(id) if id == workflow_id => {
+                            applying.insert(&flag.name, flag);
+                        }
+                        Some(_) => {}
+                    }
+                }
+                applying.into_values().cloned().collect()
+            }
+        };
+        listed.sort_by(|a, b| (&a.name, &a.workflow_id).cmp(&(&b.name, &b.workflow_id)));
+        listed
+    }
+
+    /// Whether a flag is on for a resource; unknown flags are off
+    pub fn is_enabled(&self, name: &str, resource: &Resource) -> bool {
+        self.resolve(name, &resource.workflow_id)
+            .is_some_and(|flag| {
+                flag.evaluate(&resource.id.to_string(), &resource.metadata, &resource.data)
+            })
+    }
+
+    /// Whether each flag applying to a resource's workflow is on for it
+    pub fn evaluate_for(&self, resource: &Resource) -> FlagValues {
+        let resource_id = resource.id.to_string();
+        self.list(Some(&resource.workflow_id))
+            .into_iter()
+            .map(|flag| {
+                let on = flag.evaluate(&resource_id, &resource.metadata, &resource.data);
+                (flag.name, on)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Rule, StateId};
+
+    #[test]
+    fn test_workflow_flags_override_global_flags() {
+        let flags = FeatureFlags::new();
+        flags.set_flag(FeatureFlag::new("new_review_path")).unwrap();
+        flags
+            .set_flag(
+                FeatureFlag::new("new_review_path")
+                    .for_workflow("legal")
+                    .enabled(false),
+            )
+            .unwrap();
+        flags
+            .set_flag(
+                FeatureFlag::new("fast_lane")
+                    .for_workflow("legal")
+                    .targeting(Rule::field_equals(
+                        "urgent",
+                        "priority",
+                        serde_json::json!("high"),
+                    )),
+            )
+            .unwrap();
+        assert!(flags.set_flag(FeatureFlag::new("bad name")).is_err());
+
+        let mut resource = Resource::new("legal", StateId::from("draft"));
+        resource.data = serde_json::json!({"priority": "high"});
+        assert_eq!(
+            flags.evaluate_for(&resource),
+            FlagValues::from([
+                ("new_review_path".to_string(), false),
+                ("fast_lane".to_string(), true),
+            ])
+        );
+
+        let other = Resource::new("billing", StateId::from("draft"));
+        assert!(flags.is_enabled("new_review_path", &other));
+        assert!(!flags.is_enabled("fast_lane", &other));
+        assert_eq!(flags.list(None).len(), 3);
+        assert_eq!(flags.list(Some("billing")).len(), 1);
+
+        assert!(flags
+            .remove_flag("new_review_path", Some("legal"))
+            .is_some());
+        assert!(flags.is_enabled("new_review_path", &resource));
+    }
+}
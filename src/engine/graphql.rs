@@ -1,34 +1,1159 @@
 // GraphQL API for the Circuit Breaker engine
 // This provides a GraphQL interface for defining and executing State Managed Workflows
 
-use async_graphql::{Context, Enum, InputObject, Object, Schema, SimpleObject, Subscription, ID};
+use async_graphql::dataloader::DataLoader;
+use async_graphql::extensions::{
+    Extension, ExtensionContext, ExtensionFactory, NextParseQuery, NextPrepareRequest,
+};
+use async_graphql::parser::types::{
+    DocumentOperations, ExecutableDocument, OperationDefinition, OperationType, Selection,
+};
+use async_graphql::{
+    ComplexObject, Context, Enum, ErrorExtensions, InputObject, Object, Pos, Positioned, Schema,
+    SchemaBuilder, SimpleObject, Subscription, ID,
+};
 use chrono::Utc;
 use serde_json;
+use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 
+use crate::api_keys::{ApiKey, ApiKeyRejection, ApiKeyScope, ApiKeys};
+use crate::audit::AuthSurface;
+use crate::auth_throttle::AuthThrottle;
+use crate::engine::dataloaders::{ResourceExecutionsLoader, WorkflowLoader};
+use crate::engine::event_replay::EventReplayRequest;
 use crate::engine::rules::StoredRule;
+use crate::engine::service_accounts::{
+    ServiceAccounts, ServiceOperation, ServicePrincipal, SERVICE_TOKEN_PREFIX,
+};
 use crate::engine::storage::WorkflowStorage;
-use crate::engine::{AgentEngine, AgentStorage};
+use crate::engine::{AgentEngine, AgentStorage, EventBus, StreamDelivery, StreamItem};
+use crate::llm::RoutingTrace;
 use crate::models::{
-    ActivityDefinition, ActivityId, AgentDefinition, AgentExecution, AgentExecutionStatus, AgentId,
-    AgentPrompts, AgentRetryConfig, HistoryEvent, LLMConfig, LLMProvider, Resource,
-    ResourceMetadata, Rule, RuleCondition, StateAgentConfig, StateAgentSchedule, StateId,
-    WorkflowDefinition,
+    ActivityDefinition, ActivityId, ActivityRetryPolicy, ActivityRoute, ActivityTimer,
+    AgentDefinition, AgentExecution, AgentExecutionStatus, AgentId, AgentPrompts, AgentRetryConfig,
+    CapacityQueueOrder, Gateway, HistoryEvent, LLMConfig, LLMProvider, MetadataSchema, Resource,
+    ResourceMetadata, RetryBackoff, Rule, RuleCondition, SchemaEnforcement, StateAgentConfig,
+    StateAgentSchedule, StateCapacity, StateId, StateSla, TriggerMapping, TriggerSource,
+    WebhookTrigger, WorkflowDefinition, WorkflowDiagnostic, WorkflowProvenance, WorkflowTemplate,
 };
+use crate::rbac::{graphql_permission, RoleAssignments, Subject};
+use crate::{ErrorCode, MaintenanceMode};
+
+lazy_static::lazy_static! {
+    /// Rules engine used for bulk evaluation and capacity checks when the schema has none of its own
+    static ref DEFAULT_RULES_ENGINE: std::sync::Arc<crate::engine::RulesEngine> =
+        std::sync::Arc::new(crate::engine::RulesEngine::with_common_rules());
+}
+
+/// The schema's rules engine, or the default one when it has none
+fn rules_engine(ctx: &Context<'_>) -> std::sync::Arc<crate::engine::RulesEngine> {
+    ctx.data_opt::<std::sync::Arc<crate::engine::RulesEngine>>()
+        .cloned()
+        .unwrap_or_else(|| DEFAULT_RULES_ENGINE.clone())
+}
+
+/// State executing `activity_id` from `state` moves `resource` to, after its routes;
+/// `None` when the activity cannot run from `state`
+fn routed_target_state<'w>(
+    ctx: &Context<'_>,
+    workflow: &'w WorkflowDefinition,
+    resource: &Resource,
+    state: &StateId,
+    activity_id: &ActivityId,
+) -> Option<&'w StateId> {
+    workflow
+        .activities
+        .iter()
+        .find(|a| a.id == *activity_id && a.can_execute_from(state))
+        .map(|activity| rules_engine(ctx).target_state(resource, activity))
+}
+
+/// Refuse to execute a human task directly; it is completed by approving or rejecting it
+fn check_not_manual(
+    workflow: &WorkflowDefinition,
+    activity_id: &ActivityId,
+) -> async_graphql::Result<()> {
+    if workflow
+        .activities
+        .iter()
+        .any(|a| a.id == *activity_id && a.is_manual())
+    {
+        return Err(coded_error(
+            ErrorCode::InvalidStateTransition,
+            format!(
+                "Activity '{}' is a human task; approve or reject it instead",
+                activity_id.as_str()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// The storage engine operations run against: NATS when configured, the schema's
+/// workflow storage otherwise
+fn engine_storage<'a>(ctx: &Context<'a>) -> async_graphql::Result<&'a dyn WorkflowStorage> {
+    match ctx.data::<std::sync::Arc<crate::engine::nats_storage::NATSStorage>>() {
+        Ok(nats_storage) => Ok(nats_storage.as_ref()),
+        Err(_) => Ok(ctx.data::<Box<dyn WorkflowStorage>>()?.as_ref()),
+    }
+}
+
+/// Parse a resource ID and check the caller may execute the activities of its workflow
+async fn authorize_resource(
+    ctx: &Context<'_>,
+    storage: &dyn WorkflowStorage,
+    resource_id: &str,
+) -> async_graphql::Result<Uuid> {
+    let resource_id = resource_id
+        .parse::<Uuid>()
+        .map_err(|_| coded_error(ErrorCode::InvalidInput, "Invalid resource ID format"))?;
+    let resource = storage
+        .get_resource(&resource_id)
+        .await?
+        .ok_or_else(|| coded_error(ErrorCode::ResourceNotFound, "Resource not found"))?;
+    authorize_service(
+        ctx,
+        &resource.workflow_id,
+        ServiceOperation::ExecuteActivities,
+    )?;
+    Ok(resource_id)
+}
+
+/// GraphQL error for a failed human task operation
+fn human_task_error(error: crate::engine::HumanTaskError) -> async_graphql::Error {
+    coded_error(error.code(), error.to_string())
+}
+
+/// Refuse to execute activities on a paused or cancelled resource
+fn check_active(resource: &Resource) -> async_graphql::Result<()> {
+    crate::engine::ensure_active(resource).map_err(|e| coded_error(e.code(), e.to_string()))
+}
+
+fn lifecycle_error(error: crate::CircuitBreakerError) -> async_graphql::Error {
+    match error {
+        crate::CircuitBreakerError::NotFound(_) => {
+            coded_error(ErrorCode::ResourceNotFound, "Resource not found")
+        }
+        e => coded_error(e.code(), e.to_string()),
+    }
+}
+
+/// Refuse to move a resource into a state that is at capacity; the rules engine queues
+/// it and lets it in once its turn comes
+async fn check_state_capacity(
+    ctx: &Context<'_>,
+    storage: &dyn WorkflowStorage,
+    workflow: &WorkflowDefinition,
+    resource: &Resource,
+    activity_id: &ActivityId,
+) -> async_graphql::Result<()> {
+    let Some(activity) = workflow.activities.iter().find(|a| {
+        a.id == *activity_id && a.can_execute_on(resource) && a.branch_to_move(resource).is_none()
+    }) else {
+        return Ok(());
+    };
+    let to_state = rules_engine(ctx).target_state(resource, activity);
+    let Some(capacity) = workflow.state_capacity(to_state) else {
+        return Ok(());
+    };
+
+    let occupancy = storage
+        .count_resources_by_state(&workflow.id)
+        .await
+        .map_err(|e| {
+            coded_error(
+                ErrorCode::StorageError,
+                format!("Failed to count resources: {}", e),
+            )
+        })?;
+    match rules_engine(ctx).check_capacity(resource, activity, workflow, &occupancy) {
+        crate::engine::CapacityCheck::Admitted => Ok(()),
+        crate::engine::CapacityCheck::Waiting { ahead, occupancy, .. } => Err(coded_error(
+            ErrorCode::RuleValidationFailed,
+            format!(
+                "State '{}' is at capacity ({} of {} resources); the resource is queued with {} ahead of it",
+                to_state.as_str(),
+                occupancy,
+                capacity.max_resources,
+                ahead
+            ),
+        )),
+    }
+}
+
+/// Execute `activity_id` through the parallel engine when it splits or joins, or the
+/// resource has open branches; ordinary activities return `None` and run as before
+///
+/// `expected_state` is the target state a caller named, which must be the one the
+/// activity routes the resource to.
+async fn execute_parallel(
+    ctx: &Context<'_>,
+    storage: &dyn WorkflowStorage,
+    workflow: &WorkflowDefinition,
+    resource: &Resource,
+    activity_id: &ActivityId,
+    expected_state: Option<&StateId>,
+) -> async_graphql::Result<Option<Resource>> {
+    let Some(activity) = workflow.activities.iter().find(|a| a.id == *activity_id) else {
+        return Ok(None);
+    };
+    if activity.gateway.is_none() && !resource.is_split() {
+        return Ok(None);
+    }
+    if expected_state.is_some_and(|state| state != activity.target_state(resource)) {
+        return Err(coded_error(
+            ErrorCode::InvalidStateTransition,
+            "Invalid activity",
+        ));
+    }
+
+    check_state_capacity(ctx, storage, workflow, resource, activity_id).await?;
+    let mut resource = resource.clone();
+    crate::engine::parallel::execute(&mut resource, activity)
+        .map_err(|e| coded_error(e.code(), e.to_string()))?;
+    let updated = storage.update_resource(resource).await.map_err(|e| {
+        coded_error(
+            ErrorCode::StorageError,
+            format!("Failed to update resource: {}", e),
+        )
+    })?;
+    rules_engine(ctx).capacity_queues().withdraw(&updated.id);
+    Ok(Some(updated))
+}
+
+/// Most items one batch mutation takes
+const MAX_BATCH_SIZE: usize = 1000;
+
+/// A new resource of `workflow` as described by `input`, with its metadata checked
+fn build_resource(
+    workflow: &WorkflowDefinition,
+    input: ResourceCreateInput,
+) -> async_graphql::Result<Resource> {
+    let initial_state = input
+        .initial_state
+        .map(StateId::from)
+        .unwrap_or_else(|| workflow.initial_state.clone());
+
+    let mut resource = Resource::new(&input.workflow_id, initial_state);
+    resource.workflow_version = workflow.version;
+
+    // Set data if provided
+    if let Some(data) = input.data {
+        resource.data = data;
+    }
+
+    // Set metadata if provided
+    if let Some(metadata) = input.metadata {
+        if let Some(metadata_obj) = metadata.as_object() {
+            for (key, value) in metadata_obj {
+                resource.set_metadata(key, value.clone());
+            }
+        }
+    }
+    check_confidential_metadata(workflow, &resource.metadata)?;
+    check_metadata_schema(workflow, &resource.metadata)?;
+    Ok(resource)
+}
+
+/// The current definition of `workflow_id`, loaded once per batch
+async fn batch_workflow<'w>(
+    storage: &dyn WorkflowStorage,
+    workflows: &'w mut HashMap<String, WorkflowDefinition>,
+    workflow_id: &str,
+) -> async_graphql::Result<&'w WorkflowDefinition> {
+    if !workflows.contains_key(workflow_id) {
+        let workflow = storage.get_workflow(workflow_id).await?.ok_or_else(|| {
+            coded_error(
+                ErrorCode::WorkflowNotFound,
+                format!("Workflow not found: {}", workflow_id),
+            )
+        })?;
+        workflows.insert(workflow_id.to_string(), workflow);
+    }
+    Ok(&workflows[workflow_id])
+}
+
+/// Check a batch's size, and that an atomic batch can be stored atomically
+fn check_batch(
+    storage: &dyn WorkflowStorage,
+    items: usize,
+    atomic: bool,
+) -> async_graphql::Result<()> {
+    if items > MAX_BATCH_SIZE {
+        return Err(coded_error(
+            ErrorCode::InvalidInput,
+            format!(
+                "A batch takes at most {} items, got {}",
+                MAX_BATCH_SIZE, items
+            ),
+        ));
+    }
+    if atomic && !storage.transactional_batches() {
+        return Err(coded_error(
+            ErrorCode::InvalidInput,
+            "The storage backend cannot store batches atomically",
+        ));
+    }
+    Ok(())
+}
+
+/// Execute the activity of one batch item on a copy of its resource, without storing it
+async fn prepare_batch_activity(
+    ctx: &Context<'_>,
+    storage: &dyn WorkflowStorage,
+    workflows: &mut HashMap<String, WorkflowDefinition>,
+    seen: &mut HashSet<Uuid>,
+    input: ActivityExecuteInput,
+) -> async_graphql::Result<Resource> {
+    let resource_id = input
+        .resource_id
+        .parse::<Uuid>()
+        .map_err(|_| coded_error(ErrorCode::InvalidInput, "Invalid resource ID format"))?;
+    if !seen.insert(resource_id) {
+        return Err(coded_error(
+            ErrorCode::InvalidInput,
+            format!(
+                "Resource {} appears more than once in the batch",
+                resource_id
+            ),
+        ));
+    }
+    let mut resource = storage
+        .get_resource(&resource_id)
+        .await?
+        .ok_or_else(|| coded_error(ErrorCode::ResourceNotFound, "Resource not found"))?;
+    authorize_service(
+        ctx,
+        &resource.workflow_id,
+        ServiceOperation::ExecuteActivities,
+    )?;
+    let workflow = batch_workflow(storage, workflows, &resource.workflow_id).await?;
+
+    let activity_id = ActivityId::from(input.activity_id);
+    check_not_manual(workflow, &activity_id)?;
+    let activity = workflow
+        .activities
+        .iter()
+        .find(|a| a.id == activity_id)
+        .ok_or_else(|| coded_error(ErrorCode::InvalidStateTransition, "Invalid activity"))?;
+
+    if let Some(data) = input.data {
+        resource.data = data;
+    }
+    check_state_capacity(ctx, storage, workflow, &resource, &activity_id).await?;
+    crate::engine::parallel::execute(&mut resource, activity)
+        .map_err(|e| coded_error(e.code(), e.to_string()))?;
+    Ok(resource)
+}
+
+/// Store the prepared items of a batch with `store` and report each item's outcome
+///
+/// `prepared` holds each item's resource or why preparing it failed. An atomic batch
+/// stores nothing once any item failed; otherwise the other items are still stored.
+async fn finish_batch<F, Fut>(
+    prepared: Vec<async_graphql::Result<Resource>>,
+    atomic: bool,
+    store: F,
+) -> BatchResultGQL
+where
+    F: FnOnce(Vec<Resource>) -> Fut,
+    Fut: std::future::Future<Output = crate::Result<Vec<Resource>>>,
+{
+    let first_failure = prepared.iter().position(|item| item.is_err());
+    let mut outcomes = Vec::with_capacity(prepared.len());
+    let mut pending = Vec::new();
+    for (index, item) in prepared.into_iter().enumerate() {
+        match (item, first_failure) {
+            (Ok(_), Some(failed)) if atomic => outcomes.push(Err(coded_error(
+                ErrorCode::InvalidInput,
+                format!("Batch aborted: item {} failed", failed),
+            ))),
+            (Ok(resource), _) => {
+                // Filled in once the batch is stored
+                outcomes.push(Err(coded_error(ErrorCode::Internal, "Item was not stored")));
+                pending.push((index, resource));
+            }
+            (Err(e), _) => outcomes.push(Err(e)),
+        }
+    }
+
+    if !pending.is_empty() {
+        let (indices, resources): (Vec<usize>, Vec<Resource>) = pending.into_iter().unzip();
+        match store(resources).await {
+            Ok(stored) => {
+                for (index, resource) in indices.into_iter().zip(stored) {
+                    outcomes[index] = Ok(resource);
+                }
+            }
+            Err(e) => {
+                for index in indices {
+                    outcomes[index] = Err(coded_error(
+                        ErrorCode::StorageError,
+                        format!("Failed to store batch: {}", e),
+                    ));
+                }
+            }
+        }
+    }
+
+    let items: Vec<BatchItemResultGQL> = outcomes
+        .into_iter()
+        .enumerate()
+        .map(|(index, outcome)| BatchItemResultGQL::new(index, outcome))
+        .collect();
+    let succeeded = items.iter().filter(|item| item.resource.is_some()).count();
+    BatchResultGQL {
+        succeeded: succeeded as i32,
+        failed: (items.len() - succeeded) as i32,
+        atomic,
+        items,
+    }
+}
+
+/// GraphQL error carrying a stable error code in `extensions.code`
+fn coded_error(code: ErrorCode, message: impl Into<String>) -> async_graphql::Error {
+    async_graphql::Error::new(message.into()).extend_with(|_, extensions| {
+        extensions.set("code", code.as_str());
+    })
+}
+
+/// Parse a provider name such as `openai` or `custom-<name>`
+fn parse_provider(name: &str) -> async_graphql::Result<crate::llm::LLMProviderType> {
+    name.parse().map_err(|_| {
+        coded_error(
+            ErrorCode::InvalidInput,
+            format!("Unknown provider: {}", name),
+        )
+    })
+}
+
+/// Schema extension that rejects mutations while maintenance mode is on
+///
+/// Queries and subscriptions keep working. Rejected operations carry
+/// `extensions.code = "MAINTENANCE_MODE"` and `extensions.retryAfter` in seconds.
+pub struct MaintenanceGuard {
+    mode: MaintenanceMode,
+}
+
+impl MaintenanceGuard {
+    pub fn new(mode: MaintenanceMode) -> Self {
+        Self { mode }
+    }
+}
+
+impl ExtensionFactory for MaintenanceGuard {
+    fn create(&self) -> std::sync::Arc<dyn Extension> {
+        std::sync::Arc::new(MaintenanceGuardExtension {
+            mode: self.mode.clone(),
+            operation_name: std::sync::Mutex::new(None),
+        })
+    }
+}
+
+struct MaintenanceGuardExtension {
+    mode: MaintenanceMode,
+    operation_name: std::sync::Mutex<Option<String>>,
+}
+
+#[async_trait::async_trait]
+impl Extension for MaintenanceGuardExtension {
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        request: async_graphql::Request,
+        next: NextPrepareRequest<'_>,
+    ) -> async_graphql::ServerResult<async_graphql::Request> {
+        if let Ok(mut operation_name) = self.operation_name.lock() {
+            *operation_name = request.operation_name.clone();
+        }
+        next.run(ctx, request).await
+    }
+
+    async fn parse_query(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        query: &str,
+        variables: &async_graphql::Variables,
+        next: NextParseQuery<'_>,
+    ) -> async_graphql::ServerResult<ExecutableDocument> {
+        let document = next.run(ctx, query, variables).await?;
+        let status = self.mode.status();
+        if !status.enabled {
+            return Ok(document);
+        }
+
+        match selected_operation(&document, &self.operation_name) {
+            Some(operation) if operation.node.ty == OperationType::Mutation => {
+                Err(coded_error(ErrorCode::MaintenanceMode, status.message())
+                    .extend_with(|_, extensions| {
+                        extensions.set("retryAfter", status.retry_after_secs);
+                    })
+                    .into_server_error(operation.pos))
+            }
+            _ => Ok(document),
+        }
+    }
+}
+
+/// The operation a request runs, as named by the operation name its `prepare_request`
+/// hook recorded; `None` when ambiguous, which validation reports
+fn selected_operation<'a>(
+    document: &'a ExecutableDocument,
+    operation_name: &std::sync::Mutex<Option<String>>,
+) -> Option<&'a Positioned<OperationDefinition>> {
+    let operation_name = operation_name.lock().ok().and_then(|name| name.clone());
+    match (&document.operations, operation_name) {
+        (DocumentOperations::Single(operation), _) => Some(operation),
+        (DocumentOperations::Multiple(operations), Some(name)) => operations.get(name.as_str()),
+        (DocumentOperations::Multiple(operations), None) if operations.len() == 1 => {
+            operations.values().next()
+        }
+        (DocumentOperations::Multiple(_), None) => None,
+    }
+}
+
+/// The service principal a request's `Authorization` header authenticates
+///
+/// Requests without a service token (`Bearer cbsa-...`) run unrestricted; an unknown or
+/// expired token fails with `extensions.code = "AUTHENTICATION_FAILED"`, and the client
+/// `ip` is locked out with `RATE_LIMITED` after repeated failures.
+pub fn authenticate_service_token(
+    authorization: Option<&str>,
+    ip: Option<&str>,
+) -> Result<Option<ServicePrincipal>, async_graphql::ServerError> {
+    let Some(secret) = authorization
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|token| token.starts_with(SERVICE_TOKEN_PREFIX))
+    else {
+        return Ok(None);
+    };
+
+    let throttle = AuthThrottle::global();
+    throttle.check(ip, None).map_err(|lockout| {
+        coded_error(ErrorCode::RateLimited, lockout.message()).into_server_error(Pos::default())
+    })?;
+    match ServiceAccounts::global().authenticate(secret) {
+        Some(principal) => Ok(Some(principal)),
+        None => {
+            throttle.record_failure(
+                AuthSurface::ServiceToken,
+                ip,
+                None,
+                "Invalid or expired service token",
+            );
+            Err(coded_error(
+                ErrorCode::AuthenticationFailed,
+                "Invalid or expired service token",
+            )
+            .into_server_error(Pos::default()))
+        }
+    }
+}
+
+/// The managed API key a request presents, which must allow reading workflows
+///
+/// Requests without one get `None`; whether that is allowed is up to the server's
+/// `api_key_required` setting, see [`api_key_rejection`].
+pub async fn authenticate_api_key(
+    headers: &axum::http::HeaderMap,
+) -> Result<Option<ApiKey>, async_graphql::ServerError> {
+    ApiKeys::global()
+        .authorize(headers, ApiKeyScope::WorkflowsRead)
+        .await
+        .map_err(api_key_rejection)
+}
+
+/// GraphQL error refusing a request's API key
+pub fn api_key_rejection(rejection: ApiKeyRejection) -> async_graphql::ServerError {
+    let retry_after = match &rejection {
+        ApiKeyRejection::RateLimited(secs) => Some(*secs),
+        ApiKeyRejection::LockedOut(lockout) => Some(lockout.retry_after_secs),
+        _ => None,
+    };
+    coded_error(rejection.code(), rejection.message())
+        .extend_with(|_, extensions| {
+            if let Some(secs) = retry_after {
+                extensions.set("retryAfter", secs);
+            }
+        })
+        .into_server_error(Pos::default())
+}
+
+/// Refuse a service account an operation on a workflow outside its scope; requests
+/// without a service token are unrestricted
+fn authorize_service(
+    ctx: &Context<'_>,
+    workflow_id: &str,
+    operation: ServiceOperation,
+) -> async_graphql::Result<()> {
+    match ctx.data_opt::<ServicePrincipal>() {
+        Some(principal) if !principal.account.allows(workflow_id, operation) => Err(coded_error(
+            ErrorCode::PermissionDenied,
+            format!(
+                "Service account '{}' may not perform {:?} on workflow '{}'",
+                principal.account.name, operation, workflow_id
+            ),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// The operation a top-level field performs, for the fields service accounts may use
+fn service_operation(ty: OperationType, field: &str) -> Option<ServiceOperation> {
+    match (ty, field) {
+        (OperationType::Query, "workflow" | "resource" | "resources") => {
+            Some(ServiceOperation::ReadResources)
+        }
+        (
+            OperationType::Mutation,
+            "createResource" | "createWorkflowInstance" | "createResourcesBatch",
+        ) => Some(ServiceOperation::CreateResources),
+        (
+            OperationType::Mutation,
+            "executeActivity" | "executeActivityWithNats" | "executeActivityBatch",
+        ) => Some(ServiceOperation::ExecuteActivities),
+        (OperationType::Mutation, "updateResourceMetadata") => {
+            Some(ServiceOperation::UpdateMetadata)
+        }
+        _ => None,
+    }
+}
+
+/// Schema extension that limits requests made with a service token to what its account
+/// may do
+///
+/// Such requests may only select the top-level fields of operations the account is
+/// allowed: `workflow`, `resource` and `resources` for reading, `createResource`,
+/// `createWorkflowInstance` and `createResourcesBatch` for creating, `executeActivity`,
+/// `executeActivityWithNats` and `executeActivityBatch` for executing activities and
+/// `updateResourceMetadata`. The resolvers of those fields check the workflow involved,
+/// for batches each item's, against the account's scope. Anything else fails with `extensions.code = "PERMISSION_DENIED"`.
+pub struct ServiceAccountGuard;
+
+impl ExtensionFactory for ServiceAccountGuard {
+    fn create(&self) -> std::sync::Arc<dyn Extension> {
+        std::sync::Arc::new(ServiceAccountGuardExtension {
+            operation_name: std::sync::Mutex::new(None),
+        })
+    }
+}
+
+struct ServiceAccountGuardExtension {
+    operation_name: std::sync::Mutex<Option<String>>,
+}
+
+#[async_trait::async_trait]
+impl Extension for ServiceAccountGuardExtension {
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        request: async_graphql::Request,
+        next: NextPrepareRequest<'_>,
+    ) -> async_graphql::ServerResult<async_graphql::Request> {
+        if let Ok(mut operation_name) = self.operation_name.lock() {
+            *operation_name = request.operation_name.clone();
+        }
+        next.run(ctx, request).await
+    }
+
+    async fn parse_query(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        query: &str,
+        variables: &async_graphql::Variables,
+        next: NextParseQuery<'_>,
+    ) -> async_graphql::ServerResult<ExecutableDocument> {
+        let document = next.run(ctx, query, variables).await?;
+        let Some(principal) = ctx.data_opt::<ServicePrincipal>() else {
+            return Ok(document);
+        };
+        let Some(operation) = selected_operation(&document, &self.operation_name) else {
+            return Ok(document);
+        };
+
+        for selection in &operation.node.selection_set.node.items {
+            let Selection::Field(field) = &selection.node else {
+                return Err(coded_error(
+                    ErrorCode::PermissionDenied,
+                    "Service accounts must select top-level fields directly, without fragments",
+                )
+                .into_server_error(selection.pos));
+            };
+            let name = field.node.name.node.as_str();
+            if name.starts_with("__") {
+                continue;
+            }
+            let allowed = service_operation(operation.node.ty, name)
+                .is_some_and(|op| principal.account.operations.contains(&op));
+            if !allowed {
+                return Err(coded_error(
+                    ErrorCode::PermissionDenied,
+                    format!(
+                        "Service account '{}' may not use '{}'",
+                        principal.account.name, name
+                    ),
+                )
+                .into_server_error(field.pos));
+            }
+        }
+        Ok(document)
+    }
+}
+
+/// Schema extension that keeps requests made with a read-only API key from mutating
+///
+/// The GraphQL server attaches the [`ApiKey`] a request presents; mutations need its
+/// `workflows_write` scope and fail with `extensions.code = "PERMISSION_DENIED"`
+/// otherwise. Requests without an API key are left to the server's `api_key_required`
+/// setting.
+pub struct ApiKeyGuard;
+
+impl ExtensionFactory for ApiKeyGuard {
+    fn create(&self) -> std::sync::Arc<dyn Extension> {
+        std::sync::Arc::new(ApiKeyGuardExtension {
+            operation_name: std::sync::Mutex::new(None),
+        })
+    }
+}
+
+struct ApiKeyGuardExtension {
+    operation_name: std::sync::Mutex<Option<String>>,
+}
+
+#[async_trait::async_trait]
+impl Extension for ApiKeyGuardExtension {
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        request: async_graphql::Request,
+        next: NextPrepareRequest<'_>,
+    ) -> async_graphql::ServerResult<async_graphql::Request> {
+        if let Ok(mut operation_name) = self.operation_name.lock() {
+            *operation_name = request.operation_name.clone();
+        }
+        next.run(ctx, request).await
+    }
+
+    async fn parse_query(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        query: &str,
+        variables: &async_graphql::Variables,
+        next: NextParseQuery<'_>,
+    ) -> async_graphql::ServerResult<ExecutableDocument> {
+        let document = next.run(ctx, query, variables).await?;
+        let Some(key) = ctx.data_opt::<ApiKey>() else {
+            return Ok(document);
+        };
+
+        match selected_operation(&document, &self.operation_name) {
+            Some(operation)
+                if operation.node.ty == OperationType::Mutation
+                    && !key.allows(ApiKeyScope::WorkflowsWrite) =>
+            {
+                Err(coded_error(
+                    ErrorCode::PermissionDenied,
+                    ApiKeyRejection::Forbidden(ApiKeyScope::WorkflowsWrite).message(),
+                )
+                .into_server_error(operation.pos))
+            }
+            _ => Ok(document),
+        }
+    }
+}
+
+/// Schema extension that limits requests to what the roles of their principals allow
+///
+/// The API key and the service account a request authenticates with are each checked
+/// against their [role](crate::rbac): every top-level field of the operation must be in
+/// a domain the role reads or, for mutations, writes. Fields outside every domain are
+/// left to admins. Refused requests fail with `extensions.code = "PERMISSION_DENIED"`;
+/// principals without a role are not restricted.
+pub struct RoleGuard;
+
+impl ExtensionFactory for RoleGuard {
+    fn create(&self) -> std::sync::Arc<dyn Extension> {
+        std::sync::Arc::new(RoleGuardExtension {
+            operation_name: std::sync::Mutex::new(None),
+        })
+    }
+}
+
+struct RoleGuardExtension {
+    operation_name: std::sync::Mutex<Option<String>>,
+}
+
+#[async_trait::async_trait]
+impl Extension for RoleGuardExtension {
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        request: async_graphql::Request,
+        next: NextPrepareRequest<'_>,
+    ) -> async_graphql::ServerResult<async_graphql::Request> {
+        if let Ok(mut operation_name) = self.operation_name.lock() {
+            *operation_name = request.operation_name.clone();
+        }
+        next.run(ctx, request).await
+    }
+
+    async fn parse_query(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        query: &str,
+        variables: &async_graphql::Variables,
+        next: NextParseQuery<'_>,
+    ) -> async_graphql::ServerResult<ExecutableDocument> {
+        let document = next.run(ctx, query, variables).await?;
+        let subjects: Vec<Subject> = ctx
+            .data_opt::<ApiKey>()
+            .map(|key| Subject::ApiKey(key.id.clone()))
+            .into_iter()
+            .chain(
+                ctx.data_opt::<ServicePrincipal>()
+                    .map(|principal| Subject::ServiceAccount(principal.account.id.clone())),
+            )
+            .collect();
+        if subjects.is_empty() {
+            return Ok(document);
+        }
+        let Some(operation) = selected_operation(&document, &self.operation_name) else {
+            return Ok(document);
+        };
+
+        let roles = RoleAssignments::global();
+        for selection in &operation.node.selection_set.node.items {
+            let (name, pos) = match &selection.node {
+                Selection::Field(field) => (field.node.name.node.as_str(), field.pos),
+                // Fragments may select anything, so only admins may use them at the top
+                _ => ("", selection.pos),
+            };
+            if name.starts_with("__") {
+                continue;
+            }
+            let permission = graphql_permission(operation.node.ty, name);
+            for subject in &subjects {
+                if let Err(denied) = roles.authorize(subject, permission).await {
+                    return Err(
+                        coded_error(denied.error_code(), denied.message()).into_server_error(pos)
+                    );
+                }
+            }
+        }
+        Ok(document)
+    }
+}
 
 // GraphQL types - these are the API representations of our domain models
 
 #[derive(SimpleObject, Debug, Clone)]
 pub struct WorkflowGQL {
     pub id: ID,
+    /// Version of the definition, bumped by `publishWorkflowVersion`
+    pub version: i32,
     pub name: String,
     pub states: Vec<String>,
     pub activities: Vec<ActivityGQL>,
     pub initial_state: String,
+    /// Most resources each constrained state may hold at once
+    pub state_capacities: Vec<StateCapacityGQL>,
+    /// Longest resources should sit in each state with a service level
+    pub state_slas: Vec<StateSlaGQL>,
+    /// Problems static analysis finds in the definition
+    pub diagnostics: Vec<WorkflowDiagnosticGQL>,
+    /// JSON Schema resource metadata must conform to, for rendering typed forms
+    pub metadata_schema: Option<MetadataSchemaGQL>,
+    /// Metadata fields clients encrypt before sending, opaque to rules
+    pub confidential_metadata: Vec<String>,
+    /// The definition this one was forked from, if any
+    pub forked_from: Option<WorkflowProvenanceGQL>,
     pub created_at: String,
     pub updated_at: String,
 }
 
+#[derive(SimpleObject, Debug, Clone)]
+pub struct WorkflowProvenanceGQL {
+    pub workflow_id: String,
+    pub forked_at: String,
+}
+
+impl From<&WorkflowProvenance> for WorkflowProvenanceGQL {
+    fn from(provenance: &WorkflowProvenance) -> Self {
+        WorkflowProvenanceGQL {
+            workflow_id: provenance.workflow_id.clone(),
+            forked_at: provenance.forked_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct MetadataSchemaGQL {
+    pub schema: serde_json::Value,
+    /// "strict" or "warn"
+    pub enforcement: String,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct StateCapacityGQL {
+    pub state: String,
+    pub max_resources: i32,
+    /// "fifo", "lifo" or "priority"
+    pub queue_order: String,
+    pub priority_field: Option<String>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct WorkflowDiagnosticGQL {
+    /// "error" or "warning"
+    pub severity: String,
+    /// e.g. "unreachable_state" or "deadlock"
+    pub kind: String,
+    pub message: String,
+    pub state: Option<String>,
+    pub activity: Option<String>,
+}
+
+impl From<WorkflowDiagnostic> for WorkflowDiagnosticGQL {
+    fn from(diagnostic: WorkflowDiagnostic) -> Self {
+        WorkflowDiagnosticGQL {
+            severity: if diagnostic.is_error() {
+                "error"
+            } else {
+                "warning"
+            }
+            .to_string(),
+            kind: diagnostic.kind.as_str().to_string(),
+            message: diagnostic.message,
+            state: diagnostic.state.map(|s| s.as_str().to_string()),
+            activity: diagnostic.activity.map(|a| a.as_str().to_string()),
+        }
+    }
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct StateSlaGQL {
+    pub state: String,
+    pub max_secs: i64,
+    pub escalation_activity: Option<String>,
+    pub webhook_url: Option<String>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct WorkflowTemplateGQL {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub parameters: Vec<TemplateParameterGQL>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct TemplateParameterGQL {
+    pub name: String,
+    /// "string", "number", "integer" or "boolean"
+    pub parameter_type: String,
+    pub description: String,
+    pub required: bool,
+    pub default: Option<serde_json::Value>,
+}
+
+/// An inbound webhook endpoint of a workflow; its secret is never returned
+#[derive(SimpleObject, Debug, Clone)]
+pub struct WebhookTriggerGQL {
+    pub id: String,
+    pub workflow_id: String,
+    /// "github", "stripe" or "generic"
+    pub source: String,
+    /// Path senders post webhooks to
+    pub path: String,
+    pub signature_header: String,
+    pub event_pointer: Option<String>,
+    /// The trigger's mappings, as registered
+    pub mappings: serde_json::Value,
+    pub created_at: String,
+}
+
+impl From<&WebhookTrigger> for WebhookTriggerGQL {
+    fn from(trigger: &WebhookTrigger) -> Self {
+        WebhookTriggerGQL {
+            id: trigger.id.clone(),
+            workflow_id: trigger.workflow_id.clone(),
+            source: trigger.source.as_str().to_string(),
+            path: format!("/v1/triggers/{}", trigger.id),
+            signature_header: trigger.signature_header().to_string(),
+            event_pointer: trigger.event_pointer.clone(),
+            mappings: serde_json::to_value(&trigger.mappings).unwrap_or_default(),
+            created_at: trigger.created_at.to_rfc3339(),
+        }
+    }
+}
+
+impl From<&WorkflowTemplate> for WorkflowTemplateGQL {
+    fn from(template: &WorkflowTemplate) -> Self {
+        WorkflowTemplateGQL {
+            id: template.id.clone(),
+            name: template.name.clone(),
+            description: template.description.clone(),
+            parameters: template
+                .parameters
+                .iter()
+                .map(|parameter| TemplateParameterGQL {
+                    name: parameter.name.clone(),
+                    parameter_type: parameter.parameter_type.as_str().to_string(),
+                    description: parameter.description.clone(),
+                    required: parameter.is_required(),
+                    default: parameter.default.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Diagram source of a workflow
+#[derive(SimpleObject, Debug, Clone)]
+pub struct WorkflowDiagramGQL {
+    /// Graphviz DOT
+    pub dot: String,
+    /// Mermaid stateDiagram-v2
+    pub mermaid: String,
+}
+
+/// How a workflow's resources keep to the SLA of one state
+#[derive(SimpleObject, Debug, Clone)]
+pub struct StateSlaMetricsGQL {
+    pub state: String,
+    pub max_secs: i64,
+    /// Resources in the state now
+    pub in_state: i32,
+    /// Resources in the state now and past its SLA
+    pub breaching: i32,
+    pub longest_current_secs: Option<i64>,
+    /// Stays in the state that have ended
+    pub completed_stays: i32,
+    /// Completed stays that took longer than the SLA
+    pub completed_breaches: i32,
+    pub average_stay_secs: Option<f64>,
+}
+
+impl From<crate::engine::StateSlaMetrics> for StateSlaMetricsGQL {
+    fn from(metrics: crate::engine::StateSlaMetrics) -> Self {
+        StateSlaMetricsGQL {
+            state: metrics.state.as_str().to_string(),
+            max_secs: metrics.max_secs as i64,
+            in_state: metrics.in_state as i32,
+            breaching: metrics.breaching as i32,
+            longest_current_secs: metrics.longest_current_secs,
+            completed_stays: metrics.completed_stays as i32,
+            completed_breaches: metrics.completed_breaches as i32,
+            average_stay_secs: metrics.average_stay_secs,
+        }
+    }
+}
+
+/// Throughput and time-in-state figures of a workflow
+#[derive(SimpleObject, Debug, Clone)]
+pub struct WorkflowMetricsGQL {
+    pub workflow_id: String,
+    pub resources_created: i64,
+    /// Resources neither finished nor cancelled
+    pub resources_active: i64,
+    /// Resources in a terminal state
+    pub resources_completed: i64,
+    pub resources_cancelled: i64,
+    /// Resources that reached a terminal state within the last hour
+    pub completed_last_hour: i64,
+    pub activities_fired: i64,
+    pub activities_fired_last_hour: i64,
+    pub activities: Vec<ActivityCountGQL>,
+    /// Every state of the workflow, in definition order
+    pub time_in_state: Vec<StateTimeMetricsGQL>,
+    pub terminal_states: Vec<TerminalStateCountGQL>,
+    pub collected_at: String,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct ActivityCountGQL {
+    pub activity_id: String,
+    pub total: i64,
+    pub last_hour: i64,
+}
+
+/// How long resources stay in one state, from completed stays
+#[derive(SimpleObject, Debug, Clone)]
+pub struct StateTimeMetricsGQL {
+    pub state: String,
+    /// Resources in the state now
+    pub in_state: i32,
+    pub completed_stays: i32,
+    pub mean_secs: Option<f64>,
+    pub p50_secs: Option<f64>,
+    pub p90_secs: Option<f64>,
+    pub p99_secs: Option<f64>,
+    pub max_secs: Option<f64>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct TerminalStateCountGQL {
+    pub state: String,
+    pub resources: i64,
+}
+
+impl From<crate::engine::WorkflowMetricsSnapshot> for WorkflowMetricsGQL {
+    fn from(metrics: crate::engine::WorkflowMetricsSnapshot) -> Self {
+        WorkflowMetricsGQL {
+            workflow_id: metrics.workflow_id,
+            resources_created: metrics.resources_created as i64,
+            resources_active: metrics.resources_active as i64,
+            resources_completed: metrics.resources_completed as i64,
+            resources_cancelled: metrics.resources_cancelled as i64,
+            completed_last_hour: metrics.completed_last_hour as i64,
+            activities_fired: metrics.activities_fired as i64,
+            activities_fired_last_hour: metrics.activities_fired_last_hour as i64,
+            activities: metrics
+                .activities
+                .into_iter()
+                .map(|count| ActivityCountGQL {
+                    activity_id: count.activity.as_str().to_string(),
+                    total: count.total as i64,
+                    last_hour: count.last_hour as i64,
+                })
+                .collect(),
+            time_in_state: metrics
+                .time_in_state
+                .into_iter()
+                .map(|state| StateTimeMetricsGQL {
+                    state: state.state.as_str().to_string(),
+                    in_state: state.in_state as i32,
+                    completed_stays: state.completed_stays as i32,
+                    mean_secs: state.mean_secs,
+                    p50_secs: state.p50_secs,
+                    p90_secs: state.p90_secs,
+                    p99_secs: state.p99_secs,
+                    max_secs: state.max_secs,
+                })
+                .collect(),
+            terminal_states: metrics
+                .terminal_states
+                .into_iter()
+                .map(|(state, resources)| TerminalStateCountGQL {
+                    state,
+                    resources: resources as i64,
+                })
+                .collect(),
+            collected_at: metrics.collected_at.to_rfc3339(),
+        }
+    }
+}
+
+/// A resource waiting for room in a full state
+#[derive(SimpleObject, Debug, Clone)]
+pub struct QueuedResourceGQL {
+    pub resource_id: String,
+    pub priority: f64,
+    pub enqueued_at: String,
+}
+
 #[derive(SimpleObject, Debug, Clone)]
 pub struct ActivityGQL {
     pub id: String,
@@ -37,18 +1162,147 @@ pub struct ActivityGQL {
     pub to_state: String,
     pub conditions: Vec<String>,
     pub description: Option<String>,
+    pub timer: Option<ActivityTimerGQL>,
+    pub compensation: Option<String>,
+    pub manual: Option<ManualTaskGQL>,
+    /// States an AND-split starts a branch in
+    pub split_into: Option<Vec<String>>,
+    /// Whether the activity is an AND-join waiting for all branches
+    pub join: bool,
+    /// How the activity's agent or function execution is retried
+    pub retry: Option<ActivityRetryPolicyGQL>,
+    /// Conditional targets tried in order before `to_state`
+    pub routes: Vec<ActivityRouteGQL>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct ActivityRouteGQL {
+    /// Expression over the resource's metadata and data
+    pub when: String,
+    pub to_state: String,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct ActivityRetryPolicyGQL {
+    pub max_attempts: i32,
+    pub delay_secs: i64,
+    /// "fixed", "linear" or "exponential"
+    pub backoff: String,
+    /// Growth per retry of exponential backoff
+    pub multiplier: Option<f64>,
+    pub max_delay_secs: Option<i64>,
+    pub retry_on: Vec<String>,
+}
+
+impl From<&ActivityRetryPolicy> for ActivityRetryPolicyGQL {
+    fn from(policy: &ActivityRetryPolicy) -> Self {
+        let (backoff, multiplier) = match policy.backoff {
+            RetryBackoff::Fixed => ("fixed", None),
+            RetryBackoff::Linear => ("linear", None),
+            RetryBackoff::Exponential { multiplier } => ("exponential", Some(multiplier)),
+        };
+        ActivityRetryPolicyGQL {
+            max_attempts: policy.max_attempts as i32,
+            delay_secs: policy.delay_secs as i64,
+            backoff: backoff.to_string(),
+            multiplier,
+            max_delay_secs: policy.max_delay_secs.map(|secs| secs as i64),
+            retry_on: policy.retry_on.clone(),
+        }
+    }
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct ManualTaskGQL {
+    pub instructions: Option<String>,
+    pub reject_to: Option<String>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct HumanTaskGQL {
+    pub resource_id: String,
+    pub workflow_id: String,
+    pub activity_id: String,
+    pub state: String,
+    pub instructions: Option<String>,
+    pub waiting_since: String,
+    /// Who claimed the task; unclaimed tasks can be decided by anyone
+    pub claimed_by: Option<String>,
+    pub claimed_at: Option<String>,
+}
+
+impl From<crate::engine::HumanTask> for HumanTaskGQL {
+    fn from(task: crate::engine::HumanTask) -> Self {
+        HumanTaskGQL {
+            resource_id: task.resource_id.to_string(),
+            workflow_id: task.workflow_id,
+            activity_id: task.activity_id.as_str().to_string(),
+            state: task.state.as_str().to_string(),
+            instructions: task.instructions,
+            waiting_since: task.waiting_since.to_rfc3339(),
+            claimed_by: task.claim.as_ref().map(|claim| claim.actor.clone()),
+            claimed_at: task.claim.map(|claim| claim.claimed_at.to_rfc3339()),
+        }
+    }
+}
+
+/// Timer firing an activity once a resource has sat in a source state long enough
+#[derive(SimpleObject, Debug, Clone)]
+pub struct ActivityTimerGQL {
+    /// "fire_after" or "deadline"
+    pub kind: String,
+    pub secs: i32,
 }
 
 #[derive(SimpleObject, Debug, Clone)]
+#[graphql(complex)]
 pub struct ResourceGQL {
     pub id: ID,
     pub workflow_id: String,
+    /// Version of the workflow the resource was created under or last migrated to
+    pub workflow_version: i32,
     pub state: String,
     pub data: serde_json::Value,
     pub metadata: serde_json::Value,
     pub created_at: String,
     pub updated_at: String,
+    /// Revision of the resource, bumped on every update
+    pub version: i64,
     pub history: Vec<HistoryEventGQL>,
+    /// Open branches of a resource forked by an AND-split
+    pub branches: Vec<BranchGQL>,
+    /// Why the resource is paused or cancelled; empty while it is active
+    pub lifecycle: Option<ResourceLifecycleGQL>,
+    /// Signals received since the resource entered its current state
+    pub signals: Vec<ResourceSignalGQL>,
+}
+
+/// A named message delivered to a resource
+#[derive(SimpleObject, Debug, Clone)]
+pub struct ResourceSignalGQL {
+    pub name: String,
+    pub payload: serde_json::Value,
+    /// Resource that sent the signal; empty when it came through the API
+    pub sender: Option<String>,
+    pub received_at: String,
+}
+
+/// Who paused or cancelled a resource, when and why
+#[derive(SimpleObject, Debug, Clone)]
+pub struct ResourceLifecycleGQL {
+    /// "paused" or "cancelled"
+    pub status: String,
+    pub reason: Option<String>,
+    pub actor: Option<String>,
+    pub changed_at: String,
+}
+
+/// A concurrent branch of a split resource
+#[derive(SimpleObject, Debug, Clone)]
+pub struct BranchGQL {
+    /// Named after the state the branch started in
+    pub name: String,
+    pub state: String,
 }
 
 #[derive(SimpleObject, Debug, Clone)]
@@ -130,6 +1384,7 @@ pub struct AgentPromptsGQL {
 pub struct StateAgentConfigGQL {
     pub id: String,
     pub state_id: String,
+    pub workflow_id: Option<String>,
     pub agent_id: String,
     pub llm_config: Option<LLMConfigGQL>,
     pub input_mapping: serde_json::Value,
@@ -172,6 +1427,95 @@ pub struct AgentExecutionGQL {
     pub retry_count: i32,
 }
 
+#[derive(SimpleObject, Debug, Clone)]
+pub struct StreamGaugesGQL {
+    pub active_subscribers: i32,
+    pub reaped_total: i32,
+    pub missed_events_total: i32,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct RuleStatsGQL {
+    pub rule_id: String,
+    pub evaluations: i32,
+    pub failures: i32,
+    pub mean_latency_us: f64,
+    pub max_latency_us: f64,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct RuleMetricsGQL {
+    pub activity_evaluations_total: i32,
+    pub rule_evaluations_total: i32,
+    pub evaluations_per_second: f64,
+    pub cache_hits_total: i32,
+    pub cache_misses_total: i32,
+    pub rules: Vec<RuleStatsGQL>,
+    pub most_failing: Vec<RuleStatsGQL>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct StateCountGQL {
+    pub state: String,
+    pub count: i32,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct ExecutionStatusCountGQL {
+    pub status: String,
+    pub count: i32,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct ActivityBulkSummaryGQL {
+    pub activity_id: String,
+    pub available_count: i32,
+    pub blocked_count: i32,
+    pub not_in_source_state_count: i32,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct BulkEvaluationReportGQL {
+    pub workflow_id: String,
+    pub resources_evaluated: i32,
+    pub resources_with_available_activities: i32,
+    pub activities: Vec<ActivityBulkSummaryGQL>,
+    pub duration_ms: i32,
+}
+
+impl From<crate::engine::BulkEvaluationReport> for BulkEvaluationReportGQL {
+    fn from(report: crate::engine::BulkEvaluationReport) -> Self {
+        Self {
+            workflow_id: report.workflow_id,
+            resources_evaluated: report.resources_evaluated as i32,
+            resources_with_available_activities: report.resources_with_available_activities as i32,
+            activities: report
+                .activities
+                .into_iter()
+                .map(|summary| ActivityBulkSummaryGQL {
+                    activity_id: summary.activity_id,
+                    available_count: summary.available_count as i32,
+                    blocked_count: summary.blocked_count as i32,
+                    not_in_source_state_count: summary.not_in_source_state_count as i32,
+                })
+                .collect(),
+            duration_ms: report.duration_ms as i32,
+        }
+    }
+}
+
+impl From<crate::engine::RuleStatsSnapshot> for RuleStatsGQL {
+    fn from(stats: crate::engine::RuleStatsSnapshot) -> Self {
+        Self {
+            rule_id: stats.rule_id,
+            evaluations: stats.evaluations as i32,
+            failures: stats.failures as i32,
+            mean_latency_us: stats.mean_latency_us,
+            max_latency_us: stats.max_latency_us,
+        }
+    }
+}
+
 #[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AgentExecutionStatusGQL {
     Pending,
@@ -238,114 +1582,790 @@ pub struct LLMProviderHealthGQL {
     pub last_error: Option<String>,
 }
 
-#[derive(SimpleObject, Debug, Clone)]
-pub struct LLMRequestGQL {
-    pub id: ID,
-    pub model: String,
-    pub messages: Vec<ChatMessageGQL>,
-    pub temperature: Option<f64>,
-    pub max_tokens: Option<i32>,
-    pub stream: bool,
-    pub user: Option<String>,
-    pub project_id: Option<String>,
+#[derive(SimpleObject, Debug, Clone)]
+pub struct LLMRequestGQL {
+    pub id: ID,
+    pub model: String,
+    pub messages: Vec<ChatMessageGQL>,
+    pub temperature: Option<f64>,
+    pub max_tokens: Option<i32>,
+    pub stream: bool,
+    pub user: Option<String>,
+    pub project_id: Option<String>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct ChatMessageGQL {
+    pub role: String,
+    pub content: String,
+    pub name: Option<String>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct LLMResponseGQL {
+    pub id: String,
+    pub model: String,
+    pub choices: Vec<LLMChoiceGQL>,
+    pub usage: TokenUsageGQL,
+    pub provider: String,
+    pub routing_info: RoutingInfoGQL,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct LLMChoiceGQL {
+    pub index: i32,
+    pub message: ChatMessageGQL,
+    pub finish_reason: Option<String>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct TokenUsageGQL {
+    pub prompt_tokens: i32,
+    pub completion_tokens: i32,
+    pub total_tokens: i32,
+    pub estimated_cost: f64,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct RoutingInfoGQL {
+    pub selected_provider: String,
+    pub routing_strategy: String,
+    pub latency_ms: i32,
+    pub retry_count: i32,
+    pub fallback_used: bool,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct RoutingTraceGQL {
+    pub request_id: String,
+    pub requested_model: String,
+    pub resolved_model: Option<String>,
+    pub strategy: Option<String>,
+    pub tenant_id: Option<String>,
+    pub selected_provider: Option<String>,
+    pub candidates: Vec<RoutingCandidateGQL>,
+    pub exclusions: Vec<RoutingExclusionGQL>,
+    pub attempts: Vec<RoutingAttemptGQL>,
+    pub guardrails: Vec<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct RoutingCandidateGQL {
+    pub provider: String,
+    pub model: String,
+    pub score: Option<f64>,
+    pub selected: bool,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct RoutingExclusionGQL {
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub reason: String,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct RoutingAttemptGQL {
+    pub provider: String,
+    pub model: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct CostInfoGQL {
+    pub request_id: ID,
+    pub provider: String,
+    pub model: String,
+    pub input_tokens: i32,
+    pub output_tokens: i32,
+    pub cost_usd: f64,
+    pub timestamp: String,
+    pub user_id: Option<String>,
+    pub project_id: Option<String>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct BudgetStatusGQL {
+    pub budget_id: String,
+    pub limit: f64,
+    pub used: f64,
+    pub percentage_used: f64,
+    pub is_exhausted: bool,
+    pub is_warning: bool,
+    pub remaining: f64,
+    pub message: String,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct ModelPriceGQL {
+    pub provider: String,
+    pub model: String,
+    pub input_cost_per_token: f64,
+    pub output_cost_per_token: f64,
+    pub updated_at: String,
+}
+
+impl From<crate::llm::ModelPrice> for ModelPriceGQL {
+    fn from(price: crate::llm::ModelPrice) -> Self {
+        Self {
+            provider: price.provider.to_string(),
+            model: price.model,
+            input_cost_per_token: price.input_cost_per_token,
+            output_cost_per_token: price.output_cost_per_token,
+            updated_at: price.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct FeatureFlagGQL {
+    pub name: String,
+    /// Workflow the flag is scoped to; unset for global flags
+    pub workflow_id: Option<String>,
+    pub description: String,
+    pub enabled: bool,
+    pub targeting: Vec<RuleConditionGQL>,
+    pub rollout_percentage: f64,
+    pub updated_at: String,
+}
+
+impl From<crate::models::FeatureFlag> for FeatureFlagGQL {
+    fn from(flag: crate::models::FeatureFlag) -> Self {
+        Self {
+            name: flag.name,
+            workflow_id: flag.workflow_id,
+            description: flag.description,
+            enabled: flag.enabled,
+            targeting: flag
+                .targeting
+                .iter()
+                .map(|rule| RuleConditionGQL::from(&rule.condition))
+                .collect(),
+            rollout_percentage: flag.rollout_percentage,
+            updated_at: flag.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct CostAnalyticsGQL {
+    pub total_cost: f64,
+    pub total_tokens: i32,
+    pub average_cost_per_token: f64,
+    pub provider_breakdown: serde_json::Value,
+    pub model_breakdown: serde_json::Value,
+    pub daily_costs: serde_json::Value,
+    pub period_start: String,
+    pub period_end: String,
+}
+
+// Input types for mutations
+#[derive(InputObject, Debug)]
+pub struct WorkflowDefinitionInput {
+    pub name: String,
+    pub states: Vec<String>,
+    pub activities: Vec<ActivityDefinitionInput>,
+    pub initial_state: String,
+    pub description: Option<String>,
+    /// Most resources states may hold at once; unlisted states are unconstrained
+    pub state_capacities: Option<Vec<StateCapacityInput>>,
+    /// Longest resources should sit in a state, and how breaches are escalated
+    pub state_slas: Option<Vec<StateSlaInput>>,
+    /// JSON Schema resource metadata must conform to
+    pub metadata_schema: Option<MetadataSchemaInput>,
+    /// Metadata fields clients encrypt; rules may not read them
+    pub confidential_metadata: Option<Vec<String>>,
+}
+
+impl WorkflowDefinitionInput {
+    /// The definition the input describes, stored under `id` as its first version
+    fn into_definition(self, id: String) -> async_graphql::Result<WorkflowDefinition> {
+        let activities = self
+            .activities
+            .into_iter()
+            .map(|a| {
+                Ok(ActivityDefinition {
+                    id: ActivityId::from(a.id.as_str()),
+                    from_states: a.from_states.into_iter().map(StateId::from).collect(),
+                    to_state: StateId::from(a.to_state),
+                    conditions: a.conditions,
+                    rules: vec![], // Start with empty rules - can be added later via GraphQL
+                    timer: a.timer.map(ActivityTimerInput::into_timer).transpose()?,
+                    compensation: a.compensation.map(ActivityId::from),
+                    manual: a.manual.map(|task| crate::models::ManualTask {
+                        instructions: task.instructions,
+                        reject_to: task.reject_to.map(StateId::from),
+                    }),
+                    gateway: match (a.split_into, a.join.unwrap_or(false)) {
+                        (Some(_), true) => {
+                            return Err(coded_error(
+                                ErrorCode::InvalidInput,
+                                format!("Activity '{}' cannot both split and join", a.id),
+                            ))
+                        }
+                        (Some(branches), false) => Some(Gateway::Split(
+                            branches.into_iter().map(StateId::from).collect(),
+                        )),
+                        (None, true) => Some(Gateway::Join),
+                        (None, false) => None,
+                    },
+                    retry: a
+                        .retry
+                        .map(ActivityRetryPolicyInput::into_policy)
+                        .transpose()?,
+                    routes: a
+                        .routes
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|route| ActivityRoute::new(route.when, route.to_state))
+                        .collect(),
+                })
+            })
+            .collect::<async_graphql::Result<_>>()?;
+        let state_capacities = self
+            .state_capacities
+            .unwrap_or_default()
+            .into_iter()
+            .map(StateCapacityInput::into_capacity)
+            .collect::<async_graphql::Result<_>>()?;
+        let state_slas = self
+            .state_slas
+            .unwrap_or_default()
+            .into_iter()
+            .map(StateSlaInput::into_sla)
+            .collect::<async_graphql::Result<_>>()?;
+        let metadata_schema = self
+            .metadata_schema
+            .map(MetadataSchemaInput::into_schema)
+            .transpose()?;
+
+        let workflow = WorkflowDefinition {
+            id,
+            version: 1,
+            name: self.name,
+            states: self.states.into_iter().map(StateId::from).collect(),
+            activities,
+            initial_state: StateId::from(self.initial_state),
+            state_capacities,
+            state_slas,
+            metadata_schema,
+            confidential_metadata: self.confidential_metadata.unwrap_or_default(),
+            forked_from: None,
+        };
+
+        // Validate workflow before storing
+        workflow.validate().map_err(|e| {
+            coded_error(ErrorCode::InvalidInput, format!("Invalid workflow: {}", e))
+        })?;
+        check_analysis(&workflow)?;
+        Ok(workflow)
+    }
+}
+
+/// Refuse a definition static analysis finds errors in, and log its warnings
+fn check_analysis(workflow: &WorkflowDefinition) -> async_graphql::Result<()> {
+    let (errors, warnings): (Vec<_>, Vec<_>) = workflow
+        .analyze()
+        .into_iter()
+        .partition(|diagnostic| diagnostic.is_error());
+    if !errors.is_empty() {
+        let messages: Vec<_> = errors.into_iter().map(|d| d.message).collect();
+        return Err(coded_error(
+            ErrorCode::InvalidInput,
+            format!("Invalid workflow: {}", messages.join("; ")),
+        ));
+    }
+    for warning in warnings {
+        tracing::warn!("Workflow '{}': {}", workflow.id, warning.message);
+    }
+    Ok(())
+}
+
+/// Creates a workflow from a template of the catalog
+#[derive(InputObject, Debug)]
+pub struct WorkflowFromTemplateInput {
+    pub template_id: String,
+    /// Parameter values by name, e.g. `{"approval_threshold": 5000}`
+    pub parameters: Option<serde_json::Value>,
+}
+
+/// Registers an inbound webhook endpoint for a workflow
+#[derive(InputObject, Debug)]
+pub struct WebhookTriggerInput {
+    /// ID in the trigger's path; registering an existing ID replaces that trigger
+    pub id: String,
+    pub workflow_id: String,
+    /// "github", "stripe" or "generic"
+    pub source: String,
+    /// Shared secret webhooks are signed with
+    pub secret: String,
+    /// Header holding the signature of generic webhooks, `X-Signature` by default
+    pub signature_header: Option<String>,
+    /// JSON pointer to the event name in generic webhook payloads, e.g. `/event`
+    pub event_pointer: Option<String>,
+    /// Mappings of events onto the workflow, e.g.
+    /// `[{"event": "invoice.paid", "action": {"type": "fire_activity", ...}}]`
+    pub mappings: serde_json::Value,
+}
+
+/// Moves the resources of one workflow version onto the current version
+#[derive(InputObject, Debug)]
+pub struct WorkflowMigrationInput {
+    pub workflow_id: String,
+    pub from_version: i32,
+    pub to_version: i32,
+    /// Old states to new ones; states both versions have map to themselves unless listed
+    pub state_mapping: Option<Vec<StateMappingInput>>,
+    /// Report the moves without storing them
+    pub dry_run: Option<bool>,
+}
+
+#[derive(InputObject, Debug)]
+pub struct StateMappingInput {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct WorkflowMigrationGQL {
+    pub workflow_id: String,
+    pub from_version: i32,
+    pub to_version: i32,
+    pub dry_run: bool,
+    pub resources: Vec<MigratedResourceGQL>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct MigratedResourceGQL {
+    pub resource_id: String,
+    pub from_state: String,
+    pub to_state: String,
+}
+
+impl From<crate::engine::MigrationReport> for WorkflowMigrationGQL {
+    fn from(report: crate::engine::MigrationReport) -> Self {
+        WorkflowMigrationGQL {
+            workflow_id: report.workflow_id,
+            from_version: report.from_version as i32,
+            to_version: report.to_version as i32,
+            dry_run: report.dry_run,
+            resources: report
+                .resources
+                .into_iter()
+                .map(|migrated| MigratedResourceGQL {
+                    resource_id: migrated.resource_id.to_string(),
+                    from_state: migrated.from_state.as_str().to_string(),
+                    to_state: migrated.to_state.as_str().to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// GraphQL error for a failed versioning operation
+fn versioning_error(error: crate::CircuitBreakerError) -> async_graphql::Error {
+    match error {
+        crate::CircuitBreakerError::WorkflowNotFound { id } => coded_error(
+            ErrorCode::WorkflowNotFound,
+            format!("Workflow not found: {}", id),
+        ),
+        crate::CircuitBreakerError::NotFound(what) => {
+            coded_error(ErrorCode::NotFound, format!("{} not found", what))
+        }
+        crate::CircuitBreakerError::InvalidInput(message) => {
+            coded_error(ErrorCode::InvalidInput, message)
+        }
+        e => coded_error(ErrorCode::StorageError, e.to_string()),
+    }
+}
+
+/// A workflow version number passed to GraphQL
+fn workflow_version_number(version: i32) -> async_graphql::Result<u32> {
+    u32::try_from(version)
+        .ok()
+        .filter(|version| *version > 0)
+        .ok_or_else(|| coded_error(ErrorCode::InvalidInput, "Versions start at 1"))
+}
+
+#[derive(InputObject, Debug)]
+pub struct MetadataSchemaInput {
+    pub schema: serde_json::Value,
+    /// "strict" (default) rejects non-conforming metadata, "warn" only logs it
+    pub enforcement: Option<String>,
+}
+
+impl MetadataSchemaInput {
+    fn into_schema(self) -> async_graphql::Result<MetadataSchema> {
+        let enforcement = match self.enforcement.as_deref().unwrap_or("strict") {
+            "strict" => SchemaEnforcement::Strict,
+            "warn" => SchemaEnforcement::Warn,
+            other => {
+                return Err(coded_error(
+                    ErrorCode::InvalidInput,
+                    format!(
+                        "Unknown schema enforcement '{}', expected strict or warn",
+                        other
+                    ),
+                ))
+            }
+        };
+        Ok(MetadataSchema {
+            schema: self.schema,
+            enforcement,
+        })
+    }
+}
+
+fn enforcement_name(enforcement: SchemaEnforcement) -> &'static str {
+    match enforcement {
+        SchemaEnforcement::Strict => "strict",
+        SchemaEnforcement::Warn => "warn",
+    }
+}
+
+/// Check resource metadata against its workflow's schema, if it has one: violations
+/// fail the operation under strict enforcement and are logged under warn
+fn check_metadata_schema(
+    workflow: &WorkflowDefinition,
+    metadata: &ResourceMetadata,
+) -> async_graphql::Result<()> {
+    let Some(metadata_schema) = &workflow.metadata_schema else {
+        return Ok(());
+    };
+    let violations = metadata_schema.violations(metadata);
+    if violations.is_empty() {
+        return Ok(());
+    }
+    let message = format!(
+        "Metadata does not conform to the schema of workflow '{}': {}",
+        workflow.id,
+        violations.join("; ")
+    );
+    match metadata_schema.enforcement {
+        SchemaEnforcement::Strict => Err(coded_error(ErrorCode::InvalidInput, message)),
+        SchemaEnforcement::Warn => {
+            tracing::warn!("{}", message);
+            Ok(())
+        }
+    }
+}
+
+/// Refuse plaintext in metadata fields the workflow has clients encrypt
+fn check_confidential_metadata(
+    workflow: &WorkflowDefinition,
+    metadata: &ResourceMetadata,
+) -> async_graphql::Result<()> {
+    let violations = workflow.confidential_violations(metadata);
+    if violations.is_empty() {
+        return Ok(());
+    }
+    Err(coded_error(ErrorCode::InvalidInput, violations.join("; ")))
+}
+
+/// Refuse a rule for a workflow that reads the workflow's confidential metadata
+async fn check_rule_confidentiality(
+    ctx: &Context<'_>,
+    rule: &crate::engine::rules::StoredRule,
+) -> async_graphql::Result<()> {
+    let Some(workflow_id) = &rule.workflow_id else {
+        return Ok(());
+    };
+    let storage = ctx.data::<Box<dyn WorkflowStorage>>()?;
+    let workflow = storage.get_workflow(workflow_id).await?.ok_or_else(|| {
+        coded_error(
+            ErrorCode::WorkflowNotFound,
+            format!("Workflow not found: {}", workflow_id),
+        )
+    })?;
+    workflow
+        .check_rule_fields(&crate::models::Rule::from(rule.clone()))
+        .map_err(|e| coded_error(ErrorCode::InvalidInput, e))
+}
+
+#[derive(InputObject, Debug)]
+pub struct StateCapacityInput {
+    pub state: String,
+    pub max_resources: i32,
+    /// "fifo" (default), "lifo" or "priority"
+    pub queue_order: Option<String>,
+    /// Numeric data field ranking waiting resources, required for "priority" order
+    pub priority_field: Option<String>,
+}
+
+#[derive(InputObject, Debug)]
+pub struct StateSlaInput {
+    pub state: String,
+    /// Longest a resource should sit in the state, in seconds
+    pub max_secs: i64,
+    /// Activity executed on a resource breaching the SLA
+    pub escalation_activity: Option<String>,
+    /// URL the breach is POSTed to as JSON
+    pub webhook_url: Option<String>,
+}
+
+impl StateSlaInput {
+    fn into_sla(self) -> async_graphql::Result<(StateId, StateSla)> {
+        let max_secs = u64::try_from(self.max_secs).map_err(|_| {
+            coded_error(ErrorCode::InvalidInput, "SLA max secs must not be negative")
+        })?;
+        Ok((
+            StateId::from(self.state),
+            StateSla {
+                max_secs,
+                escalation_activity: self.escalation_activity.map(ActivityId::from),
+                webhook_url: self.webhook_url,
+            },
+        ))
+    }
+}
+
+impl StateCapacityInput {
+    fn into_capacity(self) -> async_graphql::Result<(StateId, StateCapacity)> {
+        let queue_order = match self.queue_order.as_deref().unwrap_or("fifo") {
+            "fifo" => CapacityQueueOrder::Fifo,
+            "lifo" => CapacityQueueOrder::Lifo,
+            "priority" => CapacityQueueOrder::Priority,
+            other => {
+                return Err(coded_error(
+                    ErrorCode::InvalidInput,
+                    format!(
+                        "Unknown queue order '{}', expected fifo, lifo or priority",
+                        other
+                    ),
+                ))
+            }
+        };
+        let max_resources = u32::try_from(self.max_resources).map_err(|_| {
+            coded_error(
+                ErrorCode::InvalidInput,
+                "Capacity max resources must not be negative",
+            )
+        })?;
+        Ok((
+            StateId::from(self.state),
+            StateCapacity {
+                max_resources,
+                queue_order,
+                priority_field: self.priority_field,
+            },
+        ))
+    }
+}
+
+fn queue_order_name(order: CapacityQueueOrder) -> &'static str {
+    match order {
+        CapacityQueueOrder::Fifo => "fifo",
+        CapacityQueueOrder::Lifo => "lifo",
+        CapacityQueueOrder::Priority => "priority",
+    }
 }
 
-#[derive(SimpleObject, Debug, Clone)]
-pub struct ChatMessageGQL {
-    pub role: String,
-    pub content: String,
-    pub name: Option<String>,
+/// Selects the resources `evaluateTransitionsBulk` covers; omitted criteria match everything
+#[derive(InputObject, Debug, Default)]
+pub struct BulkEvaluationFilterInput {
+    pub states: Option<Vec<String>>,
+    pub resource_ids: Option<Vec<String>>,
+    /// Metadata values resources must have, as a JSON object
+    pub metadata: Option<serde_json::Value>,
+    pub limit: Option<i32>,
 }
 
-#[derive(SimpleObject, Debug, Clone)]
-pub struct LLMResponseGQL {
+impl BulkEvaluationFilterInput {
+    fn into_filter(self) -> async_graphql::Result<crate::engine::ResourceFilter> {
+        let resource_ids = self
+            .resource_ids
+            .unwrap_or_default()
+            .iter()
+            .map(|id| id.parse::<Uuid>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| coded_error(ErrorCode::InvalidInput, "Invalid resource ID format"))?;
+        let metadata = match self.metadata {
+            None | Some(serde_json::Value::Null) => serde_json::Map::new(),
+            Some(serde_json::Value::Object(metadata)) => metadata,
+            Some(_) => {
+                return Err(coded_error(
+                    ErrorCode::InvalidInput,
+                    "Metadata filter must be a JSON object",
+                ))
+            }
+        };
+        Ok(crate::engine::ResourceFilter {
+            states: self.states.unwrap_or_default(),
+            resource_ids,
+            metadata,
+            limit: self.limit.map(|limit| limit.max(0) as usize),
+        })
+    }
+}
+
+#[derive(InputObject, Debug)]
+pub struct ActivityDefinitionInput {
     pub id: String,
-    pub model: String,
-    pub choices: Vec<LLMChoiceGQL>,
-    pub usage: TokenUsageGQL,
-    pub provider: String,
-    pub routing_info: RoutingInfoGQL,
+    pub name: Option<String>,
+    pub from_states: Vec<String>,
+    pub to_state: String,
+    pub conditions: Vec<String>,
+    pub description: Option<String>,
+    /// Fire the activity without a caller once a resource has sat in a source state
+    pub timer: Option<ActivityTimerInput>,
+    /// Activity undoing this one when the resource is compensated
+    pub compensation: Option<String>,
+    /// Make the activity a human task, completed by approving or rejecting it
+    pub manual: Option<ManualTaskInput>,
+    /// Make the activity an AND-split starting a branch in each of these states
+    pub split_into: Option<Vec<String>>,
+    /// Make the activity an AND-join waiting for every branch to reach its source states
+    pub join: Option<bool>,
+    /// Retry the activity's agent or function execution when it fails
+    pub retry: Option<ActivityRetryPolicyInput>,
+    /// Move resources to the first route whose expression holds instead of `to_state`
+    pub routes: Option<Vec<ActivityRouteInput>>,
 }
 
-#[derive(SimpleObject, Debug, Clone)]
-pub struct LLMChoiceGQL {
-    pub index: i32,
-    pub message: ChatMessageGQL,
-    pub finish_reason: Option<String>,
+#[derive(InputObject, Debug)]
+pub struct ActivityRouteInput {
+    /// Expression over the resource, e.g. `amount > 1000 && region == "eu"`
+    pub when: String,
+    pub to_state: String,
 }
 
-#[derive(SimpleObject, Debug, Clone)]
-pub struct TokenUsageGQL {
-    pub prompt_tokens: i32,
-    pub completion_tokens: i32,
-    pub total_tokens: i32,
-    pub estimated_cost: f64,
+#[derive(InputObject, Debug)]
+pub struct ActivityRetryPolicyInput {
+    /// Most attempts, the first included
+    pub max_attempts: i32,
+    /// Delay before the first retry
+    pub delay_secs: i32,
+    /// "fixed" (default), "linear" or "exponential"
+    pub backoff: Option<String>,
+    /// Growth per retry of exponential backoff; 2 by default
+    pub multiplier: Option<f64>,
+    pub max_delay_secs: Option<i32>,
+    /// Retry only failures whose error mentions one of these
+    pub retry_on: Option<Vec<String>>,
 }
 
-#[derive(SimpleObject, Debug, Clone)]
-pub struct RoutingInfoGQL {
-    pub selected_provider: String,
-    pub routing_strategy: String,
-    pub latency_ms: i32,
-    pub retry_count: i32,
-    pub fallback_used: bool,
+impl ActivityRetryPolicyInput {
+    fn into_policy(self) -> async_graphql::Result<ActivityRetryPolicy> {
+        let non_negative = |value: i32, field: &str| {
+            u64::try_from(value).map_err(|_| {
+                coded_error(
+                    ErrorCode::InvalidInput,
+                    format!("Retry {} must not be negative", field),
+                )
+            })
+        };
+        let backoff = match self.backoff.as_deref().unwrap_or("fixed") {
+            "fixed" => RetryBackoff::Fixed,
+            "linear" => RetryBackoff::Linear,
+            "exponential" => RetryBackoff::Exponential {
+                multiplier: self.multiplier.unwrap_or(2.0),
+            },
+            other => {
+                return Err(coded_error(
+                    ErrorCode::InvalidInput,
+                    format!(
+                        "Unknown backoff '{}', expected fixed, linear or exponential",
+                        other
+                    ),
+                ))
+            }
+        };
+        Ok(ActivityRetryPolicy {
+            max_attempts: non_negative(self.max_attempts, "attempts")? as u32,
+            delay_secs: non_negative(self.delay_secs, "delay")?,
+            backoff,
+            max_delay_secs: self
+                .max_delay_secs
+                .map(|secs| non_negative(secs, "maximum delay"))
+                .transpose()?,
+            retry_on: self.retry_on.unwrap_or_default(),
+        })
+    }
 }
 
-#[derive(SimpleObject, Debug, Clone)]
-pub struct CostInfoGQL {
-    pub request_id: ID,
-    pub provider: String,
-    pub model: String,
-    pub input_tokens: i32,
-    pub output_tokens: i32,
-    pub cost_usd: f64,
-    pub timestamp: String,
-    pub user_id: Option<String>,
-    pub project_id: Option<String>,
+#[derive(InputObject, Debug)]
+pub struct ManualTaskInput {
+    pub instructions: Option<String>,
+    /// State a rejected resource moves to; without one it stays where it is
+    pub reject_to: Option<String>,
 }
 
-#[derive(SimpleObject, Debug, Clone)]
-pub struct BudgetStatusGQL {
-    pub budget_id: String,
-    pub limit: f64,
-    pub used: f64,
-    pub percentage_used: f64,
-    pub is_exhausted: bool,
-    pub is_warning: bool,
-    pub remaining: f64,
-    pub message: String,
+/// Approval or rejection of a human task
+#[derive(InputObject, Debug)]
+pub struct HumanTaskDecisionInput {
+    pub resource_id: String,
+    pub activity_id: String,
+    /// Who decides; must be whoever claimed the task, if anyone did
+    pub actor: String,
+    pub comment: Option<String>,
 }
 
-#[derive(SimpleObject, Debug, Clone)]
-pub struct CostAnalyticsGQL {
-    pub total_cost: f64,
-    pub total_tokens: i32,
-    pub average_cost_per_token: f64,
-    pub provider_breakdown: serde_json::Value,
-    pub model_breakdown: serde_json::Value,
-    pub daily_costs: serde_json::Value,
-    pub period_start: String,
-    pub period_end: String,
+/// Pausing, resuming or cancelling a resource
+#[derive(InputObject, Debug)]
+pub struct ResourceLifecycleInput {
+    pub resource_id: String,
+    /// Who pauses, resumes or cancels the resource
+    pub actor: Option<String>,
+    /// Why; required to cancel
+    pub reason: Option<String>,
 }
 
-// Input types for mutations
+/// Delivering a signal to a resource
 #[derive(InputObject, Debug)]
-pub struct WorkflowDefinitionInput {
-    pub name: String,
-    pub states: Vec<String>,
-    pub activities: Vec<ActivityDefinitionInput>,
-    pub initial_state: String,
-    pub description: Option<String>,
+pub struct SendSignalInput {
+    pub resource_id: String,
+    /// Name rules match on with a `SignalReceived` condition
+    pub signal: String,
+    pub payload: Option<serde_json::Value>,
+    /// Resource sending the signal, if it comes from one
+    pub sender_resource_id: Option<String>,
 }
 
 #[derive(InputObject, Debug)]
-pub struct ActivityDefinitionInput {
-    pub id: String,
-    pub name: Option<String>,
-    pub from_states: Vec<String>,
-    pub to_state: String,
-    pub conditions: Vec<String>,
-    pub description: Option<String>,
+pub struct ActivityTimerInput {
+    /// "fire_after" (default) waits for the activity's rules to pass; "deadline" fires
+    /// regardless
+    pub kind: Option<String>,
+    /// Seconds after the resource entered the state
+    pub secs: i32,
+}
+
+impl ActivityTimerInput {
+    fn into_timer(self) -> async_graphql::Result<ActivityTimer> {
+        let secs = u64::try_from(self.secs).map_err(|_| {
+            coded_error(
+                ErrorCode::InvalidInput,
+                "Timer seconds must not be negative",
+            )
+        })?;
+        match self.kind.as_deref().unwrap_or("fire_after") {
+            "fire_after" => Ok(ActivityTimer::FireAfter { secs }),
+            "deadline" => Ok(ActivityTimer::Deadline { secs }),
+            other => Err(coded_error(
+                ErrorCode::InvalidInput,
+                format!(
+                    "Unknown timer kind '{}', expected fire_after or deadline",
+                    other
+                ),
+            )),
+        }
+    }
+}
+
+impl From<ActivityTimer> for ActivityTimerGQL {
+    fn from(timer: ActivityTimer) -> Self {
+        let kind = match timer {
+            ActivityTimer::FireAfter { .. } => "fire_after",
+            ActivityTimer::Deadline { .. } => "deadline",
+        };
+        ActivityTimerGQL {
+            kind: kind.to_string(),
+            secs: i32::try_from(timer.secs()).unwrap_or(i32::MAX),
+        }
+    }
 }
 
 // LLM Router Input Types
@@ -362,6 +2382,8 @@ pub struct LLMChatCompletionInput {
     pub stream: Option<bool>,
     pub user: Option<String>,
     pub project_id: Option<String>,
+    /// OpenAI-style `response_format`, e.g. `{"type": "json_schema", "json_schema": {...}}`
+    pub response_format: Option<serde_json::Value>,
 }
 
 #[derive(InputObject, Debug)]
@@ -402,6 +2424,27 @@ pub struct BudgetInput {
     pub warning_threshold: f64,
 }
 
+#[derive(InputObject, Debug)]
+pub struct ModelPriceInput {
+    pub provider: String,
+    pub model: String,
+    pub input_cost_per_token: f64,
+    pub output_cost_per_token: f64,
+}
+
+#[derive(InputObject, Debug)]
+pub struct FeatureFlagInput {
+    pub name: String,
+    /// Scope the flag to one workflow; global when unset
+    pub workflow_id: Option<String>,
+    pub description: Option<String>,
+    pub enabled: bool,
+    /// Conditions a resource must all pass for the flag to be on for it
+    pub targeting: Option<Vec<RuleConditionInput>>,
+    /// Share of targeted resources the flag is on for; 100 when unset
+    pub rollout_percentage: Option<f64>,
+}
+
 #[derive(InputObject, Debug)]
 pub struct CostAnalyticsInput {
     pub user_id: Option<String>,
@@ -418,6 +2461,14 @@ pub struct ResourceCreateInput {
     pub metadata: Option<serde_json::Value>,
 }
 
+#[derive(InputObject, Debug)]
+pub struct ResourceMetadataUpdateInput {
+    pub resource_id: String,
+    pub metadata: serde_json::Value,
+    /// Replace all metadata instead of merging the given keys into it
+    pub replace: Option<bool>,
+}
+
 #[derive(InputObject, Debug)]
 pub struct ActivityExecuteInput {
     pub resource_id: String,
@@ -425,6 +2476,65 @@ pub struct ActivityExecuteInput {
     pub data: Option<serde_json::Value>,
 }
 
+/// Activities to execute on many resources in one call
+#[derive(InputObject, Debug)]
+pub struct ActivityBatchInput {
+    pub items: Vec<ActivityExecuteInput>,
+    /// Store every item or none; needs a storage backend with transactional batches
+    pub atomic: Option<bool>,
+}
+
+/// Resources to create in one call
+#[derive(InputObject, Debug)]
+pub struct ResourceBatchCreateInput {
+    pub items: Vec<ResourceCreateInput>,
+    /// Store every item or none; needs a storage backend with transactional batches
+    pub atomic: Option<bool>,
+}
+
+/// Outcome of one item of a batch, in input order
+#[derive(SimpleObject, Debug, Clone)]
+pub struct BatchItemResultGQL {
+    pub index: i32,
+    /// The stored resource, if the item succeeded
+    pub resource: Option<ResourceGQL>,
+    pub error: Option<String>,
+    pub error_code: Option<String>,
+}
+
+impl BatchItemResultGQL {
+    fn new(index: usize, outcome: async_graphql::Result<Resource>) -> Self {
+        match outcome {
+            Ok(resource) => BatchItemResultGQL {
+                index: index as i32,
+                resource: Some(ResourceGQL::from(&resource)),
+                error: None,
+                error_code: None,
+            },
+            Err(e) => BatchItemResultGQL {
+                index: index as i32,
+                resource: None,
+                error_code: e.extensions.as_ref().and_then(|extensions| {
+                    match extensions.get("code") {
+                        Some(async_graphql::Value::String(code)) => Some(code.clone()),
+                        _ => None,
+                    }
+                }),
+                error: Some(e.message),
+            },
+        }
+    }
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct BatchResultGQL {
+    pub items: Vec<BatchItemResultGQL>,
+    pub succeeded: i32,
+    pub failed: i32,
+    /// Whether the batch was stored all or nothing
+    pub atomic: bool,
+}
+
 #[derive(InputObject, Debug)]
 pub struct CampaignCreateInput {
     pub name: String,
@@ -480,6 +2590,8 @@ pub struct AgentPromptsInput {
 #[derive(InputObject, Debug)]
 pub struct StateAgentConfigInput {
     pub state_id: String,
+    /// Run the agent only for resources of this workflow
+    pub workflow_id: Option<String>,
     pub agent_id: String,
     pub llm_config: Option<LLMConfigInput>,
     pub input_mapping: serde_json::Value,
@@ -529,11 +2641,31 @@ pub struct RuleConditionGQL {
     pub field: Option<String>,
     pub value: Option<serde_json::Value>,
     pub substring: Option<String>,
+    /// Regular expression of a `FieldMatches` condition
+    pub pattern: Option<String>,
+    /// Flag name of a `FlagEnabled` condition
+    pub flag: Option<String>,
+    /// Correlation key, state and quorum of a `SiblingsInState` condition
+    pub siblings: Option<SiblingConditionGQL>,
+    /// Signal name of a `SignalReceived` condition
+    pub signal: Option<String>,
     pub rules: Option<Vec<RuleGQL>>,
     pub rule: Option<Box<RuleGQL>>,
     pub script: Option<String>,
 }
 
+/// Siblings a `SiblingsInState` condition waits for
+#[derive(SimpleObject, Debug, Clone)]
+pub struct SiblingConditionGQL {
+    /// Metadata key whose value siblings share
+    pub correlation_key: String,
+    pub state: String,
+    /// Workflow of the siblings counted; every workflow when unset
+    pub workflow_id: Option<String>,
+    /// Siblings required in the state; all of them when unset
+    pub min_count: Option<i32>,
+}
+
 #[derive(SimpleObject, Debug, Clone)]
 pub struct RuleEvaluationResultGQL {
     pub rule_id: String,
@@ -550,6 +2682,8 @@ pub struct RuleInput {
     pub description: String,
     pub condition: RuleConditionInput,
     pub tags: Option<Vec<String>>,
+    /// Workflow the rule belongs to; it may not read the workflow's confidential metadata
+    pub workflow_id: Option<String>,
 }
 
 #[derive(InputObject, Debug)]
@@ -558,11 +2692,31 @@ pub struct RuleConditionInput {
     pub field: Option<String>,
     pub value: Option<serde_json::Value>,
     pub substring: Option<String>,
+    /// Regular expression of a `FieldMatches` condition
+    pub pattern: Option<String>,
+    /// Flag name of a `FlagEnabled` condition
+    pub flag: Option<String>,
+    /// Correlation key, state and quorum of a `SiblingsInState` condition
+    pub siblings: Option<SiblingConditionInput>,
+    /// Signal name of a `SignalReceived` condition
+    pub signal: Option<String>,
     pub rules: Option<Vec<RuleConditionInput>>,
     pub rule: Option<Box<RuleConditionInput>>,
     pub script: Option<String>,
 }
 
+/// Siblings a `SiblingsInState` condition waits for
+#[derive(InputObject, Debug)]
+pub struct SiblingConditionInput {
+    /// Metadata key whose value siblings share
+    pub correlation_key: String,
+    pub state: String,
+    /// Workflow of the siblings counted; every workflow when unset
+    pub workflow_id: Option<String>,
+    /// Siblings required in the state; all of them when unset
+    pub min_count: Option<i32>,
+}
+
 #[derive(InputObject, Debug)]
 pub struct RuleEvaluationInput {
     pub rule_id: String,
@@ -625,6 +2779,7 @@ impl From<&WorkflowDefinition> for WorkflowGQL {
     fn from(workflow: &WorkflowDefinition) -> Self {
         WorkflowGQL {
             id: ID(workflow.id.clone()),
+            version: workflow.version as i32,
             name: workflow.name.clone(),
             states: workflow
                 .states
@@ -633,6 +2788,49 @@ impl From<&WorkflowDefinition> for WorkflowGQL {
                 .collect(),
             activities: workflow.activities.iter().map(|a| a.into()).collect(),
             initial_state: workflow.initial_state.as_str().to_string(),
+            state_capacities: {
+                let mut capacities: Vec<StateCapacityGQL> = workflow
+                    .state_capacities
+                    .iter()
+                    .map(|(state, capacity)| StateCapacityGQL {
+                        state: state.as_str().to_string(),
+                        max_resources: capacity.max_resources as i32,
+                        queue_order: queue_order_name(capacity.queue_order).to_string(),
+                        priority_field: capacity.priority_field.clone(),
+                    })
+                    .collect();
+                capacities.sort_by(|a, b| a.state.cmp(&b.state));
+                capacities
+            },
+            state_slas: {
+                let mut slas: Vec<StateSlaGQL> = workflow
+                    .state_slas
+                    .iter()
+                    .map(|(state, sla)| StateSlaGQL {
+                        state: state.as_str().to_string(),
+                        max_secs: sla.max_secs as i64,
+                        escalation_activity: sla
+                            .escalation_activity
+                            .as_ref()
+                            .map(|a| a.as_str().to_string()),
+                        webhook_url: sla.webhook_url.clone(),
+                    })
+                    .collect();
+                slas.sort_by(|a, b| a.state.cmp(&b.state));
+                slas
+            },
+            diagnostics: workflow.analyze().into_iter().map(Into::into).collect(),
+            metadata_schema: workflow.metadata_schema.as_ref().map(|metadata_schema| {
+                MetadataSchemaGQL {
+                    schema: metadata_schema.schema.clone(),
+                    enforcement: enforcement_name(metadata_schema.enforcement).to_string(),
+                }
+            }),
+            confidential_metadata: workflow.confidential_metadata.clone(),
+            forked_from: workflow
+                .forked_from
+                .as_ref()
+                .map(WorkflowProvenanceGQL::from),
             created_at: Utc::now().to_rfc3339(),
             updated_at: Utc::now().to_rfc3339(),
         }
@@ -723,6 +2921,7 @@ impl From<&StateAgentConfig> for StateAgentConfigGQL {
         StateAgentConfigGQL {
             id: config.id.to_string(),
             state_id: config.state_id.as_str().to_string(),
+            workflow_id: config.workflow_id.clone(),
             agent_id: config.agent_id.as_str().to_string(),
             llm_config: config.llm_config.as_ref().map(LLMConfigGQL::from),
             input_mapping: serde_json::to_value(&config.input_mapping).unwrap_or_default(),
@@ -792,6 +2991,51 @@ impl From<&AgentExecutionStatus> for AgentExecutionStatusGQL {
     }
 }
 
+impl From<&RoutingTrace> for RoutingTraceGQL {
+    fn from(trace: &RoutingTrace) -> Self {
+        RoutingTraceGQL {
+            request_id: trace.request_id.to_string(),
+            requested_model: trace.requested_model.clone(),
+            resolved_model: trace.resolved_model.clone(),
+            strategy: trace.strategy.as_ref().map(|s| format!("{:?}", s)),
+            tenant_id: trace.tenant_id.clone(),
+            selected_provider: trace.selected_provider.as_ref().map(|p| p.to_string()),
+            candidates: trace
+                .candidates
+                .iter()
+                .map(|candidate| RoutingCandidateGQL {
+                    provider: candidate.provider.to_string(),
+                    model: candidate.model.clone(),
+                    score: candidate.score,
+                    selected: candidate.selected,
+                })
+                .collect(),
+            exclusions: trace
+                .exclusions
+                .iter()
+                .map(|exclusion| RoutingExclusionGQL {
+                    provider: exclusion.provider.as_ref().map(|p| p.to_string()),
+                    model: exclusion.model.clone(),
+                    reason: exclusion.reason.clone(),
+                })
+                .collect(),
+            attempts: trace
+                .attempts
+                .iter()
+                .map(|attempt| RoutingAttemptGQL {
+                    provider: attempt.provider.to_string(),
+                    model: attempt.model.clone(),
+                    success: attempt.success,
+                    error: attempt.error.clone(),
+                })
+                .collect(),
+            guardrails: trace.guardrails.clone(),
+            created_at: trace.created_at.to_rfc3339(),
+            updated_at: trace.updated_at.to_rfc3339(),
+        }
+    }
+}
+
 impl From<&ActivityDefinition> for ActivityGQL {
     fn from(activity: &ActivityDefinition) -> Self {
         ActivityGQL {
@@ -805,6 +3049,31 @@ impl From<&ActivityDefinition> for ActivityGQL {
             to_state: activity.to_state.as_str().to_string(),
             conditions: activity.conditions.clone(),
             description: None,
+            timer: activity.timer.map(ActivityTimerGQL::from),
+            compensation: activity
+                .compensation
+                .as_ref()
+                .map(|c| c.as_str().to_string()),
+            manual: activity.manual.as_ref().map(|task| ManualTaskGQL {
+                instructions: task.instructions.clone(),
+                reject_to: task.reject_to.as_ref().map(|s| s.as_str().to_string()),
+            }),
+            split_into: match &activity.gateway {
+                Some(Gateway::Split(branches)) => {
+                    Some(branches.iter().map(|s| s.as_str().to_string()).collect())
+                }
+                _ => None,
+            },
+            join: activity.is_join(),
+            retry: activity.retry.as_ref().map(ActivityRetryPolicyGQL::from),
+            routes: activity
+                .routes
+                .iter()
+                .map(|route| ActivityRouteGQL {
+                    when: route.when.clone(),
+                    to_state: route.to_state.as_str().to_string(),
+                })
+                .collect(),
         }
     }
 }
@@ -814,16 +3083,80 @@ impl From<&Resource> for ResourceGQL {
         ResourceGQL {
             id: ID(resource.id.to_string()),
             workflow_id: resource.workflow_id.clone(),
+            workflow_version: resource.workflow_version as i32,
             state: resource.state.as_str().to_string(),
             data: resource.data.clone(),
             metadata: serde_json::to_value(&resource.metadata).unwrap_or_default(),
             created_at: resource.created_at.to_rfc3339(),
             updated_at: resource.updated_at.to_rfc3339(),
+            version: resource.version as i64,
             history: resource.history.iter().map(|h| h.into()).collect(),
+            branches: resource
+                .branches()
+                .into_iter()
+                .map(|(name, state)| BranchGQL {
+                    name,
+                    state: state.as_str().to_string(),
+                })
+                .collect(),
+            lifecycle: resource.lifecycle().map(|lifecycle| ResourceLifecycleGQL {
+                status: lifecycle.status.as_str().to_string(),
+                reason: lifecycle.reason,
+                actor: lifecycle.actor,
+                changed_at: lifecycle.changed_at.to_rfc3339(),
+            }),
+            signals: resource
+                .signals()
+                .into_iter()
+                .map(|(name, signal)| ResourceSignalGQL {
+                    name,
+                    payload: signal.payload,
+                    sender: signal.sender.map(|sender| sender.to_string()),
+                    received_at: signal.received_at.to_rfc3339(),
+                })
+                .collect(),
         }
     }
 }
 
+#[ComplexObject]
+impl ResourceGQL {
+    /// Workflow the resource belongs to, batched across resources by a dataloader
+    async fn workflow(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<WorkflowGQL>> {
+        let loader = ctx.data::<DataLoader<WorkflowLoader>>()?;
+        let workflow = loader
+            .load_one(self.workflow_id.clone())
+            .await
+            .map_err(|e| {
+                coded_error(
+                    ErrorCode::StorageError,
+                    format!("Failed to load workflow: {}", e),
+                )
+            })?;
+        Ok(workflow.as_ref().map(WorkflowGQL::from))
+    }
+
+    /// Agent executions run for the resource, oldest first; empty without agent support
+    async fn executions(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<AgentExecutionGQL>> {
+        let Some(loader) = ctx.data_opt::<DataLoader<ResourceExecutionsLoader>>() else {
+            return Ok(Vec::new());
+        };
+        let resource_id = Uuid::parse_str(&self.id)
+            .map_err(|_| coded_error(ErrorCode::InvalidInput, "Invalid resource ID"))?;
+        let executions = loader.load_one(resource_id).await.map_err(|e| {
+            coded_error(
+                ErrorCode::StorageError,
+                format!("Failed to load agent executions: {}", e),
+            )
+        })?;
+        Ok(executions
+            .unwrap_or_default()
+            .iter()
+            .map(AgentExecutionGQL::from)
+            .collect())
+    }
+}
+
 impl From<&HistoryEvent> for HistoryEventGQL {
     fn from(event: &HistoryEvent) -> Self {
         HistoryEventGQL {
@@ -894,6 +3227,10 @@ impl From<&RuleCondition> for RuleConditionGQL {
                 field: Some(field.clone()),
                 value: None,
                 substring: None,
+                pattern: None,
+                flag: None,
+                siblings: None,
+                signal: None,
                 rules: None,
                 rule: None,
                 script: None,
@@ -903,6 +3240,10 @@ impl From<&RuleCondition> for RuleConditionGQL {
                 field: Some(field.clone()),
                 value: Some(value.clone()),
                 substring: None,
+                pattern: None,
+                flag: None,
+                siblings: None,
+                signal: None,
                 rules: None,
                 rule: None,
                 script: None,
@@ -914,6 +3255,10 @@ impl From<&RuleCondition> for RuleConditionGQL {
                     serde_json::Number::from_f64(*value).unwrap(),
                 )),
                 substring: None,
+                pattern: None,
+                flag: None,
+                siblings: None,
+                signal: None,
                 rules: None,
                 rule: None,
                 script: None,
@@ -925,6 +3270,10 @@ impl From<&RuleCondition> for RuleConditionGQL {
                     serde_json::Number::from_f64(*value).unwrap(),
                 )),
                 substring: None,
+                pattern: None,
+                flag: None,
+                siblings: None,
+                signal: None,
                 rules: None,
                 rule: None,
                 script: None,
@@ -933,36 +3282,114 @@ impl From<&RuleCondition> for RuleConditionGQL {
                 condition_type: "FieldContains".to_string(),
                 field: Some(field.clone()),
                 value: None,
-                substring: Some(substring.clone()),
+                substring: Some(substring.clone()),
+                pattern: None,
+                flag: None,
+                siblings: None,
+                signal: None,
+                rules: None,
+                rule: None,
+                script: None,
+            },
+            RuleCondition::FieldMatches { field, pattern } => RuleConditionGQL {
+                condition_type: "FieldMatches".to_string(),
+                field: Some(field.clone()),
+                value: None,
+                substring: None,
+                pattern: Some(pattern.clone()),
+                flag: None,
+                siblings: None,
+                signal: None,
+                rules: None,
+                rule: None,
+                script: None,
+            },
+            RuleCondition::And { rules: _rules } => RuleConditionGQL {
+                condition_type: "And".to_string(),
+                field: None,
+                value: None,
+                substring: None,
+                pattern: None,
+                flag: None,
+                siblings: None,
+                signal: None,
+                rules: None, // Nested rules not fully supported yet
+                rule: None,
+                script: None,
+            },
+            RuleCondition::Or { rules: _rules } => RuleConditionGQL {
+                condition_type: "Or".to_string(),
+                field: None,
+                value: None,
+                substring: None,
+                pattern: None,
+                flag: None,
+                siblings: None,
+                signal: None,
+                rules: None, // Nested rules not fully supported yet
+                rule: None,
+                script: None,
+            },
+            RuleCondition::Not { rule } => RuleConditionGQL {
+                condition_type: "Not".to_string(),
+                field: None,
+                value: None,
+                substring: None,
+                pattern: None,
+                flag: None,
+                siblings: None,
+                signal: None,
                 rules: None,
-                rule: None,
+                rule: None, // Nested rules not fully supported in StoredRule context
                 script: None,
             },
-            RuleCondition::And { rules: _rules } => RuleConditionGQL {
-                condition_type: "And".to_string(),
+            RuleCondition::FlagEnabled { flag } => RuleConditionGQL {
+                condition_type: "FlagEnabled".to_string(),
                 field: None,
                 value: None,
                 substring: None,
-                rules: None, // Nested rules not fully supported yet
+                pattern: None,
+                flag: Some(flag.clone()),
+                siblings: None,
+                signal: None,
+                rules: None,
                 rule: None,
                 script: None,
             },
-            RuleCondition::Or { rules: _rules } => RuleConditionGQL {
-                condition_type: "Or".to_string(),
+            RuleCondition::SiblingsInState {
+                correlation_key,
+                state,
+                workflow_id,
+                min_count,
+            } => RuleConditionGQL {
+                condition_type: "SiblingsInState".to_string(),
                 field: None,
                 value: None,
                 substring: None,
-                rules: None, // Nested rules not fully supported yet
+                pattern: None,
+                flag: None,
+                siblings: Some(SiblingConditionGQL {
+                    correlation_key: correlation_key.clone(),
+                    state: state.clone(),
+                    workflow_id: workflow_id.clone(),
+                    min_count: min_count.map(|n| n as i32),
+                }),
+                signal: None,
+                rules: None,
                 rule: None,
                 script: None,
             },
-            RuleCondition::Not { rule } => RuleConditionGQL {
-                condition_type: "Not".to_string(),
+            RuleCondition::SignalReceived { signal } => RuleConditionGQL {
+                condition_type: "SignalReceived".to_string(),
                 field: None,
                 value: None,
                 substring: None,
+                pattern: None,
+                flag: None,
+                siblings: None,
+                signal: Some(signal.clone()),
                 rules: None,
-                rule: None, // Nested rules not fully supported in StoredRule context
+                rule: None,
                 script: None,
             },
             RuleCondition::Expression { script } => RuleConditionGQL {
@@ -970,6 +3397,10 @@ impl From<&RuleCondition> for RuleConditionGQL {
                 field: None,
                 value: None,
                 substring: None,
+                pattern: None,
+                flag: None,
+                siblings: None,
+                signal: None,
                 rules: None,
                 rule: None,
                 script: Some(script.clone()),
@@ -1010,6 +3441,10 @@ impl From<RuleConditionInput> for RuleCondition {
                 field: input.field.unwrap_or_default(),
                 substring: input.substring.unwrap_or_default(),
             },
+            "FieldMatches" => RuleCondition::FieldMatches {
+                field: input.field.unwrap_or_default(),
+                pattern: input.pattern.unwrap_or_default(),
+            },
             "And" => RuleCondition::And {
                 rules: input
                     .rules
@@ -1045,6 +3480,24 @@ impl From<RuleConditionInput> for RuleCondition {
                     ),
                 }),
             },
+            "FlagEnabled" => RuleCondition::FlagEnabled {
+                flag: input.flag.unwrap_or_default(),
+            },
+            "SiblingsInState" => match input.siblings {
+                Some(siblings) => RuleCondition::SiblingsInState {
+                    correlation_key: siblings.correlation_key,
+                    state: siblings.state,
+                    workflow_id: siblings.workflow_id,
+                    min_count: siblings.min_count.map(|n| n.max(0) as u32),
+                },
+                // Without siblings to wait for the condition never fires
+                None => RuleCondition::Expression {
+                    script: "false".to_string(),
+                },
+            },
+            "SignalReceived" => RuleCondition::SignalReceived {
+                signal: input.signal.unwrap_or_default(),
+            },
             "Expression" => RuleCondition::Expression {
                 script: input.script.unwrap_or_default(),
             },
@@ -1066,15 +3519,76 @@ impl Query {
         ctx: &Context<'_>,
         id: String,
     ) -> async_graphql::Result<Option<WorkflowGQL>> {
+        authorize_service(ctx, &id, ServiceOperation::ReadResources)?;
         let storage = ctx.data::<Box<dyn WorkflowStorage>>()?;
         match storage.get_workflow(&id).await {
             Ok(Some(workflow)) => Ok(Some(WorkflowGQL::from(&workflow))),
             Ok(None) => Ok(None),
-            Err(e) => Err(async_graphql::Error::new(format!(
-                "Failed to get workflow: {}",
-                e
-            ))),
+            Err(e) => Err(coded_error(
+                ErrorCode::StorageError,
+                format!("Failed to get workflow: {}", e),
+            )),
+        }
+    }
+
+    /// Human tasks resources are waiting on, longest waiting first
+    async fn human_tasks(
+        &self,
+        ctx: &Context<'_>,
+        workflow_id: Option<String>,
+        claimed_by: Option<String>,
+    ) -> async_graphql::Result<Vec<HumanTaskGQL>> {
+        if let Some(workflow_id) = &workflow_id {
+            authorize_service(ctx, workflow_id, ServiceOperation::ReadResources)?;
         }
+        let tasks = crate::engine::HumanTasks::global()
+            .pending(
+                engine_storage(ctx)?,
+                workflow_id.as_deref(),
+                claimed_by.as_deref(),
+            )
+            .await
+            .map_err(human_task_error)?;
+        Ok(tasks.into_iter().map(HumanTaskGQL::from).collect())
+    }
+
+    /// Every stored version of a workflow definition, oldest first
+    async fn workflow_versions(
+        &self,
+        ctx: &Context<'_>,
+        id: String,
+    ) -> async_graphql::Result<Vec<WorkflowGQL>> {
+        authorize_service(ctx, &id, ServiceOperation::ReadResources)?;
+        let storage = ctx.data::<Box<dyn WorkflowStorage>>()?;
+        let versions = storage.list_workflow_versions(&id).await.map_err(|e| {
+            coded_error(
+                ErrorCode::StorageError,
+                format!("Failed to list workflow versions: {}", e),
+            )
+        })?;
+        Ok(versions.iter().map(WorkflowGQL::from).collect())
+    }
+
+    /// A specific version of a workflow definition
+    async fn workflow_version(
+        &self,
+        ctx: &Context<'_>,
+        id: String,
+        version: i32,
+    ) -> async_graphql::Result<Option<WorkflowGQL>> {
+        authorize_service(ctx, &id, ServiceOperation::ReadResources)?;
+        let storage = ctx.data::<Box<dyn WorkflowStorage>>()?;
+        let version = workflow_version_number(version)?;
+        let workflow = storage
+            .get_workflow_version(&id, version)
+            .await
+            .map_err(|e| {
+                coded_error(
+                    ErrorCode::StorageError,
+                    format!("Failed to get workflow version: {}", e),
+                )
+            })?;
+        Ok(workflow.as_ref().map(WorkflowGQL::from))
     }
 
     /// List all workflow definitions
@@ -1082,13 +3596,38 @@ impl Query {
         let storage = ctx.data::<Box<dyn WorkflowStorage>>()?;
         match storage.list_workflows().await {
             Ok(workflows) => Ok(workflows.iter().map(WorkflowGQL::from).collect()),
-            Err(e) => Err(async_graphql::Error::new(format!(
-                "Failed to list workflows: {}",
-                e
-            ))),
+            Err(e) => Err(coded_error(
+                ErrorCode::StorageError,
+                format!("Failed to list workflows: {}", e),
+            )),
         }
     }
 
+    /// Workflow definitions forked from the given one
+    async fn workflow_forks(
+        &self,
+        ctx: &Context<'_>,
+        id: String,
+    ) -> async_graphql::Result<Vec<WorkflowGQL>> {
+        let storage = ctx.data::<Box<dyn WorkflowStorage>>()?;
+        let workflows = storage.list_workflows().await.map_err(|e| {
+            coded_error(
+                ErrorCode::StorageError,
+                format!("Failed to list workflows: {}", e),
+            )
+        })?;
+        Ok(workflows
+            .iter()
+            .filter(|workflow| {
+                workflow
+                    .forked_from
+                    .as_ref()
+                    .is_some_and(|origin| origin.workflow_id == id)
+            })
+            .map(WorkflowGQL::from)
+            .collect())
+    }
+
     /// Get a resource by ID
     async fn resource(
         &self,
@@ -1098,15 +3637,18 @@ impl Query {
         let storage = ctx.data::<Box<dyn WorkflowStorage>>()?;
         let resource_id = id
             .parse::<Uuid>()
-            .map_err(|_| async_graphql::Error::new("Invalid resource ID format"))?;
+            .map_err(|_| coded_error(ErrorCode::InvalidInput, "Invalid resource ID format"))?;
 
         match storage.get_resource(&resource_id).await {
-            Ok(Some(resource)) => Ok(Some(ResourceGQL::from(&resource))),
+            Ok(Some(resource)) => {
+                authorize_service(ctx, &resource.workflow_id, ServiceOperation::ReadResources)?;
+                Ok(Some(ResourceGQL::from(&resource)))
+            }
             Ok(None) => Ok(None),
-            Err(e) => Err(async_graphql::Error::new(format!(
-                "Failed to get resource: {}",
-                e
-            ))),
+            Err(e) => Err(coded_error(
+                ErrorCode::StorageError,
+                format!("Failed to get resource: {}", e),
+            )),
         }
     }
 
@@ -1117,12 +3659,27 @@ impl Query {
         workflow_id: Option<String>,
     ) -> async_graphql::Result<Vec<ResourceGQL>> {
         let storage = ctx.data::<Box<dyn WorkflowStorage>>()?;
+        let principal = ctx.data_opt::<ServicePrincipal>();
+        if let Some(workflow_id) = &workflow_id {
+            authorize_service(ctx, workflow_id, ServiceOperation::ReadResources)?;
+        }
         match storage.list_resources(workflow_id.as_deref()).await {
-            Ok(resources) => Ok(resources.iter().map(ResourceGQL::from).collect()),
-            Err(e) => Err(async_graphql::Error::new(format!(
-                "Failed to list resources: {}",
-                e
-            ))),
+            // Without a workflow filter a service account only sees the workflows it may read
+            Ok(resources) => Ok(resources
+                .iter()
+                .filter(|resource| {
+                    principal.is_none_or(|principal| {
+                        principal
+                            .account
+                            .allows(&resource.workflow_id, ServiceOperation::ReadResources)
+                    })
+                })
+                .map(ResourceGQL::from)
+                .collect()),
+            Err(e) => Err(coded_error(
+                ErrorCode::StorageError,
+                format!("Failed to list resources: {}", e),
+            )),
         }
     }
 
@@ -1135,7 +3692,7 @@ impl Query {
         let storage = ctx.data::<Box<dyn WorkflowStorage>>()?;
         let resource_uuid = resource_id
             .parse::<Uuid>()
-            .map_err(|_| async_graphql::Error::new("Invalid resource ID format"))?;
+            .map_err(|_| coded_error(ErrorCode::InvalidInput, "Invalid resource ID format"))?;
 
         // Get the resource with retry logic for timing issues
         let mut resource = None;
@@ -1152,17 +3709,18 @@ impl Query {
                 }
                 Ok(None) => {
                     if attempt == 2 {
-                        return Err(async_graphql::Error::new(
+                        return Err(coded_error(
+                            ErrorCode::ResourceNotFound,
                             "Resource not found after retries",
                         ));
                     }
                     continue;
                 }
                 Err(e) => {
-                    return Err(async_graphql::Error::new(format!(
-                        "Failed to get resource: {}",
-                        e
-                    )));
+                    return Err(coded_error(
+                        ErrorCode::StorageError,
+                        format!("Failed to get resource: {}", e),
+                    ));
                 }
             }
         }
@@ -1172,7 +3730,7 @@ impl Query {
         let workflow = storage
             .get_workflow(&resource.workflow_id)
             .await?
-            .ok_or_else(|| async_graphql::Error::new("Workflow not found"))?;
+            .ok_or_else(|| coded_error(ErrorCode::WorkflowNotFound, "Workflow not found"))?;
 
         let current_state = StateId::from(resource.current_state());
         let available = workflow.available_activities(&current_state);
@@ -1180,6 +3738,103 @@ impl Query {
         Ok(available.iter().map(|a| ActivityGQL::from(*a)).collect())
     }
 
+    /// Number of resources of a workflow in each occupied state
+    async fn resource_state_counts(
+        &self,
+        ctx: &Context<'_>,
+        workflow_id: String,
+    ) -> async_graphql::Result<Vec<StateCountGQL>> {
+        let storage = ctx.data::<Box<dyn WorkflowStorage>>()?;
+        let counts = storage
+            .count_resources_by_state(&workflow_id)
+            .await
+            .map_err(|e| {
+                coded_error(
+                    ErrorCode::StorageError,
+                    format!("Failed to count resources: {}", e),
+                )
+            })?;
+
+        let mut counts: Vec<StateCountGQL> = counts
+            .into_iter()
+            .map(|(state, count)| StateCountGQL {
+                state,
+                count: count as i32,
+            })
+            .collect();
+        counts.sort_by(|a, b| a.state.cmp(&b.state));
+        Ok(counts)
+    }
+
+    /// Number of agent executions with each status
+    async fn execution_status_counts(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<Vec<ExecutionStatusCountGQL>> {
+        let agent_storage = ctx.data::<std::sync::Arc<dyn AgentStorage>>()?;
+        let counts = agent_storage
+            .count_executions_by_status()
+            .await
+            .map_err(|e| {
+                coded_error(
+                    ErrorCode::StorageError,
+                    format!("Failed to count agent executions: {}", e),
+                )
+            })?;
+
+        let mut counts: Vec<ExecutionStatusCountGQL> = counts
+            .into_iter()
+            .map(|(status, count)| ExecutionStatusCountGQL {
+                status,
+                count: count as i32,
+            })
+            .collect();
+        counts.sort_by(|a, b| a.status.cmp(&b.status));
+        Ok(counts)
+    }
+
+    /// Evaluate every activity of a workflow across its matching resources and count,
+    /// per activity, how many resources can execute it and how many are blocked
+    async fn evaluate_transitions_bulk(
+        &self,
+        ctx: &Context<'_>,
+        workflow_id: String,
+        filters: Option<BulkEvaluationFilterInput>,
+        max_concurrency: Option<i32>,
+    ) -> async_graphql::Result<BulkEvaluationReportGQL> {
+        let storage = ctx.data::<Box<dyn WorkflowStorage>>()?;
+        let filter = filters.unwrap_or_default().into_filter()?;
+
+        let workflow = storage
+            .get_workflow(&workflow_id)
+            .await?
+            .ok_or_else(|| coded_error(ErrorCode::WorkflowNotFound, "Workflow not found"))?;
+        let resources = storage
+            .list_resources(Some(&workflow_id))
+            .await
+            .map_err(|e| {
+                coded_error(
+                    ErrorCode::StorageError,
+                    format!("Failed to list resources: {}", e),
+                )
+            })?;
+
+        let max_concurrency = match max_concurrency {
+            Some(limit) => limit.max(1) as usize,
+            None => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        };
+        let engine = rules_engine(ctx);
+
+        let report = crate::engine::bulk_evaluation::evaluate_bulk(
+            engine,
+            std::sync::Arc::new(workflow),
+            filter.apply(resources),
+            max_concurrency,
+        )
+        .await;
+        Ok(report.into())
+    }
+
     /// Get an agent by ID
     async fn agent(
         &self,
@@ -1192,10 +3847,10 @@ impl Query {
         match agent_storage.get_agent(&agent_id).await {
             Ok(Some(agent)) => Ok(Some(AgentDefinitionGQL::from(&agent))),
             Ok(None) => Ok(None),
-            Err(e) => Err(async_graphql::Error::new(format!(
-                "Failed to get agent: {}",
-                e
-            ))),
+            Err(e) => Err(coded_error(
+                ErrorCode::StorageError,
+                format!("Failed to get agent: {}", e),
+            )),
         }
     }
 
@@ -1205,10 +3860,10 @@ impl Query {
 
         match agent_storage.list_agents().await {
             Ok(agents) => Ok(agents.iter().map(AgentDefinitionGQL::from).collect()),
-            Err(e) => Err(async_graphql::Error::new(format!(
-                "Failed to list agents: {}",
-                e
-            ))),
+            Err(e) => Err(coded_error(
+                ErrorCode::StorageError,
+                format!("Failed to list agents: {}", e),
+            )),
         }
     }
 
@@ -1223,10 +3878,10 @@ impl Query {
 
         match agent_storage.get_state_agent_configs(&state).await {
             Ok(configs) => Ok(configs.iter().map(StateAgentConfigGQL::from).collect()),
-            Err(e) => Err(async_graphql::Error::new(format!(
-                "Failed to get state agent configs: {}",
-                e
-            ))),
+            Err(e) => Err(coded_error(
+                ErrorCode::StorageError,
+                format!("Failed to get state agent configs: {}", e),
+            )),
         }
     }
 
@@ -1239,15 +3894,15 @@ impl Query {
         let agent_storage = ctx.data::<std::sync::Arc<dyn AgentStorage>>()?;
         let execution_id = id
             .parse::<Uuid>()
-            .map_err(|_| async_graphql::Error::new("Invalid execution ID format"))?;
+            .map_err(|_| coded_error(ErrorCode::InvalidInput, "Invalid execution ID format"))?;
 
         match agent_storage.get_execution(&execution_id).await {
             Ok(Some(execution)) => Ok(Some(AgentExecutionGQL::from(&execution))),
             Ok(None) => Ok(None),
-            Err(e) => Err(async_graphql::Error::new(format!(
-                "Failed to get agent execution: {}",
-                e
-            ))),
+            Err(e) => Err(coded_error(
+                ErrorCode::StorageError,
+                format!("Failed to get agent execution: {}", e),
+            )),
         }
     }
 
@@ -1260,20 +3915,51 @@ impl Query {
         let agent_storage = ctx.data::<std::sync::Arc<dyn AgentStorage>>()?;
         let resource_uuid = resource_id
             .parse::<Uuid>()
-            .map_err(|_| async_graphql::Error::new("Invalid resource ID format"))?;
+            .map_err(|_| coded_error(ErrorCode::InvalidInput, "Invalid resource ID format"))?;
 
         match agent_storage
             .list_executions_for_resource(&resource_uuid)
             .await
         {
             Ok(executions) => Ok(executions.iter().map(AgentExecutionGQL::from).collect()),
-            Err(e) => Err(async_graphql::Error::new(format!(
-                "Failed to get resource executions: {}",
-                e
-            ))),
+            Err(e) => Err(coded_error(
+                ErrorCode::StorageError,
+                format!("Failed to get resource executions: {}", e),
+            )),
         }
     }
 
+    /// Get subscriber gauges for the agent execution stream
+    async fn agent_stream_gauges(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<StreamGaugesGQL> {
+        let agent_engine = ctx.data::<AgentEngine>()?;
+        let gauges = agent_engine.stream_gauges();
+        Ok(StreamGaugesGQL {
+            active_subscribers: gauges.active_subscribers as i32,
+            reaped_total: gauges.reaped_total as i32,
+            missed_events_total: gauges.missed_events_total as i32,
+        })
+    }
+
+    /// Get rules engine evaluation metrics, listing the `top` most frequently failing rules
+    async fn rule_metrics(&self, top: Option<i32>) -> async_graphql::Result<RuleMetricsGQL> {
+        let top = top.map_or(crate::engine::rule_metrics::DEFAULT_TOP_FAILING, |top| {
+            top.max(0) as usize
+        });
+        let snapshot = crate::engine::RuleMetrics::global().snapshot(top);
+        Ok(RuleMetricsGQL {
+            activity_evaluations_total: snapshot.activity_evaluations_total as i32,
+            rule_evaluations_total: snapshot.rule_evaluations_total as i32,
+            evaluations_per_second: snapshot.evaluations_per_second,
+            cache_hits_total: snapshot.cache_hits_total as i32,
+            cache_misses_total: snapshot.cache_misses_total as i32,
+            rules: snapshot.rules.into_iter().map(Into::into).collect(),
+            most_failing: snapshot.most_failing.into_iter().map(Into::into).collect(),
+        })
+    }
+
     /// NATS-specific queries for enhanced token operations
 
     /// Get resource with NATS metadata by ID
@@ -1285,15 +3971,15 @@ impl Query {
         let storage = ctx.data::<Box<dyn WorkflowStorage>>()?;
         let resource_id = id
             .parse::<Uuid>()
-            .map_err(|_| async_graphql::Error::new("Invalid resource ID format"))?;
+            .map_err(|_| coded_error(ErrorCode::InvalidInput, "Invalid resource ID format"))?;
 
         match storage.get_resource(&resource_id).await {
             Ok(Some(resource)) => Ok(Some(NATSResourceGQL::from(&resource))),
             Ok(None) => Ok(None),
-            Err(e) => Err(async_graphql::Error::new(format!(
-                "Failed to get NATS resource: {}",
-                e
-            ))),
+            Err(e) => Err(coded_error(
+                ErrorCode::StorageError,
+                format!("Failed to get NATS resource: {}", e),
+            )),
         }
     }
 
@@ -1313,10 +3999,10 @@ impl Query {
                 .await
             {
                 Ok(resources) => Ok(resources.iter().map(NATSResourceGQL::from).collect()),
-                Err(e) => Err(async_graphql::Error::new(format!(
-                    "Failed to get resources in state: {}",
-                    e
-                ))),
+                Err(e) => Err(coded_error(
+                    ErrorCode::StorageError,
+                    format!("Failed to get resources in state: {}", e),
+                )),
             }
         } else {
             // Fallback to regular storage with filtering
@@ -1330,10 +4016,10 @@ impl Query {
                         .collect();
                     Ok(filtered)
                 }
-                Err(e) => Err(async_graphql::Error::new(format!(
-                    "Failed to get resources in state: {}",
-                    e
-                ))),
+                Err(e) => Err(coded_error(
+                    ErrorCode::StorageError,
+                    format!("Failed to get resources in state: {}", e),
+                )),
             }
         }
     }
@@ -1347,7 +4033,7 @@ impl Query {
     ) -> async_graphql::Result<Option<NATSResourceGQL>> {
         let resource_uuid = resource_id
             .parse::<Uuid>()
-            .map_err(|_| async_graphql::Error::new("Invalid resource ID format"))?;
+            .map_err(|_| coded_error(ErrorCode::InvalidInput, "Invalid resource ID format"))?;
 
         // Try NATS storage first for more efficient lookup
         if let Ok(nats_storage) =
@@ -1359,10 +4045,10 @@ impl Query {
             {
                 Ok(Some(resource)) => Ok(Some(NATSResourceGQL::from(&resource))),
                 Ok(None) => Ok(None),
-                Err(e) => Err(async_graphql::Error::new(format!(
-                    "Failed to find resource: {}",
-                    e
-                ))),
+                Err(e) => Err(coded_error(
+                    ErrorCode::StorageError,
+                    format!("Failed to find resource: {}", e),
+                )),
             }
         } else {
             // Fallback to regular storage
@@ -1376,10 +4062,10 @@ impl Query {
                     }
                 }
                 Ok(None) => Ok(None),
-                Err(e) => Err(async_graphql::Error::new(format!(
-                    "Failed to find resource: {}",
-                    e
-                ))),
+                Err(e) => Err(coded_error(
+                    ErrorCode::StorageError,
+                    format!("Failed to find resource: {}", e),
+                )),
             }
         }
     }
@@ -1391,7 +4077,10 @@ impl Query {
     ) -> async_graphql::Result<Vec<LLMProviderGQL>> {
         // Create router and get providers
         let router = crate::llm::router::LLMRouter::new().await.map_err(|e| {
-            async_graphql::Error::new(format!("Failed to initialize router: {}", e))
+            coded_error(
+                ErrorCode::ProviderUnavailable,
+                format!("Failed to initialize router: {}", e),
+            )
         })?;
 
         let providers = router.get_providers().await;
@@ -1444,6 +4133,20 @@ impl Query {
             .collect())
     }
 
+    /// Get the routing decisions recorded for an LLM request
+    async fn routing_trace(
+        &self,
+        _ctx: &Context<'_>,
+        request_id: String,
+    ) -> async_graphql::Result<Option<RoutingTraceGQL>> {
+        let id = Uuid::parse_str(&request_id)
+            .map_err(|_| coded_error(ErrorCode::InvalidInput, "Invalid request ID format"))?;
+
+        // Routers share the process-wide trace store
+        let trace = crate::llm::RoutingTraceStore::global().get(&id).await;
+        Ok(trace.as_ref().map(RoutingTraceGQL::from))
+    }
+
     /// Get LLM provider by ID
     async fn llm_provider(
         &self,
@@ -1527,6 +4230,132 @@ impl Query {
         })
     }
 
+    /// Get the model prices set in the price catalog, optionally for one provider
+    async fn model_prices(
+        &self,
+        provider: Option<String>,
+    ) -> async_graphql::Result<Vec<ModelPriceGQL>> {
+        let provider = provider.map(|name| parse_provider(&name)).transpose()?;
+        Ok(crate::llm::PriceCatalog::global()
+            .list()
+            .into_iter()
+            .filter(|price| provider.is_none() || provider.as_ref() == Some(&price.provider))
+            .map(Into::into)
+            .collect())
+    }
+
+    /// Get the feature flags, or with a workflow ID the flags applying to its resources
+    async fn feature_flags(
+        &self,
+        workflow_id: Option<String>,
+    ) -> async_graphql::Result<Vec<FeatureFlagGQL>> {
+        Ok(crate::engine::FeatureFlags::global()
+            .list(workflow_id.as_deref())
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
+    /// Get the resources waiting for room in a full state, in the order they will enter
+    async fn state_capacity_queue(
+        &self,
+        ctx: &Context<'_>,
+        workflow_id: String,
+        state: String,
+    ) -> async_graphql::Result<Vec<QueuedResourceGQL>> {
+        let storage = ctx.data::<Box<dyn WorkflowStorage>>()?;
+        let workflow = storage
+            .get_workflow(&workflow_id)
+            .await?
+            .ok_or_else(|| coded_error(ErrorCode::WorkflowNotFound, "Workflow not found"))?;
+        Ok(rules_engine(ctx)
+            .capacity_queues()
+            .waiting(&workflow, &StateId::from(state))
+            .into_iter()
+            .map(|queued| QueuedResourceGQL {
+                resource_id: queued.resource_id.to_string(),
+                priority: queued.priority,
+                enqueued_at: queued.enqueued_at.to_rfc3339(),
+            })
+            .collect())
+    }
+
+    /// Get how the resources of a workflow keep to its state SLAs
+    async fn sla_metrics(
+        &self,
+        ctx: &Context<'_>,
+        workflow_id: String,
+    ) -> async_graphql::Result<Vec<StateSlaMetricsGQL>> {
+        authorize_service(ctx, &workflow_id, ServiceOperation::ReadResources)?;
+        let storage = engine_storage(ctx)?;
+        let metrics = crate::engine::sla_metrics(storage, &workflow_id, chrono::Utc::now())
+            .await
+            .map_err(|e| coded_error(e.code(), e.to_string()))?;
+        Ok(metrics.into_iter().map(Into::into).collect())
+    }
+
+    /// Get a workflow's throughput and time-in-state metrics, collected now
+    async fn workflow_metrics(
+        &self,
+        ctx: &Context<'_>,
+        workflow_id: String,
+    ) -> async_graphql::Result<WorkflowMetricsGQL> {
+        authorize_service(ctx, &workflow_id, ServiceOperation::ReadResources)?;
+        let storage = engine_storage(ctx)?;
+        let metrics =
+            crate::engine::collect_workflow_metrics(storage, &workflow_id, chrono::Utc::now())
+                .await
+                .map_err(|e| coded_error(e.code(), e.to_string()))?;
+        crate::engine::WorkflowMetrics::global().record(metrics.clone());
+        Ok(metrics.into())
+    }
+
+    /// Render a workflow as Graphviz DOT and Mermaid, optionally showing where a
+    /// resource is and has been
+    async fn workflow_diagram(
+        &self,
+        ctx: &Context<'_>,
+        workflow_id: String,
+        resource_id: Option<String>,
+    ) -> async_graphql::Result<WorkflowDiagramGQL> {
+        authorize_service(ctx, &workflow_id, ServiceOperation::ReadResources)?;
+        let resource_id = resource_id
+            .map(|id| {
+                Uuid::parse_str(&id)
+                    .map_err(|_| coded_error(ErrorCode::InvalidInput, "Invalid resource ID"))
+            })
+            .transpose()?;
+        let storage = engine_storage(ctx)?;
+        let diagram = crate::engine::workflow_diagram(storage, &workflow_id, resource_id.as_ref())
+            .await
+            .map_err(|e| coded_error(e.code(), e.to_string()))?;
+        Ok(WorkflowDiagramGQL {
+            dot: diagram.dot,
+            mermaid: diagram.mermaid,
+        })
+    }
+
+    /// List the registered webhook triggers, optionally only those of a workflow
+    async fn webhook_triggers(
+        &self,
+        workflow_id: Option<String>,
+    ) -> async_graphql::Result<Vec<WebhookTriggerGQL>> {
+        Ok(crate::engine::WebhookTriggers::global()
+            .list(workflow_id.as_deref())
+            .iter()
+            .map(WebhookTriggerGQL::from)
+            .collect())
+    }
+
+    /// List the templates workflows can be created from
+    async fn workflow_templates(&self) -> async_graphql::Result<Vec<WorkflowTemplateGQL>> {
+        Ok(crate::engine::TemplateCatalog::global()
+            .list()
+            .iter()
+            .map(WorkflowTemplateGQL::from)
+            .collect())
+    }
+
     /// Get a rule by ID
     async fn rule(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<Option<RuleGQL>> {
         let rule_storage = ctx.data::<std::sync::Arc<dyn crate::engine::rules::RuleStorage>>()?;
@@ -1534,10 +4363,10 @@ impl Query {
         match rule_storage.get_rule(&id).await {
             Ok(Some(stored_rule)) => Ok(Some(RuleGQL::from(&stored_rule))),
             Ok(None) => Ok(None),
-            Err(e) => Err(async_graphql::Error::new(format!(
-                "Failed to get rule: {}",
-                e
-            ))),
+            Err(e) => Err(coded_error(
+                ErrorCode::StorageError,
+                format!("Failed to get rule: {}", e),
+            )),
         }
     }
 
@@ -1557,10 +4386,10 @@ impl Query {
                     .collect();
                 Ok(rules)
             }
-            Err(e) => Err(async_graphql::Error::new(format!(
-                "Failed to list rules: {}",
-                e
-            ))),
+            Err(e) => Err(coded_error(
+                ErrorCode::StorageError,
+                format!("Failed to list rules: {}", e),
+            )),
         }
     }
 
@@ -1580,10 +4409,10 @@ impl Query {
                     .collect();
                 Ok(rules)
             }
-            Err(e) => Err(async_graphql::Error::new(format!(
-                "Failed to get workflow rules: {}",
-                e
-            ))),
+            Err(e) => Err(coded_error(
+                ErrorCode::StorageError,
+                format!("Failed to get workflow rules: {}", e),
+            )),
         }
     }
 }
@@ -1593,6 +4422,30 @@ pub struct Mutation;
 
 #[Object]
 impl Mutation {
+    /// Recompute the materialized resource and execution counters from stored data,
+    /// for recovery when they drifted
+    async fn rebuild_state_counters(&self, ctx: &Context<'_>) -> async_graphql::Result<bool> {
+        let storage = ctx.data::<Box<dyn WorkflowStorage>>()?;
+        storage.rebuild_state_counters().await.map_err(|e| {
+            coded_error(
+                ErrorCode::StorageError,
+                format!("Failed to rebuild resource counters: {}", e),
+            )
+        })?;
+        if let Some(agent_storage) = ctx.data_opt::<std::sync::Arc<dyn AgentStorage>>() {
+            agent_storage
+                .rebuild_execution_counters()
+                .await
+                .map_err(|e| {
+                    coded_error(
+                        ErrorCode::StorageError,
+                        format!("Failed to rebuild execution counters: {}", e),
+                    )
+                })?;
+        }
+        Ok(true)
+    }
+
     /// Create a new workflow definition
     async fn create_workflow(
         &self,
@@ -1600,39 +4453,231 @@ impl Mutation {
         input: WorkflowDefinitionInput,
     ) -> async_graphql::Result<WorkflowGQL> {
         let storage = ctx.data::<Box<dyn WorkflowStorage>>()?;
+        let workflow = input.into_definition(Uuid::new_v4().to_string())?;
 
-        // Convert input to internal types
-        let workflow_id = Uuid::new_v4().to_string();
-        let states: Vec<StateId> = input.states.into_iter().map(StateId::from).collect();
-        let activities: Vec<ActivityDefinition> = input
-            .activities
-            .into_iter()
-            .map(|a| ActivityDefinition {
-                id: ActivityId::from(a.id.as_str()),
-                from_states: a.from_states.into_iter().map(StateId::from).collect(),
-                to_state: StateId::from(a.to_state),
-                conditions: a.conditions,
-                rules: vec![], // Start with empty rules - can be added later via GraphQL
-            })
-            .collect();
+        let created = storage.create_workflow(workflow).await.map_err(|e| {
+            coded_error(
+                ErrorCode::StorageError,
+                format!("Failed to store workflow: {}", e),
+            )
+        })?;
 
-        let workflow = WorkflowDefinition {
-            id: workflow_id,
-            name: input.name,
-            states,
-            activities,
-            initial_state: StateId::from(input.initial_state),
+        Ok(WorkflowGQL::from(&created))
+    }
+
+    /// Register a webhook trigger mapping outside events onto a workflow; webhooks are
+    /// then received at `POST /v1/triggers/{id}`
+    async fn register_webhook_trigger(
+        &self,
+        ctx: &Context<'_>,
+        input: WebhookTriggerInput,
+    ) -> async_graphql::Result<WebhookTriggerGQL> {
+        let storage = engine_storage(ctx)?;
+        let workflow = storage
+            .get_workflow(&input.workflow_id)
+            .await?
+            .ok_or_else(|| {
+                coded_error(
+                    ErrorCode::WorkflowNotFound,
+                    format!("Workflow not found: {}", input.workflow_id),
+                )
+            })?;
+        let source = TriggerSource::parse(&input.source).ok_or_else(|| {
+            coded_error(
+                ErrorCode::InvalidInput,
+                format!(
+                    "Unknown source '{}', expected github, stripe or generic",
+                    input.source
+                ),
+            )
+        })?;
+        let mappings: Vec<TriggerMapping> =
+            serde_json::from_value(input.mappings).map_err(|e| {
+                coded_error(ErrorCode::InvalidInput, format!("Invalid mappings: {}", e))
+            })?;
+
+        let mut trigger = WebhookTrigger::new(&input.id, &input.workflow_id, source, &input.secret);
+        trigger.signature_header = input.signature_header;
+        trigger.event_pointer = input.event_pointer;
+        trigger.mappings = mappings;
+        crate::engine::WebhookTriggers::global()
+            .register(trigger.clone(), &workflow)
+            .map_err(|e| coded_error(e.code(), e.to_string()))?;
+        Ok(WebhookTriggerGQL::from(&trigger))
+    }
+
+    /// Remove a webhook trigger; returns whether it existed
+    async fn remove_webhook_trigger(&self, id: String) -> async_graphql::Result<bool> {
+        Ok(crate::engine::WebhookTriggers::global()
+            .remove(&id)
+            .is_some())
+    }
+
+    /// Create a workflow from a catalog template, resolving its parameters
+    async fn create_workflow_from_template(
+        &self,
+        ctx: &Context<'_>,
+        input: WorkflowFromTemplateInput,
+    ) -> async_graphql::Result<WorkflowGQL> {
+        let storage = ctx.data::<Box<dyn WorkflowStorage>>()?;
+        let parameters = match input.parameters {
+            None => serde_json::Map::new(),
+            Some(serde_json::Value::Object(parameters)) => parameters,
+            Some(_) => {
+                return Err(coded_error(
+                    ErrorCode::InvalidInput,
+                    "Parameters must be a JSON object",
+                ))
+            }
+        };
+        let workflow = crate::engine::TemplateCatalog::global()
+            .instantiate(&input.template_id, &Uuid::new_v4().to_string(), &parameters)
+            .map_err(|e| coded_error(e.code(), e.to_string()))?;
+        check_analysis(&workflow)?;
+
+        let created = storage.create_workflow(workflow).await.map_err(|e| {
+            coded_error(
+                ErrorCode::StorageError,
+                format!("Failed to store workflow: {}", e),
+            )
+        })?;
+
+        Ok(WorkflowGQL::from(&created))
+    }
+
+    /// Publish a new version of a workflow definition; earlier versions stay
+    /// addressable, and their resources can be moved over with `migrateWorkflowResources`
+    async fn publish_workflow_version(
+        &self,
+        ctx: &Context<'_>,
+        id: String,
+        input: WorkflowDefinitionInput,
+    ) -> async_graphql::Result<WorkflowGQL> {
+        let storage = ctx.data::<Box<dyn WorkflowStorage>>()?;
+        let definition = input.into_definition(id.clone())?;
+        let published = crate::engine::publish_version(storage.as_ref(), &id, definition)
+            .await
+            .map_err(versioning_error)?;
+        Ok(WorkflowGQL::from(&published))
+    }
+
+    /// Move the resources of a workflow version onto the current version, mapping
+    /// their states; nothing moves unless every resource can
+    async fn migrate_workflow_resources(
+        &self,
+        ctx: &Context<'_>,
+        input: WorkflowMigrationInput,
+    ) -> async_graphql::Result<WorkflowMigrationGQL> {
+        authorize_service(ctx, &input.workflow_id, ServiceOperation::ExecuteActivities)?;
+        let storage = ctx.data::<Box<dyn WorkflowStorage>>()?;
+        let migration = crate::engine::WorkflowMigration {
+            workflow_id: input.workflow_id,
+            from_version: workflow_version_number(input.from_version)?,
+            to_version: workflow_version_number(input.to_version)?,
+            state_mapping: input
+                .state_mapping
+                .unwrap_or_default()
+                .into_iter()
+                .map(|mapping| (StateId::from(mapping.from), StateId::from(mapping.to)))
+                .collect(),
         };
+        let report = migration
+            .run(storage.as_ref(), input.dry_run.unwrap_or(false))
+            .await
+            .map_err(versioning_error)?;
+        Ok(WorkflowMigrationGQL::from(report))
+    }
+
+    /// Fork a workflow definition for experimentation: its states, activities and
+    /// rules, workflow-specific stored rules and state agents are copied under a new
+    /// ID, recording the origin
+    async fn fork_workflow(
+        &self,
+        ctx: &Context<'_>,
+        id: String,
+        new_name: String,
+    ) -> async_graphql::Result<WorkflowGQL> {
+        let storage = ctx.data::<Box<dyn WorkflowStorage>>()?;
 
-        // Validate workflow before storing
-        workflow
-            .validate()
-            .map_err(|e| async_graphql::Error::new(format!("Invalid workflow: {}", e)))?;
+        let origin = storage.get_workflow(&id).await?.ok_or_else(|| {
+            coded_error(
+                ErrorCode::WorkflowNotFound,
+                format!("Workflow not found: {}", id),
+            )
+        })?;
+        let fork = origin.fork(Uuid::new_v4().to_string(), new_name);
+        let created = storage.create_workflow(fork).await.map_err(|e| {
+            coded_error(
+                ErrorCode::StorageError,
+                format!("Failed to store workflow: {}", e),
+            )
+        })?;
 
-        let created = storage
-            .create_workflow(workflow)
-            .await
-            .map_err(|e| async_graphql::Error::new(format!("Failed to store workflow: {}", e)))?;
+        if let Ok(rule_storage) =
+            ctx.data::<std::sync::Arc<dyn crate::engine::rules::RuleStorage>>()
+        {
+            let rules = rule_storage
+                .get_workflow_rules(&origin.id)
+                .await
+                .map_err(|e| {
+                    coded_error(
+                        ErrorCode::StorageError,
+                        format!("Failed to get workflow rules: {}", e),
+                    )
+                })?;
+            for rule in rules {
+                let now = chrono::Utc::now();
+                let copy = crate::engine::rules::StoredRule {
+                    id: Uuid::new_v4().to_string(),
+                    version: 1,
+                    created_at: now,
+                    updated_at: now,
+                    workflow_id: Some(created.id.clone()),
+                    ..rule
+                };
+                rule_storage.create_rule(copy).await.map_err(|e| {
+                    coded_error(
+                        ErrorCode::StorageError,
+                        format!("Failed to copy rule: {}", e),
+                    )
+                })?;
+            }
+        }
+
+        // State agents not tied to a workflow already apply to the fork
+        if let Ok(agent_storage) = ctx.data::<std::sync::Arc<dyn AgentStorage>>() {
+            let configs = agent_storage
+                .list_state_agent_configs()
+                .await
+                .map_err(|e| {
+                    coded_error(
+                        ErrorCode::StorageError,
+                        format!("Failed to list state agent configs: {}", e),
+                    )
+                })?;
+            for config in configs
+                .into_iter()
+                .filter(|config| config.workflow_id.as_deref() == Some(origin.id.as_str()))
+            {
+                let now = chrono::Utc::now();
+                let copy = StateAgentConfig {
+                    id: Uuid::new_v4(),
+                    workflow_id: Some(created.id.clone()),
+                    created_at: now,
+                    updated_at: now,
+                    ..config
+                };
+                agent_storage
+                    .store_state_agent_config(&copy)
+                    .await
+                    .map_err(|e| {
+                        coded_error(
+                            ErrorCode::StorageError,
+                            format!("Failed to copy state agent config: {}", e),
+                        )
+                    })?;
+            }
+        }
 
         Ok(WorkflowGQL::from(&created))
     }
@@ -1644,6 +4689,7 @@ impl Mutation {
         ctx: &Context<'_>,
         input: ResourceCreateInput,
     ) -> async_graphql::Result<ResourceGQL> {
+        authorize_service(ctx, &input.workflow_id, ServiceOperation::CreateResources)?;
         let storage = ctx.data::<Box<dyn WorkflowStorage>>()?;
 
         // Get workflow to determine initial place
@@ -1651,36 +4697,71 @@ impl Mutation {
             .get_workflow(&input.workflow_id)
             .await?
             .ok_or_else(|| {
-                async_graphql::Error::new(format!("Workflow not found: {}", input.workflow_id))
+                coded_error(
+                    ErrorCode::WorkflowNotFound,
+                    format!("Workflow not found: {}", input.workflow_id),
+                )
             })?;
 
-        let initial_state = input
-            .initial_state
-            .map(StateId::from)
-            .unwrap_or_else(|| workflow.initial_state.clone());
+        let resource = build_resource(&workflow, input)?;
 
-        let mut resource = Resource::new(&input.workflow_id, initial_state);
+        let created = storage.create_resource(resource).await.map_err(|e| {
+            coded_error(
+                ErrorCode::StorageError,
+                format!("Failed to store resource: {}", e),
+            )
+        })?;
 
-        // Set data if provided
-        if let Some(data) = input.data {
-            resource.data = data;
-        }
+        Ok(ResourceGQL::from(&created))
+    }
 
-        // Set metadata if provided
-        if let Some(metadata) = input.metadata {
-            if let Some(metadata_obj) = metadata.as_object() {
-                for (key, value) in metadata_obj {
-                    resource.set_metadata(key, value.clone());
-                }
-            }
-        }
+    /// Update a resource's metadata, merging the given keys unless `replace` is set;
+    /// the result must conform to the workflow's metadata schema
+    async fn update_resource_metadata(
+        &self,
+        ctx: &Context<'_>,
+        input: ResourceMetadataUpdateInput,
+    ) -> async_graphql::Result<ResourceGQL> {
+        let storage = ctx.data::<Box<dyn WorkflowStorage>>()?;
 
-        let created = storage
-            .create_resource(resource)
-            .await
-            .map_err(|e| async_graphql::Error::new(format!("Failed to store resource: {}", e)))?;
+        let resource_id = input
+            .resource_id
+            .parse::<Uuid>()
+            .map_err(|_| coded_error(ErrorCode::InvalidInput, "Invalid resource ID format"))?;
+        let serde_json::Value::Object(metadata) = input.metadata else {
+            return Err(coded_error(
+                ErrorCode::InvalidInput,
+                "Metadata must be a JSON object",
+            ));
+        };
 
-        Ok(ResourceGQL::from(&created))
+        let mut resource = storage
+            .get_resource(&resource_id)
+            .await?
+            .ok_or_else(|| coded_error(ErrorCode::ResourceNotFound, "Resource not found"))?;
+        authorize_service(ctx, &resource.workflow_id, ServiceOperation::UpdateMetadata)?;
+        let workflow = storage
+            .get_workflow(&resource.workflow_id)
+            .await?
+            .ok_or_else(|| coded_error(ErrorCode::WorkflowNotFound, "Workflow not found"))?;
+
+        if input.replace.unwrap_or(false) {
+            resource.metadata.clear();
+        }
+        for (key, value) in metadata {
+            resource.set_metadata(key, value);
+        }
+        check_confidential_metadata(&workflow, &resource.metadata)?;
+        check_metadata_schema(&workflow, &resource.metadata)?;
+
+        let updated = storage.update_resource(resource).await.map_err(|e| {
+            coded_error(
+                ErrorCode::StorageError,
+                format!("Failed to update resource: {}", e),
+            )
+        })?;
+
+        Ok(ResourceGQL::from(&updated))
     }
 
     /// Execute an activity - automatically uses NATS-aware execution when available
@@ -1696,7 +4777,7 @@ impl Mutation {
             let resource_id = input
                 .resource_id
                 .parse::<Uuid>()
-                .map_err(|_| async_graphql::Error::new("Invalid resource ID format"))?;
+                .map_err(|_| coded_error(ErrorCode::InvalidInput, "Invalid resource ID format"))?;
 
             // Get the resource with retry logic for timing issues
             let mut resource = None;
@@ -1715,35 +4796,68 @@ impl Mutation {
                     }
                     Ok(None) => {
                         if attempt == 2 {
-                            return Err(async_graphql::Error::new(
+                            return Err(coded_error(
+                                ErrorCode::ResourceNotFound,
                                 "Resource not found after retries",
                             ));
                         }
                         continue;
                     }
                     Err(e) => {
-                        return Err(async_graphql::Error::new(format!(
-                            "Failed to get resource: {}",
-                            e
-                        )));
+                        return Err(coded_error(
+                            ErrorCode::StorageError,
+                            format!("Failed to get resource: {}", e),
+                        ));
                     }
                 }
             }
 
             let resource = resource.unwrap();
+            authorize_service(
+                ctx,
+                &resource.workflow_id,
+                ServiceOperation::ExecuteActivities,
+            )?;
 
             let workflow = nats_storage
                 .get_workflow(&resource.workflow_id)
                 .await?
-                .ok_or_else(|| async_graphql::Error::new("Workflow not found"))?;
+                .ok_or_else(|| coded_error(ErrorCode::WorkflowNotFound, "Workflow not found"))?;
 
             let activity_id = ActivityId::from(input.activity_id.clone());
             let current_state = StateId::from(resource.current_state());
 
+            check_not_manual(&workflow, &activity_id)?;
+            check_active(&resource)?;
+
+            if let Some(updated) = execute_parallel(
+                ctx,
+                nats_storage.as_ref(),
+                &workflow,
+                &resource,
+                &activity_id,
+                None,
+            )
+            .await?
+            {
+                return Ok(ResourceGQL::from(&updated));
+            }
+
             // Check if activity is valid
-            let target_state = workflow
-                .can_execute_activity(&current_state, &activity_id)
-                .ok_or_else(|| async_graphql::Error::new("Invalid activity"))?;
+            let target_state =
+                routed_target_state(ctx, &workflow, &resource, &current_state, &activity_id)
+                    .ok_or_else(|| {
+                        coded_error(ErrorCode::InvalidStateTransition, "Invalid activity")
+                    })?;
+
+            check_state_capacity(
+                ctx,
+                nats_storage.as_ref(),
+                &workflow,
+                &resource,
+                &activity_id,
+            )
+            .await?;
 
             // Use NATS-aware execution for proper state persistence
             let executed_resource = nats_storage
@@ -1755,8 +4869,14 @@ impl Mutation {
                 )
                 .await
                 .map_err(|e| {
-                    async_graphql::Error::new(format!("Failed to execute activity: {}", e))
+                    coded_error(
+                        ErrorCode::Internal,
+                        format!("Failed to execute activity: {}", e),
+                    )
                 })?;
+            rules_engine(ctx)
+                .capacity_queues()
+                .withdraw(&executed_resource.id);
 
             Ok(ResourceGQL::from(&executed_resource))
         } else {
@@ -1766,7 +4886,7 @@ impl Mutation {
             let resource_id = input
                 .resource_id
                 .parse::<Uuid>()
-                .map_err(|_| async_graphql::Error::new("Invalid resource ID format"))?;
+                .map_err(|_| coded_error(ErrorCode::InvalidInput, "Invalid resource ID format"))?;
 
             // Get the resource with retry logic for timing issues
             let mut resource = None;
@@ -1785,53 +4905,350 @@ impl Mutation {
                     }
                     Ok(None) => {
                         if attempt == 2 {
-                            return Err(async_graphql::Error::new(
+                            return Err(coded_error(
+                                ErrorCode::ResourceNotFound,
                                 "Resource not found after retries",
                             ));
                         }
                         continue;
                     }
                     Err(e) => {
-                        return Err(async_graphql::Error::new(format!(
-                            "Failed to get resource: {}",
-                            e
-                        )));
+                        return Err(coded_error(
+                            ErrorCode::StorageError,
+                            format!("Failed to get resource: {}", e),
+                        ));
                     }
                 }
             }
 
             let mut resource = resource.unwrap();
+            authorize_service(
+                ctx,
+                &resource.workflow_id,
+                ServiceOperation::ExecuteActivities,
+            )?;
 
             let workflow = storage
                 .get_workflow(&resource.workflow_id)
                 .await?
-                .ok_or_else(|| async_graphql::Error::new("Workflow not found"))?;
+                .ok_or_else(|| coded_error(ErrorCode::WorkflowNotFound, "Workflow not found"))?;
 
             let activity_id = ActivityId::from(input.activity_id);
             let current_state = StateId::from(resource.current_state());
 
-            // Check if activity is valid
-            let target_state = workflow
-                .can_execute_activity(&current_state, &activity_id)
-                .ok_or_else(|| async_graphql::Error::new("Invalid activity"))?;
+            check_not_manual(&workflow, &activity_id)?;
+            check_active(&resource)?;
 
             // Update with any provided data before executing activity
             if let Some(data) = input.data {
                 resource.data = data;
             }
 
+            if let Some(updated) = execute_parallel(
+                ctx,
+                storage.as_ref(),
+                &workflow,
+                &resource,
+                &activity_id,
+                None,
+            )
+            .await?
+            {
+                return Ok(ResourceGQL::from(&updated));
+            }
+
+            // Check if activity is valid
+            let target_state =
+                routed_target_state(ctx, &workflow, &resource, &current_state, &activity_id)
+                    .ok_or_else(|| {
+                        coded_error(ErrorCode::InvalidStateTransition, "Invalid activity")
+                    })?;
+
+            check_state_capacity(ctx, storage.as_ref(), &workflow, &resource, &activity_id).await?;
+
             // Execute the activity
             resource.execute_activity(target_state.clone(), activity_id);
 
             // Store the updated resource
             let updated = storage.update_resource(resource).await.map_err(|e| {
-                async_graphql::Error::new(format!("Failed to update resource: {}", e))
+                coded_error(
+                    ErrorCode::StorageError,
+                    format!("Failed to update resource: {}", e),
+                )
             })?;
+            rules_engine(ctx).capacity_queues().withdraw(&updated.id);
 
             Ok(ResourceGQL::from(&updated))
         }
     }
 
+    /// Execute activities on up to 1000 resources in one call, reporting each item's
+    /// outcome; capacity is checked against occupancy before the batch
+    async fn execute_activity_batch(
+        &self,
+        ctx: &Context<'_>,
+        input: ActivityBatchInput,
+    ) -> async_graphql::Result<BatchResultGQL> {
+        let storage = engine_storage(ctx)?;
+        let atomic = input.atomic.unwrap_or(false);
+        check_batch(storage, input.items.len(), atomic)?;
+
+        let mut workflows = HashMap::new();
+        let mut seen = HashSet::new();
+        let mut prepared = Vec::with_capacity(input.items.len());
+        for item in input.items {
+            prepared
+                .push(prepare_batch_activity(ctx, storage, &mut workflows, &mut seen, item).await);
+        }
+
+        let result = finish_batch(prepared, atomic, |resources| {
+            storage.update_resources(resources)
+        })
+        .await;
+        let rules = rules_engine(ctx);
+        for item in &result.items {
+            if let Some(id) = item.resource.as_ref().and_then(|r| r.id.parse().ok()) {
+                rules.capacity_queues().withdraw(&id);
+            }
+        }
+        Ok(result)
+    }
+
+    /// Create up to 1000 resources in one call, reporting each item's outcome
+    async fn create_resources_batch(
+        &self,
+        ctx: &Context<'_>,
+        input: ResourceBatchCreateInput,
+    ) -> async_graphql::Result<BatchResultGQL> {
+        let storage = engine_storage(ctx)?;
+        let atomic = input.atomic.unwrap_or(false);
+        check_batch(storage, input.items.len(), atomic)?;
+
+        let mut workflows = HashMap::new();
+        let mut prepared = Vec::with_capacity(input.items.len());
+        for item in input.items {
+            let resource = async {
+                authorize_service(ctx, &item.workflow_id, ServiceOperation::CreateResources)?;
+                let workflow = batch_workflow(storage, &mut workflows, &item.workflow_id).await?;
+                build_resource(workflow, item)
+            }
+            .await;
+            prepared.push(resource);
+        }
+
+        Ok(finish_batch(prepared, atomic, |resources| {
+            storage.create_resources(resources)
+        })
+        .await)
+    }
+
+    /// Reserve a human task for `actor`, so nobody else decides it
+    async fn claim_human_task(
+        &self,
+        ctx: &Context<'_>,
+        resource_id: String,
+        activity_id: String,
+        actor: String,
+    ) -> async_graphql::Result<HumanTaskGQL> {
+        let storage = engine_storage(ctx)?;
+        let resource_id = authorize_resource(ctx, storage, &resource_id).await?;
+        let task = crate::engine::HumanTasks::global()
+            .claim(
+                storage,
+                &resource_id,
+                &ActivityId::from(activity_id),
+                &actor,
+            )
+            .await
+            .map_err(human_task_error)?;
+        Ok(HumanTaskGQL::from(task))
+    }
+
+    /// Approve a human task, executing its activity; the decision, actor and comment
+    /// are recorded in the resource's history
+    async fn approve_human_task(
+        &self,
+        ctx: &Context<'_>,
+        input: HumanTaskDecisionInput,
+    ) -> async_graphql::Result<ResourceGQL> {
+        let storage = engine_storage(ctx)?;
+        let resource_id = authorize_resource(ctx, storage, &input.resource_id).await?;
+        let resource = crate::engine::HumanTasks::global()
+            .approve(
+                storage,
+                &resource_id,
+                &ActivityId::from(input.activity_id),
+                &input.actor,
+                input.comment,
+            )
+            .await
+            .map_err(human_task_error)?;
+        rules_engine(ctx).capacity_queues().withdraw(&resource.id);
+        Ok(ResourceGQL::from(&resource))
+    }
+
+    /// Reject a human task, moving the resource to the task's rejection state if it has
+    /// one; the decision, actor and comment are recorded in the resource's history
+    async fn reject_human_task(
+        &self,
+        ctx: &Context<'_>,
+        input: HumanTaskDecisionInput,
+    ) -> async_graphql::Result<ResourceGQL> {
+        let storage = engine_storage(ctx)?;
+        let resource_id = authorize_resource(ctx, storage, &input.resource_id).await?;
+        let resource = crate::engine::HumanTasks::global()
+            .reject(
+                storage,
+                &resource_id,
+                &ActivityId::from(input.activity_id),
+                &input.actor,
+                input.comment,
+            )
+            .await
+            .map_err(human_task_error)?;
+        rules_engine(ctx).capacity_queues().withdraw(&resource.id);
+        Ok(ResourceGQL::from(&resource))
+    }
+
+    /// Pause a resource: no activity executes on it until it is resumed
+    async fn pause_resource(
+        &self,
+        ctx: &Context<'_>,
+        input: ResourceLifecycleInput,
+    ) -> async_graphql::Result<ResourceGQL> {
+        let storage = engine_storage(ctx)?;
+        let resource_id = authorize_resource(ctx, storage, &input.resource_id).await?;
+        let resource = crate::engine::lifecycle::pause(
+            storage,
+            &EventBus::global(),
+            &resource_id,
+            input.actor,
+            input.reason,
+        )
+        .await
+        .map_err(lifecycle_error)?;
+        rules_engine(ctx).capacity_queues().withdraw(&resource.id);
+        Ok(ResourceGQL::from(&resource))
+    }
+
+    /// Resume a paused resource
+    async fn resume_resource(
+        &self,
+        ctx: &Context<'_>,
+        input: ResourceLifecycleInput,
+    ) -> async_graphql::Result<ResourceGQL> {
+        let storage = engine_storage(ctx)?;
+        let resource_id = authorize_resource(ctx, storage, &input.resource_id).await?;
+        let resource = crate::engine::lifecycle::resume(
+            storage,
+            &EventBus::global(),
+            &resource_id,
+            input.actor,
+        )
+        .await
+        .map_err(lifecycle_error)?;
+        Ok(ResourceGQL::from(&resource))
+    }
+
+    /// Cancel a resource for good, with the reason why; it stays in its state but no
+    /// activity executes on it any more
+    async fn cancel_resource(
+        &self,
+        ctx: &Context<'_>,
+        input: ResourceLifecycleInput,
+    ) -> async_graphql::Result<ResourceGQL> {
+        let storage = engine_storage(ctx)?;
+        let resource_id = authorize_resource(ctx, storage, &input.resource_id).await?;
+        let resource = crate::engine::lifecycle::cancel(
+            storage,
+            &EventBus::global(),
+            &resource_id,
+            input.actor,
+            input.reason.unwrap_or_default(),
+        )
+        .await
+        .map_err(lifecycle_error)?;
+        rules_engine(ctx).capacity_queues().withdraw(&resource.id);
+        Ok(ResourceGQL::from(&resource))
+    }
+
+    /// Deliver a named signal to a resource, from another resource or from the caller;
+    /// activities guarded by a matching `SignalReceived` rule can then fire
+    async fn send_signal(
+        &self,
+        ctx: &Context<'_>,
+        input: SendSignalInput,
+    ) -> async_graphql::Result<ResourceGQL> {
+        let storage = engine_storage(ctx)?;
+        let resource_id = authorize_resource(ctx, storage, &input.resource_id).await?;
+        let sender = input
+            .sender_resource_id
+            .map(|sender| sender.parse::<Uuid>())
+            .transpose()
+            .map_err(|_| {
+                coded_error(ErrorCode::InvalidInput, "Invalid sender resource ID format")
+            })?;
+        let resource = crate::engine::send_signal(
+            storage,
+            &EventBus::global(),
+            &resource_id,
+            &input.signal,
+            input.payload.unwrap_or(serde_json::Value::Null),
+            sender,
+        )
+        .await
+        .map_err(|e| coded_error(e.code(), e.to_string()))?;
+        Ok(ResourceGQL::from(&resource))
+    }
+
+    /// Roll a resource back saga-style: the compensations of the activities in its
+    /// history run in reverse order, skipping activities already compensated
+    async fn compensate_resource(
+        &self,
+        ctx: &Context<'_>,
+        resource_id: String,
+    ) -> async_graphql::Result<ResourceGQL> {
+        let resource_id = resource_id
+            .parse::<Uuid>()
+            .map_err(|_| coded_error(ErrorCode::InvalidInput, "Invalid resource ID format"))?;
+        let storage = engine_storage(ctx)?;
+
+        let resource = storage
+            .get_resource(&resource_id)
+            .await?
+            .ok_or_else(|| coded_error(ErrorCode::ResourceNotFound, "Resource not found"))?;
+        authorize_service(
+            ctx,
+            &resource.workflow_id,
+            ServiceOperation::ExecuteActivities,
+        )?;
+
+        let compensation = crate::engine::compensate(storage, &resource_id)
+            .await
+            .map_err(|e| match e {
+                crate::CircuitBreakerError::NotFound(_) => {
+                    coded_error(ErrorCode::ResourceNotFound, "Resource not found")
+                }
+                crate::CircuitBreakerError::WorkflowNotFound { .. } => {
+                    coded_error(ErrorCode::WorkflowNotFound, "Workflow not found")
+                }
+                crate::CircuitBreakerError::InvalidTransition { .. }
+                | crate::CircuitBreakerError::ResourceHalted { .. } => coded_error(
+                    ErrorCode::InvalidStateTransition,
+                    format!("Cannot compensate resource: {}", e),
+                ),
+                e => coded_error(
+                    ErrorCode::StorageError,
+                    format!("Failed to compensate resource: {}", e),
+                ),
+            })?;
+        rules_engine(ctx)
+            .capacity_queues()
+            .withdraw(&compensation.resource.id);
+
+        Ok(ResourceGQL::from(&compensation.resource))
+    }
+
     /// Create a new agent
     async fn create_agent(
         &self,
@@ -1870,7 +5287,12 @@ impl Mutation {
                 headers: std::collections::HashMap::new(),
                 model: input.llm_provider.model,
             },
-            _ => return Err(async_graphql::Error::new("Invalid LLM provider type")),
+            _ => {
+                return Err(coded_error(
+                    ErrorCode::InvalidInput,
+                    "Invalid LLM provider type",
+                ))
+            }
         };
 
         let llm_config = LLMConfig {
@@ -1902,10 +5324,12 @@ impl Mutation {
             updated_at: now,
         };
 
-        agent_storage
-            .store_agent(&agent)
-            .await
-            .map_err(|e| async_graphql::Error::new(format!("Failed to store agent: {}", e)))?;
+        agent_storage.store_agent(&agent).await.map_err(|e| {
+            coded_error(
+                ErrorCode::StorageError,
+                format!("Failed to store agent: {}", e),
+            )
+        })?;
 
         Ok(AgentDefinitionGQL::from(&agent))
     }
@@ -1923,18 +5347,26 @@ impl Mutation {
         agent_storage
             .get_agent(&agent_id)
             .await?
-            .ok_or_else(|| async_graphql::Error::new("Agent not found"))?;
+            .ok_or_else(|| coded_error(ErrorCode::AgentNotFound, "Agent not found"))?;
 
         let state_id = StateId::from(input.state_id);
 
         // Convert input mappings from JSON
         let input_mapping: std::collections::HashMap<String, String> =
-            serde_json::from_value(input.input_mapping)
-                .map_err(|e| async_graphql::Error::new(format!("Invalid input mapping: {}", e)))?;
+            serde_json::from_value(input.input_mapping).map_err(|e| {
+                coded_error(
+                    ErrorCode::InvalidInput,
+                    format!("Invalid input mapping: {}", e),
+                )
+            })?;
 
         let output_mapping: std::collections::HashMap<String, String> =
-            serde_json::from_value(input.output_mapping)
-                .map_err(|e| async_graphql::Error::new(format!("Invalid output mapping: {}", e)))?;
+            serde_json::from_value(input.output_mapping).map_err(|e| {
+                coded_error(
+                    ErrorCode::InvalidInput,
+                    format!("Invalid output mapping: {}", e),
+                )
+            })?;
 
         let llm_config = input.llm_config.map(|config| LLMConfig {
             temperature: config.temperature as f32,
@@ -1961,6 +5393,7 @@ impl Mutation {
         let config = StateAgentConfig {
             id: Uuid::new_v4(),
             state_id,
+            workflow_id: input.workflow_id,
             agent_id,
             llm_config,
             trigger_conditions: vec![], // TODO: Add trigger conditions input
@@ -1978,7 +5411,10 @@ impl Mutation {
             .store_state_agent_config(&config)
             .await
             .map_err(|e| {
-                async_graphql::Error::new(format!("Failed to store state agent config: {}", e))
+                coded_error(
+                    ErrorCode::StorageError,
+                    format!("Failed to store state agent config: {}", e),
+                )
             })?;
 
         Ok(StateAgentConfigGQL::from(&config))
@@ -1996,7 +5432,7 @@ impl Mutation {
         let resource_id = input
             .resource_id
             .parse::<Uuid>()
-            .map_err(|_| async_graphql::Error::new("Invalid resource ID format"))?;
+            .map_err(|_| coded_error(ErrorCode::InvalidInput, "Invalid resource ID format"))?;
 
         // Get the resource with retry logic for timing issues
         let mut resource = None;
@@ -2013,17 +5449,18 @@ impl Mutation {
                 }
                 Ok(None) => {
                     if attempt == 2 {
-                        return Err(async_graphql::Error::new(
+                        return Err(coded_error(
+                            ErrorCode::ResourceNotFound,
                             "Resource not found after retries",
                         ));
                     }
                     continue;
                 }
                 Err(e) => {
-                    return Err(async_graphql::Error::new(format!(
-                        "Failed to get resource: {}",
-                        e
-                    )));
+                    return Err(coded_error(
+                        ErrorCode::StorageError,
+                        format!("Failed to get resource: {}", e),
+                    ));
                 }
             }
         }
@@ -2034,7 +5471,10 @@ impl Mutation {
             .execute_state_agents(&resource)
             .await
             .map_err(|e| {
-                async_graphql::Error::new(format!("Failed to execute state agents: {}", e))
+                coded_error(
+                    ErrorCode::Internal,
+                    format!("Failed to execute state agents: {}", e),
+                )
             })?;
 
         Ok(executions.iter().map(AgentExecutionGQL::from).collect())
@@ -2048,17 +5488,24 @@ impl Mutation {
         ctx: &Context<'_>,
         input: CreateWorkflowInstanceInput,
     ) -> async_graphql::Result<NATSResourceGQL> {
+        authorize_service(ctx, &input.workflow_id, ServiceOperation::CreateResources)?;
         let storage = ctx.data::<Box<dyn WorkflowStorage>>()?;
 
         // Get the workflow definition to find initial place
         let workflow = storage
             .get_workflow(&input.workflow_id)
             .await
-            .map_err(|e| async_graphql::Error::new(format!("Failed to get workflow: {}", e)))?
-            .ok_or_else(|| async_graphql::Error::new("Workflow not found"))?;
+            .map_err(|e| {
+                coded_error(
+                    ErrorCode::StorageError,
+                    format!("Failed to get workflow: {}", e),
+                )
+            })?
+            .ok_or_else(|| coded_error(ErrorCode::WorkflowNotFound, "Workflow not found"))?;
 
         // Create new resource
         let mut resource = Resource::new(&input.workflow_id, workflow.initial_state.clone());
+        resource.workflow_version = workflow.version;
 
         // Set initial data if provided
         if let Some(data) = input.initial_data {
@@ -2073,6 +5520,8 @@ impl Mutation {
                 }
             }
         }
+        check_confidential_metadata(&workflow, &resource.metadata)?;
+        check_metadata_schema(&workflow, &resource.metadata)?;
 
         // Try to use NATS storage for enhanced functionality
         if let Ok(nats_storage) =
@@ -2082,13 +5531,19 @@ impl Mutation {
                 .create_resource_with_event(resource, input.triggered_by)
                 .await
                 .map_err(|e| {
-                    async_graphql::Error::new(format!("Failed to create NATS resource: {}", e))
+                    coded_error(
+                        ErrorCode::StorageError,
+                        format!("Failed to create NATS resource: {}", e),
+                    )
                 })?;
             Ok(NATSResourceGQL::from(&created_resource))
         } else {
             // Fallback to regular storage
             let created_resource = storage.create_resource(resource).await.map_err(|e| {
-                async_graphql::Error::new(format!("Failed to create resource: {}", e))
+                coded_error(
+                    ErrorCode::StorageError,
+                    format!("Failed to create resource: {}", e),
+                )
             })?;
             Ok(NATSResourceGQL::from(&created_resource))
         }
@@ -2105,7 +5560,7 @@ impl Mutation {
         let resource_id = input
             .resource_id
             .parse::<Uuid>()
-            .map_err(|_| async_graphql::Error::new("Invalid resource ID format"))?;
+            .map_err(|_| coded_error(ErrorCode::InvalidInput, "Invalid resource ID format"))?;
 
         // Try to use NATS storage directly first for consistent behavior
         if let Ok(nats_storage) =
@@ -2128,53 +5583,84 @@ impl Mutation {
                     }
                     Ok(None) => {
                         if attempt == 2 {
-                            return Err(async_graphql::Error::new(
+                            return Err(coded_error(
+                                ErrorCode::ResourceNotFound,
                                 "Resource not found after retries",
                             ));
                         }
                         continue;
                     }
                     Err(e) => {
-                        return Err(async_graphql::Error::new(format!(
-                            "Failed to get resource: {}",
-                            e
-                        )));
+                        return Err(coded_error(
+                            ErrorCode::StorageError,
+                            format!("Failed to get resource: {}", e),
+                        ));
                     }
                 }
             }
 
             let mut resource = resource.unwrap();
+            authorize_service(
+                ctx,
+                &resource.workflow_id,
+                ServiceOperation::ExecuteActivities,
+            )?;
 
             // Get the workflow to validate activity
             let workflow = nats_storage
                 .get_workflow(&resource.workflow_id)
                 .await
-                .map_err(|e| async_graphql::Error::new(format!("Failed to get workflow: {}", e)))?
-                .ok_or_else(|| async_graphql::Error::new("Workflow not found"))?;
+                .map_err(|e| {
+                    coded_error(
+                        ErrorCode::StorageError,
+                        format!("Failed to get workflow: {}", e),
+                    )
+                })?
+                .ok_or_else(|| coded_error(ErrorCode::WorkflowNotFound, "Workflow not found"))?;
 
             let activity_id = ActivityId::from(input.activity_id);
             let new_state = StateId::from(input.new_state);
             let current_state = resource.state.clone();
 
-            // Validate activity
-            if !workflow
-                .can_execute_activity(&current_state, &activity_id)
-                .map(|s| *s == new_state)
-                .unwrap_or(false)
-            {
-                return Err(async_graphql::Error::new("Invalid activity"));
-            }
+            check_not_manual(&workflow, &activity_id)?;
+            check_active(&resource)?;
 
             // Update resource data if provided
             if let Some(data) = input.data {
                 resource.data = data;
             }
 
+            if let Some(updated) = execute_parallel(
+                ctx,
+                nats_storage.as_ref(),
+                &workflow,
+                &resource,
+                &activity_id,
+                Some(&new_state),
+            )
+            .await?
+            {
+                return Ok(NATSResourceGQL::from(&updated));
+            }
+
+            // Validate activity
+            if routed_target_state(ctx, &workflow, &resource, &current_state, &activity_id)
+                != Some(&new_state)
+            {
+                return Err(coded_error(
+                    ErrorCode::InvalidStateTransition,
+                    "Invalid activity",
+                ));
+            }
+
             let executed_resource = nats_storage
                 .execute_activity_with_nats(resource, new_state, activity_id, input.triggered_by)
                 .await
                 .map_err(|e| {
-                    async_graphql::Error::new(format!("Failed to execute NATS activity: {}", e))
+                    coded_error(
+                        ErrorCode::Internal,
+                        format!("Failed to execute NATS activity: {}", e),
+                    )
                 })?;
             Ok(NATSResourceGQL::from(&executed_resource))
         } else {
@@ -2198,52 +5684,83 @@ impl Mutation {
                     }
                     Ok(None) => {
                         if attempt == 2 {
-                            return Err(async_graphql::Error::new(
+                            return Err(coded_error(
+                                ErrorCode::ResourceNotFound,
                                 "Resource not found after retries",
                             ));
                         }
                         continue;
                     }
                     Err(e) => {
-                        return Err(async_graphql::Error::new(format!(
-                            "Failed to get resource: {}",
-                            e
-                        )));
+                        return Err(coded_error(
+                            ErrorCode::StorageError,
+                            format!("Failed to get resource: {}", e),
+                        ));
                     }
                 }
             }
 
             let mut resource = resource.unwrap();
+            authorize_service(
+                ctx,
+                &resource.workflow_id,
+                ServiceOperation::ExecuteActivities,
+            )?;
 
             // Get the workflow to validate activity
             let workflow = storage
                 .get_workflow(&resource.workflow_id)
                 .await
-                .map_err(|e| async_graphql::Error::new(format!("Failed to get workflow: {}", e)))?
-                .ok_or_else(|| async_graphql::Error::new("Workflow not found"))?;
+                .map_err(|e| {
+                    coded_error(
+                        ErrorCode::StorageError,
+                        format!("Failed to get workflow: {}", e),
+                    )
+                })?
+                .ok_or_else(|| coded_error(ErrorCode::WorkflowNotFound, "Workflow not found"))?;
 
             let activity_id = ActivityId::from(input.activity_id);
             let new_state = StateId::from(input.new_state);
             let current_state = resource.state.clone();
 
-            // Validate activity
-            if !workflow
-                .can_execute_activity(&current_state, &activity_id)
-                .map(|s| *s == new_state)
-                .unwrap_or(false)
-            {
-                return Err(async_graphql::Error::new("Invalid activity"));
-            }
+            check_not_manual(&workflow, &activity_id)?;
+            check_active(&resource)?;
 
             // Update resource data if provided
             if let Some(data) = input.data {
                 resource.data = data;
             }
 
+            if let Some(updated) = execute_parallel(
+                ctx,
+                storage.as_ref(),
+                &workflow,
+                &resource,
+                &activity_id,
+                Some(&new_state),
+            )
+            .await?
+            {
+                return Ok(NATSResourceGQL::from(&updated));
+            }
+
+            // Validate activity
+            if routed_target_state(ctx, &workflow, &resource, &current_state, &activity_id)
+                != Some(&new_state)
+            {
+                return Err(coded_error(
+                    ErrorCode::InvalidStateTransition,
+                    "Invalid activity",
+                ));
+            }
+
             // Regular activity execution
             resource.execute_activity(new_state, activity_id);
             let updated_resource = storage.update_resource(resource).await.map_err(|e| {
-                async_graphql::Error::new(format!("Failed to update resource: {}", e))
+                coded_error(
+                    ErrorCode::StorageError,
+                    format!("Failed to update resource: {}", e),
+                )
             })?;
             Ok(NATSResourceGQL::from(&updated_resource))
         }
@@ -2257,9 +5774,23 @@ impl Mutation {
     ) -> async_graphql::Result<LLMResponseGQL> {
         // Create router
         let router = crate::llm::router::LLMRouter::new().await.map_err(|e| {
-            async_graphql::Error::new(format!("Failed to initialize router: {}", e))
+            coded_error(
+                ErrorCode::ProviderUnavailable,
+                format!("Failed to initialize router: {}", e),
+            )
         })?;
 
+        let response_format = input
+            .response_format
+            .map(serde_json::from_value::<crate::llm::ResponseFormat>)
+            .transpose()
+            .map_err(|e| {
+                coded_error(
+                    ErrorCode::InvalidInput,
+                    format!("Invalid response format: {}", e),
+                )
+            })?;
+
         // Convert GraphQL input to LLM request
         let llm_request = crate::llm::LLMRequest {
             id: uuid::Uuid::new_v4(),
@@ -2273,11 +5804,15 @@ impl Mutation {
                         "user" => crate::llm::MessageRole::User,
                         "assistant" => crate::llm::MessageRole::Assistant,
                         "function" => crate::llm::MessageRole::Function,
+                        "tool" => crate::llm::MessageRole::Tool,
                         _ => crate::llm::MessageRole::User,
                     },
                     content: msg.content,
                     name: msg.name,
                     function_call: None,
+                    tool_calls: None,
+                    tool_call_id: None,
+                    content_parts: None,
                 })
                 .collect(),
             temperature: input.temperature.map(|t| t as f64),
@@ -2300,13 +5835,18 @@ impl Mutation {
                 }
                 meta
             },
+            response_format,
+            tool_choice: None,
+            n: None,
         };
 
         // Make the actual LLM request
-        let response = router
-            .chat_completion(llm_request)
-            .await
-            .map_err(|e| async_graphql::Error::new(format!("LLM request failed: {}", e)))?;
+        let response = router.chat_completion(llm_request).await.map_err(|e| {
+            coded_error(
+                ErrorCode::ProviderError,
+                format!("LLM request failed: {}", e),
+            )
+        })?;
 
         // Convert response back to GraphQL format
         Ok(LLMResponseGQL {
@@ -2323,6 +5863,7 @@ impl Mutation {
                             crate::llm::MessageRole::User => "user".to_string(),
                             crate::llm::MessageRole::Assistant => "assistant".to_string(),
                             crate::llm::MessageRole::Function => "function".to_string(),
+                            crate::llm::MessageRole::Tool => "tool".to_string(),
                         },
                         content: choice.message.content,
                         name: choice.message.name,
@@ -2414,6 +5955,73 @@ impl Mutation {
         })
     }
 
+    /// Set a model's price in the price catalog, overriding its built-in default
+    async fn set_model_price(
+        &self,
+        input: ModelPriceInput,
+    ) -> async_graphql::Result<ModelPriceGQL> {
+        let price = crate::llm::ModelPrice::new(
+            parse_provider(&input.provider)?,
+            input.model,
+            input.input_cost_per_token,
+            input.output_cost_per_token,
+        );
+        crate::llm::PriceCatalog::global()
+            .set_price(price.clone())
+            .map_err(|e| coded_error(ErrorCode::InvalidInput, e.to_string()))?;
+        Ok(price.into())
+    }
+
+    /// Remove a model's price from the price catalog so its built-in default applies again
+    async fn remove_model_price(
+        &self,
+        provider: String,
+        model: String,
+    ) -> async_graphql::Result<bool> {
+        let provider = parse_provider(&provider)?;
+        Ok(crate::llm::PriceCatalog::global()
+            .remove_price(&provider, &model)
+            .is_some())
+    }
+
+    /// Set a feature flag, replacing the flag of the same name and scope
+    async fn set_feature_flag(
+        &self,
+        input: FeatureFlagInput,
+    ) -> async_graphql::Result<FeatureFlagGQL> {
+        let mut flag = crate::models::FeatureFlag::new(input.name)
+            .enabled(input.enabled)
+            .rollout_percentage(input.rollout_percentage.unwrap_or(100.0));
+        flag.workflow_id = input.workflow_id;
+        flag.description = input.description.unwrap_or_default();
+        flag.targeting = input
+            .targeting
+            .unwrap_or_default()
+            .into_iter()
+            .enumerate()
+            .map(|(index, condition)| Rule {
+                id: format!("{}_targeting_{}", flag.name, index),
+                description: "Flag targeting rule".to_string(),
+                condition: RuleCondition::from(condition),
+            })
+            .collect();
+        crate::engine::FeatureFlags::global()
+            .set_flag(flag.clone())
+            .map_err(|e| coded_error(e.code(), e.to_string()))?;
+        Ok(flag.into())
+    }
+
+    /// Remove a feature flag; with a workflow ID, the flag scoped to that workflow
+    async fn remove_feature_flag(
+        &self,
+        name: String,
+        workflow_id: Option<String>,
+    ) -> async_graphql::Result<bool> {
+        Ok(crate::engine::FeatureFlags::global()
+            .remove_flag(&name, workflow_id.as_deref())
+            .is_some())
+    }
+
     /// Create a new rule
     async fn create_rule(
         &self,
@@ -2432,15 +6040,16 @@ impl Mutation {
             updated_at: chrono::Utc::now(),
             created_by: None, // TODO: Get from auth context
             tags: input.tags.unwrap_or_default(),
-            workflow_id: None,
+            workflow_id: input.workflow_id,
         };
+        check_rule_confidentiality(ctx, &stored_rule).await?;
 
         match rule_storage.create_rule(stored_rule).await {
             Ok(created_rule) => Ok(RuleGQL::from(&created_rule)),
-            Err(e) => Err(async_graphql::Error::new(format!(
-                "Failed to create rule: {}",
-                e
-            ))),
+            Err(e) => Err(coded_error(
+                ErrorCode::StorageError,
+                format!("Failed to create rule: {}", e),
+            )),
         }
     }
 
@@ -2463,15 +6072,16 @@ impl Mutation {
             updated_at: chrono::Utc::now(),
             created_by: None,
             tags: input.tags.unwrap_or_default(),
-            workflow_id: None,
+            workflow_id: input.workflow_id,
         };
+        check_rule_confidentiality(ctx, &stored_rule).await?;
 
         match rule_storage.update_rule(&id, stored_rule).await {
             Ok(updated_rule) => Ok(RuleGQL::from(&updated_rule)),
-            Err(e) => Err(async_graphql::Error::new(format!(
-                "Failed to update rule: {}",
-                e
-            ))),
+            Err(e) => Err(coded_error(
+                ErrorCode::StorageError,
+                format!("Failed to update rule: {}", e),
+            )),
         }
     }
 
@@ -2481,10 +6091,10 @@ impl Mutation {
 
         match rule_storage.delete_rule(&id).await {
             Ok(deleted) => Ok(deleted),
-            Err(e) => Err(async_graphql::Error::new(format!(
-                "Failed to delete rule: {}",
-                e
-            ))),
+            Err(e) => Err(coded_error(
+                ErrorCode::StorageError,
+                format!("Failed to delete rule: {}", e),
+            )),
         }
     }
 
@@ -2521,14 +6131,14 @@ impl Mutation {
                     sub_results: vec![], // TODO: Convert sub_results properly
                 })
             }
-            Ok(None) => Err(async_graphql::Error::new(format!(
-                "Rule not found: {}",
-                input.rule_id
-            ))),
-            Err(e) => Err(async_graphql::Error::new(format!(
-                "Failed to evaluate rule: {}",
-                e
-            ))),
+            Ok(None) => Err(coded_error(
+                ErrorCode::RuleNotFound,
+                format!("Rule not found: {}", input.rule_id),
+            )),
+            Err(e) => Err(coded_error(
+                ErrorCode::Internal,
+                format!("Failed to evaluate rule: {}", e),
+            )),
         }
     }
 }
@@ -2553,20 +6163,47 @@ impl Subscription {
         futures::stream::empty()
     }
 
-    /// Subscribe to agent execution stream events
+    /// Subscribe to agent execution stream events.
+    /// Set `guaranteed_delivery` to receive every event instead of lag markers when slow;
+    /// a guaranteed stream that falls too far behind is ended.
     async fn agent_execution_stream(
         &self,
         ctx: &Context<'_>,
         execution_id: String,
+        guaranteed_delivery: Option<bool>,
     ) -> async_graphql::Result<impl futures::Stream<Item = String>> {
-        let _agent_engine = ctx.data::<AgentEngine>()?;
-        let _execution_uuid = execution_id
+        let agent_engine = ctx.data::<AgentEngine>()?;
+        let execution_uuid = execution_id
             .parse::<Uuid>()
-            .map_err(|_| async_graphql::Error::new("Invalid execution ID format"))?;
+            .map_err(|_| coded_error(ErrorCode::InvalidInput, "Invalid execution ID format"))?;
+
+        // Tracked subscription so the stream reaper can close it if the client vanishes
+        use futures::StreamExt;
+        let delivery = match guaranteed_delivery {
+            Some(true) => StreamDelivery::Guaranteed,
+            Some(false) => StreamDelivery::Lossy,
+            None => agent_engine.stream_delivery(),
+        };
+        let subscription = agent_engine.subscribe_to_execution(execution_uuid, delivery);
+        let stream = subscription
+            .into_stream()
+            .filter(move |item| {
+                // Lag markers are not tied to an execution, so every stream gets them
+                futures::future::ready(match item {
+                    StreamItem::Event(event) => event.execution_id() == execution_uuid,
+                    StreamItem::Lagged { .. } => true,
+                })
+            })
+            .map(|item| match item {
+                StreamItem::Event(event) => serde_json::to_string(&event).unwrap_or_else(|_| {
+                    r#"{"type":"error","error":"JSON serialization failed"}"#.to_string()
+                }),
+                StreamItem::Lagged { missed } => {
+                    serde_json::json!({ "Lagged": { "missed": missed } }).to_string()
+                }
+            });
 
-        // TODO: Implement real-time agent execution streaming
-        // For now, return empty stream
-        Ok(futures::stream::empty())
+        Ok(stream)
     }
 
     /// Subscribe to LLM response stream for real-time streaming
@@ -2577,7 +6214,10 @@ impl Subscription {
     ) -> async_graphql::Result<impl futures::Stream<Item = String>> {
         // Create router and real LLM request
         let router = crate::llm::router::LLMRouter::new().await.map_err(|e| {
-            async_graphql::Error::new(format!("Failed to initialize router: {}", e))
+            coded_error(
+                ErrorCode::ProviderUnavailable,
+                format!("Failed to initialize router: {}", e),
+            )
         })?;
 
         let llm_request = crate::llm::LLMRequest {
@@ -2589,6 +6229,9 @@ impl Subscription {
                     .to_string(),
                 name: None,
                 function_call: None,
+                tool_calls: None,
+                tool_call_id: None,
+                content_parts: None,
             }],
             temperature: Some(0.7),
             max_tokens: Some(150),
@@ -2601,6 +6244,9 @@ impl Subscription {
             function_call: None,
             user: None,
             metadata: std::collections::HashMap::new(),
+            response_format: None,
+            tool_choice: None,
+            n: None,
         };
 
         // Get the real streaming response
@@ -2608,7 +6254,10 @@ impl Subscription {
             .stream_chat_completion(llm_request)
             .await
             .map_err(|e| {
-                async_graphql::Error::new(format!("LLM streaming request failed: {}", e))
+                coded_error(
+                    ErrorCode::ProviderError,
+                    format!("LLM streaming request failed: {}", e),
+                )
             })?;
 
         // Convert the stream to JSON strings for WebSocket
@@ -2628,6 +6277,7 @@ impl Subscription {
                                     crate::llm::MessageRole::User => "user",
                                     crate::llm::MessageRole::System => "system",
                                     crate::llm::MessageRole::Function => "function",
+                                    crate::llm::MessageRole::Tool => "tool",
                                 },
                                 "content": choice.delta.content
                             },
@@ -2649,6 +6299,57 @@ impl Subscription {
         Ok(json_stream)
     }
 
+    /// Replay stored events on a subject filter such as `cb.workflows.*.events.>`,
+    /// published from `from` until `to` (RFC 3339; `to` defaults to now), at no more than
+    /// `max_events_per_second` events per second, then end. Requires NATS storage.
+    async fn event_replay(
+        &self,
+        ctx: &Context<'_>,
+        subject: String,
+        from: String,
+        to: Option<String>,
+        max_events_per_second: Option<u32>,
+    ) -> async_graphql::Result<impl futures::Stream<Item = String>> {
+        let nats_storage = ctx
+            .data::<std::sync::Arc<crate::engine::nats_storage::NATSStorage>>()
+            .map_err(|_| {
+                coded_error(
+                    ErrorCode::StorageError,
+                    "Event replay requires NATS storage",
+                )
+            })?;
+        let parse_time = |name: &str, value: &str| {
+            chrono::DateTime::parse_from_rfc3339(value)
+                .map(|time| time.with_timezone(&chrono::Utc))
+                .map_err(|_| {
+                    coded_error(
+                        ErrorCode::InvalidInput,
+                        format!("'{}' must be an RFC 3339 timestamp", name),
+                    )
+                })
+        };
+
+        let mut request = EventReplayRequest::new(subject, parse_time("from", &from)?);
+        if let Some(to) = to {
+            request = request.to(parse_time("to", &to)?);
+        }
+        if let Some(rate) = max_events_per_second {
+            request = request.max_events_per_second(rate);
+        }
+        let events = nats_storage
+            .replay_events(request)
+            .await
+            .map_err(|e| coded_error(e.code(), e.to_string()))?;
+
+        use futures::StreamExt;
+        Ok(events.map(|event| match event {
+            Ok(event) => serde_json::to_string(&event).unwrap_or_else(|_| {
+                r#"{"type":"error","error":"JSON serialization failed"}"#.to_string()
+            }),
+            Err(e) => serde_json::json!({ "type": "error", "error": e.to_string() }).to_string(),
+        }))
+    }
+
     /// Subscribe to cost updates for real-time budget monitoring
     async fn cost_updates(
         &self,
@@ -2673,14 +6374,42 @@ pub type CircuitBreakerSchema = Schema<Query, Mutation, Subscription>;
 
 /// Create the GraphQL schema
 pub fn create_schema() -> CircuitBreakerSchema {
-    Schema::build(Query, Mutation, Subscription).finish()
+    Schema::build(Query, Mutation, Subscription)
+        .extension(MaintenanceGuard::new(MaintenanceMode::global()))
+        .extension(ServiceAccountGuard)
+        .extension(ApiKeyGuard)
+        .extension(RoleGuard)
+        .finish()
 }
 
 /// Create schema with storage backend
 pub fn create_schema_with_storage(storage: Box<dyn WorkflowStorage>) -> CircuitBreakerSchema {
-    Schema::build(Query, Mutation, Subscription)
-        .data(storage)
-        .finish()
+    let builder = Schema::build(Query, Mutation, Subscription)
+        .extension(MaintenanceGuard::new(MaintenanceMode::global()))
+        .extension(ServiceAccountGuard)
+        .extension(ApiKeyGuard)
+        .extension(RoleGuard);
+    with_dataloaders(builder, storage, None).finish()
+}
+
+/// Register workflow storage together with the dataloaders batching related-entity
+/// lookups of GraphQL resolvers
+fn with_dataloaders(
+    builder: SchemaBuilder<Query, Mutation, Subscription>,
+    storage: Box<dyn WorkflowStorage>,
+    agent_storage: Option<&std::sync::Arc<dyn AgentStorage>>,
+) -> SchemaBuilder<Query, Mutation, Subscription> {
+    // Resolvers and the workflow loader share one backend
+    let storage: std::sync::Arc<dyn WorkflowStorage> = storage.into();
+    let builder = builder
+        .data(Box::new(storage.clone()) as Box<dyn WorkflowStorage>)
+        .data(WorkflowLoader::dataloader(storage));
+    match agent_storage {
+        Some(agent_storage) => {
+            builder.data(ResourceExecutionsLoader::dataloader(agent_storage.clone()))
+        }
+        None => builder,
+    }
 }
 
 /// Create schema with workflow storage, agent storage, and agent engine
@@ -2689,8 +6418,12 @@ pub fn create_schema_with_agents(
     agent_storage: std::sync::Arc<dyn AgentStorage>,
     agent_engine: AgentEngine,
 ) -> CircuitBreakerSchema {
-    Schema::build(Query, Mutation, Subscription)
-        .data(workflow_storage)
+    let builder = Schema::build(Query, Mutation, Subscription)
+        .extension(MaintenanceGuard::new(MaintenanceMode::global()))
+        .extension(ServiceAccountGuard)
+        .extension(ApiKeyGuard)
+        .extension(RoleGuard);
+    with_dataloaders(builder, workflow_storage, Some(&agent_storage))
         .data(agent_storage)
         .data(agent_engine)
         .finish()
@@ -2706,8 +6439,12 @@ pub fn create_schema_with_nats(
         crate::engine::nats_storage::NATSStorageWrapper::new(nats_storage.clone()),
     );
 
-    Schema::build(Query, Mutation, Subscription)
-        .data(storage_boxed)
+    let builder = Schema::build(Query, Mutation, Subscription)
+        .extension(MaintenanceGuard::new(MaintenanceMode::global()))
+        .extension(ServiceAccountGuard)
+        .extension(ApiKeyGuard)
+        .extension(RoleGuard);
+    with_dataloaders(builder, storage_boxed, None)
         .data(nats_storage)
         .finish()
 }
@@ -2724,8 +6461,12 @@ pub fn create_schema_with_nats_and_agents(
         crate::engine::nats_storage::NATSStorageWrapper::new(nats_storage.clone()),
     );
 
-    Schema::build(Query, Mutation, Subscription)
-        .data(storage_boxed)
+    let builder = Schema::build(Query, Mutation, Subscription)
+        .extension(MaintenanceGuard::new(MaintenanceMode::global()))
+        .extension(ServiceAccountGuard)
+        .extension(ApiKeyGuard)
+        .extension(RoleGuard);
+    with_dataloaders(builder, storage_boxed, Some(&agent_storage))
         .data(nats_storage)
         .data(agent_storage)
         .data(agent_engine)
@@ -2744,11 +6485,157 @@ pub fn create_schema_with_full_storage(
         crate::engine::nats_storage::NATSStorageWrapper::new(nats_storage.clone()),
     );
 
-    Schema::build(Query, Mutation, Subscription)
-        .data(storage_boxed)
+    let builder = Schema::build(Query, Mutation, Subscription)
+        .extension(MaintenanceGuard::new(MaintenanceMode::global()))
+        .extension(ServiceAccountGuard)
+        .extension(ApiKeyGuard)
+        .extension(RoleGuard);
+    with_dataloaders(builder, storage_boxed, Some(&agent_storage))
         .data(nats_storage)
         .data(agent_storage)
         .data(agent_engine)
         .data(rule_storage)
         .finish()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::InMemoryStorage;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_batch_mutations_report_each_item() {
+        let storage = Arc::new(InMemoryStorage::default());
+        storage
+            .create_workflow(WorkflowDefinition::new(
+                "orders",
+                "Orders",
+                vec![StateId::from("new"), StateId::from("shipped")],
+                vec![ActivityDefinition::new("ship", vec!["new"], "shipped")],
+                "new",
+            ))
+            .await
+            .unwrap();
+        let schema = create_schema_with_storage(Box::new(storage.clone()));
+
+        let create = |atomic: bool| {
+            format!(
+                r#"mutation {{ createResourcesBatch(input: {{ atomic: {}, items: [
+                    {{ workflowId: "orders" }}, {{ workflowId: "missing" }}, {{ workflowId: "orders" }}
+                ] }}) {{ succeeded failed items {{ index resource {{ id }} errorCode }} }} }}"#,
+                atomic
+            )
+        };
+        let response = schema.execute(create(true)).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        assert_eq!(data["createResourcesBatch"]["failed"], 3);
+        assert!(storage.list_resources(None).await.unwrap().is_empty());
+
+        let response = schema.execute(create(false)).await;
+        let data = response.data.into_json().unwrap();
+        let batch = &data["createResourcesBatch"];
+        assert_eq!(batch["succeeded"], 2);
+        assert_eq!(batch["items"][1]["errorCode"], "WORKFLOW_NOT_FOUND");
+        let first = batch["items"][0]["resource"]["id"].as_str().unwrap();
+        let third = batch["items"][2]["resource"]["id"].as_str().unwrap();
+
+        let response = schema
+            .execute(format!(
+                r#"mutation {{ executeActivityBatch(input: {{ items: [
+                    {{ resourceId: "{first}", activityId: "ship" }},
+                    {{ resourceId: "{third}", activityId: "ship" }},
+                    {{ resourceId: "{first}", activityId: "ship" }}
+                ] }}) {{ succeeded items {{ resource {{ state }} error }} }} }}"#
+            ))
+            .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        let batch = &data["executeActivityBatch"];
+        assert_eq!(batch["succeeded"], 2);
+        assert_eq!(batch["items"][0]["resource"]["state"], "shipped");
+        assert!(batch["items"][2]["error"]
+            .as_str()
+            .unwrap()
+            .contains("more than once"));
+        let shipped = storage.count_resources_by_state("orders").await.unwrap();
+        assert_eq!(shipped.get("shipped"), Some(&2));
+    }
+
+    #[tokio::test]
+    async fn test_create_workflow_from_template() {
+        let storage = Arc::new(InMemoryStorage::default());
+        let schema = create_schema_with_storage(Box::new(storage.clone()));
+
+        let response = schema
+            .execute(
+                r#"mutation { createWorkflowFromTemplate(input: {
+                    templateId: "threshold_approval", parameters: { approval_threshold: 250 }
+                }) { id name states } }"#,
+            )
+            .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        let id = data["createWorkflowFromTemplate"]["id"].as_str().unwrap();
+        assert!(storage.get_workflow(id).await.unwrap().is_some());
+
+        let response = schema
+            .execute(r#"mutation { createWorkflowFromTemplate(input: { templateId: "threshold_approval" }) { id } }"#)
+            .await;
+        assert_eq!(
+            response.errors[0].message,
+            "Missing required parameter 'approval_threshold'"
+        );
+    }
+
+    #[test]
+    fn test_service_accounts_may_use_batches() {
+        assert_eq!(
+            service_operation(OperationType::Mutation, "createResourcesBatch"),
+            Some(ServiceOperation::CreateResources)
+        );
+        assert_eq!(
+            service_operation(OperationType::Mutation, "executeActivityBatch"),
+            Some(ServiceOperation::ExecuteActivities)
+        );
+        assert_eq!(
+            service_operation(OperationType::Query, "executeActivityBatch"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_every_root_field_needs_a_permission() {
+        use async_graphql::parser::types::{TypeKind, TypeSystemDefinition};
+
+        let sdl = create_schema().sdl();
+        let document = async_graphql::parser::parse_schema(&sdl).unwrap();
+        let mut unmapped = Vec::new();
+        for definition in &document.definitions {
+            let TypeSystemDefinition::Type(definition) = definition else {
+                continue;
+            };
+            let ty = match definition.node.name.node.as_str() {
+                "Query" => OperationType::Query,
+                "Mutation" => OperationType::Mutation,
+                "Subscription" => OperationType::Subscription,
+                _ => continue,
+            };
+            let TypeKind::Object(object) = &definition.node.kind else {
+                continue;
+            };
+            for field in &object.fields {
+                let name = field.node.name.node.as_str();
+                if graphql_permission(ty, name).is_none() {
+                    unmapped.push(format!("{}.{}", definition.node.name.node, name));
+                }
+            }
+        }
+        assert!(
+            unmapped.is_empty(),
+            "fields without a permission: {:?}",
+            unmapped
+        );
+    }
+}
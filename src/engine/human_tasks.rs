@@ -0,0 +1,465 @@
+// Human tasks
+// Manual activities waiting for a person to claim and approve or reject them
+
+//! # Human Tasks
+//!
+//! An activity with a [`ManualTask`](crate::models::ManualTask) is a human task: a
+//! resource in one of its source states is parked there until someone decides. Manual
+//! activities cannot be executed directly; they are completed through [`HumanTasks`]:
+//!
+//! - [`HumanTasks::pending`] lists the tasks waiting, with who claimed them
+//! - [`HumanTasks::claim`] reserves a task for one person, so two reviewers do not
+//!   pick up the same resource
+//! - [`HumanTasks::approve`] executes the activity
+//! - [`HumanTasks::reject`] moves the resource to the task's `reject_to` state, or
+//!   leaves it where it is
+//!
+//! Either decision is recorded in the resource's history: the event's data carries
+//! `{"human_task": {"decision", "actor", "comment"}}`. A task claimed by someone else can
+//! only be decided by them; an unclaimed task can be decided by anyone. The person is
+//! the gate, so decisions skip the activity's rules.
+//! Paused and cancelled resources have no pending tasks, and their tasks cannot be
+//! decided.
+//!
+//! Claims are held in memory by the process serving the API, like capacity queues,
+//! and are released once the task is decided or the resource leaves the state.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tracing::info;
+use uuid::Uuid;
+
+use super::storage::WorkflowStorage;
+use crate::models::{ActivityDefinition, ActivityId, Resource, StateId, WorkflowDefinition};
+use crate::{CircuitBreakerError, ErrorCode};
+
+lazy_static::lazy_static! {
+    static ref GLOBAL: HumanTasks = HumanTasks::new();
+}
+
+/// Key of the history event data recording a human decision
+pub const HUMAN_TASK_KEY: &str = "human_task";
+
+/// A manual activity a resource is waiting on
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HumanTask {
+    pub resource_id: Uuid,
+    pub workflow_id: String,
+    pub activity_id: ActivityId,
+    pub state: StateId,
+    pub instructions: Option<String>,
+    /// When the resource entered the state
+    pub waiting_since: DateTime<Utc>,
+    pub claim: Option<TaskClaim>,
+}
+
+/// Who claimed a task, and when
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TaskClaim {
+    pub actor: String,
+    pub claimed_at: DateTime<Utc>,
+}
+
+/// A decision on a human task
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskDecision {
+    Approved,
+    Rejected,
+}
+
+/// Why a human task operation failed
+#[derive(Debug, thiserror::Error)]
+pub enum HumanTaskError {
+    #[error("Activity '{activity_id}' is not a human task awaiting resource {resource_id}")]
+    NotPending {
+        resource_id: Uuid,
+        activity_id: ActivityId,
+    },
+    #[error("Task is claimed by {0}")]
+    ClaimedByOther(String),
+    #[error(transparent)]
+    Engine(#[from] CircuitBreakerError),
+}
+
+impl HumanTaskError {
+    /// Stable error code for API responses
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            HumanTaskError::NotPending { .. } => ErrorCode::InvalidStateTransition,
+            HumanTaskError::ClaimedByOther(_) => ErrorCode::PermissionDenied,
+            HumanTaskError::Engine(CircuitBreakerError::NotFound(_)) => ErrorCode::ResourceNotFound,
+            HumanTaskError::Engine(e) => e.code(),
+        }
+    }
+}
+
+pub type HumanTaskResult<T> = std::result::Result<T, HumanTaskError>;
+
+/// Claims on human tasks, keyed by resource and activity
+#[derive(Clone, Default)]
+pub struct HumanTasks {
+    claims: Arc<RwLock<HashMap<(Uuid, ActivityId), TaskClaim>>>,
+}
+
+impl HumanTasks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The process-wide claims the APIs share
+    pub fn global() -> Self {
+        GLOBAL.clone()
+    }
+
+    /// Human tasks waiting, optionally only those of a workflow or claimed by an actor,
+    /// longest waiting first
+    pub async fn pending<S: WorkflowStorage + ?Sized>(
+        &self,
+        storage: &S,
+        workflow_id: Option<&str>,
+        claimed_by: Option<&str>,
+    ) -> HumanTaskResult<Vec<HumanTask>> {
+        let workflows = match workflow_id {
+            Some(id) => storage.get_workflow(id).await?.into_iter().collect(),
+            None => storage.list_workflows().await?,
+        };
+
+        let mut tasks = Vec::new();
+        for workflow in workflows {
+            if !workflow
+                .activities
+                .iter()
+                .any(ActivityDefinition::is_manual)
+            {
+                continue;
+            }
+            for resource in storage.list_resources(Some(&workflow.id)).await? {
+                if resource.is_halted() {
+                    continue;
+                }
+                for activity in manual_activities(&workflow, &resource) {
+                    let task = self.task(&workflow, &resource, activity);
+                    let claimed_by_actor = match (claimed_by, &task.claim) {
+                        (None, _) => true,
+                        (Some(actor), Some(claim)) => claim.actor == actor,
+                        (Some(_), None) => false,
+                    };
+                    if claimed_by_actor {
+                        tasks.push(task);
+                    }
+                }
+            }
+        }
+        tasks.sort_by_key(|task| task.waiting_since);
+        Ok(tasks)
+    }
+
+    /// Reserve a task for `actor`; claiming a task one already holds is a no-op
+    pub async fn claim<S: WorkflowStorage + ?Sized>(
+        &self,
+        storage: &S,
+        resource_id: &Uuid,
+        activity_id: &ActivityId,
+        actor: &str,
+    ) -> HumanTaskResult<HumanTask> {
+        let (workflow, resource) = self.load(storage, resource_id).await?;
+        let activity = pending_activity(&workflow, &resource, activity_id)?;
+        {
+            let mut claims = self.claims.write().unwrap();
+            let key = (*resource_id, activity_id.clone());
+            match claims.get(&key) {
+                Some(claim) if claim.actor != actor => {
+                    return Err(HumanTaskError::ClaimedByOther(claim.actor.clone()));
+                }
+                Some(_) => {}
+                None => {
+                    claims.insert(
+                        key,
+                        TaskClaim {
+                            actor: actor.to_string(),
+                            claimed_at: Utc::now(),
+                        },
+                    );
+                }
+            }
+        }
+        info!(
+            "🙋 {} claimed task '{}' of resource {}",
+            actor,
+            activity_id.as_str(),
+            resource_id
+        );
+        Ok(self.task(&workflow, &resource, activity))
+    }
+
+    /// Approve a task, executing its activity
+    pub async fn approve<S: WorkflowStorage + ?Sized>(
+        &self,
+        storage: &S,
+        resource_id: &Uuid,
+        activity_id: &ActivityId,
+        actor: &str,
+        comment: Option<String>,
+    ) -> HumanTaskResult<Resource> {
+        self.decide(
+            storage,
+            resource_id,
+            activity_id,
+            actor,
+            TaskDecision::Approved,
+            comment,
+        )
+        .await
+    }
+
+    /// Reject a task, moving the resource to the task's `reject_to` state if it has one
+    pub async fn reject<S: WorkflowStorage + ?Sized>(
+        &self,
+        storage: &S,
+        resource_id: &Uuid,
+        activity_id: &ActivityId,
+        actor: &str,
+        comment: Option<String>,
+    ) -> HumanTaskResult<Resource> {
+        self.decide(
+            storage,
+            resource_id,
+            activity_id,
+            actor,
+            TaskDecision::Rejected,
+            comment,
+        )
+        .await
+    }
+
+    async fn decide<S: WorkflowStorage + ?Sized>(
+        &self,
+        storage: &S,
+        resource_id: &Uuid,
+        activity_id: &ActivityId,
+        actor: &str,
+        decision: TaskDecision,
+        comment: Option<String>,
+    ) -> HumanTaskResult<Resource> {
+        let (workflow, mut resource) = self.load(storage, resource_id).await?;
+        let activity = pending_activity(&workflow, &resource, activity_id)?;
+        super::lifecycle::ensure_active(&resource)?;
+        let key = (*resource_id, activity_id.clone());
+        if let Some(claim) = self.claims.read().unwrap().get(&key) {
+            if claim.actor != actor {
+                return Err(HumanTaskError::ClaimedByOther(claim.actor.clone()));
+            }
+        }
+
+        let to_state = match decision {
+            TaskDecision::Approved => activity.target_state(&resource).clone(),
+            TaskDecision::Rejected => activity
+                .manual
+                .as_ref()
+                .and_then(|task| task.reject_to.clone())
+                .unwrap_or_else(|| resource.state.clone()),
+        };
+        resource.execute_activity(to_state, activity.id.clone());
+        if let Some(event) = resource.history.last_mut() {
+            event.data = Some(serde_json::json!({
+                HUMAN_TASK_KEY: {
+                    "decision": decision,
+                    "actor": actor,
+                    "comment": comment,
+                }
+            }));
+        }
+        let updated = storage.update_resource(resource).await?;
+        self.claims.write().unwrap().remove(&key);
+        info!(
+            "✍️ {} {:?} task '{}' of resource {}",
+            actor,
+            decision,
+            activity_id.as_str(),
+            resource_id
+        );
+        Ok(updated)
+    }
+
+    async fn load<S: WorkflowStorage + ?Sized>(
+        &self,
+        storage: &S,
+        resource_id: &Uuid,
+    ) -> HumanTaskResult<(WorkflowDefinition, Resource)> {
+        let resource = storage
+            .get_resource(resource_id)
+            .await?
+            .ok_or_else(|| CircuitBreakerError::NotFound(format!("Resource {}", resource_id)))?;
+        let workflow = storage
+            .get_workflow(&resource.workflow_id)
+            .await?
+            .ok_or_else(|| CircuitBreakerError::WorkflowNotFound {
+                id: resource.workflow_id.clone(),
+            })?;
+
+        // Claims on tasks the resource has left behind are stale
+        self.claims.write().unwrap().retain(|(id, activity_id), _| {
+            id != resource_id
+                || workflow
+                    .activities
+                    .iter()
+                    .any(|a| &a.id == activity_id && a.can_execute_from(&resource.state))
+        });
+        Ok((workflow, resource))
+    }
+
+    fn task(
+        &self,
+        workflow: &WorkflowDefinition,
+        resource: &Resource,
+        activity: &ActivityDefinition,
+    ) -> HumanTask {
+        let claim = self
+            .claims
+            .read()
+            .unwrap()
+            .get(&(resource.id, activity.id.clone()))
+            .cloned();
+        HumanTask {
+            resource_id: resource.id,
+            workflow_id: workflow.id.clone(),
+            activity_id: activity.id.clone(),
+            state: resource.state.clone(),
+            instructions: activity
+                .manual
+                .as_ref()
+                .and_then(|task| task.instructions.clone()),
+            waiting_since: resource.entered_state_at(),
+            claim,
+        }
+    }
+}
+
+/// Manual activities waiting on the resource in its current state
+fn manual_activities<'a>(
+    workflow: &'a WorkflowDefinition,
+    resource: &'a Resource,
+) -> impl Iterator<Item = &'a ActivityDefinition> {
+    workflow
+        .activities
+        .iter()
+        .filter(|activity| activity.is_manual() && activity.can_execute_from(&resource.state))
+}
+
+fn pending_activity<'a>(
+    workflow: &'a WorkflowDefinition,
+    resource: &Resource,
+    activity_id: &ActivityId,
+) -> HumanTaskResult<&'a ActivityDefinition> {
+    workflow
+        .activities
+        .iter()
+        .find(|activity| {
+            &activity.id == activity_id
+                && activity.is_manual()
+                && activity.can_execute_from(&resource.state)
+        })
+        .ok_or_else(|| HumanTaskError::NotPending {
+            resource_id: resource.id,
+            activity_id: activity_id.clone(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::storage::InMemoryStorage;
+    use crate::models::ManualTask;
+
+    fn approval_workflow() -> WorkflowDefinition {
+        WorkflowDefinition::new(
+            "expenses",
+            "Expenses",
+            vec![
+                StateId::from("submitted"),
+                StateId::from("approved"),
+                StateId::from("returned"),
+            ],
+            vec![
+                ActivityDefinition::new("approve", vec!["submitted"], "approved").with_manual_task(
+                    ManualTask {
+                        instructions: Some("Check the receipts".to_string()),
+                        reject_to: Some(StateId::from("returned")),
+                    },
+                ),
+            ],
+            "submitted",
+        )
+    }
+
+    #[tokio::test]
+    async fn test_claim_and_decide_human_tasks() {
+        let storage = InMemoryStorage::default();
+        storage.create_workflow(approval_workflow()).await.unwrap();
+        let first = storage
+            .create_resource(Resource::new("expenses", StateId::from("submitted")))
+            .await
+            .unwrap();
+        let second = storage
+            .create_resource(Resource::new("expenses", StateId::from("submitted")))
+            .await
+            .unwrap();
+        let tasks = HumanTasks::new();
+        let approve = ActivityId::from("approve");
+
+        assert_eq!(tasks.pending(&storage, None, None).await.unwrap().len(), 2);
+        let claimed = tasks
+            .claim(&storage, &first.id, &approve, "ada")
+            .await
+            .unwrap();
+        assert_eq!(claimed.claim.unwrap().actor, "ada");
+        let mine = tasks.pending(&storage, None, Some("ada")).await.unwrap();
+        assert_eq!(mine.len(), 1);
+        assert_eq!(mine[0].resource_id, first.id);
+
+        // Only the claimant decides a claimed task
+        let error = tasks
+            .approve(&storage, &first.id, &approve, "grace", None)
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), ErrorCode::PermissionDenied);
+
+        let approved = tasks
+            .approve(
+                &storage,
+                &first.id,
+                &approve,
+                "ada",
+                Some("Looks right".into()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(approved.state, StateId::from("approved"));
+        assert_eq!(
+            approved.history[0].data,
+            Some(serde_json::json!({
+                "human_task": { "decision": "approved", "actor": "ada", "comment": "Looks right" }
+            }))
+        );
+
+        let rejected = tasks
+            .reject(&storage, &second.id, &approve, "grace", None)
+            .await
+            .unwrap();
+        assert_eq!(rejected.state, StateId::from("returned"));
+        assert!(tasks
+            .pending(&storage, None, None)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let error = tasks
+            .approve(&storage, &second.id, &approve, "grace", None)
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), ErrorCode::InvalidStateTransition);
+    }
+}
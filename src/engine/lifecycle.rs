@@ -0,0 +1,218 @@
+// Resource lifecycle
+// Pauses, resumes and cancels resources, halting activity execution on them
+
+//! # Pause, Resume and Cancel
+//!
+//! [`pause`] freezes a resource where it is: no activity executes on it - not through
+//! the API, timers, SLA escalations, webhook triggers nor human tasks - until [`resume`]
+//! lets it move on. [`cancel`] halts it for good, recording why. Neither moves the
+//! resource; its state and branches stay as they were, so it can be inspected, and a
+//! cancelled resource can still be compensated to roll back what it went through.
+//!
+//! The lifecycle is kept in the resource's metadata under
+//! [`LIFECYCLE_KEY`](crate::models::LIFECYCLE_KEY), so storage needs nothing new, and
+//! every change is published on the event bus as a `TokenPaused`, `TokenResumed` or
+//! `TokenCancelled` event, so functions and agents listening for them can stop their
+//! work. Executing an activity on a halted resource fails with
+//! [`ResourceHalted`](CircuitBreakerError::ResourceHalted).
+
+use chrono::Utc;
+use tracing::info;
+use uuid::Uuid;
+
+use super::events::EventBus;
+use super::storage::{update_resource_with_retry, WorkflowStorage};
+use crate::models::{LifecycleStatus, Resource, ResourceLifecycle};
+use crate::{CircuitBreakerError, Result};
+
+/// Times a lifecycle change is retried when it races an activity on the resource
+const LIFECYCLE_ATTEMPTS: usize = 3;
+
+/// Fail with [`ResourceHalted`](CircuitBreakerError::ResourceHalted) unless `resource`
+/// is active
+pub fn ensure_active(resource: &Resource) -> Result<()> {
+    match resource.lifecycle_status() {
+        LifecycleStatus::Active => Ok(()),
+        status => Err(CircuitBreakerError::ResourceHalted {
+            id: resource.id.to_string(),
+            status: status.as_str().to_string(),
+        }),
+    }
+}
+
+/// Pause an active resource until it is resumed
+pub async fn pause<S: WorkflowStorage + ?Sized>(
+    storage: &S,
+    events: &EventBus,
+    resource_id: &Uuid,
+    actor: Option<String>,
+    reason: Option<String>,
+) -> Result<Resource> {
+    change(
+        storage,
+        events,
+        resource_id,
+        LifecycleStatus::Paused,
+        actor,
+        reason,
+    )
+    .await
+}
+
+/// Let a paused resource move on again
+pub async fn resume<S: WorkflowStorage + ?Sized>(
+    storage: &S,
+    events: &EventBus,
+    resource_id: &Uuid,
+    actor: Option<String>,
+) -> Result<Resource> {
+    change(
+        storage,
+        events,
+        resource_id,
+        LifecycleStatus::Active,
+        actor,
+        None,
+    )
+    .await
+}
+
+/// Cancel an active or paused resource for good
+pub async fn cancel<S: WorkflowStorage + ?Sized>(
+    storage: &S,
+    events: &EventBus,
+    resource_id: &Uuid,
+    actor: Option<String>,
+    reason: String,
+) -> Result<Resource> {
+    if reason.trim().is_empty() {
+        return Err(CircuitBreakerError::InvalidInput(
+            "Cancelling a resource needs a reason".to_string(),
+        ));
+    }
+    change(
+        storage,
+        events,
+        resource_id,
+        LifecycleStatus::Cancelled,
+        actor,
+        Some(reason),
+    )
+    .await
+}
+
+async fn change<S: WorkflowStorage + ?Sized>(
+    storage: &S,
+    events: &EventBus,
+    resource_id: &Uuid,
+    status: LifecycleStatus,
+    actor: Option<String>,
+    reason: Option<String>,
+) -> Result<Resource> {
+    let mut current = LifecycleStatus::Active;
+    let updated =
+        update_resource_with_retry(storage, resource_id, LIFECYCLE_ATTEMPTS, |resource| {
+            current = resource.lifecycle_status();
+            let allowed = match status {
+                LifecycleStatus::Paused => current == LifecycleStatus::Active,
+                LifecycleStatus::Active => current == LifecycleStatus::Paused,
+                LifecycleStatus::Cancelled => current != LifecycleStatus::Cancelled,
+            };
+            if !allowed {
+                return Err(CircuitBreakerError::InvalidInput(format!(
+                    "Resource {} is {} and cannot be {}",
+                    resource_id,
+                    current.as_str(),
+                    match status {
+                        LifecycleStatus::Active => "resumed",
+                        other => other.as_str(),
+                    }
+                )));
+            }
+
+            resource.set_lifecycle(Some(ResourceLifecycle {
+                status,
+                reason: reason.clone(),
+                actor: actor.clone(),
+                changed_at: Utc::now(),
+            }));
+            Ok(())
+        })
+        .await?;
+    info!(
+        "Resource {} is {} (was {})",
+        updated.id,
+        status.as_str(),
+        current.as_str()
+    );
+    events.emit_lifecycle_changed(&updated, status).await?;
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::storage::InMemoryStorage;
+    use crate::models::{EventType, StateId};
+
+    #[tokio::test]
+    async fn test_pause_resume_cancel() {
+        let storage = InMemoryStorage::default();
+        let events = EventBus::new();
+        let mut received = events.subscribe();
+        let resource = storage
+            .create_resource(Resource::new("orders", StateId::from("placed")))
+            .await
+            .unwrap();
+
+        let paused = pause(
+            &storage,
+            &events,
+            &resource.id,
+            Some("ops".to_string()),
+            Some("customer on hold".to_string()),
+        )
+        .await
+        .unwrap();
+        assert!(paused.is_halted());
+        assert!(matches!(
+            ensure_active(&paused),
+            Err(CircuitBreakerError::ResourceHalted { .. })
+        ));
+        assert!(matches!(
+            received.recv().await.unwrap().event_type,
+            EventType::TokenPaused { .. }
+        ));
+        assert!(pause(&storage, &events, &resource.id, None, None)
+            .await
+            .is_err());
+
+        let resumed = resume(&storage, &events, &resource.id, None).await.unwrap();
+        assert_eq!(resumed.lifecycle(), None);
+        assert!(ensure_active(&resumed).is_ok());
+        assert!(matches!(
+            received.recv().await.unwrap().event_type,
+            EventType::TokenResumed { .. }
+        ));
+
+        assert!(
+            cancel(&storage, &events, &resource.id, None, " ".to_string())
+                .await
+                .is_err()
+        );
+        let cancelled = cancel(
+            &storage,
+            &events,
+            &resource.id,
+            None,
+            "duplicate order".to_string(),
+        )
+        .await
+        .unwrap();
+        let lifecycle = cancelled.lifecycle().unwrap();
+        assert_eq!(lifecycle.status, LifecycleStatus::Cancelled);
+        assert_eq!(lifecycle.reason.as_deref(), Some("duplicate order"));
+        assert_eq!(cancelled.state, StateId::from("placed"));
+        assert!(resume(&storage, &events, &resource.id, None).await.is_err());
+    }
+}
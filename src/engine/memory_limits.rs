@@ -0,0 +1,314 @@
+// Memory limits for in-memory storage
+// Caps how many resources InMemoryStorage keeps in RAM and where evicted ones go
+
+//! # Memory Limits
+//!
+//! `InMemoryStorage` keeps every resource it ever stored, so a long-running dev or test
+//! deployment grows without bound. [`MemoryLimits`] caps the number of resources held in
+//! memory. Once the cap is exceeded the least recently used resources in a terminal
+//! state (one no activity leads out of) are evicted: written to a [`SpillStore`] on disk
+//! when a spill path is configured, dropped otherwise. Resources that can still move
+//! are never evicted, so a workflow with nothing but live resources can exceed the cap;
+//! a warning is logged when that happens and when the cap is being approached.
+//!
+//! Spilled resources are still served by `get_resource` and `list_resources`; updating
+//! one brings it back into memory.
+
+use anyhow::Context;
+use redb::{Database, ReadableTable, ReadableTableMetadata, TableDefinition};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use crate::models::Resource;
+use crate::Result;
+
+/// Maximum number of resources `InMemoryStorage` keeps in memory
+pub const MAX_RESOURCES_ENV: &str = "CIRCUIT_BREAKER_MEMORY_MAX_RESOURCES";
+
+/// File evicted resources are spilled to; without it they are dropped
+pub const SPILL_PATH_ENV: &str = "CIRCUIT_BREAKER_MEMORY_SPILL_PATH";
+
+/// Share of the cap at which a warning is logged
+pub const DEFAULT_WARNING_RATIO: f64 = 0.8;
+
+/// Spilled resources, as JSON keyed by resource ID
+const SPILLED_RESOURCES: TableDefinition<u128, &[u8]> = TableDefinition::new("resources");
+
+/// How many resources in-memory storage keeps and where evicted ones go
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryLimits {
+    /// Resources kept in memory before terminal ones are evicted; `None` is unbounded
+    pub max_resources: Option<usize>,
+    /// Share of `max_resources` at which a warning is logged
+    pub warning_ratio: f64,
+    /// File evicted resources are written to
+    pub spill_path: Option<PathBuf>,
+}
+
+impl Default for MemoryLimits {
+    fn default() -> Self {
+        Self {
+            max_resources: None,
+            warning_ratio: DEFAULT_WARNING_RATIO,
+            spill_path: None,
+        }
+    }
+}
+
+impl MemoryLimits {
+    /// Limits from [`MAX_RESOURCES_ENV`] and [`SPILL_PATH_ENV`]; unbounded when unset
+    pub fn from_env() -> Self {
+        Self {
+            max_resources: std::env::var(MAX_RESOURCES_ENV)
+                .ok()
+                .and_then(|value| value.trim().parse().ok())
+                .filter(|max| *max > 0),
+            spill_path: std::env::var(SPILL_PATH_ENV)
+                .ok()
+                .filter(|path| !path.trim().is_empty())
+                .map(PathBuf::from),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_max_resources(mut self, max_resources: usize) -> Self {
+        self.max_resources = Some(max_resources);
+        self
+    }
+
+    pub fn with_warning_ratio(mut self, warning_ratio: f64) -> Self {
+        self.warning_ratio = warning_ratio;
+        self
+    }
+
+    pub fn with_spill_path(mut self, spill_path: impl Into<PathBuf>) -> Self {
+        self.spill_path = Some(spill_path.into());
+        self
+    }
+
+    /// Number of resources at which the cap counts as approached
+    pub fn warning_threshold(&self) -> Option<usize> {
+        self.max_resources
+            .map(|max| ((max as f64) * self.warning_ratio).ceil() as usize)
+    }
+}
+
+/// Order in which resources were last used, least recent first
+#[derive(Debug, Default)]
+pub struct ResourceLru {
+    tick: u64,
+    last_used: HashMap<Uuid, u64>,
+    order: BTreeMap<u64, Uuid>,
+}
+
+impl ResourceLru {
+    /// Mark a resource as just used
+    pub fn touch(&mut self, id: Uuid) {
+        self.tick += 1;
+        if let Some(previous) = self.last_used.insert(id, self.tick) {
+            self.order.remove(&previous);
+        }
+        self.order.insert(self.tick, id);
+    }
+
+    pub fn remove(&mut self, id: &Uuid) {
+        if let Some(previous) = self.last_used.remove(id) {
+            self.order.remove(&previous);
+        }
+    }
+
+    /// Resource IDs, least recently used first
+    pub fn least_recent(&self) -> impl Iterator<Item = &Uuid> {
+        self.order.values()
+    }
+}
+
+/// On-disk store for resources evicted from memory
+pub struct SpillStore {
+    db: Database,
+}
+
+impl SpillStore {
+    /// Open or create the spill file
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let db = Database::create(&path)
+            .with_context(|| format!("Failed to open spill file {}", path.display()))?;
+        // Create the table up front so readers never find it missing
+        let txn = db
+            .begin_write()
+            .context("Failed to initialize spill file")?;
+        txn.open_table(SPILLED_RESOURCES)
+            .context("Failed to initialize spill file")?;
+        txn.commit().context("Failed to initialize spill file")?;
+        Ok(Self { db })
+    }
+
+    pub fn put(&self, resource: &Resource) -> Result<()> {
+        let json = serde_json::to_vec(resource)?;
+        let txn = self.db.begin_write().context("Failed to spill resource")?;
+        {
+            let mut table = txn
+                .open_table(SPILLED_RESOURCES)
+                .context("Failed to spill resource")?;
+            table
+                .insert(resource.id.as_u128(), json.as_slice())
+                .context("Failed to spill resource")?;
+        }
+        txn.commit().context("Failed to spill resource")?;
+        Ok(())
+    }
+
+    pub fn get(&self, id: &Uuid) -> Result<Option<Resource>> {
+        let txn = self
+            .db
+            .begin_read()
+            .context("Failed to read spilled resource")?;
+        let table = txn
+            .open_table(SPILLED_RESOURCES)
+            .context("Failed to read spilled resource")?;
+        let Some(json) = table
+            .get(id.as_u128())
+            .context("Failed to read spilled resource")?
+        else {
+            return Ok(None);
+        };
+        Ok(Some(serde_json::from_slice(json.value())?))
+    }
+
+    /// Remove a resource, returning it if it was spilled
+    pub fn remove(&self, id: &Uuid) -> Result<Option<Resource>> {
+        let txn = self
+            .db
+            .begin_write()
+            .context("Failed to remove spilled resource")?;
+        let removed = {
+            let mut table = txn
+                .open_table(SPILLED_RESOURCES)
+                .context("Failed to remove spilled resource")?;
+            let removed = table
+                .remove(id.as_u128())
+                .context("Failed to remove spilled resource")?;
+            match removed {
+                Some(json) => Some(serde_json::from_slice(json.value())?),
+                None => None,
+            }
+        };
+        txn.commit().context("Failed to remove spilled resource")?;
+        Ok(removed)
+    }
+
+    /// Spilled resources, optionally only those of one workflow
+    pub fn list(&self, workflow_id: Option<&str>) -> Result<Vec<Resource>> {
+        let txn = self
+            .db
+            .begin_read()
+            .context("Failed to list spilled resources")?;
+        let table = txn
+            .open_table(SPILLED_RESOURCES)
+            .context("Failed to list spilled resources")?;
+        let mut resources = Vec::new();
+        for entry in table.iter().context("Failed to list spilled resources")? {
+            let (_, json) = entry.context("Failed to list spilled resources")?;
+            let resource: Resource = serde_json::from_slice(json.value())?;
+            if workflow_id.is_none_or(|wid| resource.workflow_id == wid) {
+                resources.push(resource);
+            }
+        }
+        Ok(resources)
+    }
+
+    pub fn len(&self) -> Result<u64> {
+        let txn = self
+            .db
+            .begin_read()
+            .context("Failed to count spilled resources")?;
+        let table = txn
+            .open_table(SPILLED_RESOURCES)
+            .context("Failed to count spilled resources")?;
+        Ok(table.len().context("Failed to count spilled resources")?)
+    }
+
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::storage::{InMemoryStorage, WorkflowStorage};
+    use crate::models::{ActivityDefinition, StateId, WorkflowDefinition};
+
+    #[test]
+    fn test_lru_orders_by_last_use() {
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut lru = ResourceLru::default();
+        lru.touch(a);
+        lru.touch(b);
+        lru.touch(c);
+        lru.touch(a);
+        lru.remove(&b);
+        assert_eq!(lru.least_recent().copied().collect::<Vec<_>>(), vec![c, a]);
+
+        let limits = MemoryLimits::default().with_max_resources(10);
+        assert_eq!(limits.warning_threshold(), Some(8));
+        assert_eq!(MemoryLimits::default().warning_threshold(), None);
+    }
+
+    #[tokio::test]
+    async fn test_terminal_resources_spill_past_cap() {
+        let path = std::env::temp_dir().join(format!("cb-spill-{}.redb", Uuid::new_v4()));
+        let storage = InMemoryStorage::with_limits(
+            MemoryLimits::default()
+                .with_max_resources(2)
+                .with_spill_path(&path),
+        )
+        .unwrap();
+        storage
+            .create_workflow(WorkflowDefinition::new(
+                "orders",
+                "Orders",
+                vec![StateId::from("open"), StateId::from("closed")],
+                vec![ActivityDefinition::new("close", vec!["open"], "closed")],
+                "open",
+            ))
+            .await
+            .unwrap();
+
+        let closed = storage
+            .create_resource(Resource::new("orders", StateId::from("closed")))
+            .await
+            .unwrap();
+        for _ in 0..3 {
+            storage
+                .create_resource(Resource::new("orders", StateId::from("open")))
+                .await
+                .unwrap();
+        }
+
+        // Only the closed resource could be evicted, so open ones exceed the cap
+        assert_eq!(storage.resident_resources(), 3);
+        assert_eq!(
+            storage.list_resources(Some("orders")).await.unwrap().len(),
+            4
+        );
+        let spilled = storage.get_resource(&closed.id).await.unwrap().unwrap();
+        assert_eq!(spilled.state, StateId::from("closed"));
+        let counts = storage.count_resources_by_state("orders").await.unwrap();
+        assert_eq!(counts.get("closed"), Some(&1));
+        assert_eq!(counts.get("open"), Some(&3));
+
+        // Updating a spilled resource brings it back into memory
+        let mut reopened = spilled;
+        reopened.state = StateId::from("open");
+        storage.update_resource(reopened).await.unwrap();
+        assert_eq!(storage.resident_resources(), 4);
+        let counts = storage.count_resources_by_state("orders").await.unwrap();
+        assert_eq!(counts.get("open"), Some(&4));
+
+        drop(storage);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -84,6 +84,187 @@ pub mod storage;
 /// - Legacy condition support
 pub mod rules;
 
+/// Rules engine performance metrics
+///
+/// Contains:
+/// - Evaluation counters and per-rule latency histograms
+/// - Most frequently failing rules
+/// - Prometheus text rendering for the `/metrics` endpoint
+pub mod rule_metrics;
+
+/// Compiled rule cache
+///
+/// Contains:
+/// - CompiledRule with pre-split field paths and pre-compiled regexes
+/// - RuleCache reused across evaluations by the RulesEngine
+pub mod rule_cache;
+
+/// Feature flags read by rules and agent prompts
+///
+/// Contains:
+/// - FeatureFlags store of global and workflow-scoped flags, updated at runtime
+/// - Per-resource evaluation of targeting rules and rollout percentages
+pub mod feature_flags;
+
+/// Capacity queues for constrained states
+///
+/// Contains:
+/// - CapacityQueues of resources waiting for room in a full state
+/// - Admission in FIFO, LIFO or priority order as each state's capacity configures
+pub mod state_capacity;
+
+/// Activity timers
+///
+/// Contains:
+/// - ActivityTimers firing `fire_after` and `deadline` activities from a tick loop
+/// - FiredTimer recording each resource a timer moved
+pub mod timers;
+
+/// State SLA tracking
+///
+/// Contains:
+/// - SlaMonitor tick loop flagging resources overstaying a state's SLA
+/// - Escalation activities, breach events and webhooks
+/// - SLA metrics from current and past stays
+pub mod sla;
+
+/// Throughput and time-in-state metrics per workflow
+///
+/// Contains:
+/// - collect_workflow_metrics, aggregating counters and percentiles from resource histories
+/// - WorkflowMetrics registry rendered on `/metrics`, refreshed by WorkflowMetricsCollector
+pub mod workflow_metrics;
+
+/// Saga-style compensation
+///
+/// Contains:
+/// - compensate, rolling a resource back through its activities' compensations
+/// - Compensation and CompensationStep describing what was undone
+pub mod compensation;
+
+/// Workflow definition versioning
+///
+/// Contains:
+/// - publish_version, storing a new version of a definition while keeping the old ones
+/// - WorkflowMigration mapping the resources of one version onto the current one
+pub mod workflow_versions;
+
+/// Human tasks
+///
+/// Contains:
+/// - HumanTasks listing the manual activities resources wait on, with claims
+/// - Approval and rejection recording the decision, actor and comment in history
+pub mod human_tasks;
+
+/// Parallel split/join execution
+///
+/// Contains:
+/// - AND-splits forking a resource into branches tracked in its metadata
+/// - Branch moves and AND-joins closing the branches once all arrived
+pub mod parallel;
+
+/// Workflow diagrams
+///
+/// Contains:
+/// - render_dot and render_mermaid turning definitions into diagram source
+/// - Highlighting of a resource's current state and history path
+pub mod diagram;
+
+/// Workflow template catalog
+///
+/// Contains:
+/// - TemplateCatalog of built-in and registered workflow templates
+/// - Built-in threshold approval and agent review templates
+pub mod templates;
+
+/// Activity retries
+///
+/// Contains:
+/// - run_with_retries re-running failed agent and function executions with backoff
+/// - An ActivityRecord per attempt in the resource's activity history
+pub mod retries;
+
+/// Inbound webhook triggers
+///
+/// Contains:
+/// - WebhookTriggers registry verifying GitHub, Stripe and generic HMAC signatures
+/// - Mapping of webhook events onto resource creation and activity firings
+pub mod triggers;
+
+/// Pausing, resuming and cancelling resources
+///
+/// Contains:
+/// - pause, resume and cancel, recording the lifecycle and publishing it as events
+/// - ensure_active, refusing activities on halted resources
+pub mod lifecycle;
+
+/// Signals between resources
+///
+/// Contains:
+/// - send_signal, delivering a named signal with a payload for rules to match on
+pub mod signals;
+
+/// Correlation key index for aggregate conditions
+///
+/// Contains:
+/// - CorrelationIndex of resources by the values in their metadata, updated by storage
+/// - Sibling lookups for the rules engine's `SiblingsInState` conditions
+pub mod correlation_index;
+
+/// Service accounts for CI systems and external workers
+///
+/// Contains:
+/// - ServiceAccount identities scoped to workflows and operations
+/// - ServiceAccounts registry minting short-lived tokens, with automatic rotation
+///   and last-used tracking
+pub mod service_accounts;
+
+/// Bulk transition evaluation
+///
+/// Contains:
+/// - ResourceFilter for selecting the resources of a workflow
+/// - evaluate_bulk for evaluating every activity across many resources in parallel
+/// - BulkEvaluationReport with available/blocked counts per activity
+pub mod bulk_evaluation;
+
+/// GraphQL dataloaders
+///
+/// Contains:
+/// - WorkflowLoader batching workflow lookups for resources
+/// - ResourceExecutionsLoader batching agent execution lookups per resource
+pub mod dataloaders;
+
+/// Materialized dashboard counters
+///
+/// Contains:
+/// - StateCounters with resources per workflow per state and executions per status
+/// - Incremental updates on every storage write, plus a rebuild for recovery
+pub mod state_counters;
+
+/// Memory limits for in-memory storage
+///
+/// Contains:
+/// - MemoryLimits capping the resources InMemoryStorage keeps in memory
+/// - ResourceLru ordering resources for least-recently-used eviction
+/// - SpillStore holding evicted resources in an on-disk redb file
+pub mod memory_limits;
+
+/// Event replay for NATS storage
+///
+/// Contains:
+/// - EventReplayRequest selecting stored events by subject filter and time range
+/// - ReplayedEvent carrying an event's stream sequence, subject and payload
+/// - ReplaySlot bounding how many replays run at once
+pub mod event_replay;
+
+/// Resource snapshots for NATS storage
+///
+/// Contains:
+/// - ResourceReplay for rebuilding resource state from snapshots plus newer stream messages
+/// - SnapshotCheckpoint recording how far a workflow's snapshots are up to date
+/// - ReconstructionMetrics with reconstruction and compaction timings for `/metrics`
+pub mod resource_snapshots;
+
 /// Event system for triggering functions
 ///
 /// Contains:
@@ -110,6 +291,15 @@ pub mod functions;
 /// - LLM provider integration and streaming responses
 pub mod agents;
 
+/// Stream subscription tracking and reaping
+///
+/// Contains:
+/// - SubscriberRegistry for tracking live broadcast subscribers
+/// - StreamSubscription wrapper that unregisters on drop
+/// - Background reaper for subscribers whose clients vanished
+/// - StreamGauges exposing subscriber counts per engine
+pub mod subscriptions;
+
 /// NATS storage implementation for distributed workflows
 ///
 /// Contains:
@@ -119,6 +309,20 @@ pub mod agents;
 /// - Subject hierarchy management for efficient querying
 pub mod nats_storage;
 
+/// PostgreSQL storage for deployments without NATS
+///
+/// Contains:
+/// - PostgresStorage, PostgresAgentStorage and PostgresFunctionStorage over JSONB tables
+/// - The embedded sqlx migrations creating them
+pub mod postgres_storage;
+
+/// Redis and Valkey storage for lightweight persistent deployments
+///
+/// Contains:
+/// - RedisStorage and RedisAgentStorage over hashes, sets and change streams
+/// - RedisStorageConfig with optional resource and execution TTLs
+pub mod redis_storage;
+
 // Re-export main engine types for clean API access
 // Users can import directly from engine instead of navigating submodules
 
@@ -171,6 +375,167 @@ pub use storage::{InMemoryStorage, WorkflowStorage};
 /// - WorkflowEvaluationResult: Detailed evaluation results for all transitions
 pub use rules::{RulesEngine, WorkflowEvaluationResult};
 
+/// Re-export rules engine metrics types
+///
+/// These types expose rules engine performance:
+/// - RuleMetrics: Registry of evaluation counters and latency histograms
+/// - RuleMetricsSnapshot: Point-in-time view including the most frequently failing rules
+pub use rule_metrics::{RuleMetrics, RuleMetricsSnapshot, RuleStatsSnapshot};
+
+/// Re-export compiled rule types
+///
+/// These types speed up repeated rule evaluation:
+/// - CompiledRule: A rule prepared once and evaluated many times
+/// - RuleCache: Compiled rules keyed by rule ID
+pub use rule_cache::{CompiledRule, RuleCache};
+
+/// Re-export feature flag types
+///
+/// These types toggle workflow behavior at runtime:
+/// - FeatureFlags: Global and workflow-scoped flags evaluated per resource
+pub use feature_flags::FeatureFlags;
+
+/// Re-export state capacity types
+///
+/// These types model states that hold a limited number of resources:
+/// - CapacityQueues: Resources waiting for room, per workflow and state
+/// - CapacityCheck: Whether a resource may enter a state now
+pub use state_capacity::{CapacityCheck, CapacityQueues, QueuedResource, QUEUE_ENTRY_TIMEOUT};
+
+/// Re-export activity timer types
+///
+/// These types fire activities once resources have waited long enough:
+/// - ActivityTimers: The scheduler scanning for due timers
+/// - FiredTimer: A resource a timer moved, and through which activity
+pub use timers::{ActivityTimers, FiredTimer, TIMER_TICK_INTERVAL};
+
+/// Re-export SLA tracking types
+///
+/// These types keep resources to the service levels of their states:
+/// - SlaMonitor: Flags and escalates SLA breaches on a tick
+/// - SlaBreach: A resource that overstayed a state's SLA
+/// - StateSlaMetrics: How resources keep to the SLA of one state
+pub use sla::{sla_metrics, SlaBreach, SlaMonitor, StateSlaMetrics, SLA_TICK_INTERVAL};
+
+/// Re-export workflow metrics types
+///
+/// These types point at a workflow's bottlenecks:
+/// - collect_workflow_metrics: A workflow's figures as of now
+/// - WorkflowMetrics: Latest figures of every workflow, rendered for Prometheus
+/// - WorkflowMetricsCollector: Tick loop refreshing them
+pub use workflow_metrics::{
+    collect_workflow_metrics, WorkflowMetrics, WorkflowMetricsCollector, WorkflowMetricsSnapshot,
+    METRICS_COLLECT_INTERVAL,
+};
+
+/// Re-export compensation types
+///
+/// These types roll resources back saga-style:
+/// - compensate: Executes compensations in reverse history order
+/// - Compensation: The compensated resource and the steps taken
+pub use compensation::{compensate, Compensation, CompensationStep};
+
+/// Re-export workflow versioning types
+///
+/// These types let definitions evolve without stranding resources:
+/// - publish_version: Stores the next version of a definition
+/// - WorkflowMigration: Moves resources between versions, validated first
+/// - MigrationReport: The resources a migration moved or would move
+pub use workflow_versions::{publish_version, MigratedResource, MigrationReport, WorkflowMigration};
+
+/// Re-export human task types
+///
+/// These types let people complete manual activities:
+/// - HumanTasks: Pending tasks, claims and decisions
+/// - HumanTask: A manual activity a resource waits on
+/// - HumanTaskError: Why claiming or deciding a task failed
+pub use human_tasks::{HumanTask, HumanTaskError, HumanTasks, TaskClaim, TaskDecision};
+
+/// Re-export parallel execution
+///
+/// - ParallelStep: How an activity split, joined or moved a branch of a resource
+pub use parallel::ParallelStep;
+
+/// Re-export workflow diagram types
+///
+/// These types draw workflows for UIs and docs:
+/// - workflow_diagram: Renders a stored workflow and optionally a resource's position
+/// - WorkflowDiagram: The Graphviz DOT and Mermaid sources
+pub use diagram::{render_dot, render_mermaid, workflow_diagram, WorkflowDiagram};
+
+/// Re-export template catalog
+///
+/// - TemplateCatalog: The templates workflows can be created from
+pub use templates::TemplateCatalog;
+
+/// Re-export activity retries
+///
+/// - run_with_retries: Runs an execution under an activity's retry policy
+pub use retries::run_with_retries;
+
+/// Re-export webhook trigger types
+///
+/// These types let workflows react to outside events:
+/// - WebhookTriggers: Registered triggers and the handling of their webhooks
+/// - TriggerReport: What a webhook did to which resources
+pub use triggers::{TriggerOutcome, TriggerOutcomeStatus, TriggerReport, WebhookTriggers};
+
+/// Re-export the lifecycle guard every way of executing activities goes through
+pub use lifecycle::ensure_active;
+
+/// Re-export signal delivery for coordinating related resources
+pub use signals::send_signal;
+
+/// Re-export correlation index types
+///
+/// These types find the resources that belong together:
+/// - CorrelationIndex: Resources by correlation key value, with their states
+pub use correlation_index::CorrelationIndex;
+
+/// Re-export service account types
+///
+/// These types authenticate machines with narrowly scoped tokens:
+/// - ServiceAccounts: Accounts and their tokens, shared by the API servers
+/// - ServicePrincipal: The account a request's token authenticated
+pub use service_accounts::{ServiceAccount, ServiceAccounts, ServiceOperation, ServicePrincipal};
+
+/// Re-export bulk evaluation types
+///
+/// These types evaluate transitions across many resources at once:
+/// - ResourceFilter: Which resources to evaluate
+/// - BulkEvaluationReport: Aggregate available/blocked counts per activity
+pub use bulk_evaluation::{ActivityBulkSummary, BulkEvaluationReport, ResourceFilter};
+
+/// Re-export dataloader types
+///
+/// These types batch the storage lookups of nested GraphQL fields:
+/// - WorkflowLoader: Workflow definitions by ID
+/// - ResourceExecutionsLoader: Agent executions by resource ID
+pub use dataloaders::{ResourceExecutionsLoader, WorkflowLoader};
+
+/// Re-export materialized counter types
+///
+/// These types keep dashboard queries O(1):
+/// - StateCounters: Resource and execution counts maintained by storage backends
+pub use state_counters::StateCounters;
+
+/// Re-export in-memory storage limits
+///
+/// These types keep long-running in-memory deployments bounded:
+/// - MemoryLimits: Resource cap, warning ratio and spill path
+/// - SpillStore: On-disk store for evicted resources
+pub use memory_limits::{MemoryLimits, SpillStore};
+
+/// Re-export resource snapshot types
+///
+/// These types keep NATS resource reconstruction fast:
+/// - ReconstructionMetrics: Registry of reconstruction and compaction timings
+/// - ResourceReplay: Latest resource state rebuilt from snapshots and replayed messages
+pub use resource_snapshots::{
+    ReconstructionMetrics, ReconstructionMetricsSnapshot, ReconstructionSource, ResourceReplay,
+    SnapshotCheckpoint,
+};
+
 /// Re-export event system types for workflow events
 ///
 /// These types enable event-driven function execution:
@@ -198,6 +563,16 @@ pub use agents::{
     AgentEngine, AgentEngineConfig, AgentStorage, ExecutionStats, InMemoryAgentStorage,
 };
 
+/// Re-export stream subscription types
+///
+/// These types track live stream subscribers:
+/// - SubscriberRegistry: Registry of subscribers with a background reaper
+/// - StreamSubscription: Tracked broadcast receiver
+/// - StreamGauges: Subscriber count gauges
+pub use subscriptions::{
+    StreamDelivery, StreamGauges, StreamHub, StreamItem, StreamSubscription, SubscriberRegistry,
+};
+
 /// Re-export NATS storage types for distributed workflows
 ///
 /// These types enable NATS JetStream-based distributed storage:
@@ -206,3 +581,17 @@ pub use agents::{
 /// - NATSStorageConfig: Configuration for NATS storage
 /// - WorkflowStreamManager: Automatic stream creation and management
 pub use nats_storage::{NATSStorage, NATSStorageConfig, NATSStorageWrapper, WorkflowStreamManager};
+
+/// Re-export PostgreSQL storage types
+///
+/// - PostgresStorage: Postgres implementation of WorkflowStorage
+/// - PostgresAgentStorage: Postgres implementation of AgentStorage
+/// - PostgresFunctionStorage: Postgres implementation of FunctionStorage
+pub use postgres_storage::{PostgresAgentStorage, PostgresFunctionStorage, PostgresStorage};
+
+/// Re-export Redis storage types
+///
+/// - RedisStorage: Redis implementation of WorkflowStorage
+/// - RedisAgentStorage: Redis implementation of AgentStorage
+/// - RedisStorageConfig: Connection, key prefix and TTL configuration
+pub use redis_storage::{RedisAgentStorage, RedisStorage, RedisStorageConfig};
@@ -29,21 +29,101 @@
 //! - **Storage Type**: File-based for persistence
 //! - **Replication**: Configurable based on NATS cluster setup
 //! - **Deduplication**: Based on message ID to prevent duplicates
-
-use async_nats::jetstream::{self, consumer, stream, Context};
-use async_nats::Client;
-use chrono::Utc;
+//!
+//! ## Optimistic Concurrency
+//!
+//! Resource updates are compare-and-swap: the update is checked against the
+//! `version` of the latest published copy of the resource and rejected with
+//! [`VersionConflict`](crate::CircuitBreakerError::VersionConflict) when it is stale.
+//! Before publishing, an update claims its new version in the [`VERSION_BUCKET`]
+//! key-value bucket with JetStream's expected-last-subject-sequence check, so of two
+//! processes racing on a resource only one can publish; the other gets the conflict.
+//! A claim whose resource never got published (the writer died) is ignored after
+//! [`VERSION_CLAIM_TIMEOUT`]. Updates from one process to the same resource are also
+//! serialized locally, so they don't race for claims.
+//!
+//! ## Snapshots
+//!
+//! Resource listing and lookup start from the latest-state snapshots in the
+//! [`SNAPSHOT_BUCKET`] key-value bucket and replay only the messages published after the
+//! workflow's checkpoint. [`NATSStorage::spawn_snapshot_compaction`] refreshes the
+//! snapshots every `snapshot_interval`; see [`resource_snapshots`](super::resource_snapshots).
+
+use async_nats::jetstream::context::PublishErrorKind;
+use async_nats::jetstream::{self, consumer, kv, stream, Context};
+use async_nats::{header, Client, HeaderMap, HeaderValue};
+use chrono::{DateTime, Utc};
 use futures::StreamExt;
+use serde::{Deserialize, Serialize};
 use serde_json;
 use std::collections::HashMap;
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::time::{sleep, timeout};
-use tracing::{error, warn};
+use tracing::{debug, error, warn};
 use uuid::Uuid;
 
-use crate::engine::storage::WorkflowStorage;
+use crate::engine::correlation_index::CorrelationIndex;
+use crate::engine::event_replay::{
+    published_at, EventReplayRequest, EventReplayStream, ReplaySlot, ReplayedEvent,
+};
+use crate::engine::resource_snapshots::{
+    checkpoint_key, snapshot_key, workflow_snapshot_keys, ReconstructionMetrics,
+    ReconstructionSource, ResourceReplay, SnapshotCheckpoint, DEFAULT_SNAPSHOT_INTERVAL,
+    SNAPSHOT_BUCKET,
+};
+use crate::engine::storage::{check_version, WorkflowStorage};
 use crate::models::{ActivityRecord, Resource, WorkflowDefinition};
-use crate::Result;
+use crate::{MaintenanceMode, Result};
+
+/// Messages fetched per request while replaying resource history
+const REPLAY_BATCH_SIZE: usize = 1000;
+
+/// Locks serializing resource updates, shared by resources hashing to the same one
+const UPDATE_LOCK_STRIPES: usize = 64;
+
+/// Key-value bucket holding the latest version claimed for each resource
+pub const VERSION_BUCKET: &str = "CB_RESOURCE_VERSIONS";
+
+/// How long a version claim holds off other writers before it counts as abandoned
+pub const VERSION_CLAIM_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Version an update claimed before publishing the resource
+#[derive(Debug, Serialize, Deserialize)]
+struct VersionClaim {
+    version: u64,
+    claimed_at: DateTime<Utc>,
+}
+
+impl VersionClaim {
+    fn is_abandoned(&self) -> bool {
+        (Utc::now() - self.claimed_at)
+            .to_std()
+            .is_ok_and(|age| age > VERSION_CLAIM_TIMEOUT)
+    }
+}
+
+/// Resource event messages delivered by a durable JetStream consumer
+pub type ResourceEventStream = futures::stream::BoxStream<
+    'static,
+    std::result::Result<jetstream::Message, consumer::pull::MessagesError>,
+>;
+
+/// Stop pulling from `messages` while maintenance mode is on
+fn pause_during_maintenance(
+    messages: consumer::pull::Stream,
+    maintenance: MaintenanceMode,
+) -> ResourceEventStream {
+    futures::stream::unfold(
+        (messages, maintenance),
+        |(mut messages, maintenance)| async move {
+            maintenance.wait_until_disabled().await;
+            let message = messages.next().await?;
+            Some((message, (messages, maintenance)))
+        },
+    )
+    .boxed()
+}
 
 /// Wrapper to use Arc<NATSStorage> as WorkflowStorage
 pub struct NATSStorageWrapper {
@@ -70,6 +150,18 @@ impl WorkflowStorage for NATSStorageWrapper {
         self.storage.list_workflows().await
     }
 
+    async fn list_workflow_versions(&self, id: &str) -> Result<Vec<WorkflowDefinition>> {
+        self.storage.list_workflow_versions(id).await
+    }
+
+    async fn get_workflow_version(
+        &self,
+        id: &str,
+        version: u32,
+    ) -> Result<Option<WorkflowDefinition>> {
+        self.storage.get_workflow_version(id, version).await
+    }
+
     async fn create_resource(&self, resource: Resource) -> Result<Resource> {
         self.storage.create_resource(resource).await
     }
@@ -82,9 +174,37 @@ impl WorkflowStorage for NATSStorageWrapper {
         self.storage.update_resource(resource).await
     }
 
+    async fn create_resources(&self, resources: Vec<Resource>) -> Result<Vec<Resource>> {
+        self.storage.create_resources(resources).await
+    }
+
+    async fn update_resources(&self, resources: Vec<Resource>) -> Result<Vec<Resource>> {
+        self.storage.update_resources(resources).await
+    }
+
+    fn transactional_batches(&self) -> bool {
+        self.storage.transactional_batches()
+    }
+
     async fn list_resources(&self, workflow_id: Option<&str>) -> Result<Vec<Resource>> {
         self.storage.list_resources(workflow_id).await
     }
+
+    async fn count_resources_by_state(&self, workflow_id: &str) -> Result<HashMap<String, u64>> {
+        self.storage.count_resources_by_state(workflow_id).await
+    }
+
+    async fn rebuild_state_counters(&self) -> Result<()> {
+        self.storage.rebuild_state_counters().await
+    }
+
+    async fn correlated_resources(&self, key: &str, value: &str) -> Result<Vec<Resource>> {
+        self.storage.correlated_resources(key, value).await
+    }
+
+    async fn get_workflows(&self, ids: &[String]) -> Result<HashMap<String, WorkflowDefinition>> {
+        self.storage.get_workflows(ids).await
+    }
 }
 
 /// Configuration for NATS storage
@@ -105,6 +225,9 @@ pub struct NATSStorageConfig {
     /// Connection configuration
     pub connection_timeout: Duration,
     pub reconnect_buffer_size: usize,
+
+    /// How often resource snapshots are compacted
+    pub snapshot_interval: Duration,
 }
 
 impl Default for NATSStorageConfig {
@@ -118,6 +241,7 @@ impl Default for NATSStorageConfig {
             max_deliver: 5,
             connection_timeout: Duration::from_secs(10),
             reconnect_buffer_size: 8 * 1024 * 1024, // 8MB
+            snapshot_interval: DEFAULT_SNAPSHOT_INTERVAL,
         }
     }
 }
@@ -128,6 +252,13 @@ pub struct NATSStorage {
     jetstream: Context,
     config: NATSStorageConfig,
     stream_cache: std::sync::Mutex<HashMap<String, bool>>,
+    snapshot_store: std::sync::Mutex<Option<kv::Store>>,
+    version_store: std::sync::Mutex<Option<kv::Store>>,
+    /// Resources published by this process by correlation key value, for the rules
+    /// engine's sibling conditions
+    correlations: CorrelationIndex,
+    /// Serialize the version check and publish of updates to the same resource
+    update_locks: Vec<tokio::sync::Mutex<()>>,
 }
 
 /// Stream manager for workflow-specific streams
@@ -225,6 +356,12 @@ impl NATSStorage {
             jetstream,
             config,
             stream_cache: std::sync::Mutex::new(HashMap::new()),
+            snapshot_store: std::sync::Mutex::new(None),
+            version_store: std::sync::Mutex::new(None),
+            correlations: CorrelationIndex::global(),
+            update_locks: (0..UPDATE_LOCK_STRIPES)
+                .map(|_| tokio::sync::Mutex::new(()))
+                .collect(),
         })
     }
 
@@ -428,12 +565,222 @@ impl NATSStorage {
             .await
             .map_err(|e| anyhow::anyhow!("Failed to get publish acknowledgment: {}", e))?;
 
+        self.correlations.resource_stored(resource);
+
         // Small delay to ensure message is available for consumers
         sleep(Duration::from_millis(50)).await;
 
         Ok(pub_ack.sequence)
     }
 
+    /// Publish many resources, sending every message before waiting for any
+    /// acknowledgment, so a batch costs one round trip instead of one per resource
+    async fn publish_resources(&self, resources: &[Resource]) -> Result<Vec<u64>> {
+        self.ensure_stream().await?;
+
+        let mut acks = Vec::with_capacity(resources.len());
+        for resource in resources {
+            let payload = serde_json::to_vec(resource)?;
+            let ack = self
+                .jetstream
+                .publish(resource.nats_subject_for_state(), payload.into())
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to publish token to NATS: {}", e))?;
+            acks.push(ack);
+        }
+
+        let mut sequences = Vec::with_capacity(acks.len());
+        for (resource, ack) in resources.iter().zip(acks) {
+            let pub_ack = ack
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to get publish acknowledgment: {}", e))?;
+            self.correlations.resource_stored(resource);
+            sequences.push(pub_ack.sequence);
+        }
+
+        // Small delay to ensure messages are available for consumers
+        sleep(Duration::from_millis(50)).await;
+
+        Ok(sequences)
+    }
+
+    /// Publish one event per workflow describing a batch of stored resources
+    async fn publish_batch_events(
+        &self,
+        subject_suffix: &str,
+        event_type: &str,
+        resources: &[Resource],
+        sequences: &[u64],
+    ) -> Result<()> {
+        let mut by_workflow: HashMap<&str, Vec<serde_json::Value>> = HashMap::new();
+        for (resource, sequence) in resources.iter().zip(sequences) {
+            let last = resource.history.last();
+            by_workflow
+                .entry(&resource.workflow_id)
+                .or_default()
+                .push(serde_json::json!({
+                    "resource_id": resource.id,
+                    "state": resource.state.as_str(),
+                    "activity_id": last.map(|event| event.activity.as_str()),
+                    "from_state": last.map(|event| event.from.as_str()),
+                    "nats_sequence": sequence
+                }));
+        }
+
+        let mut acks = Vec::with_capacity(by_workflow.len());
+        for (workflow_id, items) in by_workflow {
+            let event_subject = format!("cb.workflows.{}.events.{}", workflow_id, subject_suffix);
+            let event_payload = serde_json::json!({
+                "event_type": event_type,
+                "workflow_id": workflow_id,
+                "resources": items,
+                "timestamp": Utc::now()
+            });
+            let ack = self
+                .jetstream
+                .publish(event_subject, serde_json::to_vec(&event_payload)?.into())
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to publish batch event: {}", e))?;
+            acks.push(ack);
+        }
+        for ack in acks {
+            ack.await.map_err(|e| {
+                anyhow::anyhow!("Failed to get batch event publish acknowledgment: {}", e)
+            })?;
+        }
+        Ok(())
+    }
+
+    fn update_lock_stripe(&self, resource_id: &Uuid) -> usize {
+        (resource_id.as_u128() % self.update_locks.len() as u128) as usize
+    }
+
+    /// Lock the stripes of all `resources`, in stripe order so batches cannot deadlock
+    async fn lock_updates(&self, resources: &[Resource]) -> Vec<tokio::sync::MutexGuard<'_, ()>> {
+        let mut stripes: Vec<usize> = resources
+            .iter()
+            .map(|resource| self.update_lock_stripe(&resource.id))
+            .collect();
+        stripes.sort_unstable();
+        stripes.dedup();
+        let mut guards = Vec::with_capacity(stripes.len());
+        for stripe in stripes {
+            guards.push(self.update_locks[stripe].lock().await);
+        }
+        guards
+    }
+
+    /// Check an update against the latest published copy of the resource, claim the
+    /// next version and bump the resource to it
+    async fn next_version(&self, resource: &mut Resource) -> Result<()> {
+        let Some(stored) = self
+            .get_resource_from_workflow(&resource.id, &resource.workflow_id)
+            .await?
+        else {
+            return Ok(());
+        };
+
+        let store = self.version_store().await?;
+        let key = resource.id.to_string();
+        let entry = store
+            .entry(key.clone())
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to read version claim: {}", e))?;
+        let revision = entry.as_ref().map_or(0, |entry| entry.revision);
+        let claim = entry
+            .filter(|entry| entry.operation == kv::Operation::Put)
+            .and_then(|entry| serde_json::from_slice::<VersionClaim>(&entry.value).ok());
+
+        // A newer claim not yet visible in the stream is an update still publishing
+        if let Some(claim) =
+            claim.filter(|claim| claim.version > stored.version && !claim.is_abandoned())
+        {
+            return Err(crate::CircuitBreakerError::VersionConflict {
+                id: key,
+                expected: resource.version,
+                actual: claim.version,
+            });
+        }
+        check_version(resource, stored.version)?;
+
+        let version = stored.version + 1;
+        self.claim_version(&key, revision, version).await?;
+        resource.version = version;
+        Ok(())
+    }
+
+    /// Record `version` as claimed, unless someone else claimed a version since the
+    /// claim at `revision` (0 when there was none)
+    async fn claim_version(&self, key: &str, revision: u64, version: u64) -> Result<()> {
+        let claim = VersionClaim {
+            version,
+            claimed_at: Utc::now(),
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::NATS_EXPECTED_LAST_SUBJECT_SEQUENCE,
+            HeaderValue::from(revision),
+        );
+        let ack = self
+            .jetstream
+            .publish_with_headers(
+                format!("$KV.{}.{}", VERSION_BUCKET, key),
+                headers,
+                serde_json::to_vec(&claim)?.into(),
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to publish version claim: {}", e))?;
+
+        match ack.await {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == PublishErrorKind::WrongLastSequence => {
+                Err(crate::CircuitBreakerError::VersionConflict {
+                    id: key.to_string(),
+                    expected: version - 1,
+                    actual: version,
+                })
+            }
+            Err(e) => Err(anyhow::anyhow!("Failed to claim resource version: {}", e).into()),
+        }
+    }
+
+    /// Give up the version claims of resources that were not published after all
+    async fn release_versions(&self, resources: &[Resource]) {
+        let Ok(store) = self.version_store().await else {
+            return;
+        };
+        for resource in resources {
+            if let Err(e) = store.delete(resource.id.to_string()).await {
+                warn!("Failed to release version claim of {}: {}", resource.id, e);
+            }
+        }
+    }
+
+    /// Key-value bucket holding version claims, created on first use
+    async fn version_store(&self) -> Result<kv::Store> {
+        if let Some(store) = self.version_store.lock().unwrap().clone() {
+            return Ok(store);
+        }
+
+        let store = match self.jetstream.get_key_value(VERSION_BUCKET).await {
+            Ok(store) => store,
+            Err(_) => self
+                .jetstream
+                .create_key_value(kv::Config {
+                    bucket: VERSION_BUCKET.to_string(),
+                    description: "Latest claimed version of Circuit Breaker resources".to_string(),
+                    history: 1,
+                    storage: stream::StorageType::File,
+                    ..Default::default()
+                })
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to create version bucket: {}", e))?,
+        };
+
+        *self.version_store.lock().unwrap() = Some(store.clone());
+        Ok(store)
+    }
+
     /// Get resource from NATS by ID
     async fn get_resource_from_nats(
         &self,
@@ -450,12 +797,32 @@ impl NATSStorage {
         self.search_resource_across_workflows(resource_id).await
     }
 
-    /// Get resource from a specific workflow with retry logic
+    /// Get resource from a specific workflow, starting from its snapshot when there is one
     pub async fn get_resource_from_workflow(
         &self,
         resource_id: &Uuid,
         workflow_id: &str,
     ) -> Result<Option<Resource>> {
+        if let Some(checkpoint) = self.load_checkpoint(workflow_id).await? {
+            let started = Instant::now();
+            let snapshot = self.load_snapshot(workflow_id, resource_id).await?;
+            let mut replay = ResourceReplay::from_snapshots(checkpoint.sequence, snapshot);
+            self.replay_resources(
+                format!(
+                    "cb.workflows.{}.states.*.resources.{}",
+                    workflow_id, resource_id
+                ),
+                &mut replay,
+            )
+            .await?;
+            ReconstructionMetrics::global().record_reconstruction(
+                ReconstructionSource::Snapshot,
+                started.elapsed(),
+                &replay,
+            );
+            return Ok(replay.into_resources().into_iter().next());
+        }
+
         // Use the same proven approach as get_tokens_in_place, but search for specific token
         let workflow_def = match self.get_workflow_from_nats(workflow_id).await? {
             Some(workflow) => workflow,
@@ -551,42 +918,287 @@ impl NATSStorage {
 
     /// List resources in a specific workflow
     async fn list_resources_in_workflow(&self, workflow_id: &str) -> Result<Vec<Resource>> {
-        let stream_name = self.stream_manager().stream_name();
+        Ok(self
+            .reconstruct_workflow(workflow_id)
+            .await?
+            .into_resources())
+    }
 
-        let stream = match self.jetstream.get_stream(&stream_name).await {
-            Ok(stream) => stream,
-            Err(_) => return Ok(vec![]),
+    /// Rebuild the latest state of every resource in a workflow from its snapshots and the
+    /// messages published since, or from the whole stream before the first compaction
+    async fn reconstruct_workflow(&self, workflow_id: &str) -> Result<ResourceReplay> {
+        let started = Instant::now();
+        let (mut replay, source) = match self.load_checkpoint(workflow_id).await? {
+            Some(checkpoint) => (
+                ResourceReplay::from_snapshots(
+                    checkpoint.sequence,
+                    self.load_workflow_snapshots(workflow_id).await?,
+                ),
+                ReconstructionSource::Snapshot,
+            ),
+            None => (ResourceReplay::default(), ReconstructionSource::FullReplay),
         };
 
+        self.replay_resources(
+            format!("cb.workflows.{}.states.*.resources.*", workflow_id),
+            &mut replay,
+        )
+        .await?;
+
+        let elapsed = started.elapsed();
+        debug!(
+            "Reconstructed {} resources of workflow {} from {} in {:?} ({} messages replayed)",
+            replay.len(),
+            workflow_id,
+            source.as_str(),
+            elapsed,
+            replay.replayed()
+        );
+        ReconstructionMetrics::global().record_reconstruction(source, elapsed, &replay);
+        Ok(replay)
+    }
+
+    /// Apply the resource messages on `filter_subject` published after the replay's last
+    /// sequence
+    async fn replay_resources(
+        &self,
+        filter_subject: String,
+        replay: &mut ResourceReplay,
+    ) -> Result<()> {
+        let deliver_policy = match replay.last_sequence() {
+            0 => consumer::DeliverPolicy::All,
+            sequence => consumer::DeliverPolicy::ByStartSequence {
+                start_sequence: sequence + 1,
+            },
+        };
         let consumer_config = consumer::pull::Config {
             durable_name: None, // Use ephemeral consumer
-            filter_subject: format!("cb.workflows.{}.states.*.resources.*", workflow_id),
-            deliver_policy: consumer::DeliverPolicy::LastPerSubject,
-            ack_policy: consumer::AckPolicy::None, // Read-only access for resource listing
+            filter_subject,
+            deliver_policy,
+            ack_policy: consumer::AckPolicy::None, // Read-only replay
             ..Default::default()
         };
 
+        self.drain_consumer(
+            &self.stream_manager().stream_name(),
+            consumer_config,
+            |sequence, payload| {
+                if let Ok(resource) = serde_json::from_slice::<Resource>(payload) {
+                    replay.apply(sequence, resource);
+                }
+            },
+        )
+        .await
+    }
+
+    /// Deliver every message an ephemeral consumer has pending, with its stream sequence
+    async fn drain_consumer(
+        &self,
+        stream_name: &str,
+        consumer_config: consumer::pull::Config,
+        mut handle: impl FnMut(u64, &[u8]),
+    ) -> Result<()> {
+        let stream = match self.jetstream.get_stream(stream_name).await {
+            Ok(stream) => stream,
+            Err(_) => return Ok(()),
+        };
+
         let consumer = stream
             .create_consumer(consumer_config)
             .await
-            .map_err(|e| anyhow::anyhow!("Failed to create workflow resources consumer: {}", e))?;
-        let mut resources = Vec::new();
-        let mut batch = consumer
-            .batch()
-            .max_messages(1000)
-            .messages()
+            .map_err(|e| anyhow::anyhow!("Failed to create replay consumer: {}", e))?;
+
+        loop {
+            let mut batch = consumer
+                .fetch()
+                .max_messages(REPLAY_BATCH_SIZE)
+                .messages()
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to get replay batch: {}", e))?;
+
+            let mut received = 0;
+            while let Some(message) = batch.next().await {
+                let message = message
+                    .map_err(|e| anyhow::anyhow!("Failed to receive replay message: {}", e))?;
+                received += 1;
+                let sequence = message.info().map(|info| info.stream_sequence).unwrap_or(0);
+                handle(sequence, &message.payload);
+            }
+
+            if received < REPLAY_BATCH_SIZE {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Key-value bucket holding resource snapshots, created on first use
+    ///
+    /// Returns `None` when the bucket cannot be reached, in which case callers fall back
+    /// to replaying the whole stream.
+    async fn snapshot_store(&self) -> Option<kv::Store> {
+        if let Some(store) = self.snapshot_store.lock().unwrap().clone() {
+            return Some(store);
+        }
+
+        let store = match self.jetstream.get_key_value(SNAPSHOT_BUCKET).await {
+            Ok(store) => store,
+            Err(_) => match self
+                .jetstream
+                .create_key_value(kv::Config {
+                    bucket: SNAPSHOT_BUCKET.to_string(),
+                    description: "Latest state of Circuit Breaker resources".to_string(),
+                    history: 1,
+                    storage: stream::StorageType::File,
+                    ..Default::default()
+                })
+                .await
+            {
+                Ok(store) => store,
+                Err(e) => {
+                    warn!("Resource snapshot bucket unavailable: {}", e);
+                    return None;
+                }
+            },
+        };
+
+        *self.snapshot_store.lock().unwrap() = Some(store.clone());
+        Some(store)
+    }
+
+    /// Checkpoint of a workflow's snapshots, if it was ever compacted
+    async fn load_checkpoint(&self, workflow_id: &str) -> Result<Option<SnapshotCheckpoint>> {
+        let Some(store) = self.snapshot_store().await else {
+            return Ok(None);
+        };
+        let entry = store
+            .get(checkpoint_key(workflow_id))
             .await
-            .map_err(|e| anyhow::anyhow!("Failed to get workflow resources batch: {}", e))?;
+            .map_err(|e| anyhow::anyhow!("Failed to read snapshot checkpoint: {}", e))?;
+        Ok(entry.and_then(|payload| serde_json::from_slice(&payload).ok()))
+    }
 
-        while let Some(message) = batch.next().await {
-            let message = message.map_err(|e| {
-                anyhow::anyhow!("Failed to receive workflow resource message: {}", e)
-            })?;
-            if let Ok(resource) = serde_json::from_slice::<Resource>(&message.payload) {
-                resources.push(resource);
+    /// Snapshot of a single resource
+    async fn load_snapshot(
+        &self,
+        workflow_id: &str,
+        resource_id: &Uuid,
+    ) -> Result<Option<Resource>> {
+        let Some(store) = self.snapshot_store().await else {
+            return Ok(None);
+        };
+        let entry = store
+            .get(snapshot_key(workflow_id, resource_id))
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to read resource snapshot: {}", e))?;
+        Ok(entry.and_then(|payload| serde_json::from_slice(&payload).ok()))
+    }
+
+    /// Snapshots of every resource in a workflow
+    async fn load_workflow_snapshots(&self, workflow_id: &str) -> Result<Vec<Resource>> {
+        // Read the bucket's backing stream directly so only this workflow's keys are visited
+        let consumer_config = consumer::pull::Config {
+            durable_name: None, // Use ephemeral consumer
+            filter_subject: format!(
+                "$KV.{}.{}",
+                SNAPSHOT_BUCKET,
+                workflow_snapshot_keys(workflow_id)
+            ),
+            deliver_policy: consumer::DeliverPolicy::LastPerSubject,
+            ack_policy: consumer::AckPolicy::None, // Read-only access for snapshot loading
+            ..Default::default()
+        };
+
+        let mut snapshots = Vec::new();
+        self.drain_consumer(
+            &format!("KV_{}", SNAPSHOT_BUCKET),
+            consumer_config,
+            |_, payload| {
+                // Deleted keys leave empty markers behind
+                if let Ok(resource) = serde_json::from_slice::<Resource>(payload) {
+                    snapshots.push(resource);
+                }
+            },
+        )
+        .await?;
+        Ok(snapshots)
+    }
+
+    /// Snapshot the resources of a workflow changed since its last checkpoint and move the
+    /// checkpoint forward, returning how many snapshots were written
+    pub async fn compact_workflow(&self, workflow_id: &str) -> Result<usize> {
+        let store = self
+            .snapshot_store()
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Resource snapshot bucket unavailable"))?;
+        let replay = self.reconstruct_workflow(workflow_id).await?;
+
+        let mut written = 0;
+        for resource in replay.changed() {
+            store
+                .put(
+                    snapshot_key(workflow_id, &resource.id),
+                    serde_json::to_vec(resource)?.into(),
+                )
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to write resource snapshot: {}", e))?;
+            written += 1;
+        }
+
+        let checkpoint = SnapshotCheckpoint {
+            sequence: replay.last_sequence(),
+            resources: replay.len(),
+            compacted_at: Utc::now(),
+        };
+        store
+            .put(
+                checkpoint_key(workflow_id),
+                serde_json::to_vec(&checkpoint)?.into(),
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to write snapshot checkpoint: {}", e))?;
+
+        Ok(written)
+    }
+
+    /// Compact the snapshots of every workflow, returning how many snapshots were written
+    pub async fn compact_snapshots(&self) -> Result<usize> {
+        let started = Instant::now();
+        let metrics = ReconstructionMetrics::global();
+
+        let mut written = 0;
+        for workflow in self.list_all_workflows().await? {
+            match self.compact_workflow(&workflow.id).await {
+                Ok(count) => written += count,
+                Err(e) => {
+                    metrics.record_compaction_failure();
+                    return Err(e);
+                }
             }
         }
-        Ok(resources)
+
+        metrics.record_compaction(started.elapsed(), written);
+        Ok(written)
+    }
+
+    /// Compact snapshots every `snapshot_interval` until the task is aborted
+    ///
+    /// Compaction is skipped while maintenance mode is on.
+    pub fn spawn_snapshot_compaction(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let storage = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(storage.config.snapshot_interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                ticker.tick().await;
+                if MaintenanceMode::global().is_enabled() {
+                    continue;
+                }
+                match storage.compact_snapshots().await {
+                    Ok(written) => debug!("Compacted {} resource snapshots", written),
+                    Err(e) => warn!("Resource snapshot compaction failed: {}", e),
+                }
+            }
+        })
     }
 
     /// List all workflows by scanning streams
@@ -601,7 +1213,8 @@ impl NATSStorage {
                 let consumer_config = async_nats::jetstream::consumer::pull::Config {
                     durable_name: None, // Use ephemeral consumer
                     filter_subject: "cb.workflows.*.definition".to_string(),
-                    deliver_policy: async_nats::jetstream::consumer::DeliverPolicy::All,
+                    // Earlier versions stay in the stream; only list the current ones
+                    deliver_policy: async_nats::jetstream::consumer::DeliverPolicy::LastPerSubject,
                     ack_policy: async_nats::jetstream::consumer::AckPolicy::None, // Read-only access
                     max_deliver: self.config.max_deliver,
                     ack_wait: std::time::Duration::from_secs(30),
@@ -656,6 +1269,60 @@ impl NATSStorage {
         Ok(workflows)
     }
 
+    /// List every version of a workflow definition published to the stream, oldest first
+    async fn list_workflow_versions_from_nats(
+        &self,
+        workflow_id: &str,
+    ) -> Result<Vec<WorkflowDefinition>> {
+        let stream_name = self.stream_manager().stream_name();
+        let Ok(stream) = self.jetstream.get_stream(&stream_name).await else {
+            return Ok(Vec::new());
+        };
+
+        let consumer = stream
+            .create_consumer(consumer::pull::Config {
+                durable_name: None,
+                filter_subject: format!("cb.workflows.{}.definition", workflow_id),
+                deliver_policy: consumer::DeliverPolicy::All,
+                ack_policy: consumer::AckPolicy::None,
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to create NATS consumer: {}", e))?;
+        let pending = consumer
+            .cached_info()
+            .num_pending
+            .try_into()
+            .unwrap_or(usize::MAX);
+        if pending == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut messages = consumer
+            .fetch()
+            .max_messages(pending)
+            .messages()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to fetch NATS messages: {}", e))?;
+
+        // A version published again replaces the earlier message under its number
+        let mut versions: HashMap<u32, WorkflowDefinition> = HashMap::new();
+        while let Some(message) = messages.next().await {
+            let message =
+                message.map_err(|e| anyhow::anyhow!("Failed to receive NATS message: {}", e))?;
+            match serde_json::from_slice::<WorkflowDefinition>(&message.payload) {
+                Ok(workflow) => {
+                    versions.insert(workflow.version, workflow);
+                }
+                Err(e) => warn!("Failed to parse workflow definition: {}", e),
+            }
+        }
+
+        let mut versions: Vec<_> = versions.into_values().collect();
+        versions.sort_by_key(|workflow| workflow.version);
+        Ok(versions)
+    }
+
     /// Create resource with NATS activity event
     pub async fn create_resource_with_event(
         &self,
@@ -726,6 +1393,8 @@ impl NATSStorage {
         activity_id: crate::models::ActivityId,
         triggered_by: Option<String>,
     ) -> Result<Resource> {
+        let _guard = self.lock_updates(std::slice::from_ref(&resource)).await;
+        self.next_version(&mut resource).await?;
         let old_state = resource.state.clone();
         let now = Utc::now();
 
@@ -791,6 +1460,10 @@ impl WorkflowStorage for NATSStorage {
         self.list_all_workflows().await
     }
 
+    async fn list_workflow_versions(&self, id: &str) -> Result<Vec<WorkflowDefinition>> {
+        self.list_workflow_versions_from_nats(id).await
+    }
+
     async fn create_resource(&self, resource: Resource) -> Result<Resource> {
         self.create_resource_with_event(resource, Some("api".to_string()))
             .await
@@ -801,9 +1474,18 @@ impl WorkflowStorage for NATSStorage {
     }
 
     async fn update_resource(&self, mut resource: Resource) -> Result<Resource> {
+        let _guard = self.lock_updates(std::slice::from_ref(&resource)).await;
+        self.next_version(&mut resource).await?;
+
         // For updates with state changes, we need to ensure proper NATS metadata
         let now = Utc::now();
-        let sequence = self.publish_resource(&resource).await?;
+        let sequence = match self.publish_resource(&resource).await {
+            Ok(sequence) => sequence,
+            Err(e) => {
+                self.release_versions(std::slice::from_ref(&resource)).await;
+                return Err(e);
+            }
+        };
 
         // Update NATS metadata with the new subject and sequence
         resource.set_nats_metadata(sequence, now, resource.nats_subject_for_state());
@@ -811,6 +1493,59 @@ impl WorkflowStorage for NATSStorage {
         Ok(resource)
     }
 
+    /// Create a batch of resources with batched publishes and one lifecycle event per
+    /// workflow, instead of a round trip and an event per resource
+    async fn create_resources(&self, mut resources: Vec<Resource>) -> Result<Vec<Resource>> {
+        let now = Utc::now();
+        let sequences = self.publish_resources(&resources).await?;
+        for (resource, sequence) in resources.iter_mut().zip(&sequences) {
+            resource.add_activity_record(ActivityRecord {
+                from_state: resource.state.clone(),
+                to_state: resource.state.clone(),
+                activity_id: "create".into(),
+                timestamp: now,
+                triggered_by: Some("api".to_string()),
+                nats_sequence: Some(*sequence),
+                metadata: Some(serde_json::json!({
+                    "event_type": "creation",
+                    "workflow_id": resource.workflow_id,
+                    "created_at": now
+                })),
+            });
+            resource.set_nats_metadata(*sequence, now, resource.nats_subject_for_state());
+        }
+        self.publish_resources(&resources).await?;
+        self.publish_batch_events("lifecycle", "resources_created", &resources, &sequences)
+            .await?;
+        Ok(resources)
+    }
+
+    /// Update a batch of resources with batched publishes and one activity event per
+    /// workflow; a stale resource fails the batch before anything is published
+    async fn update_resources(&self, mut resources: Vec<Resource>) -> Result<Vec<Resource>> {
+        let _guards = self.lock_updates(&resources).await;
+        for claimed in 0..resources.len() {
+            if let Err(e) = self.next_version(&mut resources[claimed]).await {
+                self.release_versions(&resources[..claimed]).await;
+                return Err(e);
+            }
+        }
+        let now = Utc::now();
+        let sequences = match self.publish_resources(&resources).await {
+            Ok(sequences) => sequences,
+            Err(e) => {
+                self.release_versions(&resources).await;
+                return Err(e);
+            }
+        };
+        for (resource, sequence) in resources.iter_mut().zip(&sequences) {
+            resource.set_nats_metadata(*sequence, now, resource.nats_subject_for_state());
+        }
+        self.publish_batch_events("activities", "resources_updated", &resources, &sequences)
+            .await?;
+        Ok(resources)
+    }
+
     async fn list_resources(&self, workflow_id: Option<&str>) -> Result<Vec<Resource>> {
         match workflow_id {
             Some(wid) => {
@@ -831,6 +1566,17 @@ impl WorkflowStorage for NATSStorage {
             }
         }
     }
+
+    async fn get_workflows(&self, ids: &[String]) -> Result<HashMap<String, WorkflowDefinition>> {
+        // One scan of the definition subjects answers every ID
+        Ok(self
+            .list_all_workflows()
+            .await?
+            .into_iter()
+            .filter(|workflow| ids.contains(&workflow.id))
+            .map(|workflow| (workflow.id.clone(), workflow))
+            .collect())
+    }
 }
 
 /// Utility functions for NATS resource operations
@@ -920,10 +1666,13 @@ impl NATSStorage {
     }
 
     /// Subscribe to resource events for real-time updates
+    ///
+    /// The consumer stops pulling messages while maintenance mode is on and resumes
+    /// where it left off once it is switched off.
     pub async fn subscribe_to_resource_events(
         &self,
         workflow_id: &str,
-    ) -> Result<consumer::pull::Stream> {
+    ) -> Result<ResourceEventStream> {
         let stream_name = self.stream_manager().stream_name();
         let stream = self
             .jetstream
@@ -946,7 +1695,94 @@ impl NATSStorage {
             .messages()
             .await
             .map_err(|e| anyhow::anyhow!("Failed to get events stream: {}", e))?;
-        Ok(stream)
+        Ok(pause_during_maintenance(
+            stream,
+            crate::MaintenanceMode::global(),
+        ))
+    }
+
+    /// Replay the stored events a request selects, at no more than its rate
+    ///
+    /// The replay ends after the last matching event stored when it started, or at the
+    /// first event published after the request's `to`.
+    pub async fn replay_events(&self, request: EventReplayRequest) -> Result<EventReplayStream> {
+        request.validate()?;
+        let slot = ReplaySlot::acquire()?;
+
+        let stream_name = self.stream_manager().stream_name();
+        let stream = self
+            .jetstream
+            .get_stream(&stream_name)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to get NATS stream: {}", e))?;
+        let start_time = time::OffsetDateTime::from_unix_timestamp(request.from.timestamp())
+            .map_err(|e| anyhow::anyhow!("Invalid replay start time: {}", e))?;
+        let mut consumer = stream
+            .create_consumer(consumer::pull::Config {
+                durable_name: None, // Use ephemeral consumer
+                filter_subject: request.subject.clone(),
+                deliver_policy: consumer::DeliverPolicy::ByStartTime { start_time },
+                ack_policy: consumer::AckPolicy::None, // Read-only replay
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to create replay consumer: {}", e))?;
+        let pending = consumer
+            .info()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to get replay consumer info: {}", e))?
+            .num_pending;
+        let messages = consumer
+            .messages()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to get replay stream: {}", e))?;
+
+        let from = request.from;
+        let to = request.to.unwrap_or_else(Utc::now);
+        let mut pacer = tokio::time::interval(request.interval());
+        pacer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        debug!(
+            "Replaying {} events on {} at up to {}/s",
+            pending, request.subject, request.max_events_per_second
+        );
+
+        let events = futures::stream::unfold(
+            (messages, pacer, pending, slot),
+            move |(mut messages, mut pacer, mut remaining, slot)| async move {
+                while remaining > 0 {
+                    remaining -= 1;
+                    let message = match messages.next().await? {
+                        Ok(message) => message,
+                        Err(e) => {
+                            let error = anyhow::anyhow!("Failed to receive replayed event: {}", e);
+                            return Some((Err(error.into()), (messages, pacer, 0, slot)));
+                        }
+                    };
+                    let (sequence, published) = match message.info() {
+                        Ok(info) => (info.stream_sequence, published_at(info.published)),
+                        Err(_) => continue,
+                    };
+                    // The start time only has second precision
+                    if published < from {
+                        continue;
+                    }
+                    if published > to {
+                        return None;
+                    }
+
+                    pacer.tick().await;
+                    let event = ReplayedEvent::new(
+                        sequence,
+                        message.subject.to_string(),
+                        published,
+                        &message.payload,
+                    );
+                    return Some((Ok(event), (messages, pacer, remaining, slot)));
+                }
+                None
+            },
+        );
+        Ok(events.boxed())
     }
 
     /// Find resource by ID with known workflow (more efficient)
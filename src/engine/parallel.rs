@@ -0,0 +1,237 @@
+// Parallel split/join execution
+// Forks resources into concurrent branches and synchronizes them again
+
+//! # Parallel Branches
+//!
+//! A resource normally sits in a single state. An activity with a
+//! [`Gateway::Split`] forks it instead: the resource moves to the split's `to_state`
+//! and a branch starts in every listed state. The branches are tracked in the
+//! resource's metadata under [`BRANCHES_KEY`](crate::models::BRANCHES_KEY), so storage needs nothing new.
+//!
+//! While the resource is split, [`execute`] moves whichever branch sits in a source
+//! state of the activity, leaving the resource's own state alone. A
+//! [`Gateway::Join`] executes once every branch has arrived in one of its source
+//! states - the rules engine evaluates that through
+//! [`ActivityDefinition::can_execute_on`] - closes the branches and moves the resource
+//! on. An ordinary activity executed from the resource's own state leaves the parallel
+//! section early and abandons the open branches, e.g. to cancel.
+//!
+//! Every history event records the step in its data: `{"split": [...]}`,
+//! `{"branch": <name>}`, `{"join": {...}}` or `{"abandoned": {...}}`.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tracing::debug;
+
+use crate::models::{ActivityDefinition, Gateway, HistoryEvent, Resource, StateId};
+use crate::{CircuitBreakerError, Result};
+
+/// How executing an activity moved a resource
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParallelStep {
+    /// The resource forked into a branch per state
+    Split { branches: Vec<StateId> },
+    /// One branch moved; the resource kept its state
+    Branch {
+        branch: String,
+        from: StateId,
+        to: StateId,
+    },
+    /// All branches arrived and were closed
+    Join { branches: BTreeMap<String, StateId> },
+    /// The resource itself moved, abandoning any open branches
+    Linear {
+        abandoned: BTreeMap<String, StateId>,
+    },
+}
+
+/// Execute `activity` on `resource`, splitting, joining or moving a branch as needed
+///
+/// Fails with [`InvalidTransition`](CircuitBreakerError::InvalidTransition) when the
+/// activity cannot run where the resource and its branches are, and with
+/// [`ResourceHalted`](CircuitBreakerError::ResourceHalted) when the resource is paused
+/// or cancelled. Rules are left to the caller.
+pub fn execute(resource: &mut Resource, activity: &ActivityDefinition) -> Result<ParallelStep> {
+    super::lifecycle::ensure_active(resource)?;
+    if !activity.can_execute_on(resource) {
+        return Err(CircuitBreakerError::InvalidTransition {
+            from: resource.state.as_str().to_string(),
+            to: activity.to_state.as_str().to_string(),
+            transition: activity.id.as_str().to_string(),
+        });
+    }
+
+    // Routes decide where a resource or branch moving on its own goes
+    let to = activity.target_state(resource).clone();
+    let step = match &activity.gateway {
+        Some(Gateway::Split(branches)) => {
+            resource.execute_activity(activity.to_state.clone(), activity.id.clone());
+            resource.set_branches(
+                branches
+                    .iter()
+                    .map(|state| (state.as_str().to_string(), state.clone()))
+                    .collect(),
+            );
+            ParallelStep::Split {
+                branches: branches.clone(),
+            }
+        }
+        Some(Gateway::Join) => {
+            let branches = resource.branches();
+            resource.set_branches(BTreeMap::new());
+            resource.execute_activity(activity.to_state.clone(), activity.id.clone());
+            ParallelStep::Join { branches }
+        }
+        None => match activity.branch_to_move(resource) {
+            Some(branch) => {
+                let mut branches = resource.branches();
+                let from = branches
+                    .insert(branch.clone(), to.clone())
+                    .unwrap_or_else(|| resource.state.clone());
+                resource.history.push(HistoryEvent {
+                    timestamp: chrono::Utc::now(),
+                    activity: activity.id.clone(),
+                    from: from.clone(),
+                    to: to.clone(),
+                    data: None,
+                });
+                resource.set_branches(branches);
+                ParallelStep::Branch { branch, from, to }
+            }
+            None => {
+                let abandoned = resource.branches();
+                resource.set_branches(BTreeMap::new());
+                resource.execute_activity(to, activity.id.clone());
+                ParallelStep::Linear { abandoned }
+            }
+        },
+    };
+
+    if let Some(event) = resource.history.last_mut() {
+        event.data = step.history_data();
+    }
+    debug!(
+        "Activity '{}' stepped resource {}: {:?}",
+        activity.id.as_str(),
+        resource.id,
+        step
+    );
+    Ok(step)
+}
+
+impl ParallelStep {
+    /// Data recorded on the history event of the step, if any
+    fn history_data(&self) -> Option<serde_json::Value> {
+        match self {
+            ParallelStep::Split { branches } => Some(serde_json::json!({ "split": branches })),
+            ParallelStep::Branch { branch, .. } => Some(serde_json::json!({ "branch": branch })),
+            ParallelStep::Join { branches } => Some(serde_json::json!({ "join": branches })),
+            ParallelStep::Linear { abandoned } if abandoned.is_empty() => None,
+            ParallelStep::Linear { abandoned } => {
+                Some(serde_json::json!({ "abandoned": abandoned }))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::rules::RulesEngine;
+    use crate::models::{WorkflowDefinition, BRANCHES_KEY};
+
+    fn contract_workflow() -> WorkflowDefinition {
+        WorkflowDefinition::new(
+            "contract",
+            "Contract",
+            vec![
+                StateId::from("draft"),
+                StateId::from("in_review"),
+                StateId::from("legal"),
+                StateId::from("legal_done"),
+                StateId::from("finance"),
+                StateId::from("finance_done"),
+                StateId::from("signed"),
+                StateId::from("cancelled"),
+            ],
+            vec![
+                ActivityDefinition::new("submit", vec!["draft"], "in_review")
+                    .with_split(vec!["legal", "finance"]),
+                ActivityDefinition::new("legal_ok", vec!["legal"], "legal_done"),
+                ActivityDefinition::new("finance_ok", vec!["finance"], "finance_done"),
+                ActivityDefinition::new("sign", vec!["legal_done", "finance_done"], "signed")
+                    .with_join(),
+                ActivityDefinition::new("cancel", vec!["in_review"], "cancelled"),
+            ],
+            "draft",
+        )
+    }
+
+    fn activity<'a>(workflow: &'a WorkflowDefinition, id: &str) -> &'a ActivityDefinition {
+        workflow
+            .activities
+            .iter()
+            .find(|a| a.id.as_str() == id)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_split_branches_and_join() {
+        let workflow = contract_workflow();
+        assert!(workflow.validate().is_ok());
+        let engine = RulesEngine::new();
+        let mut resource = Resource::new("contract", StateId::from("draft"));
+
+        let step = execute(&mut resource, activity(&workflow, "submit")).unwrap();
+        assert!(matches!(step, ParallelStep::Split { .. }));
+        assert_eq!(resource.state, StateId::from("in_review"));
+        assert_eq!(resource.branches().len(), 2);
+
+        execute(&mut resource, activity(&workflow, "legal_ok")).unwrap();
+        assert_eq!(resource.state, StateId::from("in_review"));
+        assert_eq!(
+            resource.branches().get("legal"),
+            Some(&StateId::from("legal_done"))
+        );
+        assert_eq!(
+            resource.history.last().unwrap().data,
+            Some(serde_json::json!({ "branch": "legal" }))
+        );
+
+        // The join waits for the finance branch
+        let sign = activity(&workflow, "sign");
+        assert!(!engine.can_execute_activity(&resource, sign));
+        assert!(execute(&mut resource, sign).is_err());
+
+        execute(&mut resource, activity(&workflow, "finance_ok")).unwrap();
+        assert!(engine.can_execute_activity(&resource, sign));
+        let step = execute(&mut resource, sign).unwrap();
+        assert!(matches!(step, ParallelStep::Join { ref branches } if branches.len() == 2));
+        assert_eq!(resource.state, StateId::from("signed"));
+        assert!(!resource.is_split());
+        assert!(!resource.metadata.contains_key(BRANCHES_KEY));
+        assert_eq!(resource.history.len(), 4);
+    }
+
+    #[test]
+    fn test_leaving_the_split_abandons_branches() {
+        let workflow = contract_workflow();
+        let mut resource = Resource::new("contract", StateId::from("draft"));
+        execute(&mut resource, activity(&workflow, "submit")).unwrap();
+        execute(&mut resource, activity(&workflow, "legal_ok")).unwrap();
+
+        let step = execute(&mut resource, activity(&workflow, "cancel")).unwrap();
+        assert!(matches!(step, ParallelStep::Linear { ref abandoned } if abandoned.len() == 2));
+        assert_eq!(resource.state, StateId::from("cancelled"));
+        assert!(!resource.is_split());
+
+        let mut workflow = contract_workflow();
+        workflow.activities[0] =
+            ActivityDefinition::new("submit", vec!["draft"], "in_review").with_split(vec!["legal"]);
+        assert!(workflow
+            .validate()
+            .unwrap_err()
+            .contains("at least two distinct states"));
+    }
+}
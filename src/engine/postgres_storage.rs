@@ -0,0 +1,718 @@
+// PostgreSQL storage
+// Persists workflows, resources, agents and functions in Postgres tables
+
+//! # PostgreSQL Storage
+//!
+//! [`PostgresStorage`], [`PostgresAgentStorage`] and [`PostgresFunctionStorage`] keep
+//! what the engine stores in Postgres, for deployments that want durable storage without
+//! running NATS JetStream. Select them with `STORAGE_BACKEND=postgres` and a
+//! `DATABASE_URL`; MCP state goes to the same database through
+//! [`PostgresMCPStorage`](crate::api::mcp_storage::PostgresMCPStorage).
+//!
+//! ## Schema
+//!
+//! The tables are created by the sqlx migrations in `migrations/postgres`, embedded in
+//! the binary and run by [`migrate`] whenever a store connects. Each record is kept whole
+//! in a JSONB `record` column; the fields queries filter on are pulled out into indexed
+//! columns beside it:
+//!
+//! - `resources` by `(workflow_id, state)`, for listing and counting per state, and by a
+//!   GIN index on their JSONB `metadata`, for correlation lookups
+//! - `state_agent_configs` by `state_id`
+//! - `agent_executions` by `agent_id`, `resource_id` and `status`
+//! - `function_executions` by `function_id`
+//!
+//! ## Optimistic Concurrency
+//!
+//! Resource updates lock the stored row, check its version with
+//! [`check_version`] and write the bumped one in the same transaction, so concurrent
+//! writers on any number of nodes get a
+//! [`VersionConflict`](CircuitBreakerError::VersionConflict) rather than losing an
+//! update. Batches run in one transaction and store all resources or none.
+
+use std::collections::HashMap;
+
+use sqlx::types::Json;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use super::agents::AgentStorage;
+use super::functions::FunctionStorage;
+use super::state_counters::status_key;
+use super::storage::{check_version, WorkflowStorage};
+use crate::models::correlation::correlation_value;
+use crate::models::{
+    AgentDefinition, AgentExecution, AgentId, ChainExecution, FunctionDefinition,
+    FunctionExecution, FunctionId, Resource, StateAgentConfig, StateId, WorkflowDefinition,
+};
+use crate::{CircuitBreakerError, Result};
+
+/// Migrations creating the tables of every Postgres store
+pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./migrations/postgres");
+
+/// Bring the database behind `pool` up to the latest schema
+pub async fn migrate(pool: &PgPool) -> Result<()> {
+    MIGRATOR
+        .run(pool)
+        .await
+        .map_err(|e| storage_error("migrate Postgres schema", e))
+}
+
+/// Connect to Postgres and migrate its schema
+pub async fn connect(database_url: &str) -> Result<PgPool> {
+    let pool = PgPool::connect(database_url)
+        .await
+        .map_err(|e| storage_error("connect to Postgres", e))?;
+    migrate(&pool).await?;
+    Ok(pool)
+}
+
+fn storage_error(action: &str, error: impl std::fmt::Display) -> CircuitBreakerError {
+    CircuitBreakerError::Storage(anyhow::anyhow!("Failed to {}: {}", action, error))
+}
+
+/// Workflow and resource storage backed by Postgres tables
+#[derive(Clone)]
+pub struct PostgresStorage {
+    pool: PgPool,
+}
+
+impl PostgresStorage {
+    /// Connect to Postgres and create the workflow tables if needed
+    pub async fn connect(database_url: &str) -> Result<Self> {
+        Ok(Self {
+            pool: connect(database_url).await?,
+        })
+    }
+
+    /// Store workflows through an existing, migrated connection pool
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// The connection pool behind the storage, for sharing with the other stores
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    /// Store a resource, checking an update against the stored version and bumping it
+    async fn write_resource(
+        connection: &mut PgConnection,
+        mut resource: Resource,
+        update: bool,
+    ) -> Result<Resource> {
+        if update {
+            let stored: Option<(i64,)> =
+                sqlx::query_as("SELECT version FROM resources WHERE id = $1 FOR UPDATE")
+                    .bind(resource.id)
+                    .fetch_optional(&mut *connection)
+                    .await
+                    .map_err(|e| storage_error("read resource version", e))?;
+            if let Some((stored,)) = stored {
+                check_version(&resource, stored as u64)?;
+                resource.version = stored as u64 + 1;
+            }
+        }
+
+        sqlx::query(
+            "INSERT INTO resources
+                (id, workflow_id, state, version, metadata, record, created_at, updated_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+             ON CONFLICT (id) DO UPDATE SET
+                workflow_id = EXCLUDED.workflow_id,
+                state = EXCLUDED.state,
+                version = EXCLUDED.version,
+                metadata = EXCLUDED.metadata,
+                record = EXCLUDED.record,
+                updated_at = EXCLUDED.updated_at",
+        )
+        .bind(resource.id)
+        .bind(&resource.workflow_id)
+        .bind(resource.state.as_str())
+        .bind(resource.version as i64)
+        .bind(Json(&resource.metadata))
+        .bind(Json(&resource))
+        .bind(resource.created_at)
+        .bind(resource.updated_at)
+        .execute(&mut *connection)
+        .await
+        .map_err(|e| storage_error("store resource", e))?;
+        Ok(resource)
+    }
+
+    /// Store a batch in one transaction, so it is stored whole or not at all
+    async fn write_resources(&self, batch: Vec<Resource>, update: bool) -> Result<Vec<Resource>> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| storage_error("begin resource batch", e))?;
+        let mut stored = Vec::with_capacity(batch.len());
+        for resource in batch {
+            stored.push(Self::write_resource(&mut transaction, resource, update).await?);
+        }
+        transaction
+            .commit()
+            .await
+            .map_err(|e| storage_error("commit resource batch", e))?;
+        Ok(stored)
+    }
+}
+
+#[async_trait::async_trait]
+impl WorkflowStorage for PostgresStorage {
+    /// Store a new current version of a workflow, replacing one stored again under its
+    /// number
+    async fn create_workflow(&self, definition: WorkflowDefinition) -> Result<WorkflowDefinition> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| storage_error("store workflow", e))?;
+        sqlx::query(
+            "INSERT INTO workflow_versions (id, version, definition) VALUES ($1, $2, $3)
+             ON CONFLICT (id, version) DO UPDATE SET
+                definition = EXCLUDED.definition,
+                stored_at = now()",
+        )
+        .bind(&definition.id)
+        .bind(definition.version as i32)
+        .bind(Json(&definition))
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| storage_error("store workflow version", e))?;
+        sqlx::query(
+            "INSERT INTO workflows (id, version, definition) VALUES ($1, $2, $3)
+             ON CONFLICT (id) DO UPDATE SET
+                version = EXCLUDED.version,
+                definition = EXCLUDED.definition",
+        )
+        .bind(&definition.id)
+        .bind(definition.version as i32)
+        .bind(Json(&definition))
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| storage_error("store workflow", e))?;
+        transaction
+            .commit()
+            .await
+            .map_err(|e| storage_error("store workflow", e))?;
+        Ok(definition)
+    }
+
+    async fn get_workflow(&self, id: &str) -> Result<Option<WorkflowDefinition>> {
+        let row: Option<(Json<WorkflowDefinition>,)> =
+            sqlx::query_as("SELECT definition FROM workflows WHERE id = $1")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| storage_error("read workflow", e))?;
+        Ok(row.map(|(Json(definition),)| definition))
+    }
+
+    async fn list_workflows(&self) -> Result<Vec<WorkflowDefinition>> {
+        let rows: Vec<(Json<WorkflowDefinition>,)> =
+            sqlx::query_as("SELECT definition FROM workflows")
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| storage_error("list workflows", e))?;
+        Ok(rows
+            .into_iter()
+            .map(|(Json(definition),)| definition)
+            .collect())
+    }
+
+    async fn list_workflow_versions(&self, id: &str) -> Result<Vec<WorkflowDefinition>> {
+        let rows: Vec<(Json<WorkflowDefinition>,)> = sqlx::query_as(
+            "SELECT definition FROM workflow_versions WHERE id = $1 ORDER BY version",
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| storage_error("list workflow versions", e))?;
+        Ok(rows
+            .into_iter()
+            .map(|(Json(definition),)| definition)
+            .collect())
+    }
+
+    async fn get_workflow_version(
+        &self,
+        id: &str,
+        version: u32,
+    ) -> Result<Option<WorkflowDefinition>> {
+        let row: Option<(Json<WorkflowDefinition>,)> = sqlx::query_as(
+            "SELECT definition FROM workflow_versions WHERE id = $1 AND version = $2",
+        )
+        .bind(id)
+        .bind(version as i32)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| storage_error("read workflow version", e))?;
+        Ok(row.map(|(Json(definition),)| definition))
+    }
+
+    async fn create_resource(&self, resource: Resource) -> Result<Resource> {
+        let mut connection = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| storage_error("store resource", e))?;
+        Self::write_resource(&mut connection, resource, false).await
+    }
+
+    async fn get_resource(&self, id: &Uuid) -> Result<Option<Resource>> {
+        let row: Option<(Json<Resource>,)> =
+            sqlx::query_as("SELECT record FROM resources WHERE id = $1")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| storage_error("read resource", e))?;
+        Ok(row.map(|(Json(resource),)| resource))
+    }
+
+    /// Update a resource (or create it if it doesn't exist) in its own transaction
+    async fn update_resource(&self, resource: Resource) -> Result<Resource> {
+        let mut stored = self.write_resources(vec![resource], true).await?;
+        Ok(stored.remove(0))
+    }
+
+    async fn create_resources(&self, resources: Vec<Resource>) -> Result<Vec<Resource>> {
+        self.write_resources(resources, false).await
+    }
+
+    async fn update_resources(&self, resources: Vec<Resource>) -> Result<Vec<Resource>> {
+        self.write_resources(resources, true).await
+    }
+
+    fn transactional_batches(&self) -> bool {
+        true
+    }
+
+    async fn list_resources(&self, workflow_id: Option<&str>) -> Result<Vec<Resource>> {
+        let rows: Vec<(Json<Resource>,)> = match workflow_id {
+            Some(workflow_id) => {
+                sqlx::query_as("SELECT record FROM resources WHERE workflow_id = $1")
+                    .bind(workflow_id)
+                    .fetch_all(&self.pool)
+                    .await
+            }
+            None => {
+                sqlx::query_as("SELECT record FROM resources")
+                    .fetch_all(&self.pool)
+                    .await
+            }
+        }
+        .map_err(|e| storage_error("list resources", e))?;
+        Ok(rows.into_iter().map(|(Json(resource),)| resource).collect())
+    }
+
+    /// Count on the `(workflow_id, state)` index instead of reading resources
+    async fn count_resources_by_state(&self, workflow_id: &str) -> Result<HashMap<String, u64>> {
+        let rows: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT state, COUNT(*) FROM resources WHERE workflow_id = $1 GROUP BY state",
+        )
+        .bind(workflow_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| storage_error("count resources", e))?;
+        Ok(rows
+            .into_iter()
+            .map(|(state, count)| (state, count as u64))
+            .collect())
+    }
+
+    /// Narrow down to resources with the key on the metadata index, then compare values
+    /// the way the in-memory correlation index does
+    async fn correlated_resources(&self, key: &str, value: &str) -> Result<Vec<Resource>> {
+        let rows: Vec<(Json<Resource>,)> = sqlx::query_as(
+            "SELECT record FROM resources WHERE metadata ? $1 AND metadata ->> $1 = $2",
+        )
+        .bind(key)
+        .bind(value)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| storage_error("list correlated resources", e))?;
+        Ok(rows
+            .into_iter()
+            .map(|(Json(resource),)| resource)
+            .filter(|resource| {
+                correlation_value(&resource.metadata, key).is_some_and(|v| v == value)
+            })
+            .collect())
+    }
+
+    async fn get_workflows(&self, ids: &[String]) -> Result<HashMap<String, WorkflowDefinition>> {
+        let rows: Vec<(String, Json<WorkflowDefinition>)> =
+            sqlx::query_as("SELECT id, definition FROM workflows WHERE id = ANY($1)")
+                .bind(ids)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| storage_error("read workflows", e))?;
+        Ok(rows
+            .into_iter()
+            .map(|(id, Json(definition))| (id, definition))
+            .collect())
+    }
+}
+
+/// Agent, state agent configuration and execution storage backed by Postgres tables
+#[derive(Clone)]
+pub struct PostgresAgentStorage {
+    pool: PgPool,
+}
+
+impl PostgresAgentStorage {
+    /// Connect to Postgres and create the agent tables if needed
+    pub async fn connect(database_url: &str) -> Result<Self> {
+        Ok(Self {
+            pool: connect(database_url).await?,
+        })
+    }
+
+    /// Store agents through an existing, migrated connection pool
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl AgentStorage for PostgresAgentStorage {
+    async fn store_agent(&self, agent: &AgentDefinition) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO agents (id, record) VALUES ($1, $2)
+             ON CONFLICT (id) DO UPDATE SET record = EXCLUDED.record",
+        )
+        .bind(agent.id.as_str())
+        .bind(Json(agent))
+        .execute(&self.pool)
+        .await
+        .map_err(|e| storage_error("store agent", e))?;
+        Ok(())
+    }
+
+    async fn get_agent(&self, id: &AgentId) -> Result<Option<AgentDefinition>> {
+        let row: Option<(Json<AgentDefinition>,)> =
+            sqlx::query_as("SELECT record FROM agents WHERE id = $1")
+                .bind(id.as_str())
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| storage_error("read agent", e))?;
+        Ok(row.map(|(Json(agent),)| agent))
+    }
+
+    async fn list_agents(&self) -> Result<Vec<AgentDefinition>> {
+        let rows: Vec<(Json<AgentDefinition>,)> = sqlx::query_as("SELECT record FROM agents")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| storage_error("list agents", e))?;
+        Ok(rows.into_iter().map(|(Json(agent),)| agent).collect())
+    }
+
+    async fn delete_agent(&self, id: &AgentId) -> Result<bool> {
+        let deleted = sqlx::query("DELETE FROM agents WHERE id = $1")
+            .bind(id.as_str())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| storage_error("delete agent", e))?;
+        Ok(deleted.rows_affected() > 0)
+    }
+
+    async fn store_state_agent_config(&self, config: &StateAgentConfig) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO state_agent_configs (id, state_id, agent_id, record)
+             VALUES ($1, $2, $3, $4)
+             ON CONFLICT (id) DO UPDATE SET
+                state_id = EXCLUDED.state_id,
+                agent_id = EXCLUDED.agent_id,
+                record = EXCLUDED.record",
+        )
+        .bind(config.id)
+        .bind(config.state_id.as_str())
+        .bind(config.agent_id.as_str())
+        .bind(Json(config))
+        .execute(&self.pool)
+        .await
+        .map_err(|e| storage_error("store state agent config", e))?;
+        Ok(())
+    }
+
+    async fn get_state_agent_configs(&self, state_id: &StateId) -> Result<Vec<StateAgentConfig>> {
+        let rows: Vec<(Json<StateAgentConfig>,)> =
+            sqlx::query_as("SELECT record FROM state_agent_configs WHERE state_id = $1")
+                .bind(state_id.as_str())
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| storage_error("read state agent configs", e))?;
+        Ok(rows.into_iter().map(|(Json(config),)| config).collect())
+    }
+
+    async fn list_state_agent_configs(&self) -> Result<Vec<StateAgentConfig>> {
+        let rows: Vec<(Json<StateAgentConfig>,)> =
+            sqlx::query_as("SELECT record FROM state_agent_configs")
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| storage_error("list state agent configs", e))?;
+        Ok(rows.into_iter().map(|(Json(config),)| config).collect())
+    }
+
+    async fn delete_state_agent_config(&self, id: &Uuid) -> Result<bool> {
+        let deleted = sqlx::query("DELETE FROM state_agent_configs WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| storage_error("delete state agent config", e))?;
+        Ok(deleted.rows_affected() > 0)
+    }
+
+    async fn store_execution(&self, execution: &AgentExecution) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO agent_executions (id, agent_id, resource_id, status, record)
+             VALUES ($1, $2, $3, $4, $5)
+             ON CONFLICT (id) DO UPDATE SET
+                agent_id = EXCLUDED.agent_id,
+                resource_id = EXCLUDED.resource_id,
+                status = EXCLUDED.status,
+                record = EXCLUDED.record",
+        )
+        .bind(execution.id)
+        .bind(execution.agent_id.as_str())
+        .bind(execution.resource_id)
+        .bind(status_key(&execution.status))
+        .bind(Json(execution))
+        .execute(&self.pool)
+        .await
+        .map_err(|e| storage_error("store agent execution", e))?;
+        Ok(())
+    }
+
+    async fn get_execution(&self, id: &Uuid) -> Result<Option<AgentExecution>> {
+        let row: Option<(Json<AgentExecution>,)> =
+            sqlx::query_as("SELECT record FROM agent_executions WHERE id = $1")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| storage_error("read agent execution", e))?;
+        Ok(row.map(|(Json(execution),)| execution))
+    }
+
+    async fn list_executions_for_resource(
+        &self,
+        resource_id: &Uuid,
+    ) -> Result<Vec<AgentExecution>> {
+        let rows: Vec<(Json<AgentExecution>,)> =
+            sqlx::query_as("SELECT record FROM agent_executions WHERE resource_id = $1")
+                .bind(resource_id)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| storage_error("list agent executions", e))?;
+        Ok(rows
+            .into_iter()
+            .map(|(Json(execution),)| execution)
+            .collect())
+    }
+
+    async fn list_executions_for_agent(&self, agent_id: &AgentId) -> Result<Vec<AgentExecution>> {
+        let rows: Vec<(Json<AgentExecution>,)> =
+            sqlx::query_as("SELECT record FROM agent_executions WHERE agent_id = $1")
+                .bind(agent_id.as_str())
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| storage_error("list agent executions", e))?;
+        Ok(rows
+            .into_iter()
+            .map(|(Json(execution),)| execution)
+            .collect())
+    }
+
+    /// Read the executions of every resource in one query
+    async fn list_executions_for_resources(
+        &self,
+        resource_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Vec<AgentExecution>>> {
+        let rows: Vec<(Json<AgentExecution>,)> =
+            sqlx::query_as("SELECT record FROM agent_executions WHERE resource_id = ANY($1)")
+                .bind(resource_ids)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| storage_error("list agent executions", e))?;
+        let mut by_resource: HashMap<Uuid, Vec<AgentExecution>> = resource_ids
+            .iter()
+            .map(|resource_id| (*resource_id, Vec::new()))
+            .collect();
+        for (Json(execution),) in rows {
+            if let Some(list) = by_resource.get_mut(&execution.resource_id) {
+                list.push(execution);
+            }
+        }
+        Ok(by_resource)
+    }
+
+    /// Count on the status index instead of reading executions
+    async fn count_executions_by_status(&self) -> Result<HashMap<String, u64>> {
+        let rows: Vec<(String, i64)> =
+            sqlx::query_as("SELECT status, COUNT(*) FROM agent_executions GROUP BY status")
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| storage_error("count agent executions", e))?;
+        Ok(rows
+            .into_iter()
+            .map(|(status, count)| (status, count as u64))
+            .collect())
+    }
+}
+
+/// Function, execution and chain storage backed by Postgres tables
+#[derive(Clone)]
+pub struct PostgresFunctionStorage {
+    pool: PgPool,
+}
+
+impl PostgresFunctionStorage {
+    /// Connect to Postgres and create the function tables if needed
+    pub async fn connect(database_url: &str) -> Result<Self> {
+        Ok(Self {
+            pool: connect(database_url).await?,
+        })
+    }
+
+    /// Store functions through an existing, migrated connection pool
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    async fn put_function(&self, function: &FunctionDefinition) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO functions (id, record) VALUES ($1, $2)
+             ON CONFLICT (id) DO UPDATE SET record = EXCLUDED.record",
+        )
+        .bind(function.id.as_str())
+        .bind(Json(function))
+        .execute(&self.pool)
+        .await
+        .map_err(|e| storage_error("store function", e))?;
+        Ok(())
+    }
+
+    async fn put_execution(&self, execution: &FunctionExecution) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO function_executions (id, function_id, record) VALUES ($1, $2, $3)
+             ON CONFLICT (id) DO UPDATE SET
+                function_id = EXCLUDED.function_id,
+                record = EXCLUDED.record",
+        )
+        .bind(execution.id)
+        .bind(execution.function_id.as_str())
+        .bind(Json(execution))
+        .execute(&self.pool)
+        .await
+        .map_err(|e| storage_error("store function execution", e))?;
+        Ok(())
+    }
+
+    async fn put_chain(&self, chain: &ChainExecution) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO function_chains (id, record) VALUES ($1, $2)
+             ON CONFLICT (id) DO UPDATE SET record = EXCLUDED.record",
+        )
+        .bind(chain.id)
+        .bind(Json(chain))
+        .execute(&self.pool)
+        .await
+        .map_err(|e| storage_error("store function chain", e))?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl FunctionStorage for PostgresFunctionStorage {
+    async fn create_function(&self, function: FunctionDefinition) -> Result<FunctionDefinition> {
+        self.put_function(&function).await?;
+        Ok(function)
+    }
+
+    async fn get_function(&self, id: &FunctionId) -> Result<Option<FunctionDefinition>> {
+        let row: Option<(Json<FunctionDefinition>,)> =
+            sqlx::query_as("SELECT record FROM functions WHERE id = $1")
+                .bind(id.as_str())
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| storage_error("read function", e))?;
+        Ok(row.map(|(Json(function),)| function))
+    }
+
+    async fn update_function(&self, function: FunctionDefinition) -> Result<FunctionDefinition> {
+        self.put_function(&function).await?;
+        Ok(function)
+    }
+
+    async fn delete_function(&self, id: &FunctionId) -> Result<bool> {
+        let deleted = sqlx::query("DELETE FROM functions WHERE id = $1")
+            .bind(id.as_str())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| storage_error("delete function", e))?;
+        Ok(deleted.rows_affected() > 0)
+    }
+
+    async fn list_functions(&self) -> Result<Vec<FunctionDefinition>> {
+        let rows: Vec<(Json<FunctionDefinition>,)> = sqlx::query_as("SELECT record FROM functions")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| storage_error("list functions", e))?;
+        Ok(rows.into_iter().map(|(Json(function),)| function).collect())
+    }
+
+    async fn create_execution(&self, execution: FunctionExecution) -> Result<FunctionExecution> {
+        self.put_execution(&execution).await?;
+        Ok(execution)
+    }
+
+    async fn update_execution(&self, execution: FunctionExecution) -> Result<FunctionExecution> {
+        self.put_execution(&execution).await?;
+        Ok(execution)
+    }
+
+    async fn get_execution(&self, id: &Uuid) -> Result<Option<FunctionExecution>> {
+        let row: Option<(Json<FunctionExecution>,)> =
+            sqlx::query_as("SELECT record FROM function_executions WHERE id = $1")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| storage_error("read function execution", e))?;
+        Ok(row.map(|(Json(execution),)| execution))
+    }
+
+    async fn list_executions(&self, function_id: &FunctionId) -> Result<Vec<FunctionExecution>> {
+        let rows: Vec<(Json<FunctionExecution>,)> =
+            sqlx::query_as("SELECT record FROM function_executions WHERE function_id = $1")
+                .bind(function_id.as_str())
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| storage_error("list function executions", e))?;
+        Ok(rows
+            .into_iter()
+            .map(|(Json(execution),)| execution)
+            .collect())
+    }
+
+    async fn create_chain(&self, chain: ChainExecution) -> Result<ChainExecution> {
+        self.put_chain(&chain).await?;
+        Ok(chain)
+    }
+
+    async fn update_chain(&self, chain: ChainExecution) -> Result<ChainExecution> {
+        self.put_chain(&chain).await?;
+        Ok(chain)
+    }
+
+    async fn get_chain(&self, id: &Uuid) -> Result<Option<ChainExecution>> {
+        let row: Option<(Json<ChainExecution>,)> =
+            sqlx::query_as("SELECT record FROM function_chains WHERE id = $1")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| storage_error("read function chain", e))?;
+        Ok(row.map(|(Json(chain),)| chain))
+    }
+}
@@ -0,0 +1,784 @@
+// Redis storage
+// Persists workflows, resources and agents in Redis or Valkey hashes and streams
+
+//! # Redis Storage
+//!
+//! [`RedisStorage`] and [`RedisAgentStorage`] keep what the engine stores in Redis or
+//! Valkey: a lightweight persistent store for deployments that outgrow in-memory storage
+//! but don't need NATS JetStream. Select them with `STORAGE_BACKEND=redis` and a
+//! `REDIS_URL`.
+//!
+//! ## Layout
+//!
+//! Every key starts with the configured prefix (`circuit_breaker` by default):
+//!
+//! - `<prefix>:workflows` hashes each workflow ID to its current definition, and
+//!   `<prefix>:workflow:<id>:versions` each version number to that version
+//! - `<prefix>:resource:<id>` is a hash holding the resource's JSON `record`, with its
+//!   `version`, `workflow_id` and `state` beside it; `<prefix>:resources` and
+//!   `<prefix>:workflow:<id>:resources` are sets of resource IDs
+//! - `<prefix>:workflow:<id>:changes` is a stream with an entry for every resource
+//!   write, capped at [`RedisStorageConfig::stream_max_len`]; read it with
+//!   [`RedisStorage::resource_changes`] or tail it with `XREAD`
+//! - agents and state agent configurations are hashed under `<prefix>:agents` and
+//!   `<prefix>:state_agent_configs`; each execution is a hash under
+//!   `<prefix>:agent_execution:<id>`, listed in sets per agent and per resource
+//!
+//! Redis Cluster needs every key in one slot: use a hash tag such as `{cb}` as prefix.
+//!
+//! ## Expiry
+//!
+//! With [`RedisStorageConfig::resource_ttl`] or
+//! [`RedisStorageConfig::execution_ttl`] set, resources or agent executions expire that
+//! long after they were last written, keeping the store small for short-lived workflows.
+//! Workflows and agents never expire. Sets still naming an expired record are cleaned up
+//! when they are next read.
+//!
+//! ## Optimistic Concurrency
+//!
+//! Resources are written by a Lua script that checks the stored versions with the same
+//! rules as [`check_version`](super::storage::check_version) and writes the batch only
+//! if all of them match, so batches are stored whole or not at all and concurrent
+//! writers get a [`VersionConflict`](CircuitBreakerError::VersionConflict).
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use redis::aio::ConnectionManager;
+use redis::streams::StreamRangeReply;
+use redis::AsyncCommands;
+use uuid::Uuid;
+
+use super::agents::AgentStorage;
+use super::state_counters::status_key;
+use super::storage::WorkflowStorage;
+use crate::models::{
+    AgentDefinition, AgentExecution, AgentId, Resource, StateAgentConfig, StateId,
+    WorkflowDefinition,
+};
+use crate::{CircuitBreakerError, Result};
+
+/// Prefix of every key the storage writes
+pub const KEY_PREFIX_ENV: &str = "CIRCUIT_BREAKER_REDIS_PREFIX";
+
+/// Seconds after their last write resources expire; unset or 0 keeps them
+pub const RESOURCE_TTL_ENV: &str = "CIRCUIT_BREAKER_REDIS_RESOURCE_TTL_SECS";
+
+/// Seconds after their last write agent executions expire; unset or 0 keeps them
+pub const EXECUTION_TTL_ENV: &str = "CIRCUIT_BREAKER_REDIS_EXECUTION_TTL_SECS";
+
+/// Checks the versions of a batch of resources and writes all of them, or none
+///
+/// KEYS[1] is the set of every resource ID, followed by the hash, workflow resource set
+/// and change stream of each resource. ARGV[1] is 1 when versions are checked, ARGV[2]
+/// the TTL in milliseconds or 0, ARGV[3] the stream length cap, followed by the ID,
+/// version, record to store over an existing resource, record to store when there is
+/// none, workflow ID and state of each resource.
+///
+/// Returns 0 followed by 1 for each resource whose version was bumped, or the 1-based
+/// position of a stale resource followed by its stored version.
+const WRITE_RESOURCES_SCRIPT: &str = r"
+local count = (#KEYS - 1) / 3
+local check = ARGV[1] == '1'
+local ttl = tonumber(ARGV[2])
+local stored = {}
+for i = 1, count do
+    local version = redis.call('HGET', KEYS[3 * i - 1], 'version')
+    if check and version and version ~= ARGV[4 + 6 * (i - 1) + 1] then
+        return {i, tonumber(version)}
+    end
+    stored[i] = version
+end
+local bumped = {0}
+for i = 1, count do
+    local arg = 4 + 6 * (i - 1)
+    local key = KEYS[3 * i - 1]
+    local version = tonumber(ARGV[arg + 1])
+    local record = ARGV[arg + 3]
+    bumped[i + 1] = 0
+    if check and stored[i] then
+        version = version + 1
+        record = ARGV[arg + 2]
+        bumped[i + 1] = 1
+    end
+    redis.call('HSET', key, 'record', record, 'version', version,
+        'workflow_id', ARGV[arg + 4], 'state', ARGV[arg + 5])
+    if ttl > 0 then
+        redis.call('PEXPIRE', key, ttl)
+    else
+        redis.call('PERSIST', key)
+    end
+    redis.call('SADD', KEYS[1], ARGV[arg])
+    redis.call('SADD', KEYS[3 * i], ARGV[arg])
+    redis.call('XADD', KEYS[3 * i + 1], 'MAXLEN', '~', ARGV[3], '*',
+        'resource_id', ARGV[arg], 'state', ARGV[arg + 5], 'version', version)
+end
+return bumped
+";
+
+/// Where Redis storage keeps its data and how long resources and executions live
+#[derive(Debug, Clone, PartialEq)]
+pub struct RedisStorageConfig {
+    /// Redis or Valkey connection URL
+    pub redis_url: String,
+    /// Prefix of every key the storage writes
+    pub key_prefix: String,
+    /// How long resources live after their last write; `None` keeps them
+    pub resource_ttl: Option<Duration>,
+    /// How long agent executions live after their last write; `None` keeps them
+    pub execution_ttl: Option<Duration>,
+    /// Approximate number of entries kept in each workflow's change stream
+    pub stream_max_len: usize,
+}
+
+impl Default for RedisStorageConfig {
+    fn default() -> Self {
+        Self {
+            redis_url: "redis://localhost:6379".to_string(),
+            key_prefix: "circuit_breaker".to_string(),
+            resource_ttl: None,
+            execution_ttl: None,
+            stream_max_len: 10_000,
+        }
+    }
+}
+
+impl RedisStorageConfig {
+    /// Storage at `redis_url` configured from [`KEY_PREFIX_ENV`], [`RESOURCE_TTL_ENV`]
+    /// and [`EXECUTION_TTL_ENV`]
+    pub fn from_env(redis_url: &str) -> Self {
+        let defaults = Self::default();
+        Self {
+            redis_url: redis_url.to_string(),
+            key_prefix: std::env::var(KEY_PREFIX_ENV)
+                .ok()
+                .filter(|prefix| !prefix.trim().is_empty())
+                .unwrap_or(defaults.key_prefix),
+            resource_ttl: parse_ttl(std::env::var(RESOURCE_TTL_ENV).ok().as_deref()),
+            execution_ttl: parse_ttl(std::env::var(EXECUTION_TTL_ENV).ok().as_deref()),
+            stream_max_len: defaults.stream_max_len,
+        }
+    }
+
+    fn key(&self, parts: &[&str]) -> String {
+        let mut key = self.key_prefix.clone();
+        for part in parts {
+            key.push(':');
+            key.push_str(part);
+        }
+        key
+    }
+}
+
+/// A TTL in seconds, `None` when unset, zero or unparsable
+fn parse_ttl(seconds: Option<&str>) -> Option<Duration> {
+    seconds
+        .and_then(|seconds| seconds.trim().parse::<u64>().ok())
+        .filter(|seconds| *seconds > 0)
+        .map(Duration::from_secs)
+}
+
+/// Milliseconds of an optional TTL, 0 for none
+fn ttl_millis(ttl: Option<Duration>) -> u64 {
+    ttl.map_or(0, |ttl| ttl.as_millis().max(1) as u64)
+}
+
+fn storage_error(action: &str, error: impl std::fmt::Display) -> CircuitBreakerError {
+    CircuitBreakerError::Storage(anyhow::anyhow!("Failed to {}: {}", action, error))
+}
+
+fn decode<T: serde::de::DeserializeOwned>(record: &str) -> Result<T> {
+    Ok(serde_json::from_str(record)?)
+}
+
+async fn connect(redis_url: &str) -> Result<ConnectionManager> {
+    let client =
+        redis::Client::open(redis_url).map_err(|e| storage_error("open Redis client", e))?;
+    ConnectionManager::new(client)
+        .await
+        .map_err(|e| storage_error("connect to Redis", e))
+}
+
+/// Read the `record` field of each hash in `keys`, dropping the IDs of missing ones from
+/// the sets in `sets`
+async fn read_records<T: serde::de::DeserializeOwned>(
+    connection: &mut ConnectionManager,
+    ids: Vec<String>,
+    key: impl Fn(&str) -> String,
+    sets: &[String],
+) -> Result<Vec<T>> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut pipeline = redis::pipe();
+    for id in &ids {
+        pipeline.hget(key(id), "record");
+    }
+    let records: Vec<Option<String>> = pipeline
+        .query_async(connection)
+        .await
+        .map_err(|e| storage_error("read records", e))?;
+
+    let mut found = Vec::with_capacity(ids.len());
+    let mut expired = Vec::new();
+    for (id, record) in ids.into_iter().zip(records) {
+        match record {
+            Some(record) => found.push(decode(&record)?),
+            None => expired.push(id),
+        }
+    }
+    if !expired.is_empty() {
+        let mut pipeline = redis::pipe();
+        for set in sets {
+            pipeline.srem(set, &expired).ignore();
+        }
+        pipeline
+            .query_async::<_, ()>(connection)
+            .await
+            .map_err(|e| storage_error("drop expired records", e))?;
+    }
+    Ok(found)
+}
+
+/// A resource write recorded in a workflow's change stream
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceChange {
+    /// Stream entry ID, to resume reading after
+    pub id: String,
+    pub resource_id: Uuid,
+    pub state: String,
+    pub version: u64,
+}
+
+/// Workflow and resource storage backed by Redis or Valkey
+#[derive(Clone)]
+pub struct RedisStorage {
+    connection: ConnectionManager,
+    config: RedisStorageConfig,
+    write_script: redis::Script,
+}
+
+impl RedisStorage {
+    /// Connect to the Redis server in `config`
+    pub async fn connect(config: RedisStorageConfig) -> Result<Self> {
+        Ok(Self {
+            connection: connect(&config.redis_url).await?,
+            config,
+            write_script: redis::Script::new(WRITE_RESOURCES_SCRIPT),
+        })
+    }
+
+    /// Agent storage sharing this storage's connection and configuration
+    pub fn agent_storage(&self) -> RedisAgentStorage {
+        RedisAgentStorage {
+            connection: self.connection.clone(),
+            config: self.config.clone(),
+        }
+    }
+
+    /// The latest `count` resource writes of a workflow, newest first
+    pub async fn resource_changes(
+        &self,
+        workflow_id: &str,
+        count: usize,
+    ) -> Result<Vec<ResourceChange>> {
+        let mut connection = self.connection.clone();
+        let reply: StreamRangeReply = connection
+            .xrevrange_count(
+                self.config.key(&["workflow", workflow_id, "changes"]),
+                "+",
+                "-",
+                count,
+            )
+            .await
+            .map_err(|e| storage_error("read resource changes", e))?;
+        Ok(reply
+            .ids
+            .into_iter()
+            .filter_map(|entry| {
+                Some(ResourceChange {
+                    resource_id: entry
+                        .get::<String>("resource_id")
+                        .and_then(|id| Uuid::parse_str(&id).ok())?,
+                    state: entry.get("state")?,
+                    version: entry.get("version")?,
+                    id: entry.id,
+                })
+            })
+            .collect())
+    }
+
+    fn resource_key(&self, id: &str) -> String {
+        self.config.key(&["resource", id])
+    }
+
+    /// Write a batch through the write script, checking versions when `update` is set
+    async fn write_resources(
+        &self,
+        mut batch: Vec<Resource>,
+        update: bool,
+    ) -> Result<Vec<Resource>> {
+        if batch.is_empty() {
+            return Ok(batch);
+        }
+        let mut invocation = self.write_script.prepare_invoke();
+        invocation
+            .key(self.config.key(&["resources"]))
+            .arg(if update { "1" } else { "0" })
+            .arg(ttl_millis(self.config.resource_ttl))
+            .arg(self.config.stream_max_len);
+        for resource in &batch {
+            // The stored record carries the version it is stored under, which depends on
+            // whether the resource is already stored
+            let bumped = if update {
+                let mut bumped = resource.clone();
+                bumped.version += 1;
+                serde_json::to_string(&bumped)?
+            } else {
+                String::new()
+            };
+            invocation
+                .key(self.resource_key(&resource.id.to_string()))
+                .key(
+                    self.config
+                        .key(&["workflow", &resource.workflow_id, "resources"]),
+                )
+                .key(
+                    self.config
+                        .key(&["workflow", &resource.workflow_id, "changes"]),
+                )
+                .arg(resource.id.to_string())
+                .arg(resource.version)
+                .arg(bumped)
+                .arg(serde_json::to_string(resource)?)
+                .arg(&resource.workflow_id)
+                .arg(resource.state.as_str());
+        }
+
+        let mut connection = self.connection.clone();
+        let reply: Vec<u64> = invocation
+            .invoke_async(&mut connection)
+            .await
+            .map_err(|e| storage_error("store resources", e))?;
+        match reply.as_slice() {
+            [0, bumped @ ..] => {
+                for (resource, bumped) in batch.iter_mut().zip(bumped) {
+                    if *bumped == 1 {
+                        resource.version += 1;
+                    }
+                }
+                Ok(batch)
+            }
+            [position, actual] => {
+                let stale = &batch[*position as usize - 1];
+                Err(CircuitBreakerError::VersionConflict {
+                    id: stale.id.to_string(),
+                    expected: stale.version,
+                    actual: *actual,
+                })
+            }
+            _ => Err(storage_error(
+                "store resources",
+                format!("unexpected reply {:?}", reply),
+            )),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl WorkflowStorage for RedisStorage {
+    /// Store a new current version of a workflow, replacing one stored again under its
+    /// number
+    async fn create_workflow(&self, definition: WorkflowDefinition) -> Result<WorkflowDefinition> {
+        let record = serde_json::to_string(&definition)?;
+        let mut connection = self.connection.clone();
+        redis::pipe()
+            .atomic()
+            .hset(self.config.key(&["workflows"]), &definition.id, &record)
+            .ignore()
+            .hset(
+                self.config.key(&["workflow", &definition.id, "versions"]),
+                definition.version,
+                &record,
+            )
+            .ignore()
+            .query_async::<_, ()>(&mut connection)
+            .await
+            .map_err(|e| storage_error("store workflow", e))?;
+        Ok(definition)
+    }
+
+    async fn get_workflow(&self, id: &str) -> Result<Option<WorkflowDefinition>> {
+        let mut connection = self.connection.clone();
+        let record: Option<String> = connection
+            .hget(self.config.key(&["workflows"]), id)
+            .await
+            .map_err(|e| storage_error("read workflow", e))?;
+        record.as_deref().map(decode).transpose()
+    }
+
+    async fn list_workflows(&self) -> Result<Vec<WorkflowDefinition>> {
+        let mut connection = self.connection.clone();
+        let records: Vec<String> = connection
+            .hvals(self.config.key(&["workflows"]))
+            .await
+            .map_err(|e| storage_error("list workflows", e))?;
+        records.iter().map(|record| decode(record)).collect()
+    }
+
+    async fn list_workflow_versions(&self, id: &str) -> Result<Vec<WorkflowDefinition>> {
+        let mut connection = self.connection.clone();
+        let records: Vec<String> = connection
+            .hvals(self.config.key(&["workflow", id, "versions"]))
+            .await
+            .map_err(|e| storage_error("list workflow versions", e))?;
+        let mut versions = records
+            .iter()
+            .map(|record| decode(record))
+            .collect::<Result<Vec<WorkflowDefinition>>>()?;
+        versions.sort_by_key(|workflow| workflow.version);
+        Ok(versions)
+    }
+
+    async fn get_workflow_version(
+        &self,
+        id: &str,
+        version: u32,
+    ) -> Result<Option<WorkflowDefinition>> {
+        let mut connection = self.connection.clone();
+        let record: Option<String> = connection
+            .hget(self.config.key(&["workflow", id, "versions"]), version)
+            .await
+            .map_err(|e| storage_error("read workflow version", e))?;
+        record.as_deref().map(decode).transpose()
+    }
+
+    async fn create_resource(&self, resource: Resource) -> Result<Resource> {
+        let mut stored = self.write_resources(vec![resource], false).await?;
+        Ok(stored.remove(0))
+    }
+
+    async fn get_resource(&self, id: &Uuid) -> Result<Option<Resource>> {
+        let mut connection = self.connection.clone();
+        let record: Option<String> = connection
+            .hget(self.resource_key(&id.to_string()), "record")
+            .await
+            .map_err(|e| storage_error("read resource", e))?;
+        record.as_deref().map(decode).transpose()
+    }
+
+    /// Update a resource (or create it if it doesn't exist) if its version still matches
+    async fn update_resource(&self, resource: Resource) -> Result<Resource> {
+        let mut stored = self.write_resources(vec![resource], true).await?;
+        Ok(stored.remove(0))
+    }
+
+    async fn create_resources(&self, resources: Vec<Resource>) -> Result<Vec<Resource>> {
+        self.write_resources(resources, false).await
+    }
+
+    async fn update_resources(&self, resources: Vec<Resource>) -> Result<Vec<Resource>> {
+        self.write_resources(resources, true).await
+    }
+
+    fn transactional_batches(&self) -> bool {
+        true
+    }
+
+    /// List the resources in the workflow's set, or in the set of every resource
+    async fn list_resources(&self, workflow_id: Option<&str>) -> Result<Vec<Resource>> {
+        let all = self.config.key(&["resources"]);
+        let mut sets = vec![all.clone()];
+        let set = match workflow_id {
+            Some(workflow_id) => {
+                let set = self.config.key(&["workflow", workflow_id, "resources"]);
+                sets.push(set.clone());
+                set
+            }
+            None => all,
+        };
+
+        let mut connection = self.connection.clone();
+        let ids: Vec<String> = connection
+            .smembers(&set)
+            .await
+            .map_err(|e| storage_error("list resources", e))?;
+        read_records(&mut connection, ids, |id| self.resource_key(id), &sets).await
+    }
+
+    /// Count from the `state` field of each hash without decoding resources
+    async fn count_resources_by_state(&self, workflow_id: &str) -> Result<HashMap<String, u64>> {
+        let mut connection = self.connection.clone();
+        let ids: Vec<String> = connection
+            .smembers(self.config.key(&["workflow", workflow_id, "resources"]))
+            .await
+            .map_err(|e| storage_error("count resources", e))?;
+        let mut pipeline = redis::pipe();
+        for id in &ids {
+            pipeline.hget(self.resource_key(id), "state");
+        }
+        let states: Vec<Option<String>> = pipeline
+            .query_async(&mut connection)
+            .await
+            .map_err(|e| storage_error("count resources", e))?;
+
+        let mut counts = HashMap::new();
+        for state in states.into_iter().flatten() {
+            *counts.entry(state).or_default() += 1;
+        }
+        Ok(counts)
+    }
+
+    async fn get_workflows(&self, ids: &[String]) -> Result<HashMap<String, WorkflowDefinition>> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let mut connection = self.connection.clone();
+        let records: Vec<Option<String>> = redis::cmd("HMGET")
+            .arg(self.config.key(&["workflows"]))
+            .arg(ids)
+            .query_async(&mut connection)
+            .await
+            .map_err(|e| storage_error("read workflows", e))?;
+        let mut workflows = HashMap::new();
+        for (id, record) in ids.iter().zip(records) {
+            if let Some(record) = record {
+                workflows.insert(id.clone(), decode(&record)?);
+            }
+        }
+        Ok(workflows)
+    }
+}
+
+/// Agent, state agent configuration and execution storage backed by Redis or Valkey
+#[derive(Clone)]
+pub struct RedisAgentStorage {
+    connection: ConnectionManager,
+    config: RedisStorageConfig,
+}
+
+impl RedisAgentStorage {
+    /// Connect to the Redis server in `config`
+    pub async fn connect(config: RedisStorageConfig) -> Result<Self> {
+        Ok(Self {
+            connection: connect(&config.redis_url).await?,
+            config,
+        })
+    }
+
+    fn execution_key(&self, id: &str) -> String {
+        self.config.key(&["agent_execution", id])
+    }
+
+    /// Read the executions listed in `set`, dropping expired ones from it and from the
+    /// set of every execution
+    async fn read_executions(&self, set: String) -> Result<Vec<AgentExecution>> {
+        let mut connection = self.connection.clone();
+        let ids: Vec<String> = connection
+            .smembers(&set)
+            .await
+            .map_err(|e| storage_error("list agent executions", e))?;
+        let sets = [self.config.key(&["agent_executions"]), set];
+        read_records(&mut connection, ids, |id| self.execution_key(id), &sets).await
+    }
+}
+
+#[async_trait::async_trait]
+impl AgentStorage for RedisAgentStorage {
+    async fn store_agent(&self, agent: &AgentDefinition) -> Result<()> {
+        let mut connection = self.connection.clone();
+        connection
+            .hset::<_, _, _, ()>(
+                self.config.key(&["agents"]),
+                agent.id.as_str(),
+                serde_json::to_string(agent)?,
+            )
+            .await
+            .map_err(|e| storage_error("store agent", e))
+    }
+
+    async fn get_agent(&self, id: &AgentId) -> Result<Option<AgentDefinition>> {
+        let mut connection = self.connection.clone();
+        let record: Option<String> = connection
+            .hget(self.config.key(&["agents"]), id.as_str())
+            .await
+            .map_err(|e| storage_error("read agent", e))?;
+        record.as_deref().map(decode).transpose()
+    }
+
+    async fn list_agents(&self) -> Result<Vec<AgentDefinition>> {
+        let mut connection = self.connection.clone();
+        let records: Vec<String> = connection
+            .hvals(self.config.key(&["agents"]))
+            .await
+            .map_err(|e| storage_error("list agents", e))?;
+        records.iter().map(|record| decode(record)).collect()
+    }
+
+    async fn delete_agent(&self, id: &AgentId) -> Result<bool> {
+        let mut connection = self.connection.clone();
+        let deleted: u64 = connection
+            .hdel(self.config.key(&["agents"]), id.as_str())
+            .await
+            .map_err(|e| storage_error("delete agent", e))?;
+        Ok(deleted > 0)
+    }
+
+    async fn store_state_agent_config(&self, config: &StateAgentConfig) -> Result<()> {
+        let mut connection = self.connection.clone();
+        connection
+            .hset::<_, _, _, ()>(
+                self.config.key(&["state_agent_configs"]),
+                config.id.to_string(),
+                serde_json::to_string(config)?,
+            )
+            .await
+            .map_err(|e| storage_error("store state agent config", e))
+    }
+
+    async fn get_state_agent_configs(&self, state_id: &StateId) -> Result<Vec<StateAgentConfig>> {
+        Ok(self
+            .list_state_agent_configs()
+            .await?
+            .into_iter()
+            .filter(|config| &config.state_id == state_id)
+            .collect())
+    }
+
+    async fn list_state_agent_configs(&self) -> Result<Vec<StateAgentConfig>> {
+        let mut connection = self.connection.clone();
+        let records: Vec<String> = connection
+            .hvals(self.config.key(&["state_agent_configs"]))
+            .await
+            .map_err(|e| storage_error("list state agent configs", e))?;
+        records.iter().map(|record| decode(record)).collect()
+    }
+
+    async fn delete_state_agent_config(&self, id: &Uuid) -> Result<bool> {
+        let mut connection = self.connection.clone();
+        let deleted: u64 = connection
+            .hdel(self.config.key(&["state_agent_configs"]), id.to_string())
+            .await
+            .map_err(|e| storage_error("delete state agent config", e))?;
+        Ok(deleted > 0)
+    }
+
+    async fn store_execution(&self, execution: &AgentExecution) -> Result<()> {
+        let id = execution.id.to_string();
+        let key = self.execution_key(&id);
+        let mut pipeline = redis::pipe();
+        pipeline
+            .atomic()
+            .hset_multiple(
+                &key,
+                &[
+                    ("record", serde_json::to_string(execution)?),
+                    ("status", status_key(&execution.status).to_string()),
+                ],
+            )
+            .ignore();
+        match self.config.execution_ttl {
+            Some(ttl) => pipeline
+                .pexpire(&key, ttl_millis(Some(ttl)) as i64)
+                .ignore(),
+            None => pipeline.persist(&key).ignore(),
+        };
+        pipeline
+            .sadd(self.config.key(&["agent_executions"]), &id)
+            .ignore()
+            .sadd(
+                self.config
+                    .key(&["agent", execution.agent_id.as_str(), "executions"]),
+                &id,
+            )
+            .ignore()
+            .sadd(
+                self.config.key(&[
+                    "resource",
+                    &execution.resource_id.to_string(),
+                    "agent_executions",
+                ]),
+                &id,
+            )
+            .ignore();
+
+        let mut connection = self.connection.clone();
+        pipeline
+            .query_async::<_, ()>(&mut connection)
+            .await
+            .map_err(|e| storage_error("store agent execution", e))
+    }
+
+    async fn get_execution(&self, id: &Uuid) -> Result<Option<AgentExecution>> {
+        let mut connection = self.connection.clone();
+        let record: Option<String> = connection
+            .hget(self.execution_key(&id.to_string()), "record")
+            .await
+            .map_err(|e| storage_error("read agent execution", e))?;
+        record.as_deref().map(decode).transpose()
+    }
+
+    async fn list_executions_for_resource(
+        &self,
+        resource_id: &Uuid,
+    ) -> Result<Vec<AgentExecution>> {
+        self.read_executions(self.config.key(&[
+            "resource",
+            &resource_id.to_string(),
+            "agent_executions",
+        ]))
+        .await
+    }
+
+    async fn list_executions_for_agent(&self, agent_id: &AgentId) -> Result<Vec<AgentExecution>> {
+        self.read_executions(self.config.key(&["agent", agent_id.as_str(), "executions"]))
+            .await
+    }
+
+    /// Count from the `status` field of each hash without decoding executions
+    async fn count_executions_by_status(&self) -> Result<HashMap<String, u64>> {
+        let mut connection = self.connection.clone();
+        let ids: Vec<String> = connection
+            .smembers(self.config.key(&["agent_executions"]))
+            .await
+            .map_err(|e| storage_error("count agent executions", e))?;
+        let mut pipeline = redis::pipe();
+        for id in &ids {
+            pipeline.hget(self.execution_key(id), "status");
+        }
+        let statuses: Vec<Option<String>> = pipeline
+            .query_async(&mut connection)
+            .await
+            .map_err(|e| storage_error("count agent executions", e))?;
+
+        let mut counts = HashMap::new();
+        for status in statuses.into_iter().flatten() {
+            *counts.entry(status).or_default() += 1;
+        }
+        Ok(counts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redis_storage_config() {
+        assert_eq!(parse_ttl(None), None);
+        assert_eq!(parse_ttl(Some("0")), None);
+        assert_eq!(parse_ttl(Some("soon")), None);
+        assert_eq!(parse_ttl(Some(" 90 ")), Some(Duration::from_secs(90)));
+        assert_eq!(ttl_millis(None), 0);
+        assert_eq!(ttl_millis(Some(Duration::from_secs(2))), 2000);
+
+        let config = RedisStorageConfig {
+            key_prefix: "{cb}".to_string(),
+            ..RedisStorageConfig::default()
+        };
+        assert_eq!(
+            config.key(&["workflow", "orders", "changes"]),
+            "{cb}:workflow:orders:changes"
+        );
+    }
+}
@@ -0,0 +1,442 @@
+// Resource snapshots for NATS storage
+// Latest-state snapshots and replay bookkeeping that keep resource reconstruction fast
+
+//! # Resource Snapshots
+//!
+//! NATS storage keeps every version of a resource in the global stream, so rebuilding
+//! the current state of a workflow by replaying the stream gets slower as history grows.
+//! A periodic compaction pass writes the latest state of each resource into a JetStream
+//! key-value bucket, one key per resource, and records the stream sequence it covered
+//! in a per-workflow [`SnapshotCheckpoint`]. Reads then load the snapshots and replay
+//! only the messages published after the checkpoint.
+//!
+//! Every reconstruction is timed into [`ReconstructionMetrics`], labelled by whether it
+//! started from a snapshot or had to replay the whole stream, and rendered on the
+//! GraphQL server's `/metrics` endpoint next to the rules engine metrics.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::models::Resource;
+
+/// Key-value bucket holding resource snapshots and workflow checkpoints
+pub const SNAPSHOT_BUCKET: &str = "CB_RESOURCE_SNAPSHOTS";
+
+/// How often the compaction task snapshots every workflow by default
+pub const DEFAULT_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+lazy_static::lazy_static! {
+    static ref GLOBAL: ReconstructionMetrics = ReconstructionMetrics::new();
+}
+
+/// Snapshot key of one resource
+pub fn snapshot_key(workflow_id: &str, resource_id: &Uuid) -> String {
+    format!("{}.resources.{}", workflow_id, resource_id)
+}
+
+/// Key pattern matching the snapshots of every resource in a workflow
+pub fn workflow_snapshot_keys(workflow_id: &str) -> String {
+    format!("{}.resources.*", workflow_id)
+}
+
+/// Checkpoint key of a workflow
+pub fn checkpoint_key(workflow_id: &str) -> String {
+    format!("{}.checkpoint", workflow_id)
+}
+
+/// How far the snapshots of a workflow are up to date
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotCheckpoint {
+    /// Last stream sequence folded into the snapshots
+    pub sequence: u64,
+    /// Resources in the workflow at compaction time
+    pub resources: usize,
+    pub compacted_at: DateTime<Utc>,
+}
+
+/// Where a reconstruction started from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReconstructionSource {
+    /// Snapshots plus the messages published after the checkpoint
+    Snapshot,
+    /// Every message in the stream
+    FullReplay,
+}
+
+impl ReconstructionSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReconstructionSource::Snapshot => "snapshot",
+            ReconstructionSource::FullReplay => "full_replay",
+        }
+    }
+}
+
+/// Latest state of resources rebuilt from snapshots and replayed stream messages
+#[derive(Debug, Default)]
+pub struct ResourceReplay {
+    /// Resource ID -> stream sequence of the version kept (0 for snapshots) and the resource
+    resources: HashMap<Uuid, (u64, Resource)>,
+    /// Resources replayed from the stream rather than loaded from a snapshot
+    changed: HashSet<Uuid>,
+    /// Highest stream sequence replayed
+    last_sequence: u64,
+    replayed: usize,
+    snapshots: usize,
+}
+
+impl ResourceReplay {
+    /// Start from the snapshots taken up to `checkpoint_sequence`
+    pub fn from_snapshots(
+        checkpoint_sequence: u64,
+        snapshots: impl IntoIterator<Item = Resource>,
+    ) -> Self {
+        let mut replay = Self {
+            last_sequence: checkpoint_sequence,
+            ..Default::default()
+        };
+        for resource in snapshots {
+            replay.snapshots += 1;
+            replay.resources.insert(resource.id, (0, resource));
+        }
+        replay
+    }
+
+    /// Apply a resource version published at stream `sequence`
+    pub fn apply(&mut self, sequence: u64, resource: Resource) {
+        self.replayed += 1;
+        self.last_sequence = self.last_sequence.max(sequence);
+        match self.resources.get(&resource.id) {
+            Some((kept, _)) if *kept > sequence => {}
+            _ => {
+                self.changed.insert(resource.id);
+                self.resources.insert(resource.id, (sequence, resource));
+            }
+        }
+    }
+
+    /// Highest stream sequence covered
+    pub fn last_sequence(&self) -> u64 {
+        self.last_sequence
+    }
+
+    /// Stream messages applied
+    pub fn replayed(&self) -> usize {
+        self.replayed
+    }
+
+    /// Resources loaded from snapshots
+    pub fn snapshots(&self) -> usize {
+        self.snapshots
+    }
+
+    /// Resources whose snapshot is out of date
+    pub fn changed(&self) -> impl Iterator<Item = &Resource> {
+        self.changed
+            .iter()
+            .filter_map(|id| self.resources.get(id).map(|(_, resource)| resource))
+    }
+
+    pub fn len(&self) -> usize {
+        self.resources.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.resources.is_empty()
+    }
+
+    /// Latest state of every resource, oldest first
+    pub fn into_resources(self) -> Vec<Resource> {
+        let mut resources: Vec<Resource> = self
+            .resources
+            .into_values()
+            .map(|(_, resource)| resource)
+            .collect();
+        resources.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+        resources
+    }
+}
+
+/// Shared registry of resource reconstruction and compaction timings
+///
+/// Clones record into the same registry.
+#[derive(Debug, Clone, Default)]
+pub struct ReconstructionMetrics {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    reconstructions: HashMap<ReconstructionSource, ReconstructionStats>,
+    compactions_total: u64,
+    compaction_failures_total: u64,
+    last_compaction: Duration,
+    resources_snapshotted_total: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ReconstructionStats {
+    count: u64,
+    duration_sum: Duration,
+    duration_max: Duration,
+    last_duration: Duration,
+    messages_replayed: u64,
+    snapshots_loaded: u64,
+}
+
+/// Point-in-time view of reconstructions from one source
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReconstructionStatsSnapshot {
+    pub source: ReconstructionSource,
+    pub count: u64,
+    pub mean_duration_ms: f64,
+    pub max_duration_ms: f64,
+    pub last_duration_ms: f64,
+    pub messages_replayed: u64,
+    pub snapshots_loaded: u64,
+}
+
+/// Point-in-time view of the reconstruction metrics
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReconstructionMetricsSnapshot {
+    pub reconstructions: Vec<ReconstructionStatsSnapshot>,
+    pub compactions_total: u64,
+    pub compaction_failures_total: u64,
+    pub last_compaction_ms: f64,
+    pub resources_snapshotted_total: u64,
+}
+
+impl ReconstructionMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The process-wide registry NATS storage records into
+    pub fn global() -> Self {
+        GLOBAL.clone()
+    }
+
+    /// Record one reconstruction of resource state
+    pub fn record_reconstruction(
+        &self,
+        source: ReconstructionSource,
+        elapsed: Duration,
+        replay: &ResourceReplay,
+    ) {
+        let mut inner = self.lock();
+        let stats = inner.reconstructions.entry(source).or_default();
+        stats.count += 1;
+        stats.duration_sum += elapsed;
+        stats.duration_max = stats.duration_max.max(elapsed);
+        stats.last_duration = elapsed;
+        stats.messages_replayed += replay.replayed() as u64;
+        stats.snapshots_loaded += replay.snapshots() as u64;
+    }
+
+    /// Record a compaction pass that wrote `snapshotted` resource snapshots
+    pub fn record_compaction(&self, elapsed: Duration, snapshotted: usize) {
+        let mut inner = self.lock();
+        inner.compactions_total += 1;
+        inner.last_compaction = elapsed;
+        inner.resources_snapshotted_total += snapshotted as u64;
+    }
+
+    /// Record a compaction pass that failed
+    pub fn record_compaction_failure(&self) {
+        self.lock().compaction_failures_total += 1;
+    }
+
+    pub fn snapshot(&self) -> ReconstructionMetricsSnapshot {
+        let inner = self.lock();
+        let mut reconstructions: Vec<ReconstructionStatsSnapshot> = inner
+            .reconstructions
+            .iter()
+            .map(|(source, stats)| stats.snapshot(*source))
+            .collect();
+        reconstructions.sort_by_key(|stats| stats.source.as_str());
+
+        ReconstructionMetricsSnapshot {
+            reconstructions,
+            compactions_total: inner.compactions_total,
+            compaction_failures_total: inner.compaction_failures_total,
+            last_compaction_ms: inner.last_compaction.as_secs_f64() * 1000.0,
+            resources_snapshotted_total: inner.resources_snapshotted_total,
+        }
+    }
+
+    /// Render the metrics in the Prometheus text exposition format
+    pub fn render_prometheus(&self) -> String {
+        let snapshot = self.snapshot();
+        let mut out = String::new();
+
+        let _ = writeln!(
+            out,
+            "# HELP circuit_breaker_resource_reconstructions_total Resource state reconstructions by source\n\
+             # TYPE circuit_breaker_resource_reconstructions_total counter"
+        );
+        for stats in &snapshot.reconstructions {
+            let _ = writeln!(
+                out,
+                "circuit_breaker_resource_reconstructions_total{{source=\"{}\"}} {}",
+                stats.source.as_str(),
+                stats.count
+            );
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP circuit_breaker_resource_reconstruction_seconds Time spent reconstructing resource state\n\
+             # TYPE circuit_breaker_resource_reconstruction_seconds summary"
+        );
+        for stats in &snapshot.reconstructions {
+            let source = stats.source.as_str();
+            let _ = writeln!(
+                out,
+                "circuit_breaker_resource_reconstruction_seconds_sum{{source=\"{}\"}} {}\n\
+                 circuit_breaker_resource_reconstruction_seconds_count{{source=\"{}\"}} {}",
+                source,
+                stats.mean_duration_ms * stats.count as f64 / 1000.0,
+                source,
+                stats.count
+            );
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP circuit_breaker_resource_reconstruction_last_seconds Duration of the latest reconstruction\n\
+             # TYPE circuit_breaker_resource_reconstruction_last_seconds gauge"
+        );
+        for stats in &snapshot.reconstructions {
+            let _ = writeln!(
+                out,
+                "circuit_breaker_resource_reconstruction_last_seconds{{source=\"{}\"}} {}",
+                stats.source.as_str(),
+                stats.last_duration_ms / 1000.0
+            );
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP circuit_breaker_resource_messages_replayed_total Stream messages replayed during reconstruction\n\
+             # TYPE circuit_breaker_resource_messages_replayed_total counter"
+        );
+        for stats in &snapshot.reconstructions {
+            let _ = writeln!(
+                out,
+                "circuit_breaker_resource_messages_replayed_total{{source=\"{}\"}} {}",
+                stats.source.as_str(),
+                stats.messages_replayed
+            );
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP circuit_breaker_snapshot_compactions_total Snapshot compaction passes\n\
+             # TYPE circuit_breaker_snapshot_compactions_total counter\n\
+             circuit_breaker_snapshot_compactions_total {}\n\
+             # HELP circuit_breaker_snapshot_compaction_failures_total Snapshot compaction passes that failed\n\
+             # TYPE circuit_breaker_snapshot_compaction_failures_total counter\n\
+             circuit_breaker_snapshot_compaction_failures_total {}\n\
+             # HELP circuit_breaker_snapshot_compaction_last_seconds Duration of the latest compaction pass\n\
+             # TYPE circuit_breaker_snapshot_compaction_last_seconds gauge\n\
+             circuit_breaker_snapshot_compaction_last_seconds {}\n\
+             # HELP circuit_breaker_resources_snapshotted_total Resource snapshots written\n\
+             # TYPE circuit_breaker_resources_snapshotted_total counter\n\
+             circuit_breaker_resources_snapshotted_total {}",
+            snapshot.compactions_total,
+            snapshot.compaction_failures_total,
+            snapshot.last_compaction_ms / 1000.0,
+            snapshot.resources_snapshotted_total
+        );
+
+        out
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        // Metrics stay usable even if a recording thread panicked
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl ReconstructionStats {
+    fn snapshot(&self, source: ReconstructionSource) -> ReconstructionStatsSnapshot {
+        ReconstructionStatsSnapshot {
+            source,
+            count: self.count,
+            mean_duration_ms: if self.count == 0 {
+                0.0
+            } else {
+                self.duration_sum.as_secs_f64() * 1000.0 / self.count as f64
+            },
+            max_duration_ms: self.duration_max.as_secs_f64() * 1000.0,
+            last_duration_ms: self.last_duration.as_secs_f64() * 1000.0,
+            messages_replayed: self.messages_replayed,
+            snapshots_loaded: self.snapshots_loaded,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::StateId;
+
+    #[test]
+    fn test_replay_applies_newer_versions_over_snapshots() {
+        let mut snapshotted = Resource::new("review", StateId::from("draft"));
+        let untouched = Resource::new("review", StateId::from("draft"));
+        let mut replay = ResourceReplay::from_snapshots(10, [snapshotted.clone(), untouched]);
+
+        snapshotted.state = StateId::from("published");
+        replay.apply(14, snapshotted.clone());
+        let mut stale = snapshotted.clone();
+        stale.state = StateId::from("review");
+        replay.apply(12, stale);
+        let created = Resource::new("review", StateId::from("draft"));
+        replay.apply(15, created.clone());
+
+        assert_eq!(replay.last_sequence(), 15);
+        assert_eq!(replay.replayed(), 3);
+        assert_eq!(replay.snapshots(), 2);
+        let mut changed: Vec<Uuid> = replay.changed().map(|r| r.id).collect();
+        changed.sort();
+        let mut expected = vec![snapshotted.id, created.id];
+        expected.sort();
+        assert_eq!(changed, expected);
+
+        let metrics = ReconstructionMetrics::new();
+        metrics.record_reconstruction(
+            ReconstructionSource::Snapshot,
+            Duration::from_millis(4),
+            &replay,
+        );
+        metrics.record_compaction(Duration::from_millis(20), 2);
+
+        let resources = replay.into_resources();
+        assert_eq!(resources.len(), 3);
+        let published = resources.iter().find(|r| r.id == snapshotted.id).unwrap();
+        assert_eq!(published.state.as_str(), "published");
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.reconstructions.len(), 1);
+        assert_eq!(snapshot.reconstructions[0].messages_replayed, 3);
+        assert_eq!(snapshot.reconstructions[0].snapshots_loaded, 2);
+        assert_eq!(snapshot.reconstructions[0].last_duration_ms, 4.0);
+        assert_eq!(snapshot.resources_snapshotted_total, 2);
+
+        let text = metrics.render_prometheus();
+        assert!(
+            text.contains("circuit_breaker_resource_reconstructions_total{source=\"snapshot\"} 1")
+        );
+        assert!(text.contains(
+            "circuit_breaker_resource_reconstruction_last_seconds{source=\"snapshot\"} 0.004"
+        ));
+        assert!(text.contains("circuit_breaker_snapshot_compactions_total 1"));
+    }
+}
@@ -0,0 +1,160 @@
+// Activity retries
+// Re-runs failed agent and function executions as an activity's retry policy allows
+
+//! # Activity Retries
+//!
+//! [`run_with_retries`] runs the agent or function execution attached to an activity
+//! under the activity's [`ActivityRetryPolicy`]: a failed attempt is followed by
+//! another after the policy's backoff, as long as attempts remain and the error is one
+//! the policy retries. Without a policy the execution runs once.
+//!
+//! Every attempt is recorded in the resource's activity history as an
+//! [`ActivityRecord`] that keeps the resource where it is, with metadata like:
+//!
+//! ```json
+//! { "attempt": 2, "max_attempts": 3, "outcome": "failed", "error": "timeout", "retry_in_secs": 20 }
+//! ```
+
+use chrono::Utc;
+use serde_json::json;
+use std::fmt::Display;
+use std::future::Future;
+
+use crate::models::{ActivityId, ActivityRecord, ActivityRetryPolicy, Resource};
+
+/// Run `attempt` until it succeeds or `policy` gives up, recording each attempt on
+/// `resource`
+///
+/// `attempt` is called with the number of the attempt, counted from 1. The result of
+/// the last attempt is returned.
+pub async fn run_with_retries<T, E, F, Fut>(
+    policy: Option<&ActivityRetryPolicy>,
+    activity_id: &ActivityId,
+    resource: &mut Resource,
+    triggered_by: &str,
+    mut attempt: F,
+) -> std::result::Result<T, E>
+where
+    E: Display,
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = std::result::Result<T, E>>,
+{
+    let max_attempts = policy.map_or(1, |p| p.max_attempts.max(1));
+    let mut number = 1;
+    loop {
+        let result = attempt(number).await;
+        let retry_in = match &result {
+            Err(error) => policy
+                .filter(|p| p.retries(number, &error.to_string()))
+                .map(|p| p.delay_before(number + 1)),
+            Ok(_) => None,
+        };
+
+        let mut metadata = json!({
+            "attempt": number,
+            "max_attempts": max_attempts,
+            "outcome": if result.is_ok() { "succeeded" } else { "failed" },
+        });
+        if let Err(error) = &result {
+            metadata["error"] = json!(error.to_string());
+        }
+        if let Some(delay) = retry_in {
+            metadata["retry_in_secs"] = json!(delay.as_secs());
+        }
+        resource.add_activity_record(ActivityRecord {
+            from_state: resource.state.clone(),
+            to_state: resource.state.clone(),
+            activity_id: activity_id.clone(),
+            timestamp: Utc::now(),
+            triggered_by: Some(triggered_by.to_string()),
+            nats_sequence: None,
+            metadata: Some(metadata),
+        });
+
+        match retry_in {
+            Some(delay) => {
+                tracing::warn!(
+                    "Attempt {} of activity '{}' failed for resource {}, retrying in {:?}",
+                    number,
+                    activity_id.as_str(),
+                    resource.id,
+                    delay
+                );
+                tokio::time::sleep(delay).await;
+                number += 1;
+            }
+            None => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::StateId;
+
+    #[tokio::test]
+    async fn test_retries_until_success() {
+        let policy = ActivityRetryPolicy::new(3, 0);
+        let activity = ActivityId::from("summarize");
+        let mut resource = Resource::new("docs", StateId::from("draft"));
+
+        let result = run_with_retries(
+            Some(&policy),
+            &activity,
+            &mut resource,
+            "agent:a",
+            |n| async move {
+                if n < 2 {
+                    Err("rate_limit exceeded")
+                } else {
+                    Ok(n)
+                }
+            },
+        )
+        .await;
+        assert_eq!(result, Ok(2));
+
+        let attempts: Vec<_> = resource
+            .activity_history
+            .iter()
+            .map(|r| r.metadata.clone().unwrap())
+            .collect();
+        assert_eq!(attempts.len(), 2);
+        assert_eq!(attempts[0]["outcome"], "failed");
+        assert_eq!(attempts[0]["error"], "rate_limit exceeded");
+        assert_eq!(attempts[0]["retry_in_secs"], 0);
+        assert_eq!(attempts[1]["outcome"], "succeeded");
+        assert_eq!(attempts[1]["attempt"], 2);
+        assert_eq!(
+            resource.activity_history[1].to_state,
+            StateId::from("draft")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stops_on_unretried_errors() {
+        let policy = ActivityRetryPolicy::new(5, 0).retry_on(vec!["timeout"]);
+        let activity = ActivityId::from("summarize");
+        let mut resource = Resource::new("docs", StateId::from("draft"));
+
+        let result: std::result::Result<(), _> = run_with_retries(
+            Some(&policy),
+            &activity,
+            &mut resource,
+            "agent:a",
+            |_| async { Err("invalid credentials") },
+        )
+        .await;
+        assert!(result.is_err());
+        assert_eq!(resource.activity_history.len(), 1);
+
+        let result: std::result::Result<(), _> =
+            run_with_retries(None, &activity, &mut resource, "agent:a", |_| async {
+                Err("timeout")
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(resource.activity_history.len(), 2);
+    }
+}
@@ -0,0 +1,433 @@
+// Compiled rule cache for the RulesEngine
+// Rules are compiled once and the compiled form is reused for every evaluation
+
+//! # Compiled Rules
+//!
+//! [`Rule::evaluate`] interprets a rule from scratch on every call: field names are
+//! re-split into paths, regular expressions are re-compiled and `And`/`Or` branches
+//! run in declaration order. That is fine for a single check but adds up when
+//! thousands of resources are evaluated against every activity of a workflow.
+//!
+//! A [`CompiledRule`] does that work once:
+//! - field paths such as `review.status` are split up front
+//! - `FieldMatches` patterns are compiled into a [`Regex`]
+//! - `Expression` conditions other than flag checks, nested `And`/`Or` of the same kind and branches with a
+//!   constant outcome are folded away
+//! - `And`/`Or` branches are ordered cheapest first, so that a cheap check that
+//!   decides the outcome skips the expensive ones
+//!
+//! [`RuleCache`] keeps compiled rules by rule ID. A cached rule is reused only while
+//! its source is unchanged; an edited rule with the same ID is recompiled.
+
+use crate::models::confidential::is_encrypted;
+use crate::models::correlation::count_siblings;
+use crate::models::feature_flag::{flag_enabled, parse_flag_call};
+use crate::models::rule::signal_received;
+use crate::models::{resolve_path, ResourceMetadata, Rule, RuleCondition};
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Number of compiled rules kept before the cache is cleared
+pub const DEFAULT_RULE_CACHE_CAPACITY: usize = 10_000;
+
+/// A field reference resolved once at compile time
+#[derive(Debug)]
+struct CompiledField {
+    name: String,
+    /// Path segments for dotted names, empty otherwise
+    path: Vec<String>,
+}
+
+impl CompiledField {
+    fn new(name: &str) -> Self {
+        let path = if name.contains('.') {
+            name.split('.').map(str::to_string).collect()
+        } else {
+            Vec::new()
+        };
+        Self {
+            name: name.to_string(),
+            path,
+        }
+    }
+
+    fn resolve<'a>(
+        &self,
+        metadata: &'a ResourceMetadata,
+        data: &'a serde_json::Value,
+    ) -> Option<&'a serde_json::Value> {
+        metadata
+            .get(&self.name)
+            .or_else(|| data.get(&self.name))
+            .or_else(|| resolve_path(metadata, data, &self.path))
+            .filter(|value| !is_encrypted(value))
+    }
+}
+
+#[derive(Debug)]
+enum CompiledCondition {
+    Exists(CompiledField),
+    Equals(CompiledField, serde_json::Value),
+    GreaterThan(CompiledField, f64),
+    LessThan(CompiledField, f64),
+    Contains(CompiledField, String),
+    Matches(CompiledField, Regex),
+    Flag(String),
+    Signal(String),
+    Siblings {
+        correlation_key: String,
+        state: String,
+        workflow_id: Option<String>,
+        min_count: Option<u32>,
+    },
+    And(Vec<CompiledCondition>),
+    Or(Vec<CompiledCondition>),
+    Not(Box<CompiledCondition>),
+    Constant(bool),
+}
+
+impl CompiledCondition {
+    fn compile(condition: &RuleCondition) -> Self {
+        match condition {
+            RuleCondition::FieldExists { field } => Self::Exists(CompiledField::new(field)),
+            RuleCondition::FieldEquals { field, value } => {
+                Self::Equals(CompiledField::new(field), value.clone())
+            }
+            RuleCondition::FieldGreaterThan { field, value } => {
+                Self::GreaterThan(CompiledField::new(field), *value)
+            }
+            RuleCondition::FieldLessThan { field, value } => {
+                Self::LessThan(CompiledField::new(field), *value)
+            }
+            RuleCondition::FieldContains { field, substring } => {
+                Self::Contains(CompiledField::new(field), substring.clone())
+            }
+            RuleCondition::FieldMatches { field, pattern } => match Regex::new(pattern) {
+                Ok(regex) => Self::Matches(CompiledField::new(field), regex),
+                // An invalid pattern never matches
+                Err(_) => Self::Constant(false),
+            },
+            RuleCondition::And { rules } => Self::all(
+                rules
+                    .iter()
+                    .map(|rule| Self::compile(&rule.condition))
+                    .collect(),
+            ),
+            RuleCondition::Or { rules } => Self::any(
+                rules
+                    .iter()
+                    .map(|rule| Self::compile(&rule.condition))
+                    .collect(),
+            ),
+            RuleCondition::Not { rule } => match Self::compile(&rule.condition) {
+                Self::Constant(value) => Self::Constant(!value),
+                inner => Self::Not(Box::new(inner)),
+            },
+            RuleCondition::FlagEnabled { flag } => Self::Flag(flag.clone()),
+            RuleCondition::SignalReceived { signal } => Self::Signal(signal.clone()),
+            RuleCondition::SiblingsInState {
+                correlation_key,
+                state,
+                workflow_id,
+                min_count,
+            } => Self::Siblings {
+                correlation_key: correlation_key.clone(),
+                state: state.clone(),
+                workflow_id: workflow_id.clone(),
+                min_count: *min_count,
+            },
+            // Expressions other than flag checks are not evaluated yet and always fail
+            RuleCondition::Expression { script } => match parse_flag_call(script) {
+                Some(flag) => Self::Flag(flag.to_string()),
+                None => Self::Constant(false),
+            },
+        }
+    }
+
+    /// Fold an AND: nested ANDs are flattened, `true` branches dropped and a `false`
+    /// branch decides the whole condition
+    fn all(conditions: Vec<Self>) -> Self {
+        let mut branches = Vec::with_capacity(conditions.len());
+        for condition in conditions {
+            match condition {
+                Self::Constant(true) => {}
+                Self::Constant(false) => return Self::Constant(false),
+                Self::And(nested) => branches.extend(nested),
+                other => branches.push(other),
+            }
+        }
+        match branches.len() {
+            0 => Self::Constant(true),
+            1 => branches.pop().unwrap(),
+            _ => {
+                branches.sort_by_key(Self::cost);
+                Self::And(branches)
+            }
+        }
+    }
+
+    /// Fold an OR: nested ORs are flattened, `false` branches dropped and a `true`
+    /// branch decides the whole condition
+    fn any(conditions: Vec<Self>) -> Self {
+        let mut branches = Vec::with_capacity(conditions.len());
+        for condition in conditions {
+            match condition {
+                Self::Constant(false) => {}
+                Self::Constant(true) => return Self::Constant(true),
+                Self::Or(nested) => branches.extend(nested),
+                other => branches.push(other),
+            }
+        }
+        match branches.len() {
+            0 => Self::Constant(false),
+            1 => branches.pop().unwrap(),
+            _ => {
+                branches.sort_by_key(Self::cost);
+                Self::Or(branches)
+            }
+        }
+    }
+
+    /// Rough relative cost of evaluating the condition
+    fn cost(&self) -> u32 {
+        match self {
+            Self::Constant(_) => 0,
+            Self::Exists(_) | Self::Flag(_) | Self::Signal(_) => 1,
+            Self::Equals(..) | Self::GreaterThan(..) | Self::LessThan(..) => 2,
+            Self::Contains(..) => 4,
+            Self::Matches(..) => 8,
+            Self::Siblings { .. } => 16,
+            Self::And(branches) | Self::Or(branches) => branches.iter().map(Self::cost).sum(),
+            Self::Not(inner) => inner.cost(),
+        }
+    }
+
+    fn evaluate(&self, metadata: &ResourceMetadata, data: &serde_json::Value) -> bool {
+        match self {
+            Self::Exists(field) => field.resolve(metadata, data).is_some(),
+            Self::Equals(field, value) => {
+                metadata.get(&field.name) == Some(value)
+                    || data.get(&field.name) == Some(value)
+                    || field.resolve(metadata, data) == Some(value)
+            }
+            Self::GreaterThan(field, value) => field
+                .resolve(metadata, data)
+                .and_then(|v| v.as_f64())
+                .is_some_and(|v| v > *value),
+            Self::LessThan(field, value) => field
+                .resolve(metadata, data)
+                .and_then(|v| v.as_f64())
+                .is_some_and(|v| v < *value),
+            Self::Contains(field, substring) => field
+                .resolve(metadata, data)
+                .and_then(|v| v.as_str())
+                .is_some_and(|v| v.contains(substring.as_str())),
+            Self::Matches(field, regex) => field
+                .resolve(metadata, data)
+                .and_then(|v| v.as_str())
+                .is_some_and(|v| regex.is_match(v)),
+            Self::Flag(flag) => flag_enabled(flag),
+            Self::Signal(signal) => signal_received(metadata, signal),
+            Self::Siblings {
+                correlation_key,
+                state,
+                workflow_id,
+                min_count,
+            } => count_siblings(correlation_key, state, workflow_id.as_deref()).meets(*min_count),
+            Self::And(branches) => branches.iter().all(|c| c.evaluate(metadata, data)),
+            Self::Or(branches) => branches.iter().any(|c| c.evaluate(metadata, data)),
+            Self::Not(inner) => !inner.evaluate(metadata, data),
+            Self::Constant(value) => *value,
+        }
+    }
+}
+
+/// A rule prepared for repeated evaluation
+///
+/// Evaluates to the same result as [`Rule::evaluate`] on the rule it was compiled from.
+#[derive(Debug)]
+pub struct CompiledRule {
+    source: Rule,
+    condition: CompiledCondition,
+    uses_flags: bool,
+    correlation_keys: Vec<String>,
+}
+
+impl CompiledRule {
+    pub fn compile(rule: &Rule) -> Self {
+        Self {
+            source: rule.clone(),
+            condition: CompiledCondition::compile(&rule.condition),
+            uses_flags: rule.uses_flags(),
+            correlation_keys: rule.correlation_keys(),
+        }
+    }
+
+    /// The rule this was compiled from
+    pub fn source(&self) -> &Rule {
+        &self.source
+    }
+
+    /// Whether the rule reads feature flags, so needs a flag scope to evaluate
+    pub fn uses_flags(&self) -> bool {
+        self.uses_flags
+    }
+
+    /// Correlation keys the rule reads siblings by, so needs a sibling scope for
+    pub fn correlation_keys(&self) -> &[String] {
+        &self.correlation_keys
+    }
+
+    pub fn evaluate(&self, metadata: &ResourceMetadata, data: &serde_json::Value) -> bool {
+        self.condition.evaluate(metadata, data)
+    }
+}
+
+/// Compiled rules keyed by rule ID
+#[derive(Debug)]
+pub struct RuleCache {
+    entries: RwLock<HashMap<String, Arc<CompiledRule>>>,
+    capacity: usize,
+}
+
+impl RuleCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: RwLock::new(HashMap::new()),
+            capacity,
+        }
+    }
+
+    /// Get the compiled form of `rule`, compiling it if it is not cached or has
+    /// changed since it was. The flag tells whether the cache was hit.
+    pub fn get_or_compile(&self, rule: &Rule) -> (Arc<CompiledRule>, bool) {
+        if let Some(compiled) = self.entries.read().unwrap().get(&rule.id) {
+            if compiled.source == *rule {
+                return (compiled.clone(), true);
+            }
+        }
+        (self.insert(rule), false)
+    }
+
+    /// Compile `rule` and cache it, replacing any earlier version
+    pub fn insert(&self, rule: &Rule) -> Arc<CompiledRule> {
+        let compiled = Arc::new(CompiledRule::compile(rule));
+        let mut entries = self.entries.write().unwrap();
+        if entries.len() >= self.capacity && !entries.contains_key(&rule.id) {
+            entries.clear();
+        }
+        entries.insert(rule.id.clone(), compiled.clone());
+        compiled
+    }
+
+    pub fn invalidate(&self, rule_id: &str) {
+        self.entries.write().unwrap().remove(rule_id);
+    }
+
+    pub fn clear(&self) {
+        self.entries.write().unwrap().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for RuleCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_RULE_CACHE_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_compiled_rules_match_interpreted() {
+        let mut metadata = HashMap::new();
+        metadata.insert("priority".to_string(), json!(7));
+        let data = json!({
+            "email": "alice@example.com",
+            "review": {"status": "approved", "score": 92},
+        });
+
+        let rules = vec![
+            Rule::field_exists("nested", "review.status"),
+            Rule::field_equals("nested_equals", "review.status", json!("approved")),
+            Rule::field_greater_than("nested_score", "review.score", 90.0),
+            Rule::field_matches("email", "email", r"@example\.com$"),
+            Rule::field_matches("invalid_pattern", "email", "("),
+            Rule::and(
+                "mixed",
+                "Mixed",
+                vec![
+                    Rule::field_matches("regex", "email", "^alice"),
+                    Rule::field_greater_than("priority", "priority", 5.0),
+                    Rule::not(
+                        "not_missing",
+                        "Not missing",
+                        Rule::field_exists("missing", "missing"),
+                    ),
+                ],
+            ),
+            Rule::or(
+                "expression_or",
+                "Expression or field",
+                vec![
+                    Rule {
+                        id: "script".to_string(),
+                        description: "Script".to_string(),
+                        condition: RuleCondition::Expression {
+                            script: "true".to_string(),
+                        },
+                    },
+                    Rule::field_exists("missing", "missing"),
+                ],
+            ),
+            Rule::and("empty_and", "Empty", vec![]),
+            Rule::or("empty_or", "Empty", vec![]),
+        ];
+
+        for rule in &rules {
+            assert_eq!(
+                CompiledRule::compile(rule).evaluate(&metadata, &data),
+                rule.evaluate(&metadata, &data),
+                "rule {}",
+                rule.id
+            );
+        }
+    }
+
+    #[test]
+    fn test_cache_recompiles_changed_rules() {
+        let cache = RuleCache::new(2);
+        let metadata = HashMap::new();
+        let data = json!({"status": "approved"});
+
+        let rule = Rule::field_equals("status", "status", json!("approved"));
+        let (compiled, hit) = cache.get_or_compile(&rule);
+        assert!(!hit);
+        assert!(compiled.evaluate(&metadata, &data));
+        assert!(cache.get_or_compile(&rule).1);
+
+        // Same ID, different condition
+        let edited = Rule::field_equals("status", "status", json!("rejected"));
+        let (compiled, hit) = cache.get_or_compile(&edited);
+        assert!(!hit);
+        assert!(!compiled.evaluate(&metadata, &data));
+
+        cache.insert(&Rule::field_exists("a", "a"));
+        assert_eq!(cache.len(), 2);
+        // Over capacity the cache starts over
+        cache.insert(&Rule::field_exists("b", "b"));
+        assert_eq!(cache.len(), 1);
+    }
+}
@@ -0,0 +1,362 @@
+// Rules engine performance metrics
+// Counters and latency histograms recorded by the RulesEngine, served on `/metrics`
+
+//! # Rule Metrics
+//!
+//! Every [`RulesEngine`](crate::engine::RulesEngine) evaluation is recorded here: how many
+//! activities were evaluated, how long each rule took and whether it passed, and how often
+//! compiled rule expressions were found in the cache. The numbers help find the rules that
+//! dominate evaluation time or block resources most often in complex workflows.
+//!
+//! Engines record into the process-wide [`RuleMetrics::global`] registry unless given their
+//! own. It is rendered in the Prometheus text format on the GraphQL server's `/metrics`
+//! endpoint and returned by the `ruleMetrics` GraphQL query.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Upper bounds of the latency histogram buckets, in seconds
+pub const LATENCY_BUCKETS: &[f64] = &[
+    0.000_001, 0.000_005, 0.000_01, 0.000_05, 0.000_1, 0.000_5, 0.001, 0.005, 0.01, 0.05,
+];
+
+/// Window the evaluation rate is averaged over
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Rules listed as most frequently failing when no limit is given
+pub const DEFAULT_TOP_FAILING: usize = 10;
+
+lazy_static::lazy_static! {
+    static ref GLOBAL: RuleMetrics = RuleMetrics::new();
+}
+
+/// Shared registry of rules engine metrics
+///
+/// Clones record into the same registry.
+#[derive(Debug, Clone, Default)]
+pub struct RuleMetrics {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    activity_evaluations_total: u64,
+    cache_hits_total: u64,
+    cache_misses_total: u64,
+    rules: HashMap<String, RuleStats>,
+    /// Rule evaluations per second, oldest first
+    recent: VecDeque<(Instant, u64)>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct RuleStats {
+    evaluations: u64,
+    failures: u64,
+    /// Per-bucket counts; the extra last bucket is `+Inf`
+    buckets: Vec<u64>,
+    latency_sum: Duration,
+    latency_max: Duration,
+}
+
+/// Point-in-time view of one rule's metrics
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleStatsSnapshot {
+    pub rule_id: String,
+    pub evaluations: u64,
+    pub failures: u64,
+    pub mean_latency_us: f64,
+    pub max_latency_us: f64,
+}
+
+/// Point-in-time view of the rules engine metrics
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleMetricsSnapshot {
+    pub activity_evaluations_total: u64,
+    pub rule_evaluations_total: u64,
+    /// Rule evaluations per second over the last minute
+    pub evaluations_per_second: f64,
+    pub cache_hits_total: u64,
+    pub cache_misses_total: u64,
+    /// Every rule evaluated so far, by rule ID
+    pub rules: Vec<RuleStatsSnapshot>,
+    /// Rules with the most failed evaluations, most failures first
+    pub most_failing: Vec<RuleStatsSnapshot>,
+}
+
+impl RuleMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The process-wide registry engines record into by default
+    pub fn global() -> Self {
+        GLOBAL.clone()
+    }
+
+    /// Record one activity evaluated for a resource
+    pub fn record_activity_evaluation(&self) {
+        self.lock().activity_evaluations_total += 1;
+    }
+
+    /// Record the outcome and latency of one rule evaluation
+    pub fn record_rule(&self, rule_id: &str, passed: bool, elapsed: Duration) {
+        let mut guard = self.lock();
+        let inner = &mut *guard;
+        if !inner.rules.contains_key(rule_id) {
+            inner
+                .rules
+                .insert(rule_id.to_string(), RuleStats::default());
+        }
+        if let Some(stats) = inner.rules.get_mut(rule_id) {
+            stats.record(passed, elapsed);
+        }
+
+        let now = Instant::now();
+        match inner.recent.back_mut() {
+            Some((second, count)) if now.duration_since(*second) < Duration::from_secs(1) => {
+                *count += 1
+            }
+            _ => inner.recent.push_back((now, 1)),
+        }
+        while let Some((second, _)) = inner.recent.front() {
+            if now.duration_since(*second) <= RATE_WINDOW {
+                break;
+            }
+            inner.recent.pop_front();
+        }
+    }
+
+    /// Record a compiled rule expression served from the cache
+    pub fn record_cache_hit(&self) {
+        self.lock().cache_hits_total += 1;
+    }
+
+    /// Record a rule expression that had to be compiled
+    pub fn record_cache_miss(&self) {
+        self.lock().cache_misses_total += 1;
+    }
+
+    /// Current metrics, listing at most `top_failing` most frequently failing rules
+    pub fn snapshot(&self, top_failing: usize) -> RuleMetricsSnapshot {
+        let inner = self.lock();
+
+        let mut rules: Vec<RuleStatsSnapshot> = inner
+            .rules
+            .iter()
+            .map(|(rule_id, stats)| stats.snapshot(rule_id))
+            .collect();
+        rules.sort_by(|a, b| a.rule_id.cmp(&b.rule_id));
+
+        let mut most_failing: Vec<RuleStatsSnapshot> =
+            rules.iter().filter(|r| r.failures > 0).cloned().collect();
+        most_failing.sort_by_key(|r| std::cmp::Reverse(r.failures));
+        most_failing.truncate(top_failing);
+
+        RuleMetricsSnapshot {
+            activity_evaluations_total: inner.activity_evaluations_total,
+            rule_evaluations_total: rules.iter().map(|r| r.evaluations).sum(),
+            evaluations_per_second: inner.evaluations_per_second(),
+            cache_hits_total: inner.cache_hits_total,
+            cache_misses_total: inner.cache_misses_total,
+            rules,
+            most_failing,
+        }
+    }
+
+    /// Render the metrics in the Prometheus text exposition format
+    pub fn render_prometheus(&self) -> String {
+        let inner = self.lock();
+        let mut out = String::new();
+
+        let mut rule_ids: Vec<&String> = inner.rules.keys().collect();
+        rule_ids.sort();
+
+        let _ = writeln!(
+            out,
+            "# HELP circuit_breaker_activity_evaluations_total Activities evaluated for a resource\n\
+             # TYPE circuit_breaker_activity_evaluations_total counter\n\
+             circuit_breaker_activity_evaluations_total {}",
+            inner.activity_evaluations_total
+        );
+        let _ = writeln!(
+            out,
+            "# HELP circuit_breaker_rule_evaluations_per_second Rule evaluations per second over the last minute\n\
+             # TYPE circuit_breaker_rule_evaluations_per_second gauge\n\
+             circuit_breaker_rule_evaluations_per_second {}",
+            inner.evaluations_per_second()
+        );
+        let _ = writeln!(
+            out,
+            "# HELP circuit_breaker_rule_cache_hits_total Compiled rule expressions served from the cache\n\
+             # TYPE circuit_breaker_rule_cache_hits_total counter\n\
+             circuit_breaker_rule_cache_hits_total {}\n\
+             # HELP circuit_breaker_rule_cache_misses_total Rule expressions compiled on a cache miss\n\
+             # TYPE circuit_breaker_rule_cache_misses_total counter\n\
+             circuit_breaker_rule_cache_misses_total {}",
+            inner.cache_hits_total, inner.cache_misses_total
+        );
+
+        let _ = writeln!(
+            out,
+            "# HELP circuit_breaker_rule_evaluations_total Rule evaluations by outcome\n\
+             # TYPE circuit_breaker_rule_evaluations_total counter"
+        );
+        for rule_id in &rule_ids {
+            let stats = &inner.rules[*rule_id];
+            let rule = escape_label(rule_id);
+            let _ = writeln!(
+                out,
+                "circuit_breaker_rule_evaluations_total{{rule=\"{}\",result=\"pass\"}} {}\n\
+                 circuit_breaker_rule_evaluations_total{{rule=\"{}\",result=\"fail\"}} {}",
+                rule,
+                stats.evaluations - stats.failures,
+                rule,
+                stats.failures
+            );
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP circuit_breaker_rule_evaluation_duration_seconds Time spent evaluating a rule\n\
+             # TYPE circuit_breaker_rule_evaluation_duration_seconds histogram"
+        );
+        for rule_id in &rule_ids {
+            let stats = &inner.rules[*rule_id];
+            let rule = escape_label(rule_id);
+            let mut cumulative = 0;
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(&stats.buckets) {
+                cumulative += count;
+                let _ = writeln!(
+                    out,
+                    "circuit_breaker_rule_evaluation_duration_seconds_bucket{{rule=\"{}\",le=\"{}\"}} {}",
+                    rule, bound, cumulative
+                );
+            }
+            let _ = writeln!(
+                out,
+                "circuit_breaker_rule_evaluation_duration_seconds_bucket{{rule=\"{}\",le=\"+Inf\"}} {}\n\
+                 circuit_breaker_rule_evaluation_duration_seconds_sum{{rule=\"{}\"}} {}\n\
+                 circuit_breaker_rule_evaluation_duration_seconds_count{{rule=\"{}\"}} {}",
+                rule,
+                stats.evaluations,
+                rule,
+                stats.latency_sum.as_secs_f64(),
+                rule,
+                stats.evaluations
+            );
+        }
+
+        out
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        // Metrics stay usable even if a recording thread panicked
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Inner {
+    fn evaluations_per_second(&self) -> f64 {
+        let total: u64 = self.recent.iter().map(|(_, count)| count).sum();
+        total as f64 / RATE_WINDOW.as_secs_f64()
+    }
+}
+
+impl RuleStats {
+    fn record(&mut self, passed: bool, elapsed: Duration) {
+        if self.buckets.is_empty() {
+            self.buckets = vec![0; LATENCY_BUCKETS.len() + 1];
+        }
+        self.evaluations += 1;
+        if !passed {
+            self.failures += 1;
+        }
+        let seconds = elapsed.as_secs_f64();
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.buckets[bucket] += 1;
+        self.latency_sum += elapsed;
+        self.latency_max = self.latency_max.max(elapsed);
+    }
+
+    fn snapshot(&self, rule_id: &str) -> RuleStatsSnapshot {
+        RuleStatsSnapshot {
+            rule_id: rule_id.to_string(),
+            evaluations: self.evaluations,
+            failures: self.failures,
+            mean_latency_us: if self.evaluations == 0 {
+                0.0
+            } else {
+                self.latency_sum.as_secs_f64() * 1_000_000.0 / self.evaluations as f64
+            },
+            max_latency_us: self.latency_max.as_secs_f64() * 1_000_000.0,
+        }
+    }
+}
+
+/// Escape a Prometheus label value
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_ranks_failing_rules() {
+        let metrics = RuleMetrics::new();
+        metrics.record_activity_evaluation();
+        metrics.record_rule("has_content", true, Duration::from_micros(2));
+        metrics.record_rule("has_reviewer", false, Duration::from_micros(4));
+        metrics.record_rule("has_reviewer", false, Duration::from_micros(8));
+        metrics.record_rule("status_approved", false, Duration::from_micros(1));
+        metrics.record_cache_hit();
+        metrics.record_cache_miss();
+
+        let snapshot = metrics.snapshot(1);
+        assert_eq!(snapshot.activity_evaluations_total, 1);
+        assert_eq!(snapshot.rule_evaluations_total, 4);
+        assert_eq!(snapshot.cache_hits_total, 1);
+        assert_eq!(snapshot.cache_misses_total, 1);
+        assert_eq!(snapshot.rules.len(), 3);
+        assert!(snapshot.evaluations_per_second > 0.0);
+
+        assert_eq!(snapshot.most_failing.len(), 1);
+        let reviewer = &snapshot.most_failing[0];
+        assert_eq!(reviewer.rule_id, "has_reviewer");
+        assert_eq!(reviewer.failures, 2);
+        assert_eq!(reviewer.mean_latency_us, 6.0);
+        assert_eq!(reviewer.max_latency_us, 8.0);
+    }
+
+    #[test]
+    fn test_prometheus_histogram_is_cumulative() {
+        let metrics = RuleMetrics::new();
+        metrics.record_rule("a\"b", true, Duration::from_micros(3));
+        metrics.record_rule("a\"b", false, Duration::from_secs(1));
+
+        let text = metrics.render_prometheus();
+        assert!(text
+            .contains("circuit_breaker_rule_evaluations_total{rule=\"a\\\"b\",result=\"fail\"} 1"));
+        assert!(text.contains(
+            "circuit_breaker_rule_evaluation_duration_seconds_bucket{rule=\"a\\\"b\",le=\"0.000005\"} 1"
+        ));
+        assert!(text.contains(
+            "circuit_breaker_rule_evaluation_duration_seconds_bucket{rule=\"a\\\"b\",le=\"0.05\"} 1"
+        ));
+        assert!(text.contains(
+            "circuit_breaker_rule_evaluation_duration_seconds_bucket{rule=\"a\\\"b\",le=\"+Inf\"} 2"
+        ));
+        assert!(text
+            .contains("circuit_breaker_rule_evaluation_duration_seconds_count{rule=\"a\\\"b\"} 2"));
+    }
+}
@@ -29,9 +29,16 @@
 //! Some methods return references to workflow data, requiring lifetime
 //! annotations to ensure the references remain valid.
 
+use super::correlation_index::CorrelationIndex;
+use super::feature_flags::FeatureFlags;
+use super::rule_cache::RuleCache;
+use super::rule_metrics::RuleMetrics;
+use super::state_capacity::{CapacityCheck, CapacityQueues};
+use crate::models::correlation::with_siblings;
+use crate::models::feature_flag::with_flags;
 use crate::models::{
     activity::ActivityRuleEvaluation, ActivityDefinition, Resource, Rule, RuleCondition,
-    WorkflowDefinition,
+    RuleEvaluationResult, StateId, WorkflowDefinition,
 };
 use crate::{CircuitBreakerError, Result};
 use async_nats::{
@@ -42,7 +49,7 @@ use async_trait;
 use chrono::{DateTime, Utc};
 use futures::StreamExt;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Instant};
 
 /// Central rules engine for evaluating resource activities
 ///
@@ -280,6 +287,22 @@ pub struct RulesEngine {
 
     /// Rule storage backend for persistence
     rule_storage: Option<Arc<dyn RuleStorage>>,
+
+    /// Evaluation counters and latencies, the process-wide registry by default
+    metrics: RuleMetrics,
+
+    /// Compiled form of every rule evaluated so far, reused across evaluations
+    compiled: RuleCache,
+
+    /// Feature flags read by rule conditions, the process-wide store by default
+    flags: FeatureFlags,
+
+    /// Resources waiting for room in full states, the process-wide queues by default
+    capacity: CapacityQueues,
+
+    /// Resources by correlation key value read by sibling conditions, the process-wide
+    /// index by default
+    correlations: CorrelationIndex,
 }
 
 /// Detailed evaluation results for all activities in a workflow
@@ -322,6 +345,11 @@ impl RulesEngine {
         Self {
             global_rules: HashMap::new(),
             rule_storage: None,
+            metrics: RuleMetrics::global(),
+            compiled: RuleCache::default(),
+            flags: FeatureFlags::global(),
+            capacity: CapacityQueues::global(),
+            correlations: CorrelationIndex::global(),
         }
     }
 
@@ -330,14 +358,54 @@ impl RulesEngine {
         Self {
             global_rules: HashMap::new(),
             rule_storage: Some(rule_storage),
+            metrics: RuleMetrics::global(),
+            compiled: RuleCache::default(),
+            flags: FeatureFlags::global(),
+            capacity: CapacityQueues::global(),
+            correlations: CorrelationIndex::global(),
         }
     }
 
+    /// Record evaluation metrics into `metrics` instead of the process-wide registry
+    pub fn with_metrics(mut self, metrics: RuleMetrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Read feature flags from `flags` instead of the process-wide store
+    pub fn with_feature_flags(mut self, flags: FeatureFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Queue resources waiting for capacity in `capacity` instead of the process-wide queues
+    pub fn with_capacity_queues(mut self, capacity: CapacityQueues) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Read siblings for sibling conditions from `correlations` instead of the
+    /// process-wide index
+    pub fn with_correlation_index(mut self, correlations: CorrelationIndex) -> Self {
+        self.correlations = correlations;
+        self
+    }
+
+    /// Get the queues of resources waiting for capacity
+    pub fn capacity_queues(&self) -> &CapacityQueues {
+        &self.capacity
+    }
+
     /// Get the rule storage backend
     pub fn storage(&self) -> Option<&Arc<dyn RuleStorage>> {
         self.rule_storage.as_ref()
     }
 
+    /// Get the registry evaluation metrics are recorded into
+    pub fn metrics(&self) -> &RuleMetrics {
+        &self.metrics
+    }
+
     /// Create a rules engine with common predefined rules
     ///
     /// This provides a starting set of useful rules that cover common
@@ -449,6 +517,7 @@ impl RulesEngine {
     /// engine.register_rule(Rule::field_equals("custom_status", "status", json!("custom")));
     /// ```
     pub fn register_rule(&mut self, rule: Rule) {
+        self.compiled.insert(&rule);
         self.global_rules.insert(rule.id.clone(), rule);
     }
 
@@ -483,6 +552,7 @@ impl RulesEngine {
     /// ## Returns
     /// `Some(Rule)` if the rule was found and removed, `None` if not found
     pub fn remove_rule(&mut self, rule_id: &str) -> Option<Rule> {
+        self.compiled.invalidate(rule_id);
         self.global_rules.remove(rule_id)
     }
 
@@ -491,12 +561,13 @@ impl RulesEngine {
     /// This removes all registered rules. Useful for testing or resetting state.
     pub fn clear_rules(&mut self) {
         self.global_rules.clear();
+        self.compiled.clear();
     }
 
     /// Evaluate if a resource can execute a specific activity
     ///
     /// This is the **authoritative method** for complete activity evaluation that combines:
-    /// 1. State compatibility (is resource in the right state?)
+    /// 1. State compatibility (is resource in the right state? for joins, did every branch arrive?)
     /// 2. Structured rule evaluation (do all activity rules pass?)
     /// 3. Legacy condition support (for backwards compatibility)
     ///
@@ -525,13 +596,24 @@ impl RulesEngine {
     /// // can_execute may be false even if partial is true due to legacy conditions
     /// ```
     pub fn can_execute_activity(&self, resource: &Resource, activity: &ActivityDefinition) -> bool {
-        // First check state compatibility
-        if !activity.can_execute_from(&resource.state) {
+        self.metrics.record_activity_evaluation();
+
+        // Paused and cancelled resources execute nothing
+        if resource.is_halted() {
+            return false;
+        }
+
+        // First check state compatibility, including whether a join's branches arrived
+        if !activity.can_execute_on(resource) {
             return false;
         }
 
         // Then evaluate structured rules
-        if !activity.rules_pass(resource) {
+        if !activity
+            .rules
+            .iter()
+            .all(|rule| self.evaluate_rule(rule, resource))
+        {
             return false;
         }
 
@@ -539,6 +621,38 @@ impl RulesEngine {
         self.evaluate_legacy_conditions(resource, activity)
     }
 
+    /// Check whether an activity's target state has room for a resource
+    ///
+    /// `occupancy` holds the number of resources in each state of the workflow, as
+    /// returned by `WorkflowStorage::count_resources_by_state`. States without a
+    /// capacity constraint always have room. When the target state is full, or its free
+    /// places belong to resources queued ahead, the resource joins the state's queue
+    /// and the activity stays blocked until its turn comes.
+    pub fn check_capacity(
+        &self,
+        resource: &Resource,
+        activity: &ActivityDefinition,
+        workflow: &WorkflowDefinition,
+        occupancy: &HashMap<String, u64>,
+    ) -> CapacityCheck {
+        let target = self.target_state(resource, activity);
+        let in_state = occupancy.get(target.as_str()).copied().unwrap_or(0);
+        self.capacity.admit(workflow, target, resource, in_state)
+    }
+
+    /// State executing an activity moves a resource to
+    ///
+    /// Routes of the activity are evaluated in order against the resource's metadata
+    /// and data; the first whose expression holds picks the state, and the activity's
+    /// `to_state` applies when none does.
+    pub fn target_state<'a>(
+        &self,
+        resource: &Resource,
+        activity: &'a ActivityDefinition,
+    ) -> &'a StateId {
+        activity.target_state(resource)
+    }
+
     /// Get all available activities for a resource in a workflow
     ///
     /// This returns all activities that the resource can currently execute.
@@ -596,7 +710,10 @@ impl RulesEngine {
             .activities
             .iter()
             .map(|activity| {
-                let mut result = activity.evaluate_with_resource(resource);
+                self.metrics.record_activity_evaluation();
+                let mut result = activity.evaluate_with_resource_by(resource, |rule| {
+                    self.evaluate_rule_detailed(rule, resource)
+                });
 
                 // Also check legacy conditions and incorporate into result
                 if result.can_execute {
@@ -648,7 +765,7 @@ impl RulesEngine {
     ) -> bool {
         activity.conditions.iter().all(|condition_name| {
             if let Some(rule) = self.global_rules.get(condition_name) {
-                rule.evaluate(&resource.metadata, &resource.data)
+                self.evaluate_rule(rule, resource)
             } else {
                 // Default to true for unknown conditions (backwards compatibility)
                 true
@@ -677,7 +794,7 @@ impl RulesEngine {
             .iter()
             .map(|condition_name| {
                 if let Some(rule) = self.global_rules.get(condition_name) {
-                    let result = rule.evaluate_detailed(&resource.metadata, &resource.data);
+                    let result = self.evaluate_rule_detailed(rule, resource);
                     (condition_name.clone(), result.passed, result.explanation)
                 } else {
                     (
@@ -692,6 +809,67 @@ impl RulesEngine {
             })
             .collect()
     }
+
+    /// Get the cache of compiled rules
+    pub fn compiled_rules(&self) -> &RuleCache {
+        &self.compiled
+    }
+
+    /// Evaluate a single rule through its compiled form, recording its outcome and latency
+    fn evaluate_rule(&self, rule: &Rule, resource: &Resource) -> bool {
+        let started = Instant::now();
+        let (compiled, hit) = self.compiled.get_or_compile(rule);
+        if hit {
+            self.metrics.record_cache_hit();
+        } else {
+            self.metrics.record_cache_miss();
+        }
+        let passed = self.in_rule_scope(
+            compiled.uses_flags(),
+            compiled.correlation_keys(),
+            resource,
+            || compiled.evaluate(&resource.metadata, &resource.data),
+        );
+        self.metrics
+            .record_rule(&rule.id, passed, started.elapsed());
+        passed
+    }
+
+    /// Evaluate a single rule with an explanation, recording its outcome and latency
+    fn evaluate_rule_detailed(&self, rule: &Rule, resource: &Resource) -> RuleEvaluationResult {
+        let started = Instant::now();
+        let result = self.in_rule_scope(
+            rule.uses_flags(),
+            &rule.correlation_keys(),
+            resource,
+            || rule.evaluate_detailed(&resource.metadata, &resource.data),
+        );
+        self.metrics
+            .record_rule(&rule.id, result.passed, started.elapsed());
+        result
+    }
+
+    /// Run `f` with the flag values and siblings of `resource` the rule reads in scope
+    fn in_rule_scope<R>(
+        &self,
+        uses_flags: bool,
+        correlation_keys: &[String],
+        resource: &Resource,
+        f: impl FnOnce() -> R,
+    ) -> R {
+        let with_correlations = || {
+            if correlation_keys.is_empty() {
+                f()
+            } else {
+                with_siblings(self.correlations.siblings(resource, correlation_keys), f)
+            }
+        };
+        if uses_flags {
+            with_flags(self.flags.evaluate_for(resource), with_correlations)
+        } else {
+            with_correlations()
+        }
+    }
 }
 
 impl Default for RulesEngine {
@@ -861,6 +1039,58 @@ mod tests {
         assert!(!engine.can_execute_activity(&resource, &activity));
     }
 
+    #[test]
+    fn test_evaluations_are_recorded() {
+        let metrics = RuleMetrics::new();
+        let engine = RulesEngine::with_common_rules().with_metrics(metrics.clone());
+        let resource = create_test_resource();
+        let workflow = create_test_workflow();
+
+        engine.available_activities(&resource, &workflow);
+        engine.evaluate_all_activities(&resource, &workflow);
+
+        let snapshot = metrics.snapshot(5);
+        assert_eq!(snapshot.activity_evaluations_total, 4);
+        // "approve" is never state-compatible, so its rule only runs in the detailed pass
+        assert_eq!(snapshot.rule_evaluations_total, 5);
+        assert_eq!(snapshot.most_failing.len(), 1);
+        assert_eq!(snapshot.most_failing[0].rule_id, "status_approved");
+    }
+
+    #[test]
+    fn test_compiled_rules_are_cached() {
+        let metrics = RuleMetrics::new();
+        let mut engine = RulesEngine::new().with_metrics(metrics.clone());
+        let resource = create_test_resource();
+
+        let activity = ActivityDefinition::with_rules(
+            "submit",
+            vec!["draft"],
+            "review",
+            vec![Rule::field_exists("has_content", "content")],
+        );
+        assert!(engine.can_execute_activity(&resource, &activity));
+        assert!(engine.can_execute_activity(&resource, &activity));
+
+        // Global rules are compiled when registered
+        engine.register_rule(Rule::field_equals(
+            "status_pending",
+            "status",
+            serde_json::json!("pending"),
+        ));
+        let mut legacy = ActivityDefinition::new("legacy", vec!["draft"], "review");
+        legacy.conditions = vec!["status_pending".to_string()];
+        assert!(engine.can_execute_activity(&resource, &legacy));
+
+        let snapshot = metrics.snapshot(0);
+        assert_eq!(snapshot.cache_misses_total, 1);
+        assert_eq!(snapshot.cache_hits_total, 2);
+        assert_eq!(engine.compiled_rules().len(), 2);
+
+        engine.remove_rule("status_pending");
+        assert_eq!(engine.compiled_rules().len(), 1);
+    }
+
     #[test]
     fn test_complex_rules() {
         let engine = RulesEngine::with_common_rules();
@@ -893,4 +1123,131 @@ mod tests {
         assert_eq!(result.rule_results.len(), 1);
         assert!(result.rule_results[0].passed);
     }
+
+    #[test]
+    fn test_flag_conditions() {
+        let flags = FeatureFlags::new();
+        let engine = RulesEngine::new().with_feature_flags(flags.clone());
+        let resource = create_test_resource();
+
+        let activity = ActivityDefinition::with_rules(
+            "fast_review",
+            vec!["draft"],
+            "review",
+            vec![Rule {
+                id: "new_path".to_string(),
+                description: "New review path".to_string(),
+                condition: RuleCondition::Expression {
+                    script: "flag(\"new_review_path\")".to_string(),
+                },
+            }],
+        );
+        assert!(!engine.can_execute_activity(&resource, &activity));
+
+        flags
+            .set_flag(
+                crate::models::FeatureFlag::new("new_review_path").for_workflow("test_workflow"),
+            )
+            .unwrap();
+        assert!(engine.can_execute_activity(&resource, &activity));
+
+        flags
+            .set_flag(
+                crate::models::FeatureFlag::new("new_review_path")
+                    .for_workflow("test_workflow")
+                    .targeting(Rule::field_greater_than("important", "priority", 8.0)),
+            )
+            .unwrap();
+        assert!(!engine.can_execute_activity(&resource, &activity));
+
+        let workflow = WorkflowDefinition::new(
+            "test_workflow",
+            "Test",
+            vec![StateId::from("draft"), StateId::from("review")],
+            vec![activity],
+            StateId::from("draft"),
+        );
+        let result = engine.evaluate_all_activities(&resource, &workflow);
+        assert_eq!(
+            result.activity_results[0].rule_results[0].explanation,
+            "Flag 'new_review_path' is off"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sibling_conditions() {
+        use crate::engine::{CorrelationIndex, InMemoryStorage, WorkflowStorage};
+
+        let correlations = CorrelationIndex::new();
+        let storage = InMemoryStorage::default()
+            .with_correlation_index(correlations.clone())
+            .unwrap();
+        let engine = RulesEngine::new().with_correlation_index(correlations);
+
+        let mut order = Resource::new("order", StateId::from("open"));
+        order
+            .metadata
+            .insert("order_id".to_string(), serde_json::json!("o-1"));
+        let order = storage.create_resource(order).await.unwrap();
+        let mut items = Vec::new();
+        for _ in 0..3 {
+            let mut item = Resource::new("line_item", StateId::from("pending"));
+            item.metadata
+                .insert("order_id".to_string(), serde_json::json!("o-1"));
+            items.push(storage.create_resource(item).await.unwrap());
+        }
+
+        let close = ActivityDefinition::with_rules(
+            "close",
+            vec!["open"],
+            "closed",
+            vec![
+                Rule::siblings_in_state("items_validated", "order_id", "validated")
+                    .in_sibling_workflow("line_item"),
+            ],
+        );
+        let quorum = ActivityDefinition::with_rules(
+            "expedite",
+            vec!["open"],
+            "expedited",
+            vec![
+                Rule::siblings_in_state("two_validated", "order_id", "validated")
+                    .in_sibling_workflow("line_item")
+                    .at_least(2),
+            ],
+        );
+        assert!(!engine.can_execute_activity(&order, &close));
+
+        for item in items.iter_mut().take(2) {
+            item.state = StateId::from("validated");
+            storage.update_resource(item.clone()).await.unwrap();
+        }
+        assert!(engine.can_execute_activity(&order, &quorum));
+        assert!(!engine.can_execute_activity(&order, &close));
+
+        items[2].state = StateId::from("validated");
+        storage.update_resource(items[2].clone()).await.unwrap();
+        assert!(engine.can_execute_activity(&order, &close));
+        assert_eq!(
+            storage
+                .correlated_resources("order_id", "o-1")
+                .await
+                .unwrap()
+                .len(),
+            4
+        );
+
+        let workflow = WorkflowDefinition::new(
+            "order",
+            "Order",
+            vec![StateId::from("open"), StateId::from("closed")],
+            vec![close],
+            StateId::from("open"),
+        );
+        let result = engine.evaluate_all_activities(&order, &workflow);
+        assert_eq!(
+            result.activity_results[0].rule_results[0].explanation,
+            "3 of 3 siblings by 'order_id' are in state 'validated' (all required)"
+        );
+    }
 }
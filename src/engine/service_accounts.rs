@@ -0,0 +1,367 @@
+// Service accounts for CI systems and external workers
+// Identities limited to some workflows and operations, authenticated by short-lived tokens
+
+//! # Service Accounts
+//!
+//! A [`ServiceAccount`] is an identity for a machine rather than a person: a CI pipeline
+//! creating resources, or an external worker executing one activity. Each account is
+//! scoped to the workflows it may touch and the [`ServiceOperation`]s it may perform
+//! there, and authenticates with bearer tokens that expire after the account's token
+//! lifetime (an hour by default).
+//!
+//! Tokens are minted through the admin API and only their SHA-256 digest is kept, so a
+//! secret is shown once. Rotation is automatic: once a token has used up
+//! three quarters of its lifetime, the next request it authenticates mints its
+//! successor, which the server hands back in the [`ROTATED_TOKEN_HEADER`] response
+//! header. The old token keeps
+//! working until it expires, so workers switch over without a gap. Every
+//! authentication records when the token and its account were last used.
+//!
+//! Like maintenance mode, the registry is process-wide: the admin API mints tokens
+//! that the GraphQL server accepts.
+
+use chrono::{DateTime, Duration, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use uuid::Uuid;
+
+lazy_static::lazy_static! {
+    static ref GLOBAL: ServiceAccounts = ServiceAccounts::new();
+}
+
+/// Prefix of service account tokens
+pub const SERVICE_TOKEN_PREFIX: &str = "cbsa-";
+
+/// Response header carrying a token's successor when a request rotated it
+pub const ROTATED_TOKEN_HEADER: &str = "x-rotated-service-token";
+
+/// Token lifetime of accounts that do not set one
+pub const DEFAULT_TOKEN_TTL_SECS: u64 = 3600;
+
+/// Longest token lifetime an account may set
+pub const MAX_TOKEN_TTL_SECS: u64 = 86_400;
+
+/// Operations a service account may be allowed to perform on its workflows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceOperation {
+    /// Read workflow definitions and resources
+    ReadResources,
+    /// Create resources
+    CreateResources,
+    /// Execute activities on resources
+    ExecuteActivities,
+    /// Update resource metadata
+    UpdateMetadata,
+}
+
+/// A machine identity limited to some workflows and operations
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServiceAccount {
+    pub id: String,
+    pub name: String,
+    /// Workflows the account may act on
+    pub workflow_ids: Vec<String>,
+    /// What the account may do on those workflows
+    pub operations: Vec<ServiceOperation>,
+    /// Lifetime of the account's tokens
+    pub token_ttl_secs: u64,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl ServiceAccount {
+    /// An account allowed `operations` on `workflow_ids`, with the default token lifetime
+    pub fn new(
+        name: impl Into<String>,
+        workflow_ids: Vec<String>,
+        operations: Vec<ServiceOperation>,
+    ) -> Self {
+        Self {
+            id: format!("sa_{}", &Uuid::new_v4().simple().to_string()[..16]),
+            name: name.into(),
+            workflow_ids,
+            operations,
+            token_ttl_secs: DEFAULT_TOKEN_TTL_SECS,
+            created_at: Utc::now(),
+            last_used_at: None,
+        }
+    }
+
+    /// Whether the account may perform `operation` on resources of `workflow_id`
+    pub fn allows(&self, workflow_id: &str, operation: ServiceOperation) -> bool {
+        self.operations.contains(&operation) && self.workflow_ids.iter().any(|id| id == workflow_id)
+    }
+}
+
+/// A token issued to a service account, without its secret
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServiceToken {
+    pub token_id: String,
+    pub account_id: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<DateTime<Utc>>,
+    /// The token minted to replace this one, once it has been rotated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotated_to: Option<String>,
+}
+
+impl ServiceToken {
+    /// Whether the token has used up enough of its lifetime to be rotated at `now`
+    fn due_for_rotation(&self, now: DateTime<Utc>) -> bool {
+        let lifetime = self.expires_at - self.created_at;
+        now >= self.created_at + lifetime * 3 / 4
+    }
+}
+
+/// A newly minted token together with its secret, which is not retrievable later
+#[derive(Debug, Clone, Serialize)]
+pub struct MintedToken {
+    #[serde(flatten)]
+    pub token: ServiceToken,
+    /// The bearer token
+    pub secret: String,
+}
+
+/// The account a token authenticated, and the token's successor if it was rotated
+#[derive(Debug, Clone)]
+pub struct ServicePrincipal {
+    pub account: ServiceAccount,
+    pub token_id: String,
+    pub rotated: Option<MintedToken>,
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    accounts: HashMap<String, ServiceAccount>,
+    /// Token digest -> token
+    tokens: HashMap<String, ServiceToken>,
+}
+
+impl Registry {
+    fn mint(&mut self, account_id: &str, now: DateTime<Utc>) -> Option<MintedToken> {
+        let account = self.accounts.get(account_id)?;
+        let bytes: [u8; 24] = rand::thread_rng().gen();
+        let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let secret = format!("{}{}", SERVICE_TOKEN_PREFIX, hex);
+        let token = ServiceToken {
+            token_id: format!("tok_{}", &Uuid::new_v4().simple().to_string()[..16]),
+            account_id: account.id.clone(),
+            created_at: now,
+            expires_at: now + Duration::seconds(account.token_ttl_secs as i64),
+            last_used_at: None,
+            rotated_to: None,
+        };
+        self.tokens.insert(digest(&secret), token.clone());
+        Some(MintedToken { token, secret })
+    }
+
+    fn purge_expired(&mut self, now: DateTime<Utc>) {
+        self.tokens.retain(|_, token| token.expires_at > now);
+    }
+}
+
+/// SHA-256 hex digest under which a token is stored
+fn digest(secret: &str) -> String {
+    Sha256::digest(secret.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Shared registry of service accounts and their tokens
+///
+/// Clones read and update the same registry.
+#[derive(Debug, Clone, Default)]
+pub struct ServiceAccounts {
+    registry: Arc<RwLock<Registry>>,
+}
+
+impl ServiceAccounts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The process-wide registry shared by the admin API and the GraphQL server
+    pub fn global() -> Self {
+        GLOBAL.clone()
+    }
+
+    /// Register an account, returning `false` when one with the same ID exists
+    pub fn create(&self, account: ServiceAccount) -> bool {
+        let mut registry = self.registry.write().unwrap();
+        if registry.accounts.contains_key(&account.id) {
+            return false;
+        }
+        registry.accounts.insert(account.id.clone(), account);
+        true
+    }
+
+    pub fn get(&self, account_id: &str) -> Option<ServiceAccount> {
+        self.registry
+            .read()
+            .unwrap()
+            .accounts
+            .get(account_id)
+            .cloned()
+    }
+
+    /// All accounts, by ID
+    pub fn list(&self) -> Vec<ServiceAccount> {
+        let mut accounts: Vec<ServiceAccount> = self
+            .registry
+            .read()
+            .unwrap()
+            .accounts
+            .values()
+            .cloned()
+            .collect();
+        accounts.sort_by(|a, b| a.id.cmp(&b.id));
+        accounts
+    }
+
+    /// Remove an account and revoke all of its tokens
+    pub fn remove(&self, account_id: &str) -> Option<ServiceAccount> {
+        let mut registry = self.registry.write().unwrap();
+        let account = registry.accounts.remove(account_id)?;
+        registry
+            .tokens
+            .retain(|_, token| token.account_id != account_id);
+        Some(account)
+    }
+
+    /// Mint a token for an account; `None` if the account does not exist
+    pub fn mint(&self, account_id: &str) -> Option<MintedToken> {
+        let now = Utc::now();
+        let mut registry = self.registry.write().unwrap();
+        registry.purge_expired(now);
+        registry.mint(account_id, now)
+    }
+
+    /// An account's unexpired tokens, oldest first
+    pub fn tokens(&self, account_id: &str) -> Vec<ServiceToken> {
+        let now = Utc::now();
+        let mut tokens: Vec<ServiceToken> = self
+            .registry
+            .read()
+            .unwrap()
+            .tokens
+            .values()
+            .filter(|token| token.account_id == account_id && token.expires_at > now)
+            .cloned()
+            .collect();
+        tokens.sort_by_key(|token| token.created_at);
+        tokens
+    }
+
+    /// Revoke one of an account's tokens, returning `false` if it has no such token
+    pub fn revoke(&self, account_id: &str, token_id: &str) -> bool {
+        let mut registry = self.registry.write().unwrap();
+        let before = registry.tokens.len();
+        registry
+            .tokens
+            .retain(|_, token| !(token.account_id == account_id && token.token_id == token_id));
+        registry.tokens.len() < before
+    }
+
+    /// The account an unexpired token belongs to, recording its use and minting its
+    /// successor when it is due for rotation; `None` for unknown or expired tokens
+    pub fn authenticate(&self, secret: &str) -> Option<ServicePrincipal> {
+        self.authenticate_at(secret, Utc::now())
+    }
+
+    fn authenticate_at(&self, secret: &str, now: DateTime<Utc>) -> Option<ServicePrincipal> {
+        let key = digest(secret);
+        let mut registry = self.registry.write().unwrap();
+        let token = registry.tokens.get_mut(&key)?;
+        if token.expires_at <= now {
+            registry.tokens.remove(&key);
+            return None;
+        }
+        token.last_used_at = Some(now);
+        let token = token.clone();
+
+        let account = registry.accounts.get_mut(&token.account_id)?;
+        account.last_used_at = Some(now);
+        let account = account.clone();
+
+        let rotated = if token.rotated_to.is_none() && token.due_for_rotation(now) {
+            let successor = registry.mint(&account.id, now)?;
+            if let Some(token) = registry.tokens.get_mut(&key) {
+                token.rotated_to = Some(successor.token.token_id.clone());
+            }
+            Some(successor)
+        } else {
+            None
+        };
+
+        Some(ServicePrincipal {
+            account,
+            token_id: token.token_id,
+            rotated,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens_authenticate_rotate_and_expire() {
+        let accounts = ServiceAccounts::new();
+        let account = ServiceAccount::new(
+            "ci",
+            vec!["deploy".to_string()],
+            vec![ServiceOperation::CreateResources],
+        );
+        assert!(account.allows("deploy", ServiceOperation::CreateResources));
+        assert!(!account.allows("deploy", ServiceOperation::ExecuteActivities));
+        assert!(!account.allows("billing", ServiceOperation::CreateResources));
+        let account_id = account.id.clone();
+        assert!(accounts.create(account));
+        assert!(accounts.mint("unknown").is_none());
+
+        let minted = accounts.mint(&account_id).unwrap();
+        assert!(minted.secret.starts_with(SERVICE_TOKEN_PREFIX));
+        assert!(accounts.authenticate("cbsa-guess").is_none());
+
+        // A fresh token authenticates without rotating and records its use
+        let start = minted.token.created_at;
+        let principal = accounts.authenticate_at(&minted.secret, start).unwrap();
+        assert_eq!(principal.account.id, account_id);
+        assert!(principal.rotated.is_none());
+        assert_eq!(accounts.get(&account_id).unwrap().last_used_at, Some(start));
+
+        // Late in its life it mints a successor once, and keeps working until it expires
+        let late = start + Duration::seconds(DEFAULT_TOKEN_TTL_SECS as i64 * 4 / 5);
+        let rotated = accounts
+            .authenticate_at(&minted.secret, late)
+            .unwrap()
+            .rotated
+            .unwrap();
+        assert!(accounts
+            .authenticate_at(&minted.secret, late)
+            .unwrap()
+            .rotated
+            .is_none());
+        assert!(accounts.authenticate_at(&rotated.secret, late).is_some());
+        assert_eq!(accounts.tokens(&account_id).len(), 2);
+
+        let expired = start + Duration::seconds(DEFAULT_TOKEN_TTL_SECS as i64);
+        assert!(accounts.authenticate_at(&minted.secret, expired).is_none());
+
+        assert!(accounts.revoke(&account_id, &rotated.token.token_id));
+        assert!(accounts.authenticate(&rotated.secret).is_none());
+
+        let other = accounts.mint(&account_id).unwrap();
+        accounts.remove(&account_id);
+        assert!(accounts.authenticate(&other.secret).is_none());
+    }
+}
@@ -0,0 +1,153 @@
+// Resource signals
+// Delivers named messages with payloads to resources, for rules to match on
+
+//! # Signals
+//!
+//! [`send_signal`] delivers a named signal with a JSON payload to a resource, from
+//! another resource or from an API caller. Signals let related workflow instances
+//! coordinate: an order waiting in `awaiting_payment` can have its `ship` activity
+//! guarded by a `SignalReceived` rule on `payment_settled`, which the payment's
+//! resource sends once it settles.
+//!
+//! Signals are kept in the resource's metadata under
+//! [`SIGNALS_KEY`](crate::models::SIGNALS_KEY), so storage needs nothing new and field
+//! conditions can read a payload as `signals.<name>.payload`. They belong to the state
+//! the resource is in when they arrive: the next activity moving the resource drops
+//! them. Paused resources still receive signals; cancelled ones refuse them. Every
+//! delivery is published on the event bus as a `SignalReceived` event.
+
+use chrono::Utc;
+use tracing::info;
+use uuid::Uuid;
+
+use super::events::EventBus;
+use super::storage::{update_resource_with_retry, WorkflowStorage};
+use crate::models::{LifecycleStatus, Resource, ResourceSignal};
+use crate::{CircuitBreakerError, Result};
+
+/// Times a delivery is retried when it races an activity on the resource
+const SIGNAL_ATTEMPTS: usize = 3;
+
+/// Deliver the signal `name` with `payload` to a resource, from the resource `sender`
+/// or, without one, from outside the engine
+pub async fn send_signal<S: WorkflowStorage + ?Sized>(
+    storage: &S,
+    events: &EventBus,
+    resource_id: &Uuid,
+    name: &str,
+    payload: serde_json::Value,
+    sender: Option<Uuid>,
+) -> Result<Resource> {
+    if name.trim().is_empty() || name.contains('.') {
+        return Err(CircuitBreakerError::InvalidInput(format!(
+            "Invalid signal name '{}': it must be non-empty and contain no '.'",
+            name
+        )));
+    }
+    if let Some(sender) = &sender {
+        if storage.get_resource(sender).await?.is_none() {
+            return Err(CircuitBreakerError::NotFound(format!(
+                "Sending resource {}",
+                sender
+            )));
+        }
+    }
+
+    let signal = ResourceSignal {
+        payload: payload.clone(),
+        sender,
+        received_at: Utc::now(),
+    };
+    let updated = update_resource_with_retry(storage, resource_id, SIGNAL_ATTEMPTS, |resource| {
+        if resource.lifecycle_status() == LifecycleStatus::Cancelled {
+            return Err(CircuitBreakerError::ResourceHalted {
+                id: resource.id.to_string(),
+                status: LifecycleStatus::Cancelled.as_str().to_string(),
+            });
+        }
+        resource.receive_signal(name, signal.clone());
+        Ok(())
+    })
+    .await?;
+
+    info!(
+        "Resource {} received signal '{}' in state {}",
+        updated.id,
+        name,
+        updated.current_state()
+    );
+    events.emit_signal_received(&updated, name, payload).await?;
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::storage::InMemoryStorage;
+    use crate::models::{ActivityId, EventType, Rule, StateId};
+
+    #[tokio::test]
+    async fn test_send_signal() {
+        let storage = InMemoryStorage::default();
+        let events = EventBus::new();
+        let mut received = events.subscribe();
+        let order = storage
+            .create_resource(Resource::new("orders", StateId::from("awaiting_payment")))
+            .await
+            .unwrap();
+        let payment = storage
+            .create_resource(Resource::new("payments", StateId::from("settled")))
+            .await
+            .unwrap();
+        let settled = Rule::signal_received("settled", "payment_settled");
+        assert!(!settled.evaluate(&order.metadata, &order.data));
+
+        let mut order = send_signal(
+            &storage,
+            &events,
+            &order.id,
+            "payment_settled",
+            serde_json::json!({"amount": 42}),
+            Some(payment.id),
+        )
+        .await
+        .unwrap();
+        let signal = &order.signals()["payment_settled"];
+        assert_eq!(signal.sender, Some(payment.id));
+        assert!(settled.evaluate(&order.metadata, &order.data));
+        assert!(
+            Rule::field_equals("paid", "signals.payment_settled.payload.amount", 42.into())
+                .evaluate(&order.metadata, &order.data)
+        );
+        assert!(matches!(
+            received.recv().await.unwrap().event_type,
+            EventType::SignalReceived { signal: Some(name), .. } if name == "payment_settled"
+        ));
+
+        // Signals belong to the state they arrived in
+        order.execute_activity(StateId::from("shipped"), ActivityId::from("ship"));
+        assert!(order.signals().is_empty());
+        assert!(!settled.evaluate(&order.metadata, &order.data));
+
+        assert!(send_signal(
+            &storage,
+            &events,
+            &order.id,
+            "a.b",
+            serde_json::Value::Null,
+            None
+        )
+        .await
+        .is_err());
+        assert!(send_signal(
+            &storage,
+            &events,
+            &order.id,
+            "payment_settled",
+            serde_json::Value::Null,
+            Some(Uuid::new_v4()),
+        )
+        .await
+        .is_err());
+    }
+}
@@ -0,0 +1,422 @@
+// State SLA tracking
+// Flags resources overstaying a state's SLA and escalates the breaches
+
+//! # State SLAs
+//!
+//! A state can declare a [`StateSla`]: how long a resource should sit in it at most.
+//! [`SlaMonitor`] is the engine's tick loop tracking time-in-state against those
+//! durations. A resource still in a state past its SLA is breaching; the monitor flags
+//! the breach once per stay:
+//!
+//! - the breach is recorded in the resource's metadata under [`SLA_BREACH_KEY`]
+//! - an SLA breached event is published on the global [`EventBus`]
+//! - the SLA's escalation activity, if any, is executed like any other execution,
+//!   splitting, joining or moving a branch - rules are skipped, as for a deadline timer,
+//!   but a full target state holds the escalation back
+//! - the SLA's webhook, if any, receives the breach as JSON
+//!
+//! Time in state counts from the resource's last activity, as for timers, and nothing
+//! is flagged while maintenance mode holds writes back. [`sla_metrics`] summarizes how
+//! a workflow's resources keep to the SLAs, from both current stays and the stays
+//! recorded in their history.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use super::events::EventBus;
+use super::rules::RulesEngine;
+use super::storage::WorkflowStorage;
+use crate::models::{
+    ActivityDefinition, ActivityId, Resource, StateId, StateSla, WorkflowDefinition,
+};
+use crate::{CircuitBreakerError, MaintenanceMode, Result};
+
+/// How often the server's monitor looks for SLA breaches
+pub const SLA_TICK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Metadata key holding the latest SLA breach of a resource, and history event data key
+/// of escalations
+pub const SLA_BREACH_KEY: &str = "sla_breach";
+
+/// A resource that overstayed a state's SLA
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SlaBreach {
+    pub resource_id: Uuid,
+    pub workflow_id: String,
+    pub state: StateId,
+    pub max_secs: u64,
+    /// When the resource entered the state
+    pub entered_at: DateTime<Utc>,
+    /// When the SLA ran out
+    pub breached_at: DateTime<Utc>,
+    /// Activity the breach was escalated with, if it could be executed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escalated_with: Option<ActivityId>,
+}
+
+/// How a workflow's resources keep to the SLA of one state
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StateSlaMetrics {
+    pub state: StateId,
+    pub max_secs: u64,
+    /// Resources in the state now
+    pub in_state: usize,
+    /// Resources in the state now and past its SLA
+    pub breaching: usize,
+    /// Longest time a resource in the state now has spent in it, in seconds
+    pub longest_current_secs: Option<i64>,
+    /// Stays in the state that have ended, from resource histories
+    pub completed_stays: usize,
+    /// Completed stays that took longer than the SLA
+    pub completed_breaches: usize,
+    /// Average length of the completed stays, in seconds
+    pub average_stay_secs: Option<f64>,
+}
+
+/// Tick loop flagging and escalating SLA breaches
+#[derive(Clone)]
+pub struct SlaMonitor {
+    storage: Arc<dyn WorkflowStorage>,
+    rules: Arc<RulesEngine>,
+    events: EventBus,
+    http: reqwest::Client,
+}
+
+impl SlaMonitor {
+    pub fn new(storage: Arc<dyn WorkflowStorage>, rules: Arc<RulesEngine>) -> Self {
+        Self {
+            storage,
+            rules,
+            events: EventBus::global(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Publish breach events on `events` instead of the global bus
+    pub fn with_event_bus(mut self, events: EventBus) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Flag every breach not flagged yet at `now`
+    ///
+    /// A resource storage refuses to update is left for a later call.
+    pub async fn check(&self, now: DateTime<Utc>) -> Result<Vec<SlaBreach>> {
+        let mut breaches = Vec::new();
+        for workflow in self.storage.list_workflows().await? {
+            if workflow.state_slas.is_empty() {
+                continue;
+            }
+            for resource in self.storage.list_resources(Some(&workflow.id)).await? {
+                if resource.is_halted() {
+                    continue;
+                }
+                let Some(sla) = workflow.state_sla(&resource.state) else {
+                    continue;
+                };
+                let entered_at = resource.entered_state_at();
+                let breached_at = entered_at + Duration::seconds(sla.max_secs as i64);
+                if breached_at > now || is_flagged(&resource, entered_at) {
+                    continue;
+                }
+                let breach = SlaBreach {
+                    resource_id: resource.id,
+                    workflow_id: workflow.id.clone(),
+                    state: resource.state.clone(),
+                    max_secs: sla.max_secs,
+                    entered_at,
+                    breached_at,
+                    escalated_with: None,
+                };
+                let resource_id = resource.id;
+                match self.flag(&workflow, sla, resource, breach).await {
+                    Ok(breach) => breaches.push(breach),
+                    Err(e) => warn!(
+                        "Failed to flag SLA breach of resource {} in workflow '{}': {}",
+                        resource_id, workflow.id, e
+                    ),
+                }
+            }
+        }
+        Ok(breaches)
+    }
+
+    async fn flag(
+        &self,
+        workflow: &WorkflowDefinition,
+        sla: &StateSla,
+        mut resource: Resource,
+        mut breach: SlaBreach,
+    ) -> Result<SlaBreach> {
+        if let Some(activity) = self.escalation(workflow, sla, &resource).await? {
+            breach.escalated_with = Some(activity.id.clone());
+            super::parallel::execute(&mut resource, activity)?;
+            if let Some(event) = resource.history.last_mut() {
+                let state = serde_json::json!(breach.state);
+                match &mut event.data {
+                    Some(serde_json::Value::Object(data)) => {
+                        data.insert(SLA_BREACH_KEY.to_string(), state);
+                    }
+                    data => *data = Some(serde_json::json!({ SLA_BREACH_KEY: state })),
+                }
+            }
+        }
+        let breach_json = serde_json::to_value(&breach)?;
+        resource.set_metadata(SLA_BREACH_KEY, breach_json.clone());
+        let updated = self.storage.update_resource(resource).await?;
+        if breach.escalated_with.is_some() {
+            self.rules.capacity_queues().withdraw(&updated.id);
+        }
+
+        warn!(
+            "⏱️ Resource {} breached the {}s SLA of state '{}'{}",
+            updated.id,
+            breach.max_secs,
+            breach.state.as_str(),
+            breach
+                .escalated_with
+                .as_ref()
+                .map(|a| format!(", escalated with '{}'", a.as_str()))
+                .unwrap_or_default()
+        );
+        self.events
+            .emit_sla_breached(&updated, breach_json.clone())
+            .await?;
+        if let Some(url) = &sla.webhook_url {
+            self.notify(url.clone(), breach_json);
+        }
+        Ok(breach)
+    }
+
+    /// The SLA's escalation activity, when it can move the resource now
+    async fn escalation<'a>(
+        &self,
+        workflow: &'a WorkflowDefinition,
+        sla: &StateSla,
+        resource: &Resource,
+    ) -> Result<Option<&'a ActivityDefinition>> {
+        let Some(activity) = sla.escalation_activity.as_ref().and_then(|id| {
+            workflow
+                .activities
+                .iter()
+                .find(|a| &a.id == id && a.can_execute_on(resource))
+        }) else {
+            return Ok(None);
+        };
+        let to_state = self.rules.target_state(resource, activity);
+        if workflow.state_capacity(to_state).is_some() {
+            let occupancy = self.storage.count_resources_by_state(&workflow.id).await?;
+            let check = self
+                .rules
+                .check_capacity(resource, activity, workflow, &occupancy);
+            if !check.is_admitted() {
+                info!(
+                    "SLA escalation '{}' waits for room in '{}' for resource {}",
+                    activity.id.as_str(),
+                    to_state.as_str(),
+                    resource.id
+                );
+                return Ok(None);
+            }
+        }
+        Ok(Some(activity))
+    }
+
+    /// POST a breach to a webhook without holding the tick loop up
+    fn notify(&self, url: String, breach: serde_json::Value) {
+        let http = self.http.clone();
+        tokio::spawn(async move {
+            let result = http
+                .post(&url)
+                .json(&breach)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            if let Err(e) = result {
+                warn!("Failed to deliver SLA breach to {}: {}", url, e);
+            }
+        });
+    }
+
+    /// Spawn the tick loop, looking for breaches every `interval`
+    pub fn spawn(self, interval: std::time::Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if MaintenanceMode::global().is_enabled() {
+                    continue;
+                }
+                if let Err(e) = self.check(Utc::now()).await {
+                    warn!("Failed to check state SLAs: {}", e);
+                }
+            }
+        })
+    }
+}
+
+/// Whether the breach of the stay that began at `entered_at` was flagged already
+fn is_flagged(resource: &Resource, entered_at: DateTime<Utc>) -> bool {
+    resource
+        .get_metadata(SLA_BREACH_KEY)
+        .and_then(|breach| serde_json::from_value::<SlaBreach>(breach.clone()).ok())
+        .is_some_and(|breach| breach.state == resource.state && breach.entered_at == entered_at)
+}
+
+/// The states a resource went through and how long it stayed in each, excluding the
+/// state it is in now
+pub(crate) fn completed_stays(resource: &Resource) -> Vec<(StateId, Duration)> {
+    let mut state = resource
+        .history
+        .first()
+        .map_or_else(|| resource.state.clone(), |event| event.from.clone());
+    let mut since = resource.created_at;
+    let mut stays = Vec::new();
+    // Branch moves of a split resource leave its own state alone
+    for event in resource.history.iter().filter(|event| {
+        event
+            .data
+            .as_ref()
+            .and_then(|data| data.get("branch"))
+            .is_none()
+    }) {
+        stays.push((state, event.timestamp - since));
+        state = event.to.clone();
+        since = event.timestamp;
+    }
+    stays
+}
+
+/// SLA metrics of every state of a workflow that has an SLA, as of `now`
+pub async fn sla_metrics<S: WorkflowStorage + ?Sized>(
+    storage: &S,
+    workflow_id: &str,
+    now: DateTime<Utc>,
+) -> Result<Vec<StateSlaMetrics>> {
+    let workflow = storage.get_workflow(workflow_id).await?.ok_or_else(|| {
+        CircuitBreakerError::WorkflowNotFound {
+            id: workflow_id.to_string(),
+        }
+    })?;
+    let resources = storage.list_resources(Some(workflow_id)).await?;
+
+    let mut metrics: Vec<StateSlaMetrics> = workflow
+        .state_slas
+        .iter()
+        .map(|(state, sla)| {
+            let max = Duration::seconds(sla.max_secs as i64);
+            let current: Vec<Duration> = resources
+                .iter()
+                .filter(|r| &r.state == state)
+                .map(|r| now - r.entered_state_at())
+                .collect();
+            let completed: Vec<Duration> = resources
+                .iter()
+                .flat_map(completed_stays)
+                .filter(|(stayed_in, _)| stayed_in == state)
+                .map(|(_, stay)| stay)
+                .collect();
+            StateSlaMetrics {
+                state: state.clone(),
+                max_secs: sla.max_secs,
+                in_state: current.len(),
+                breaching: current.iter().filter(|stay| **stay > max).count(),
+                longest_current_secs: current.iter().max().map(|stay| stay.num_seconds()),
+                completed_stays: completed.len(),
+                completed_breaches: completed.iter().filter(|stay| **stay > max).count(),
+                average_stay_secs: (!completed.is_empty()).then(|| {
+                    completed
+                        .iter()
+                        .map(|stay| stay.num_milliseconds() as f64 / 1000.0)
+                        .sum::<f64>()
+                        / completed.len() as f64
+                }),
+            }
+        })
+        .collect();
+    metrics.sort_by(|a, b| a.state.as_str().cmp(b.state.as_str()));
+    Ok(metrics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::storage::InMemoryStorage;
+    use crate::models::TriggerEvent;
+
+    fn support_workflow() -> WorkflowDefinition {
+        WorkflowDefinition::new(
+            "support",
+            "Support",
+            vec![
+                StateId::from("triage"),
+                StateId::from("escalated"),
+                StateId::from("resolved"),
+            ],
+            vec![
+                ActivityDefinition::new("resolve", vec!["triage", "escalated"], "resolved"),
+                ActivityDefinition::new("escalate", vec!["triage"], "escalated"),
+            ],
+            "triage",
+        )
+        .with_state_sla("triage", StateSla::new(3600).escalate_with("escalate"))
+        .with_state_sla("escalated", StateSla::new(600))
+    }
+
+    #[tokio::test]
+    async fn test_breaches_are_flagged_and_escalated_once() {
+        let storage: Arc<dyn WorkflowStorage> = Arc::new(InMemoryStorage::default());
+        let workflow = storage.create_workflow(support_workflow()).await.unwrap();
+        assert!(workflow.validate().is_ok());
+        let ticket = storage
+            .create_resource(Resource::new(&workflow.id, StateId::from("triage")))
+            .await
+            .unwrap();
+        let events = EventBus::new();
+        let mut received = events.subscribe();
+        let monitor =
+            SlaMonitor::new(storage.clone(), Arc::new(RulesEngine::new())).with_event_bus(events);
+
+        let start = ticket.created_at;
+        assert!(monitor.check(start).await.unwrap().is_empty());
+
+        let breaches = monitor
+            .check(start + Duration::seconds(3601))
+            .await
+            .unwrap();
+        assert_eq!(breaches.len(), 1);
+        assert_eq!(
+            breaches[0].escalated_with,
+            Some(ActivityId::from("escalate"))
+        );
+        let stored = storage.get_resource(&ticket.id).await.unwrap().unwrap();
+        assert_eq!(stored.state, StateId::from("escalated"));
+        assert!(stored.get_metadata(SLA_BREACH_KEY).is_some());
+        let event: TriggerEvent = received.try_recv().unwrap();
+        assert_eq!(event.token_id, Some(ticket.id));
+
+        // The escalated state starts its own SLA from the escalation
+        let escalated_at = stored.entered_state_at();
+        assert!(monitor
+            .check(escalated_at + Duration::seconds(60))
+            .await
+            .unwrap()
+            .is_empty());
+        let later = escalated_at + Duration::seconds(601);
+        assert_eq!(monitor.check(later).await.unwrap().len(), 1);
+        assert!(monitor.check(later).await.unwrap().is_empty());
+
+        let metrics = sla_metrics(storage.as_ref(), &workflow.id, later)
+            .await
+            .unwrap();
+        let states: Vec<_> = metrics
+            .iter()
+            .map(|m| (m.state.as_str(), m.in_state, m.breaching, m.completed_stays))
+            .collect();
+        assert_eq!(states, vec![("escalated", 1, 1, 0), ("triage", 0, 0, 1)]);
+    }
+}
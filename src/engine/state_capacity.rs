@@ -0,0 +1,344 @@
+// Capacity queues for constrained states
+// Resources waiting for room in states that may only hold a limited number of resources
+
+//! # State Capacity
+//!
+//! A workflow can cap how many resources a state holds at once through its
+//! [`StateCapacity`] constraints, modelling real resources such as deployment slots or
+//! reviewers. When an activity targets a full state the resource is not moved; it joins
+//! that state's queue in [`CapacityQueues`] and is let in once a resource has left the
+//! state and the resources queued ahead of it have entered.
+//!
+//! Occupancy comes from storage (`count_resources_by_state`), so callers pass it in; the
+//! queues only remember who is waiting. A resource that executes any activity leaves
+//! every queue it was in, so a resource that took another path does not hold up those
+//! behind it. A waiting resource that stops asking to enter, say because it was deleted
+//! or moved on outside the engine, is dropped from the queue once it has not asked for
+//! [`QUEUE_ENTRY_TIMEOUT`].
+
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::models::{CapacityQueueOrder, Resource, StateCapacity, StateId, WorkflowDefinition};
+
+lazy_static::lazy_static! {
+    static ref GLOBAL: CapacityQueues = CapacityQueues::new();
+}
+
+/// How long a queued resource keeps its place without asking to enter again
+pub const QUEUE_ENTRY_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Queues keyed by workflow and state
+type QueueKey = (String, StateId);
+
+/// A resource waiting for room in a state
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueuedResource {
+    pub resource_id: Uuid,
+    /// Value of the state's priority field when the resource last asked to enter
+    pub priority: f64,
+    pub enqueued_at: DateTime<Utc>,
+    /// When the resource last asked to enter
+    pub checked_at: DateTime<Utc>,
+    /// Order of arrival across all queues
+    sequence: u64,
+}
+
+impl QueuedResource {
+    fn is_expired(&self, timeout: Duration, now: DateTime<Utc>) -> bool {
+        (now - self.checked_at)
+            .to_std()
+            .is_ok_and(|waited| waited > timeout)
+    }
+}
+
+/// Outcome of asking to move a resource into a state
+#[derive(Debug, Clone, PartialEq)]
+pub enum CapacityCheck {
+    /// The state has room for the resource, or no capacity constraint
+    Admitted,
+    /// The state is full, or has room only for resources queued ahead of this one
+    Waiting {
+        /// Resources queued ahead of this one
+        ahead: usize,
+        occupancy: u64,
+        max_resources: u32,
+    },
+}
+
+impl CapacityCheck {
+    pub fn is_admitted(&self) -> bool {
+        matches!(self, CapacityCheck::Admitted)
+    }
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    queues: HashMap<QueueKey, Vec<QueuedResource>>,
+    next_sequence: u64,
+}
+
+/// Shared queues of resources waiting for capacity
+///
+/// Clones read and update the same queues.
+#[derive(Debug, Clone)]
+pub struct CapacityQueues {
+    state: Arc<RwLock<QueueState>>,
+    entry_timeout: Duration,
+}
+
+impl Default for CapacityQueues {
+    fn default() -> Self {
+        Self {
+            state: Arc::default(),
+            entry_timeout: QUEUE_ENTRY_TIMEOUT,
+        }
+    }
+}
+
+impl CapacityQueues {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drop queued resources that have not asked to enter for `timeout`
+    pub fn with_entry_timeout(mut self, timeout: Duration) -> Self {
+        self.entry_timeout = timeout;
+        self
+    }
+
+    /// The process-wide queues consulted by the rules engine
+    pub fn global() -> Self {
+        GLOBAL.clone()
+    }
+
+    /// Ask to move a resource into `state`, which currently holds `occupancy` resources
+    ///
+    /// An admitted resource leaves the state's queue; a waiting one is queued, or keeps
+    /// its place when it already was.
+    pub fn admit(
+        &self,
+        workflow: &WorkflowDefinition,
+        state: &StateId,
+        resource: &Resource,
+        occupancy: u64,
+    ) -> CapacityCheck {
+        let capacity = match workflow.state_capacity(state) {
+            // Moving within the state does not change its occupancy
+            Some(capacity) if resource.state != *state => capacity,
+            _ => return CapacityCheck::Admitted,
+        };
+
+        let mut guard = self.state.write().unwrap();
+        let QueueState {
+            queues,
+            next_sequence,
+        } = &mut *guard;
+        let key = (workflow.id.clone(), state.clone());
+        let queue = queues.entry(key.clone()).or_default();
+
+        let now = Utc::now();
+        let priority = priority_of(capacity, resource);
+        match queue.iter_mut().find(|q| q.resource_id == resource.id) {
+            Some(queued) => {
+                queued.priority = priority;
+                queued.checked_at = now;
+            }
+            None => {
+                *next_sequence += 1;
+                queue.push(QueuedResource {
+                    resource_id: resource.id,
+                    priority,
+                    enqueued_at: now,
+                    checked_at: now,
+                    sequence: *next_sequence,
+                });
+            }
+        }
+        queue.retain(|q| !q.is_expired(self.entry_timeout, now));
+        sort_queue(queue, capacity.queue_order);
+
+        let free = (capacity.max_resources as u64).saturating_sub(occupancy) as usize;
+        let position = queue
+            .iter()
+            .position(|q| q.resource_id == resource.id)
+            .expect("resource was just queued");
+        if position < free {
+            queue.remove(position);
+            if queue.is_empty() {
+                queues.remove(&key);
+            }
+            CapacityCheck::Admitted
+        } else {
+            CapacityCheck::Waiting {
+                ahead: position,
+                occupancy,
+                max_resources: capacity.max_resources,
+            }
+        }
+    }
+
+    /// Remove a resource from every queue it waits in
+    pub fn withdraw(&self, resource_id: &Uuid) {
+        self.state.write().unwrap().queues.retain(|_, queue| {
+            queue.retain(|q| q.resource_id != *resource_id);
+            !queue.is_empty()
+        });
+    }
+
+    /// Resources waiting for room in a state, in the order they will be let in
+    pub fn waiting(&self, workflow: &WorkflowDefinition, state: &StateId) -> Vec<QueuedResource> {
+        let mut queue = self
+            .state
+            .read()
+            .unwrap()
+            .queues
+            .get(&(workflow.id.clone(), state.clone()))
+            .cloned()
+            .unwrap_or_default();
+        let now = Utc::now();
+        queue.retain(|q| !q.is_expired(self.entry_timeout, now));
+        if let Some(capacity) = workflow.state_capacity(state) {
+            sort_queue(&mut queue, capacity.queue_order);
+        }
+        queue
+    }
+}
+
+/// Value of the capacity's priority field in the resource's data; 0 when missing
+fn priority_of(capacity: &StateCapacity, resource: &Resource) -> f64 {
+    capacity
+        .priority_field
+        .as_deref()
+        .filter(|_| capacity.queue_order == CapacityQueueOrder::Priority)
+        .and_then(|field| {
+            resource
+                .data
+                .pointer(&format!("/{}", field.replace('.', "/")))
+        })
+        .and_then(serde_json::Value::as_f64)
+        .unwrap_or(0.0)
+}
+
+fn sort_queue(queue: &mut [QueuedResource], order: CapacityQueueOrder) {
+    match order {
+        CapacityQueueOrder::Fifo => queue.sort_by_key(|q| q.sequence),
+        CapacityQueueOrder::Lifo => queue.sort_by_key(|q| std::cmp::Reverse(q.sequence)),
+        CapacityQueueOrder::Priority => queue.sort_by(|a, b| {
+            b.priority
+                .total_cmp(&a.priority)
+                .then(a.sequence.cmp(&b.sequence))
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ActivityDefinition;
+
+    fn workflow(capacity: StateCapacity) -> WorkflowDefinition {
+        WorkflowDefinition::new(
+            "release",
+            "Release",
+            vec![StateId::from("ready"), StateId::from("deploying")],
+            vec![ActivityDefinition::new(
+                "deploy",
+                vec!["ready"],
+                "deploying",
+            )],
+            "ready",
+        )
+        .with_state_capacity("deploying", capacity)
+    }
+
+    fn resource(priority: i64) -> Resource {
+        let mut resource = Resource::new("release", StateId::from("ready"));
+        resource.data = serde_json::json!({"release": {"priority": priority}});
+        resource
+    }
+
+    #[test]
+    fn test_waiting_resources_enter_in_order() {
+        let queues = CapacityQueues::new();
+        let workflow = workflow(StateCapacity::new(2));
+        let deploying = StateId::from("deploying");
+        let (first, second, third) = (resource(0), resource(0), resource(0));
+
+        assert!(queues.admit(&workflow, &deploying, &first, 1).is_admitted());
+        assert_eq!(
+            queues.admit(&workflow, &deploying, &second, 2),
+            CapacityCheck::Waiting {
+                ahead: 0,
+                occupancy: 2,
+                max_resources: 2
+            }
+        );
+        assert!(!queues.admit(&workflow, &deploying, &third, 2).is_admitted());
+
+        // Room for one: only the longest waiting resource gets it
+        assert!(!queues.admit(&workflow, &deploying, &third, 1).is_admitted());
+        assert!(queues
+            .admit(&workflow, &deploying, &second, 1)
+            .is_admitted());
+        assert_eq!(queues.waiting(&workflow, &deploying).len(), 1);
+
+        queues.withdraw(&third.id);
+        assert!(queues.waiting(&workflow, &deploying).is_empty());
+
+        // Resources already in the state are never blocked
+        let mut inside = resource(0);
+        inside.state = deploying.clone();
+        assert!(queues
+            .admit(&workflow, &deploying, &inside, 2)
+            .is_admitted());
+    }
+
+    #[test]
+    fn test_queue_orders() {
+        let deploying = StateId::from("deploying");
+        let (low, high) = (resource(1), resource(9));
+
+        let queues = CapacityQueues::new();
+        let by_priority = workflow(StateCapacity::new(1).by_priority("release.priority"));
+        queues.admit(&by_priority, &deploying, &low, 1);
+        queues.admit(&by_priority, &deploying, &high, 1);
+        let waiting = queues.waiting(&by_priority, &deploying);
+        assert_eq!(waiting[0].resource_id, high.id);
+        assert_eq!(waiting[0].priority, 9.0);
+        assert!(!queues
+            .admit(&by_priority, &deploying, &low, 0)
+            .is_admitted());
+        assert!(queues
+            .admit(&by_priority, &deploying, &high, 0)
+            .is_admitted());
+
+        let queues = CapacityQueues::new();
+        let lifo = workflow(StateCapacity::new(1).lifo());
+        queues.admit(&lifo, &deploying, &low, 1);
+        queues.admit(&lifo, &deploying, &high, 1);
+        assert_eq!(queues.waiting(&lifo, &deploying)[0].resource_id, high.id);
+    }
+
+    #[test]
+    fn test_abandoned_entries_expire() {
+        let queues = CapacityQueues::new().with_entry_timeout(Duration::from_millis(20));
+        let workflow = workflow(StateCapacity::new(1));
+        let deploying = StateId::from("deploying");
+        let (abandoned, waiting) = (resource(0), resource(0));
+
+        assert!(!queues
+            .admit(&workflow, &deploying, &abandoned, 1)
+            .is_admitted());
+        std::thread::sleep(Duration::from_millis(40));
+
+        // The resource ahead never asked again, so it no longer holds the place
+        assert!(queues
+            .admit(&workflow, &deploying, &waiting, 0)
+            .is_admitted());
+        assert!(queues.waiting(&workflow, &deploying).is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+// Materialized counters for dashboards
+// Resource and execution counts kept up to date on every write instead of computed by scanning
+
+//! # State Counters
+//!
+//! Dashboards ask the same questions over and over: how many resources sit in each
+//! state of a workflow, how many agent executions are running or failed. Storage
+//! backends that keep a [`StateCounters`] adjust it on every create and transition, so
+//! those questions are answered without scanning resources.
+//!
+//! Counters are derived data. If they drift (a crash between a write and its counter
+//! update, data loaded behind the storage layer's back), `rebuild_resources` and
+//! `rebuild_executions` recompute them from the stored records.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::models::{AgentExecution, AgentExecutionStatus, Resource};
+
+/// Counter key of an execution status, as reported to dashboards
+pub fn status_key(status: &AgentExecutionStatus) -> &'static str {
+    match status {
+        AgentExecutionStatus::Pending => "pending",
+        AgentExecutionStatus::Running => "running",
+        AgentExecutionStatus::Completed => "completed",
+        AgentExecutionStatus::Failed => "failed",
+        AgentExecutionStatus::Timeout => "timeout",
+        AgentExecutionStatus::Cancelled => "cancelled",
+    }
+}
+
+/// Incrementally maintained resource and execution counts
+#[derive(Debug, Default)]
+pub struct StateCounters {
+    /// Workflow ID -> state -> number of resources
+    resources: RwLock<HashMap<String, HashMap<String, u64>>>,
+    /// Execution status -> number of executions
+    executions: RwLock<HashMap<&'static str, u64>>,
+}
+
+impl StateCounters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a resource stored for the first time
+    pub fn resource_added(&self, workflow_id: &str, state: &str) {
+        let mut resources = self.resources.write().unwrap();
+        *resources
+            .entry(workflow_id.to_string())
+            .or_default()
+            .entry(state.to_string())
+            .or_default() += 1;
+    }
+
+    /// Move a resource between states
+    pub fn resource_moved(&self, workflow_id: &str, from: &str, to: &str) {
+        if from == to {
+            return;
+        }
+        let mut resources = self.resources.write().unwrap();
+        let states = resources.entry(workflow_id.to_string()).or_default();
+        if let Some(count) = states.get_mut(from) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                states.remove(from);
+            }
+        }
+        *states.entry(to.to_string()).or_default() += 1;
+    }
+
+    /// Account for a stored resource replacing `previous`, if there was one
+    pub fn resource_stored(&self, previous: Option<&Resource>, resource: &Resource) {
+        match previous {
+            Some(previous) if previous.workflow_id == resource.workflow_id => self.resource_moved(
+                &resource.workflow_id,
+                previous.current_state(),
+                resource.current_state(),
+            ),
+            Some(previous) => {
+                self.resource_removed(&previous.workflow_id, previous.current_state());
+                self.resource_added(&resource.workflow_id, resource.current_state());
+            }
+            None => self.resource_added(&resource.workflow_id, resource.current_state()),
+        }
+    }
+
+    /// Stop counting a resource
+    pub fn resource_removed(&self, workflow_id: &str, state: &str) {
+        let mut resources = self.resources.write().unwrap();
+        if let Some(states) = resources.get_mut(workflow_id) {
+            if let Some(count) = states.get_mut(state) {
+                *count = count.saturating_sub(1);
+                if *count == 0 {
+                    states.remove(state);
+                }
+            }
+        }
+    }
+
+    /// Account for an execution stored with `status`, previously stored with `previous`
+    pub fn execution_stored(
+        &self,
+        previous: Option<&AgentExecutionStatus>,
+        status: &AgentExecutionStatus,
+    ) {
+        let mut executions = self.executions.write().unwrap();
+        if let Some(previous) = previous {
+            if previous == status {
+                return;
+            }
+            if let Some(count) = executions.get_mut(status_key(previous)) {
+                *count = count.saturating_sub(1);
+            }
+        }
+        *executions.entry(status_key(status)).or_default() += 1;
+    }
+
+    /// Number of resources of a workflow in each of its occupied states
+    pub fn resource_counts(&self, workflow_id: &str) -> HashMap<String, u64> {
+        self.resources
+            .read()
+            .unwrap()
+            .get(workflow_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Number of resources of a workflow in one state
+    pub fn resources_in_state(&self, workflow_id: &str, state: &str) -> u64 {
+        self.resources
+            .read()
+            .unwrap()
+            .get(workflow_id)
+            .and_then(|states| states.get(state))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Number of executions with each status seen so far
+    pub fn execution_counts(&self) -> HashMap<String, u64> {
+        self.executions
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, count)| **count > 0)
+            .map(|(status, count)| (status.to_string(), *count))
+            .collect()
+    }
+
+    /// Recompute resource counts from the stored resources
+    pub fn rebuild_resources<'a>(&self, stored: impl IntoIterator<Item = &'a Resource>) {
+        let mut counts: HashMap<String, HashMap<String, u64>> = HashMap::new();
+        for resource in stored {
+            *counts
+                .entry(resource.workflow_id.clone())
+                .or_default()
+                .entry(resource.current_state().to_string())
+                .or_default() += 1;
+        }
+        *self.resources.write().unwrap() = counts;
+    }
+
+    /// Recompute execution counts from the stored executions
+    pub fn rebuild_executions<'a>(&self, stored: impl IntoIterator<Item = &'a AgentExecution>) {
+        let mut counts: HashMap<&'static str, u64> = HashMap::new();
+        for execution in stored {
+            *counts.entry(status_key(&execution.status)).or_default() += 1;
+        }
+        *self.executions.write().unwrap() = counts;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::StateId;
+
+    #[test]
+    fn test_counters_follow_writes_and_rebuild() {
+        let counters = StateCounters::new();
+        let mut first = Resource::new("review", StateId::from("draft"));
+        let second = Resource::new("review", StateId::from("draft"));
+        counters.resource_stored(None, &first);
+        counters.resource_stored(None, &second);
+        assert_eq!(counters.resources_in_state("review", "draft"), 2);
+
+        let previous = first.clone();
+        first.state = StateId::from("published");
+        counters.resource_stored(Some(&previous), &first);
+        assert_eq!(counters.resources_in_state("review", "draft"), 1);
+        assert_eq!(counters.resources_in_state("review", "published"), 1);
+
+        counters.execution_stored(None, &AgentExecutionStatus::Pending);
+        counters.execution_stored(
+            Some(&AgentExecutionStatus::Pending),
+            &AgentExecutionStatus::Completed,
+        );
+        assert_eq!(
+            counters.execution_counts(),
+            HashMap::from([("completed".to_string(), 1)])
+        );
+
+        // Drifted counters are fixed by a rebuild
+        counters.resource_added("review", "draft");
+        counters.rebuild_resources([&first, &second]);
+        assert_eq!(
+            counters.resource_counts("review"),
+            HashMap::from([("draft".to_string(), 1), ("published".to_string(), 1)])
+        );
+    }
+}
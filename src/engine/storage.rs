@@ -12,7 +12,7 @@
 //! The storage layer follows the **Repository Pattern**:
 //! - **WorkflowStorage trait**: Defines the interface for all storage operations
 //! - **InMemoryStorage**: Default implementation for development/testing
-//! - **Future implementations**: NATS, PostgreSQL, Redis, etc.
+//! - **NATSStorage**, **PostgresStorage** and **RedisStorage**: Persistent implementations
 //!
 //! ## Async Design
 //!
@@ -39,10 +39,16 @@
 //! - Option types for nullable database results
 
 use std::collections::HashMap; // Hash map for key-value storage
+use std::sync::atomic::{AtomicBool, Ordering}; // Lock-free flag for cap warnings
+use tracing::warn;
 use uuid::Uuid; // UUID type for token IDs
 
+use super::correlation_index::CorrelationIndex; // Resources by correlation key value
+use super::memory_limits::{MemoryLimits, ResourceLru, SpillStore}; // Resource cap and spill file
+use super::state_counters::StateCounters; // Materialized dashboard counters
+use crate::models::correlation::correlation_value; // Correlation key values of resources
 use crate::models::{Resource, WorkflowDefinition}; // Domain models
-use crate::Result; // Custom Result type with our error types
+use crate::{CircuitBreakerError, Result}; // Custom Result and error types
 
 /// Storage trait for workflow and resource persistence
 ///
@@ -105,6 +111,28 @@ pub trait WorkflowStorage: Send + Sync {
     /// this might be paginated or filtered.
     async fn list_workflows(&self) -> Result<Vec<WorkflowDefinition>>;
 
+    /// List every stored version of a workflow definition, oldest first
+    ///
+    /// `create_workflow` with an existing ID stores a new current version; backends
+    /// that keep the earlier ones return them too. The default implementation only
+    /// knows the current version.
+    async fn list_workflow_versions(&self, id: &str) -> Result<Vec<WorkflowDefinition>> {
+        Ok(self.get_workflow(id).await?.into_iter().collect())
+    }
+
+    /// Get a specific version of a workflow definition
+    async fn get_workflow_version(
+        &self,
+        id: &str,
+        version: u32,
+    ) -> Result<Option<WorkflowDefinition>> {
+        Ok(self
+            .list_workflow_versions(id)
+            .await?
+            .into_iter()
+            .find(|workflow| workflow.version == version))
+    }
+
     /// Create a new resource
     ///
     /// Stores a resource and returns it back. The resource ID is generated
@@ -120,8 +148,42 @@ pub trait WorkflowStorage: Send + Sync {
     ///
     /// Replaces the stored resource with the new version. This is used
     /// when resources execute activities between states or metadata is updated.
+    ///
+    /// Updates are compare-and-swap: the stored resource must still be at the
+    /// `version` the update was made from, and the returned resource carries the
+    /// bumped one. Otherwise another writer got there first and the update fails
+    /// with [`VersionConflict`](CircuitBreakerError::VersionConflict); re-read the
+    /// resource and retry, e.g. with [`update_resource_with_retry`].
     async fn update_resource(&self, resource: Resource) -> Result<Resource>;
 
+    /// Create many resources in one call
+    ///
+    /// The default implementation creates them one at a time, so a failure leaves the
+    /// earlier ones stored; backends reporting [`transactional_batches`] store all or none.
+    ///
+    /// [`transactional_batches`]: WorkflowStorage::transactional_batches
+    async fn create_resources(&self, resources: Vec<Resource>) -> Result<Vec<Resource>> {
+        let mut created = Vec::with_capacity(resources.len());
+        for resource in resources {
+            created.push(self.create_resource(resource).await?);
+        }
+        Ok(created)
+    }
+
+    /// Update many resources in one call, with the same guarantees as `create_resources`
+    async fn update_resources(&self, resources: Vec<Resource>) -> Result<Vec<Resource>> {
+        let mut updated = Vec::with_capacity(resources.len());
+        for resource in resources {
+            updated.push(self.update_resource(resource).await?);
+        }
+        Ok(updated)
+    }
+
+    /// Whether `create_resources` and `update_resources` store all resources or none
+    fn transactional_batches(&self) -> bool {
+        false
+    }
+
     /// List resources, optionally filtered by workflow
     ///
     /// If workflow_id is Some, returns only resources for that workflow.
@@ -130,6 +192,172 @@ pub trait WorkflowStorage: Send + Sync {
     /// ## Parameters
     /// - `workflow_id: Option<&str>`: Optional filter by workflow ID
     async fn list_resources(&self, workflow_id: Option<&str>) -> Result<Vec<Resource>>;
+
+    /// Count the resources of a workflow in each state
+    ///
+    /// Backends that maintain `StateCounters` answer without touching resources;
+    /// the default implementation scans them.
+    async fn count_resources_by_state(&self, workflow_id: &str) -> Result<HashMap<String, u64>> {
+        let mut counts = HashMap::new();
+        for resource in self.list_resources(Some(workflow_id)).await? {
+            *counts
+                .entry(resource.current_state().to_string())
+                .or_default() += 1;
+        }
+        Ok(counts)
+    }
+
+    /// Recompute materialized counters from the stored resources
+    ///
+    /// A no-op for backends that count by scanning.
+    async fn rebuild_state_counters(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// List the resources with `value` under the metadata key `key`, across workflows
+    ///
+    /// Backends that maintain a `CorrelationIndex` answer from it; the default
+    /// implementation scans every resource.
+    async fn correlated_resources(&self, key: &str, value: &str) -> Result<Vec<Resource>> {
+        Ok(self
+            .list_resources(None)
+            .await?
+            .into_iter()
+            .filter(|resource| {
+                correlation_value(&resource.metadata, key).is_some_and(|v| v == value)
+            })
+            .collect())
+    }
+
+    /// Get several workflow definitions at once, keyed by ID
+    ///
+    /// Used by GraphQL dataloaders to resolve the workflows of many resources in one
+    /// call. IDs without a workflow are left out. The default implementation looks them
+    /// up one by one.
+    async fn get_workflows(&self, ids: &[String]) -> Result<HashMap<String, WorkflowDefinition>> {
+        let mut workflows = HashMap::new();
+        for id in ids {
+            if let Some(workflow) = self.get_workflow(id).await? {
+                workflows.insert(id.clone(), workflow);
+            }
+        }
+        Ok(workflows)
+    }
+}
+
+/// Fail with [`VersionConflict`](CircuitBreakerError::VersionConflict) unless an update
+/// to `resource` was made from the `stored` version
+pub fn check_version(resource: &Resource, stored: u64) -> Result<()> {
+    if resource.version == stored {
+        Ok(())
+    } else {
+        Err(CircuitBreakerError::VersionConflict {
+            id: resource.id.to_string(),
+            expected: resource.version,
+            actual: stored,
+        })
+    }
+}
+
+/// Read a resource, apply `change` to it and store it, reading it again and
+/// reapplying `change` when a concurrent update wins, up to `attempts` times
+pub async fn update_resource_with_retry<S, F>(
+    storage: &S,
+    id: &Uuid,
+    attempts: usize,
+    mut change: F,
+) -> Result<Resource>
+where
+    S: WorkflowStorage + ?Sized,
+    F: FnMut(&mut Resource) -> Result<()> + Send,
+{
+    let mut attempt = 1;
+    loop {
+        let mut resource = storage
+            .get_resource(id)
+            .await?
+            .ok_or_else(|| CircuitBreakerError::NotFound(format!("Resource {}", id)))?;
+        change(&mut resource)?;
+        match storage.update_resource(resource).await {
+            Err(CircuitBreakerError::VersionConflict { .. }) if attempt < attempts => {
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Shared storage handles are storage too, so one backend can be owned by the GraphQL
+/// schema and by the dataloaders batching its lookups
+#[async_trait::async_trait]
+impl<T: WorkflowStorage + ?Sized> WorkflowStorage for std::sync::Arc<T> {
+    async fn create_workflow(&self, definition: WorkflowDefinition) -> Result<WorkflowDefinition> {
+        (**self).create_workflow(definition).await
+    }
+
+    async fn get_workflow(&self, id: &str) -> Result<Option<WorkflowDefinition>> {
+        (**self).get_workflow(id).await
+    }
+
+    async fn list_workflows(&self) -> Result<Vec<WorkflowDefinition>> {
+        (**self).list_workflows().await
+    }
+
+    async fn list_workflow_versions(&self, id: &str) -> Result<Vec<WorkflowDefinition>> {
+        (**self).list_workflow_versions(id).await
+    }
+
+    async fn get_workflow_version(
+        &self,
+        id: &str,
+        version: u32,
+    ) -> Result<Option<WorkflowDefinition>> {
+        (**self).get_workflow_version(id, version).await
+    }
+
+    async fn create_resource(&self, resource: Resource) -> Result<Resource> {
+        (**self).create_resource(resource).await
+    }
+
+    async fn get_resource(&self, id: &Uuid) -> Result<Option<Resource>> {
+        (**self).get_resource(id).await
+    }
+
+    async fn update_resource(&self, resource: Resource) -> Result<Resource> {
+        (**self).update_resource(resource).await
+    }
+
+    async fn create_resources(&self, resources: Vec<Resource>) -> Result<Vec<Resource>> {
+        (**self).create_resources(resources).await
+    }
+
+    async fn update_resources(&self, resources: Vec<Resource>) -> Result<Vec<Resource>> {
+        (**self).update_resources(resources).await
+    }
+
+    fn transactional_batches(&self) -> bool {
+        (**self).transactional_batches()
+    }
+
+    async fn list_resources(&self, workflow_id: Option<&str>) -> Result<Vec<Resource>> {
+        (**self).list_resources(workflow_id).await
+    }
+
+    async fn count_resources_by_state(&self, workflow_id: &str) -> Result<HashMap<String, u64>> {
+        (**self).count_resources_by_state(workflow_id).await
+    }
+
+    async fn rebuild_state_counters(&self) -> Result<()> {
+        (**self).rebuild_state_counters().await
+    }
+
+    async fn correlated_resources(&self, key: &str, value: &str) -> Result<Vec<Resource>> {
+        (**self).correlated_resources(key, value).await
+    }
+
+    async fn get_workflows(&self, ids: &[String]) -> Result<HashMap<String, WorkflowDefinition>> {
+        (**self).get_workflows(ids).await
+    }
 }
 
 /// In-memory storage implementation for development and testing
@@ -145,7 +373,7 @@ pub trait WorkflowStorage: Send + Sync {
 ///
 /// - **Not persistent**: Data is lost when process restarts
 /// - **Not distributed**: Cannot share data across multiple processes
-/// - **Memory bound**: Limited by available RAM
+/// - **Memory bound**: Limited by available RAM, unless capped with [`MemoryLimits`]
 /// - **Not durable**: No backup or recovery mechanisms
 ///
 /// ## Thread Safety
@@ -170,15 +398,283 @@ pub trait WorkflowStorage: Send + Sync {
 /// ### Interior Mutability Pattern
 /// Even though the struct fields are not `mut`, we can still modify
 /// the data inside through `RwLock`. This is called "interior mutability".
-#[derive(Default)]
 pub struct InMemoryStorage {
     /// Thread-safe storage for workflow definitions
     /// Key: workflow ID (String), Value: workflow definition
     workflows: std::sync::RwLock<HashMap<String, WorkflowDefinition>>,
 
+    /// Every version of each workflow definition, oldest first
+    workflow_versions: std::sync::RwLock<HashMap<String, Vec<WorkflowDefinition>>>,
+
     /// Thread-safe storage for resources
     /// Key: resource ID (Uuid), Value: resource
     resources: std::sync::RwLock<HashMap<Uuid, Resource>>,
+
+    /// Resources per workflow per state, updated on every write
+    counters: StateCounters,
+
+    /// Resources by correlation key value, updated on every write; the process-wide
+    /// index read by the rules engine by default
+    correlations: CorrelationIndex,
+
+    /// Cap on resources kept in memory
+    limits: MemoryLimits,
+
+    /// Order resources were last used in, for evicting the least recent first
+    lru: std::sync::Mutex<ResourceLru>,
+
+    /// Where evicted resources go, if anywhere
+    spill: Option<SpillStore>,
+
+    /// Whether the approaching-cap warning has been logged since storage was last below it
+    near_cap: AtomicBool,
+
+    /// Whether the over-cap warning has been logged since storage was last within the cap
+    over_cap: AtomicBool,
+}
+
+impl Default for InMemoryStorage {
+    fn default() -> Self {
+        Self {
+            workflows: Default::default(),
+            workflow_versions: Default::default(),
+            resources: Default::default(),
+            counters: StateCounters::default(),
+            correlations: CorrelationIndex::global(),
+            limits: MemoryLimits::default(),
+            lru: Default::default(),
+            spill: None,
+            near_cap: AtomicBool::new(false),
+            over_cap: AtomicBool::new(false),
+        }
+    }
+}
+
+impl InMemoryStorage {
+    /// In-memory storage that keeps at most `limits.max_resources` resources in memory
+    ///
+    /// Fails when the spill file cannot be opened.
+    pub fn with_limits(limits: MemoryLimits) -> Result<Self> {
+        let spill = match &limits.spill_path {
+            Some(path) => Some(SpillStore::open(path)?),
+            None => None,
+        };
+        let storage = Self {
+            limits,
+            spill,
+            ..Self::default()
+        };
+        // Resources spilled by an earlier run are still stored
+        storage.recount_resources()?;
+        Ok(storage)
+    }
+
+    /// In-memory storage limited by `MemoryLimits::from_env`, unbounded if the spill
+    /// file cannot be opened
+    pub fn from_env() -> Self {
+        Self::with_limits(MemoryLimits::from_env()).unwrap_or_else(|e| {
+            warn!(
+                "In-memory storage limits unavailable, storage is unbounded: {}",
+                e
+            );
+            Self::default()
+        })
+    }
+
+    /// Keep resources by correlation key value in `correlations` instead of the
+    /// process-wide index
+    ///
+    /// Resources already stored are filed in it too.
+    pub fn with_correlation_index(mut self, correlations: CorrelationIndex) -> Result<Self> {
+        self.correlations = correlations;
+        self.recount_resources()?;
+        Ok(self)
+    }
+
+    /// Number of resources held in memory, not counting spilled ones
+    pub fn resident_resources(&self) -> usize {
+        self.resources.read().unwrap().len()
+    }
+
+    fn recount_resources(&self) -> Result<()> {
+        let resources = self.resources.read().unwrap();
+        let spilled = match &self.spill {
+            Some(spill) => spill.list(None)?,
+            None => Vec::new(),
+        };
+        self.counters
+            .rebuild_resources(resources.values().chain(spilled.iter()));
+        self.correlations
+            .rebuild(resources.values().chain(spilled.iter()));
+        Ok(())
+    }
+
+    /// Record a write and evict terminal resources if it took storage over the cap
+    fn resource_written(&self, resources: &mut HashMap<Uuid, Resource>, id: Uuid) -> Result<()> {
+        let Some(max_resources) = self.limits.max_resources else {
+            return Ok(());
+        };
+        self.lru.lock().unwrap().touch(id);
+
+        if resources.len() > max_resources {
+            self.evict(resources, resources.len() - max_resources)?;
+        }
+
+        // Warn once each time storage approaches or exceeds the cap
+        let near_cap = self
+            .limits
+            .warning_threshold()
+            .is_some_and(|threshold| resources.len() >= threshold);
+        if near_cap && !self.near_cap.swap(near_cap, Ordering::Relaxed) {
+            warn!(
+                "In-memory storage holds {} resources, approaching its cap of {}",
+                resources.len(),
+                max_resources
+            );
+        }
+        self.near_cap.store(near_cap, Ordering::Relaxed);
+        let over_cap = resources.len() > max_resources;
+        if over_cap && !self.over_cap.swap(over_cap, Ordering::Relaxed) {
+            warn!(
+                "In-memory storage holds {} resources, over its cap of {}: no finished resources are left to evict",
+                resources.len(),
+                max_resources
+            );
+        }
+        self.over_cap.store(over_cap, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Evict up to `count` of the least recently used resources in a terminal state
+    fn evict(&self, resources: &mut HashMap<Uuid, Resource>, count: usize) -> Result<()> {
+        let workflows = self.workflows.read().unwrap();
+        let mut lru = self.lru.lock().unwrap();
+        let evicted: Vec<Uuid> = lru
+            .least_recent()
+            .filter(|id| {
+                resources.get(id).is_some_and(|resource| {
+                    workflows
+                        .get(&resource.workflow_id)
+                        .is_some_and(|workflow| workflow.is_terminal_state(&resource.state))
+                })
+            })
+            .take(count)
+            .copied()
+            .collect();
+
+        for id in evicted {
+            lru.remove(&id);
+            let Some(resource) = resources.remove(&id) else {
+                continue;
+            };
+            match &self.spill {
+                // Spilled resources are still stored, so counters stay as they are
+                Some(spill) => spill.put(&resource)?,
+                None => {
+                    self.counters
+                        .resource_removed(&resource.workflow_id, resource.current_state());
+                    self.correlations.resource_removed(&resource.id);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Version of a stored resource, whether in memory or spilled
+    fn stored_version(
+        &self,
+        resources: &HashMap<Uuid, Resource>,
+        id: &Uuid,
+    ) -> Result<Option<u64>> {
+        if let Some(resource) = resources.get(id) {
+            return Ok(Some(resource.version));
+        }
+        match &self.spill {
+            Some(spill) => Ok(spill.get(id)?.map(|resource| resource.version)),
+            None => Ok(None),
+        }
+    }
+
+    /// Check an update against the stored version and bump it
+    fn next_version(
+        &self,
+        resources: &HashMap<Uuid, Resource>,
+        resource: &mut Resource,
+    ) -> Result<()> {
+        if let Some(stored) = self.stored_version(resources, &resource.id)? {
+            check_version(resource, stored)?;
+            resource.version = stored + 1;
+        }
+        Ok(())
+    }
+
+    fn store_resource(&self, mut resource: Resource, update: bool) -> Result<Resource> {
+        let mut resources = self.resources.write().unwrap();
+        if update {
+            self.next_version(&resources, &mut resource)?;
+        }
+
+        // Insert will either create or update the resource; a spilled resource
+        // comes back into memory
+        let mut previous = resources.insert(resource.id, resource.clone());
+        if previous.is_none() {
+            if let Some(spill) = &self.spill {
+                previous = spill.remove(&resource.id)?;
+            }
+        }
+        self.counters.resource_stored(previous.as_ref(), &resource);
+        self.correlations.resource_stored(&resource);
+        self.resource_written(&mut resources, resource.id)?;
+
+        Ok(resource)
+    }
+
+    /// Store a batch under one write lock, so readers see all of it or none of it
+    ///
+    /// An update batch with a stale resource in it fails with nothing stored. Spilled
+    /// copies are read before anything is inserted: an unreadable spill file
+    /// fails the batch with nothing stored. Once the batch is in memory it stays, and
+    /// failures to drop stale spilled copies or to evict are only logged.
+    fn store_resources(&self, mut batch: Vec<Resource>, update: bool) -> Result<Vec<Resource>> {
+        let mut resources = self.resources.write().unwrap();
+        if update {
+            for resource in batch.iter_mut() {
+                self.next_version(&resources, resource)?;
+            }
+        }
+
+        let mut spilled = HashMap::new();
+        if let Some(spill) = &self.spill {
+            for resource in batch.iter().filter(|r| !resources.contains_key(&r.id)) {
+                if let Some(previous) = spill.get(&resource.id)? {
+                    spilled.insert(resource.id, previous);
+                }
+            }
+        }
+
+        let unspilled: Vec<Uuid> = spilled.keys().copied().collect();
+        for resource in &batch {
+            let previous = resources
+                .insert(resource.id, resource.clone())
+                .or_else(|| spilled.remove(&resource.id));
+            self.counters.resource_stored(previous.as_ref(), resource);
+            self.correlations.resource_stored(resource);
+        }
+        if let Some(spill) = &self.spill {
+            for id in unspilled {
+                if let Err(e) = spill.remove(&id) {
+                    warn!("Failed to drop spilled copy of resource {}: {}", id, e);
+                }
+            }
+        }
+        for resource in &batch {
+            if let Err(e) = self.resource_written(&mut resources, resource.id) {
+                warn!("Failed to evict resources after a batch write: {}", e);
+            }
+        }
+
+        Ok(batch)
+    }
 }
 
 /// Implementation of WorkflowStorage trait for in-memory storage
@@ -213,6 +709,13 @@ impl WorkflowStorage for InMemoryStorage {
         // Store the workflow using its ID as the key
         workflows.insert(definition.id.clone(), definition.clone());
 
+        // Keep every version addressable, replacing one stored again under its number
+        let mut versions = self.workflow_versions.write().unwrap();
+        let versions = versions.entry(definition.id.clone()).or_default();
+        versions.retain(|stored| stored.version != definition.version);
+        versions.push(definition.clone());
+        versions.sort_by_key(|stored| stored.version);
+
         // Return the workflow (could be modified by storage layer)
         Ok(definition)
     }
@@ -238,14 +741,16 @@ impl WorkflowStorage for InMemoryStorage {
         Ok(workflows.values().cloned().collect())
     }
 
+    /// List the stored versions of a workflow
+    async fn list_workflow_versions(&self, id: &str) -> Result<Vec<WorkflowDefinition>> {
+        let versions = self.workflow_versions.read().unwrap();
+        Ok(versions.get(id).cloned().unwrap_or_default())
+    }
+
     /// Create and store a new resource
     async fn create_resource(&self, resource: Resource) -> Result<Resource> {
-        let mut resources = self.resources.write().unwrap();
-
         // Store the resource using its UUID as the key
-        resources.insert(resource.id, resource.clone());
-
-        Ok(resource)
+        self.store_resource(resource, false)
     }
 
     /// Retrieve a resource by UUID
@@ -253,17 +758,37 @@ impl WorkflowStorage for InMemoryStorage {
         let resources = self.resources.read().unwrap();
 
         // Look up resource by UUID and clone if found
-        Ok(resources.get(id).cloned())
+        if let Some(resource) = resources.get(id) {
+            if self.limits.max_resources.is_some() {
+                self.lru.lock().unwrap().touch(*id);
+            }
+            return Ok(Some(resource.clone()));
+        }
+
+        // Evicted resources are read from the spill file without reloading them
+        match &self.spill {
+            Some(spill) => spill.get(id),
+            None => Ok(None),
+        }
     }
 
     /// Update an existing resource (or create if it doesn't exist)
     async fn update_resource(&self, resource: Resource) -> Result<Resource> {
-        let mut resources = self.resources.write().unwrap();
+        self.store_resource(resource, true)
+    }
 
-        // Insert will either create or update the resource
-        resources.insert(resource.id, resource.clone());
+    /// Store a batch of new resources under a single lock
+    async fn create_resources(&self, resources: Vec<Resource>) -> Result<Vec<Resource>> {
+        self.store_resources(resources, false)
+    }
 
-        Ok(resource)
+    /// Store a batch of updated resources under a single lock
+    async fn update_resources(&self, resources: Vec<Resource>) -> Result<Vec<Resource>> {
+        self.store_resources(resources, true)
+    }
+
+    fn transactional_batches(&self) -> bool {
+        true
     }
 
     /// List resources, optionally filtered by workflow ID
@@ -282,6 +807,98 @@ impl WorkflowStorage for InMemoryStorage {
             .cloned() // Clone each resource
             .collect(); // Collect into vector
 
-        Ok(filtered)
+        // Evicted resources are still listed
+        match &self.spill {
+            Some(spill) => {
+                let mut filtered = filtered;
+                filtered.extend(spill.list(workflow_id)?);
+                Ok(filtered)
+            }
+            None => Ok(filtered),
+        }
+    }
+
+    /// Read the materialized counts instead of scanning resources
+    async fn count_resources_by_state(&self, workflow_id: &str) -> Result<HashMap<String, u64>> {
+        Ok(self.counters.resource_counts(workflow_id))
+    }
+
+    /// Recount resources per state from scratch, spilled ones included
+    async fn rebuild_state_counters(&self) -> Result<()> {
+        self.recount_resources()
+    }
+
+    /// Look the resources up in the correlation index instead of scanning them
+    async fn correlated_resources(&self, key: &str, value: &str) -> Result<Vec<Resource>> {
+        let mut correlated = Vec::new();
+        for id in self.correlations.resource_ids(key, value) {
+            if let Some(resource) = self.get_resource(&id).await? {
+                correlated.push(resource);
+            }
+        }
+        Ok(correlated)
+    }
+
+    /// Look up all requested workflows under a single read lock
+    async fn get_workflows(&self, ids: &[String]) -> Result<HashMap<String, WorkflowDefinition>> {
+        let workflows = self.workflows.read().unwrap();
+        Ok(ids
+            .iter()
+            .filter_map(|id| workflows.get(id).map(|w| (id.clone(), w.clone())))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::StateId;
+
+    #[tokio::test]
+    async fn test_update_resource_compare_and_swap() {
+        let storage = InMemoryStorage::default();
+        let created = storage
+            .create_resource(Resource::new("orders", StateId::from("placed")))
+            .await
+            .unwrap();
+        assert_eq!(created.version, 0);
+
+        let mut first = created.clone();
+        first.state = StateId::from("paid");
+        let first = storage.update_resource(first).await.unwrap();
+        assert_eq!(first.version, 1);
+
+        let mut stale = created.clone();
+        stale.state = StateId::from("cancelled");
+        assert!(matches!(
+            storage.update_resource(stale).await,
+            Err(CircuitBreakerError::VersionConflict {
+                expected: 0,
+                actual: 1,
+                ..
+            })
+        ));
+        assert!(storage
+            .update_resources(vec![first.clone(), created.clone()])
+            .await
+            .is_err());
+        assert_eq!(
+            storage
+                .get_resource(&created.id)
+                .await
+                .unwrap()
+                .unwrap()
+                .version,
+            1
+        );
+
+        let retried = update_resource_with_retry(&storage, &created.id, 3, |resource| {
+            resource.state = StateId::from("shipped");
+            Ok(())
+        })
+        .await
+        .unwrap();
+        assert_eq!(retried.version, 2);
+        assert_eq!(retried.state, StateId::from("shipped"));
     }
 }
@@ -0,0 +1,576 @@
+// Stream subscription tracking for engines that fan out events
+// This module keeps track of live stream subscribers and reaps the ones whose clients vanished
+
+//! # Stream Subscriptions
+//!
+//! Engines such as the [`AgentEngine`](crate::engine::AgentEngine) broadcast events to any
+//! number of subscribers. A subscriber whose client disappears without the stream being
+//! dropped keeps its registry entry and broadcast receiver alive forever. This module wraps
+//! broadcast receivers in a [`StreamSubscription`] that is tracked by a [`SubscriberRegistry`],
+//! and provides a background reaper that closes subscriptions that stopped consuming events.
+//!
+//! A subscription is considered stale when it is not currently waiting for the next event
+//! and has not received one for longer than the configured idle timeout.
+//!
+//! Subscribers choose a [`StreamDelivery`] mode. Lossy subscribers read from the shared
+//! broadcast channel and receive a [`StreamItem::Lagged`] marker when they fall behind and
+//! events are dropped. Guaranteed subscribers get their own bounded queue, holding only the
+//! events their filter accepts, and never miss an event; one that lets its queue fill up is
+//! closed rather than holding up the publisher.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, mpsc, Notify};
+use tokio::task::JoinHandle;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+/// Point-in-time subscriber gauges for an engine
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StreamGauges {
+    /// Subscribers currently registered
+    pub active_subscribers: usize,
+    /// Subscribers removed by the reaper since startup
+    pub reaped_total: u64,
+    /// Events dropped for lagging subscribers since startup
+    #[serde(default)]
+    pub missed_events_total: u64,
+}
+
+/// How events are delivered to a subscriber
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StreamDelivery {
+    /// Read from the shared broadcast channel; slow subscribers miss events and are told so
+    #[default]
+    Lossy,
+    /// Per-subscriber bounded queue; a subscriber whose queue is full is closed
+    Guaranteed,
+}
+
+/// An item yielded by a [`StreamSubscription`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamItem<T> {
+    /// The next event
+    Event(T),
+    /// The subscriber fell behind and `missed` events were dropped
+    Lagged { missed: u64 },
+}
+
+/// Liveness state shared between a subscription and its registry
+#[derive(Debug)]
+pub struct SubscriberState {
+    pub id: Uuid,
+    pub created_at: Instant,
+    last_activity: Mutex<Instant>,
+    waiting: AtomicBool,
+    closed: AtomicBool,
+    close_notify: Notify,
+}
+
+impl SubscriberState {
+    fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            id: Uuid::new_v4(),
+            created_at: now,
+            last_activity: Mutex::new(now),
+            waiting: AtomicBool::new(false),
+            closed: AtomicBool::new(false),
+            close_notify: Notify::new(),
+        }
+    }
+
+    fn touch(&self) {
+        if let Ok(mut last_activity) = self.last_activity.lock() {
+            *last_activity = Instant::now();
+        }
+    }
+
+    /// Time since the subscriber last received an event
+    pub fn idle_for(&self) -> Duration {
+        self.last_activity
+            .lock()
+            .map(|last_activity| last_activity.elapsed())
+            .unwrap_or_default()
+    }
+
+    /// Whether the subscriber is currently parked waiting for the next event
+    pub fn is_waiting(&self) -> bool {
+        self.waiting.load(Ordering::SeqCst)
+    }
+
+    /// Whether the subscription has been closed
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// Close the subscription; any pending or future `recv` returns `None`
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        self.close_notify.notify_waiters();
+    }
+
+    /// Wait until the subscription is closed
+    pub async fn closed(&self) {
+        // Register for the notification before checking the flag so a
+        // concurrent `close()` cannot slip in between
+        let notified = self.close_notify.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+
+        if !self.is_closed() {
+            notified.await;
+        }
+    }
+
+    fn is_stale(&self, idle_timeout: Duration) -> bool {
+        self.is_closed() || (!self.is_waiting() && self.idle_for() > idle_timeout)
+    }
+}
+
+/// Registry of live subscribers for a single engine
+#[derive(Debug, Clone, Default)]
+pub struct SubscriberRegistry {
+    subscribers: Arc<Mutex<HashMap<Uuid, Arc<SubscriberState>>>>,
+    reaped_total: Arc<AtomicU64>,
+    missed_events_total: Arc<AtomicU64>,
+}
+
+impl SubscriberRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a new subscriber and return its shared state
+    pub fn register(&self) -> Arc<SubscriberState> {
+        let state = Arc::new(SubscriberState::new());
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.insert(state.id, state.clone());
+        }
+        state
+    }
+
+    /// Remove a subscriber from the registry
+    pub fn unregister(&self, id: &Uuid) {
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.remove(id);
+        }
+    }
+
+    /// Close and remove every stale subscriber, returning how many were reaped
+    pub fn reap(&self, idle_timeout: Duration) -> usize {
+        let stale: Vec<Arc<SubscriberState>> = match self.subscribers.lock() {
+            Ok(mut subscribers) => {
+                let stale_ids: Vec<Uuid> = subscribers
+                    .values()
+                    .filter(|state| state.is_stale(idle_timeout))
+                    .map(|state| state.id)
+                    .collect();
+                stale_ids
+                    .iter()
+                    .filter_map(|id| subscribers.remove(id))
+                    .collect()
+            }
+            Err(_) => Vec::new(),
+        };
+
+        for state in &stale {
+            debug!(
+                "Reaping stream subscriber {} (idle for {:?})",
+                state.id,
+                state.idle_for()
+            );
+            state.close();
+        }
+
+        self.reaped_total
+            .fetch_add(stale.len() as u64, Ordering::SeqCst);
+        stale.len()
+    }
+
+    /// Number of registered subscribers
+    pub fn active_count(&self) -> usize {
+        self.subscribers
+            .lock()
+            .map(|subscribers| subscribers.len())
+            .unwrap_or(0)
+    }
+
+    /// Current gauges for this registry
+    pub fn gauges(&self) -> StreamGauges {
+        StreamGauges {
+            active_subscribers: self.active_count(),
+            reaped_total: self.reaped_total.load(Ordering::SeqCst),
+            missed_events_total: self.missed_events_total.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Subscribe to a broadcast channel with tracking
+    pub fn track<T: Clone>(&self, receiver: broadcast::Receiver<T>) -> StreamSubscription<T> {
+        self.track_source(SubscriptionSource::Broadcast(receiver), self.register())
+    }
+
+    fn track_source<T: Clone>(
+        &self,
+        source: SubscriptionSource<T>,
+        state: Arc<SubscriberState>,
+    ) -> StreamSubscription<T> {
+        StreamSubscription {
+            source,
+            state,
+            registry: self.clone(),
+        }
+    }
+
+    /// Spawn a background task that periodically reaps stale subscribers
+    pub fn spawn_reaper(&self, interval: Duration, idle_timeout: Duration) -> JoinHandle<()> {
+        let registry = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let reaped = registry.reap(idle_timeout);
+                if reaped > 0 {
+                    info!(
+                        "Reaped {} stale stream subscribers ({} still active)",
+                        reaped,
+                        registry.active_count()
+                    );
+                }
+            }
+        })
+    }
+}
+
+/// Predicate selecting the events a subscriber wants
+pub type StreamFilter<T> = Arc<dyn Fn(&T) -> bool + Send + Sync>;
+
+/// Guaranteed-delivery queue of a subscriber
+#[derive(Clone)]
+struct SubscriberQueue<T> {
+    state: Arc<SubscriberState>,
+    sender: mpsc::Sender<T>,
+    filter: Option<StreamFilter<T>>,
+}
+
+impl<T> SubscriberQueue<T> {
+    fn accepts(&self, event: &T) -> bool {
+        self.filter.as_ref().is_none_or(|filter| filter(event))
+    }
+}
+
+/// Event fan-out supporting both lossy and guaranteed-delivery subscribers
+pub struct StreamHub<T: Clone> {
+    broadcast: broadcast::Sender<T>,
+    queues: Arc<Mutex<Vec<SubscriberQueue<T>>>>,
+    queue_capacity: usize,
+    registry: SubscriberRegistry,
+}
+
+impl<T: Clone + Send + 'static> StreamHub<T> {
+    /// Create a hub with the shared broadcast capacity and the per-subscriber queue
+    /// capacity used for guaranteed delivery
+    pub fn new(broadcast_capacity: usize, queue_capacity: usize) -> Self {
+        let (broadcast, _) = broadcast::channel(broadcast_capacity.max(1));
+        Self {
+            broadcast,
+            queues: Arc::new(Mutex::new(Vec::new())),
+            queue_capacity: queue_capacity.max(1),
+            registry: SubscriberRegistry::new(),
+        }
+    }
+
+    /// Registry tracking this hub's subscribers
+    pub fn registry(&self) -> &SubscriberRegistry {
+        &self.registry
+    }
+
+    /// Untracked receiver on the shared broadcast channel
+    pub fn subscribe_raw(&self) -> broadcast::Receiver<T> {
+        self.broadcast.subscribe()
+    }
+
+    /// Subscribe with the given delivery mode
+    pub fn subscribe(&self, delivery: StreamDelivery) -> StreamSubscription<T> {
+        self.subscribe_queued(delivery, None)
+    }
+
+    /// Subscribe to the events `filter` accepts
+    ///
+    /// Guaranteed subscribers only have accepted events queued, so unrelated traffic
+    /// cannot fill their queue. Lossy subscribers share the broadcast channel and still
+    /// receive every event.
+    pub fn subscribe_filtered(
+        &self,
+        delivery: StreamDelivery,
+        filter: impl Fn(&T) -> bool + Send + Sync + 'static,
+    ) -> StreamSubscription<T> {
+        self.subscribe_queued(delivery, Some(Arc::new(filter)))
+    }
+
+    fn subscribe_queued(
+        &self,
+        delivery: StreamDelivery,
+        filter: Option<StreamFilter<T>>,
+    ) -> StreamSubscription<T> {
+        match delivery {
+            StreamDelivery::Lossy => self.registry.track(self.broadcast.subscribe()),
+            StreamDelivery::Guaranteed => {
+                let (sender, receiver) = mpsc::channel(self.queue_capacity);
+                let state = self.registry.register();
+                if let Ok(mut queues) = self.queues.lock() {
+                    queues.push(SubscriberQueue {
+                        state: state.clone(),
+                        sender,
+                        filter,
+                    });
+                }
+                self.registry
+                    .track_source(SubscriptionSource::Queue(receiver), state)
+            }
+        }
+    }
+
+    /// Publish an event to every subscriber
+    ///
+    /// Never waits for subscribers: a guaranteed subscriber whose queue is full is
+    /// closed, ending its stream, instead of silently missing the event.
+    pub async fn publish(&self, event: T) {
+        let _ = self.broadcast.send(event.clone());
+
+        let queues: Vec<SubscriberQueue<T>> = match self.queues.lock() {
+            Ok(mut queues) => {
+                queues.retain(|queue| !queue.state.is_closed() && !queue.sender.is_closed());
+                queues.clone()
+            }
+            Err(_) => Vec::new(),
+        };
+
+        for queue in queues.iter().filter(|queue| queue.accepts(&event)) {
+            if let Err(mpsc::error::TrySendError::Full(_)) = queue.sender.try_send(event.clone()) {
+                debug!(
+                    "Closing stream subscriber {}: its queue of {} events is full",
+                    queue.state.id, self.queue_capacity
+                );
+                queue.state.close();
+            }
+        }
+    }
+}
+
+enum SubscriptionSource<T> {
+    Broadcast(broadcast::Receiver<T>),
+    Queue(mpsc::Receiver<T>),
+}
+
+/// A tracked stream subscription
+///
+/// Dropping the subscription removes it from its registry.
+pub struct StreamSubscription<T: Clone> {
+    source: SubscriptionSource<T>,
+    state: Arc<SubscriberState>,
+    registry: SubscriberRegistry,
+}
+
+impl<T: Clone> StreamSubscription<T> {
+    /// Subscription identifier
+    pub fn id(&self) -> Uuid {
+        self.state.id
+    }
+
+    /// Shared liveness state
+    pub fn state(&self) -> &Arc<SubscriberState> {
+        &self.state
+    }
+
+    /// Delivery mode of this subscription
+    pub fn delivery(&self) -> StreamDelivery {
+        match self.source {
+            SubscriptionSource::Broadcast(_) => StreamDelivery::Lossy,
+            SubscriptionSource::Queue(_) => StreamDelivery::Guaranteed,
+        }
+    }
+
+    /// Receive the next item, or `None` once the channel or subscription is closed
+    pub async fn recv(&mut self) -> Option<StreamItem<T>> {
+        // Register for the close notification before checking the flag so a
+        // concurrent `close()` cannot slip in between
+        let notified = self.state.close_notify.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+
+        if self.state.is_closed() {
+            return None;
+        }
+
+        self.state.waiting.store(true, Ordering::SeqCst);
+        let result = tokio::select! {
+            result = Self::recv_source(&mut self.source) => Some(result),
+            _ = &mut notified => None,
+        };
+        self.state.waiting.store(false, Ordering::SeqCst);
+
+        match result {
+            Some(Some(item)) => {
+                self.state.touch();
+                if let StreamItem::Lagged { missed } = item {
+                    debug!(
+                        "Stream subscriber {} lagged behind by {} events",
+                        self.state.id, missed
+                    );
+                    self.registry
+                        .missed_events_total
+                        .fetch_add(missed, Ordering::SeqCst);
+                }
+                Some(item)
+            }
+            Some(None) | None => {
+                self.state.close();
+                None
+            }
+        }
+    }
+
+    async fn recv_source(source: &mut SubscriptionSource<T>) -> Option<StreamItem<T>> {
+        match source {
+            SubscriptionSource::Broadcast(receiver) => match receiver.recv().await {
+                Ok(event) => Some(StreamItem::Event(event)),
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    Some(StreamItem::Lagged { missed })
+                }
+                Err(broadcast::error::RecvError::Closed) => None,
+            },
+            SubscriptionSource::Queue(receiver) => receiver.recv().await.map(StreamItem::Event),
+        }
+    }
+
+    /// Convert into a `Stream` of items
+    pub fn into_stream(self) -> impl futures::Stream<Item = StreamItem<T>>
+    where
+        T: Send + 'static,
+    {
+        futures::stream::unfold(self, |mut subscription| async move {
+            subscription.recv().await.map(|item| (item, subscription))
+        })
+    }
+}
+
+impl<T: Clone> Drop for StreamSubscription<T> {
+    fn drop(&mut self) {
+        self.state.close();
+        self.registry.unregister(&self.state.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_subscription_unregisters_on_drop() {
+        let registry = SubscriberRegistry::new();
+        let (sender, _) = broadcast::channel::<u32>(8);
+
+        let subscription = registry.track(sender.subscribe());
+        assert_eq!(registry.active_count(), 1);
+
+        drop(subscription);
+        assert_eq!(registry.active_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_reap_idle_subscriber() {
+        let registry = SubscriberRegistry::new();
+        let (sender, _) = broadcast::channel::<u32>(8);
+        let mut subscription = registry.track(sender.subscribe());
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(registry.reap(Duration::from_millis(5)), 1);
+        assert_eq!(registry.gauges().reaped_total, 1);
+        assert_eq!(registry.active_count(), 0);
+
+        sender.send(1).unwrap();
+        assert!(subscription.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_waiting_subscriber_is_not_reaped() {
+        let registry = SubscriberRegistry::new();
+        let (sender, _) = broadcast::channel::<u32>(8);
+        let mut subscription = registry.track(sender.subscribe());
+
+        let handle = tokio::spawn(async move { subscription.recv().await });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        assert_eq!(registry.reap(Duration::from_millis(5)), 0);
+        sender.send(7).unwrap();
+        assert_eq!(handle.await.unwrap(), Some(StreamItem::Event(7)));
+    }
+
+    #[tokio::test]
+    async fn test_lossy_subscriber_reports_lag() {
+        let hub = StreamHub::<u32>::new(2, 2);
+        let mut subscription = hub.subscribe(StreamDelivery::Lossy);
+
+        for event in 0..5 {
+            hub.publish(event).await;
+        }
+
+        assert_eq!(
+            subscription.recv().await,
+            Some(StreamItem::Lagged { missed: 3 })
+        );
+        assert_eq!(subscription.recv().await, Some(StreamItem::Event(3)));
+        assert_eq!(hub.registry().gauges().missed_events_total, 3);
+    }
+
+    #[tokio::test]
+    async fn test_guaranteed_subscriber_receives_every_event() {
+        let hub = StreamHub::<u32>::new(2, 5);
+        let mut subscription = hub.subscribe(StreamDelivery::Guaranteed);
+
+        for event in 0..5 {
+            hub.publish(event).await;
+        }
+        for expected in 0..5 {
+            assert_eq!(subscription.recv().await, Some(StreamItem::Event(expected)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_full_guaranteed_subscriber_is_closed() {
+        let hub = StreamHub::<u32>::new(2, 2);
+        let mut slow = hub.subscribe(StreamDelivery::Guaranteed);
+        let mut evens = hub.subscribe_filtered(StreamDelivery::Guaranteed, |n| n % 2 == 0);
+
+        // The publisher never waits; the slow subscriber is closed once its queue fills
+        tokio::time::timeout(Duration::from_secs(1), async {
+            for event in 0..4 {
+                hub.publish(event).await;
+            }
+        })
+        .await
+        .expect("publish should not block on a full queue");
+        assert!(slow.recv().await.is_none());
+
+        // Filtered events never take up queue space
+        assert_eq!(evens.recv().await, Some(StreamItem::Event(0)));
+        assert_eq!(evens.recv().await, Some(StreamItem::Event(2)));
+    }
+
+    #[tokio::test]
+    async fn test_dropped_guaranteed_subscriber_does_not_block_publisher() {
+        let hub = StreamHub::<u32>::new(2, 1);
+        let subscription = hub.subscribe(StreamDelivery::Guaranteed);
+        hub.publish(1).await;
+        drop(subscription);
+
+        tokio::time::timeout(Duration::from_secs(1), hub.publish(2))
+            .await
+            .expect("publish should not block on a dropped subscriber");
+    }
+}
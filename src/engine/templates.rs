@@ -0,0 +1,271 @@
+// Workflow template catalog
+// Built-in and registered templates new workflows are created from
+
+//! # Template Catalog
+//!
+//! [`TemplateCatalog`] holds the [`WorkflowTemplate`]s workflows can be created from.
+//! It starts with the built-in templates:
+//!
+//! - `threshold_approval`: requests up to an amount are approved automatically, larger
+//!   ones wait for a person to approve or reject them
+//! - `agent_review`: documents are reviewed by an AI agent before publishing, with a
+//!   service level on the review
+//!
+//! Further templates can be registered at runtime; each is validated first, and a
+//! registered template replaces one with the same ID.
+
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use crate::models::{
+    ParameterType, TemplateError, TemplateParameter, WorkflowDefinition, WorkflowTemplate,
+};
+
+lazy_static::lazy_static! {
+    static ref GLOBAL: TemplateCatalog = TemplateCatalog::with_builtins();
+}
+
+/// Templates by ID
+#[derive(Clone, Default)]
+pub struct TemplateCatalog {
+    templates: Arc<RwLock<HashMap<String, WorkflowTemplate>>>,
+}
+
+impl TemplateCatalog {
+    /// An empty catalog
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A catalog holding the built-in templates
+    pub fn with_builtins() -> Self {
+        let catalog = Self::new();
+        for template in builtin_templates() {
+            catalog
+                .register(template)
+                .expect("built-in templates are valid");
+        }
+        catalog
+    }
+
+    /// The process-wide catalog used by the API
+    pub fn global() -> Self {
+        GLOBAL.clone()
+    }
+
+    pub fn register(&self, template: WorkflowTemplate) -> Result<(), TemplateError> {
+        template.validate()?;
+        self.templates
+            .write()
+            .unwrap()
+            .insert(template.id.clone(), template);
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Option<WorkflowTemplate> {
+        self.templates.read().unwrap().get(id).cloned()
+    }
+
+    /// Every template, by ID
+    pub fn list(&self) -> Vec<WorkflowTemplate> {
+        let mut templates: Vec<_> = self.templates.read().unwrap().values().cloned().collect();
+        templates.sort_by(|a, b| a.id.cmp(&b.id));
+        templates
+    }
+
+    /// Create the workflow `workflow_id` from the template `template_id`
+    pub fn instantiate(
+        &self,
+        template_id: &str,
+        workflow_id: &str,
+        parameters: &Map<String, Value>,
+    ) -> Result<WorkflowDefinition, TemplateError> {
+        self.get(template_id)
+            .ok_or_else(|| TemplateError::NotFound(template_id.to_string()))?
+            .instantiate(workflow_id, parameters)
+    }
+}
+
+fn builtin_templates() -> Vec<WorkflowTemplate> {
+    vec![
+        WorkflowTemplate::new(
+            "threshold_approval",
+            "Threshold approval",
+            "Requests up to a threshold are approved automatically; larger ones need an approver",
+            json!({
+                "name": "{{name}}",
+                "states": ["submitted", "awaiting_approval", "approved", "rejected"],
+                "initial_state": "submitted",
+                "activities": [{
+                    "id": "auto_approve",
+                    "from_states": ["submitted"],
+                    "to_state": "approved",
+                    "conditions": [],
+                    "rules": [{
+                        "id": "within_threshold",
+                        "description": "Amount is below the approval threshold",
+                        "condition": {
+                            "type": "FieldLessThan",
+                            "field": "{{amount_field}}",
+                            "value": "{{approval_threshold}}"
+                        }
+                    }]
+                }, {
+                    "id": "request_approval",
+                    "from_states": ["submitted"],
+                    "to_state": "awaiting_approval",
+                    "conditions": [],
+                    "rules": [{
+                        "id": "over_threshold",
+                        "description": "Amount is at or above the approval threshold",
+                        "condition": {
+                            "type": "Not",
+                            "rule": {
+                                "id": "within_threshold",
+                                "description": "Amount is below the approval threshold",
+                                "condition": {
+                                    "type": "FieldLessThan",
+                                    "field": "{{amount_field}}",
+                                    "value": "{{approval_threshold}}"
+                                }
+                            }
+                        }
+                    }]
+                }, {
+                    "id": "approve",
+                    "from_states": ["awaiting_approval"],
+                    "to_state": "approved",
+                    "conditions": [],
+                    "rules": [],
+                    "manual": {
+                        "instructions": "{{approver_instructions}}",
+                        "reject_to": "rejected"
+                    }
+                }]
+            }),
+        )
+        .with_parameter(
+            TemplateParameter::new("name", ParameterType::String, "Name of the workflow")
+                .with_default(json!("Threshold approval")),
+        )
+        .with_parameter(TemplateParameter::new(
+            "approval_threshold",
+            ParameterType::Number,
+            "Amounts from this one up need an approver",
+        ))
+        .with_parameter(
+            TemplateParameter::new(
+                "amount_field",
+                ParameterType::String,
+                "Resource field holding the amount",
+            )
+            .with_default(json!("amount")),
+        )
+        .with_parameter(
+            TemplateParameter::new(
+                "approver_instructions",
+                ParameterType::String,
+                "Guidance shown to approvers",
+            )
+            .with_default(json!("Approve or reject the request")),
+        ),
+        WorkflowTemplate::new(
+            "agent_review",
+            "Agent review",
+            "Documents are reviewed by an AI agent, revised as needed and published",
+            json!({
+                "name": "{{name}}",
+                "states": ["draft", "in_review", "changes_requested", "published"],
+                "initial_state": "draft",
+                "activities": [{
+                    "id": "submit",
+                    "from_states": ["draft", "changes_requested"],
+                    "to_state": "in_review",
+                    "conditions": [],
+                    "rules": []
+                }, {
+                    "id": "request_changes",
+                    "from_states": ["in_review"],
+                    "to_state": "changes_requested",
+                    "conditions": [],
+                    "rules": []
+                }, {
+                    "id": "publish",
+                    "from_states": ["in_review"],
+                    "to_state": "published",
+                    "conditions": [],
+                    "rules": [{
+                        "id": "reviewed_by_agent",
+                        "description": "The review agent signed off",
+                        "condition": {
+                            "type": "FieldEquals",
+                            "field": "reviewed_by",
+                            "value": "{{agent_id}}"
+                        }
+                    }]
+                }],
+                "state_slas": {
+                    "in_review": { "max_secs": "{{review_sla_secs}}" }
+                }
+            }),
+        )
+        .with_parameter(
+            TemplateParameter::new("name", ParameterType::String, "Name of the workflow")
+                .with_default(json!("Agent review")),
+        )
+        .with_parameter(TemplateParameter::new(
+            "agent_id",
+            ParameterType::String,
+            "Agent whose sign-off publishes a document",
+        ))
+        .with_parameter(
+            TemplateParameter::new(
+                "review_sla_secs",
+                ParameterType::Integer,
+                "Longest a review should take, in seconds",
+            )
+            .with_default(json!(86400)),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::RuleCondition;
+
+    #[test]
+    fn test_builtin_templates_instantiate() {
+        let catalog = TemplateCatalog::with_builtins();
+        assert_eq!(catalog.list().len(), 2);
+
+        let parameters = json!({ "approval_threshold": 1000 });
+        let workflow = catalog
+            .instantiate(
+                "threshold_approval",
+                "expenses",
+                parameters.as_object().unwrap(),
+            )
+            .unwrap();
+        assert_eq!(workflow.name, "Threshold approval");
+        assert!(workflow.analyze().is_empty());
+
+        let parameters = json!({ "agent_id": "reviewer-1", "name": "Blog posts" });
+        let workflow = catalog
+            .instantiate("agent_review", "blog", parameters.as_object().unwrap())
+            .unwrap();
+        assert_eq!(workflow.name, "Blog posts");
+        assert!(matches!(
+            &workflow.activities[2].rules[0].condition,
+            RuleCondition::FieldEquals { value, .. } if value == "reviewer-1"
+        ));
+
+        assert_eq!(
+            catalog
+                .instantiate("missing", "x", &Map::new())
+                .unwrap_err(),
+            TemplateError::NotFound("missing".to_string())
+        );
+    }
+}
@@ -0,0 +1,304 @@
+// Timer-driven activities
+// Fires activities on their own once resources have sat in a state long enough
+
+//! # Activity Timers
+//!
+//! An activity with an [`ActivityTimer`] is executed by the engine itself once a resource
+//! has sat in one of its source states for the timer's duration. A `fire_after` timer also
+//! waits for the activity's rules and conditions to pass; a `deadline` timer fires
+//! regardless, which suits timeouts and escalations.
+//!
+//! [`ActivityTimers`] is the scheduler: an internal tick loop that scans the resources of
+//! workflows with timed activities and moves each due resource through the earliest due
+//! timer's activity, splitting, joining or moving a branch like any other execution. The
+//! history event it records carries the timer in its data, so timed moves can be told
+//! apart from ones a caller made. Target states at capacity are respected as for any
+//! other activity: the resource queues, and the timer tries again on later ticks. No
+//! timers fire while maintenance mode holds writes back.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use super::rules::RulesEngine;
+use super::storage::WorkflowStorage;
+use crate::models::{
+    ActivityDefinition, ActivityId, ActivityTimer, Resource, StateId, WorkflowDefinition,
+};
+use crate::{MaintenanceMode, Result};
+
+/// How often the server's scheduler looks for due timers
+pub const TIMER_TICK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// A timer that moved a resource
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FiredTimer {
+    pub resource_id: Uuid,
+    pub workflow_id: String,
+    pub activity_id: ActivityId,
+    pub from_state: StateId,
+    pub to_state: StateId,
+    pub timer: ActivityTimer,
+}
+
+/// Scheduler firing timed activities
+#[derive(Clone)]
+pub struct ActivityTimers {
+    storage: Arc<dyn WorkflowStorage>,
+    rules: Arc<RulesEngine>,
+}
+
+impl ActivityTimers {
+    pub fn new(storage: Arc<dyn WorkflowStorage>, rules: Arc<RulesEngine>) -> Self {
+        Self { storage, rules }
+    }
+
+    /// Fire every timer due at `now`
+    ///
+    /// A resource that cannot be moved, because its target state is full or storage
+    /// refused the update, is left for a later call.
+    pub async fn fire_due(&self, now: DateTime<Utc>) -> Result<Vec<FiredTimer>> {
+        let mut fired = Vec::new();
+        for workflow in self.storage.list_workflows().await? {
+            if !workflow.activities.iter().any(|a| a.timer.is_some()) {
+                continue;
+            }
+            for resource in self.storage.list_resources(Some(&workflow.id)).await? {
+                if resource.is_halted() {
+                    continue;
+                }
+                let Some((activity, timer)) = self.due_timer(&workflow, &resource, now) else {
+                    continue;
+                };
+                match self.fire(&workflow, resource, activity, timer).await {
+                    Ok(Some(timer)) => fired.push(timer),
+                    Ok(None) => {}
+                    Err(e) => warn!(
+                        "Failed to fire timer of activity '{}' in workflow '{}': {}",
+                        activity.id.as_str(),
+                        workflow.id,
+                        e
+                    ),
+                }
+            }
+        }
+        Ok(fired)
+    }
+
+    /// The timed activity leaving the resource's state that fell due first, if any did
+    fn due_timer<'a>(
+        &self,
+        workflow: &'a WorkflowDefinition,
+        resource: &Resource,
+        now: DateTime<Utc>,
+    ) -> Option<(&'a ActivityDefinition, ActivityTimer)> {
+        let entered_at = resource.entered_state_at();
+        workflow
+            .activities
+            .iter()
+            .filter(|activity| activity.can_execute_from(&resource.state))
+            .filter_map(|activity| {
+                let timer = activity.timer?;
+                let due_at = entered_at + Duration::seconds(timer.secs() as i64);
+                (due_at <= now).then_some((due_at, activity, timer))
+            })
+            .filter(|(_, activity, timer)| {
+                timer.overrides_rules() || self.rules.can_execute_activity(resource, activity)
+            })
+            .min_by_key(|(due_at, _, _)| *due_at)
+            .map(|(_, activity, timer)| (activity, timer))
+    }
+
+    async fn fire(
+        &self,
+        workflow: &WorkflowDefinition,
+        mut resource: Resource,
+        activity: &ActivityDefinition,
+        timer: ActivityTimer,
+    ) -> Result<Option<FiredTimer>> {
+        let to_state = self.rules.target_state(&resource, activity).clone();
+        if workflow.state_capacity(&to_state).is_some() {
+            let occupancy = self.storage.count_resources_by_state(&workflow.id).await?;
+            let check = self
+                .rules
+                .check_capacity(&resource, activity, workflow, &occupancy);
+            if !check.is_admitted() {
+                debug!(
+                    "Timer of activity '{}' waits for room in '{}' for resource {}",
+                    activity.id.as_str(),
+                    to_state.as_str(),
+                    resource.id
+                );
+                return Ok(None);
+            }
+        }
+
+        super::parallel::execute(&mut resource, activity)?;
+        let Some(event) = resource.history.last_mut() else {
+            return Ok(None);
+        };
+        let (from_state, to_state) = (event.from.clone(), event.to.clone());
+        match &mut event.data {
+            Some(serde_json::Value::Object(data)) => {
+                data.insert("timer".to_string(), serde_json::json!(timer));
+            }
+            data => *data = Some(serde_json::json!({ "timer": timer })),
+        }
+        let updated = self.storage.update_resource(resource).await?;
+        self.rules.capacity_queues().withdraw(&updated.id);
+        info!(
+            "⏰ Timer fired activity '{}' for resource {}: {} -> {}",
+            activity.id.as_str(),
+            updated.id,
+            from_state.as_str(),
+            to_state.as_str()
+        );
+
+        Ok(Some(FiredTimer {
+            resource_id: updated.id,
+            workflow_id: workflow.id.clone(),
+            activity_id: activity.id.clone(),
+            from_state,
+            to_state,
+            timer,
+        }))
+    }
+
+    /// Spawn the tick loop, looking for due timers every `interval`
+    pub fn spawn(self, interval: std::time::Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if MaintenanceMode::global().is_enabled() {
+                    continue;
+                }
+                if let Err(e) = self.fire_due(Utc::now()).await {
+                    warn!("Failed to fire activity timers: {}", e);
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::storage::InMemoryStorage;
+    use crate::models::{Gateway, Rule};
+
+    fn workflow() -> WorkflowDefinition {
+        WorkflowDefinition::new(
+            "timed_review",
+            "Timed Review",
+            vec![
+                StateId::from("pending"),
+                StateId::from("reminded"),
+                StateId::from("escalated"),
+            ],
+            vec![
+                ActivityDefinition::with_rules(
+                    "remind",
+                    vec!["pending"],
+                    "reminded",
+                    vec![Rule::field_exists("reviewer", "reviewer")],
+                )
+                .with_timer(ActivityTimer::FireAfter { secs: 60 }),
+                ActivityDefinition::new("escalate", vec!["pending"], "escalated")
+                    .with_timer(ActivityTimer::Deadline { secs: 3600 }),
+            ],
+            "pending",
+        )
+    }
+
+    #[tokio::test]
+    async fn test_timers_fire_once_due() {
+        let storage: Arc<dyn WorkflowStorage> = Arc::new(InMemoryStorage::default());
+        let workflow = storage.create_workflow(workflow()).await.unwrap();
+        let unassigned = storage
+            .create_resource(Resource::new(&workflow.id, StateId::from("pending")))
+            .await
+            .unwrap();
+        let mut assigned = Resource::new(&workflow.id, StateId::from("pending"));
+        assigned.data = serde_json::json!({ "reviewer": "ada" });
+        let assigned = storage.create_resource(assigned).await.unwrap();
+        let timers =
+            ActivityTimers::new(storage.clone(), Arc::new(RulesEngine::with_common_rules()));
+
+        let start = assigned.created_at;
+        assert!(timers.fire_due(start).await.unwrap().is_empty());
+
+        // The delay only fires where its rule passes
+        let fired = timers
+            .fire_due(start + Duration::seconds(61))
+            .await
+            .unwrap();
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].resource_id, assigned.id);
+        assert_eq!(fired[0].to_state, StateId::from("reminded"));
+        let reminded = storage.get_resource(&assigned.id).await.unwrap().unwrap();
+        assert_eq!(
+            reminded.history[0].data,
+            Some(serde_json::json!({ "timer": { "fire_after": { "secs": 60 } } }))
+        );
+
+        // The deadline fires whatever the rules say
+        let fired = timers
+            .fire_due(start + Duration::seconds(3601))
+            .await
+            .unwrap();
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].resource_id, unassigned.id);
+        assert_eq!(fired[0].activity_id, ActivityId::from("escalate"));
+    }
+
+    #[tokio::test]
+    async fn test_timers_split_like_other_executions() {
+        let storage: Arc<dyn WorkflowStorage> = Arc::new(InMemoryStorage::default());
+        let mut fork = ActivityDefinition::new("fork", vec!["pending"], "forked")
+            .with_timer(ActivityTimer::Deadline { secs: 60 });
+        fork.gateway = Some(Gateway::Split(vec![
+            StateId::from("legal"),
+            StateId::from("finance"),
+        ]));
+        let workflow = storage
+            .create_workflow(WorkflowDefinition::new(
+                "timed_fork",
+                "Timed Fork",
+                vec![
+                    StateId::from("pending"),
+                    StateId::from("forked"),
+                    StateId::from("legal"),
+                    StateId::from("finance"),
+                ],
+                vec![fork],
+                "pending",
+            ))
+            .await
+            .unwrap();
+        let resource = storage
+            .create_resource(Resource::new(&workflow.id, StateId::from("pending")))
+            .await
+            .unwrap();
+        let timers =
+            ActivityTimers::new(storage.clone(), Arc::new(RulesEngine::with_common_rules()));
+
+        let fired = timers
+            .fire_due(resource.created_at + Duration::seconds(61))
+            .await
+            .unwrap();
+        assert_eq!(fired.len(), 1);
+        let forked = storage.get_resource(&resource.id).await.unwrap().unwrap();
+        assert!(forked.is_split());
+        assert_eq!(
+            forked.history[0].data,
+            Some(serde_json::json!({
+                "split": ["legal", "finance"],
+                "timer": { "deadline": { "secs": 60 } }
+            }))
+        );
+    }
+}
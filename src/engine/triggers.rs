@@ -0,0 +1,681 @@
+// Inbound webhook triggers
+// Verifies webhooks and maps their events onto resource creation and activity firings
+
+//! # Webhook Triggers
+//!
+//! [`WebhookTriggers`] holds the registered [`WebhookTrigger`]s and handles the
+//! webhooks posted to them:
+//!
+//! 1. the signature is checked against the trigger's secret in the sender's scheme,
+//!    comparing in constant time; Stripe signatures older than five minutes are refused
+//! 2. the event is named from the request, e.g. `pull_request.closed` or `invoice.paid`
+//! 3. every mapping applying to the event runs in order: `create_resource` stores a
+//!    resource built from the payload, `fire_activity` executes an activity on each
+//!    selected resource its rules, source states and target capacity allow
+//!
+//! The [`TriggerReport`] lists what each mapping did to which resource; resources the
+//! activity could not run on are reported as skipped rather than failing the webhook,
+//! so senders do not retry deliveries that cannot succeed. Firings are recorded in the
+//! resource's history with `{"trigger": {"id", "event"}}` as the event's data.
+//!
+//! Triggers are held in memory by the process serving the API, like human task claims.
+
+use axum::http::HeaderMap;
+use chrono::Utc;
+use ring::hmac;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tracing::info;
+use uuid::Uuid;
+
+use super::correlation_index::CorrelationIndex;
+use super::rules::RulesEngine;
+use super::storage::WorkflowStorage;
+use crate::models::{
+    ActivityDefinition, ActivityId, Resource, ResourceSelector, TriggerAction, TriggerError,
+    TriggerSource, WebhookTrigger, WorkflowDefinition,
+};
+
+lazy_static::lazy_static! {
+    static ref GLOBAL: WebhookTriggers = WebhookTriggers::new();
+}
+
+/// Key of the history event data recording a webhook firing
+pub const TRIGGER_KEY: &str = "trigger";
+
+/// Oldest Stripe signature timestamp accepted, in seconds
+pub const STRIPE_TOLERANCE_SECS: i64 = 300;
+
+/// What one mapping did to one resource
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerOutcomeStatus {
+    Created,
+    Fired,
+    Skipped,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TriggerOutcome {
+    /// Position of the mapping in the trigger
+    pub mapping: usize,
+    pub status: TriggerOutcomeStatus,
+    pub resource_id: Option<Uuid>,
+    pub activity_id: Option<ActivityId>,
+    /// Why the mapping skipped or failed
+    pub message: Option<String>,
+}
+
+/// What a webhook did
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TriggerReport {
+    pub trigger_id: String,
+    pub event: Option<String>,
+    pub outcomes: Vec<TriggerOutcome>,
+}
+
+/// Registered webhook triggers, by ID
+#[derive(Clone, Default)]
+pub struct WebhookTriggers {
+    triggers: Arc<RwLock<HashMap<String, WebhookTrigger>>>,
+}
+
+impl WebhookTriggers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The process-wide triggers the APIs share
+    pub fn global() -> Self {
+        GLOBAL.clone()
+    }
+
+    /// Register `trigger` for `workflow`, replacing one with the same ID
+    pub fn register(
+        &self,
+        trigger: WebhookTrigger,
+        workflow: &WorkflowDefinition,
+    ) -> Result<(), TriggerError> {
+        trigger.validate(workflow)?;
+        info!(
+            "🪝 Registered {} webhook trigger '{}' for workflow '{}'",
+            trigger.source.as_str(),
+            trigger.id,
+            trigger.workflow_id
+        );
+        self.triggers
+            .write()
+            .unwrap()
+            .insert(trigger.id.clone(), trigger);
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Option<WebhookTrigger> {
+        self.triggers.read().unwrap().get(id).cloned()
+    }
+
+    /// Every trigger, optionally only those of a workflow, by ID
+    pub fn list(&self, workflow_id: Option<&str>) -> Vec<WebhookTrigger> {
+        let mut triggers: Vec<_> = self
+            .triggers
+            .read()
+            .unwrap()
+            .values()
+            .filter(|t| workflow_id.is_none_or(|id| t.workflow_id == id))
+            .cloned()
+            .collect();
+        triggers.sort_by(|a, b| a.id.cmp(&b.id));
+        triggers
+    }
+
+    pub fn remove(&self, id: &str) -> Option<WebhookTrigger> {
+        self.triggers.write().unwrap().remove(id)
+    }
+
+    /// Verify a webhook posted to trigger `id` and run the mappings its event applies to
+    pub async fn handle<S: WorkflowStorage + ?Sized>(
+        &self,
+        storage: &S,
+        rules: &RulesEngine,
+        id: &str,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<TriggerReport, TriggerError> {
+        let trigger = self
+            .get(id)
+            .ok_or_else(|| TriggerError::NotFound(id.to_string()))?;
+        verify_signature(&trigger, headers, body, Utc::now().timestamp())?;
+        let payload: Value = serde_json::from_slice(body)
+            .map_err(|e| TriggerError::InvalidPayload(e.to_string()))?;
+        let event = event_name(&trigger, headers, &payload);
+
+        let workflow = storage
+            .get_workflow(&trigger.workflow_id)
+            .await
+            .map_err(|e| TriggerError::Storage(e.to_string()))?
+            .ok_or_else(|| {
+                TriggerError::Invalid(format!(
+                    "Workflow '{}' no longer exists",
+                    trigger.workflow_id
+                ))
+            })?;
+
+        let mut outcomes = Vec::new();
+        for (index, mapping) in trigger.mappings.iter().enumerate() {
+            if !mapping.matches(event.as_deref()) {
+                continue;
+            }
+            let run = MappingRun {
+                storage,
+                rules,
+                workflow: &workflow,
+                trigger: &trigger,
+                event: event.as_deref(),
+                payload: &payload,
+                mapping: index,
+            };
+            match &mapping.action {
+                TriggerAction::CreateResource {
+                    initial_state,
+                    data_pointer,
+                    metadata,
+                } => outcomes.push(
+                    run.create_resource(initial_state.as_ref(), data_pointer.as_deref(), metadata)
+                        .await,
+                ),
+                TriggerAction::FireActivity {
+                    activity_id,
+                    resources,
+                } => outcomes.extend(run.fire_activity(activity_id, resources).await),
+            }
+        }
+
+        info!(
+            "🪝 Webhook trigger '{}' handled event {:?} with {} outcomes",
+            trigger.id,
+            event,
+            outcomes.len()
+        );
+        Ok(TriggerReport {
+            trigger_id: trigger.id,
+            event,
+            outcomes,
+        })
+    }
+}
+
+/// One mapping applied to one webhook
+struct MappingRun<'a, S: WorkflowStorage + ?Sized> {
+    storage: &'a S,
+    rules: &'a RulesEngine,
+    workflow: &'a WorkflowDefinition,
+    trigger: &'a WebhookTrigger,
+    event: Option<&'a str>,
+    payload: &'a Value,
+    mapping: usize,
+}
+
+impl<S: WorkflowStorage + ?Sized> MappingRun<'_, S> {
+    fn outcome(
+        &self,
+        status: TriggerOutcomeStatus,
+        resource_id: Option<Uuid>,
+        activity_id: Option<&ActivityId>,
+        message: Option<String>,
+    ) -> TriggerOutcome {
+        TriggerOutcome {
+            mapping: self.mapping,
+            status,
+            resource_id,
+            activity_id: activity_id.cloned(),
+            message,
+        }
+    }
+
+    async fn create_resource(
+        &self,
+        initial_state: Option<&crate::models::StateId>,
+        data_pointer: Option<&str>,
+        metadata: &HashMap<String, String>,
+    ) -> TriggerOutcome {
+        let state = initial_state
+            .cloned()
+            .unwrap_or_else(|| self.workflow.initial_state.clone());
+        let mut resource = Resource::new(&self.workflow.id, state);
+        resource.workflow_version = self.workflow.version;
+        resource.data = match data_pointer {
+            Some(pointer) => self
+                .payload
+                .pointer(pointer)
+                .cloned()
+                .unwrap_or(Value::Null),
+            None => self.payload.clone(),
+        };
+        for (key, pointer) in metadata {
+            if let Some(value) = self.payload.pointer(pointer) {
+                resource.set_metadata(key, value.clone());
+            }
+        }
+
+        let mut problems = self.workflow.confidential_violations(&resource.metadata);
+        if let Some(schema) = &self.workflow.metadata_schema {
+            if schema.enforcement == crate::models::SchemaEnforcement::Strict {
+                problems.extend(schema.violations(&resource.metadata));
+            }
+        }
+        if !problems.is_empty() {
+            return self.outcome(
+                TriggerOutcomeStatus::Failed,
+                None,
+                None,
+                Some(problems.join("; ")),
+            );
+        }
+
+        match self.storage.create_resource(resource).await {
+            Ok(created) => {
+                self.outcome(TriggerOutcomeStatus::Created, Some(created.id), None, None)
+            }
+            Err(e) => self.outcome(
+                TriggerOutcomeStatus::Failed,
+                None,
+                None,
+                Some(format!("Failed to store resource: {}", e)),
+            ),
+        }
+    }
+
+    async fn fire_activity(
+        &self,
+        activity_id: &ActivityId,
+        selector: &ResourceSelector,
+    ) -> Vec<TriggerOutcome> {
+        let failed = |message: String| {
+            vec![self.outcome(
+                TriggerOutcomeStatus::Failed,
+                None,
+                Some(activity_id),
+                Some(message),
+            )]
+        };
+        let Some(activity) = self
+            .workflow
+            .activities
+            .iter()
+            .find(|a| &a.id == activity_id)
+        else {
+            return failed(format!(
+                "Activity '{}' is no longer in the workflow",
+                activity_id.as_str()
+            ));
+        };
+        let resources = match self.select(selector).await {
+            Ok(resources) => resources,
+            Err(message) => return failed(message),
+        };
+        if resources.is_empty() {
+            return vec![self.outcome(
+                TriggerOutcomeStatus::Skipped,
+                None,
+                Some(activity_id),
+                Some("No resource matches the payload".to_string()),
+            )];
+        }
+
+        let mut outcomes = Vec::new();
+        for resource in resources {
+            let resource_id = resource.id;
+            let (status, message) = match self.fire(activity, resource).await {
+                Ok(()) => (TriggerOutcomeStatus::Fired, None),
+                Err((status, message)) => (status, Some(message)),
+            };
+            outcomes.push(self.outcome(status, Some(resource_id), Some(activity_id), message));
+        }
+        outcomes
+    }
+
+    /// The resources of the workflow `selector` picks from the payload
+    async fn select(&self, selector: &ResourceSelector) -> Result<Vec<Resource>, String> {
+        let (pointer, ids) = match selector {
+            ResourceSelector::Id { pointer } => {
+                let id = payload_text(self.payload, pointer)
+                    .and_then(|id| Uuid::parse_str(&id).ok())
+                    .ok_or_else(|| format!("No resource ID at '{}'", pointer))?;
+                (pointer, vec![id])
+            }
+            ResourceSelector::Correlation { key, pointer } => {
+                let value = payload_text(self.payload, pointer)
+                    .ok_or_else(|| format!("No correlation value at '{}'", pointer))?;
+                (
+                    pointer,
+                    CorrelationIndex::global().resource_ids(key, &value),
+                )
+            }
+        };
+
+        let mut resources = Vec::new();
+        for id in ids {
+            match self.storage.get_resource(&id).await {
+                Ok(Some(resource)) if resource.workflow_id == self.workflow.id => {
+                    resources.push(resource)
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    return Err(format!(
+                        "Failed to load resource selected by '{}': {}",
+                        pointer, e
+                    ))
+                }
+            }
+        }
+        Ok(resources)
+    }
+
+    async fn fire(
+        &self,
+        activity: &ActivityDefinition,
+        mut resource: Resource,
+    ) -> Result<(), (TriggerOutcomeStatus, String)> {
+        let skipped = |message: String| Err((TriggerOutcomeStatus::Skipped, message));
+        if resource.is_halted() {
+            return skipped(format!(
+                "Resource is {}",
+                resource.lifecycle_status().as_str()
+            ));
+        }
+        if !activity.can_execute_on(&resource) {
+            return skipped(format!(
+                "Activity cannot run from state '{}'",
+                resource.state.as_str()
+            ));
+        }
+        if !self.rules.can_execute_activity(&resource, activity) {
+            return skipped("Activity rules do not pass".to_string());
+        }
+        let to_state = self.rules.target_state(&resource, activity);
+        if self.workflow.state_capacity(to_state).is_some() {
+            let occupancy = self
+                .storage
+                .count_resources_by_state(&self.workflow.id)
+                .await
+                .map_err(|e| (TriggerOutcomeStatus::Failed, e.to_string()))?;
+            let check = self
+                .rules
+                .check_capacity(&resource, activity, self.workflow, &occupancy);
+            if !check.is_admitted() {
+                return skipped(format!("State '{}' is at capacity", to_state.as_str()));
+            }
+        }
+
+        super::parallel::execute(&mut resource, activity)
+            .map_err(|e| (TriggerOutcomeStatus::Failed, e.to_string()))?;
+        if let Some(event) = resource.history.last_mut() {
+            let firing = json!({ "id": self.trigger.id, "event": self.event });
+            match &mut event.data {
+                Some(Value::Object(data)) => {
+                    data.insert(TRIGGER_KEY.to_string(), firing);
+                }
+                data => *data = Some(json!({ TRIGGER_KEY: firing })),
+            }
+        }
+        let updated = self
+            .storage
+            .update_resource(resource)
+            .await
+            .map_err(|e| (TriggerOutcomeStatus::Failed, e.to_string()))?;
+        self.rules.capacity_queues().withdraw(&updated.id);
+        Ok(())
+    }
+}
+
+/// A string, number or boolean in the payload at `pointer`, as text
+fn payload_text(payload: &Value, pointer: &str) -> Option<String> {
+    match payload.pointer(pointer)? {
+        Value::String(value) => Some(value.clone()),
+        Value::Number(value) => Some(value.to_string()),
+        Value::Bool(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Whether `signature` is the hex HMAC-SHA256 of `message` under `secret`
+fn signature_matches(secret: &str, message: &[u8], signature: &str) -> bool {
+    let Some(tag) = decode_hex(signature.trim()) else {
+        return false;
+    };
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    hmac::verify(&key, message, &tag).is_ok()
+}
+
+/// Check the request carries a valid signature of `body` under the trigger's secret
+pub fn verify_signature(
+    trigger: &WebhookTrigger,
+    headers: &HeaderMap,
+    body: &[u8],
+    now: i64,
+) -> Result<(), TriggerError> {
+    let signature =
+        header(headers, trigger.signature_header()).ok_or(TriggerError::InvalidSignature)?;
+    let valid = match trigger.source {
+        TriggerSource::Github => signature
+            .strip_prefix("sha256=")
+            .is_some_and(|hex| signature_matches(&trigger.secret, body, hex)),
+        TriggerSource::Stripe => {
+            let mut timestamp = None;
+            let mut candidates = Vec::new();
+            for part in signature.split(',') {
+                match part.trim().split_once('=') {
+                    Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+                    Some(("v1", value)) => candidates.push(value),
+                    _ => {}
+                }
+            }
+            let Some(timestamp) = timestamp else {
+                return Err(TriggerError::InvalidSignature);
+            };
+            if (now - timestamp).abs() > STRIPE_TOLERANCE_SECS {
+                return Err(TriggerError::InvalidSignature);
+            }
+            let mut message = format!("{}.", timestamp).into_bytes();
+            message.extend_from_slice(body);
+            candidates
+                .iter()
+                .any(|hex| signature_matches(&trigger.secret, &message, hex))
+        }
+        TriggerSource::Generic => {
+            let hex = signature.strip_prefix("sha256=").unwrap_or(signature);
+            signature_matches(&trigger.secret, body, hex)
+        }
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(TriggerError::InvalidSignature)
+    }
+}
+
+/// Name of the event a webhook reports, if it names one
+pub fn event_name(
+    trigger: &WebhookTrigger,
+    headers: &HeaderMap,
+    payload: &Value,
+) -> Option<String> {
+    match trigger.source {
+        TriggerSource::Github => {
+            let event = header(headers, "X-GitHub-Event")?;
+            match payload.get("action").and_then(Value::as_str) {
+                Some(action) => Some(format!("{}.{}", event, action)),
+                None => Some(event.to_string()),
+            }
+        }
+        TriggerSource::Stripe => payload
+            .get("type")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        TriggerSource::Generic => trigger
+            .event_pointer
+            .as_deref()
+            .and_then(|pointer| payload_text(payload, pointer)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::storage::InMemoryStorage;
+    use crate::models::{ActivityDefinition, StateId};
+
+    fn sign(secret: &str, message: &[u8]) -> String {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+        hmac::sign(&key, message)
+            .as_ref()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    fn headers(pairs: &[(&'static str, String)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_verify_signatures() {
+        let body = br#"{"type":"invoice.paid"}"#;
+        let github = WebhookTrigger::new("gh", "wf", TriggerSource::Github, "secret");
+        let valid = headers(&[(
+            "x-hub-signature-256",
+            format!("sha256={}", sign("secret", body)),
+        )]);
+        assert!(verify_signature(&github, &valid, body, 0).is_ok());
+        assert_eq!(
+            verify_signature(&github, &valid, b"{}", 0),
+            Err(TriggerError::InvalidSignature)
+        );
+        assert_eq!(
+            verify_signature(&github, &HeaderMap::new(), body, 0),
+            Err(TriggerError::InvalidSignature)
+        );
+
+        let stripe = WebhookTrigger::new("st", "wf", TriggerSource::Stripe, "whsec");
+        let signed = format!("1700000000.{}", std::str::from_utf8(body).unwrap());
+        let valid = headers(&[(
+            "stripe-signature",
+            format!("t=1700000000,v1={}", sign("whsec", signed.as_bytes())),
+        )]);
+        assert!(verify_signature(&stripe, &valid, body, 1700000100).is_ok());
+        assert_eq!(
+            verify_signature(&stripe, &valid, body, 1700001000),
+            Err(TriggerError::InvalidSignature)
+        );
+        assert_eq!(
+            event_name(&stripe, &valid, &serde_json::from_slice(body).unwrap()).as_deref(),
+            Some("invoice.paid")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handle_creates_and_fires() {
+        let storage = InMemoryStorage::default();
+        let workflow = WorkflowDefinition::new(
+            "pull-requests",
+            "Pull requests",
+            vec![StateId::from("open"), StateId::from("merged")],
+            vec![ActivityDefinition::new("merge", vec!["open"], "merged")],
+            "open",
+        );
+        storage.create_workflow(workflow.clone()).await.unwrap();
+
+        let mut metadata = HashMap::new();
+        metadata.insert("pr".to_string(), "/number".to_string());
+        let trigger =
+            WebhookTrigger::new("github-prs", "pull-requests", TriggerSource::Github, "s")
+                .with_mapping(
+                    Some("pull_request.opened"),
+                    TriggerAction::CreateResource {
+                        initial_state: None,
+                        data_pointer: Some("/pull_request".to_string()),
+                        metadata,
+                    },
+                )
+                .with_mapping(
+                    Some("pull_request.closed"),
+                    TriggerAction::FireActivity {
+                        activity_id: ActivityId::from("merge"),
+                        resources: ResourceSelector::Correlation {
+                            key: "pr".to_string(),
+                            pointer: "/number".to_string(),
+                        },
+                    },
+                );
+        let triggers = WebhookTriggers::new();
+        triggers.register(trigger, &workflow).unwrap();
+        let rules = RulesEngine::new();
+
+        let deliver = |action: &str| {
+            let body =
+                json!({ "action": action, "number": 4711, "pull_request": { "title": "Fix" } })
+                    .to_string();
+            let headers = headers(&[
+                ("x-github-event", "pull_request".to_string()),
+                (
+                    "x-hub-signature-256",
+                    format!("sha256={}", sign("s", body.as_bytes())),
+                ),
+            ]);
+            (headers, body)
+        };
+
+        let (headers, body) = deliver("opened");
+        let report = triggers
+            .handle(&storage, &rules, "github-prs", &headers, body.as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(report.event.as_deref(), Some("pull_request.opened"));
+        assert_eq!(report.outcomes[0].status, TriggerOutcomeStatus::Created);
+        let id = report.outcomes[0].resource_id.unwrap();
+        let created = storage.get_resource(&id).await.unwrap().unwrap();
+        assert_eq!(created.data, json!({ "title": "Fix" }));
+
+        let (headers, body) = deliver("closed");
+        let report = triggers
+            .handle(&storage, &rules, "github-prs", &headers, body.as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(report.outcomes.len(), 1);
+        assert_eq!(report.outcomes[0].status, TriggerOutcomeStatus::Fired);
+        let merged = storage.get_resource(&id).await.unwrap().unwrap();
+        assert_eq!(merged.state, StateId::from("merged"));
+        assert_eq!(
+            merged.history.last().unwrap().data,
+            Some(json!({ "trigger": { "id": "github-prs", "event": "pull_request.closed" } }))
+        );
+
+        // The resource has moved on, so a repeated delivery is skipped
+        let report = triggers
+            .handle(&storage, &rules, "github-prs", &headers, body.as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(report.outcomes[0].status, TriggerOutcomeStatus::Skipped);
+    }
+}
@@ -0,0 +1,503 @@
+// Workflow metrics
+// Throughput and time-in-state figures per workflow, for finding bottlenecks
+
+//! # Workflow Metrics
+//!
+//! [`collect_workflow_metrics`] aggregates what a workflow's resources went through,
+//! read from their histories:
+//!
+//! - how many resources were created, are still moving, finished in a terminal state
+//!   or were cancelled, and how many finished within the last hour
+//! - how often each activity fired, in total and within the last hour
+//! - per state, how long completed stays took: mean, p50, p90, p99 and longest, next
+//!   to how many resources sit in the state now
+//! - how the finished resources are distributed over the terminal states
+//!
+//! States where stays run long, or many resources pile up, are the bottlenecks.
+//! [`WorkflowMetricsCollector`] refreshes the figures of every workflow on a tick into
+//! the process-wide [`WorkflowMetrics`] registry, which is rendered in the Prometheus
+//! text format on the GraphQL server's `/metrics` endpoint. The `workflowMetrics`
+//! GraphQL query collects a workflow's figures on demand.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::sync::{Arc, RwLock};
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use super::sla::completed_stays;
+use super::storage::WorkflowStorage;
+use crate::models::{ActivityId, LifecycleStatus, StateId};
+use crate::{CircuitBreakerError, Result};
+
+/// How often the collector refreshes every workflow's metrics by default
+pub const METRICS_COLLECT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+lazy_static::lazy_static! {
+    static ref GLOBAL: WorkflowMetrics = WorkflowMetrics::new();
+}
+
+/// How long resources stay in one state
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StateTimeMetrics {
+    pub state: StateId,
+    /// Resources in the state now
+    pub in_state: usize,
+    /// Stays in the state that have ended
+    pub completed_stays: usize,
+    pub mean_secs: Option<f64>,
+    pub p50_secs: Option<f64>,
+    pub p90_secs: Option<f64>,
+    pub p99_secs: Option<f64>,
+    pub max_secs: Option<f64>,
+}
+
+/// How often one activity fired
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ActivityCount {
+    pub activity: ActivityId,
+    pub total: u64,
+    pub last_hour: u64,
+}
+
+/// Throughput and time-in-state figures of one workflow
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WorkflowMetricsSnapshot {
+    pub workflow_id: String,
+    pub resources_created: u64,
+    /// Resources neither finished nor cancelled
+    pub resources_active: u64,
+    /// Resources in a terminal state
+    pub resources_completed: u64,
+    pub resources_cancelled: u64,
+    /// Resources that reached a terminal state within the last hour
+    pub completed_last_hour: u64,
+    pub activities_fired: u64,
+    pub activities_fired_last_hour: u64,
+    /// By activity ID
+    pub activities: Vec<ActivityCount>,
+    /// By state, every state of the workflow
+    pub time_in_state: Vec<StateTimeMetrics>,
+    /// Finished resources by terminal state
+    pub terminal_states: BTreeMap<String, u64>,
+    pub collected_at: DateTime<Utc>,
+}
+
+/// Value at `percentile` of ascending `sorted` values, by nearest rank
+fn percentile(sorted: &[f64], percentile: f64) -> Option<f64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (percentile / 100.0 * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+/// Aggregate the metrics of `workflow_id` from its resources as of `now`
+pub async fn collect_workflow_metrics<S: WorkflowStorage + ?Sized>(
+    storage: &S,
+    workflow_id: &str,
+    now: DateTime<Utc>,
+) -> Result<WorkflowMetricsSnapshot> {
+    let workflow = storage.get_workflow(workflow_id).await?.ok_or_else(|| {
+        CircuitBreakerError::WorkflowNotFound {
+            id: workflow_id.to_string(),
+        }
+    })?;
+    let resources = storage.list_resources(Some(workflow_id)).await?;
+    let hour_ago = now - Duration::hours(1);
+
+    let mut snapshot = WorkflowMetricsSnapshot {
+        workflow_id: workflow_id.to_string(),
+        resources_created: resources.len() as u64,
+        resources_active: 0,
+        resources_completed: 0,
+        resources_cancelled: 0,
+        completed_last_hour: 0,
+        activities_fired: 0,
+        activities_fired_last_hour: 0,
+        activities: Vec::new(),
+        time_in_state: Vec::new(),
+        terminal_states: BTreeMap::new(),
+        collected_at: now,
+    };
+    let mut activities: BTreeMap<String, ActivityCount> = BTreeMap::new();
+    let mut stays: HashMap<StateId, Vec<f64>> = HashMap::new();
+    let mut in_state: HashMap<&StateId, usize> = HashMap::new();
+
+    for resource in &resources {
+        for event in &resource.history {
+            let count = activities
+                .entry(event.activity.as_str().to_string())
+                .or_insert_with(|| ActivityCount {
+                    activity: event.activity.clone(),
+                    total: 0,
+                    last_hour: 0,
+                });
+            count.total += 1;
+            if event.timestamp > hour_ago {
+                count.last_hour += 1;
+            }
+        }
+        for (state, stay) in completed_stays(resource) {
+            stays
+                .entry(state)
+                .or_default()
+                .push(stay.num_milliseconds() as f64 / 1000.0);
+        }
+        *in_state.entry(&resource.state).or_default() += 1;
+
+        if resource.lifecycle_status() == LifecycleStatus::Cancelled {
+            snapshot.resources_cancelled += 1;
+        } else if workflow.is_terminal_state(&resource.state) && !resource.is_split() {
+            snapshot.resources_completed += 1;
+            *snapshot
+                .terminal_states
+                .entry(resource.state.as_str().to_string())
+                .or_default() += 1;
+            if resource.entered_state_at() > hour_ago {
+                snapshot.completed_last_hour += 1;
+            }
+        } else {
+            snapshot.resources_active += 1;
+        }
+    }
+
+    snapshot.activities_fired = activities.values().map(|a| a.total).sum();
+    snapshot.activities_fired_last_hour = activities.values().map(|a| a.last_hour).sum();
+    snapshot.activities = activities.into_values().collect();
+    snapshot.time_in_state = workflow
+        .states
+        .iter()
+        .map(|state| {
+            let mut secs = stays.remove(state).unwrap_or_default();
+            secs.sort_by(f64::total_cmp);
+            StateTimeMetrics {
+                state: state.clone(),
+                in_state: in_state.get(state).copied().unwrap_or(0),
+                completed_stays: secs.len(),
+                mean_secs: (!secs.is_empty()).then(|| secs.iter().sum::<f64>() / secs.len() as f64),
+                p50_secs: percentile(&secs, 50.0),
+                p90_secs: percentile(&secs, 90.0),
+                p99_secs: percentile(&secs, 99.0),
+                max_secs: secs.last().copied(),
+            }
+        })
+        .collect();
+    Ok(snapshot)
+}
+
+/// Latest collected metrics of every workflow
+///
+/// Clones share the same registry.
+#[derive(Debug, Clone, Default)]
+pub struct WorkflowMetrics {
+    workflows: Arc<RwLock<BTreeMap<String, WorkflowMetricsSnapshot>>>,
+}
+
+impl WorkflowMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The process-wide registry the collector fills and `/metrics` renders
+    pub fn global() -> Self {
+        GLOBAL.clone()
+    }
+
+    /// Keep `snapshot` as the latest metrics of its workflow
+    pub fn record(&self, snapshot: WorkflowMetricsSnapshot) {
+        self.workflows
+            .write()
+            .unwrap()
+            .insert(snapshot.workflow_id.clone(), snapshot);
+    }
+
+    /// Latest metrics of `workflow_id`, if collected
+    pub fn get(&self, workflow_id: &str) -> Option<WorkflowMetricsSnapshot> {
+        self.workflows.read().unwrap().get(workflow_id).cloned()
+    }
+
+    /// Forget workflows not in `workflow_ids`, e.g. after they were deleted
+    pub fn retain(&self, workflow_ids: &[String]) {
+        self.workflows
+            .write()
+            .unwrap()
+            .retain(|id, _| workflow_ids.contains(id));
+    }
+
+    /// Render the metrics in the Prometheus text exposition format
+    pub fn render_prometheus(&self) -> String {
+        let workflows = self.workflows.read().unwrap();
+        let mut out = String::new();
+
+        let _ = writeln!(
+            out,
+            "# HELP circuit_breaker_workflow_resources Resources of a workflow by status\n\
+             # TYPE circuit_breaker_workflow_resources gauge"
+        );
+        for (id, metrics) in workflows.iter() {
+            for (status, count) in [
+                ("active", metrics.resources_active),
+                ("completed", metrics.resources_completed),
+                ("cancelled", metrics.resources_cancelled),
+            ] {
+                let _ = writeln!(
+                    out,
+                    "circuit_breaker_workflow_resources{{workflow=\"{}\",status=\"{}\"}} {}",
+                    id, status, count
+                );
+            }
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP circuit_breaker_workflow_activities_fired Activities fired per workflow and activity\n\
+             # TYPE circuit_breaker_workflow_activities_fired gauge"
+        );
+        for (id, metrics) in workflows.iter() {
+            for activity in &metrics.activities {
+                let _ = writeln!(
+                    out,
+                    "circuit_breaker_workflow_activities_fired{{workflow=\"{}\",activity=\"{}\"}} {}",
+                    id,
+                    activity.activity.as_str(),
+                    activity.total
+                );
+            }
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP circuit_breaker_workflow_completed_last_hour Resources that reached a terminal state within the last hour\n\
+             # TYPE circuit_breaker_workflow_completed_last_hour gauge"
+        );
+        for (id, metrics) in workflows.iter() {
+            let _ = writeln!(
+                out,
+                "circuit_breaker_workflow_completed_last_hour{{workflow=\"{}\"}} {}",
+                id, metrics.completed_last_hour
+            );
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP circuit_breaker_workflow_terminal_resources Finished resources by terminal state\n\
+             # TYPE circuit_breaker_workflow_terminal_resources gauge"
+        );
+        for (id, metrics) in workflows.iter() {
+            for (state, count) in &metrics.terminal_states {
+                let _ = writeln!(
+                    out,
+                    "circuit_breaker_workflow_terminal_resources{{workflow=\"{}\",state=\"{}\"}} {}",
+                    id, state, count
+                );
+            }
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP circuit_breaker_workflow_state_seconds Time resources spent in a state\n\
+             # TYPE circuit_breaker_workflow_state_seconds summary"
+        );
+        for (id, metrics) in workflows.iter() {
+            for state in &metrics.time_in_state {
+                let labels = format!("workflow=\"{}\",state=\"{}\"", id, state.state.as_str());
+                for (quantile, value) in [
+                    ("0.5", state.p50_secs),
+                    ("0.9", state.p90_secs),
+                    ("0.99", state.p99_secs),
+                ] {
+                    if let Some(value) = value {
+                        let _ = writeln!(
+                            out,
+                            "circuit_breaker_workflow_state_seconds{{{},quantile=\"{}\"}} {}",
+                            labels, quantile, value
+                        );
+                    }
+                }
+                let _ = writeln!(
+                    out,
+                    "circuit_breaker_workflow_state_seconds_sum{{{}}} {}\n\
+                     circuit_breaker_workflow_state_seconds_count{{{}}} {}",
+                    labels,
+                    state.mean_secs.unwrap_or(0.0) * state.completed_stays as f64,
+                    labels,
+                    state.completed_stays
+                );
+            }
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP circuit_breaker_workflow_state_resources Resources in a state now\n\
+             # TYPE circuit_breaker_workflow_state_resources gauge"
+        );
+        for (id, metrics) in workflows.iter() {
+            for state in &metrics.time_in_state {
+                let _ = writeln!(
+                    out,
+                    "circuit_breaker_workflow_state_resources{{workflow=\"{}\",state=\"{}\"}} {}",
+                    id,
+                    state.state.as_str(),
+                    state.in_state
+                );
+            }
+        }
+
+        out
+    }
+}
+
+/// Tick loop refreshing the metrics of every workflow
+#[derive(Clone)]
+pub struct WorkflowMetricsCollector {
+    storage: Arc<dyn WorkflowStorage>,
+    metrics: WorkflowMetrics,
+}
+
+impl WorkflowMetricsCollector {
+    pub fn new(storage: Arc<dyn WorkflowStorage>) -> Self {
+        Self {
+            storage,
+            metrics: WorkflowMetrics::global(),
+        }
+    }
+
+    /// Record into `metrics` instead of the global registry
+    pub fn with_metrics(mut self, metrics: WorkflowMetrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Collect the metrics of every workflow as of `now`
+    ///
+    /// A workflow whose metrics cannot be collected keeps its previous figures.
+    pub async fn collect(&self, now: DateTime<Utc>) -> Result<usize> {
+        let workflows = self.storage.list_workflows().await?;
+        let ids: Vec<String> = workflows.iter().map(|w| w.id.clone()).collect();
+        self.metrics.retain(&ids);
+        let mut collected = 0;
+        for id in &ids {
+            match collect_workflow_metrics(self.storage.as_ref(), id, now).await {
+                Ok(snapshot) => {
+                    self.metrics.record(snapshot);
+                    collected += 1;
+                }
+                Err(e) => warn!("Failed to collect metrics of workflow '{}': {}", id, e),
+            }
+        }
+        Ok(collected)
+    }
+
+    /// Spawn the tick loop, collecting every `interval`
+    pub fn spawn(self, interval: std::time::Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.collect(Utc::now()).await {
+                    warn!("Failed to collect workflow metrics: {}", e);
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::storage::InMemoryStorage;
+    use crate::models::{ActivityDefinition, HistoryEvent, Resource, WorkflowDefinition};
+
+    fn review_workflow() -> WorkflowDefinition {
+        WorkflowDefinition::new(
+            "review",
+            "Review",
+            vec![
+                StateId::from("draft"),
+                StateId::from("review"),
+                StateId::from("published"),
+                StateId::from("rejected"),
+            ],
+            vec![
+                ActivityDefinition::new("submit", vec!["draft"], "review"),
+                ActivityDefinition::new("publish", vec!["review"], "published"),
+                ActivityDefinition::new("reject", vec!["review"], "rejected"),
+            ],
+            "draft",
+        )
+    }
+
+    /// A resource created at `start` that moved along `steps` of (activity, state, secs later)
+    fn resource_through(start: DateTime<Utc>, steps: &[(&str, &str, i64)]) -> Resource {
+        let mut resource = Resource::new("review", StateId::from("draft"));
+        resource.created_at = start;
+        let mut at = start;
+        for (activity, state, secs) in steps {
+            at += Duration::seconds(*secs);
+            resource.history.push(HistoryEvent {
+                timestamp: at,
+                activity: ActivityId::from(*activity),
+                from: resource.state.clone(),
+                to: StateId::from(*state),
+                data: None,
+            });
+            resource.state = StateId::from(*state);
+        }
+        resource
+    }
+
+    #[tokio::test]
+    async fn test_collect_workflow_metrics() {
+        let storage = InMemoryStorage::default();
+        storage.create_workflow(review_workflow()).await.unwrap();
+        let now = Utc::now();
+        let day_ago = now - Duration::days(1);
+        for resource in [
+            resource_through(
+                day_ago,
+                &[("submit", "review", 10), ("publish", "published", 100)],
+            ),
+            resource_through(
+                day_ago,
+                &[("submit", "review", 20), ("reject", "rejected", 300)],
+            ),
+            resource_through(now - Duration::minutes(30), &[("submit", "review", 60)]),
+            resource_through(now, &[]),
+        ] {
+            storage.create_resource(resource).await.unwrap();
+        }
+
+        let metrics = collect_workflow_metrics(&storage, "review", now)
+            .await
+            .unwrap();
+        assert_eq!(metrics.resources_created, 4);
+        assert_eq!(metrics.resources_active, 2);
+        assert_eq!(metrics.resources_completed, 2);
+        assert_eq!(metrics.completed_last_hour, 0);
+        assert_eq!(metrics.activities_fired, 5);
+        assert_eq!(metrics.activities_fired_last_hour, 1);
+        assert_eq!(metrics.terminal_states["published"], 1);
+        assert_eq!(metrics.terminal_states["rejected"], 1);
+
+        let review = &metrics.time_in_state[1];
+        assert_eq!(review.state, StateId::from("review"));
+        assert_eq!(review.in_state, 1);
+        assert_eq!(review.completed_stays, 2);
+        assert_eq!(review.mean_secs, Some(200.0));
+        assert_eq!(review.p50_secs, Some(100.0));
+        assert_eq!(review.p99_secs, Some(300.0));
+
+        let registry = WorkflowMetrics::new();
+        registry.record(metrics);
+        let text = registry.render_prometheus();
+        assert!(text.contains(
+            "circuit_breaker_workflow_state_seconds{workflow=\"review\",state=\"review\",quantile=\"0.9\"} 300"
+        ));
+        assert!(text.contains(
+            "circuit_breaker_workflow_terminal_resources{workflow=\"review\",state=\"rejected\"} 1"
+        ));
+    }
+}
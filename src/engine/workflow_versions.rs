@@ -0,0 +1,308 @@
+// Workflow definition versioning
+// Publishes new versions of a definition and migrates in-flight resources onto them
+
+//! # Workflow Versions
+//!
+//! A workflow definition evolves by publishing new versions under the same ID with
+//! [`publish_version`]: the new version becomes the current definition, and earlier
+//! versions stay addressable through
+//! [`WorkflowStorage::get_workflow_version`]. Resources record the version they were
+//! created under in `workflow_version`.
+//!
+//! Activities run against the current definition, so a resource sitting in a state the
+//! new version dropped would be stranded. A [`WorkflowMigration`] moves the resources of
+//! one version onto the current one, mapping each old state to a new one. States both
+//! versions have map to themselves unless the mapping says otherwise; every state the
+//! new version dropped must be mapped. The migration is validated before any resource
+//! is touched, and a dry run reports the moves without storing them.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::info;
+use uuid::Uuid;
+
+use super::storage::WorkflowStorage;
+use crate::models::{StateId, WorkflowDefinition};
+use crate::{CircuitBreakerError, Result};
+
+/// Publish `definition` as the next version of the workflow `id`
+///
+/// The definition's ID and version are set from the current one, and its provenance
+/// carried over; the previous versions stay stored.
+pub async fn publish_version<S: WorkflowStorage + ?Sized>(
+    storage: &S,
+    id: &str,
+    mut definition: WorkflowDefinition,
+) -> Result<WorkflowDefinition> {
+    let current = storage
+        .get_workflow(id)
+        .await?
+        .ok_or_else(|| CircuitBreakerError::WorkflowNotFound { id: id.to_string() })?;
+
+    definition.id = current.id;
+    definition.version = current.version + 1;
+    definition.forked_from = current.forked_from;
+    definition
+        .validate()
+        .map_err(|e| CircuitBreakerError::InvalidInput(format!("Invalid workflow: {}", e)))?;
+
+    let published = storage.create_workflow(definition).await?;
+    info!(
+        "📦 Published version {} of workflow {}",
+        published.version, published.id
+    );
+    Ok(published)
+}
+
+/// Moves the resources of one version of a workflow onto its current version
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowMigration {
+    pub workflow_id: String,
+    /// Version whose resources are migrated
+    pub from_version: u32,
+    /// Version to migrate to, which must be the current one
+    pub to_version: u32,
+    /// State of `from_version` to state of `to_version`; states of both map to
+    /// themselves unless listed
+    #[serde(default)]
+    pub state_mapping: HashMap<StateId, StateId>,
+}
+
+/// A resource moved, or to be moved, by a migration
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MigratedResource {
+    pub resource_id: Uuid,
+    pub from_state: StateId,
+    pub to_state: StateId,
+}
+
+/// Outcome of a migration
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationReport {
+    pub workflow_id: String,
+    pub from_version: u32,
+    pub to_version: u32,
+    /// Whether the moves were only planned, not stored
+    pub dry_run: bool,
+    pub resources: Vec<MigratedResource>,
+}
+
+impl WorkflowMigration {
+    /// Check the migration against both versions, returning the state each old state
+    /// maps to
+    pub fn validate(
+        &self,
+        from: &WorkflowDefinition,
+        to: &WorkflowDefinition,
+    ) -> Result<HashMap<StateId, StateId>> {
+        let invalid = |message: String| Err(CircuitBreakerError::InvalidInput(message));
+
+        for (old, new) in &self.state_mapping {
+            if !from.states.contains(old) {
+                return invalid(format!(
+                    "State '{}' is not a state of version {}",
+                    old.as_str(),
+                    from.version
+                ));
+            }
+            if !to.states.contains(new) {
+                return invalid(format!(
+                    "State '{}' is not a state of version {}",
+                    new.as_str(),
+                    to.version
+                ));
+            }
+        }
+
+        let mut mapping = HashMap::with_capacity(from.states.len());
+        for state in &from.states {
+            let target = match self.state_mapping.get(state) {
+                Some(target) => target.clone(),
+                None if to.states.contains(state) => state.clone(),
+                None => {
+                    return invalid(format!(
+                        "State '{}' of version {} does not exist in version {} and is not mapped",
+                        state.as_str(),
+                        from.version,
+                        to.version
+                    ))
+                }
+            };
+            mapping.insert(state.clone(), target);
+        }
+        Ok(mapping)
+    }
+
+    /// Migrate the resources of `from_version`, or only plan it when `dry_run` is set
+    ///
+    /// Fails without moving anything when a version is missing, `to_version` is not the
+    /// current version, or the mapping is invalid.
+    pub async fn run<S: WorkflowStorage + ?Sized>(
+        &self,
+        storage: &S,
+        dry_run: bool,
+    ) -> Result<MigrationReport> {
+        let current = storage
+            .get_workflow(&self.workflow_id)
+            .await?
+            .ok_or_else(|| CircuitBreakerError::WorkflowNotFound {
+                id: self.workflow_id.clone(),
+            })?;
+        if self.to_version != current.version {
+            return Err(CircuitBreakerError::InvalidInput(format!(
+                "Resources can only be migrated to the current version {} of workflow {}",
+                current.version, self.workflow_id
+            )));
+        }
+        let from = storage
+            .get_workflow_version(&self.workflow_id, self.from_version)
+            .await?
+            .ok_or_else(|| {
+                CircuitBreakerError::NotFound(format!(
+                    "Version {} of workflow {}",
+                    self.from_version, self.workflow_id
+                ))
+            })?;
+        let mapping = self.validate(&from, &current)?;
+
+        // Plan every move before storing any, so a stray resource fails the whole run
+        let mut moves = Vec::new();
+        for resource in storage.list_resources(Some(&self.workflow_id)).await? {
+            if resource.workflow_version != self.from_version {
+                continue;
+            }
+            let to_state = mapping.get(&resource.state).cloned().ok_or_else(|| {
+                CircuitBreakerError::InvalidInput(format!(
+                    "Resource {} is in state '{}', which version {} does not have",
+                    resource.id,
+                    resource.state.as_str(),
+                    self.from_version
+                ))
+            })?;
+            moves.push((resource, to_state));
+        }
+
+        let mut resources = Vec::with_capacity(moves.len());
+        for (mut resource, to_state) in moves {
+            resources.push(MigratedResource {
+                resource_id: resource.id,
+                from_state: resource.state.clone(),
+                to_state: to_state.clone(),
+            });
+            if !dry_run {
+                resource.state = to_state;
+                resource.workflow_version = current.version;
+                resource.updated_at = Utc::now();
+                storage.update_resource(resource).await?;
+            }
+        }
+
+        if !dry_run {
+            info!(
+                "🚚 Migrated {} resources of workflow {} from version {} to {}",
+                resources.len(),
+                self.workflow_id,
+                self.from_version,
+                self.to_version
+            );
+        }
+        Ok(MigrationReport {
+            workflow_id: self.workflow_id.clone(),
+            from_version: self.from_version,
+            to_version: self.to_version,
+            dry_run,
+            resources,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::storage::InMemoryStorage;
+    use crate::models::{ActivityDefinition, Resource};
+
+    fn review_v1() -> WorkflowDefinition {
+        WorkflowDefinition::new(
+            "review",
+            "Review",
+            vec![
+                StateId::from("draft"),
+                StateId::from("legal_review"),
+                StateId::from("approved"),
+            ],
+            vec![
+                ActivityDefinition::new("submit", vec!["draft"], "legal_review"),
+                ActivityDefinition::new("approve", vec!["legal_review"], "approved"),
+            ],
+            "draft",
+        )
+    }
+
+    fn review_v2() -> WorkflowDefinition {
+        WorkflowDefinition::new(
+            "review",
+            "Review",
+            vec![
+                StateId::from("draft"),
+                StateId::from("in_review"),
+                StateId::from("approved"),
+            ],
+            vec![
+                ActivityDefinition::new("submit", vec!["draft"], "in_review"),
+                ActivityDefinition::new("approve", vec!["in_review"], "approved"),
+            ],
+            "draft",
+        )
+    }
+
+    #[tokio::test]
+    async fn test_publish_and_migrate() {
+        let storage = InMemoryStorage::default();
+        storage.create_workflow(review_v1()).await.unwrap();
+        let mut in_review = Resource::new("review", StateId::from("draft"));
+        in_review.execute_activity(StateId::from("legal_review"), "submit".into());
+        let in_review = storage.create_resource(in_review).await.unwrap();
+        let draft = storage
+            .create_resource(Resource::new("review", StateId::from("draft")))
+            .await
+            .unwrap();
+
+        let v2 = publish_version(&storage, "review", review_v2())
+            .await
+            .unwrap();
+        assert_eq!(v2.version, 2);
+        let v1 = storage.get_workflow_version("review", 1).await.unwrap();
+        assert!(v1.unwrap().states.contains(&StateId::from("legal_review")));
+
+        let mut migration = WorkflowMigration {
+            workflow_id: "review".to_string(),
+            from_version: 1,
+            to_version: 2,
+            state_mapping: HashMap::new(),
+        };
+        let error = migration.run(&storage, false).await.unwrap_err();
+        assert!(error.to_string().contains("'legal_review'"));
+
+        migration
+            .state_mapping
+            .insert(StateId::from("legal_review"), StateId::from("in_review"));
+        let planned = migration.run(&storage, true).await.unwrap();
+        assert_eq!(planned.resources.len(), 2);
+        let stored = storage.get_resource(&in_review.id).await.unwrap().unwrap();
+        assert_eq!(stored.workflow_version, 1);
+
+        migration.run(&storage, false).await.unwrap();
+        let stored = storage.get_resource(&in_review.id).await.unwrap().unwrap();
+        assert_eq!(stored.state, StateId::from("in_review"));
+        assert_eq!(stored.workflow_version, 2);
+        let stored = storage.get_resource(&draft.id).await.unwrap().unwrap();
+        assert_eq!(stored.state, StateId::from("draft"));
+        assert_eq!(stored.workflow_version, 2);
+
+        // Nothing is left on version 1
+        let again = migration.run(&storage, false).await.unwrap();
+        assert!(again.resources.is_empty());
+    }
+}
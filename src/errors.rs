@@ -0,0 +1,151 @@
+//! Stable error codes
+//!
+//! Error messages are meant for humans and may change between releases. The codes in
+//! this module are the machine-readable contract: every REST error body, GraphQL error
+//! and MCP error carries one, so clients can branch on failures without parsing text.
+//!
+//! - REST: `{"error": {"message": ..., "type": ..., "error_code": "WORKFLOW_NOT_FOUND"}}`
+//! - GraphQL: `{"errors": [{"message": ..., "extensions": {"code": "WORKFLOW_NOT_FOUND"}}]}`
+//! - MCP: `{"error": {"code": -32600, "message": ..., "data": {"error_code": "WORKFLOW_NOT_FOUND"}}}`
+//!
+//! Codes are only ever added, never renamed or removed.
+
+use serde::{Deserialize, Serialize};
+
+/// Machine-readable error code shared by every API surface
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// Input failed validation or could not be parsed
+    InvalidInput,
+    /// A workflow definition does not exist
+    WorkflowNotFound,
+    /// A resource does not exist
+    ResourceNotFound,
+    /// An agent definition does not exist
+    AgentNotFound,
+    /// An agent execution does not exist
+    ExecutionNotFound,
+    /// A rule does not exist
+    RuleNotFound,
+    /// The requested model is unknown or unsupported
+    ModelNotFound,
+    /// Any other missing entity
+    NotFound,
+    /// The requested state transition is not allowed
+    InvalidStateTransition,
+    /// A business rule rejected the operation
+    RuleValidationFailed,
+    /// The prompt does not fit the model's context window
+    ContextLengthExceeded,
+    /// Moderation flagged the request's content
+    ContentFlagged,
+    /// A spending budget has been exhausted
+    BudgetExceeded,
+    /// A request's estimated cost would exceed its spending budget
+    QuotaExceeded,
+    /// A rate limit or queue capacity was exceeded
+    RateLimited,
+    /// No provider is configured or reachable for the request
+    ProviderUnavailable,
+    /// The upstream provider returned an error
+    ProviderError,
+    /// Credentials are missing or invalid
+    AuthenticationFailed,
+    /// The caller is not allowed to perform the operation
+    PermissionDenied,
+    /// The operation did not finish in time
+    Timeout,
+    /// The storage backend failed
+    StorageError,
+    /// The resource was changed concurrently; re-read it and retry
+    Conflict,
+    /// The server is in maintenance mode and only serves reads
+    MaintenanceMode,
+    /// An unexpected server error
+    Internal,
+}
+
+impl ErrorCode {
+    /// Wire representation, e.g. `WORKFLOW_NOT_FOUND`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::InvalidInput => "INVALID_INPUT",
+            ErrorCode::WorkflowNotFound => "WORKFLOW_NOT_FOUND",
+            ErrorCode::ResourceNotFound => "RESOURCE_NOT_FOUND",
+            ErrorCode::AgentNotFound => "AGENT_NOT_FOUND",
+            ErrorCode::ExecutionNotFound => "EXECUTION_NOT_FOUND",
+            ErrorCode::RuleNotFound => "RULE_NOT_FOUND",
+            ErrorCode::ModelNotFound => "MODEL_NOT_FOUND",
+            ErrorCode::NotFound => "NOT_FOUND",
+            ErrorCode::InvalidStateTransition => "INVALID_STATE_TRANSITION",
+            ErrorCode::RuleValidationFailed => "RULE_VALIDATION_FAILED",
+            ErrorCode::ContextLengthExceeded => "CONTEXT_LENGTH_EXCEEDED",
+            ErrorCode::ContentFlagged => "CONTENT_FLAGGED",
+            ErrorCode::BudgetExceeded => "BUDGET_EXCEEDED",
+            ErrorCode::QuotaExceeded => "QUOTA_EXCEEDED",
+            ErrorCode::RateLimited => "RATE_LIMITED",
+            ErrorCode::ProviderUnavailable => "PROVIDER_UNAVAILABLE",
+            ErrorCode::ProviderError => "PROVIDER_ERROR",
+            ErrorCode::AuthenticationFailed => "AUTHENTICATION_FAILED",
+            ErrorCode::PermissionDenied => "PERMISSION_DENIED",
+            ErrorCode::Timeout => "TIMEOUT",
+            ErrorCode::Conflict => "CONFLICT",
+            ErrorCode::StorageError => "STORAGE_ERROR",
+            ErrorCode::MaintenanceMode => "MAINTENANCE_MODE",
+            ErrorCode::Internal => "INTERNAL",
+        }
+    }
+
+    /// HTTP status code conventionally returned with this error
+    pub fn http_status(&self) -> u16 {
+        match self {
+            ErrorCode::InvalidInput
+            | ErrorCode::InvalidStateTransition
+            | ErrorCode::RuleValidationFailed
+            | ErrorCode::ContextLengthExceeded
+            | ErrorCode::ContentFlagged => 400,
+            ErrorCode::AuthenticationFailed => 401,
+            ErrorCode::BudgetExceeded | ErrorCode::QuotaExceeded => 402,
+            ErrorCode::PermissionDenied => 403,
+            ErrorCode::WorkflowNotFound
+            | ErrorCode::ResourceNotFound
+            | ErrorCode::AgentNotFound
+            | ErrorCode::ExecutionNotFound
+            | ErrorCode::RuleNotFound
+            | ErrorCode::ModelNotFound
+            | ErrorCode::NotFound => 404,
+            ErrorCode::Conflict => 409,
+            ErrorCode::RateLimited => 429,
+            ErrorCode::ProviderError => 502,
+            ErrorCode::ProviderUnavailable | ErrorCode::MaintenanceMode => 503,
+            ErrorCode::Timeout => 504,
+            ErrorCode::StorageError | ErrorCode::Internal => 500,
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_code_wire_format_matches_as_str() {
+        for code in [
+            ErrorCode::WorkflowNotFound,
+            ErrorCode::BudgetExceeded,
+            ErrorCode::ProviderUnavailable,
+            ErrorCode::InvalidStateTransition,
+        ] {
+            let json = serde_json::to_string(&code).unwrap();
+            assert_eq!(json, format!("\"{}\"", code.as_str()));
+            assert_eq!(serde_json::from_str::<ErrorCode>(&json).unwrap(), code);
+        }
+    }
+}
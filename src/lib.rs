@@ -109,6 +109,27 @@ pub mod llm;
 // This contains REST API endpoints that are compatible with OpenAI's API specification
 pub mod api;
 
+// Stable error codes shared by REST, GraphQL and MCP error responses
+pub mod errors;
+
+// Read-only switch for upgrades and storage migrations
+pub mod maintenance;
+
+// Security events published by every surface that authenticates callers
+pub mod audit;
+
+// Append-only record of authentications, key usage, grants, tool calls and admin changes
+pub mod audit_trail;
+
+// Progressive lockouts after repeated failed authentications
+pub mod auth_throttle;
+
+// Scoped, rate-limited API keys required by every API surface when configured
+pub mod api_keys;
+
+// Roles granting read or write access to workflows, resources, agents, budgets and MCP
+pub mod rbac;
+
 // TODO: Implement these modules as we build them
 // These are commented out because the modules don't exist yet
 // pub mod rules;
@@ -174,6 +195,15 @@ pub use api::{
     OpenAIApiConfig, OpenAIApiServer, OpenAIApiServerBuilder,
 };
 
+// Re-export the stable error code taxonomy
+pub use errors::ErrorCode;
+pub use maintenance::{MaintenanceMode, MaintenanceStatus};
+pub use audit::{AuditLog, SecurityEvent};
+pub use audit_trail::{AuditRecord, AuditTrail};
+pub use auth_throttle::AuthThrottle;
+pub use api_keys::{ApiKey, ApiKeyScope, ApiKeys};
+pub use rbac::{Role, RoleAssignments, Subject};
+
 // Core error types
 // Using the `thiserror` crate to make error handling easier
 use thiserror::Error;
@@ -225,6 +255,18 @@ pub enum CircuitBreakerError {
     #[error("Invalid input: {0}")]
     InvalidInput(String),
 
+    /// Error when executing an activity on a paused or cancelled resource
+    #[error("Resource {id} is {status}")]
+    ResourceHalted { id: String, status: String },
+
+    /// Error when a resource was updated from a revision storage no longer holds
+    #[error("Resource {id} was updated concurrently: expected version {expected}, found {actual}")]
+    VersionConflict {
+        id: String,
+        expected: u64,
+        actual: u64,
+    },
+
     /// Storage-related errors
     /// Using anyhow::Error for flexible error handling with NATS and other storage backends
     #[error("Storage error: {0}")]
@@ -240,6 +282,26 @@ pub enum CircuitBreakerError {
     GraphQL(String),
 }
 
+impl CircuitBreakerError {
+    /// Stable error code for API responses
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            CircuitBreakerError::InvalidTransition { .. } => ErrorCode::InvalidStateTransition,
+            CircuitBreakerError::RuleValidationFailed { .. } => ErrorCode::RuleValidationFailed,
+            CircuitBreakerError::TokenNotFound { .. } => ErrorCode::ResourceNotFound,
+            CircuitBreakerError::WorkflowNotFound { .. } => ErrorCode::WorkflowNotFound,
+            CircuitBreakerError::NotFound(_) => ErrorCode::NotFound,
+            CircuitBreakerError::InvalidInput(_) => ErrorCode::InvalidInput,
+            CircuitBreakerError::ResourceHalted { .. } => ErrorCode::InvalidStateTransition,
+            CircuitBreakerError::VersionConflict { .. } => ErrorCode::Conflict,
+            CircuitBreakerError::Storage(_) => ErrorCode::StorageError,
+            CircuitBreakerError::Serialization(_) | CircuitBreakerError::GraphQL(_) => {
+                ErrorCode::Internal
+            }
+        }
+    }
+}
+
 /// Type alias for Results that use our custom error type
 ///
 /// ## Rust Learning Notes:
@@ -0,0 +1,546 @@
+//! Audio Transcription and Speech
+//!
+//! `POST /v1/audio/transcriptions` and `POST /v1/audio/speech` are routed to
+//! [`AudioProvider`]s by model: Whisper models to OpenAI or Groq, whose transcription
+//! APIs are OpenAI-compatible, and text-to-speech models to OpenAI. [`AudioProviders`]
+//! holds the providers configured by the environment.
+//!
+//! Audio is priced by what it is made of rather than by tokens: transcriptions by the
+//! minute of audio transcribed and speech by the character of input read out, as the
+//! providers bill them. To learn the length of the audio, transcriptions are requested
+//! as `verbose_json`, which carries the duration, and reduced to the format the client
+//! asked for; subtitle formats are passed through and their last cue gives the duration.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::{LLMError, LLMProviderType, LLMResult};
+
+/// Largest audio file accepted for transcription, as for OpenAI
+pub const MAX_AUDIO_FILE_BYTES: usize = 25 * 1024 * 1024;
+
+/// Longest text accepted for speech, as for OpenAI
+pub const MAX_SPEECH_INPUT_CHARS: usize = 4096;
+
+/// Transcription models and their price in USD per minute of audio
+const TRANSCRIPTION_PRICES: &[(LLMProviderType, &str, f64)] = &[
+    (LLMProviderType::OpenAI, "whisper-1", 0.006),
+    (LLMProviderType::Groq, "whisper-large-v3", 0.111 / 60.0),
+    (LLMProviderType::Groq, "whisper-large-v3-turbo", 0.04 / 60.0),
+    (
+        LLMProviderType::Groq,
+        "distil-whisper-large-v3-en",
+        0.02 / 60.0,
+    ),
+];
+
+/// Speech models and their price in USD per character of input
+const SPEECH_PRICES: &[(LLMProviderType, &str, f64)] = &[
+    (LLMProviderType::OpenAI, "tts-1", 15.0 / 1_000_000.0),
+    (LLMProviderType::OpenAI, "tts-1-hd", 30.0 / 1_000_000.0),
+];
+
+/// Format of a transcription returned to the client
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscriptionFormat {
+    #[default]
+    Json,
+    Text,
+    Srt,
+    VerboseJson,
+    Vtt,
+}
+
+impl TranscriptionFormat {
+    pub fn parse(format: &str) -> Option<Self> {
+        match format {
+            "json" => Some(Self::Json),
+            "text" => Some(Self::Text),
+            "srt" => Some(Self::Srt),
+            "verbose_json" => Some(Self::VerboseJson),
+            "vtt" => Some(Self::Vtt),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Text => "text",
+            Self::Srt => "srt",
+            Self::VerboseJson => "verbose_json",
+            Self::Vtt => "vtt",
+        }
+    }
+
+    /// Format requested from the provider: one that reveals the audio's duration
+    fn upstream(&self) -> Self {
+        match self {
+            Self::Srt | Self::Vtt => *self,
+            Self::Json | Self::Text | Self::VerboseJson => Self::VerboseJson,
+        }
+    }
+
+    /// Content type of a transcription in this format
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Json | Self::VerboseJson => "application/json",
+            Self::Text => "text/plain; charset=utf-8",
+            Self::Srt => "application/x-subrip",
+            Self::Vtt => "text/vtt",
+        }
+    }
+}
+
+/// An audio file to transcribe
+#[derive(Debug, Clone)]
+pub struct TranscriptionRequest {
+    pub file: bytes::Bytes,
+    pub filename: String,
+    pub content_type: Option<String>,
+    pub model: String,
+    pub language: Option<String>,
+    pub prompt: Option<String>,
+    pub response_format: TranscriptionFormat,
+    pub temperature: Option<f32>,
+}
+
+/// A transcription in the format the client asked for
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transcription {
+    pub body: String,
+    pub format: TranscriptionFormat,
+    /// Length of the transcribed audio in seconds, when the provider reported it
+    pub duration_seconds: Option<f64>,
+}
+
+impl Transcription {
+    /// Reduce a provider's response in `received` format to `format`
+    pub fn from_response(
+        body: String,
+        received: TranscriptionFormat,
+        format: TranscriptionFormat,
+    ) -> LLMResult<Self> {
+        if received != TranscriptionFormat::VerboseJson {
+            return Ok(Self {
+                duration_seconds: subtitle_duration(&body),
+                body,
+                format,
+            });
+        }
+
+        let verbose: serde_json::Value = serde_json::from_str(&body)
+            .map_err(|e| LLMError::Parse(format!("Invalid transcription response: {}", e)))?;
+        let text = verbose["text"].as_str().unwrap_or_default().to_string();
+        let body = match format {
+            TranscriptionFormat::Json => serde_json::json!({ "text": text }).to_string(),
+            TranscriptionFormat::Text => text,
+            _ => body,
+        };
+        Ok(Self {
+            body,
+            format,
+            duration_seconds: verbose["duration"].as_f64(),
+        })
+    }
+
+    /// Minutes of audio billed, rounded up to the second
+    pub fn billed_minutes(&self) -> f64 {
+        self.duration_seconds.unwrap_or(0.0).max(0.0).ceil() / 60.0
+    }
+}
+
+/// End of the last cue of SRT or WebVTT subtitles, in seconds
+fn subtitle_duration(subtitles: &str) -> Option<f64> {
+    subtitles
+        .lines()
+        .filter_map(|line| line.split_once("-->"))
+        .filter_map(|(_, end)| {
+            let end = end.split_whitespace().next()?.replace(',', ".");
+            let mut seconds = 0.0;
+            for part in end.split(':') {
+                seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+            }
+            Some(seconds)
+        })
+        .reduce(f64::max)
+}
+
+/// Text to read out, in OpenAI's request format
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeechRequest {
+    pub model: String,
+    pub input: String,
+    pub voice: String,
+    /// "mp3" (default), "opus", "aac", "flac", "wav" or "pcm"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed: Option<f32>,
+}
+
+/// Generated speech
+#[derive(Debug, Clone)]
+pub struct SpeechAudio {
+    pub audio: bytes::Bytes,
+    pub content_type: String,
+}
+
+/// Price in USD of transcribing `minutes` of audio with a model; unknown models are free
+pub fn transcription_cost(provider: &LLMProviderType, model: &str, minutes: f64) -> f64 {
+    TRANSCRIPTION_PRICES
+        .iter()
+        .find(|(p, m, _)| p == provider && *m == model)
+        .map_or(0.0, |(_, _, per_minute)| per_minute * minutes)
+}
+
+/// Price in USD of reading out `input` with a model; unknown models are free
+pub fn speech_cost(provider: &LLMProviderType, model: &str, input: &str) -> f64 {
+    SPEECH_PRICES
+        .iter()
+        .find(|(p, m, _)| p == provider && *m == model)
+        .map_or(0.0, |(_, _, per_char)| {
+            per_char * input.chars().count() as f64
+        })
+}
+
+/// Transcribes audio and generates speech
+#[async_trait]
+pub trait AudioProvider: Send + Sync {
+    /// Provider costs are recorded under
+    fn provider_type(&self) -> LLMProviderType;
+
+    fn supports_transcription(&self, model: &str) -> bool;
+
+    fn supports_speech(&self, model: &str) -> bool;
+
+    async fn transcribe(&self, request: &TranscriptionRequest) -> LLMResult<Transcription>;
+
+    async fn speak(&self, request: &SpeechRequest) -> LLMResult<SpeechAudio>;
+}
+
+/// A provider serving OpenAI's audio API, such as OpenAI itself or Groq
+#[derive(Debug, Clone)]
+pub struct OpenAICompatibleAudio {
+    provider_type: LLMProviderType,
+    api_key: String,
+    base_url: String,
+    transcription_models: Vec<String>,
+    speech_models: Vec<String>,
+    client: reqwest::Client,
+}
+
+impl OpenAICompatibleAudio {
+    pub fn new(
+        provider_type: LLMProviderType,
+        api_key: impl Into<String>,
+        base_url: impl Into<String>,
+    ) -> Self {
+        let models = |prices: &[(LLMProviderType, &str, f64)]| {
+            prices
+                .iter()
+                .filter(|(p, _, _)| *p == provider_type)
+                .map(|(_, model, _)| model.to_string())
+                .collect()
+        };
+        Self {
+            transcription_models: models(TRANSCRIPTION_PRICES),
+            speech_models: models(SPEECH_PRICES),
+            provider_type,
+            api_key: api_key.into(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// OpenAI: Whisper transcription and TTS
+    pub fn openai(api_key: impl Into<String>, base_url: Option<String>) -> Self {
+        Self::new(
+            LLMProviderType::OpenAI,
+            api_key,
+            base_url.unwrap_or_else(|| "https://api.openai.com/v1".to_string()),
+        )
+    }
+
+    /// Groq: Whisper transcription
+    pub fn groq(api_key: impl Into<String>, base_url: Option<String>) -> Self {
+        Self::new(
+            LLMProviderType::Groq,
+            api_key,
+            base_url.unwrap_or_else(|| "https://api.groq.com/openai/v1".to_string()),
+        )
+    }
+
+    /// Serve `model` for transcription as well as the models with known prices
+    pub fn with_transcription_model(mut self, model: impl Into<String>) -> Self {
+        self.transcription_models.push(model.into());
+        self
+    }
+
+    /// Serve `model` for speech as well as the models with known prices
+    pub fn with_speech_model(mut self, model: impl Into<String>) -> Self {
+        self.speech_models.push(model.into());
+        self
+    }
+
+    async fn check(&self, response: reqwest::Response) -> LLMResult<reqwest::Response> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response.text().await.unwrap_or_default();
+        let message = format!(
+            "{} audio API returned {}: {}",
+            self.provider_type, status, body
+        );
+        Err(match status.as_u16() {
+            400 | 413 | 415 => LLMError::InvalidRequest(message),
+            401 | 403 => LLMError::AuthenticationFailed(message),
+            429 => LLMError::RateLimitExceeded(message),
+            _ => LLMError::Provider(message),
+        })
+    }
+}
+
+#[async_trait]
+impl AudioProvider for OpenAICompatibleAudio {
+    fn provider_type(&self) -> LLMProviderType {
+        self.provider_type.clone()
+    }
+
+    fn supports_transcription(&self, model: &str) -> bool {
+        self.transcription_models.iter().any(|m| m == model)
+    }
+
+    fn supports_speech(&self, model: &str) -> bool {
+        self.speech_models.iter().any(|m| m == model)
+    }
+
+    async fn transcribe(&self, request: &TranscriptionRequest) -> LLMResult<Transcription> {
+        let upstream = request.response_format.upstream();
+        let mut file = reqwest::multipart::Part::stream(request.file.clone())
+            .file_name(request.filename.clone());
+        if let Some(content_type) = &request.content_type {
+            file = file
+                .mime_str(content_type)
+                .map_err(|e| LLMError::InvalidRequest(format!("Invalid file type: {}", e)))?;
+        }
+        let mut form = reqwest::multipart::Form::new()
+            .part("file", file)
+            .text("model", request.model.clone())
+            .text("response_format", upstream.as_str());
+        if let Some(language) = &request.language {
+            form = form.text("language", language.clone());
+        }
+        if let Some(prompt) = &request.prompt {
+            form = form.text("prompt", prompt.clone());
+        }
+        if let Some(temperature) = request.temperature {
+            form = form.text("temperature", temperature.to_string());
+        }
+
+        let response = self
+            .client
+            .post(format!("{}/audio/transcriptions", self.base_url))
+            .bearer_auth(&self.api_key)
+            .multipart(form)
+            .send()
+            .await
+            .map_err(|e| LLMError::Network(format!("Transcription request failed: {}", e)))?;
+        let body = self
+            .check(response)
+            .await?
+            .text()
+            .await
+            .map_err(|e| LLMError::Network(format!("Failed to read transcription: {}", e)))?;
+        Transcription::from_response(body, upstream, request.response_format)
+    }
+
+    async fn speak(&self, request: &SpeechRequest) -> LLMResult<SpeechAudio> {
+        let response = self
+            .client
+            .post(format!("{}/audio/speech", self.base_url))
+            .bearer_auth(&self.api_key)
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| LLMError::Network(format!("Speech request failed: {}", e)))?;
+        let response = self.check(response).await?;
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("audio/mpeg")
+            .to_string();
+        let audio = response
+            .bytes()
+            .await
+            .map_err(|e| LLMError::Network(format!("Failed to read speech: {}", e)))?;
+        Ok(SpeechAudio {
+            audio,
+            content_type,
+        })
+    }
+}
+
+/// Audio providers, each serving the models it supports
+#[derive(Clone, Default)]
+pub struct AudioProviders {
+    providers: Vec<Arc<dyn AudioProvider>>,
+}
+
+impl std::fmt::Debug for AudioProviders {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AudioProviders")
+            .field(
+                "providers",
+                &self
+                    .providers
+                    .iter()
+                    .map(|p| p.provider_type().to_string())
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl AudioProviders {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// OpenAI when `OPENAI_API_KEY` is set and Groq when `GROQ_API_KEY` is set
+    pub fn from_env() -> Self {
+        let key = |name: &str| std::env::var(name).ok().filter(|key| !key.is_empty());
+        let mut providers = Self::new();
+        if let Some(api_key) = key("OPENAI_API_KEY") {
+            providers = providers.with_provider(Arc::new(OpenAICompatibleAudio::openai(
+                api_key,
+                key("OPENAI_BASE_URL"),
+            )));
+        }
+        if let Some(api_key) = key("GROQ_API_KEY") {
+            providers = providers.with_provider(Arc::new(OpenAICompatibleAudio::groq(
+                api_key,
+                key("GROQ_BASE_URL"),
+            )));
+        }
+        providers
+    }
+
+    /// Add a provider; earlier providers win for models several support
+    pub fn with_provider(mut self, provider: Arc<dyn AudioProvider>) -> Self {
+        self.providers.push(provider);
+        self
+    }
+
+    /// The provider transcribing with `model`
+    pub fn transcription_provider(&self, model: &str) -> LLMResult<Arc<dyn AudioProvider>> {
+        self.providers
+            .iter()
+            .find(|provider| provider.supports_transcription(model))
+            .cloned()
+            .ok_or_else(|| {
+                LLMError::ModelNotSupported(format!(
+                    "No audio provider transcribes with '{}'",
+                    model
+                ))
+            })
+    }
+
+    /// The provider generating speech with `model`
+    pub fn speech_provider(&self, model: &str) -> LLMResult<Arc<dyn AudioProvider>> {
+        self.providers
+            .iter()
+            .find(|provider| provider.supports_speech(model))
+            .cloned()
+            .ok_or_else(|| {
+                LLMError::ModelNotSupported(format!("No audio provider speaks with '{}'", model))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transcription_formats_and_duration() {
+        let verbose =
+            r#"{"task":"transcribe","language":"english","duration":61.2,"text":"Hello there."}"#;
+
+        let json = Transcription::from_response(
+            verbose.to_string(),
+            TranscriptionFormat::VerboseJson,
+            TranscriptionFormat::Json,
+        )
+        .unwrap();
+        assert_eq!(json.body, r#"{"text":"Hello there."}"#);
+        assert_eq!(json.duration_seconds, Some(61.2));
+        assert_eq!(json.billed_minutes(), 62.0 / 60.0);
+
+        let text = Transcription::from_response(
+            verbose.to_string(),
+            TranscriptionFormat::VerboseJson,
+            TranscriptionFormat::Text,
+        )
+        .unwrap();
+        assert_eq!(text.body, "Hello there.");
+
+        let srt =
+            "1\n00:00:00,000 --> 00:00:02,500\nHello\n\n2\n00:01:02,500 --> 00:01:05,250\nthere.\n";
+        let srt = Transcription::from_response(
+            srt.to_string(),
+            TranscriptionFormat::Srt,
+            TranscriptionFormat::Srt,
+        )
+        .unwrap();
+        assert_eq!(srt.duration_seconds, Some(65.25));
+
+        let vtt = "WEBVTT\n\n00:00.000 --> 00:03.120\nHello there.\n";
+        assert_eq!(subtitle_duration(vtt), Some(3.12));
+        assert_eq!(
+            TranscriptionFormat::Text.upstream(),
+            TranscriptionFormat::VerboseJson
+        );
+    }
+
+    #[test]
+    fn test_audio_pricing_and_routing() {
+        assert!(
+            (transcription_cost(&LLMProviderType::OpenAI, "whisper-1", 2.5) - 0.015).abs() < 1e-12
+        );
+        assert_eq!(
+            transcription_cost(&LLMProviderType::OpenAI, "unknown", 2.5),
+            0.0
+        );
+        assert!(
+            (speech_cost(&LLMProviderType::OpenAI, "tts-1", &"a".repeat(1000)) - 0.015).abs()
+                < 1e-12
+        );
+
+        let providers = AudioProviders::new()
+            .with_provider(Arc::new(OpenAICompatibleAudio::openai("sk-test", None)))
+            .with_provider(Arc::new(OpenAICompatibleAudio::groq("gsk-test", None)));
+        assert_eq!(
+            providers
+                .transcription_provider("whisper-large-v3-turbo")
+                .unwrap()
+                .provider_type(),
+            LLMProviderType::Groq
+        );
+        assert_eq!(
+            providers
+                .speech_provider("tts-1-hd")
+                .unwrap()
+                .provider_type(),
+            LLMProviderType::OpenAI
+        );
+        assert!(matches!(
+            providers.speech_provider("whisper-1"),
+            Err(LLMError::ModelNotSupported(_))
+        ));
+    }
+}
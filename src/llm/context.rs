@@ -0,0 +1,268 @@
+//! Context Window Management
+//!
+//! This module estimates prompt sizes and decides what the router should do when a
+//! request does not fit in the selected model's context window. Instead of letting the
+//! provider come back with an opaque 400, the router can reject the request up front,
+//! re-route it to a model with a larger window, or shrink the conversation.
+
+use serde::{Deserialize, Serialize};
+
+use super::tokenizer::{self, Tokenizer};
+use super::{ChatMessage, LLMRequest, MessageRole};
+
+/// What to do when a request exceeds the model's context window
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum ContextOverflowPolicy {
+    /// Fail fast with `LLMError::ContextLengthExceeded`
+    #[default]
+    Reject,
+    /// Switch to the cheapest available model whose context window fits the request
+    Reroute,
+    /// Drop the oldest non-system messages until the request fits
+    TruncateOldest,
+    /// Replace the oldest non-system messages with a generated summary.
+    /// Uses `model` for the summary call, or the request's own model when `None`.
+    Summarize { model: Option<String> },
+}
+
+/// Context window configuration for the router
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextWindowConfig {
+    /// Whether context window checks are performed at all
+    pub enabled: bool,
+    /// Policy applied when a request does not fit
+    pub overflow_policy: ContextOverflowPolicy,
+    /// Output tokens to reserve when the request does not set `max_tokens`
+    pub default_output_reserve: u32,
+    /// Extra headroom to absorb estimation error (fraction of the window, 0.0 - 1.0)
+    pub safety_margin: f64,
+}
+
+impl Default for ContextWindowConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            overflow_policy: ContextOverflowPolicy::default(),
+            default_output_reserve: 0,
+            safety_margin: 0.05,
+        }
+    }
+}
+
+impl ContextWindowConfig {
+    /// Usable prompt budget for a model, after the safety margin and output reserve
+    pub fn prompt_budget(&self, context_window: u32, request: &LLMRequest) -> u32 {
+        let margin = (context_window as f64 * self.safety_margin.clamp(0.0, 1.0)) as u32;
+        let output = request.max_tokens.unwrap_or(self.default_output_reserve);
+        context_window.saturating_sub(margin).saturating_sub(output)
+    }
+
+    /// Total tokens a model must accommodate to serve this request
+    pub fn required_window(&self, request: &LLMRequest) -> u32 {
+        let prompt = tokenizer::count_prompt_tokens(&request.model, &request.messages);
+        let output = request.max_tokens.unwrap_or(self.default_output_reserve);
+        let needed = prompt.saturating_add(output);
+        let margin = 1.0 - self.safety_margin.clamp(0.0, 0.99);
+        (needed as f64 / margin).ceil() as u32
+    }
+}
+
+/// Result of truncating a conversation to fit a token budget
+#[derive(Debug, Clone)]
+pub struct TruncatedConversation {
+    /// Messages that fit within the budget, in original order
+    pub kept: Vec<ChatMessage>,
+    /// Messages that were removed, in original order
+    pub dropped: Vec<ChatMessage>,
+}
+
+/// Drop the oldest non-system messages until the conversation fits `budget` tokens,
+/// counted with `model`'s tokenizer.
+///
+/// System messages and the final message are always preserved. Returns `None` when
+/// the conversation cannot fit even after every droppable message is removed.
+pub fn truncate_oldest(
+    model: &str,
+    messages: &[ChatMessage],
+    budget: u32,
+) -> Option<TruncatedConversation> {
+    let tokenizer = Tokenizer::for_model(model);
+    let mut total = tokenizer.count_prompt(messages);
+    if total <= budget {
+        return Some(TruncatedConversation {
+            kept: messages.to_vec(),
+            dropped: Vec::new(),
+        });
+    }
+
+    let last_index = messages.len().checked_sub(1)?;
+    let mut keep = vec![true; messages.len()];
+
+    for (index, message) in messages.iter().enumerate() {
+        if total <= budget {
+            break;
+        }
+        if index == last_index || matches!(message.role, MessageRole::System) {
+            continue;
+        }
+        keep[index] = false;
+        total -= tokenizer.count_message(message);
+    }
+
+    if total > budget {
+        return None;
+    }
+
+    let (kept, dropped): (Vec<_>, Vec<_>) = messages
+        .iter()
+        .cloned()
+        .zip(keep)
+        .partition(|(_, keep)| *keep);
+
+    Some(TruncatedConversation {
+        kept: kept.into_iter().map(|(message, _)| message).collect(),
+        dropped: dropped.into_iter().map(|(message, _)| message).collect(),
+    })
+}
+
+/// Output tokens a summary of dropped messages may take
+pub const SUMMARY_MAX_TOKENS: u32 = 512;
+
+/// Tokens to keep free for the inserted summary: its content plus the message overhead
+/// and the "Summary of earlier conversation" prefix
+pub const SUMMARY_RESERVE: u32 = SUMMARY_MAX_TOKENS + 16;
+
+/// Build the request used to summarize dropped messages
+pub fn build_summary_request(dropped: &[ChatMessage], model: &str) -> LLMRequest {
+    let transcript = dropped
+        .iter()
+        .map(|message| format!("{:?}: {}", message.role, message.content))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    LLMRequest {
+        id: uuid::Uuid::new_v4(),
+        model: model.to_string(),
+        messages: vec![
+            ChatMessage {
+                role: MessageRole::System,
+                content: "Summarize the following conversation excerpt in a few sentences. \
+                          Preserve facts, decisions and open questions."
+                    .to_string(),
+                name: None,
+                function_call: None,
+                tool_calls: None,
+                tool_call_id: None,
+                content_parts: None,
+            },
+            ChatMessage {
+                role: MessageRole::User,
+                content: transcript,
+                name: None,
+                function_call: None,
+                tool_calls: None,
+                tool_call_id: None,
+                content_parts: None,
+            },
+        ],
+        temperature: Some(0.0),
+        max_tokens: Some(SUMMARY_MAX_TOKENS),
+        top_p: None,
+        frequency_penalty: None,
+        presence_penalty: None,
+        stop: None,
+        stream: Some(false),
+        functions: None,
+        function_call: None,
+        user: None,
+        metadata: std::collections::HashMap::new(),
+        response_format: None,
+        tool_choice: None,
+        n: None,
+    }
+}
+
+/// Insert a summary message after the leading system messages
+pub fn insert_summary(kept: &mut Vec<ChatMessage>, summary: String) {
+    let position = kept
+        .iter()
+        .take_while(|message| matches!(message.role, MessageRole::System))
+        .count();
+    kept.insert(
+        position,
+        ChatMessage {
+            role: MessageRole::System,
+            content: format!("Summary of earlier conversation: {}", summary),
+            name: None,
+            function_call: None,
+            tool_calls: None,
+            tool_call_id: None,
+            content_parts: None,
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: MessageRole, content: &str) -> ChatMessage {
+        ChatMessage {
+            role,
+            content: content.to_string(),
+            name: None,
+            function_call: None,
+            tool_calls: None,
+            tool_call_id: None,
+            content_parts: None,
+        }
+    }
+
+    #[test]
+    fn test_truncate_oldest_preserves_system_and_last() {
+        let long = "x".repeat(400);
+        let messages = vec![
+            message(MessageRole::System, "be helpful"),
+            message(MessageRole::User, &long),
+            message(MessageRole::Assistant, &long),
+            message(MessageRole::User, "latest question"),
+        ];
+
+        let budget =
+            tokenizer::count_prompt_tokens("gpt-4", &[messages[0].clone(), messages[3].clone()]);
+        let result = truncate_oldest("gpt-4", &messages, budget).unwrap();
+
+        assert_eq!(result.kept.len(), 2);
+        assert_eq!(result.dropped.len(), 2);
+        assert!(matches!(result.kept[0].role, MessageRole::System));
+        assert_eq!(result.kept[1].content, "latest question");
+    }
+
+    #[test]
+    fn test_truncate_oldest_impossible() {
+        let messages = vec![message(MessageRole::User, &"x".repeat(4000))];
+        assert!(truncate_oldest("claude-3-haiku", &messages, 10).is_none());
+    }
+
+    #[test]
+    fn test_insert_summary_after_system_messages() {
+        let mut kept = vec![
+            message(MessageRole::System, "rules"),
+            message(MessageRole::User, "hi"),
+        ];
+        insert_summary(&mut kept, "earlier stuff".to_string());
+        assert_eq!(kept.len(), 3);
+        assert!(kept[1].content.contains("earlier stuff"));
+    }
+
+    #[test]
+    fn test_prompt_budget_reserves_output() {
+        let config = ContextWindowConfig {
+            safety_margin: 0.0,
+            ..Default::default()
+        };
+        let mut request = build_summary_request(&[], "gpt-4");
+        request.max_tokens = Some(1000);
+        assert_eq!(config.prompt_budget(8192, &request), 7192);
+    }
+}
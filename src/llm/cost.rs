@@ -8,14 +8,22 @@ use std::collections::BTreeMap;
 use std::cmp::Ordering;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use chrono::Datelike;
+use chrono::{Datelike, Days, FixedOffset, Months, NaiveDate};
+
+/// How often the rollover task started by [`BudgetManager::spawn_rollover`] closes ended periods
+pub const BUDGET_ROLLOVER_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Closed periods kept per budget, enough for a year of daily periods
+pub const MAX_BUDGET_PERIOD_HISTORY: usize = 400;
+
+/// Largest offset from UTC, in minutes, of a budget's timezone
+pub const MAX_UTC_OFFSET_MINUTES: i32 = 14 * 60;
 
 /// Cost optimization engine
 pub struct CostOptimizer {
     budget_manager: Arc<BudgetManager>,
     cost_analyzer: Arc<CostAnalyzer>,
     optimization_rules: Arc<RwLock<Vec<OptimizationRule>>>,
-    cost_history: Arc<RwLock<BTreeMap<DateTime<Utc>, Vec<CostInfo>>>>,
 }
 
 impl CostOptimizer {
@@ -54,10 +62,30 @@ impl CostOptimizer {
             budget_manager,
             cost_analyzer,
             optimization_rules: Arc::new(RwLock::new(rules)),
-            cost_history: Arc::new(RwLock::new(BTreeMap::new())),
         }
     }
 
+    /// Create an optimizer whose budgets and analytics are kept by the given tracker
+    ///
+    /// Pass a persistent tracker such as [`NATSUsageTracker`](super::usage_tracking::NATSUsageTracker)
+    /// so spending survives restarts and is shared between replicas.
+    pub fn with_usage_tracker(usage_tracker: Arc<dyn UsageTracker>) -> Self {
+        Self::new(
+            Arc::new(BudgetManager::new(usage_tracker)),
+            Arc::new(CostAnalyzer::new()),
+        )
+    }
+
+    /// The budget manager enforcing spending limits
+    pub fn budget_manager(&self) -> Arc<BudgetManager> {
+        self.budget_manager.clone()
+    }
+
+    /// The tracker recording actual costs
+    pub fn usage_tracker(&self) -> Arc<dyn UsageTracker> {
+        self.budget_manager.usage_tracker()
+    }
+
     /// Analyze costs and suggest optimal provider
     pub async fn suggest_provider(
         &self,
@@ -225,11 +253,7 @@ impl CostOptimizer {
 
     /// Estimate tokens in a request
     fn estimate_request_tokens(&self, request: &LLMRequest) -> u32 {
-        // Simple token estimation: ~4 characters per token
-        let total_chars: usize = request.messages.iter()
-            .map(|msg| msg.content.len())
-            .sum();
-        (total_chars / 4) as u32
+        super::tokenizer::count_prompt_tokens(&request.model, &request.messages)
     }
 
     /// Calculate latency penalty for provider selection
@@ -262,16 +286,11 @@ impl CostOptimizer {
 
     /// Get tokens used since a specific time
     async fn get_tokens_used_since(&self, user_id: &str, since: DateTime<Utc>) -> Result<u32, CostError> {
-        let history = self.cost_history.read().await;
-        let mut total_tokens = 0;
-
-        for (_timestamp, costs) in history.range(since..) {
-            for cost in costs {
-                if cost.user_id.as_ref() == Some(&user_id.to_string()) {
-                    total_tokens += cost.input_tokens + cost.output_tokens;
-                }
-            }
-        }
+        let costs = self.usage_tracker().list_usage(since, Utc::now()).await?;
+        let total_tokens = costs.iter()
+            .filter(|cost| cost.user_id.as_deref() == Some(user_id))
+            .map(|cost| cost.input_tokens + cost.output_tokens)
+            .sum();
 
         Ok(total_tokens)
     }
@@ -324,17 +343,27 @@ impl CostOptimizer {
         rules.clone()
     }
 
+    /// Estimate the cost of a request from the analyzer's pricing
+    pub async fn estimate_cost(
+        &self,
+        provider: &LLMProviderType,
+        model: &str,
+        input_tokens: u32,
+        output_tokens: u32,
+    ) -> Result<CostEstimate, CostError> {
+        self.cost_analyzer.estimate_cost(provider, model, input_tokens, output_tokens).await
+    }
+
     /// Record actual cost for learning and optimization
     pub async fn record_actual_cost(&self, cost_info: CostInfo) {
-        let mut history = self.cost_history.write().await;
-        let day_key = cost_info.timestamp.date_naive().and_hms_opt(0, 0, 0)
-            .unwrap().and_local_timezone(Utc).unwrap();
-        
-        history.entry(day_key).or_insert_with(Vec::new).push(cost_info);
-        
-        // Keep only last 30 days
-        let cutoff = Utc::now() - chrono::Duration::days(30);
-        history.retain(|k, _| *k >= cutoff);
+        if let Err(e) = self.usage_tracker().record_usage(&cost_info).await {
+            tracing::warn!("Failed to record cost of request {}: {}", cost_info.request_id, e);
+        }
+    }
+
+    /// Cost recorded for a single request
+    pub async fn get_request_cost(&self, request_id: &Uuid) -> Result<Option<CostInfo>, CostError> {
+        self.usage_tracker().get_usage(request_id).await
     }
 
     /// Get cost analytics for a time period
@@ -345,31 +374,29 @@ impl CostOptimizer {
         start: DateTime<Utc>,
         end: DateTime<Utc>,
     ) -> CostAnalytics {
-        let history = self.cost_history.read().await;
+        let costs = self.usage_tracker().list_usage(start, end).await.unwrap_or_else(|e| {
+            tracing::warn!("Failed to load usage for cost analytics: {}", e);
+            Vec::new()
+        });
         let mut total_cost = 0.0;
         let mut total_tokens = 0;
         let mut provider_costs = HashMap::new();
         let mut model_costs = HashMap::new();
         let mut daily_costs = BTreeMap::new();
 
-        for (day, costs) in history.range(start..=end) {
-            let mut day_cost = 0.0;
-            
-            for cost in costs {
-                let matches_filter = user_id.map_or(true, |uid| cost.user_id.as_ref() == Some(&uid.to_string())) &&
-                                   project_id.map_or(true, |pid| cost.project_id.as_ref() == Some(&pid.to_string()));
-                
-                if matches_filter {
-                    total_cost += cost.cost_usd;
-                    total_tokens += cost.input_tokens + cost.output_tokens;
-                    day_cost += cost.cost_usd;
-                    
-                    *provider_costs.entry(cost.provider.clone()).or_insert(0.0) += cost.cost_usd;
-                    *model_costs.entry(cost.model.clone()).or_insert(0.0) += cost.cost_usd;
-                }
+        for cost in &costs {
+            let day_cost = daily_costs.entry(day_start(cost.timestamp)).or_insert(0.0);
+            let matches_filter = user_id.is_none_or(|uid| cost.user_id.as_deref() == Some(uid)) &&
+                               project_id.is_none_or(|pid| cost.project_id.as_deref() == Some(pid));
+
+            if matches_filter {
+                total_cost += cost.cost_usd;
+                total_tokens += cost.input_tokens + cost.output_tokens;
+                *day_cost += cost.cost_usd;
+
+                *provider_costs.entry(cost.provider.clone()).or_insert(0.0) += cost.cost_usd;
+                *model_costs.entry(cost.model.clone()).or_insert(0.0) += cost.cost_usd;
             }
-            
-            daily_costs.insert(*day, day_cost);
         }
 
         CostAnalytics {
@@ -386,71 +413,110 @@ impl CostOptimizer {
 }
 
 /// Budget manager for tracking and enforcing spending limits
+///
+/// Every budget runs in periods starting at midnight in the budget's timezone. When a
+/// period ends it is closed: its spend is recorded in the budget's period history and,
+/// depending on the budget's [`CarryOver`], what was left of or overspent in it adjusts
+/// the next period's limit. Periods are closed when a budget is next checked and by the
+/// task [`spawn_rollover`](Self::spawn_rollover) starts, whichever comes first.
 pub struct BudgetManager {
     budgets: Arc<RwLock<HashMap<String, Budget>>>,
-    usage_tracker: Arc<InMemoryUsageTracker>,
+    periods: Arc<RwLock<HashMap<String, PeriodLedger>>>,
+    usage_tracker: Arc<dyn UsageTracker>,
+}
+
+/// The open period of a budget and the periods it closed
+#[derive(Debug, Clone, Default)]
+struct PeriodLedger {
+    open: Option<OpenPeriod>,
+    closed: Vec<BudgetPeriodRecord>,
+}
+
+#[derive(Debug, Clone)]
+struct OpenPeriod {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    carried_in: f64,
 }
 
 impl BudgetManager {
-    pub fn new(usage_tracker: Arc<InMemoryUsageTracker>) -> Self {
+    pub fn new(usage_tracker: Arc<dyn UsageTracker>) -> Self {
         Self {
             budgets: Arc::new(RwLock::new(HashMap::new())),
+            periods: Arc::new(RwLock::new(HashMap::new())),
             usage_tracker,
         }
     }
 
+    /// The tracker budgets are checked against
+    pub fn usage_tracker(&self) -> Arc<dyn UsageTracker> {
+        self.usage_tracker.clone()
+    }
+
     /// Set budget for user or project
+    ///
+    /// Changing a budget's period or timezone starts a new period without carry-over; the
+    /// closed periods stay in its history.
     pub async fn set_budget(&self, budget: Budget) {
         let mut budgets = self.budgets.write().await;
+        if let Some(previous) = budgets.get(&budget.id) {
+            if previous.period != budget.period || previous.utc_offset_minutes != budget.utc_offset_minutes {
+                if let Some(ledger) = self.periods.write().await.get_mut(&budget.id) {
+                    ledger.open = None;
+                }
+            }
+        }
         budgets.insert(budget.id.clone(), budget);
     }
 
+    /// Budget with the given ID, e.g. `project:acme`
+    pub async fn get_budget(&self, budget_id: &str) -> Option<Budget> {
+        self.budgets.read().await.get(budget_id).cloned()
+    }
+
+    /// Remove a budget along with its period history, returning it if one was set
+    pub async fn remove_budget(&self, budget_id: &str) -> Option<Budget> {
+        self.periods.write().await.remove(budget_id);
+        self.budgets.write().await.remove(budget_id)
+    }
+
     /// Check budget status
     pub async fn check_budget(&self, context: &CostContext) -> Result<BudgetStatus, CostError> {
-        let budget_id = if let Some(project_id) = &context.project_id {
-            format!("project:{}", project_id)
-        } else {
-            format!("user:{}", context.user_id)
-        };
+        let budget_id = usage_key(&context.user_id, context.project_id.as_deref());
 
-        let budgets = self.budgets.read().await;
-        if let Some(budget) = budgets.get(&budget_id) {
-            let current_usage = match budget.period {
-                BudgetPeriod::Daily => {
-                    self.usage_tracker.get_daily_usage(&context.user_id, context.project_id.as_deref()).await?
-                }
-                BudgetPeriod::Monthly => {
-                    self.usage_tracker.get_monthly_usage(&context.user_id, context.project_id.as_deref()).await?
-                }
-                BudgetPeriod::Yearly => {
-                    self.usage_tracker.get_yearly_usage(&context.user_id, context.project_id.as_deref()).await?
-                }
-            };
+        let budget = self.budgets.read().await.get(&budget_id).cloned();
+        if let Some(budget) = budget {
+            let period = self.open_period(&budget, Utc::now()).await?;
+            let current_usage = self.period_usage(&budget, &period).await?;
+            let limit = budget.limit + period.carried_in;
 
-            let percentage_used = if budget.limit > 0.0 {
-                current_usage.total_cost / budget.limit
+            let percentage_used = if limit > 0.0 {
+                current_usage.total_cost / limit
             } else {
                 0.0
             };
 
-            let is_exhausted = current_usage.total_cost >= budget.limit;
+            let is_exhausted = current_usage.total_cost >= limit;
             let is_warning = percentage_used >= budget.warning_threshold;
 
             Ok(BudgetStatus {
                 budget_id: budget.id.clone(),
-                limit: budget.limit,
+                limit,
                 used: current_usage.total_cost,
                 percentage_used,
                 is_exhausted,
                 is_warning,
-                remaining: budget.limit - current_usage.total_cost,
+                remaining: limit - current_usage.total_cost,
                 message: if is_exhausted {
-                    format!("Budget exhausted: ${:.2} of ${:.2} used", current_usage.total_cost, budget.limit)
+                    format!("Budget exhausted: ${:.2} of ${:.2} used", current_usage.total_cost, limit)
                 } else if is_warning {
                     format!("Budget warning: {:.1}% of budget used", percentage_used * 100.0)
                 } else {
-                    format!("Budget healthy: ${:.2} of ${:.2} used", current_usage.total_cost, budget.limit)
+                    format!("Budget healthy: ${:.2} of ${:.2} used", current_usage.total_cost, limit)
                 },
+                carried_over: period.carried_in,
+                period_start: Some(period.start),
+                resets_at: Some(period.end),
             })
         } else {
             // No budget set - unlimited
@@ -463,10 +529,169 @@ impl BudgetManager {
                 is_warning: false,
                 remaining: f64::MAX,
                 message: "No budget limit set".to_string(),
+                carried_over: 0.0,
+                period_start: None,
+                resets_at: None,
             })
         }
     }
 
+    /// Check whether a request estimated to cost `estimated_cost` fits its budget
+    ///
+    /// Fails with [`CostError::QuotaExceeded`] when the budget is exhausted or the request
+    /// would exceed it. Returns `None` when no budget applies.
+    pub async fn preflight(&self, context: &CostContext, estimated_cost: f64) -> Result<Option<BudgetPreflight>, CostError> {
+        let budget_id = usage_key(&context.user_id, context.project_id.as_deref());
+        let Some(budget) = self.budgets.read().await.get(&budget_id).cloned() else {
+            return Ok(None);
+        };
+        let status = self.check_budget(context).await?;
+
+        // The status limit includes what was carried over from the previous period
+        let projected = status.used + estimated_cost;
+        if status.is_exhausted || projected > status.limit {
+            return Err(CostError::QuotaExceeded(QuotaExceeded {
+                budget_id,
+                period: budget.period,
+                limit: status.limit,
+                used: status.used,
+                estimated_cost,
+                remaining: status.remaining,
+            }));
+        }
+
+        let projected_percentage = if status.limit > 0.0 { projected / status.limit } else { 0.0 };
+        Ok(Some(BudgetPreflight {
+            is_warning: projected_percentage >= budget.warning_threshold,
+            status,
+            estimated_cost,
+            projected_percentage,
+        }))
+    }
+
+    /// Close every budget period that ended by `now`, returning the closed periods
+    pub async fn roll_over(&self, now: DateTime<Utc>) -> Result<Vec<BudgetPeriodRecord>, CostError> {
+        let budgets: Vec<Budget> = self.budgets.read().await.values().cloned().collect();
+        let mut closed = Vec::new();
+        for budget in budgets {
+            let before = self.periods.read().await.get(&budget.id).map_or(0, |ledger| ledger.closed.len());
+            self.open_period(&budget, now).await?;
+            if let Some(ledger) = self.periods.read().await.get(&budget.id) {
+                closed.extend(ledger.closed.iter().skip(before).cloned());
+            }
+        }
+        Ok(closed)
+    }
+
+    /// Spawn the background task that closes ended budget periods
+    pub fn spawn_rollover(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(BUDGET_ROLLOVER_INTERVAL);
+            loop {
+                ticker.tick().await;
+                match self.roll_over(Utc::now()).await {
+                    Ok(closed) => {
+                        for period in closed {
+                            tracing::info!(
+                                "Closed {:?} period of budget {}: ${:.2} of ${:.2} used",
+                                period.period, period.budget_id, period.used, period.limit + period.carried_in
+                            );
+                        }
+                    }
+                    Err(e) => tracing::warn!("Failed to roll over budget periods: {}", e),
+                }
+            }
+        })
+    }
+
+    /// A budget with its open period and the periods it closed, most recent first
+    pub async fn budget_periods(&self, budget_id: &str) -> Result<Option<BudgetPeriods>, CostError> {
+        let Some(budget) = self.get_budget(budget_id).await else {
+            return Ok(None);
+        };
+        let period = self.open_period(&budget, Utc::now()).await?;
+        let usage = self.period_usage(&budget, &period).await?;
+        let current = BudgetPeriodRecord {
+            budget_id: budget.id.clone(),
+            period: budget.period.clone(),
+            period_start: period.start,
+            period_end: period.end,
+            limit: budget.limit,
+            carried_in: period.carried_in,
+            used: usage.total_cost,
+            request_count: usage.request_count,
+            carried_out: None,
+        };
+        let history = self.periods.read().await
+            .get(budget_id)
+            .map(|ledger| ledger.closed.iter().rev().cloned().collect())
+            .unwrap_or_default();
+        Ok(Some(BudgetPeriods { budget, current, history }))
+    }
+
+    /// The budget's open period at `now`, closing the periods that ended before it
+    async fn open_period(&self, budget: &Budget, now: DateTime<Utc>) -> Result<OpenPeriod, CostError> {
+        let mut periods = self.periods.write().await;
+        let ledger = periods.entry(budget.id.clone()).or_default();
+        let mut open = match ledger.open.take() {
+            Some(open) => open,
+            None => {
+                let (start, end) = budget.period_bounds(now);
+                OpenPeriod { start, end, carried_in: 0.0 }
+            }
+        };
+
+        while now >= open.end {
+            let usage = self.usage_tracker.get_usage_between(&budget.id, open.start, open.end).await?;
+            let unspent = budget.limit + open.carried_in - usage.total_cost;
+            let carried_out = match budget.carry_over {
+                CarryOver::None => 0.0,
+                CarryOver::Unused => unspent.max(0.0),
+                CarryOver::Balance => unspent,
+            };
+            ledger.closed.push(BudgetPeriodRecord {
+                budget_id: budget.id.clone(),
+                period: budget.period.clone(),
+                period_start: open.start,
+                period_end: open.end,
+                limit: budget.limit,
+                carried_in: open.carried_in,
+                used: usage.total_cost,
+                request_count: usage.request_count,
+                carried_out: Some(carried_out),
+            });
+
+            let (start, end) = budget.period_bounds(open.end);
+            open = OpenPeriod { start, end, carried_in: carried_out };
+        }
+        if ledger.closed.len() > MAX_BUDGET_PERIOD_HISTORY {
+            let excess = ledger.closed.len() - MAX_BUDGET_PERIOD_HISTORY;
+            ledger.closed.drain(..excess);
+        }
+
+        ledger.open = Some(open.clone());
+        Ok(open)
+    }
+
+    /// Spend recorded against a budget in one of its periods
+    async fn period_usage(&self, budget: &Budget, period: &OpenPeriod) -> Result<UsageInfo, CostError> {
+        // Trackers keep running totals of the current UTC periods
+        if budget.utc_offset_minutes == 0 && period.start == period_start(&budget.period, Utc::now()) {
+            let (user_id, project_id) = match budget.id.split_once(':') {
+                Some(("project", project_id)) => ("", Some(project_id)),
+                Some((_, user_id)) => (user_id, None),
+                None => (budget.id.as_str(), None),
+            };
+            return match budget.period {
+                BudgetPeriod::Daily => self.usage_tracker.get_daily_usage(user_id, project_id).await,
+                BudgetPeriod::Weekly => self.usage_tracker.get_weekly_usage(user_id, project_id).await,
+                BudgetPeriod::Monthly => self.usage_tracker.get_monthly_usage(user_id, project_id).await,
+                BudgetPeriod::Yearly => self.usage_tracker.get_yearly_usage(user_id, project_id).await,
+            };
+        }
+        self.usage_tracker.get_usage_between(&budget.id, period.start, period.end).await
+    }
+
     /// Get daily usage
     pub async fn get_daily_usage(&self, user_id: &str, project_id: Option<&str>) -> Result<UsageInfo, CostError> {
         self.usage_tracker.get_daily_usage(user_id, project_id).await
@@ -543,6 +768,21 @@ impl CostAnalyzer {
         input_tokens: u32,
         output_tokens: u32,
     ) -> Result<CostEstimate, CostError> {
+        // Prices set in the catalog take precedence over the defaults
+        if let Some(price) = PriceCatalog::global().price(provider, model) {
+            let input_cost = input_tokens as f64 * price.input_cost_per_token;
+            let output_cost = output_tokens as f64 * price.output_cost_per_token;
+            let total_cost = input_cost + output_cost;
+
+            return Ok(CostEstimate {
+                input_cost,
+                output_cost,
+                total_cost,
+                cost_per_token: total_cost / (input_tokens + output_tokens) as f64,
+                confidence: 0.9,
+            });
+        }
+
         if let Some(provider_models) = self.provider_pricing.get(provider) {
             if let Some(pricing) = provider_models.get(model) {
                 let input_cost = input_tokens as f64 * pricing.input_cost_per_token;
@@ -608,6 +848,43 @@ impl CostAnalyzer {
     }
 }
 
+/// Image models and their price in USD per image, by provider, model, size and
+/// quality; `*` matches any size or quality and the first matching entry wins
+const IMAGE_PRICES: &[(LLMProviderType, &str, &str, &str, f64)] = &[
+    (LLMProviderType::OpenAI, "dall-e-2", "256x256", "*", 0.016),
+    (LLMProviderType::OpenAI, "dall-e-2", "512x512", "*", 0.018),
+    (LLMProviderType::OpenAI, "dall-e-2", "*", "*", 0.020),
+    (LLMProviderType::OpenAI, "dall-e-3", "1024x1024", "standard", 0.040),
+    (LLMProviderType::OpenAI, "dall-e-3", "*", "standard", 0.080),
+    (LLMProviderType::OpenAI, "dall-e-3", "1024x1024", "hd", 0.080),
+    (LLMProviderType::OpenAI, "dall-e-3", "*", "hd", 0.120),
+    (LLMProviderType::Google, "imagen-3.0-generate-002", "*", "*", 0.030),
+    (LLMProviderType::Google, "imagen-3.0-fast-generate-001", "*", "*", 0.020),
+    (LLMProviderType::Replicate, "black-forest-labs/flux-schnell", "*", "*", 0.003),
+    (LLMProviderType::Replicate, "black-forest-labs/flux-dev", "*", "*", 0.025),
+    (LLMProviderType::Replicate, "black-forest-labs/flux-1.1-pro", "*", "*", 0.040),
+];
+
+/// Image models with a known price at a provider
+pub fn priced_image_models(provider: &LLMProviderType) -> Vec<String> {
+    let mut models: Vec<String> = Vec::new();
+    for (p, model, _, _, _) in IMAGE_PRICES {
+        if p == provider && !models.iter().any(|m| m == model) {
+            models.push(model.to_string());
+        }
+    }
+    models
+}
+
+/// Price in USD of generating `count` images of `size` (e.g. `1024x1024`) and
+/// `quality` with a model; unknown models are free
+pub fn image_cost(provider: &LLMProviderType, model: &str, size: &str, quality: &str, count: usize) -> f64 {
+    IMAGE_PRICES
+        .iter()
+        .find(|(p, m, s, q, _)| p == provider && *m == model && (*s == "*" || *s == size) && (*q == "*" || *q == quality))
+        .map_or(0.0, |(_, _, _, _, per_image)| per_image * count as f64)
+}
+
 /// Data structures
 
 #[derive(Debug, Clone)]
@@ -666,7 +943,7 @@ pub enum RuleAction {
     BlockProvider,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Budget {
     pub id: String,
     pub user_id: Option<String>,
@@ -674,17 +951,78 @@ pub struct Budget {
     pub limit: f64,
     pub period: BudgetPeriod,
     pub warning_threshold: f64,
+    /// What a closed period passes on to the next one
+    #[serde(default)]
+    pub carry_over: CarryOver,
+    /// Offset from UTC, in minutes, of the timezone whose midnight starts each period
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone)]
+impl Budget {
+    /// Timezone periods start in; UTC when the offset exceeds [`MAX_UTC_OFFSET_MINUTES`]
+    pub fn timezone(&self) -> FixedOffset {
+        let minutes = if self.utc_offset_minutes.abs() <= MAX_UTC_OFFSET_MINUTES { self.utc_offset_minutes } else { 0 };
+        FixedOffset::east_opt(minutes * 60).unwrap()
+    }
+
+    /// Start and end of the period containing `time`
+    pub fn period_bounds(&self, time: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+        period_bounds(&self.period, time, self.timezone())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum BudgetPeriod {
     Daily,
+    /// Weeks start on Monday
+    Weekly,
     Monthly,
     Yearly,
 }
 
+/// What a closed budget period passes on to the next one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CarryOver {
+    /// Every period starts with the budget's limit
+    #[default]
+    None,
+    /// What was left unspent is added to the next period's limit
+    Unused,
+    /// What was left unspent is added to, and overspend taken from, the next period's limit
+    Balance,
+}
+
+/// Spend of a budget over one period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BudgetPeriodRecord {
+    pub budget_id: String,
+    pub period: BudgetPeriod,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    /// The budget's limit, before carry-over
+    pub limit: f64,
+    /// Added to the limit from the previous period; negative after overspending
+    pub carried_in: f64,
+    pub used: f64,
+    pub request_count: u32,
+    /// Passed on to the next period; `None` while the period is open
+    pub carried_out: Option<f64>,
+}
+
+/// A budget with its open period and the periods it closed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetPeriods {
+    pub budget: Budget,
+    pub current: BudgetPeriodRecord,
+    /// Closed periods, most recent first
+    pub history: Vec<BudgetPeriodRecord>,
+}
+
 #[derive(Debug, Clone)]
 pub struct BudgetStatus {
     pub budget_id: String,
@@ -695,6 +1033,51 @@ pub struct BudgetStatus {
     pub is_warning: bool,
     pub remaining: f64,
     pub message: String,
+    /// Part of the limit carried over from the previous period
+    pub carried_over: f64,
+    pub period_start: Option<DateTime<Utc>>,
+    /// When the budget's current period ends
+    pub resets_at: Option<DateTime<Utc>>,
+}
+
+/// Outcome of checking a request's estimated cost against its budget before dispatch
+#[derive(Debug, Clone)]
+pub struct BudgetPreflight {
+    /// The budget as it stands before the request
+    pub status: BudgetStatus,
+    pub estimated_cost: f64,
+    /// Share of the limit used once the request's estimated cost is spent
+    pub projected_percentage: f64,
+    /// Whether the request takes the budget past its warning threshold
+    pub is_warning: bool,
+}
+
+impl BudgetPreflight {
+    /// Budget left once the request's estimated cost is spent
+    pub fn projected_remaining(&self) -> f64 {
+        self.status.remaining - self.estimated_cost
+    }
+}
+
+/// Why a request was refused by [`BudgetManager::preflight`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuotaExceeded {
+    pub budget_id: String,
+    pub period: BudgetPeriod,
+    pub limit: f64,
+    pub used: f64,
+    pub estimated_cost: f64,
+    pub remaining: f64,
+}
+
+impl std::fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "request estimated at ${:.4} exceeds the ${:.4} remaining of budget {} (${:.2} of ${:.2} used)",
+            self.estimated_cost, self.remaining.max(0.0), self.budget_id, self.used, self.limit
+        )
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -748,9 +1131,90 @@ pub struct RoutingDecision {
 #[async_trait::async_trait]
 pub trait UsageTracker: Send + Sync {
     async fn get_daily_usage(&self, user_id: &str, project_id: Option<&str>) -> Result<UsageInfo, CostError>;
+    async fn get_weekly_usage(&self, user_id: &str, project_id: Option<&str>) -> Result<UsageInfo, CostError>;
     async fn get_monthly_usage(&self, user_id: &str, project_id: Option<&str>) -> Result<UsageInfo, CostError>;
     async fn get_yearly_usage(&self, user_id: &str, project_id: Option<&str>) -> Result<UsageInfo, CostError>;
     async fn record_usage(&self, cost_info: &CostInfo) -> Result<(), CostError>;
+    /// Every cost recorded between `start` and `end`, across users and projects
+    async fn list_usage(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<CostInfo>, CostError>;
+    /// Usage tracked under `usage_key` from `start` up to, but excluding, `end`
+    async fn get_usage_between(&self, usage_key: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<UsageInfo, CostError> {
+        let costs = self.list_usage(start, end).await?;
+        Ok(summarize_usage(costs.iter().filter(|cost| cost.timestamp < end && cost_usage_key(cost) == usage_key)))
+    }
+    /// Cost recorded for a request, the latest if it was recorded more than once
+    async fn get_usage(&self, request_id: &Uuid) -> Result<Option<CostInfo>, CostError>;
+}
+
+/// Key usage and budgets are tracked under: the project when there is one, else the user
+pub fn usage_key(user_id: &str, project_id: Option<&str>) -> String {
+    if let Some(pid) = project_id {
+        format!("project:{}", pid)
+    } else {
+        format!("user:{}", user_id)
+    }
+}
+
+/// Key a recorded cost is tracked under
+///
+/// Costs without a user or project are kept for analytics under `anonymous`, which no
+/// budget can name.
+pub fn cost_usage_key(cost_info: &CostInfo) -> String {
+    match (&cost_info.project_id, &cost_info.user_id) {
+        (Some(project_id), _) => format!("project:{}", project_id),
+        (None, Some(user_id)) => format!("user:{}", user_id),
+        (None, None) => "anonymous".to_string(),
+    }
+}
+
+/// Start of the UTC day containing `time`
+pub fn day_start(time: DateTime<Utc>) -> DateTime<Utc> {
+    time.date_naive().and_hms_opt(0, 0, 0).unwrap().and_local_timezone(Utc).unwrap()
+}
+
+/// Start of the UTC budget period containing `time`
+pub fn period_start(period: &BudgetPeriod, time: DateTime<Utc>) -> DateTime<Utc> {
+    period_bounds(period, time, FixedOffset::east_opt(0).unwrap()).0
+}
+
+/// Start and end of the budget period containing `time`, with periods starting at midnight in `timezone`
+pub fn period_bounds(period: &BudgetPeriod, time: DateTime<Utc>, timezone: FixedOffset) -> (DateTime<Utc>, DateTime<Utc>) {
+    let date = time.with_timezone(&timezone).date_naive();
+    let (first_day, next_first_day) = match period {
+        BudgetPeriod::Daily => (date, date + Days::new(1)),
+        BudgetPeriod::Weekly => {
+            let monday = date - Days::new(date.weekday().num_days_from_monday() as u64);
+            (monday, monday + Days::new(7))
+        }
+        BudgetPeriod::Monthly => {
+            let first = date.with_day(1).unwrap();
+            (first, first + Months::new(1))
+        }
+        BudgetPeriod::Yearly => {
+            let first = date.with_ordinal(1).unwrap();
+            (first, first + Months::new(12))
+        }
+    };
+    let midnight = |day: NaiveDate| {
+        day.and_hms_opt(0, 0, 0).unwrap().and_local_timezone(timezone).unwrap().with_timezone(&Utc)
+    };
+    (midnight(first_day), midnight(next_first_day))
+}
+
+/// Sum recorded costs into usage totals
+pub fn summarize_usage<'a>(costs: impl IntoIterator<Item = &'a CostInfo>) -> UsageInfo {
+    let mut usage = UsageInfo {
+        total_cost: 0.0,
+        total_tokens: 0,
+        request_count: 0,
+        percentage_used: 0.0,
+    };
+    for cost in costs {
+        usage.total_cost += cost.cost_usd;
+        usage.total_tokens += cost.input_tokens + cost.output_tokens;
+        usage.request_count += 1;
+    }
+    usage
 }
 
 /// Cost optimization errors
@@ -758,6 +1222,9 @@ pub trait UsageTracker: Send + Sync {
 pub enum CostError {
     #[error("Budget exhausted: {0}")]
     BudgetExhausted(String),
+
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(QuotaExceeded),
     
     #[error("No valid providers available")]
     NoValidProviders,
@@ -775,148 +1242,225 @@ pub enum CostError {
     Internal(String),
 }
 
+impl CostError {
+    /// Stable error code for API responses
+    pub fn code(&self) -> crate::ErrorCode {
+        use crate::ErrorCode;
+        match self {
+            CostError::BudgetExhausted(_) => ErrorCode::BudgetExceeded,
+            CostError::QuotaExceeded(_) => ErrorCode::QuotaExceeded,
+            CostError::NoValidProviders | CostError::UnknownProvider(_) => {
+                ErrorCode::ProviderUnavailable
+            }
+            CostError::UsageTracking(_)
+            | CostError::BudgetManagement(_)
+            | CostError::Internal(_) => ErrorCode::Internal,
+        }
+    }
+}
+
 /// In-memory usage tracker for development
+///
+/// Usage is lost on restart and not shared between replicas; see
+/// [`usage_tracking`](super::usage_tracking) for persistent trackers. Costs without a
+/// user or project are rejected unless [`with_anonymous_usage`](Self::with_anonymous_usage)
+/// asks to keep them.
 pub struct InMemoryUsageTracker {
     usage_data: Arc<RwLock<HashMap<String, Vec<CostInfo>>>>,
+    record_anonymous: bool,
 }
 
 impl InMemoryUsageTracker {
     pub fn new() -> Self {
         Self {
             usage_data: Arc::new(RwLock::new(HashMap::new())),
+            record_anonymous: false,
         }
     }
+
+    /// Keep costs without a user or project under `anonymous` for analytics
+    pub fn with_anonymous_usage(mut self) -> Self {
+        self.record_anonymous = true;
+        self
+    }
+
+    async fn usage_since(&self, user_id: &str, project_id: Option<&str>, period: BudgetPeriod) -> UsageInfo {
+        let usage_data = self.usage_data.read().await;
+        let since = period_start(&period, Utc::now());
+        let costs = usage_data.get(&usage_key(user_id, project_id)).into_iter().flatten();
+        summarize_usage(costs.filter(|cost| cost.timestamp >= since))
+    }
 }
 
 #[async_trait::async_trait]
 impl UsageTracker for InMemoryUsageTracker {
     async fn get_daily_usage(&self, user_id: &str, project_id: Option<&str>) -> Result<UsageInfo, CostError> {
-        let usage_data = self.usage_data.read().await;
-        let key = if let Some(pid) = project_id {
-            format!("project:{}", pid)
-        } else {
-            format!("user:{}", user_id)
-        };
-        
-        if let Some(costs) = usage_data.get(&key) {
-            let today = Utc::now().date_naive();
-            let day_start = today.and_hms_opt(0, 0, 0).unwrap().and_local_timezone(Utc).unwrap();
-            
-            let daily_costs: Vec<_> = costs.iter()
-                .filter(|cost| cost.timestamp >= day_start)
-                .collect();
-            
-            let total_cost = daily_costs.iter().map(|c| c.cost_usd).sum();
-            let total_tokens = daily_costs.iter().map(|c| c.input_tokens + c.output_tokens).sum();
-            let request_count = daily_costs.len() as u32;
-            
-            Ok(UsageInfo {
-                total_cost,
-                total_tokens,
-                request_count,
-                percentage_used: 0.0,
-            })
-        } else {
-            Ok(UsageInfo {
-                total_cost: 0.0,
-                total_tokens: 0,
-                request_count: 0,
-                percentage_used: 0.0,
-            })
-        }
+        Ok(self.usage_since(user_id, project_id, BudgetPeriod::Daily).await)
+    }
+
+    async fn get_weekly_usage(&self, user_id: &str, project_id: Option<&str>) -> Result<UsageInfo, CostError> {
+        Ok(self.usage_since(user_id, project_id, BudgetPeriod::Weekly).await)
     }
 
     async fn get_monthly_usage(&self, user_id: &str, project_id: Option<&str>) -> Result<UsageInfo, CostError> {
-        let usage_data = self.usage_data.read().await;
-        let key = if let Some(pid) = project_id {
-            format!("project:{}", pid)
-        } else {
-            format!("user:{}", user_id)
-        };
-        
-        if let Some(costs) = usage_data.get(&key) {
-            let now = Utc::now();
-            let month_start = now.date_naive()
-                .with_day(1).unwrap()
-                .and_hms_opt(0, 0, 0).unwrap()
-                .and_local_timezone(Utc).unwrap();
-            
-            let monthly_costs: Vec<_> = costs.iter()
-                .filter(|cost| cost.timestamp >= month_start)
-                .collect();
-            
-            let total_cost = monthly_costs.iter().map(|c| c.cost_usd).sum();
-            let total_tokens = monthly_costs.iter().map(|c| c.input_tokens + c.output_tokens).sum();
-            let request_count = monthly_costs.len() as u32;
-            
-            Ok(UsageInfo {
-                total_cost,
-                total_tokens,
-                request_count,
-                percentage_used: 0.0,
-            })
-        } else {
-            Ok(UsageInfo {
-                total_cost: 0.0,
-                total_tokens: 0,
-                request_count: 0,
-                percentage_used: 0.0,
-            })
-        }
+        Ok(self.usage_since(user_id, project_id, BudgetPeriod::Monthly).await)
     }
 
     async fn get_yearly_usage(&self, user_id: &str, project_id: Option<&str>) -> Result<UsageInfo, CostError> {
-        let usage_data = self.usage_data.read().await;
-        let key = if let Some(pid) = project_id {
-            format!("project:{}", pid)
-        } else {
-            format!("user:{}", user_id)
-        };
-        
-        if let Some(costs) = usage_data.get(&key) {
-            let now = Utc::now();
-            let year_start = now.date_naive()
-                .with_ordinal(1).unwrap()
-                .and_hms_opt(0, 0, 0).unwrap()
-                .and_local_timezone(Utc).unwrap();
-            
-            let yearly_costs: Vec<_> = costs.iter()
-                .filter(|cost| cost.timestamp >= year_start)
-                .collect();
-            
-            let total_cost = yearly_costs.iter().map(|c| c.cost_usd).sum();
-            let total_tokens = yearly_costs.iter().map(|c| c.input_tokens + c.output_tokens).sum();
-            let request_count = yearly_costs.len() as u32;
-            
-            Ok(UsageInfo {
-                total_cost,
-                total_tokens,
-                request_count,
-                percentage_used: 0.0,
-            })
-        } else {
-            Ok(UsageInfo {
-                total_cost: 0.0,
-                total_tokens: 0,
-                request_count: 0,
-                percentage_used: 0.0,
-            })
-        }
+        Ok(self.usage_since(user_id, project_id, BudgetPeriod::Yearly).await)
     }
 
     async fn record_usage(&self, cost_info: &CostInfo) -> Result<(), CostError> {
-        let mut usage_data = self.usage_data.write().await;
-        
-        let key = if let Some(project_id) = &cost_info.project_id {
-            format!("project:{}", project_id)
-        } else if let Some(user_id) = &cost_info.user_id {
-            format!("user:{}", user_id)
-        } else {
+        if !self.record_anonymous && cost_info.project_id.is_none() && cost_info.user_id.is_none() {
             return Err(CostError::UsageTracking("No user or project ID provided".to_string()));
-        };
-        
-        usage_data.entry(key).or_insert_with(Vec::new).push(cost_info.clone());
-        
+        }
+        let mut usage_data = self.usage_data.write().await;
+        usage_data.entry(cost_usage_key(cost_info)).or_insert_with(Vec::new).push(cost_info.clone());
         Ok(())
     }
-}
\ No newline at end of file
+
+    async fn list_usage(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<CostInfo>, CostError> {
+        let usage_data = self.usage_data.read().await;
+        let mut costs: Vec<CostInfo> = usage_data.values()
+            .flatten()
+            .filter(|cost| cost.timestamp >= start && cost.timestamp <= end)
+            .cloned()
+            .collect();
+        costs.sort_by_key(|cost| cost.timestamp);
+        Ok(costs)
+    }
+
+    async fn get_usage(&self, request_id: &Uuid) -> Result<Option<CostInfo>, CostError> {
+        let usage_data = self.usage_data.read().await;
+        Ok(usage_data.values()
+            .flatten()
+            .filter(|cost| cost.request_id == *request_id)
+            .max_by_key(|cost| cost.timestamp)
+            .cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> DateTime<Utc> {
+        time.parse().unwrap()
+    }
+
+    fn budget(id: &str, carry_over: CarryOver) -> Budget {
+        let (user_id, project_id) = match id.split_once(':') {
+            Some(("project", project_id)) => (None, Some(project_id.to_string())),
+            _ => (id.strip_prefix("user:").map(str::to_string), None),
+        };
+        Budget {
+            id: id.to_string(),
+            user_id,
+            project_id,
+            limit: 1.0,
+            period: BudgetPeriod::Daily,
+            warning_threshold: 0.8,
+            carry_over,
+            utc_offset_minutes: 0,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn cost(user_id: Option<&str>, project_id: Option<&str>, cost_usd: f64, timestamp: DateTime<Utc>) -> CostInfo {
+        CostInfo {
+            request_id: Uuid::new_v4(),
+            provider: LLMProviderType::OpenAI,
+            model: "gpt-4".to_string(),
+            input_tokens: 100,
+            output_tokens: 50,
+            cost_usd,
+            timestamp,
+            user_id: user_id.map(str::to_string),
+            project_id: project_id.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_period_bounds() {
+        let utc = FixedOffset::east_opt(0).unwrap();
+        // Wednesday
+        let time = at("2026-10-14T12:00:00Z");
+        assert_eq!(period_bounds(&BudgetPeriod::Weekly, time, utc), (at("2026-10-12T00:00:00Z"), at("2026-10-19T00:00:00Z")));
+        assert_eq!(period_bounds(&BudgetPeriod::Monthly, at("2026-12-31T23:00:00Z"), utc), (at("2026-12-01T00:00:00Z"), at("2027-01-01T00:00:00Z")));
+        assert_eq!(period_bounds(&BudgetPeriod::Yearly, time, utc), (at("2026-01-01T00:00:00Z"), at("2027-01-01T00:00:00Z")));
+
+        // 20:00 UTC is already the next day in Tokyo
+        let mut tokyo = budget("user:alice", CarryOver::None);
+        tokyo.utc_offset_minutes = 9 * 60;
+        assert_eq!(tokyo.period_bounds(at("2026-10-14T20:00:00Z")), (at("2026-10-14T15:00:00Z"), at("2026-10-15T15:00:00Z")));
+        tokyo.utc_offset_minutes = 24 * 60;
+        assert_eq!(tokyo.timezone(), utc);
+    }
+
+    #[tokio::test]
+    async fn test_rollover_carries_balances() {
+        let tracker = Arc::new(InMemoryUsageTracker::new());
+        let manager = BudgetManager::new(tracker.clone());
+        manager.set_budget(budget("user:alice", CarryOver::Unused)).await;
+        manager.set_budget(budget("project:acme", CarryOver::Balance)).await;
+        manager.set_budget(budget("user:bob", CarryOver::None)).await;
+
+        assert!(manager.roll_over(at("2026-10-14T12:00:00Z")).await.unwrap().is_empty());
+        for spend in [
+            cost(Some("alice"), None, 0.25, at("2026-10-14T13:00:00Z")),
+            cost(Some("alice"), Some("acme"), 1.5, at("2026-10-14T14:00:00Z")),
+            cost(Some("bob"), None, 0.5, at("2026-10-14T15:00:00Z")),
+        ] {
+            tracker.record_usage(&spend).await.unwrap();
+        }
+
+        let closed = manager.roll_over(at("2026-10-15T00:30:00Z")).await.unwrap();
+        let carried: HashMap<String, (f64, Option<f64>)> = closed
+            .iter()
+            .map(|period| (period.budget_id.clone(), (period.used, period.carried_out)))
+            .collect();
+        assert_eq!(carried["user:alice"], (0.25, Some(0.75)));
+        assert_eq!(carried["project:acme"], (1.5, Some(-0.5)));
+        assert_eq!(carried["user:bob"], (0.5, Some(0.0)));
+        assert!(closed.iter().all(|period| period.period_start == at("2026-10-14T00:00:00Z")));
+
+        // Two days later the unused budget has accumulated and the overspend is paid off
+        let closed = manager.roll_over(at("2026-10-17T00:30:00Z")).await.unwrap();
+        assert_eq!(closed.len(), 6);
+        let last = |id: &str| closed.iter().rfind(|period| period.budget_id == id).unwrap().clone();
+        assert_eq!(last("user:alice").carried_in, 1.75);
+        assert_eq!(last("user:alice").carried_out, Some(2.75));
+        assert_eq!(last("project:acme").carried_in, 0.5);
+        assert_eq!(last("user:bob").carried_in, 0.0);
+
+        // Changing the period starts afresh, keeping the history
+        let mut weekly = budget("user:alice", CarryOver::Unused);
+        weekly.period = BudgetPeriod::Weekly;
+        manager.set_budget(weekly).await;
+        assert!(manager.roll_over(at("2026-10-17T01:00:00Z")).await.unwrap().is_empty());
+        assert_eq!(manager.periods.read().await["user:alice"].closed.len(), 3);
+        assert_eq!(manager.periods.read().await["user:alice"].open.as_ref().unwrap().carried_in, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_anonymous_usage_is_opt_in() {
+        let anonymous = cost(None, None, 0.25, at("2026-10-14T13:00:00Z"));
+        assert!(InMemoryUsageTracker::new().record_usage(&anonymous).await.is_err());
+
+        let tracker = InMemoryUsageTracker::new().with_anonymous_usage();
+        tracker.record_usage(&anonymous).await.unwrap();
+        assert_eq!(tracker.get_usage(&anonymous.request_id).await.unwrap().unwrap().cost_usd, 0.25);
+    }
+
+    #[test]
+    fn test_image_cost() {
+        let openai = LLMProviderType::OpenAI;
+        assert_eq!(image_cost(&openai, "dall-e-3", "1024x1024", "standard", 2), 0.08);
+        assert_eq!(image_cost(&openai, "dall-e-3", "1792x1024", "hd", 1), 0.12);
+        assert_eq!(image_cost(&openai, "dall-e-2", "512x512", "standard", 1), 0.018);
+        assert_eq!(image_cost(&LLMProviderType::Replicate, "black-forest-labs/flux-schnell", "1024x1024", "hd", 4), 0.012);
+        assert_eq!(image_cost(&openai, "unknown", "1024x1024", "standard", 1), 0.0);
+        assert_eq!(priced_image_models(&openai), vec!["dall-e-2", "dall-e-3"]);
+    }
+}
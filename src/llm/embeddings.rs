@@ -0,0 +1,112 @@
+//! Embeddings
+//!
+//! Embedding backends differ in how many inputs one call accepts and in whether they
+//! can shorten their vectors, so the router normalizes both before a response reaches
+//! the caller. Inputs beyond a provider's [`max_batch_size`] are split across several
+//! provider calls and the results stitched back together in order. A requested number
+//! of `dimensions` is passed to models that support it natively (OpenAI's
+//! `text-embedding-3` family); every other model's vectors are shortened afterwards by
+//! [`reduce_dimensions`], which truncates and re-normalizes them the way those models
+//! do internally.
+//!
+//! [`encode_base64`] produces OpenAI's `encoding_format: base64` representation.
+
+use base64::{engine::general_purpose, Engine as _};
+
+use super::{EmbeddingsInput, LLMError, LLMProviderType, LLMResult};
+
+/// Most inputs sent to a provider in one embeddings call
+pub fn max_batch_size(provider: &LLMProviderType) -> usize {
+    match provider {
+        LLMProviderType::OpenAI => 2048,
+        LLMProviderType::Ollama => 512,
+        _ => 256,
+    }
+}
+
+/// Whether a model shortens its embeddings itself when asked for fewer `dimensions`
+pub fn supports_native_dimensions(provider: &LLMProviderType, model: &str) -> bool {
+    *provider == LLMProviderType::OpenAI && model.starts_with("text-embedding-3")
+}
+
+impl EmbeddingsInput {
+    /// The texts to embed, in order
+    pub fn texts(&self) -> Vec<String> {
+        match self {
+            EmbeddingsInput::Text(text) => vec![text.clone()],
+            EmbeddingsInput::TextArray(texts) => texts.clone(),
+        }
+    }
+
+    /// Split into inputs of at most `size` texts each; a single input when it fits
+    pub fn batches(&self, size: usize) -> Vec<EmbeddingsInput> {
+        match self {
+            EmbeddingsInput::TextArray(texts) if texts.len() > size => texts
+                .chunks(size.max(1))
+                .map(|chunk| EmbeddingsInput::TextArray(chunk.to_vec()))
+                .collect(),
+            _ => vec![self.clone()],
+        }
+    }
+}
+
+/// Shorten an embedding to its first `dimensions` components and scale it back to unit
+/// length; fails if it has fewer components than requested
+pub fn reduce_dimensions(embedding: &mut Vec<f64>, dimensions: usize) -> LLMResult<()> {
+    if dimensions == 0 || dimensions > embedding.len() {
+        return Err(LLMError::InvalidRequest(format!(
+            "dimensions must be between 1 and {} for this model",
+            embedding.len()
+        )));
+    }
+    if dimensions == embedding.len() {
+        return Ok(());
+    }
+    embedding.truncate(dimensions);
+    let norm = embedding.iter().map(|x| x * x).sum::<f64>().sqrt();
+    if norm > 0.0 {
+        embedding.iter_mut().for_each(|x| *x /= norm);
+    }
+    Ok(())
+}
+
+/// An embedding as base64 of its little-endian 32-bit floats, as OpenAI encodes it
+pub fn encode_base64(embedding: &[f32]) -> String {
+    let bytes: Vec<u8> = embedding.iter().flat_map(|x| x.to_le_bytes()).collect();
+    general_purpose::STANDARD.encode(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batches() {
+        let texts: Vec<String> = (0..5).map(|i| i.to_string()).collect();
+        let batches = EmbeddingsInput::TextArray(texts).batches(2);
+        let sizes: Vec<usize> = batches.iter().map(|batch| batch.texts().len()).collect();
+        assert_eq!(sizes, vec![2, 2, 1]);
+        assert_eq!(batches[2].texts(), vec!["4".to_string()]);
+
+        let single = EmbeddingsInput::Text("hello".to_string()).batches(2);
+        assert!(matches!(single.as_slice(), [EmbeddingsInput::Text(_)]));
+    }
+
+    #[test]
+    fn test_reduce_dimensions() {
+        let mut embedding = vec![3.0, 4.0, 12.0];
+        reduce_dimensions(&mut embedding, 2).unwrap();
+        assert_eq!(embedding, vec![0.6, 0.8]);
+
+        assert!(reduce_dimensions(&mut embedding, 3).is_err());
+        assert!(reduce_dimensions(&mut embedding, 0).is_err());
+    }
+
+    #[test]
+    fn test_encode_base64() {
+        let encoded = encode_base64(&[1.0, -2.0]);
+        let bytes = general_purpose::STANDARD.decode(encoded).unwrap();
+        assert_eq!(bytes.len(), 8);
+        assert_eq!(f32::from_le_bytes(bytes[4..8].try_into().unwrap()), -2.0);
+    }
+}
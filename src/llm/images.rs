@@ -0,0 +1,725 @@
+//! Image Generation
+//!
+//! `POST /v1/images/generations` is routed to [`ImageProvider`]s by model: DALL·E
+//! models to OpenAI, Imagen models to Gemini and FLUX models to Replicate.
+//! [`ImageProviders`] holds the providers configured by the environment.
+//!
+//! Requests use OpenAI's parameters and each provider maps them onto its own API: the
+//! size is snapped to one the model can generate (DALL·E) or turned into an aspect
+//! ratio (Imagen, FLUX), and quality and style only reach models that know them. Images
+//! come back as URLs or base64 as the client asked: Gemini only returns image bytes, so
+//! its URLs are `data:` URLs, and Replicate only returns URLs, so its images are
+//! downloaded when base64 is wanted.
+//!
+//! Images are priced per image by model, size and quality in the
+//! [`cost`](super::cost) module.
+
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::cost::priced_image_models;
+use super::{LLMError, LLMProviderType, LLMResult};
+
+/// How long to wait for a Replicate prediction to finish
+const REPLICATE_PREDICTION_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Width and height of generated images, in pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for ImageSize {
+    fn default() -> Self {
+        Self::square(1024)
+    }
+}
+
+impl ImageSize {
+    pub fn square(side: u32) -> Self {
+        Self {
+            width: side,
+            height: side,
+        }
+    }
+
+    /// Parse OpenAI's `WIDTHxHEIGHT` form
+    pub fn parse(size: &str) -> Option<Self> {
+        let (width, height) = size.split_once('x')?;
+        let size = Self {
+            width: width.trim().parse().ok()?,
+            height: height.trim().parse().ok()?,
+        };
+        (size.width > 0 && size.height > 0).then_some(size)
+    }
+
+    pub fn as_string(&self) -> String {
+        format!("{}x{}", self.width, self.height)
+    }
+
+    /// Size DALL·E generates for a requested size
+    ///
+    /// DALL·E 2 makes squares of 256, 512 or 1024 pixels, the smallest that covers the
+    /// request; DALL·E 3 makes a square, landscape or portrait image by aspect.
+    pub fn for_dalle(&self, model: &str) -> Self {
+        if model == "dall-e-2" {
+            let side = self.width.max(self.height);
+            return Self::square([256, 512].into_iter().find(|&s| side <= s).unwrap_or(1024));
+        }
+        if model == "dall-e-3" {
+            return match self.width.cmp(&self.height) {
+                std::cmp::Ordering::Greater => Self {
+                    width: 1792,
+                    height: 1024,
+                },
+                std::cmp::Ordering::Less => Self {
+                    width: 1024,
+                    height: 1792,
+                },
+                std::cmp::Ordering::Equal => Self::square(1024),
+            };
+        }
+        *self
+    }
+
+    /// The closest of the aspect ratios Imagen and FLUX accept
+    pub fn aspect_ratio(&self) -> &'static str {
+        const RATIOS: [(&str, f64); 5] = [
+            ("1:1", 1.0),
+            ("4:3", 4.0 / 3.0),
+            ("3:4", 3.0 / 4.0),
+            ("16:9", 16.0 / 9.0),
+            ("9:16", 9.0 / 16.0),
+        ];
+        let ratio = (self.width as f64 / self.height as f64).ln();
+        RATIOS
+            .iter()
+            .min_by(|a, b| {
+                (a.1.ln() - ratio)
+                    .abs()
+                    .total_cmp(&(b.1.ln() - ratio).abs())
+            })
+            .map(|(name, _)| *name)
+            .unwrap_or("1:1")
+    }
+}
+
+/// Image quality, as OpenAI names it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageQuality {
+    #[default]
+    Standard,
+    Hd,
+}
+
+impl ImageQuality {
+    pub fn parse(quality: &str) -> Option<Self> {
+        match quality {
+            "standard" => Some(Self::Standard),
+            "hd" => Some(Self::Hd),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Standard => "standard",
+            Self::Hd => "hd",
+        }
+    }
+}
+
+/// How generated images are returned to the client
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageResponseFormat {
+    #[default]
+    Url,
+    B64Json,
+}
+
+impl ImageResponseFormat {
+    pub fn parse(format: &str) -> Option<Self> {
+        match format {
+            "url" => Some(Self::Url),
+            "b64_json" => Some(Self::B64Json),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Url => "url",
+            Self::B64Json => "b64_json",
+        }
+    }
+}
+
+/// Images to generate
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageRequest {
+    pub model: String,
+    pub prompt: String,
+    /// Number of images
+    pub n: u32,
+    pub size: ImageSize,
+    pub quality: ImageQuality,
+    pub response_format: ImageResponseFormat,
+    /// "vivid" or "natural", for DALL·E 3
+    pub style: Option<String>,
+}
+
+/// A generated image, in OpenAI's response format
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeneratedImage {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub b64_json: Option<String>,
+    /// Prompt the model actually drew, when it rewrote the request's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revised_prompt: Option<String>,
+}
+
+impl GeneratedImage {
+    /// An image from its bytes, base64 encoded with their content type
+    fn from_base64(data: String, mime_type: &str, format: ImageResponseFormat) -> Self {
+        let (url, b64_json) = match format {
+            ImageResponseFormat::Url => (Some(format!("data:{};base64,{}", mime_type, data)), None),
+            ImageResponseFormat::B64Json => (None, Some(data)),
+        };
+        Self {
+            url,
+            b64_json,
+            revised_prompt: None,
+        }
+    }
+}
+
+/// Images a provider generated and the size it billed them at
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageGeneration {
+    pub images: Vec<GeneratedImage>,
+    pub size: ImageSize,
+}
+
+/// Generates images from prompts
+#[async_trait]
+pub trait ImageProvider: Send + Sync {
+    /// Provider costs are recorded under
+    fn provider_type(&self) -> LLMProviderType;
+
+    fn supports_model(&self, model: &str) -> bool;
+
+    async fn generate(&self, request: &ImageRequest) -> LLMResult<ImageGeneration>;
+}
+
+/// Error for a failed response from an image API
+async fn provider_error(provider: &LLMProviderType, response: reqwest::Response) -> LLMError {
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    let message = format!("{} image API returned {}: {}", provider, status, body);
+    match status.as_u16() {
+        400 | 422 => LLMError::InvalidRequest(message),
+        401 | 403 => LLMError::AuthenticationFailed(message),
+        429 => LLMError::RateLimitExceeded(message),
+        _ => LLMError::Provider(message),
+    }
+}
+
+/// Send a request to an image API and read its JSON response
+async fn send_json(
+    provider: &LLMProviderType,
+    request: reqwest::RequestBuilder,
+) -> LLMResult<serde_json::Value> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| LLMError::Network(format!("Image request failed: {}", e)))?;
+    if !response.status().is_success() {
+        return Err(provider_error(provider, response).await);
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| LLMError::Parse(format!("Invalid image response: {}", e)))
+}
+
+/// OpenAI: DALL·E 2 and DALL·E 3
+#[derive(Debug, Clone)]
+pub struct OpenAIImages {
+    api_key: String,
+    base_url: String,
+    models: Vec<String>,
+    client: reqwest::Client,
+}
+
+impl OpenAIImages {
+    pub fn new(api_key: impl Into<String>, base_url: Option<String>) -> Self {
+        Self {
+            api_key: api_key.into(),
+            base_url: base_url
+                .unwrap_or_else(|| "https://api.openai.com/v1".to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            models: priced_image_models(&LLMProviderType::OpenAI),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Serve `model` as well as the models with known prices
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.models.push(model.into());
+        self
+    }
+
+    /// Request body for `n` images in the size DALL·E generates
+    fn body(request: &ImageRequest, size: ImageSize, n: u32) -> serde_json::Value {
+        let mut body = serde_json::json!({
+            "model": request.model,
+            "prompt": request.prompt,
+            "n": n,
+            "size": size.as_string(),
+            "response_format": request.response_format.as_str(),
+        });
+        if request.model == "dall-e-3" {
+            body["quality"] = request.quality.as_str().into();
+            if let Some(style) = &request.style {
+                body["style"] = style.as_str().into();
+            }
+        }
+        body
+    }
+}
+
+#[async_trait]
+impl ImageProvider for OpenAIImages {
+    fn provider_type(&self) -> LLMProviderType {
+        LLMProviderType::OpenAI
+    }
+
+    fn supports_model(&self, model: &str) -> bool {
+        self.models.iter().any(|m| m == model)
+    }
+
+    async fn generate(&self, request: &ImageRequest) -> LLMResult<ImageGeneration> {
+        let size = request.size.for_dalle(&request.model);
+        // DALL·E 3 draws one image per request
+        let batches = if request.model == "dall-e-3" {
+            vec![1; request.n as usize]
+        } else {
+            vec![request.n]
+        };
+
+        let mut images = Vec::new();
+        for n in batches {
+            let response = send_json(
+                &LLMProviderType::OpenAI,
+                self.client
+                    .post(format!("{}/images/generations", self.base_url))
+                    .bearer_auth(&self.api_key)
+                    .json(&Self::body(request, size, n)),
+            )
+            .await?;
+            let data: Vec<GeneratedImage> = serde_json::from_value(response["data"].clone())
+                .map_err(|e| LLMError::Parse(format!("Invalid image response: {}", e)))?;
+            images.extend(data);
+        }
+        Ok(ImageGeneration { images, size })
+    }
+}
+
+/// Gemini API: Imagen
+#[derive(Debug, Clone)]
+pub struct GeminiImages {
+    api_key: String,
+    base_url: String,
+    models: Vec<String>,
+    client: reqwest::Client,
+}
+
+impl GeminiImages {
+    pub fn new(api_key: impl Into<String>, base_url: Option<String>) -> Self {
+        Self {
+            api_key: api_key.into(),
+            base_url: base_url
+                .unwrap_or_else(|| "https://generativelanguage.googleapis.com/v1beta".to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            models: priced_image_models(&LLMProviderType::Google),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Serve `model` as well as the models with known prices
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.models.push(model.into());
+        self
+    }
+
+    /// Images of an Imagen `predict` response
+    fn images(response: &serde_json::Value, format: ImageResponseFormat) -> Vec<GeneratedImage> {
+        response["predictions"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|prediction| {
+                let data = prediction["bytesBase64Encoded"].as_str()?;
+                let mime_type = prediction["mimeType"].as_str().unwrap_or("image/png");
+                Some(GeneratedImage::from_base64(
+                    data.to_string(),
+                    mime_type,
+                    format,
+                ))
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl ImageProvider for GeminiImages {
+    fn provider_type(&self) -> LLMProviderType {
+        LLMProviderType::Google
+    }
+
+    fn supports_model(&self, model: &str) -> bool {
+        self.models.iter().any(|m| m == model)
+    }
+
+    async fn generate(&self, request: &ImageRequest) -> LLMResult<ImageGeneration> {
+        let response = send_json(
+            &LLMProviderType::Google,
+            self.client
+                .post(format!(
+                    "{}/models/{}:predict",
+                    self.base_url, request.model
+                ))
+                .header("x-goog-api-key", &self.api_key)
+                .json(&serde_json::json!({
+                    "instances": [{ "prompt": request.prompt }],
+                    "parameters": {
+                        "sampleCount": request.n,
+                        "aspectRatio": request.size.aspect_ratio(),
+                    },
+                })),
+        )
+        .await?;
+        Ok(ImageGeneration {
+            images: Self::images(&response, request.response_format),
+            size: request.size,
+        })
+    }
+}
+
+/// Replicate: FLUX and other models addressed as `owner/name`
+#[derive(Debug, Clone)]
+pub struct ReplicateImages {
+    api_token: String,
+    base_url: String,
+    models: Vec<String>,
+    client: reqwest::Client,
+}
+
+impl ReplicateImages {
+    pub fn new(api_token: impl Into<String>, base_url: Option<String>) -> Self {
+        Self {
+            api_token: api_token.into(),
+            base_url: base_url
+                .unwrap_or_else(|| "https://api.replicate.com/v1".to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            models: priced_image_models(&LLMProviderType::Replicate),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Serve `model` as well as the models with known prices
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.models.push(model.into());
+        self
+    }
+
+    /// Output URLs of a finished prediction; `None` while it is still running
+    fn output_urls(prediction: &serde_json::Value) -> Option<LLMResult<Vec<String>>> {
+        match prediction["status"].as_str().unwrap_or_default() {
+            "starting" | "processing" => None,
+            "succeeded" => Some(Ok(match &prediction["output"] {
+                serde_json::Value::String(url) => vec![url.clone()],
+                output => output
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|url| url.as_str().map(str::to_string))
+                    .collect(),
+            })),
+            status => Some(Err(LLMError::Provider(format!(
+                "Replicate prediction {}: {}",
+                status, prediction["error"]
+            )))),
+        }
+    }
+
+    /// Wait for a prediction to finish, polling it once it outlives `Prefer: wait`
+    async fn finish(&self, mut prediction: serde_json::Value) -> LLMResult<Vec<String>> {
+        let deadline = tokio::time::Instant::now() + REPLICATE_PREDICTION_TIMEOUT;
+        loop {
+            if let Some(urls) = Self::output_urls(&prediction) {
+                return urls;
+            }
+            let Some(poll_url) = prediction["urls"]["get"].as_str().map(str::to_string) else {
+                return Err(LLMError::Provider(
+                    "Replicate prediction has no status URL".to_string(),
+                ));
+            };
+            if tokio::time::Instant::now() >= deadline {
+                return Err(LLMError::Timeout(format!(
+                    "Replicate prediction did not finish within {}s",
+                    REPLICATE_PREDICTION_TIMEOUT.as_secs()
+                )));
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            prediction = send_json(
+                &LLMProviderType::Replicate,
+                self.client.get(poll_url).bearer_auth(&self.api_token),
+            )
+            .await?;
+        }
+    }
+
+    /// An output image, downloaded when base64 is wanted
+    async fn image(&self, url: String, format: ImageResponseFormat) -> LLMResult<GeneratedImage> {
+        if format == ImageResponseFormat::Url {
+            return Ok(GeneratedImage {
+                url: Some(url),
+                b64_json: None,
+                revised_prompt: None,
+            });
+        }
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| LLMError::Network(format!("Failed to download image: {}", e)))?;
+        if !response.status().is_success() {
+            return Err(provider_error(&LLMProviderType::Replicate, response).await);
+        }
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| LLMError::Network(format!("Failed to download image: {}", e)))?;
+        Ok(GeneratedImage::from_base64(
+            general_purpose::STANDARD.encode(bytes),
+            "image/png",
+            format,
+        ))
+    }
+}
+
+#[async_trait]
+impl ImageProvider for ReplicateImages {
+    fn provider_type(&self) -> LLMProviderType {
+        LLMProviderType::Replicate
+    }
+
+    fn supports_model(&self, model: &str) -> bool {
+        self.models.iter().any(|m| m == model)
+    }
+
+    async fn generate(&self, request: &ImageRequest) -> LLMResult<ImageGeneration> {
+        let mut input = serde_json::json!({
+            "prompt": request.prompt,
+            "aspect_ratio": request.size.aspect_ratio(),
+            "output_format": "png",
+        });
+        // Single-image models reject the parameter altogether
+        if request.n > 1 {
+            input["num_outputs"] = request.n.into();
+        }
+        let prediction = send_json(
+            &LLMProviderType::Replicate,
+            self.client
+                .post(format!(
+                    "{}/models/{}/predictions",
+                    self.base_url, request.model
+                ))
+                .bearer_auth(&self.api_token)
+                .header("Prefer", "wait")
+                .json(&serde_json::json!({ "input": input })),
+        )
+        .await?;
+
+        let mut images = Vec::new();
+        for url in self.finish(prediction).await? {
+            images.push(self.image(url, request.response_format).await?);
+        }
+        Ok(ImageGeneration {
+            images,
+            size: request.size,
+        })
+    }
+}
+
+/// Image providers, each serving the models it supports
+#[derive(Clone, Default)]
+pub struct ImageProviders {
+    providers: Vec<Arc<dyn ImageProvider>>,
+}
+
+impl std::fmt::Debug for ImageProviders {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ImageProviders")
+            .field(
+                "providers",
+                &self
+                    .providers
+                    .iter()
+                    .map(|p| p.provider_type().to_string())
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl ImageProviders {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// OpenAI when `OPENAI_API_KEY` is set, Gemini when `GOOGLE_API_KEY` is set and
+    /// Replicate when `REPLICATE_API_TOKEN` is set
+    pub fn from_env() -> Self {
+        let key = |name: &str| std::env::var(name).ok().filter(|key| !key.is_empty());
+        let mut providers = Self::new();
+        if let Some(api_key) = key("OPENAI_API_KEY") {
+            providers = providers
+                .with_provider(Arc::new(OpenAIImages::new(api_key, key("OPENAI_BASE_URL"))));
+        }
+        if let Some(api_key) = key("GOOGLE_API_KEY") {
+            providers = providers.with_provider(Arc::new(GeminiImages::new(api_key, None)));
+        }
+        if let Some(api_token) = key("REPLICATE_API_TOKEN") {
+            providers = providers.with_provider(Arc::new(ReplicateImages::new(api_token, None)));
+        }
+        providers
+    }
+
+    /// Add a provider; earlier providers win for models several support
+    pub fn with_provider(mut self, provider: Arc<dyn ImageProvider>) -> Self {
+        self.providers.push(provider);
+        self
+    }
+
+    /// The provider generating images with `model`
+    pub fn provider(&self, model: &str) -> LLMResult<Arc<dyn ImageProvider>> {
+        self.providers
+            .iter()
+            .find(|provider| provider.supports_model(model))
+            .cloned()
+            .ok_or_else(|| {
+                LLMError::ModelNotSupported(format!("No image provider generates with '{}'", model))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_size_and_quality_mapping() {
+        let landscape = ImageSize::parse("1536x1024").unwrap();
+        assert_eq!(landscape.for_dalle("dall-e-3").as_string(), "1792x1024");
+        assert_eq!(landscape.for_dalle("dall-e-2").as_string(), "1024x1024");
+        assert_eq!(
+            ImageSize::parse("300x300")
+                .unwrap()
+                .for_dalle("dall-e-2")
+                .as_string(),
+            "512x512"
+        );
+        assert_eq!(landscape.aspect_ratio(), "4:3");
+        assert_eq!(
+            ImageSize::parse("1024x1792").unwrap().aspect_ratio(),
+            "9:16"
+        );
+        assert_eq!(ImageSize::parse("1024").or(ImageSize::parse("0x5")), None);
+
+        let request = ImageRequest {
+            model: "dall-e-2".to_string(),
+            prompt: "a lighthouse".to_string(),
+            n: 2,
+            size: landscape,
+            quality: ImageQuality::Hd,
+            response_format: ImageResponseFormat::B64Json,
+            style: Some("vivid".to_string()),
+        };
+        let body = OpenAIImages::body(&request, landscape.for_dalle("dall-e-2"), 2);
+        assert_eq!(body["size"], "1024x1024");
+        assert!(body.get("quality").is_none() && body.get("style").is_none());
+        let dalle3 = ImageRequest {
+            model: "dall-e-3".to_string(),
+            ..request
+        };
+        let body = OpenAIImages::body(&dalle3, landscape.for_dalle("dall-e-3"), 1);
+        assert_eq!(body["quality"], "hd");
+        assert_eq!(body["style"], "vivid");
+    }
+
+    #[test]
+    fn test_provider_responses_and_routing() {
+        let imagen = serde_json::json!({
+            "predictions": [{"bytesBase64Encoded": "iVBORw0", "mimeType": "image/png"}]
+        });
+        assert_eq!(
+            GeminiImages::images(&imagen, ImageResponseFormat::Url)[0]
+                .url
+                .as_deref(),
+            Some("data:image/png;base64,iVBORw0")
+        );
+        assert_eq!(
+            GeminiImages::images(&imagen, ImageResponseFormat::B64Json)[0]
+                .b64_json
+                .as_deref(),
+            Some("iVBORw0")
+        );
+
+        let running = serde_json::json!({"status": "processing"});
+        assert!(ReplicateImages::output_urls(&running).is_none());
+        let done = serde_json::json!({"status": "succeeded", "output": ["https://x/1.png"]});
+        assert_eq!(
+            ReplicateImages::output_urls(&done).unwrap().unwrap(),
+            vec!["https://x/1.png"]
+        );
+        let failed = serde_json::json!({"status": "failed", "error": "NSFW"});
+        assert!(ReplicateImages::output_urls(&failed).unwrap().is_err());
+
+        let providers = ImageProviders::new()
+            .with_provider(Arc::new(OpenAIImages::new("sk-test", None)))
+            .with_provider(Arc::new(GeminiImages::new("g-test", None)))
+            .with_provider(Arc::new(ReplicateImages::new("r-test", None)));
+        assert_eq!(
+            providers
+                .provider("imagen-3.0-generate-002")
+                .unwrap()
+                .provider_type(),
+            LLMProviderType::Google
+        );
+        assert_eq!(
+            providers
+                .provider("black-forest-labs/flux-schnell")
+                .unwrap()
+                .provider_type(),
+            LLMProviderType::Replicate
+        );
+        assert!(matches!(
+            providers.provider("midjourney"),
+            Err(LLMError::ModelNotSupported(_))
+        ));
+    }
+}
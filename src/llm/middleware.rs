@@ -0,0 +1,42 @@
+//! Router Middleware
+//!
+//! Hooks that run around every chat completion routed through the [`LLMRouter`]. A
+//! middleware can rewrite or reject a request before it is routed, adjust the response
+//! before it is returned, and observe failures. This is the extension point for custom
+//! logging, prompt rewriting or PII redaction without forking the router.
+//!
+//! Middleware runs in registration order before the request and in reverse order after
+//! it, so the first registered middleware wraps all the others.
+//!
+//! [`LLMRouter`]: super::LLMRouter
+
+use async_trait::async_trait;
+
+use super::{LLMError, LLMRequest, LLMResponse, LLMResult};
+
+/// Interceptor for requests routed through the LLM router
+///
+/// Every hook has a no-op default, so implementors only override what they need.
+#[async_trait]
+pub trait RouterMiddleware: Send + Sync {
+    /// Name used in logs
+    fn name(&self) -> &str;
+
+    /// Called before the request is routed. Returning an error rejects the request.
+    async fn pre_request(&self, _request: &mut LLMRequest) -> LLMResult<()> {
+        Ok(())
+    }
+
+    /// Called with the response of a successful (non-streaming) completion
+    async fn post_response(
+        &self,
+        _request: &LLMRequest,
+        _response: &mut LLMResponse,
+    ) -> LLMResult<()> {
+        Ok(())
+    }
+
+    /// Called when routing fails, including rejections by `pre_request` and
+    /// `post_response`
+    async fn on_error(&self, _request: &LLMRequest, _error: &LLMError) {}
+}
@@ -8,8 +8,26 @@ pub mod router;
 pub mod streaming;
 pub mod security;
 pub mod cost;
+pub mod usage_tracking;
 pub mod traits;
 pub mod sse;
+pub mod context;
+pub mod tenant;
+pub mod queue;
+pub mod middleware;
+pub mod stream_filter;
+pub mod trace;
+pub mod rate_limit;
+pub mod structured;
+pub mod tools;
+pub mod multimodal;
+pub mod tokenizer;
+pub mod pricing;
+pub mod moderation;
+pub mod audio;
+pub mod images;
+pub mod embeddings;
+pub mod sampling;
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -52,19 +70,88 @@ impl std::fmt::Display for LLMProviderType {
     }
 }
 
+impl std::str::FromStr for LLMProviderType {
+    type Err = LLMError;
+
+    /// Parse the names produced by `Display`, case-insensitively
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        Ok(match name.as_str() {
+            "openai" => LLMProviderType::OpenAI,
+            "anthropic" => LLMProviderType::Anthropic,
+            "google" => LLMProviderType::Google,
+            "cohere" => LLMProviderType::Cohere,
+            "mistral" => LLMProviderType::Mistral,
+            "perplexity" => LLMProviderType::Perplexity,
+            "groq" => LLMProviderType::Groq,
+            "together" => LLMProviderType::Together,
+            "replicate" => LLMProviderType::Replicate,
+            "ollama" => LLMProviderType::Ollama,
+            "vllm" => LLMProviderType::VLLM,
+            _ => match name.strip_prefix("custom-") {
+                Some(custom) if !custom.is_empty() => LLMProviderType::Custom(custom.to_string()),
+                _ => return Err(LLMError::ProviderNotFound(s.to_string())),
+            },
+        })
+    }
+}
+
 // Re-export main types for easier access
 pub use router::LLMRouter;
 pub use traits::{
     LLMProviderClient, ModelInfo, ProviderConfig, ProviderConfigRequirements,
     AuthMethod, RateLimitInfo, ParameterRestriction, ModelCapability,
     ProviderFactory, CostCalculator, CostBreakdown, ProviderHealth,
-    ProviderRegistry
+    ProviderRegistry, KeyValidation, KeyQuota, QuotaHeaders
 };
-pub use cost::{CostOptimizer, BudgetManager, CostAnalyzer, InMemoryUsageTracker};
+pub use cost::{CostOptimizer, BudgetManager, CostAnalyzer, InMemoryUsageTracker, UsageTracker, BudgetPeriodRecord, BudgetPeriods, CarryOver};
+pub use usage_tracking::{NATSUsageTracker, PostgresUsageTracker};
 
 // Re-export streaming types
 pub use streaming::{StreamEvent, StreamingSession, StreamingProtocol};
 
+// Re-export context window types
+pub use context::{ContextOverflowPolicy, ContextWindowConfig};
+
+// Re-export tenant routing types
+pub use tenant::{TenantId, TenantPolicyStore, TenantRoutingPolicy};
+
+// Re-export request queue types
+pub use queue::{PriorityRequestQueue, QueueStats, RequestPriority, RequestQueueConfig};
+
+// Re-export router middleware
+pub use middleware::RouterMiddleware;
+
+// Re-export routing trace types
+pub use trace::{RoutingAttempt, RoutingCandidate, RoutingExclusion, RoutingTrace, RoutingTraceStore};
+
+// Re-export rate-limit tracking types
+pub use rate_limit::{ProviderQuota, RateLimitConfig, RateLimitTracker};
+
+// Re-export structured output types
+pub use structured::{JsonSchemaFormat, ResponseFormat};
+
+// Re-export tool calling types
+pub use tools::{ToolCall, ToolChoice, ToolDefinition};
+
+// Re-export multimodal content types
+pub use multimodal::{ContentPart, ImageUrl, MessageContent};
+
+// Re-export token counting
+pub use tokenizer::Tokenizer;
+
+// Re-export the model price catalog
+pub use pricing::{ModelPrice, PriceCatalog};
+
+// Re-export content moderation
+pub use moderation::{ModerationProvider, ModerationProviders, ModerationResult};
+
+// Re-export audio transcription and speech
+pub use audio::{AudioProvider, AudioProviders, SpeechRequest, TranscriptionFormat, TranscriptionRequest};
+
+// Re-export image generation
+pub use images::{GeneratedImage, ImageProvider, ImageProviders, ImageRequest};
+
 /// LLM Provider configuration with secure key management
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LLMProvider {
@@ -144,6 +231,15 @@ pub struct LLMRequest {
     pub function_call: Option<String>,
     pub user: Option<String>,
     pub metadata: HashMap<String, serde_json::Value>,
+    /// Structured output the reply must conform to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    /// Whether and which of `functions` the model must call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    /// Number of choices to sample; see [`sampling`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
 }
 
 /// Embeddings request structure
@@ -154,6 +250,9 @@ pub struct EmbeddingsRequest {
     pub input: EmbeddingsInput,
     pub user: Option<String>,
     pub metadata: HashMap<String, serde_json::Value>,
+    /// Length the returned embeddings are shortened to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dimensions: Option<u32>,
 }
 
 /// Input for embeddings generation
@@ -169,9 +268,20 @@ pub enum EmbeddingsInput {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatMessage {
     pub role: MessageRole,
+    /// Text content, empty when the model only called tools
+    #[serde(default, deserialize_with = "tools::deserialize_nullable_string")]
     pub content: String,
     pub name: Option<String>,
     pub function_call: Option<FunctionCall>,
+    /// Tool calls made by an assistant message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ToolCall>>,
+    /// The tool call a `Tool` message answers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    /// Text and image parts in order, set only when the message carries images
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_parts: Option<Vec<ContentPart>>,
 }
 
 /// Message roles
@@ -182,6 +292,7 @@ pub enum MessageRole {
     User,
     Assistant,
     Function,
+    Tool,
 }
 
 impl<'de> Deserialize<'de> for MessageRole {
@@ -195,7 +306,8 @@ impl<'de> Deserialize<'de> for MessageRole {
             "user" => Ok(MessageRole::User),
             "assistant" => Ok(MessageRole::Assistant),
             "function" => Ok(MessageRole::Function),
-            _ => Err(serde::de::Error::unknown_variant(&s, &["system", "user", "assistant", "function"])),
+            "tool" => Ok(MessageRole::Tool),
+            _ => Err(serde::de::Error::unknown_variant(&s, &["system", "user", "assistant", "function", "tool"])),
         }
     }
 }
@@ -204,14 +316,20 @@ impl<'de> Deserialize<'de> for MessageRole {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FunctionDefinition {
     pub name: String,
+    #[serde(default)]
     pub description: String,
+    /// JSON schema of the arguments
+    #[serde(default = "tools::empty_parameters")]
     pub parameters: serde_json::Value,
 }
 
 /// Function call structure
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct FunctionCall {
+    #[serde(default)]
     pub name: String,
+    /// Arguments as a JSON string
+    #[serde(default)]
     pub arguments: String,
 }
 
@@ -226,6 +344,9 @@ pub struct LLMResponse {
     pub usage: TokenUsage,
     pub provider: LLMProviderType,
     pub routing_info: RoutingInfo,
+    /// Rate-limit quota the provider reported alongside this response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<KeyQuota>,
 }
 
 /// Embeddings response structure
@@ -296,6 +417,9 @@ pub struct StreamingChunk {
     pub created: u64,
     pub model: String,
     pub provider: LLMProviderType,
+    /// Token usage reported by the provider, usually on the last chunks of a stream
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TokenUsage>,
 }
 
 /// Streaming choice for chunked responses
@@ -386,6 +510,38 @@ pub enum LLMError {
     
     #[error("Provider error: {0}")]
     Provider(String),
+
+    #[error("Context length exceeded: {0}")]
+    ContextLengthExceeded(String),
+
+    #[error("Not permitted: {0}")]
+    NotPermitted(String),
+
+    #[error("Structured output invalid: {0}")]
+    StructuredOutput(String),
+}
+
+impl LLMError {
+    /// Stable error code for API responses
+    pub fn code(&self) -> crate::ErrorCode {
+        use crate::ErrorCode;
+        match self {
+            LLMError::ProviderNotFound(_)
+            | LLMError::ProviderUnhealthy(_)
+            | LLMError::Network(_) => ErrorCode::ProviderUnavailable,
+            LLMError::ModelNotSupported(_) => ErrorCode::ModelNotFound,
+            LLMError::RateLimitExceeded(_) => ErrorCode::RateLimited,
+            LLMError::AuthenticationFailed(_) => ErrorCode::AuthenticationFailed,
+            LLMError::Timeout(_) => ErrorCode::Timeout,
+            LLMError::InvalidRequest(_) => ErrorCode::InvalidInput,
+            LLMError::Parse(_) | LLMError::Provider(_) | LLMError::StructuredOutput(_) => {
+                ErrorCode::ProviderError
+            }
+            LLMError::ContextLengthExceeded(_) => ErrorCode::ContextLengthExceeded,
+            LLMError::NotPermitted(_) => ErrorCode::PermissionDenied,
+            LLMError::Internal(_) | LLMError::Serialization(_) => ErrorCode::Internal,
+        }
+    }
 }
 
 /// Result type for LLM operations
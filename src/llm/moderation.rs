@@ -0,0 +1,433 @@
+//! Content Moderation
+//!
+//! Moderation scores text for harmful content in the categories OpenAI's moderation
+//! models use, so `POST /v1/moderations` answers in OpenAI's format whichever provider
+//! does the scoring. A [`ModerationProvider`] either calls a hosted moderation model
+//! ([`OpenAIModerationProvider`]) or classifies text locally ([`KeywordClassifier`]);
+//! [`ModerationProviders`] picks the provider serving the requested model.
+//!
+//! When chat moderation is switched on in the API configuration, the user messages of
+//! every chat completion are moderated with the default model before the request is
+//! routed, and flagged requests are rejected without reaching a provider.
+
+use async_trait::async_trait;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use super::{LLMError, LLMResult};
+
+/// Model answering moderation requests that do not name one; see
+/// [`ModerationProviders::from_env`] for the default
+pub const MODERATION_MODEL_ENV: &str = "CIRCUIT_BREAKER_MODERATION_MODEL";
+
+/// Model name of the built-in [`KeywordClassifier`]
+pub const LOCAL_MODERATION_MODEL: &str = "circuit-breaker-moderation";
+
+/// OpenAI moderation model used by default when an OpenAI key is configured
+pub const DEFAULT_OPENAI_MODERATION_MODEL: &str = "omni-moderation-latest";
+
+/// Categories reported for every input, as named by OpenAI
+pub const MODERATION_CATEGORIES: &[&str] = &[
+    "harassment",
+    "harassment/threatening",
+    "hate",
+    "hate/threatening",
+    "illicit",
+    "illicit/violent",
+    "self-harm",
+    "self-harm/intent",
+    "self-harm/instructions",
+    "sexual",
+    "sexual/minors",
+    "violence",
+    "violence/graphic",
+];
+
+/// Moderation of one input, in OpenAI's format
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModerationResult {
+    /// Whether any category is flagged
+    pub flagged: bool,
+    pub categories: BTreeMap<String, bool>,
+    /// Confidence per category, between 0 and 1
+    pub category_scores: BTreeMap<String, f64>,
+}
+
+impl ModerationResult {
+    /// Flag every category scoring at least `threshold`; categories without a score get 0
+    pub fn from_scores(mut category_scores: BTreeMap<String, f64>, threshold: f64) -> Self {
+        for category in MODERATION_CATEGORIES {
+            category_scores.entry(category.to_string()).or_insert(0.0);
+        }
+        let categories: BTreeMap<String, bool> = category_scores
+            .iter()
+            .map(|(category, score)| (category.clone(), *score >= threshold))
+            .collect();
+        Self {
+            flagged: categories.values().any(|flagged| *flagged),
+            categories,
+            category_scores,
+        }
+    }
+
+    /// Names of the flagged categories
+    pub fn flagged_categories(&self) -> Vec<&str> {
+        self.categories
+            .iter()
+            .filter(|(_, flagged)| **flagged)
+            .map(|(category, _)| category.as_str())
+            .collect()
+    }
+}
+
+/// Scores text with a moderation model
+#[async_trait]
+pub trait ModerationProvider: Send + Sync {
+    /// Name used in logs
+    fn name(&self) -> &str;
+
+    /// Whether the provider serves `model`
+    fn supports_model(&self, model: &str) -> bool;
+
+    /// Moderate each input, returning one result per input in order
+    async fn moderate(&self, model: &str, inputs: &[String]) -> LLMResult<Vec<ModerationResult>>;
+}
+
+/// OpenAI's hosted moderation models
+#[derive(Debug, Clone)]
+pub struct OpenAIModerationProvider {
+    api_key: String,
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl OpenAIModerationProvider {
+    pub fn new(api_key: impl Into<String>, base_url: Option<String>) -> Self {
+        Self {
+            api_key: api_key.into(),
+            base_url: base_url
+                .unwrap_or_else(|| "https://api.openai.com/v1".to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIModerationResponse {
+    results: Vec<ModerationResult>,
+}
+
+#[async_trait]
+impl ModerationProvider for OpenAIModerationProvider {
+    fn name(&self) -> &str {
+        "openai"
+    }
+
+    fn supports_model(&self, model: &str) -> bool {
+        model.starts_with("omni-moderation") || model.starts_with("text-moderation")
+    }
+
+    async fn moderate(&self, model: &str, inputs: &[String]) -> LLMResult<Vec<ModerationResult>> {
+        let response = self
+            .client
+            .post(format!("{}/moderations", self.base_url))
+            .bearer_auth(&self.api_key)
+            .json(&serde_json::json!({"model": model, "input": inputs}))
+            .send()
+            .await
+            .map_err(|e| LLMError::Network(format!("OpenAI moderation request failed: {}", e)))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            let message = format!("OpenAI moderation returned {}: {}", status, body);
+            return Err(match status.as_u16() {
+                401 | 403 => LLMError::AuthenticationFailed(message),
+                429 => LLMError::RateLimitExceeded(message),
+                _ => LLMError::Provider(message),
+            });
+        }
+
+        let body: OpenAIModerationResponse = response
+            .json()
+            .await
+            .map_err(|e| LLMError::Parse(format!("Invalid OpenAI moderation response: {}", e)))?;
+        if body.results.len() != inputs.len() {
+            return Err(LLMError::Provider(format!(
+                "OpenAI moderation returned {} results for {} inputs",
+                body.results.len(),
+                inputs.len()
+            )));
+        }
+        Ok(body.results)
+    }
+}
+
+/// Local classifier scoring text by the category terms it contains
+///
+/// Each term found in an input halves the remaining distance of its category's score
+/// to 1, so one match scores 0.5, two 0.75 and so on. It needs no network access and
+/// catches explicit wording only; use a hosted model where nuance matters.
+#[derive(Debug, Clone)]
+pub struct KeywordClassifier {
+    terms: BTreeMap<String, Vec<Regex>>,
+    threshold: f64,
+}
+
+impl Default for KeywordClassifier {
+    fn default() -> Self {
+        Self {
+            terms: BTreeMap::new(),
+            threshold: 0.5,
+        }
+    }
+}
+
+impl KeywordClassifier {
+    /// A classifier without terms; nothing is flagged
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A classifier with a small built-in list of explicit terms
+    pub fn with_defaults() -> Self {
+        let defaults: &[(&str, &[&str])] = &[
+            (
+                "harassment",
+                &["shut up", "you idiot", "you moron", "loser"],
+            ),
+            (
+                "harassment/threatening",
+                &["watch your back", "i will hurt you", "you will regret"],
+            ),
+            ("hate", &["subhuman", "vermin", "inferior race"]),
+            ("hate/threatening", &["exterminate them", "wipe them out"]),
+            (
+                "illicit",
+                &["buy drugs", "stolen credit card", "launder money"],
+            ),
+            (
+                "illicit/violent",
+                &["build a bomb", "make a bomb", "buy a gun illegally"],
+            ),
+            ("self-harm", &["hurt myself", "self-harm", "cut myself"]),
+            (
+                "self-harm/intent",
+                &["kill myself", "end my life", "want to die"],
+            ),
+            (
+                "self-harm/instructions",
+                &["how to kill myself", "ways to self-harm"],
+            ),
+            ("sexual", &["porn", "explicit sex", "nude photos"]),
+            ("violence", &["kill", "murder", "stab", "shoot"]),
+            ("violence/graphic", &["gore", "dismember", "mutilate"]),
+        ];
+        defaults
+            .iter()
+            .fold(Self::new(), |classifier, (category, terms)| {
+                classifier.with_terms(category, terms.iter().copied())
+            })
+    }
+
+    /// Count the terms towards `category`; terms match whole words, ignoring case
+    pub fn with_terms<I, S>(mut self, category: &str, terms: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let patterns = self.terms.entry(category.to_string()).or_default();
+        for term in terms {
+            let term = term.as_ref().trim();
+            if !term.is_empty() {
+                patterns.push(
+                    Regex::new(&format!(r"(?i)\b{}\b", regex::escape(term)))
+                        .expect("escaped terms form a valid pattern"),
+                );
+            }
+        }
+        self
+    }
+
+    /// Flag categories scoring at least `threshold`, 0.5 by default
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Score one input
+    pub fn classify(&self, input: &str) -> ModerationResult {
+        let scores = self
+            .terms
+            .iter()
+            .map(|(category, patterns)| {
+                let hits = patterns
+                    .iter()
+                    .map(|pattern| pattern.find_iter(input).count())
+                    .sum::<usize>();
+                (category.clone(), 1.0 - 0.5f64.powi(hits.min(64) as i32))
+            })
+            .collect();
+        ModerationResult::from_scores(scores, self.threshold)
+    }
+}
+
+#[async_trait]
+impl ModerationProvider for KeywordClassifier {
+    fn name(&self) -> &str {
+        "local"
+    }
+
+    fn supports_model(&self, model: &str) -> bool {
+        model == LOCAL_MODERATION_MODEL
+    }
+
+    async fn moderate(&self, _model: &str, inputs: &[String]) -> LLMResult<Vec<ModerationResult>> {
+        Ok(inputs.iter().map(|input| self.classify(input)).collect())
+    }
+}
+
+/// Moderation providers, each serving the models it supports
+#[derive(Clone)]
+pub struct ModerationProviders {
+    providers: Vec<Arc<dyn ModerationProvider>>,
+    default_model: String,
+}
+
+impl std::fmt::Debug for ModerationProviders {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ModerationProviders")
+            .field(
+                "providers",
+                &self.providers.iter().map(|p| p.name()).collect::<Vec<_>>(),
+            )
+            .field("default_model", &self.default_model)
+            .finish()
+    }
+}
+
+impl Default for ModerationProviders {
+    /// Only the local classifier
+    fn default() -> Self {
+        Self::new(LOCAL_MODERATION_MODEL)
+            .with_provider(Arc::new(KeywordClassifier::with_defaults()))
+    }
+}
+
+impl ModerationProviders {
+    /// No providers; requests for `default_model` fail until one serving it is added
+    pub fn new(default_model: impl Into<String>) -> Self {
+        Self {
+            providers: Vec::new(),
+            default_model: default_model.into(),
+        }
+    }
+
+    /// Providers configured by the environment: OpenAI when `OPENAI_API_KEY` is set, and
+    /// the local classifier. The default model is [`MODERATION_MODEL_ENV`] when set,
+    /// otherwise OpenAI's when it is configured and the local classifier's if not.
+    pub fn from_env() -> Self {
+        let openai = std::env::var("OPENAI_API_KEY")
+            .ok()
+            .filter(|key| !key.is_empty())
+            .map(|key| OpenAIModerationProvider::new(key, std::env::var("OPENAI_BASE_URL").ok()));
+        let default_model = std::env::var(MODERATION_MODEL_ENV)
+            .ok()
+            .filter(|model| !model.is_empty())
+            .unwrap_or_else(|| {
+                if openai.is_some() {
+                    DEFAULT_OPENAI_MODERATION_MODEL
+                } else {
+                    LOCAL_MODERATION_MODEL
+                }
+                .to_string()
+            });
+
+        let mut providers = Self::new(default_model);
+        if let Some(openai) = openai {
+            providers = providers.with_provider(Arc::new(openai));
+        }
+        providers.with_provider(Arc::new(KeywordClassifier::with_defaults()))
+    }
+
+    /// Add a provider; earlier providers win for models several support
+    pub fn with_provider(mut self, provider: Arc<dyn ModerationProvider>) -> Self {
+        self.providers.push(provider);
+        self
+    }
+
+    /// Model used when a request names none
+    pub fn default_model(&self) -> &str {
+        &self.default_model
+    }
+
+    /// Moderate `inputs` with `model`, or the default model, returning the model used
+    pub async fn moderate(
+        &self,
+        model: Option<&str>,
+        inputs: &[String],
+    ) -> LLMResult<(String, Vec<ModerationResult>)> {
+        let model = model.unwrap_or(&self.default_model);
+        let provider = self
+            .providers
+            .iter()
+            .find(|provider| provider.supports_model(model))
+            .ok_or_else(|| {
+                LLMError::ModelNotSupported(format!("No moderation provider serves '{}'", model))
+            })?;
+        let results = provider.moderate(model, inputs).await?;
+        Ok((model.to_string(), results))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keyword_classifier_scores() {
+        let classifier = KeywordClassifier::with_defaults();
+
+        let result = classifier.classify("Have a lovely day");
+        assert!(!result.flagged);
+        assert_eq!(result.categories.len(), MODERATION_CATEGORIES.len());
+        assert_eq!(result.category_scores["violence"], 0.0);
+
+        let result = classifier.classify("I will KILL them and murder the rest");
+        assert!(result.flagged);
+        assert_eq!(result.category_scores["violence"], 0.75);
+        assert_eq!(result.flagged_categories(), vec!["violence"]);
+
+        // Terms match whole words only
+        assert!(!classifier.classify("skill and killjoy").flagged);
+
+        let strict = KeywordClassifier::new()
+            .with_terms("harassment", ["bozo"])
+            .with_threshold(0.9);
+        assert!(!strict.classify("bozo").flagged);
+        assert!(strict.classify("bozo bozo bozo bozo").flagged);
+    }
+
+    #[tokio::test]
+    async fn test_providers_route_by_model() {
+        let providers = ModerationProviders::default();
+        assert_eq!(providers.default_model(), LOCAL_MODERATION_MODEL);
+
+        let inputs = vec!["hello".to_string(), "I want to die".to_string()];
+        let (model, results) = providers.moderate(None, &inputs).await.unwrap();
+        assert_eq!(model, LOCAL_MODERATION_MODEL);
+        assert_eq!(results.len(), 2);
+        assert!(!results[0].flagged);
+        assert!(results[1].categories["self-harm/intent"]);
+
+        let error = providers
+            .moderate(Some(DEFAULT_OPENAI_MODERATION_MODEL), &inputs)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, LLMError::ModelNotSupported(_)));
+    }
+}
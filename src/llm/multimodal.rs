@@ -0,0 +1,382 @@
+//! Multimodal Input
+//!
+//! Message content is either plain text or a list of parts in OpenAI's format, where
+//! each part is text or an image given by an `https` URL or a base64 `data:` URL.
+//! Internally a message keeps its text in `content` for everything that only deals with
+//! text (token counting, context trimming, cost) and the ordered parts in
+//! `content_parts` whenever it carries images. Each provider translates the parts to
+//! its vision format: OpenAI-compatible APIs take them as is, Anthropic uses `image`
+//! blocks, Gemini `inlineData`/`fileData` parts and Ollama base64 `images`.
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use super::providers::anthropic::types::{AnthropicImageSource, AnthropicRequestBlock};
+use super::providers::google::types::{GoogleBlob, GoogleFileData, GooglePart};
+use super::{ChatMessage, LLMRequest};
+
+/// Image formats every vision-capable provider accepts
+pub const SUPPORTED_IMAGE_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+/// Message content: a string, or an array of text and image parts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+/// One part of a multi-part message, e.g. `{"type": "image_url", "image_url": {"url": ...}}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    Text { text: String },
+    ImageUrl { image_url: ImageUrl },
+}
+
+/// Location of an image with an optional fidelity hint (`low`, `high` or `auto`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageUrl {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// An image embedded in a `data:` URL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InlineImage<'a> {
+    pub media_type: &'a str,
+    /// Base64-encoded image bytes
+    pub data: &'a str,
+}
+
+impl Default for MessageContent {
+    fn default() -> Self {
+        Self::Text(String::new())
+    }
+}
+
+impl From<String> for MessageContent {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl From<&str> for MessageContent {
+    fn from(text: &str) -> Self {
+        Self::Text(text.to_string())
+    }
+}
+
+impl PartialEq<&str> for MessageContent {
+    fn eq(&self, other: &&str) -> bool {
+        matches!(self, Self::Text(text) if text == other)
+    }
+}
+
+impl MessageContent {
+    /// The text of the content, with the text parts joined by newlines
+    pub fn text(&self) -> String {
+        match self {
+            Self::Text(text) => text.clone(),
+            Self::Parts(parts) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    ContentPart::Text { text } => Some(text.as_str()),
+                    ContentPart::ImageUrl { .. } => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+
+    /// Split into the text and, when there are images, the parts
+    pub fn into_text_and_parts(self) -> (String, Option<Vec<ContentPart>>) {
+        let text = self.text();
+        match self {
+            Self::Parts(parts) if parts.iter().any(ContentPart::is_image) => (text, Some(parts)),
+            _ => (text, None),
+        }
+    }
+
+    /// Content for a message, multi-part when it carries images
+    pub fn from_message(message: &ChatMessage) -> Self {
+        match &message.content_parts {
+            Some(parts) => Self::Parts(parts.clone()),
+            None => Self::Text(message.content.clone()),
+        }
+    }
+
+    /// Deserialize `null` as empty text; OpenAI sends `"content": null` for messages that
+    /// only call tools
+    pub(crate) fn deserialize_nullable<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        Ok(Option::<Self>::deserialize(deserializer)?.unwrap_or_default())
+    }
+}
+
+impl ContentPart {
+    pub fn is_image(&self) -> bool {
+        matches!(self, Self::ImageUrl { .. })
+    }
+}
+
+impl ImageUrl {
+    /// The embedded image when the URL is a base64 `data:` URL, e.g. `data:image/png;base64,...`
+    pub fn inline(&self) -> Option<InlineImage<'_>> {
+        let (header, data) = self.url.strip_prefix("data:")?.split_once(',')?;
+        let media_type = header.strip_suffix(";base64")?;
+        Some(InlineImage { media_type, data })
+    }
+
+    /// Media type of the image, guessed from the file extension for remote URLs
+    pub fn media_type(&self) -> &str {
+        if let Some(inline) = self.inline() {
+            return inline.media_type;
+        }
+        let path = self.url.split(['?', '#']).next().unwrap_or_default();
+        match path
+            .rsplit('.')
+            .next()
+            .map(str::to_ascii_lowercase)
+            .as_deref()
+        {
+            Some("png") => "image/png",
+            Some("gif") => "image/gif",
+            Some("webp") => "image/webp",
+            _ => "image/jpeg",
+        }
+    }
+
+    /// Check the URL is an `http(s)` URL or a base64 `data:` URL of a supported format
+    pub fn validate(&self) -> Result<(), String> {
+        if self.url.starts_with("https://") || self.url.starts_with("http://") {
+            return Ok(());
+        }
+        match self.inline() {
+            Some(inline) if SUPPORTED_IMAGE_TYPES.contains(&inline.media_type) => Ok(()),
+            Some(inline) => Err(format!(
+                "Unsupported image type '{}', expected one of {}",
+                inline.media_type,
+                SUPPORTED_IMAGE_TYPES.join(", ")
+            )),
+            None => Err("Image URL must be an http(s) URL or a base64 data URL".to_string()),
+        }
+    }
+}
+
+impl ChatMessage {
+    /// Images attached to the message, in order
+    pub fn images(&self) -> impl Iterator<Item = &ImageUrl> {
+        self.content_parts
+            .iter()
+            .flatten()
+            .filter_map(|part| match part {
+                ContentPart::ImageUrl { image_url } => Some(image_url),
+                ContentPart::Text { .. } => None,
+            })
+    }
+}
+
+impl LLMRequest {
+    /// Whether any message carries images, which needs a vision-capable model
+    pub fn has_images(&self) -> bool {
+        self.messages
+            .iter()
+            .any(|message| message.images().next().is_some())
+    }
+
+    /// Check every attached image can be sent to a provider
+    pub fn validate_images(&self) -> Result<(), String> {
+        self.messages
+            .iter()
+            .flat_map(ChatMessage::images)
+            .try_for_each(ImageUrl::validate)
+    }
+}
+
+// Anthropic
+
+pub fn to_anthropic_blocks(parts: &[ContentPart]) -> Vec<AnthropicRequestBlock> {
+    parts
+        .iter()
+        .map(|part| match part {
+            ContentPart::Text { text } => AnthropicRequestBlock::Text { text: text.clone() },
+            ContentPart::ImageUrl { image_url } => AnthropicRequestBlock::Image {
+                source: match image_url.inline() {
+                    Some(inline) => AnthropicImageSource::Base64 {
+                        media_type: inline.media_type.to_string(),
+                        data: inline.data.to_string(),
+                    },
+                    None => AnthropicImageSource::Url {
+                        url: image_url.url.clone(),
+                    },
+                },
+            },
+        })
+        .collect()
+}
+
+// Google Gemini
+
+pub fn to_google_parts(parts: &[ContentPart]) -> Vec<GooglePart> {
+    parts
+        .iter()
+        .map(|part| match part {
+            ContentPart::Text { text } => GooglePart {
+                text: text.clone(),
+                ..Default::default()
+            },
+            ContentPart::ImageUrl { image_url } => match image_url.inline() {
+                Some(inline) => GooglePart {
+                    inline_data: Some(GoogleBlob {
+                        mime_type: inline.media_type.to_string(),
+                        data: inline.data.to_string(),
+                    }),
+                    ..Default::default()
+                },
+                None => GooglePart {
+                    file_data: Some(GoogleFileData {
+                        mime_type: image_url.media_type().to_string(),
+                        file_uri: image_url.url.clone(),
+                    }),
+                    ..Default::default()
+                },
+            },
+        })
+        .collect()
+}
+
+// Ollama
+
+/// Base64 images for Ollama, which cannot fetch remote URLs; those are left out
+pub fn to_ollama_images(message: &ChatMessage) -> Vec<String> {
+    message
+        .images()
+        .filter_map(|image_url| {
+            let inline = image_url.inline();
+            if inline.is_none() {
+                tracing::warn!(
+                    "Ollama only accepts base64 images, skipping image at {}",
+                    image_url.url
+                );
+            }
+            inline.map(|inline| inline.data.to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_content_wire_format() {
+        let content: MessageContent = serde_json::from_value(json!([
+            { "type": "text", "text": "What is in this image?" },
+            { "type": "image_url", "image_url": { "url": "https://example.com/cat.PNG", "detail": "low" } }
+        ]))
+        .unwrap();
+        assert_eq!(content.text(), "What is in this image?");
+
+        let (text, parts) = content.into_text_and_parts();
+        assert_eq!(text, "What is in this image?");
+        let parts = parts.unwrap();
+        assert!(parts[1].is_image());
+
+        // Text-only part lists collapse to plain text
+        let content = MessageContent::Parts(vec![ContentPart::Text {
+            text: "hi".to_string(),
+        }]);
+        assert_eq!(content.into_text_and_parts(), ("hi".to_string(), None));
+
+        let content: MessageContent = serde_json::from_value(json!("hello")).unwrap();
+        assert_eq!(content, "hello");
+    }
+
+    #[test]
+    fn test_image_urls() {
+        let inline = ImageUrl {
+            url: "data:image/webp;base64,UklGRg==".to_string(),
+            detail: None,
+        };
+        assert_eq!(
+            inline.inline(),
+            Some(InlineImage {
+                media_type: "image/webp",
+                data: "UklGRg=="
+            })
+        );
+        assert!(inline.validate().is_ok());
+
+        let remote = ImageUrl {
+            url: "https://example.com/cat.PNG?size=large".to_string(),
+            detail: None,
+        };
+        assert_eq!(remote.inline(), None);
+        assert_eq!(remote.media_type(), "image/png");
+        assert!(remote.validate().is_ok());
+
+        for url in [
+            "data:image/tiff;base64,AAAA",
+            "file:///etc/passwd",
+            "data:image/png,raw",
+        ] {
+            let image = ImageUrl {
+                url: url.to_string(),
+                detail: None,
+            };
+            assert!(image.validate().is_err(), "{}", url);
+        }
+    }
+
+    #[test]
+    fn test_provider_formats() {
+        let parts = vec![
+            ContentPart::Text {
+                text: "Compare these".to_string(),
+            },
+            ContentPart::ImageUrl {
+                image_url: ImageUrl {
+                    url: "data:image/png;base64,iVBORw0KGgo=".to_string(),
+                    detail: None,
+                },
+            },
+            ContentPart::ImageUrl {
+                image_url: ImageUrl {
+                    url: "https://example.com/dog.gif".to_string(),
+                    detail: None,
+                },
+            },
+        ];
+
+        assert_eq!(
+            serde_json::to_value(to_anthropic_blocks(&parts)).unwrap(),
+            json!([
+                { "type": "text", "text": "Compare these" },
+                { "type": "image", "source": { "type": "base64", "media_type": "image/png", "data": "iVBORw0KGgo=" } },
+                { "type": "image", "source": { "type": "url", "url": "https://example.com/dog.gif" } }
+            ])
+        );
+        assert_eq!(
+            serde_json::to_value(to_google_parts(&parts)).unwrap(),
+            json!([
+                { "text": "Compare these" },
+                { "inlineData": { "mimeType": "image/png", "data": "iVBORw0KGgo=" } },
+                { "fileData": { "mimeType": "image/gif", "fileUri": "https://example.com/dog.gif" } }
+            ])
+        );
+
+        let message = ChatMessage {
+            role: super::super::MessageRole::User,
+            content: "Compare these".to_string(),
+            name: None,
+            function_call: None,
+            tool_calls: None,
+            tool_call_id: None,
+            content_parts: Some(parts),
+        };
+        assert_eq!(to_ollama_images(&message), vec!["iVBORw0KGgo="]);
+    }
+}
@@ -0,0 +1,274 @@
+//! Model Price Catalog
+//!
+//! Per-token prices that take precedence over the defaults compiled into each provider's
+//! `config.rs`, so a provider changing its prices does not require a new build. The
+//! process-wide [`PriceCatalog::global`] is seeded from the JSON or YAML file named by
+//! [`PRICE_CATALOG_ENV`] and updated at runtime through the `setModelPrice` and
+//! `removeModelPrice` GraphQL mutations.
+//!
+//! Provider [`CostCalculator`](super::traits::CostCalculator) implementations and the
+//! [`CostAnalyzer`](super::cost::CostAnalyzer) look a model up here first and fall back to
+//! their built-in prices when the catalog has no entry for it.
+//!
+//! A catalog file lists one entry per provider and model:
+//!
+//! ```yaml
+//! prices:
+//!   - provider: openai
+//!     model: gpt-4o
+//!     input_cost_per_token: 0.0000025
+//!     output_cost_per_token: 0.00001
+//! ```
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use tracing::{info, warn};
+
+use super::{LLMError, LLMProviderType, LLMResult};
+
+/// Path of a JSON or YAML file the global catalog is seeded from
+pub const PRICE_CATALOG_ENV: &str = "CIRCUIT_BREAKER_PRICE_CATALOG";
+
+lazy_static::lazy_static! {
+    static ref GLOBAL: PriceCatalog = PriceCatalog::from_env();
+}
+
+/// Price of one provider's model, in USD per token
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub provider: LLMProviderType,
+    pub model: String,
+    pub input_cost_per_token: f64,
+    pub output_cost_per_token: f64,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ModelPrice {
+    pub fn new(
+        provider: LLMProviderType,
+        model: impl Into<String>,
+        input_cost_per_token: f64,
+        output_cost_per_token: f64,
+    ) -> Self {
+        Self {
+            provider,
+            model: model.into(),
+            input_cost_per_token,
+            output_cost_per_token,
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// Cost in USD of a request with these token counts
+    pub fn cost(&self, input_tokens: u32, output_tokens: u32) -> f64 {
+        input_tokens as f64 * self.input_cost_per_token
+            + output_tokens as f64 * self.output_cost_per_token
+    }
+}
+
+/// Catalog file layout
+#[derive(Debug, Deserialize)]
+struct CatalogFile {
+    #[serde(default)]
+    prices: Vec<CatalogEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CatalogEntry {
+    /// Provider name as accepted by `LLMProviderType::from_str`
+    provider: String,
+    model: String,
+    input_cost_per_token: f64,
+    output_cost_per_token: f64,
+}
+
+/// Shared catalog of model prices
+///
+/// Clones read and update the same catalog.
+#[derive(Debug, Clone, Default)]
+pub struct PriceCatalog {
+    prices: Arc<RwLock<HashMap<(LLMProviderType, String), ModelPrice>>>,
+}
+
+impl PriceCatalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The process-wide catalog consulted when costing requests
+    pub fn global() -> Self {
+        GLOBAL.clone()
+    }
+
+    /// Catalog seeded from the file named by [`PRICE_CATALOG_ENV`]; empty when unset or
+    /// when the file cannot be loaded, leaving the built-in prices in effect
+    pub fn from_env() -> Self {
+        let catalog = Self::new();
+        if let Ok(path) = std::env::var(PRICE_CATALOG_ENV) {
+            match catalog.load_file(&path) {
+                Ok(count) => info!("Loaded {} model prices from {}", count, path),
+                Err(e) => warn!("Failed to load price catalog from {}: {}", path, e),
+            }
+        }
+        catalog
+    }
+
+    /// Add or replace the prices listed in a JSON or YAML file, chosen by its extension,
+    /// returning how many were loaded
+    pub fn load_file(&self, path: impl AsRef<Path>) -> LLMResult<usize> {
+        let file: CatalogFile = config::Config::builder()
+            .add_source(config::File::from(path.as_ref()))
+            .build()
+            .and_then(|config| config.try_deserialize())
+            .map_err(|e| LLMError::Parse(format!("Invalid price catalog: {}", e)))?;
+
+        let prices = file
+            .prices
+            .into_iter()
+            .map(|entry| {
+                validate_price(entry.input_cost_per_token, entry.output_cost_per_token)?;
+                Ok(ModelPrice::new(
+                    entry.provider.parse()?,
+                    entry.model,
+                    entry.input_cost_per_token,
+                    entry.output_cost_per_token,
+                ))
+            })
+            .collect::<LLMResult<Vec<_>>>()?;
+
+        let count = prices.len();
+        let mut catalog = self.prices.write().unwrap();
+        for price in prices {
+            catalog.insert((price.provider.clone(), price.model.clone()), price);
+        }
+        Ok(count)
+    }
+
+    /// Set a model's price, returning the price it replaces
+    pub fn set_price(&self, price: ModelPrice) -> LLMResult<Option<ModelPrice>> {
+        validate_price(price.input_cost_per_token, price.output_cost_per_token)?;
+        Ok(self
+            .prices
+            .write()
+            .unwrap()
+            .insert((price.provider.clone(), price.model.clone()), price))
+    }
+
+    /// Remove a model's price so its built-in default applies again
+    pub fn remove_price(&self, provider: &LLMProviderType, model: &str) -> Option<ModelPrice> {
+        self.prices
+            .write()
+            .unwrap()
+            .remove(&(provider.clone(), model.to_string()))
+    }
+
+    /// A model's price, if the catalog has one
+    pub fn price(&self, provider: &LLMProviderType, model: &str) -> Option<ModelPrice> {
+        self.prices
+            .read()
+            .unwrap()
+            .get(&(provider.clone(), model.to_string()))
+            .cloned()
+    }
+
+    /// Input and output cost per token of a model, if the catalog has a price for it
+    pub fn cost_per_token(&self, provider: &LLMProviderType, model: &str) -> Option<(f64, f64)> {
+        self.price(provider, model)
+            .map(|price| (price.input_cost_per_token, price.output_cost_per_token))
+    }
+
+    /// All prices, ordered by provider and model
+    pub fn list(&self) -> Vec<ModelPrice> {
+        let mut prices: Vec<_> = self.prices.read().unwrap().values().cloned().collect();
+        prices.sort_by(|a, b| {
+            (a.provider.to_string(), &a.model).cmp(&(b.provider.to_string(), &b.model))
+        });
+        prices
+    }
+}
+
+fn validate_price(input_cost_per_token: f64, output_cost_per_token: f64) -> LLMResult<()> {
+    if [input_cost_per_token, output_cost_per_token]
+        .iter()
+        .all(|cost| cost.is_finite() && *cost >= 0.0)
+    {
+        Ok(())
+    } else {
+        Err(LLMError::InvalidRequest(
+            "Token prices must be non-negative numbers".to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalog_file(extension: &str, contents: &str) -> std::path::PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("cb-prices-{}{}", uuid::Uuid::new_v4(), extension));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_load_yaml_and_json_catalogs() {
+        let catalog = PriceCatalog::new();
+        let yaml = catalog_file(
+            ".yaml",
+            "prices:\n  - provider: openai\n    model: gpt-4o\n    input_cost_per_token: 0.0000025\n    output_cost_per_token: 0.00001\n",
+        );
+        assert_eq!(catalog.load_file(&yaml).unwrap(), 1);
+        assert_eq!(
+            catalog.cost_per_token(&LLMProviderType::OpenAI, "gpt-4o"),
+            Some((0.0000025, 0.00001))
+        );
+
+        let json = catalog_file(
+            ".json",
+            r#"{"prices": [{"provider": "Anthropic", "model": "claude-3-haiku", "input_cost_per_token": 0.00000025, "output_cost_per_token": 0.00000125}]}"#,
+        );
+        assert_eq!(catalog.load_file(&json).unwrap(), 1);
+        assert_eq!(catalog.list().len(), 2);
+
+        let unknown = catalog_file(
+            ".json",
+            r#"{"prices": [{"provider": "nobody", "model": "m", "input_cost_per_token": 0.1, "output_cost_per_token": 0.1}]}"#,
+        );
+        assert!(catalog.load_file(&unknown).is_err());
+        assert_eq!(catalog.list().len(), 2);
+
+        for path in [yaml, json, unknown] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_set_and_remove_price() {
+        let catalog = PriceCatalog::new();
+        let price = ModelPrice::new(
+            LLMProviderType::Google,
+            "gemini-1.5-pro",
+            0.000001,
+            0.000002,
+        );
+        assert_eq!(catalog.set_price(price.clone()).unwrap(), None);
+        let cost = catalog
+            .price(&LLMProviderType::Google, "gemini-1.5-pro")
+            .unwrap()
+            .cost(1000, 500);
+        assert!((cost - 0.002).abs() < 1e-12);
+
+        let negative = ModelPrice::new(LLMProviderType::Google, "gemini-1.5-pro", -1.0, 0.0);
+        assert!(catalog.set_price(negative).is_err());
+
+        assert_eq!(
+            catalog.remove_price(&LLMProviderType::Google, "gemini-1.5-pro"),
+            Some(price)
+        );
+        assert!(catalog.list().is_empty());
+    }
+}
@@ -12,19 +12,30 @@ use crate::llm::{
     LLMError, LLMRequest, LLMResponse, LLMResult, StreamingChunk,
     Choice, LLMProviderType, RoutingInfo, RoutingStrategy, MessageRole,
     EmbeddingsRequest, EmbeddingsResponse,
-    sse::{response_to_sse_stream, anthropic::anthropic_event_to_chunk}
+    sse::{response_to_sse_stream, anthropic::{anthropic_event_to_chunk, ToolCallIndexer}},
+    tools,
 };
 
 use crate::llm::traits::{
-    LLMProviderClient, ModelInfo, ProviderConfigRequirements, CostCalculator, CostBreakdown
+    LLMProviderClient, ModelInfo, ProviderConfigRequirements, CostCalculator, CostBreakdown,
+    KeyQuota, KeyValidation, QuotaHeaders, key_validation_from_response
 };
 
 use super::types::{
-    AnthropicRequest, AnthropicResponse, AnthropicUsage, AnthropicMessage,
-    AnthropicError
+    AnthropicRequest, AnthropicResponse, AnthropicUsage,
+    AnthropicError, AnthropicTool, AnthropicToolChoice
 };
 use super::config::{AnthropicConfig, get_config_requirements, get_available_models};
 
+/// Rate-limit headers returned by the Anthropic API
+const ANTHROPIC_QUOTA_HEADERS: QuotaHeaders = QuotaHeaders {
+    requests_limit: "anthropic-ratelimit-requests-limit",
+    requests_remaining: "anthropic-ratelimit-requests-remaining",
+    tokens_limit: "anthropic-ratelimit-tokens-limit",
+    tokens_remaining: "anthropic-ratelimit-tokens-remaining",
+    requests_reset: "anthropic-ratelimit-requests-reset",
+};
+
 /// Anthropic provider client
 pub struct AnthropicClient {
     client: Client,
@@ -78,20 +89,35 @@ impl AnthropicClient {
 
     /// Convert our internal request format to Anthropic's format
     fn convert_request(&self, request: &LLMRequest) -> LLMResult<AnthropicRequest> {
-        let mut system_prompt = None;
-        let mut messages = Vec::new();
-
-        // Anthropic handles system messages differently
-        for msg in &request.messages {
-            match msg.role {
-                MessageRole::System => {
-                    system_prompt = Some(msg.content.clone());
-                }
-                _ => {
-                    messages.push(AnthropicMessage::from(msg));
-                }
-            }
-        }
+        // Anthropic takes the system prompt separately from the messages
+        let system_prompt = request
+            .messages
+            .iter()
+            .rev()
+            .find(|msg| matches!(msg.role, MessageRole::System))
+            .map(|msg| msg.content.clone());
+        let messages = tools::to_anthropic_messages(&request.messages);
+
+        let structured_output = request
+            .response_format
+            .as_ref()
+            .and_then(AnthropicTool::for_response_format);
+        let mut anthropic_tools = request
+            .functions
+            .as_deref()
+            .map(tools::to_anthropic_tools)
+            .unwrap_or_default();
+
+        // Structured output is forced only when the caller has no tools of their own,
+        // which would otherwise never get called
+        let tool_choice = match (&structured_output, anthropic_tools.is_empty()) {
+            (Some(tool), true) => Some(AnthropicToolChoice {
+                choice_type: "tool".to_string(),
+                name: Some(tool.name.clone()),
+            }),
+            _ => request.tool_choice.as_ref().map(tools::to_anthropic_tool_choice),
+        };
+        anthropic_tools.extend(structured_output);
 
         let anthropic_request = AnthropicRequest {
             model: request.model.clone(),
@@ -103,17 +129,24 @@ impl AnthropicClient {
             stop_sequences: request.stop.clone(),
             stream: Some(false), // Force non-streaming for regular chat_completion
             system: system_prompt,
+            tools: (!anthropic_tools.is_empty()).then_some(anthropic_tools),
+            tool_choice,
         };
 
         Ok(anthropic_request)
     }
 
     /// Convert Anthropic response to our internal format
-    fn convert_response(&self, response: AnthropicResponse) -> LLMResult<LLMResponse> {
+    fn convert_response(
+        &self,
+        response: AnthropicResponse,
+        structured_output_tool: Option<&str>,
+    ) -> LLMResult<LLMResponse> {
+        let message = response.to_chat_message(structured_output_tool);
         let choice = Choice {
             index: 0,
-            message: response.to_chat_message(),
-            finish_reason: response.stop_reason.clone(),
+            finish_reason: tools::finish_reason(&message, response.stop_reason.clone()),
+            message,
         };
 
         let usage = crate::llm::TokenUsage {
@@ -141,6 +174,7 @@ impl AnthropicClient {
                 total_latency_ms: 0,
                 provider_latency_ms: 0,
             },
+            rate_limit: None,
         })
     }
 
@@ -209,6 +243,8 @@ impl LLMProviderClient for AnthropicClient {
             .await
             .map_err(|e| LLMError::Network(e.to_string()))?;
 
+        let rate_limit = KeyQuota::from_headers(response.headers(), &ANTHROPIC_QUOTA_HEADERS);
+
         if !response.status().is_success() {
             let status = response.status();
             let error_text = response
@@ -225,7 +261,15 @@ impl LLMProviderClient for AnthropicClient {
             .await
             .map_err(|e| LLMError::Serialization(e.to_string()))?;
 
-        temp_client.convert_response(anthropic_response)
+        let structured_output_tool = request
+            .response_format
+            .as_ref()
+            .and_then(AnthropicTool::for_response_format)
+            .map(|tool| tool.name);
+        let mut llm_response = temp_client
+            .convert_response(anthropic_response, structured_output_tool.as_deref())?;
+        llm_response.rate_limit = rate_limit;
+        Ok(llm_response)
     }
 
     async fn chat_completion_stream(
@@ -276,21 +320,22 @@ impl LLMProviderClient for AnthropicClient {
         let model = request.model;
         
         let sse_stream = response_to_sse_stream(response);
+        let mut tool_calls = ToolCallIndexer::default();
         let chunk_stream = sse_stream.filter_map(move |sse_result| {
-            let request_id = request_id.clone();
-            let model = model.clone();
-            async move {
-                match sse_result {
-                    Ok(sse_event) => {
-                        match anthropic_event_to_chunk(&sse_event, &request_id, &model) {
-                            Ok(Some(chunk)) => Some(Ok(chunk)),
-                            Ok(None) => None, // Skip empty chunks
-                            Err(e) => Some(Err(e)),
+            let chunk = match sse_result {
+                Ok(sse_event) => {
+                    match anthropic_event_to_chunk(&sse_event, &request_id, &model) {
+                        Ok(Some(mut chunk)) => {
+                            tool_calls.renumber(&mut chunk);
+                            Some(Ok(chunk))
                         }
+                        Ok(None) => None, // Skip empty chunks
+                        Err(e) => Some(Err(e)),
                     }
-                    Err(e) => Some(Err(e)),
                 }
-            }
+                Err(e) => Some(Err(e)),
+            };
+            futures::future::ready(chunk)
         });
 
         Ok(Box::new(Box::pin(chunk_stream)))
@@ -328,6 +373,36 @@ impl LLMProviderClient for AnthropicClient {
         Ok(response.status().is_success())
     }
 
+    async fn validate_api_key(&self, api_key: &str) -> LLMResult<KeyValidation> {
+        let mut client_config = self.config.clone();
+        client_config.api_key = api_key.to_string();
+        let temp_client = AnthropicClient::new(client_config);
+
+        let headers = temp_client.build_headers()?;
+        // Listing models is free, unlike the message request used by health checks
+        let request_url = format!("{}/v1/models", temp_client.config.base_url);
+
+        let response = temp_client.client
+            .get(&request_url)
+            .headers(headers)
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .map_err(|e| LLMError::Network(e.to_string()))?;
+
+        key_validation_from_response(
+            LLMProviderType::Anthropic,
+            response,
+            &get_available_models(),
+            Some(&ANTHROPIC_QUOTA_HEADERS),
+            |json| {
+                json["data"].as_array().map(|models| {
+                    models.iter().filter_map(|m| m["id"].as_str().map(str::to_string)).collect()
+                }).unwrap_or_default()
+            },
+        ).await
+    }
+
     fn get_available_models(&self) -> Vec<ModelInfo> {
         get_available_models()
     }
@@ -400,6 +475,7 @@ impl CostCalculator for AnthropicClient {
 mod tests {
     use super::*;
     use crate::llm::{ChatMessage, MessageRole};
+    use super::super::types::AnthropicMessageContent;
 
     #[test]
     fn test_anthropic_client_creation() {
@@ -420,12 +496,18 @@ mod tests {
                     content: "You are a helpful assistant".to_string(),
                     name: None,
                     function_call: None,
+                    tool_calls: None,
+                    tool_call_id: None,
+                    content_parts: None,
                 },
                 ChatMessage {
                     role: MessageRole::User,
                     content: "Hello".to_string(),
                     name: None,
                     function_call: None,
+                    tool_calls: None,
+                    tool_call_id: None,
+                    content_parts: None,
                 }
             ],
             temperature: Some(0.7),
@@ -439,13 +521,46 @@ mod tests {
             function_call: None,
             user: None,
             metadata: std::collections::HashMap::new(),
+            response_format: None,
+            tool_choice: None,
+            n: None,
         };
 
         let anthropic_request = client.convert_request(&request).unwrap();
         assert_eq!(anthropic_request.model, "claude-3-sonnet-20240229");
         assert_eq!(anthropic_request.system, Some("You are a helpful assistant".to_string()));
         assert_eq!(anthropic_request.messages.len(), 1);
-        assert_eq!(anthropic_request.messages[0].content, "Hello");
+        assert_eq!(
+            anthropic_request.messages[0].content,
+            AnthropicMessageContent::Text("Hello".to_string())
+        );
+    }
+
+    #[test]
+    fn test_json_schema_uses_forced_tool() {
+        let client = AnthropicClient::with_api_key("test-key".to_string());
+        let schema = json!({ "type": "object", "properties": { "answer": { "type": "string" } } });
+        let mut request = crate::llm::context::build_summary_request(&[], "claude-3-haiku-20240307");
+        request.response_format = Some(crate::llm::ResponseFormat::json_schema("answer", schema.clone()));
+
+        let anthropic_request = client.convert_request(&request).unwrap();
+        let tools = anthropic_request.tools.unwrap();
+        assert_eq!(tools[0].name, "answer");
+        assert_eq!(tools[0].input_schema, schema);
+        assert_eq!(anthropic_request.tool_choice.unwrap().name.as_deref(), Some("answer"));
+
+        let response: AnthropicResponse = serde_json::from_value(json!({
+            "id": "msg_1",
+            "type": "message",
+            "role": "assistant",
+            "model": "claude-3-haiku-20240307",
+            "content": [{ "type": "tool_use", "id": "toolu_1", "name": "answer", "input": { "answer": "42" } }],
+            "stop_reason": "tool_use",
+            "stop_sequence": null,
+            "usage": { "input_tokens": 10, "output_tokens": 5 }
+        }))
+        .unwrap();
+        assert_eq!(response.to_chat_message(Some("answer")).content, r#"{"answer":"42"}"#);
     }
 
     #[test]
@@ -9,7 +9,7 @@ use crate::llm::{
         AuthMethod, ModelCapability, ModelInfo, ParameterRestriction, ProviderConfig,
         ProviderConfigRequirements, RateLimitInfo,
     },
-    LLMProviderType,
+    LLMProviderType, PriceCatalog,
 };
 
 /// Anthropic-specific configuration
@@ -87,7 +87,7 @@ pub fn get_available_models() -> Vec<ModelInfo> {
         .unwrap_or_else(|_| "claude-3-sonnet-20240229".to_string());
 
     // Create a single model info for the default model from environment
-    let model_info = ModelInfo {
+    let mut model_info = ModelInfo {
         id: default_model.clone(),
         name: format!("Anthropic {}", default_model),
         provider: LLMProviderType::Anthropic,
@@ -107,6 +107,9 @@ pub fn get_available_models() -> Vec<ModelInfo> {
         ],
         parameter_restrictions: HashMap::new(),
     };
+    if is_vision_model(&default_model) {
+        model_info.capabilities.push(ModelCapability::Vision);
+    }
 
     vec![model_info]
 }
@@ -126,6 +129,13 @@ pub fn is_claude_model(model: &str) -> bool {
     model.starts_with("claude-")
 }
 
+/// Check if a model accepts image input (every model since Claude 3)
+pub fn is_vision_model(model: &str) -> bool {
+    is_claude_model(model)
+        && !model.starts_with("claude-2")
+        && !model.starts_with("claude-instant")
+}
+
 /// Check if a model supports a specific capability
 pub fn model_supports_capability(model: &str, capability: &ModelCapability) -> bool {
     let models = get_available_models();
@@ -136,8 +146,13 @@ pub fn model_supports_capability(model: &str, capability: &ModelCapability) -> b
         .unwrap_or(false)
 }
 
-/// Get cost information for a model
+/// Get cost information for a model, preferring the price catalog over the defaults
 pub fn get_model_cost_info(model: &str) -> Option<(f64, f64)> {
+    if let Some(prices) =
+        PriceCatalog::global().cost_per_token(&LLMProviderType::Anthropic, model)
+    {
+        return Some(prices);
+    }
     let models = get_available_models();
     models
         .iter()
@@ -12,6 +12,7 @@ pub use config::{
     get_default_config, 
     get_available_models,
     is_claude_model,
+    is_vision_model,
     has_parameter_restriction,
     model_supports_capability,
     get_model_cost_info,
@@ -27,7 +28,9 @@ pub use types::{
     AnthropicStreamingChunk,
     AnthropicDelta,
     AnthropicError,
-    AnthropicErrorDetails
+    AnthropicErrorDetails,
+    AnthropicTool,
+    AnthropicToolChoice
 };
 
 /// Create a new Anthropic client with API key
@@ -96,4 +99,12 @@ mod tests {
         assert!(!is_claude_model("gpt-4"));
         assert!(!is_claude_model("gemini-pro"));
     }
+
+    #[test]
+    fn test_vision_model_detection() {
+        assert!(is_vision_model("claude-3-haiku-20240307"));
+        assert!(is_vision_model("claude-sonnet-4-20250514"));
+        assert!(!is_vision_model("claude-2.1"));
+        assert!(!is_vision_model("gpt-4o"));
+    }
 }
\ No newline at end of file
@@ -22,13 +22,94 @@ pub struct AnthropicRequest {
     pub stream: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub system: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<AnthropicTool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<AnthropicToolChoice>,
+}
+
+/// Anthropic tool definition
+#[derive(Debug, Clone, Serialize)]
+pub struct AnthropicTool {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub input_schema: serde_json::Value,
+}
+
+/// Anthropic tool choice
+#[derive(Debug, Clone, Serialize)]
+pub struct AnthropicToolChoice {
+    #[serde(rename = "type")]
+    pub choice_type: String, // "auto", "any" or "tool"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl AnthropicTool {
+    /// Tool whose input is the requested structured output. Anthropic has no native JSON
+    /// mode, so structured output is requested by forcing a call to this tool.
+    pub fn for_response_format(format: &crate::llm::ResponseFormat) -> Option<Self> {
+        if !format.expects_json() {
+            return None;
+        }
+        Some(match format.schema() {
+            Some(json_schema) => Self {
+                name: json_schema.name.clone(),
+                description: json_schema.description.clone(),
+                input_schema: json_schema.schema.clone(),
+            },
+            None => Self {
+                name: "json_response".to_string(),
+                description: Some("Respond with a JSON object".to_string()),
+                input_schema: serde_json::json!({ "type": "object" }),
+            },
+        })
+    }
 }
 
 /// Anthropic message format
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AnthropicMessage {
     pub role: String,
-    pub content: String,
+    pub content: AnthropicMessageContent,
+}
+
+/// Message content: plain text, or blocks when tool calls or tool results are involved
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AnthropicMessageContent {
+    Text(String),
+    Blocks(Vec<AnthropicRequestBlock>),
+}
+
+/// Content block sent in a request message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnthropicRequestBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+    },
+    Image {
+        source: AnthropicImageSource,
+    },
+}
+
+/// Where an image block's data comes from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnthropicImageSource {
+    Base64 { media_type: String, data: String },
+    Url { url: String },
 }
 
 /// Anthropic API response structure
@@ -50,7 +131,17 @@ pub struct AnthropicResponse {
 pub struct AnthropicContentBlock {
     #[serde(rename = "type")]
     pub content_type: String,
+    #[serde(default)]
     pub text: String,
+    /// ID of a `tool_use` block
+    #[serde(default)]
+    pub id: Option<String>,
+    /// Tool name of a `tool_use` block
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Input of a `tool_use` block
+    #[serde(default)]
+    pub input: Option<serde_json::Value>,
 }
 
 /// Anthropic usage statistics
@@ -102,9 +193,15 @@ impl From<&ChatMessage> for AnthropicMessage {
                 MessageRole::System => "system".to_string(),
                 MessageRole::User => "user".to_string(),
                 MessageRole::Assistant => "assistant".to_string(),
-                MessageRole::Function => "user".to_string(), // Anthropic doesn't have function role
+                // Anthropic doesn't have function or tool roles
+                MessageRole::Function | MessageRole::Tool => "user".to_string(),
+            },
+            content: match &msg.content_parts {
+                Some(parts) => AnthropicMessageContent::Blocks(
+                    crate::llm::multimodal::to_anthropic_blocks(parts),
+                ),
+                None => AnthropicMessageContent::Text(msg.content.clone()),
             },
-            content: msg.content.clone(),
         }
     }
 }
@@ -122,18 +219,21 @@ impl From<AnthropicUsage> for TokenUsage {
 
 impl AnthropicResponse {
     /// Convert to our internal ChatMessage format
-    pub fn to_chat_message(&self) -> ChatMessage {
-        let content = self.content
-            .iter()
-            .map(|block| block.text.clone())
-            .collect::<Vec<_>>()
-            .join("\n");
+    ///
+    /// A call to `structured_output_tool` is the structured reply itself and becomes the
+    /// message content; calls to any other tool become tool calls.
+    pub fn to_chat_message(&self, structured_output_tool: Option<&str>) -> ChatMessage {
+        let (content, tool_calls) =
+            crate::llm::tools::from_anthropic_content(&self.content, structured_output_tool);
 
         ChatMessage {
             role: MessageRole::Assistant,
             content,
             name: None,
             function_call: None,
+            tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+            tool_call_id: None,
+            content_parts: None,
         }
     }
 }
\ No newline at end of file
@@ -10,16 +10,17 @@ use std::time::Duration;
 use crate::llm::{
     LLMError, LLMRequest, LLMResponse, LLMResult, StreamingChunk, StreamingChoice,
     Choice, LLMProviderType, RoutingInfo, RoutingStrategy, ChatMessage, MessageRole,
-    EmbeddingsRequest, EmbeddingsResponse,
+    EmbeddingsRequest, EmbeddingsResponse, tools,
 };
 
 use crate::llm::traits::{
-    LLMProviderClient, ModelInfo, ProviderConfigRequirements, CostCalculator, CostBreakdown
+    LLMProviderClient, ModelInfo, ProviderConfigRequirements, CostCalculator, CostBreakdown,
+    KeyValidation, key_validation_from_response
 };
 
 use super::types::{
     GoogleRequest, GoogleResponse, GoogleUsageMetadata, GoogleGenerationConfig,
-    GoogleError, convert_conversation_history
+    GoogleError, convert_conversation_history, to_gemini_schema
 };
 use super::config::{GoogleConfig, get_config_requirements, get_available_models};
 
@@ -75,13 +76,24 @@ impl GoogleClient {
             max_output_tokens: request.max_tokens,
             candidate_count: Some(1),
             stop_sequences: request.stop.clone(),
+            response_mime_type: request
+                .response_format
+                .as_ref()
+                .filter(|format| format.expects_json())
+                .map(|_| "application/json".to_string()),
+            response_schema: request
+                .response_format
+                .as_ref()
+                .and_then(|format| format.schema())
+                .map(|json_schema| to_gemini_schema(&json_schema.schema)),
         };
 
         let google_request = GoogleRequest {
             contents,
             generation_config: Some(generation_config),
             safety_settings: Some(super::config::get_default_safety_settings()),
-            tools: None, // TODO: Implement function calling support
+            tools: request.functions.as_deref().map(tools::to_google_tools),
+            tool_config: request.tool_choice.as_ref().map(tools::to_google_tool_config),
         };
 
         Ok(google_request)
@@ -130,6 +142,7 @@ impl GoogleClient {
                 total_latency_ms: 0,
                 provider_latency_ms: 0,
             },
+            rate_limit: None,
         })
     }
 
@@ -220,8 +233,23 @@ fn parse_google_json_chunk(json_str: &str, request_id: &str, model: &str) -> LLM
                 .map(|part| part.text.clone())
                 .collect::<Vec<String>>()
                 .join("");
+            // Each function call arrives whole, so one delta carries all of it
+            let tool_calls: Vec<crate::llm::ToolCall> = content.parts
+                .iter()
+                .filter_map(|part| part.function_call.as_ref())
+                .enumerate()
+                .map(|(index, call)| {
+                    crate::llm::ToolCall::delta(
+                        index as u32,
+                        crate::llm::tools::generate_call_id(),
+                        call.name.clone(),
+                        call.args.to_string(),
+                    )
+                })
+                .collect();
+            let finish_reason = google_response.get_finish_reason();
             
-            if !text.is_empty() || candidate.finish_reason.is_some() {
+            if !text.is_empty() || !tool_calls.is_empty() || finish_reason.is_some() {
                 Ok(Some(StreamingChunk {
                     id: request_id.to_string(),
                     object: "chat.completion.chunk".to_string(),
@@ -234,10 +262,21 @@ fn parse_google_json_chunk(json_str: &str, request_id: &str, model: &str) -> LLM
                             content: text,
                             name: None,
                             function_call: None,
+                            tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+                            tool_call_id: None,
+                            content_parts: None,
                         },
-                        finish_reason: candidate.finish_reason.clone(),
+                        finish_reason,
                     }],
                     provider: LLMProviderType::Google,
+                    usage: google_response.usage_metadata.as_ref().map(|usage| {
+                        crate::llm::TokenUsage {
+                            prompt_tokens: usage.prompt_token_count,
+                            completion_tokens: usage.candidates_token_count,
+                            total_tokens: usage.total_token_count,
+                            estimated_cost: 0.0,
+                        }
+                    }),
                 }))
             } else {
                 Ok(None)
@@ -471,6 +510,39 @@ impl LLMProviderClient for GoogleClient {
         Ok(response.status().is_success())
     }
 
+    async fn validate_api_key(&self, api_key: &str) -> LLMResult<KeyValidation> {
+        let mut client_config = self.config.clone();
+        client_config.api_key = api_key.to_string();
+        let temp_client = GoogleClient::new(client_config);
+
+        let headers = temp_client.build_headers()?;
+        let request_url = format!("{}/models?key={}", temp_client.config.base_url, api_key);
+
+        let response = temp_client.client
+            .get(&request_url)
+            .headers(headers)
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .map_err(|e| LLMError::Network(e.to_string()))?;
+
+        // Google does not report quota in response headers
+        key_validation_from_response(
+            LLMProviderType::Google,
+            response,
+            &get_available_models(),
+            None,
+            |json| {
+                json["models"].as_array().map(|models| {
+                    models.iter()
+                        .filter_map(|m| m["name"].as_str())
+                        .map(|name| name.trim_start_matches("models/").to_string())
+                        .collect()
+                }).unwrap_or_default()
+            },
+        ).await
+    }
+
     fn get_available_models(&self) -> Vec<ModelInfo> {
         get_available_models()
     }
@@ -563,12 +635,18 @@ mod tests {
                     content: "You are a helpful assistant".to_string(),
                     name: None,
                     function_call: None,
+                    tool_calls: None,
+                    tool_call_id: None,
+                    content_parts: None,
                 },
                 ChatMessage {
                     role: MessageRole::User,
                     content: "Hello".to_string(),
                     name: None,
                     function_call: None,
+                    tool_calls: None,
+                    tool_call_id: None,
+                    content_parts: None,
                 }
             ],
             temperature: Some(0.7),
@@ -582,6 +660,9 @@ mod tests {
             function_call: None,
             user: None,
             metadata: std::collections::HashMap::new(),
+            response_format: None,
+            tool_choice: None,
+            n: None,
         };
 
         let google_request = client.convert_request(&request).unwrap();
@@ -622,4 +703,16 @@ mod tests {
         assert!(!is_gemini_model("gpt-4"));
         assert!(!is_gemini_model("claude-3"));
     }
+
+    #[test]
+    fn test_stream_chunk_with_function_call() {
+        let json = r#"{"candidates":[{"content":{"role":"model","parts":[{"functionCall":{"name":"get_weather","args":{"city":"Oslo"}}}]},"finishReason":"STOP","index":0}]}"#;
+        let chunk = parse_google_json_chunk(json, "test-id", "gemini-1.5-pro").unwrap().unwrap();
+
+        let calls = chunk.choices[0].delta.tool_calls.as_ref().unwrap();
+        assert_eq!(calls[0].index, Some(0));
+        assert_eq!(calls[0].function.name, "get_weather");
+        assert_eq!(calls[0].arguments()["city"], "Oslo");
+        assert_eq!(chunk.choices[0].finish_reason.as_deref(), Some("tool_calls"));
+    }
 }
\ No newline at end of file
@@ -9,7 +9,7 @@ use crate::llm::{
         AuthMethod, ModelCapability, ModelInfo, ParameterRestriction, ProviderConfig,
         ProviderConfigRequirements, RateLimitInfo,
     },
-    LLMProviderType,
+    LLMProviderType, PriceCatalog,
 };
 
 /// Google-specific configuration
@@ -190,8 +190,11 @@ pub fn model_supports_capability(model: &str, capability: &ModelCapability) -> b
         .unwrap_or(false)
 }
 
-/// Get cost information for a model
+/// Get cost information for a model, preferring the price catalog over the defaults
 pub fn get_model_cost_info(model: &str) -> Option<(f64, f64)> {
+    if let Some(prices) = PriceCatalog::global().cost_per_token(&LLMProviderType::Google, model) {
+        return Some(prices);
+    }
     let models = get_available_models();
     models
         .iter()
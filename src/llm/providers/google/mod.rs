@@ -18,7 +18,7 @@ pub use types::{
     GoogleError, GoogleErrorDetails, GoogleFunctionDeclaration, GoogleGenerationConfig,
     GoogleModel, GoogleModelsResponse, GooglePart, GooglePromptFeedback, GoogleRequest,
     GoogleResponse, GoogleSafetyRating, GoogleSafetySetting, GoogleStreamingCandidate,
-    GoogleStreamingChunk, GoogleTool, GoogleUsageMetadata,
+    GoogleStreamingChunk, GoogleTool, GoogleUsageMetadata, to_gemini_schema,
 };
 
 /// Create a new Google client with API key
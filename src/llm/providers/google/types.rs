@@ -14,6 +14,8 @@ pub struct GoogleRequest {
     pub safety_settings: Option<Vec<GoogleSafetySetting>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<Vec<GoogleTool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_config: Option<GoogleToolConfig>,
 }
 
 /// Google content structure
@@ -26,9 +28,72 @@ pub struct GoogleContent {
 }
 
 /// Google content part
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct GooglePart {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub text: String,
+    #[serde(
+        rename = "functionCall",
+        alias = "function_call",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub function_call: Option<GoogleFunctionCall>,
+    #[serde(
+        rename = "functionResponse",
+        alias = "function_response",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub function_response: Option<GoogleFunctionResponse>,
+    /// Image sent inline as base64
+    #[serde(
+        rename = "inlineData",
+        alias = "inline_data",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub inline_data: Option<GoogleBlob>,
+    /// Image referenced by URI
+    #[serde(
+        rename = "fileData",
+        alias = "file_data",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub file_data: Option<GoogleFileData>,
+}
+
+/// Base64-encoded media
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GoogleBlob {
+    #[serde(rename = "mimeType")]
+    pub mime_type: String,
+    pub data: String,
+}
+
+/// Media referenced by URI
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GoogleFileData {
+    #[serde(rename = "mimeType")]
+    pub mime_type: String,
+    #[serde(rename = "fileUri")]
+    pub file_uri: String,
+}
+
+/// Function call made by the model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoogleFunctionCall {
+    pub name: String,
+    #[serde(default)]
+    pub args: serde_json::Value,
+}
+
+/// Result of a function call, sent back to the model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoogleFunctionResponse {
+    pub name: String,
+    pub response: serde_json::Value,
 }
 
 /// Google generation configuration
@@ -46,6 +111,10 @@ pub struct GoogleGenerationConfig {
     pub candidate_count: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_mime_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_schema: Option<serde_json::Value>,
 }
 
 /// Google safety setting
@@ -66,7 +135,23 @@ pub struct GoogleTool {
 pub struct GoogleFunctionDeclaration {
     pub name: String,
     pub description: String,
-    pub parameters: serde_json::Value,
+    /// Omitted for functions without arguments, which Gemini rejects as empty objects
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<serde_json::Value>,
+}
+
+/// Google tool configuration
+#[derive(Debug, Clone, Serialize)]
+pub struct GoogleToolConfig {
+    pub function_calling_config: GoogleFunctionCallingConfig,
+}
+
+/// Google function calling mode
+#[derive(Debug, Clone, Serialize)]
+pub struct GoogleFunctionCallingConfig {
+    pub mode: String, // "AUTO", "ANY" or "NONE"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_function_names: Option<Vec<String>>,
 }
 
 /// Google API response structure
@@ -178,14 +263,18 @@ pub struct GoogleModel {
 impl From<&ChatMessage> for GoogleContent {
     fn from(msg: &ChatMessage) -> Self {
         Self {
-            parts: vec![GooglePart {
-                text: msg.content.clone(),
-            }],
+            parts: match &msg.content_parts {
+                Some(parts) => crate::llm::multimodal::to_google_parts(parts),
+                None => vec![GooglePart {
+                    text: msg.content.clone(),
+                    ..Default::default()
+                }],
+            },
             role: Some(match msg.role {
                 MessageRole::System => "user".to_string(), // Google doesn't have system role
                 MessageRole::User => "user".to_string(),
                 MessageRole::Assistant => "model".to_string(),
-                MessageRole::Function => "user".to_string(),
+                MessageRole::Function | MessageRole::Tool => "user".to_string(),
             }),
         }
     }
@@ -205,32 +294,39 @@ impl From<GoogleUsageMetadata> for TokenUsage {
 impl GoogleResponse {
     /// Convert to our internal ChatMessage format
     pub fn to_chat_message(&self) -> ChatMessage {
-        let content = self.candidates
+        let parts = self.candidates
             .first()
-            .and_then(|candidate| {
-                candidate.content.as_ref().and_then(|content| {
-                    if content.parts.is_empty() {
-                        None
-                    } else {
-                        content.parts.first().map(|part| part.text.clone())
-                    }
-                })
-            })
-            .unwrap_or_else(|| "No response generated".to_string());
+            .and_then(|candidate| candidate.content.as_ref())
+            .map(|content| content.parts.as_slice())
+            .unwrap_or_default();
+        let (mut content, tool_calls) = crate::llm::tools::from_google_parts(parts);
+        if content.is_empty() && tool_calls.is_empty() {
+            content = "No response generated".to_string();
+        }
 
         ChatMessage {
             role: MessageRole::Assistant,
             content,
             name: None,
             function_call: None,
+            tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+            tool_call_id: None,
+            content_parts: None,
         }
     }
 
-    /// Get finish reason from first candidate
+    /// Get finish reason from first candidate, `tool_calls` when it called functions
     pub fn get_finish_reason(&self) -> Option<String> {
-        self.candidates
-            .first()
-            .and_then(|candidate| candidate.finish_reason.clone())
+        let candidate = self.candidates.first()?;
+        let called_functions = candidate
+            .content
+            .as_ref()
+            .is_some_and(|content| content.parts.iter().any(|part| part.function_call.is_some()));
+        if called_functions {
+            Some("tool_calls".to_string())
+        } else {
+            candidate.finish_reason.clone()
+        }
     }
 }
 
@@ -239,33 +335,78 @@ pub fn create_system_content(system_message: &str) -> GoogleContent {
     GoogleContent {
         parts: vec![GooglePart {
             text: format!("System: {}", system_message),
+            ..Default::default()
         }],
         role: Some("user".to_string()),
     }
 }
 
-/// Helper function to convert conversation history for Google
-pub fn convert_conversation_history(messages: &[ChatMessage]) -> Vec<GoogleContent> {
-    let mut contents = Vec::new();
-    let mut system_messages = Vec::new();
-    
-    // Collect system messages separately
-    for msg in messages {
-        match msg.role {
-            MessageRole::System => {
-                system_messages.push(msg.content.clone());
+/// Convert a JSON schema to the OpenAPI subset Gemini accepts as `responseSchema`
+///
+/// Unsupported keywords such as `additionalProperties` are dropped and `"type": [T, "null"]`
+/// becomes `"type": T` with `"nullable": true`; the router still validates the reply
+/// against the full schema.
+pub fn to_gemini_schema(schema: &serde_json::Value) -> serde_json::Value {
+    use serde_json::Value;
+
+    const SUPPORTED: &[&str] = &[
+        "type", "format", "description", "nullable", "enum", "properties", "required",
+        "items", "minItems", "maxItems", "minimum", "maximum", "anyOf", "propertyOrdering",
+    ];
+
+    let Some(object) = schema.as_object() else {
+        return schema.clone();
+    };
+
+    let mut converted = serde_json::Map::new();
+    for (key, value) in object {
+        if !SUPPORTED.contains(&key.as_str()) {
+            continue;
+        }
+        let value = match (key.as_str(), value) {
+            ("type", Value::Array(types)) => {
+                if types.iter().any(|t| t == "null") {
+                    converted.insert("nullable".to_string(), Value::Bool(true));
+                }
+                types
+                    .iter()
+                    .find(|t| *t != "null")
+                    .cloned()
+                    .unwrap_or(Value::String("string".to_string()))
             }
-            _ => {
-                contents.push(GoogleContent::from(msg));
+            ("properties", Value::Object(properties)) => Value::Object(
+                properties
+                    .iter()
+                    .map(|(name, property)| (name.clone(), to_gemini_schema(property)))
+                    .collect(),
+            ),
+            ("items", items) => to_gemini_schema(items),
+            ("anyOf", Value::Array(branches)) => {
+                Value::Array(branches.iter().map(to_gemini_schema).collect())
             }
-        }
+            _ => value.clone(),
+        };
+        converted.insert(key.clone(), value);
     }
+    Value::Object(converted)
+}
+
+/// Helper function to convert conversation history for Google
+pub fn convert_conversation_history(messages: &[ChatMessage]) -> Vec<GoogleContent> {
+    // Collect system messages separately
+    let system_messages: Vec<String> = messages
+        .iter()
+        .filter(|msg| matches!(msg.role, MessageRole::System))
+        .map(|msg| msg.content.clone())
+        .collect();
+    let mut contents = crate::llm::tools::to_google_contents(messages);
     
     // If we have system messages, prepend them as user content
     if !system_messages.is_empty() {
         let system_content = GoogleContent {
             parts: vec![GooglePart {
                 text: format!("System instructions: {}", system_messages.join("\n")),
+                ..Default::default()
             }],
             role: Some("user".to_string()),
         };
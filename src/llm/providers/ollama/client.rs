@@ -18,7 +18,7 @@ use crate::llm::{
 
 use crate::llm::traits::{LLMProviderClient, ModelInfo, ProviderConfigRequirements};
 
-use super::config::{get_config_requirements, get_fallback_models, OllamaConfig};
+use super::config::{get_config_requirements, get_fallback_models, is_vision_model, OllamaConfig};
 use super::types::{
     OllamaChatMessage, OllamaEmbeddingsRequest, OllamaEmbeddingsResponse, OllamaError,
     OllamaModelInfo, OllamaModelsResponse, OllamaOptions, OllamaRequest, OllamaResponse,
@@ -112,7 +112,13 @@ impl OllamaClient {
             model: request.model.clone(),
             messages: filtered_messages,
             stream: request.stream,
-            format: None, // Could be "json" for structured output
+            format: request
+                .response_format
+                .as_ref()
+                .and_then(|format| match format.schema() {
+                    Some(json_schema) => Some(json_schema.schema.clone()),
+                    None => format.expects_json().then(|| serde_json::json!("json")),
+                }),
             options: Some(options),
             system: system_message,
             template: None,
@@ -172,6 +178,7 @@ impl OllamaClient {
             usage,
             provider: LLMProviderType::Ollama,
             routing_info,
+            rate_limit: None,
         })
     }
 
@@ -586,6 +593,19 @@ impl OllamaStreamAdapter {
                         },
                     }],
                     provider: LLMProviderType::Ollama,
+                    usage: match (ollama_chunk.prompt_eval_count, ollama_chunk.eval_count) {
+                        (None, None) => None,
+                        (prompt, completion) => {
+                            let prompt_tokens = prompt.unwrap_or(0);
+                            let completion_tokens = completion.unwrap_or(0);
+                            Some(crate::llm::TokenUsage {
+                                prompt_tokens,
+                                completion_tokens,
+                                total_tokens: prompt_tokens + completion_tokens,
+                                estimated_cost: 0.0,
+                            })
+                        }
+                    },
                 };
                 Some(Ok(streaming_chunk))
             }
@@ -781,6 +801,11 @@ fn determine_model_capabilities_from_name(name: &str) -> Vec<crate::llm::traits:
         capabilities.push(ModelCapability::Reasoning);
     }
 
+    // Vision models accept images alongside text
+    if is_vision_model(name) {
+        capabilities.push(ModelCapability::Vision);
+    }
+
     // Embedding models
     if name.contains("embed") {
         capabilities.push(ModelCapability::Embedding);
@@ -801,8 +826,7 @@ pub fn determine_model_capabilities(name: &str) -> Vec<crate::llm::traits::Model
     determine_model_capabilities_from_name(name)
 }
 
-/// Simple token estimation for embeddings
+/// Token estimate for embeddings; Ollama models have no local tokenizer
 fn estimate_tokens(text: &str) -> u32 {
-    // Rough estimation: ~4 characters per token for English text
-    (text.len() as f32 / 4.0).ceil() as u32
+    crate::llm::Tokenizer::Heuristic.count(text)
 }
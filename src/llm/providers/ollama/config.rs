@@ -161,6 +161,11 @@ fn determine_model_capabilities_from_name(name: &str) -> Vec<crate::llm::traits:
         capabilities.push(ModelCapability::Reasoning);
     }
 
+    // Vision models accept images alongside text
+    if is_vision_model(name) {
+        capabilities.push(ModelCapability::Vision);
+    }
+
     // Embedding models
     if name.contains("embed") {
         capabilities.push(ModelCapability::Embedding);
@@ -192,6 +197,21 @@ fn estimate_model_size_from_name(name: &str) -> (u32, u32) {
     }
 }
 
+/// Check if a model accepts image input, e.g. `llava`, `llama3.2-vision` or `gemma3`
+pub fn is_vision_model(model: &str) -> bool {
+    const VISION_PATTERNS: &[&str] = &[
+        "llava",
+        "vision",
+        "moondream",
+        "minicpm-v",
+        "qwen2.5vl",
+        "gemma3",
+        "llama4",
+        "mistral-small3",
+    ];
+    VISION_PATTERNS.iter().any(|pattern| model.contains(pattern))
+}
+
 /// Check if a model is a code-focused model
 pub fn is_code_model(model: &str) -> bool {
     model.starts_with("codellama") || model == "phi" || model.starts_with("qwen2.5-coder")
@@ -9,7 +9,7 @@ pub use client::OllamaClient;
 pub use config::{
     get_config_requirements, get_default_config, get_fallback_models, get_model_info,
     get_recommended_model_patterns, is_code_model, is_embedding_model, is_reasoning_model,
-    model_supports_capability, OllamaConfig,
+    is_vision_model, model_supports_capability, OllamaConfig,
 };
 pub use types::{
     OllamaBatchEmbeddingsRequest, OllamaBatchEmbeddingsResponse, OllamaChatMessage,
@@ -131,6 +131,10 @@ mod tests {
 
         assert!(is_embedding_model("nomic-embed-text:latest"));
         assert!(!is_embedding_model("granite3-dense:8b"));
+
+        assert!(is_vision_model("llava:13b"));
+        assert!(is_vision_model("llama3.2-vision"));
+        assert!(!is_vision_model("llama3"));
     }
 
     #[test]
@@ -15,9 +15,9 @@ pub struct OllamaRequest {
     /// Whether to stream the response
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
-    /// Format to return a response in (e.g., "json")
+    /// Format to return a response in: "json" or a JSON schema
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub format: Option<String>,
+    pub format: Option<serde_json::Value>,
     /// Additional model parameters
     #[serde(skip_serializing_if = "Option::is_none")]
     pub options: Option<OllamaOptions>,
@@ -302,9 +302,13 @@ impl From<&crate::llm::ChatMessage> for OllamaChatMessage {
                 crate::llm::MessageRole::User => "user".to_string(),
                 crate::llm::MessageRole::Assistant => "assistant".to_string(),
                 crate::llm::MessageRole::Function => "assistant".to_string(), // Map function to assistant
+                crate::llm::MessageRole::Tool => "tool".to_string(),
             },
             content: msg.content.clone(),
-            images: None, // TODO: Add support for multimodal when needed
+            images: msg
+                .content_parts
+                .is_some()
+                .then(|| crate::llm::multimodal::to_ollama_images(msg)),
         }
     }
 }
@@ -321,6 +325,9 @@ impl From<OllamaChatMessage> for crate::llm::ChatMessage {
             content: msg.content,
             name: None,
             function_call: None,
+            tool_calls: None,
+            tool_call_id: None,
+            content_parts: None,
         }
     }
 }
\ No newline at end of file
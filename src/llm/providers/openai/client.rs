@@ -12,18 +12,30 @@ use crate::llm::{
     LLMError, LLMRequest, LLMResponse, LLMResult, StreamingChunk,
     Choice, LLMProviderType, RoutingInfo, RoutingStrategy,
     EmbeddingsRequest, EmbeddingsResponse,
-    sse::{response_to_sse_stream, openai::openai_event_to_chunk}
+    sse::{response_to_sse_stream, openai::openai_event_to_chunk},
+    tools,
 };
 
 use crate::llm::traits::{
-    LLMProviderClient, ModelInfo, ProviderConfigRequirements, CostCalculator, CostBreakdown
+    LLMProviderClient, ModelInfo, ProviderConfigRequirements, CostCalculator, CostBreakdown,
+    KeyQuota, KeyValidation, QuotaHeaders, key_validation_from_response
 };
 
 use super::types::{
-    OpenAIRequest, OpenAIResponse, OpenAIUsage, OpenAIChatMessage, OpenAIError
+    OpenAIRequest, OpenAIResponse, OpenAIUsage, OpenAIChatMessage, OpenAIError,
+    OpenAIStreamOptions, OpenAIEmbeddingsRequest, OpenAIEmbeddingsResponse,
 };
 use super::config::{OpenAIConfig, get_config_requirements, get_available_models, is_o4_model};
 
+/// Rate-limit headers returned by the OpenAI API
+const OPENAI_QUOTA_HEADERS: QuotaHeaders = QuotaHeaders {
+    requests_limit: "x-ratelimit-limit-requests",
+    requests_remaining: "x-ratelimit-remaining-requests",
+    tokens_limit: "x-ratelimit-limit-tokens",
+    tokens_remaining: "x-ratelimit-remaining-tokens",
+    requests_reset: "x-ratelimit-reset-requests",
+};
+
 /// OpenAI provider client
 pub struct OpenAIClient {
     client: Client,
@@ -115,9 +127,11 @@ impl OpenAIClient {
             stop: request.stop.clone(),
             stream: Some(false), // Force non-streaming for regular chat_completion
             user: request.user.clone(),
-            response_format: None,
-            tools: None,
-            tool_choice: None,
+            response_format: request.response_format.as_ref().map(Into::into),
+            tools: request.functions.as_deref().map(tools::to_openai_tools),
+            tool_choice: request.tool_choice.as_ref().map(tools::to_openai_tool_choice),
+            stream_options: None,
+            n: request.n,
         };
 
         // Set the appropriate max tokens field
@@ -165,6 +179,7 @@ impl OpenAIClient {
                 total_latency_ms: 0,
                 provider_latency_ms: 0,
             },
+            rate_limit: None,
         })
     }
 
@@ -233,6 +248,8 @@ impl LLMProviderClient for OpenAIClient {
             .await
             .map_err(|e| LLMError::Network(e.to_string()))?;
 
+        let rate_limit = KeyQuota::from_headers(response.headers(), &OPENAI_QUOTA_HEADERS);
+
         if !response.status().is_success() {
             let status = response.status();
             let error_text = response
@@ -249,7 +266,9 @@ impl LLMProviderClient for OpenAIClient {
             .await
             .map_err(|e| LLMError::Serialization(e.to_string()))?;
 
-        temp_client.convert_response(openai_response)
+        let mut llm_response = temp_client.convert_response(openai_response)?;
+        llm_response.rate_limit = rate_limit;
+        Ok(llm_response)
     }
 
     async fn chat_completion_stream(
@@ -269,6 +288,10 @@ impl LLMProviderClient for OpenAIClient {
         
         // Enable streaming for this request
         openai_request.stream = Some(true);
+        // Ask for a final usage chunk so streamed completions report real token counts
+        openai_request.stream_options = Some(OpenAIStreamOptions {
+            include_usage: true,
+        });
 
         let request_url = format!("{}/chat/completions", temp_client.config.base_url);
         
@@ -335,6 +358,35 @@ impl LLMProviderClient for OpenAIClient {
         Ok(response.status().is_success())
     }
 
+    async fn validate_api_key(&self, api_key: &str) -> LLMResult<KeyValidation> {
+        let mut client_config = self.config.clone();
+        client_config.api_key = api_key.to_string();
+        let temp_client = OpenAIClient::new(client_config);
+
+        let headers = temp_client.build_headers()?;
+        let request_url = format!("{}/models", temp_client.config.base_url);
+
+        let response = temp_client.client
+            .get(&request_url)
+            .headers(headers)
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .map_err(|e| LLMError::Network(e.to_string()))?;
+
+        key_validation_from_response(
+            LLMProviderType::OpenAI,
+            response,
+            &get_available_models(),
+            Some(&OPENAI_QUOTA_HEADERS),
+            |json| {
+                json["data"].as_array().map(|models| {
+                    models.iter().filter_map(|m| m["id"].as_str().map(str::to_string)).collect()
+                }).unwrap_or_default()
+            },
+        ).await
+    }
+
     fn get_available_models(&self) -> Vec<ModelInfo> {
         get_available_models()
     }
@@ -344,6 +396,10 @@ impl LLMProviderClient for OpenAIClient {
         models.iter().any(|m| m.id == model)
     }
 
+    fn supports_native_choices(&self, _model: &str) -> bool {
+        true
+    }
+
     fn get_config_requirements(&self) -> ProviderConfigRequirements {
         get_config_requirements()
     }
@@ -352,9 +408,88 @@ impl LLMProviderClient for OpenAIClient {
         self
     }
 
-    async fn embeddings(&self, _request: &EmbeddingsRequest, _api_key: &str) -> LLMResult<EmbeddingsResponse> {
-        // TODO: Implement OpenAI embeddings support
-        Err(LLMError::Provider("Embeddings not yet implemented for OpenAI provider".to_string()))
+    async fn embeddings(&self, request: &EmbeddingsRequest, api_key: &str) -> LLMResult<EmbeddingsResponse> {
+        let start_time = std::time::Instant::now();
+        let mut client_config = self.config.clone();
+        if !api_key.is_empty() {
+            client_config.api_key = api_key.to_string();
+        }
+        let temp_client = OpenAIClient::new(client_config);
+
+        let headers = temp_client.build_headers()?;
+        let openai_request = OpenAIEmbeddingsRequest {
+            model: request.model.clone(),
+            input: request.input.texts(),
+            encoding_format: "float".to_string(),
+            dimensions: request.dimensions,
+            user: request.user.clone(),
+        };
+
+        let request_url = format!("{}/embeddings", temp_client.config.base_url);
+
+        debug!("OpenAI Embeddings Request: URL={}, Model={}", request_url, request.model);
+
+        let response = temp_client.client
+            .post(&request_url)
+            .headers(headers)
+            .json(&openai_request)
+            .timeout(Duration::from_secs(temp_client.config.timeout_seconds))
+            .send()
+            .await
+            .map_err(|e| LLMError::Network(e.to_string()))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+
+            error!("OpenAI Embeddings API Error: {} - {}", status, error_text);
+            return Err(temp_client.handle_error_response(status.as_u16(), &error_text));
+        }
+
+        let openai_response: OpenAIEmbeddingsResponse = response
+            .json()
+            .await
+            .map_err(|e| LLMError::Serialization(e.to_string()))?;
+
+        let estimated_cost = super::config::get_model_cost_info(&request.model)
+            .map(|(input_cost, _)| openai_response.usage.prompt_tokens as f64 * input_cost)
+            .unwrap_or(0.0);
+        let latency_ms = start_time.elapsed().as_millis() as u64;
+
+        Ok(EmbeddingsResponse {
+            id: uuid::Uuid::new_v4().to_string(),
+            object: "list".to_string(),
+            created: chrono::Utc::now().timestamp() as u64,
+            model: openai_response.model,
+            data: openai_response
+                .data
+                .into_iter()
+                .map(|embedding| crate::llm::EmbeddingData {
+                    index: embedding.index,
+                    embedding: embedding.embedding,
+                    object: "embedding".to_string(),
+                })
+                .collect(),
+            usage: crate::llm::EmbeddingsUsage {
+                prompt_tokens: openai_response.usage.prompt_tokens,
+                total_tokens: openai_response.usage.total_tokens,
+                estimated_cost,
+            },
+            provider: LLMProviderType::OpenAI,
+            routing_info: RoutingInfo {
+                selected_provider: LLMProviderType::OpenAI,
+                routing_strategy: RoutingStrategy::ModelSpecific(request.model.clone()),
+                latency_ms,
+                retry_count: 0,
+                fallback_used: false,
+                provider_used: LLMProviderType::OpenAI,
+                total_latency_ms: latency_ms,
+                provider_latency_ms: latency_ms,
+            },
+        })
     }
 }
 
@@ -426,6 +561,9 @@ mod tests {
                 content: "Hello".to_string(),
                 name: None,
                 function_call: None,
+                tool_calls: None,
+                tool_call_id: None,
+                content_parts: None,
             }],
             temperature: Some(0.7),
             max_tokens: Some(100),
@@ -438,6 +576,9 @@ mod tests {
             functions: None,
             function_call: None,
             metadata: HashMap::new(),
+            response_format: None,
+            tool_choice: None,
+            n: None,
         };
 
         let openai_request = client.convert_request(&request).unwrap();
@@ -458,6 +599,9 @@ mod tests {
                 content: "Hello".to_string(),
                 name: None,
                 function_call: None,
+                tool_calls: None,
+                tool_call_id: None,
+                content_parts: None,
             }],
             temperature: Some(0.7),
             max_tokens: Some(100),
@@ -470,6 +614,9 @@ mod tests {
             functions: None,
             function_call: None,
             metadata: HashMap::new(),
+            response_format: None,
+            tool_choice: None,
+            n: None,
         };
 
         let openai_request = client.convert_request(&request).unwrap();
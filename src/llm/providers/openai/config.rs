@@ -9,7 +9,7 @@ use crate::llm::{
         AuthMethod, ModelCapability, ModelInfo, ParameterRestriction, ProviderConfig,
         ProviderConfigRequirements, RateLimitInfo,
     },
-    LLMProviderType,
+    LLMProviderType, PriceCatalog,
 };
 
 /// OpenAI-specific configuration
@@ -101,7 +101,7 @@ pub fn get_available_models() -> Vec<ModelInfo> {
         std::env::var("OPENAI_DEFAULT_MODEL").unwrap_or_else(|_| "gpt-4".to_string());
 
     // Create a single model info for the default model from environment
-    let model_info = ModelInfo {
+    let mut model_info = ModelInfo {
         id: default_model.clone(),
         name: format!("OpenAI {}", default_model),
         provider: LLMProviderType::OpenAI,
@@ -120,6 +120,9 @@ pub fn get_available_models() -> Vec<ModelInfo> {
         ],
         parameter_restrictions: HashMap::new(),
     };
+    if is_vision_model(&default_model) {
+        model_info.capabilities.push(ModelCapability::Vision);
+    }
 
     vec![model_info]
 }
@@ -139,6 +142,26 @@ pub fn is_o4_model(model: &str) -> bool {
     model.starts_with("o4-")
 }
 
+/// Check if a model accepts image input (GPT-4o, GPT-4.1, GPT-4 Turbo, GPT-5 and the
+/// full-size o-series reasoning models)
+pub fn is_vision_model(model: &str) -> bool {
+    const VISION_PREFIXES: &[&str] = &[
+        "gpt-4o",
+        "gpt-4.1",
+        "gpt-4.5",
+        "gpt-4-turbo",
+        "gpt-4-vision",
+        "gpt-5",
+        "o1",
+        "o3",
+        "o4-",
+    ];
+    VISION_PREFIXES.iter().any(|prefix| model.starts_with(prefix))
+        && !matches!(model, "o1-mini" | "o3-mini")
+        && !model.starts_with("o1-mini-")
+        && !model.starts_with("o3-mini-")
+}
+
 /// Check if a model supports a specific capability
 pub fn model_supports_capability(model: &str, capability: &ModelCapability) -> bool {
     let models = get_available_models();
@@ -149,8 +172,11 @@ pub fn model_supports_capability(model: &str, capability: &ModelCapability) -> b
         .unwrap_or(false)
 }
 
-/// Get cost information for a model
+/// Get cost information for a model, preferring the price catalog over the defaults
 pub fn get_model_cost_info(model: &str) -> Option<(f64, f64)> {
+    if let Some(prices) = PriceCatalog::global().cost_per_token(&LLMProviderType::OpenAI, model) {
+        return Some(prices);
+    }
     let models = get_available_models();
     models
         .iter()
@@ -12,6 +12,7 @@ pub use config::{
     get_default_config, 
     get_available_models,
     is_o4_model,
+    is_vision_model,
     has_parameter_restriction,
     model_supports_capability,
     get_model_cost_info
@@ -103,4 +104,14 @@ mod tests {
         assert!(!is_o4_model("gpt-4"));
         assert!(!is_o4_model("claude-3"));
     }
+
+    #[test]
+    fn test_vision_model_detection() {
+        assert!(is_vision_model("gpt-4o-mini"));
+        assert!(is_vision_model("gpt-4-turbo-2024-04-09"));
+        assert!(is_vision_model("o4-mini-2025-04-16"));
+        assert!(!is_vision_model("gpt-4"));
+        assert!(!is_vision_model("gpt-3.5-turbo"));
+        assert!(!is_vision_model("o3-mini-2025-01-31"));
+    }
 }
\ No newline at end of file
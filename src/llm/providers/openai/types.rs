@@ -33,13 +33,24 @@ pub struct OpenAIRequest {
     pub tools: Option<Vec<Tool>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_choice: Option<ToolChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<OpenAIStreamOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
+}
+
+/// Options for streamed responses
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenAIStreamOptions {
+    /// Send a final chunk with the token usage of the whole request
+    pub include_usage: bool,
 }
 
 /// OpenAI chat message format
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OpenAIChatMessage {
     pub role: String,
-    pub content: String,
+    pub content: crate::llm::MessageContent,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -52,7 +63,24 @@ pub struct OpenAIChatMessage {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResponseFormat {
     #[serde(rename = "type")]
-    pub format_type: String, // "text" or "json_object"
+    pub format_type: String, // "text", "json_object" or "json_schema"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub json_schema: Option<crate::llm::JsonSchemaFormat>,
+}
+
+impl From<&crate::llm::ResponseFormat> for ResponseFormat {
+    fn from(format: &crate::llm::ResponseFormat) -> Self {
+        use crate::llm::ResponseFormat as Format;
+        let format_type = match format {
+            Format::Text => "text",
+            Format::JsonObject => "json_object",
+            Format::JsonSchema { .. } => "json_schema",
+        };
+        Self {
+            format_type: format_type.to_string(),
+            json_schema: format.schema().cloned(),
+        }
+    }
 }
 
 /// Tool definition for function calling
@@ -154,6 +182,40 @@ pub struct CompletionTokensDetails {
     pub reasoning_tokens: Option<u32>,
 }
 
+/// OpenAI embeddings request
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenAIEmbeddingsRequest {
+    pub model: String,
+    pub input: Vec<String>,
+    pub encoding_format: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dimensions: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+/// OpenAI embeddings response
+#[derive(Debug, Deserialize)]
+pub struct OpenAIEmbeddingsResponse {
+    pub data: Vec<OpenAIEmbedding>,
+    pub model: String,
+    pub usage: OpenAIEmbeddingsUsage,
+}
+
+/// OpenAI embedding data
+#[derive(Debug, Deserialize)]
+pub struct OpenAIEmbedding {
+    pub index: u32,
+    pub embedding: Vec<f64>,
+}
+
+/// OpenAI embeddings usage
+#[derive(Debug, Deserialize)]
+pub struct OpenAIEmbeddingsUsage {
+    pub prompt_tokens: u32,
+    pub total_tokens: u32,
+}
+
 /// OpenAI streaming response chunk
 #[derive(Debug, Deserialize)]
 pub struct OpenAIStreamingChunk {
@@ -218,11 +280,15 @@ impl From<&ChatMessage> for OpenAIChatMessage {
                 crate::llm::MessageRole::User => "user".to_string(),
                 crate::llm::MessageRole::Assistant => "assistant".to_string(),
                 crate::llm::MessageRole::Function => "function".to_string(),
+                crate::llm::MessageRole::Tool => "tool".to_string(),
             },
-            content: msg.content.clone(),
+            content: crate::llm::MessageContent::from_message(msg),
             name: msg.name.clone(),
-            tool_calls: None, // TODO: Implement tool calls conversion
-            tool_call_id: None,
+            tool_calls: msg
+                .tool_calls
+                .as_deref()
+                .map(crate::llm::tools::to_openai_tool_calls),
+            tool_call_id: msg.tool_call_id.clone(),
         }
     }
 }
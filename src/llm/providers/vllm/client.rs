@@ -11,7 +11,8 @@ use crate::llm::{
     LLMError, LLMRequest, LLMResponse, LLMResult, StreamingChunk,
     Choice, LLMProviderType, RoutingInfo, RoutingStrategy,
     EmbeddingsRequest, EmbeddingsResponse, EmbeddingsInput,
-    sse::{response_to_sse_stream, openai::openai_event_to_chunk}
+    sse::{response_to_sse_stream, openai::openai_event_to_chunk},
+    tools,
 };
 
 use crate::llm::traits::{
@@ -102,9 +103,11 @@ impl VLLMClient {
             stop: request.stop.clone(),
             stream: Some(false), // Force non-streaming for regular chat_completion
             user: request.user.clone(),
-            response_format: None,
-            tools: None,
-            tool_choice: None,
+            response_format: request.response_format.as_ref().map(Into::into),
+            tools: request.functions.as_deref().map(tools::to_openai_tools),
+            tool_choice: request.tool_choice.as_ref().map(tools::to_openai_tool_choice),
+            stream_options: None,
+            n: request.n,
         };
 
         Ok(vllm_request)
@@ -145,6 +148,7 @@ impl VLLMClient {
                 total_latency_ms: 0,
                 provider_latency_ms: 0,
             },
+            rate_limit: None,
         })
     }
 
@@ -202,6 +206,10 @@ impl VLLMClient {
         let model_infos = models_response.data.into_iter()
             .map(|model| {
                 // Try to get predefined model info, otherwise create basic info
+                let mut capabilities = vec![crate::llm::traits::ModelCapability::TextGeneration];
+                if super::config::is_vision_model(&model.id) {
+                    capabilities.push(crate::llm::traits::ModelCapability::Vision);
+                }
                 super::config::get_model_info(&model.id).unwrap_or_else(|| ModelInfo {
                     id: model.id.clone(),
                     name: model.id.clone(),
@@ -212,7 +220,7 @@ impl VLLMClient {
                     supports_function_calling: false,
                     cost_per_input_token: 0.0,
                     cost_per_output_token: 0.0,
-                    capabilities: vec![crate::llm::traits::ModelCapability::TextGeneration],
+                    capabilities,
                     parameter_restrictions: std::collections::HashMap::new(),
                 })
             })
@@ -388,6 +396,10 @@ impl LLMProviderClient for VLLMClient {
         models.iter().any(|m| m.id == model)
     }
 
+    fn supports_native_choices(&self, _model: &str) -> bool {
+        true
+    }
+
     fn get_config_requirements(&self) -> ProviderConfigRequirements {
         get_config_requirements()
     }
@@ -489,19 +501,27 @@ impl LLMProviderClient for VLLMClient {
 }
 
 impl CostCalculator for VLLMClient {
-    fn calculate_cost(&self, _usage: &crate::llm::TokenUsage, _model: &str) -> f64 {
-        0.0 // Local inference is free
+    fn calculate_cost(&self, usage: &crate::llm::TokenUsage, model: &str) -> f64 {
+        self.get_cost_breakdown(usage, model).total_cost
     }
 
-    fn estimate_cost(&self, _input_tokens: u32, _estimated_output_tokens: u32, _model: &str) -> f64 {
-        0.0 // Local inference is free
+    fn estimate_cost(&self, input_tokens: u32, estimated_output_tokens: u32, model: &str) -> f64 {
+        // Local inference is free unless the price catalog assigns it an internal cost
+        crate::llm::PriceCatalog::global()
+            .price(&LLMProviderType::VLLM, model)
+            .map_or(0.0, |price| price.cost(input_tokens, estimated_output_tokens))
     }
 
-    fn get_cost_breakdown(&self, _usage: &crate::llm::TokenUsage, _model: &str) -> CostBreakdown {
+    fn get_cost_breakdown(&self, usage: &crate::llm::TokenUsage, model: &str) -> CostBreakdown {
+        let (input_cost, output_cost) = crate::llm::PriceCatalog::global()
+            .cost_per_token(&LLMProviderType::VLLM, model)
+            .unwrap_or((0.0, 0.0));
+        let input_cost = usage.prompt_tokens as f64 * input_cost;
+        let output_cost = usage.completion_tokens as f64 * output_cost;
         CostBreakdown {
-            input_cost: 0.0,
-            output_cost: 0.0,
-            total_cost: 0.0,
+            input_cost,
+            output_cost,
+            total_cost: input_cost + output_cost,
             currency: "USD".to_string(),
         }
     }
@@ -174,6 +174,14 @@ pub fn is_embedding_model(model: &str) -> bool {
     model.to_lowercase().contains("sentence-transformers")
 }
 
+/// Check if a model accepts image input, e.g. LLaVA, Qwen2-VL or Pixtral
+pub fn is_vision_model(model: &str) -> bool {
+    let model = model.to_lowercase();
+    ["llava", "vision", "-vl", "pixtral", "internvl", "paligemma"]
+        .iter()
+        .any(|pattern| model.contains(pattern))
+}
+
 /// Get recommended models for different use cases
 pub fn get_recommended_models() -> HashMap<&'static str, Vec<&'static str>> {
     let mut recommendations = HashMap::new();
@@ -222,6 +230,10 @@ mod tests {
         
         assert!(is_embedding_model("sentence-transformers/all-MiniLM-L6-v2"));
         assert!(!is_embedding_model("microsoft/DialoGPT-medium"));
+
+        assert!(is_vision_model("Qwen/Qwen2-VL-7B-Instruct"));
+        assert!(is_vision_model("llava-hf/llava-1.5-7b-hf"));
+        assert!(!is_vision_model("meta-llama/Llama-2-7b-chat-hf"));
     }
 
     #[test]
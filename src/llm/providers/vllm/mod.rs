@@ -8,8 +8,8 @@ pub mod types;
 pub use client::VLLMClient;
 pub use config::{
     get_config_requirements, get_default_config, get_default_models, get_model_info,
-    get_recommended_models, is_code_model, is_embedding_model, model_supports_capability,
-    VLLMConfig,
+    get_recommended_models, is_code_model, is_embedding_model, is_vision_model,
+    model_supports_capability, VLLMConfig,
 };
 pub use types::{
     VLLMChatMessage, VLLMChoice, VLLMEmbedding, VLLMEmbeddingsRequest, VLLMEmbeddingsResponse,
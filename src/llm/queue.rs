@@ -0,0 +1,342 @@
+//! Priority Request Queue
+//!
+//! A bounded admission queue in front of the provider calls made by the router. At most
+//! `max_in_flight` requests are dispatched at once; when that limit is reached, further
+//! requests wait in a per-priority queue and are dispatched highest priority first, so
+//! interactive chat traffic overtakes background agent and function traffic.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+use super::{LLMError, LLMRequest, LLMResult};
+
+/// Metadata key on `LLMRequest` carrying the request priority
+pub const PRIORITY_METADATA_KEY: &str = "priority";
+
+/// Priority class of a request
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestPriority {
+    /// User-facing chat traffic
+    Interactive,
+    /// Background agent, function and batch traffic
+    #[default]
+    Batch,
+}
+
+impl RequestPriority {
+    /// Parse a priority name (`interactive` or `batch`)
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "interactive" => Some(RequestPriority::Interactive),
+            "batch" => Some(RequestPriority::Batch),
+            _ => None,
+        }
+    }
+
+    /// Priority stored in the request metadata, defaulting to `Batch`
+    pub fn from_request(request: &LLMRequest) -> Self {
+        request
+            .metadata
+            .get(PRIORITY_METADATA_KEY)
+            .and_then(|value| value.as_str())
+            .and_then(Self::parse)
+            .unwrap_or_default()
+    }
+
+    /// Store this priority in the request metadata
+    pub fn apply_to(self, request: &mut LLMRequest) {
+        request.metadata.insert(
+            PRIORITY_METADATA_KEY.to_string(),
+            serde_json::Value::String(self.to_string()),
+        );
+    }
+}
+
+impl std::fmt::Display for RequestPriority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RequestPriority::Interactive => write!(f, "interactive"),
+            RequestPriority::Batch => write!(f, "batch"),
+        }
+    }
+}
+
+/// Request queue configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestQueueConfig {
+    /// Whether requests are admitted through the queue at all
+    pub enabled: bool,
+    /// Maximum number of provider calls dispatched concurrently
+    pub max_in_flight: usize,
+    /// Maximum number of waiting interactive requests
+    pub max_queued_interactive: usize,
+    /// Maximum number of waiting batch requests
+    pub max_queued_batch: usize,
+}
+
+impl Default for RequestQueueConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_in_flight: 64,
+            max_queued_interactive: 256,
+            max_queued_batch: 1024,
+        }
+    }
+}
+
+/// Point-in-time queue statistics
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueueStats {
+    pub in_flight: usize,
+    pub queued_interactive: usize,
+    pub queued_batch: usize,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    in_flight: usize,
+    interactive: VecDeque<oneshot::Sender<()>>,
+    batch: VecDeque<oneshot::Sender<()>>,
+}
+
+impl QueueState {
+    fn waiters(&mut self, priority: RequestPriority) -> &mut VecDeque<oneshot::Sender<()>> {
+        match priority {
+            RequestPriority::Interactive => &mut self.interactive,
+            RequestPriority::Batch => &mut self.batch,
+        }
+    }
+
+    /// Whether a request of `priority` would have to wait behind queued requests
+    fn has_waiters_at_or_above(&self, priority: RequestPriority) -> bool {
+        match priority {
+            RequestPriority::Interactive => !self.interactive.is_empty(),
+            RequestPriority::Batch => !self.interactive.is_empty() || !self.batch.is_empty(),
+        }
+    }
+}
+
+/// Bounded priority admission queue
+#[derive(Debug, Clone)]
+pub struct PriorityRequestQueue {
+    config: RequestQueueConfig,
+    state: Arc<Mutex<QueueState>>,
+}
+
+impl PriorityRequestQueue {
+    pub fn new(config: RequestQueueConfig) -> Self {
+        Self {
+            config,
+            state: Arc::new(Mutex::new(QueueState::default())),
+        }
+    }
+
+    /// Wait for a dispatch slot
+    ///
+    /// Fails with `LLMError::RateLimitExceeded` when the queue for `priority` is full.
+    pub async fn acquire(&self, priority: RequestPriority) -> LLMResult<QueuePermit> {
+        if !self.config.enabled {
+            return Ok(QueuePermit { state: None });
+        }
+
+        let receiver = {
+            let mut state = self
+                .state
+                .lock()
+                .map_err(|_| LLMError::Internal("Request queue lock poisoned".to_string()))?;
+
+            if state.in_flight < self.config.max_in_flight.max(1)
+                && !state.has_waiters_at_or_above(priority)
+            {
+                state.in_flight += 1;
+                return Ok(QueuePermit {
+                    state: Some(self.state.clone()),
+                });
+            }
+
+            let capacity = match priority {
+                RequestPriority::Interactive => self.config.max_queued_interactive,
+                RequestPriority::Batch => self.config.max_queued_batch,
+            };
+            let waiters = state.waiters(priority);
+            if waiters.len() >= capacity {
+                return Err(LLMError::RateLimitExceeded(format!(
+                    "Request queue full for {} priority ({} waiting)",
+                    priority,
+                    waiters.len()
+                )));
+            }
+
+            let (sender, receiver) = oneshot::channel();
+            waiters.push_back(sender);
+            receiver
+        };
+
+        let mut waiter = Waiter {
+            receiver,
+            state: self.state.clone(),
+            granted: false,
+        };
+        (&mut waiter.receiver)
+            .await
+            .map_err(|_| LLMError::Internal("Request queue dropped waiter".to_string()))?;
+        waiter.granted = true;
+
+        Ok(QueuePermit {
+            state: Some(self.state.clone()),
+        })
+    }
+
+    /// Current queue statistics
+    pub fn stats(&self) -> QueueStats {
+        self.state
+            .lock()
+            .map(|state| QueueStats {
+                in_flight: state.in_flight,
+                queued_interactive: state.interactive.len(),
+                queued_batch: state.batch.len(),
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Hand the slot to the highest priority live waiter, or free it
+fn release(state: &Mutex<QueueState>) {
+    let Ok(mut state) = state.lock() else {
+        return;
+    };
+
+    while let Some(waiter) = state
+        .interactive
+        .pop_front()
+        .or_else(|| state.batch.pop_front())
+    {
+        if waiter.send(()).is_ok() {
+            return;
+        }
+    }
+    state.in_flight = state.in_flight.saturating_sub(1);
+}
+
+/// A queued request that has not been dispatched yet
+struct Waiter {
+    receiver: oneshot::Receiver<()>,
+    state: Arc<Mutex<QueueState>>,
+    granted: bool,
+}
+
+impl Drop for Waiter {
+    fn drop(&mut self) {
+        // A cancelled waiter may have been handed a slot just before it was dropped
+        if !self.granted {
+            self.receiver.close();
+            if self.receiver.try_recv().is_ok() {
+                release(&self.state);
+            }
+        }
+    }
+}
+
+/// A dispatch slot; dropping it admits the next queued request
+pub struct QueuePermit {
+    state: Option<Arc<Mutex<QueueState>>>,
+}
+
+impl Drop for QueuePermit {
+    fn drop(&mut self) {
+        if let Some(state) = self.state.take() {
+            release(&state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn queue(max_in_flight: usize, max_queued: usize) -> PriorityRequestQueue {
+        PriorityRequestQueue::new(RequestQueueConfig {
+            enabled: true,
+            max_in_flight,
+            max_queued_interactive: max_queued,
+            max_queued_batch: max_queued,
+        })
+    }
+
+    #[test]
+    fn test_parse_priority() {
+        assert_eq!(
+            RequestPriority::parse(" Interactive "),
+            Some(RequestPriority::Interactive)
+        );
+        assert_eq!(
+            RequestPriority::parse("batch"),
+            Some(RequestPriority::Batch)
+        );
+        assert_eq!(RequestPriority::parse("urgent"), None);
+    }
+
+    #[tokio::test]
+    async fn test_interactive_dispatched_before_batch() {
+        let queue = queue(1, 8);
+        let permit = queue.acquire(RequestPriority::Batch).await.unwrap();
+
+        let (order_tx, mut order_rx) = tokio::sync::mpsc::unbounded_channel();
+        let batch = {
+            let (queue, order_tx) = (queue.clone(), order_tx.clone());
+            tokio::spawn(async move {
+                let _permit = queue.acquire(RequestPriority::Batch).await.unwrap();
+                order_tx.send(RequestPriority::Batch).unwrap();
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let interactive = {
+            let queue = queue.clone();
+            tokio::spawn(async move {
+                let _permit = queue.acquire(RequestPriority::Interactive).await.unwrap();
+                order_tx.send(RequestPriority::Interactive).unwrap();
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(queue.stats().queued_batch, 1);
+        assert_eq!(queue.stats().queued_interactive, 1);
+
+        drop(permit);
+        batch.await.unwrap();
+        interactive.await.unwrap();
+
+        assert_eq!(order_rx.recv().await, Some(RequestPriority::Interactive));
+        assert_eq!(order_rx.recv().await, Some(RequestPriority::Batch));
+        assert_eq!(queue.stats(), QueueStats::default());
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_rejects() {
+        let queue = queue(1, 0);
+        let _permit = queue.acquire(RequestPriority::Interactive).await.unwrap();
+        let result = queue.acquire(RequestPriority::Interactive).await;
+        assert!(matches!(result, Err(LLMError::RateLimitExceeded(_))));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_waiter_releases_slot() {
+        let queue = queue(1, 8);
+        let permit = queue.acquire(RequestPriority::Batch).await.unwrap();
+
+        let waiting = tokio::time::timeout(
+            Duration::from_millis(10),
+            queue.acquire(RequestPriority::Batch),
+        )
+        .await;
+        assert!(waiting.is_err());
+
+        drop(permit);
+        assert_eq!(queue.stats().in_flight, 0);
+        let _permit = queue.acquire(RequestPriority::Batch).await.unwrap();
+    }
+}
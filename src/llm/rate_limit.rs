@@ -0,0 +1,276 @@
+//! Rate-Limit Aware Routing
+//!
+//! Providers report their remaining request and token quota in response headers
+//! (`x-ratelimit-*` for OpenAI and OpenAI-compatible APIs such as Groq,
+//! `anthropic-ratelimit-*` for Anthropic). The router records the latest quota per
+//! provider here and shifts traffic away from a provider once it gets close to its
+//! limit, instead of waiting for it to start returning 429s.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use super::traits::KeyQuota;
+use super::LLMProviderType;
+
+/// Rate-limit aware routing configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    /// Whether reported quota influences routing at all
+    pub enabled: bool,
+    /// Avoid a provider once its remaining requests or tokens fall to this fraction
+    /// of the limit
+    pub min_remaining_fraction: f64,
+    /// How long a 429 keeps a provider marked exhausted
+    pub exhausted_cooldown_secs: u64,
+    /// Quota observations older than this are ignored
+    pub max_observation_age_secs: u64,
+    /// Also move requests for a concrete model to a comparable model on another
+    /// provider. Virtual models are always resolved away from constrained providers.
+    pub reroute_concrete_models: bool,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_remaining_fraction: 0.05,
+            exhausted_cooldown_secs: 30,
+            max_observation_age_secs: 60,
+            reroute_concrete_models: false,
+        }
+    }
+}
+
+/// Latest quota observed for a provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderQuota {
+    pub quota: KeyQuota,
+    pub observed_at: DateTime<Utc>,
+    /// When the request quota resets, if the provider said
+    pub resets_at: Option<DateTime<Utc>>,
+    /// Set when the provider answered with a 429
+    pub exhausted: bool,
+}
+
+impl ProviderQuota {
+    /// Why traffic should avoid this provider at `now`, if it should
+    fn constraint(&self, config: &RateLimitConfig, now: DateTime<Utc>) -> Option<String> {
+        if self.resets_at.is_some_and(|resets_at| now >= resets_at) {
+            return None;
+        }
+
+        if self.exhausted {
+            let cooldown_end =
+                self.observed_at + Duration::seconds(config.exhausted_cooldown_secs as i64);
+            return (now < cooldown_end || self.resets_at.is_some())
+                .then(|| "Provider returned 429 Too Many Requests".to_string());
+        }
+
+        if now - self.observed_at > Duration::seconds(config.max_observation_age_secs as i64) {
+            return None;
+        }
+
+        let near_limit = |remaining: Option<u64>, limit: Option<u64>| match (remaining, limit) {
+            (Some(0), _) => true,
+            (Some(remaining), Some(limit)) if limit > 0 => {
+                (remaining as f64) / (limit as f64) <= config.min_remaining_fraction
+            }
+            _ => false,
+        };
+
+        if near_limit(self.quota.requests_remaining, self.quota.requests_limit) {
+            return Some(format!(
+                "Near request rate limit ({} of {} remaining)",
+                self.quota.requests_remaining.unwrap_or_default(),
+                describe_limit(self.quota.requests_limit)
+            ));
+        }
+        if near_limit(self.quota.tokens_remaining, self.quota.tokens_limit) {
+            return Some(format!(
+                "Near token rate limit ({} of {} remaining)",
+                self.quota.tokens_remaining.unwrap_or_default(),
+                describe_limit(self.quota.tokens_limit)
+            ));
+        }
+        None
+    }
+}
+
+fn describe_limit(limit: Option<u64>) -> String {
+    limit.map_or_else(|| "unknown".to_string(), |limit| limit.to_string())
+}
+
+/// Parse a quota reset value: an RFC 3339 timestamp (Anthropic) or a duration such as
+/// `1s`, `6m0s` or `120ms` (OpenAI)
+pub fn parse_reset(value: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Some(timestamp.with_timezone(&Utc));
+    }
+
+    let mut total_ms = 0f64;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let amount: f64 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let unit_ms = match &rest[..unit_len] {
+            "ms" => 1.0,
+            "s" | "" => 1000.0,
+            "m" => 60_000.0,
+            "h" => 3_600_000.0,
+            _ => return None,
+        };
+        rest = &rest[unit_len..];
+        total_ms += amount * unit_ms;
+    }
+
+    Some(now + Duration::milliseconds(total_ms.round() as i64))
+}
+
+/// Per-provider quota tracker shared by the router
+#[derive(Debug, Clone)]
+pub struct RateLimitTracker {
+    config: RateLimitConfig,
+    quotas: Arc<RwLock<HashMap<LLMProviderType, ProviderQuota>>>,
+}
+
+impl RateLimitTracker {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            quotas: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub fn config(&self) -> &RateLimitConfig {
+        &self.config
+    }
+
+    /// Record the quota a provider reported with a response
+    pub fn record(&self, provider: &LLMProviderType, quota: &KeyQuota) {
+        let now = Utc::now();
+        let observation = ProviderQuota {
+            resets_at: quota
+                .requests_reset
+                .as_deref()
+                .and_then(|reset| parse_reset(reset, now)),
+            quota: quota.clone(),
+            observed_at: now,
+            exhausted: false,
+        };
+        if let Ok(mut quotas) = self.quotas.write() {
+            quotas.insert(provider.clone(), observation);
+        }
+    }
+
+    /// Record that a provider rejected a request with a 429
+    pub fn record_exhausted(&self, provider: &LLMProviderType) {
+        let now = Utc::now();
+        if let Ok(mut quotas) = self.quotas.write() {
+            let entry = quotas
+                .entry(provider.clone())
+                .or_insert_with(|| ProviderQuota {
+                    quota: KeyQuota::default(),
+                    observed_at: now,
+                    resets_at: None,
+                    exhausted: true,
+                });
+            entry.exhausted = true;
+            entry.observed_at = now;
+            // A reset time from an earlier observation may already have passed
+            if entry.resets_at.is_some_and(|resets_at| resets_at <= now) {
+                entry.resets_at = None;
+            }
+        }
+    }
+
+    /// Why traffic should avoid `provider` right now, if it should
+    pub fn constraint(&self, provider: &LLMProviderType) -> Option<String> {
+        if !self.config.enabled {
+            return None;
+        }
+        let quotas = self.quotas.read().ok()?;
+        quotas
+            .get(provider)
+            .and_then(|quota| quota.constraint(&self.config, Utc::now()))
+    }
+
+    /// Latest quota observed for every provider
+    pub fn snapshot(&self) -> HashMap<LLMProviderType, ProviderQuota> {
+        self.quotas
+            .read()
+            .map(|quotas| quotas.clone())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quota(requests_remaining: u64, requests_limit: u64) -> KeyQuota {
+        KeyQuota {
+            requests_limit: Some(requests_limit),
+            requests_remaining: Some(requests_remaining),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_reset_formats() {
+        let now = Utc::now();
+        assert_eq!(parse_reset("6m0s", now), Some(now + Duration::seconds(360)));
+        assert_eq!(
+            parse_reset("120ms", now),
+            Some(now + Duration::milliseconds(120))
+        );
+        assert_eq!(
+            parse_reset("1.5s", now),
+            Some(now + Duration::milliseconds(1500))
+        );
+        assert_eq!(
+            parse_reset("2024-01-15T10:30:00Z", now).map(|t| t.to_rfc3339()),
+            Some("2024-01-15T10:30:00+00:00".to_string())
+        );
+        assert_eq!(parse_reset("soon", now), None);
+    }
+
+    #[test]
+    fn test_provider_near_limit_is_avoided() {
+        let tracker = RateLimitTracker::new(RateLimitConfig::default());
+        let provider = LLMProviderType::OpenAI;
+        assert_eq!(tracker.constraint(&provider), None);
+
+        tracker.record(&provider, &quota(400, 500));
+        assert_eq!(tracker.constraint(&provider), None);
+
+        tracker.record(&provider, &quota(10, 500));
+        assert!(tracker
+            .constraint(&provider)
+            .unwrap()
+            .contains("request rate limit"));
+    }
+
+    #[test]
+    fn test_exhausted_provider_recovers_after_reset() {
+        let tracker = RateLimitTracker::new(RateLimitConfig::default());
+        let provider = LLMProviderType::Anthropic;
+
+        tracker.record_exhausted(&provider);
+        assert!(tracker.constraint(&provider).is_some());
+
+        let mut recovered = quota(0, 50);
+        recovered.requests_reset = Some("2000-01-01T00:00:00Z".to_string());
+        tracker.record(&provider, &recovered);
+        assert_eq!(tracker.constraint(&provider), None);
+    }
+}
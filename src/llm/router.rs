@@ -3,8 +3,18 @@
 //! This module implements a router that uses the new modular provider architecture
 //! with support for multiple providers and proper API key management.
 
+use super::context::{self, ContextOverflowPolicy, ContextWindowConfig};
+use super::embeddings;
+use super::middleware::RouterMiddleware;
 use super::providers;
-use super::traits::LLMProviderClient;
+use super::queue::{PriorityRequestQueue, QueueStats, RequestPriority, RequestQueueConfig};
+use super::rate_limit::{ProviderQuota, RateLimitConfig, RateLimitTracker};
+use super::sampling;
+use super::structured;
+use super::tenant::{TenantId, TenantPolicyStore, TenantRoutingPolicy};
+use super::tokenizer;
+use super::trace::{RoutingAttempt, RoutingCandidate, RoutingTrace, RoutingTraceStore};
+use super::traits::{KeyValidation, LLMProviderClient};
 use super::*;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -12,6 +22,9 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{debug, error, info, warn};
 
+/// Comma-separated extra endpoints provider keys may be validated against, e.g. proxies
+pub const PROVIDER_URL_ALLOWLIST_ENV: &str = "CIRCUIT_BREAKER_PROVIDER_URL_ALLOWLIST";
+
 /// Provider health status tracking
 #[derive(Debug, Clone)]
 pub struct ProviderHealthStatus {
@@ -40,6 +53,11 @@ pub struct LLMRouterConfig {
     pub health_check_interval_seconds: u64,
     pub enable_cost_tracking: bool,
     pub enable_health_monitoring: bool,
+    pub context_window: ContextWindowConfig,
+    pub request_queue: RequestQueueConfig,
+    /// Record a routing trace for every request
+    pub record_routing_traces: bool,
+    pub rate_limits: RateLimitConfig,
 }
 
 impl Default for LLMRouterConfig {
@@ -50,6 +68,10 @@ impl Default for LLMRouterConfig {
             health_check_interval_seconds: 300, // 5 minutes
             enable_cost_tracking: true,
             enable_health_monitoring: true,
+            context_window: ContextWindowConfig::default(),
+            request_queue: RequestQueueConfig::default(),
+            record_routing_traces: true,
+            rate_limits: RateLimitConfig::default(),
         }
     }
 }
@@ -60,6 +82,11 @@ pub struct LLMRouter {
     providers: HashMap<LLMProviderType, Box<dyn LLMProviderClient>>,
     health_status: Arc<RwLock<HashMap<LLMProviderType, ProviderHealthStatus>>>,
     configured_api_keys: HashMap<LLMProviderType, String>,
+    tenant_policies: TenantPolicyStore,
+    request_queue: PriorityRequestQueue,
+    middleware: Vec<Arc<dyn RouterMiddleware>>,
+    routing_traces: RoutingTraceStore,
+    rate_limits: RateLimitTracker,
 }
 
 impl LLMRouter {
@@ -76,10 +103,15 @@ impl LLMRouter {
         let configured_api_keys = HashMap::new();
 
         Ok(Self {
-            config,
             providers,
             health_status: Arc::new(RwLock::new(health_status)),
             configured_api_keys,
+            tenant_policies: TenantPolicyStore::default(),
+            request_queue: PriorityRequestQueue::new(config.request_queue.clone()),
+            middleware: Vec::new(),
+            routing_traces: RoutingTraceStore::global(),
+            rate_limits: RateLimitTracker::new(config.rate_limits.clone()),
+            config,
         })
     }
 
@@ -174,17 +206,156 @@ impl LLMRouter {
         }
 
         Ok(Self {
-            config,
             providers,
             health_status: Arc::new(RwLock::new(health_status)),
             configured_api_keys,
+            tenant_policies: TenantPolicyStore::default(),
+            request_queue: PriorityRequestQueue::new(config.request_queue.clone()),
+            middleware: Vec::new(),
+            routing_traces: RoutingTraceStore::global(),
+            rate_limits: RateLimitTracker::new(config.rate_limits.clone()),
+            config,
         })
     }
 
+    /// Replace the router configuration
+    pub fn with_config(mut self, config: LLMRouterConfig) -> Self {
+        self.request_queue = PriorityRequestQueue::new(config.request_queue.clone());
+        self.rate_limits = RateLimitTracker::new(config.rate_limits.clone());
+        self.config = config;
+        self
+    }
+
+    /// Current request queue statistics
+    pub fn queue_stats(&self) -> QueueStats {
+        self.request_queue.stats()
+    }
+
+    /// Latest rate-limit quota reported by each provider
+    pub fn rate_limit_status(&self) -> HashMap<LLMProviderType, ProviderQuota> {
+        self.rate_limits.snapshot()
+    }
+
+    /// Rate-limit tracker fed by provider responses
+    pub fn rate_limits(&self) -> &RateLimitTracker {
+        &self.rate_limits
+    }
+
+    /// Get the router configuration
+    pub fn config(&self) -> &LLMRouterConfig {
+        &self.config
+    }
+
+    /// Register a middleware; it runs after the ones already registered
+    pub fn with_middleware(mut self, middleware: Arc<dyn RouterMiddleware>) -> Self {
+        self.add_middleware(middleware);
+        self
+    }
+
+    /// Register a middleware; it runs after the ones already registered
+    pub fn add_middleware(&mut self, middleware: Arc<dyn RouterMiddleware>) {
+        debug!("Registered router middleware: {}", middleware.name());
+        self.middleware.push(middleware);
+    }
+
+    /// Replace the routing trace store (defaults to the process-wide store)
+    pub fn with_routing_traces(mut self, routing_traces: RoutingTraceStore) -> Self {
+        self.routing_traces = routing_traces;
+        self
+    }
+
+    /// Routing trace store
+    pub fn routing_traces(&self) -> &RoutingTraceStore {
+        &self.routing_traces
+    }
+
+    /// Routing trace recorded for a request
+    pub async fn routing_trace(&self, request_id: &Uuid) -> Option<RoutingTrace> {
+        self.routing_traces.get(request_id).await
+    }
+
+    /// Record a guardrail action taken outside the router in the request's trace
+    pub async fn record_guardrail(&self, request: &LLMRequest, action: impl Into<String>) {
+        self.trace(request, |trace| trace.guardrail(action)).await;
+    }
+
+    /// Record routing decisions in the request's trace
+    async fn trace<F>(&self, request: &LLMRequest, f: F)
+    where
+        F: FnOnce(&mut RoutingTrace),
+    {
+        self.trace_id(request.id, &request.model, f).await;
+    }
+
+    async fn trace_id<F>(&self, request_id: Uuid, requested_model: &str, f: F)
+    where
+        F: FnOnce(&mut RoutingTrace),
+    {
+        if self.config.record_routing_traces {
+            self.routing_traces
+                .update(request_id, requested_model, f)
+                .await;
+        }
+    }
+
+    /// Run every middleware's pre-request hook in registration order
+    async fn run_pre_request(&self, request: &mut LLMRequest) -> LLMResult<()> {
+        for middleware in &self.middleware {
+            if let Err(e) = middleware.pre_request(request).await {
+                debug!("Middleware '{}' rejected request: {}", middleware.name(), e);
+                self.run_on_error(request, &e).await;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Run every middleware's post-response hook in reverse registration order
+    async fn run_post_response(
+        &self,
+        request: &LLMRequest,
+        response: &mut LLMResponse,
+    ) -> LLMResult<()> {
+        for middleware in self.middleware.iter().rev() {
+            if let Err(e) = middleware.post_response(request, response).await {
+                debug!(
+                    "Middleware '{}' rejected response: {}",
+                    middleware.name(),
+                    e
+                );
+                self.run_on_error(request, &e).await;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Run every middleware's error hook in reverse registration order
+    async fn run_on_error(&self, request: &LLMRequest, error: &LLMError) {
+        for middleware in self.middleware.iter().rev() {
+            middleware.on_error(request, error).await;
+        }
+    }
+
     /// Route a chat completion request to the appropriate provider
-    pub async fn chat_completion(&self, request: LLMRequest) -> LLMResult<LLMResponse> {
+    pub async fn chat_completion(&self, mut request: LLMRequest) -> LLMResult<LLMResponse> {
+        self.run_pre_request(&mut request).await?;
+
+        match self.route_chat_completion(request.clone()).await {
+            Ok(mut response) => {
+                self.run_post_response(&request, &mut response).await?;
+                Ok(response)
+            }
+            Err(e) => {
+                self.run_on_error(&request, &e).await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn route_chat_completion(&self, request: LLMRequest) -> LLMResult<LLMResponse> {
         // Resolve virtual model to actual model
-        let resolved_model = self.resolve_virtual_model(&request.model);
+        let resolved_model = self.resolve_virtual_model_traced(&request, None).await;
         let provider_type = self.determine_provider_for_model(&resolved_model);
 
         debug!(
@@ -192,25 +363,68 @@ impl LLMRouter {
             request.model, resolved_model, provider_type
         );
 
-        let provider_client = self.providers.get(&provider_type).ok_or_else(|| {
-            LLMError::Internal(format!("Provider {} not available", provider_type))
-        })?;
-
-        let api_key = self.get_api_key(&provider_type).await?;
-
         // Create modified request with resolved model name
         let mut resolved_request = request.clone();
         resolved_request.model = resolved_model.clone();
 
+        // Move off a provider that is about to start rejecting requests
+        let (resolved_request, provider_type) = self
+            .avoid_rate_limited_provider(resolved_request, provider_type)
+            .await;
+
+        // Make sure the request fits the selected model's context window
+        let (resolved_request, provider_type) = self
+            .apply_context_policy_traced(resolved_request, provider_type)
+            .await?;
+
+        let Some(provider_client) = self.providers.get(&provider_type) else {
+            let error = LLMError::Internal(format!("Provider {} not available", provider_type));
+            self.trace(&request, |trace| {
+                trace.exclude(
+                    Some(provider_type.clone()),
+                    Some(resolved_request.model.clone()),
+                    error.to_string(),
+                )
+            })
+            .await;
+            return Err(error);
+        };
+
+        let api_key = self
+            .get_api_key_traced(&resolved_request, &provider_type)
+            .await?;
+        self.trace(&request, |trace| {
+            trace.select(&provider_type, &resolved_request.model)
+        })
+        .await;
+
+        // Wait for a dispatch slot; higher priority requests are admitted first
+        let _permit = self
+            .request_queue
+            .acquire(RequestPriority::from_request(&resolved_request))
+            .await?;
+
         let max_retries = self.config.max_retries;
         let mut retry_count = 0;
 
         while retry_count <= max_retries {
-            match provider_client
-                .chat_completion(&resolved_request, &api_key)
-                .await
-            {
+            let result =
+                sampling::complete(provider_client.as_ref(), &resolved_request, &api_key).await;
+            let attempt = RoutingAttempt {
+                provider: provider_type.clone(),
+                model: resolved_request.model.clone(),
+                success: result.is_ok(),
+                error: result.as_ref().err().map(|e| e.to_string()),
+            };
+            self.trace(&request, |trace| trace.attempts.push(attempt))
+                .await;
+
+            match result {
                 Ok(mut response) => {
+                    if let Some(quota) = &response.rate_limit {
+                        self.rate_limits.record(&provider_type, quota);
+                    }
+
                     // Update routing info
                     response.routing_info.latency_ms = 0; // TODO: Measure actual latency
                     response.routing_info.retry_count = retry_count;
@@ -222,12 +436,30 @@ impl LLMRouter {
                     // Update health status on success
                     self.update_health_success(&provider_type).await;
 
+                    // Providers enforce schemas with varying strictness; check the reply
+                    if let Some(format) = &resolved_request.response_format {
+                        let repaired = structured::enforce(&mut response, format)?;
+                        if repaired > 0 {
+                            self.trace(&request, |trace| {
+                                trace.guardrail(format!(
+                                    "Repaired malformed JSON in {} structured output choice(s)",
+                                    repaired
+                                ))
+                            })
+                            .await;
+                        }
+                    }
+
                     return Ok(response);
                 }
                 Err(e) => {
                     warn!("Request failed for provider {}: {}", provider_type, e);
                     retry_count += 1;
 
+                    if matches!(e, LLMError::RateLimitExceeded(_)) {
+                        self.rate_limits.record_exhausted(&provider_type);
+                    }
+
                     // Update health status on failure
                     self.update_health_failure(&provider_type, &e).await;
 
@@ -247,27 +479,82 @@ impl LLMRouter {
     }
 
     /// Route a streaming chat completion request
+    ///
+    /// Middleware pre-request and error hooks apply; post-response hooks do not run
+    /// for streamed responses. Structured output is validated on the whole reply, so a
+    /// request with a JSON `response_format` is rejected rather than streamed unchecked.
     pub async fn stream_chat_completion(
+        &self,
+        mut request: LLMRequest,
+    ) -> LLMResult<Box<dyn futures::Stream<Item = LLMResult<StreamingChunk>> + Send + Unpin>> {
+        if request
+            .response_format
+            .as_ref()
+            .is_some_and(structured::ResponseFormat::expects_json)
+        {
+            return Err(LLMError::InvalidRequest(
+                "response_format is not supported with stream: true".to_string(),
+            ));
+        }
+        self.run_pre_request(&mut request).await?;
+
+        match self.route_stream_chat_completion(request.clone()).await {
+            Ok(stream) => Ok(stream),
+            Err(e) => {
+                self.run_on_error(&request, &e).await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn route_stream_chat_completion(
         &self,
         request: LLMRequest,
     ) -> LLMResult<Box<dyn futures::Stream<Item = LLMResult<StreamingChunk>> + Send + Unpin>> {
         let provider = self.determine_provider_for_model(&request.model);
-        let api_key = self.get_api_key(&provider).await?;
+        let (request, provider) = self.avoid_rate_limited_provider(request, provider).await;
+        let (request, provider) = self.apply_context_policy_traced(request, provider).await?;
+        let api_key = self.get_api_key_traced(&request, &provider).await?;
 
         if let Some(client) = self.providers.get(&provider) {
-            let stream_result = client
-                .chat_completion_stream(request.clone(), api_key)
+            self.trace(&request, |trace| trace.select(&provider, &request.model))
+                .await;
+            let permit = self
+                .request_queue
+                .acquire(RequestPriority::from_request(&request))
+                .await?;
+            let stream_result = sampling::stream(client.as_ref(), request.clone(), api_key).await;
+            let attempt = RoutingAttempt {
+                provider: provider.clone(),
+                model: request.model.clone(),
+                success: stream_result.is_ok(),
+                error: stream_result.as_ref().err().map(|e| e.to_string()),
+            };
+            self.trace(&request, |trace| trace.attempts.push(attempt))
                 .await;
             match stream_result {
-                Ok(stream) => Ok(Box::new(Box::pin(stream))),
+                Ok(stream) => {
+                    // The queue slot is held until the stream ends or is dropped
+                    let stream = futures::stream::unfold(
+                        (stream, permit),
+                        |(mut stream, permit)| async move {
+                            let chunk = futures::StreamExt::next(&mut stream).await?;
+                            Some((chunk, (stream, permit)))
+                        },
+                    );
+                    Ok(Box::new(Box::pin(stream)))
+                }
                 Err(e) => {
                     error!("Router: provider returned error: {}", e);
+                    if matches!(e, LLMError::RateLimitExceeded(_)) {
+                        self.rate_limits.record_exhausted(&provider);
+                    }
                     Err(e)
                 }
             }
         } else {
             // For unsupported providers, fall back to mock streaming
-            let response = self.chat_completion(request).await?;
+            let response = self.route_chat_completion(request).await?;
 
             let chunk = StreamingChunk {
                 id: response.id,
@@ -284,6 +571,7 @@ impl LLMRouter {
                     })
                     .collect(),
                 provider: response.provider,
+                usage: Some(response.usage),
             };
 
             let stream = futures::stream::once(async move { Ok(chunk) });
@@ -291,8 +579,304 @@ impl LLMRouter {
         }
     }
 
+    /// Look up the context window for a model served by a provider
+    pub fn model_context_window(
+        &self,
+        provider_type: &LLMProviderType,
+        model: &str,
+    ) -> Option<u32> {
+        self.providers
+            .get(provider_type)?
+            .get_available_models()
+            .into_iter()
+            .find(|model_info| model_info.id == model)
+            .map(|model_info| model_info.context_window)
+    }
+
+    /// Check a request against the selected model's context window and apply the
+    /// configured overflow policy. Returns the (possibly rewritten) request together
+    /// with the provider that should serve it.
+    pub async fn apply_context_policy(
+        &self,
+        request: LLMRequest,
+        provider_type: LLMProviderType,
+    ) -> LLMResult<(LLMRequest, LLMProviderType)> {
+        let config = &self.config.context_window;
+        if !config.enabled {
+            return Ok((request, provider_type));
+        }
+
+        // Unknown models are passed through; the provider remains the source of truth
+        let Some(context_window) = self.model_context_window(&provider_type, &request.model) else {
+            return Ok((request, provider_type));
+        };
+
+        let estimated = tokenizer::count_prompt_tokens(&request.model, &request.messages);
+        let budget = config.prompt_budget(context_window, &request);
+        if estimated <= budget {
+            return Ok((request, provider_type));
+        }
+
+        warn!(
+            "Request {} needs ~{} prompt tokens but model '{}' allows {} (window {})",
+            request.id, estimated, request.model, budget, context_window
+        );
+
+        let exceeded = || {
+            LLMError::ContextLengthExceeded(format!(
+                "estimated {} prompt tokens exceeds the {} token budget of model '{}' (context window {})",
+                estimated, budget, request.model, context_window
+            ))
+        };
+
+        match &config.overflow_policy {
+            ContextOverflowPolicy::Reject => Err(exceeded()),
+            ContextOverflowPolicy::Reroute => {
+                let required = config.required_window(&request);
+                let (model, provider) = self
+                    .find_model_with_context_window(required, |_| true)
+                    .ok_or_else(exceeded)?;
+                info!(
+                    "Re-routing request {} from '{}' to '{}' ({}) for a larger context window",
+                    request.id, request.model, model, provider
+                );
+                let mut rerouted = request;
+                rerouted.model = model;
+                Ok((rerouted, provider))
+            }
+            ContextOverflowPolicy::TruncateOldest => {
+                let truncated = context::truncate_oldest(&request.model, &request.messages, budget)
+                    .ok_or_else(exceeded)?;
+                info!(
+                    "Truncated {} oldest messages from request {} to fit context window",
+                    truncated.dropped.len(),
+                    request.id
+                );
+                let mut shortened = request;
+                shortened.messages = truncated.kept;
+                Ok((shortened, provider_type))
+            }
+            ContextOverflowPolicy::Summarize { model } => {
+                // Leave room for the summary message we are about to insert
+                let truncated = context::truncate_oldest(
+                    &request.model,
+                    &request.messages,
+                    budget.saturating_sub(context::SUMMARY_RESERVE),
+                )
+                .ok_or_else(exceeded)?;
+                let summary_model = model.clone().unwrap_or_else(|| request.model.clone());
+                let mut shortened = request;
+                shortened.messages = truncated.kept;
+
+                match self
+                    .summarize_messages(&truncated.dropped, &summary_model)
+                    .await
+                {
+                    Ok(summary) => {
+                        info!(
+                            "Summarized {} oldest messages from request {} using '{}'",
+                            truncated.dropped.len(),
+                            shortened.id,
+                            summary_model
+                        );
+                        context::insert_summary(&mut shortened.messages, summary);
+                    }
+                    Err(e) => {
+                        warn!(
+                            "Summarization failed for request {}, falling back to truncation: {}",
+                            shortened.id, e
+                        );
+                    }
+                }
+                Ok((shortened, provider_type))
+            }
+        }
+    }
+
+    /// `apply_context_policy`, recording rejections and re-routes in the request's trace
+    async fn apply_context_policy_traced(
+        &self,
+        request: LLMRequest,
+        provider_type: LLMProviderType,
+    ) -> LLMResult<(LLMRequest, LLMProviderType)> {
+        let (request_id, model) = (request.id, request.model.clone());
+        let messages = request.messages.len();
+
+        let result = self
+            .apply_context_policy(request, provider_type.clone())
+            .await;
+        let reason = match &result {
+            Ok((request, _)) if request.model != model => format!(
+                "Prompt exceeds the context window; re-routed to '{}'",
+                request.model
+            ),
+            Ok((request, _)) if request.messages.len() != messages => {
+                self.trace_id(request_id, &model, |trace| {
+                    trace.guardrail(format!(
+                        "Shortened the prompt from {} to {} messages to fit the context window",
+                        messages,
+                        request.messages.len()
+                    ))
+                })
+                .await;
+                return result;
+            }
+            Ok(_) => return result,
+            Err(e) => e.to_string(),
+        };
+
+        self.trace_id(request_id, &model, |trace| {
+            trace.exclude(Some(provider_type), Some(model.clone()), reason)
+        })
+        .await;
+        result
+    }
+
+    /// `get_api_key`, recording a missing key in the request's trace
+    async fn get_api_key_traced(
+        &self,
+        request: &LLMRequest,
+        provider_type: &LLMProviderType,
+    ) -> LLMResult<String> {
+        let result = self.get_api_key(provider_type).await;
+        if let Err(e) = &result {
+            self.trace(request, |trace| {
+                trace.exclude(
+                    Some(provider_type.clone()),
+                    Some(request.model.clone()),
+                    e.to_string(),
+                )
+            })
+            .await;
+        }
+        result
+    }
+
+    /// Move a concrete-model request to the cheapest model on another provider with at
+    /// least the same context window when its provider is close to its rate limit. Only
+    /// applies when `reroute_concrete_models` is enabled; the request is returned
+    /// unchanged when no unconstrained provider has a suitable model.
+    async fn avoid_rate_limited_provider(
+        &self,
+        request: LLMRequest,
+        provider_type: LLMProviderType,
+    ) -> (LLMRequest, LLMProviderType) {
+        if !self.rate_limits.config().reroute_concrete_models {
+            return (request, provider_type);
+        }
+        let Some(reason) = self.rate_limits.constraint(&provider_type) else {
+            return (request, provider_type);
+        };
+
+        let required = self
+            .model_context_window(&provider_type, &request.model)
+            .unwrap_or_default();
+        let Some((model, provider)) = self.find_model_with_context_window(required, |provider| {
+            provider != &provider_type && self.rate_limits.constraint(provider).is_none()
+        }) else {
+            debug!(
+                "Provider {} is rate limited but no alternative serves request {}",
+                provider_type, request.id
+            );
+            return (request, provider_type);
+        };
+
+        info!(
+            "Re-routing request {} from '{}' to '{}' ({}): {}",
+            request.id, request.model, model, provider, reason
+        );
+        self.trace(&request, |trace| {
+            trace.exclude(Some(provider_type), Some(request.model.clone()), reason)
+        })
+        .await;
+
+        let mut rerouted = request;
+        rerouted.model = model;
+        (rerouted, provider)
+    }
+
+    /// Find the cheapest available model whose context window is at least `required` tokens,
+    /// among the providers accepted by `provider_filter`
+    fn find_model_with_context_window(
+        &self,
+        required: u32,
+        provider_filter: impl Fn(&LLMProviderType) -> bool,
+    ) -> Option<(String, LLMProviderType)> {
+        self.providers
+            .iter()
+            .filter(|(provider_type, _)| provider_filter(provider_type))
+            .flat_map(|(provider_type, client)| {
+                client
+                    .get_available_models()
+                    .into_iter()
+                    .map(move |model_info| (model_info, provider_type.clone()))
+            })
+            .filter(|(model_info, _)| {
+                model_info.context_window >= required
+                    && !crate::api::types::is_virtual_model(&model_info.id)
+            })
+            .min_by(|a, b| {
+                let cost_a = a.0.cost_per_input_token + a.0.cost_per_output_token;
+                let cost_b = b.0.cost_per_input_token + b.0.cost_per_output_token;
+                cost_a
+                    .partial_cmp(&cost_b)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .map(|(model_info, provider_type)| (model_info.id, provider_type))
+    }
+
+    /// Summarize messages with a direct provider call (bypasses context policies)
+    async fn summarize_messages(&self, messages: &[ChatMessage], model: &str) -> LLMResult<String> {
+        let provider_type = self.determine_provider_for_model(model);
+        let client = self.providers.get(&provider_type).ok_or_else(|| {
+            LLMError::Internal(format!("Provider {} not available", provider_type))
+        })?;
+        let api_key = self.get_api_key(&provider_type).await?;
+
+        let summary_request = context::build_summary_request(messages, model);
+        let response = client.chat_completion(&summary_request, &api_key).await?;
+        response
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message.content)
+            .ok_or_else(|| LLMError::Parse("Summary response contained no choices".to_string()))
+    }
+
     /// Resolve virtual model name to actual model name using smart routing
     pub fn resolve_virtual_model(&self, model: &str) -> String {
+        self.resolve_virtual_model_with_policy(model, None, None)
+    }
+
+    /// Resolve the request's model, recording the candidates and the choice in its trace
+    async fn resolve_virtual_model_traced(
+        &self,
+        request: &LLMRequest,
+        policy: Option<&TenantRoutingPolicy>,
+    ) -> String {
+        if !crate::api::types::is_virtual_model(&request.model) {
+            return request.model.clone();
+        }
+
+        if !self.config.record_routing_traces {
+            return self.resolve_virtual_model_with_policy(&request.model, policy, None);
+        }
+
+        let mut trace = RoutingTrace::new(request.id, request.model.clone());
+        let resolved =
+            self.resolve_virtual_model_with_policy(&request.model, policy, Some(&mut trace));
+        self.trace(request, |recorded| recorded.absorb(trace)).await;
+        resolved
+    }
+
+    /// Resolve a virtual model, restricted to the models a tenant policy allows and
+    /// using the policy's strategy in place of the virtual model's default
+    fn resolve_virtual_model_with_policy(
+        &self,
+        model: &str,
+        policy: Option<&TenantRoutingPolicy>,
+        mut trace: Option<&mut RoutingTrace>,
+    ) -> String {
         // Check if this is a virtual model
         if !crate::api::types::is_virtual_model(model) {
             return model.to_string();
@@ -317,18 +901,61 @@ impl LLMRouter {
             let models = client.get_available_models();
             for model_info in models {
                 // Skip virtual models, only include real provider models
-                if !crate::api::types::is_virtual_model(&model_info.id) {
+                if crate::api::types::is_virtual_model(&model_info.id) {
+                    continue;
+                }
+                if policy.is_none_or(|policy| policy.allows(provider_type, &model_info.id)) {
                     available_models.push((model_info, provider_type.clone()));
+                } else if let Some(trace) = trace.as_deref_mut() {
+                    trace.exclude(
+                        Some(provider_type.clone()),
+                        Some(model_info.id.clone()),
+                        "Not allowed by tenant routing policy",
+                    );
                 }
             }
         }
 
         if available_models.is_empty() {
+            if let Some(trace) = trace {
+                trace.exclude(None, None, "No models available from configured providers");
+            }
             return model.to_string(); // Return original if no models available
         }
 
+        // Leave out providers close to their rate limits, unless that would leave nothing
+        let constraints: HashMap<LLMProviderType, String> = self
+            .providers
+            .keys()
+            .filter_map(|provider_type| {
+                self.rate_limits
+                    .constraint(provider_type)
+                    .map(|reason| (provider_type.clone(), reason))
+            })
+            .collect();
+        let (open, constrained): (Vec<_>, Vec<_>) = available_models
+            .into_iter()
+            .partition(|(_, provider_type)| !constraints.contains_key(provider_type));
+        let available_models = if open.is_empty() {
+            constrained
+        } else {
+            if let Some(trace) = trace.as_deref_mut() {
+                for (model_info, provider_type) in &constrained {
+                    trace.exclude(
+                        Some(provider_type.clone()),
+                        Some(model_info.id.clone()),
+                        constraints[provider_type].clone(),
+                    );
+                }
+            }
+            open
+        };
+
         // Apply routing strategy to select best model
-        let selected_model = match virtual_model_def.strategy {
+        let strategy = policy
+            .and_then(|policy| policy.strategy.clone())
+            .unwrap_or_else(|| virtual_model_def.strategy.clone());
+        let selected_model = match &strategy {
             crate::api::types::SmartRoutingStrategy::CostOptimized => {
                 // Select cheapest model
                 available_models
@@ -420,6 +1047,27 @@ impl LLMRouter {
 
         let selected = selected_model.unwrap_or_else(|| model.to_string());
 
+        if let Some(trace) = trace {
+            let scored = matches!(
+                strategy,
+                crate::api::types::SmartRoutingStrategy::CostOptimized
+                    | crate::api::types::SmartRoutingStrategy::Balanced
+            );
+            trace.candidates = available_models
+                .iter()
+                .map(|(model_info, provider_type)| RoutingCandidate {
+                    provider: provider_type.clone(),
+                    model: model_info.id.clone(),
+                    score: scored.then_some(
+                        model_info.cost_per_input_token + model_info.cost_per_output_token,
+                    ),
+                    selected: model_info.id == selected,
+                })
+                .collect();
+            trace.strategy = Some(strategy);
+            trace.resolved_model = Some(selected.clone());
+        }
+
         info!(
             "Resolved virtual model '{}' to real model '{}'",
             model, selected
@@ -427,6 +1075,63 @@ impl LLMRouter {
         selected
     }
 
+    /// Tenant routing policy store
+    pub fn tenant_policies(&self) -> &TenantPolicyStore {
+        &self.tenant_policies
+    }
+
+    /// Apply a tenant's routing policy to a request
+    ///
+    /// Virtual models are resolved with the tenant's strategy over the models it may use,
+    /// and concrete models outside the tenant's allowlist or provider set are rejected.
+    /// Requests from tenants without a policy are returned unchanged.
+    pub async fn apply_tenant_policy(
+        &self,
+        mut request: LLMRequest,
+        tenant: &TenantId,
+    ) -> LLMResult<LLMRequest> {
+        let Some(policy) = self.tenant_policies.get(tenant).await else {
+            return Ok(request);
+        };
+        self.trace(&request, |trace| trace.tenant_id = Some(tenant.to_string()))
+            .await;
+
+        if crate::api::types::is_virtual_model(&request.model) {
+            let resolved = self
+                .resolve_virtual_model_traced(&request, Some(&policy))
+                .await;
+            if crate::api::types::is_virtual_model(&resolved) {
+                return Err(LLMError::NotPermitted(format!(
+                    "No models available to tenant '{}' for '{}'",
+                    tenant, request.model
+                )));
+            }
+            debug!(
+                "Tenant '{}': resolved virtual model '{}' to '{}'",
+                tenant, request.model, resolved
+            );
+            request.model = resolved;
+        }
+
+        let provider_type = self.determine_provider_for_model(&request.model);
+        if !policy.allows(&provider_type, &request.model) {
+            self.trace(&request, |trace| {
+                trace.exclude(
+                    Some(provider_type.clone()),
+                    Some(request.model.clone()),
+                    "Not allowed by tenant routing policy",
+                )
+            })
+            .await;
+            return Err(LLMError::NotPermitted(format!(
+                "Model '{}' ({}) is not allowed for tenant '{}'",
+                request.model, provider_type, tenant
+            )));
+        }
+
+        Ok(request)
+    }
+
     /// Smart chat completion (for API handler compatibility)
     pub async fn smart_chat_completion(
         &self,
@@ -435,7 +1140,7 @@ impl LLMRouter {
     ) -> LLMResult<LLMResponse> {
         // Check if this is a virtual model that needs smart routing
         if crate::api::types::is_virtual_model(&request.model) {
-            let real_model = self.resolve_virtual_model(&request.model);
+            let real_model = self.resolve_virtual_model_traced(&request, None).await;
             let mut real_request = request.clone();
             real_request.model = real_model;
             self.chat_completion(real_request).await
@@ -452,7 +1157,7 @@ impl LLMRouter {
     ) -> LLMResult<Box<dyn futures::Stream<Item = LLMResult<StreamingChunk>> + Send + Unpin>> {
         // Check if this is a virtual model that needs smart routing
         if crate::api::types::is_virtual_model(&request.model) {
-            let real_model = self.resolve_virtual_model(&request.model);
+            let real_model = self.resolve_virtual_model_traced(&request, None).await;
             let mut real_request = request.clone();
             real_request.model = real_model;
             self.stream_chat_completion(real_request).await
@@ -575,6 +1280,97 @@ impl LLMRouter {
         health_map.get(provider_type).cloned()
     }
 
+    /// Endpoint the router uses for a provider
+    fn provider_base_url(&self, provider_type: &LLMProviderType) -> Option<String> {
+        match provider_type {
+            LLMProviderType::OpenAI => Some("https://api.openai.com/v1".to_string()),
+            LLMProviderType::Anthropic => Some("https://api.anthropic.com".to_string()),
+            LLMProviderType::Google => {
+                Some("https://generativelanguage.googleapis.com/v1beta".to_string())
+            }
+            LLMProviderType::Ollama => Some(
+                self.configured_api_keys
+                    .get(provider_type)
+                    .cloned()
+                    .unwrap_or_else(|| "http://localhost:11434".to_string()),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Whether a key may be sent to `base_url` for validation: the provider's own
+    /// endpoint, or one listed in `CIRCUIT_BREAKER_PROVIDER_URL_ALLOWLIST`
+    fn permits_base_url(&self, provider_type: &LLMProviderType, base_url: &str) -> bool {
+        let base_url = base_url.trim().trim_end_matches('/');
+        if self
+            .provider_base_url(provider_type)
+            .is_some_and(|url| url.trim_end_matches('/') == base_url)
+        {
+            return true;
+        }
+        std::env::var(PROVIDER_URL_ALLOWLIST_ENV)
+            .map(|allowlist| {
+                allowlist
+                    .split(',')
+                    .map(|url| url.trim().trim_end_matches('/'))
+                    .any(|url| !url.is_empty() && url == base_url)
+            })
+            .unwrap_or(false)
+    }
+
+    /// Validate an API key against a provider without storing it
+    ///
+    /// Uses the configured client for the provider when there is one (so custom base
+    /// URLs apply), otherwise a temporary client pointed at `base_url` or the default.
+    /// `base_url` must be the provider's endpoint or on the URL allowlist, so the key
+    /// cannot be sent to an arbitrary host.
+    pub async fn validate_provider_key(
+        &self,
+        provider_type: &LLMProviderType,
+        api_key: &str,
+        base_url: Option<String>,
+    ) -> LLMResult<KeyValidation> {
+        if let Some(url) = base_url.as_deref() {
+            if !self.permits_base_url(provider_type, url) {
+                return Err(LLMError::NotPermitted(format!(
+                    "Base URL '{}' is not an allowed endpoint for provider {}; add it to {}",
+                    url, provider_type, PROVIDER_URL_ALLOWLIST_ENV
+                )));
+            }
+        }
+
+        if base_url.is_none() {
+            if let Some(client) = self.providers.get(provider_type) {
+                return client.validate_api_key(api_key).await;
+            }
+        }
+
+        let client: Box<dyn LLMProviderClient> = match provider_type {
+            LLMProviderType::OpenAI => Box::new(providers::openai::create_client(
+                api_key.to_string(),
+                base_url,
+            )),
+            LLMProviderType::Anthropic => Box::new(providers::anthropic::create_client(
+                api_key.to_string(),
+                base_url,
+            )),
+            LLMProviderType::Google => Box::new(providers::google::create_client(
+                api_key.to_string(),
+                base_url,
+            )),
+            LLMProviderType::Ollama | LLMProviderType::VLLM => {
+                providers::create_provider_client(provider_type.clone(), base_url)
+            }
+            other => {
+                return Err(LLMError::ProviderNotFound(format!(
+                    "Key validation is not supported for provider {}",
+                    other
+                )))
+            }
+        };
+        client.validate_api_key(api_key).await
+    }
+
     /// Get all available providers
     pub fn get_available_providers(&self) -> Vec<LLMProviderType> {
         self.providers.keys().cloned().collect()
@@ -661,6 +1457,10 @@ impl LLMRouter {
                             crate::llm::traits::ModelCapability::ReasoningChain => {
                                 ModelCapability::Reasoning
                             }
+                            crate::llm::traits::ModelCapability::Vision
+                            | crate::llm::traits::ModelCapability::VisionInput => {
+                                ModelCapability::Vision
+                            }
                             _ => ModelCapability::TextGeneration,
                         })
                         .collect(),
@@ -671,19 +1471,7 @@ impl LLMRouter {
                 id: uuid::Uuid::new_v4(),
                 provider_type: provider_type.clone(),
                 name: format!("{:?}", provider_type),
-                base_url: match provider_type {
-                    LLMProviderType::OpenAI => "https://api.openai.com/v1".to_string(),
-                    LLMProviderType::Anthropic => "https://api.anthropic.com".to_string(),
-                    LLMProviderType::Google => {
-                        "https://generativelanguage.googleapis.com/v1beta".to_string()
-                    }
-                    LLMProviderType::Ollama => self
-                        .configured_api_keys
-                        .get(provider_type)
-                        .cloned()
-                        .unwrap_or_else(|| "http://localhost:11434".to_string()),
-                    _ => "".to_string(),
-                },
+                base_url: self.provider_base_url(provider_type).unwrap_or_default(),
                 api_key_id: Some(format!("{}_key", provider_type.to_string())),
                 models: llm_models,
                 rate_limits: RateLimits::default(),
@@ -701,21 +1489,64 @@ impl LLMRouter {
     }
 
     /// Generate embeddings using the appropriate provider
+    ///
+    /// Inputs beyond the provider's batch size are split across several calls, and
+    /// requested `dimensions` are applied by shortening the vectors for models that
+    /// cannot do so themselves. An empty `api_key` uses the provider's configured key.
     pub async fn embeddings(
         &self,
         request: &crate::llm::EmbeddingsRequest,
         api_key: &str,
     ) -> LLMResult<crate::llm::EmbeddingsResponse> {
         let provider_type = self.determine_provider_for_model(&request.model);
-
-        if let Some(client) = self.providers.get(&provider_type) {
-            client.embeddings(request, api_key).await
-        } else {
-            Err(LLMError::Provider(format!(
+        let Some(client) = self.providers.get(&provider_type) else {
+            return Err(LLMError::Provider(format!(
                 "No provider available for model: {}",
                 request.model
-            )))
+            )));
+        };
+        let api_key = if api_key.is_empty() {
+            self.get_api_key(&provider_type).await.unwrap_or_default()
+        } else {
+            api_key.to_string()
+        };
+
+        let mut provider_request = request.clone();
+        if !embeddings::supports_native_dimensions(&provider_type, &request.model) {
+            provider_request.dimensions = None;
         }
+
+        let mut merged: Option<crate::llm::EmbeddingsResponse> = None;
+        for batch in request
+            .input
+            .batches(embeddings::max_batch_size(&provider_type))
+        {
+            provider_request.input = batch;
+            let mut response = client.embeddings(&provider_request, &api_key).await?;
+            match merged.as_mut() {
+                None => merged = Some(response),
+                Some(merged) => {
+                    let offset = merged.data.len() as u32;
+                    for data in &mut response.data {
+                        data.index += offset;
+                    }
+                    merged.data.append(&mut response.data);
+                    merged.usage.prompt_tokens += response.usage.prompt_tokens;
+                    merged.usage.total_tokens += response.usage.total_tokens;
+                    merged.usage.estimated_cost += response.usage.estimated_cost;
+                }
+            }
+        }
+        let mut response = merged.ok_or_else(|| {
+            LLMError::InvalidRequest("Embeddings input must not be empty".to_string())
+        })?;
+
+        if let Some(dimensions) = request.dimensions {
+            for data in &mut response.data {
+                embeddings::reduce_dimensions(&mut data.embedding, dimensions as usize)?;
+            }
+        }
+        Ok(response)
     }
 
     /// Run health checks on all providers
@@ -804,10 +1635,412 @@ mod tests {
             providers: HashMap::new(),
             health_status: Arc::new(RwLock::new(HashMap::new())),
             configured_api_keys: HashMap::new(),
+            tenant_policies: TenantPolicyStore::default(),
+            request_queue: PriorityRequestQueue::new(RequestQueueConfig::default()),
+            middleware: Vec::new(),
+            routing_traces: RoutingTraceStore::default(),
+            rate_limits: RateLimitTracker::new(RateLimitConfig::default()),
         };
 
         let display = format!("{}", router);
         assert!(display.contains("LLMRouter"));
         assert!(display.contains("0 providers"));
     }
+
+    fn oversized_request(model: &str) -> LLMRequest {
+        LLMRequest {
+            id: uuid::Uuid::new_v4(),
+            model: model.to_string(),
+            messages: vec![
+                ChatMessage {
+                    role: MessageRole::User,
+                    content: "word ".repeat(10_000),
+                    name: None,
+                    function_call: None,
+                    tool_calls: None,
+                    tool_call_id: None,
+                    content_parts: None,
+                },
+                ChatMessage {
+                    role: MessageRole::User,
+                    content: "What now?".to_string(),
+                    name: None,
+                    function_call: None,
+                    tool_calls: None,
+                    tool_call_id: None,
+                    content_parts: None,
+                },
+            ],
+            temperature: None,
+            max_tokens: None,
+            top_p: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            stop: None,
+            stream: None,
+            functions: None,
+            function_call: None,
+            user: None,
+            metadata: HashMap::new(),
+            response_format: None,
+            tool_choice: None,
+            n: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_context_policy_reject_and_reroute() {
+        let router = LLMRouter::new_with_keys(
+            Some("test-openai-key".to_string()),
+            Some("test-anthropic-key".to_string()),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        let openai_model = router
+            .get_provider_client(&LLMProviderType::OpenAI)
+            .unwrap()
+            .get_available_models()[0]
+            .id
+            .clone();
+
+        // The default OpenAI model has an 8k window, so a ~10k token prompt is rejected
+        let result = router
+            .apply_context_policy(oversized_request(&openai_model), LLMProviderType::OpenAI)
+            .await;
+        assert!(matches!(result, Err(LLMError::ContextLengthExceeded(_))));
+
+        // Re-routing picks the Anthropic model with the 200k window
+        let mut config = LLMRouterConfig::default();
+        config.context_window.overflow_policy = ContextOverflowPolicy::Reroute;
+        let router = router.with_config(config);
+        let (request, provider) = router
+            .apply_context_policy(oversized_request(&openai_model), LLMProviderType::OpenAI)
+            .await
+            .unwrap();
+        assert_eq!(provider, LLMProviderType::Anthropic);
+        assert_ne!(request.model, openai_model);
+    }
+
+    #[tokio::test]
+    async fn test_context_policy_truncate_oldest() {
+        let mut config = LLMRouterConfig::default();
+        config.context_window.overflow_policy = ContextOverflowPolicy::TruncateOldest;
+        let router =
+            LLMRouter::new_with_keys(Some("test-openai-key".to_string()), None, None, None)
+                .await
+                .unwrap()
+                .with_config(config);
+        let openai_model = router
+            .get_provider_client(&LLMProviderType::OpenAI)
+            .unwrap()
+            .get_available_models()[0]
+            .id
+            .clone();
+
+        let (request, provider) = router
+            .apply_context_policy(oversized_request(&openai_model), LLMProviderType::OpenAI)
+            .await
+            .unwrap();
+        assert_eq!(provider, LLMProviderType::OpenAI);
+        assert_eq!(request.messages.len(), 1);
+        assert_eq!(request.messages[0].content, "What now?");
+    }
+
+    #[tokio::test]
+    async fn test_tenant_policy_restricts_routing() {
+        let router = LLMRouter::new_with_keys(
+            Some("test-openai-key".to_string()),
+            Some("test-anthropic-key".to_string()),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        let openai_model = router
+            .get_provider_client(&LLMProviderType::OpenAI)
+            .unwrap()
+            .get_available_models()[0]
+            .id
+            .clone();
+
+        let tenant = TenantId::new("acme");
+        router
+            .tenant_policies()
+            .set(
+                tenant.clone(),
+                TenantRoutingPolicy {
+                    strategy: Some(crate::api::types::SmartRoutingStrategy::CostOptimized),
+                    allowed_providers: Some(vec![LLMProviderType::Anthropic]),
+                    model_allowlist: None,
+                },
+            )
+            .await;
+
+        // Virtual models only resolve to providers the tenant may use
+        let request = router
+            .apply_tenant_policy(oversized_request("auto"), &tenant)
+            .await
+            .unwrap();
+        assert_eq!(
+            router.determine_provider_for_model(&request.model),
+            LLMProviderType::Anthropic
+        );
+
+        // Concrete models from other providers are rejected
+        let result = router
+            .apply_tenant_policy(oversized_request(&openai_model), &tenant)
+            .await;
+        assert!(matches!(result, Err(LLMError::NotPermitted(_))));
+
+        // Tenants without a policy are untouched
+        let request = router
+            .apply_tenant_policy(oversized_request(&openai_model), &TenantId::new("other"))
+            .await
+            .unwrap();
+        assert_eq!(request.model, openai_model);
+    }
+
+    #[tokio::test]
+    async fn test_routing_trace_records_candidates_and_exclusions() {
+        let router = LLMRouter::new_with_keys(
+            Some("test-openai-key".to_string()),
+            Some("test-anthropic-key".to_string()),
+            None,
+            None,
+        )
+        .await
+        .unwrap()
+        .with_routing_traces(RoutingTraceStore::new(16));
+
+        let tenant = TenantId::new("acme");
+        router
+            .tenant_policies()
+            .set(
+                tenant.clone(),
+                TenantRoutingPolicy {
+                    strategy: Some(crate::api::types::SmartRoutingStrategy::CostOptimized),
+                    allowed_providers: Some(vec![LLMProviderType::Anthropic]),
+                    model_allowlist: None,
+                },
+            )
+            .await;
+
+        let request = oversized_request("auto");
+        let request_id = request.id;
+        let request = router.apply_tenant_policy(request, &tenant).await.unwrap();
+
+        let trace = router.routing_trace(&request_id).await.unwrap();
+        assert_eq!(trace.requested_model, "auto");
+        assert_eq!(trace.tenant_id.as_deref(), Some("acme"));
+        assert_eq!(
+            trace.strategy,
+            Some(crate::api::types::SmartRoutingStrategy::CostOptimized)
+        );
+        assert_eq!(
+            trace.resolved_model.as_deref(),
+            Some(request.model.as_str())
+        );
+
+        // Only allowed providers are scored; the rest are excluded with a reason
+        assert!(!trace.candidates.is_empty());
+        assert!(trace
+            .candidates
+            .iter()
+            .all(|c| c.provider == LLMProviderType::Anthropic && c.score.is_some()));
+        assert!(trace
+            .candidates
+            .iter()
+            .any(|c| c.selected && c.model == request.model));
+        assert!(trace
+            .exclusions
+            .iter()
+            .any(|e| e.provider == Some(LLMProviderType::OpenAI)));
+    }
+
+    #[tokio::test]
+    async fn test_key_validation_rejects_unknown_base_url() {
+        let router = LLMRouter::new_for_testing().await.unwrap();
+
+        assert!(router.permits_base_url(&LLMProviderType::OpenAI, "https://api.openai.com/v1/"));
+        let result = router
+            .validate_provider_key(
+                &LLMProviderType::OpenAI,
+                "sk-test",
+                Some("http://169.254.169.254/latest".to_string()),
+            )
+            .await;
+        assert!(matches!(result, Err(LLMError::NotPermitted(_))));
+    }
+
+    #[tokio::test]
+    async fn test_virtual_models_resolve_without_routing_traces() {
+        let router = LLMRouter::new_with_keys(
+            Some("test-openai-key".to_string()),
+            Some("test-anthropic-key".to_string()),
+            None,
+            None,
+        )
+        .await
+        .unwrap()
+        .with_routing_traces(RoutingTraceStore::new(16))
+        .with_config(LLMRouterConfig {
+            record_routing_traces: false,
+            ..Default::default()
+        });
+
+        let request = oversized_request("auto");
+        let resolved = router.resolve_virtual_model_traced(&request, None).await;
+        assert_ne!(resolved, "auto");
+        assert!(router.routing_trace(&request.id).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_provider_is_avoided() {
+        let router = LLMRouter::new_with_keys(
+            Some("test-openai-key".to_string()),
+            Some("test-anthropic-key".to_string()),
+            None,
+            None,
+        )
+        .await
+        .unwrap()
+        .with_routing_traces(RoutingTraceStore::new(16));
+        router.rate_limits().record(
+            &LLMProviderType::Anthropic,
+            &KeyQuota {
+                requests_limit: Some(1000),
+                requests_remaining: Some(3),
+                ..Default::default()
+            },
+        );
+
+        // Virtual models resolve away from the constrained provider
+        let request = oversized_request("auto");
+        let resolved = router.resolve_virtual_model_traced(&request, None).await;
+        assert_eq!(
+            router.determine_provider_for_model(&resolved),
+            LLMProviderType::OpenAI
+        );
+        let trace = router.routing_trace(&request.id).await.unwrap();
+        assert!(trace.exclusions.iter().any(|e| {
+            e.provider == Some(LLMProviderType::Anthropic) && e.reason.contains("rate limit")
+        }));
+
+        // Concrete models only move when rerouting is enabled
+        let claude = oversized_request("claude-3-haiku-20240307");
+        let (unchanged, provider) = router
+            .avoid_rate_limited_provider(claude.clone(), LLMProviderType::Anthropic)
+            .await;
+        assert_eq!(provider, LLMProviderType::Anthropic);
+        assert_eq!(unchanged.model, claude.model);
+
+        let mut config = LLMRouterConfig::default();
+        config.rate_limits.reroute_concrete_models = true;
+        let router = router.with_config(config);
+        router
+            .rate_limits()
+            .record_exhausted(&LLMProviderType::Anthropic);
+        let (rerouted, provider) = router
+            .avoid_rate_limited_provider(claude, LLMProviderType::Anthropic)
+            .await;
+        assert_eq!(provider, LLMProviderType::OpenAI);
+        assert_eq!(
+            router.determine_provider_for_model(&rerouted.model),
+            LLMProviderType::OpenAI
+        );
+    }
+
+    struct RedactEmails;
+
+    #[async_trait::async_trait]
+    impl RouterMiddleware for RedactEmails {
+        fn name(&self) -> &str {
+            "redact-emails"
+        }
+
+        async fn pre_request(&self, request: &mut LLMRequest) -> LLMResult<()> {
+            for message in &mut request.messages {
+                message.content = message.content.replace("jane@example.com", "[email]");
+            }
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordErrors {
+        seen: std::sync::Mutex<Vec<String>>,
+    }
+
+    #[async_trait::async_trait]
+    impl RouterMiddleware for RecordErrors {
+        fn name(&self) -> &str {
+            "record-errors"
+        }
+
+        async fn on_error(&self, request: &LLMRequest, _error: &LLMError) {
+            self.seen
+                .lock()
+                .unwrap()
+                .push(request.messages[0].content.clone());
+        }
+    }
+
+    struct RejectAll;
+
+    #[async_trait::async_trait]
+    impl RouterMiddleware for RejectAll {
+        fn name(&self) -> &str {
+            "reject-all"
+        }
+
+        async fn pre_request(&self, _request: &mut LLMRequest) -> LLMResult<()> {
+            Err(LLMError::NotPermitted("rejected by middleware".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middleware_rewrites_request_and_observes_errors() {
+        let recorder = Arc::new(RecordErrors::default());
+        let router = LLMRouter::new_for_testing()
+            .await
+            .unwrap()
+            .with_middleware(Arc::new(RedactEmails))
+            .with_middleware(recorder.clone());
+
+        let mut request = oversized_request("gpt-4");
+        request.messages[0].content = "Contact jane@example.com".to_string();
+
+        // No providers are configured, so routing fails after the pre-request hooks
+        assert!(router.chat_completion(request).await.is_err());
+        assert_eq!(
+            *recorder.seen.lock().unwrap(),
+            vec!["Contact [email]".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_middleware_can_reject_request() {
+        let recorder = Arc::new(RecordErrors::default());
+        let router = LLMRouter::new_for_testing()
+            .await
+            .unwrap()
+            .with_middleware(recorder.clone())
+            .with_middleware(Arc::new(RejectAll));
+
+        let result = router.chat_completion(oversized_request("gpt-4")).await;
+        assert!(matches!(result, Err(LLMError::NotPermitted(_))));
+        assert_eq!(recorder.seen.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_structured_output_is_not_streamed() {
+        let router = LLMRouter::new_for_testing().await.unwrap();
+        let mut request = oversized_request("gpt-4");
+        request.response_format = Some(structured::ResponseFormat::JsonObject);
+
+        let result = router.stream_chat_completion(request).await;
+        assert!(matches!(result, Err(LLMError::InvalidRequest(_))));
+    }
 }
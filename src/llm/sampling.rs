@@ -0,0 +1,323 @@
+//! Parallel Sampling
+//!
+//! `n` asks for several independent choices for the same prompt, as best-of-N
+//! workflows do. Providers that sample them natively (OpenAI, vLLM) get the request
+//! as is. For the others the router sends `n` single-choice requests concurrently and
+//! merges the replies: choices are numbered in request order and usage is summed, so
+//! cost tracking sees what the fan-out actually consumed.
+
+use futures::stream::{self, SelectAll, StreamExt};
+
+use super::traits::LLMProviderClient;
+use super::{LLMRequest, LLMResponse, LLMResult, StreamingChunk, TokenUsage};
+
+/// Most choices a single request may ask for
+pub const MAX_CHOICES: u32 = 128;
+
+/// Stream of completion chunks, as returned by providers
+pub type ChunkStream = Box<dyn futures::Stream<Item = LLMResult<StreamingChunk>> + Send + Unpin>;
+
+/// Number of choices `request` asks for
+pub fn choices(request: &LLMRequest) -> u32 {
+    request.n.unwrap_or(1).max(1)
+}
+
+/// Whether `client` has to fan `request` out into parallel requests
+fn fans_out(client: &dyn LLMProviderClient, request: &LLMRequest) -> bool {
+    choices(request) > 1 && !client.supports_native_choices(&request.model)
+}
+
+/// `request` reduced to a single choice
+fn single_choice(request: &LLMRequest) -> LLMRequest {
+    let mut single = request.clone();
+    single.n = None;
+    single
+}
+
+/// Complete `request`, sampling `n` choices natively or in parallel
+pub async fn complete(
+    client: &dyn LLMProviderClient,
+    request: &LLMRequest,
+    api_key: &str,
+) -> LLMResult<LLMResponse> {
+    if !fans_out(client, request) {
+        return client.chat_completion(request, api_key).await;
+    }
+
+    let single = single_choice(request);
+    let responses = futures::future::try_join_all(
+        (0..choices(request)).map(|_| client.chat_completion(&single, api_key)),
+    )
+    .await?;
+    Ok(merge_responses(responses))
+}
+
+/// Stream `request`, sampling `n` choices natively or in parallel
+pub async fn stream(
+    client: &dyn LLMProviderClient,
+    request: LLMRequest,
+    api_key: String,
+) -> LLMResult<ChunkStream> {
+    if !fans_out(client, &request) {
+        return client.chat_completion_stream(request, api_key).await;
+    }
+
+    let single = single_choice(&request);
+    let streams = futures::future::try_join_all(
+        (0..choices(&request))
+            .map(|_| client.chat_completion_stream(single.clone(), api_key.clone())),
+    )
+    .await?;
+    Ok(merge_streams(streams))
+}
+
+/// Merge single-choice responses into one response with a choice per response
+///
+/// The first response supplies the id and metadata.
+pub fn merge_responses(responses: Vec<LLMResponse>) -> LLMResponse {
+    let mut responses = responses.into_iter();
+    let mut merged = responses
+        .next()
+        .expect("merge_responses needs at least one response");
+    for response in responses {
+        merged.usage = add_usage(&merged.usage, &response.usage);
+        merged.choices.extend(response.choices);
+        if response.rate_limit.is_some() {
+            merged.rate_limit = response.rate_limit;
+        }
+    }
+    for (index, choice) in merged.choices.iter_mut().enumerate() {
+        choice.index = index as u32;
+    }
+    merged
+}
+
+/// Interleave single-choice streams into one stream with a choice per stream
+///
+/// Chunks take the first chunk's id and the index of the stream they came from.
+/// Providers report usage on one or more chunks of each stream; those reports are
+/// held back and a single chunk with the summed usage ends the merged stream.
+pub fn merge_streams(streams: Vec<ChunkStream>) -> ChunkStream {
+    let state = MergeState {
+        usage: vec![None; streams.len()],
+        streams: stream::select_all(
+            streams
+                .into_iter()
+                .enumerate()
+                .map(|(index, stream)| stream.map(move |chunk| (index, chunk)).boxed()),
+        ),
+        last: None,
+        finished: false,
+    };
+
+    Box::new(Box::pin(stream::unfold(state, |mut state| async move {
+        loop {
+            match state.streams.next().await {
+                Some((index, Ok(mut chunk))) => {
+                    if let Some(usage) = chunk.usage.take() {
+                        let reported = &mut state.usage[index];
+                        if reported
+                            .as_ref()
+                            .is_none_or(|max| usage.total_tokens >= max.total_tokens)
+                        {
+                            *reported = Some(usage);
+                        }
+                    }
+                    if chunk.choices.is_empty() {
+                        continue;
+                    }
+                    for choice in &mut chunk.choices {
+                        choice.index = index as u32;
+                    }
+                    if let Some(last) = &state.last {
+                        chunk.id = last.id.clone();
+                    }
+                    state.last = Some(StreamingChunk {
+                        choices: Vec::new(),
+                        usage: None,
+                        ..chunk.clone()
+                    });
+                    return Some((Ok(chunk), state));
+                }
+                Some((_, Err(error))) => return Some((Err(error), state)),
+                None if state.finished => return None,
+                None => {
+                    state.finished = true;
+                    let usage = state
+                        .usage
+                        .iter()
+                        .flatten()
+                        .cloned()
+                        .reduce(|total, usage| add_usage(&total, &usage));
+                    match (state.last.take(), usage) {
+                        (Some(last), Some(usage)) => {
+                            let chunk = StreamingChunk {
+                                usage: Some(usage),
+                                ..last
+                            };
+                            return Some((Ok(chunk), state));
+                        }
+                        _ => return None,
+                    }
+                }
+            }
+        }
+    })))
+}
+
+struct MergeState {
+    streams: SelectAll<stream::BoxStream<'static, (usize, LLMResult<StreamingChunk>)>>,
+    /// Largest usage each stream reported
+    usage: Vec<Option<TokenUsage>>,
+    /// Metadata of the last chunk sent, without choices
+    last: Option<StreamingChunk>,
+    finished: bool,
+}
+
+fn add_usage(a: &TokenUsage, b: &TokenUsage) -> TokenUsage {
+    TokenUsage {
+        prompt_tokens: a.prompt_tokens + b.prompt_tokens,
+        completion_tokens: a.completion_tokens + b.completion_tokens,
+        total_tokens: a.total_tokens + b.total_tokens,
+        estimated_cost: a.estimated_cost + b.estimated_cost,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{
+        ChatMessage, Choice, LLMProviderType, MessageRole, RoutingInfo, RoutingStrategy,
+        StreamingChoice,
+    };
+
+    fn message(content: &str) -> ChatMessage {
+        ChatMessage {
+            role: MessageRole::Assistant,
+            content: content.to_string(),
+            name: None,
+            function_call: None,
+            tool_calls: None,
+            tool_call_id: None,
+            content_parts: None,
+        }
+    }
+
+    fn usage(prompt_tokens: u32, completion_tokens: u32) -> TokenUsage {
+        TokenUsage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+            estimated_cost: 0.01,
+        }
+    }
+
+    fn response(id: &str, content: &str) -> LLMResponse {
+        LLMResponse {
+            id: id.to_string(),
+            object: "chat.completion".to_string(),
+            created: 0,
+            model: "claude-3-haiku".to_string(),
+            choices: vec![Choice {
+                index: 0,
+                message: message(content),
+                finish_reason: Some("stop".to_string()),
+            }],
+            usage: usage(10, 5),
+            provider: LLMProviderType::Anthropic,
+            routing_info: RoutingInfo {
+                selected_provider: LLMProviderType::Anthropic,
+                routing_strategy: RoutingStrategy::CostOptimized,
+                latency_ms: 0,
+                retry_count: 0,
+                fallback_used: false,
+                provider_used: LLMProviderType::Anthropic,
+                total_latency_ms: 0,
+                provider_latency_ms: 0,
+            },
+            rate_limit: None,
+        }
+    }
+
+    fn chunk(
+        id: &str,
+        content: Option<&str>,
+        usage: Option<TokenUsage>,
+    ) -> LLMResult<StreamingChunk> {
+        Ok(StreamingChunk {
+            id: id.to_string(),
+            object: "chat.completion.chunk".to_string(),
+            choices: content
+                .map(|content| StreamingChoice {
+                    index: 0,
+                    delta: message(content),
+                    finish_reason: None,
+                })
+                .into_iter()
+                .collect(),
+            created: 0,
+            model: "claude-3-haiku".to_string(),
+            provider: LLMProviderType::Anthropic,
+            usage,
+        })
+    }
+
+    #[test]
+    fn test_merge_responses() {
+        let merged = merge_responses(vec![
+            response("a", "first"),
+            response("b", "second"),
+            response("c", "third"),
+        ]);
+
+        assert_eq!(merged.id, "a");
+        let choices: Vec<_> = merged
+            .choices
+            .iter()
+            .map(|choice| (choice.index, choice.message.content.as_str()))
+            .collect();
+        assert_eq!(choices, vec![(0, "first"), (1, "second"), (2, "third")]);
+        assert_eq!(merged.usage.prompt_tokens, 30);
+        assert_eq!(merged.usage.completion_tokens, 15);
+        assert_eq!(merged.usage.total_tokens, 45);
+        assert!((merged.usage.estimated_cost - 0.03).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_merge_streams() {
+        let first: ChunkStream = Box::new(stream::iter(vec![
+            chunk("a", Some("one"), Some(usage(10, 1))),
+            chunk("a", Some(" two"), None),
+            chunk("a", None, Some(usage(10, 2))),
+        ]));
+        let second: ChunkStream = Box::new(stream::iter(vec![
+            chunk("b", Some("uno"), None),
+            chunk("b", None, Some(usage(10, 3))),
+        ]));
+
+        let chunks: Vec<_> = merge_streams(vec![first, second])
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+
+        let (content, summary) = chunks.split_at(chunks.len() - 1);
+        assert!(content.iter().all(|chunk| chunk.id == content[0].id));
+        assert!(content.iter().all(|chunk| chunk.usage.is_none()));
+        let text = |index: u32| {
+            content
+                .iter()
+                .flat_map(|chunk| &chunk.choices)
+                .filter(|choice| choice.index == index)
+                .map(|choice| choice.delta.content.as_str())
+                .collect::<String>()
+        };
+        assert_eq!(text(0), "one two");
+        assert_eq!(text(1), "uno");
+
+        let summary = &summary[0];
+        assert!(summary.choices.is_empty());
+        let usage = summary.usage.as_ref().unwrap();
+        assert_eq!(usage.prompt_tokens, 20);
+        assert_eq!(usage.completion_tokens, 5);
+    }
+}
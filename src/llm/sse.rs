@@ -3,11 +3,21 @@
 //! This module provides utilities for parsing SSE streams from different LLM providers.
 //! Each provider has slightly different SSE formats, so we provide both generic and
 //! provider-specific parsing functions.
+//!
+//! On the outgoing side, [`ResumableStream`] numbers every event it emits and keeps it
+//! around for a while, so clients reconnecting with `Last-Event-ID` resume where they
+//! left off.
 
 use futures::{Stream, StreamExt};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, error};
 
-use crate::llm::{LLMError, LLMResult, StreamingChunk, StreamingChoice, ChatMessage, MessageRole, LLMProviderType};
+use crate::llm::{LLMError, LLMResult, StreamingChunk, StreamingChoice, ChatMessage, MessageRole, LLMProviderType, TokenUsage};
 
 /// SSE event structure
 #[derive(Debug, Clone)]
@@ -18,6 +28,37 @@ pub struct SSEEvent {
     pub retry: Option<u32>,
 }
 
+impl SSEEvent {
+    /// An event carrying only data
+    pub fn data(data: impl Into<String>) -> Self {
+        Self {
+            event_type: None,
+            data: data.into(),
+            id: None,
+            retry: None,
+        }
+    }
+
+    /// Serialize the event in wire format, terminated by a blank line
+    pub fn to_wire(&self) -> String {
+        let mut wire = String::new();
+        if let Some(id) = &self.id {
+            wire.push_str(&format!("id: {}\n", id));
+        }
+        if let Some(event_type) = &self.event_type {
+            wire.push_str(&format!("event: {}\n", event_type));
+        }
+        if let Some(retry) = self.retry {
+            wire.push_str(&format!("retry: {}\n", retry));
+        }
+        for line in self.data.split('\n') {
+            wire.push_str(&format!("data: {}\n", line));
+        }
+        wire.push('\n');
+        wire
+    }
+}
+
 /// SSE stream parser that converts bytes into SSE events
 pub struct SSEParser {
     buffer: String,
@@ -168,9 +209,254 @@ pub fn response_to_sse_stream(
     }))
 }
 
+/// How long a finished stream stays available for resumption
+pub const STREAM_RETENTION: Duration = Duration::from_secs(300);
+
+/// How long a stream keeps generating after its last client disconnected, giving the
+/// client a chance to resume before the completion is cancelled
+pub const ABANDONED_STREAM_GRACE: Duration = Duration::from_secs(30);
+
+lazy_static::lazy_static! {
+    static ref GLOBAL_STREAMS: StreamRegistry = StreamRegistry::new();
+}
+
+/// Position of an event within a resumable stream, written as `<stream_id>:<sequence>`
+/// in the `id` field and echoed back by clients in `Last-Event-ID`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventId {
+    pub stream_id: String,
+    /// 1-based position of the event within its stream
+    pub sequence: u64,
+}
+
+impl EventId {
+    pub fn parse(value: &str) -> Option<Self> {
+        let (stream_id, sequence) = value.trim().rsplit_once(':')?;
+        if stream_id.is_empty() {
+            return None;
+        }
+        Some(Self {
+            stream_id: stream_id.to_string(),
+            sequence: sequence.parse().ok()?,
+        })
+    }
+}
+
+impl std::fmt::Display for EventId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.stream_id, self.sequence)
+    }
+}
+
+struct ReplayState {
+    events: Vec<SSEEvent>,
+    finished_at: Option<Instant>,
+}
+
+/// An outgoing event stream that keeps every event it emitted, so a client that lost
+/// its connection can pick up after the last event it saw instead of starting over
+pub struct ResumableStream {
+    id: String,
+    state: Mutex<ReplayState>,
+    /// Bumped whenever an event is added or the stream ends
+    updates: watch::Sender<u64>,
+    /// Cancelled to stop the completion feeding the stream
+    cancellation: CancellationToken,
+    /// Clients currently following the stream
+    subscribers: AtomicUsize,
+}
+
+/// Counts a client following a stream for as long as it is alive
+struct Subscriber(Arc<ResumableStream>);
+
+impl Subscriber {
+    fn new(stream: Arc<ResumableStream>) -> Self {
+        stream.subscribers.fetch_add(1, Ordering::SeqCst);
+        Self(stream)
+    }
+}
+
+impl Drop for Subscriber {
+    fn drop(&mut self) {
+        self.0.subscribers.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl ResumableStream {
+    fn new(id: String) -> Self {
+        let (updates, _) = watch::channel(0);
+        Self {
+            id,
+            state: Mutex::new(ReplayState {
+                events: Vec::new(),
+                finished_at: None,
+            }),
+            updates,
+            cancellation: CancellationToken::new(),
+            subscribers: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Append an event with the given data and return its sequence number
+    pub fn push(&self, data: impl Into<String>) -> u64 {
+        let sequence = {
+            let mut state = self.state.lock().unwrap();
+            let sequence = state.events.len() as u64 + 1;
+            let mut event = SSEEvent::data(data);
+            event.id = Some(
+                EventId {
+                    stream_id: self.id.clone(),
+                    sequence,
+                }
+                .to_string(),
+            );
+            state.events.push(event);
+            sequence
+        };
+        self.updates.send_replace(sequence);
+        sequence
+    }
+
+    /// Mark the stream as complete; subscribers end once they have caught up
+    pub fn finish(&self) {
+        self.state.lock().unwrap().finished_at = Some(Instant::now());
+        self.updates.send_modify(|_| {});
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state.lock().unwrap().finished_at.is_some()
+    }
+
+    /// Wait until the stream has ended
+    pub async fn finished(&self) {
+        let mut updates = self.updates.subscribe();
+        while !self.is_finished() {
+            if updates.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Ask the producer to stop generating; it still ends the stream with `finish`
+    pub fn cancel(&self) {
+        self.cancellation.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+
+    /// Resolves once the stream has been cancelled
+    pub async fn cancelled(&self) {
+        self.cancellation.cancelled().await
+    }
+
+    /// Number of clients currently following the stream
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers.load(Ordering::SeqCst)
+    }
+
+    /// Sequence number of the latest event, 0 if none was emitted yet
+    pub fn last_sequence(&self) -> u64 {
+        self.state.lock().unwrap().events.len() as u64
+    }
+
+    /// Events emitted after `sequence`, and whether the stream has ended
+    pub fn events_after(&self, sequence: u64) -> (Vec<SSEEvent>, bool) {
+        let state = self.state.lock().unwrap();
+        let start = (sequence as usize).min(state.events.len());
+        (state.events[start..].to_vec(), state.finished_at.is_some())
+    }
+
+    /// Replay the events after `sequence`, then follow new ones until the stream ends
+    pub fn subscribe(
+        self: &Arc<Self>,
+        sequence: u64,
+    ) -> impl Stream<Item = SSEEvent> + Send + Unpin {
+        let updates = self.updates.subscribe();
+        Box::pin(
+            futures::stream::unfold(
+                (Subscriber::new(self.clone()), sequence, updates),
+                |(subscriber, mut cursor, mut updates)| async move {
+                    loop {
+                        let (events, finished) = subscriber.0.events_after(cursor);
+                        if !events.is_empty() {
+                            cursor += events.len() as u64;
+                            return Some((
+                                futures::stream::iter(events),
+                                (subscriber, cursor, updates),
+                            ));
+                        }
+                        if finished || updates.changed().await.is_err() {
+                            return None;
+                        }
+                    }
+                },
+            )
+            .flatten(),
+        )
+    }
+
+    fn expired(&self, now: Instant) -> bool {
+        match self.state.lock().unwrap().finished_at {
+            Some(finished_at) => now.duration_since(finished_at) >= STREAM_RETENTION,
+            None => false,
+        }
+    }
+}
+
+/// Streams that can currently be resumed, keyed by stream ID
+#[derive(Clone)]
+pub struct StreamRegistry {
+    streams: Arc<Mutex<HashMap<String, Arc<ResumableStream>>>>,
+}
+
+impl StreamRegistry {
+    pub fn new() -> Self {
+        Self {
+            streams: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Process-wide registry shared by every streaming endpoint
+    pub fn global() -> Self {
+        GLOBAL_STREAMS.clone()
+    }
+
+    /// Start recording a new stream, dropping finished streams past their retention
+    pub fn create(&self, id: impl Into<String>) -> Arc<ResumableStream> {
+        let stream = Arc::new(ResumableStream::new(id.into()));
+        let now = Instant::now();
+        let mut streams = self.streams.lock().unwrap();
+        streams.retain(|_, stream| !stream.expired(now));
+        streams.insert(stream.id.clone(), stream.clone());
+        stream
+    }
+
+    /// Look up a stream that is still running or finished recently
+    pub fn get(&self, id: &str) -> Option<Arc<ResumableStream>> {
+        let streams = self.streams.lock().unwrap();
+        streams
+            .get(id)
+            .filter(|stream| !stream.expired(Instant::now()))
+            .cloned()
+    }
+}
+
+impl Default for StreamRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Anthropic-specific SSE parsing
 pub mod anthropic {
     use super::*;
+    use crate::llm::ToolCall;
     use serde::Deserialize;
 
     #[derive(Debug, Deserialize)]
@@ -198,7 +484,12 @@ pub mod anthropic {
         ContentBlockStop { index: u32 },
         
         #[serde(rename = "message_delta")]
-        MessageDelta { delta: AnthropicMessageDelta },
+        MessageDelta {
+            delta: AnthropicMessageDelta,
+            /// Output tokens so far; Anthropic sends it next to the delta
+            #[serde(default)]
+            usage: Option<AnthropicUsage>,
+        },
         
         #[serde(rename = "message_stop")]
         MessageStop,
@@ -220,6 +511,11 @@ pub mod anthropic {
         #[serde(rename = "type")]
         pub block_type: String,
         pub text: Option<String>,
+        /// Call ID and tool name of a `tool_use` block
+        #[serde(default)]
+        pub id: Option<String>,
+        #[serde(default)]
+        pub name: Option<String>,
     }
 
     #[derive(Debug, Deserialize)]
@@ -227,6 +523,8 @@ pub mod anthropic {
         #[serde(rename = "type")]
         pub delta_type: String,
         pub text: Option<String>,
+        /// Fragment of a tool call's input (`input_json_delta`)
+        pub partial_json: Option<String>,
     }
 
     #[derive(Debug, Deserialize)]
@@ -241,12 +539,78 @@ pub mod anthropic {
         pub output_tokens: Option<u32>,
     }
 
+    impl AnthropicUsage {
+        pub fn to_token_usage(&self) -> TokenUsage {
+            let prompt_tokens = self.input_tokens.unwrap_or(0);
+            let completion_tokens = self.output_tokens.unwrap_or(0);
+            TokenUsage {
+                prompt_tokens,
+                completion_tokens,
+                total_tokens: prompt_tokens + completion_tokens,
+                estimated_cost: 0.0,
+            }
+        }
+    }
+
     #[derive(Debug, Deserialize)]
     pub struct AnthropicStreamError {
         pub error_type: String,
         pub message: String,
     }
 
+    /// Renumbers streamed tool calls from Anthropic content block positions, which also
+    /// count text blocks, to OpenAI's positions among the message's tool calls
+    #[derive(Debug, Default)]
+    pub struct ToolCallIndexer {
+        positions: HashMap<u32, u32>,
+    }
+
+    impl ToolCallIndexer {
+        pub fn renumber(&mut self, chunk: &mut StreamingChunk) {
+            let calls = chunk
+                .choices
+                .iter_mut()
+                .filter_map(|choice| choice.delta.tool_calls.as_mut())
+                .flatten();
+            for call in calls {
+                if let Some(block) = call.index {
+                    let next = self.positions.len() as u32;
+                    call.index = Some(*self.positions.entry(block).or_insert(next));
+                }
+            }
+        }
+    }
+
+    fn delta_chunk(
+        request_id: &str,
+        model: &str,
+        content: String,
+        tool_calls: Option<Vec<ToolCall>>,
+        finish_reason: Option<String>,
+    ) -> StreamingChunk {
+        StreamingChunk {
+            id: request_id.to_string(),
+            object: "chat.completion.chunk".to_string(),
+            created: chrono::Utc::now().timestamp() as u64,
+            model: model.to_string(),
+            choices: vec![StreamingChoice {
+                index: 0,
+                delta: ChatMessage {
+                    role: MessageRole::Assistant,
+                    content,
+                    name: None,
+                    function_call: None,
+                    tool_calls,
+                    tool_call_id: None,
+                    content_parts: None,
+                },
+                finish_reason,
+            }],
+            provider: LLMProviderType::Anthropic,
+            usage: None,
+        }
+    }
+
     /// Convert Anthropic SSE event to our StreamingChunk
     pub fn anthropic_event_to_chunk(
         event: &SSEEvent,
@@ -275,49 +639,56 @@ pub mod anthropic {
                 debug!("Received ping event, ignoring");
                 Ok(None) // Ignore ping events
             }
-            AnthropicStreamEvent::ContentBlockDelta { delta, .. } => {
+            AnthropicStreamEvent::ContentBlockStart { index, content_block }
+                if content_block.block_type == "tool_use" =>
+            {
+                // A tool call opens with its ID and name; its input follows in pieces.
+                // The call is keyed by its content block index; see `ToolCallIndexer`.
+                let call = ToolCall::delta(
+                    index,
+                    content_block.id.unwrap_or_default(),
+                    content_block.name.unwrap_or_default(),
+                    "",
+                );
+                Ok(Some(delta_chunk(request_id, model, String::new(), Some(vec![call]), None)))
+            }
+            AnthropicStreamEvent::ContentBlockDelta { index, delta } => {
                 if let Some(text) = delta.text {
-                    Ok(Some(StreamingChunk {
-                        id: request_id.to_string(),
-                        object: "chat.completion.chunk".to_string(),
-                        created: chrono::Utc::now().timestamp() as u64,
-                        model: model.to_string(),
-                        choices: vec![StreamingChoice {
-                            index: 0,
-                            delta: ChatMessage {
-                                role: MessageRole::Assistant,
-                                content: text,
-                                name: None,
-                                function_call: None,
-                            },
-                            finish_reason: None,
-                        }],
-                        provider: LLMProviderType::Anthropic,
-                    }))
+                    Ok(Some(delta_chunk(request_id, model, text, None, None)))
+                } else if let Some(partial_json) = delta.partial_json {
+                    let call = ToolCall::delta(index, "", "", partial_json);
+                    Ok(Some(delta_chunk(request_id, model, String::new(), Some(vec![call]), None)))
                 } else {
                     debug!("Content delta with no text");
                     Ok(None)
                 }
             }
-            AnthropicStreamEvent::MessageDelta { delta } => {
+            AnthropicStreamEvent::MessageStart { message } => {
+                // The prompt is counted up front; output tokens follow in `message_delta`
+                Ok(Some(StreamingChunk {
+                    id: request_id.to_string(),
+                    object: "chat.completion.chunk".to_string(),
+                    created: chrono::Utc::now().timestamp() as u64,
+                    model: model.to_string(),
+                    choices: Vec::new(),
+                    provider: LLMProviderType::Anthropic,
+                    usage: Some(message.usage.to_token_usage()),
+                }))
+            }
+            AnthropicStreamEvent::MessageDelta { delta, usage } => {
+                let usage = usage
+                    .as_ref()
+                    .or(delta.usage.as_ref())
+                    .map(AnthropicUsage::to_token_usage);
                 if let Some(stop_reason) = delta.stop_reason {
-                    Ok(Some(StreamingChunk {
-                        id: request_id.to_string(),
-                        object: "chat.completion.chunk".to_string(),
-                        created: chrono::Utc::now().timestamp() as u64,
-                        model: model.to_string(),
-                        choices: vec![StreamingChoice {
-                            index: 0,
-                            delta: ChatMessage {
-                                role: MessageRole::Assistant,
-                                content: String::new(),
-                                name: None,
-                                function_call: None,
-                            },
-                            finish_reason: Some(stop_reason),
-                        }],
-                        provider: LLMProviderType::Anthropic,
-                    }))
+                    let finish_reason = match stop_reason.as_str() {
+                        "tool_use" => "tool_calls".to_string(),
+                        _ => stop_reason,
+                    };
+                    let mut chunk =
+                        delta_chunk(request_id, model, String::new(), None, Some(finish_reason));
+                    chunk.usage = usage;
+                    Ok(Some(chunk))
                 } else {
                     debug!("Message delta with no stop reason");
                     Ok(None)
@@ -345,6 +716,16 @@ pub mod openai {
         pub created: u64,
         pub model: String,
         pub choices: Vec<OpenAIStreamChoice>,
+        /// Only on the final chunk, when `stream_options.include_usage` was requested
+        #[serde(default)]
+        pub usage: Option<OpenAIStreamUsage>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct OpenAIStreamUsage {
+        pub prompt_tokens: u32,
+        pub completion_tokens: u32,
+        pub total_tokens: u32,
     }
 
     #[derive(Debug, Deserialize)]
@@ -358,6 +739,8 @@ pub mod openai {
     pub struct OpenAIDelta {
         pub role: Option<String>,
         pub content: Option<String>,
+        #[serde(default)]
+        pub tool_calls: Option<Vec<crate::llm::ToolCall>>,
     }
 
     /// Convert OpenAI SSE event to our StreamingChunk
@@ -369,6 +752,12 @@ pub mod openai {
 
         let chunk: OpenAIStreamChunk = serde_json::from_str(&event.data)
             .map_err(|e| LLMError::Parse(format!("Failed to parse OpenAI stream chunk: {}", e)))?;
+        let usage = chunk.usage.as_ref().map(|usage| TokenUsage {
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            total_tokens: usage.total_tokens,
+            estimated_cost: 0.0,
+        });
 
         if let Some(choice) = chunk.choices.first() {
             let content = choice.delta.content.clone().unwrap_or_default();
@@ -393,10 +782,25 @@ pub mod openai {
                         content,
                         name: None,
                         function_call: None,
+                        tool_calls: choice.delta.tool_calls.clone(),
+                        tool_call_id: None,
+                        content_parts: None,
                     },
                     finish_reason: choice.finish_reason.clone(),
                 }],
                 provider: LLMProviderType::OpenAI,
+                usage,
+            }))
+        } else if usage.is_some() {
+            // The usage chunk carries no choices
+            Ok(Some(StreamingChunk {
+                id: chunk.id,
+                object: chunk.object,
+                created: chunk.created,
+                model: chunk.model,
+                choices: Vec::new(),
+                provider: LLMProviderType::OpenAI,
+                usage,
             }))
         } else {
             Ok(None)
@@ -407,6 +811,9 @@ pub mod openai {
 /// Google-specific SSE parsing
 pub mod google {
     use super::*;
+    use crate::llm::providers::google::types::GoogleFunctionCall;
+    use crate::llm::tools::generate_call_id;
+    use crate::llm::ToolCall;
     use serde::Deserialize;
 
     #[derive(Debug, Deserialize)]
@@ -433,6 +840,8 @@ pub mod google {
     #[derive(Debug, Deserialize)]
     pub struct GooglePart {
         pub text: Option<String>,
+        #[serde(rename = "functionCall", alias = "function_call", default)]
+        pub function_call: Option<GoogleFunctionCall>,
     }
 
     #[derive(Debug, Deserialize)]
@@ -466,8 +875,22 @@ pub mod google {
                 .cloned()
                 .collect::<Vec<String>>()
                 .join("");
+            // Gemini sends each function call whole, so one delta carries all of it
+            let tool_calls: Vec<ToolCall> = candidate.content.parts
+                .iter()
+                .filter_map(|part| part.function_call.as_ref())
+                .enumerate()
+                .map(|(index, call)| {
+                    ToolCall::delta(index as u32, generate_call_id(), call.name.clone(), call.args.to_string())
+                })
+                .collect();
+            let finish_reason = if tool_calls.is_empty() {
+                candidate.finish_reason.clone()
+            } else {
+                Some("tool_calls".to_string())
+            };
 
-            if !content.is_empty() || candidate.finish_reason.is_some() {
+            if !content.is_empty() || !tool_calls.is_empty() || finish_reason.is_some() {
                 Ok(Some(StreamingChunk {
                     id: request_id.to_string(),
                     object: "chat.completion.chunk".to_string(),
@@ -480,10 +903,25 @@ pub mod google {
                             content,
                             name: None,
                             function_call: None,
+                            tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+                            tool_call_id: None,
+                            content_parts: None,
                         },
-                        finish_reason: candidate.finish_reason.clone(),
+                        finish_reason,
                     }],
                     provider: LLMProviderType::Google,
+                    usage: chunk.usage_metadata.as_ref().map(|usage| {
+                        let prompt_tokens = usage.prompt_token_count.unwrap_or(0);
+                        let completion_tokens = usage.candidates_token_count.unwrap_or(0);
+                        TokenUsage {
+                            prompt_tokens,
+                            completion_tokens,
+                            total_tokens: usage
+                                .total_token_count
+                                .unwrap_or(prompt_tokens + completion_tokens),
+                            estimated_cost: 0.0,
+                        }
+                    }),
                 }))
             } else {
                 Ok(None)
@@ -555,6 +993,60 @@ mod tests {
         assert_eq!(chunk.provider, LLMProviderType::Anthropic);
     }
 
+    #[test]
+    fn test_anthropic_tool_use_becomes_tool_call_deltas() {
+        let convert = |data: &str| {
+            anthropic::anthropic_event_to_chunk(&SSEEvent::data(data), "test-id", "claude-3-sonnet")
+                .unwrap()
+                .unwrap()
+        };
+
+        let start = convert(
+            r#"{"type":"content_block_start","index":1,"content_block":{"type":"tool_use","id":"toolu_1","name":"get_weather","input":{}}}"#,
+        );
+        let call = &start.choices[0].delta.tool_calls.as_ref().unwrap()[0];
+        assert_eq!(call.index, Some(1));
+        assert_eq!(call.id, "toolu_1");
+        assert_eq!(call.function.name, "get_weather");
+
+        let delta = convert(
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"{\"city\": \"Oslo\"}"}}"#,
+        );
+        assert!(delta.choices[0].delta.content.is_empty());
+        let call = &delta.choices[0].delta.tool_calls.as_ref().unwrap()[0];
+        assert_eq!(call.index, Some(1));
+        assert_eq!(call.function.arguments, r#"{"city": "Oslo"}"#);
+
+        let stop = convert(r#"{"type":"message_delta","delta":{"stop_reason":"tool_use"}}"#);
+        assert_eq!(stop.choices[0].finish_reason.as_deref(), Some("tool_calls"));
+
+        // The call in content block 1 is the message's first tool call
+        let mut indexer = anthropic::ToolCallIndexer::default();
+        let (mut start, mut delta) = (start, delta);
+        indexer.renumber(&mut start);
+        indexer.renumber(&mut delta);
+        assert_eq!(start.choices[0].delta.tool_calls.as_ref().unwrap()[0].index, Some(0));
+        assert_eq!(delta.choices[0].delta.tool_calls.as_ref().unwrap()[0].index, Some(0));
+    }
+
+    #[test]
+    fn test_google_function_call_becomes_tool_call_delta() {
+        let event = SSEEvent::data(
+            r#"{"candidates":[{"content":{"role":"model","parts":[{"functionCall":{"name":"get_weather","args":{"city":"Oslo"}}}]},"finishReason":"STOP","index":0}]}"#,
+        );
+        let chunk = google::google_event_to_chunk(&event, "test-id", "gemini-1.5-pro")
+            .unwrap()
+            .unwrap();
+
+        let calls = chunk.choices[0].delta.tool_calls.as_ref().unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].index, Some(0));
+        assert!(!calls[0].id.is_empty());
+        assert_eq!(calls[0].function.name, "get_weather");
+        assert_eq!(calls[0].arguments()["city"], "Oslo");
+        assert_eq!(chunk.choices[0].finish_reason.as_deref(), Some("tool_calls"));
+    }
+
     #[test]
     fn test_openai_delta_parsing() {
         let event = SSEEvent {
@@ -571,4 +1063,76 @@ mod tests {
         assert_eq!(chunk.choices[0].delta.content, "Hello");
         assert_eq!(chunk.provider, LLMProviderType::OpenAI);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_stream_usage_parsing() {
+        let event = SSEEvent::data(
+            r#"{"id":"test","object":"chat.completion.chunk","created":1234567890,"model":"gpt-4","choices":[],"usage":{"prompt_tokens":12,"completion_tokens":30,"total_tokens":42}}"#,
+        );
+        let chunk = openai::openai_event_to_chunk(&event).unwrap().unwrap();
+        assert!(chunk.choices.is_empty());
+        assert_eq!(chunk.usage.unwrap().total_tokens, 42);
+
+        let event = SSEEvent::data(
+            r#"{"type":"message_delta","delta":{"stop_reason":"end_turn","stop_sequence":null},"usage":{"output_tokens":15}}"#,
+        );
+        let chunk = anthropic::anthropic_event_to_chunk(&event, "test-id", "claude-3-sonnet")
+            .unwrap()
+            .unwrap();
+        assert_eq!(chunk.choices[0].finish_reason.as_deref(), Some("end_turn"));
+        assert_eq!(chunk.usage.unwrap().completion_tokens, 15);
+    }
+
+    #[test]
+    fn test_event_id_round_trip() {
+        let id = EventId::parse("0b6f6d2e-stream:42").unwrap();
+        assert_eq!(id.stream_id, "0b6f6d2e-stream");
+        assert_eq!(id.sequence, 42);
+        assert_eq!(id.to_string(), "0b6f6d2e-stream:42");
+        assert!(EventId::parse("no-sequence").is_none());
+        assert!(EventId::parse(":7").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_resumable_stream_replays_and_follows() {
+        let registry = StreamRegistry::new();
+        let stream = registry.create("req-1");
+        assert_eq!(stream.push("one"), 1);
+        assert_eq!(stream.push("two"), 2);
+
+        // A client that saw event 1 gets the rest, including events emitted later
+        let mut events = registry.get("req-1").unwrap().subscribe(1);
+        let next = events.next().await.unwrap();
+        assert_eq!(next.data, "two");
+        assert_eq!(next.to_wire(), "id: req-1:2\ndata: two\n\n");
+
+        stream.push("three");
+        stream.finish();
+        assert_eq!(events.next().await.unwrap().data, "three");
+        assert!(events.next().await.is_none());
+        assert!(registry.get("req-2").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_resumable_stream_tracks_subscribers_and_cancellation() {
+        let stream = StreamRegistry::new().create("req-1");
+        let events = stream.subscribe(0);
+        let more = stream.subscribe(0);
+        assert_eq!(stream.subscriber_count(), 2);
+        drop(events);
+        drop(more);
+        assert_eq!(stream.subscriber_count(), 0);
+
+        assert!(!stream.is_cancelled());
+        stream.cancel();
+        stream.cancelled().await;
+        assert!(stream.is_cancelled());
+
+        let waiter = tokio::spawn({
+            let stream = stream.clone();
+            async move { stream.finished().await }
+        });
+        stream.finish();
+        waiter.await.unwrap();
+    }
+}
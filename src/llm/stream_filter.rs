@@ -0,0 +1,497 @@
+//! Streaming Content Filters
+//!
+//! Stages that inspect a streamed completion before its chunks reach the client. A
+//! [`StreamFilter`] looks at text that has not been sent yet and either lets it through,
+//! rewrites it (redacting secrets or PII), or terminates the stream when it finds blocked
+//! content. [`PatternFilter`] is the built-in regex and keyword implementation; custom
+//! filters plug in through [`StreamFilters::with_filter`].
+//!
+//! Content is split across chunks at arbitrary points, so a [`StreamFilterStage`] holds
+//! back the last `holdback` bytes of every choice until more text arrives or the choice
+//! finishes. Anything a filter must catch whole has to fit in that window.
+//!
+//! A terminated stream ends with a chunk whose `finish_reason` is `content_filter`; the
+//! held-back text containing the blocked content is never sent.
+
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::StreamingChunk;
+
+/// Set to `on` to redact common secrets and PII from streamed completions
+pub const STREAM_FILTER_ENV: &str = "CIRCUIT_BREAKER_STREAM_FILTER";
+
+/// Comma-separated keywords that terminate a stream when the model produces them
+pub const BLOCKED_TERMS_ENV: &str = "CIRCUIT_BREAKER_BLOCKED_TERMS";
+
+/// Bytes of each choice held back so matches spanning chunk boundaries are caught
+pub const DEFAULT_HOLDBACK: usize = 64;
+
+/// Finish reason of a stream terminated by a filter
+pub const CONTENT_FILTER_FINISH_REASON: &str = "content_filter";
+
+/// What a filter decided about pending text
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterVerdict {
+    /// Send the text unchanged
+    Allow,
+    /// Send this text instead
+    Redact(String),
+    /// Stop the stream, for the given reason
+    Terminate(String),
+}
+
+/// Inspects streamed text before it is sent to the client
+pub trait StreamFilter: Send + Sync {
+    /// Name used in logs and termination reports
+    fn name(&self) -> &str;
+
+    /// Inspect text not yet sent for one choice
+    fn inspect(&self, text: &str) -> FilterVerdict;
+}
+
+/// What a matching [`PatternRule`] does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatternAction {
+    /// Replace the match with `[REDACTED:<rule>]`
+    Redact,
+    /// Terminate the stream
+    Block,
+}
+
+/// A named pattern and what to do when it matches
+#[derive(Debug, Clone)]
+pub struct PatternRule {
+    pub name: String,
+    pub pattern: Regex,
+    pub action: PatternAction,
+}
+
+/// Regex and keyword filter
+#[derive(Debug, Clone, Default)]
+pub struct PatternFilter {
+    rules: Vec<PatternRule>,
+}
+
+impl PatternFilter {
+    /// A filter without rules
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A filter redacting common secrets and PII
+    pub fn with_defaults() -> Self {
+        let defaults = [
+            ("private_key", r"-----BEGIN [A-Z ]*PRIVATE KEY-----"),
+            ("aws_access_key", r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b"),
+            ("api_key", r"\b(?:sk|pk|rk)-[A-Za-z0-9_-]{20,}"),
+            ("github_token", r"\bgh[pousr]_[A-Za-z0-9]{36,}\b"),
+            ("slack_token", r"\bxox[abposr]-[A-Za-z0-9-]{10,}"),
+            (
+                "email",
+                r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}\b",
+            ),
+            ("ssn", r"\b\d{3}-\d{2}-\d{4}\b"),
+            ("credit_card", r"\b(?:\d[ -]?){13,16}\b"),
+        ];
+        let mut filter = Self::new();
+        for (name, pattern) in defaults {
+            filter = filter
+                .redact(name, pattern)
+                .expect("default stream filter patterns are valid");
+        }
+        filter
+    }
+
+    /// Redact text matching `pattern`
+    pub fn redact(self, name: &str, pattern: &str) -> Result<Self, regex::Error> {
+        self.rule(name, pattern, PatternAction::Redact)
+    }
+
+    /// Terminate the stream when text matches `pattern`
+    pub fn block(self, name: &str, pattern: &str) -> Result<Self, regex::Error> {
+        self.rule(name, pattern, PatternAction::Block)
+    }
+
+    /// Terminate the stream when any of the keywords appears as a whole word, ignoring case
+    pub fn block_keywords<I, S>(self, keywords: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let alternatives: Vec<String> = keywords
+            .into_iter()
+            .map(|keyword| keyword.as_ref().trim().to_string())
+            .filter(|keyword| !keyword.is_empty())
+            .map(|keyword| regex::escape(&keyword))
+            .collect();
+        if alternatives.is_empty() {
+            return self;
+        }
+        self.block(
+            "blocked_term",
+            &format!(r"(?i)\b(?:{})\b", alternatives.join("|")),
+        )
+        .expect("escaped keywords form a valid pattern")
+    }
+
+    fn rule(
+        mut self,
+        name: &str,
+        pattern: &str,
+        action: PatternAction,
+    ) -> Result<Self, regex::Error> {
+        self.rules.push(PatternRule {
+            name: name.to_string(),
+            pattern: Regex::new(pattern)?,
+            action,
+        });
+        Ok(self)
+    }
+
+    pub fn rules(&self) -> &[PatternRule] {
+        &self.rules
+    }
+}
+
+impl StreamFilter for PatternFilter {
+    fn name(&self) -> &str {
+        "pattern"
+    }
+
+    fn inspect(&self, text: &str) -> FilterVerdict {
+        if let Some(rule) = self
+            .rules
+            .iter()
+            .find(|rule| rule.action == PatternAction::Block && rule.pattern.is_match(text))
+        {
+            return FilterVerdict::Terminate(format!("matched blocked pattern '{}'", rule.name));
+        }
+
+        let mut redacted: Option<String> = None;
+        for rule in self
+            .rules
+            .iter()
+            .filter(|rule| rule.action == PatternAction::Redact)
+        {
+            let current = redacted.as_deref().unwrap_or(text);
+            if rule.pattern.is_match(current) {
+                let replacement = format!("[REDACTED:{}]", rule.name);
+                redacted = Some(
+                    rule.pattern
+                        .replace_all(current, replacement.as_str())
+                        .into_owned(),
+                );
+            }
+        }
+        match redacted {
+            Some(text) => FilterVerdict::Redact(text),
+            None => FilterVerdict::Allow,
+        }
+    }
+}
+
+/// Why a stream was terminated
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterTermination {
+    pub filter: String,
+    pub reason: String,
+}
+
+/// Filters applied to every streamed completion
+#[derive(Clone)]
+pub struct StreamFilters {
+    filters: Vec<Arc<dyn StreamFilter>>,
+    holdback: usize,
+}
+
+impl Default for StreamFilters {
+    fn default() -> Self {
+        Self {
+            filters: Vec::new(),
+            holdback: DEFAULT_HOLDBACK,
+        }
+    }
+}
+
+impl std::fmt::Debug for StreamFilters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamFilters")
+            .field(
+                "filters",
+                &self.filters.iter().map(|f| f.name()).collect::<Vec<_>>(),
+            )
+            .field("holdback", &self.holdback)
+            .finish()
+    }
+}
+
+impl StreamFilters {
+    /// No filters; chunks pass through untouched
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Filters configured by the environment: secret and PII redaction when
+    /// [`STREAM_FILTER_ENV`] is `on`, and blocking of the keywords in [`BLOCKED_TERMS_ENV`]
+    pub fn from_env() -> Self {
+        let redact = std::env::var(STREAM_FILTER_ENV)
+            .map(|value| matches!(value.to_lowercase().as_str(), "on" | "true" | "1"))
+            .unwrap_or(false);
+        let blocked = std::env::var(BLOCKED_TERMS_ENV).unwrap_or_default();
+
+        let filter = if redact {
+            PatternFilter::with_defaults()
+        } else {
+            PatternFilter::new()
+        }
+        .block_keywords(blocked.split(','));
+        if filter.rules().is_empty() {
+            return Self::new();
+        }
+        Self::new().with_filter(Arc::new(filter))
+    }
+
+    /// Add a filter; it runs after the ones already added
+    pub fn with_filter(mut self, filter: Arc<dyn StreamFilter>) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    /// Bytes held back per choice
+    pub fn with_holdback(mut self, holdback: usize) -> Self {
+        self.holdback = holdback;
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+
+    /// A stage filtering one stream
+    pub fn stage(&self) -> StreamFilterStage {
+        StreamFilterStage {
+            filters: self.filters.clone(),
+            holdback: self.holdback,
+            pending: HashMap::new(),
+            last_chunk: None,
+            termination: None,
+        }
+    }
+}
+
+/// Filtering state of one stream
+pub struct StreamFilterStage {
+    filters: Vec<Arc<dyn StreamFilter>>,
+    holdback: usize,
+    /// Choice index -> text inspected but not sent yet
+    pending: HashMap<u32, String>,
+    /// Template for the chunk that flushes held-back text
+    last_chunk: Option<StreamingChunk>,
+    termination: Option<FilterTermination>,
+}
+
+impl StreamFilterStage {
+    /// Filter a chunk, holding back the tail of its content
+    ///
+    /// Returns `None` when all of the chunk's content is held back. After a termination
+    /// the returned chunk is the final `content_filter` chunk and
+    /// [`termination`](Self::termination) says why; nothing further should be sent.
+    pub fn process(&mut self, mut chunk: StreamingChunk) -> Option<StreamingChunk> {
+        if self.filters.is_empty() {
+            return Some(chunk);
+        }
+
+        let mut held_back = true;
+        for choice in &mut chunk.choices {
+            if choice.delta.content.is_empty()
+                || choice.finish_reason.is_some()
+                || choice.delta.tool_calls.is_some()
+            {
+                held_back = false;
+            }
+            let pending = self.pending.entry(choice.index).or_default();
+            pending.push_str(&choice.delta.content);
+
+            for filter in &self.filters {
+                match filter.inspect(pending) {
+                    FilterVerdict::Allow => {}
+                    FilterVerdict::Redact(text) => *pending = text,
+                    FilterVerdict::Terminate(reason) => {
+                        self.termination = Some(FilterTermination {
+                            filter: filter.name().to_string(),
+                            reason,
+                        });
+                        return Some(self.terminated_chunk(chunk));
+                    }
+                }
+            }
+
+            choice.delta.content = if choice.finish_reason.is_some() {
+                std::mem::take(pending)
+            } else {
+                release(pending, self.holdback)
+            };
+            if !choice.delta.content.is_empty() {
+                held_back = false;
+            }
+        }
+
+        let mut template = chunk.clone();
+        template.usage = None;
+        self.last_chunk = Some(template);
+        (!held_back).then_some(chunk)
+    }
+
+    /// Text still held back when the stream ends, as a final chunk
+    pub fn flush(&mut self) -> Option<StreamingChunk> {
+        if self.termination.is_some() {
+            return None;
+        }
+        let mut pending: Vec<(u32, String)> = self
+            .pending
+            .drain()
+            .filter(|(_, text)| !text.is_empty())
+            .collect();
+        if pending.is_empty() {
+            return None;
+        }
+        pending.sort_by_key(|(index, _)| *index);
+
+        let mut chunk = self.last_chunk.clone()?;
+        let template = chunk.choices.first().cloned()?;
+        chunk.choices = pending
+            .into_iter()
+            .map(|(index, text)| {
+                let mut choice = template.clone();
+                choice.index = index;
+                choice.delta.content = text;
+                choice.delta.tool_calls = None;
+                choice.finish_reason = None;
+                choice
+            })
+            .collect();
+        Some(chunk)
+    }
+
+    /// Why the stream was terminated, if it was
+    pub fn termination(&self) -> Option<&FilterTermination> {
+        self.termination.as_ref()
+    }
+
+    fn terminated_chunk(&mut self, mut chunk: StreamingChunk) -> StreamingChunk {
+        self.pending.clear();
+        for choice in &mut chunk.choices {
+            choice.delta.content = String::new();
+            choice.delta.tool_calls = None;
+            choice.finish_reason = Some(CONTENT_FILTER_FINISH_REASON.to_string());
+        }
+        chunk.usage = None;
+        chunk
+    }
+}
+
+/// Split off everything but the last `holdback` bytes of `pending`
+fn release(pending: &mut String, holdback: usize) -> String {
+    let mut split = pending.len().saturating_sub(holdback);
+    while !pending.is_char_boundary(split) {
+        split -= 1;
+    }
+    let held = pending.split_off(split);
+    std::mem::replace(pending, held)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{ChatMessage, LLMProviderType, MessageRole, StreamingChoice};
+
+    fn chunk(content: &str, finish_reason: Option<&str>) -> StreamingChunk {
+        StreamingChunk {
+            id: "chatcmpl-1".to_string(),
+            object: "chat.completion.chunk".to_string(),
+            choices: vec![StreamingChoice {
+                index: 0,
+                delta: ChatMessage {
+                    role: MessageRole::Assistant,
+                    content: content.to_string(),
+                    name: None,
+                    function_call: None,
+                    tool_calls: None,
+                    tool_call_id: None,
+                    content_parts: None,
+                },
+                finish_reason: finish_reason.map(|r| r.to_string()),
+            }],
+            created: 0,
+            model: "gpt-4".to_string(),
+            provider: LLMProviderType::OpenAI,
+            usage: None,
+        }
+    }
+
+    fn sent(stage: &mut StreamFilterStage, parts: &[&str]) -> String {
+        let mut text = String::new();
+        for part in parts {
+            if let Some(sent) = stage.process(chunk(part, None)) {
+                text.push_str(&sent.choices[0].delta.content);
+            }
+        }
+        if let Some(flushed) = stage.flush() {
+            text.push_str(&flushed.choices[0].delta.content);
+        }
+        text
+    }
+
+    #[test]
+    fn test_redacts_secrets_split_across_chunks() {
+        let filters = StreamFilters::new()
+            .with_filter(Arc::new(PatternFilter::with_defaults()))
+            .with_holdback(16);
+        let mut stage = filters.stage();
+
+        let text = sent(
+            &mut stage,
+            &[
+                "Your key is AKIA",
+                "IOSFODNN7EXAMPLE and mail ",
+                "jane.doe@exam",
+                "ple.com for help, thanks for waiting ",
+            ],
+        );
+        assert_eq!(
+            text,
+            "Your key is [REDACTED:aws_access_key] and mail [REDACTED:email] for help, thanks for waiting "
+        );
+        assert!(stage.termination().is_none());
+    }
+
+    #[test]
+    fn test_blocked_keyword_terminates_stream() {
+        let filters = StreamFilters::new().with_filter(Arc::new(
+            PatternFilter::new().block_keywords(["Project X", ""]),
+        ));
+        let mut stage = filters.stage();
+
+        assert!(stage
+            .process(chunk("All about the secret project", None))
+            .is_none());
+        let last = stage.process(chunk(" x launch", None)).unwrap();
+        assert_eq!(
+            last.choices[0].finish_reason.as_deref(),
+            Some(CONTENT_FILTER_FINISH_REASON)
+        );
+        assert!(last.choices[0].delta.content.is_empty());
+        assert_eq!(stage.termination().unwrap().filter, "pattern");
+        assert!(stage.flush().is_none());
+
+        // Held-back text is released on the finishing chunk
+        let mut stage = StreamFilters::new()
+            .with_filter(Arc::new(PatternFilter::new()))
+            .stage();
+        assert!(stage.process(chunk("héllo", None)).is_none());
+        let last = stage.process(chunk(" world", Some("stop"))).unwrap();
+        assert_eq!(last.choices[0].delta.content, "héllo world");
+    }
+}
@@ -268,12 +268,16 @@ pub fn create_streaming_chunk(
                 content,
                 name: None,
                 function_call: None,
+                tool_calls: None,
+                tool_call_id: None,
+                content_parts: None,
             },
             finish_reason,
         }],
         created: chrono::Utc::now().timestamp() as u64,
         model,
         provider,
+        usage: None,
     }
 }
 
@@ -0,0 +1,407 @@
+//! Structured Output
+//!
+//! `response_format: json_schema` asks for a reply that conforms to a JSON schema. Each
+//! provider maps it to its native mechanism: OpenAI structured outputs, a forced tool
+//! call on Anthropic, `responseSchema` on Gemini and `format` on Ollama. Because those
+//! mechanisms differ in how strictly they hold, the router also validates the returned
+//! JSON against the schema, repairing common defects (code fences, surrounding prose,
+//! trailing commas) before the response reaches the caller.
+//!
+//! Validation needs the whole reply, so structured output is only available on
+//! non-streaming completions; a streamed request with a JSON `response_format` is
+//! rejected as invalid.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{LLMError, LLMResponse, LLMResult};
+
+/// Requested format of the model's reply
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// Free-form text (the default)
+    Text,
+    /// Any valid JSON object
+    JsonObject,
+    /// JSON conforming to a schema
+    JsonSchema { json_schema: JsonSchemaFormat },
+}
+
+/// Named JSON schema for `ResponseFormat::JsonSchema`, in OpenAI's wire format
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonSchemaFormat {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub schema: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+impl ResponseFormat {
+    /// Request JSON conforming to `schema`
+    pub fn json_schema(name: impl Into<String>, schema: Value) -> Self {
+        Self::JsonSchema {
+            json_schema: JsonSchemaFormat {
+                name: name.into(),
+                description: None,
+                schema,
+                strict: Some(true),
+            },
+        }
+    }
+
+    /// The schema the reply must conform to, if any
+    pub fn schema(&self) -> Option<&JsonSchemaFormat> {
+        match self {
+            Self::JsonSchema { json_schema } => Some(json_schema),
+            _ => None,
+        }
+    }
+
+    /// Whether the reply must be JSON
+    pub fn expects_json(&self) -> bool {
+        !matches!(self, Self::Text)
+    }
+}
+
+/// Validate `value` against `schema`, returning one message per violation
+///
+/// Supports the subset of JSON Schema that providers accept for structured output:
+/// `type`, `enum`, `const`, `properties`, `required`, `additionalProperties`, `items`,
+/// `anyOf`/`oneOf`, and length, size and range bounds.
+pub fn validate(value: &Value, schema: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    validate_at(value, schema, "$", &mut errors);
+    errors
+}
+
+fn validate_at(value: &Value, schema: &Value, path: &str, errors: &mut Vec<String>) {
+    let Some(schema) = schema.as_object() else {
+        if schema == &Value::Bool(false) {
+            errors.push(format!("{}: no value is allowed here", path));
+        }
+        return;
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        let nullable = schema.get("nullable").and_then(Value::as_bool) == Some(true);
+        let matches = allowed.is_empty()
+            || allowed.iter().any(|name| matches_type(value, name))
+            || (nullable && value.is_null());
+        if !matches {
+            errors.push(format!(
+                "{}: expected {}, found {}",
+                path,
+                allowed.join(" or "),
+                type_name(value)
+            ));
+            return;
+        }
+    }
+
+    if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+        if !options.contains(value) {
+            errors.push(format!(
+                "{}: {} is not one of the allowed values",
+                path, value
+            ));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            errors.push(format!("{}: expected {}", path, expected));
+        }
+    }
+
+    for (keyword, exactly_one) in [("anyOf", false), ("oneOf", true)] {
+        if let Some(branches) = schema.get(keyword).and_then(Value::as_array) {
+            let matching = branches
+                .iter()
+                .filter(|branch| validate(value, branch).is_empty())
+                .count();
+            if matching == 0 || (exactly_one && matching > 1) {
+                errors.push(format!("{}: does not match {}", path, keyword));
+            }
+        }
+    }
+
+    match value {
+        Value::Object(object) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            if let Some(required) = schema.get("required").and_then(Value::as_array) {
+                for name in required.iter().filter_map(Value::as_str) {
+                    if !object.contains_key(name) {
+                        errors.push(format!("{}: missing required property '{}'", path, name));
+                    }
+                }
+            }
+            for (name, property) in object {
+                let property_path = format!("{}.{}", path, name);
+                match properties.and_then(|properties| properties.get(name)) {
+                    Some(property_schema) => {
+                        validate_at(property, property_schema, &property_path, errors)
+                    }
+                    None => match schema.get("additionalProperties") {
+                        Some(Value::Bool(false)) => {
+                            errors.push(format!("{}: unexpected property", property_path))
+                        }
+                        Some(additional @ Value::Object(_)) => {
+                            validate_at(property, additional, &property_path, errors)
+                        }
+                        _ => {}
+                    },
+                }
+            }
+        }
+        Value::Array(items) => {
+            check_bounds(
+                path,
+                items.len() as f64,
+                schema,
+                "minItems",
+                "maxItems",
+                errors,
+            );
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    validate_at(item, item_schema, &format!("{}[{}]", path, index), errors);
+                }
+            }
+        }
+        Value::String(text) => {
+            let length = text.chars().count() as f64;
+            check_bounds(path, length, schema, "minLength", "maxLength", errors);
+        }
+        Value::Number(number) => {
+            if let Some(number) = number.as_f64() {
+                check_bounds(path, number, schema, "minimum", "maximum", errors);
+            }
+        }
+        Value::Bool(_) | Value::Null => {}
+    }
+}
+
+fn check_bounds(
+    path: &str,
+    actual: f64,
+    schema: &serde_json::Map<String, Value>,
+    min_keyword: &str,
+    max_keyword: &str,
+    errors: &mut Vec<String>,
+) {
+    if let Some(min) = schema.get(min_keyword).and_then(Value::as_f64) {
+        if actual < min {
+            errors.push(format!("{}: below {} of {}", path, min_keyword, min));
+        }
+    }
+    if let Some(max) = schema.get(max_keyword).and_then(Value::as_f64) {
+        if actual > max {
+            errors.push(format!("{}: above {} of {}", path, max_keyword, max));
+        }
+    }
+}
+
+fn matches_type(value: &Value, name: &str) -> bool {
+    match name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Object(_) => "object",
+        Value::Array(_) => "array",
+        Value::String(_) => "string",
+        Value::Number(_) => "number",
+        Value::Bool(_) => "boolean",
+        Value::Null => "null",
+    }
+}
+
+/// Parse the JSON in a model reply, repairing common defects: Markdown code fences,
+/// prose around the JSON, and trailing commas
+pub fn extract_json(text: &str) -> Option<Value> {
+    let trimmed = text.trim();
+    if let Ok(value) = serde_json::from_str(trimmed) {
+        return Some(value);
+    }
+
+    let unfenced = strip_code_fence(trimmed);
+    let start = unfenced.find(['{', '['])?;
+    let close = if unfenced[start..].starts_with('{') {
+        '}'
+    } else {
+        ']'
+    };
+    let end = unfenced.rfind(close)?;
+    if end < start {
+        return None;
+    }
+    let candidate = &unfenced[start..=end];
+
+    serde_json::from_str(candidate)
+        .ok()
+        .or_else(|| serde_json::from_str(&remove_trailing_commas(candidate)).ok())
+}
+
+fn strip_code_fence(text: &str) -> &str {
+    let Some(rest) = text.strip_prefix("```") else {
+        return text;
+    };
+    // Skip the language tag on the opening fence
+    let rest = rest.split_once('\n').map_or(rest, |(_, body)| body);
+    rest.trim_end().strip_suffix("```").unwrap_or(rest)
+}
+
+fn remove_trailing_commas(json: &str) -> String {
+    let mut repaired = String::with_capacity(json.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    let chars: Vec<char> = json.chars().collect();
+
+    for (index, &c) in chars.iter().enumerate() {
+        if in_string {
+            // A closing quote ends the string unless it is escaped
+            in_string = c != '"' || escaped;
+            escaped = c == '\\' && !escaped;
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' {
+            let next = chars[index + 1..].iter().find(|c| !c.is_whitespace());
+            if matches!(next, Some('}') | Some(']')) {
+                continue;
+            }
+        }
+        repaired.push(c);
+    }
+    repaired
+}
+
+/// Make every choice in `response` conform to `format`, rewriting repaired JSON in place
+///
+/// Returns the number of choices whose JSON had to be repaired.
+pub fn enforce(response: &mut LLMResponse, format: &ResponseFormat) -> LLMResult<usize> {
+    if !format.expects_json() {
+        return Ok(0);
+    }
+
+    let mut repaired = 0;
+    for choice in &mut response.choices {
+        if serde_json::from_str::<Value>(&choice.message.content).is_err() {
+            repaired += 1;
+        }
+
+        let value = extract_json(&choice.message.content).ok_or_else(|| {
+            LLMError::StructuredOutput(format!("choice {} is not valid JSON", choice.index))
+        })?;
+
+        match format.schema() {
+            Some(json_schema) => {
+                let errors = validate(&value, &json_schema.schema);
+                if !errors.is_empty() {
+                    return Err(LLMError::StructuredOutput(format!(
+                        "choice {} does not match schema '{}': {}",
+                        choice.index,
+                        json_schema.name,
+                        errors.join("; ")
+                    )));
+                }
+            }
+            None if !value.is_object() => {
+                return Err(LLMError::StructuredOutput(format!(
+                    "choice {} is not a JSON object",
+                    choice.index
+                )));
+            }
+            None => {}
+        }
+
+        choice.message.content = value.to_string();
+    }
+    Ok(repaired)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn person_schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string", "minLength": 1 },
+                "age": { "type": "integer", "minimum": 0 },
+                "tags": { "type": "array", "items": { "type": "string" } }
+            },
+            "required": ["name", "age"],
+            "additionalProperties": false
+        })
+    }
+
+    #[test]
+    fn test_response_format_wire_format() {
+        let format: ResponseFormat = serde_json::from_value(json!({
+            "type": "json_schema",
+            "json_schema": { "name": "person", "schema": person_schema(), "strict": true }
+        }))
+        .unwrap();
+        assert_eq!(
+            format,
+            ResponseFormat::json_schema("person", person_schema())
+        );
+
+        let format: ResponseFormat =
+            serde_json::from_value(json!({ "type": "json_object" })).unwrap();
+        assert_eq!(format, ResponseFormat::JsonObject);
+    }
+
+    #[test]
+    fn test_validate_reports_violations() {
+        let schema = person_schema();
+        assert!(validate(&json!({ "name": "Ada", "age": 36 }), &schema).is_empty());
+
+        let errors = validate(
+            &json!({ "name": "", "age": "36", "tags": [1], "extra": true }),
+            &schema,
+        );
+        assert_eq!(errors.len(), 4, "{:?}", errors);
+        assert!(errors
+            .iter()
+            .any(|e| e.starts_with("$.age: expected integer")));
+        assert!(errors.iter().any(|e| e.starts_with("$.tags[0]")));
+        assert!(errors.iter().any(|e| e.contains("unexpected property")));
+
+        let errors = validate(&json!({ "age": 1 }), &schema);
+        assert_eq!(errors, vec!["$: missing required property 'name'"]);
+    }
+
+    #[test]
+    fn test_extract_json_repairs_common_defects() {
+        let expected = json!({ "name": "Ada", "age": 36 });
+        assert_eq!(
+            extract_json("```json\n{\"name\": \"Ada\", \"age\": 36}\n```"),
+            Some(expected.clone())
+        );
+        assert_eq!(
+            extract_json("Here you go: {\"name\": \"Ada\", \"age\": 36,} Hope it helps!"),
+            Some(expected)
+        );
+        assert_eq!(extract_json("[\"a, ]\",]"), Some(json!(["a, ]"])));
+        assert_eq!(extract_json("no json here"), None);
+    }
+}
@@ -0,0 +1,148 @@
+//! Tenant Routing Policies
+//!
+//! Routing strategy is global by default. This module lets each tenant override it:
+//! the smart routing strategy used for virtual models, which providers may serve the
+//! tenant's requests, and which concrete models the tenant is allowed to call.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use super::LLMProviderType;
+use crate::api::types::SmartRoutingStrategy;
+
+/// Identifier of the tenant a request belongs to
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TenantId(pub String);
+
+impl TenantId {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for TenantId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Routing overrides for a single tenant
+///
+/// Every field is optional; `None` means "use the global behaviour".
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TenantRoutingPolicy {
+    /// Strategy used when resolving virtual models (`auto`, `cb:*`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<SmartRoutingStrategy>,
+    /// Providers allowed to serve this tenant's requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_providers: Option<Vec<LLMProviderType>>,
+    /// Concrete models this tenant may use
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_allowlist: Option<Vec<String>>,
+}
+
+impl TenantRoutingPolicy {
+    /// Whether the policy permits routing to `provider`
+    pub fn allows_provider(&self, provider: &LLMProviderType) -> bool {
+        self.allowed_providers
+            .as_ref()
+            .is_none_or(|providers| providers.contains(provider))
+    }
+
+    /// Whether the policy permits calling `model`
+    pub fn allows_model(&self, model: &str) -> bool {
+        self.model_allowlist
+            .as_ref()
+            .is_none_or(|models| models.iter().any(|allowed| allowed == model))
+    }
+
+    /// Whether the policy permits `model` served by `provider`
+    pub fn allows(&self, provider: &LLMProviderType, model: &str) -> bool {
+        self.allows_provider(provider) && self.allows_model(model)
+    }
+}
+
+/// In-memory store of tenant routing policies
+#[derive(Debug, Clone, Default)]
+pub struct TenantPolicyStore {
+    policies: Arc<RwLock<HashMap<TenantId, TenantRoutingPolicy>>>,
+}
+
+impl TenantPolicyStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the policy for a tenant, if one is set
+    pub async fn get(&self, tenant: &TenantId) -> Option<TenantRoutingPolicy> {
+        self.policies.read().await.get(tenant).cloned()
+    }
+
+    /// Set or replace the policy for a tenant
+    pub async fn set(&self, tenant: TenantId, policy: TenantRoutingPolicy) {
+        self.policies.write().await.insert(tenant, policy);
+    }
+
+    /// Remove the policy for a tenant, returning the previous one
+    pub async fn remove(&self, tenant: &TenantId) -> Option<TenantRoutingPolicy> {
+        self.policies.write().await.remove(tenant)
+    }
+
+    /// List all tenant policies
+    pub async fn list(&self) -> Vec<(TenantId, TenantRoutingPolicy)> {
+        self.policies
+            .read()
+            .await
+            .iter()
+            .map(|(tenant, policy)| (tenant.clone(), policy.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_policy_allows_everything() {
+        let policy = TenantRoutingPolicy::default();
+        assert!(policy.allows(&LLMProviderType::OpenAI, "gpt-4"));
+    }
+
+    #[test]
+    fn test_policy_restricts_providers_and_models() {
+        let policy = TenantRoutingPolicy {
+            strategy: None,
+            allowed_providers: Some(vec![LLMProviderType::Anthropic]),
+            model_allowlist: Some(vec!["claude-3-haiku-20240307".to_string()]),
+        };
+
+        assert!(policy.allows(&LLMProviderType::Anthropic, "claude-3-haiku-20240307"));
+        assert!(!policy.allows(&LLMProviderType::OpenAI, "claude-3-haiku-20240307"));
+        assert!(!policy.allows(&LLMProviderType::Anthropic, "claude-3-opus-20240229"));
+    }
+
+    #[tokio::test]
+    async fn test_policy_store_roundtrip() {
+        let store = TenantPolicyStore::new();
+        let tenant = TenantId::new("acme");
+        assert!(store.get(&tenant).await.is_none());
+
+        let policy = TenantRoutingPolicy {
+            strategy: Some(SmartRoutingStrategy::CostOptimized),
+            ..Default::default()
+        };
+        store.set(tenant.clone(), policy.clone()).await;
+        assert_eq!(store.get(&tenant).await, Some(policy));
+
+        assert!(store.remove(&tenant).await.is_some());
+        assert!(store.list().await.is_empty());
+    }
+}
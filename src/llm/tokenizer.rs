@@ -0,0 +1,184 @@
+//! Prompt Token Counting
+//!
+//! One estimator shared by budget checks, context-window routing and `POST /v1/tokenize`.
+//! OpenAI models are counted with the BPE encoding the model uses, matching tiktoken.
+//! Other providers do not ship a tokenizer we can run locally, so their text is counted
+//! with a heuristic that leans towards overestimating: a budget or context check that
+//! is slightly too cautious is cheaper than one the provider rejects.
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use tiktoken_rs::CoreBPE;
+
+use super::ChatMessage;
+
+/// Per-message overhead for role markers and separators
+pub const TOKENS_PER_MESSAGE: u32 = 4;
+
+/// Tokens used to prime the assistant reply
+pub const REPLY_PRIMING_TOKENS: u32 = 3;
+
+/// Heuristic ASCII characters per token
+const ASCII_CHARS_PER_TOKEN: usize = 4;
+
+lazy_static! {
+    // Loaded on first use; `None` if the bundled ranks fail to load, which falls back
+    // to the heuristic rather than failing requests
+    static ref O200K_BASE: Option<CoreBPE> = tiktoken_rs::o200k_base().ok();
+    static ref CL100K_BASE: Option<CoreBPE> = tiktoken_rs::cl100k_base().ok();
+}
+
+/// How tokens are counted for a model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Tokenizer {
+    /// GPT-4o, GPT-4.1 and o-series models
+    O200kBase,
+    /// GPT-4, GPT-3.5 and OpenAI embedding models
+    Cl100kBase,
+    /// Character-based estimate for models without a local tokenizer
+    Heuristic,
+}
+
+impl Tokenizer {
+    /// Tokenizer for a model name, with or without an `openai/` prefix
+    pub fn for_model(model: &str) -> Self {
+        let model = model.strip_prefix("openai/").unwrap_or(model);
+        match tiktoken_rs::tokenizer::get_tokenizer(model) {
+            Some(tiktoken_rs::tokenizer::Tokenizer::O200kBase) => return Self::O200kBase,
+            Some(tiktoken_rs::tokenizer::Tokenizer::Cl100kBase) => return Self::Cl100kBase,
+            _ => {}
+        }
+        // Newer families tiktoken-rs does not list yet
+        if ["gpt-4o", "gpt-4.1", "gpt-4.5", "gpt-5", "o1", "o3", "o4"]
+            .iter()
+            .any(|family| model.starts_with(family))
+        {
+            Self::O200kBase
+        } else if ["gpt-4", "gpt-3.5", "text-embedding-"]
+            .iter()
+            .any(|family| model.starts_with(family))
+        {
+            Self::Cl100kBase
+        } else {
+            Self::Heuristic
+        }
+    }
+
+    /// Whether counts match what the provider bills
+    pub fn is_exact(&self) -> bool {
+        self.bpe().is_some()
+    }
+
+    fn bpe(&self) -> Option<&'static CoreBPE> {
+        match self {
+            Self::O200kBase => O200K_BASE.as_ref(),
+            Self::Cl100kBase => CL100K_BASE.as_ref(),
+            Self::Heuristic => None,
+        }
+    }
+
+    /// Tokens in a piece of text
+    pub fn count(&self, text: &str) -> u32 {
+        match self.bpe() {
+            Some(bpe) => bpe.encode_ordinary(text).len() as u32,
+            None => heuristic_tokens(text),
+        }
+    }
+
+    /// Tokens in a single message, including its role and tool-call overhead
+    pub fn count_message(&self, message: &ChatMessage) -> u32 {
+        let mut tokens = TOKENS_PER_MESSAGE + self.count(&message.content);
+        if let Some(name) = &message.name {
+            tokens += self.count(name);
+        }
+        if let Some(function_call) = &message.function_call {
+            tokens += self.count(&function_call.name) + self.count(&function_call.arguments);
+        }
+        for call in message.tool_calls.iter().flatten() {
+            tokens += self.count(&call.function.name) + self.count(&call.function.arguments);
+        }
+        tokens
+    }
+
+    /// Prompt tokens of a conversation
+    pub fn count_prompt(&self, messages: &[ChatMessage]) -> u32 {
+        messages
+            .iter()
+            .map(|message| self.count_message(message))
+            .sum::<u32>()
+            + REPLY_PRIMING_TOKENS
+    }
+}
+
+/// Character-based token estimate: ~4 ASCII characters per token, and ~1 token per
+/// non-ASCII character since CJK text and accented scripts rarely merge into longer tokens
+fn heuristic_tokens(text: &str) -> u32 {
+    let (ascii, other) = text.chars().fold((0usize, 0usize), |(ascii, other), c| {
+        if c.is_ascii() {
+            (ascii + 1, other)
+        } else {
+            (ascii, other + 1)
+        }
+    });
+    (ascii.div_ceil(ASCII_CHARS_PER_TOKEN) + other) as u32
+}
+
+/// Prompt tokens of a conversation sent to `model`
+pub fn count_prompt_tokens(model: &str, messages: &[ChatMessage]) -> u32 {
+    Tokenizer::for_model(model).count_prompt(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::MessageRole;
+
+    fn user(content: &str) -> ChatMessage {
+        ChatMessage {
+            role: MessageRole::User,
+            content: content.to_string(),
+            name: None,
+            function_call: None,
+            tool_calls: None,
+            tool_call_id: None,
+            content_parts: None,
+        }
+    }
+
+    #[test]
+    fn test_tokenizer_for_model() {
+        assert_eq!(Tokenizer::for_model("gpt-4o-mini"), Tokenizer::O200kBase);
+        assert_eq!(Tokenizer::for_model("o3-mini"), Tokenizer::O200kBase);
+        assert_eq!(Tokenizer::for_model("openai/gpt-4"), Tokenizer::Cl100kBase);
+        assert_eq!(Tokenizer::for_model("gpt-3.5-turbo"), Tokenizer::Cl100kBase);
+        assert_eq!(
+            Tokenizer::for_model("claude-3-5-sonnet-20241022"),
+            Tokenizer::Heuristic
+        );
+        assert!(!Tokenizer::Heuristic.is_exact());
+    }
+
+    #[test]
+    fn test_bpe_counts_match_tiktoken() {
+        // Reference counts from tiktoken
+        assert_eq!(Tokenizer::Cl100kBase.count("hello world"), 2);
+        assert_eq!(Tokenizer::O200kBase.count("hello world"), 2);
+        assert!(Tokenizer::O200kBase.is_exact());
+
+        let messages = vec![user("hello world")];
+        assert_eq!(
+            count_prompt_tokens("gpt-4o", &messages),
+            2 + TOKENS_PER_MESSAGE + REPLY_PRIMING_TOKENS
+        );
+    }
+
+    #[test]
+    fn test_heuristic_counts() {
+        assert_eq!(Tokenizer::Heuristic.count("abcdefgh"), 2);
+        assert_eq!(Tokenizer::Heuristic.count("abcdefghi"), 3);
+        // Non-ASCII characters count one token each
+        assert_eq!(Tokenizer::Heuristic.count("日本語"), 3);
+        assert_eq!(Tokenizer::Heuristic.count(""), 0);
+    }
+}
@@ -0,0 +1,606 @@
+//! Tool Calling
+//!
+//! Clients define tools and receive tool calls in OpenAI's format: `tools` and
+//! `tool_choice` on the request, `tool_calls` on assistant messages, and `tool` role
+//! messages carrying results. This module converts that format to each provider's
+//! native shape and back, so tool calling behaves the same wherever a request is
+//! routed. OpenAI-compatible APIs take it as is, Anthropic uses `tool_use` and
+//! `tool_result` content blocks, and Gemini uses `functionCall` and `functionResponse`
+//! parts, which are matched by function name rather than call ID.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use super::providers::anthropic::types::{
+    AnthropicContentBlock, AnthropicMessage, AnthropicMessageContent, AnthropicRequestBlock,
+    AnthropicTool, AnthropicToolChoice,
+};
+use super::providers::google::types::{
+    to_gemini_schema, GoogleContent, GoogleFunctionCall, GoogleFunctionCallingConfig,
+    GoogleFunctionDeclaration, GoogleFunctionResponse, GooglePart, GoogleTool, GoogleToolConfig,
+};
+use super::providers::openai::types as openai;
+use super::{ChatMessage, FunctionCall, FunctionDefinition, MessageRole};
+
+/// A tool the model may call, e.g.
+/// `{"type": "function", "function": {"name": "get_weather", "parameters": {...}}}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolDefinition {
+    #[serde(rename = "type", default = "function_type")]
+    pub tool_type: String,
+    pub function: FunctionDefinition,
+}
+
+/// Whether and which tool the model must call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToolChoice {
+    /// The model decides (the default when tools are given)
+    Auto,
+    /// The model must not call tools
+    None,
+    /// The model must call at least one tool
+    Required,
+    /// The model must call the named function
+    Function(String),
+}
+
+impl Serialize for ToolChoice {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ToolChoice::Auto => serializer.serialize_str("auto"),
+            ToolChoice::None => serializer.serialize_str("none"),
+            ToolChoice::Required => serializer.serialize_str("required"),
+            ToolChoice::Function(name) => {
+                json!({ "type": "function", "function": { "name": name } }).serialize(serializer)
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ToolChoice {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        match &value {
+            Value::String(choice) => match choice.as_str() {
+                "auto" => Ok(ToolChoice::Auto),
+                "none" => Ok(ToolChoice::None),
+                "required" => Ok(ToolChoice::Required),
+                other => Err(serde::de::Error::unknown_variant(
+                    other,
+                    &["auto", "none", "required"],
+                )),
+            },
+            _ => value
+                .pointer("/function/name")
+                .and_then(Value::as_str)
+                .map(|name| ToolChoice::Function(name.to_string()))
+                .ok_or_else(|| {
+                    serde::de::Error::custom(
+                        "tool_choice must be a string or {\"type\": \"function\", \"function\": {\"name\": ...}}",
+                    )
+                }),
+        }
+    }
+}
+
+/// A tool call made by the model, e.g.
+/// `{"id": "call_1", "type": "function", "function": {"name": ..., "arguments": "{...}"}}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    /// Position of the call in a streamed message, whose calls arrive in pieces
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<u32>,
+    #[serde(default)]
+    pub id: String,
+    #[serde(rename = "type", default = "function_type")]
+    pub call_type: String,
+    #[serde(default)]
+    pub function: FunctionCall,
+}
+
+impl ToolCall {
+    pub fn new(id: impl Into<String>, name: impl Into<String>, arguments: &Value) -> Self {
+        Self {
+            index: None,
+            id: id.into(),
+            call_type: function_type(),
+            function: FunctionCall {
+                name: name.into(),
+                arguments: arguments.to_string(),
+            },
+        }
+    }
+
+    /// Piece of a streamed call at `index`; the first piece carries the id and name,
+    /// later ones only a fragment of the arguments
+    pub fn delta(
+        index: u32,
+        id: impl Into<String>,
+        name: impl Into<String>,
+        arguments: impl Into<String>,
+    ) -> Self {
+        Self {
+            index: Some(index),
+            id: id.into(),
+            call_type: function_type(),
+            function: FunctionCall {
+                name: name.into(),
+                arguments: arguments.into(),
+            },
+        }
+    }
+
+    /// Arguments as JSON, repairing the invalid JSON models occasionally emit
+    pub fn arguments(&self) -> Value {
+        super::structured::extract_json(&self.function.arguments).unwrap_or_else(|| json!({}))
+    }
+}
+
+fn function_type() -> String {
+    "function".to_string()
+}
+
+/// Schema of a function without arguments
+pub(crate) fn empty_parameters() -> Value {
+    json!({ "type": "object", "properties": {} })
+}
+
+/// Deserialize `null` as an empty string; OpenAI sends `"content": null` for messages
+/// that only call tools
+pub(crate) fn deserialize_nullable_string<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<String, D::Error> {
+    Ok(Option::<String>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// ID for a call from a provider that does not assign one
+pub(crate) fn generate_call_id() -> String {
+    format!("call_{}", uuid::Uuid::new_v4().simple())
+}
+
+fn has_tool_calls(message: &ChatMessage) -> bool {
+    message
+        .tool_calls
+        .as_ref()
+        .is_some_and(|calls| !calls.is_empty())
+}
+
+/// OpenAI's finish reason for a message: `tool_calls` whenever the model called tools
+pub fn finish_reason(message: &ChatMessage, native: Option<String>) -> Option<String> {
+    if has_tool_calls(message) {
+        Some("tool_calls".to_string())
+    } else {
+        native
+    }
+}
+
+// OpenAI and OpenAI-compatible APIs
+
+pub fn to_openai_tools(functions: &[FunctionDefinition]) -> Vec<openai::Tool> {
+    functions
+        .iter()
+        .map(|function| openai::Tool {
+            tool_type: function_type(),
+            function: openai::Function {
+                name: function.name.clone(),
+                description: function.description.clone(),
+                parameters: function.parameters.clone(),
+            },
+        })
+        .collect()
+}
+
+pub fn to_openai_tool_choice(choice: &ToolChoice) -> openai::ToolChoice {
+    match choice {
+        ToolChoice::Auto => openai::ToolChoice::Auto("auto".to_string()),
+        ToolChoice::None => openai::ToolChoice::Auto("none".to_string()),
+        ToolChoice::Required => openai::ToolChoice::Auto("required".to_string()),
+        ToolChoice::Function(name) => openai::ToolChoice::Specific {
+            choice_type: function_type(),
+            function: openai::FunctionChoice { name: name.clone() },
+        },
+    }
+}
+
+pub fn to_openai_tool_calls(calls: &[ToolCall]) -> Vec<openai::ToolCall> {
+    calls
+        .iter()
+        .map(|call| openai::ToolCall {
+            id: call.id.clone(),
+            call_type: call.call_type.clone(),
+            function: openai::FunctionCall {
+                name: call.function.name.clone(),
+                arguments: call.function.arguments.clone(),
+            },
+        })
+        .collect()
+}
+
+// Anthropic
+
+pub fn to_anthropic_tools(functions: &[FunctionDefinition]) -> Vec<AnthropicTool> {
+    functions
+        .iter()
+        .map(|function| AnthropicTool {
+            name: function.name.clone(),
+            description: (!function.description.is_empty()).then(|| function.description.clone()),
+            input_schema: function.parameters.clone(),
+        })
+        .collect()
+}
+
+pub fn to_anthropic_tool_choice(choice: &ToolChoice) -> AnthropicToolChoice {
+    let (choice_type, name) = match choice {
+        ToolChoice::Auto => ("auto", None),
+        ToolChoice::None => ("none", None),
+        ToolChoice::Required => ("any", None),
+        ToolChoice::Function(name) => ("tool", Some(name.clone())),
+    };
+    AnthropicToolChoice {
+        choice_type: choice_type.to_string(),
+        name,
+    }
+}
+
+/// Convert the non-system messages of a conversation to Anthropic messages
+///
+/// Assistant tool calls become `tool_use` blocks, and consecutive tool results are
+/// grouped into a single user message of `tool_result` blocks as Anthropic requires.
+pub fn to_anthropic_messages(messages: &[ChatMessage]) -> Vec<AnthropicMessage> {
+    let mut converted: Vec<AnthropicMessage> = Vec::new();
+
+    for message in messages {
+        match message.role {
+            MessageRole::System => {}
+            MessageRole::Tool => {
+                let result = AnthropicRequestBlock::ToolResult {
+                    tool_use_id: message.tool_call_id.clone().unwrap_or_default(),
+                    content: message.content.clone(),
+                };
+                match converted.last_mut() {
+                    Some(AnthropicMessage {
+                        role,
+                        content: AnthropicMessageContent::Blocks(blocks),
+                    }) if role == "user"
+                        && blocks.iter().all(|block| {
+                            matches!(block, AnthropicRequestBlock::ToolResult { .. })
+                        }) =>
+                    {
+                        blocks.push(result)
+                    }
+                    _ => converted.push(AnthropicMessage {
+                        role: "user".to_string(),
+                        content: AnthropicMessageContent::Blocks(vec![result]),
+                    }),
+                }
+            }
+            MessageRole::Assistant if has_tool_calls(message) => {
+                let mut blocks = Vec::new();
+                if !message.content.is_empty() {
+                    blocks.push(AnthropicRequestBlock::Text {
+                        text: message.content.clone(),
+                    });
+                }
+                for call in message.tool_calls.iter().flatten() {
+                    blocks.push(AnthropicRequestBlock::ToolUse {
+                        id: call.id.clone(),
+                        name: call.function.name.clone(),
+                        input: call.arguments(),
+                    });
+                }
+                converted.push(AnthropicMessage {
+                    role: "assistant".to_string(),
+                    content: AnthropicMessageContent::Blocks(blocks),
+                });
+            }
+            _ => converted.push(AnthropicMessage::from(message)),
+        }
+    }
+    converted
+}
+
+/// Split Anthropic response content into text and tool calls
+///
+/// A call to `structured_output_tool` carries a structured reply and is rendered as
+/// JSON text instead of a tool call.
+pub fn from_anthropic_content(
+    blocks: &[AnthropicContentBlock],
+    structured_output_tool: Option<&str>,
+) -> (String, Vec<ToolCall>) {
+    let mut text = Vec::new();
+    let mut tool_calls = Vec::new();
+
+    for block in blocks {
+        match (&block.input, block.content_type.as_str()) {
+            (Some(input), "tool_use")
+                if structured_output_tool.is_some()
+                    && block.name.as_deref() == structured_output_tool =>
+            {
+                text.push(input.to_string())
+            }
+            (Some(input), "tool_use") => tool_calls.push(ToolCall::new(
+                block.id.clone().unwrap_or_else(generate_call_id),
+                block.name.clone().unwrap_or_default(),
+                input,
+            )),
+            _ => text.push(block.text.clone()),
+        }
+    }
+    (text.join("\n"), tool_calls)
+}
+
+// Google Gemini
+
+pub fn to_google_tools(functions: &[FunctionDefinition]) -> Vec<GoogleTool> {
+    let function_declarations = functions
+        .iter()
+        .map(|function| {
+            let has_properties = function
+                .parameters
+                .get("properties")
+                .and_then(Value::as_object)
+                .is_some_and(|properties| !properties.is_empty());
+            GoogleFunctionDeclaration {
+                name: function.name.clone(),
+                description: function.description.clone(),
+                parameters: has_properties.then(|| to_gemini_schema(&function.parameters)),
+            }
+        })
+        .collect();
+    vec![GoogleTool {
+        function_declarations,
+    }]
+}
+
+pub fn to_google_tool_config(choice: &ToolChoice) -> GoogleToolConfig {
+    let (mode, allowed_function_names) = match choice {
+        ToolChoice::Auto => ("AUTO", None),
+        ToolChoice::None => ("NONE", None),
+        ToolChoice::Required => ("ANY", None),
+        ToolChoice::Function(name) => ("ANY", Some(vec![name.clone()])),
+    };
+    GoogleToolConfig {
+        function_calling_config: GoogleFunctionCallingConfig {
+            mode: mode.to_string(),
+            allowed_function_names,
+        },
+    }
+}
+
+/// Convert the non-system messages of a conversation to Gemini contents
+///
+/// Tool calls become `functionCall` parts, and tool results become `functionResponse`
+/// parts named after the function they answer, grouped into one turn per batch of calls.
+pub fn to_google_contents(messages: &[ChatMessage]) -> Vec<GoogleContent> {
+    let function_names: HashMap<&str, &str> = messages
+        .iter()
+        .flat_map(|message| message.tool_calls.iter().flatten())
+        .map(|call| (call.id.as_str(), call.function.name.as_str()))
+        .collect();
+    let mut contents: Vec<GoogleContent> = Vec::new();
+
+    for message in messages {
+        match message.role {
+            MessageRole::System => {}
+            MessageRole::Tool => {
+                let name = message
+                    .tool_call_id
+                    .as_deref()
+                    .and_then(|id| function_names.get(id).copied())
+                    .or(message.name.as_deref())
+                    .unwrap_or_default()
+                    .to_string();
+                // Gemini expects an object; wrap plain-text results
+                let response = match serde_json::from_str::<Value>(&message.content) {
+                    Ok(object @ Value::Object(_)) => object,
+                    _ => json!({ "content": message.content }),
+                };
+                let part = GooglePart {
+                    function_response: Some(GoogleFunctionResponse { name, response }),
+                    ..Default::default()
+                };
+                match contents.last_mut() {
+                    Some(content)
+                        if content.role.as_deref() == Some("user")
+                            && content
+                                .parts
+                                .iter()
+                                .all(|part| part.function_response.is_some()) =>
+                    {
+                        content.parts.push(part)
+                    }
+                    _ => contents.push(GoogleContent {
+                        parts: vec![part],
+                        role: Some("user".to_string()),
+                    }),
+                }
+            }
+            MessageRole::Assistant if has_tool_calls(message) => {
+                let mut parts = Vec::new();
+                if !message.content.is_empty() {
+                    parts.push(GooglePart {
+                        text: message.content.clone(),
+                        ..Default::default()
+                    });
+                }
+                for call in message.tool_calls.iter().flatten() {
+                    parts.push(GooglePart {
+                        function_call: Some(GoogleFunctionCall {
+                            name: call.function.name.clone(),
+                            args: call.arguments(),
+                        }),
+                        ..Default::default()
+                    });
+                }
+                contents.push(GoogleContent {
+                    parts,
+                    role: Some("model".to_string()),
+                });
+            }
+            _ => contents.push(GoogleContent::from(message)),
+        }
+    }
+    contents
+}
+
+/// Split Gemini response parts into text and tool calls, assigning call IDs
+pub fn from_google_parts(parts: &[GooglePart]) -> (String, Vec<ToolCall>) {
+    let text = parts
+        .iter()
+        .map(|part| part.text.as_str())
+        .collect::<Vec<_>>()
+        .join("");
+    let tool_calls = parts
+        .iter()
+        .filter_map(|part| part.function_call.as_ref())
+        .map(|call| ToolCall::new(generate_call_id(), call.name.clone(), &call.args))
+        .collect();
+    (text, tool_calls)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: MessageRole, content: &str) -> ChatMessage {
+        ChatMessage {
+            role,
+            content: content.to_string(),
+            name: None,
+            function_call: None,
+            tool_calls: None,
+            tool_call_id: None,
+            content_parts: None,
+        }
+    }
+
+    fn weather_conversation() -> Vec<ChatMessage> {
+        let mut assistant = message(MessageRole::Assistant, "");
+        assistant.tool_calls = Some(vec![
+            ToolCall::new("call_1", "get_weather", &json!({ "city": "Paris" })),
+            ToolCall::new("call_2", "get_weather", &json!({ "city": "Rome" })),
+        ]);
+        let mut paris = message(MessageRole::Tool, r#"{"temperature": 21}"#);
+        paris.tool_call_id = Some("call_1".to_string());
+        let mut rome = message(MessageRole::Tool, "sunny");
+        rome.tool_call_id = Some("call_2".to_string());
+
+        vec![
+            message(MessageRole::System, "Be brief"),
+            message(MessageRole::User, "Weather in Paris and Rome?"),
+            assistant,
+            paris,
+            rome,
+        ]
+    }
+
+    #[test]
+    fn test_openai_wire_format() {
+        let tools: Vec<ToolDefinition> = serde_json::from_value(json!([
+            { "type": "function", "function": { "name": "now" } }
+        ]))
+        .unwrap();
+        assert_eq!(tools[0].function.parameters, empty_parameters());
+
+        for (wire, choice) in [
+            (json!("auto"), ToolChoice::Auto),
+            (json!("required"), ToolChoice::Required),
+            (
+                json!({ "type": "function", "function": { "name": "now" } }),
+                ToolChoice::Function("now".to_string()),
+            ),
+        ] {
+            assert_eq!(
+                serde_json::from_value::<ToolChoice>(wire.clone()).unwrap(),
+                choice
+            );
+            assert_eq!(serde_json::to_value(&choice).unwrap(), wire);
+        }
+        assert!(serde_json::from_value::<ToolChoice>(json!("sometimes")).is_err());
+
+        // Messages that only call tools arrive with null content
+        let reply: ChatMessage = serde_json::from_value(json!({
+            "role": "assistant",
+            "content": null,
+            "tool_calls": [{
+                "id": "call_1",
+                "type": "function",
+                "function": { "name": "now", "arguments": "{}" }
+            }]
+        }))
+        .unwrap();
+        assert_eq!(reply.content, "");
+        assert_eq!(reply.tool_calls.unwrap()[0].function.name, "now");
+    }
+
+    #[test]
+    fn test_anthropic_round_trip() {
+        let messages = to_anthropic_messages(&weather_conversation());
+        assert_eq!(messages.len(), 3);
+        assert_eq!(
+            messages[1].content,
+            AnthropicMessageContent::Blocks(vec![
+                AnthropicRequestBlock::ToolUse {
+                    id: "call_1".to_string(),
+                    name: "get_weather".to_string(),
+                    input: json!({ "city": "Paris" }),
+                },
+                AnthropicRequestBlock::ToolUse {
+                    id: "call_2".to_string(),
+                    name: "get_weather".to_string(),
+                    input: json!({ "city": "Rome" }),
+                },
+            ])
+        );
+        // Both results share one user turn
+        assert_eq!(messages[2].role, "user");
+        assert!(matches!(
+            &messages[2].content,
+            AnthropicMessageContent::Blocks(blocks) if blocks.len() == 2
+        ));
+
+        let blocks: Vec<AnthropicContentBlock> = serde_json::from_value(json!([
+            { "type": "text", "text": "Checking." },
+            { "type": "tool_use", "id": "toolu_1", "name": "get_weather", "input": { "city": "Oslo" } }
+        ]))
+        .unwrap();
+        let (text, calls) = from_anthropic_content(&blocks, None);
+        assert_eq!(text, "Checking.");
+        assert_eq!(calls[0].id, "toolu_1");
+        assert_eq!(calls[0].arguments(), json!({ "city": "Oslo" }));
+    }
+
+    #[test]
+    fn test_google_round_trip() {
+        let contents = to_google_contents(&weather_conversation());
+        assert_eq!(contents.len(), 3);
+        assert_eq!(contents[1].role.as_deref(), Some("model"));
+        assert_eq!(
+            contents[1].parts[1].function_call.as_ref().unwrap().args,
+            json!({ "city": "Rome" })
+        );
+
+        // Results are matched to their function by call ID
+        let responses: Vec<_> = contents[2]
+            .parts
+            .iter()
+            .map(|part| part.function_response.clone().unwrap())
+            .collect();
+        assert_eq!(responses[0].name, "get_weather");
+        assert_eq!(responses[0].response, json!({ "temperature": 21 }));
+        assert_eq!(responses[1].response, json!({ "content": "sunny" }));
+
+        let parts: Vec<GooglePart> = serde_json::from_value(json!([
+            { "functionCall": { "name": "get_weather", "args": { "city": "Oslo" } } }
+        ]))
+        .unwrap();
+        let (text, calls) = from_google_parts(&parts);
+        assert!(text.is_empty());
+        assert!(calls[0].id.starts_with("call_"));
+        assert_eq!(calls[0].arguments(), json!({ "city": "Oslo" }));
+
+        let config = to_google_tool_config(&ToolChoice::Function("get_weather".to_string()));
+        assert_eq!(config.function_calling_config.mode, "ANY");
+    }
+}
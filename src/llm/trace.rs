@@ -0,0 +1,265 @@
+//! Routing Traces
+//!
+//! A structured record of how the router handled each request: the models it
+//! considered and how they scored, the ones it excluded and why, and the provider that
+//! finally served the request. Traces are kept in a bounded in-memory store and are
+//! exposed through `GET /v1/requests/{id}/routing` and the `routingTrace` GraphQL query,
+//! so it is possible to see after the fact why e.g. `CostOptimized` picked a provider.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use super::LLMProviderType;
+use crate::api::types::SmartRoutingStrategy;
+
+/// Number of traces kept before the oldest are evicted
+pub const DEFAULT_TRACE_CAPACITY: usize = 1000;
+
+/// A model the router considered for a request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoutingCandidate {
+    pub provider: LLMProviderType,
+    pub model: String,
+    /// Score the strategy ranked the candidate by; for cost-based strategies this is
+    /// the combined per-token cost (lower wins). `None` for order-based strategies.
+    pub score: Option<f64>,
+    pub selected: bool,
+}
+
+/// A provider or model the router ruled out, and why
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoutingExclusion {
+    pub provider: Option<LLMProviderType>,
+    pub model: Option<String>,
+    pub reason: String,
+}
+
+/// One provider call made for a request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoutingAttempt {
+    pub provider: LLMProviderType,
+    pub model: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+/// Routing decisions recorded for a single request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutingTrace {
+    pub request_id: Uuid,
+    /// Model named by the caller, possibly a virtual model
+    pub requested_model: String,
+    /// Concrete model the request was routed to
+    pub resolved_model: Option<String>,
+    /// Smart routing strategy applied to a virtual model
+    pub strategy: Option<SmartRoutingStrategy>,
+    pub tenant_id: Option<String>,
+    pub selected_provider: Option<LLMProviderType>,
+    pub candidates: Vec<RoutingCandidate>,
+    pub exclusions: Vec<RoutingExclusion>,
+    pub attempts: Vec<RoutingAttempt>,
+    /// Guardrail actions taken on the request or its response, e.g. shortening a
+    /// prompt to fit the context window
+    #[serde(default)]
+    pub guardrails: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl RoutingTrace {
+    pub fn new(request_id: Uuid, requested_model: impl Into<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            request_id,
+            requested_model: requested_model.into(),
+            resolved_model: None,
+            strategy: None,
+            tenant_id: None,
+            selected_provider: None,
+            candidates: Vec::new(),
+            exclusions: Vec::new(),
+            attempts: Vec::new(),
+            guardrails: Vec::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Mark the provider and model that will serve the request
+    pub fn select(&mut self, provider: &LLMProviderType, model: &str) {
+        self.resolved_model = Some(model.to_string());
+        self.selected_provider = Some(provider.clone());
+
+        let mut found = false;
+        for candidate in &mut self.candidates {
+            candidate.selected = &candidate.provider == provider && candidate.model == model;
+            found |= candidate.selected;
+        }
+        if !found {
+            self.candidates.push(RoutingCandidate {
+                provider: provider.clone(),
+                model: model.to_string(),
+                score: None,
+                selected: true,
+            });
+        }
+    }
+
+    /// Record that a provider or model was ruled out
+    pub fn exclude(
+        &mut self,
+        provider: Option<LLMProviderType>,
+        model: Option<String>,
+        reason: impl Into<String>,
+    ) {
+        self.exclusions.push(RoutingExclusion {
+            provider,
+            model,
+            reason: reason.into(),
+        });
+    }
+
+    /// Record a guardrail action
+    pub fn guardrail(&mut self, action: impl Into<String>) {
+        self.guardrails.push(action.into());
+    }
+
+    /// Merge the decisions of a trace recorded separately, e.g. while resolving a
+    /// virtual model, into this one
+    pub fn absorb(&mut self, other: RoutingTrace) {
+        if other.resolved_model.is_some() {
+            self.resolved_model = other.resolved_model;
+        }
+        if other.strategy.is_some() {
+            self.strategy = other.strategy;
+        }
+        if other.selected_provider.is_some() {
+            self.selected_provider = other.selected_provider;
+        }
+        if !other.candidates.is_empty() {
+            self.candidates = other.candidates;
+        }
+        self.exclusions.extend(other.exclusions);
+        self.attempts.extend(other.attempts);
+        self.guardrails.extend(other.guardrails);
+    }
+}
+
+#[derive(Debug, Default)]
+struct TraceState {
+    traces: HashMap<Uuid, RoutingTrace>,
+    order: VecDeque<Uuid>,
+}
+
+/// Bounded in-memory store of routing traces, oldest evicted first
+#[derive(Debug, Clone)]
+pub struct RoutingTraceStore {
+    capacity: usize,
+    state: Arc<RwLock<TraceState>>,
+}
+
+lazy_static::lazy_static! {
+    static ref GLOBAL_TRACES: RoutingTraceStore = RoutingTraceStore::new(DEFAULT_TRACE_CAPACITY);
+}
+
+impl Default for RoutingTraceStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_TRACE_CAPACITY)
+    }
+}
+
+impl RoutingTraceStore {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            state: Arc::new(RwLock::new(TraceState::default())),
+        }
+    }
+
+    /// Process-wide store shared by every router instance
+    pub fn global() -> Self {
+        GLOBAL_TRACES.clone()
+    }
+
+    /// Get the trace for a request
+    pub async fn get(&self, request_id: &Uuid) -> Option<RoutingTrace> {
+        self.state.read().await.traces.get(request_id).cloned()
+    }
+
+    /// Update the trace for a request, creating it if this is the first decision
+    pub async fn update<F>(&self, request_id: Uuid, requested_model: &str, f: F)
+    where
+        F: FnOnce(&mut RoutingTrace),
+    {
+        let mut state = self.state.write().await;
+        if !state.traces.contains_key(&request_id) {
+            while state.order.len() >= self.capacity {
+                if let Some(oldest) = state.order.pop_front() {
+                    state.traces.remove(&oldest);
+                }
+            }
+            state.order.push_back(request_id);
+            state
+                .traces
+                .insert(request_id, RoutingTrace::new(request_id, requested_model));
+        }
+
+        if let Some(trace) = state.traces.get_mut(&request_id) {
+            f(trace);
+            trace.updated_at = Utc::now();
+        }
+    }
+
+    /// Number of traces currently stored
+    pub async fn len(&self) -> usize {
+        self.state.read().await.traces.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_update_creates_and_merges_trace() {
+        let store = RoutingTraceStore::new(10);
+        let id = Uuid::new_v4();
+
+        store
+            .update(id, "auto", |trace| {
+                trace.strategy = Some(SmartRoutingStrategy::CostOptimized)
+            })
+            .await;
+        store
+            .update(id, "ignored", |trace| {
+                trace.selected_provider = Some(LLMProviderType::OpenAI)
+            })
+            .await;
+
+        let trace = store.get(&id).await.unwrap();
+        assert_eq!(trace.requested_model, "auto");
+        assert_eq!(trace.strategy, Some(SmartRoutingStrategy::CostOptimized));
+        assert_eq!(trace.selected_provider, Some(LLMProviderType::OpenAI));
+    }
+
+    #[tokio::test]
+    async fn test_oldest_trace_evicted_at_capacity() {
+        let store = RoutingTraceStore::new(2);
+        let ids: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+        for id in &ids {
+            store.update(*id, "gpt-4", |_| {}).await;
+        }
+
+        assert_eq!(store.len().await, 2);
+        assert!(store.get(&ids[0]).await.is_none());
+        assert!(store.get(&ids[2]).await.is_some());
+    }
+}
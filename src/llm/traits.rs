@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 use super::{
-    LLMRequest, LLMResponse, LLMResult, StreamingChunk,
+    LLMRequest, LLMResponse, LLMResult, StreamingChunk, LLMError,
     LLMProviderType, TokenUsage, EmbeddingsRequest, EmbeddingsResponse
 };
 
@@ -41,6 +41,25 @@ pub trait LLMProviderClient: Send + Sync {
     /// Generate embeddings for the given input
     async fn embeddings(&self, request: &EmbeddingsRequest, api_key: &str) -> LLMResult<EmbeddingsResponse>;
 
+    /// Check an API key with the provider's cheapest authenticated call
+    ///
+    /// The default falls back to `health_check` and reports the statically known
+    /// models; providers that can list models override it to report what the key sees.
+    async fn validate_api_key(&self, api_key: &str) -> LLMResult<KeyValidation> {
+        let valid = self.health_check(api_key).await?;
+        let catalog = self.get_available_models();
+        let models = if valid { catalog.iter().map(|m| m.id.clone()).collect() } else { Vec::new() };
+        Ok(KeyValidation::new(self.provider_type(), valid).with_models(models, &catalog))
+    }
+
+    /// Whether the provider samples `n` choices of `model` in a single request
+    ///
+    /// When it does not, the router fans requests for several choices out into
+    /// parallel single-choice requests.
+    fn supports_native_choices(&self, _model: &str) -> bool {
+        false
+    }
+
     /// Enable downcasting to concrete types
     fn as_any(&self) -> &dyn std::any::Any;
 }
@@ -208,6 +227,144 @@ pub struct ProviderHealth {
     pub last_error: Option<String>,
 }
 
+/// Result of validating a provider API key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyValidation {
+    pub provider: LLMProviderType,
+    /// Whether the provider accepted the key
+    pub valid: bool,
+    /// HTTP status returned by the validation call
+    pub status_code: Option<u16>,
+    /// Provider error message when the key was rejected
+    pub message: Option<String>,
+    /// Models visible to the key
+    pub models: Vec<String>,
+    /// Capabilities of the visible models the router knows about
+    pub capabilities: Vec<ModelCapability>,
+    /// Whether any visible model supports streaming
+    pub supports_streaming: bool,
+    /// Whether any visible model supports function calling
+    pub supports_function_calling: bool,
+    /// Remaining quota, when the provider reports it in response headers
+    pub quota: Option<KeyQuota>,
+}
+
+impl KeyValidation {
+    pub fn new(provider: LLMProviderType, valid: bool) -> Self {
+        Self {
+            provider,
+            valid,
+            status_code: None,
+            message: None,
+            models: Vec::new(),
+            capabilities: Vec::new(),
+            supports_streaming: false,
+            supports_function_calling: false,
+            quota: None,
+        }
+    }
+
+    /// Record the models visible to the key, deriving capabilities from the known catalog
+    pub fn with_models(mut self, models: Vec<String>, catalog: &[ModelInfo]) -> Self {
+        for info in catalog.iter().filter(|info| models.contains(&info.id)) {
+            self.supports_streaming |= info.supports_streaming;
+            self.supports_function_calling |= info.supports_function_calling;
+            for capability in &info.capabilities {
+                if !self.capabilities.contains(capability) {
+                    self.capabilities.push(capability.clone());
+                }
+            }
+        }
+        self.models = models;
+        self
+    }
+
+    pub fn with_status(mut self, status_code: u16) -> Self {
+        self.status_code = Some(status_code);
+        self
+    }
+
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    pub fn with_quota(mut self, quota: Option<KeyQuota>) -> Self {
+        self.quota = quota;
+        self
+    }
+}
+
+/// Rate-limit quota reported by a provider
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct KeyQuota {
+    pub requests_limit: Option<u64>,
+    pub requests_remaining: Option<u64>,
+    pub tokens_limit: Option<u64>,
+    pub tokens_remaining: Option<u64>,
+    /// When the request quota resets, as reported by the provider
+    pub requests_reset: Option<String>,
+}
+
+/// Names of the response headers a provider reports its rate-limit quota in
+#[derive(Debug, Clone, Copy)]
+pub struct QuotaHeaders {
+    pub requests_limit: &'static str,
+    pub requests_remaining: &'static str,
+    pub tokens_limit: &'static str,
+    pub tokens_remaining: &'static str,
+    pub requests_reset: &'static str,
+}
+
+impl KeyQuota {
+    /// Read quota from response headers; `None` when the provider reported none
+    pub fn from_headers(headers: &reqwest::header::HeaderMap, names: &QuotaHeaders) -> Option<Self> {
+        let text = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+        let number = |name: &str| text(name).and_then(|v| v.trim().parse().ok());
+
+        let quota = KeyQuota {
+            requests_limit: number(names.requests_limit),
+            requests_remaining: number(names.requests_remaining),
+            tokens_limit: number(names.tokens_limit),
+            tokens_remaining: number(names.tokens_remaining),
+            requests_reset: text(names.requests_reset),
+        };
+        (quota != KeyQuota::default()).then_some(quota)
+    }
+}
+
+/// Build a key validation result from a provider's model-listing response
+///
+/// 400/401/403 mean the key was rejected; 429 means the key is valid but currently rate
+/// limited. Any other failure is reported as a provider error.
+pub async fn key_validation_from_response(
+    provider: LLMProviderType,
+    response: reqwest::Response,
+    catalog: &[ModelInfo],
+    quota_headers: Option<&QuotaHeaders>,
+    model_ids: fn(&serde_json::Value) -> Vec<String>,
+) -> LLMResult<KeyValidation> {
+    let status = response.status();
+    let quota = quota_headers.and_then(|names| KeyQuota::from_headers(response.headers(), names));
+    let body = response.text().await.map_err(|e| LLMError::Network(e.to_string()))?;
+
+    let validation = if status.is_success() {
+        let ids = serde_json::from_str(&body).map(|json| model_ids(&json)).unwrap_or_default();
+        KeyValidation::new(provider, true).with_models(ids, catalog)
+    } else if matches!(status.as_u16(), 400 | 401 | 403) {
+        KeyValidation::new(provider, false).with_message(body)
+    } else if status.as_u16() == 429 {
+        KeyValidation::new(provider, true).with_message(body)
+    } else {
+        return Err(LLMError::Provider(format!(
+            "Key validation failed with HTTP {}: {}",
+            status, body
+        )));
+    };
+
+    Ok(validation.with_status(status.as_u16()).with_quota(quota))
+}
+
 /// Provider registry for managing multiple providers
 pub trait ProviderRegistry: Send + Sync {
     /// Register a new provider
@@ -224,4 +381,61 @@ pub trait ProviderRegistry: Send + Sync {
     
     /// Get provider health status
     fn get_provider_health(&self, provider_type: &LLMProviderType) -> Option<ProviderHealth>;
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderMap, HeaderValue};
+
+    const TEST_QUOTA_HEADERS: QuotaHeaders = QuotaHeaders {
+        requests_limit: "x-ratelimit-limit-requests",
+        requests_remaining: "x-ratelimit-remaining-requests",
+        tokens_limit: "x-ratelimit-limit-tokens",
+        tokens_remaining: "x-ratelimit-remaining-tokens",
+        requests_reset: "x-ratelimit-reset-requests",
+    };
+
+    #[test]
+    fn test_quota_from_headers() {
+        assert_eq!(KeyQuota::from_headers(&HeaderMap::new(), &TEST_QUOTA_HEADERS), None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-limit-requests", HeaderValue::from_static("500"));
+        headers.insert("x-ratelimit-remaining-requests", HeaderValue::from_static("499"));
+        headers.insert("x-ratelimit-reset-requests", HeaderValue::from_static("120ms"));
+
+        let quota = KeyQuota::from_headers(&headers, &TEST_QUOTA_HEADERS).unwrap();
+        assert_eq!(quota.requests_limit, Some(500));
+        assert_eq!(quota.requests_remaining, Some(499));
+        assert_eq!(quota.tokens_limit, None);
+        assert_eq!(quota.requests_reset.as_deref(), Some("120ms"));
+    }
+
+    #[test]
+    fn test_key_validation_capabilities_from_visible_models() {
+        let model = |id: &str, capabilities: Vec<ModelCapability>| ModelInfo {
+            id: id.to_string(),
+            name: id.to_string(),
+            provider: LLMProviderType::OpenAI,
+            context_window: 8192,
+            max_output_tokens: 4096,
+            supports_streaming: true,
+            supports_function_calling: capabilities.contains(&ModelCapability::FunctionCalling),
+            cost_per_input_token: 0.0,
+            cost_per_output_token: 0.0,
+            capabilities,
+            parameter_restrictions: HashMap::new(),
+        };
+        let catalog = vec![
+            model("small", vec![ModelCapability::TextGeneration]),
+            model("large", vec![ModelCapability::TextGeneration, ModelCapability::FunctionCalling]),
+        ];
+
+        let validation = KeyValidation::new(LLMProviderType::OpenAI, true)
+            .with_models(vec!["small".to_string(), "unlisted".to_string()], &catalog);
+        assert_eq!(validation.capabilities, vec![ModelCapability::TextGeneration]);
+        assert!(validation.supports_streaming);
+        assert!(!validation.supports_function_calling);
+        assert_eq!(validation.models.len(), 2);
+    }
+}
@@ -0,0 +1,515 @@
+//! Persistent Usage Tracking
+//!
+//! [`InMemoryUsageTracker`](super::cost::InMemoryUsageTracker) forgets every recorded
+//! cost on restart and each replica only sees its own requests, so budgets reset and
+//! analytics are partial. The trackers here keep usage in shared storage instead:
+//!
+//! - [`NATSUsageTracker`] appends every cost to a JetStream stream, which analytics
+//!   replay, and keeps running daily, monthly and yearly totals per user and project in
+//!   a key-value bucket, so a budget check is a single read
+//! - [`PostgresUsageTracker`] stores costs in a table and sums them on demand
+//!
+//! Either plugs into [`CostOptimizer::with_usage_tracker`](super::cost::CostOptimizer::with_usage_tracker).
+//! The server picks one with [`USAGE_TRACKER_ENV`].
+
+use async_nats::jetstream::{self, consumer, kv, stream};
+use chrono::{DateTime, FixedOffset, Utc};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use uuid::Uuid;
+
+use super::cost::{
+    cost_usage_key, period_bounds, usage_key, BudgetPeriod, CostError,
+    UsageInfo, UsageTracker,
+};
+use super::{CostInfo, LLMProviderType};
+
+/// Usage tracker backend: `memory` (default), `nats` or `postgres`
+pub const USAGE_TRACKER_ENV: &str = "CIRCUIT_BREAKER_USAGE_TRACKER";
+
+/// Stream holding every recorded cost
+pub const USAGE_STREAM: &str = "CB_USAGE";
+
+/// Subjects recorded costs are published on, followed by the encoded usage key and the
+/// request ID
+const USAGE_SUBJECT_PREFIX: &str = "cb.usage";
+
+/// Key-value bucket holding per-period usage totals
+pub const USAGE_TOTALS_BUCKET: &str = "CB_USAGE_TOTALS";
+
+/// How long recorded costs and totals are kept; long enough for yearly budgets
+pub const USAGE_RETENTION: Duration = Duration::from_secs(400 * 24 * 60 * 60);
+
+/// Attempts at updating a total before giving up on concurrent writers
+const MAX_TOTAL_UPDATES: usize = 10;
+
+/// Costs fetched per batch when replaying the stream
+const REPLAY_BATCH_SIZE: usize = 1000;
+
+const PERIODS: [BudgetPeriod; 4] = [BudgetPeriod::Daily, BudgetPeriod::Weekly, BudgetPeriod::Monthly, BudgetPeriod::Yearly];
+
+/// Encode a usage key as a NATS subject token or key-value key
+///
+/// Letters, digits, `-` and `_` are kept; every other byte becomes `=` and two hex digits.
+fn encode_token(key: &str) -> String {
+    let mut token = String::with_capacity(key.len());
+    for byte in key.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_' {
+            token.push(byte as char);
+        } else {
+            token.push_str(&format!("={:02X}", byte));
+        }
+    }
+    token
+}
+
+/// Key of the running total of `usage_key` for the period containing `time`
+fn totals_key(period: &BudgetPeriod, usage_key: &str, time: DateTime<Utc>) -> String {
+    let (name, format) = match period {
+        BudgetPeriod::Daily => ("daily", "%Y-%m-%d"),
+        BudgetPeriod::Weekly => ("weekly", "%G-W%V"),
+        BudgetPeriod::Monthly => ("monthly", "%Y-%m"),
+        BudgetPeriod::Yearly => ("yearly", "%Y"),
+    };
+    format!("{}.{}.{}", name, encode_token(usage_key), time.format(format))
+}
+
+fn tracking_error(action: &str, error: impl std::fmt::Display) -> CostError {
+    CostError::UsageTracking(format!("Failed to {}: {}", action, error))
+}
+
+/// Running usage total of one user or project over one period
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct UsageTotals {
+    total_cost: f64,
+    total_tokens: u32,
+    request_count: u32,
+}
+
+impl UsageTotals {
+    fn add(&mut self, cost_info: &CostInfo) {
+        self.total_cost += cost_info.cost_usd;
+        self.total_tokens += cost_info.input_tokens + cost_info.output_tokens;
+        self.request_count += 1;
+    }
+}
+
+impl From<UsageTotals> for UsageInfo {
+    fn from(totals: UsageTotals) -> Self {
+        UsageInfo {
+            total_cost: totals.total_cost,
+            total_tokens: totals.total_tokens,
+            request_count: totals.request_count,
+            percentage_used: 0.0,
+        }
+    }
+}
+
+/// Usage tracker backed by NATS JetStream, shared by every replica
+pub struct NATSUsageTracker {
+    jetstream: jetstream::Context,
+    totals: kv::Store,
+}
+
+impl NATSUsageTracker {
+    /// Connect to NATS and create the usage stream and totals bucket if needed
+    pub async fn connect(nats_url: &str) -> Result<Self, CostError> {
+        let client = async_nats::connect(nats_url)
+            .await
+            .map_err(|e| tracking_error("connect to NATS", e))?;
+        Self::new(jetstream::new(client)).await
+    }
+
+    /// Track usage through an existing JetStream context
+    pub async fn new(jetstream: jetstream::Context) -> Result<Self, CostError> {
+        jetstream
+            .get_or_create_stream(stream::Config {
+                name: USAGE_STREAM.to_string(),
+                subjects: vec![format!("{}.>", USAGE_SUBJECT_PREFIX)],
+                max_age: USAGE_RETENTION,
+                storage: stream::StorageType::File,
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| tracking_error("create usage stream", e))?;
+
+        let totals = match jetstream.get_key_value(USAGE_TOTALS_BUCKET).await {
+            Ok(store) => store,
+            Err(_) => jetstream
+                .create_key_value(kv::Config {
+                    bucket: USAGE_TOTALS_BUCKET.to_string(),
+                    description: "Usage totals per user and project".to_string(),
+                    history: 1,
+                    max_age: USAGE_RETENTION,
+                    storage: stream::StorageType::File,
+                    ..Default::default()
+                })
+                .await
+                .map_err(|e| tracking_error("create usage totals bucket", e))?,
+        };
+
+        Ok(Self { jetstream, totals })
+    }
+
+    async fn get_totals(&self, period: BudgetPeriod, user_id: &str, project_id: Option<&str>) -> Result<UsageInfo, CostError> {
+        let key = totals_key(&period, &usage_key(user_id, project_id), Utc::now());
+        let totals = match self.totals.get(&key).await.map_err(|e| tracking_error("read usage totals", e))? {
+            Some(value) => serde_json::from_slice::<UsageTotals>(&value)
+                .map_err(|e| tracking_error("decode usage totals", e))?,
+            None => UsageTotals::default(),
+        };
+        Ok(totals.into())
+    }
+
+    /// Add a cost to a running total, retrying when another replica updated it first
+    async fn add_to_total(&self, key: &str, cost_info: &CostInfo) -> Result<(), CostError> {
+        for _ in 0..MAX_TOTAL_UPDATES {
+            let entry = self.totals.entry(key).await.map_err(|e| tracking_error("read usage totals", e))?;
+            let (mut totals, revision) = match entry {
+                Some(entry) if entry.operation == kv::Operation::Put => (
+                    serde_json::from_slice::<UsageTotals>(&entry.value).unwrap_or_default(),
+                    Some(entry.revision),
+                ),
+                Some(entry) => (UsageTotals::default(), Some(entry.revision)),
+                None => (UsageTotals::default(), None),
+            };
+            totals.add(cost_info);
+
+            let value = serde_json::to_vec(&totals).map_err(|e| tracking_error("encode usage totals", e))?;
+            let written = match revision {
+                Some(revision) => self.totals.update(key, value.into(), revision).await.is_ok(),
+                None => self.totals.create(key, value.into()).await.is_ok(),
+            };
+            if written {
+                return Ok(());
+            }
+        }
+        Err(CostError::UsageTracking(format!("Usage total {} is contended", key)))
+    }
+}
+
+#[async_trait::async_trait]
+impl UsageTracker for NATSUsageTracker {
+    async fn get_daily_usage(&self, user_id: &str, project_id: Option<&str>) -> Result<UsageInfo, CostError> {
+        self.get_totals(BudgetPeriod::Daily, user_id, project_id).await
+    }
+
+    async fn get_weekly_usage(&self, user_id: &str, project_id: Option<&str>) -> Result<UsageInfo, CostError> {
+        self.get_totals(BudgetPeriod::Weekly, user_id, project_id).await
+    }
+
+    async fn get_monthly_usage(&self, user_id: &str, project_id: Option<&str>) -> Result<UsageInfo, CostError> {
+        self.get_totals(BudgetPeriod::Monthly, user_id, project_id).await
+    }
+
+    async fn get_yearly_usage(&self, user_id: &str, project_id: Option<&str>) -> Result<UsageInfo, CostError> {
+        self.get_totals(BudgetPeriod::Yearly, user_id, project_id).await
+    }
+
+    async fn record_usage(&self, cost_info: &CostInfo) -> Result<(), CostError> {
+        let key = cost_usage_key(cost_info);
+        let subject = format!("{}.{}.{}", USAGE_SUBJECT_PREFIX, encode_token(&key), cost_info.request_id);
+        let payload = serde_json::to_vec(cost_info).map_err(|e| tracking_error("encode cost", e))?;
+        self.jetstream
+            .publish(subject, payload.into())
+            .await
+            .map_err(|e| tracking_error("publish cost", e))?
+            .await
+            .map_err(|e| tracking_error("store cost", e))?;
+
+        // Anonymous costs only feed analytics; no budget can name them
+        if cost_info.project_id.is_none() && cost_info.user_id.is_none() {
+            return Ok(());
+        }
+        for period in &PERIODS {
+            self.add_to_total(&totals_key(period, &key, cost_info.timestamp), cost_info).await?;
+        }
+        Ok(())
+    }
+
+    async fn list_usage(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<CostInfo>, CostError> {
+        let start_time = time::OffsetDateTime::from_unix_timestamp(start.timestamp())
+            .map_err(|e| tracking_error("convert start time", e))?;
+        let stream = self.jetstream.get_stream(USAGE_STREAM).await
+            .map_err(|e| tracking_error("open usage stream", e))?;
+        let consumer = stream
+            .create_consumer(consumer::pull::Config {
+                durable_name: None,
+                deliver_policy: consumer::DeliverPolicy::ByStartTime { start_time },
+                ack_policy: consumer::AckPolicy::None,
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| tracking_error("create usage consumer", e))?;
+
+        let mut costs = Vec::new();
+        loop {
+            let mut batch = consumer
+                .fetch()
+                .max_messages(REPLAY_BATCH_SIZE)
+                .messages()
+                .await
+                .map_err(|e| tracking_error("fetch usage", e))?;
+
+            let mut received = 0;
+            while let Some(message) = batch.next().await {
+                let message = message.map_err(|e| tracking_error("receive usage", e))?;
+                received += 1;
+                if let Ok(cost) = serde_json::from_slice::<CostInfo>(&message.payload) {
+                    if cost.timestamp >= start && cost.timestamp <= end {
+                        costs.push(cost);
+                    }
+                }
+            }
+            if received < REPLAY_BATCH_SIZE {
+                break;
+            }
+        }
+
+        costs.sort_by_key(|cost| cost.timestamp);
+        Ok(costs)
+    }
+
+    async fn get_usage(&self, request_id: &Uuid) -> Result<Option<CostInfo>, CostError> {
+        let stream = self.jetstream.get_stream(USAGE_STREAM).await
+            .map_err(|e| tracking_error("open usage stream", e))?;
+        let subject = format!("{}.*.{}", USAGE_SUBJECT_PREFIX, request_id);
+        let raw = match stream.get_last_raw_message_by_subject(&subject).await {
+            Ok(raw) => raw,
+            Err(e) if e.kind() == stream::LastRawMessageErrorKind::NoMessageFound => return Ok(None),
+            Err(e) => return Err(tracking_error("read cost", e)),
+        };
+        let message = async_nats::Message::try_from(raw).map_err(|e| tracking_error("decode cost", e))?;
+        let cost = serde_json::from_slice(&message.payload).map_err(|e| tracking_error("decode cost", e))?;
+        Ok(Some(cost))
+    }
+}
+
+/// Usage tracker backed by a Postgres table
+pub struct PostgresUsageTracker {
+    pool: sqlx::PgPool,
+}
+
+impl PostgresUsageTracker {
+    /// Connect to Postgres and migrate its schema
+    pub async fn connect(database_url: &str) -> Result<Self, CostError> {
+        let pool = crate::engine::postgres_storage::connect(database_url)
+            .await
+            .map_err(|e| tracking_error("connect to Postgres", e))?;
+        Ok(Self::new(pool))
+    }
+
+    /// Track usage through an existing, migrated connection pool
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self { pool }
+    }
+
+    async fn usage_since(&self, period: BudgetPeriod, user_id: &str, project_id: Option<&str>) -> Result<UsageInfo, CostError> {
+        let (start, end) = period_bounds(&period, Utc::now(), FixedOffset::east_opt(0).unwrap());
+        self.get_usage_between(&usage_key(user_id, project_id), start, end).await
+    }
+}
+
+type UsageRow = (Uuid, String, String, i64, i64, f64, DateTime<Utc>, Option<String>, Option<String>);
+
+/// Cost stored in a row, `None` when the provider is no longer known
+fn cost_from_row(row: UsageRow) -> Option<CostInfo> {
+    let (request_id, provider, model, input_tokens, output_tokens, cost_usd, timestamp, user_id, project_id) = row;
+    let provider: LLMProviderType = serde_json::from_value(serde_json::Value::String(provider)).ok()?;
+    Some(CostInfo {
+        request_id,
+        provider,
+        model,
+        input_tokens: input_tokens as u32,
+        output_tokens: output_tokens as u32,
+        cost_usd,
+        timestamp,
+        user_id,
+        project_id,
+    })
+}
+
+#[async_trait::async_trait]
+impl UsageTracker for PostgresUsageTracker {
+    async fn get_usage_between(&self, usage_key: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<UsageInfo, CostError> {
+        let (total_cost, total_tokens, request_count): (f64, i64, i64) = sqlx::query_as(
+            "SELECT COALESCE(SUM(cost_usd), 0)::DOUBLE PRECISION,
+                    COALESCE(SUM(input_tokens + output_tokens), 0)::BIGINT,
+                    COUNT(*)
+             FROM llm_usage WHERE usage_key = $1 AND recorded_at >= $2 AND recorded_at < $3",
+        )
+        .bind(usage_key)
+        .bind(start)
+        .bind(end)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| tracking_error("query usage", e))?;
+
+        Ok(UsageInfo {
+            total_cost,
+            total_tokens: total_tokens as u32,
+            request_count: request_count as u32,
+            percentage_used: 0.0,
+        })
+    }
+
+    async fn get_daily_usage(&self, user_id: &str, project_id: Option<&str>) -> Result<UsageInfo, CostError> {
+        self.usage_since(BudgetPeriod::Daily, user_id, project_id).await
+    }
+
+    async fn get_weekly_usage(&self, user_id: &str, project_id: Option<&str>) -> Result<UsageInfo, CostError> {
+        self.usage_since(BudgetPeriod::Weekly, user_id, project_id).await
+    }
+
+    async fn get_monthly_usage(&self, user_id: &str, project_id: Option<&str>) -> Result<UsageInfo, CostError> {
+        self.usage_since(BudgetPeriod::Monthly, user_id, project_id).await
+    }
+
+    async fn get_yearly_usage(&self, user_id: &str, project_id: Option<&str>) -> Result<UsageInfo, CostError> {
+        self.usage_since(BudgetPeriod::Yearly, user_id, project_id).await
+    }
+
+    async fn record_usage(&self, cost_info: &CostInfo) -> Result<(), CostError> {
+        let provider = serde_json::to_value(&cost_info.provider)
+            .ok()
+            .and_then(|value| value.as_str().map(str::to_string))
+            .ok_or_else(|| CostError::UsageTracking(format!("Unknown provider {:?}", cost_info.provider)))?;
+
+        sqlx::query(
+            "INSERT INTO llm_usage
+                (request_id, usage_key, provider, model, input_tokens, output_tokens, cost_usd, recorded_at, user_id, project_id)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
+        )
+        .bind(cost_info.request_id)
+        .bind(cost_usage_key(cost_info))
+        .bind(provider)
+        .bind(&cost_info.model)
+        .bind(cost_info.input_tokens as i64)
+        .bind(cost_info.output_tokens as i64)
+        .bind(cost_info.cost_usd)
+        .bind(cost_info.timestamp)
+        .bind(&cost_info.user_id)
+        .bind(&cost_info.project_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| tracking_error("store cost", e))?;
+        Ok(())
+    }
+
+    async fn list_usage(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<CostInfo>, CostError> {
+        let rows: Vec<UsageRow> = sqlx::query_as(
+            "SELECT request_id, provider, model, input_tokens, output_tokens, cost_usd, recorded_at, user_id, project_id
+             FROM llm_usage WHERE recorded_at >= $1 AND recorded_at <= $2 ORDER BY recorded_at",
+        )
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| tracking_error("query usage", e))?;
+
+        Ok(rows.into_iter().filter_map(cost_from_row).collect())
+    }
+
+    async fn get_usage(&self, request_id: &Uuid) -> Result<Option<CostInfo>, CostError> {
+        let row: Option<UsageRow> = sqlx::query_as(
+            "SELECT request_id, provider, model, input_tokens, output_tokens, cost_usd, recorded_at, user_id, project_id
+             FROM llm_usage WHERE request_id = $1 ORDER BY recorded_at DESC LIMIT 1",
+        )
+        .bind(request_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| tracking_error("query usage", e))?;
+
+        Ok(row.and_then(cost_from_row))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::cost::{Budget, CostContext, CostOptimizer, InMemoryUsageTracker};
+    use std::sync::Arc;
+
+    fn cost(user_id: Option<&str>, project_id: Option<&str>, cost_usd: f64, timestamp: DateTime<Utc>) -> CostInfo {
+        CostInfo {
+            request_id: Uuid::new_v4(),
+            provider: LLMProviderType::OpenAI,
+            model: "gpt-4".to_string(),
+            input_tokens: 100,
+            output_tokens: 50,
+            cost_usd,
+            timestamp,
+            user_id: user_id.map(str::to_string),
+            project_id: project_id.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_keys_are_valid_nats_tokens() {
+        assert_eq!(encode_token("user:alice"), "user=3Aalice");
+        assert_eq!(encode_token("project:a.b c"), "project=3Aa=2Eb=20c");
+        assert_ne!(encode_token("user:a_b"), encode_token("user:a.b"));
+
+        let time = DateTime::parse_from_rfc3339("2026-10-15T12:00:00Z").unwrap().with_timezone(&Utc);
+        assert_eq!(totals_key(&BudgetPeriod::Daily, "user:alice", time), "daily.user=3Aalice.2026-10-15");
+        assert_eq!(totals_key(&BudgetPeriod::Weekly, "user:alice", time), "weekly.user=3Aalice.2026-W42");
+        assert_eq!(totals_key(&BudgetPeriod::Monthly, "user:alice", time), "monthly.user=3Aalice.2026-10");
+        assert_eq!(totals_key(&BudgetPeriod::Yearly, "user:alice", time), "yearly.user=3Aalice.2026");
+    }
+
+    #[tokio::test]
+    async fn test_optimizers_sharing_a_tracker_share_budgets_and_analytics() {
+        let tracker = Arc::new(InMemoryUsageTracker::new().with_anonymous_usage());
+        let first = CostOptimizer::with_usage_tracker(tracker.clone());
+        let now = Utc::now();
+        let recorded = cost(Some("alice"), None, 0.5, now);
+        let request_id = recorded.request_id;
+        first.record_actual_cost(recorded).await;
+        first.record_actual_cost(cost(Some("alice"), Some("billing"), 2.0, now)).await;
+        first.record_actual_cost(cost(None, None, 1.0, now)).await;
+
+        // A restarted or second replica sees what the first one recorded
+        let second = CostOptimizer::with_usage_tracker(tracker);
+        let budget_manager = crate::llm::cost::BudgetManager::new(second.usage_tracker());
+        budget_manager
+            .set_budget(Budget {
+                id: "user:alice".to_string(),
+                limit: 0.5,
+                period: BudgetPeriod::Daily,
+                warning_threshold: 0.8,
+                carry_over: Default::default(),
+                utc_offset_minutes: 0,
+                user_id: Some("alice".to_string()),
+                project_id: None,
+                created_at: now,
+                updated_at: now,
+            })
+            .await;
+        let status = budget_manager
+            .check_budget(&CostContext {
+                user_id: "alice".to_string(),
+                project_id: None,
+                request_size: 0,
+                expected_output_tokens: 0,
+                current_time: now,
+            })
+            .await
+            .unwrap();
+        assert!(status.is_exhausted);
+
+        let analytics = second
+            .get_cost_analytics(None, None, now - chrono::Duration::hours(1), now + chrono::Duration::hours(1))
+            .await;
+        assert_eq!(analytics.total_cost, 3.5);
+        assert_eq!(analytics.total_tokens, 450);
+        let alice = second
+            .get_cost_analytics(Some("alice"), None, now - chrono::Duration::hours(1), now + chrono::Duration::hours(1))
+            .await;
+        assert_eq!(alice.total_cost, 2.5);
+
+        let request_cost = second.get_request_cost(&request_id).await.unwrap().unwrap();
+        assert_eq!(request_cost.cost_usd, 0.5);
+        assert!(second.get_request_cost(&Uuid::new_v4()).await.unwrap().is_none());
+    }
+}
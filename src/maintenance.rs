@@ -0,0 +1,168 @@
+//! Maintenance Mode
+//!
+//! A read-only switch for safe upgrades and storage migrations. While it is on, the
+//! REST, GraphQL and MCP surfaces reject mutations and new executions with
+//! `MAINTENANCE_MODE` (HTTP 503 with `Retry-After`), reads and streams that are
+//! already running carry on, and NATS consumers stop pulling messages until the switch
+//! is turned off again.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// Retry hint sent to clients when the operator does not give one
+pub const DEFAULT_RETRY_AFTER_SECS: u64 = 60;
+
+lazy_static::lazy_static! {
+    static ref GLOBAL: MaintenanceMode = MaintenanceMode::new();
+}
+
+/// Current maintenance state as reported to operators and clients
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MaintenanceStatus {
+    pub enabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Seconds clients are told to wait before retrying
+    pub retry_after_secs: u64,
+    /// When maintenance mode was switched on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<DateTime<Utc>>,
+}
+
+impl Default for MaintenanceStatus {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            reason: None,
+            retry_after_secs: DEFAULT_RETRY_AFTER_SECS,
+            since: None,
+        }
+    }
+}
+
+impl MaintenanceStatus {
+    /// Human-readable explanation returned with rejected requests
+    pub fn message(&self) -> String {
+        let mut message = "Circuit Breaker is in maintenance mode".to_string();
+        if let Some(reason) = &self.reason {
+            message.push_str(&format!(" ({})", reason));
+        }
+        message.push_str(&format!(
+            "; reads are available, retry writes in {} seconds",
+            self.retry_after_secs
+        ));
+        message
+    }
+}
+
+/// Shared maintenance switch
+///
+/// Clones observe the same state. Servers use [`MaintenanceMode::global`]; separate
+/// instances are mainly useful in tests.
+#[derive(Debug, Clone)]
+pub struct MaintenanceMode {
+    state: Arc<watch::Sender<MaintenanceStatus>>,
+}
+
+impl Default for MaintenanceMode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MaintenanceMode {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(watch::channel(MaintenanceStatus::default()).0),
+        }
+    }
+
+    /// The process-wide switch shared by every API surface
+    pub fn global() -> Self {
+        GLOBAL.clone()
+    }
+
+    pub fn status(&self) -> MaintenanceStatus {
+        self.state.borrow().clone()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.state.borrow().enabled
+    }
+
+    /// Switch maintenance mode on, keeping the original start time if it already is
+    pub fn enable(
+        &self,
+        reason: Option<String>,
+        retry_after_secs: Option<u64>,
+    ) -> MaintenanceStatus {
+        self.state.send_modify(|status| {
+            if !status.enabled {
+                status.enabled = true;
+                status.since = Some(Utc::now());
+            }
+            status.reason = reason;
+            status.retry_after_secs = retry_after_secs.unwrap_or(DEFAULT_RETRY_AFTER_SECS);
+        });
+        self.status()
+    }
+
+    /// Switch maintenance mode off
+    pub fn disable(&self) -> MaintenanceStatus {
+        self.state.send_replace(MaintenanceStatus::default());
+        self.status()
+    }
+
+    /// Wait until maintenance mode is off, returning immediately if it already is
+    pub async fn wait_until_disabled(&self) {
+        let mut receiver = self.state.subscribe();
+        let _ = receiver.wait_for(|status| !status.enabled).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_enable_and_disable() {
+        let mode = MaintenanceMode::new();
+        assert!(!mode.is_enabled());
+
+        let status = mode.enable(Some("storage migration".to_string()), Some(120));
+        assert!(status.enabled);
+        assert_eq!(status.retry_after_secs, 120);
+        assert!(status.message().contains("storage migration"));
+
+        // Updating the reason keeps the original start time
+        let since = status.since;
+        assert_eq!(mode.enable(None, None).since, since);
+        assert_eq!(mode.status().retry_after_secs, DEFAULT_RETRY_AFTER_SECS);
+
+        assert_eq!(mode.disable(), MaintenanceStatus::default());
+        assert!(!mode.is_enabled());
+    }
+
+    #[tokio::test]
+    async fn test_wait_until_disabled() {
+        let mode = MaintenanceMode::new();
+        mode.wait_until_disabled().await;
+
+        mode.enable(None, None);
+        let waiter = tokio::spawn({
+            let mode = mode.clone();
+            async move { mode.wait_until_disabled().await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiter.is_finished());
+
+        mode.disable();
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
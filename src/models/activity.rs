@@ -8,6 +8,18 @@
 //! - Which state a resource will go to (target state)
 //! - What conditions must be met for the activity to execute
 //! - What rules must be satisfied for the activity to be enabled
+//! - Optionally, an [`ActivityTimer`] that fires the activity on its own once a
+//!   resource has sat in a source state long enough
+//! - Optionally, a compensating activity that undoes it when the resource's history is
+//!   rolled back saga-style
+//! - Optionally, a [`ManualTask`] making it a human task: resources wait in the source
+//!   state until someone approves or rejects it
+//! - Optionally, a [`Gateway`] making it an AND-split forking the resource into
+//!   concurrent branches, or an AND-join synchronizing them again
+//! - Optionally, an [`ActivityRetryPolicy`] retrying the agent or function execution
+//!   attached to it when that fails
+//! - Optionally, [`ActivityRoute`]s choosing the target state by [`Expression`]s over
+//!   the resource, falling back to the target state when none matches
 //!
 //! ## Workflow Theory
 //!
@@ -25,6 +37,7 @@
 //! - Iterator methods and functional programming
 //! - Collection operations (contains, map, collect)
 
+use super::expression::Expression;
 use super::resource::Resource;
 use super::rule::{Rule, RuleEvaluationResult}; // Import rules engine
 use super::state::{ActivityId, StateId}; // Import from sibling module
@@ -86,6 +99,223 @@ pub struct ActivityDefinition {
     /// These provide more sophisticated condition evaluation than simple strings
     /// Rules can check metadata, data fields, and combine with logical operations
     pub rules: Vec<Rule>,
+
+    /// Timer firing the activity without a caller once a resource has sat in one of
+    /// `from_states` long enough
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timer: Option<ActivityTimer>,
+
+    /// Activity undoing this one when a resource's history is compensated
+    /// Examples: "release_inventory" for "reserve_inventory", "refund" for "charge"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compensation: Option<ActivityId>,
+
+    /// Human decision the activity waits for; manual activities park resources until
+    /// someone approves or rejects the task instead of being executed directly
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manual: Option<ManualTask>,
+
+    /// Parallel routing of the activity; splits fork the resource into branches,
+    /// joins wait for every branch before moving the resource on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gateway: Option<Gateway>,
+
+    /// How the agent or function execution attached to the activity is retried when
+    /// it fails; without a policy it runs once
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<ActivityRetryPolicy>,
+
+    /// Conditional targets tried in order when the activity executes; the resource
+    /// moves to the first whose expression holds, or to `to_state` when none does
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub routes: Vec<ActivityRoute>,
+}
+
+/// Conditional target of an activity
+///
+/// `when` is an [`Expression`] over the resource, such as
+/// `amount * quantity > 1000 && customer.tier != "gold"`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActivityRoute {
+    /// Expression choosing this route when true
+    pub when: String,
+
+    /// State the resource moves to along this route
+    pub to_state: StateId,
+}
+
+impl ActivityRoute {
+    /// Route to `to_state` when `when` holds
+    pub fn new<W: Into<String>, T: Into<StateId>>(when: W, to_state: T) -> Self {
+        Self {
+            when: when.into(),
+            to_state: to_state.into(),
+        }
+    }
+
+    /// Whether the route applies to `resource`; expressions that fail to parse or
+    /// evaluate never match
+    pub fn matches(&self, resource: &Resource) -> bool {
+        Expression::parse(&self.when)
+            .and_then(|expression| expression.matches(resource))
+            .unwrap_or(false)
+    }
+}
+
+/// Parallel routing of an activity
+///
+/// A split moves the resource to its `to_state`, which it keeps while the branches run,
+/// and starts one branch in each listed state. Ordinary activities then move the
+/// branches independently. A join executes once every branch sits in one of its
+/// `from_states` and each of those states holds a branch; it closes the branches and
+/// moves the resource to its `to_state`. Branches do not nest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Gateway {
+    /// AND-split starting a branch in each state
+    Split(Vec<StateId>),
+    /// AND-join synchronizing all branches
+    Join,
+}
+
+/// What a manual activity asks of the person completing it
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManualTask {
+    /// Guidance shown to whoever picks the task up
+    /// Examples: "Check the contract against the legal checklist"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+
+    /// State a rejected resource moves to; without one it stays where it is
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reject_to: Option<StateId>,
+}
+
+/// How long to wait between attempts of a retried execution
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryBackoff {
+    /// The same delay before every retry
+    #[default]
+    Fixed,
+    /// The delay times the number of the retry: 10s, 20s, 30s, ...
+    Linear,
+    /// The delay multiplied by `multiplier` for every further retry: 10s, 20s, 40s, ...
+    Exponential { multiplier: f64 },
+}
+
+/// Retries of the agent or function execution attached to an activity
+///
+/// Attempts are counted from 1 and include the first one, so `max_attempts: 3`
+/// allows two retries.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActivityRetryPolicy {
+    /// Most attempts, the first included
+    pub max_attempts: u32,
+
+    /// Delay before the first retry, in seconds
+    pub delay_secs: u64,
+
+    #[serde(default)]
+    pub backoff: RetryBackoff,
+
+    /// Longest delay between attempts however far the backoff grows, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_delay_secs: Option<u64>,
+
+    /// Only failures whose error contains one of these, ignoring case, are retried;
+    /// every failure is when empty
+    /// Examples: ["timeout", "rate_limit"]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub retry_on: Vec<String>,
+}
+
+impl ActivityRetryPolicy {
+    /// Allow `max_attempts` attempts, `delay_secs` apart
+    pub fn new(max_attempts: u32, delay_secs: u64) -> Self {
+        Self {
+            max_attempts,
+            delay_secs,
+            backoff: RetryBackoff::Fixed,
+            max_delay_secs: None,
+            retry_on: Vec::new(),
+        }
+    }
+
+    pub fn with_backoff(mut self, backoff: RetryBackoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn with_max_delay(mut self, max_delay_secs: u64) -> Self {
+        self.max_delay_secs = Some(max_delay_secs);
+        self
+    }
+
+    /// Retry only failures whose error mentions one of `errors`
+    pub fn retry_on<S: Into<String>>(mut self, errors: Vec<S>) -> Self {
+        self.retry_on = errors.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Delay before attempt `attempt`, the second attempt being the first retry
+    pub fn delay_before(&self, attempt: u32) -> std::time::Duration {
+        let retry = attempt.saturating_sub(1).max(1);
+        let secs = match self.backoff {
+            RetryBackoff::Fixed => self.delay_secs as f64,
+            RetryBackoff::Linear => self.delay_secs as f64 * retry as f64,
+            RetryBackoff::Exponential { multiplier } => {
+                self.delay_secs as f64 * multiplier.powi(retry as i32 - 1)
+            }
+        };
+        let secs = match self.max_delay_secs {
+            Some(max_delay_secs) => secs.min(max_delay_secs as f64),
+            None => secs,
+        };
+        std::time::Duration::try_from_secs_f64(secs.max(0.0)).unwrap_or(std::time::Duration::MAX)
+    }
+
+    /// Whether attempt `attempt` failing with `error` is followed by another
+    pub fn retries(&self, attempt: u32, error: &str) -> bool {
+        if attempt >= self.max_attempts {
+            return false;
+        }
+        let error = error.to_lowercase();
+        self.retry_on.is_empty()
+            || self
+                .retry_on
+                .iter()
+                .any(|pattern| error.contains(&pattern.to_lowercase()))
+    }
+}
+
+/// When the engine executes an activity by itself
+///
+/// Both kinds count from the time the resource entered its current state, so a
+/// resource that leaves and re-enters the state starts over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityTimer {
+    /// Delay: fire `secs` after the resource entered the state, once the activity's
+    /// rules and conditions pass
+    FireAfter { secs: u64 },
+    /// Timeout: fire `secs` after the resource entered the state whatever the rules
+    /// say, e.g. to escalate a review nobody picked up
+    Deadline { secs: u64 },
+}
+
+impl ActivityTimer {
+    /// Seconds the resource sits in the state before the timer is due
+    pub fn secs(&self) -> u64 {
+        match self {
+            ActivityTimer::FireAfter { secs } | ActivityTimer::Deadline { secs } => *secs,
+        }
+    }
+
+    /// Whether the activity fires even when its rules and conditions fail
+    pub fn overrides_rules(&self) -> bool {
+        matches!(self, ActivityTimer::Deadline { .. })
+    }
 }
 
 /// Results of evaluating structured rules for an activity
@@ -163,6 +393,18 @@ impl ActivityDefinition {
 
             // Start with no rules - can be added later if needed
             rules: vec![],
+
+            // Only fired by callers until a timer is added
+            timer: None,
+
+            // Nothing to undo until a compensation is declared
+            compensation: None,
+
+            // Executed directly unless made a human task
+            manual: None,
+            gateway: None,
+            retry: None,
+            routes: vec![],
         }
     }
 
@@ -199,6 +441,12 @@ impl ActivityDefinition {
             to_state: to_state.into(),
             conditions,    // Move the conditions vector directly
             rules: vec![], // Start with no rules
+            timer: None,
+            compensation: None,
+            manual: None,
+            gateway: None,
+            retry: None,
+            routes: vec![],
         }
     }
 
@@ -234,6 +482,12 @@ impl ActivityDefinition {
             to_state: to_state.into(),
             conditions: vec![],
             rules,
+            timer: None,
+            compensation: None,
+            manual: None,
+            gateway: None,
+            retry: None,
+            routes: vec![],
         }
     }
 
@@ -259,9 +513,84 @@ impl ActivityDefinition {
             to_state: to_state.into(),
             conditions,
             rules,
+            timer: None,
+            compensation: None,
+            manual: None,
+            gateway: None,
+            retry: None,
+            routes: vec![],
         }
     }
 
+    /// Fire this activity on its own according to `timer`
+    pub fn with_timer(mut self, timer: ActivityTimer) -> Self {
+        self.timer = Some(timer);
+        self
+    }
+
+    /// Make this activity a human task, completed by approving or rejecting it
+    pub fn with_manual_task(mut self, task: ManualTask) -> Self {
+        self.manual = Some(task);
+        self
+    }
+
+    /// Whether the activity waits for a human decision
+    pub fn is_manual(&self) -> bool {
+        self.manual.is_some()
+    }
+
+    /// Fork the resource into a branch per state of `branches` when this activity runs
+    pub fn with_split<S: Into<StateId>>(mut self, branches: Vec<S>) -> Self {
+        self.gateway = Some(Gateway::Split(
+            branches.into_iter().map(Into::into).collect(),
+        ));
+        self
+    }
+
+    /// Make this activity synchronize all branches of the resource
+    pub fn with_join(mut self) -> Self {
+        self.gateway = Some(Gateway::Join);
+        self
+    }
+
+    /// Whether the activity is an AND-join
+    pub fn is_join(&self) -> bool {
+        self.gateway == Some(Gateway::Join)
+    }
+
+    /// Undo this activity with `compensation` when the resource is compensated
+    pub fn with_compensation<C: Into<ActivityId>>(mut self, compensation: C) -> Self {
+        self.compensation = Some(compensation.into());
+        self
+    }
+
+    /// Retry the activity's agent or function execution under `policy`
+    pub fn with_retry(mut self, policy: ActivityRetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Move resources to `to_state` instead when `when` holds; routes are tried in the
+    /// order they were added
+    pub fn with_route<W: Into<String>, T: Into<StateId>>(mut self, when: W, to_state: T) -> Self {
+        self.routes.push(ActivityRoute::new(when, to_state));
+        self
+    }
+
+    /// State executing the activity moves `resource` to: the target of the first
+    /// matching route, `to_state` otherwise
+    pub fn target_state(&self, resource: &Resource) -> &StateId {
+        self.routes
+            .iter()
+            .find(|route| route.matches(resource))
+            .map_or(&self.to_state, |route| &route.to_state)
+    }
+
+    /// Every state the activity can move a resource to
+    pub fn target_states(&self) -> impl Iterator<Item = &StateId> {
+        std::iter::once(&self.to_state).chain(self.routes.iter().map(|route| &route.to_state))
+    }
+
     /// Check if this activity can be executed from the given state
     ///
     /// This is used by the workflow engine to determine which activities
@@ -286,6 +615,49 @@ impl ActivityDefinition {
         self.from_states.contains(state)
     }
 
+    /// Check if this activity can be executed given where the resource and its branches are
+    ///
+    /// A join needs every branch to have arrived in its source states; a split can
+    /// only fork a resource that is not already split. Other activities execute from
+    /// the resource's state or move a branch sitting in a source state.
+    pub fn can_execute_on(&self, resource: &Resource) -> bool {
+        match &self.gateway {
+            Some(Gateway::Join) => self.join_ready(resource),
+            Some(Gateway::Split(_)) => {
+                !resource.is_split() && self.can_execute_from(&resource.state)
+            }
+            None => {
+                self.can_execute_from(&resource.state) || self.branch_to_move(resource).is_some()
+            }
+        }
+    }
+
+    /// Branch of the resource this activity would move, if it moves one
+    ///
+    /// Activities executable from the resource's own state move the resource instead;
+    /// when several branches qualify the first by name is moved.
+    pub fn branch_to_move(&self, resource: &Resource) -> Option<String> {
+        if self.gateway.is_some() || self.can_execute_from(&resource.state) {
+            return None;
+        }
+        resource
+            .branches()
+            .into_iter()
+            .find(|(_, state)| self.can_execute_from(state))
+            .map(|(branch, _)| branch)
+    }
+
+    /// Whether every branch sits in a source state and every source state holds one
+    fn join_ready(&self, resource: &Resource) -> bool {
+        let branches = resource.branches();
+        !branches.is_empty()
+            && branches.values().all(|state| self.can_execute_from(state))
+            && self
+                .from_states
+                .iter()
+                .all(|state| branches.values().any(|branch| branch == state))
+    }
+
     /// Check if all rules pass for the given resource
     ///
     /// This evaluates all structured rules against the resource's metadata and data.
@@ -341,7 +713,7 @@ impl ActivityDefinition {
     /// ```
     pub fn can_execute_with_resource(&self, resource: &Resource) -> bool {
         // Must be in a compatible state AND all structured rules must pass
-        self.can_execute_from(&resource.state) && self.rules_pass(resource)
+        self.can_execute_on(resource) && self.rules_pass(resource)
     }
 
     /// Get comprehensive evaluation results for debugging
@@ -385,16 +757,30 @@ impl ActivityDefinition {
     /// // let evaluation = engine.evaluate_all_activities(&resource, &activities);
     /// ```
     pub fn evaluate_with_resource(&self, resource: &Resource) -> ActivityRuleEvaluation {
-        let state_compatible = self.can_execute_from(&resource.state);
+        self.evaluate_with_resource_by(resource, |rule| {
+            rule.evaluate_detailed(&resource.metadata, &resource.data)
+        })
+    }
+
+    /// Like [`evaluate_with_resource`](Self::evaluate_with_resource), but with each
+    /// structured rule evaluated by `evaluate_rule`
+    ///
+    /// Lets the `RulesEngine` wrap every rule evaluation, e.g. to record metrics.
+    pub fn evaluate_with_resource_by<F>(
+        &self,
+        resource: &Resource,
+        evaluate_rule: F,
+    ) -> ActivityRuleEvaluation
+    where
+        F: FnMut(&Rule) -> RuleEvaluationResult,
+    {
+        let state_compatible = self.can_execute_on(resource);
 
         // Evaluate each structured rule individually for detailed feedback
         // NOTE: Legacy string-based conditions (self.conditions) are NOT evaluated here
         // They are handled by the RulesEngine which has access to global rule registries
-        let rule_results: Vec<RuleEvaluationResult> = self
-            .rules
-            .iter()
-            .map(|rule| rule.evaluate_detailed(&resource.metadata, &resource.data))
-            .collect();
+        let rule_results: Vec<RuleEvaluationResult> =
+            self.rules.iter().map(evaluate_rule).collect();
 
         let rules_passed = rule_results.iter().all(|result| result.passed);
         let can_execute = state_compatible && rules_passed;
@@ -555,6 +941,48 @@ mod tests {
         assert_eq!(act2.id.as_str(), act3.id.as_str());
     }
 
+    #[test]
+    fn test_retry_policy() {
+        use std::time::Duration;
+
+        let fixed = ActivityRetryPolicy::new(3, 10);
+        assert_eq!(fixed.delay_before(2), Duration::from_secs(10));
+        assert_eq!(fixed.delay_before(3), Duration::from_secs(10));
+        assert!(fixed.retries(2, "anything"));
+        assert!(!fixed.retries(3, "anything"));
+
+        let linear = ActivityRetryPolicy::new(5, 10).with_backoff(RetryBackoff::Linear);
+        assert_eq!(linear.delay_before(4), Duration::from_secs(30));
+
+        let exponential = ActivityRetryPolicy::new(5, 10)
+            .with_backoff(RetryBackoff::Exponential { multiplier: 2.0 })
+            .with_max_delay(60)
+            .retry_on(vec!["Timeout", "rate_limit"]);
+        assert_eq!(exponential.delay_before(2), Duration::from_secs(10));
+        assert_eq!(exponential.delay_before(4), Duration::from_secs(40));
+        assert_eq!(exponential.delay_before(5), Duration::from_secs(60));
+        assert!(exponential.retries(1, "request timeout after 30s"));
+        assert!(!exponential.retries(1, "invalid credentials"));
+    }
+
+    #[test]
+    fn test_routes() {
+        let activity = ActivityDefinition::new("review", vec!["submitted"], "approved")
+            .with_route("amount > 10000", "board_review")
+            .with_route("amount > 1000 || lower(region) == 'eu'", "manager_review");
+
+        let mut resource = Resource::new("orders", StateId::from("submitted"));
+        assert_eq!(activity.target_state(&resource).as_str(), "approved");
+
+        resource.set_metadata("region", serde_json::json!("EU"));
+        assert_eq!(activity.target_state(&resource).as_str(), "manager_review");
+
+        // The first matching route wins
+        resource.set_metadata("amount", serde_json::json!(25000));
+        assert_eq!(activity.target_state(&resource).as_str(), "board_review");
+        assert_eq!(activity.target_states().count(), 3);
+    }
+
     #[test]
     fn test_legacy_conditions_not_evaluated() {
         use super::super::rule::Rule;
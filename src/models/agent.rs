@@ -3,7 +3,8 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
 
-use crate::models::{ActivityId, Rule, StateId};
+use crate::models::feature_flag::{parse_flag_call, FlagValues};
+use crate::models::{ActivityId, ActivityRetryPolicy, Rule, StateId};
 
 /// Unique identifier for an AI agent
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -87,10 +88,84 @@ impl Default for LLMConfig {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentPrompts {
     pub system: String,
+    /// User prompt; see [`AgentPrompts::render_user_prompt`] for the template syntax
     pub user_template: String,
     pub context_instructions: Option<String>,
 }
 
+impl AgentPrompts {
+    /// Render the user prompt template for an agent input and the resource's flags
+    ///
+    /// - `{{ path }}` is replaced by the input value at a dotted path, strings without
+    ///   quotes and missing values by nothing
+    /// - `{{ flag("name") }}` is replaced by `true` or `false`
+    /// - `{{#if flag("name")}} ... {{else}} ... {{/if}}` keeps the first branch when the
+    ///   flag is on and the optional second one otherwise; `#if` also accepts a path,
+    ///   which holds when its value is present and neither `false` nor empty
+    pub fn render_user_prompt(&self, input: &serde_json::Value, flags: &FlagValues) -> String {
+        let mut output = String::with_capacity(self.user_template.len());
+        // Per open `#if`: whether its condition held, and whether it is past `else`
+        let mut branches: Vec<(bool, bool)> = Vec::new();
+        let rendering =
+            |branches: &[(bool, bool)]| branches.iter().all(|(holds, in_else)| holds != in_else);
+
+        let mut rest = self.user_template.as_str();
+        while let Some(start) = rest.find("{{") {
+            let Some(length) = rest[start + 2..].find("}}") else {
+                break;
+            };
+            if rendering(&branches) {
+                output.push_str(&rest[..start]);
+            }
+            let tag = rest[start + 2..start + 2 + length].trim();
+            rest = &rest[start + 4 + length..];
+
+            if let Some(condition) = tag.strip_prefix("#if ") {
+                branches.push((template_condition(condition.trim(), input, flags), false));
+            } else if tag == "else" && !branches.is_empty() {
+                if let Some(branch) = branches.last_mut() {
+                    branch.1 = true;
+                }
+            } else if tag == "/if" && !branches.is_empty() {
+                branches.pop();
+            } else if rendering(&branches) {
+                output.push_str(&template_value(tag, input, flags));
+            }
+        }
+        if rendering(&branches) {
+            output.push_str(rest);
+        }
+        output
+    }
+}
+
+fn template_lookup<'a>(path: &str, input: &'a serde_json::Value) -> Option<&'a serde_json::Value> {
+    path.split('.')
+        .try_fold(input, |value, segment| value.get(segment))
+}
+
+fn template_condition(condition: &str, input: &serde_json::Value, flags: &FlagValues) -> bool {
+    match parse_flag_call(condition) {
+        Some(flag) => flags.get(flag).copied().unwrap_or(false),
+        None => match template_lookup(condition, input) {
+            None | Some(serde_json::Value::Null) | Some(serde_json::Value::Bool(false)) => false,
+            Some(serde_json::Value::String(value)) => !value.is_empty(),
+            Some(_) => true,
+        },
+    }
+}
+
+fn template_value(tag: &str, input: &serde_json::Value, flags: &FlagValues) -> String {
+    if let Some(flag) = parse_flag_call(tag) {
+        return flags.get(flag).copied().unwrap_or(false).to_string();
+    }
+    match template_lookup(tag, input) {
+        None | Some(serde_json::Value::Null) => String::new(),
+        Some(serde_json::Value::String(value)) => value.clone(),
+        Some(value) => value.to_string(),
+    }
+}
+
 /// Agent definition with LLM configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentDefinition {
@@ -128,6 +203,14 @@ impl Default for AgentRetryConfig {
     }
 }
 
+impl From<&AgentRetryConfig> for ActivityRetryPolicy {
+    /// The agent's retries as an activity retry policy with a fixed backoff
+    fn from(config: &AgentRetryConfig) -> Self {
+        ActivityRetryPolicy::new(config.max_attempts, config.backoff_seconds)
+            .retry_on(config.retry_on_errors.clone())
+    }
+}
+
 /// Configuration for agent execution in activities
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentActivityConfig {
@@ -153,6 +236,9 @@ pub struct StateAgentSchedule {
 pub struct StateAgentConfig {
     pub id: Uuid,
     pub state_id: StateId,
+    /// Workflow whose resources in the state run the agent; any workflow's when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workflow_id: Option<String>,
     pub agent_id: AgentId,
     pub llm_config: Option<LLMConfig>,
     pub trigger_conditions: Vec<Rule>,
@@ -172,6 +258,7 @@ impl StateAgentConfig {
         Self {
             id: Uuid::new_v4(),
             state_id,
+            workflow_id: None,
             agent_id,
             llm_config: None,
             trigger_conditions: vec![],
@@ -185,6 +272,19 @@ impl StateAgentConfig {
             updated_at: now,
         }
     }
+
+    /// Run the agent only for resources of one workflow
+    pub fn for_workflow(mut self, workflow_id: impl Into<String>) -> Self {
+        self.workflow_id = Some(workflow_id.into());
+        self
+    }
+
+    /// Whether the agent runs for resources of `workflow_id`
+    pub fn applies_to_workflow(&self, workflow_id: &str) -> bool {
+        self.workflow_id
+            .as_deref()
+            .is_none_or(|id| id == workflow_id)
+    }
 }
 
 /// Agent execution status
@@ -295,6 +395,20 @@ pub enum AgentStreamEvent {
     },
 }
 
+impl AgentStreamEvent {
+    /// The execution this event belongs to
+    pub fn execution_id(&self) -> Uuid {
+        match self {
+            AgentStreamEvent::ContentChunk { execution_id, .. }
+            | AgentStreamEvent::ThinkingStatus { execution_id, .. }
+            | AgentStreamEvent::ToolCall { execution_id, .. }
+            | AgentStreamEvent::ToolResult { execution_id, .. }
+            | AgentStreamEvent::Completed { execution_id, .. }
+            | AgentStreamEvent::Failed { execution_id, .. } => *execution_id,
+        }
+    }
+}
+
 /// Conversation record for agent interactions
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Conversation {
@@ -324,3 +438,37 @@ pub enum MessageRole {
     Assistant,
     Tool,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_user_prompt() {
+        let prompts = AgentPrompts {
+            system: String::new(),
+            user_template: "Review {{ title }} ({{ meta.pages }} pages, flag {{flag(\"strict\")}}).\
+                {{#if flag(\"strict\")}} Be strict.{{else}} Be brief.{{#if notes}} Notes: {{notes}}{{/if}}{{/if}}{{missing}} {{ unclosed"
+                .to_string(),
+            context_instructions: None,
+        };
+        let input = serde_json::json!({"title": "Q3 report", "meta": {"pages": 12}, "notes": ""});
+
+        let strict = FlagValues::from([("strict".to_string(), true)]);
+        assert_eq!(
+            prompts.render_user_prompt(&input, &strict),
+            "Review Q3 report (12 pages, flag true). Be strict. {{ unclosed"
+        );
+        assert_eq!(
+            prompts.render_user_prompt(&input, &FlagValues::new()),
+            "Review Q3 report (12 pages, flag false). Be brief. {{ unclosed"
+        );
+
+        let input =
+            serde_json::json!({"title": "Q3 report", "meta": {"pages": 12}, "notes": "late"});
+        assert_eq!(
+            prompts.render_user_prompt(&input, &FlagValues::new()),
+            "Review Q3 report (12 pages, flag false). Be brief. Notes: late {{ unclosed"
+        );
+    }
+}
@@ -0,0 +1,509 @@
+// Static workflow analysis
+// Finds structural problems in a workflow definition before any resource runs into them
+
+//! # Workflow Analysis
+//!
+//! [`WorkflowDefinition::validate`] rejects definitions that reference things that do
+//! not exist. [`WorkflowDefinition::analyze`] goes further and looks at the shape of
+//! the state graph and the rules guarding it, returning a [`WorkflowDiagnostic`] per
+//! problem found:
+//!
+//! - **Unsatisfiable rules** (error): an activity whose rules contradict each other,
+//!   e.g. `status == "open"` and `status == "closed"`, or `score > 90` and `score < 50`,
+//!   can never execute
+//! - **Unreachable states** (warning): no chain of activities leads from the initial
+//!   state to them
+//! - **No terminal state** (warning): every state has an activity leaving it, so no
+//!   resource ever finishes
+//! - **Deadlocks** (warning): resources reaching a state whose every activity is
+//!   unsatisfiable, or from which no terminal state can be reached, are stuck; so are
+//!   the branches of a split that a join waits for in a state they cannot reach
+//!
+//! Activities with unsatisfiable rules count as missing for the graph checks. Splits
+//! lead to each of their branch states, and rejections of human tasks to their
+//! `reject_to` state.
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use super::activity::{ActivityDefinition, Gateway};
+use super::rule::{Rule, RuleCondition};
+use super::state::{ActivityId, StateId};
+use super::workflow::WorkflowDefinition;
+
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticSeverity {
+    /// The definition cannot work as written
+    Error,
+    /// The definition works, but likely not as intended
+    Warning,
+}
+
+/// What kind of problem a diagnostic reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticKind {
+    UnsatisfiableRules,
+    UnreachableState,
+    NoTerminalState,
+    Deadlock,
+}
+
+impl DiagnosticKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiagnosticKind::UnsatisfiableRules => "unsatisfiable_rules",
+            DiagnosticKind::UnreachableState => "unreachable_state",
+            DiagnosticKind::NoTerminalState => "no_terminal_state",
+            DiagnosticKind::Deadlock => "deadlock",
+        }
+    }
+}
+
+/// One problem found by [`WorkflowDefinition::analyze`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WorkflowDiagnostic {
+    pub severity: DiagnosticSeverity,
+    pub kind: DiagnosticKind,
+    pub message: String,
+    /// State the problem is about, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<StateId>,
+    /// Activity the problem is about, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub activity: Option<ActivityId>,
+}
+
+impl WorkflowDiagnostic {
+    fn error(kind: DiagnosticKind, message: String) -> Self {
+        Self {
+            severity: DiagnosticSeverity::Error,
+            kind,
+            message,
+            state: None,
+            activity: None,
+        }
+    }
+
+    fn warning(kind: DiagnosticKind, message: String) -> Self {
+        Self {
+            severity: DiagnosticSeverity::Warning,
+            ..Self::error(kind, message)
+        }
+    }
+
+    fn in_state(mut self, state: &StateId) -> Self {
+        self.state = Some(state.clone());
+        self
+    }
+
+    fn on_activity(mut self, activity: &ActivityId) -> Self {
+        self.activity = Some(activity.clone());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == DiagnosticSeverity::Error
+    }
+}
+
+impl WorkflowDefinition {
+    /// Look for unsatisfiable rules, unreachable states, a missing terminal state and
+    /// deadlocks; errors first, then warnings, each in definition order
+    pub fn analyze(&self) -> Vec<WorkflowDiagnostic> {
+        let mut diagnostics = Vec::new();
+
+        let mut satisfiable = Vec::with_capacity(self.activities.len());
+        for activity in &self.activities {
+            match contradiction(&activity.rules) {
+                Some(reason) => diagnostics.push(
+                    WorkflowDiagnostic::error(
+                        DiagnosticKind::UnsatisfiableRules,
+                        format!(
+                            "Activity '{}' can never execute: {}",
+                            activity.id.as_str(),
+                            reason
+                        ),
+                    )
+                    .on_activity(&activity.id),
+                ),
+                None => satisfiable.push(activity),
+            }
+        }
+
+        let graph = StateGraph::new(self, &satisfiable);
+        let reachable = graph.reachable_from(&self.initial_state);
+        for state in self.states.iter().filter(|s| !reachable.contains(s)) {
+            diagnostics.push(
+                WorkflowDiagnostic::warning(
+                    DiagnosticKind::UnreachableState,
+                    format!(
+                        "State '{}' cannot be reached from the initial state '{}'",
+                        state.as_str(),
+                        self.initial_state.as_str()
+                    ),
+                )
+                .in_state(state),
+            );
+        }
+
+        let terminal: HashSet<&StateId> = self
+            .states
+            .iter()
+            .filter(|s| !self.activities.iter().any(|a| a.can_execute_from(s)))
+            .filter(|s| !graph.split_regions.contains(s))
+            .collect();
+        if terminal.is_empty() {
+            diagnostics.push(WorkflowDiagnostic::warning(
+                DiagnosticKind::NoTerminalState,
+                "Every state has an activity leaving it, so no resource ever finishes".to_string(),
+            ));
+        }
+
+        for state in self.states.iter().filter(|s| reachable.contains(s)) {
+            if terminal.contains(state) {
+                continue;
+            }
+            if graph.successors(state).is_empty() {
+                diagnostics.push(
+                    WorkflowDiagnostic::warning(
+                        DiagnosticKind::Deadlock,
+                        format!(
+                            "Resources in state '{}' can never leave it: every activity from it is unsatisfiable",
+                            state.as_str()
+                        ),
+                    )
+                    .in_state(state),
+                );
+            } else if !terminal.is_empty()
+                && !graph
+                    .reachable_from(state)
+                    .iter()
+                    .any(|s| terminal.contains(s))
+            {
+                diagnostics.push(
+                    WorkflowDiagnostic::warning(
+                        DiagnosticKind::Deadlock,
+                        format!(
+                            "Resources reaching state '{}' can never reach a terminal state",
+                            state.as_str()
+                        ),
+                    )
+                    .in_state(state),
+                );
+            }
+        }
+
+        diagnostics.extend(self.stranded_joins(&satisfiable, &graph));
+        diagnostics.sort_by_key(|d| !d.is_error());
+        diagnostics
+    }
+
+    /// Joins waiting for a branch in a state no branch of any split can reach
+    fn stranded_joins(
+        &self,
+        satisfiable: &[&ActivityDefinition],
+        graph: &StateGraph,
+    ) -> Vec<WorkflowDiagnostic> {
+        let branch_states: HashSet<StateId> = self
+            .activities
+            .iter()
+            .filter_map(|a| match &a.gateway {
+                Some(Gateway::Split(branches)) => Some(branches),
+                _ => None,
+            })
+            .flatten()
+            .flat_map(|branch| graph.reachable_from(branch))
+            .collect();
+        satisfiable
+            .iter()
+            .filter(|a| a.is_join())
+            .filter_map(|join| {
+                let stranded = join
+                    .from_states
+                    .iter()
+                    .find(|s| !branch_states.contains(*s))?;
+                Some(
+                    WorkflowDiagnostic::warning(
+                        DiagnosticKind::Deadlock,
+                        format!(
+                            "Join '{}' waits for a branch in state '{}', which no split branch reaches",
+                            join.id.as_str(),
+                            stranded.as_str()
+                        ),
+                    )
+                    .on_activity(&join.id)
+                    .in_state(stranded),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Where resources can go from each state through satisfiable activities
+struct StateGraph {
+    edges: HashMap<StateId, HashSet<StateId>>,
+    /// States split resources sit in while their branches run
+    split_regions: HashSet<StateId>,
+}
+
+impl StateGraph {
+    fn new(workflow: &WorkflowDefinition, activities: &[&ActivityDefinition]) -> Self {
+        let mut edges: HashMap<StateId, HashSet<StateId>> = HashMap::new();
+        let mut split_regions = HashSet::new();
+        for activity in activities {
+            let mut targets: Vec<StateId> = activity.target_states().cloned().collect();
+            if let Some(reject_to) = activity.manual.as_ref().and_then(|m| m.reject_to.clone()) {
+                targets.push(reject_to);
+            }
+            if let Some(Gateway::Split(branches)) = &activity.gateway {
+                split_regions.insert(activity.to_state.clone());
+                edges
+                    .entry(activity.to_state.clone())
+                    .or_default()
+                    .extend(branches.iter().cloned());
+            }
+            for from in &activity.from_states {
+                edges
+                    .entry(from.clone())
+                    .or_default()
+                    .extend(targets.iter().cloned());
+            }
+        }
+        // Keep states without edges addressable
+        for state in &workflow.states {
+            edges.entry(state.clone()).or_default();
+        }
+        Self {
+            edges,
+            split_regions,
+        }
+    }
+
+    fn successors(&self, state: &StateId) -> &HashSet<StateId> {
+        &self.edges[state]
+    }
+
+    /// `start` and every state reachable from it
+    fn reachable_from(&self, start: &StateId) -> HashSet<StateId> {
+        let mut seen = HashSet::from([start.clone()]);
+        let mut queue = VecDeque::from([start.clone()]);
+        while let Some(state) = queue.pop_front() {
+            for next in self.edges.get(&state).into_iter().flatten() {
+                if seen.insert(next.clone()) {
+                    queue.push_back(next.clone());
+                }
+            }
+        }
+        seen
+    }
+}
+
+/// What a conjunction of rules requires of one field
+#[derive(Default)]
+struct FieldBounds {
+    equals: Option<serde_json::Value>,
+    above: Option<f64>,
+    below: Option<f64>,
+    must_exist: bool,
+    must_not_exist: bool,
+}
+
+/// Why rules that must all pass never can, if they contradict each other
+///
+/// Only direct contradictions on the same field are detected - equality to two
+/// values, empty numeric ranges, a field both required and forbidden, a rule and its
+/// negation, and `Or`s without alternatives; anything subtler is assumed satisfiable.
+fn contradiction(rules: &[Rule]) -> Option<String> {
+    let mut conditions = Vec::new();
+    flatten_and(rules, &mut conditions);
+
+    let mut fields: HashMap<&str, FieldBounds> = HashMap::new();
+    for condition in &conditions {
+        match condition {
+            RuleCondition::Or { rules } if rules.is_empty() => {
+                return Some("an 'Or' without alternatives never passes".to_string())
+            }
+            RuleCondition::Not { rule } if conditions.contains(&&rule.condition) => {
+                return Some(format!("rule '{}' is required and negated", rule.id))
+            }
+            RuleCondition::Not { rule } => {
+                if let RuleCondition::FieldExists { field } = &rule.condition {
+                    fields.entry(field).or_default().must_not_exist = true;
+                }
+            }
+            RuleCondition::FieldExists { field }
+            | RuleCondition::FieldContains { field, .. }
+            | RuleCondition::FieldMatches { field, .. } => {
+                fields.entry(field).or_default().must_exist = true;
+            }
+            RuleCondition::FieldEquals { field, value } => {
+                let bounds = fields.entry(field).or_default();
+                bounds.must_exist = true;
+                match &bounds.equals {
+                    Some(other) if other != value => {
+                        return Some(format!(
+                            "'{}' must equal both {} and {}",
+                            field, other, value
+                        ))
+                    }
+                    _ => bounds.equals = Some(value.clone()),
+                }
+            }
+            RuleCondition::FieldGreaterThan { field, value } => {
+                let bounds = fields.entry(field).or_default();
+                bounds.must_exist = true;
+                bounds.above = Some(bounds.above.map_or(*value, |above| above.max(*value)));
+            }
+            RuleCondition::FieldLessThan { field, value } => {
+                let bounds = fields.entry(field).or_default();
+                bounds.must_exist = true;
+                bounds.below = Some(bounds.below.map_or(*value, |below| below.min(*value)));
+            }
+            _ => {}
+        }
+    }
+
+    let mut fields: Vec<_> = fields.into_iter().collect();
+    fields.sort_by_key(|(field, _)| *field);
+    fields.into_iter().find_map(|(field, bounds)| {
+        if bounds.must_exist && bounds.must_not_exist {
+            return Some(format!("'{}' must both exist and not exist", field));
+        }
+        if let (Some(above), Some(below)) = (bounds.above, bounds.below) {
+            if above >= below {
+                return Some(format!(
+                    "'{}' must be greater than {} and less than {}",
+                    field, above, below
+                ));
+            }
+        }
+        let number = bounds.equals.as_ref()?.as_f64()?;
+        if bounds.above.is_some_and(|above| number <= above)
+            || bounds.below.is_some_and(|below| number >= below)
+        {
+            return Some(format!(
+                "'{}' must equal {} outside its required range",
+                field, number
+            ));
+        }
+        None
+    })
+}
+
+/// Collect the conditions all of `rules` require, looking through nested `And`s
+fn flatten_and<'a>(rules: &'a [Rule], conditions: &mut Vec<&'a RuleCondition>) {
+    for rule in rules {
+        match &rule.condition {
+            RuleCondition::And { rules } => flatten_and(rules, conditions),
+            condition => conditions.push(condition),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn ticket_workflow() -> WorkflowDefinition {
+        WorkflowDefinition::new(
+            "tickets",
+            "Tickets",
+            vec![
+                StateId::from("open"),
+                StateId::from("working"),
+                StateId::from("closed"),
+            ],
+            vec![
+                ActivityDefinition::new("start", vec!["open"], "working"),
+                ActivityDefinition::new("close", vec!["working"], "closed"),
+            ],
+            "open",
+        )
+    }
+
+    fn kinds(workflow: &WorkflowDefinition) -> Vec<(DiagnosticKind, Option<String>)> {
+        workflow
+            .analyze()
+            .into_iter()
+            .map(|d| (d.kind, d.state.map(|s| s.as_str().to_string())))
+            .collect()
+    }
+
+    #[test]
+    fn test_sound_workflow_has_no_diagnostics() {
+        assert!(ticket_workflow().analyze().is_empty());
+    }
+
+    #[test]
+    fn test_graph_problems_are_warnings() {
+        let mut workflow = ticket_workflow();
+        workflow.states.push(StateId::from("archived"));
+        workflow.states.push(StateId::from("limbo"));
+        workflow
+            .activities
+            .push(ActivityDefinition::new("park", vec!["working"], "limbo"));
+        workflow
+            .activities
+            .push(ActivityDefinition::new("spin", vec!["limbo"], "limbo"));
+        assert_eq!(
+            kinds(&workflow),
+            vec![
+                (
+                    DiagnosticKind::UnreachableState,
+                    Some("archived".to_string())
+                ),
+                (DiagnosticKind::Deadlock, Some("limbo".to_string())),
+            ]
+        );
+
+        workflow
+            .activities
+            .push(ActivityDefinition::new("reopen", vec!["closed"], "open"));
+        workflow.activities.push(ActivityDefinition::new(
+            "unarchive",
+            vec!["archived"],
+            "open",
+        ));
+        assert!(kinds(&workflow).contains(&(DiagnosticKind::NoTerminalState, None)));
+        assert!(workflow.analyze().iter().all(|d| !d.is_error()));
+    }
+
+    #[test]
+    fn test_contradicting_rules_are_errors() {
+        let mut workflow = ticket_workflow();
+        workflow.activities[1].rules = vec![
+            Rule::field_equals("open", "status", json!("open")),
+            Rule::and(
+                "range",
+                "Score in range",
+                vec![
+                    Rule::field_greater_than("high", "score", 90.0),
+                    Rule {
+                        id: "low".to_string(),
+                        description: "Score below 50".to_string(),
+                        condition: RuleCondition::FieldLessThan {
+                            field: "score".to_string(),
+                            value: 50.0,
+                        },
+                    },
+                ],
+            ),
+        ];
+        let diagnostics = workflow.analyze();
+        assert!(diagnostics[0].is_error());
+        assert_eq!(diagnostics[0].kind, DiagnosticKind::UnsatisfiableRules);
+        assert!(diagnostics[0].message.contains("'score'"));
+        // With the only way out gone, resources get stuck in working
+        assert!(kinds(&workflow).contains(&(DiagnosticKind::Deadlock, Some("working".to_string()))));
+
+        workflow.activities[1].rules = vec![
+            Rule::field_equals("open", "status", json!("open")),
+            Rule::field_equals("closed", "status", json!("closed")),
+        ];
+        assert!(workflow.analyze()[0].message.contains("must equal both"));
+    }
+}
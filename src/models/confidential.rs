@@ -0,0 +1,115 @@
+// Client-side encrypted metadata
+// Envelopes for metadata values the server stores without being able to read
+
+//! # Confidential Metadata
+//!
+//! A workflow may mark metadata fields confidential (see
+//! [`WorkflowDefinition::confidential_metadata`](super::WorkflowDefinition)). The SDKs
+//! encrypt such fields before a resource is sent, with keys only the client holds, so
+//! the server stores and returns ciphertext it cannot read. Each encrypted value is an
+//! envelope:
+//!
+//! ```json
+//! {"$encrypted": {"alg": "A256GCM", "kid": "payments-2024", "nonce": "...", "ciphertext": "..."}}
+//! ```
+//!
+//! The server rejects plaintext values in confidential fields. The rules engine only
+//! operates on non-encrypted fields: rules whose conditions read a confidential field
+//! are refused when they are defined, and a condition meeting an envelope anyway finds
+//! no value and explains that the field is encrypted.
+
+use serde::{Deserialize, Serialize};
+
+use super::resource::ResourceMetadata;
+
+/// Key under which a metadata value holds its [`EncryptedValue`]
+pub const ENCRYPTED_KEY: &str = "$encrypted";
+
+/// Algorithm the SDKs encrypt with: AES-256-GCM, the field name as associated data
+pub const ENCRYPTION_ALGORITHM: &str = "A256GCM";
+
+/// A value encrypted by the client; base64 fields are opaque to the server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncryptedValue {
+    pub alg: String,
+    /// Identifies the client key, for rotation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+impl EncryptedValue {
+    /// The envelope a metadata value holds, if it is one
+    pub fn from_value(value: &serde_json::Value) -> Option<Self> {
+        let object = value.as_object()?;
+        if object.len() != 1 {
+            return None;
+        }
+        serde_json::from_value(object.get(ENCRYPTED_KEY)?.clone()).ok()
+    }
+
+    /// The value to store in metadata
+    pub fn to_value(&self) -> serde_json::Value {
+        serde_json::json!({ ENCRYPTED_KEY: self })
+    }
+}
+
+/// Whether a value is an encrypted envelope
+pub fn is_encrypted(value: &serde_json::Value) -> bool {
+    EncryptedValue::from_value(value).is_some()
+}
+
+/// Whether a field, or a value on its path, is encrypted in metadata or data
+pub fn references_encrypted(
+    metadata: &ResourceMetadata,
+    data: &serde_json::Value,
+    field: &str,
+) -> bool {
+    if let Some(value) = metadata.get(field).or_else(|| data.get(field)) {
+        return is_encrypted(value);
+    }
+    let mut segments = field.split('.');
+    let Some(first) = segments.next() else {
+        return false;
+    };
+    let mut value = metadata.get(first).or_else(|| data.get(first));
+    for segment in segments {
+        match value {
+            Some(current) if is_encrypted(current) => return true,
+            Some(current) => value = current.get(segment),
+            None => return false,
+        }
+    }
+    value.is_some_and(is_encrypted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_envelopes() {
+        let envelope = EncryptedValue {
+            alg: ENCRYPTION_ALGORITHM.to_string(),
+            kid: Some("k1".to_string()),
+            nonce: "bm9uY2U=".to_string(),
+            ciphertext: "Y2lwaGVy".to_string(),
+        };
+        let value = envelope.to_value();
+        assert_eq!(EncryptedValue::from_value(&value), Some(envelope));
+        assert!(!is_encrypted(&json!({"$encrypted": {"alg": "A256GCM"}})));
+        assert!(!is_encrypted(&json!("123-45-6789")));
+
+        let mut metadata = ResourceMetadata::new();
+        metadata.insert("ssn".to_string(), value);
+        metadata.insert("tier".to_string(), json!("gold"));
+        let data = json!({"patient": {"record": metadata["ssn"].clone()}});
+        assert!(references_encrypted(&metadata, &data, "ssn"));
+        assert!(references_encrypted(&metadata, &data, "ssn.ciphertext"));
+        assert!(references_encrypted(&metadata, &data, "patient.record"));
+        assert!(!references_encrypted(&metadata, &data, "tier"));
+        assert!(!references_encrypted(&metadata, &data, "missing.path"));
+    }
+}
@@ -0,0 +1,153 @@
+// Correlated resources for aggregate conditions
+// Sibling resources sharing a correlation key, as read by join-style rule conditions
+
+//! # Correlation
+//!
+//! Resources that belong together, such as the line items of one order, share a value
+//! under a metadata key: the correlation key. A
+//! [`RuleCondition::SiblingsInState`](super::RuleCondition) fires only once all, or at
+//! least N, of a resource's siblings (the other resources with the same value under
+//! that key) have reached a state, e.g. "all line items validated → close order".
+//!
+//! Conditions see siblings through the [`SiblingGroups`] in effect on the current
+//! thread, installed with [`with_siblings`] by whoever evaluates the rules for a
+//! resource; the rules engine does this for every rule with a sibling condition, from
+//! the correlation index storage keeps up to date. Outside such a scope a resource has
+//! no siblings and sibling conditions fail.
+
+use super::resource::ResourceMetadata;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// A resource sharing a correlation key value with the resource being evaluated
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sibling {
+    pub resource_id: Uuid,
+    pub workflow_id: String,
+    pub state: String,
+}
+
+/// The siblings of one resource, by correlation key
+pub type SiblingGroups = HashMap<String, Vec<Sibling>>;
+
+thread_local! {
+    static SIBLING_SCOPE: RefCell<Option<SiblingGroups>> = const { RefCell::new(None) };
+}
+
+/// How many siblings in a state a `SiblingsInState` condition found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SiblingCount {
+    /// Siblings in the state
+    pub matched: usize,
+    /// Siblings considered, those of the condition's workflow when it names one
+    pub total: usize,
+}
+
+impl SiblingCount {
+    /// Whether at least `min_count` siblings are in the state, or all of them (and at
+    /// least one) without a minimum
+    pub fn meets(&self, min_count: Option<u32>) -> bool {
+        match min_count {
+            Some(min_count) => self.matched >= min_count as usize,
+            None => self.total > 0 && self.matched == self.total,
+        }
+    }
+}
+
+/// Correlation key value of a resource: a string, number or boolean under `key` in its
+/// metadata, as text
+pub fn correlation_value(metadata: &ResourceMetadata, key: &str) -> Option<String> {
+    match metadata.get(key)? {
+        serde_json::Value::String(value) => Some(value.clone()),
+        serde_json::Value::Number(value) => Some(value.to_string()),
+        serde_json::Value::Bool(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// Run `f` with `siblings` as the siblings rule conditions see on this thread
+pub fn with_siblings<R>(siblings: SiblingGroups, f: impl FnOnce() -> R) -> R {
+    let previous = SIBLING_SCOPE.with(|scope| scope.replace(Some(siblings)));
+    let result = f();
+    SIBLING_SCOPE.with(|scope| *scope.borrow_mut() = previous);
+    result
+}
+
+/// Count the siblings under `correlation_key` in `state` in the current sibling scope,
+/// only those of `workflow_id` when given; none outside a scope
+pub fn count_siblings(
+    correlation_key: &str,
+    state: &str,
+    workflow_id: Option<&str>,
+) -> SiblingCount {
+    SIBLING_SCOPE.with(|scope| {
+        let scope = scope.borrow();
+        let considered = scope
+            .as_ref()
+            .and_then(|groups| groups.get(correlation_key))
+            .into_iter()
+            .flatten()
+            .filter(|sibling| workflow_id.is_none_or(|id| sibling.workflow_id == id));
+        let mut count = SiblingCount {
+            matched: 0,
+            total: 0,
+        };
+        for sibling in considered {
+            count.total += 1;
+            if sibling.state == state {
+                count.matched += 1;
+            }
+        }
+        count
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sibling(workflow_id: &str, state: &str) -> Sibling {
+        Sibling {
+            resource_id: Uuid::new_v4(),
+            workflow_id: workflow_id.to_string(),
+            state: state.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_count_siblings() {
+        let groups = SiblingGroups::from([(
+            "order_id".to_string(),
+            vec![
+                sibling("line_item", "validated"),
+                sibling("line_item", "validated"),
+                sibling("line_item", "pending"),
+                sibling("shipment", "validated"),
+            ],
+        )]);
+
+        with_siblings(groups, || {
+            let line_items = count_siblings("order_id", "validated", Some("line_item"));
+            assert_eq!(
+                line_items,
+                SiblingCount {
+                    matched: 2,
+                    total: 3
+                }
+            );
+            assert!(!line_items.meets(None));
+            assert!(line_items.meets(Some(2)));
+            assert!(!line_items.meets(Some(3)));
+
+            assert_eq!(count_siblings("order_id", "validated", None).matched, 3);
+            assert_eq!(count_siblings("customer_id", "validated", None).total, 0);
+        });
+
+        // Outside a scope there are no siblings, and "all of none" does not fire
+        let outside = count_siblings("order_id", "validated", None);
+        assert_eq!(outside.total, 0);
+        assert!(!outside.meets(None));
+    }
+}
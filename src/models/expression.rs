@@ -0,0 +1,647 @@
+// Metadata expressions
+// A small expression language over resource metadata, used for conditional routing
+
+//! # Metadata Expressions
+//!
+//! An [`Expression`] is a one-line formula over a resource, such as
+//!
+//! ```text
+//! amount * quantity > 1000 && lower(customer.tier) != "gold"
+//! ```
+//!
+//! - names read the resource's metadata, with dots reaching into nested objects;
+//!   `data.` and `metadata.` prefixes read the resource's data or metadata explicitly,
+//!   and `state` is its current state
+//! - literals: numbers, `"strings"` or `'strings'`, `true`, `false` and `null`
+//! - arithmetic `+ - * / %` on numbers, where `+` concatenates when either side is a
+//!   string; comparisons `== != < <= > >=`; logic `&& || !` with short-circuiting
+//! - functions: `len`, `lower`, `upper`, `trim`, `contains`, `starts_with`,
+//!   `ends_with`, `abs`, `min` and `max`
+//!
+//! Missing values are `null`. Logic treats `null`, `false`, `0`, `""` and empty
+//! collections as false, and ordering comparisons involving anything but two numbers
+//! or two strings are false rather than errors, so a route reading metadata a resource
+//! lacks simply does not match.
+
+use serde_json::{json, Value};
+use std::fmt;
+
+use super::resource::Resource;
+
+/// Why an expression could not be parsed or evaluated
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum ExpressionError {
+    #[error("Syntax error at position {position}: {message}")]
+    Syntax { position: usize, message: String },
+    #[error("Evaluation error: {0}")]
+    Evaluation(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Or,
+    And,
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOperator {
+    Not,
+    Negate,
+}
+
+/// A parsed expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Literal(Value),
+    /// Dotted path of a value of the resource
+    Path(Vec<String>),
+    Unary(UnaryOperator, Box<Expression>),
+    Binary(BinaryOperator, Box<Expression>, Box<Expression>),
+    Call(String, Vec<Expression>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Text(String),
+    Name(String),
+    Symbol(&'static str),
+    LeftParen,
+    RightParen,
+    Comma,
+    Dot,
+}
+
+const SYMBOLS: [&str; 17] = [
+    "||", "&&", "==", "!=", "<=", ">=", "<", ">", "+", "-", "*", "/", "%", "!", "(", ")", ",",
+];
+
+fn syntax_error<T>(position: usize, message: impl Into<String>) -> Result<T, ExpressionError> {
+    Err(ExpressionError::Syntax {
+        position,
+        message: message.into(),
+    })
+}
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, ExpressionError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            match text.parse::<f64>() {
+                Ok(number) => tokens.push((start, Token::Number(number))),
+                Err(_) => return syntax_error(start, format!("invalid number '{}'", text)),
+            }
+        } else if c == '"' || c == '\'' {
+            let start = i;
+            let mut text = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return syntax_error(start, "unterminated string"),
+                    Some('\\') => {
+                        match chars.get(i + 1) {
+                            Some('n') => text.push('\n'),
+                            Some('t') => text.push('\t'),
+                            Some(other) => text.push(*other),
+                            None => return syntax_error(start, "unterminated string"),
+                        }
+                        i += 2;
+                    }
+                    Some(quote) if *quote == c => {
+                        i += 1;
+                        break;
+                    }
+                    Some(other) => {
+                        text.push(*other);
+                        i += 1;
+                    }
+                }
+            }
+            tokens.push((start, Token::Text(text)));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push((start, Token::Name(chars[start..i].iter().collect())));
+        } else if c == '.' {
+            tokens.push((i, Token::Dot));
+            i += 1;
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let Some(symbol) = SYMBOLS.iter().find(|s| rest.starts_with(**s)) else {
+                return syntax_error(i, format!("unexpected character '{}'", c));
+            };
+            let token = match *symbol {
+                "(" => Token::LeftParen,
+                ")" => Token::RightParen,
+                "," => Token::Comma,
+                symbol => Token::Symbol(symbol),
+            };
+            tokens.push((i, token));
+            i += symbol.len();
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(_, token)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.next)
+            .map_or(self.end, |(position, _)| *position)
+    }
+
+    fn eat_symbol(&mut self, symbols: &[&'static str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Symbol(symbol)) if symbols.contains(symbol) => {
+                let symbol = *symbol;
+                self.next += 1;
+                Some(symbol)
+            }
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, expected: Token, description: &str) -> Result<(), ExpressionError> {
+        if self.peek() == Some(&expected) {
+            self.next += 1;
+            Ok(())
+        } else {
+            syntax_error(self.position(), format!("expected {}", description))
+        }
+    }
+
+    fn binary_level(
+        &mut self,
+        symbols: &[&'static str],
+        operand: fn(&mut Self) -> Result<Expression, ExpressionError>,
+    ) -> Result<Expression, ExpressionError> {
+        let mut left = operand(self)?;
+        while let Some(symbol) = self.eat_symbol(symbols) {
+            let right = operand(self)?;
+            left = Expression::Binary(binary_operator(symbol), Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn or(&mut self) -> Result<Expression, ExpressionError> {
+        self.binary_level(&["||"], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Expression, ExpressionError> {
+        self.binary_level(&["&&"], Self::comparison)
+    }
+
+    fn comparison(&mut self) -> Result<Expression, ExpressionError> {
+        let left = self.sum()?;
+        match self.eat_symbol(&["==", "!=", "<=", ">=", "<", ">"]) {
+            Some(symbol) => {
+                let right = self.sum()?;
+                Ok(Expression::Binary(
+                    binary_operator(symbol),
+                    Box::new(left),
+                    Box::new(right),
+                ))
+            }
+            None => Ok(left),
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expression, ExpressionError> {
+        self.binary_level(&["+", "-"], Self::product)
+    }
+
+    fn product(&mut self) -> Result<Expression, ExpressionError> {
+        self.binary_level(&["*", "/", "%"], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Expression, ExpressionError> {
+        match self.eat_symbol(&["!", "-"]) {
+            Some("!") => Ok(Expression::Unary(
+                UnaryOperator::Not,
+                Box::new(self.unary()?),
+            )),
+            Some(_) => Ok(Expression::Unary(
+                UnaryOperator::Negate,
+                Box::new(self.unary()?),
+            )),
+            None => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expression, ExpressionError> {
+        let position = self.position();
+        let Some(token) = self.peek().cloned() else {
+            return syntax_error(position, "unexpected end of expression");
+        };
+        self.next += 1;
+        match token {
+            Token::Number(number) => Ok(Expression::Literal(json!(number))),
+            Token::Text(text) => Ok(Expression::Literal(Value::String(text))),
+            Token::LeftParen => {
+                let inner = self.or()?;
+                self.expect(Token::RightParen, "')'")?;
+                Ok(inner)
+            }
+            Token::Name(name) => match name.as_str() {
+                "true" => Ok(Expression::Literal(Value::Bool(true))),
+                "false" => Ok(Expression::Literal(Value::Bool(false))),
+                "null" => Ok(Expression::Literal(Value::Null)),
+                _ if self.peek() == Some(&Token::LeftParen) => {
+                    self.next += 1;
+                    let mut arguments = Vec::new();
+                    if self.peek() != Some(&Token::RightParen) {
+                        arguments.push(self.or()?);
+                        while self.peek() == Some(&Token::Comma) {
+                            self.next += 1;
+                            arguments.push(self.or()?);
+                        }
+                    }
+                    self.expect(Token::RightParen, "')'")?;
+                    check_call(&name, arguments.len(), position)?;
+                    Ok(Expression::Call(name, arguments))
+                }
+                _ => {
+                    let mut path = vec![name];
+                    while self.peek() == Some(&Token::Dot) {
+                        self.next += 1;
+                        match self.peek().cloned() {
+                            Some(Token::Name(field)) => {
+                                self.next += 1;
+                                path.push(field);
+                            }
+                            _ => return syntax_error(self.position(), "expected a field name"),
+                        }
+                    }
+                    Ok(Expression::Path(path))
+                }
+            },
+            _ => syntax_error(position, "expected a value"),
+        }
+    }
+}
+
+fn binary_operator(symbol: &str) -> BinaryOperator {
+    match symbol {
+        "||" => BinaryOperator::Or,
+        "&&" => BinaryOperator::And,
+        "==" => BinaryOperator::Equal,
+        "!=" => BinaryOperator::NotEqual,
+        "<" => BinaryOperator::Less,
+        "<=" => BinaryOperator::LessOrEqual,
+        ">" => BinaryOperator::Greater,
+        ">=" => BinaryOperator::GreaterOrEqual,
+        "+" => BinaryOperator::Add,
+        "-" => BinaryOperator::Subtract,
+        "*" => BinaryOperator::Multiply,
+        "/" => BinaryOperator::Divide,
+        _ => BinaryOperator::Remainder,
+    }
+}
+
+/// Check a function exists and takes `arguments` arguments
+fn check_call(name: &str, arguments: usize, position: usize) -> Result<(), ExpressionError> {
+    let expected = match name {
+        "len" | "lower" | "upper" | "trim" | "abs" => 1,
+        "contains" | "starts_with" | "ends_with" | "min" | "max" => 2,
+        _ => return syntax_error(position, format!("unknown function '{}'", name)),
+    };
+    if arguments != expected {
+        return syntax_error(
+            position,
+            format!(
+                "'{}' takes {} argument(s), got {}",
+                name, expected, arguments
+            ),
+        );
+    }
+    Ok(())
+}
+
+/// Whether a value counts as true
+pub fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(fields) => !fields.is_empty(),
+    }
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn number(value: &Value, operation: &str) -> Result<f64, ExpressionError> {
+    value.as_f64().ok_or_else(|| {
+        ExpressionError::Evaluation(format!("{} needs numbers, got {}", operation, value))
+    })
+}
+
+/// A number as JSON, integral numbers as integers so they compare and print as such
+fn number_value(n: f64) -> Result<Value, ExpressionError> {
+    if !n.is_finite() {
+        return Err(ExpressionError::Evaluation(
+            "result is not a finite number".to_string(),
+        ));
+    }
+    if n.fract() == 0.0 && n.abs() < 9.0e15 {
+        Ok(json!(n as i64))
+    } else {
+        Ok(json!(n))
+    }
+}
+
+fn equal(left: &Value, right: &Value) -> bool {
+    match (left.as_f64(), right.as_f64()) {
+        (Some(l), Some(r)) => l == r,
+        _ => left == right,
+    }
+}
+
+impl Expression {
+    /// Parse `source`
+    pub fn parse(source: &str) -> Result<Self, ExpressionError> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            next: 0,
+            end: source.chars().count(),
+        };
+        let expression = parser.or()?;
+        if parser.next < parser.tokens.len() {
+            return syntax_error(parser.position(), "unexpected input after expression");
+        }
+        Ok(expression)
+    }
+
+    /// Value of the expression for `resource`
+    pub fn evaluate(&self, resource: &Resource) -> Result<Value, ExpressionError> {
+        match self {
+            Expression::Literal(value) => Ok(value.clone()),
+            Expression::Path(path) => Ok(lookup(resource, path)),
+            Expression::Unary(UnaryOperator::Not, operand) => {
+                Ok(Value::Bool(!truthy(&operand.evaluate(resource)?)))
+            }
+            Expression::Unary(UnaryOperator::Negate, operand) => {
+                number_value(-number(&operand.evaluate(resource)?, "'-'")?)
+            }
+            Expression::Binary(BinaryOperator::And, left, right) => Ok(Value::Bool(
+                truthy(&left.evaluate(resource)?) && truthy(&right.evaluate(resource)?),
+            )),
+            Expression::Binary(BinaryOperator::Or, left, right) => Ok(Value::Bool(
+                truthy(&left.evaluate(resource)?) || truthy(&right.evaluate(resource)?),
+            )),
+            Expression::Binary(operator, left, right) => binary(
+                *operator,
+                &left.evaluate(resource)?,
+                &right.evaluate(resource)?,
+            ),
+            Expression::Call(name, arguments) => {
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| argument.evaluate(resource))
+                    .collect::<Result<Vec<_>, _>>()?;
+                call(name, &arguments)
+            }
+        }
+    }
+
+    /// Whether the expression is true for `resource`
+    pub fn matches(&self, resource: &Resource) -> Result<bool, ExpressionError> {
+        Ok(truthy(&self.evaluate(resource)?))
+    }
+}
+
+impl fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            BinaryOperator::Or => "||",
+            BinaryOperator::And => "&&",
+            BinaryOperator::Equal => "==",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::Less => "<",
+            BinaryOperator::LessOrEqual => "<=",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::GreaterOrEqual => ">=",
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Remainder => "%",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+/// The value at `path`: metadata by default, data or metadata with a prefix
+fn lookup(resource: &Resource, path: &[String]) -> Value {
+    let (root, fields) = match path[0].as_str() {
+        "state" if path.len() == 1 => return Value::String(resource.state.as_str().to_string()),
+        "data" if path.len() > 1 => (Some(&resource.data), &path[1..]),
+        "metadata" if path.len() > 1 => (resource.metadata.get(&path[1]), &path[2..]),
+        key => (resource.metadata.get(key), &path[1..]),
+    };
+    let mut value = root;
+    for field in fields {
+        value = value.and_then(|v| v.get(field));
+    }
+    value.cloned().unwrap_or(Value::Null)
+}
+
+fn binary(operator: BinaryOperator, left: &Value, right: &Value) -> Result<Value, ExpressionError> {
+    let operation = format!("'{}'", operator);
+    match operator {
+        BinaryOperator::Equal => Ok(Value::Bool(equal(left, right))),
+        BinaryOperator::NotEqual => Ok(Value::Bool(!equal(left, right))),
+        BinaryOperator::Less
+        | BinaryOperator::LessOrEqual
+        | BinaryOperator::Greater
+        | BinaryOperator::GreaterOrEqual => {
+            let ordering = match (left, right) {
+                (Value::Number(l), Value::Number(r)) => l.as_f64().partial_cmp(&r.as_f64()),
+                (Value::String(l), Value::String(r)) => Some(l.cmp(r)),
+                _ => None,
+            };
+            let Some(ordering) = ordering else {
+                return Ok(Value::Bool(false));
+            };
+            Ok(Value::Bool(match operator {
+                BinaryOperator::Less => ordering.is_lt(),
+                BinaryOperator::LessOrEqual => ordering.is_le(),
+                BinaryOperator::Greater => ordering.is_gt(),
+                _ => ordering.is_ge(),
+            }))
+        }
+        BinaryOperator::Add if left.is_string() || right.is_string() => {
+            Ok(Value::String(text(left) + &text(right)))
+        }
+        BinaryOperator::Add => number_value(number(left, &operation)? + number(right, &operation)?),
+        BinaryOperator::Subtract => {
+            number_value(number(left, &operation)? - number(right, &operation)?)
+        }
+        BinaryOperator::Multiply => {
+            number_value(number(left, &operation)? * number(right, &operation)?)
+        }
+        BinaryOperator::Divide | BinaryOperator::Remainder => {
+            let divisor = number(right, &operation)?;
+            if divisor == 0.0 {
+                return Err(ExpressionError::Evaluation("division by zero".to_string()));
+            }
+            let dividend = number(left, &operation)?;
+            number_value(if operator == BinaryOperator::Divide {
+                dividend / divisor
+            } else {
+                dividend % divisor
+            })
+        }
+        BinaryOperator::And | BinaryOperator::Or => unreachable!("short-circuited"),
+    }
+}
+
+fn call(name: &str, arguments: &[Value]) -> Result<Value, ExpressionError> {
+    match (name, arguments) {
+        ("len", [value]) => Ok(json!(match value {
+            Value::String(s) => s.chars().count(),
+            Value::Array(items) => items.len(),
+            Value::Object(fields) => fields.len(),
+            Value::Null => 0,
+            other => text(other).chars().count(),
+        })),
+        ("lower", [value]) => Ok(Value::String(text(value).to_lowercase())),
+        ("upper", [value]) => Ok(Value::String(text(value).to_uppercase())),
+        ("trim", [value]) => Ok(Value::String(text(value).trim().to_string())),
+        ("abs", [value]) => number_value(number(value, "'abs'")?.abs()),
+        ("contains", [Value::Array(items), needle]) => {
+            Ok(Value::Bool(items.iter().any(|item| equal(item, needle))))
+        }
+        ("contains", [haystack, needle]) => Ok(Value::Bool(text(haystack).contains(&text(needle)))),
+        ("starts_with", [value, prefix]) => Ok(Value::Bool(text(value).starts_with(&text(prefix)))),
+        ("ends_with", [value, suffix]) => Ok(Value::Bool(text(value).ends_with(&text(suffix)))),
+        ("min", [a, b]) => number_value(number(a, "'min'")?.min(number(b, "'min'")?)),
+        ("max", [a, b]) => number_value(number(a, "'max'")?.max(number(b, "'max'")?)),
+        _ => Err(ExpressionError::Evaluation(format!(
+            "unknown function '{}'",
+            name
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::StateId;
+
+    fn order() -> Resource {
+        let mut resource = Resource::new("orders", StateId::from("submitted"));
+        resource.set_metadata("amount", json!(250));
+        resource.set_metadata("quantity", json!(5));
+        resource.set_metadata("customer", json!({ "tier": "Gold", "name": " Ada " }));
+        resource.set_metadata("tags", json!(["rush", "gift"]));
+        resource.data = json!({ "country": "NL" });
+        resource
+    }
+
+    fn eval(source: &str) -> Value {
+        Expression::parse(source)
+            .unwrap()
+            .evaluate(&order())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_evaluate() {
+        assert_eq!(eval("amount * quantity"), json!(1250));
+        assert_eq!(
+            eval("amount * quantity > 1000 && quantity <= 5"),
+            json!(true)
+        );
+        assert_eq!(eval("(amount + 50) / 4 % 7"), json!(5));
+        assert_eq!(eval("-amount + 0.5"), json!(-249.5));
+        assert_eq!(eval("lower(customer.tier) == 'gold'"), json!(true));
+        assert_eq!(eval("trim(customer.name) + \"!\""), json!("Ada!"));
+        assert_eq!(
+            eval("contains(tags, 'rush') && len(tags) == 2"),
+            json!(true)
+        );
+        assert_eq!(
+            eval("starts_with(data.country, 'N') && state == 'submitted'"),
+            json!(true)
+        );
+        assert_eq!(eval("max(amount, 300) - min(quantity, 1)"), json!(299));
+
+        // Missing values are null, and order against null is false
+        assert_eq!(eval("discount"), Value::Null);
+        assert_eq!(eval("discount > 0 || !discount"), json!(true));
+        assert_eq!(eval("metadata.customer.tier != null"), json!(true));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(
+            Expression::parse("amount >"),
+            Err(ExpressionError::Syntax { position: 8, .. })
+        ));
+        assert!(matches!(
+            Expression::parse("amount $ 2"),
+            Err(ExpressionError::Syntax { position: 7, .. })
+        ));
+        assert!(matches!(
+            Expression::parse("shout(amount)"),
+            Err(ExpressionError::Syntax { .. })
+        ));
+        assert!(matches!(
+            Expression::parse("len(a, b)"),
+            Err(ExpressionError::Syntax { .. })
+        ));
+        assert!(matches!(
+            Expression::parse("amount 2"),
+            Err(ExpressionError::Syntax { .. })
+        ));
+
+        let resource = order();
+        for source in ["amount / 0", "customer * 2"] {
+            assert!(matches!(
+                Expression::parse(source).unwrap().evaluate(&resource),
+                Err(ExpressionError::Evaluation(_))
+            ));
+        }
+    }
+}
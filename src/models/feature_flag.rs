@@ -0,0 +1,248 @@
+// Feature flags for workflow behavior
+// Flags are evaluated per resource and read by rule conditions and agent prompt templates
+
+//! # Feature Flags
+//!
+//! A [`FeatureFlag`] switches a piece of workflow behavior on or off without redeploying
+//! the workflow definition. A flag is either global or scoped to one workflow, and for a
+//! given resource it is on when it is enabled, the resource passes every one of its
+//! targeting rules and the resource falls inside its rollout percentage. Rollout buckets
+//! are derived from the flag name and resource ID, so a resource keeps its outcome while
+//! the percentage only grows.
+//!
+//! Rule conditions read flags through [`RuleCondition::FlagEnabled`](super::RuleCondition)
+//! or an `Expression` of the form `flag("new_review_path")`. They see the flag values in
+//! effect on the current thread, installed with [`with_flags`] by whoever evaluates the
+//! rules for a resource; the rules engine does this for every rule that reads a flag.
+//! Outside such a scope every flag reads as off.
+
+use super::resource::ResourceMetadata;
+use super::rule::Rule;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// Whether each flag is on for one resource, by flag name
+pub type FlagValues = HashMap<String, bool>;
+
+thread_local! {
+    static FLAG_SCOPE: RefCell<Option<FlagValues>> = const { RefCell::new(None) };
+}
+
+/// A named switch for workflow behavior
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeatureFlag {
+    pub name: String,
+    /// Workflow the flag applies to; global flags apply to every workflow
+    #[serde(default)]
+    pub workflow_id: Option<String>,
+    #[serde(default)]
+    pub description: String,
+    pub enabled: bool,
+    /// Rules a resource must all pass for the flag to be on for it
+    #[serde(default)]
+    pub targeting: Vec<Rule>,
+    /// Share of targeted resources the flag is on for, between 0 and 100
+    #[serde(default = "default_rollout_percentage")]
+    pub rollout_percentage: f64,
+    pub updated_at: DateTime<Utc>,
+}
+
+fn default_rollout_percentage() -> f64 {
+    100.0
+}
+
+impl FeatureFlag {
+    /// An enabled global flag rolled out to every resource
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            workflow_id: None,
+            description: String::new(),
+            enabled: true,
+            targeting: Vec::new(),
+            rollout_percentage: default_rollout_percentage(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// Scope the flag to one workflow
+    pub fn for_workflow(mut self, workflow_id: impl Into<String>) -> Self {
+        self.workflow_id = Some(workflow_id.into());
+        self
+    }
+
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Only turn the flag on for resources passing `rule`
+    pub fn targeting(mut self, rule: Rule) -> Self {
+        self.targeting.push(rule);
+        self
+    }
+
+    /// Only turn the flag on for `percentage` percent of targeted resources
+    pub fn rollout_percentage(mut self, percentage: f64) -> Self {
+        self.rollout_percentage = percentage;
+        self
+    }
+
+    /// Check the name and rollout percentage
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty()
+            || !self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        {
+            return Err(format!(
+                "Flag name '{}' must be non-empty and use only letters, digits, '_', '-' and '.'",
+                self.name
+            ));
+        }
+        if !(0.0..=100.0).contains(&self.rollout_percentage) {
+            return Err(format!(
+                "Rollout percentage of flag '{}' must be between 0 and 100",
+                self.name
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether the flag is on for a resource
+    ///
+    /// Targeting rules are evaluated without flag values, so a flag condition inside
+    /// them reads as off.
+    pub fn evaluate(
+        &self,
+        resource_id: &str,
+        metadata: &ResourceMetadata,
+        data: &serde_json::Value,
+    ) -> bool {
+        self.enabled
+            && self.rollout_bucket(resource_id) < self.rollout_percentage
+            && with_flags(FlagValues::new(), || {
+                self.targeting
+                    .iter()
+                    .all(|rule| rule.evaluate(metadata, data))
+            })
+    }
+
+    /// Stable position of a resource in this flag's rollout, in `[0, 100)`
+    pub fn rollout_bucket(&self, resource_id: &str) -> f64 {
+        let digest = Sha256::new()
+            .chain_update(self.name.as_bytes())
+            .chain_update([0])
+            .chain_update(resource_id.as_bytes())
+            .finalize();
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&digest[..8]);
+        (u64::from_be_bytes(bytes) % 10_000) as f64 / 100.0
+    }
+}
+
+/// Run `f` with `flags` as the flag values rule conditions see on this thread
+pub fn with_flags<R>(flags: FlagValues, f: impl FnOnce() -> R) -> R {
+    let previous = FLAG_SCOPE.with(|scope| scope.replace(Some(flags)));
+    let result = f();
+    FLAG_SCOPE.with(|scope| *scope.borrow_mut() = previous);
+    result
+}
+
+/// Whether a flag is on in the current flag scope; off outside one
+pub fn flag_enabled(name: &str) -> bool {
+    FLAG_SCOPE.with(|scope| {
+        scope
+            .borrow()
+            .as_ref()
+            .and_then(|flags| flags.get(name).copied())
+            .unwrap_or(false)
+    })
+}
+
+/// Flag name of an expression of the form `flag("name")`
+pub fn parse_flag_call(script: &str) -> Option<&str> {
+    let name = script
+        .trim()
+        .strip_prefix("flag(")?
+        .strip_suffix(')')?
+        .trim();
+    let name = name
+        .strip_prefix('"')
+        .and_then(|name| name.strip_suffix('"'))
+        .or_else(|| {
+            name.strip_prefix('\'')
+                .and_then(|name| name.strip_suffix('\''))
+        })?;
+    (!name.is_empty() && !name.contains(['"', '\''])).then_some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flag_evaluation() {
+        let metadata = ResourceMetadata::new();
+        let data = serde_json::json!({"region": "eu", "priority": "high"});
+
+        let flag = FeatureFlag::new("new_review_path").targeting(Rule::field_equals(
+            "eu_only",
+            "region",
+            serde_json::json!("eu"),
+        ));
+        assert!(flag.evaluate("r1", &metadata, &data));
+        assert!(!flag.clone().enabled(false).evaluate("r1", &metadata, &data));
+        assert!(!flag.evaluate("r1", &metadata, &serde_json::json!({"region": "us"})));
+
+        // Buckets are stable and a rollout only adds resources as it grows
+        let ids: Vec<String> = (0..200).map(|i| format!("resource-{}", i)).collect();
+        let on = |percentage: f64| -> Vec<&String> {
+            let flag = FeatureFlag::new("gradual").rollout_percentage(percentage);
+            ids.iter()
+                .filter(|id| flag.evaluate(id, &metadata, &data))
+                .collect()
+        };
+        assert!(on(0.0).is_empty());
+        assert_eq!(on(100.0).len(), ids.len());
+        let (quarter, half) = (on(25.0), on(50.0));
+        assert!(quarter.len() > 20 && quarter.len() < 80);
+        assert!(quarter.iter().all(|id| half.contains(id)));
+
+        assert!(FeatureFlag::new("").validate().is_err());
+        assert!(FeatureFlag::new("a b").validate().is_err());
+        assert!(FeatureFlag::new("ok")
+            .rollout_percentage(101.0)
+            .validate()
+            .is_err());
+        assert!(FeatureFlag::new("review.v2-fast_path").validate().is_ok());
+    }
+
+    #[test]
+    fn test_flag_scope() {
+        assert!(!flag_enabled("new_review_path"));
+        let flags = FlagValues::from([("new_review_path".to_string(), true)]);
+        with_flags(flags, || {
+            assert!(flag_enabled("new_review_path"));
+            assert!(!flag_enabled("other"));
+            with_flags(FlagValues::new(), || {
+                assert!(!flag_enabled("new_review_path"))
+            });
+            assert!(flag_enabled("new_review_path"));
+        });
+        assert!(!flag_enabled("new_review_path"));
+
+        assert_eq!(
+            parse_flag_call("flag(\"new_review_path\")"),
+            Some("new_review_path")
+        );
+        assert_eq!(parse_flag_call(" flag( 'x' ) "), Some("x"));
+        assert_eq!(parse_flag_call("flag(x)"), None);
+        assert_eq!(parse_flag_call("flag(\"\")"), None);
+        assert_eq!(parse_flag_call("metadata.score > 5"), None);
+    }
+}
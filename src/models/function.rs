@@ -72,6 +72,19 @@ pub enum EventType {
     TokenUpdated { place: Option<StateId> },
     /// Token completed in a specific place
     TokenCompleted { place: Option<StateId> },
+    /// Token sat in a place longer than the place's SLA
+    SlaBreached { place: Option<StateId> },
+    /// Token was paused; no transition fires until it is resumed
+    TokenPaused { place: Option<StateId> },
+    /// Paused token was resumed
+    TokenResumed { place: Option<StateId> },
+    /// Token was cancelled; no transition fires any more
+    TokenCancelled { place: Option<StateId> },
+    /// Token received a signal from another token or an API caller
+    SignalReceived {
+        place: Option<StateId>,
+        signal: Option<String>,
+    },
     /// Workflow was created
     WorkflowCreated,
     /// Function completed execution (for chaining)
@@ -435,6 +448,43 @@ impl FunctionDefinition {
                 },
                 EventType::TokenCompleted { place: event_place },
             ) => filter_place.is_none() || filter_place == event_place,
+            (
+                EventType::SlaBreached {
+                    place: filter_place,
+                },
+                EventType::SlaBreached { place: event_place },
+            )
+            | (
+                EventType::TokenPaused {
+                    place: filter_place,
+                },
+                EventType::TokenPaused { place: event_place },
+            )
+            | (
+                EventType::TokenResumed {
+                    place: filter_place,
+                },
+                EventType::TokenResumed { place: event_place },
+            )
+            | (
+                EventType::TokenCancelled {
+                    place: filter_place,
+                },
+                EventType::TokenCancelled { place: event_place },
+            ) => filter_place.is_none() || filter_place == event_place,
+            (
+                EventType::SignalReceived {
+                    place: filter_place,
+                    signal: filter_signal,
+                },
+                EventType::SignalReceived {
+                    place: event_place,
+                    signal: event_signal,
+                },
+            ) => {
+                (filter_place.is_none() || filter_place == event_place)
+                    && (filter_signal.is_none() || filter_signal == event_signal)
+            }
             (EventType::WorkflowCreated, EventType::WorkflowCreated) => true,
             (
                 EventType::Custom {
@@ -45,6 +45,18 @@ pub mod resource;
 // Contains Rule and RuleCondition - the rules engine for token gating
 pub mod rule;
 
+// Declares the `feature_flag` submodule from `feature_flag.rs`
+// Contains FeatureFlag - runtime switches read by rule conditions and prompt templates
+pub mod feature_flag;
+
+// Declares the `confidential` submodule from `confidential.rs`
+// Contains EncryptedValue - metadata encrypted client-side that rules cannot read
+pub mod confidential;
+
+// Declares the `correlation` submodule from `correlation.rs`
+// Contains Sibling - resources sharing a correlation key, read by aggregate conditions
+pub mod correlation;
+
 // Declares the `function` submodule from `function.rs`
 // Contains FunctionDefinition and event-driven execution types
 pub mod function;
@@ -53,6 +65,22 @@ pub mod function;
 // Contains AgentDefinition and AI agent execution types
 pub mod agent;
 
+// Declares the `analysis` submodule from `analysis.rs`
+// Contains WorkflowDiagnostic - problems static analysis finds in a workflow definition
+pub mod analysis;
+
+// Declares the `template` submodule from `template.rs`
+// Contains WorkflowTemplate - workflow definitions with typed parameters
+pub mod template;
+
+// Declares the `trigger` submodule from `trigger.rs`
+// Contains WebhookTrigger - inbound webhooks mapped onto workflow operations
+pub mod trigger;
+
+// Declares the `expression` submodule from `expression.rs`
+// Contains Expression - the metadata expression language of conditional routes
+pub mod expression;
+
 // Re-export main types for convenience
 // This creates shortcuts so users don't need to know the internal structure
 
@@ -62,24 +90,78 @@ pub use state::{ActivityId, StateId};
 
 /// Re-export activity definitions
 /// ActivityDefinition defines how resources can move between states
-pub use activity::ActivityDefinition;
+/// ActivityTimer fires an activity once a resource has sat in a state long enough
+/// ManualTask makes an activity a human task awaiting approval or rejection
+/// Gateway makes an activity an AND-split or AND-join of parallel branches
+/// ActivityRetryPolicy and RetryBackoff retry the execution attached to an activity
+/// ActivityRoute picks an activity's target state by an expression over the resource
+pub use activity::{
+    ActivityDefinition, ActivityRetryPolicy, ActivityRoute, ActivityTimer, Gateway, ManualTask,
+    RetryBackoff,
+};
+
+/// Re-export the metadata expression language
+pub use expression::{Expression, ExpressionError};
 
 /// Re-export workflow definitions
 /// WorkflowDefinition contains the complete workflow structure
-pub use workflow::WorkflowDefinition;
+/// StateCapacity and CapacityQueueOrder limit how many resources a state holds
+/// StateSla sets how long resources should sit in a state and how breaches escalate
+/// WorkflowBuilder assembles a definition fluently, checking every reference on build
+pub use workflow::{
+    CapacityQueueOrder, MetadataSchema, SchemaEnforcement, StateCapacity, StateSla,
+    WorkflowBlueprint, WorkflowBuilder, WorkflowDefinition, WorkflowProvenance,
+};
+
+/// Re-export workflow analysis types
+/// WorkflowDiagnostic reports one problem found by WorkflowDefinition::analyze
+/// DiagnosticSeverity and DiagnosticKind classify it
+pub use analysis::{DiagnosticKind, DiagnosticSeverity, WorkflowDiagnostic};
+
+/// Re-export workflow template types
+/// WorkflowTemplate is a definition with placeholders filled in at instantiation
+/// TemplateParameter and ParameterType describe the values it takes
+pub use template::{ParameterType, TemplateError, TemplateParameter, WorkflowTemplate};
+
+/// Re-export webhook trigger types
+/// WebhookTrigger verifies inbound webhooks and maps their events onto a workflow
+/// TriggerMapping and TriggerAction say what each event does
+pub use trigger::{
+    ResourceSelector, TriggerAction, TriggerError, TriggerMapping, TriggerSource, WebhookTrigger,
+};
 
 /// Re-export resource types
 /// - Resource: The main workflow execution instance
 /// - HistoryEvent: Records each state transition
 /// - ResourceMetadata: Key-value metadata storage
 /// - ActivityRecord: NATS-specific activity tracking
-pub use resource::{ActivityRecord, HistoryEvent, Resource, ResourceMetadata};
+/// - BRANCHES_KEY: Metadata key of the branches of a split resource
+/// - ResourceLifecycle: Why a resource is paused or cancelled, under LIFECYCLE_KEY
+/// - ResourceSignal: A named message delivered to a resource, under SIGNALS_KEY
+pub use resource::{
+    ActivityRecord, HistoryEvent, LifecycleStatus, Resource, ResourceLifecycle, ResourceMetadata,
+    ResourceSignal, BRANCHES_KEY, LIFECYCLE_KEY, SIGNALS_KEY,
+};
 
 /// Re-export rules engine types
 /// - Rule: A single evaluatable condition
 /// - RuleCondition: The actual evaluation logic (field checks, logical operations)
 /// - RuleEvaluationResult: Detailed results for debugging
-pub use rule::{Rule, RuleCondition, RuleEvaluationResult};
+pub use rule::{resolve_field, resolve_path, Rule, RuleCondition, RuleEvaluationResult};
+
+/// Re-export feature flag types
+/// - FeatureFlag: A global or workflow-scoped switch with targeting and rollout
+/// - FlagValues: Whether each flag is on for one resource
+pub use feature_flag::{FeatureFlag, FlagValues};
+
+/// Re-export confidential metadata types
+/// - EncryptedValue: A metadata value encrypted by the client, opaque to the server
+pub use confidential::EncryptedValue;
+
+/// Re-export correlation types
+/// - Sibling: A resource sharing a correlation key value with another
+/// - SiblingGroups: The siblings of one resource, by correlation key
+pub use correlation::{Sibling, SiblingGroups};
 
 /// Re-export function types
 /// - FunctionDefinition: Docker-based event-driven functions
@@ -23,10 +23,68 @@
 
 use chrono::{DateTime, Utc}; // Date/time with UTC timezone support
 use serde::{Deserialize, Serialize}; // JSON conversion traits
-use std::collections::HashMap; // Standard library hash map
+use std::collections::{BTreeMap, HashMap}; // Standard library maps
 use uuid::Uuid; // UUID generation and handling
 
 use super::state::{ActivityId, StateId}; // Import from sibling module
+use super::workflow::first_version;
+
+/// Metadata key holding the branches of a resource forked by an AND-split
+pub const BRANCHES_KEY: &str = "parallel_branches";
+
+/// Metadata key holding whether a resource is paused or cancelled
+pub const LIFECYCLE_KEY: &str = "lifecycle";
+
+/// Metadata key holding the signals delivered to a resource in its current state
+pub const SIGNALS_KEY: &str = "signals";
+
+/// Whether a resource can still move through its workflow
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LifecycleStatus {
+    /// Activities execute as usual
+    #[default]
+    Active,
+    /// No activity executes until the resource is resumed
+    Paused,
+    /// No activity executes any more
+    Cancelled,
+}
+
+impl LifecycleStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LifecycleStatus::Active => "active",
+            LifecycleStatus::Paused => "paused",
+            LifecycleStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// Who paused or cancelled a resource, when and why
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceLifecycle {
+    pub status: LifecycleStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor: Option<String>,
+    pub changed_at: DateTime<Utc>,
+}
+
+/// Named message delivered to a resource by another resource or an API caller
+///
+/// Signals are kept by name under [`SIGNALS_KEY`], a later one replacing an earlier
+/// one of the same name, until the next activity moves the resource on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceSignal {
+    #[serde(default)]
+    pub payload: serde_json::Value,
+    /// Resource that sent the signal; `None` when it came from outside the engine
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sender: Option<Uuid>,
+    pub received_at: DateTime<Utc>,
+}
 
 /// NATS-specific activity record for detailed activity tracking
 ///
@@ -137,6 +195,11 @@ pub struct Resource {
     /// This is just a string ID - the actual workflow is stored separately
     pub workflow_id: String,
 
+    /// Version of the workflow definition the resource was created under or last
+    /// migrated to
+    #[serde(default = "first_version")]
+    pub workflow_version: u32,
+
     /// Current state where this resource resides
     /// This is the "current state" of the workflow execution
     pub state: StateId,
@@ -155,6 +218,11 @@ pub struct Resource {
     /// When this resource was last modified
     pub updated_at: DateTime<Utc>,
 
+    /// Revision of the resource, bumped by storage on every update; an update made
+    /// from an older revision is rejected with a version conflict
+    #[serde(default)]
+    pub version: u64,
+
     /// Complete history of all state transitions
     /// This provides full audit trail of the resource's journey
     pub history: Vec<HistoryEvent>,
@@ -210,6 +278,9 @@ impl Resource {
             // Convert the borrowed string to an owned String
             workflow_id: workflow_id.to_string(),
 
+            // The first version until the creator pins the current one
+            workflow_version: first_version(),
+
             // Move the initial_state into the struct
             state: initial_state,
 
@@ -224,6 +295,9 @@ impl Resource {
             created_at: now,
             updated_at: now,
 
+            // Storage bumps the revision from here on every update
+            version: 0,
+
             // Start with empty history - no activities yet
             history: vec![], // vec![] is a macro to create an empty vector
 
@@ -285,6 +359,9 @@ impl Resource {
         // Update the resource's current state
         self.state = new_state;
 
+        // Signals were for the state the resource just left
+        self.metadata.remove(SIGNALS_KEY);
+
         // Update the modification timestamp
         self.updated_at = Utc::now();
     }
@@ -364,6 +441,83 @@ impl Resource {
         self.history.last()
     }
 
+    /// When the resource entered its current state: its last activity, or its creation
+    /// when it has not left the initial state yet
+    pub fn entered_state_at(&self) -> DateTime<Utc> {
+        self.last_activity()
+            .map_or(self.created_at, |event| event.timestamp)
+    }
+
+    /// Branches of a split resource by name, with the state each one is in
+    ///
+    /// Empty unless an AND-split forked the resource and no join closed the branches
+    /// yet. Branches are named after the state they started in.
+    pub fn branches(&self) -> BTreeMap<String, StateId> {
+        self.metadata
+            .get(BRANCHES_KEY)
+            .and_then(|branches| serde_json::from_value(branches.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Whether the resource has open branches
+    pub fn is_split(&self) -> bool {
+        !self.branches().is_empty()
+    }
+
+    /// Replace the branches of the resource, dropping them from the metadata when empty
+    pub fn set_branches(&mut self, branches: BTreeMap<String, StateId>) {
+        if branches.is_empty() {
+            self.metadata.remove(BRANCHES_KEY);
+            self.updated_at = Utc::now();
+        } else {
+            self.set_metadata(BRANCHES_KEY, serde_json::json!(branches));
+        }
+    }
+
+    /// Why the resource is paused or cancelled; `None` while it is active
+    pub fn lifecycle(&self) -> Option<ResourceLifecycle> {
+        self.metadata
+            .get(LIFECYCLE_KEY)
+            .and_then(|lifecycle| serde_json::from_value(lifecycle.clone()).ok())
+    }
+
+    /// Whether the resource is active, paused or cancelled
+    pub fn lifecycle_status(&self) -> LifecycleStatus {
+        self.lifecycle()
+            .map_or(LifecycleStatus::Active, |lifecycle| lifecycle.status)
+    }
+
+    /// Whether the resource is paused or cancelled, so no activity may execute on it
+    pub fn is_halted(&self) -> bool {
+        self.lifecycle_status() != LifecycleStatus::Active
+    }
+
+    /// Replace the lifecycle of the resource, dropping it from the metadata once active
+    pub fn set_lifecycle(&mut self, lifecycle: Option<ResourceLifecycle>) {
+        match lifecycle.filter(|l| l.status != LifecycleStatus::Active) {
+            Some(lifecycle) => self.set_metadata(LIFECYCLE_KEY, serde_json::json!(lifecycle)),
+            None => {
+                self.metadata.remove(LIFECYCLE_KEY);
+                self.updated_at = Utc::now();
+            }
+        }
+    }
+
+    /// Signals delivered to the resource since it entered its current state, by name
+    pub fn signals(&self) -> BTreeMap<String, ResourceSignal> {
+        self.metadata
+            .get(SIGNALS_KEY)
+            .and_then(|signals| serde_json::from_value(signals.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Deliver a signal, replacing any earlier one of the same name
+    pub fn receive_signal<N: Into<String>>(&mut self, name: N, signal: ResourceSignal) {
+        let mut signals = self.signals();
+        signals.insert(name.into(), signal);
+        self.set_metadata(SIGNALS_KEY, serde_json::json!(signals));
+    }
+
     /// NATS-specific methods for streaming support
 
     /// Set NATS metadata for this resource
@@ -441,6 +595,7 @@ impl Resource {
         self.history.push(history_event);
         self.activity_history.push(activity_record);
         self.state = new_state;
+        self.metadata.remove(SIGNALS_KEY);
         self.updated_at = Utc::now();
 
         // Update NATS subject for new state
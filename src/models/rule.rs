@@ -24,7 +24,11 @@
 //! Instead of nested objects, it creates flat objects with a "type" field:
 //! `{"type": "FieldEquals", "field": "status", "value": "approved"}`
 
-use super::resource::ResourceMetadata;
+use super::confidential::{is_encrypted, references_encrypted};
+use super::correlation::count_siblings;
+use super::feature_flag::{flag_enabled, parse_flag_call};
+use super::resource::{ResourceMetadata, SIGNALS_KEY};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 
 /// A single rule that can be evaluated against token state
@@ -61,7 +65,7 @@ use serde::{Deserialize, Serialize};
 ///     }
 /// };
 /// ```
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Rule {
     /// Unique identifier for this rule
     /// Used for referencing rules in transitions and debugging
@@ -93,13 +97,15 @@ pub struct Rule {
 /// The `Not` variant uses `Box<Rule>` because Rust enums must have a known size.
 /// Since `Rule` contains `RuleCondition` which contains `Rule`, we need `Box`
 /// to break the infinite size chain.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum RuleCondition {
     /// Check if a metadata or data field exists (regardless of value)
     ///
     /// This is useful for checking if required fields are present.
-    /// Checks both token.metadata and token.data.
+    /// Checks both token.metadata and token.data. Like every field condition, a
+    /// dotted name such as `review.status` that matches no top-level field is
+    /// followed as a path into nested objects.
     ///
     /// Example: `{"type": "FieldExists", "field": "reviewer"}`
     FieldExists { field: String },
@@ -137,6 +143,14 @@ pub enum RuleCondition {
     /// Example: `{"type": "FieldContains", "field": "tags", "substring": "urgent"}`
     FieldContains { field: String, substring: String },
 
+    /// Check if a string field matches a regular expression
+    ///
+    /// Uses the `regex` crate syntax; the pattern is unanchored unless it uses
+    /// `^`/`$`. An invalid pattern never matches.
+    ///
+    /// Example: `{"type": "FieldMatches", "field": "email", "pattern": "@example\\.com$"}`
+    FieldMatches { field: String, pattern: String },
+
     /// Logical AND - all nested rules must pass
     ///
     /// This is recursive - each rule in the vector can itself be And/Or/etc.
@@ -159,10 +173,48 @@ pub enum RuleCondition {
     /// Example: `{"type": "Not", "rule": {...}}`
     Not { rule: Box<Rule> },
 
+    /// Check if a feature flag is on for the resource being evaluated
+    ///
+    /// Reads the flag values in the current flag scope (see
+    /// [`feature_flag`](super::feature_flag)); outside one every flag is off.
+    ///
+    /// Example: `{"type": "FlagEnabled", "flag": "new_review_path"}`
+    FlagEnabled { flag: String },
+
+    /// Check if the resource's siblings have reached a state
+    ///
+    /// Siblings are the other resources with the same value under the metadata key
+    /// `correlation_key`, optionally only those of `workflow_id`. Passes once at least
+    /// `min_count` of them are in `state`, or without a minimum once all of them are
+    /// (and there is at least one). Reads the siblings in the current sibling scope
+    /// (see [`correlation`](super::correlation)); outside one it fails.
+    ///
+    /// Example: `{"type": "SiblingsInState", "correlation_key": "order_id",
+    /// "state": "validated", "workflow_id": "line_item"}`
+    SiblingsInState {
+        correlation_key: String,
+        state: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        workflow_id: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min_count: Option<u32>,
+    },
+
+    /// Check if the resource received a signal since it entered its current state
+    ///
+    /// Signals are delivered by other resources or API callers (see
+    /// [`ResourceSignal`](super::ResourceSignal)). Their payload is readable by field
+    /// conditions as `signals.<name>.payload`.
+    ///
+    /// Example: `{"type": "SignalReceived", "signal": "payment_settled"}`
+    SignalReceived { signal: String },
+
     /// Custom JavaScript expression for complex logic (future)
     ///
     /// This is a placeholder for future WASM/JavaScript integration.
     /// Would allow arbitrary expressions like "metadata.score > data.threshold * 1.5"
+    /// For now only a feature flag check of the form `flag("new_review_path")` is
+    /// evaluated, like `FlagEnabled`; any other script fails.
     ///
     /// Example: `{"type": "Expression", "script": "metadata.score > data.threshold"}`
     Expression { script: String },
@@ -237,9 +289,84 @@ impl Rule {
             explanation,
         }
     }
+
+    /// Whether the rule reads any feature flag, directly or through nested rules
+    pub fn uses_flags(&self) -> bool {
+        match &self.condition {
+            RuleCondition::FlagEnabled { .. } => true,
+            RuleCondition::Expression { script } => parse_flag_call(script).is_some(),
+            RuleCondition::And { rules } | RuleCondition::Or { rules } => {
+                rules.iter().any(Rule::uses_flags)
+            }
+            RuleCondition::Not { rule } => rule.uses_flags(),
+            _ => false,
+        }
+    }
+
+    /// Correlation keys of the rule's sibling conditions, directly or through nested
+    /// rules, without duplicates
+    pub fn correlation_keys(&self) -> Vec<String> {
+        let mut keys = Vec::new();
+        self.collect_correlation_keys(&mut keys);
+        keys
+    }
+
+    fn collect_correlation_keys(&self, keys: &mut Vec<String>) {
+        match &self.condition {
+            RuleCondition::SiblingsInState {
+                correlation_key, ..
+            } if !keys.contains(correlation_key) => keys.push(correlation_key.clone()),
+            RuleCondition::And { rules } | RuleCondition::Or { rules } => {
+                for rule in rules {
+                    rule.collect_correlation_keys(keys);
+                }
+            }
+            RuleCondition::Not { rule } => rule.collect_correlation_keys(keys),
+            _ => {}
+        }
+    }
+
+    /// Metadata and data fields the rule reads, directly or through nested rules,
+    /// correlation keys included, without duplicates
+    pub fn fields(&self) -> Vec<String> {
+        let mut fields = self.correlation_keys();
+        self.collect_fields(&mut fields);
+        fields
+    }
+
+    fn collect_fields(&self, fields: &mut Vec<String>) {
+        match &self.condition {
+            RuleCondition::And { rules } | RuleCondition::Or { rules } => {
+                for rule in rules {
+                    rule.collect_fields(fields);
+                }
+            }
+            RuleCondition::Not { rule } => rule.collect_fields(fields),
+            condition => {
+                if let Some(field) = condition.field() {
+                    if !fields.iter().any(|known| known == field) {
+                        fields.push(field.to_string());
+                    }
+                }
+            }
+        }
+    }
 }
 
 impl RuleCondition {
+    /// The field a field condition reads
+    fn field(&self) -> Option<&str> {
+        match self {
+            RuleCondition::FieldExists { field }
+            | RuleCondition::FieldEquals { field, .. }
+            | RuleCondition::FieldGreaterThan { field, .. }
+            | RuleCondition::FieldLessThan { field, .. }
+            | RuleCondition::FieldContains { field, .. }
+            | RuleCondition::FieldMatches { field, .. } => Some(field),
+            _ => None,
+        }
+    }
+
     /// Evaluate the condition against token state
     ///
     /// This method contains the core evaluation logic for each condition type.
@@ -260,45 +387,50 @@ impl RuleCondition {
         match self {
             RuleCondition::FieldExists { field } => {
                 // Check both metadata HashMap and data JSON object
-                metadata.contains_key(field) || data.get(field).is_some()
+                resolve_field(metadata, data, field).is_some()
             }
 
             RuleCondition::FieldEquals { field, value } => {
                 // Check metadata first, then data
-                metadata.get(field) == Some(value) || data.get(field) == Some(value)
+                metadata.get(field) == Some(value)
+                    || data.get(field) == Some(value)
+                    || resolve_field(metadata, data, field) == Some(value)
             }
 
             RuleCondition::FieldGreaterThan { field, value } => {
-                // Try to get numeric value from either metadata or data
-                let field_value = metadata
-                    .get(field)
-                    .or_else(|| data.get(field)) // Try data if not in metadata
-                    .and_then(|v| v.as_f64()); // Convert to f64 if possible
+                // Try to get numeric value from either metadata or data,
+                // converted to f64 if possible
+                let field_value = resolve_field(metadata, data, field).and_then(|v| v.as_f64());
 
                 // Compare if we got a valid number
                 field_value.map_or(false, |v| v > *value)
             }
 
             RuleCondition::FieldLessThan { field, value } => {
-                let field_value = metadata
-                    .get(field)
-                    .or_else(|| data.get(field))
-                    .and_then(|v| v.as_f64());
+                let field_value = resolve_field(metadata, data, field).and_then(|v| v.as_f64());
 
                 field_value.map_or(false, |v| v < *value)
             }
 
             RuleCondition::FieldContains { field, substring } => {
-                // Try to get string value from either metadata or data
-                let field_value = metadata
-                    .get(field)
-                    .or_else(|| data.get(field))
-                    .and_then(|v| v.as_str()); // Convert to &str if possible
+                // Try to get string value from either metadata or data,
+                // converted to &str if possible
+                let field_value = resolve_field(metadata, data, field).and_then(|v| v.as_str());
 
                 // Check substring if we got a valid string
                 field_value.map_or(false, |v| v.contains(substring))
             }
 
+            RuleCondition::FieldMatches { field, pattern } => {
+                // The pattern is compiled on every call here; the RulesEngine
+                // caches compiled rules so this only happens once per rule
+                let field_value = resolve_field(metadata, data, field).and_then(|v| v.as_str());
+                match (field_value, Regex::new(pattern)) {
+                    (Some(v), Ok(regex)) => regex.is_match(v),
+                    _ => false,
+                }
+            }
+
             RuleCondition::And { rules } => {
                 // All rules must pass - use iterator's all() method
                 rules.iter().all(|rule| rule.evaluate(metadata, data))
@@ -314,11 +446,22 @@ impl RuleCondition {
                 !rule.evaluate(metadata, data)
             }
 
-            RuleCondition::Expression { script: _ } => {
+            RuleCondition::FlagEnabled { flag } => flag_enabled(flag),
+
+            RuleCondition::SiblingsInState {
+                correlation_key,
+                state,
+                workflow_id,
+                min_count,
+            } => count_siblings(correlation_key, state, workflow_id.as_deref()).meets(*min_count),
+
+            RuleCondition::SignalReceived { signal } => signal_received(metadata, signal),
+
+            RuleCondition::Expression { script } => {
                 // TODO: Implement JavaScript/WASM evaluation
-                // For now, always return false as a safe default
-                // In the future, this could use a JS engine or WASM module
-                false
+                // For now only flag checks are understood and anything else
+                // returns false as a safe default
+                parse_flag_call(script).is_some_and(flag_enabled)
             }
         }
     }
@@ -338,9 +481,21 @@ impl RuleCondition {
         metadata: &ResourceMetadata,
         data: &serde_json::Value,
     ) -> (Vec<(String, bool)>, String) {
+        if let Some(field) = self.field() {
+            if references_encrypted(metadata, data, field) {
+                return (
+                    vec![],
+                    format!(
+                        "Field '{}' is encrypted client-side; rules can only use non-encrypted fields",
+                        field
+                    ),
+                );
+            }
+        }
+
         match self {
             RuleCondition::FieldExists { field } => {
-                let exists = metadata.contains_key(field) || data.get(field).is_some();
+                let exists = resolve_field(metadata, data, field).is_some();
                 let explanation = if exists {
                     format!("Field '{}' exists", field)
                 } else {
@@ -350,7 +505,9 @@ impl RuleCondition {
             }
 
             RuleCondition::FieldEquals { field, value } => {
-                let matches = metadata.get(field) == Some(value) || data.get(field) == Some(value);
+                let matches = metadata.get(field) == Some(value)
+                    || data.get(field) == Some(value)
+                    || resolve_field(metadata, data, field) == Some(value);
                 let explanation = if matches {
                     format!("Field '{}' equals {:?}", field, value)
                 } else {
@@ -360,10 +517,7 @@ impl RuleCondition {
             }
 
             RuleCondition::FieldGreaterThan { field, value } => {
-                let field_value = metadata
-                    .get(field)
-                    .or_else(|| data.get(field))
-                    .and_then(|v| v.as_f64());
+                let field_value = resolve_field(metadata, data, field).and_then(|v| v.as_f64());
                 let explanation = match field_value {
                     Some(v) if v > *value => format!("Field '{}' ({}) > {}", field, v, value),
                     Some(v) => format!("Field '{}' ({}) <= {}", field, v, value),
@@ -373,10 +527,7 @@ impl RuleCondition {
             }
 
             RuleCondition::FieldLessThan { field, value } => {
-                let field_value = metadata
-                    .get(field)
-                    .or_else(|| data.get(field))
-                    .and_then(|v| v.as_f64());
+                let field_value = resolve_field(metadata, data, field).and_then(|v| v.as_f64());
                 let explanation = match field_value {
                     Some(v) if v < *value => format!("Field '{}' ({}) < {}", field, v, value),
                     Some(v) => format!("Field '{}' ({}) >= {}", field, v, value),
@@ -386,10 +537,7 @@ impl RuleCondition {
             }
 
             RuleCondition::FieldContains { field, substring } => {
-                let field_value = metadata
-                    .get(field)
-                    .or_else(|| data.get(field))
-                    .and_then(|v| v.as_str());
+                let field_value = resolve_field(metadata, data, field).and_then(|v| v.as_str());
                 let explanation = match field_value {
                     Some(v) if v.contains(substring) => {
                         format!("Field '{}' contains '{}'", field, substring)
@@ -400,6 +548,19 @@ impl RuleCondition {
                 (vec![], explanation)
             }
 
+            RuleCondition::FieldMatches { field, pattern } => {
+                let field_value = resolve_field(metadata, data, field).and_then(|v| v.as_str());
+                let explanation = match (field_value, Regex::new(pattern)) {
+                    (_, Err(e)) => format!("Pattern '{}' is invalid: {}", pattern, e),
+                    (Some(v), Ok(regex)) if regex.is_match(v) => {
+                        format!("Field '{}' matches '{}'", field, pattern)
+                    }
+                    (Some(_), Ok(_)) => format!("Field '{}' does not match '{}'", field, pattern),
+                    (None, Ok(_)) => format!("Field '{}' is not a string", field),
+                };
+                (vec![], explanation)
+            }
+
             RuleCondition::And { rules } => {
                 let sub_results: Vec<(String, bool)> = rules
                     .iter()
@@ -440,13 +601,107 @@ impl RuleCondition {
                 (vec![(rule.id.clone(), !passed)], explanation)
             }
 
-            RuleCondition::Expression { script } => {
-                (vec![], format!("Expression '{}' not implemented", script))
+            RuleCondition::FlagEnabled { flag } => {
+                let explanation = if flag_enabled(flag) {
+                    format!("Flag '{}' is on", flag)
+                } else {
+                    format!("Flag '{}' is off", flag)
+                };
+                (vec![], explanation)
+            }
+
+            RuleCondition::SiblingsInState {
+                correlation_key,
+                state,
+                workflow_id,
+                min_count,
+            } => {
+                let count = count_siblings(correlation_key, state, workflow_id.as_deref());
+                let required = match min_count {
+                    Some(min_count) => format!("at least {}", min_count),
+                    None => "all".to_string(),
+                };
+                let explanation = format!(
+                    "{} of {} siblings by '{}' are in state '{}' ({} required)",
+                    count.matched, count.total, correlation_key, state, required
+                );
+                (vec![], explanation)
+            }
+
+            RuleCondition::SignalReceived { signal } => {
+                let explanation = if signal_received(metadata, signal) {
+                    format!("Signal '{}' was received", signal)
+                } else {
+                    format!("Signal '{}' has not been received", signal)
+                };
+                (vec![], explanation)
             }
+
+            RuleCondition::Expression { script } => match parse_flag_call(script) {
+                Some(flag) if flag_enabled(flag) => (vec![], format!("Flag '{}' is on", flag)),
+                Some(flag) => (vec![], format!("Flag '{}' is off", flag)),
+                None => (vec![], format!("Expression '{}' not implemented", script)),
+            },
         }
     }
 }
 
+/// Whether the signals in `metadata` include one named `signal`
+pub(crate) fn signal_received(metadata: &ResourceMetadata, signal: &str) -> bool {
+    metadata
+        .get(SIGNALS_KEY)
+        .and_then(|signals| signals.get(signal))
+        .is_some()
+}
+
+/// Look a field up in metadata first, then in data
+///
+/// A dotted name such as `review.status` that matches no top-level field is followed
+/// as a path: `review` is looked up in metadata or data and `status` inside it.
+/// Values encrypted client-side never resolve, nor do paths into them.
+pub fn resolve_field<'a>(
+    metadata: &'a ResourceMetadata,
+    data: &'a serde_json::Value,
+    field: &str,
+) -> Option<&'a serde_json::Value> {
+    metadata
+        .get(field)
+        .or_else(|| data.get(field))
+        .or_else(|| {
+            if field.contains('.') {
+                let segments: Vec<&str> = field.split('.').collect();
+                resolve_path(metadata, data, &segments)
+            } else {
+                None
+            }
+        })
+        .filter(|value| !is_encrypted(value))
+}
+
+/// Follow an already split field path; paths with fewer than two segments never resolve
+pub fn resolve_path<'a, S: AsRef<str>>(
+    metadata: &'a ResourceMetadata,
+    data: &'a serde_json::Value,
+    segments: &[S],
+) -> Option<&'a serde_json::Value> {
+    let (first, rest) = segments.split_first()?;
+    if rest.is_empty() {
+        return None;
+    }
+    let root = metadata
+        .get(first.as_ref())
+        .or_else(|| data.get(first.as_ref()))?;
+    rest.iter()
+        .try_fold(root, |value, segment| {
+            if is_encrypted(value) {
+                None
+            } else {
+                value.get(segment.as_ref())
+            }
+        })
+        .filter(|value| !is_encrypted(value))
+}
+
 // Builder methods for easier rule construction
 impl Rule {
     /// Create a simple field exists rule
@@ -505,6 +760,120 @@ impl Rule {
         }
     }
 
+    /// Create a field matches regular expression rule
+    ///
+    /// ## Example:
+    /// ```
+    /// use circuit_breaker::models::Rule;
+    ///
+    /// let rule = Rule::field_matches("company_email", "email", r"@example\.com$");
+    /// ```
+    pub fn field_matches(id: &str, field: &str, pattern: &str) -> Self {
+        Rule {
+            id: id.to_string(),
+            description: format!("Field '{}' must match '{}'", field, pattern),
+            condition: RuleCondition::FieldMatches {
+                field: field.to_string(),
+                pattern: pattern.to_string(),
+            },
+        }
+    }
+
+    /// Create a feature flag rule
+    ///
+    /// ## Example:
+    /// ```
+    /// use circuit_breaker::models::Rule;
+    ///
+    /// let rule = Rule::flag_enabled("new_path", "new_review_path");
+    /// ```
+    pub fn flag_enabled(id: &str, flag: &str) -> Self {
+        Rule {
+            id: id.to_string(),
+            description: format!("Flag '{}' must be on", flag),
+            condition: RuleCondition::FlagEnabled {
+                flag: flag.to_string(),
+            },
+        }
+    }
+
+    /// Create a rule requiring the resource to have received `signal` in its state
+    ///
+    /// ## Example:
+    /// ```
+    /// use circuit_breaker::models::Rule;
+    ///
+    /// let rule = Rule::signal_received("settled", "payment_settled");
+    /// ```
+    pub fn signal_received(id: &str, signal: &str) -> Self {
+        Rule {
+            id: id.to_string(),
+            description: format!("Signal '{}' must have been received", signal),
+            condition: RuleCondition::SignalReceived {
+                signal: signal.to_string(),
+            },
+        }
+    }
+
+    /// Create a rule requiring every sibling sharing `correlation_key` to be in `state`
+    ///
+    /// ## Example:
+    /// ```
+    /// use circuit_breaker::models::Rule;
+    ///
+    /// let rule = Rule::siblings_in_state("items_validated", "order_id", "validated")
+    ///     .in_sibling_workflow("line_item");
+    /// ```
+    pub fn siblings_in_state(id: &str, correlation_key: &str, state: &str) -> Self {
+        Rule {
+            id: id.to_string(),
+            description: format!(
+                "All siblings by '{}' must be in state '{}'",
+                correlation_key, state
+            ),
+            condition: RuleCondition::SiblingsInState {
+                correlation_key: correlation_key.to_string(),
+                state: state.to_string(),
+                workflow_id: None,
+                min_count: None,
+            },
+        }
+    }
+
+    /// Require only `min_count` siblings in the state instead of all of them
+    ///
+    /// Only applies to `SiblingsInState` rules; others are returned unchanged.
+    pub fn at_least(mut self, min_count: u32) -> Self {
+        if let RuleCondition::SiblingsInState {
+            correlation_key,
+            state,
+            min_count: required,
+            ..
+        } = &mut self.condition
+        {
+            *required = Some(min_count);
+            self.description = format!(
+                "At least {} siblings by '{}' must be in state '{}'",
+                min_count, correlation_key, state
+            );
+        }
+        self
+    }
+
+    /// Only count siblings of `workflow_id`
+    ///
+    /// Only applies to `SiblingsInState` rules; others are returned unchanged.
+    pub fn in_sibling_workflow(mut self, workflow_id: &str) -> Self {
+        if let RuleCondition::SiblingsInState {
+            workflow_id: sibling_workflow,
+            ..
+        } = &mut self.condition
+        {
+            *sibling_workflow = Some(workflow_id.to_string());
+        }
+        self
+    }
+
     /// Create an AND combination of rules
     ///
     /// ## Example:
@@ -695,6 +1064,29 @@ mod tests {
         assert!(!rule.evaluate(&metadata, &data));
     }
 
+    #[test]
+    fn test_nested_paths_and_patterns() {
+        let metadata = HashMap::new();
+        let data = serde_json::json!({
+            "email": "alice@example.com",
+            "review": {"status": "approved"},
+        });
+
+        assert!(Rule::field_exists("nested", "review.status").evaluate(&metadata, &data));
+        assert!(!Rule::field_exists("missing", "review.reviewer").evaluate(&metadata, &data));
+        assert!(
+            Rule::field_equals("approved", "review.status", serde_json::json!("approved"))
+                .evaluate(&metadata, &data)
+        );
+
+        assert!(
+            Rule::field_matches("company", "email", r"@example\.com$").evaluate(&metadata, &data)
+        );
+        assert!(!Rule::field_matches("other", "email", r"@other\.com$").evaluate(&metadata, &data));
+        // Invalid patterns never match
+        assert!(!Rule::field_matches("invalid", "email", "(").evaluate(&metadata, &data));
+    }
+
     #[test]
     fn test_detailed_evaluation() {
         let rule = Rule::and(
@@ -721,4 +1113,32 @@ mod tests {
         assert_eq!(result.sub_results[1].0, "status_approved");
         assert!(!result.sub_results[1].1); // Second rule should fail
     }
+
+    #[test]
+    fn test_encrypted_fields() {
+        let rule = Rule::and(
+            "verified",
+            "Verified patient",
+            vec![
+                Rule::field_exists("has_ssn", "ssn"),
+                Rule::field_equals("gold", "tier", serde_json::json!("gold")),
+                Rule::field_exists("has_tier", "tier"),
+            ],
+        );
+        assert_eq!(rule.fields(), vec!["ssn", "tier"]);
+
+        let mut metadata = HashMap::new();
+        metadata.insert(
+            "ssn".to_string(),
+            serde_json::json!({"$encrypted": {"alg": "A256GCM", "nonce": "bg==", "ciphertext": "Yw=="}}),
+        );
+        metadata.insert("tier".to_string(), serde_json::json!("gold"));
+        let data = serde_json::json!({});
+
+        // Encrypted values, and paths into them, never resolve
+        assert!(!rule.evaluate(&metadata, &data));
+        assert!(resolve_field(&metadata, &data, "ssn.nonce").is_none());
+        let result = Rule::field_exists("has_ssn", "ssn").evaluate_detailed(&metadata, &data);
+        assert!(result.explanation.contains("encrypted client-side"));
+    }
 }
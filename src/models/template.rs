@@ -0,0 +1,381 @@
+// Parameterized workflow templates
+// Workflow definitions with typed placeholders resolved when a workflow is created
+
+//! # Workflow Templates
+//!
+//! A [`WorkflowTemplate`] is a workflow definition in JSON form with `{{name}}`
+//! placeholders, plus the typed [`TemplateParameter`]s filling them in:
+//!
+//! ```json
+//! { "type": "FieldGreaterThan", "field": "amount", "value": "{{approval_threshold}}" }
+//! ```
+//!
+//! [`WorkflowTemplate::instantiate`] checks the given parameters - unknown names,
+//! missing required ones and wrongly typed values are refused - fills in defaults and
+//! replaces the placeholders. A string that is exactly one placeholder becomes the
+//! parameter's value with its JSON type, so numbers stay numbers; placeholders inside
+//! longer strings, and in object keys such as the states of `state_slas`, are replaced
+//! by the value's text. The result must be a valid [`WorkflowDefinition`].
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::workflow::WorkflowDefinition;
+use crate::ErrorCode;
+
+lazy_static::lazy_static! {
+    static ref PLACEHOLDER: Regex = Regex::new(r"\{\{\s*([A-Za-z0-9_]+)\s*\}\}").unwrap();
+}
+
+/// JSON type a template parameter takes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParameterType {
+    String,
+    Number,
+    Integer,
+    Boolean,
+}
+
+impl ParameterType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ParameterType::String => "string",
+            ParameterType::Number => "number",
+            ParameterType::Integer => "integer",
+            ParameterType::Boolean => "boolean",
+        }
+    }
+
+    fn accepts(&self, value: &Value) -> bool {
+        match self {
+            ParameterType::String => value.is_string(),
+            ParameterType::Number => value.is_number(),
+            ParameterType::Integer => value.is_i64() || value.is_u64(),
+            ParameterType::Boolean => value.is_boolean(),
+        }
+    }
+}
+
+/// A value a template needs at instantiation, e.g. an approval threshold
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateParameter {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub parameter_type: ParameterType,
+    #[serde(default)]
+    pub description: String,
+    /// Value used when none is given; parameters without one are required
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<Value>,
+}
+
+impl TemplateParameter {
+    pub fn new(name: &str, parameter_type: ParameterType, description: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            parameter_type,
+            description: description.to_string(),
+            default: None,
+        }
+    }
+
+    /// Make the parameter optional, falling back to `default`
+    pub fn with_default(mut self, default: Value) -> Self {
+        self.default = Some(default);
+        self
+    }
+
+    pub fn is_required(&self) -> bool {
+        self.default.is_none()
+    }
+}
+
+/// A workflow definition with placeholders for its parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkflowTemplate {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub parameters: Vec<TemplateParameter>,
+    /// The workflow definition as JSON, without an `id`
+    pub definition: Value,
+}
+
+/// Why a template could not be registered or instantiated
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum TemplateError {
+    #[error("Template not found: {0}")]
+    NotFound(String),
+    #[error("Missing required parameter '{0}'")]
+    MissingParameter(String),
+    #[error("Unknown parameter '{0}'")]
+    UnknownParameter(String),
+    #[error("Parameter '{name}' must be a {expected}")]
+    InvalidParameter {
+        name: String,
+        expected: &'static str,
+    },
+    #[error("Placeholder '{{{{{0}}}}}' has no parameter")]
+    UndeclaredPlaceholder(String),
+    #[error("Template does not produce a valid workflow: {0}")]
+    InvalidDefinition(String),
+}
+
+impl TemplateError {
+    /// Stable error code for API responses
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            TemplateError::NotFound(_) => ErrorCode::NotFound,
+            _ => ErrorCode::InvalidInput,
+        }
+    }
+}
+
+impl WorkflowTemplate {
+    pub fn new(id: &str, name: &str, description: &str, definition: Value) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            description: description.to_string(),
+            parameters: Vec::new(),
+            definition,
+        }
+    }
+
+    pub fn with_parameter(mut self, parameter: TemplateParameter) -> Self {
+        self.parameters.push(parameter);
+        self
+    }
+
+    /// Check every placeholder has a parameter and every default has its type
+    pub fn validate(&self) -> Result<(), TemplateError> {
+        for parameter in &self.parameters {
+            if let Some(default) = &parameter.default {
+                check_type(parameter, default)?;
+            }
+        }
+        let mut undeclared = None;
+        visit_strings(&self.definition, &mut |text| {
+            for captures in PLACEHOLDER.captures_iter(text) {
+                let name = &captures[1];
+                if undeclared.is_none() && !self.parameters.iter().any(|p| p.name == name) {
+                    undeclared = Some(name.to_string());
+                }
+            }
+        });
+        match undeclared {
+            Some(name) => Err(TemplateError::UndeclaredPlaceholder(name)),
+            None => Ok(()),
+        }
+    }
+
+    /// Create the workflow `id` from the template with `parameters`
+    pub fn instantiate(
+        &self,
+        id: &str,
+        parameters: &Map<String, Value>,
+    ) -> Result<WorkflowDefinition, TemplateError> {
+        self.validate()?;
+        if let Some(unknown) = parameters
+            .keys()
+            .find(|name| !self.parameters.iter().any(|p| &p.name == *name))
+        {
+            return Err(TemplateError::UnknownParameter(unknown.clone()));
+        }
+
+        let mut values = Map::new();
+        for parameter in &self.parameters {
+            let value = parameters
+                .get(&parameter.name)
+                .or(parameter.default.as_ref())
+                .ok_or_else(|| TemplateError::MissingParameter(parameter.name.clone()))?;
+            check_type(parameter, value)?;
+            values.insert(parameter.name.clone(), value.clone());
+        }
+
+        let mut definition = substitute(&self.definition, &values);
+        if let Value::Object(fields) = &mut definition {
+            fields.insert("id".to_string(), Value::String(id.to_string()));
+        }
+        let workflow: WorkflowDefinition = serde_json::from_value(definition)
+            .map_err(|e| TemplateError::InvalidDefinition(e.to_string()))?;
+        workflow
+            .validate()
+            .map_err(TemplateError::InvalidDefinition)?;
+        Ok(workflow)
+    }
+}
+
+fn check_type(parameter: &TemplateParameter, value: &Value) -> Result<(), TemplateError> {
+    if parameter.parameter_type.accepts(value) {
+        return Ok(());
+    }
+    Err(TemplateError::InvalidParameter {
+        name: parameter.name.clone(),
+        expected: parameter.parameter_type.as_str(),
+    })
+}
+
+/// Call `visit` with every string in `value`, object keys included
+fn visit_strings(value: &Value, visit: &mut impl FnMut(&str)) {
+    match value {
+        Value::String(text) => visit(text),
+        Value::Array(items) => items.iter().for_each(|item| visit_strings(item, visit)),
+        Value::Object(fields) => {
+            for (key, field) in fields {
+                visit(key);
+                visit_strings(field, visit);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Replace the placeholders in the strings of `value` by `values`
+fn substitute(value: &Value, values: &Map<String, Value>) -> Value {
+    match value {
+        Value::String(text) => {
+            if let Some(captures) = PLACEHOLDER.captures(text) {
+                if captures[0].len() == text.len() {
+                    return values[&captures[1]].clone();
+                }
+            }
+            Value::String(substitute_text(text, values))
+        }
+        Value::Array(items) => Value::Array(items.iter().map(|i| substitute(i, values)).collect()),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, field)| (substitute_text(key, values), substitute(field, values)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn substitute_text(text: &str, values: &Map<String, Value>) -> String {
+    PLACEHOLDER
+        .replace_all(text, |captures: &regex::Captures| {
+            match &values[&captures[1]] {
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            }
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{RuleCondition, StateId};
+    use serde_json::json;
+
+    fn threshold_template() -> WorkflowTemplate {
+        WorkflowTemplate::new(
+            "threshold",
+            "Threshold",
+            "",
+            json!({
+                "name": "{{team}} approvals",
+                "states": ["open", "{{team}}_review", "done"],
+                "initial_state": "open",
+                "activities": [{
+                    "id": "escalate",
+                    "from_states": ["open"],
+                    "to_state": "{{team}}_review",
+                    "conditions": [],
+                    "rules": [{
+                        "id": "large",
+                        "description": "Large amounts",
+                        "condition": {
+                            "type": "FieldGreaterThan",
+                            "field": "amount",
+                            "value": "{{threshold}}"
+                        }
+                    }]
+                }, {
+                    "id": "finish",
+                    "from_states": ["{{team}}_review"],
+                    "to_state": "done",
+                    "conditions": [],
+                    "rules": []
+                }],
+                "state_slas": { "{{team}}_review": { "max_secs": "{{sla_secs}}" } }
+            }),
+        )
+        .with_parameter(TemplateParameter::new(
+            "threshold",
+            ParameterType::Number,
+            "Amount above which the team reviews",
+        ))
+        .with_parameter(TemplateParameter::new("team", ParameterType::String, ""))
+        .with_parameter(
+            TemplateParameter::new("sla_secs", ParameterType::Integer, "")
+                .with_default(json!(3600)),
+        )
+    }
+
+    #[test]
+    fn test_instantiate_resolves_typed_placeholders() {
+        let parameters = json!({ "threshold": 5000.0, "team": "finance" });
+        let workflow = threshold_template()
+            .instantiate("wf-1", parameters.as_object().unwrap())
+            .unwrap();
+
+        assert_eq!(workflow.id, "wf-1");
+        assert_eq!(workflow.name, "finance approvals");
+        assert!(workflow.states.contains(&StateId::from("finance_review")));
+        assert_eq!(
+            workflow.activities[0].rules[0].condition,
+            RuleCondition::FieldGreaterThan {
+                field: "amount".to_string(),
+                value: 5000.0
+            }
+        );
+        let sla = workflow
+            .state_sla(&StateId::from("finance_review"))
+            .unwrap();
+        assert_eq!(sla.max_secs, 3600);
+    }
+
+    #[test]
+    fn test_instantiate_checks_parameters() {
+        let template = threshold_template();
+        let instantiate = |parameters: Value| {
+            template
+                .instantiate("wf-1", parameters.as_object().unwrap())
+                .unwrap_err()
+        };
+
+        assert_eq!(
+            instantiate(json!({ "team": "finance" })),
+            TemplateError::MissingParameter("threshold".to_string())
+        );
+        assert_eq!(
+            instantiate(json!({ "team": "finance", "threshold": "high" })),
+            TemplateError::InvalidParameter {
+                name: "threshold".to_string(),
+                expected: "number"
+            }
+        );
+        assert_eq!(
+            instantiate(json!({ "team": "finance", "threshold": 1, "owner": "ada" })),
+            TemplateError::UnknownParameter("owner".to_string())
+        );
+        assert!(matches!(
+            instantiate(json!({ "team": "finance", "threshold": 1, "sla_secs": 0 })),
+            TemplateError::InvalidDefinition(_)
+        ));
+
+        let mut template = threshold_template();
+        template.parameters.pop();
+        assert_eq!(
+            template.validate(),
+            Err(TemplateError::UndeclaredPlaceholder("sla_secs".to_string()))
+        );
+    }
+}
@@ -0,0 +1,363 @@
+// Inbound webhook triggers
+// External events mapped onto resource creation and activity firings
+
+//! # Webhook Triggers
+//!
+//! A [`WebhookTrigger`] lets a workflow react to events from outside: GitHub, Stripe or
+//! any service posting JSON to the trigger's URL. Each request is authenticated with an
+//! HMAC of its body under the trigger's shared secret, in the sender's own scheme:
+//!
+//! - `github`: `X-Hub-Signature-256: sha256=<hex>`; the event is the `X-GitHub-Event`
+//!   header, followed by the payload's `action` if it has one, e.g. `pull_request.closed`
+//! - `stripe`: `Stripe-Signature: t=<timestamp>,v1=<hex>` over `<timestamp>.<body>`;
+//!   the event is the payload's `type`, e.g. `invoice.paid`
+//! - `generic`: the hex HMAC-SHA256 of the body in `signature_header` (`X-Signature` by
+//!   default), optionally prefixed `sha256=`; the event is read from the payload at
+//!   `event_pointer`, if set
+//!
+//! The trigger's [`TriggerMapping`]s then say what the event does. A mapping applies
+//! to events equal to its `event` or beneath it, so `invoice` also covers
+//! `invoice.paid`; a mapping without one applies to every event:
+//!
+//! ```json
+//! { "event": "pull_request.closed",
+//!   "action": { "type": "fire_activity", "activity_id": "merge",
+//!               "resources": { "by": "correlation", "key": "pr", "pointer": "/number" } } }
+//! ```
+//!
+//! Payload values are addressed with JSON pointers (RFC 6901).
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::state::{ActivityId, StateId};
+use super::workflow::WorkflowDefinition;
+use crate::ErrorCode;
+
+/// Header carrying the signature of generic webhooks unless configured otherwise
+pub const DEFAULT_SIGNATURE_HEADER: &str = "X-Signature";
+
+/// Who sends a trigger's webhooks, which decides how they are signed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerSource {
+    Github,
+    Stripe,
+    Generic,
+}
+
+impl TriggerSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TriggerSource::Github => "github",
+            TriggerSource::Stripe => "stripe",
+            TriggerSource::Generic => "generic",
+        }
+    }
+
+    pub fn parse(source: &str) -> Option<Self> {
+        match source {
+            "github" => Some(TriggerSource::Github),
+            "stripe" => Some(TriggerSource::Stripe),
+            "generic" => Some(TriggerSource::Generic),
+            _ => None,
+        }
+    }
+}
+
+/// Which resources an event fires an activity on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "by", rename_all = "snake_case")]
+pub enum ResourceSelector {
+    /// The resource whose ID is in the payload at `pointer`
+    Id { pointer: String },
+    /// The resources whose metadata `key` holds the value in the payload at `pointer`
+    Correlation { key: String, pointer: String },
+}
+
+/// What an event does to the trigger's workflow
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TriggerAction {
+    /// Create a resource from the payload
+    CreateResource {
+        /// State the resource starts in; the workflow's initial state by default
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        initial_state: Option<StateId>,
+        /// Part of the payload becoming the resource's data; all of it by default
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        data_pointer: Option<String>,
+        /// Metadata keys and the payload values they are set to
+        /// Examples: {"customer": "/data/object/customer"}
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        metadata: HashMap<String, String>,
+    },
+    /// Fire an activity on existing resources whose rules allow it
+    FireActivity {
+        activity_id: ActivityId,
+        resources: ResourceSelector,
+    },
+}
+
+/// An event a trigger reacts to, and how
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TriggerMapping {
+    /// Event the mapping applies to, along with the events beneath it; every event
+    /// when absent
+    /// Examples: "push", "pull_request.closed", "invoice.paid"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<String>,
+    pub action: TriggerAction,
+}
+
+impl TriggerMapping {
+    /// Whether the mapping applies to `event`
+    pub fn matches(&self, event: Option<&str>) -> bool {
+        let Some(mapped) = &self.event else {
+            return true;
+        };
+        event.is_some_and(|event| {
+            event == mapped
+                || event
+                    .strip_prefix(mapped.as_str())
+                    .is_some_and(|rest| rest.starts_with('.'))
+        })
+    }
+}
+
+/// An inbound webhook endpoint of a workflow, served at `/v1/triggers/{id}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookTrigger {
+    pub id: String,
+    pub workflow_id: String,
+    pub source: TriggerSource,
+    /// Shared secret requests are signed with
+    #[serde(skip_serializing)]
+    pub secret: String,
+    /// Header holding the signature of generic webhooks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature_header: Option<String>,
+    /// Where generic webhook payloads name their event
+    /// Examples: "/event", "/meta/type"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_pointer: Option<String>,
+    pub mappings: Vec<TriggerMapping>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Why a webhook trigger could not be registered or handled
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum TriggerError {
+    #[error("Webhook trigger not found: {0}")]
+    NotFound(String),
+    #[error("Invalid webhook trigger: {0}")]
+    Invalid(String),
+    #[error("Webhook signature is missing or invalid")]
+    InvalidSignature,
+    #[error("Invalid webhook payload: {0}")]
+    InvalidPayload(String),
+    #[error("Storage error: {0}")]
+    Storage(String),
+}
+
+impl TriggerError {
+    /// Stable error code for API responses
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            TriggerError::NotFound(_) => ErrorCode::NotFound,
+            TriggerError::InvalidSignature => ErrorCode::AuthenticationFailed,
+            TriggerError::Invalid(_) | TriggerError::InvalidPayload(_) => ErrorCode::InvalidInput,
+            TriggerError::Storage(_) => ErrorCode::StorageError,
+        }
+    }
+}
+
+impl WebhookTrigger {
+    pub fn new(id: &str, workflow_id: &str, source: TriggerSource, secret: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            workflow_id: workflow_id.to_string(),
+            source,
+            secret: secret.to_string(),
+            signature_header: None,
+            event_pointer: None,
+            mappings: Vec::new(),
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn with_mapping(mut self, event: Option<&str>, action: TriggerAction) -> Self {
+        self.mappings.push(TriggerMapping {
+            event: event.map(str::to_string),
+            action,
+        });
+        self
+    }
+
+    /// Header the signature is read from
+    pub fn signature_header(&self) -> &str {
+        match self.source {
+            TriggerSource::Github => "X-Hub-Signature-256",
+            TriggerSource::Stripe => "Stripe-Signature",
+            TriggerSource::Generic => self
+                .signature_header
+                .as_deref()
+                .unwrap_or(DEFAULT_SIGNATURE_HEADER),
+        }
+    }
+
+    /// The mappings applying to `event`, in order
+    pub fn mappings_for<'a>(
+        &'a self,
+        event: Option<&'a str>,
+    ) -> impl Iterator<Item = &'a TriggerMapping> + 'a {
+        self.mappings.iter().filter(move |m| m.matches(event))
+    }
+
+    /// Check the trigger is usable with `workflow`: it has a secret and mappings, and
+    /// these name the workflow's states and activities
+    pub fn validate(&self, workflow: &WorkflowDefinition) -> Result<(), TriggerError> {
+        let invalid = |message: String| Err(TriggerError::Invalid(message));
+        if self.id.is_empty()
+            || !self
+                .id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return invalid(format!(
+                "ID '{}' must be letters, digits, '-' and '_'",
+                self.id
+            ));
+        }
+        if self.workflow_id != workflow.id {
+            return invalid(format!("Trigger is not for workflow '{}'", workflow.id));
+        }
+        if self.secret.is_empty() {
+            return invalid("A secret is required to verify signatures".to_string());
+        }
+        if self.mappings.is_empty() {
+            return invalid("At least one mapping is required".to_string());
+        }
+
+        let mut pointers: Vec<&str> = self.event_pointer.iter().map(String::as_str).collect();
+        for mapping in &self.mappings {
+            match &mapping.action {
+                TriggerAction::CreateResource {
+                    initial_state,
+                    data_pointer,
+                    metadata,
+                } => {
+                    if let Some(state) = initial_state {
+                        if !workflow.states.contains(state) {
+                            return invalid(format!(
+                                "Mapping creates resources in invalid state '{}'",
+                                state.as_str()
+                            ));
+                        }
+                    }
+                    pointers.extend(data_pointer.as_deref());
+                    pointers.extend(metadata.values().map(String::as_str));
+                }
+                TriggerAction::FireActivity {
+                    activity_id,
+                    resources,
+                } => {
+                    let Some(activity) = workflow.activities.iter().find(|a| &a.id == activity_id)
+                    else {
+                        return invalid(format!(
+                            "Mapping fires invalid activity '{}'",
+                            activity_id.as_str()
+                        ));
+                    };
+                    if activity.is_manual() {
+                        return invalid(format!(
+                            "Activity '{}' is a human task and cannot be fired by a webhook",
+                            activity_id.as_str()
+                        ));
+                    }
+                    match resources {
+                        ResourceSelector::Id { pointer } => pointers.push(pointer),
+                        ResourceSelector::Correlation { key, pointer } => {
+                            if key.is_empty() {
+                                return invalid("Correlation key must not be empty".to_string());
+                            }
+                            pointers.push(pointer);
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(pointer) = pointers
+            .into_iter()
+            .find(|p| !p.is_empty() && !p.starts_with('/'))
+        {
+            return invalid(format!("'{}' is not a JSON pointer", pointer));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ActivityDefinition;
+
+    fn workflow() -> WorkflowDefinition {
+        WorkflowDefinition::new(
+            "orders",
+            "Orders",
+            vec![StateId::from("open"), StateId::from("paid")],
+            vec![ActivityDefinition::new("pay", vec!["open"], "paid")],
+            "open",
+        )
+    }
+
+    #[test]
+    fn test_mapping_matches_events_beneath_it() {
+        let mapping = TriggerMapping {
+            event: Some("invoice".to_string()),
+            action: TriggerAction::FireActivity {
+                activity_id: ActivityId::from("pay"),
+                resources: ResourceSelector::Id {
+                    pointer: "/id".to_string(),
+                },
+            },
+        };
+        assert!(mapping.matches(Some("invoice")));
+        assert!(mapping.matches(Some("invoice.paid")));
+        assert!(!mapping.matches(Some("invoiceitem.created")));
+        assert!(!mapping.matches(None));
+    }
+
+    #[test]
+    fn test_validate() {
+        let fire = |activity: &str, pointer: &str| TriggerAction::FireActivity {
+            activity_id: ActivityId::from(activity),
+            resources: ResourceSelector::Correlation {
+                key: "order".to_string(),
+                pointer: pointer.to_string(),
+            },
+        };
+        let trigger = WebhookTrigger::new("stripe-orders", "orders", TriggerSource::Stripe, "s")
+            .with_mapping(Some("invoice.paid"), fire("pay", "/data/object/id"));
+        assert!(trigger.validate(&workflow()).is_ok());
+
+        for invalid in [
+            WebhookTrigger::new("stripe orders", "orders", TriggerSource::Stripe, "s")
+                .with_mapping(None, fire("pay", "/id")),
+            WebhookTrigger::new("t", "orders", TriggerSource::Stripe, "")
+                .with_mapping(None, fire("pay", "/id")),
+            WebhookTrigger::new("t", "orders", TriggerSource::Stripe, "s"),
+            WebhookTrigger::new("t", "orders", TriggerSource::Stripe, "s")
+                .with_mapping(None, fire("ship", "/id")),
+            WebhookTrigger::new("t", "orders", TriggerSource::Stripe, "s")
+                .with_mapping(None, fire("pay", "id")),
+        ] {
+            assert!(matches!(
+                invalid.validate(&workflow()),
+                Err(TriggerError::Invalid(_))
+            ));
+        }
+    }
+}
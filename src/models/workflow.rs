@@ -33,9 +33,17 @@
 //! - Hash sets for efficient lookups
 //! - Complex generic functions
 
-use super::activity::ActivityDefinition;
+use super::activity::{ActivityDefinition, Gateway, RetryBackoff};
+use super::agent::{AgentId, StateAgentConfig};
+use super::confidential::is_encrypted;
+use super::expression::Expression;
+use super::function::{EventTrigger, EventType, FunctionId};
+use super::resource::ResourceMetadata;
+use super::rule::Rule;
 use super::state::{ActivityId, StateId}; // Basic workflow components
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize}; // JSON serialization support
+use std::collections::HashMap;
 
 /// Generic workflow definition - completely domain-agnostic
 ///
@@ -82,6 +90,11 @@ pub struct WorkflowDefinition {
     /// Examples: "document_review", "deployment_pipeline", "order_fulfillment"
     pub id: String,
 
+    /// Version of the definition, starting at 1 and bumped each time a new version is
+    /// published; earlier versions stay addressable in storage
+    #[serde(default = "first_version")]
+    pub version: u32,
+
     /// Human-readable name for this workflow
     /// Examples: "Document Review Process", "CI/CD Pipeline", "E-commerce Order"
     pub name: String,
@@ -100,6 +113,186 @@ pub struct WorkflowDefinition {
     /// Every new resource in this workflow begins here
     /// Must be one of the states in the `states` vector
     pub initial_state: StateId,
+
+    /// Most resources each listed state may hold at once
+    /// Activities targeting a full state are blocked until a resource leaves it
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub state_capacities: HashMap<StateId, StateCapacity>,
+
+    /// Service levels of the listed states: how long a resource should sit in each,
+    /// and how a breach is escalated
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub state_slas: HashMap<StateId, StateSla>,
+
+    /// JSON Schema resource metadata must conform to when resources are created or
+    /// their metadata updated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata_schema: Option<MetadataSchema>,
+
+    /// Metadata fields clients encrypt before sending them (see
+    /// [`confidential`](super::confidential)); the server only stores their ciphertext
+    /// and rules may not read them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub confidential_metadata: Vec<String>,
+
+    /// The definition this one was forked from, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forked_from: Option<WorkflowProvenance>,
+}
+
+/// Version of newly created definitions, and of definitions stored before versioning
+pub(crate) fn first_version() -> u32 {
+    1
+}
+
+/// Origin of a forked workflow definition
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkflowProvenance {
+    /// ID of the definition forked
+    pub workflow_id: String,
+    /// When the fork was made
+    pub forked_at: DateTime<Utc>,
+}
+
+/// Schema of a workflow's resource metadata and how strictly it is enforced
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetadataSchema {
+    /// JSON Schema of the metadata object
+    pub schema: serde_json::Value,
+
+    /// Whether non-conforming metadata is rejected or only logged
+    #[serde(default)]
+    pub enforcement: SchemaEnforcement,
+}
+
+/// What happens to metadata that does not conform to its workflow's schema
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaEnforcement {
+    /// The operation is rejected
+    #[default]
+    Strict,
+    /// The operation goes ahead and the violations are logged
+    Warn,
+}
+
+impl MetadataSchema {
+    /// Reject metadata not conforming to `schema`
+    pub fn strict(schema: serde_json::Value) -> Self {
+        Self {
+            schema,
+            enforcement: SchemaEnforcement::Strict,
+        }
+    }
+
+    /// Only log metadata not conforming to `schema`
+    pub fn warn(schema: serde_json::Value) -> Self {
+        Self {
+            schema,
+            enforcement: SchemaEnforcement::Warn,
+        }
+    }
+
+    /// Violations of the schema by `metadata`, one message each; empty if it conforms
+    pub fn violations(&self, metadata: &ResourceMetadata) -> Vec<String> {
+        let metadata = serde_json::Value::Object(
+            metadata
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        );
+        crate::llm::structured::validate(&metadata, &self.schema)
+    }
+}
+
+/// Capacity constraint of one state, e.g. at most 5 resources `deploying` at once
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateCapacity {
+    /// Most resources the state may hold at once
+    pub max_resources: u32,
+
+    /// Which waiting resource enters first when the state has room again
+    #[serde(default)]
+    pub queue_order: CapacityQueueOrder,
+
+    /// Numeric data field ranking waiting resources under `Priority` order, highest first
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority_field: Option<String>,
+}
+
+/// Service level of one state, e.g. resources should not sit in `triage` over an hour
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateSla {
+    /// Longest a resource should sit in the state, in seconds
+    pub max_secs: u64,
+
+    /// Activity executed on a resource breaching the SLA, e.g. "escalate"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escalation_activity: Option<ActivityId>,
+
+    /// URL the breach is POSTed to as JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+}
+
+impl StateSla {
+    /// Expect resources to leave the state within `max_secs`
+    pub fn new(max_secs: u64) -> Self {
+        Self {
+            max_secs,
+            escalation_activity: None,
+            webhook_url: None,
+        }
+    }
+
+    /// Execute `activity` on resources breaching the SLA
+    pub fn escalate_with<A: Into<ActivityId>>(mut self, activity: A) -> Self {
+        self.escalation_activity = Some(activity.into());
+        self
+    }
+
+    /// POST breaches of the SLA to `url`
+    pub fn notify(mut self, url: impl Into<String>) -> Self {
+        self.webhook_url = Some(url.into());
+        self
+    }
+}
+
+/// Order in which resources waiting for a full state are let in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CapacityQueueOrder {
+    /// Longest waiting first
+    #[default]
+    Fifo,
+    /// Most recently blocked first
+    Lifo,
+    /// Highest `priority_field` first, longest waiting among equals
+    Priority,
+}
+
+impl StateCapacity {
+    /// Let in at most `max_resources` at once, longest waiting first
+    pub fn new(max_resources: u32) -> Self {
+        Self {
+            max_resources,
+            queue_order: CapacityQueueOrder::Fifo,
+            priority_field: None,
+        }
+    }
+
+    /// Let in the most recently blocked resource first
+    pub fn lifo(mut self) -> Self {
+        self.queue_order = CapacityQueueOrder::Lifo;
+        self
+    }
+
+    /// Let in the waiting resource with the highest value of a numeric data field first
+    pub fn by_priority(mut self, field: impl Into<String>) -> Self {
+        self.queue_order = CapacityQueueOrder::Priority;
+        self.priority_field = Some(field.into());
+        self
+    }
 }
 
 impl WorkflowDefinition {
@@ -129,13 +322,125 @@ impl WorkflowDefinition {
     ) -> Self {
         WorkflowDefinition {
             id: id.into(),                       // Convert to String
+            version: first_version(),            // A new definition
             name: name.into(),                   // Convert to String
             states,                              // Move the vector
             activities,                          // Move the vector
             initial_state: initial_state.into(), // Convert to StateId
+            state_capacities: HashMap::new(),    // No capacity constraints
+            state_slas: HashMap::new(),          // No service levels
+            metadata_schema: None,               // Any metadata accepted
+            confidential_metadata: Vec::new(),   // Nothing encrypted client-side
+            forked_from: None,                   // An original definition
+        }
+    }
+
+    /// Start a [`WorkflowBuilder`] for a definition with this ID and name
+    pub fn builder<S: Into<String>, N: Into<String>>(id: S, name: N) -> WorkflowBuilder {
+        WorkflowBuilder::new(id, name)
+    }
+
+    /// Limit how many resources a state may hold at once
+    pub fn with_state_capacity<I: Into<StateId>>(
+        mut self,
+        state: I,
+        capacity: StateCapacity,
+    ) -> Self {
+        self.state_capacities.insert(state.into(), capacity);
+        self
+    }
+
+    /// Capacity constraint of a state, if it has one
+    pub fn state_capacity(&self, state: &StateId) -> Option<&StateCapacity> {
+        self.state_capacities.get(state)
+    }
+
+    /// Expect resources to leave a state within its SLA
+    pub fn with_state_sla<I: Into<StateId>>(mut self, state: I, sla: StateSla) -> Self {
+        self.state_slas.insert(state.into(), sla);
+        self
+    }
+
+    /// Service level of a state, if it has one
+    pub fn state_sla(&self, state: &StateId) -> Option<&StateSla> {
+        self.state_slas.get(state)
+    }
+
+    /// Copy this definition under a new ID and name, recording it as the origin
+    ///
+    /// States, activities with their rules, capacities and the metadata schema are all
+    /// copied; the copy can then be changed without affecting this definition. The fork
+    /// starts its own versions at 1.
+    pub fn fork<S: Into<String>, N: Into<String>>(&self, id: S, name: N) -> Self {
+        WorkflowDefinition {
+            id: id.into(),
+            version: first_version(),
+            name: name.into(),
+            forked_from: Some(WorkflowProvenance {
+                workflow_id: self.id.clone(),
+                forked_at: Utc::now(),
+            }),
+            ..self.clone()
+        }
+    }
+
+    /// Require resource metadata to conform to a JSON Schema
+    pub fn with_metadata_schema(mut self, schema: MetadataSchema) -> Self {
+        self.metadata_schema = Some(schema);
+        self
+    }
+
+    /// Have clients encrypt these metadata fields, which rules may then not read
+    pub fn with_confidential_metadata<S: Into<String>>(
+        mut self,
+        fields: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.confidential_metadata = fields.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Whether a field name or a path into it reads confidential metadata
+    pub fn is_confidential(&self, field: &str) -> bool {
+        self.confidential_metadata.iter().any(|confidential| {
+            field
+                .strip_prefix(confidential.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+        })
+    }
+
+    /// Refuse a rule that reads confidential metadata
+    pub fn check_rule_fields(&self, rule: &Rule) -> Result<(), String> {
+        match rule
+            .fields()
+            .into_iter()
+            .find(|field| self.is_confidential(field))
+        {
+            Some(field) => Err(format!(
+                "Rule '{}' reads '{}', which is encrypted client-side; rules can only use non-encrypted fields",
+                rule.id, field
+            )),
+            None => Ok(()),
         }
     }
 
+    /// Confidential metadata fields holding plaintext rather than an encrypted envelope
+    pub fn confidential_violations(&self, metadata: &ResourceMetadata) -> Vec<String> {
+        self.confidential_metadata
+            .iter()
+            .filter(|field| {
+                metadata
+                    .get(*field)
+                    .is_some_and(|value| !is_encrypted(value))
+            })
+            .map(|field| {
+                format!(
+                    "Metadata field '{}' is confidential and must be encrypted client-side",
+                    field
+                )
+            })
+            .collect()
+    }
+
     /// Check if an activity is valid from the current state
     ///
     /// This is the core method used by the workflow engine to determine if
@@ -251,6 +556,177 @@ impl WorkflowDefinition {
                     activity.to_state.as_str()
                 ));
             }
+
+            // Check a declared compensation is an activity of this workflow
+            if let Some(compensation) = &activity.compensation {
+                if !self.activities.iter().any(|a| &a.id == compensation) {
+                    return Err(format!(
+                        "Activity '{}' is compensated by unknown activity '{}'",
+                        activity.id.as_str(),
+                        compensation.as_str()
+                    ));
+                }
+            }
+
+            // Check a split forks into at least two distinct states of this workflow
+            if let Some(Gateway::Split(branches)) = &activity.gateway {
+                if let Some(branch) = branches.iter().find(|b| !state_set.contains(b)) {
+                    return Err(format!(
+                        "Activity '{}' splits into invalid state '{}'",
+                        activity.id.as_str(),
+                        branch.as_str()
+                    ));
+                }
+                let distinct: std::collections::HashSet<_> = branches.iter().collect();
+                if distinct.len() < 2 || distinct.len() != branches.len() {
+                    return Err(format!(
+                        "Activity '{}' must split into at least two distinct states",
+                        activity.id.as_str()
+                    ));
+                }
+            }
+
+            // Check a human task rejects resources into a state of this workflow
+            if let Some(reject_to) = activity.manual.as_ref().and_then(|m| m.reject_to.as_ref()) {
+                if !state_set.contains(reject_to) {
+                    return Err(format!(
+                        "Activity '{}' rejects to invalid state '{}'",
+                        activity.id.as_str(),
+                        reject_to.as_str()
+                    ));
+                }
+            }
+
+            // Check routes parse and lead to states of this workflow; gateways fix where
+            // the resource goes, so they take no routes
+            if !activity.routes.is_empty() && activity.gateway.is_some() {
+                return Err(format!(
+                    "Activity '{}' cannot combine routes with a split or join",
+                    activity.id.as_str()
+                ));
+            }
+            for route in &activity.routes {
+                if let Err(e) = Expression::parse(&route.when) {
+                    return Err(format!(
+                        "Route of activity '{}' has an invalid expression '{}': {}",
+                        activity.id.as_str(),
+                        route.when,
+                        e
+                    ));
+                }
+                if !state_set.contains(&route.to_state) {
+                    return Err(format!(
+                        "Activity '{}' routes to invalid state '{}'",
+                        activity.id.as_str(),
+                        route.to_state.as_str()
+                    ));
+                }
+            }
+
+            // Check a retry policy makes at least one attempt and backs off forward
+            if let Some(retry) = &activity.retry {
+                if retry.max_attempts == 0 {
+                    return Err(format!(
+                        "Retry policy of activity '{}' must allow at least 1 attempt",
+                        activity.id.as_str()
+                    ));
+                }
+                if let RetryBackoff::Exponential { multiplier } = retry.backoff {
+                    if !(multiplier >= 1.0 && multiplier.is_finite()) {
+                        return Err(format!(
+                            "Retry policy of activity '{}' needs a backoff multiplier of at least 1",
+                            activity.id.as_str()
+                        ));
+                    }
+                }
+            }
+        }
+
+        // Check capacity constraints name existing states and can rank waiting resources
+        for (state, capacity) in &self.state_capacities {
+            if !state_set.contains(state) {
+                return Err(format!(
+                    "Capacity references invalid state '{}'",
+                    state.as_str()
+                ));
+            }
+            if capacity.max_resources == 0 {
+                return Err(format!(
+                    "Capacity of state '{}' must be at least 1",
+                    state.as_str()
+                ));
+            }
+            if capacity.queue_order == CapacityQueueOrder::Priority
+                && capacity.priority_field.as_deref().is_none_or(str::is_empty)
+            {
+                return Err(format!(
+                    "Capacity of state '{}' orders by priority but names no priority field",
+                    state.as_str()
+                ));
+            }
+        }
+
+        // Check SLAs name existing states and escalate through activities leaving them
+        for (state, sla) in &self.state_slas {
+            if !state_set.contains(state) {
+                return Err(format!("SLA references invalid state '{}'", state.as_str()));
+            }
+            if sla.max_secs == 0 {
+                return Err(format!(
+                    "SLA of state '{}' must be at least 1 second",
+                    state.as_str()
+                ));
+            }
+            if let Some(escalation) = &sla.escalation_activity {
+                if !self
+                    .activities
+                    .iter()
+                    .any(|a| &a.id == escalation && a.can_execute_from(state))
+                {
+                    return Err(format!(
+                        "SLA of state '{}' escalates with '{}', which is not an activity leaving it",
+                        state.as_str(),
+                        escalation.as_str()
+                    ));
+                }
+            }
+            if let Some(url) = &sla.webhook_url {
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    return Err(format!(
+                        "SLA webhook of state '{}' must be an http(s) URL",
+                        state.as_str()
+                    ));
+                }
+            }
+        }
+
+        // Check confidential fields are named and no activity rule reads them
+        if self
+            .confidential_metadata
+            .iter()
+            .any(|field| field.trim().is_empty())
+        {
+            return Err("Confidential metadata field names must not be empty".to_string());
+        }
+        for activity in &self.activities {
+            for rule in &activity.rules {
+                self.check_rule_fields(rule)?;
+            }
+        }
+
+        // Check the metadata schema describes an object
+        if let Some(metadata_schema) = &self.metadata_schema {
+            if !metadata_schema.schema.is_object() {
+                return Err("Metadata schema must be a JSON object".to_string());
+            }
+            if let Some(kind) = metadata_schema.schema.get("type") {
+                if kind != "object" {
+                    return Err(format!(
+                        "Metadata schema must have type 'object', not {}",
+                        kind
+                    ));
+                }
+            }
         }
 
         // If we get here, validation passed
@@ -272,14 +748,15 @@ impl WorkflowDefinition {
     pub fn incoming_states(&self, to_state: &StateId) -> Vec<&StateId> {
         self.activities
             .iter() // Iterate over activities
-            .filter(|a| a.to_state == *to_state) // Keep only activities to target state
+            .filter(|a| a.target_states().any(|s| s == to_state)) // Keep activities to target state
             .flat_map(|a| &a.from_states) // Flatten all from_states into single iterator
             .collect() // Collect into vector
     }
 
     /// Get all states that can be reached from the given state
     ///
-    /// This finds all target states reachable in one activity from the source.
+    /// This finds all target states reachable in one activity from the source,
+    /// including the targets of conditional routes.
     /// Different from `incoming_states` - this looks forward instead of backward.
     pub fn outgoing_states(&self, from_state: &StateId) -> Vec<&StateId> {
         self.activities
@@ -288,10 +765,20 @@ impl WorkflowDefinition {
                 // Keep activities that...
                 a.from_states.contains(from_state) // ...can execute from source state
             })
-            .map(|a| &a.to_state) // Extract target states
+            .flat_map(|a| a.target_states()) // Extract target states, routes included
             .collect() // Collect into vector
     }
 
+    /// Whether no activity leads out of the given state
+    ///
+    /// Resources in a terminal state are finished: nothing can move them any more.
+    pub fn is_terminal_state(&self, state: &StateId) -> bool {
+        !self
+            .activities
+            .iter()
+            .any(|a| a.from_states.contains(state))
+    }
+
     /// Check if the workflow has any unreachable states
     ///
     /// This implements a graph traversal algorithm to find states that can
@@ -346,6 +833,289 @@ impl WorkflowDefinition {
     }
 }
 
+/// A workflow definition built by [`WorkflowBuilder`], with the agents and function
+/// triggers attached to its states
+#[derive(Debug, Clone)]
+pub struct WorkflowBlueprint {
+    pub definition: WorkflowDefinition,
+    /// Agents run on the workflow's resources in the states they monitor
+    pub state_agents: Vec<StateAgentConfig>,
+    /// Triggers to add to functions, limited to the workflow's resources
+    pub function_triggers: Vec<(FunctionId, EventTrigger)>,
+}
+
+/// Fluent builder for workflow definitions
+///
+/// States are declared with [`state`](Self::state) or [`states`](Self::states), and
+/// everything else refers to them by name. [`build`](Self::build) fails naming the
+/// first activity, capacity, SLA, agent or function trigger referring to a state or
+/// activity that was never declared, then runs [`WorkflowDefinition::validate`].
+///
+/// ```rust
+/// use circuit_breaker::models::{EventTrigger, Rule, StateId, WorkflowDefinition};
+///
+/// let blueprint = WorkflowDefinition::builder("review", "Document Review")
+///     .states(["draft", "review", "approved"])
+///     .initial_state("draft")
+///     .activity("submit", ["draft"], "review")
+///     .activity_with("approve", ["review"], "approved", |activity| {
+///         activity.with_compensation("submit")
+///     })
+///     .rule("approve", Rule::field_exists("reviewer", "reviewer"))
+///     .agent("review", "reviewer", |agent| agent)
+///     .function(
+///         "notify",
+///         EventTrigger::on_token_created("on_draft", Some(StateId::from("draft"))),
+///     )
+///     .build()
+///     .unwrap();
+/// assert_eq!(blueprint.definition.activities.len(), 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct WorkflowBuilder {
+    definition: WorkflowDefinition,
+    initial_state: Option<StateId>,
+    state_agents: Vec<StateAgentConfig>,
+    function_triggers: Vec<(FunctionId, EventTrigger)>,
+    /// Problems found while building, reported by `build`
+    errors: Vec<String>,
+}
+
+impl WorkflowBuilder {
+    /// Start an empty definition with this ID and name
+    pub fn new<S: Into<String>, N: Into<String>>(id: S, name: N) -> Self {
+        WorkflowBuilder {
+            definition: WorkflowDefinition::new(id, name, vec![], vec![], ""),
+            initial_state: None,
+            state_agents: Vec::new(),
+            function_triggers: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Declare a state
+    pub fn state<S: Into<StateId>>(mut self, state: S) -> Self {
+        let state = state.into();
+        if self.definition.states.contains(&state) {
+            self.errors
+                .push(format!("State '{}' is declared twice", state.as_str()));
+        } else {
+            self.definition.states.push(state);
+        }
+        self
+    }
+
+    /// Declare several states
+    pub fn states<I, S>(self, states: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<StateId>,
+    {
+        states
+            .into_iter()
+            .fold(self, |builder, state| builder.state(state))
+    }
+
+    /// Set the state new resources start in
+    pub fn initial_state<S: Into<StateId>>(mut self, state: S) -> Self {
+        self.initial_state = Some(state.into());
+        self
+    }
+
+    /// Add an activity moving resources from any of `from_states` to `to_state`
+    pub fn activity<I, F, T>(self, id: I, from_states: F, to_state: T) -> Self
+    where
+        I: Into<ActivityId>,
+        F: IntoIterator,
+        F::Item: Into<StateId>,
+        T: Into<StateId>,
+    {
+        self.activity_with(id, from_states, to_state, |activity| activity)
+    }
+
+    /// Add an activity, configured with the [`ActivityDefinition`] builder methods
+    /// (timers, routes, splits, retries, ...)
+    pub fn activity_with<I, F, T, C>(self, id: I, from_states: F, to_state: T, configure: C) -> Self
+    where
+        I: Into<ActivityId>,
+        F: IntoIterator,
+        F::Item: Into<StateId>,
+        T: Into<StateId>,
+        C: FnOnce(ActivityDefinition) -> ActivityDefinition,
+    {
+        let from_states: Vec<StateId> = from_states.into_iter().map(Into::into).collect();
+        self.add_activity(configure(ActivityDefinition::new(
+            id,
+            from_states,
+            to_state,
+        )))
+    }
+
+    /// Add an activity defined elsewhere
+    pub fn add_activity(mut self, activity: ActivityDefinition) -> Self {
+        if self
+            .definition
+            .activities
+            .iter()
+            .any(|a| a.id == activity.id)
+        {
+            self.errors.push(format!(
+                "Activity '{}' is declared twice",
+                activity.id.as_str()
+            ));
+        } else {
+            self.definition.activities.push(activity);
+        }
+        self
+    }
+
+    /// Add a rule to a declared activity
+    pub fn rule<A: Into<ActivityId>>(mut self, activity: A, rule: Rule) -> Self {
+        let activity = activity.into();
+        match self
+            .definition
+            .activities
+            .iter_mut()
+            .find(|a| a.id == activity)
+        {
+            Some(definition) => definition.add_rule(rule),
+            None => self.errors.push(format!(
+                "Rule '{}' is added to undeclared activity '{}'",
+                rule.id,
+                activity.as_str()
+            )),
+        }
+        self
+    }
+
+    /// Limit how many resources a state may hold at once
+    pub fn capacity<S: Into<StateId>>(mut self, state: S, capacity: StateCapacity) -> Self {
+        self.definition = self.definition.with_state_capacity(state, capacity);
+        self
+    }
+
+    /// Set the service level of a state
+    pub fn sla<S: Into<StateId>>(mut self, state: S, sla: StateSla) -> Self {
+        self.definition = self.definition.with_state_sla(state, sla);
+        self
+    }
+
+    /// Require resource metadata to conform to a JSON Schema
+    pub fn metadata_schema(mut self, schema: MetadataSchema) -> Self {
+        self.definition = self.definition.with_metadata_schema(schema);
+        self
+    }
+
+    /// Declare metadata fields clients encrypt before sending them
+    pub fn confidential_metadata<S: Into<String>>(
+        mut self,
+        fields: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.definition = self.definition.with_confidential_metadata(fields);
+        self
+    }
+
+    /// Run an agent on the workflow's resources in `state`, configured with the
+    /// [`StateAgentConfig`] fields
+    pub fn agent<S, A, C>(mut self, state: S, agent: A, configure: C) -> Self
+    where
+        S: Into<StateId>,
+        A: Into<AgentId>,
+        C: FnOnce(StateAgentConfig) -> StateAgentConfig,
+    {
+        let config = StateAgentConfig::new(state.into(), agent.into())
+            .for_workflow(self.definition.id.clone());
+        self.state_agents.push(configure(config));
+        self
+    }
+
+    /// Trigger a function on the workflow's resources
+    pub fn function<F: Into<FunctionId>>(mut self, function: F, trigger: EventTrigger) -> Self {
+        let trigger = trigger.for_workflow(self.definition.id.clone());
+        self.function_triggers.push((function.into(), trigger));
+        self
+    }
+
+    /// Check every reference and assemble the definition and its attachments
+    pub fn build(self) -> Result<WorkflowBlueprint, String> {
+        if let Some(error) = self.errors.into_iter().next() {
+            return Err(error);
+        }
+        let mut definition = self.definition;
+        definition.initial_state = self
+            .initial_state
+            .ok_or_else(|| format!("Workflow '{}' has no initial state", definition.id))?;
+
+        let undeclared = |state: &StateId| !definition.states.contains(state);
+        for agent in &self.state_agents {
+            if undeclared(&agent.state_id) {
+                return Err(format!(
+                    "Agent '{}' monitors undeclared state '{}'",
+                    agent.agent_id.as_str(),
+                    agent.state_id.as_str()
+                ));
+            }
+            if let Some(activity) = &agent.auto_activity {
+                if !definition.activities.iter().any(|a| &a.id == activity) {
+                    return Err(format!(
+                        "Agent '{}' fires undeclared activity '{}'",
+                        agent.agent_id.as_str(),
+                        activity.as_str()
+                    ));
+                }
+            }
+        }
+        for (function, trigger) in &self.function_triggers {
+            let (states, activity) = trigger_references(&trigger.event_type);
+            if let Some(state) = states.into_iter().find(|state| undeclared(state)) {
+                return Err(format!(
+                    "Function '{}' is triggered in undeclared state '{}'",
+                    function,
+                    state.as_str()
+                ));
+            }
+            if let Some(activity) = activity {
+                if !definition.activities.iter().any(|a| &a.id == activity) {
+                    return Err(format!(
+                        "Function '{}' is triggered by undeclared activity '{}'",
+                        function,
+                        activity.as_str()
+                    ));
+                }
+            }
+        }
+
+        definition.validate()?;
+        Ok(WorkflowBlueprint {
+            definition,
+            state_agents: self.state_agents,
+            function_triggers: self.function_triggers,
+        })
+    }
+}
+
+/// States and activity a function trigger's event names
+fn trigger_references(event_type: &EventType) -> (Vec<&StateId>, Option<&ActivityId>) {
+    match event_type {
+        EventType::TokenCreated { place }
+        | EventType::TokenUpdated { place }
+        | EventType::TokenCompleted { place }
+        | EventType::SlaBreached { place }
+        | EventType::TokenPaused { place }
+        | EventType::TokenResumed { place }
+        | EventType::TokenCancelled { place }
+        | EventType::SignalReceived { place, .. } => (place.iter().collect(), None),
+        EventType::TokenTransitioned {
+            from,
+            to,
+            transition,
+        } => (from.iter().chain(to.iter()).collect(), transition.as_ref()),
+        EventType::WorkflowCreated
+        | EventType::FunctionCompleted { .. }
+        | EventType::Custom { .. } => (Vec::new(), None),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -454,4 +1224,285 @@ mod tests {
         assert_eq!(unreachable.len(), 1);
         assert_eq!(unreachable[0], &StateId::from("orphan"));
     }
+
+    #[test]
+    fn test_route_validation() {
+        let mut workflow = WorkflowDefinition::new(
+            "orders",
+            "Orders",
+            vec![
+                StateId::from("submitted"),
+                StateId::from("approved"),
+                StateId::from("manager_review"),
+            ],
+            vec![
+                ActivityDefinition::new("review", vec!["submitted"], "approved")
+                    .with_route("amount > 1000", "manager_review"),
+            ],
+            "submitted",
+        );
+        assert!(workflow.validate().is_ok());
+        assert!(workflow.find_unreachable_states().is_empty());
+        assert_eq!(
+            workflow.incoming_states(&StateId::from("manager_review")),
+            vec![&StateId::from("submitted")]
+        );
+
+        workflow.activities[0].routes[0].when = "amount >".to_string();
+        assert!(workflow
+            .validate()
+            .unwrap_err()
+            .contains("invalid expression"));
+
+        workflow.activities[0].routes[0].when = "amount > 1000".to_string();
+        workflow.activities[0].routes[0].to_state = StateId::from("board_review");
+        assert!(workflow
+            .validate()
+            .unwrap_err()
+            .contains("routes to invalid state"));
+    }
+
+    #[test]
+    fn test_state_capacity_validation() {
+        let workflow = WorkflowDefinition::new(
+            "release",
+            "Release",
+            vec![StateId::from("ready"), StateId::from("deploying")],
+            vec![ActivityDefinition::new(
+                "deploy",
+                vec!["ready"],
+                "deploying",
+            )],
+            "ready",
+        );
+
+        // Capacities round-trip through JSON keyed by state
+        let limited = workflow
+            .clone()
+            .with_state_capacity("deploying", StateCapacity::new(5).by_priority("priority"));
+        assert!(limited.validate().is_ok());
+        let json = serde_json::to_value(&limited).unwrap();
+        assert_eq!(
+            json["state_capacities"]["deploying"]["queue_order"],
+            "priority"
+        );
+        let parsed: WorkflowDefinition = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.state_capacities, limited.state_capacities);
+
+        let mut missing_field = StateCapacity::new(5);
+        missing_field.queue_order = CapacityQueueOrder::Priority;
+        for invalid in [
+            workflow
+                .clone()
+                .with_state_capacity("unknown", StateCapacity::new(5)),
+            workflow
+                .clone()
+                .with_state_capacity("deploying", StateCapacity::new(0)),
+            workflow.with_state_capacity("deploying", missing_field),
+        ] {
+            assert!(invalid.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn test_metadata_schema() {
+        let workflow = WorkflowDefinition::new(
+            "ticket",
+            "Ticket",
+            vec![StateId::from("open")],
+            vec![],
+            "open",
+        );
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "priority": {"type": "string", "enum": ["low", "high"]},
+                "customer_id": {"type": "string"}
+            },
+            "required": ["customer_id"]
+        });
+
+        // The schema round-trips through JSON, strict unless stated otherwise
+        let schematized = workflow
+            .clone()
+            .with_metadata_schema(MetadataSchema::strict(schema.clone()));
+        assert!(schematized.validate().is_ok());
+        let json = serde_json::to_value(&schematized).unwrap();
+        assert_eq!(json["metadata_schema"]["enforcement"], "strict");
+        let parsed: WorkflowDefinition = serde_json::from_value(serde_json::json!({
+            "id": "ticket",
+            "name": "Ticket",
+            "states": ["open"],
+            "activities": [],
+            "initial_state": "open",
+            "metadata_schema": {"schema": schema}
+        }))
+        .unwrap();
+        assert_eq!(parsed.metadata_schema, schematized.metadata_schema);
+
+        let metadata_schema = schematized.metadata_schema.unwrap();
+        let mut metadata = ResourceMetadata::new();
+        metadata.insert("customer_id".to_string(), serde_json::json!("c-1"));
+        assert!(metadata_schema.violations(&metadata).is_empty());
+        metadata.insert("priority".to_string(), serde_json::json!("urgent"));
+        metadata.remove("customer_id");
+        assert_eq!(metadata_schema.violations(&metadata).len(), 2);
+
+        for invalid in [
+            serde_json::json!(true),
+            serde_json::json!({"type": "array"}),
+        ] {
+            let workflow = workflow
+                .clone()
+                .with_metadata_schema(MetadataSchema::warn(invalid));
+            assert!(workflow.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn test_fork() {
+        let origin = WorkflowDefinition::new(
+            "release",
+            "Release",
+            vec![StateId::from("ready"), StateId::from("deploying")],
+            vec![ActivityDefinition::new(
+                "deploy",
+                vec!["ready"],
+                "deploying",
+            )],
+            "ready",
+        )
+        .with_state_capacity("deploying", StateCapacity::new(2));
+
+        let mut fork = origin.fork("release-canary", "Release (canary)");
+        assert_eq!(fork.id, "release-canary");
+        assert_eq!(fork.forked_from.as_ref().unwrap().workflow_id, "release");
+        assert_eq!(fork.activities.len(), 1);
+        assert_eq!(fork.state_capacities, origin.state_capacities);
+
+        // The fork changes independently of its origin
+        fork.activities[0].to_state = StateId::from("ready");
+        assert_eq!(origin.activities[0].to_state, StateId::from("deploying"));
+        assert!(origin.forked_from.is_none());
+
+        let json = serde_json::to_value(&fork).unwrap();
+        let parsed: WorkflowDefinition = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.forked_from, fork.forked_from);
+    }
+
+    #[test]
+    fn test_confidential_metadata() {
+        let workflow = WorkflowDefinition::new(
+            "intake",
+            "Intake",
+            vec![StateId::from("new"), StateId::from("triaged")],
+            vec![ActivityDefinition::new("triage", vec!["new"], "triaged")],
+            "new",
+        )
+        .with_confidential_metadata(["patient"]);
+        assert!(workflow.validate().is_ok());
+        assert!(workflow.is_confidential("patient.ssn"));
+        assert!(!workflow.is_confidential("patient_count"));
+
+        // Rules may only read non-encrypted fields
+        assert!(workflow
+            .check_rule_fields(&Rule::field_exists("has_tier", "tier"))
+            .is_ok());
+        let error = workflow
+            .check_rule_fields(&Rule::field_exists("has_ssn", "patient.ssn"))
+            .unwrap_err();
+        assert!(error.contains("patient.ssn"));
+        let mut reads_patient = workflow.clone();
+        reads_patient.activities[0]
+            .rules
+            .push(Rule::field_exists("has_patient", "patient"));
+        assert!(reads_patient.validate().is_err());
+
+        // Confidential fields must hold envelopes
+        let mut metadata = ResourceMetadata::new();
+        metadata.insert("patient".to_string(), serde_json::json!({"ssn": "123"}));
+        assert_eq!(workflow.confidential_violations(&metadata).len(), 1);
+        metadata.insert(
+            "patient".to_string(),
+            serde_json::json!({"$encrypted": {"alg": "A256GCM", "nonce": "bg==", "ciphertext": "Yw=="}}),
+        );
+        assert!(workflow.confidential_violations(&metadata).is_empty());
+    }
+
+    #[test]
+    fn test_workflow_builder() {
+        let blueprint = WorkflowDefinition::builder("orders", "Order Processing")
+            .states(["placed", "paid", "shipped", "refunded"])
+            .initial_state("placed")
+            .activity("pay", ["placed"], "paid")
+            .activity_with("ship", ["paid"], "shipped", |activity| {
+                activity.with_route("express == true", "shipped")
+            })
+            .activity("refund", ["paid"], "refunded")
+            .rule("ship", Rule::field_exists("has_address", "address"))
+            .sla("paid", StateSla::new(3600).escalate_with("refund"))
+            .agent("paid", "fraud_checker", |agent| agent)
+            .function(
+                "send_receipt",
+                EventTrigger::on_token_transitioned(
+                    "receipt",
+                    None,
+                    Some(StateId::from("paid")),
+                    Some(ActivityId::from("pay")),
+                ),
+            )
+            .build()
+            .unwrap();
+        let definition = &blueprint.definition;
+        assert_eq!(definition.initial_state, StateId::from("placed"));
+        assert_eq!(definition.activities.len(), 3);
+        assert_eq!(definition.activities[1].rules.len(), 1);
+        assert!(definition.state_sla(&StateId::from("paid")).is_some());
+        assert_eq!(
+            blueprint.state_agents[0].workflow_id.as_deref(),
+            Some("orders")
+        );
+        assert_eq!(
+            blueprint.function_triggers[0].1.workflow_id.as_deref(),
+            Some("orders")
+        );
+
+        // Undeclared states and activities fail the build
+        let builder = WorkflowDefinition::builder("orders", "Order Processing")
+            .states(["placed", "paid"])
+            .initial_state("placed")
+            .activity("pay", ["placed"], "paid");
+        let error = builder
+            .clone()
+            .activity("ship", ["paid"], "shipped")
+            .build()
+            .unwrap_err();
+        assert!(error.contains("shipped"));
+        let error = builder
+            .clone()
+            .rule("ship", Rule::field_exists("has_address", "address"))
+            .build()
+            .unwrap_err();
+        assert!(error.contains("undeclared activity 'ship'"));
+        let error = builder
+            .clone()
+            .agent("review", "fraud_checker", |agent| agent)
+            .build()
+            .unwrap_err();
+        assert!(error.contains("undeclared state 'review'"));
+        let error = builder
+            .clone()
+            .function(
+                "send_receipt",
+                EventTrigger::on_token_created("receipt", Some(StateId::from("cart"))),
+            )
+            .build()
+            .unwrap_err();
+        assert!(error.contains("undeclared state 'cart'"));
+        assert!(builder.clone().state("paid").build().is_err());
+        assert!(WorkflowDefinition::builder("orders", "Order Processing")
+            .state("placed")
+            .build()
+            .is_err());
+    }
 }
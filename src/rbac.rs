@@ -0,0 +1,967 @@
+//! Role-Based Access Control
+//!
+//! Operators assign each principal one [`Role`]: `admin`, `operator`, `viewer` or
+//! `agent`. A role grants read or write [`Access`] over the [`Domain`]s of the server -
+//! workflows, resources, agents, budgets and MCP instances:
+//!
+//! | Role       | Workflows | Resources | Agents | Budgets | MCP instances |
+//! |------------|-----------|-----------|--------|---------|---------------|
+//! | `admin`    | write     | write     | write  | write   | write         |
+//! | `operator` | write     | write     | write  | read    | write         |
+//! | `viewer`   | read      | read      | read   | read    | read          |
+//! | `agent`    | read      | write     | write  | -       | write         |
+//!
+//! Write access includes read access. Principals are [`Subject`]s: managed API keys,
+//! service accounts and MCP installations. The GraphQL server checks the top-level
+//! fields of each operation with [`graphql_permission`], the REST API each route with
+//! [`rest_permission`] and the MCP server its management routes with
+//! [`mcp_permission`] and its JSON-RPC methods with [`mcp_method_permission`].
+//! Principals without a role keep what their credentials allow, so assigning roles is
+//! opt-in per principal.
+//!
+//! Assignments are managed through `/v1/admin/roles` and kept in a [`RoleStore`]: in
+//! memory by default, or in NATS or Postgres next to the API keys, so a restart or
+//! another replica never loses a principal's role and lets it through unrestricted.
+//! Every check reads the store; when it cannot be reached, requests from principals are
+//! refused rather than let through.
+
+use async_graphql::parser::types::OperationType;
+use async_nats::jetstream::{self, kv, stream};
+use axum::http::Method;
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+lazy_static::lazy_static! {
+    static ref GLOBAL: RoleAssignments = RoleAssignments::new();
+}
+
+/// Key-value bucket holding role assignments
+pub const ROLE_ASSIGNMENTS_BUCKET: &str = "CB_ROLE_ASSIGNMENTS";
+
+/// A set of permissions assigned to principals
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Everything
+    Admin,
+    /// Runs workflows, agents and MCP instances; reads budgets
+    Operator,
+    /// Reads everything, changes nothing
+    Viewer,
+    /// Automation that works resources through agents and MCP tools
+    Agent,
+}
+
+/// What a permission is over
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Domain {
+    /// Workflow definitions, forks, rules and feature flags
+    Workflows,
+    /// Resources and the activities executed on them
+    Resources,
+    /// Agents, their executions, and the LLM providers they call
+    Agents,
+    /// Budgets, prices and cost analytics
+    Budgets,
+    /// MCP server instances, their tools and their OAuth apps
+    McpInstances,
+}
+
+/// Whether a permission reads or changes its domain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// Access to a domain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Permission {
+    pub domain: Domain,
+    pub access: Access,
+}
+
+impl Permission {
+    pub fn read(domain: Domain) -> Self {
+        Self {
+            domain,
+            access: Access::Read,
+        }
+    }
+
+    pub fn write(domain: Domain) -> Self {
+        Self {
+            domain,
+            access: Access::Write,
+        }
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let access = match self.access {
+            Access::Read => "read",
+            Access::Write => "write",
+        };
+        write!(f, "{} {}", access, self.domain.name())
+    }
+}
+
+impl Domain {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Domain::Workflows => "workflows",
+            Domain::Resources => "resources",
+            Domain::Agents => "agents",
+            Domain::Budgets => "budgets",
+            Domain::McpInstances => "MCP instances",
+        }
+    }
+}
+
+impl Role {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+            Role::Operator => "operator",
+            Role::Viewer => "viewer",
+            Role::Agent => "agent",
+        }
+    }
+
+    /// Most access the role has to a domain; `None` when it has none
+    pub fn access(&self, domain: Domain) -> Option<Access> {
+        match (self, domain) {
+            (Role::Admin, _) => Some(Access::Write),
+            (Role::Operator, Domain::Budgets) => Some(Access::Read),
+            (Role::Operator, _) => Some(Access::Write),
+            (Role::Viewer, _) => Some(Access::Read),
+            (Role::Agent, Domain::Workflows) => Some(Access::Read),
+            (Role::Agent, Domain::Budgets) => None,
+            (Role::Agent, _) => Some(Access::Write),
+        }
+    }
+
+    /// Whether the role grants a permission; write access includes read access
+    pub fn allows(&self, permission: Permission) -> bool {
+        self.access(permission.domain)
+            .is_some_and(|access| access >= permission.access)
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "admin" => Ok(Role::Admin),
+            "operator" => Ok(Role::Operator),
+            "viewer" => Ok(Role::Viewer),
+            "agent" => Ok(Role::Agent),
+            _ => Err(format!(
+                "Unknown role '{}'; expected admin, operator, viewer or agent",
+                s
+            )),
+        }
+    }
+}
+
+/// A principal roles are assigned to, written `<kind>:<id>`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Subject {
+    /// A managed API key, by key ID: `api_key:<id>`
+    ApiKey(String),
+    /// A service account, by account ID: `service_account:<id>`
+    ServiceAccount(String),
+    /// An MCP installation, by installation ID: `mcp_installation:<id>`
+    McpInstallation(String),
+}
+
+impl fmt::Display for Subject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Subject::ApiKey(id) => write!(f, "api_key:{}", id),
+            Subject::ServiceAccount(id) => write!(f, "service_account:{}", id),
+            Subject::McpInstallation(id) => write!(f, "mcp_installation:{}", id),
+        }
+    }
+}
+
+impl FromStr for Subject {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, id) = s
+            .split_once(':')
+            .filter(|(_, id)| !id.trim().is_empty())
+            .ok_or_else(|| format!("Subject '{}' must be written <kind>:<id>", s))?;
+        match kind {
+            "api_key" => Ok(Subject::ApiKey(id.to_string())),
+            "service_account" => Ok(Subject::ServiceAccount(id.to_string())),
+            "mcp_installation" => Ok(Subject::McpInstallation(id.to_string())),
+            _ => Err(format!(
+                "Unknown subject kind '{}'; expected api_key, service_account or mcp_installation",
+                kind
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for Subject {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Subject> for String {
+    fn from(subject: Subject) -> Self {
+        subject.to_string()
+    }
+}
+
+/// A role assigned to a principal
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoleAssignment {
+    pub subject: Subject,
+    pub role: Role,
+    pub assigned_at: DateTime<Utc>,
+}
+
+/// Why a principal's request was refused
+#[derive(Debug, Clone, PartialEq)]
+pub enum RoleDenied {
+    /// The principal's role does not grant what the request needs
+    Forbidden {
+        subject: Subject,
+        role: Role,
+        /// `None` when the request is outside every domain, which only admins may reach
+        permission: Option<Permission>,
+    },
+    /// The principal's role could not be looked up
+    Unavailable { subject: Subject, error: String },
+}
+
+impl RoleDenied {
+    pub fn error_code(&self) -> crate::ErrorCode {
+        match self {
+            RoleDenied::Forbidden { .. } => crate::ErrorCode::PermissionDenied,
+            RoleDenied::Unavailable { .. } => crate::ErrorCode::StorageError,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            RoleDenied::Forbidden {
+                subject,
+                role,
+                permission: Some(permission),
+            } => format!(
+                "Role '{}' of {} does not allow {}",
+                role.name(),
+                subject,
+                permission
+            ),
+            RoleDenied::Forbidden {
+                subject,
+                role,
+                permission: None,
+            } => format!(
+                "Role '{}' of {} does not allow this operation",
+                role.name(),
+                subject
+            ),
+            RoleDenied::Unavailable { subject, error } => {
+                format!("Could not check the role of {}: {}", subject, error)
+            }
+        }
+    }
+}
+
+/// Role store errors
+#[derive(Debug, thiserror::Error)]
+pub enum RoleStoreError {
+    #[error("Role store error: {0}")]
+    Store(String),
+}
+
+fn store_error(action: &str, error: impl std::fmt::Display) -> RoleStoreError {
+    RoleStoreError::Store(format!("Failed to {}: {}", action, error))
+}
+
+/// Persistence for role assignments
+#[async_trait::async_trait]
+pub trait RoleStore: Send + Sync {
+    /// Insert or replace a principal's assignment
+    async fn put(&self, assignment: &RoleAssignment) -> Result<(), RoleStoreError>;
+
+    async fn get(&self, subject: &Subject) -> Result<Option<RoleAssignment>, RoleStoreError>;
+
+    /// Remove a principal's assignment, returning it
+    async fn delete(&self, subject: &Subject) -> Result<Option<RoleAssignment>, RoleStoreError>;
+
+    async fn list(&self) -> Result<Vec<RoleAssignment>, RoleStoreError>;
+}
+
+/// Role store that forgets every assignment on restart
+#[derive(Debug, Default)]
+pub struct InMemoryRoleStore {
+    assignments: RwLock<HashMap<Subject, RoleAssignment>>,
+}
+
+#[async_trait::async_trait]
+impl RoleStore for InMemoryRoleStore {
+    async fn put(&self, assignment: &RoleAssignment) -> Result<(), RoleStoreError> {
+        self.assignments
+            .write()
+            .unwrap()
+            .insert(assignment.subject.clone(), assignment.clone());
+        Ok(())
+    }
+
+    async fn get(&self, subject: &Subject) -> Result<Option<RoleAssignment>, RoleStoreError> {
+        Ok(self.assignments.read().unwrap().get(subject).cloned())
+    }
+
+    async fn delete(&self, subject: &Subject) -> Result<Option<RoleAssignment>, RoleStoreError> {
+        Ok(self.assignments.write().unwrap().remove(subject))
+    }
+
+    async fn list(&self) -> Result<Vec<RoleAssignment>, RoleStoreError> {
+        Ok(self.assignments.read().unwrap().values().cloned().collect())
+    }
+}
+
+/// Role store backed by a NATS key-value bucket, shared by every replica
+///
+/// Subjects may hold characters keys cannot, so each assignment is stored under the
+/// SHA-256 digest of its subject.
+pub struct NATSRoleStore {
+    bucket: kv::Store,
+}
+
+impl NATSRoleStore {
+    /// Connect to NATS and create the role assignment bucket if needed
+    pub async fn connect(nats_url: &str) -> Result<Self, RoleStoreError> {
+        let client = async_nats::connect(nats_url)
+            .await
+            .map_err(|e| store_error("connect to NATS", e))?;
+        Self::new(jetstream::new(client)).await
+    }
+
+    /// Store assignments through an existing JetStream context
+    pub async fn new(jetstream: jetstream::Context) -> Result<Self, RoleStoreError> {
+        let bucket = match jetstream.get_key_value(ROLE_ASSIGNMENTS_BUCKET).await {
+            Ok(bucket) => bucket,
+            Err(_) => jetstream
+                .create_key_value(kv::Config {
+                    bucket: ROLE_ASSIGNMENTS_BUCKET.to_string(),
+                    description: "Roles assigned to API keys, service accounts and MCP \
+                                  installations"
+                        .to_string(),
+                    history: 1,
+                    storage: stream::StorageType::File,
+                    ..Default::default()
+                })
+                .await
+                .map_err(|e| store_error("create role assignment bucket", e))?,
+        };
+        Ok(Self { bucket })
+    }
+
+    fn entry(subject: &Subject) -> String {
+        Sha256::digest(subject.to_string().as_bytes())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    async fn read(&self, entry: &str) -> Result<Option<RoleAssignment>, RoleStoreError> {
+        match self
+            .bucket
+            .get(entry)
+            .await
+            .map_err(|e| store_error("read role assignment", e))?
+        {
+            Some(value) => serde_json::from_slice(&value)
+                .map(Some)
+                .map_err(|e| store_error("decode role assignment", e)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl RoleStore for NATSRoleStore {
+    async fn put(&self, assignment: &RoleAssignment) -> Result<(), RoleStoreError> {
+        let value =
+            serde_json::to_vec(assignment).map_err(|e| store_error("encode role assignment", e))?;
+        self.bucket
+            .put(Self::entry(&assignment.subject), value.into())
+            .await
+            .map_err(|e| store_error("store role assignment", e))?;
+        Ok(())
+    }
+
+    async fn get(&self, subject: &Subject) -> Result<Option<RoleAssignment>, RoleStoreError> {
+        self.read(&Self::entry(subject)).await
+    }
+
+    async fn delete(&self, subject: &Subject) -> Result<Option<RoleAssignment>, RoleStoreError> {
+        let entry = Self::entry(subject);
+        let Some(assignment) = self.read(&entry).await? else {
+            return Ok(None);
+        };
+        self.bucket
+            .delete(entry)
+            .await
+            .map_err(|e| store_error("remove role assignment", e))?;
+        Ok(Some(assignment))
+    }
+
+    async fn list(&self) -> Result<Vec<RoleAssignment>, RoleStoreError> {
+        let mut entries = self
+            .bucket
+            .keys()
+            .await
+            .map_err(|e| store_error("list role assignments", e))?;
+        let mut assignments = Vec::new();
+        while let Some(entry) = entries.next().await {
+            let entry = entry.map_err(|e| store_error("list role assignments", e))?;
+            if let Some(assignment) = self.read(&entry).await? {
+                assignments.push(assignment);
+            }
+        }
+        Ok(assignments)
+    }
+}
+
+/// Role store backed by the `role_assignments` Postgres table
+pub struct PostgresRoleStore {
+    pool: sqlx::PgPool,
+}
+
+impl PostgresRoleStore {
+    /// Connect to Postgres and migrate its schema
+    pub async fn connect(database_url: &str) -> Result<Self, RoleStoreError> {
+        let pool = crate::engine::postgres_storage::connect(database_url)
+            .await
+            .map_err(|e| store_error("connect to Postgres", e))?;
+        Ok(Self::new(pool))
+    }
+
+    /// Store assignments through an existing, migrated connection pool
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+type AssignmentRow = (String, String, DateTime<Utc>);
+
+fn assignment_from_row(
+    (subject, role, assigned_at): AssignmentRow,
+) -> Result<RoleAssignment, RoleStoreError> {
+    Ok(RoleAssignment {
+        subject: subject
+            .parse()
+            .map_err(|e| store_error("decode role assignment", e))?,
+        role: role
+            .parse()
+            .map_err(|e| store_error("decode role assignment", e))?,
+        assigned_at,
+    })
+}
+
+#[async_trait::async_trait]
+impl RoleStore for PostgresRoleStore {
+    async fn put(&self, assignment: &RoleAssignment) -> Result<(), RoleStoreError> {
+        sqlx::query(
+            "INSERT INTO role_assignments (subject, role, assigned_at) VALUES ($1, $2, $3)
+             ON CONFLICT (subject) DO UPDATE
+             SET role = EXCLUDED.role, assigned_at = EXCLUDED.assigned_at",
+        )
+        .bind(assignment.subject.to_string())
+        .bind(assignment.role.name())
+        .bind(assignment.assigned_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| store_error("store role assignment", e))?;
+        Ok(())
+    }
+
+    async fn get(&self, subject: &Subject) -> Result<Option<RoleAssignment>, RoleStoreError> {
+        let row: Option<AssignmentRow> = sqlx::query_as(
+            "SELECT subject, role, assigned_at FROM role_assignments WHERE subject = $1",
+        )
+        .bind(subject.to_string())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| store_error("read role assignment", e))?;
+        row.map(assignment_from_row).transpose()
+    }
+
+    async fn delete(&self, subject: &Subject) -> Result<Option<RoleAssignment>, RoleStoreError> {
+        let row: Option<AssignmentRow> = sqlx::query_as(
+            "DELETE FROM role_assignments WHERE subject = $1
+             RETURNING subject, role, assigned_at",
+        )
+        .bind(subject.to_string())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| store_error("remove role assignment", e))?;
+        row.map(assignment_from_row).transpose()
+    }
+
+    async fn list(&self) -> Result<Vec<RoleAssignment>, RoleStoreError> {
+        let rows: Vec<AssignmentRow> =
+            sqlx::query_as("SELECT subject, role, assigned_at FROM role_assignments")
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| store_error("list role assignments", e))?;
+        rows.into_iter().map(assignment_from_row).collect()
+    }
+}
+
+/// Shared registry of role assignments
+///
+/// Clones read and update the same store. Servers use [`RoleAssignments::global`],
+/// whose store [`RoleAssignments::use_store`] replaces at startup.
+#[derive(Clone)]
+pub struct RoleAssignments {
+    store: Arc<RwLock<Arc<dyn RoleStore>>>,
+}
+
+impl std::fmt::Debug for RoleAssignments {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RoleAssignments").finish_non_exhaustive()
+    }
+}
+
+impl Default for RoleAssignments {
+    fn default() -> Self {
+        Self::with_store(Arc::new(InMemoryRoleStore::default()))
+    }
+}
+
+impl RoleAssignments {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_store(store: Arc<dyn RoleStore>) -> Self {
+        Self {
+            store: Arc::new(RwLock::new(store)),
+        }
+    }
+
+    /// The process-wide registry shared by the admin API and every API surface
+    pub fn global() -> Self {
+        GLOBAL.clone()
+    }
+
+    /// Keep assignments in `store` from now on, for this registry and its clones
+    pub fn use_store(&self, store: Arc<dyn RoleStore>) {
+        *self.store.write().unwrap() = store;
+    }
+
+    fn store(&self) -> Arc<dyn RoleStore> {
+        self.store.read().unwrap().clone()
+    }
+
+    /// Assign a role, replacing the principal's previous one
+    pub async fn assign(
+        &self,
+        subject: Subject,
+        role: Role,
+    ) -> Result<RoleAssignment, RoleStoreError> {
+        let assignment = RoleAssignment {
+            subject,
+            role,
+            assigned_at: Utc::now(),
+        };
+        self.store().put(&assignment).await?;
+        Ok(assignment)
+    }
+
+    /// Remove a principal's role, returning the assignment it had
+    pub async fn unassign(
+        &self,
+        subject: &Subject,
+    ) -> Result<Option<RoleAssignment>, RoleStoreError> {
+        self.store().delete(subject).await
+    }
+
+    pub async fn get(&self, subject: &Subject) -> Result<Option<RoleAssignment>, RoleStoreError> {
+        self.store().get(subject).await
+    }
+
+    /// All assignments, by subject
+    pub async fn list(&self) -> Result<Vec<RoleAssignment>, RoleStoreError> {
+        let mut assignments = self.store().list().await?;
+        assignments.sort_by(|a, b| a.subject.cmp(&b.subject));
+        Ok(assignments)
+    }
+
+    /// A principal's role; a store failure refuses the principal instead of leaving it
+    /// unrestricted
+    pub async fn role_of(&self, subject: &Subject) -> Result<Option<Role>, RoleDenied> {
+        match self.store().get(subject).await {
+            Ok(assignment) => Ok(assignment.map(|assignment| assignment.role)),
+            Err(e) => Err(RoleDenied::Unavailable {
+                subject: subject.clone(),
+                error: e.to_string(),
+            }),
+        }
+    }
+
+    /// Check a principal's role against what a request needs
+    ///
+    /// Principals without a role are not restricted. `None` stands for requests outside
+    /// every domain, which only admins may make.
+    pub async fn authorize(
+        &self,
+        subject: &Subject,
+        permission: Option<Permission>,
+    ) -> Result<(), RoleDenied> {
+        let Some(role) = self.role_of(subject).await? else {
+            return Ok(());
+        };
+        let allowed = match permission {
+            Some(permission) => role.allows(permission),
+            None => role == Role::Admin,
+        };
+        if allowed {
+            Ok(())
+        } else {
+            Err(RoleDenied::Forbidden {
+                subject: subject.clone(),
+                role,
+                permission,
+            })
+        }
+    }
+}
+
+fn access_of(method: &Method) -> Access {
+    if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) {
+        Access::Read
+    } else {
+        Access::Write
+    }
+}
+
+/// What a top-level GraphQL field needs; `None` for fields outside every domain, which only
+/// admins may reach
+///
+/// Queries and subscriptions read, mutations write.
+pub fn graphql_permission(ty: OperationType, field: &str) -> Option<Permission> {
+    let domain = match field {
+        "workflow"
+        | "workflows"
+        | "workflowForks"
+        | "evaluateTransitionsBulk"
+        | "stateCapacityQueue"
+        | "featureFlags"
+        | "rule"
+        | "rules"
+        | "workflowRules"
+        | "ruleMetrics"
+        | "rebuildStateCounters"
+        | "createWorkflow"
+        | "forkWorkflow"
+        | "setFeatureFlag"
+        | "removeFeatureFlag"
+        | "createRule"
+        | "updateRule"
+        | "deleteRule"
+        | "evaluateRule"
+        | "workflowEvents"
+        | "eventReplay"
+        | "workflowVersions"
+        | "workflowVersion"
+        | "workflowTemplates"
+        | "workflowMetrics"
+        | "workflowDiagram"
+        | "slaMetrics"
+        | "webhookTriggers"
+        | "createWorkflowFromTemplate"
+        | "publishWorkflowVersion"
+        | "migrateWorkflowResources"
+        | "registerWebhookTrigger"
+        | "removeWebhookTrigger" => Domain::Workflows,
+        "resource"
+        | "resources"
+        | "availableActivities"
+        | "resourceStateCounts"
+        | "executionStatusCounts"
+        | "natsResource"
+        | "resourcesInState"
+        | "findResource"
+        | "createResource"
+        | "updateResourceMetadata"
+        | "executeActivity"
+        | "executeActivityWithNats"
+        | "createWorkflowInstance"
+        | "resourceUpdates"
+        | "humanTasks"
+        | "executeActivityBatch"
+        | "createResourcesBatch"
+        | "pauseResource"
+        | "resumeResource"
+        | "cancelResource"
+        | "compensateResource"
+        | "sendSignal"
+        | "claimHumanTask"
+        | "approveHumanTask"
+        | "rejectHumanTask" => Domain::Resources,
+        "agent"
+        | "agents"
+        | "stateAgentConfigs"
+        | "agentExecution"
+        | "resourceExecutions"
+        | "agentStreamGauges"
+        | "llmProviders"
+        | "llmProvider"
+        | "routingTrace"
+        | "createAgent"
+        | "createStateAgentConfig"
+        | "triggerStateAgents"
+        | "llmChatCompletion"
+        | "configureLlmProvider"
+        | "agentExecutionStream"
+        | "llmStream" => Domain::Agents,
+        "budgetStatus" | "costAnalytics" | "modelPrices" | "setBudget" | "setModelPrice"
+        | "removeModelPrice" | "costUpdates" => Domain::Budgets,
+        _ => return None,
+    };
+    Some(match ty {
+        OperationType::Mutation => Permission::write(domain),
+        OperationType::Query | OperationType::Subscription => Permission::read(domain),
+    })
+}
+
+/// What a route of the OpenAI-compatible API needs
+///
+/// Usage, cost and budget reports are budgets; everything else calls models on behalf of
+/// agents. Reading routes and tokenizing read, other routes write. Health checks and
+/// admin endpoints, which require the admin token, return `None` and are not checked.
+pub fn rest_permission(method: &Method, path: &str) -> Option<Permission> {
+    if path == "/health"
+        || path == "/v1/health"
+        || path.starts_with("/admin/")
+        || path.starts_with("/v1/admin/")
+    {
+        return None;
+    }
+    let budgets = path == "/v1/usage/export"
+        || path.starts_with("/v1/analytics/")
+        || (path.starts_with("/v1/requests/") && path.ends_with("/cost"));
+    if budgets {
+        // Reconciliation posts a billing export to compare, changing nothing
+        return Some(if path == "/v1/analytics/reconciliation" {
+            Permission::read(Domain::Budgets)
+        } else {
+            Permission {
+                domain: Domain::Budgets,
+                access: access_of(method),
+            }
+        });
+    }
+    Some(if path == "/v1/tokenize" {
+        Permission::read(Domain::Agents)
+    } else {
+        Permission {
+            domain: Domain::Agents,
+            access: access_of(method),
+        }
+    })
+}
+
+/// What a route of the MCP server needs
+///
+/// Instance, app and installation management read or write MCP instances by method.
+/// Posts to the JSON-RPC endpoints return `None`: what they need depends on the method
+/// called, see [`mcp_method_permission`].
+pub fn mcp_permission(method: &Method, path: &str) -> Option<Permission> {
+    let json_rpc = path == "/"
+        || path.strip_prefix("/mcp/").is_some_and(|rest| {
+            (!rest.contains('/') && rest != "instances") || rest.ends_with("/stream")
+        });
+    if json_rpc && *method == Method::POST {
+        return None;
+    }
+    if path.ends_with("/stream") && *method == Method::DELETE {
+        // Ending a session changes nothing
+        return Some(Permission::read(Domain::McpInstances));
+    }
+    Some(Permission {
+        domain: Domain::McpInstances,
+        access: access_of(method),
+    })
+}
+
+/// What a JSON-RPC method of the MCP server needs
+///
+/// Calling tools, sampling and changing the log level write; listing and reading read.
+pub fn mcp_method_permission(method: &str) -> Permission {
+    match method {
+        "tools/call" | "sampling/createMessage" | "logging/setLevel" => {
+            Permission::write(Domain::McpInstances)
+        }
+        _ => Permission::read(Domain::McpInstances),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roles_grant_their_permissions() {
+        assert!(Role::Admin.allows(Permission::write(Domain::Budgets)));
+        assert!(Role::Operator.allows(Permission::write(Domain::Workflows)));
+        assert!(Role::Operator.allows(Permission::read(Domain::Budgets)));
+        assert!(!Role::Operator.allows(Permission::write(Domain::Budgets)));
+        assert!(Role::Viewer.allows(Permission::read(Domain::McpInstances)));
+        assert!(!Role::Viewer.allows(Permission::write(Domain::Resources)));
+        assert!(Role::Agent.allows(Permission::write(Domain::Resources)));
+        assert!(!Role::Agent.allows(Permission::write(Domain::Workflows)));
+        assert!(!Role::Agent.allows(Permission::read(Domain::Budgets)));
+
+        let subject: Subject = "api_key:key_123".parse().unwrap();
+        assert_eq!(subject, Subject::ApiKey("key_123".to_string()));
+        assert_eq!(subject.to_string(), "api_key:key_123");
+        assert!("api_key:".parse::<Subject>().is_err());
+        assert!("user:42".parse::<Subject>().is_err());
+        assert_eq!("operator".parse::<Role>(), Ok(Role::Operator));
+    }
+
+    #[tokio::test]
+    async fn test_assignments_restrict_only_assigned_subjects() {
+        let assignments = RoleAssignments::new();
+        let viewer = Subject::ServiceAccount("sa_ci".to_string());
+        let other = Subject::McpInstallation("inst_1".to_string());
+
+        assert!(assignments
+            .authorize(&viewer, Some(Permission::write(Domain::Workflows)))
+            .await
+            .is_ok());
+        assignments
+            .assign(viewer.clone(), Role::Viewer)
+            .await
+            .unwrap();
+        assert_eq!(assignments.role_of(&viewer).await, Ok(Some(Role::Viewer)));
+
+        let denied = assignments
+            .authorize(&viewer, Some(Permission::write(Domain::Workflows)))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            denied.message(),
+            "Role 'viewer' of service_account:sa_ci does not allow write workflows"
+        );
+        assert!(assignments
+            .authorize(&viewer, Some(Permission::read(Domain::Workflows)))
+            .await
+            .is_ok());
+        assert!(assignments.authorize(&viewer, None).await.is_err());
+        assert!(assignments.authorize(&other, None).await.is_ok());
+
+        assignments
+            .assign(viewer.clone(), Role::Admin)
+            .await
+            .unwrap();
+        assert!(assignments.authorize(&viewer, None).await.is_ok());
+        assert_eq!(assignments.list().await.unwrap().len(), 1);
+        assert!(assignments.unassign(&viewer).await.unwrap().is_some());
+        assert!(assignments.get(&viewer).await.unwrap().is_none());
+    }
+
+    struct UnreachableStore;
+
+    #[async_trait::async_trait]
+    impl RoleStore for UnreachableStore {
+        async fn put(&self, _assignment: &RoleAssignment) -> Result<(), RoleStoreError> {
+            Err(store_error("store role assignment", "unreachable"))
+        }
+
+        async fn get(&self, _subject: &Subject) -> Result<Option<RoleAssignment>, RoleStoreError> {
+            Err(store_error("read role assignment", "unreachable"))
+        }
+
+        async fn delete(
+            &self,
+            _subject: &Subject,
+        ) -> Result<Option<RoleAssignment>, RoleStoreError> {
+            Err(store_error("remove role assignment", "unreachable"))
+        }
+
+        async fn list(&self) -> Result<Vec<RoleAssignment>, RoleStoreError> {
+            Err(store_error("list role assignments", "unreachable"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_store_refuses_principals() {
+        let assignments = RoleAssignments::with_store(Arc::new(UnreachableStore));
+        let subject = Subject::ApiKey("key_123".to_string());
+        assert!(matches!(
+            assignments
+                .authorize(&subject, Some(Permission::read(Domain::Workflows)))
+                .await,
+            Err(RoleDenied::Unavailable { .. })
+        ));
+    }
+
+    #[test]
+    fn test_requests_map_to_permissions() {
+        assert_eq!(
+            graphql_permission(OperationType::Mutation, "createWorkflow"),
+            Some(Permission::write(Domain::Workflows))
+        );
+        assert_eq!(
+            graphql_permission(OperationType::Subscription, "costUpdates"),
+            Some(Permission::read(Domain::Budgets))
+        );
+        assert_eq!(graphql_permission(OperationType::Query, "unknown"), None);
+
+        assert_eq!(
+            rest_permission(&Method::POST, "/v1/chat/completions"),
+            Some(Permission::write(Domain::Agents))
+        );
+        assert_eq!(
+            rest_permission(&Method::GET, "/v1/analytics/chargeback"),
+            Some(Permission::read(Domain::Budgets))
+        );
+        assert_eq!(rest_permission(&Method::GET, "/v1/admin/roles"), None);
+
+        assert_eq!(mcp_permission(&Method::POST, "/mcp/inst_1"), None);
+        assert_eq!(mcp_permission(&Method::POST, "/mcp/inst_1/stream"), None);
+        assert_eq!(
+            mcp_permission(&Method::POST, "/mcp/instances"),
+            Some(Permission::write(Domain::McpInstances))
+        );
+        assert_eq!(
+            mcp_permission(&Method::GET, "/mcp/inst_1/tools"),
+            Some(Permission::read(Domain::McpInstances))
+        );
+        assert_eq!(
+            mcp_method_permission("tools/call"),
+            Permission::write(Domain::McpInstances)
+        );
+        assert_eq!(
+            mcp_method_permission("tools/list"),
+            Permission::read(Domain::McpInstances)
+        );
+    }
+}
@@ -4,30 +4,45 @@
 use async_graphql::Schema;
 use async_graphql_axum::{GraphQLRequest, GraphQLResponse, GraphQLSubscription};
 use axum::{
-    extract::State,
-    http::StatusCode,
-    response::{Html, IntoResponse},
+    body::Body,
+    extract::{Extension, State},
+    http::{header::AUTHORIZATION, HeaderMap, HeaderValue, Request, StatusCode},
+    middleware::Next,
+    response::{Html, IntoResponse, Response},
     routing::{get, post},
     Router, Server,
 };
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tower_http::cors::CorsLayer;
 use tracing::{debug, info};
 
+use crate::api_keys::{ApiKeyRejection, ApiKeyScope, ApiKeys};
+use crate::auth_throttle::{client_ip, record_peer_addr};
 use crate::engine::{
     agents::{AgentEngine, AgentEngineConfig, AgentStorage, InMemoryAgentStorage},
     graphql::{
+        api_key_rejection, authenticate_api_key, authenticate_service_token,
         create_schema_with_agents, create_schema_with_full_storage, create_schema_with_nats,
         create_schema_with_nats_and_agents, create_schema_with_storage, Mutation, Query,
         Subscription,
     },
     nats_storage::{NATSStorage, NATSStorageConfig, NATSStorageWrapper},
+    postgres_storage::{PostgresAgentStorage, PostgresStorage},
+    redis_storage::{RedisStorage, RedisStorageConfig},
     rules::RulesEngine,
+    service_accounts::ROTATED_TOKEN_HEADER,
+    sla::{SlaMonitor, SLA_TICK_INTERVAL},
     storage::{InMemoryStorage, WorkflowStorage},
+    timers::{ActivityTimers, TIMER_TICK_INTERVAL},
+    workflow_metrics::{WorkflowMetricsCollector, METRICS_COLLECT_INTERVAL},
 };
 use crate::models::{ActivityDefinition, ActivityId, StateId, WorkflowDefinition};
 
+use super::human_tasks;
+use super::triggers;
+
 pub type GraphQLSchema = Schema<Query, Mutation, Subscription>;
 
 /// GraphQL server configuration
@@ -35,6 +50,8 @@ pub type GraphQLSchema = Schema<Query, Mutation, Subscription>;
 pub struct GraphQLServerConfig {
     pub port: u16,
     pub cors_enabled: bool,
+    /// Whether requests need an API key or service token
+    pub api_key_required: bool,
 }
 
 impl Default for GraphQLServerConfig {
@@ -42,10 +59,15 @@ impl Default for GraphQLServerConfig {
         Self {
             port: 8080,
             cors_enabled: true,
+            api_key_required: false,
         }
     }
 }
 
+/// Whether the handler refuses requests without an API key or service token
+#[derive(Clone, Copy)]
+struct ApiKeyRequired(bool);
+
 /// GraphQL server
 pub struct GraphQLServer {
     config: GraphQLServerConfig,
@@ -60,7 +82,7 @@ impl GraphQLServer {
     pub fn new() -> Self {
         Self {
             config: GraphQLServerConfig::default(),
-            storage: Box::new(InMemoryStorage::default()),
+            storage: Box::new(InMemoryStorage::from_env()),
             agent_storage: None,
             agent_engine: None,
             nats_storage: None,
@@ -78,9 +100,12 @@ impl GraphQLServer {
         self
     }
 
-    pub fn with_agents(mut self) -> Self {
-        // Create a single shared agent storage instance
-        let agent_storage = std::sync::Arc::new(InMemoryAgentStorage::default());
+    pub fn with_agents(self) -> Self {
+        self.with_agent_storage(std::sync::Arc::new(InMemoryAgentStorage::default()))
+    }
+
+    /// Serve agents kept in `agent_storage`
+    pub fn with_agent_storage(mut self, agent_storage: std::sync::Arc<dyn AgentStorage>) -> Self {
         let rules_engine = std::sync::Arc::new(RulesEngine::new());
 
         // Create agent engine with shared storage
@@ -90,11 +115,8 @@ impl GraphQLServer {
             AgentEngineConfig::default(),
         );
 
-        // Clone the shared Arc<dyn AgentStorage> and assign it to self.agent_storage
-        // This ensures the storage is shared across the application
-        let shared_storage = agent_storage.clone();
-
-        self.agent_storage = Some(shared_storage as std::sync::Arc<dyn AgentStorage>);
+        // Keep the same storage the engine uses, so it is shared across the application
+        self.agent_storage = Some(agent_storage);
         self.agent_engine = Some(agent_engine);
         self
     }
@@ -116,6 +138,23 @@ impl GraphQLServer {
         // Add default workflows
         self.add_default_workflows().await?;
 
+        // Reap agent stream subscribers whose clients went away
+        if let Some(agent_engine) = &self.agent_engine {
+            agent_engine.spawn_stream_reaper();
+        }
+
+        // Fire timed activities, watch state SLAs, collect workflow metrics, serve human
+        // tasks and receive webhooks against whichever storage the schema serves
+        let storage: Arc<dyn WorkflowStorage> = self.storage.into();
+        let engine_storage: Arc<dyn WorkflowStorage> = match &self.nats_storage {
+            Some(nats_storage) => nats_storage.clone(),
+            None => storage.clone(),
+        };
+        let rules = Arc::new(RulesEngine::with_common_rules());
+        ActivityTimers::new(engine_storage.clone(), rules.clone()).spawn(TIMER_TICK_INTERVAL);
+        SlaMonitor::new(engine_storage.clone(), rules.clone()).spawn(SLA_TICK_INTERVAL);
+        WorkflowMetricsCollector::new(engine_storage.clone()).spawn(METRICS_COLLECT_INTERVAL);
+
         let schema = match (
             self.nats_storage,
             self.agent_storage,
@@ -141,11 +180,11 @@ impl GraphQLServer {
             }
             (None, Some(agent_storage), Some(agent_engine), _) => {
                 info!("🤖 Starting server with AI agent support");
-                create_schema_with_agents(self.storage, agent_storage, agent_engine)
+                create_schema_with_agents(Box::new(storage), agent_storage, agent_engine)
             }
             (None, _, _, _) => {
                 info!("📋 Starting server with basic workflow support");
-                create_schema_with_storage(self.storage)
+                create_schema_with_storage(Box::new(storage))
             }
         };
 
@@ -153,11 +192,26 @@ impl GraphQLServer {
 
         let subscription_service = GraphQLSubscription::new(schema);
 
+        // Subscriptions skip the handler, so they check API keys in middleware
+        let mut subscriptions = Router::new().route_service("/ws", subscription_service);
+        if self.config.api_key_required {
+            subscriptions =
+                subscriptions.route_layer(axum::middleware::from_fn(require_subscription_api_key));
+        }
+
         let mut app = Router::new()
             .route("/", get(graphiql).post(graphql_handler))
             .route("/graphql", post(graphql_handler))
-            .route_service("/ws", subscription_service)
+            .merge(subscriptions)
             .route("/health", get(health_check))
+            .route("/metrics", get(metrics))
+            .merge(human_tasks::router(
+                engine_storage.clone(),
+                self.config.api_key_required,
+            ))
+            .merge(triggers::router(engine_storage, rules))
+            .layer(Extension(ApiKeyRequired(self.config.api_key_required)))
+            .layer(axum::middleware::from_fn(record_peer_addr))
             .with_state(app_state);
 
         if self.config.cors_enabled {
@@ -182,10 +236,14 @@ impl GraphQLServer {
             "📡 GraphQL WebSocket: ws://localhost:{}/ws",
             self.config.port
         );
+        info!(
+            "📈 Metrics endpoint: http://localhost:{}/metrics",
+            self.config.port
+        );
 
         // Use axum 0.6 syntax
         Server::bind(&addr.parse()?)
-            .serve(app.into_make_service())
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
             .await?;
         Ok(())
     }
@@ -194,6 +252,7 @@ impl GraphQLServer {
         // Document Review Workflow
         let _document_workflow = WorkflowDefinition {
             id: "document_review".to_string(),
+            version: 1,
             name: "Document Review Process".to_string(),
             states: vec![
                 StateId::from("draft"),
@@ -209,6 +268,12 @@ impl GraphQLServer {
                     to_state: StateId::from("pending_review"),
                     conditions: vec![],
                     rules: vec![],
+                    timer: None,
+                    compensation: None,
+                    manual: None,
+                    gateway: None,
+                    retry: None,
+                    routes: vec![],
                 },
                 ActivityDefinition {
                     id: ActivityId::from("review"),
@@ -216,6 +281,12 @@ impl GraphQLServer {
                     to_state: StateId::from("reviewed"),
                     conditions: vec![],
                     rules: vec![],
+                    timer: None,
+                    compensation: None,
+                    manual: None,
+                    gateway: None,
+                    retry: None,
+                    routes: vec![],
                 },
                 ActivityDefinition {
                     id: ActivityId::from("approve"),
@@ -223,6 +294,12 @@ impl GraphQLServer {
                     to_state: StateId::from("approved"),
                     conditions: vec![],
                     rules: vec![],
+                    timer: None,
+                    compensation: None,
+                    manual: None,
+                    gateway: None,
+                    retry: None,
+                    routes: vec![],
                 },
                 ActivityDefinition {
                     id: ActivityId::from("reject"),
@@ -230,6 +307,12 @@ impl GraphQLServer {
                     to_state: StateId::from("rejected"),
                     conditions: vec![],
                     rules: vec![],
+                    timer: None,
+                    compensation: None,
+                    manual: None,
+                    gateway: None,
+                    retry: None,
+                    routes: vec![],
                 },
                 ActivityDefinition {
                     id: ActivityId::from("revise"),
@@ -237,14 +320,26 @@ impl GraphQLServer {
                     to_state: StateId::from("draft"),
                     conditions: vec![],
                     rules: vec![],
+                    timer: None,
+                    compensation: None,
+                    manual: None,
+                    gateway: None,
+                    retry: None,
+                    routes: vec![],
                 },
             ],
             initial_state: StateId::from("draft"),
+            state_capacities: Default::default(),
+            state_slas: Default::default(),
+            metadata_schema: None,
+            confidential_metadata: Vec::new(),
+            forked_from: None,
         };
 
         // Software Deployment Workflow
         let _deployment_workflow = WorkflowDefinition {
             id: "software_deployment".to_string(),
+            version: 1,
             name: "Software Deployment Pipeline".to_string(),
             states: vec![
                 StateId::from("development"),
@@ -260,6 +355,12 @@ impl GraphQLServer {
                     to_state: StateId::from("staging"),
                     conditions: vec!["tests_passed".to_string()],
                     rules: vec![],
+                    timer: None,
+                    compensation: None,
+                    manual: None,
+                    gateway: None,
+                    retry: None,
+                    routes: vec![],
                 },
                 ActivityDefinition {
                     id: ActivityId::from("deploy_to_production"),
@@ -267,6 +368,12 @@ impl GraphQLServer {
                     to_state: StateId::from("production"),
                     conditions: vec!["qa_approved".to_string()],
                     rules: vec![],
+                    timer: None,
+                    compensation: None,
+                    manual: None,
+                    gateway: None,
+                    retry: None,
+                    routes: vec![],
                 },
                 ActivityDefinition {
                     id: ActivityId::from("rollback_from_production"),
@@ -274,6 +381,12 @@ impl GraphQLServer {
                     to_state: StateId::from("rollback"),
                     conditions: vec![],
                     rules: vec![],
+                    timer: None,
+                    compensation: None,
+                    manual: None,
+                    gateway: None,
+                    retry: None,
+                    routes: vec![],
                 },
                 ActivityDefinition {
                     id: ActivityId::from("create_hotfix"),
@@ -281,6 +394,12 @@ impl GraphQLServer {
                     to_state: StateId::from("hotfix"),
                     conditions: vec!["critical_bug_detected".to_string()],
                     rules: vec![],
+                    timer: None,
+                    compensation: None,
+                    manual: None,
+                    gateway: None,
+                    retry: None,
+                    routes: vec![],
                 },
                 ActivityDefinition {
                     id: ActivityId::from("deploy_hotfix"),
@@ -288,6 +407,12 @@ impl GraphQLServer {
                     to_state: StateId::from("staging"),
                     conditions: vec![],
                     rules: vec![],
+                    timer: None,
+                    compensation: None,
+                    manual: None,
+                    gateway: None,
+                    retry: None,
+                    routes: vec![],
                 },
                 ActivityDefinition {
                     id: ActivityId::from("hotfix_to_staging"),
@@ -295,9 +420,20 @@ impl GraphQLServer {
                     to_state: StateId::from("staging"),
                     conditions: vec!["hotfix_tested".to_string()],
                     rules: vec![],
+                    timer: None,
+                    compensation: None,
+                    manual: None,
+                    gateway: None,
+                    retry: None,
+                    routes: vec![],
                 },
             ],
             initial_state: StateId::from("development"),
+            state_capacities: Default::default(),
+            state_slas: Default::default(),
+            metadata_schema: None,
+            confidential_metadata: Vec::new(),
+            forked_from: None,
         };
 
         // Store workflows - we'll need to implement this in the storage trait
@@ -339,6 +475,13 @@ impl GraphQLServerBuilder {
         self
     }
 
+    pub fn with_api_key_required(mut self, required: bool) -> Self {
+        let mut config = self.server.config.clone();
+        config.api_key_required = required;
+        self.server = self.server.with_config(config);
+        self
+    }
+
     pub fn with_agents(mut self) -> Self {
         self.server = self.server.with_agents();
         self
@@ -359,6 +502,7 @@ impl GraphQLServerBuilder {
         };
 
         let nats_storage = std::sync::Arc::new(NATSStorage::new(nats_config).await?);
+        nats_storage.spawn_snapshot_compaction();
         let storage_wrapper = NATSStorageWrapper::new(nats_storage.clone());
 
         // Create NATS client for rule storage
@@ -372,6 +516,35 @@ impl GraphQLServerBuilder {
         Ok(self)
     }
 
+    /// Keep workflows, resources and agents in Postgres at `database_url`
+    pub async fn with_postgres(
+        mut self,
+        database_url: &str,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let storage = PostgresStorage::connect(database_url).await?;
+        let agent_storage = PostgresAgentStorage::new(storage.pool().clone());
+
+        self.server = self
+            .server
+            .with_agent_storage(std::sync::Arc::new(agent_storage));
+        self.server = self.server.with_storage(Box::new(storage));
+        Ok(self)
+    }
+
+    /// Keep workflows, resources and agents in the Redis or Valkey server in `config`
+    pub async fn with_redis(
+        mut self,
+        config: RedisStorageConfig,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let storage = RedisStorage::connect(config).await?;
+
+        self.server = self
+            .server
+            .with_agent_storage(std::sync::Arc::new(storage.agent_storage()));
+        self.server = self.server.with_storage(Box::new(storage));
+        Ok(self)
+    }
+
     pub async fn build_and_run(self) -> Result<(), Box<dyn std::error::Error>> {
         self.server.run().await
     }
@@ -384,12 +557,63 @@ impl Default for GraphQLServerBuilder {
 }
 
 // GraphQL handler
+// Requests presenting a service token run as its account, see ServiceAccountGuard;
+// those presenting an API key are limited to its scopes, see ApiKeyGuard; both are
+// limited to what their roles allow, see RoleGuard
 async fn graphql_handler(
     State(schema): State<Arc<RwLock<GraphQLSchema>>>,
+    Extension(ApiKeyRequired(api_key_required)): Extension<ApiKeyRequired>,
+    headers: HeaderMap,
     req: GraphQLRequest,
 ) -> GraphQLResponse {
+    let mut request = req.into_inner();
+    let api_key = match authenticate_api_key(&headers).await {
+        Ok(key) => key,
+        Err(error) => return async_graphql::Response::from_errors(vec![error]).into(),
+    };
+    let authorization = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+    let mut rotated = None;
+    let ip = client_ip(&headers);
+    match authenticate_service_token(authorization, ip.as_deref()) {
+        Ok(Some(principal)) => {
+            rotated = principal.rotated.as_ref().map(|token| token.secret.clone());
+            request = request.data(principal);
+        }
+        Ok(None) if api_key_required && api_key.is_none() => {
+            let error = api_key_rejection(ApiKeyRejection::Missing);
+            return async_graphql::Response::from_errors(vec![error]).into();
+        }
+        Ok(None) => {}
+        Err(error) => return async_graphql::Response::from_errors(vec![error]).into(),
+    }
+    if let Some(key) = api_key {
+        request = request.data(key);
+    }
+
     let schema = schema.read().await;
-    schema.execute(req.into_inner()).await.into()
+    let mut response = schema.execute(request).await;
+    if let Some(secret) = rotated.and_then(|secret| HeaderValue::from_str(&secret).ok()) {
+        response.http_headers.insert(ROTATED_TOKEN_HEADER, secret);
+    }
+    response.into()
+}
+
+// Subscription middleware used when API keys are required
+// Refuses WebSocket upgrades without an API key allowing reads
+async fn require_subscription_api_key(request: Request<Body>, next: Next<Body>) -> Response {
+    let rejection = match ApiKeys::global()
+        .authorize(request.headers(), ApiKeyScope::WorkflowsRead)
+        .await
+    {
+        Ok(Some(_)) => return next.run(request).await,
+        Ok(None) => ApiKeyRejection::Missing,
+        Err(rejection) => rejection,
+    };
+    let status =
+        StatusCode::from_u16(rejection.code().http_status()).unwrap_or(StatusCode::UNAUTHORIZED);
+    (status, rejection.message()).into_response()
 }
 
 // GraphiQL interface with WebSocket support
@@ -446,3 +670,20 @@ async fn graphiql() -> impl IntoResponse {
 async fn health_check() -> impl IntoResponse {
     (StatusCode::OK, "Circuit Breaker GraphQL Server is running!")
 }
+
+/// Engine metrics in the Prometheus text format
+async fn metrics() -> impl IntoResponse {
+    (
+        StatusCode::OK,
+        [(
+            axum::http::header::CONTENT_TYPE,
+            "text/plain; version=0.0.4",
+        )],
+        format!(
+            "{}{}{}",
+            crate::engine::RuleMetrics::global().render_prometheus(),
+            crate::engine::ReconstructionMetrics::global().render_prometheus(),
+            crate::engine::WorkflowMetrics::global().render_prometheus()
+        ),
+    )
+}
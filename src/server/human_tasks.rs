@@ -0,0 +1,351 @@
+// Human task REST endpoints
+// Lets task inboxes and approval UIs work without GraphQL
+
+//! # Human Task Endpoints
+//!
+//! REST counterparts of the GraphQL `humanTasks` query and task mutations, served next
+//! to `/graphql`:
+//!
+//! - `GET /tasks?workflow_id=&claimed_by=` lists pending human tasks
+//! - `POST /tasks/{resource_id}/{activity_id}/claim`
+//! - `POST /tasks/{resource_id}/{activity_id}/approve` with `{"comment"}`
+//! - `POST /tasks/{resource_id}/{activity_id}/reject` with `{"comment"}`
+//!
+//! Requests authenticate like GraphQL ones, with an API key or a service token. Listing
+//! needs the `workflows_read` scope and the other endpoints `workflows_write`; roles
+//! and service account scopes apply as they do to the `humanTasks` query and task
+//! mutations. Tasks are claimed and decided as the authenticated principal; only
+//! requests without credentials name their `actor` in the body. Decisions are refused
+//! in maintenance mode. Errors carry the usual stable `error_code`.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::api_keys::{ApiKey, ApiKeyRejection, ApiKeyScope, ApiKeys};
+use crate::auth_throttle::client_ip;
+use crate::engine::graphql::authenticate_service_token;
+use crate::engine::{
+    HumanTask, HumanTaskError, HumanTasks, ServiceOperation, ServicePrincipal, WorkflowStorage,
+};
+use crate::models::{ActivityId, Resource};
+use crate::rbac::{Domain, Permission, RoleAssignments, Subject};
+use crate::{ErrorCode, MaintenanceMode};
+
+#[derive(Clone)]
+struct TaskState {
+    storage: Arc<dyn WorkflowStorage>,
+    tasks: HumanTasks,
+    api_key_required: bool,
+}
+
+/// Query of `GET /tasks`
+#[derive(Debug, Deserialize)]
+pub struct TaskListQuery {
+    pub workflow_id: Option<String>,
+    pub claimed_by: Option<String>,
+}
+
+/// Body of `POST /tasks/{resource_id}/{activity_id}/claim`
+#[derive(Debug, Default, Deserialize)]
+pub struct ClaimRequest {
+    /// Who claims the task, for requests without credentials
+    #[serde(default)]
+    pub actor: Option<String>,
+}
+
+/// Body of the approve and reject endpoints
+#[derive(Debug, Deserialize)]
+pub struct DecisionRequest {
+    /// Who decides, for requests without credentials
+    #[serde(default)]
+    pub actor: Option<String>,
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+/// Routes serving human tasks from `storage`
+pub fn router<S>(storage: Arc<dyn WorkflowStorage>, api_key_required: bool) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/tasks", get(list_tasks))
+        .route("/tasks/:resource_id/:activity_id/claim", post(claim_task))
+        .route(
+            "/tasks/:resource_id/:activity_id/approve",
+            post(approve_task),
+        )
+        .route("/tasks/:resource_id/:activity_id/reject", post(reject_task))
+        .with_state(TaskState {
+            storage,
+            tasks: HumanTasks::global(),
+            api_key_required,
+        })
+}
+
+fn error_response(code: ErrorCode, message: String) -> Response {
+    let status = StatusCode::from_u16(code.http_status()).unwrap_or(StatusCode::BAD_REQUEST);
+    let body = serde_json::json!({
+        "error": { "message": message, "error_code": code }
+    });
+    (status, Json(body)).into_response()
+}
+
+fn task_error(error: HumanTaskError) -> Response {
+    error_response(error.code(), error.to_string())
+}
+
+fn rejected(rejection: ApiKeyRejection) -> Response {
+    error_response(rejection.code(), rejection.message())
+}
+
+fn missing_actor() -> Response {
+    error_response(
+        ErrorCode::InvalidInput,
+        "An actor is required without credentials".to_string(),
+    )
+}
+
+/// The principals a task request authenticates as
+struct Caller {
+    api_key: Option<ApiKey>,
+    service: Option<ServicePrincipal>,
+}
+
+impl Caller {
+    fn subjects(&self) -> Vec<Subject> {
+        self.api_key
+            .iter()
+            .map(|key| Subject::ApiKey(key.id.clone()))
+            .chain(
+                self.service
+                    .iter()
+                    .map(|principal| Subject::ServiceAccount(principal.account.id.clone())),
+            )
+            .collect()
+    }
+
+    /// Who acts on a task: the authenticated principal, else the actor the body names
+    fn actor(&self, named: Option<String>) -> Option<String> {
+        match self.subjects().into_iter().next() {
+            Some(subject) => Some(subject.to_string()),
+            None => named.filter(|actor| !actor.is_empty()),
+        }
+    }
+
+    /// Whether a service account may perform an operation on a workflow; callers without
+    /// a service token may
+    fn allows(&self, workflow_id: &str, operation: ServiceOperation) -> bool {
+        self.service
+            .as_ref()
+            .is_none_or(|principal| principal.account.allows(workflow_id, operation))
+    }
+
+    fn service_denied(&self, workflow_id: &str, operation: ServiceOperation) -> Response {
+        let name = self
+            .service
+            .as_ref()
+            .map(|principal| principal.account.name.as_str())
+            .unwrap_or_default();
+        error_response(
+            ErrorCode::PermissionDenied,
+            format!(
+                "Service account '{}' may not perform {:?} on workflow '{}'",
+                name, operation, workflow_id
+            ),
+        )
+    }
+}
+
+/// Authenticate a task request and check its roles allow `permission`
+async fn authorize(
+    state: &TaskState,
+    headers: &HeaderMap,
+    scope: ApiKeyScope,
+    permission: Permission,
+) -> Result<Caller, Response> {
+    let api_key = ApiKeys::global()
+        .authorize(headers, scope)
+        .await
+        .map_err(rejected)?;
+    let authorization = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+    let ip = client_ip(headers);
+    let service = authenticate_service_token(authorization, ip.as_deref()).map_err(|error| {
+        let code = match error.extensions.as_ref().and_then(|ext| ext.get("code")) {
+            Some(async_graphql::Value::String(code)) if code == ErrorCode::RateLimited.as_str() => {
+                ErrorCode::RateLimited
+            }
+            _ => ErrorCode::AuthenticationFailed,
+        };
+        error_response(code, error.message)
+    })?;
+    let caller = Caller { api_key, service };
+
+    let subjects = caller.subjects();
+    if subjects.is_empty() && state.api_key_required {
+        return Err(rejected(ApiKeyRejection::Missing));
+    }
+    let roles = RoleAssignments::global();
+    for subject in &subjects {
+        roles
+            .authorize(subject, Some(permission))
+            .await
+            .map_err(|denied| error_response(denied.error_code(), denied.message()))?;
+    }
+    Ok(caller)
+}
+
+/// Authenticate a decision on a task and check the caller may act on its resource
+async fn authorize_decision(
+    state: &TaskState,
+    headers: &HeaderMap,
+    resource_id: &str,
+) -> Result<(Caller, Uuid), Response> {
+    let maintenance = MaintenanceMode::global().status();
+    if maintenance.enabled {
+        return Err(error_response(
+            ErrorCode::MaintenanceMode,
+            maintenance.message(),
+        ));
+    }
+    let caller = authorize(
+        state,
+        headers,
+        ApiKeyScope::WorkflowsWrite,
+        Permission::write(Domain::Resources),
+    )
+    .await?;
+    let resource_id = Uuid::parse_str(resource_id).map_err(|_| invalid_resource_id())?;
+    let resource = state
+        .storage
+        .get_resource(&resource_id)
+        .await
+        .map_err(|e| error_response(ErrorCode::StorageError, e.to_string()))?
+        .ok_or_else(|| {
+            error_response(
+                ErrorCode::ResourceNotFound,
+                "Resource not found".to_string(),
+            )
+        })?;
+    let operation = ServiceOperation::ExecuteActivities;
+    if !caller.allows(&resource.workflow_id, operation) {
+        return Err(caller.service_denied(&resource.workflow_id, operation));
+    }
+    Ok((caller, resource_id))
+}
+
+fn invalid_resource_id() -> Response {
+    error_response(
+        ErrorCode::InvalidInput,
+        "Invalid resource ID format".to_string(),
+    )
+}
+
+/// List pending human tasks - GET /tasks
+async fn list_tasks(
+    State(state): State<TaskState>,
+    headers: HeaderMap,
+    Query(query): Query<TaskListQuery>,
+) -> Result<Json<Vec<HumanTask>>, Response> {
+    let caller = authorize(
+        &state,
+        &headers,
+        ApiKeyScope::WorkflowsRead,
+        Permission::read(Domain::Resources),
+    )
+    .await?;
+    let operation = ServiceOperation::ReadResources;
+    if let Some(workflow_id) = &query.workflow_id {
+        if !caller.allows(workflow_id, operation) {
+            return Err(caller.service_denied(workflow_id, operation));
+        }
+    }
+    let mut tasks = state
+        .tasks
+        .pending(
+            state.storage.as_ref(),
+            query.workflow_id.as_deref(),
+            query.claimed_by.as_deref(),
+        )
+        .await
+        .map_err(task_error)?;
+    tasks.retain(|task| caller.allows(&task.workflow_id, operation));
+    Ok(Json(tasks))
+}
+
+/// Claim a human task - POST /tasks/{resource_id}/{activity_id}/claim
+async fn claim_task(
+    State(state): State<TaskState>,
+    headers: HeaderMap,
+    Path((resource_id, activity_id)): Path<(String, String)>,
+    request: Option<Json<ClaimRequest>>,
+) -> Result<Json<HumanTask>, Response> {
+    let (caller, resource_id) = authorize_decision(&state, &headers, &resource_id).await?;
+    let Json(request) = request.unwrap_or_default();
+    let actor = caller.actor(request.actor).ok_or_else(missing_actor)?;
+    let activity_id = ActivityId::from(activity_id);
+    let task = state
+        .tasks
+        .claim(state.storage.as_ref(), &resource_id, &activity_id, &actor)
+        .await
+        .map_err(task_error)?;
+    Ok(Json(task))
+}
+
+/// Approve a human task - POST /tasks/{resource_id}/{activity_id}/approve
+async fn approve_task(
+    State(state): State<TaskState>,
+    headers: HeaderMap,
+    Path((resource_id, activity_id)): Path<(String, String)>,
+    Json(request): Json<DecisionRequest>,
+) -> Result<Json<Resource>, Response> {
+    let (caller, resource_id) = authorize_decision(&state, &headers, &resource_id).await?;
+    let actor = caller.actor(request.actor).ok_or_else(missing_actor)?;
+    let activity_id = ActivityId::from(activity_id);
+    let resource = state
+        .tasks
+        .approve(
+            state.storage.as_ref(),
+            &resource_id,
+            &activity_id,
+            &actor,
+            request.comment,
+        )
+        .await
+        .map_err(task_error)?;
+    Ok(Json(resource))
+}
+
+/// Reject a human task - POST /tasks/{resource_id}/{activity_id}/reject
+async fn reject_task(
+    State(state): State<TaskState>,
+    headers: HeaderMap,
+    Path((resource_id, activity_id)): Path<(String, String)>,
+    Json(request): Json<DecisionRequest>,
+) -> Result<Json<Resource>, Response> {
+    let (caller, resource_id) = authorize_decision(&state, &headers, &resource_id).await?;
+    let actor = caller.actor(request.actor).ok_or_else(missing_actor)?;
+    let activity_id = ActivityId::from(activity_id);
+    let resource = state
+        .tasks
+        .reject(
+            state.storage.as_ref(),
+            &resource_id,
+            &activity_id,
+            &actor,
+            request.comment,
+        )
+        .await
+        .map_err(task_error)?;
+    Ok(Json(resource))
+}
@@ -59,6 +59,21 @@
 /// - Builder pattern for server configuration
 pub mod graphql;
 
+/// Human task REST endpoints
+/// 
+/// Contains:
+/// - Listing of resources parked at manual activities
+/// - Claim, approve and reject endpoints
+/// - API key checks matching the GraphQL surface
+pub mod human_tasks;
+
+/// Webhook trigger endpoint
+/// 
+/// Contains:
+/// - `POST /v1/triggers/{trigger_id}` receiving GitHub, Stripe and generic webhooks
+/// - Signature checks in place of API keys
+pub mod triggers;
+
 // Re-export main server types for easy access
 // This allows users to import server types directly from the server module
 
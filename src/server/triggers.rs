@@ -0,0 +1,84 @@
+// Webhook trigger endpoint
+// Receives webhooks from GitHub, Stripe and other services for registered triggers
+
+//! # Webhook Trigger Endpoint
+//!
+//! `POST /v1/triggers/{trigger_id}` receives the webhooks of a trigger registered with
+//! the GraphQL `registerWebhookTrigger` mutation. Senders authenticate with the
+//! trigger's HMAC signature rather than an API key, so the endpoint is open to them
+//! even when the server requires API keys.
+//!
+//! The response is the [`TriggerReport`] of what the webhook did. Unknown triggers
+//! answer 404, bad signatures 401 and payloads that are not JSON 400, each with the
+//! usual stable `error_code`; webhooks are refused with 503 during maintenance.
+
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use std::sync::Arc;
+
+use crate::engine::rules::RulesEngine;
+use crate::engine::{TriggerReport, WebhookTriggers, WorkflowStorage};
+use crate::{ErrorCode, MaintenanceMode};
+
+#[derive(Clone)]
+struct TriggerState {
+    storage: Arc<dyn WorkflowStorage>,
+    rules: Arc<RulesEngine>,
+    triggers: WebhookTriggers,
+}
+
+/// Routes receiving webhooks for the registered triggers
+pub fn router<S>(storage: Arc<dyn WorkflowStorage>, rules: Arc<RulesEngine>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/v1/triggers/:trigger_id", post(receive_webhook))
+        .with_state(TriggerState {
+            storage,
+            rules,
+            triggers: WebhookTriggers::global(),
+        })
+}
+
+fn error_response(code: ErrorCode, message: String) -> Response {
+    let status = StatusCode::from_u16(code.http_status()).unwrap_or(StatusCode::BAD_REQUEST);
+    let body = serde_json::json!({
+        "error": { "message": message, "error_code": code }
+    });
+    (status, Json(body)).into_response()
+}
+
+/// Handle a webhook - POST /v1/triggers/{trigger_id}
+async fn receive_webhook(
+    State(state): State<TriggerState>,
+    Path(trigger_id): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<TriggerReport>, Response> {
+    let maintenance = MaintenanceMode::global().status();
+    if maintenance.enabled {
+        return Err(error_response(
+            ErrorCode::MaintenanceMode,
+            maintenance.message(),
+        ));
+    }
+    let report = state
+        .triggers
+        .handle(
+            state.storage.as_ref(),
+            &state.rules,
+            &trigger_id,
+            &headers,
+            &body,
+        )
+        .await
+        .map_err(|e| error_response(e.code(), e.to_string()))?;
+    Ok(Json(report))
+}